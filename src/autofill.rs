@@ -0,0 +1,217 @@
+use crate::secure_storage::EncryptedFile;
+use serde::{Deserialize, Serialize};
+
+const AUTOFILL_FILE: &str = "autofill.json";
+const AUTOFILL_KEY_NAME: &str = "autofill-encryption-key-v1";
+
+/// The kinds of form field autofill knows how to fill
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutofillField {
+    Name,
+    GivenName,
+    FamilyName,
+    Email,
+    Tel,
+    Organization,
+    StreetAddress,
+    AddressLine1,
+    AddressLine2,
+    City,
+    Region,
+    PostalCode,
+    Country,
+}
+
+/// Substrings of a field's name, id or placeholder that give away what it's for, checked in order
+/// so that more specific words win ("zip" before "address", "first name" before "name")
+const FIELD_HINTS: &[(&[&str], AutofillField)] = &[
+    (&["email", "e-mail"], AutofillField::Email),
+    (&["phone", "tel", "mobile"], AutofillField::Tel),
+    (&["zip", "postal", "postcode"], AutofillField::PostalCode),
+    (&["first_name", "firstname", "first-name", "first name", "given", "fname"], AutofillField::GivenName),
+    (&["last_name", "lastname", "last-name", "last name", "surname", "family", "lname"], AutofillField::FamilyName),
+    (&["company", "organization", "organisation", "org"], AutofillField::Organization),
+    (&["address2", "address_2", "address-2", "line2", "apt", "suite"], AutofillField::AddressLine2),
+    (&["address1", "address_1", "address-1", "line1"], AutofillField::AddressLine1),
+    (&["address", "street"], AutofillField::StreetAddress),
+    (&["city", "town", "locality"], AutofillField::City),
+    (&["state", "province", "region", "county"], AutofillField::Region),
+    (&["country"], AutofillField::Country),
+    (&["name"], AutofillField::Name),
+];
+
+impl AutofillField {
+    /// The field named by an `autocomplete` attribute, ignoring section and billing/shipping
+    /// tokens. `off` and unknown tokens give `None`.
+    pub fn from_autocomplete(value: &str) -> Option<Self> {
+        let token = value.split_ascii_whitespace().last()?.to_ascii_lowercase();
+        Some(match token.as_str() {
+            "name" => Self::Name,
+            "given-name" => Self::GivenName,
+            "family-name" => Self::FamilyName,
+            "email" => Self::Email,
+            "tel" | "tel-national" => Self::Tel,
+            "organization" => Self::Organization,
+            "street-address" => Self::StreetAddress,
+            "address-line1" => Self::AddressLine1,
+            "address-line2" => Self::AddressLine2,
+            "address-level2" => Self::City,
+            "address-level1" => Self::Region,
+            "postal-code" => Self::PostalCode,
+            "country" | "country-name" => Self::Country,
+            _ => return None,
+        })
+    }
+
+    /// Guess what a field is for from its input type and the words around it (its name, id and
+    /// placeholder)
+    pub fn guess(input_type: &str, hints: &str) -> Option<Self> {
+        match input_type {
+            "email" => return Some(Self::Email),
+            "tel" => return Some(Self::Tel),
+            "text" | "search" | "" => {}
+            _ => return None,
+        }
+        let hints = hints.to_ascii_lowercase();
+        // Sign-in fields look like names but aren't part of an address
+        if ["user", "login", "account"].iter().any(|word| hints.contains(word)) {
+            return None;
+        }
+        FIELD_HINTS.iter()
+            .find(|(words, _)| words.iter().any(|word| hints.contains(word)))
+            .map(|&(_, field)| field)
+    }
+}
+
+/// A named set of details to fill forms with
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutofillProfile {
+    pub label: String,
+    #[serde(default)]
+    pub full_name: String,
+    #[serde(default)]
+    pub email: String,
+    #[serde(default)]
+    pub phone: String,
+    #[serde(default)]
+    pub organization: String,
+    /// One line per address line
+    #[serde(default)]
+    pub street_address: String,
+    #[serde(default)]
+    pub city: String,
+    #[serde(default)]
+    pub region: String,
+    #[serde(default)]
+    pub postal_code: String,
+    #[serde(default)]
+    pub country: String,
+}
+
+impl AutofillProfile {
+    /// What to put in a field of the given kind; empty when the profile has nothing for it
+    pub fn value(&self, field: AutofillField) -> String {
+        let name = self.full_name.trim();
+        let (given, family) = name.rsplit_once(' ').unwrap_or((name, ""));
+        let mut lines = self.street_address.lines().map(str::trim);
+        match field {
+            AutofillField::Name => name.to_string(),
+            AutofillField::GivenName => given.to_string(),
+            AutofillField::FamilyName => family.to_string(),
+            AutofillField::Email => self.email.clone(),
+            AutofillField::Tel => self.phone.clone(),
+            AutofillField::Organization => self.organization.clone(),
+            AutofillField::StreetAddress => self.street_address.lines().map(str::trim).collect::<Vec<_>>().join(", "),
+            AutofillField::AddressLine1 => lines.next().unwrap_or_default().to_string(),
+            AutofillField::AddressLine2 => lines.skip(1).collect::<Vec<_>>().join(", "),
+            AutofillField::City => self.city.clone(),
+            AutofillField::Region => self.region.clone(),
+            AutofillField::PostalCode => self.postal_code.clone(),
+            AutofillField::Country => self.country.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PersistedAutofill {
+    #[serde(default)]
+    profiles: Vec<AutofillProfile>,
+}
+
+/// Saved autofill profiles, kept by the parent process in an encrypted file
+#[derive(Debug, Clone)]
+pub struct AutofillStore {
+    profiles: Vec<AutofillProfile>,
+    file: EncryptedFile,
+}
+
+impl AutofillStore {
+    pub fn load_from_disk() -> Self {
+        let file = EncryptedFile::open(AUTOFILL_FILE, AUTOFILL_KEY_NAME);
+        let profiles = file.read::<PersistedAutofill>().unwrap_or_default().profiles;
+        Self { profiles, file }
+    }
+
+    pub fn save_to_disk(&self) {
+        self.file.write(&PersistedAutofill { profiles: self.profiles.clone() });
+    }
+
+    pub fn profiles(&self) -> &[AutofillProfile] {
+        &self.profiles
+    }
+
+    pub fn get(&self, label: &str) -> Option<&AutofillProfile> {
+        self.profiles.iter().find(|profile| profile.label == label)
+    }
+
+    /// Add `profile`, replacing the one with the same label
+    pub fn save_profile(&mut self, profile: AutofillProfile) {
+        match self.profiles.iter_mut().find(|existing| existing.label == profile.label) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+
+    pub fn remove_profile(&mut self, label: &str) {
+        self.profiles.retain(|profile| profile.label != label);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AutofillField, AutofillProfile};
+
+    #[test]
+    fn autocomplete_tokens_name_fields() {
+        assert_eq!(AutofillField::from_autocomplete("shipping postal-code"), Some(AutofillField::PostalCode));
+        assert_eq!(AutofillField::from_autocomplete("section-a billing address-level2"), Some(AutofillField::City));
+        assert_eq!(AutofillField::from_autocomplete("off"), None);
+        assert_eq!(AutofillField::from_autocomplete(""), None);
+    }
+
+    #[test]
+    fn guesses_fields_from_names_and_types() {
+        assert_eq!(AutofillField::guess("email", ""), Some(AutofillField::Email));
+        assert_eq!(AutofillField::guess("text", "billing_zip"), Some(AutofillField::PostalCode));
+        assert_eq!(AutofillField::guess("text", "firstName"), Some(AutofillField::GivenName));
+        assert_eq!(AutofillField::guess("text", "full_name"), Some(AutofillField::Name));
+        assert_eq!(AutofillField::guess("text", "street address"), Some(AutofillField::StreetAddress));
+        assert_eq!(AutofillField::guess("text", "username"), None);
+        assert_eq!(AutofillField::guess("password", "name"), None);
+        assert_eq!(AutofillField::guess("text", "search query"), None);
+    }
+
+    #[test]
+    fn splits_names_and_address_lines() {
+        let profile = AutofillProfile {
+            full_name: "Ada King Lovelace".to_string(),
+            street_address: "12 St James's Square\nFlat 3".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(profile.value(AutofillField::GivenName), "Ada King");
+        assert_eq!(profile.value(AutofillField::FamilyName), "Lovelace");
+        assert_eq!(profile.value(AutofillField::AddressLine1), "12 St James's Square");
+        assert_eq!(profile.value(AutofillField::AddressLine2), "Flat 3");
+        assert_eq!(profile.value(AutofillField::StreetAddress), "12 St James's Square, Flat 3");
+    }
+}
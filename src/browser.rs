@@ -1,36 +1,61 @@
 use anyrender::PaintScene;
 use blitz_traits::shell::Viewport;
+use glutin::context::PossiblyCurrentGlContext;
 use glutin::surface::GlSurface;
 use cursor_icon::CursorIcon;
 use parley::{FontContext, LayoutContext};
+use std::collections::HashMap;
 use std::num::NonZeroU32;
 use std::str::FromStr;
-use std::time::Instant;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use taffy::Point;
 use winit::application::ApplicationHandler;
 use winit::dpi::{LogicalPosition, LogicalSize, PhysicalPosition};
-use winit::event::{ElementState, Modifiers, MouseButton, WindowEvent};
+use winit::event::{DeviceEvent, DeviceId, ElementState, Modifiers, MouseButton, PointerKind, PointerSource, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::keyboard::{Key, NamedKey};
 use winit::window::WindowId;
+use winit_core::monitor::Fullscreen;
 use winit_core::cursor::Cursor;
 use winit_core::event::ButtonSource;
-use winit_core::window::{ImeCapabilities, ImeEnableRequest, ImeRequest, ImeRequestData};
-use crate::ipc::{ParentToTabMessage, TabToParentMessage};
+use winit_core::event_loop::ControlFlow;
+use winit_core::window::{CursorGrabMode, ImeCapabilities, ImeEnableRequest, ImeRequest, ImeRequestData};
+use crate::ipc::{self, ParentToTabMessage, TabToParentMessage};
 use crate::renderer::painter::{ScenePainter, SkiaCache};
 use crate::tab_manager::{ManagedTab, TabManager};
-use crate::ui::{BookmarkUiAction, BrowserUI, TextBrush};
+use crate::visual_viewport::VisualViewport;
+use crate::ui::{BookmarkUiAction, BrowserUI, PageDialog, PromptBar, TabMenuAction, TextBrush, ThemeSettings};
 use crate::window::{create_surface, Env};
-use crate::{input, ipc};
+use crate::window_placement::WindowPlacement;
+use crate::frame_timing::PresentClock;
+use crate::clipboard::{self, Selection};
+use crate::gamepad::Gamepads;
+use crate::{input, ipc, logging, profile, web_apps};
 use crate::convert_events::{button_source_to_blitz, pointer_source_to_blitz, pointer_source_to_blitz_details, winit_ime_to_blitz, winit_key_event_to_blitz, winit_modifiers_to_kbt_modifiers};
 use crate::events::{BlitzPointerEvent, BlitzPointerId, BlitzWheelDelta, BlitzWheelEvent, MouseEventButton, MouseEventButtons, PointerCoords, PointerDetails, UiEvent};
 use crate::shell_provider::ShellProviderMessage;
 use crate::bookmarks::BookmarkStore;
+use crate::visited_links::VisitedLinkStore;
+use crate::geolocation::{parse_coordinates, GeolocationError, LocationProvider, LocationSettings};
+use crate::permissions::{display_origin, PermissionKind, PermissionState, PermissionStore};
+use crate::keymap::Keymap;
+use crate::languages::{self, parse_language_list, LanguageSettings};
+use crate::media_preferences::MediaPreferences;
+use crate::site_settings::SiteSettingsStore;
+use crate::autofill::{AutofillProfile, AutofillStore};
+use crate::passwords::{self, ManagementRequest, PasswordStore, SavedLogin};
+use crate::js::JsDialog;
+use crate::extensions::{self, manifest::WebExtension, ExtensionStore};
+use crate::networking::conditions::NetworkConditions;
+use crate::networking::http_auth::{AuthChallenge, AuthScheme};
+use crate::networking::security::SecurityState;
 
 /// Result of closing a tab
 #[derive(Debug, PartialEq)]
 enum TabCloseResult {
     Closed,
-    QuitApp,
+    CloseWindow,
     NoAction,
 }
 
@@ -40,6 +65,78 @@ pub const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "-dev");
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 const DEFAULT_HOMEPAGE: &str = "https://html.duckduckgo.com";
+const MAX_RECENTLY_CLOSED: usize = 25;
+/// Limits of the page zoom
+const MIN_PAGE_ZOOM: f32 = 0.25;
+const MAX_PAGE_ZOOM: f32 = 5.0;
+/// How much one Ctrl+wheel notch magnifies the visual viewport
+const VISUAL_ZOOM_PER_LINE: f32 = 1.1;
+/// Pixels of a touchpad's scroll that count as one wheel notch
+const WHEEL_PIXELS_PER_LINE: f32 = 50.0;
+/// Pages prerendered at once, each a whole tab process
+const MAX_PRERENDERS: usize = 2;
+/// Dialogs a page may open before the user is offered to stop it from opening more
+const DIALOGS_BEFORE_MUTE_OFFER: usize = 2;
+
+/// Milliseconds since the Unix epoch, comparable across processes
+fn unix_time_ms() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0.0, |elapsed| elapsed.as_secs_f64() * 1000.0)
+}
+
+/// Ask which of `profiles` to use, or the name of a new one. With a single profile there is nothing
+/// to choose and it's returned straight away. `None` when the user cancels.
+pub fn pick_profile(profiles: &[String]) -> Option<String> {
+    if let [only] = profiles {
+        return Some(only.clone());
+    }
+    let mut message = format!(
+        "Profiles: {}\nEnter one of them, or a new name to create a profile:",
+        profiles.join(", ")
+    );
+    loop {
+        let name = BrowserApp::prompt_input("Choose a Profile", &message, profile::DEFAULT_PROFILE)?;
+        if let Some(existing) = profiles.iter().find(|profile| profile.eq_ignore_ascii_case(&name)) {
+            return Some(existing.clone());
+        }
+        if profile::is_valid_name(&name) {
+            return Some(name);
+        }
+        message = format!(
+            "\"{name}\" can't be used as a profile name. Use letters, digits, spaces, '-', '_' or '.'.\nProfiles: {}",
+            profiles.join(", ")
+        );
+    }
+}
+
+/// What `runtime.sendMessage` rejects with when no background page can receive the message
+const NO_RECEIVING_END: &str = "Could not establish connection. Receiving end does not exist.";
+
+/// A closed tab that can be reopened with Ctrl+Shift+T
+struct ClosedTab {
+    url: String,
+    title: String,
+    scroll: Point<f64>,
+    history: Vec<String>,
+    history_index: usize,
+    is_pinned: bool,
+}
+
+/// State of a browser window that is not currently active.
+/// The active window's state lives directly on `BrowserApp` and is swapped out by `activate_window`.
+struct WindowState {
+    env: Env,
+    ui: BrowserUI,
+    viewport: Viewport,
+    page_viewport: Viewport,
+    skia_cache: SkiaCache,
+    tab_order: Vec<String>,
+    active_tab_index: usize,
+    pointer_position: (f64, f64),
+    buttons: MouseEventButtons,
+    page_fullscreen_tab: Option<String>,
+    minimized: bool,
+    occluded: bool,
+}
 
 /// The main browser application (parent process)
 pub(crate) struct BrowserApp {
@@ -54,18 +151,167 @@ pub(crate) struct BrowserApp {
     pointer_position: (f64, f64),
     loading_spinner_angle: f32,
     last_spinner_update: Instant,
+    /// When a tooltip or toast of each window changes by itself, as of the last check. Background
+    /// windows are included, as a screenshot's toast can be shown in a window the user left.
+    ui_redraw_at: HashMap<WindowId, Instant>,
+    /// When the active window's display refreshes, learned from its presents
+    present_clock: PresentClock,
     tab_order: Vec<String>,
     font_ctx: FontContext,
     layout_ctx: LayoutContext<TextBrush>,
     startup_url: Option<String>,
+    /// The next window opens as an app window, without the chrome. Set for the first window when
+    /// the browser was started from an installed web app's shortcut.
+    app_mode: bool,
+    /// Links an app window wanted opened in a new tab. App windows have no tab strip, so they are
+    /// opened in a browser window once the event loop can create one.
+    app_links: Vec<String>,
     buttons: MouseEventButtons,
     bookmarks: BookmarkStore,
+    /// Pages visited in any tab, for styling :visited links and completing addresses
+    visited_links: VisitedLinkStore,
+    /// Origin last preconnected to while typing in the address bar
+    preconnected_origin: Option<String>,
+    /// Tab whose page the find bar searches
+    find_tab: Option<String>,
+    /// Query last sent to `find_tab`, so typing searches again only when the text changes
+    find_query: String,
+    /// Recently closed tabs, most recent first
+    recently_closed: Vec<ClosedTab>,
+    /// Tab that put the window into fullscreen through the Fullscreen API, if any
+    page_fullscreen_tab: Option<String>,
+    /// The window was minimized, or shrunk to no area. Its tabs are hidden and nothing is drawn
+    /// until it is restored.
+    minimized: bool,
+    /// The window is fully covered by others, so drawing it would show nothing
+    occluded: bool,
+    /// Where the last window that was in a normal state was, and how big. The first window of
+    /// the next launch opens this way.
+    window_placement: Option<WindowPlacement>,
+    /// Id of the window whose state is currently loaded into the fields above
+    window_id: Option<WindowId>,
+    /// All other open windows
+    background_windows: HashMap<WindowId, WindowState>,
+    /// Window whose GL context is current. Loading a window's state doesn't switch contexts;
+    /// drawing does, through `make_gl_current`.
+    gl_current: Option<WindowId>,
+    permissions: PermissionStore,
+    /// Permission requests waiting for the user, oldest first
+    pending_permissions: Vec<PendingPermission>,
+    /// alert(), confirm() and prompt() dialogs waiting for the user, oldest first. Each blocks its
+    /// tab's script until it is answered.
+    pending_dialogs: Vec<PendingDialog>,
+    location_settings: LocationSettings,
+    /// Shared with the worker threads that answer geolocation requests
+    location_provider: Arc<dyn LocationProvider>,
+    /// The languages to ask sites for; the list itself is in `languages::current()`
+    language_settings: LanguageSettings,
+    /// Desktop dark mode and reduced-motion settings, mirrored into every tab
+    media_preferences: MediaPreferences,
+    site_settings: SiteSettingsStore,
+    /// Saved names and addresses offered when a form field is focused
+    autofill: AutofillStore,
+    /// What the autofill popup is offering
+    autofill_offer: AutofillOffer,
+    /// A submitted login the prompt bar offers to save, with the tab it came from
+    pending_login: Option<(String, SavedLogin)>,
+    /// Keyboard shortcuts, with the user's overrides from keybindings.json
+    keymap: Keymap,
+    /// A pointer move or wheel turn not yet sent to its tab, with the tab's id. Further moves and
+    /// turns are folded into it until the event loop goes idle or other input needs to go first.
+    pending_input: Option<(String, UiEvent)>,
+    /// Fingers on the active window, by winit's finger id
+    touches: HashMap<u64, ActiveTouch>,
+    /// The pinch the fingers on the page are making, when there are exactly two of them
+    pinch: Option<Pinch>,
+    /// Game controllers, whose changes go to the active tab
+    gamepads: Gamepads,
+    /// Tab of the active window that locked the pointer. The cursor is grabbed and hidden, and
+    /// the mouse's motion goes to the tab as relative movement.
+    pointer_lock_tab: Option<String>,
+    /// The hidden tab running each WebExtension's background page, by extension id. Started the
+    /// first time a content script sends the extension a message.
+    extension_hosts: HashMap<String, String>,
+    /// `runtime.sendMessage` calls handed to a background page and not yet answered, by the id the
+    /// background page was given
+    extension_requests: HashMap<u64, PendingExtensionRequest>,
+    next_extension_request_id: u64,
+    /// The hidden tab prerendering each tab's `<link rel="prerender">` target, by the id of the
+    /// tab whose page asked for it
+    prerenders: HashMap<String, Prerender>,
+}
+
+/// Distance between two fingers, in physical pixels
+fn finger_distance(first: PhysicalPosition<f64>, second: PhysicalPosition<f64>) -> f64 {
+    (first.x - second.x).hypot(first.y - second.y)
+}
+
+/// A finger on the window
+struct ActiveTouch {
+    position: PhysicalPosition<f64>,
+    /// The finger landed on the chrome or on something covering the page, and acts as a mouse
+    on_chrome: bool,
+}
+
+/// Two fingers on the page pinching it, with how far apart they started and the visual
+/// viewport's scale then
+struct Pinch {
+    distance: f64,
+    scale: f32,
+}
+
+/// A page loading in a hidden tab, to be swapped in when the tab that asked for it follows a link
+/// to it
+struct Prerender {
+    url: String,
+    host_id: String,
+}
+
+/// A content script's message waiting for its extension's background page to answer
+struct PendingExtensionRequest {
+    host_id: String,
+    tab_id: String,
+    request_id: u64,
+}
+
+/// A page's request for a permission, shown in the prompt bar while its tab is active
+struct PendingPermission {
+    tab_id: String,
+    request_id: u64,
+    origin: String,
+    kind: PermissionKind,
+}
+
+/// A page dialog, shown over its tab's content while the tab is active
+struct PendingDialog {
+    tab_id: String,
+    request_id: u64,
+    origin: String,
+    dialog: JsDialog,
+    /// Whether the page has opened enough dialogs to be offered muting
+    offer_mute: bool,
+}
+
+/// What the entries of the autofill popup stand for
+enum AutofillOffer {
+    /// The saved autofill profiles, in order
+    Profiles,
+    /// Logins saved for the focused login form's origin
+    Logins(Vec<SavedLogin>),
 }
 
 impl BrowserApp {
-    pub(crate) async fn new(el: &EventLoop, startup_url: Option<String>) -> Self {
+    pub(crate) async fn new(el: &EventLoop, startup_url: Option<String>, app_mode: bool) -> Self {
+        // The event loop sleeps until there is something to do, so tabs wake it with their messages
+        let proxy = el.create_proxy();
+        ipc::set_main_loop_waker(move || proxy.wake_up());
+
         // Create tab manager
         let tab_manager = TabManager::new().expect("Failed to create tab manager");
+        let location_settings = LocationSettings::load_from_disk();
+        let location_provider = Arc::from(location_settings.provider());
+        let language_settings = LanguageSettings::load_from_disk();
+        languages::set(language_settings.languages());
 
         Self {
             env: None,
@@ -79,12 +325,49 @@ impl BrowserApp {
             page_viewport: None,
             loading_spinner_angle: 0.0,
             last_spinner_update: Instant::now(),
+            ui_redraw_at: HashMap::new(),
+            present_clock: PresentClock::default(),
             tab_order: vec![],
             font_ctx: FontContext::new(),
             layout_ctx: LayoutContext::new(),
             startup_url,
+            app_mode,
+            app_links: Vec::new(),
             buttons: MouseEventButtons::None,
             bookmarks: BookmarkStore::load_from_disk(),
+            visited_links: VisitedLinkStore::load_from_disk(),
+            preconnected_origin: None,
+            find_tab: None,
+            find_query: String::new(),
+            recently_closed: Vec::new(),
+            page_fullscreen_tab: None,
+            minimized: false,
+            occluded: false,
+            window_placement: WindowPlacement::load_from_disk(),
+            window_id: None,
+            background_windows: HashMap::new(),
+            gl_current: None,
+            permissions: PermissionStore::load_from_disk(),
+            pending_permissions: Vec::new(),
+            pending_dialogs: Vec::new(),
+            location_settings,
+            location_provider,
+            language_settings,
+            media_preferences: MediaPreferences::default(),
+            site_settings: SiteSettingsStore::load_from_disk(),
+            autofill: AutofillStore::load_from_disk(),
+            autofill_offer: AutofillOffer::Profiles,
+            pending_login: None,
+            keymap: Keymap::load(),
+            pending_input: None,
+            touches: HashMap::new(),
+            pinch: None,
+            gamepads: Gamepads::new(),
+            pointer_lock_tab: None,
+            extension_hosts: HashMap::new(),
+            extension_requests: HashMap::new(),
+            next_extension_request_id: 1,
+            prerenders: HashMap::new(),
         }
     }
 
@@ -116,7 +399,7 @@ impl BrowserApp {
         let vp = self.viewport.as_ref().unwrap();
         // Calculate the page viewport height in physical pixels by subtracting the chrome height
         // converted to physical pixels using the current hidpi scale.
-        let chrome_physical = (BrowserUI::CHROME_HEIGHT as f32 * vp.hidpi_scale).round() as u32;
+        let chrome_physical = self.ui().chrome_height().round() as u32;
 
         let pvp = self.page_viewport.as_mut().unwrap();
 
@@ -131,9 +414,203 @@ impl BrowserApp {
         self.tab_order.get(self.active_tab_index)
     }
 
+    /// Move the active window's state out of `self`, leaving it empty
+    fn take_window_state(&mut self) -> Option<WindowState> {
+        let env = self.env.take()?;
+        Some(WindowState {
+            env,
+            ui: self.ui.take()?,
+            viewport: self.viewport.take()?,
+            page_viewport: self.page_viewport.take()?,
+            skia_cache: std::mem::take(&mut self.skia_cache),
+            tab_order: std::mem::take(&mut self.tab_order),
+            active_tab_index: std::mem::take(&mut self.active_tab_index),
+            pointer_position: self.pointer_position,
+            buttons: self.buttons,
+            page_fullscreen_tab: self.page_fullscreen_tab.take(),
+            minimized: std::mem::take(&mut self.minimized),
+            occluded: std::mem::take(&mut self.occluded),
+        })
+    }
+
+    fn load_window_state(&mut self, window_id: WindowId, state: WindowState) {
+        self.env = Some(state.env);
+        self.note_refresh_interval();
+        self.ui = Some(state.ui);
+        self.viewport = Some(state.viewport);
+        self.page_viewport = Some(state.page_viewport);
+        self.skia_cache = state.skia_cache;
+        self.tab_order = state.tab_order;
+        self.active_tab_index = state.active_tab_index;
+        self.pointer_position = state.pointer_position;
+        self.buttons = state.buttons;
+        self.page_fullscreen_tab = state.page_fullscreen_tab;
+        self.minimized = state.minimized;
+        self.occluded = state.occluded;
+        self.window_id = Some(window_id);
+    }
+
+    /// Every window has its own GL context. Make the active window's current before touching its
+    /// surface; tab messages load a window's state far more often than it is drawn.
+    fn make_gl_current(&mut self) {
+        let (Some(window_id), Some(env)) = (self.window_id, self.env.as_ref()) else {
+            return;
+        };
+        if self.gl_current == Some(window_id) {
+            return;
+        }
+        match env.gl_context.make_current(&env.gl_surface) {
+            Ok(()) => self.gl_current = Some(window_id),
+            Err(e) => tracing::error!("Failed to make GL context current for window {:?}: {}", window_id, e),
+        }
+    }
+
+    /// Load the state of `window_id` into `self`, stashing the previously active window.
+    /// Returns false if the window is unknown (e.g. it was just closed).
+    fn activate_window(&mut self, window_id: WindowId) -> bool {
+        if self.window_id == Some(window_id) {
+            return true;
+        }
+        let Some(state) = self.background_windows.remove(&window_id) else {
+            return false;
+        };
+        if let (Some(current_id), Some(current)) = (self.window_id, self.take_window_state()) {
+            self.background_windows.insert(current_id, current);
+        }
+        self.load_window_state(window_id, state);
+        true
+    }
+
+    /// Activate whichever window owns `tab_id`
+    fn activate_window_for_tab(&mut self, tab_id: &str) {
+        if self.tab_order.iter().any(|id| id == tab_id) {
+            return;
+        }
+        let owner = self
+            .background_windows
+            .iter()
+            .find(|(_, state)| state.tab_order.iter().any(|id| id == tab_id))
+            .map(|(window_id, _)| *window_id);
+        if let Some(window_id) = owner {
+            self.activate_window(window_id);
+        }
+    }
+
+    /// Open a new top-level window with its own surface, UI and tab set
+    fn open_window(&mut self, event_loop: &dyn ActiveEventLoop, url: Option<&str>) {
+        // Only the first window goes where the last one was left; later ones open where the
+        // platform puts them
+        let first_window = self.window_id.is_none() && self.background_windows.is_empty();
+        let placement = self.window_placement.filter(|_| first_window);
+        let env = match crate::window::create_window(event_loop, placement) {
+            Ok(env) => env,
+            Err(e) => {
+                tracing::error!("Failed to open a window: {}", e);
+                self.show_window_error(&e);
+                // Without any window there is nothing left to keep running for
+                if first_window {
+                    event_loop.exit();
+                }
+                return;
+            }
+        };
+        if let (Some(current_id), Some(current)) = (self.window_id, self.take_window_state()) {
+            self.background_windows.insert(current_id, current);
+        }
+
+        self.media_preferences = MediaPreferences::detect(env.window.theme());
+        let viewport = Viewport {
+            color_scheme: self.media_preferences.color_scheme(),
+            window_size: env.window.surface_size().into(),
+            hidpi_scale: env.window.scale_factor() as f32,
+            zoom: 1.0,
+        };
+
+        // Initialize UI
+        let mut ui = BrowserUI::new(&env.gr_context, &viewport);
+        ui.initialize_renderer();
+        ui.set_app_mode(std::mem::take(&mut self.app_mode));
+        let page_viewport = Viewport {
+            // The page gets what the chrome leaves of the window, in physical pixels
+            window_size: (viewport.window_size.0, viewport.window_size.1.saturating_sub(ui.chrome_height().round() as u32)),
+            ..viewport.clone()
+        };
+        self.window_id = Some(env.window.id());
+        // Creating the window left its context current
+        self.gl_current = self.window_id;
+        self.env = Some(env);
+        self.note_refresh_interval();
+        self.ui = Some(ui);
+        self.viewport = Some(viewport);
+        self.page_viewport = Some(page_viewport);
+        self.pointer_position = (0.0, 0.0);
+        self.buttons = MouseEventButtons::None;
+        self.sync_bookmarks_ui();
+        self.sync_recently_closed_ui();
+
+        self.add_tab_with_url(url);
+    }
+
+    /// Close the active window and all of its tabs, exiting once no windows remain
+    fn close_window(&mut self, event_loop: &dyn ActiveEventLoop) {
+        self.save_window_placement();
+        for tab_id in std::mem::take(&mut self.tab_order) {
+            self.remember_closed_tab(&tab_id);
+            self.cancel_prerender(&tab_id);
+            let _ = self.tab_manager.close_tab(&tab_id);
+        }
+        // Its surface and Skia context are freed with the window's own context current
+        self.make_gl_current();
+        drop(self.take_window_state());
+        self.window_id = None;
+        self.gl_current = None;
+
+        let next = self.background_windows.keys().next().copied();
+        match next {
+            Some(window_id) => {
+                let state = self.background_windows.remove(&window_id).unwrap();
+                self.load_window_state(window_id, state);
+                self.request_redraw();
+            }
+            None => event_loop.exit(),
+        }
+    }
+
+    /// Enter or leave fullscreen, hiding the chrome and resizing the tabs to the new page area
+    fn set_fullscreen(&mut self, fullscreen: bool) {
+        if self.ui().is_fullscreen() == fullscreen {
+            return;
+        }
+
+        self.env().window.set_fullscreen(fullscreen.then_some(Fullscreen::Borderless(None)));
+        self.ui_mut().set_fullscreen(fullscreen);
+        self.update_page_viewport();
+
+        let (width, height) = self.page_viewport.as_ref().unwrap().window_size;
+        for tab_id in &self.tab_order {
+            let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::Resize {
+                width: width as f32,
+                height: height as f32,
+            });
+        }
+
+        // Let the page know it lost fullscreen when the user left it from the browser
+        if !fullscreen {
+            if let Some(tab_id) = self.page_fullscreen_tab.take() {
+                let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::ExitFullscreen);
+            }
+        }
+        self.request_redraw();
+    }
+
     fn navigate_to_url(&mut self, url: &str) {
         if let Some(tab_id) = self.active_tab_id().cloned() {
-            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::Navigate(url.to_string()));
+            self.dismiss_page_dialogs(&tab_id);
+            if self.prerenders.get(&tab_id).is_some_and(|prerender| prerender.url == url) {
+                self.activate_prerender(&tab_id, url.to_string());
+            } else {
+                let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::Navigate(url.to_string()));
+            }
             self.env.as_ref().unwrap().window.set_title(&format!("Loading: {}", url));
             self.ui.as_mut().unwrap().clear_focus();
         }
@@ -144,7 +621,6 @@ impl BrowserApp {
     }
 
     pub(crate) fn add_tab_with_url(&mut self, url: Option<&str>) {
-        let env = self.env.as_ref().unwrap();
         let ui = self.ui.as_mut().unwrap();
         if let Ok(new_tab_id) = self.tab_manager.create_tab() {
             ui.add_tab(&new_tab_id, "New Tab");
@@ -154,14 +630,10 @@ impl BrowserApp {
             self.active_tab_index = self.tab_order.len() - 1;
             ui.set_active_tab(&new_tab_id);
 
-            // Send initial configuration
-            let (width, height) = self.page_viewport.as_ref().unwrap().window_size;
-            let _ = self.tab_manager.send_to_tab(&new_tab_id, ParentToTabMessage::Resize {
-                width: width as f32,
-                height: height as f32
-            });
-            let _ = self.tab_manager.send_to_tab(&new_tab_id, ParentToTabMessage::SetScaleFactor(self.viewport.as_ref().unwrap().hidpi_scale));
+            self.send_initial_config(&new_tab_id);
 
+            let env = self.env.as_ref().unwrap();
+            let ui = self.ui.as_mut().unwrap();
             if let Some(u) = url {
                 // Navigate to the provided URL immediately
                 ui.update_address_bar(u);
@@ -174,16 +646,195 @@ impl BrowserApp {
             }
 
             self.update_bookmark_button_state();
+            self.update_javascript_indicator();
+            self.update_popup_indicator();
+            self.sync_find_bar();
+            self.update_tab_visibility();
+        }
+    }
+
+    /// Send a tab process the window size, scale and desktop preferences
+    fn send_initial_config(&mut self, tab_id: &str) {
+        let (width, height) = self.page_viewport.as_ref().unwrap().window_size;
+        let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::Resize {
+            width: width as f32,
+            height: height as f32
+        });
+        let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::SetScaleFactor(self.viewport.as_ref().unwrap().hidpi_scale));
+        let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::SetMediaPreferences(self.media_preferences));
+        let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::SetLanguages(languages::current()));
+        let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::SetVisitedLinks(self.visited_links.filter()));
+    }
+
+    /// While the user types in the address bar, have the active tab connect to the origin the text
+    /// most likely leads to, so pressing Enter doesn't wait for DNS and a full TLS handshake
+    fn preconnect_address_bar_completion(&mut self) {
+        let Some(origin) = self.ui().address_bar_input().and_then(|typed| self.visited_links.likely_completion(typed)) else {
+            return;
+        };
+        if self.preconnected_origin.as_ref() == Some(&origin) {
+            return;
+        }
+        if let Some(tab_id) = self.active_tab_id().cloned() {
+            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::Preconnect(origin.clone()));
+        }
+        self.preconnected_origin = Some(origin);
+    }
+
+    /// Remember a visited page and send every tab the updated visited-link filter
+    fn record_visited_link(&mut self, url: &str) {
+        if !self.visited_links.add(url) {
+            return;
+        }
+        self.visited_links.save_to_disk();
+
+        let filter = self.visited_links.filter();
+        let tab_ids = self.tab_order.iter()
+            .chain(self.background_windows.values().flat_map(|state| state.tab_order.iter()))
+            .cloned()
+            .collect::<Vec<_>>();
+        for tab_id in tab_ids {
+            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::SetVisitedLinks(filter.clone()));
+        }
+    }
+
+    /// Show the active tab of this window and throttle the others, or all of them while the
+    /// window is minimized. A discarded tab is loaded again when it is shown.
+    fn update_tab_visibility(&mut self) {
+        if self.minimized {
+            for tab_id in &self.tab_order {
+                let _ = self.tab_manager.set_tab_visibility(tab_id, false);
+            }
+            return;
+        }
+        if let Some(tab_id) = self.active_tab_id().cloned() {
+            if self.tab_manager.get_tab(&tab_id).is_some_and(ManagedTab::is_discarded) {
+                self.reload_discarded_tab(&tab_id);
+            }
+        }
+        for (index, tab_id) in self.tab_order.iter().enumerate() {
+            let _ = self.tab_manager.set_tab_visibility(tab_id, index == self.active_tab_index);
+        }
+    }
+
+    /// Start a discarded tab's process again and restore its session history, carrying on the
+    /// navigation it was in the middle of, if any
+    fn reload_discarded_tab(&mut self, tab_id: &str) {
+        if let Err(e) = self.tab_manager.reload_discarded_tab(tab_id) {
+            tracing::error!("Failed to reload discarded tab {}: {}", tab_id, e);
+            return;
+        }
+        self.send_initial_config(tab_id);
+        let Some(tab) = self.tab_manager.get_tab(tab_id) else {
+            return;
+        };
+        let zoom = tab.zoom;
+        let tab_muted = tab.is_muted;
+        let network_conditions = tab.network_conditions;
+        let message = if let Some(navigation) = tab.pending_navigation.clone() {
+            // The process died on its way to another page; go on to it rather than back
+            Some(ParentToTabMessage::ResumeNavigation {
+                history: tab.history.clone(),
+                history_index: tab.history_index,
+                navigation,
+            })
+        } else if !tab.history.is_empty() {
+            Some(ParentToTabMessage::RestoreSession {
+                history: tab.history.clone(),
+                history_index: tab.history_index,
+                scroll: (tab.viewport_scroll.x, tab.viewport_scroll.y),
+            })
+        } else if !tab.url.is_empty() {
+            Some(ParentToTabMessage::Navigate(tab.url.clone()))
+        } else {
+            None
+        };
+        if zoom != 1.0 {
+            let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::SetZoom(zoom));
+        }
+        if tab_muted {
+            let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::SetMuted(true));
+        }
+        if network_conditions.is_emulated() {
+            let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::SetNetworkConditions(network_conditions));
+        }
+        if let Some(message) = message {
+            let _ = self.tab_manager.send_to_tab(tab_id, message);
+        }
+        self.ui_mut().set_tab_discarded(tab_id, false);
+    }
+
+    /// Send input to a tab after any pointer move or wheel turn still waiting, so events arrive
+    /// in order
+    fn send_ui_event(&mut self, tab_id: &str, event: UiEvent) {
+        // A tab showing a dialog is blocked; it gets no input until the dialog is answered
+        if self.pending_dialogs.iter().any(|pending| pending.tab_id == tab_id) {
+            return;
+        }
+        self.flush_pending_input();
+        let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::UI(event));
+    }
+
+    /// Hold a pointer move or wheel turn back, combining it with the ones that follow in the same
+    /// event loop iteration
+    fn queue_ui_event(&mut self, tab_id: &str, event: UiEvent) {
+        if self.pending_dialogs.iter().any(|pending| pending.tab_id == tab_id) {
+            return;
+        }
+        let event = match &mut self.pending_input {
+            Some((pending_tab, pending)) if pending_tab == tab_id => match pending.coalesce(event) {
+                None => return,
+                Some(event) => event,
+            },
+            _ => event,
+        };
+        self.flush_pending_input();
+        self.pending_input = Some((tab_id.to_string(), event));
+    }
+
+    fn flush_pending_input(&mut self) {
+        if let Some((tab_id, event)) = self.pending_input.take() {
+            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::UI(event));
+        }
+    }
+
+    /// Let the tab manager freeze or discard hidden tabs when memory is tight, and mark the tabs it
+    /// discarded, including those whose process died
+    fn apply_memory_policy(&mut self) {
+        if self.env.is_none() || self.ui.is_none() {
+            return;
+        }
+        self.tab_manager.apply_memory_policy();
+        for tab_id in self.tab_manager.take_newly_discarded() {
+            if self.extension_hosts.values().any(|host_id| *host_id == tab_id) {
+                self.stop_extension_host(&tab_id);
+                continue;
+            }
+            if let Some(owner) = self.prerenders.iter().find(|(_, prerender)| prerender.host_id == tab_id).map(|(owner, _)| owner.clone()) {
+                self.cancel_prerender(&owner);
+                continue;
+            }
+            // The page that asked for a prerender is gone
+            self.cancel_prerender(&tab_id);
+            self.activate_window_for_tab(&tab_id);
+            self.ui_mut().set_tab_discarded(&tab_id, true);
         }
     }
 
     fn close_tab(&mut self, tab_index: usize) -> TabCloseResult {
         if self.tab_order.len() <= 1 {
-            return TabCloseResult::QuitApp;
+            return TabCloseResult::CloseWindow;
         }
 
         if tab_index < self.tab_order.len() {
             let tab_id = self.tab_order.remove(tab_index);
+            self.remember_closed_tab(&tab_id);
+            self.pending_permissions.retain(|pending| pending.tab_id != tab_id);
+            self.pending_dialogs.retain(|pending| pending.tab_id != tab_id);
+            if self.pending_login.as_ref().is_some_and(|(login_tab, _)| *login_tab == tab_id) {
+                self.pending_login = None;
+            }
+            self.cancel_prerender(&tab_id);
             let _ = self.tab_manager.close_tab(&tab_id);
             self.ui.as_mut().unwrap().remove_tab(&tab_id);
 
@@ -202,14 +853,89 @@ impl BrowserApp {
                     self.env.as_ref().unwrap().window.set_title(&format!("{} - Stokes Browser", tab.title));
                 }
                 self.update_bookmark_button_state();
+                self.update_javascript_indicator();
+                self.update_popup_indicator();
+                self.sync_find_bar();
             }
+            self.update_tab_visibility();
 
             return TabCloseResult::Closed;
         }
         TabCloseResult::NoAction
     }
 
+    /// Push a tab onto the recently closed stack before its process goes away
+    fn remember_closed_tab(&mut self, tab_id: &str) {
+        let Some(tab) = self.tab_manager.get_tab(tab_id) else {
+            return;
+        };
+        if tab.url.is_empty() && tab.history.is_empty() {
+            return;
+        }
+
+        self.recently_closed.insert(0, ClosedTab {
+            url: tab.url.clone(),
+            title: tab.title.clone(),
+            scroll: tab.viewport_scroll,
+            history: tab.history.clone(),
+            history_index: tab.history_index,
+            is_pinned: tab.is_pinned,
+        });
+        self.recently_closed.truncate(MAX_RECENTLY_CLOSED);
+        self.sync_recently_closed_ui();
+    }
+
+    fn sync_recently_closed_ui(&mut self) {
+        let titles: Vec<String> = self.recently_closed.iter().map(|closed| closed.title.clone()).collect();
+        for state in self.background_windows.values_mut() {
+            state.ui.set_recently_closed(titles.clone());
+        }
+        self.ui_mut().set_recently_closed(titles);
+    }
+
+    /// Reopen a recently closed tab with its history and scroll position
+    fn restore_closed_tab(&mut self, index: usize) {
+        if index >= self.recently_closed.len() {
+            return;
+        }
+        let closed = self.recently_closed.remove(index);
+        self.sync_recently_closed_ui();
+
+        self.add_tab_with_url(None);
+        let Some(tab_id) = self.active_tab_id().cloned() else {
+            return;
+        };
+
+        let message = if closed.history.is_empty() {
+            ParentToTabMessage::Navigate(closed.url.clone())
+        } else {
+            ParentToTabMessage::RestoreSession {
+                history: closed.history,
+                history_index: closed.history_index,
+                scroll: (closed.scroll.x, closed.scroll.y),
+            }
+        };
+        let _ = self.tab_manager.send_to_tab(&tab_id, message);
+
+        let ui = self.ui_mut();
+        ui.update_address_bar(&closed.url);
+        ui.update_tab_title(&tab_id, &closed.title);
+        ui.clear_focus();
+        self.env().window.set_title(&format!("Loading: {}", closed.url));
+
+        if closed.is_pinned {
+            self.toggle_pin_tab(self.active_tab_index);
+        }
+    }
+
     fn switch_to_tab(&mut self, index: usize) {
+        // A page in fullscreen or holding the pointer loses it when another tab is shown
+        if self.page_fullscreen_tab.is_some() && self.tab_order.get(index) != self.page_fullscreen_tab.as_ref() {
+            self.set_fullscreen(false);
+        }
+        if self.pointer_lock_tab.is_some() && self.tab_order.get(index) != self.pointer_lock_tab.as_ref() {
+            self.release_pointer_lock(true);
+        }
         if index < self.tab_order.len() {
             self.active_tab_index = index;
             let tab_id = &self.tab_order[index];
@@ -217,10 +943,105 @@ impl BrowserApp {
 
             if let Some(tab) = self.tab_manager.get_tab(tab_id) {
                 self.ui.as_mut().unwrap().update_address_bar(&tab.url);
+                self.ui.as_mut().unwrap().set_security_state(tab.security.clone());
                 self.env.as_ref().unwrap().window.set_title(&format!("{} - Stokes Browser", tab.title));
             }
             self.update_bookmark_button_state();
+            self.update_javascript_indicator();
+            self.update_popup_indicator();
+            self.sync_find_bar();
+            self.update_install_button();
             self.ui.as_mut().unwrap().clear_focus();
+            self.ui.as_mut().unwrap().hide_autofill_popup();
+            self.update_tab_visibility();
+        }
+    }
+
+    /// Move a tab to a new position in the tab strip, keeping the active tab selected.
+    /// Pinned tabs always stay in front of unpinned tabs.
+    fn reorder_tab(&mut self, from_index: usize, to_index: usize) {
+        if from_index >= self.tab_order.len() || to_index >= self.tab_order.len() {
+            return;
+        }
+
+        let pinned_count = self.ui().pinned_tab_count();
+        let is_pinned = self.tab_manager.get_tab(&self.tab_order[from_index]).is_some_and(|tab| tab.is_pinned);
+        let to_index = if is_pinned {
+            to_index.min(pinned_count.saturating_sub(1))
+        } else {
+            to_index.max(pinned_count)
+        };
+        if from_index == to_index {
+            return;
+        }
+
+        // Reorder tabs in UI
+        self.ui_mut().reorder_tabs(from_index, to_index);
+
+        // Reorder tabs in tab_order
+        let tab_id = self.tab_order.remove(from_index);
+        self.tab_order.insert(to_index, tab_id);
+
+        // Update active tab index if needed
+        if self.active_tab_index == from_index {
+            self.active_tab_index = to_index;
+        } else if from_index < self.active_tab_index && to_index >= self.active_tab_index {
+            self.active_tab_index -= 1;
+        } else if from_index > self.active_tab_index && to_index <= self.active_tab_index {
+            self.active_tab_index += 1;
+        }
+    }
+
+    /// Pin or unpin a tab, moving it to the boundary between pinned and unpinned tabs
+    fn toggle_pin_tab(&mut self, index: usize) {
+        let Some(tab_id) = self.tab_order.get(index).cloned() else {
+            return;
+        };
+        let Some(tab) = self.tab_manager.get_tab_mut(&tab_id) else {
+            return;
+        };
+        tab.is_pinned = !tab.is_pinned;
+        let pinned = tab.is_pinned;
+
+        self.ui_mut().set_tab_pinned(&tab_id, pinned);
+        let pinned_count = self.ui().pinned_tab_count();
+        let target = if pinned { pinned_count - 1 } else { pinned_count };
+        self.reorder_tab(index, target.min(self.tab_order.len() - 1));
+    }
+
+    fn handle_tab_menu_action(&mut self, action: TabMenuAction, event_loop: &dyn ActiveEventLoop) {
+        let (TabMenuAction::TogglePin(tab_id) | TabMenuAction::Close(tab_id)) = &action;
+        let Some(index) = self.tab_order.iter().position(|id| id == tab_id) else {
+            return;
+        };
+        match action {
+            TabMenuAction::TogglePin(_) => self.handle_input_action(&input::InputAction::TogglePinTab(index), event_loop),
+            TabMenuAction::Close(_) => self.handle_input_action(&input::InputAction::CloseTab(index), event_loop),
+        }
+    }
+
+    /// Mute or unmute a tab's sound
+    fn toggle_tab_mute(&mut self, index: usize) {
+        let Some(tab_id) = self.tab_order.get(index).cloned() else {
+            return;
+        };
+        let Some(tab) = self.tab_manager.get_tab(&tab_id) else {
+            return;
+        };
+        let (muted, audible) = (!tab.is_muted, tab.is_audible);
+        let _ = self.tab_manager.set_tab_muted(&tab_id, muted);
+        self.ui_mut().set_tab_audio(&tab_id, audible, muted);
+        self.request_redraw();
+    }
+
+    /// Open files dropped onto the window, either in the active tab or in new tabs
+    fn handle_file_drop(&mut self, paths: &[std::path::PathBuf], y: f32, event_loop: &dyn ActiveEventLoop) {
+        let actions = input::handle_file_drop(paths, y, self.ui());
+        if actions.is_empty() {
+            tracing::info!("Ignoring drop of unsupported files: {:?}", paths);
+        }
+        for action in actions {
+            self.handle_input_action(&action, event_loop);
         }
     }
 
@@ -267,7 +1088,7 @@ impl BrowserApp {
         if action == input::InputAction::None {
             if let Some(tab_id) = self.active_tab_id().cloned() {
                 // Apply chrome offset to forwarded coordinates so tab sees coordinates relative to its page canvas
-                let chrome_offset = BrowserUI::CHROME_HEIGHT as f32 * self.viewport.as_ref().unwrap().hidpi_scale;
+                let chrome_offset = self.ui().chrome_height();
                 let forwarded_y = (y - chrome_offset).max(0.0);
 
                 let key_modifiers = ipc::KeyModifiers {
@@ -288,8 +1109,8 @@ impl BrowserApp {
     fn handle_input_action(&mut self, action: &input::InputAction, event_loop: &dyn ActiveEventLoop) {
         match action {
             input::InputAction::CloseTab(tab_index) => {
-                if self.close_tab(*tab_index) == TabCloseResult::QuitApp {
-                    event_loop.exit();
+                if self.close_tab(*tab_index) == TabCloseResult::CloseWindow {
+                    self.close_window(event_loop);
                 }
             }
             input::InputAction::Navigate(url) => {
@@ -301,37 +1122,36 @@ impl BrowserApp {
             input::InputAction::AddTab => {
                 self.add_tab();
             }
+            input::InputAction::OpenInNewTab(url) => {
+                self.add_tab_with_url(Some(url.as_str()));
+            }
             input::InputAction::ReorderTab { from_index, to_index } => {
-                // Reorder tabs in UI
-                self.ui.as_mut().unwrap().reorder_tabs(*from_index, *to_index);
-
-                // Reorder tabs in tab_order
-                if *from_index < self.tab_order.len() && *to_index < self.tab_order.len() {
-                    let tab_id = self.tab_order.remove(*from_index);
-                    self.tab_order.insert(*to_index, tab_id);
-
-                    // Update active tab index if needed
-                    if self.active_tab_index == *from_index {
-                        self.active_tab_index = *to_index;
-                    } else if *from_index < self.active_tab_index && *to_index >= self.active_tab_index {
-                        self.active_tab_index -= 1;
-                    } else if *from_index > self.active_tab_index && *to_index <= self.active_tab_index {
-                        self.active_tab_index += 1;
-                    }
-                }
+                self.reorder_tab(*from_index, *to_index);
+            }
+            input::InputAction::TogglePinTab(index) => {
+                self.toggle_pin_tab(*index);
+            }
+            input::InputAction::ToggleTabMute(index) => {
+                self.toggle_tab_mute(*index);
+            }
+            input::InputAction::RestoreClosedTab(index) => {
+                self.restore_closed_tab(*index);
             }
             input::InputAction::ReloadPage => {
                 if let Some(tab_id) = self.active_tab_id().cloned() {
+                    self.dismiss_page_dialogs(&tab_id);
                     let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::Reload);
                 }
             }
             input::InputAction::GoBack => {
                 if let Some(tab_id) = self.active_tab_id().cloned() {
+                    self.dismiss_page_dialogs(&tab_id);
                     let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::GoBack);
                 }
             }
             input::InputAction::GoForward => {
                 if let Some(tab_id) = self.active_tab_id().cloned() {
+                    self.dismiss_page_dialogs(&tab_id);
                     let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::GoForward);
                 }
             }
@@ -339,35 +1159,167 @@ impl BrowserApp {
                 self.navigate_to_url(DEFAULT_HOMEPAGE);
             }
             input::InputAction::OpenSettings => {
-                self.ui.as_mut().unwrap().toggle_settings();
+                let settings = self.active_tab_origin()
+                    .map(|origin| self.site_settings.get(&origin))
+                    .unwrap_or_default();
+                let ui = self.ui.as_mut().unwrap();
+                ui.set_site_color_scheme(settings.color_scheme);
+                ui.set_site_force_dark(settings.force_dark);
+                ui.set_site_autoplay(settings.autoplay);
+                ui.set_javascript_enabled(self.site_settings.javascript_enabled());
+                ui.set_image_settings(settings.images.unwrap_or(self.site_settings.images_enabled()), self.site_settings.images_enabled());
+                ui.toggle_settings();
             }
-            input::InputAction::SetDefaultBrowser => {
-                crate::default_browser::set_as_default_browser();
-                self.show_alert("Stokes Browser has been set as your default browser.");
+            input::InputAction::CycleSiteColorScheme => {
+                self.cycle_site_color_scheme();
             }
-            input::InputAction::AddCurrentPageBookmark { parent_id } => {
-                self.add_current_page_bookmark(parent_id.clone());
+            input::InputAction::ToggleSiteForceDark => {
+                self.toggle_site_force_dark();
             }
-            input::InputAction::CreateBookmarkFolder { parent_id } => {
-                self.create_bookmark_folder(parent_id.clone());
+            input::InputAction::CycleSiteAutoplay => {
+                self.cycle_site_autoplay();
             }
-            input::InputAction::RenameBookmark(id) => {
-                self.rename_bookmark(id);
+            input::InputAction::ToggleSiteJavaScript => {
+                self.toggle_site_javascript();
             }
-            input::InputAction::EditBookmarkUrl(id) => {
-                self.edit_bookmark_url(id);
+            input::InputAction::AllowSitePopups => {
+                self.allow_site_popups();
             }
-            input::InputAction::DeleteBookmark(id) => {
-                self.delete_bookmark(id);
+            input::InputAction::OpenFindBar => {
+                self.open_find_bar();
             }
-            input::InputAction::MoveBookmark { id, parent_id, index } => {
-                self.move_bookmark(id, parent_id.clone(), *index);
+            input::InputAction::FindInPage { forward } => {
+                self.find_in_page(*forward);
             }
-            input::InputAction::ToggleCurrentPageBookmark => {
-                self.toggle_current_page_bookmark();
+            input::InputAction::ToggleFindHighlightAll => {
+                self.toggle_find_highlight_all();
+            }
+            input::InputAction::CloseFindBar => {
+                self.close_find_bar();
+            }
+            input::InputAction::ToggleGlobalJavaScript => {
+                self.toggle_global_javascript();
+            }
+            input::InputAction::ToggleSiteImages => {
+                self.toggle_site_images();
+            }
+            input::InputAction::ToggleGlobalImages => {
+                self.toggle_global_images();
+            }
+            input::InputAction::CycleUiTheme => {
+                let mut settings = self.ui().theme_settings().clone();
+                settings.theme = settings.theme.next();
+                self.set_theme_settings(settings);
+            }
+            input::InputAction::ToggleUiDensity => {
+                let mut settings = self.ui().theme_settings().clone();
+                settings.density = settings.density.toggled();
+                self.set_theme_settings(settings);
+            }
+            input::InputAction::ZoomPage(factor) => {
+                let zoom = factor.map_or(1.0, |factor| self.active_tab_zoom() * factor);
+                self.set_active_tab_zoom(zoom);
+            }
+            input::InputAction::CycleMaxLatency => {
+                let settings = self.ui().frame_timing_settings().cycled();
+                settings.save_to_disk();
+                self.ui_mut().set_frame_timing_settings(settings);
+                for state in self.background_windows.values_mut() {
+                    state.ui.set_frame_timing_settings(settings);
+                }
+                self.send_frame_timing();
+                self.request_redraw();
+            }
+            input::InputAction::SetDefaultBrowser => {
+                crate::default_browser::set_as_default_browser();
+                self.show_alert("Stokes Browser has been set as your default browser.");
+            }
+            input::InputAction::SavePage => {
+                self.save_current_page();
+            }
+            input::InputAction::SetLocationOverride => {
+                self.set_location_override();
+            }
+            input::InputAction::SetLanguagesOverride => {
+                self.set_languages_override();
+            }
+            input::InputAction::EmulateNetworkConditions => {
+                self.emulate_network_conditions();
+            }
+            input::InputAction::PermissionDecision { granted } => {
+                self.answer_permission_prompt(*granted);
+            }
+            input::InputAction::AnswerPageDialog { accepted } => {
+                self.answer_page_dialog(*accepted);
+            }
+            input::InputAction::Autofill(index) => {
+                let message = match &self.autofill_offer {
+                    AutofillOffer::Profiles => self.autofill.profiles().get(*index).cloned().map(ParentToTabMessage::Autofill),
+                    AutofillOffer::Logins(logins) => logins.get(*index).map(|login| ParentToTabMessage::FillLogin {
+                        origin: login.origin.clone(),
+                        username: login.username.clone(),
+                        password: login.password.clone(),
+                    }),
+                };
+                if let (Some(message), Some(tab_id)) = (message, self.active_tab_id().cloned()) {
+                    let _ = self.tab_manager.send_to_tab(&tab_id, message);
+                }
+            }
+            input::InputAction::ManageAutofillProfiles => {
+                self.manage_autofill_profiles();
+            }
+            input::InputAction::OpenProfile => {
+                self.open_profile_window();
+            }
+            input::InputAction::InstallWebApp => {
+                if let Some(tab_id) = self.active_tab_id().cloned() {
+                    self.install_web_app(&tab_id);
+                }
+            }
+            input::InputAction::CaptureScreenshot { full_page } => {
+                if let Some(tab_id) = self.active_tab_id().cloned() {
+                    let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::CaptureScreenshot { full_page: *full_page });
+                }
+            }
+            input::InputAction::ReportResources => {
+                if let Some(tab_id) = self.active_tab_id().cloned() {
+                    let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::ReportResources);
+                }
+            }
+            input::InputAction::AddCurrentPageBookmark { parent_id } => {
+                self.add_current_page_bookmark(parent_id.clone());
+            }
+            input::InputAction::CreateBookmarkFolder { parent_id } => {
+                self.create_bookmark_folder(parent_id.clone());
+            }
+            input::InputAction::RenameBookmark(id) => {
+                self.rename_bookmark(id);
+            }
+            input::InputAction::EditBookmarkUrl(id) => {
+                self.edit_bookmark_url(id);
+            }
+            input::InputAction::DeleteBookmark(id) => {
+                self.delete_bookmark(id);
+            }
+            input::InputAction::MoveBookmark { id, parent_id, index } => {
+                self.move_bookmark(id, parent_id.clone(), *index);
+            }
+            input::InputAction::ToggleCurrentPageBookmark => {
+                self.toggle_current_page_bookmark();
+            }
+            input::InputAction::RequestRedraw => {}
+            input::InputAction::ToggleFullscreen => {
+                let fullscreen = !self.ui().is_fullscreen();
+                self.set_fullscreen(fullscreen);
+            }
+            input::InputAction::ExitFullscreen => {
+                self.set_fullscreen(false);
+            }
+            input::InputAction::NewWindow => {
+                self.open_window(event_loop, Some(DEFAULT_HOMEPAGE));
             }
-            input::InputAction::RequestRedraw => {}
             input::InputAction::QuitApp => {
+                self.save_window_placement();
                 event_loop.exit();
             }
             input::InputAction::ForwardToTab(_) => {
@@ -375,7 +1327,10 @@ impl BrowserApp {
             }
             input::InputAction::None => {}
         }
-        self.env.as_ref().unwrap().window.request_redraw();
+        // The action may have closed the last window
+        if let Some(env) = self.env.as_ref() {
+            env.window.request_redraw();
+        }
     }
 
     fn handle_bookmark_ui_action(&mut self, action: BookmarkUiAction, event_loop: &dyn ActiveEventLoop) {
@@ -398,19 +1353,58 @@ impl BrowserApp {
         if self.env.is_none() || self.ui.is_none() {
             return;
         }
+        let _span = tracing::debug_span!("tab_messages").entered();
 
         let messages = self.tab_manager.poll_messages();
 
         for (tab_id, message) in messages {
             self.tab_manager.process_tab_message(&tab_id, message.clone());
+            if self.tab_manager.get_tab(&tab_id).is_some_and(|tab| tab.is_extension_host) {
+                self.process_extension_host_message(&tab_id, message);
+                continue;
+            }
+            if self.tab_manager.get_tab(&tab_id).is_some_and(|tab| tab.is_prerender) {
+                self.process_prerender_host_message(&tab_id, message);
+                continue;
+            }
+            self.activate_window_for_tab(&tab_id);
+            // Whatever else a tab reports may show in the chrome
+            if !matches!(message, TabToParentMessage::Log(_) | TabToParentMessage::MemoryUsage { .. }) {
+                self.request_redraw();
+            }
 
             // Update UI based on messages
             match message {
                 TabToParentMessage::NavigationStarted(_) => {
+                    // Prompts, the pointer lock and prerenders belong to the document that asked
+                    self.pending_permissions.retain(|pending| pending.tab_id != tab_id);
+                    self.cancel_prerender(&tab_id);
+                    if self.pointer_lock_tab.as_ref() == Some(&tab_id) {
+                        self.release_pointer_lock(false);
+                    }
                     self.ui.as_mut().unwrap().update_tab_loading(&tab_id, true);
                     self.ui.as_mut().unwrap().update_tab_favicon(&tab_id, None);
+                    // The new document hasn't played anything yet
+                    let muted = self.tab_manager.get_tab(&tab_id).is_some_and(|tab| tab.is_muted);
+                    self.ui_mut().set_tab_audio(&tab_id, false, muted);
+                    if Some(&tab_id) == self.active_tab_id() {
+                        self.ui_mut().set_security_state(SecurityState::default());
+                        self.ui_mut().hide_autofill_popup();
+                        self.update_install_button();
+                        self.update_popup_indicator();
+                    }
+                    if self.find_tab.as_ref() == Some(&tab_id) {
+                        // The new document has its own matches, found when the user searches again
+                        self.find_query.clear();
+                        self.ui_mut().set_find_result(None);
+                    }
                     self.env.as_ref().unwrap().window.request_redraw();
                 }
+                TabToParentMessage::FindResult { active, count } => {
+                    if self.find_tab.as_ref() == Some(&tab_id) {
+                        self.ui_mut().set_find_result(Some((active, count)));
+                    }
+                }
                 TabToParentMessage::TitleChanged(title) => {
                     self.ui.as_mut().unwrap().update_tab_title(&tab_id, &title);
                     if Some(&tab_id) == self.active_tab_id() {
@@ -418,6 +1412,7 @@ impl BrowserApp {
                     }
                 }
                 TabToParentMessage::NavigationCompleted { url, title } => {
+                    self.record_visited_link(&url);
                     self.ui.as_mut().unwrap().update_tab_title(&tab_id, &title);
                     self.ui.as_mut().unwrap().update_tab_loading(&tab_id, false);
                     if let Some(favicon_bytes) = self
@@ -431,6 +1426,7 @@ impl BrowserApp {
                         self.ui.as_mut().unwrap().update_address_bar(&url);
                         self.env.as_ref().unwrap().window.set_title(&format!("{} - Stokes Browser", title));
                         self.update_bookmark_button_state();
+                        self.update_javascript_indicator();
                     }
                 }
                 TabToParentMessage::LoadingStateChanged(_is_loading) => {
@@ -445,26 +1441,49 @@ impl BrowserApp {
                 }
                 TabToParentMessage::NavigateRequest(url) => {
                     // Handle navigation request from web content (e.g., link clicks)
-                    println!("Handling navigation request to: {}", url);
+                    tracing::debug!("Handling navigation request to: {}", url);
                     let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::Navigate(url.clone()));
                     if Some(&tab_id) == self.active_tab_id() {
                         self.ui.as_mut().unwrap().update_address_bar(&url);
                         self.update_bookmark_button_state();
+                        self.update_javascript_indicator();
                     }
                 }
-                TabToParentMessage::NavigateRequestInNewTab(url) => {
+                TabToParentMessage::NavigateRequestInNewTab { url, .. } if self.ui().is_app_mode() => {
+                    self.app_links.push(url);
+                }
+                TabToParentMessage::NavigateRequestInNewTab { url, opener } => {
                     // Handle navigation request in a new tab (e.g., Ctrl+click on link)
-                    println!("Handling navigation request in new tab to: {}", url);
+                    tracing::debug!("Handling navigation request in new tab to: {}", url);
                     let tab_index = self.active_tab_index;
                     self.add_tab();
+                    if let (Some(opener), Some(new_tab_id)) = (opener, self.active_tab_id().cloned()) {
+                        let _ = self.tab_manager.send_to_tab(&new_tab_id, ParentToTabMessage::SetOpener(opener));
+                    }
                     self.navigate_to_url(&*url);
 
                     self.switch_to_tab(tab_index);
                 }
-                TabToParentMessage::Alert(message) => {
-                    // Display alert dialog using native dialog
-                    println!("Alert from tab {}: {}", tab_id, message);
-                    self.show_alert(&message);
+                TabToParentMessage::Dialog { request_id, origin, dialog } => {
+                    tracing::debug!("Dialog from tab {}: {:?}", tab_id, dialog);
+                    let shown = self.tab_manager.get_tab_mut(&tab_id).and_then(|tab| {
+                        (!tab.dialogs_muted).then(|| {
+                            tab.dialogs_shown += 1;
+                            tab.dialogs_shown
+                        })
+                    });
+                    if let Some(shown) = shown {
+                        if Some(&tab_id) == self.active_tab_id() {
+                            // The dialog is drawn with the chrome, which fullscreen hides
+                            self.set_fullscreen(false);
+                        }
+                        let offer_mute = shown > DIALOGS_BEFORE_MUTE_OFFER;
+                        self.pending_dialogs.push(PendingDialog { tab_id: tab_id.clone(), request_id, origin, dialog, offer_mute });
+                        self.request_redraw();
+                    } else {
+                        // A muted page's dialogs are answered as if dismissed, so its script carries on at once
+                        let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::DialogAnswer { request_id, value: None });
+                    }
                 }
                 TabToParentMessage::ShellProvider(shell_msg) => {
                     match shell_msg {
@@ -494,15 +1513,182 @@ impl BrowserApp {
                                 ),
                             ));
                         },
+                        ShellProviderMessage::SetPointerLock(true) => {
+                            self.lock_pointer(&tab_id);
+                        }
+                        ShellProviderMessage::SetPointerLock(false) => {
+                            if self.pointer_lock_tab.as_ref() == Some(&tab_id) {
+                                self.release_pointer_lock(false);
+                            }
+                        }
+                        ShellProviderMessage::SetFullscreen(fullscreen) => {
+                            if fullscreen {
+                                self.set_fullscreen(true);
+                                self.page_fullscreen_tab = Some(tab_id.clone());
+                            } else if self.page_fullscreen_tab.as_ref() == Some(&tab_id) {
+                                // The page already knows, so don't echo ExitFullscreen back
+                                self.page_fullscreen_tab = None;
+                                self.set_fullscreen(false);
+                            }
+                        }
                         ShellProviderMessage::ViewportScroll((x, y)) => {
                             let tab = self.tab_mut();
-                            tab.viewport_scroll = Point { x, y }
+                            tab.viewport_scroll = Point { x, y };
+                            // The popup would no longer line up with its field
+                            if self.ui_mut().hide_autofill_popup() {
+                                self.request_redraw();
+                            }
+                        }
+                        ShellProviderMessage::ShowNotification { title, body } => {
+                            let allowed = self.tab_origin(&tab_id)
+                                .is_some_and(|origin| self.permissions.state(&origin, PermissionKind::Notifications) == PermissionState::Granted);
+                            // The page checks too, but only the parent's decision counts
+                            if allowed {
+                                let message = if body.is_empty() { title } else { format!("{title}: {body}") };
+                                self.ui_mut().show_toast(message);
+                                self.request_redraw();
+                            }
+                        }
+                        ShellProviderMessage::RequestGeolocation { request_id, origin } => {
+                            self.locate_for_tab(&tab_id, request_id, &origin);
+                        }
+                        ShellProviderMessage::AutofillField { x, y, width, height } => {
+                            let labels: Vec<String> = self.autofill.profiles().iter()
+                                .map(|profile| profile.label.clone())
+                                .collect();
+                            if Some(&tab_id) == self.active_tab_id() && !labels.is_empty() {
+                                self.autofill_offer = AutofillOffer::Profiles;
+                                self.ui_mut().show_autofill_popup((x, y, width, height), labels, None);
+                                self.request_redraw();
+                            }
+                        }
+                        ShellProviderMessage::LoginField { x, y, width, height } => {
+                            // Never the origin the tab claims, so a compromised tab can't ask for another site's logins
+                            let logins = self.tab_origin(&tab_id)
+                                .map(|origin| PasswordStore::load_from_disk().logins_for(&origin))
+                                .unwrap_or_default();
+                            if Some(&tab_id) == self.active_tab_id() && !logins.is_empty() {
+                                let labels = logins.iter()
+                                    .map(|login| {
+                                        let username = if login.username.is_empty() { "(no username)" } else { &login.username };
+                                        format!("{username}  ••••••••")
+                                    })
+                                    .collect();
+                                self.autofill_offer = AutofillOffer::Logins(logins);
+                                // Preselected so that Enter fills the only or first login
+                                self.ui_mut().show_autofill_popup((x, y, width, height), labels, Some(0));
+                                self.request_redraw();
+                            }
+                        }
+                        ShellProviderMessage::LoginSubmitted { username, password } => {
+                            if let Some(origin) = self.tab_origin(&tab_id) {
+                                let login = SavedLogin { origin, username, password };
+                                if PasswordStore::load_from_disk().should_offer(&login) {
+                                    self.pending_login = Some((tab_id.clone(), login));
+                                    self.request_redraw();
+                                }
+                            }
+                        }
+                        ShellProviderMessage::AutofillFieldBlurred => {
+                            if Some(&tab_id) == self.active_tab_id() && self.ui_mut().hide_autofill_popup() {
+                                self.request_redraw();
+                            }
+                        }
+                        // Turned into TabToParentMessage::SecurityState by the tab process
+                        ShellProviderMessage::MixedContentChanged => {}
+                        // Turned into TabToParentMessage::PermissionRequest by the tab process
+                        ShellProviderMessage::RequestPermission { .. } => {}
+                        // Turned into TabToParentMessage::AudioStateChanged by the tab process
+                        ShellProviderMessage::AudioStateChanged { .. } => {}
+                        ShellProviderMessage::ExtensionMessage { extension_id, request_id, message } => {
+                            self.send_extension_message(&tab_id, &extension_id, request_id, message);
+                        }
+                        // Only background pages answer messages, and theirs are handled above
+                        ShellProviderMessage::ExtensionResponse { .. } => {}
+                        ShellProviderMessage::RequestAppInstall => {
+                            self.install_web_app(&tab_id);
+                        }
+                        ShellProviderMessage::Prerender(url) => {
+                            self.start_prerender(&tab_id, url);
+                        }
+                        ShellProviderMessage::PopupBlocked(url) => {
+                            tracing::debug!("Blocked a popup to {} in tab {}", url, tab_id);
+                            if let Some(tab) = self.tab_manager.get_tab_mut(&tab_id) {
+                                tab.blocked_popups.push(url);
+                            }
+                            if Some(&tab_id) == self.active_tab_id() {
+                                self.update_popup_indicator();
+                                self.request_redraw();
+                            }
                         }
                     }
                 },
                 TabToParentMessage::UpdateButtons(buttons) => {
                     self.buttons = buttons;
                 }
+                TabToParentMessage::ActivatePrerender(url) => {
+                    self.activate_prerender(&tab_id, url);
+                }
+                TabToParentMessage::SecurityState(state) => {
+                    if Some(&tab_id) == self.active_tab_id() {
+                        self.ui_mut().set_security_state(state);
+                        self.request_redraw();
+                    }
+                }
+                TabToParentMessage::PermissionRequest { request_id, kind } => {
+                    // A tab with no committed origin can't be granted anything
+                    let origin = self.tab_origin(&tab_id);
+                    let state = origin.as_deref().map_or(PermissionState::Denied, |origin| self.permissions.state(origin, kind));
+                    match (state, origin) {
+                        (PermissionState::Prompt, Some(origin)) => {
+                            self.pending_permissions.push(PendingPermission { tab_id: tab_id.clone(), request_id, origin, kind });
+                            self.request_redraw();
+                        }
+                        (state, _) => {
+                            let granted = state == PermissionState::Granted;
+                            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::PermissionDecision { request_id, granted });
+                        }
+                    }
+                }
+                TabToParentMessage::PasswordsPageRequest { request_id, url } => {
+                    let html = self.passwords_page(&url);
+                    let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::PasswordsPage { request_id, html });
+                }
+                TabToParentMessage::AuthRequired { challenge, retry } => {
+                    self.answer_auth_challenge(&tab_id, challenge, retry);
+                }
+                TabToParentMessage::ScreenshotSaved(result) => {
+                    let message = match result {
+                        Ok(path) => format!("Screenshot saved to {path}"),
+                        Err(error) => format!("Screenshot failed: {error}"),
+                    };
+                    if let Some(ui) = self.ui.as_mut() {
+                        ui.show_toast(message);
+                    }
+                    self.request_redraw();
+                }
+                TabToParentMessage::ResourceReport(report) => {
+                    tracing::info!("Resources held by tab {}: {report}", tab_id);
+                    if let Some(ui) = self.ui.as_mut() {
+                        ui.show_toast(format!("Tab resources: {report}"));
+                    }
+                    self.request_redraw();
+                }
+                TabToParentMessage::PageSaved { path, error } => match error {
+                    Some(error) => self.show_alert(&format!("Failed to save page to {path}: {error}")),
+                    None => tracing::info!("Saved page from tab {} to {}", tab_id, path),
+                },
+                TabToParentMessage::WebAppManifest { .. } => {
+                    if Some(&tab_id) == self.active_tab_id() {
+                        self.update_install_button();
+                        self.request_redraw();
+                    }
+                }
+                TabToParentMessage::AudioStateChanged { audible } => {
+                    let muted = self.tab_manager.get_tab(&tab_id).is_some_and(|tab| tab.is_muted);
+                    self.ui_mut().set_tab_audio(&tab_id, audible, muted);
+                    self.request_redraw();
+                }
                 TabToParentMessage::FaviconUpdated(favicon) => {
                     self.ui.as_mut().unwrap().update_tab_favicon(&tab_id, favicon.as_deref());
                     let tab_url = self
@@ -531,18 +1717,11 @@ impl BrowserApp {
     }
 
     pub fn pointer_coords(&self, position: PhysicalPosition<f64>) -> PointerCoords {
-        let scale = self.viewport.as_ref().unwrap().scale_f64();
-        let chrome_offset = BrowserUI::CHROME_HEIGHT;
-        let LogicalPosition::<f32> {
-            x: screen_x,
-            y: mut screen_y,
-        } = position.to_logical(scale);
-        screen_y = screen_y - chrome_offset;
+        let (screen_x, screen_y) = self.page_area_point(position);
         let viewport_scroll_offset = self.tab().viewport_scroll;
 
-
-        let client_x = screen_x;
-        let client_y = screen_y;
+        // On a magnified page the point on screen shows a point further into the layout viewport
+        let (client_x, client_y) = self.tab().visual_viewport.to_layout((screen_x, screen_y));
         let page_x = client_x + viewport_scroll_offset.x as f32;
         let page_y = client_y + viewport_scroll_offset.y as f32;
 
@@ -557,6 +1736,45 @@ impl BrowserApp {
     }
 
     fn render(&mut self) -> Result<(), String> {
+        let _span = tracing::debug_span!("composite").entered();
+        self.make_gl_current();
+        let prompt = self
+            .active_permission_request()
+            .map(|pending| PromptBar {
+                message: format!("{} wants to {}", display_origin(&pending.origin), pending.kind.prompt_text()),
+                accept_label: "Allow",
+                decline_label: "Block",
+            })
+            .or_else(|| self.active_pending_login().map(|login| PromptBar {
+                message: if login.username.is_empty() {
+                    format!("Save the password for {}?", display_origin(&login.origin))
+                } else {
+                    format!("Save the password for {} on {}?", login.username, display_origin(&login.origin))
+                },
+                accept_label: "Save",
+                decline_label: "Never",
+            }));
+        self.ui_mut().set_permission_prompt(prompt);
+        let dialog = self.active_page_dialog().map(|pending| {
+            let title = match pending.origin.as_str() {
+                "" | "null" => "This page says".to_string(),
+                origin => format!("{} says", display_origin(origin)),
+            };
+            let (message, input) = match &pending.dialog {
+                JsDialog::Alert(message) | JsDialog::Confirm(message) => (message.clone(), None),
+                JsDialog::Prompt { message, default } => (message.clone(), Some(default.clone())),
+            };
+            PageDialog {
+                key: format!("{}:{}", pending.tab_id, pending.request_id),
+                title,
+                message,
+                cancelable: !matches!(pending.dialog, JsDialog::Alert(_)),
+                input,
+                offer_mute: pending.offer_mute,
+            }
+        });
+        self.ui_mut().set_page_dialog(dialog);
+
         let active_tab_id = self.active_tab_id().cloned();
         let env = self.env.as_mut().unwrap();
         let ui = self.ui.as_mut().unwrap();
@@ -586,10 +1804,12 @@ impl BrowserApp {
         }
 
         // Get the rendered frame before borrowing canvas
-        let frame_to_render = active_tab_id.as_ref()
-            .and_then(|id| self.tab_manager.get_tab(id))
+        let active_tab = active_tab_id.as_ref().and_then(|id| self.tab_manager.get_tab(id));
+        let frame_to_render = active_tab
             .and_then(|tab| tab.rendered_frame.as_ref())
             .map(|frame| &frame.image);
+        let visual_viewport = active_tab.map(|tab| tab.visual_viewport).unwrap_or_default();
+        let hidpi_scale = self.viewport.as_ref().map_or(1.0, |viewport| viewport.hidpi_scale);
 
         let canvas = self.env.as_mut().unwrap().surface.canvas();
 
@@ -603,13 +1823,25 @@ impl BrowserApp {
         // Render the active tab's frame from shared memory
         if let Some(image) = frame_to_render {
             // Offset the page content so it renders below the chrome
-            let chrome_offset = BrowserUI::CHROME_HEIGHT * self.viewport.as_ref().unwrap().hidpi_scale;
+            let chrome_offset = ui.chrome_height();
 
             // GL readback is bottom-up; flip in canvas space to avoid a CPU flip/copy.
             canvas.save();
-            canvas.translate((0.0, chrome_offset + image.height() as f32));
+            canvas.translate((0.0, chrome_offset));
+            // Magnify the visual viewport's part of the frame to fill the page area
+            if visual_viewport.is_magnified() {
+                canvas.clip_rect(skia_safe::Rect::from_wh(image.width() as f32, image.height() as f32), None, None);
+                canvas.scale((visual_viewport.scale, visual_viewport.scale));
+                canvas.translate((-visual_viewport.offset_x * hidpi_scale, -visual_viewport.offset_y * hidpi_scale));
+            }
+            canvas.translate((0.0, image.height() as f32));
             canvas.scale((1.0, -1.0));
-            canvas.draw_image(image, (0.0, 0.0), None);
+            let sampling = if visual_viewport.is_magnified() {
+                skia_safe::SamplingOptions::new(skia_safe::FilterMode::Linear, skia_safe::MipmapMode::None)
+            } else {
+                skia_safe::SamplingOptions::default()
+            };
+            canvas.draw_image_with_sampling_options(image, (0.0, 0.0), sampling, None);
             canvas.restore();
         }
 
@@ -622,6 +1854,11 @@ impl BrowserApp {
             env.gl_surface.swap_buffers(&env.gl_context)
                 .map_err(|e| format!("Failed to swap buffers: {}", e))?;
         }
+        logging::frame_mark();
+
+        // With vsync the swap returns once the frame is up, which marks the display's refresh
+        self.present_clock.presented(Instant::now());
+        self.send_frame_timing();
 
         Ok(())
     }
@@ -633,80 +1870,1358 @@ impl BrowserApp {
         use rfd::MessageDialog;
         use rfd::MessageLevel;
 
-        MessageDialog::new()
-            .set_level(MessageLevel::Info)
-            .set_title("Alert")
-            .set_description(message)
-            .set_buttons(rfd::MessageButtons::Ok)
+        MessageDialog::new()
+            .set_level(MessageLevel::Info)
+            .set_title("Alert")
+            .set_description(message)
+            .set_buttons(rfd::MessageButtons::Ok)
+            .show();
+    }
+
+    /// Track the active window's geometry while it is in a normal state, since that is what
+    /// un-maximizing it goes back to
+    fn note_window_placement(&mut self) {
+        if self.minimized {
+            return;
+        }
+        let Some(window) = self.env.as_ref().map(|env| &env.window) else {
+            return;
+        };
+        if window.is_maximized() || window.fullscreen().is_some() {
+            return;
+        }
+        let size = window.surface_size().to_logical::<f64>(window.scale_factor());
+        self.window_placement = Some(WindowPlacement {
+            position: window.outer_position().ok().map(|position| (position.x, position.y)),
+            width: size.width,
+            height: size.height,
+            maximized: false,
+        });
+    }
+
+    /// Save the active window's placement for the next launch
+    fn save_window_placement(&mut self) {
+        self.note_window_placement();
+        let maximized = self.env.as_ref().is_some_and(|env| env.window.is_maximized());
+        if let Some(placement) = self.window_placement.as_mut() {
+            placement.maximized = maximized;
+            placement.save_to_disk();
+        }
+    }
+
+    /// Pause the window's tabs while it is minimized and show the active one again on restore
+    fn set_minimized(&mut self, minimized: bool) {
+        if self.minimized == minimized {
+            return;
+        }
+        tracing::debug!("Window {}", if minimized { "minimized" } else { "restored" });
+        self.minimized = minimized;
+        self.update_tab_visibility();
+        if !minimized {
+            self.request_redraw();
+        }
+    }
+
+    fn active_tab_zoom(&self) -> f32 {
+        self.active_tab_id()
+            .and_then(|tab_id| self.tab_manager.get_tab(tab_id))
+            .map(|tab| tab.zoom)
+            .unwrap_or(1.0)
+    }
+
+    /// Zoom the active tab's page, within the zoom limits
+    fn set_active_tab_zoom(&mut self, zoom: f32) {
+        let Some(tab_id) = self.active_tab_id().cloned() else {
+            return;
+        };
+        let zoom = zoom.clamp(MIN_PAGE_ZOOM, MAX_PAGE_ZOOM);
+        let Some(tab) = self.tab_manager.get_tab_mut(&tab_id) else {
+            return;
+        };
+        if (tab.zoom - zoom).abs() < 0.005 {
+            return;
+        }
+        tab.zoom = zoom;
+        let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::SetZoom(zoom));
+        self.request_redraw();
+    }
+
+    /// The first two fingers on the page, if there are exactly two
+    fn pinching_fingers(&self) -> Option<(PhysicalPosition<f64>, PhysicalPosition<f64>)> {
+        let mut fingers = self.touches.values().filter(|touch| !touch.on_chrome);
+        match (fingers.next(), fingers.next(), fingers.next()) {
+            (Some(first), Some(second), None) => Some((first.position, second.position)),
+            _ => None,
+        }
+    }
+
+    /// Start a pinch when a finger lands or lifts and leaves two on the page, and end it when it
+    /// leaves any other number
+    fn update_pinch(&mut self) {
+        self.pinch = self.pinching_fingers().map(|(first, second)| Pinch {
+            distance: finger_distance(first, second).max(1.0),
+            scale: self.active_visual_viewport().scale,
+        });
+    }
+
+    /// Magnify the page by how much farther apart the pinching fingers are than when they landed,
+    /// around the point between them
+    fn continue_pinch(&mut self) {
+        let (Some(pinch), Some((first, second))) = (&self.pinch, self.pinching_fingers()) else {
+            return;
+        };
+        let scale = pinch.scale * (finger_distance(first, second) / pinch.distance) as f32;
+        let midpoint = PhysicalPosition::new((first.x + second.x) / 2.0, (first.y + second.y) / 2.0);
+        self.zoom_visual_viewport(scale / self.active_visual_viewport().scale, midpoint);
+    }
+
+    fn active_visual_viewport(&self) -> VisualViewport {
+        self.active_tab_id()
+            .and_then(|tab_id| self.tab_manager.get_tab(tab_id))
+            .map(|tab| tab.visual_viewport)
+            .unwrap_or_default()
+    }
+
+    /// Logical size of the active window's page area: the layout viewport, which the visual
+    /// viewport moves within
+    fn page_area_size(&self) -> (f32, f32) {
+        let viewport = self.page_viewport.as_ref().unwrap();
+        let (width, height) = viewport.window_size;
+        (width as f32 / viewport.hidpi_scale, height as f32 / viewport.hidpi_scale)
+    }
+
+    /// Where a position in the window is on the page area, in logical pixels
+    fn page_area_point(&self, position: PhysicalPosition<f64>) -> (f32, f32) {
+        let scale = self.viewport.as_ref().unwrap().scale_f64();
+        let chrome_offset = self.ui().chrome_height() / scale as f32;
+        let LogicalPosition::<f32> { x, y } = position.to_logical(scale);
+        (x, y - chrome_offset)
+    }
+
+    /// Magnify the active tab's page by `factor` around `focus`, a position in the window. Only
+    /// the composite changes; the page isn't laid out again.
+    fn zoom_visual_viewport(&mut self, factor: f32, focus: PhysicalPosition<f64>) {
+        let Some(tab_id) = self.active_tab_id().cloned() else {
+            return;
+        };
+        let mut visual_viewport = self.active_visual_viewport();
+        visual_viewport.zoom_around(factor, self.page_area_point(focus), self.page_area_size());
+        if let Err(e) = self.tab_manager.set_tab_visual_viewport(&tab_id, visual_viewport) {
+            tracing::error!("Failed to update the visual viewport of tab {}: {}", tab_id, e);
+        }
+        self.request_redraw();
+    }
+
+    /// Pan a magnified page by a wheel turn. Returns what is left of the turn once the visual
+    /// viewport reaches the edge of the layout viewport, for the page to scroll by.
+    fn pan_visual_viewport(&mut self, tab_id: &str, delta: BlitzWheelDelta) -> Option<BlitzWheelDelta> {
+        let mut visual_viewport = self.active_visual_viewport();
+        if !visual_viewport.is_magnified() {
+            return Some(delta);
+        }
+        // The page scrolls the other way from how wheel deltas are signed
+        let hidpi_scale = self.page_viewport.as_ref().unwrap().hidpi_scale as f64;
+        let (dx, dy) = match delta {
+            BlitzWheelDelta::Lines(x, y) => (x * 40.0, y * 50.0),
+            BlitzWheelDelta::Pixels(x, y) => (x / hidpi_scale, y / hidpi_scale),
+        };
+        let (left_x, left_y) = visual_viewport.pan(-dx as f32, -dy as f32, self.page_area_size());
+        if let Err(e) = self.tab_manager.set_tab_visual_viewport(tab_id, visual_viewport) {
+            tracing::error!("Failed to update the visual viewport of tab {}: {}", tab_id, e);
+        }
+        self.request_redraw();
+
+        let (left_x, left_y) = (-left_x as f64, -left_y as f64);
+        if left_x.abs() < 0.5 && left_y.abs() < 0.5 {
+            return None;
+        }
+        Some(match delta {
+            BlitzWheelDelta::Lines(..) => BlitzWheelDelta::Lines(left_x / 40.0, left_y / 50.0),
+            BlitzWheelDelta::Pixels(..) => BlitzWheelDelta::Pixels(left_x * hidpi_scale, left_y * hidpi_scale),
+        })
+    }
+
+    /// Keep the visual viewports of the active window's tabs inside their page area after it
+    /// changes size
+    fn clamp_visual_viewports(&mut self) {
+        let page_area = self.page_area_size();
+        for tab_id in self.tab_order.clone() {
+            let Some(mut visual_viewport) = self.tab_manager.get_tab(&tab_id).map(|tab| tab.visual_viewport) else {
+                continue;
+            };
+            visual_viewport.clamp(page_area);
+            let _ = self.tab_manager.set_tab_visual_viewport(&tab_id, visual_viewport);
+        }
+    }
+
+    /// Grab and hide the cursor for `tab_id`, which must be the active tab of a focused window.
+    /// When the platform can't lock the cursor in place it is confined to the window instead.
+    fn lock_pointer(&mut self, tab_id: &str) {
+        let window = &self.env().window;
+        let grabbed = self.active_tab_id().map(String::as_str) == Some(tab_id)
+            && window.has_focus()
+            && window
+                .set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+                .is_ok();
+        if !grabbed {
+            tracing::debug!("Refused to lock the pointer for tab {}", tab_id);
+            let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::ExitPointerLock);
+            return;
+        }
+        window.set_cursor_visible(false);
+        if let Some(previous) = self.pointer_lock_tab.replace(tab_id.to_string()) {
+            if previous != tab_id {
+                let _ = self.tab_manager.send_to_tab(&previous, ParentToTabMessage::ExitPointerLock);
+            }
+        }
+    }
+
+    /// Give the cursor back. `notify_page` tells the page that held it, for when the browser
+    /// rather than the page ended the lock.
+    fn release_pointer_lock(&mut self, notify_page: bool) {
+        let Some(tab_id) = self.pointer_lock_tab.take() else {
+            return;
+        };
+        let window = &self.env().window;
+        let _ = window.set_cursor_grab(CursorGrabMode::None);
+        window.set_cursor_visible(true);
+        if notify_page {
+            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::ExitPointerLock);
+        }
+    }
+
+    /// Tell the user a window could not be opened, since it may be the only sign anything happened
+    fn show_window_error(&self, error: &crate::window::WindowError) {
+        use rfd::{MessageButtons, MessageDialog, MessageLevel};
+
+        MessageDialog::new()
+            .set_level(MessageLevel::Error)
+            .set_title("Stokes Browser")
+            .set_description(format!("Stokes Browser could not open a window.\n\n{}", error))
+            .set_buttons(MessageButtons::Ok)
+            .show();
+    }
+
+    fn request_redraw(&self) {
+        if let Some(env) = self.env.as_ref() {
+            env.window.request_redraw();
+        }
+    }
+
+    /// Apply new desktop preferences to every window's viewport and every open tab
+    fn set_media_preferences(&mut self, preferences: MediaPreferences) {
+        if preferences == self.media_preferences {
+            return;
+        }
+        self.media_preferences = preferences;
+        let color_scheme = preferences.color_scheme();
+        for viewport in self.viewport.iter_mut().chain(self.page_viewport.iter_mut()) {
+            viewport.color_scheme = color_scheme;
+        }
+        for state in self.background_windows.values_mut() {
+            state.viewport.color_scheme = color_scheme;
+            state.page_viewport.color_scheme = color_scheme;
+        }
+
+        let tab_ids = self.tab_order.iter()
+            .chain(self.background_windows.values().flat_map(|state| state.tab_order.iter()))
+            .cloned()
+            .collect::<Vec<_>>();
+        for tab_id in tab_ids {
+            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::SetMediaPreferences(preferences));
+        }
+    }
+
+    fn sync_bookmarks_ui(&mut self) {
+        let snapshot = self.bookmarks.items().to_vec();
+        for state in self.background_windows.values_mut() {
+            state.ui.set_bookmarks(snapshot.clone());
+        }
+        self.ui_mut().set_bookmarks(snapshot);
+        self.update_bookmark_button_state();
+        self.update_javascript_indicator();
+    }
+
+    fn prompt_input(title: &str, message: &str, default: &str) -> Option<String> {
+        tinyfiledialogs::input_box(title, message, default)
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn selected_folder_target(&self) -> Option<String> {
+        self.ui()
+            .selected_bookmark_id()
+            .and_then(|id| self.bookmarks.get(id))
+            .and_then(|bookmark| if bookmark.is_folder() { Some(bookmark.id.clone()) } else { None })
+    }
+
+    fn update_bookmark_button_state(&mut self) {
+        let url = self
+            .active_tab_id()
+            .and_then(|id| self.tab_manager.get_tab(id))
+            .map(|tab| tab.url.clone())
+            .unwrap_or_default();
+        let is_bookmarked = !url.trim().is_empty() && self.bookmarks.find_by_url(&url).is_some();
+        self.ui_mut().set_current_page_bookmarked(is_bookmarked);
+    }
+
+    fn persist_bookmark_favicon_for_url(&mut self, url: &str, favicon: &[u8]) {
+        if url.trim().is_empty() || favicon.is_empty() {
+            return;
+        }
+        if self.bookmarks.set_favicon_for_url(url, favicon) {
+            self.bookmarks.save_to_disk();
+            self.sync_bookmarks_ui();
+        }
+    }
+
+    fn find_folder_id_by_title(nodes: &[crate::bookmarks::BookmarkNode], title: &str) -> Option<String> {
+        for node in nodes {
+            if node.is_folder() && node.title.eq_ignore_ascii_case(title) {
+                return Some(node.id.clone());
+            }
+            if let Some(found) = Self::find_folder_id_by_title(&node.children, title) {
+                return Some(found);
+            }
+        }
+        None
+    }
+
+    fn resolve_folder_field(&mut self, folder_field: &str) -> Result<Option<String>, String> {
+        let trimmed = folder_field.trim();
+        if trimmed.is_empty() {
+            return Ok(None);
+        }
+
+        if let Some(found) = Self::find_folder_id_by_title(self.bookmarks.items(), trimmed) {
+            return Ok(Some(found));
+        }
+
+        self.bookmarks.add_folder(trimmed.to_string(), None).map(Some)
+    }
+
+    /// The request shown in the prompt bar: the oldest one from the active tab
+    fn active_permission_request(&self) -> Option<&PendingPermission> {
+        let active_tab_id = self.active_tab_id()?;
+        self.pending_permissions.iter().find(|pending| &pending.tab_id == active_tab_id)
+    }
+
+    fn active_pending_login(&self) -> Option<&SavedLogin> {
+        let active_tab_id = self.active_tab_id()?;
+        self.pending_login.as_ref().filter(|(tab_id, _)| tab_id == active_tab_id).map(|(_, login)| login)
+    }
+
+    /// Persist the user's answer to the prompt bar and let the page know. Permission requests are
+    /// shown before the offer to save a login, so they are answered first.
+    fn answer_permission_prompt(&mut self, granted: bool) {
+        let Some(active_tab_id) = self.active_tab_id().cloned() else {
+            return;
+        };
+        let Some(position) = self.pending_permissions.iter().position(|pending| pending.tab_id == active_tab_id) else {
+            self.answer_login_prompt(granted);
+            return;
+        };
+        let answered = self.pending_permissions.remove(position);
+        self.permissions.set(&answered.origin, answered.kind, granted);
+        self.permissions.save_to_disk();
+        for tab_id in self.tabs_showing_origin(&answered.origin) {
+            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::PermissionsChanged);
+        }
+
+        // Other requests from the same origin for the same permission get the same answer
+        let mut answered_requests = vec![(answered.tab_id, answered.request_id)];
+        self.pending_permissions.retain(|pending| {
+            let same = pending.origin == answered.origin && pending.kind == answered.kind;
+            if same {
+                answered_requests.push((pending.tab_id.clone(), pending.request_id));
+            }
+            !same
+        });
+        for (tab_id, request_id) in answered_requests {
+            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::PermissionDecision { request_id, granted });
+        }
+        self.request_redraw();
+    }
+
+    /// The dialog shown over the active tab: the oldest one it opened
+    fn active_page_dialog(&self) -> Option<&PendingDialog> {
+        let active_tab_id = self.active_tab_id()?;
+        self.pending_dialogs.iter().find(|pending| &pending.tab_id == active_tab_id)
+    }
+
+    /// Answer the active tab's dialog, letting its script carry on. A prompt() that is accepted
+    /// gets the text of the dialog's input field.
+    fn answer_page_dialog(&mut self, accepted: bool) {
+        let Some(active_tab_id) = self.active_tab_id().cloned() else {
+            return;
+        };
+        let Some(position) = self.pending_dialogs.iter().position(|pending| pending.tab_id == active_tab_id) else {
+            return;
+        };
+        let answered = self.pending_dialogs.remove(position);
+        let value = accepted.then(|| match answered.dialog {
+            JsDialog::Prompt { .. } => self.ui().page_dialog_value().unwrap_or_default(),
+            JsDialog::Alert(_) | JsDialog::Confirm(_) => String::new(),
+        });
+        let _ = self.tab_manager.send_to_tab(&active_tab_id, ParentToTabMessage::DialogAnswer { request_id: answered.request_id, value });
+        if answered.offer_mute && self.ui().page_dialog_mute() {
+            // Until it navigates, the page's dialogs go unshown, the ones already waiting too
+            if let Some(tab) = self.tab_manager.get_tab_mut(&active_tab_id) {
+                tab.dialogs_muted = true;
+            }
+            self.dismiss_page_dialogs(&active_tab_id);
+        }
+        self.ui_mut().set_page_dialog(None);
+        self.request_redraw();
+    }
+
+    /// Cancel every dialog `tab_id` has open, so that it can act on what is sent next
+    fn dismiss_page_dialogs(&mut self, tab_id: &str) {
+        let mut dismissed = Vec::new();
+        self.pending_dialogs.retain(|pending| {
+            let matches = pending.tab_id == tab_id;
+            if matches {
+                dismissed.push(pending.request_id);
+            }
+            !matches
+        });
+        for request_id in dismissed {
+            let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::DialogAnswer { request_id, value: None });
+        }
+    }
+
+    /// Save the login offered in the prompt bar, or never offer to save logins for its origin again
+    fn answer_login_prompt(&mut self, save: bool) {
+        if self.active_pending_login().is_none() {
+            return;
+        }
+        let Some((_, login)) = self.pending_login.take() else {
+            return;
+        };
+        let mut passwords = PasswordStore::load_from_disk();
+        if save {
+            passwords.save_login(login);
+            self.ui_mut().show_toast("Password saved");
+        } else {
+            passwords.never_save(&login.origin);
+        }
+        passwords.save_to_disk();
+        self.request_redraw();
+    }
+
+    /// Build the stokes://passwords page a tab is loading. Whatever loaded the URL, removing a login
+    /// and showing the passwords only happen once the user confirms them here.
+    fn passwords_page(&self, url: &str) -> String {
+        let mut store = PasswordStore::load_from_disk();
+        let request = url::Url::parse(url)
+            .ok()
+            .filter(passwords::is_management_url)
+            .map(|url| ManagementRequest::parse(&url))
+            .unwrap_or_default();
+        let confirm = |message: &str| {
+            tinyfiledialogs::message_box_yes_no("Passwords", message, tinyfiledialogs::MessageBoxIcon::Question, tinyfiledialogs::YesNo::No)
+                == tinyfiledialogs::YesNo::Yes
+        };
+
+        let mut changed = false;
+        if let Some((origin, username)) = &request.remove
+            && store.has_login(origin, username)
+        {
+            let login = if username.is_empty() {
+                format!("the saved password for {}", display_origin(origin))
+            } else {
+                format!("the saved password for {username} on {}", display_origin(origin))
+            };
+            if confirm(&format!("Remove {login}?")) {
+                store.remove_login(origin, username);
+                changed = true;
+            }
+        }
+        if let Some(origin) = &request.allow {
+            store.allow_saving(origin);
+            changed = true;
+        }
+        if changed {
+            store.save_to_disk();
+        }
+        let reveal = request.reveal && confirm("Show your saved passwords on the page?");
+        store.management_page(reveal)
+    }
+
+    /// Ask for the credentials a page's HTTP authentication challenge wants and reload its tab with
+    /// them. The origin comes from the tab's committed URL, not from the tab, and the user is always
+    /// asked: a saved login only fills in the username, and its password is used for an empty answer
+    /// on https pages alone.
+    fn answer_auth_challenge(&mut self, tab_id: &str, challenge: AuthChallenge, retry: bool) {
+        let Some(origin) = self.tab_origin(tab_id) else {
+            return;
+        };
+        let secure = origin.starts_with("https://");
+        let mut passwords = PasswordStore::load_from_disk();
+        let saved = passwords.logins_for(&origin).into_iter().next();
+
+        let site = display_origin(&origin).to_string();
+        let mut message = if challenge.realm.is_empty() {
+            format!("{site} requires a username and password.")
+        } else {
+            format!("{site} requires a username and password for \"{}\".", challenge.realm)
+        };
+        if retry {
+            message.insert_str(0, "The username or password was not accepted.\n");
+        }
+        if challenge.scheme == AuthScheme::Basic && !secure {
+            message.push_str("\nThe password will be sent unencrypted.");
+        }
+        let saved_username = saved.as_ref().map(|login| login.username.clone()).unwrap_or_default();
+        let Some(username) = Self::prompt_input("Sign In", &message, &saved_username) else {
+            return;
+        };
+        let saved_password = saved
+            .filter(|login| secure && !retry && login.username == username)
+            .map(|login| login.password);
+        let password_message = if saved_password.is_some() {
+            format!("Password for {username} on {site} (leave empty to use the saved one):")
+        } else {
+            format!("Password for {username} on {site}:")
+        };
+        let Some(password) = tinyfiledialogs::password_box("Sign In", &password_message) else {
+            return;
+        };
+        let (password, typed) = match saved_password {
+            Some(saved_password) if password.is_empty() => (saved_password, false),
+            _ => (password, true),
+        };
+
+        if typed {
+            let remember = tinyfiledialogs::message_box_yes_no(
+                "Sign In",
+                &format!("Remember this password for {site}?"),
+                tinyfiledialogs::MessageBoxIcon::Question,
+                tinyfiledialogs::YesNo::No,
+            );
+            if remember == tinyfiledialogs::YesNo::Yes {
+                passwords.save_login(SavedLogin { origin: origin.clone(), username: username.clone(), password: password.clone() });
+                passwords.save_to_disk();
+            }
+        }
+        let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::HttpAuthCredentials { origin, username, password });
+    }
+
+    /// Hand a content script's `runtime.sendMessage` to its extension's background page, starting
+    /// the page if it isn't running yet
+    fn send_extension_message(&mut self, tab_id: &str, extension_id: &str, request_id: u64, message: String) {
+        let Some(host_id) = self.extension_host(extension_id) else {
+            let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::ExtensionResponse {
+                request_id,
+                response: Err(NO_RECEIVING_END.to_string()),
+            });
+            return;
+        };
+        let sender = match self.tab_manager.get_tab(tab_id) {
+            Some(tab) => serde_json::json!({
+                "id": extension_id,
+                "url": tab.url,
+                "tab": {
+                    "id": tab_id.trim_start_matches("tab").parse::<u64>().unwrap_or_default(),
+                    "url": tab.url,
+                    "title": tab.title,
+                },
+            }),
+            None => serde_json::json!({ "id": extension_id }),
+        };
+
+        let host_request_id = self.next_extension_request_id;
+        self.next_extension_request_id += 1;
+        self.extension_requests.insert(host_request_id, PendingExtensionRequest {
+            host_id: host_id.clone(),
+            tab_id: tab_id.to_string(),
+            request_id,
+        });
+        let _ = self.tab_manager.send_to_tab(&host_id, ParentToTabMessage::ExtensionMessage {
+            request_id: host_request_id,
+            message,
+            sender: sender.to_string(),
+        });
+    }
+
+    /// The tab running `extension_id`'s background page, started on first use. `None` when the
+    /// extension is off or has no background scripts.
+    fn extension_host(&mut self, extension_id: &str) -> Option<String> {
+        let runnable = ExtensionStore::load_from_disk().web_extension(extension_id).is_some_and(WebExtension::has_background);
+        if let Some(host_id) = self.extension_hosts.get(extension_id).cloned() {
+            // Keep a running page until the extension is turned off or its process dies
+            if runnable && self.tab_manager.get_tab(&host_id).is_some_and(|tab| !tab.is_discarded()) {
+                return Some(host_id);
+            }
+            self.stop_extension_host(&host_id);
+        }
+        if !runnable {
+            return None;
+        }
+
+        let host_id = match self.tab_manager.create_extension_host() {
+            Ok(host_id) => host_id,
+            Err(e) => {
+                tracing::error!("Failed to start the background page of extension {}: {}", extension_id, e);
+                return None;
+            }
+        };
+        self.send_initial_config(&host_id);
+        let _ = self.tab_manager.set_tab_visibility(&host_id, false);
+        let url = extensions::background_page_url(extension_id);
+        let _ = self.tab_manager.send_to_tab(&host_id, ParentToTabMessage::Navigate(url));
+        self.extension_hosts.insert(extension_id.to_string(), host_id.clone());
+        Some(host_id)
+    }
+
+    /// Forget a background page whose process is gone, failing the messages it was given
+    fn stop_extension_host(&mut self, host_id: &str) {
+        self.extension_hosts.retain(|_, id| id != host_id);
+        let failed: Vec<u64> = self.extension_requests.iter()
+            .filter(|(_, pending)| pending.host_id == host_id)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in failed {
+            if let Some(pending) = self.extension_requests.remove(&id) {
+                let _ = self.tab_manager.send_to_tab(&pending.tab_id, ParentToTabMessage::ExtensionResponse {
+                    request_id: pending.request_id,
+                    response: Err(NO_RECEIVING_END.to_string()),
+                });
+            }
+        }
+        let _ = self.tab_manager.close_tab(host_id);
+    }
+
+    /// Background pages have no window to show anything in: pass their answers back to the
+    /// content scripts that asked, and turn down their dialogs and permission requests
+    fn process_extension_host_message(&mut self, host_id: &str, message: TabToParentMessage) {
+        match message {
+            TabToParentMessage::ShellProvider(ShellProviderMessage::ExtensionResponse { request_id, response }) => {
+                let Some(pending) = self.extension_requests.remove(&request_id) else {
+                    return;
+                };
+                let _ = self.tab_manager.send_to_tab(&pending.tab_id, ParentToTabMessage::ExtensionResponse {
+                    request_id: pending.request_id,
+                    response,
+                });
+            }
+            TabToParentMessage::ShellProvider(ShellProviderMessage::ExtensionMessage { request_id, .. }) => {
+                // A background page's own messages have no other receiver
+                let _ = self.tab_manager.send_to_tab(host_id, ParentToTabMessage::ExtensionResponse {
+                    request_id,
+                    response: Err(NO_RECEIVING_END.to_string()),
+                });
+            }
+            TabToParentMessage::Dialog { request_id, .. } => {
+                let _ = self.tab_manager.send_to_tab(host_id, ParentToTabMessage::DialogAnswer { request_id, value: None });
+            }
+            TabToParentMessage::PermissionRequest { request_id, .. } => {
+                let _ = self.tab_manager.send_to_tab(host_id, ParentToTabMessage::PermissionDecision { request_id, granted: false });
+            }
+            _ => {}
+        }
+    }
+
+    /// Load `url`, which `tab_id`'s page named as its likely next navigation, in a hidden tab, so
+    /// following the link shows it at once. Replaces the tab's earlier prerender.
+    fn start_prerender(&mut self, tab_id: &str, url: String) {
+        self.cancel_prerender(tab_id);
+        if self.prerenders.len() >= MAX_PRERENDERS {
+            tracing::debug!("Not prerendering {} for tab {}: too many prerenders", url, tab_id);
+            return;
+        }
+        let host_id = match self.tab_manager.create_prerender_host() {
+            Ok(host_id) => host_id,
+            Err(e) => {
+                tracing::warn!("Failed to start a tab to prerender {}: {}", url, e);
+                return;
+            }
+        };
+        self.send_initial_config(&host_id);
+        let _ = self.tab_manager.set_tab_visibility(&host_id, false);
+        let _ = self.tab_manager.send_to_tab(&host_id, ParentToTabMessage::Navigate(url.clone()));
+        self.prerenders.insert(tab_id.to_string(), Prerender { url, host_id });
+    }
+
+    /// Drop the page prerendered for `tab_id`, if there is one
+    fn cancel_prerender(&mut self, tab_id: &str) {
+        if let Some(prerender) = self.prerenders.remove(tab_id) {
+            let _ = self.tab_manager.close_tab(&prerender.host_id);
+        }
+    }
+
+    /// Show `url` in `tab_id` by swapping in the tab prerendering it, or load it the usual way
+    /// when there is none
+    fn activate_prerender(&mut self, tab_id: &str, url: String) {
+        let swapped = match self.prerenders.remove(tab_id) {
+            Some(prerender) if prerender.url == url => {
+                let result = self.tab_manager.swap_in_prerender(tab_id, &prerender.host_id);
+                if let Err(e) = &result {
+                    tracing::warn!("Failed to swap in the prerender of {}: {}", url, e);
+                    let _ = self.tab_manager.close_tab(&prerender.host_id);
+                }
+                result.is_ok()
+            }
+            Some(prerender) => {
+                let _ = self.tab_manager.close_tab(&prerender.host_id);
+                false
+            }
+            None => false,
+        };
+        if !swapped {
+            let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::Navigate(url));
+            return;
+        }
+
+        // The prerendered page's own navigation messages went unheard while it was hidden
+        self.pending_permissions.retain(|pending| pending.tab_id != tab_id);
+        if self.pointer_lock_tab.as_deref() == Some(tab_id) {
+            self.release_pointer_lock(false);
+        }
+        let Some(tab) = self.tab_manager.get_tab(tab_id) else {
+            return;
+        };
+        let (url, title, favicon, is_loading, security) =
+            (tab.url.clone(), tab.title.clone(), tab.favicon.clone(), tab.is_loading, tab.security.clone());
+        if !is_loading {
+            self.record_visited_link(&url);
+        }
+        let ui = self.ui_mut();
+        ui.update_tab_title(tab_id, &title);
+        ui.update_tab_favicon(tab_id, favicon.as_deref());
+        ui.update_tab_loading(tab_id, is_loading);
+        if Some(tab_id) == self.active_tab_id().map(String::as_str) {
+            self.ui_mut().update_address_bar(&url);
+            self.ui_mut().set_security_state(security);
+            self.env.as_ref().unwrap().window.set_title(&format!("{} - Stokes Browser", title));
+            self.update_bookmark_button_state();
+            self.update_javascript_indicator();
+            self.update_popup_indicator();
+            self.sync_find_bar();
+            self.update_install_button();
+        }
+        self.request_redraw();
+    }
+
+    /// A prerendering page has no window until it is swapped in: turn down its dialogs and
+    /// permission requests, and leave the rest of its state for the tab manager to keep
+    fn process_prerender_host_message(&mut self, host_id: &str, message: TabToParentMessage) {
+        match message {
+            TabToParentMessage::Dialog { request_id, .. } => {
+                let _ = self.tab_manager.send_to_tab(host_id, ParentToTabMessage::DialogAnswer { request_id, value: None });
+            }
+            TabToParentMessage::PermissionRequest { request_id, .. } => {
+                let _ = self.tab_manager.send_to_tab(host_id, ParentToTabMessage::PermissionDecision { request_id, granted: false });
+            }
+            TabToParentMessage::ShellProvider(ShellProviderMessage::ExtensionMessage { request_id, .. }) => {
+                let _ = self.tab_manager.send_to_tab(host_id, ParentToTabMessage::ExtensionResponse {
+                    request_id,
+                    response: Err(NO_RECEIVING_END.to_string()),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Look up the position for a page that has geolocation permission. Providers can block for
+    /// seconds (GeoClue waits for a fix), so the lookup runs on its own thread.
+    fn locate_for_tab(&self, tab_id: &str, request_id: u64, origin: &str) {
+        let Some(sender) = self.tab_manager.tab_sender(tab_id) else {
+            return;
+        };
+        if self.permissions.state(origin, PermissionKind::Geolocation) != PermissionState::Granted {
+            let _ = sender.send(ParentToTabMessage::GeolocationPosition {
+                request_id,
+                result: Err(GeolocationError::PermissionDenied),
+            });
+            return;
+        }
+        let provider = Arc::clone(&self.location_provider);
+        std::thread::spawn(move || {
+            let result = provider.current_position();
+            let _ = sender.send(ParentToTabMessage::GeolocationPosition { request_id, result });
+        });
+    }
+
+    /// Origin of the page in the active tab, if it has one worth remembering settings for
+    fn active_tab_origin(&self) -> Option<String> {
+        self.tab_origin(self.active_tab_id()?)
+    }
+
+    /// Origin of the page a tab last committed to, as the browser saw it
+    fn tab_origin(&self, tab_id: &str) -> Option<String> {
+        let tab = self.tab_manager.get_tab(tab_id)?;
+        let origin = url::Url::parse(&tab.url).ok()?.origin();
+        origin.is_tuple().then(|| origin.ascii_serialization())
+    }
+
+    /// Step the active site through automatic, light and dark colors and restyle its open tabs
+    fn cycle_site_color_scheme(&mut self) {
+        let Some(origin) = self.active_tab_origin() else {
+            return;
+        };
+        let mut settings = self.site_settings.get(&origin);
+        settings.color_scheme = settings.color_scheme.next();
+        self.site_settings.set(&origin, settings.clone());
+        self.site_settings.save_to_disk();
+        self.ui_mut().set_site_color_scheme(settings.color_scheme);
+        self.notify_site_settings_changed(&origin);
+    }
+
+    /// Turn forced dark colors on or off for the active site and repaint its open tabs
+    fn toggle_site_force_dark(&mut self) {
+        let Some(origin) = self.active_tab_origin() else {
+            return;
+        };
+        let mut settings = self.site_settings.get(&origin);
+        settings.force_dark = !settings.force_dark;
+        self.site_settings.set(&origin, settings.clone());
+        self.site_settings.save_to_disk();
+        self.ui_mut().set_site_force_dark(settings.force_dark);
+        self.notify_site_settings_changed(&origin);
+    }
+
+    /// Step the active site's autoplay through limiting sound, allowing and blocking it. Open tabs
+    /// apply it to the next play().
+    fn cycle_site_autoplay(&mut self) {
+        let Some(origin) = self.active_tab_origin() else {
+            return;
+        };
+        let mut settings = self.site_settings.get(&origin);
+        settings.autoplay = settings.autoplay.next();
+        self.site_settings.set(&origin, settings.clone());
+        self.site_settings.save_to_disk();
+        self.ui_mut().set_site_autoplay(settings.autoplay);
+        self.notify_site_settings_changed(&origin);
+    }
+
+    /// Allow or block JavaScript for the active site and reload its open tabs, since scripts only
+    /// start or stop with a new document
+    fn toggle_site_javascript(&mut self) {
+        let Some(origin) = self.active_tab_origin() else {
+            return;
+        };
+        let allowed = !self.site_settings.javascript_allowed(&origin);
+        self.site_settings.set_javascript_allowed(&origin, allowed);
+        self.site_settings.save_to_disk();
+        self.ui_mut().set_site_javascript(allowed);
+        self.ui_mut().show_toast(if allowed { "JavaScript allowed on this site" } else { "JavaScript blocked on this site" });
+        for tab_id in self.tabs_showing_origin(&origin) {
+            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::Reload);
+        }
+    }
+
+    /// Let the active site open popups without a user gesture, and open the ones its page was
+    /// stopped from opening
+    fn allow_site_popups(&mut self) {
+        let Some(origin) = self.active_tab_origin() else {
+            return;
+        };
+        let mut settings = self.site_settings.get(&origin);
+        settings.popups = true;
+        self.site_settings.set(&origin, settings);
+        self.site_settings.save_to_disk();
+        self.notify_site_settings_changed(&origin);
+
+        let blocked = self
+            .active_tab_id()
+            .cloned()
+            .and_then(|tab_id| self.tab_manager.get_tab_mut(&tab_id))
+            .map(|tab| std::mem::take(&mut tab.blocked_popups))
+            .unwrap_or_default();
+        let tab_index = self.active_tab_index;
+        for url in &blocked {
+            self.add_tab();
+            self.navigate_to_url(url);
+        }
+        if !blocked.is_empty() {
+            self.switch_to_tab(tab_index);
+        }
+        self.update_popup_indicator();
+        self.ui_mut().show_toast("Popups allowed on this site");
+    }
+
+    /// Turn JavaScript on or off for every site without an exception and reload all tabs
+    fn toggle_global_javascript(&mut self) {
+        let enabled = !self.site_settings.javascript_enabled();
+        self.site_settings.set_javascript_enabled(enabled);
+        self.site_settings.save_to_disk();
+        self.ui_mut().set_javascript_enabled(enabled);
+        self.update_javascript_indicator();
+        for tab_id in self.tab_manager.tab_ids() {
+            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::Reload);
+        }
+    }
+
+    /// Switch the active site between loading images and showing placeholders, then reload its tabs
+    fn toggle_site_images(&mut self) {
+        let Some(origin) = self.active_tab_origin() else {
+            return;
+        };
+        let allowed = !self.site_settings.images_allowed(&origin);
+        self.site_settings.set_images_allowed(&origin, allowed);
+        self.site_settings.save_to_disk();
+        let global = self.site_settings.images_enabled();
+        self.ui_mut().set_image_settings(allowed, global);
+        for tab_id in self.tabs_showing_origin(&origin) {
+            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::Reload);
+        }
+    }
+
+    /// Turn image loading on or off for every site without an exception and reload all tabs
+    fn toggle_global_images(&mut self) {
+        let enabled = !self.site_settings.images_enabled();
+        self.site_settings.set_images_enabled(enabled);
+        self.site_settings.save_to_disk();
+        let site = match self.active_tab_origin() {
+            Some(origin) => self.site_settings.images_allowed(&origin),
+            None => enabled,
+        };
+        self.ui_mut().set_image_settings(site, enabled);
+        for tab_id in self.tab_manager.tab_ids() {
+            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::Reload);
+        }
+    }
+
+    /// Tell the active tab when the display next refreshes, so it paints its next frame in time
+    /// for that composite
+    fn send_frame_timing(&mut self) {
+        let Some(tab_id) = self.active_tab_id().cloned() else {
+            return;
+        };
+        let max_latency_frames = self.ui().frame_timing_settings().max_latency_frames;
+        let timing = self.present_clock.timing(Instant::now(), max_latency_frames);
+        let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::FrameTiming(timing));
+    }
+
+    /// Go by the refresh rate of the monitor the active window is on, when the platform reports it
+    fn note_refresh_interval(&mut self) {
+        if let Some(env) = &self.env {
+            self.present_clock.set_refresh_interval(crate::window::refresh_interval(env.window.as_ref()));
+        }
+    }
+
+    /// Save the chrome's theme and density and apply them to every window. The chrome height
+    /// depends on the density, so the tabs are resized to fit the page viewports that are left.
+    fn set_theme_settings(&mut self, settings: ThemeSettings) {
+        settings.save_to_disk();
+        self.ui_mut().set_theme_settings(settings.clone());
+        self.update_page_viewport();
+
+        let (width, height) = self.page_viewport.as_ref().unwrap().window_size;
+        let mut resized: Vec<(String, u32, u32)> = self.tab_order.iter().map(|tab_id| (tab_id.clone(), width, height)).collect();
+        for state in self.background_windows.values_mut() {
+            state.ui.set_theme_settings(settings.clone());
+            let chrome_physical = state.ui.chrome_height().round() as u32;
+            let (width, height) = (state.viewport.window_size.0, state.viewport.window_size.1.saturating_sub(chrome_physical));
+            state.page_viewport.window_size = (width, height);
+            resized.extend(state.tab_order.iter().map(|tab_id| (tab_id.clone(), width, height)));
+        }
+        for (tab_id, width, height) in resized {
+            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::Resize {
+                width: width as f32,
+                height: height as f32,
+            });
+        }
+        self.request_redraw();
+    }
+
+    /// Show in the address bar whether the active tab's site may run JavaScript
+    fn update_javascript_indicator(&mut self) {
+        let allowed = match self.active_tab_origin() {
+            Some(origin) => self.site_settings.javascript_allowed(&origin),
+            None => self.site_settings.javascript_enabled(),
+        };
+        self.ui_mut().set_site_javascript(allowed);
+    }
+
+    /// Open the find bar, or focus it again, and search the active tab for the text it holds
+    fn open_find_bar(&mut self) {
+        let Some(tab_id) = self.active_tab_id().cloned() else {
+            return;
+        };
+        self.ui_mut().show_find_bar();
+        if self.find_tab.as_ref() != Some(&tab_id) {
+            if let Some(previous) = self.find_tab.replace(tab_id) {
+                let _ = self.tab_manager.send_to_tab(&previous, ParentToTabMessage::StopFinding);
+            }
+            self.find_query.clear();
+        }
+        self.update_find_query();
+        self.request_redraw();
+    }
+
+    /// Search again when the find bar's text changed since the last search
+    fn update_find_query(&mut self) {
+        let Some(query) = self.ui().find_query() else {
+            return;
+        };
+        if query != self.find_query {
+            self.find_query = query;
+            self.send_find(true);
+        }
+    }
+
+    /// Step to the next or previous match, opening the find bar if it is closed
+    fn find_in_page(&mut self, forward: bool) {
+        if !self.ui().is_find_bar_shown() {
+            self.open_find_bar();
+            return;
+        }
+        self.find_query = self.ui().find_query().unwrap_or_default();
+        self.send_find(forward);
+    }
+
+    /// Send the find bar's query to the tab it searches; an empty query ends the search
+    fn send_find(&mut self, forward: bool) {
+        let Some(tab_id) = self.find_tab.clone() else {
+            return;
+        };
+        let message = if self.find_query.is_empty() {
+            self.ui_mut().set_find_result(None);
+            ParentToTabMessage::StopFinding
+        } else {
+            ParentToTabMessage::Find {
+                query: self.find_query.clone(),
+                forward,
+                highlight_all: self.ui().find_highlight_all(),
+            }
+        };
+        let _ = self.tab_manager.send_to_tab(&tab_id, message);
+    }
+
+    /// Switch between highlighting every match and only the current one
+    fn toggle_find_highlight_all(&mut self) {
+        let highlight_all = self.ui_mut().toggle_find_highlight_all();
+        if let Some(tab_id) = &self.find_tab {
+            let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::SetFindHighlightAll(highlight_all));
+        }
+        self.request_redraw();
+    }
+
+    /// Close the find bar and clear the highlights from the page it searched
+    fn close_find_bar(&mut self) {
+        self.ui_mut().hide_find_bar();
+        if let Some(tab_id) = self.find_tab.take() {
+            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::StopFinding);
+        }
+        self.find_query.clear();
+        self.request_redraw();
+    }
+
+    /// Close the find bar once the tab it searches is no longer the active one
+    fn sync_find_bar(&mut self) {
+        if self.find_tab.is_some() && self.find_tab.as_ref() != self.active_tab_id() {
+            self.close_find_bar();
+        }
+    }
+
+    /// Count the active tab's blocked popups on the address bar's badge
+    fn update_popup_indicator(&mut self) {
+        let count = self
+            .active_tab_id()
+            .and_then(|tab_id| self.tab_manager.get_tab(tab_id))
+            .map_or(0, |tab| tab.blocked_popups.len());
+        self.ui_mut().set_popups_blocked(count);
+    }
+
+    /// Offer the active tab's web app in the settings panel when it can be installed
+    fn update_install_button(&mut self) {
+        let name = self
+            .active_tab_id()
+            .and_then(|tab_id| self.tab_manager.get_tab(tab_id))
+            .and_then(|tab| tab.web_app.as_ref())
+            .filter(|manifest| manifest.is_installable() && !web_apps::is_installed(manifest))
+            .map(|manifest| manifest.short_name.clone());
+        self.ui_mut().set_installable_app(name);
+    }
+
+    /// Ask whether to install the web app of `tab_id`'s page and create its desktop shortcut. The
+    /// page is told the answer, for its beforeinstallprompt event and appinstalled.
+    fn install_web_app(&mut self, tab_id: &str) {
+        use rfd::{MessageButtons, MessageDialog, MessageDialogResult};
+
+        let Some(tab) = self.tab_manager.get_tab(tab_id) else {
+            return;
+        };
+        let Some(manifest) = tab.web_app.clone().filter(|manifest| manifest.is_installable()) else {
+            self.ui_mut().show_toast("This page can't be installed as an app");
+            let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::AppInstallResult { accepted: false });
+            return;
+        };
+        let icon = tab.web_app_icon.clone();
+
+        let accepted = MessageDialog::new()
+            .set_title("Install app")
+            .set_description(format!(
+                "Install {}?\n\nA shortcut on your desktop will open {} in its own window.",
+                manifest.name, manifest.start_url,
+            ))
+            .set_buttons(MessageButtons::OkCancelCustom("Install".to_string(), "Cancel".to_string()))
             .show();
+        let accepted = match accepted {
+            MessageDialogResult::Custom(choice) => choice == "Install",
+            MessageDialogResult::Ok => true,
+            _ => false,
+        };
+
+        let installed = accepted && match web_apps::install(&manifest, icon.as_deref()) {
+            Ok(shortcut) => {
+                tracing::info!("Installed web app {} with shortcut {}", manifest.name, shortcut.display());
+                self.ui_mut().show_toast(format!("Installed {}", manifest.name));
+                true
+            }
+            Err(err) => {
+                tracing::error!("Failed to install web app {}: {}", manifest.name, err);
+                self.show_alert(&format!("Failed to install {}: {err}", manifest.name));
+                false
+            }
+        };
+        let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::AppInstallResult { accepted: installed });
+        self.update_install_button();
     }
 
-    fn request_redraw(&self) {
-        self.env.as_ref().unwrap().window.request_redraw();
+    fn tabs_showing_origin(&self, origin: &str) -> Vec<String> {
+        self.tab_manager.tab_ids().into_iter()
+            .filter(|tab_id| {
+                self.tab_manager.get_tab(tab_id)
+                    .and_then(|tab| url::Url::parse(&tab.url).ok())
+                    .is_some_and(|url| url.origin().ascii_serialization() == origin)
+            })
+            .collect()
     }
 
-    fn sync_bookmarks_ui(&mut self) {
-        let snapshot = self.bookmarks.items().to_vec();
-        self.ui_mut().set_bookmarks(snapshot);
-        self.update_bookmark_button_state();
+    /// Tell every tab showing `origin` to re-read its site settings
+    fn notify_site_settings_changed(&mut self, origin: &str) {
+        for tab_id in self.tabs_showing_origin(origin) {
+            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::SiteSettingsChanged);
+        }
     }
 
-    fn prompt_input(title: &str, message: &str, default: &str) -> Option<String> {
-        tinyfiledialogs::input_box(title, message, default)
-            .map(|value| value.trim().to_string())
-            .filter(|value| !value.is_empty())
+    /// Let the user pin a location for sites, or clear the pin to go back to the OS location service
+    fn set_location_override(&mut self) {
+        let current = self.location_settings.manual_override
+            .map(|(latitude, longitude)| format!("{latitude}, {longitude}"))
+            .unwrap_or_default();
+        let Some(input) = Self::prompt_input(
+            "Location",
+            "Latitude, longitude (leave blank to use the system location):",
+            &current,
+        ) else {
+            return;
+        };
+
+        let input = input.trim();
+        let manual_override = if input.is_empty() {
+            None
+        } else if let Some(coordinates) = parse_coordinates(input) {
+            Some(coordinates)
+        } else {
+            self.show_alert("Enter a latitude and longitude such as 51.5074, -0.1278.");
+            return;
+        };
+
+        self.location_settings.manual_override = manual_override;
+        self.location_settings.save_to_disk();
+        self.location_provider = Arc::from(self.location_settings.provider());
     }
 
-    fn selected_folder_target(&self) -> Option<String> {
-        self.ui()
-            .selected_bookmark_id()
-            .and_then(|id| self.bookmarks.get(id))
-            .and_then(|bookmark| if bookmark.is_folder() { Some(bookmark.id.clone()) } else { None })
+    /// Let the user list the languages sites should be asked for, or clear the list to go back to
+    /// the desktop's
+    fn set_languages_override(&mut self) {
+        let current = self.language_settings.manual_override.as_deref().unwrap_or_default().join(", ");
+        let Some(input) = Self::prompt_input(
+            "Languages",
+            "Languages in order of preference, such as en-GB, fr (leave blank to use the system languages):",
+            &current,
+        ) else {
+            return;
+        };
+
+        let Some(list) = parse_language_list(&input) else {
+            self.show_alert("Enter language tags separated by commas, such as en-GB, fr.");
+            return;
+        };
+        self.language_settings.manual_override = (!list.is_empty()).then_some(list);
+        self.language_settings.save_to_disk();
+        languages::set(self.language_settings.languages());
+
+        let tab_ids = self.tab_order.iter()
+            .chain(self.background_windows.values().flat_map(|state| state.tab_order.iter()))
+            .cloned()
+            .collect::<Vec<_>>();
+        for tab_id in tab_ids {
+            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::SetLanguages(languages::current()));
+        }
     }
 
-    fn update_bookmark_button_state(&mut self) {
-        let url = self
-            .active_tab_id()
-            .and_then(|id| self.tab_manager.get_tab(id))
-            .map(|tab| tab.url.clone())
-            .unwrap_or_default();
-        let is_bookmarked = !url.trim().is_empty() && self.bookmarks.find_by_url(&url).is_some();
-        self.ui_mut().set_current_page_bookmarked(is_bookmarked);
+    /// Let the user pick network conditions for the active tab, to try a page offline or on a
+    /// slow connection
+    fn emulate_network_conditions(&mut self) {
+        let Some(tab_id) = self.active_tab_id().cloned() else {
+            return;
+        };
+        let current = self.tab_manager.get_tab(&tab_id).map(|tab| tab.network_conditions).unwrap_or_default();
+        let Some(input) = Self::prompt_input(
+            "Network conditions",
+            "Offline, Slow 3G, Fast 3G, or a DevTools Network.emulateNetworkConditions command as JSON (leave blank for the real network):",
+            &current.to_input(),
+        ) else {
+            return;
+        };
+
+        let conditions = match NetworkConditions::parse(&input) {
+            Ok(conditions) => conditions,
+            Err(e) => {
+                self.show_alert(&format!("Those network conditions couldn't be used: {e}"));
+                return;
+            }
+        };
+        if let Err(e) = self.tab_manager.set_tab_network_conditions(&tab_id, conditions) {
+            tracing::error!("Failed to set network conditions for tab {}: {}", tab_id, e);
+            return;
+        }
+        self.ui_mut().show_toast(format!("Network conditions for this tab: {}", conditions.label()));
     }
 
-    fn persist_bookmark_favicon_for_url(&mut self, url: &str, favicon: &[u8]) {
-        if url.trim().is_empty() || favicon.is_empty() {
+    /// Add or edit a saved autofill profile, one field per dialog, or remove an existing one
+    /// Start another browser process for a profile the user picks. Its data is kept apart from this
+    /// window's, so the same profile isn't opened twice.
+    fn open_profile_window(&self) {
+        let current = profile::current_name();
+        let profiles = profile::list();
+        let message = format!(
+            "You are using \"{current}\".\nProfiles: {}\nEnter one of them to open it in a new window, or a new name to create a profile:",
+            profiles.join(", ")
+        );
+        let Some(name) = Self::prompt_input("Profiles", &message, "") else {
+            return;
+        };
+        let name = profiles.iter().find(|profile| profile.eq_ignore_ascii_case(&name)).cloned().unwrap_or(name);
+        if name.eq_ignore_ascii_case(&current) {
             return;
         }
-        if self.bookmarks.set_favicon_for_url(url, favicon) {
-            self.bookmarks.save_to_disk();
-            self.sync_bookmarks_ui();
+        if !profile::is_valid_name(&name) {
+            self.show_alert(&format!("\"{name}\" can't be used as a profile name. Use letters, digits, spaces, '-', '_' or '.'."));
+            return;
+        }
+
+        let spawned = std::env::current_exe().and_then(|exe| std::process::Command::new(exe).arg("--profile").arg(&name).spawn());
+        if let Err(err) = spawned {
+            tracing::error!("Failed to open profile {}: {}", name, err);
         }
     }
 
-    fn find_folder_id_by_title(nodes: &[crate::bookmarks::BookmarkNode], title: &str) -> Option<String> {
-        for node in nodes {
-            if node.is_folder() && node.title.eq_ignore_ascii_case(title) {
-                return Some(node.id.clone());
-            }
-            if let Some(found) = Self::find_folder_id_by_title(&node.children, title) {
-                return Some(found);
+    fn manage_autofill_profiles(&mut self) {
+        let existing: Vec<&str> = self.autofill.profiles().iter().map(|profile| profile.label.as_str()).collect();
+        let message = if existing.is_empty() {
+            "Name for a new profile (e.g. Home):".to_string()
+        } else {
+            format!("Saved profiles: {}\nEnter one of them to edit it, or a new name to add a profile:", existing.join(", "))
+        };
+        let Some(label) = Self::prompt_input("Autofill Profiles", &message, "") else {
+            return;
+        };
+
+        let mut profile = match self.autofill.get(&label) {
+            Some(existing) => {
+                let remove = tinyfiledialogs::message_box_yes_no(
+                    "Autofill Profiles",
+                    &format!("Remove the profile \"{label}\"?\nChoose No to edit it."),
+                    tinyfiledialogs::MessageBoxIcon::Question,
+                    tinyfiledialogs::YesNo::No,
+                );
+                if remove == tinyfiledialogs::YesNo::Yes {
+                    self.autofill.remove_profile(&label);
+                    self.autofill.save_to_disk();
+                    return;
+                }
+                existing.clone()
             }
+            None => AutofillProfile { label, ..Default::default() },
+        };
+
+        // Cancelling any dialog leaves the profile as it was
+        let fields: [(&str, &mut String); 9] = [
+            ("Full name:", &mut profile.full_name),
+            ("Email:", &mut profile.email),
+            ("Phone:", &mut profile.phone),
+            ("Organization:", &mut profile.organization),
+            ("Street address (separate lines with \";\"):", &mut profile.street_address),
+            ("City:", &mut profile.city),
+            ("State, province or region:", &mut profile.region),
+            ("Postal code:", &mut profile.postal_code),
+            ("Country:", &mut profile.country),
+        ];
+        for (message, value) in fields {
+            let default = value.replace('\n', "; ");
+            let Some(input) = tinyfiledialogs::input_box("Autofill Profiles", message, &default) else {
+                return;
+            };
+            *value = input.split(';').map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<_>>().join("\n");
         }
-        None
+        self.autofill.save_profile(profile);
+        self.autofill.save_to_disk();
+        self.ui_mut().show_toast("Autofill profile saved");
     }
 
-    fn resolve_folder_field(&mut self, folder_field: &str) -> Result<Option<String>, String> {
-        let trimmed = folder_field.trim();
-        if trimmed.is_empty() {
-            return Ok(None);
-        }
+    /// Ask where to save the active page and whether to include its resources, then let the tab write it
+    fn save_current_page(&mut self) {
+        use rfd::{MessageButtons, MessageDialog, MessageDialogResult};
 
-        if let Some(found) = Self::find_folder_id_by_title(self.bookmarks.items(), trimmed) {
-            return Ok(Some(found));
+        let Some(tab_id) = self.active_tab_id().cloned() else {
+            return;
+        };
+        let Some(tab) = self.tab_manager.get_tab(&tab_id) else {
+            return;
+        };
+        if tab.url.trim().is_empty() {
+            self.show_alert("Open a page before saving it.");
+            return;
         }
 
-        self.bookmarks.add_folder(trimmed.to_string(), None).map(Some)
+        let file_stem: String = tab
+            .title
+            .chars()
+            .map(|c| if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.') { c } else { '_' })
+            .collect();
+        let file_stem = file_stem.trim();
+        let file_name = format!("{}.html", if file_stem.is_empty() { "page" } else { file_stem });
+
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Save Page As")
+            .set_file_name(&file_name)
+            .add_filter("Web page", &["html", "htm"])
+            .save_file()
+        else {
+            return;
+        };
+
+        let complete = match MessageDialog::new()
+            .set_title("Save Page As")
+            .set_description("Save only the HTML, or the complete page with its images and stylesheets?")
+            .set_buttons(MessageButtons::YesNoCancelCustom(
+                "Complete".to_string(),
+                "HTML only".to_string(),
+                "Cancel".to_string(),
+            ))
+            .show()
+        {
+            MessageDialogResult::Custom(choice) if choice == "Complete" => true,
+            MessageDialogResult::Custom(choice) if choice == "HTML only" => false,
+            MessageDialogResult::Yes => true,
+            MessageDialogResult::No => false,
+            _ => return,
+        };
+
+        let message = ParentToTabMessage::SavePage {
+            path: path.to_string_lossy().into_owned(),
+            complete,
+        };
+        if let Err(err) = self.tab_manager.send_to_tab(&tab_id, message) {
+            self.show_alert(&format!("Failed to save page: {err}"));
+        }
     }
 
     fn add_current_page_bookmark(&mut self, parent_id: Option<String>) {
@@ -897,75 +3412,125 @@ impl BrowserApp {
 
 impl ApplicationHandler for BrowserApp {
     fn can_create_surfaces(&mut self, event_loop: &dyn ActiveEventLoop) {
-        self.env = Some(crate::window::create_window(event_loop));
-
-        let env = self.env.as_ref().unwrap();
-        let viewport = Viewport {
-            color_scheme: Default::default(),
-            window_size: env.window.surface_size().into(),
-            hidpi_scale: env.window.scale_factor() as f32,
-            zoom: 1.0,
-        };
-        let page_viewport = Viewport {
-            color_scheme: Default::default(),
-            window_size: (
-                env.window.surface_size().width,
-                // Subtract the chrome height converted into physical pixels (logical chrome * scale)
-                (env.window.surface_size().height as f32 - (BrowserUI::CHROME_HEIGHT as f32 * env.window.scale_factor() as f32))
-                    .max(0.0)
-                    .round() as u32,
-            ),
-            hidpi_scale: env.window.scale_factor() as f32,
-            zoom: 1.0,
-        };
-
-        // Initialize UI
-        let mut ui = BrowserUI::new(&env.gr_context, &viewport);
-        ui.initialize_renderer();
-        self.ui = Some(ui);
-        self.viewport = Some(viewport);
-        self.page_viewport = Some(page_viewport);
-        self.sync_bookmarks_ui();
-
-        // Create initial tab, navigating to the startup URL if one was provided
-        if let Some(url) = self.startup_url.clone() {
-            self.add_tab_with_url(Some(&url));
-        } else {
-            self.add_tab_with_url(Some(DEFAULT_HOMEPAGE));
+        if self.window_id.is_some() {
+            return;
         }
-        self.startup_url = None;
+
+        // Create the initial window, navigating to the startup URL if one was provided
+        let url = self.startup_url.take().unwrap_or_else(|| DEFAULT_HOMEPAGE.to_string());
+        self.open_window(event_loop, Some(&url));
     }
 
     fn resumed(&mut self, _event_loop: &dyn ActiveEventLoop) {
         self.env.as_ref().unwrap().window.request_redraw();
     }
 
-    fn about_to_wait(&mut self, _event_loop: &dyn ActiveEventLoop) {
+    fn about_to_wait(&mut self, event_loop: &dyn ActiveEventLoop) {
         // Drain all pending tab messages every loop iteration so the main
         // process never falls behind the tab processes.  Doing this here
         // (rather than only inside render()) means we don't have to wait for
         // a GPU frame to finish before we notice a new FrameRendered / title
         // change / navigation event from a tab.
+        self.flush_pending_input();
         self.process_tab_messages();
-        if let Some(env) = self.env.as_ref() {
-            env.window.request_redraw();
+        self.apply_memory_policy();
+        if let Some(gamepads) = self.gamepads.poll() {
+            if let Some(tab_id) = self.active_tab_id().cloned() {
+                let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::Gamepads(gamepads));
+            }
+        }
+        for url in std::mem::take(&mut self.app_links) {
+            self.open_window(event_loop, Some(&url));
         }
+
+        // Nothing is drawn unless something changed: input, a tab's new frame, or one of the
+        // timed changes below. Until then the loop sleeps.
+        let now = Instant::now();
+        for (window_id, _) in self.ui_redraw_at.iter().filter(|(_, at)| **at <= now) {
+            match self.background_windows.get(window_id) {
+                Some(state) => state.env.window.request_redraw(),
+                None if self.window_id == Some(*window_id) => self.request_redraw(),
+                None => {}
+            }
+        }
+        self.ui_redraw_at = self.window_id
+            .zip(self.ui.as_ref().and_then(|ui| ui.next_redraw_at()))
+            .into_iter()
+            .chain(self.background_windows.iter().filter_map(|(window_id, state)| Some((*window_id, state.ui.next_redraw_at()?))))
+            .collect();
+        let gamepad_poll = self.gamepads.poll_interval().map(|interval| now + interval);
+        let ui_redraw_at = self.ui_redraw_at.values().min().copied();
+        let wake_at = [ui_redraw_at, gamepad_poll, Some(self.tab_manager.next_memory_check())]
+            .into_iter()
+            .flatten()
+            .min();
+        event_loop.set_control_flow(wake_at.map_or(ControlFlow::Wait, ControlFlow::WaitUntil));
+    }
+
+    fn device_event(&mut self, _event_loop: &dyn ActiveEventLoop, _device_id: Option<DeviceId>, event: DeviceEvent) {
+        // A locked pointer stays put, so only the device reports how far the mouse moved
+        let DeviceEvent::PointerMotion { delta: (dx, dy) } = event else {
+            return;
+        };
+        let Some(tab_id) = self.pointer_lock_tab.clone() else {
+            return;
+        };
+        let scale = self.viewport.as_ref().unwrap().scale_f64();
+        let position = PhysicalPosition::new(self.pointer_position.0, self.pointer_position.1);
+        let event = UiEvent::PointerMove(BlitzPointerEvent {
+            id: BlitzPointerId::Mouse,
+            is_primary: true,
+            coords: self.pointer_coords(position),
+            button: Default::default(),
+            buttons: self.buttons,
+            mods: winit_modifiers_to_kbt_modifiers(self.modifiers.state()),
+            details: PointerDetails {
+                movement_x: (dx / scale) as f32,
+                movement_y: (dy / scale) as f32,
+                ..PointerDetails::default()
+            },
+        });
+        self.queue_ui_event(&tab_id, event);
     }
 
-    fn window_event(&mut self, event_loop: &dyn ActiveEventLoop, _window_id: WindowId, event: WindowEvent) {
+    fn window_event(&mut self, event_loop: &dyn ActiveEventLoop, window_id: WindowId, event: WindowEvent) {
+        if !self.activate_window(window_id) {
+            return;
+        }
+
         match event {
+            WindowEvent::Focused(false) => {
+                self.release_pointer_lock(true);
+            }
             WindowEvent::CloseRequested => {
-                event_loop.exit();
+                self.close_window(event_loop);
             }
             WindowEvent::SurfaceResized(new_size) => {
+                // Minimizing shrinks the window to nothing on some platforms. Keep the surface and
+                // the tabs' viewports as they were so restoring needs no relayout.
+                let minimized = new_size.width == 0
+                    || new_size.height == 0
+                    || self.env().window.is_minimized().unwrap_or(false);
+                self.set_minimized(minimized);
+                if minimized {
+                    return;
+                }
+
+                self.note_window_placement();
+
+                self.make_gl_current();
                 let env = self.env.as_mut().unwrap();
-                env.surface = create_surface(
+                // Keep drawing to the old surface if the new size cannot be wrapped
+                match create_surface(
                     &env.window,
                     env.fb_info,
                     &mut env.gr_context,
                     env.num_samples,
                     env.stencil_size
-                );
+                ) {
+                    Ok(surface) => env.surface = surface,
+                    Err(e) => tracing::warn!("Failed to resize the window surface: {}", e),
+                }
 
                 let (width, height): (u32, u32) = new_size.into();
                 env.gl_surface.resize(
@@ -986,13 +3551,13 @@ impl ApplicationHandler for BrowserApp {
                         height: height as f32
                     });
                 }
+                self.clamp_visual_viewports();
             }
             WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
                 let scale_factor = scale_factor as f32;
-                let mut viewport = self.viewport.as_mut().unwrap();
-                let old_scale = viewport.hidpi_scale;
+                let viewport = self.viewport.as_mut().unwrap();
                 viewport.hidpi_scale = scale_factor;
-                self.ui.as_mut().unwrap().update_scale(viewport.hidpi_scale, old_scale);
+                self.ui.as_mut().unwrap().update_scale(scale_factor);
 
                 self.update_page_viewport();
 
@@ -1000,15 +3565,48 @@ impl ApplicationHandler for BrowserApp {
                 for tab_id in &self.tab_order {
                     let _ = self.tab_manager.send_to_tab(tab_id, ParentToTabMessage::SetScaleFactor(scale_factor));
                 }
+                // Track the logical size as of the new scale factor
+                self.note_window_placement();
+                // A new scale factor usually means the window moved to another monitor
+                self.note_refresh_interval();
 
                 self.env.as_ref().unwrap().window.request_redraw();
             }
+            WindowEvent::ThemeChanged(theme) => {
+                self.set_media_preferences(MediaPreferences::detect(Some(theme)));
+            }
+            WindowEvent::Moved(_) => {
+                self.note_window_placement();
+                self.note_refresh_interval();
+            }
+            WindowEvent::Occluded(occluded) => {
+                self.occluded = occluded;
+                // Not every platform resizes a minimized window, but they report it covered
+                if let Some(minimized) = self.env().window.is_minimized() {
+                    let size = self.env().window.surface_size();
+                    self.set_minimized(minimized || size.width == 0 || size.height == 0);
+                }
+                if !occluded {
+                    self.env().window.request_redraw();
+                }
+            }
             WindowEvent::RedrawRequested => {
+                // Nothing of a minimized or fully covered window can be seen, so skip compositing
+                if self.minimized || self.occluded {
+                    return;
+                }
                 if let Err(e) = self.render() {
-                    eprintln!("Render error: {}", e);
+                    tracing::error!("Render error: {}", e);
                 }
             }
             WindowEvent::PointerButton { state: ElementState::Pressed, button: ButtonSource::Mouse(MouseButton::Left), primary, position, .. } => {
+                let (menu_x, menu_y) = (self.pointer_position.0 as f32, self.pointer_position.1 as f32);
+                if let Some(action) = self.ui_mut().handle_tab_context_menu_click(menu_x, menu_y) {
+                    self.handle_tab_menu_action(action, event_loop);
+                    self.request_redraw();
+                    return;
+                }
+
                 let ui = self.ui.as_mut().unwrap();
                 // Update hover state before handling click
                 ui.update_mouse_hover(
@@ -1024,8 +3622,8 @@ impl ApplicationHandler for BrowserApp {
 
                 // Only try to start drag if we're in the tab area (first row)
                 if y < tab_row_height {
-                    // Check if this is a close button click first
-                    if ui.check_close_button_click(x, y).is_some() {
+                    // Check if this is a close button or speaker click first
+                    if ui.check_close_button_click(x, y).is_some() || ui.check_tab_audio_button_click(x, y).is_some() {
                         // Let handle_click process the close button
                         self.handle_click(x, y, event_loop);
                     } else {
@@ -1038,6 +3636,15 @@ impl ApplicationHandler for BrowserApp {
                         }
                     }
                 } else {
+                    // The permission prompt bar, autofill popup and page dialogs cover the page; their clicks
+                    // stay in the chrome
+                    if (ui.is_over_permission_prompt(x, y) || ui.is_over_autofill_popup(x, y) || ui.is_over_page_dialog(x, y))
+                        && !ui.show_settings
+                    {
+                        self.handle_click(x, y, event_loop);
+                        self.request_redraw();
+                        return;
+                    }
                     if ui.begin_bookmark_drag(x, y) {
                         self.request_redraw();
                         return;
@@ -1066,7 +3673,7 @@ impl ApplicationHandler for BrowserApp {
                             mods: winit_modifiers_to_kbt_modifiers(self.modifiers.state()),
                             details: PointerDetails::default()
                         });
-                        let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::UI(event));
+                        self.send_ui_event(&tab_id, event);
                     }
 
                     let event = BlitzPointerEvent {
@@ -1082,7 +3689,7 @@ impl ApplicationHandler for BrowserApp {
 
                     let event = UiEvent::PointerDown(event);
 
-                    let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::UI(event));
+                    self.send_ui_event(&tab_id, event);
                     self.request_redraw();
                 }
             }
@@ -1091,6 +3698,12 @@ impl ApplicationHandler for BrowserApp {
                 let y = self.pointer_position.1 as f32;
                 let mut consumed_bookmark = false;
 
+                // Text selected in a text field with the mouse becomes the primary selection
+                if self.ui().is_text_selection_drag_active() {
+                    if let Some(text) = self.ui().get_selected_text() {
+                        clipboard::set_text(Selection::Primary, &text);
+                    }
+                }
                 self.ui_mut().end_text_selection_drag();
 
                 if self.ui().is_dragging_bookmark() {
@@ -1106,23 +3719,7 @@ impl ApplicationHandler for BrowserApp {
                 if self.ui().is_dragging_tab() {
                     // End the drag and get reorder info
                     if let Some((from_index, to_index)) = self.ui_mut().end_tab_drag() {
-                        // Reorder tabs in UI
-                        self.ui_mut().reorder_tabs(from_index, to_index);
-
-                        // Reorder tabs in tab_order
-                        if from_index < self.tab_order.len() && to_index < self.tab_order.len() {
-                            let tab_id = self.tab_order.remove(from_index);
-                            self.tab_order.insert(to_index, tab_id);
-
-                            // Update active tab index if needed
-                            if self.active_tab_index == from_index {
-                                self.active_tab_index = to_index;
-                            } else if from_index < self.active_tab_index && to_index >= self.active_tab_index {
-                                self.active_tab_index -= 1;
-                            } else if from_index > self.active_tab_index && to_index <= self.active_tab_index {
-                                self.active_tab_index += 1;
-                            }
-                        }
+                        self.reorder_tab(from_index, to_index);
                     } else {
                         // It was a click without significant drag - switch to the tab
                         if let Some(component_id) = self.ui_mut().handle_click(x, y) {
@@ -1164,7 +3761,7 @@ impl ApplicationHandler for BrowserApp {
                         mods: winit_modifiers_to_kbt_modifiers(self.modifiers.state()),
                         details: PointerDetails::default()
                     });
-                    let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::UI(event));
+                    self.send_ui_event(&tab_id, event);
                 }
 
                 let event = BlitzPointerEvent {
@@ -1180,7 +3777,7 @@ impl ApplicationHandler for BrowserApp {
 
                 let event = UiEvent::PointerUp(event);
 
-                let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::UI(event));
+                self.send_ui_event(&tab_id, event);
                 self.request_redraw();
             }
             WindowEvent::PointerButton { state: ElementState::Pressed, button: ButtonSource::Mouse(MouseButton::Middle), primary, position, .. } => {
@@ -1207,7 +3804,7 @@ impl ApplicationHandler for BrowserApp {
                         mods: winit_modifiers_to_kbt_modifiers(self.modifiers.state()),
                         details: PointerDetails::default()
                     });
-                    let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::UI(event));
+                    self.send_ui_event(&tab_id, event);
                 }
 
                 let event = BlitzPointerEvent {
@@ -1223,7 +3820,7 @@ impl ApplicationHandler for BrowserApp {
 
                 let event = UiEvent::PointerDown(event);
 
-                let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::UI(event));
+                self.send_ui_event(&tab_id, event);
                 self.request_redraw();
             }
             WindowEvent::PointerButton { state: ElementState::Released, button: ButtonSource::Mouse(MouseButton::Middle), primary, position, .. } => {
@@ -1247,7 +3844,7 @@ impl ApplicationHandler for BrowserApp {
                         mods: winit_modifiers_to_kbt_modifiers(self.modifiers.state()),
                         details: PointerDetails::default()
                     });
-                    let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::UI(event));
+                    self.send_ui_event(&tab_id, event);
                 }
 
                 let event = BlitzPointerEvent {
@@ -1262,12 +3859,20 @@ impl ApplicationHandler for BrowserApp {
 
                 let event = UiEvent::PointerUp(event);
 
-                let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::UI(event));
+                self.send_ui_event(&tab_id, event);
                 self.request_redraw();
             }
             WindowEvent::PointerButton { state: ElementState::Pressed, button: ButtonSource::Mouse(MouseButton::Right), primary, position, .. } => {
                 let x = self.pointer_position.0 as f32;
                 let y = self.pointer_position.1 as f32;
+                // Right-clicking a tab opens its context menu
+                if self.ui_mut().open_tab_context_menu(x, y) {
+                    self.request_redraw();
+                    return;
+                }
+                if self.ui_mut().close_tab_context_menu() {
+                    self.request_redraw();
+                }
                 if let Some(action) = self.ui_mut().handle_bookmark_right_click(x, y) {
                     self.handle_bookmark_ui_action(action, event_loop);
                     return;
@@ -1293,7 +3898,7 @@ impl ApplicationHandler for BrowserApp {
                     details: PointerDetails::default(),
                 });
 
-                let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::UI(event));
+                self.send_ui_event(&tab_id, event);
             }
             WindowEvent::PointerButton { state: ElementState::Released, button: ButtonSource::Mouse(MouseButton::Right), primary, position, .. } => {
                 let Some(tab_id) = self.active_tab_id().cloned() else {
@@ -1316,7 +3921,61 @@ impl ApplicationHandler for BrowserApp {
                     details: PointerDetails::default(),
                 });
 
-                let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::UI(event));
+                self.send_ui_event(&tab_id, event);
+            }
+            WindowEvent::PointerButton { device_id, state, position, primary, button: ButtonSource::Touch { finger_id, force } } => {
+                let finger = finger_id.into_raw() as u64;
+                let on_chrome = match state {
+                    ElementState::Pressed => {
+                        let (x, y) = (position.x as f32, position.y as f32);
+                        let ui = self.ui();
+                        let on_chrome = y < ui.chrome_height()
+                            || ui.show_settings
+                            || ui.is_over_permission_prompt(x, y)
+                            || ui.is_over_autofill_popup(x, y)
+                            || ui.is_over_page_dialog(x, y);
+                        self.touches.insert(finger, ActiveTouch { position, on_chrome });
+                        on_chrome
+                    }
+                    ElementState::Released => match self.touches.remove(&finger) {
+                        Some(touch) => touch.on_chrome,
+                        None => return,
+                    },
+                };
+                self.update_pinch();
+
+                // The chrome has no touch handling of its own; a tap on it is a click
+                if on_chrome {
+                    if primary {
+                        self.pointer_position = (position.x, position.y);
+                        let button = ButtonSource::Mouse(MouseButton::Left);
+                        self.window_event(event_loop, window_id, WindowEvent::PointerButton { device_id, state, position, primary, button });
+                    }
+                    return;
+                }
+
+                let Some(tab_id) = self.active_tab_id().cloned() else {
+                    return;
+                };
+                let button = MouseEventButton::Main;
+                let event = BlitzPointerEvent {
+                    id: BlitzPointerId::Finger(finger),
+                    is_primary: primary,
+                    coords: self.pointer_coords(position),
+                    button,
+                    buttons: match state {
+                        ElementState::Pressed => button.into(),
+                        ElementState::Released => MouseEventButtons::None,
+                    },
+                    mods: winit_modifiers_to_kbt_modifiers(self.modifiers.state()),
+                    details: pointer_source_to_blitz_details(&PointerSource::Touch { finger_id, force }),
+                };
+                let event = match state {
+                    ElementState::Pressed => UiEvent::PointerDown(event),
+                    ElementState::Released => UiEvent::PointerUp(event),
+                };
+                self.send_ui_event(&tab_id, event);
+                self.request_redraw();
             }
             WindowEvent::PointerButton { state, button, primary, position, .. } => {
                 let Some(tab_id) = self.active_tab_id().cloned() else {
@@ -1339,7 +3998,7 @@ impl ApplicationHandler for BrowserApp {
                         mods: winit_modifiers_to_kbt_modifiers(self.modifiers.state()),
                         details: PointerDetails::default()
                     });
-                    let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::UI(event));
+                    self.send_ui_event(&tab_id, event);
                 }
 
                 let event = BlitzPointerEvent {
@@ -1355,10 +4014,47 @@ impl ApplicationHandler for BrowserApp {
 
                 let event = UiEvent::PointerDown(event);
 
-                let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::UI(event));
+                self.send_ui_event(&tab_id, event);
                 self.request_redraw();
             }
+            WindowEvent::PointerMoved { device_id, position, source: PointerSource::Touch { finger_id, force }, primary } => {
+                let finger = finger_id.into_raw() as u64;
+                let Some(touch) = self.touches.get_mut(&finger) else {
+                    return;
+                };
+                touch.position = position;
+                if touch.on_chrome {
+                    if primary {
+                        let source = PointerSource::Mouse;
+                        self.window_event(event_loop, window_id, WindowEvent::PointerMoved { device_id, position, source, primary });
+                    }
+                    return;
+                }
+                self.continue_pinch();
+
+                let Some(tab_id) = self.active_tab_id().cloned() else {
+                    return;
+                };
+                let source = PointerSource::Touch { finger_id, force };
+                let event = UiEvent::PointerMove(BlitzPointerEvent {
+                    id: pointer_source_to_blitz(&source),
+                    is_primary: primary,
+                    coords: self.pointer_coords(position),
+                    button: Default::default(),
+                    buttons: MouseEventButton::Main.into(),
+                    mods: winit_modifiers_to_kbt_modifiers(self.modifiers.state()),
+                    details: pointer_source_to_blitz_details(&source),
+                });
+                self.queue_ui_event(&tab_id, event);
+            }
             WindowEvent::PointerMoved { position, source, primary, .. } => {
+                // The mouse's motion is taken from the device while the pointer is locked
+                if self.pointer_lock_tab.is_some() {
+                    return;
+                }
+                let scale = self.viewport.as_ref().unwrap().scale_f64();
+                let movement_x = ((position.x - self.pointer_position.0) / scale) as f32;
+                let movement_y = ((position.y - self.pointer_position.1) / scale) as f32;
                 self.pointer_position = (position.x, position.y);
 
                 let ui = self.ui.as_mut().unwrap();
@@ -1387,9 +4083,13 @@ impl ApplicationHandler for BrowserApp {
                             button: Default::default(),
                             buttons: self.buttons,
                             mods: winit_modifiers_to_kbt_modifiers(self.modifiers.state()),
-                            details: pointer_source_to_blitz_details(&source)
+                            details: PointerDetails {
+                                movement_x,
+                                movement_y,
+                                ..pointer_source_to_blitz_details(&source)
+                            }
                         });
-                        let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::UI(event));
+                        self.queue_ui_event(&tab_id, event);
                         }
                     }
                 }
@@ -1397,21 +4097,70 @@ impl ApplicationHandler for BrowserApp {
                 // Request redraw to show hover effects
                 self.env.as_ref().unwrap().window.request_redraw();
             }
+            WindowEvent::PointerLeft { kind: PointerKind::Touch(finger_id), .. } => {
+                // A finger that leaves the window without lifting is forgotten; the page sees it
+                // replaced when one with the same id lands again
+                if self.touches.remove(&(finger_id.into_raw() as u64)).is_some() {
+                    self.update_pinch();
+                }
+            }
+            WindowEvent::PinchGesture { delta, .. } => {
+                // Touchpad pinches report how much the fingers spread since the last event
+                if !self.ui().show_settings {
+                    self.zoom_visual_viewport(1.0 + delta as f32, PhysicalPosition::from(self.pointer_position));
+                }
+            }
+            WindowEvent::PointerLeft { .. } => {
+                // A tab dragged out of the window is dropped at its current position
+                if self.ui().is_dragging_tab() {
+                    if let Some((from_index, to_index)) = self.ui_mut().end_tab_drag() {
+                        self.reorder_tab(from_index, to_index);
+                    }
+                    self.request_redraw();
+                }
+            }
+            WindowEvent::DragEntered { position, .. } | WindowEvent::DragMoved { position } => {
+                if self.ui_mut().update_drop_hover(Some(position.y as f32)) {
+                    self.request_redraw();
+                }
+            }
+            WindowEvent::DragLeft { .. } => {
+                if self.ui_mut().update_drop_hover(None) {
+                    self.request_redraw();
+                }
+            }
+            WindowEvent::DragDropped { paths, position } => {
+                self.ui_mut().update_drop_hover(None);
+                self.handle_file_drop(&paths, position.y as f32, event_loop);
+                self.request_redraw();
+            }
             WindowEvent::MouseWheel { delta, .. } => {
-                if let Some(tab_id) = self.active_tab_id().cloned() {
+                // Ctrl+wheel magnifies the page like a pinch
+                if self.modifiers.state().control_key() && self.active_tab_id().is_some() {
+                    let notches = match delta {
+                        winit::event::MouseScrollDelta::LineDelta(_, y) => y,
+                        winit::event::MouseScrollDelta::PixelDelta(pos) => pos.y as f32 / WHEEL_PIXELS_PER_LINE,
+                    };
+                    self.zoom_visual_viewport(VISUAL_ZOOM_PER_LINE.powf(notches), PhysicalPosition::from(self.pointer_position));
+                } else if let Some(tab_id) = self.active_tab_id().cloned() {
                     let blitz_delta = match delta {
                         winit::event::MouseScrollDelta::LineDelta(x, y) => BlitzWheelDelta::Lines(x as f64, y as f64),
                         winit::event::MouseScrollDelta::PixelDelta(pos) => BlitzWheelDelta::Pixels(pos.x, pos.y),
                     };
+                    // A magnified page pans before it scrolls
+                    let Some(blitz_delta) = self.pan_visual_viewport(&tab_id, blitz_delta) else {
+                        return;
+                    };
 
                     let event = BlitzWheelEvent {
                         delta: blitz_delta,
                         coords: self.pointer_coords(PhysicalPosition::from(self.pointer_position)),
                         buttons: self.buttons,
                         mods: winit_modifiers_to_kbt_modifiers(self.modifiers.state()),
+                        timestamp: unix_time_ms(),
                     };
 
-                    let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::UI(UiEvent::Wheel(event)));
+                    self.queue_ui_event(&tab_id, UiEvent::Wheel(event));
                 }
                 self.env.as_ref().unwrap().window.request_redraw();
             }
@@ -1420,14 +4169,23 @@ impl ApplicationHandler for BrowserApp {
             }
             WindowEvent::Ime(ime) => {
                 let active_tab_id = self.active_tab_id().cloned().unwrap();
-                let _ = self.tab_manager.send_to_tab(&active_tab_id, ParentToTabMessage::UI(UiEvent::Ime(winit_ime_to_blitz(ime))));
+                self.send_ui_event(&active_tab_id, UiEvent::Ime(winit_ime_to_blitz(ime)));
                 self.env.as_ref().unwrap().window.request_redraw();
             }
             WindowEvent::KeyboardInput { event, .. } => {
+                // Escape always gives the cursor back; the page does not see the key
+                if self.pointer_lock_tab.is_some()
+                    && event.state.is_pressed()
+                    && event.logical_key == Key::Named(NamedKey::Escape)
+                {
+                    self.release_pointer_lock(true);
+                    return;
+                }
                 // Handle keyboard input with the new multi-process architecture
                 let action = input::handle_keyboard_input(
                     &event,
                     &self.modifiers,
+                    &self.keymap,
                     self.ui.as_mut().unwrap(),
                     self.active_tab_index,
                     self.tab_order.len(),
@@ -1439,13 +4197,16 @@ impl ApplicationHandler for BrowserApp {
                         // Forward keyboard input to active tab process
                         if let Some(tab_id) = self.active_tab_id().cloned() {
                             let key_event_data = winit_key_event_to_blitz(&event, self.modifiers.state());
+                            if event.state.is_pressed() {
+                                self.ui_mut().hide_autofill_popup();
+                            }
                             let event = if event.state.is_pressed() {
                                 UiEvent::KeyDown(key_event_data)
                             } else {
                                 UiEvent::KeyUp(key_event_data)
                             };
 
-                            let _ = self.tab_manager.send_to_tab(&tab_id, ParentToTabMessage::UI(event));
+                            self.send_ui_event(&tab_id, event);
                         }
                         self.env.as_ref().unwrap().window.request_redraw();
                     }
@@ -1454,6 +4215,10 @@ impl ApplicationHandler for BrowserApp {
                         self.handle_input_action(&action, event_loop);
                     }
                 }
+                if matches!(action, input::InputAction::RequestRedraw) {
+                    self.preconnect_address_bar_completion();
+                    self.update_find_query();
+                }
             }
             _ => {}
         }
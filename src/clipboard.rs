@@ -0,0 +1,102 @@
+// The system clipboard, and on Linux the primary selection: whatever text was last selected,
+// pasted with a middle click. arboard picks the backend when the first clipboard is opened,
+// speaking Wayland's data-control protocol in a Wayland session whose compositor has it and X11
+// (through XWayland on Wayland) otherwise. Each process keeps one clipboard open for its whole
+// life because on X11 the text a process copied is only there while the process still owns it.
+use std::sync::{LazyLock, Mutex};
+use tracing::{debug, warn};
+
+/// Which of the system's selections to read or write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// The clipboard that copy, cut and paste use
+    Clipboard,
+    /// The text selected last, which a middle click pastes. Only Linux desktops have one; on
+    /// other platforms nothing is written to it and reading it finds nothing.
+    Primary,
+}
+
+static CLIPBOARD: LazyLock<Mutex<Option<arboard::Clipboard>>> = LazyLock::new(|| {
+    debug!("Opening the clipboard in a {} session", session_kind());
+    match arboard::Clipboard::new() {
+        Ok(clipboard) => Mutex::new(Some(clipboard)),
+        Err(e) => {
+            warn!("Failed to open the clipboard: {}", e);
+            Mutex::new(None)
+        }
+    }
+});
+
+/// The display server the process runs under, as arboard will see it
+fn session_kind() -> &'static str {
+    if cfg!(not(target_os = "linux")) {
+        "native"
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        "Wayland"
+    } else if std::env::var_os("DISPLAY").is_some() {
+        "X11"
+    } else {
+        "headless"
+    }
+}
+
+/// Whether the platform has a primary selection to paste from
+pub fn has_primary_selection() -> bool {
+    cfg!(target_os = "linux")
+}
+
+/// The text in `selection`, if there is any
+pub fn get_text(selection: Selection) -> Option<String> {
+    if selection == Selection::Primary && !has_primary_selection() {
+        return None;
+    }
+    let mut guard = CLIPBOARD.lock().ok()?;
+    let clipboard = guard.as_mut()?;
+    let text = match selection {
+        Selection::Clipboard => clipboard.get_text(),
+        #[cfg(target_os = "linux")]
+        Selection::Primary => {
+            use arboard::{GetExtLinux, LinuxClipboardKind};
+            clipboard.get().clipboard(LinuxClipboardKind::Primary).text()
+        }
+        #[cfg(not(target_os = "linux"))]
+        Selection::Primary => return None,
+    };
+    match text {
+        Ok(text) => Some(text),
+        // An empty selection is not worth a warning
+        Err(arboard::Error::ContentNotAvailable) => None,
+        Err(e) => {
+            warn!("Failed to read from the {:?} selection: {}", selection, e);
+            None
+        }
+    }
+}
+
+/// Put `text` in `selection`. Returns whether it is there now.
+pub fn set_text(selection: Selection, text: &str) -> bool {
+    if selection == Selection::Primary && (!has_primary_selection() || text.is_empty()) {
+        return false;
+    }
+    let Ok(mut guard) = CLIPBOARD.lock() else {
+        return false;
+    };
+    let Some(clipboard) = guard.as_mut() else {
+        return false;
+    };
+    let result = match selection {
+        Selection::Clipboard => clipboard.set_text(text),
+        #[cfg(target_os = "linux")]
+        Selection::Primary => {
+            use arboard::{LinuxClipboardKind, SetExtLinux};
+            clipboard.set().clipboard(LinuxClipboardKind::Primary).text(text)
+        }
+        #[cfg(not(target_os = "linux"))]
+        Selection::Primary => return false,
+    };
+    if let Err(e) = result {
+        warn!("Failed to write to the {:?} selection: {}", selection, e);
+        return false;
+    }
+    true
+}
@@ -0,0 +1,158 @@
+// Color management. Pages are authored in sRGB, and photos often carry an ICC profile for a wider
+// gamut such as Display P3 or Adobe RGB. Decoded images are converted from their embedded profile
+// to sRGB, and every Skia image and surface is tagged with its color space, so Skia converts
+// between them when drawing: the page's sRGB content lands on surfaces in the display's color space
+// instead of being stretched over a wide-gamut display's primaries, which oversaturates it, and
+// tagged photos are no longer read as sRGB, which washes them out.
+//
+// The browser and tab processes each find the display's profile the same way, so the frames a tab
+// paints are already in the color space the browser composites in.
+
+use moxcms::{ColorProfile, Layout, TransformOptions, Xyzd};
+use skia_safe::ColorSpace;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use tracing::{debug, warn};
+
+/// A display profile the user supplies, in the profile directory
+const DISPLAY_PROFILE_FILE: &str = "display.icc";
+
+/// The gamuts Skia knows by name, which a display is matched to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisplayGamut {
+    #[default]
+    Srgb,
+    DisplayP3,
+    AdobeRgb,
+    Rec2020,
+}
+
+impl DisplayGamut {
+    const ALL: [Self; 4] = [Self::Srgb, Self::DisplayP3, Self::AdobeRgb, Self::Rec2020];
+
+    fn reference_profile(self) -> ColorProfile {
+        match self {
+            Self::Srgb => ColorProfile::new_srgb(),
+            Self::DisplayP3 => ColorProfile::new_display_p3(),
+            Self::AdobeRgb => ColorProfile::new_adobe_rgb(),
+            Self::Rec2020 => ColorProfile::new_bt2020(),
+        }
+    }
+
+    /// The named gamut whose primaries are nearest the profile's
+    fn nearest(profile: &ColorProfile) -> Self {
+        let primaries = chromaticities(profile);
+        Self::ALL
+            .into_iter()
+            .min_by(|a, b| {
+                let distance = |gamut: &Self| primaries_distance(primaries, chromaticities(&gamut.reference_profile()));
+                distance(a).total_cmp(&distance(b))
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn color_space(self) -> ColorSpace {
+        use skia_safe::{named_gamut, named_transfer_fn};
+        match self {
+            Self::Srgb => ColorSpace::new_srgb(),
+            Self::DisplayP3 => ColorSpace::new_rgb(&named_transfer_fn::SRGB, &named_gamut::DISPLAY_P3),
+            Self::AdobeRgb => ColorSpace::new_rgb(&named_transfer_fn::TWO_DOT_TWO, &named_gamut::ADOBE_RGB),
+            Self::Rec2020 => ColorSpace::new_rgb(&named_transfer_fn::REC2020, &named_gamut::REC2020),
+        }
+    }
+}
+
+/// The red, green and blue primaries' xy chromaticities
+fn chromaticities(profile: &ColorProfile) -> [(f64, f64); 3] {
+    let xy = |colorant: Xyzd| {
+        let sum = colorant.x + colorant.y + colorant.z;
+        if sum <= 0.0 { (0.0, 0.0) } else { (colorant.x / sum, colorant.y / sum) }
+    };
+    [xy(profile.red_colorant), xy(profile.green_colorant), xy(profile.blue_colorant)]
+}
+
+fn primaries_distance(a: [(f64, f64); 3], b: [(f64, f64); 3]) -> f64 {
+    a.iter().zip(b.iter()).map(|(a, b)| (a.0 - b.0).hypot(a.1 - b.1)).sum()
+}
+
+/// Primaries this close to sRGB's are sRGB, give or take the rounding in a profile
+const SRGB_TOLERANCE: f64 = 0.01;
+
+static DISPLAY_GAMUT: LazyLock<DisplayGamut> = LazyLock::new(|| {
+    let gamut = display_profile()
+        .and_then(|icc| ColorProfile::new_from_slice(&icc).ok())
+        .map(|profile| DisplayGamut::nearest(&profile))
+        .unwrap_or_default();
+    debug!("Compositing for a {gamut:?} display");
+    gamut
+});
+
+/// The color space the display shows, which frames are painted and composited in
+pub fn display_color_space() -> ColorSpace {
+    DISPLAY_GAMUT.color_space()
+}
+
+/// The display's ICC profile: the one the user put in the profile directory, or on Linux the one
+/// colord made for the monitor from its EDID
+fn display_profile() -> Option<Vec<u8>> {
+    let user_profile = crate::profile::data_dir().join(DISPLAY_PROFILE_FILE);
+    if let Ok(icc) = std::fs::read(&user_profile) {
+        return Some(icc);
+    }
+    if cfg!(target_os = "linux") {
+        let dir = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?
+            .join("icc");
+        let mut profiles: Vec<PathBuf> = std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+                name.starts_with("edid-") && name.ends_with(".icc")
+            })
+            .collect();
+        profiles.sort();
+        return profiles.first().and_then(|path| std::fs::read(path).ok());
+    }
+    None
+}
+
+/// Convert decoded RGBA8 pixels from the embedded ICC profile `icc` to sRGB in place. Profiles
+/// that can't be read, and ones with sRGB's primaries, leave the pixels as they are.
+pub fn convert_to_srgb(icc: &[u8], rgba: &mut [u8]) {
+    let source = match ColorProfile::new_from_slice(icc) {
+        Ok(profile) => profile,
+        Err(e) => {
+            warn!("Ignoring an image's unreadable ICC profile: {e:?}");
+            return;
+        }
+    };
+    let srgb = ColorProfile::new_srgb();
+    if primaries_distance(chromaticities(&source), chromaticities(&srgb)) < SRGB_TOLERANCE {
+        return;
+    }
+    let transform = match source.create_transform_8bit(Layout::Rgba, &srgb, Layout::Rgba, TransformOptions::default()) {
+        Ok(transform) => transform,
+        Err(e) => {
+            warn!("Can't convert an image from its ICC profile: {e:?}");
+            return;
+        }
+    };
+    let source_pixels = rgba.to_vec();
+    if let Err(e) = transform.transform(&source_pixels, rgba) {
+        warn!("Failed to convert an image from its ICC profile: {e:?}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displays_match_the_nearest_named_gamut() {
+        assert_eq!(DisplayGamut::nearest(&ColorProfile::new_display_p3()), DisplayGamut::DisplayP3);
+        assert_eq!(DisplayGamut::nearest(&ColorProfile::new_srgb()), DisplayGamut::Srgb);
+        assert_eq!(DisplayGamut::nearest(&ColorProfile::new_bt2020()), DisplayGamut::Rec2020);
+    }
+}
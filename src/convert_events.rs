@@ -109,6 +109,7 @@ pub(crate) fn pointer_source_to_blitz_details(source: &PointerSource) -> Pointer
             twist: data.twist.unwrap_or(0),
             altitude: data.angle.map(|angle| angle.altitude).unwrap_or(0.0),
             azimuth: data.angle.map(|angle| angle.azimuth).unwrap_or(0.0),
+            ..PointerDetails::default()
         },
     }
 }
@@ -0,0 +1,190 @@
+use crate::js::bindings::cookie::{self, Cookie, SameSite};
+use std::collections::BTreeMap;
+use std::time::{Duration, UNIX_EPOCH};
+use url::Url;
+
+const MANAGEMENT_PAGE_HTML: &str = include_str!("../assets/cookies.html");
+/// The page listing stored cookies
+pub const MANAGEMENT_URL: &str = "stokes://cookies";
+/// Longer values are cut short in the table
+const MAX_SHOWN_VALUE_LEN: usize = 48;
+
+pub fn is_management_url(url: &Url) -> bool {
+    url.scheme() == "stokes" && url.host_str() == Some("cookies")
+}
+
+/// The stokes://cookies page. Its links delete cookies and change the clear-on-exit setting
+/// through the query string, which is only acted on when `from_management_page` (the link was
+/// followed from the page itself) so that other pages can't use them. The search (`q`) is always
+/// applied.
+pub fn management_page(url: &Url, from_management_page: bool) -> String {
+    let query = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+    let search = query("q").unwrap_or_default();
+
+    if from_management_page {
+        if let Some(domain) = query("remove") {
+            let name = query("name").unwrap_or_default();
+            let path = query("path").unwrap_or_else(|| "/".to_string());
+            cookie::manage_cookies(|jar| jar.remove_cookie(&domain, &name, &path));
+        }
+        if let Some(domain) = query("clear_site") {
+            cookie::manage_cookies(|jar| jar.remove_site(&domain));
+        }
+        if let Some(enabled) = query("clear_on_exit") {
+            cookie::manage_cookies(|jar| jar.set_clear_on_exit(enabled == "1"));
+        }
+    }
+
+    let clear_on_exit = cookie::CookieJar::load_from_disk().clear_on_exit();
+    render_management_page(&cookie::saved_cookies(), search.trim(), clear_on_exit)
+}
+
+fn management_link(search: &str, params: &[(&str, &str)]) -> String {
+    let mut query = url::form_urlencoded::Serializer::new(String::new());
+    query.extend_pairs(params);
+    if !search.is_empty() {
+        query.append_pair("q", search);
+    }
+    let query = query.finish();
+    if query.is_empty() {
+        MANAGEMENT_URL.to_string()
+    } else {
+        format!("{MANAGEMENT_URL}?{query}")
+    }
+}
+
+/// Cookies grouped by the domain they were set for, leaving out those that don't match `search`
+fn group_by_domain<'a>(cookies: &'a [Cookie], search: &str) -> BTreeMap<&'a str, Vec<&'a Cookie>> {
+    let search = search.to_ascii_lowercase();
+    let mut groups: BTreeMap<&str, Vec<&Cookie>> = BTreeMap::new();
+    for cookie in cookies {
+        if !search.is_empty()
+            && !cookie.domain.contains(&search)
+            && !cookie.name.to_ascii_lowercase().contains(&search)
+        {
+            continue;
+        }
+        groups.entry(cookie.domain.as_str()).or_default().push(cookie);
+    }
+    for cookies in groups.values_mut() {
+        cookies.sort_by(|a, b| (&a.name, &a.path).cmp(&(&b.name, &b.path)));
+    }
+    groups
+}
+
+fn shown_value(value: &str) -> String {
+    if value.chars().count() <= MAX_SHOWN_VALUE_LEN {
+        return value.to_string();
+    }
+    let mut shown: String = value.chars().take(MAX_SHOWN_VALUE_LEN).collect();
+    shown.push('…');
+    shown
+}
+
+fn attributes(cookie: &Cookie) -> String {
+    let mut attributes = Vec::new();
+    if cookie.secure {
+        attributes.push("Secure");
+    }
+    if cookie.http_only {
+        attributes.push("HttpOnly");
+    }
+    match cookie.same_site {
+        Some(SameSite::Strict) => attributes.push("SameSite=Strict"),
+        Some(SameSite::Lax) => attributes.push("SameSite=Lax"),
+        Some(SameSite::None) => attributes.push("SameSite=None"),
+        None => {}
+    }
+    attributes.join(", ")
+}
+
+fn expiry(cookie: &Cookie) -> String {
+    match cookie.expires_at {
+        Some(expires_at) => httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_millis(expires_at)),
+        None => "End of session".to_string(),
+    }
+}
+
+fn render_management_page(cookies: &[Cookie], search: &str, clear_on_exit: bool) -> String {
+    let groups = group_by_domain(cookies, search);
+
+    let sites: String = groups.iter().map(|(domain, cookies)| {
+        let rows: String = cookies.iter().map(|cookie| {
+            let remove = management_link(search, &[("remove", domain), ("name", &cookie.name), ("path", &cookie.path)]);
+            format!(
+                "<tr><td>{}</td><td class=\"value\">{}</td><td>{}</td><td>{}</td><td>{}</td><td><a class=\"remove\" href=\"{}\">Delete</a></td></tr>\n",
+                html_escape::encode_text(&cookie.name),
+                html_escape::encode_text(&shown_value(&cookie.value)),
+                html_escape::encode_text(&cookie.path),
+                attributes(cookie),
+                html_escape::encode_text(&expiry(cookie)),
+                html_escape::encode_double_quoted_attribute(&remove),
+            )
+        }).collect();
+        let clear = management_link(search, &[("clear_site", domain)]);
+        format!(
+            "<h2>{} <a class=\"remove\" href=\"{}\">Clear site</a></h2>\n<table>\n<tr><th>Name</th><th>Value</th><th>Path</th><th>Attributes</th><th>Expires</th><th></th></tr>\n{rows}</table>\n",
+            html_escape::encode_text(domain),
+            html_escape::encode_double_quoted_attribute(&clear),
+        )
+    }).collect();
+    let sites = if !sites.is_empty() {
+        sites
+    } else if search.is_empty() {
+        "<p class=\"empty\">No cookies saved yet</p>".to_string()
+    } else {
+        "<p class=\"empty\">No cookies match the search</p>".to_string()
+    };
+
+    let count: usize = groups.values().map(Vec::len).sum();
+    let summary = match (count, groups.len()) {
+        (1, _) => "1 cookie from 1 site.".to_string(),
+        (count, 1) => format!("{count} cookies from 1 site."),
+        (count, sites) => format!("{count} cookies from {sites} sites."),
+    };
+    let (toggle, toggle_label) = if clear_on_exit {
+        ("0", "Clear all cookies on exit: On")
+    } else {
+        ("1", "Clear all cookies on exit: Off")
+    };
+    MANAGEMENT_PAGE_HTML
+        .replace("{{SUMMARY}}", &summary)
+        .replace("{{SEARCH}}", &html_escape::encode_double_quoted_attribute(search))
+        .replace("{{CLEAR_ON_EXIT_URL}}", &html_escape::encode_double_quoted_attribute(&management_link(search, &[("clear_on_exit", toggle)])))
+        .replace("{{CLEAR_ON_EXIT_LABEL}}", toggle_label)
+        .replace("{{SITES}}", &sites)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{group_by_domain, management_link, render_management_page};
+    use crate::js::bindings::cookie::Cookie;
+
+    fn cookie(name: &str, domain: &str) -> Cookie {
+        Cookie::parse(&format!("{name}=value"), domain, "/").unwrap()
+    }
+
+    #[test]
+    fn groups_cookies_by_domain_and_filters_by_search() {
+        let cookies = [cookie("b", "example.com"), cookie("a", "example.com"), cookie("session", "news.test")];
+        let groups = group_by_domain(&cookies, "");
+        assert_eq!(groups.keys().copied().collect::<Vec<_>>(), ["example.com", "news.test"]);
+        assert_eq!(groups["example.com"].iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+
+        assert_eq!(group_by_domain(&cookies, "NEWS").keys().copied().collect::<Vec<_>>(), ["news.test"]);
+        assert_eq!(group_by_domain(&cookies, "sess").keys().copied().collect::<Vec<_>>(), ["news.test"]);
+    }
+
+    #[test]
+    fn page_escapes_cookies_and_keeps_the_search_in_links() {
+        let cookies = [cookie("<b>", "example.com")];
+        let page = render_management_page(&cookies, "exa", true);
+        assert!(page.contains("&lt;b&gt;"));
+        assert!(page.contains("1 cookie from 1 site."));
+        assert!(page.contains("Clear all cookies on exit: On"));
+        assert_eq!(
+            management_link("exa", &[("clear_site", "example.com")]),
+            "stokes://cookies?clear_site=example.com&q=exa"
+        );
+    }
+}
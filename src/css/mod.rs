@@ -1,2 +1,2 @@
 pub(crate) mod stylo;
-mod parse;
\ No newline at end of file
+pub(crate) mod parse;
\ No newline at end of file
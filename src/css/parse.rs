@@ -5,11 +5,15 @@ pub(crate) fn parse_color(value: &str) -> Option<(u8, u8, u8, f32)> {
         return None;
     }
 
+    // Slicing below is by byte, which only lines up with characters in ASCII
     let value = &value[1..];
+    if !value.is_ascii() {
+        return None;
+    }
     if value.len() == 3 {
-        let r = u8::from_str_radix(&value[0..1], 16).ok()?;
-        let g = u8::from_str_radix(&value[1..2], 16).ok()?;
-        let b = u8::from_str_radix(&value[2..3], 16).ok()?;
+        let r = u8::from_str_radix(&value[0..1], 16).ok()? * 17;
+        let g = u8::from_str_radix(&value[1..2], 16).ok()? * 17;
+        let b = u8::from_str_radix(&value[2..3], 16).ok()? * 17;
         return Some((r, g, b, 1.0));
     }
 
@@ -44,4 +48,17 @@ pub(crate) fn parse_size(
     Some(LengthPercentage::Length(NoCalcLength::Absolute(
         AbsoluteLength::Px(val),
     )))
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::parse_color;
+
+    #[test]
+    fn hex_colors_parse_and_other_text_does_not() {
+        assert_eq!(parse_color("#f80"), Some((255, 136, 0, 1.0)));
+        assert_eq!(parse_color("#ff8800"), Some((255, 136, 0, 1.0)));
+        assert_eq!(parse_color("red"), None);
+        assert_eq!(parse_color("#aé"), None);
+        assert_eq!(parse_color("#ééé"), None);
+    }
+}
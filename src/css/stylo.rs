@@ -313,11 +313,13 @@ impl selectors::Element for Node<'_> {
             NonTSPseudoClass::Indeterminate => false,
             NonTSPseudoClass::Invalid => false,
             NonTSPseudoClass::Lang(_) => false,
-            NonTSPseudoClass::Link => self.data.element().map(|element| {
-                (element.name.local == local_name!("a") || element.name.local == local_name!("area")) && element.has_attr(local_name!("href"))
-            }).unwrap_or(false),
-            NonTSPseudoClass::Modal => false,
-            NonTSPseudoClass::Open => false,
+            // Links get their unvisited and :visited styles matched separately; which one is
+            // used is decided by `is_visited_link`
+            NonTSPseudoClass::Link => self.is_link() && context.visited_handling().matches_unvisited(),
+            NonTSPseudoClass::Modal => self.flags.is_modal(),
+            NonTSPseudoClass::Open => self.data.element().is_some_and(|element| {
+                matches!(element.name.local.as_ref(), "details" | "dialog") && element.has_attr(local_name!("open"))
+            }),
             NonTSPseudoClass::MozMeterOptimum => false,
             NonTSPseudoClass::MozMeterSubOptimum => false,
             NonTSPseudoClass::MozMeterSubSubOptimum => false,
@@ -333,7 +335,7 @@ impl selectors::Element for Node<'_> {
             NonTSPseudoClass::UserInvalid => false,
             NonTSPseudoClass::UserValid => false,
             NonTSPseudoClass::Valid => false,
-            NonTSPseudoClass::Visited => false
+            NonTSPseudoClass::Visited => self.is_link() && context.visited_handling().matches_visited(),
         }
     }
 
@@ -365,7 +367,7 @@ impl selectors::Element for Node<'_> {
     }
 
     fn is_link(&self) -> bool {
-        self.data.is_element_with_tag_name(&local_name!("a"))
+        self.element_state.intersects(ElementState::VISITED | ElementState::UNVISITED)
     }
 
     fn is_html_slot_element(&self) -> bool {
@@ -491,6 +493,10 @@ impl<'a> TElement for Node<'a> {
         self.element_state
     }
 
+    fn is_visited_link(&self) -> bool {
+        self.element_state.contains(ElementState::VISITED)
+    }
+
     fn has_part_attr(&self) -> bool {
         self.attr(local_name!("part")).is_some()
     }
@@ -27,15 +27,15 @@ fn set_default_linux() {
 
     match status {
         Ok(s) if s.success() => {
-            println!("[default_browser] Set as default browser via xdg-settings");
+            tracing::info!("Set as default browser via xdg-settings");
         }
         Ok(s) => {
-            eprintln!("[default_browser] xdg-settings exited with status {}", s);
+            tracing::warn!("xdg-settings exited with status {}", s);
             // Fallback: update-desktop-database / xdg-mime
             set_default_linux_xdg_mime();
         }
         Err(e) => {
-            eprintln!("[default_browser] xdg-settings not found: {}. Trying xdg-mime fallback.", e);
+            tracing::warn!("xdg-settings not found: {}. Trying xdg-mime fallback.", e);
             set_default_linux_xdg_mime();
         }
     }
@@ -54,10 +54,10 @@ fn set_default_linux_xdg_mime() {
             .status();
         match status {
             Ok(s) if s.success() => {
-                println!("[default_browser] xdg-mime default set for {}", mime);
+                tracing::info!("xdg-mime default set for {}", mime);
             }
-            Ok(s) => eprintln!("[default_browser] xdg-mime failed for {}: {}", mime, s),
-            Err(e) => eprintln!("[default_browser] xdg-mime not found for {}: {}", mime, e),
+            Ok(s) => tracing::warn!("xdg-mime failed for {}: {}", mime, s),
+            Err(e) => tracing::warn!("xdg-mime not found for {}: {}", mime, e),
         }
     }
 }
@@ -101,7 +101,7 @@ fn try_defaultbrowser_cli(bundle_id: &str) -> bool {
 
     match status {
         Ok(s) if s.success() => {
-            println!("[default_browser] Set default browser via 'defaultbrowser' CLI");
+            tracing::info!("Set default browser via 'defaultbrowser' CLI");
             true
         }
         _ => false,
@@ -124,7 +124,7 @@ fn try_applescript_default_browser() {
 
     match status {
         Ok(s) if s.success() => {
-            println!("[default_browser] Activated app via osascript");
+            tracing::info!("Activated app via osascript");
         }
         _ => {}
     }
@@ -160,7 +160,7 @@ fn write_launchservices_plist() {
         .args(["-kill", "-r", "-domain", "local", "-domain", "system", "-domain", "user"])
         .status();
 
-    println!("[default_browser] Wrote LaunchServices plist entries (takes effect after re-login)");
+    tracing::info!("Wrote LaunchServices plist entries (takes effect after re-login)");
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -175,13 +175,13 @@ fn set_default_windows() {
     let exe_path = match std::env::current_exe() {
         Ok(p) => p,
         Err(e) => {
-            eprintln!("[default_browser] Could not determine exe path: {}", e);
+            tracing::warn!("Could not determine exe path: {}", e);
             return;
         }
     };
 
     if let Err(e) = register_app_windows(&exe_path) {
-        eprintln!("[default_browser] Failed to register app in registry: {}", e);
+        tracing::warn!("Failed to register app in registry: {}", e);
         return;
     }
 
@@ -248,7 +248,7 @@ fn register_app_windows(exe_path: &std::path::Path) -> Result<(), Box<dyn std::e
         ),
     )?;
 
-    println!("[default_browser] Registered app in Windows registry");
+    tracing::info!("Registered app in Windows registry");
     Ok(())
 }
 
@@ -261,6 +261,6 @@ fn open_default_apps_settings() {
         .args(["/c", "start", "ms-settings:defaultapps"])
         .status();
 
-    println!("[default_browser] Opened Windows Default Apps settings page");
+    tracing::info!("Opened Windows Default Apps settings page");
 }
 
@@ -0,0 +1,35 @@
+// User activation. A click or key press on the document activates it for good (sticky activation,
+// which autoplay and pointer lock ask for) and for a few seconds (transient activation, which
+// opening a popup uses up). Both are noted where the UI event is dispatched, before any listener
+// runs, so a page's click handler already counts as triggered by the user.
+use std::time::{Duration, Instant};
+use url::Url;
+use crate::dom::Dom;
+use crate::shell_provider::ShellProviderMessage;
+
+/// How long after a click or key press the page may still open a popup
+const TRANSIENT_ACTIVATION: Duration = Duration::from_secs(5);
+
+impl Dom {
+    /// The user clicked or pressed a key on the document
+    pub(crate) fn note_user_activation(&mut self) {
+        self.user_activated = true;
+        self.last_activation = Some(Instant::now());
+    }
+
+    /// Use up the transient activation, when the user clicked or typed recently enough
+    pub(crate) fn consume_transient_activation(&mut self) -> bool {
+        self.last_activation.take().is_some_and(|at| at.elapsed() < TRANSIENT_ACTIVATION)
+    }
+
+    /// Whether the page may open `url` in a new window now: its site allows popups, or the user
+    /// just clicked or typed. Blocked popups are reported to the browser, which shows them in the
+    /// address bar and offers to allow popups for the site.
+    pub(crate) fn may_open_popup(&mut self, url: &Url) -> bool {
+        if self.popups_allowed || self.consume_transient_activation() {
+            return true;
+        }
+        let _ = self.shell_provider.sender.send(ShellProviderMessage::PopupBlocked(url.to_string()));
+        false
+    }
+}
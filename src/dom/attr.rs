@@ -9,6 +9,14 @@ use stylo_atoms::Atom;
 use crate::dom::node::{Attribute, SpecialElementData};
 use crate::qual_name;
 
+/// Attributes read by box construction or layout rather than through computed styles
+fn is_layout_attribute(name: &QualName) -> bool {
+    matches!(
+        name.local.as_ref(),
+        "colspan" | "rowspan" | "span" | "size" | "cols" | "rows" | "type" | "width" | "height" | "src" | "alt" | "open" | "multiple"
+    )
+}
+
 macro_rules! tag_attr {
     ($tag:tt, $attr:tt) => {
         (&local_name!($tag), &local_name!($attr))
@@ -16,6 +24,29 @@ macro_rules! tag_attr {
 }
 
 impl Dom {
+    /// Invalidation for a changed attribute. Restyles that depend on selectors (classes, ids,
+    /// attribute selectors and the combinators built on them) are found by Stylo from the
+    /// snapshot taken before the change, so only the element itself is restyled here, for
+    /// presentational attributes like `bgcolor`. Attributes that layout reads directly instead of
+    /// through style also throw away the element's boxes.
+    fn invalidate_attribute(&mut self, node_id: usize, name: &QualName) {
+        let node = &mut self.nodes[node_id];
+        if let Some(mut data) = node.stylo_data.get_mut() {
+            data.hint |= RestyleHint::RESTYLE_SELF;
+            if is_layout_attribute(name) {
+                data.damage.insert(ALL_DAMAGE);
+            }
+            // `open` shows or hides a details' content and flips `:open`, which descendant
+            // selectors depend on without any attribute of theirs changing
+            if name.local == local_name!("open") {
+                data.hint |= RestyleHint::restyle_subtree();
+            }
+        }
+
+        // Mark ancestors dirty so the style traversal visits this element
+        node.mark_ancestors_dirty();
+    }
+
     pub fn add_attrs_if_missing(&mut self, node_id: usize, attrs: Vec<Attribute>) {
         let node = &mut self.nodes[node_id];
         node.insert_damage(ALL_DAMAGE);
@@ -38,22 +69,7 @@ impl Dom {
     pub fn set_attribute(&mut self, node_id: usize, name: QualName, value: &str) {
         self.snapshot(node_id);
 
-        let node = &mut self.nodes[node_id];
-        if let Some(mut data) = node.stylo_data.get_mut() {
-            data.hint |= RestyleHint::restyle_subtree();
-            data.damage.insert(ALL_DAMAGE);
-        }
-
-        // TODO: make this fine grained / conditional based on ElementSelectorFlags
-        let parent = node.parent;
-        if let Some(parent_id) = parent {
-            let parent = &mut self.nodes[parent_id];
-            if let Some(mut data) = parent.stylo_data.get_mut() {
-                data.hint |= RestyleHint::restyle_subtree();
-            }
-        }
-
-        self.nodes[node_id].mark_ancestors_dirty();
+        self.invalidate_attribute(node_id, &name);
 
         let mut old_id = None;
         let mut old_class = None;
@@ -74,6 +90,10 @@ impl Dom {
             old_class = element.attr(local_name!("class")).map(ToOwned::to_owned);
         }
 
+        let opens_details = name.local == local_name!("open")
+            && element.name.local == local_name!("details")
+            && element.attr(local_name!("open")).is_none();
+
         element.attributes.set(name.clone(), value);
 
         let tag = &element.name.local;
@@ -155,6 +175,12 @@ impl Dom {
             self.load_custom_paint_src(node_id);
         } else if (tag, attr) == tag_attr!("link", "href") {
             self.load_linked_stylesheet(node_id);
+        } else if (tag, attr) == tag_attr!("a", "href") || (tag, attr) == tag_attr!("area", "href") {
+            self.update_link_state(node_id);
+        } else if (tag, attr) == tag_attr!("base", "href") {
+            self.update_base_url();
+        } else if (tag, attr) == tag_attr!("iframe", "srcdoc") || (tag, attr) == tag_attr!("iframe", "src") {
+            self.load_iframe(node_id);
         }
 
         let is_form_associated = matches!(
@@ -169,6 +195,10 @@ impl Dom {
         if name.local == local_name!("id") && tag_local.as_ref().is_some_and(|tag| *tag == local_name!("form")) {
             self.reset_all_form_owners();
         }
+
+        if opens_details {
+            self.queue_toggle_event(node_id);
+        }
     }
 
     pub fn clear_attribute(&mut self, node_id: usize, name: QualName) {
@@ -178,21 +208,18 @@ impl Dom {
         let mut should_unload_stylesheet = false;
         let mut should_reset_form_owner = false;
         let mut should_reset_all_form_owners = false;
+        let mut closes_details = false;
+        let mut closes_modal = false;
+        let mut should_update_link_state = false;
+        let mut should_update_base_url = false;
+        let mut should_reload_iframe = false;
         let mut removed_class_value: Option<String> = None;
         let node_in_doc = self.nodes[node_id].flags.is_in_document();
 
         {
+            self.invalidate_attribute(node_id, &name);
             let node = &mut self.nodes[node_id];
 
-            if let Some(mut data) = node.stylo_data.get_mut() {
-                data.hint |= RestyleHint::restyle_subtree();
-                data.damage.insert(ALL_DAMAGE);
-            }
-
-            // Mark ancestors dirty so the style traversal visits this subtree.
-            // Without this, the traversal may skip nodes with pending RestyleHint/damage.
-            node.mark_ancestors_dirty();
-
             let Some(element) = node.element_data_mut() else {
                 return;
             };
@@ -240,6 +267,11 @@ impl Dom {
 
             should_recompute_canvas = tag == local_name!("canvas") && attr == local_name!("src");
             should_unload_stylesheet = tag == local_name!("link") && attr == local_name!("href");
+            should_update_link_state = attr == local_name!("href") && matches!(tag.as_ref(), "a" | "area");
+            should_update_base_url = node_in_doc && attr == local_name!("href") && tag == local_name!("base");
+            should_reload_iframe = node_in_doc
+                && tag == local_name!("iframe")
+                && matches!(attr.as_ref(), "srcdoc" | "src");
 
             let is_form_associated = matches!(
                 tag.as_ref(),
@@ -247,6 +279,8 @@ impl Dom {
             );
             should_reset_form_owner = name.local == local_name!("form") && is_form_associated;
             should_reset_all_form_owners = name.local == local_name!("id") && tag == local_name!("form");
+            closes_details = node_in_doc && tag == local_name!("details") && attr == local_name!("open");
+            closes_modal = node.flags.is_modal() && attr == local_name!("open");
         }
 
         if should_recompute_canvas {
@@ -255,12 +289,27 @@ impl Dom {
         if should_unload_stylesheet {
             self.unload_stylesheet(node_id);
         }
+        if should_update_link_state {
+            self.update_link_state(node_id);
+        }
+        if should_update_base_url {
+            self.update_base_url();
+        }
+        if should_reload_iframe {
+            self.load_iframe(node_id);
+        }
         if should_reset_form_owner {
             self.reset_form_owner(node_id);
         }
         if should_reset_all_form_owners {
             self.reset_all_form_owners();
         }
+        if closes_details {
+            self.queue_toggle_event(node_id);
+        }
+        if closes_modal {
+            self.set_modal(node_id, false);
+        }
 
         if node_in_doc {
             if let Some(removed_classes) = removed_class_value.as_deref() {
@@ -0,0 +1,169 @@
+use blitz_traits::shell::ShellProvider;
+use markup5ever::local_name;
+use crate::autofill::{AutofillField, AutofillProfile};
+use crate::dom::Dom;
+use crate::dom::traverse::TreeTraverser;
+use crate::events::{BlitzInputEvent, DomEvent, DomEventData};
+use crate::js::bindings::event_listeners::fire_js_event_on_chain;
+use crate::js::runtime::RUNTIME;
+use crate::shell_provider::ShellProviderMessage;
+
+impl Dom {
+    /// What an editable text field is for, from its `autocomplete` attribute or, failing that, its
+    /// type, name, id and placeholder
+    pub(crate) fn autofill_field_kind(&self, node_id: usize) -> Option<AutofillField> {
+        let element = self.get_node(node_id)?.element_data()?;
+        element.text_input_data()?;
+        if element.has_attr(local_name!("disabled")) || element.has_attr(local_name!("readonly")) {
+            return None;
+        }
+        let input_type = match element.name.local.as_ref() {
+            "input" => element.attr(local_name!("type")).unwrap_or("text").to_ascii_lowercase(),
+            "textarea" => "text".to_string(),
+            _ => return None,
+        };
+
+        if let Some(field) = element.attr(local_name!("autocomplete")).and_then(AutofillField::from_autocomplete) {
+            return Some(field);
+        }
+        let hints = [local_name!("name"), local_name!("id"), local_name!("placeholder"), local_name!("aria-label")]
+            .into_iter()
+            .filter_map(|attr| element.attr(attr))
+            .collect::<Vec<_>>()
+            .join(" ");
+        AutofillField::guess(&input_type, &hints)
+    }
+
+    /// The username field (if there is one) and password field of the login form owned by
+    /// `form_id`, or among the formless controls when it is `None`. The username field is the last
+    /// text field before the password.
+    fn login_fields(&self, form_id: Option<usize>) -> Option<(Option<usize>, usize)> {
+        let mut username = None;
+        for node_id in TreeTraverser::new(self) {
+            if self.controls_to_form.get(&node_id).copied() != form_id {
+                continue;
+            }
+            let Some(element) = self.nodes[node_id].element_data() else {
+                continue;
+            };
+            if element.name.local.as_ref() != "input" || element.text_input_data().is_none() {
+                continue;
+            }
+            match element.attr(local_name!("type")).unwrap_or("text").to_ascii_lowercase().as_str() {
+                "password" => return Some((username, node_id)),
+                "text" | "email" | "tel" => username = Some(node_id),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn control_value(&self, node_id: usize) -> String {
+        self.nodes[node_id]
+            .element_data()
+            .and_then(|element| element.text_input_data())
+            .map(|input_data| input_data.editor.raw_text().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Tell the parent where a newly focused fillable field is so it can offer saved logins or
+    /// profiles
+    pub(crate) fn notify_autofill_focus(&self, node_id: usize) {
+        let node = &self.nodes[node_id];
+        let position = node.absolute_position(0.0, 0.0);
+        let scale = self.viewport.scale();
+        let (x, y) = ((position.x - self.viewport_scroll.x as f32) * scale, (position.y - self.viewport_scroll.y as f32) * scale);
+        let (width, height) = (node.final_layout.size.width * scale, node.final_layout.size.height * scale);
+
+        let form_id = self.controls_to_form.get(&node_id).copied();
+        let message = match self.login_fields(form_id) {
+            Some((username, password)) if node_id == password || username == Some(node_id) => {
+                ShellProviderMessage::LoginField { x, y, width, height }
+            }
+            _ if self.autofill_field_kind(node_id).is_some() => ShellProviderMessage::AutofillField { x, y, width, height },
+            _ => ShellProviderMessage::AutofillFieldBlurred,
+        };
+        let _ = self.shell_provider.sender.send(message);
+    }
+
+    /// Offer to save the login a form is submitting with, if it has a filled in password field
+    pub(crate) fn notify_login_submitted(&self, form_id: usize) {
+        let Some((username, password)) = self.login_fields(Some(form_id)) else {
+            return;
+        };
+        let password = self.control_value(password);
+        if password.trim().is_empty() {
+            return;
+        }
+        let username = username.map(|id| self.control_value(id).trim().to_string()).unwrap_or_default();
+        let _ = self.shell_provider.sender.send(ShellProviderMessage::LoginSubmitted { username, password });
+    }
+
+    /// Fill the focused field's login form with a username and password saved for `origin`. Nothing
+    /// is filled once the page has left that origin.
+    pub(crate) fn fill_login(&mut self, origin: &str, username: &str, password: &str) {
+        if self.url.origin().ascii_serialization() != origin {
+            return;
+        }
+        let Some(focus_id) = self.focus_node_id else {
+            return;
+        };
+        let Some((username_id, password_id)) = self.login_fields(self.controls_to_form.get(&focus_id).copied()) else {
+            return;
+        };
+        let mut values = vec![(password_id, password.to_string())];
+        if let Some(username_id) = username_id {
+            values.push((username_id, username.to_string()));
+        }
+        self.set_control_values(values);
+    }
+
+    /// Fill the empty fields of the focused field's form (or, outside a form, every formless
+    /// field) from `profile`, firing `input` events for the ones that changed
+    pub(crate) fn autofill(&mut self, profile: &AutofillProfile) {
+        let Some(focus_id) = self.focus_node_id else {
+            return;
+        };
+        let form_id = self.controls_to_form.get(&focus_id).copied();
+        let candidates = self.nodes.iter()
+            .map(|(node_id, _)| node_id)
+            .filter(|node_id| self.controls_to_form.get(node_id).copied() == form_id)
+            .filter_map(|node_id| Some((node_id, self.autofill_field_kind(node_id)?)))
+            .collect::<Vec<_>>();
+
+        let values = candidates.into_iter()
+            .map(|(node_id, field)| (node_id, profile.value(field)))
+            .filter(|(node_id, value)| !value.is_empty() && self.control_value(*node_id).trim().is_empty())
+            .collect();
+        self.set_control_values(values);
+    }
+
+    /// Set the text of form controls the way typing would, firing an `input` event for each
+    fn set_control_values(&mut self, values: Vec<(usize, String)>) {
+        let mut filled = Vec::new();
+        for (node_id, value) in values {
+            let Some(input_data) = self.nodes[node_id].element_data_mut().and_then(|element| element.text_input_data_mut()) else {
+                continue;
+            };
+            input_data.set_text(&mut self.font_ctx.lock().unwrap(), &mut self.layout_ctx, &value);
+            filled.push((node_id, value));
+        }
+        if filled.is_empty() {
+            return;
+        }
+
+        RUNTIME.with(|cell| {
+            if let Some(rt_ptr) = *cell.borrow() {
+                let rt = unsafe { &mut *rt_ptr };
+                for (node_id, value) in filled {
+                    let chain = self.node_chain(node_id);
+                    let event = DomEvent::new(node_id, DomEventData::Input(BlitzInputEvent { value }));
+                    rt.do_with_jsapi(|cx, global| unsafe {
+                        fire_js_event_on_chain(cx, global.get(), &chain, &event);
+                    });
+                }
+            }
+        });
+        self.shell_provider.request_redraw();
+    }
+}
@@ -0,0 +1,88 @@
+use blitz_traits::shell::ColorScheme;
+use markup5ever::local_name;
+use style::invalidation::element::restyle_hints::RestyleHint;
+use style::values::specified::color::ColorSchemeFlags;
+use crate::dom::Dom;
+use crate::site_settings::ColorSchemeOverride;
+
+/// Dark variants of the UA stylesheet, layered over DEFAULT_CSS while the used color scheme is dark
+pub(crate) const DEFAULT_DARK_CSS: &str = include_str!("../../assets/default-dark.css");
+
+impl Dom {
+    /// Color schemes the page says it can be rendered in: the root element's `color-scheme`
+    /// property, or `<meta name="color-scheme">` when that is `normal`
+    fn supported_color_schemes(&self) -> ColorSchemeFlags {
+        let root_element = self.root_node().children.iter().copied().find(|&id| self.nodes[id].is_element());
+        let declared = root_element
+            .and_then(|id| self.nodes[id].primary_styles())
+            .map(|style| style.clone_color_scheme().bits)
+            .unwrap_or_default();
+        if !declared.is_empty() {
+            return declared;
+        }
+
+        let content = self.candidate_nodes_for_tag("meta").into_iter().find_map(|id| {
+            let meta = self.nodes.get(id)?;
+            let name = meta.attr(local_name!("name"))?;
+            name.eq_ignore_ascii_case("color-scheme")
+                .then(|| meta.attr(local_name!("content")).unwrap_or_default().to_ascii_lowercase())
+        });
+        let Some(content) = content else {
+            return ColorSchemeFlags::empty();
+        };
+        let mut flags = ColorSchemeFlags::empty();
+        for token in content.split_ascii_whitespace() {
+            match token {
+                "light" => flags |= ColorSchemeFlags::LIGHT,
+                "dark" => flags |= ColorSchemeFlags::DARK,
+                "only" => flags |= ColorSchemeFlags::ONLY,
+                _ => {}
+            }
+        }
+        flags
+    }
+
+    /// The scheme the UA defaults are drawn in. The preferred scheme (viewport.color_scheme, which
+    /// already includes any per-site override) only applies to pages that support it, unless the
+    /// user forced dark for this site.
+    fn compute_used_color_scheme(&self) -> ColorScheme {
+        match self.color_scheme_override {
+            ColorSchemeOverride::Light => return ColorScheme::Light,
+            ColorSchemeOverride::Dark => return ColorScheme::Dark,
+            ColorSchemeOverride::Auto => {}
+        }
+
+        let supported = self.supported_color_schemes();
+        let prefers_dark = self.viewport.color_scheme == ColorScheme::Dark;
+        let dark_only = supported.contains(ColorSchemeFlags::DARK) && !supported.contains(ColorSchemeFlags::LIGHT);
+        if dark_only || (prefers_dark && supported.contains(ColorSchemeFlags::DARK)) {
+            ColorScheme::Dark
+        } else {
+            ColorScheme::Light
+        }
+    }
+
+    /// Swap the dark UA stylesheet in or out after styles were resolved. Returns true when the
+    /// used scheme changed and styles have to be flushed again.
+    pub(crate) fn update_used_color_scheme(&mut self) -> bool {
+        let used = self.compute_used_color_scheme();
+        if used == self.used_color_scheme {
+            return false;
+        }
+        self.used_color_scheme = used;
+        match used {
+            ColorScheme::Dark => self.add_stylesheet(DEFAULT_DARK_CSS),
+            ColorScheme::Light => self.remove_stylesheet(DEFAULT_DARK_CSS),
+        }
+        self.restyle_root_subtree();
+        true
+    }
+
+    /// Mark the whole document for restyling, e.g. after the media environment changed
+    pub(crate) fn restyle_root_subtree(&mut self) {
+        let root_element = self.root_node().children.iter().copied().find(|&id| self.nodes[id].is_element());
+        if let Some(root_element) = root_element {
+            self.nodes[root_element].set_restyle_hint(RestyleHint::restyle_subtree());
+        }
+    }
+}
@@ -10,10 +10,14 @@ use crate::engine::js_provider::StokesJsProvider;
 pub struct DomConfig {
     pub viewport: Option<Viewport>,
     pub base_url: Option<String>,
+    /// Resolve relative URLs against this rather than `base_url` when there is no `<base href>`
+    pub fallback_base_url: Option<String>,
     pub stylesheets: Option<Vec<String>>,
     pub net_provider: Option<Arc<StokesNetProvider>>,
     pub shell_provider: Option<Arc<StokesShellProvider>>,
     pub nav_provider: Option<Arc<StokesNavigationProvider>>,
     pub js_provider: Option<Arc<StokesJsProvider>>,
     pub font_ctx: Option<FontContext>,
+    /// Show placeholders instead of fetching images
+    pub block_images: bool,
 }
\ No newline at end of file
@@ -71,12 +71,13 @@ impl Dom {
         // have changed, then we should clear it's layout cache.
         if damage.intersects(ONLY_RELAYOUT | CONSTRUCT_BOX) {
             node.cache.clear();
+            self.resolve_stats.relaid_out += 1;
             if let Some(inline_layout) = node
                 .data
                 .element_mut()
                 .and_then(|el| el.inline_layout_data.as_mut())
             {
-                inline_layout.content_widths = None;
+                inline_layout.invalidate_measurements();
             }
             damage.remove(ONLY_RELAYOUT);
         }
@@ -215,6 +216,7 @@ pub(crate) fn compute_layout_damage(old: &ComputedValues, new: &ComputedValues)
             || old_text.overflow_wrap != new_text.overflow_wrap
             || old_text.letter_spacing != new_text.letter_spacing
             || old_text.word_spacing != new_text.word_spacing
+            || old_text.tab_size != new_text.tab_size
             || old_text.text_rendering != new_text.text_rendering)
         {
             return true;
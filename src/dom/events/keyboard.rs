@@ -31,13 +31,7 @@ pub(crate) fn handle_keypress<F: FnMut(DomEvent)>(
             if let Key::Character(c) = &event.key {
                 if c.to_lowercase() == "c" {
                     // Check if we have a text selection (and no focused text input)
-                    let has_focused_text_input = doc.focus_node_id.is_some_and(|id| {
-                        doc.get_node(id)
-                            .and_then(|n| n.element_data())
-                            .is_some_and(|e| e.text_input_data().is_some())
-                    });
-
-                    if !has_focused_text_input {
+                    if !has_focused_text_input(doc) {
                         if let Some(text) = doc.get_selected_text() {
                             let _ = doc.shell_provider.set_clipboard_text(text);
                             return;
@@ -48,6 +42,12 @@ pub(crate) fn handle_keypress<F: FnMut(DomEvent)>(
         }
     }
 
+    if event.state.is_pressed() && !has_focused_text_input(doc) {
+        if let Some((dx, dy)) = keyboard_scroll_delta(doc, &event) {
+            doc.smooth_scroll_by(None, dx, dy);
+        }
+    }
+
     let Some(node_id) = doc.focus_node_id else {
         if should_forward_keypress(&event) {
             dispatch_event(DomEvent::new(target, DomEventData::KeyPress(event.clone())));
@@ -106,6 +106,41 @@ const ACTION_MOD: Modifiers = Modifiers::SUPER;
 #[cfg(not(target_os = "macos"))]
 const ACTION_MOD: Modifiers = Modifiers::CONTROL;
 
+fn has_focused_text_input(doc: &Dom) -> bool {
+    doc.focus_node_id.is_some_and(|id| {
+        doc.get_node(id)
+            .and_then(|n| n.element_data())
+            .is_some_and(|e| e.text_input_data().is_some())
+    })
+}
+
+/// How far a scrolling key moves the viewport, in `scroll_by`'s convention (positive scrolls
+/// towards the top/left)
+fn keyboard_scroll_delta(doc: &Dom, event: &BlitzKeyEvent) -> Option<(f64, f64)> {
+    const LINE: f64 = 40.0;
+    let scale = doc.viewport.scale() as f64;
+    let viewport_height = doc.viewport.window_size.1 as f64 / scale;
+    // Keep a little of the previous page in view when paging
+    let page = (viewport_height * 0.875).max(LINE);
+    let shift = event.modifiers.contains(Modifiers::SHIFT);
+
+    match &event.key {
+        Key::ArrowDown => Some((0.0, -LINE)),
+        Key::ArrowUp => Some((0.0, LINE)),
+        Key::ArrowRight => Some((-LINE, 0.0)),
+        Key::ArrowLeft => Some((LINE, 0.0)),
+        Key::PageDown => Some((0.0, -page)),
+        Key::PageUp => Some((0.0, page)),
+        Key::Character(c) if c == " " => Some((0.0, if shift { page } else { -page })),
+        Key::Home => Some((0.0, doc.viewport_scroll.y)),
+        Key::End => {
+            let content_height = doc.root_element().final_layout.size.height as f64;
+            Some((0.0, doc.viewport_scroll.y - (content_height - viewport_height).max(0.0)))
+        }
+        _ => None,
+    }
+}
+
 fn should_forward_keypress(event: &BlitzKeyEvent) -> bool {
     if !event.state.is_pressed() {
         return false;
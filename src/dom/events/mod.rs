@@ -2,6 +2,7 @@ pub mod pointer;
 pub mod focus;
 pub mod keyboard;
 mod ime;
+mod touch;
 
 // Event system for DOM nodes using mozjs
 use crate::dom::{Dom, DomNode};
@@ -14,6 +15,7 @@ use blitz_traits::shell::ShellProvider;
 use crate::dom::events::ime::handle_ime_event;
 use crate::dom::events::keyboard::handle_keypress;
 use crate::dom::events::pointer::{handle_click, handle_pointerdown, handle_pointermove, handle_pointerup, handle_wheel};
+use crate::dom::events::touch::{touch_event_for, TouchPhase};
 use crate::events::{BlitzPointerEvent, BlitzPointerId, BlitzWheelDelta, BlitzWheelEvent, DomEvent, DomEventData, EventState, UiEvent};
 
 impl Dom {
@@ -43,6 +45,7 @@ impl Dom {
                     target_node_id,
                     event.client_x(),
                     event.client_y(),
+                    event.button,
                     event.mods,
                     &mut dispatch_event,
                 );
@@ -125,6 +128,12 @@ impl Dom {
             DomEventData::FocusOut(_) => {
                 // Do nothing (no default action)
             }
+            DomEventData::TouchStart(_)
+            | DomEventData::TouchMove(_)
+            | DomEventData::TouchEnd(_)
+            | DomEventData::TouchCancel(_) => {
+                // Do nothing (the pointer events of the same fingers carry the default actions)
+            }
         }
     }
 }
@@ -858,6 +867,7 @@ impl<'doc, Handler: EventHandler> EventDriver<'doc, Handler> {
                 hover_node_id = self.handle_pointer_move(event);
                 self.doc.active_node();
                 self.doc.set_mousedown_node_id(hover_node_id);
+                self.doc.note_user_activation();
             }
             UiEvent::PointerUp(event) => {
                 hover_node_id = self.handle_pointer_move(event);
@@ -867,6 +877,9 @@ impl<'doc, Handler: EventHandler> EventDriver<'doc, Handler> {
                     should_clear_hover = true;
                 }
             }
+            UiEvent::KeyDown(_) => {
+                self.doc.note_user_activation();
+            }
             _ => {}
         };
 
@@ -886,6 +899,7 @@ impl<'doc, Handler: EventHandler> EventDriver<'doc, Handler> {
                 self.handle_pointer_event(
                     target,
                     data,
+                    TouchPhase::Move,
                     DomEventData::PointerMove,
                     DomEventData::MouseMove,
                 );
@@ -894,6 +908,7 @@ impl<'doc, Handler: EventHandler> EventDriver<'doc, Handler> {
                 self.handle_pointer_event(
                     target,
                     data,
+                    TouchPhase::End,
                     DomEventData::PointerUp,
                     DomEventData::MouseUp,
                 );
@@ -902,6 +917,7 @@ impl<'doc, Handler: EventHandler> EventDriver<'doc, Handler> {
                 self.handle_pointer_event(
                     target,
                     data,
+                    TouchPhase::Start,
                     DomEventData::PointerDown,
                     DomEventData::MouseDown,
                 );
@@ -935,6 +951,7 @@ impl<'doc, Handler: EventHandler> EventDriver<'doc, Handler> {
         &mut self,
         target: usize,
         data: BlitzPointerEvent,
+        touch_phase: TouchPhase,
         make_ptr_data: impl FnOnce(BlitzPointerEvent) -> DomEventData,
         make_mouse_data: impl FnOnce(BlitzPointerEvent) -> DomEventData,
     ) {
@@ -942,10 +959,23 @@ impl<'doc, Handler: EventHandler> EventDriver<'doc, Handler> {
         let mut event_state = EventState::default();
         event_state = self.run_handler_event(&mut ptr_event, event_state);
         if !event_state.is_cancelled() && data.is_mouse() {
-            let mut mouse_event = DomEvent::new(target, make_mouse_data(data));
+            let mut mouse_event = DomEvent::new(target, make_mouse_data(data.clone()));
             event_state = self.run_handler_event(&mut mouse_event, event_state);
         }
-        if !event_state.is_cancelled() {
+
+        // Fingers fire touch events too. Cancelling a touchstart keeps its touch from scrolling
+        // or clicking; cancelling a later touch event only skips that event's default action.
+        let mut touch_cancelled = false;
+        if let Some(mut touch_event) = touch_event_for(self.doc, touch_phase, &data, target) {
+            touch_cancelled = self.run_handler_event(&mut touch_event, EventState::default()).is_cancelled();
+            if touch_cancelled && touch_phase == TouchPhase::Start {
+                self.doc.touch_prevented = true;
+            }
+        }
+        // Only the first finger scrolls, selects and clicks; further ones are for gestures
+        let runs_default = !data.is_finger() || (data.is_primary && !self.doc.touch_prevented);
+
+        if !event_state.is_cancelled() && !touch_cancelled && runs_default {
             self.run_default_action(&mut ptr_event);
         }
         self.process_queue();
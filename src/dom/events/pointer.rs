@@ -8,8 +8,11 @@ use blitz_traits::navigation::{NavigationOptions, NavigationProvider};
 use blitz_traits::shell::ShellProvider;
 use keyboard_types::Modifiers;
 use markup5ever::local_name;
+use crate::clipboard::{self, Selection};
 use crate::dom::Dom;
+use crate::dom::link_opening::LinkOpening;
 use crate::dom::node::SpecialElementData;
+use crate::dom::scrollbar::ScrollbarDrag;
 use crate::events::{BlitzInputEvent, BlitzPointerEvent, BlitzPointerId, BlitzWheelDelta, BlitzWheelEvent, DomEvent, DomEventData, MouseEventButton, MouseEventButtons};
 use super::focus::generate_focus_events;
 
@@ -21,10 +24,23 @@ pub(crate) struct FlingState {
     pub(crate) y_velocity: f64,
 }
 
+/// An animated scroll (see dom/scroll.rs). `dx`/`dy` is the whole distance and `applied_*` how
+/// much of it has been scrolled so far.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SmoothScrollState {
+    pub(crate) target: Option<usize>,
+    pub(crate) start_time: f64,
+    pub(crate) dx: f64,
+    pub(crate) dy: f64,
+    pub(crate) applied_x: f64,
+    pub(crate) applied_y: f64,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum ScrollAnimationState {
     None,
     Fling(FlingState),
+    Smooth(SmoothScrollState),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -50,6 +66,8 @@ pub(crate) enum DragMode {
     Selecting,
     /// We are currently panning the document with a drag (probably touch)
     Panning(PanState),
+    /// We are currently dragging a scrollbar's thumb
+    Scrollbar(ScrollbarDrag),
 }
 
 impl DragMode {
@@ -142,7 +160,13 @@ pub(crate) fn handle_pointermove<F: FnMut(DomEvent)>(
 ) -> bool {
     let x = event.client_x();
     let y = event.client_y();
-    let buttons = event.buttons;
+    // Dragging with the middle button held neither selects nor pans
+    let buttons = event.buttons.difference(MouseEventButtons::Auxiliary);
+
+    if let DragMode::Scrollbar(drag) = &doc.drag_mode {
+        let drag = drag.clone();
+        return doc.drag_scrollbar(&drag, x, y);
+    }
 
     let mut changed = doc.set_hover_client(x, y);
 
@@ -175,6 +199,10 @@ pub(crate) fn handle_pointermove<F: FnMut(DomEvent)>(
 
         let target = state.target;
         let (dx, dy) = state.update(time_ms, event.screen_x(), event.screen_y());
+        // With a second finger down the gesture is a pinch, which the browser turns into zoom
+        if doc.touches.len() > 1 {
+            return false;
+        }
 
         let has_changed = doc.scroll_by(Some(target), dx, dy, &mut dispatch_event);
         return has_changed;
@@ -251,9 +279,17 @@ pub(crate) fn handle_pointerdown(
     _target: usize,
     x: f32,
     y: f32,
+    button: MouseEventButton,
     mods: Modifiers,
     dispatch_event: &mut dyn FnMut(DomEvent),
 ) {
+    // The middle button pastes the primary selection into text inputs and leaves any selection
+    // as it is
+    if button == MouseEventButton::Auxiliary {
+        paste_primary_selection(doc, x, y, dispatch_event);
+        return;
+    }
+
     // Compute click count using the previous mousedown position (before updating)
     // This handles both double-click detection and text input word/line selection
     // TODO: For text inputs, only increment click count if click maps to the same/similar caret position
@@ -275,6 +311,13 @@ pub(crate) fn handle_pointerdown(
     doc.drag_mode = DragMode::None;
     doc.scroll_animation = ScrollAnimationState::None;
 
+    // Scrollbars are above the content: pressing one drags its thumb instead
+    if let Some(scrollbar) = doc.hit_scrollbar(x, y) {
+        let drag = doc.start_scrollbar_drag(scrollbar, x, y);
+        doc.drag_mode = DragMode::Scrollbar(drag);
+        return;
+    }
+
     let Some(hit) = doc.hit_client(x, y) else {
         // Clear text selection when clicking outside any element
         doc.clear_text_selection();
@@ -375,6 +418,76 @@ pub(crate) fn handle_pointerdown(
     }
 }
 
+/// Insert the primary selection into the text input at client point (`x`, `y`), if there is one,
+/// with the caret where the input was clicked
+fn paste_primary_selection(doc: &mut Dom, x: f32, y: f32, dispatch_event: &mut dyn FnMut(DomEvent)) {
+    let Some(hit) = doc.hit_client(x, y) else {
+        return;
+    };
+    let node = &doc.nodes[hit.node_id];
+    let Some(el) = node.data.element() else {
+        return;
+    };
+    let Some(text_input_data) = el.text_input_data() else {
+        return;
+    };
+    if el.has_attr(local_name!("disabled")) || el.has_attr(local_name!("readonly")) {
+        return;
+    }
+    let Some(text) = clipboard::get_text(Selection::Primary) else {
+        return;
+    };
+
+    let mut content_box_offset = taffy::Point {
+        x: node.final_layout.padding.left + node.final_layout.border.left,
+        y: node.final_layout.padding.top + node.final_layout.border.top,
+    };
+    if !text_input_data.is_multiline {
+        let layout = text_input_data.editor.try_layout().unwrap();
+        let content_box_height = node.final_layout.content_box_height();
+        let input_height = layout.height() / layout.scale();
+        content_box_offset.y += ((content_box_height - input_height) / 2.0).max(0.0);
+    }
+    let tx = (hit.x - content_box_offset.x) as f64 * doc.viewport.scale_f64();
+    let ty = (hit.y - content_box_offset.y) as f64 * doc.viewport.scale_f64();
+
+    generate_focus_events(
+        doc,
+        &mut |doc| {
+            doc.set_focus_to(hit.node_id);
+        },
+        dispatch_event,
+    );
+
+    let el = doc.nodes[hit.node_id].data.element_mut().unwrap();
+    let SpecialElementData::TextInput(ref mut text_input_data) = el.special_data else {
+        return;
+    };
+    let mut font_ctx = doc.font_ctx.lock().unwrap();
+    let mut driver = text_input_data.editor.driver(&mut font_ctx, &mut doc.layout_ctx);
+    driver.move_to_point(tx as f32, ty as f32);
+    driver.insert_or_replace_selection(&text);
+    drop(font_ctx);
+
+    let value = text_input_data.editor.raw_text().to_string();
+    dispatch_event(DomEvent::new(hit.node_id, DomEventData::Input(BlitzInputEvent { value })));
+    doc.shell_provider.request_redraw();
+}
+
+/// What the user has selected, in the focused text input or else the page, for the primary
+/// selection
+fn selected_text(doc: &Dom) -> Option<String> {
+    let focused_input = doc
+        .focus_node_id
+        .and_then(|id| doc.get_node(id))
+        .and_then(|node| node.element_data())
+        .and_then(|el| el.text_input_data());
+    match focused_input {
+        Some(input) => input.editor.selected_text().map(str::to_owned),
+        None => doc.get_selected_text(),
+    }
+}
+
 pub(crate) fn handle_pointerup<F: FnMut(DomEvent)>(
     doc: &mut Dom,
     target: usize,
@@ -385,8 +498,8 @@ pub(crate) fn handle_pointerup<F: FnMut(DomEvent)>(
     // locally for use within this function
     let drag_mode = doc.drag_mode.take();
 
-    // Don't dispatch click if we were doing a text selection drag or panning
-    // the document with a touch
+    // Don't dispatch click if we were doing a text selection drag, panning
+    // the document with a touch or dragging a scrollbar
     let do_click = drag_mode == DragMode::None;
 
     let time_ms = SystemTime::now()
@@ -401,6 +514,19 @@ pub(crate) fn handle_pointerup<F: FnMut(DomEvent)>(
         }
     }
 
+    // Selecting text with the mouse, by dragging or by double or triple clicking, makes it the
+    // primary selection
+    if event.button == MouseEventButton::Main && (drag_mode == DragMode::Selecting || doc.quick_clicks > 1) {
+        if let Some(text) = selected_text(doc) {
+            clipboard::set_text(Selection::Primary, &text);
+        }
+    }
+
+    // Let the released thumb go back to its resting colour
+    if matches!(drag_mode, DragMode::Scrollbar(_)) {
+        doc.shell_provider.request_redraw();
+    }
+
     // Dispatch a click event
     if do_click && matches!(event.button, MouseEventButton::Main | MouseEventButton::Auxiliary) {
         dispatch_event(DomEvent::new(target, DomEventData::Click(event.clone())));
@@ -498,16 +624,26 @@ pub(crate) fn handle_click(
                 local_name!("a") => {
                     if let Some(href) = el.attr(local_name!("href")) {
                         if let Some(url) = doc.url.resolve_relative(href) {
+                            let force_new_tab = event.button == MouseEventButton::Auxiliary
+                                || event.mods.contains(Modifiers::CONTROL);
+                            let opening = LinkOpening::for_link(
+                                el.attr(local_name!("rel")),
+                                el.attr(local_name!("target")),
+                                force_new_tab,
+                                &doc.url,
+                                &url,
+                            );
+                            if !opening.new_tab && !opening.referrer {
+                                doc.net_provider.omit_referrer_for_navigation(&url);
+                            }
                             let options = NavigationOptions::new(
                                 url,
                                 String::from("text/plain"),
                                 doc.id(),
                             );
 
-                            if event.button == MouseEventButton::Auxiliary
-                                || event.mods.contains(Modifiers::CONTROL)
-                            {
-                                doc.nav_provider.navigate_to_in_new_tab(options);
+                            if opening.new_tab {
+                                doc.nav_provider.navigate_to_in_new_tab(options, opening.opener);
                             } else {
                                 doc.nav_provider.navigate_to(options);
                             }
@@ -517,6 +653,12 @@ pub(crate) fn handle_click(
                     } else {
                     }
                 }
+                // Clicking a details' summary opens or closes it
+                local_name!("summary") => {
+                    if doc.activate_summary(node_id) {
+                        break 'matched true;
+                    }
+                }
                 local_name!("button") if el.is_submit_button() => {
                     if let Some(form_owner) = doc.controls_to_form.get(&node_id) {
                         doc.submit_form_with_event(*form_owner, node_id);
@@ -596,18 +738,23 @@ pub(crate) fn handle_wheel<F: FnMut(DomEvent)>(
     event: BlitzWheelEvent,
     mut dispatch_event: F,
 ) {
-    let (scroll_x, scroll_y) = match event.delta {
-        BlitzWheelDelta::Lines(x, y) => (x * 40.0, y * 50.0),
-        BlitzWheelDelta::Pixels(x, y) => (x, y),
-    };
-
-    let has_changed = doc.scroll_by(
-        doc.get_hover_node_id(),
-        scroll_x,
-        scroll_y,
-        &mut dispatch_event,
-    );
-    if has_changed {
-        doc.shell_provider.request_redraw();
+    match event.delta {
+        // Mouse wheel notches are animated
+        BlitzWheelDelta::Lines(x, y) => {
+            doc.smooth_scroll_by(doc.get_hover_node_id(), x * 40.0, y * 50.0);
+        }
+        // Touchpads already deliver many small, pixel-precise deltas, so follow them directly
+        BlitzWheelDelta::Pixels(x, y) => {
+            doc.scroll_animation = ScrollAnimationState::None;
+            let has_changed = doc.scroll_by(
+                doc.get_hover_node_id(),
+                x,
+                y,
+                &mut dispatch_event,
+            );
+            if has_changed {
+                doc.shell_provider.request_redraw();
+            }
+        }
     }
 }
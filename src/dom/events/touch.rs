@@ -0,0 +1,58 @@
+// Touch events for pages that listen for them. Fingers arrive as pointer events; each also
+// becomes a touchstart, touchmove or touchend carrying every finger still on the page.
+use crate::dom::Dom;
+use crate::events::{BlitzPointerEvent, BlitzPointerId, BlitzTouch, BlitzTouchEvent, DomEvent, DomEventData};
+
+/// The stage of a touch a pointer event reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TouchPhase {
+    Start,
+    Move,
+    End,
+}
+
+/// Record what `event`, a finger's pointer event, did to the fingers on the page and return the
+/// touch event to dispatch for it. `target` is the node under the finger, which only matters for
+/// a finger that just landed.
+pub(crate) fn touch_event_for(doc: &mut Dom, phase: TouchPhase, event: &BlitzPointerEvent, target: usize) -> Option<DomEvent> {
+    let BlitzPointerId::Finger(identifier) = event.id else {
+        return None;
+    };
+    let changed = match phase {
+        TouchPhase::Start => {
+            // A finger that lifted without us hearing of it is replaced
+            doc.touches.retain(|touch| touch.identifier != identifier);
+            if doc.touches.is_empty() {
+                doc.touch_prevented = false;
+            }
+            let touch = BlitzTouch { identifier, target, coords: event.coords, force: event.details.pressure };
+            doc.touches.push(touch.clone());
+            touch
+        }
+        TouchPhase::Move => {
+            let touch = doc.touches.iter_mut().find(|touch| touch.identifier == identifier)?;
+            touch.coords = event.coords;
+            touch.force = event.details.pressure;
+            touch.clone()
+        }
+        TouchPhase::End => {
+            let index = doc.touches.iter().position(|touch| touch.identifier == identifier)?;
+            let mut touch = doc.touches.remove(index);
+            touch.coords = event.coords;
+            touch
+        }
+    };
+
+    let target = changed.target;
+    let touch_event = BlitzTouchEvent {
+        touches: doc.touches.clone(),
+        changed_touches: vec![changed],
+        mods: event.mods,
+    };
+    let data = match phase {
+        TouchPhase::Start => DomEventData::TouchStart(touch_event),
+        TouchPhase::Move => DomEventData::TouchMove(touch_event),
+        TouchPhase::End => DomEventData::TouchEnd(touch_event),
+    };
+    Some(DomEvent::new(target, data))
+}
@@ -0,0 +1,198 @@
+// Find in page. The query is looked for in the text of the document's inline layouts, in document
+// order and ignoring case. The current match is scrolled into view and painted in orange; in
+// "highlight all" mode the other matches are painted in yellow, and each gets a tick on the
+// viewport's scrollbar at its place in the document so the user can see where the rest are.
+use std::collections::HashMap;
+use kurbo::Rect;
+use parley::{Affinity, Cursor, Selection};
+use crate::dom::Dom;
+use crate::dom::scroll::{ScrollAlignment, ScrollBehavior};
+
+/// One occurrence of the query: a byte range of an inline root's text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FindMatch {
+    pub node: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The document's find-in-page session
+#[derive(Debug, Default)]
+pub struct FindState {
+    query: String,
+    matches: Vec<FindMatch>,
+    /// Index of the current match
+    active: Option<usize>,
+    highlight_all: bool,
+}
+
+impl Dom {
+    /// Search for `query` and move to its next match, or the previous one when `forward` is false.
+    /// A new query starts at the first match from the top of the viewport down. Returns the
+    /// current match's index and how many there are.
+    pub(crate) fn find_in_page(&mut self, query: &str, forward: bool, highlight_all: bool) -> (Option<usize>, usize) {
+        // Search again each time, since the document may have changed since the last step
+        let mut roots = Vec::new();
+        if let Some(root) = self.try_root_element() {
+            self.collect_all_inline_roots_in_subtree(root.id, &mut roots);
+        }
+        let matches: Vec<FindMatch> = roots
+            .into_iter()
+            .filter_map(|node| {
+                let text = &self.get_node(node)?.element_data()?.inline_layout_data.as_ref()?.text;
+                Some(find_all(text, query).into_iter().map(move |(start, end)| FindMatch { node, start, end }))
+            })
+            .flatten()
+            .collect();
+
+        let count = matches.len();
+        let active = if count == 0 {
+            None
+        } else if self.find.query != query || self.find.active.is_none() {
+            let top = self.viewport_scroll.y;
+            let below = matches.iter().position(|m| self.find_match_rect(m).is_some_and(|rect| rect.y0 >= top));
+            Some(below.unwrap_or(0))
+        } else {
+            let current = self.find.active.unwrap_or(0).min(count - 1);
+            Some(if forward { (current + 1) % count } else { (current + count - 1) % count })
+        };
+
+        self.find = FindState { query: query.to_string(), matches, active, highlight_all };
+        if let Some(rect) = active.and_then(|index| self.find_match_rect(&self.find.matches[index])) {
+            self.scroll_to_find_match(rect);
+        }
+        self.shell_provider.request_redraw();
+        (active, count)
+    }
+
+    /// Switch between painting every match and only the current one
+    pub(crate) fn set_find_highlight_all(&mut self, highlight_all: bool) {
+        if self.find.highlight_all != highlight_all {
+            self.find.highlight_all = highlight_all;
+            self.shell_provider.request_redraw();
+        }
+    }
+
+    /// End the find session, clearing its highlights
+    pub(crate) fn stop_finding(&mut self) {
+        if !self.find.matches.is_empty() {
+            self.shell_provider.request_redraw();
+        }
+        self.find = FindState::default();
+    }
+
+    /// The matches to paint, by inline root: each one's byte range and whether it is the current one
+    pub(crate) fn find_highlights(&self) -> HashMap<usize, Vec<(usize, usize, bool)>> {
+        let mut highlights: HashMap<usize, Vec<(usize, usize, bool)>> = HashMap::new();
+        for (m, current) in self.shown_find_matches() {
+            highlights.entry(m.node).or_default().push((m.start, m.end, current));
+        }
+        highlights
+    }
+
+    /// Where the painted matches are down the document, as fractions of its height, for ticks on
+    /// the scrollbar; and whether each is the current match
+    pub(crate) fn find_markers(&self) -> Vec<(f64, bool)> {
+        let Some(root) = self.try_root_element() else {
+            return Vec::new();
+        };
+        let viewport_height = self.viewport.window_size.1 as f64 / self.viewport.scale() as f64;
+        let content_height = (root.final_layout.size.height as f64).max(viewport_height);
+        let mut markers: Vec<(f64, bool)> = self
+            .shown_find_matches()
+            .filter_map(|(m, current)| Some((self.find_match_rect(m)?.center().y / content_height, current)))
+            .collect();
+        // The current match's tick goes on top
+        markers.sort_by_key(|&(_, current)| current);
+        markers
+    }
+
+    /// The matches that are painted, and whether each is the current one. Text that changed
+    /// since the search may no longer have a match where it was, which is then left out.
+    fn shown_find_matches(&self) -> impl Iterator<Item = (&FindMatch, bool)> {
+        self.find.matches.iter().enumerate().filter_map(|(index, m)| {
+            let current = self.find.active == Some(index);
+            let text = &self.get_node(m.node)?.element_data()?.inline_layout_data.as_ref()?.text;
+            let valid = m.end <= text.len() && text.is_char_boundary(m.start) && text.is_char_boundary(m.end);
+            (valid && (current || self.find.highlight_all)).then_some((m, current))
+        })
+    }
+
+    /// The match's bounding box in page coordinates, CSS px, taking in every line it wraps over
+    fn find_match_rect(&self, m: &FindMatch) -> Option<Rect> {
+        let node = self.get_node(m.node)?;
+        let layout = &node.element_data()?.inline_layout_data.as_ref()?.layout;
+        let scale = layout.scale() as f64;
+        let anchor = Cursor::from_byte_index(layout, m.start, Affinity::Downstream);
+        let focus = Cursor::from_byte_index(layout, m.end, Affinity::Downstream);
+        let mut bounds: Option<Rect> = None;
+        Selection::new(anchor, focus).geometry_with(layout, |rect, _line_idx| {
+            let rect = Rect::new(rect.x0, rect.y0, rect.x1, rect.y1);
+            bounds = Some(bounds.map_or(rect, |bounds| bounds.union(rect)));
+        });
+        let bounds = bounds?;
+
+        let position = node.absolute_position(0.0, 0.0);
+        let edges = node.final_layout.padding + node.final_layout.border;
+        let x = (position.x + edges.left) as f64;
+        let y = (position.y + edges.top) as f64;
+        Some(Rect::new(
+            x + bounds.x0 / scale,
+            y + bounds.y0 / scale,
+            x + bounds.x1 / scale,
+            y + bounds.y1 / scale,
+        ))
+    }
+
+    /// Bring a match into view, centered, unless it is already in view
+    fn scroll_to_find_match(&mut self, rect: Rect) {
+        let scale = self.viewport.scale() as f64;
+        let viewport = Rect::from_origin_size(
+            (self.viewport_scroll.x, self.viewport_scroll.y),
+            (self.viewport.window_size.0 as f64 / scale, self.viewport.window_size.1 as f64 / scale),
+        );
+        if viewport.intersect(rect) == rect {
+            return;
+        }
+        self.scroll_rect_into_view(rect, ScrollAlignment::Center, ScrollAlignment::Nearest, ScrollBehavior::Instant);
+    }
+}
+
+/// The byte ranges of `text` that match `query`, ignoring case and with any whitespace in the
+/// query matching any whitespace character
+fn find_all(text: &str, query: &str) -> Vec<(usize, usize)> {
+    let fold = |c: char| if c.is_whitespace() { ' ' } else { c.to_lowercase().next().unwrap_or(c) };
+    let query: Vec<char> = query.chars().map(fold).collect();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let chars: Vec<(usize, char)> = text.char_indices().map(|(index, c)| (index, fold(c))).collect();
+
+    let mut matches = Vec::new();
+    let mut i = 0;
+    while i + query.len() <= chars.len() {
+        if chars[i..i + query.len()].iter().map(|&(_, c)| c).eq(query.iter().copied()) {
+            let end = chars.get(i + query.len()).map_or(text.len(), |&(index, _)| index);
+            matches.push((chars[i].0, end));
+            i += query.len();
+        } else {
+            i += 1;
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_all;
+
+    #[test]
+    fn matches_ignore_case_and_whitespace_kind() {
+        assert_eq!(find_all("Find the FIND in find", "find"), vec![(0, 4), (9, 13), (17, 21)]);
+        assert_eq!(find_all("one\u{a0}two", "one two"), vec![(0, 8)]);
+        assert_eq!(find_all("Ünïcode ünïcode", "ÜNÏ"), vec![(0, 5), (10, 15)]);
+        assert!(find_all("anything", "").is_empty());
+        // Matches don't overlap
+        assert_eq!(find_all("aaaa", "aa"), vec![(0, 2), (2, 4)]);
+    }
+}
@@ -0,0 +1,258 @@
+// Web font loading. Each `@font-face` the document fetches is tracked from the moment its fetch
+// starts, so script can watch it through `document.fonts` and text set in it follows the face's
+// `font-display`: hidden during the block period (so it doesn't flash in a fallback font), drawn
+// in the fallback during the swap period and replaced once the font arrives, and past the swap
+// period kept in the fallback for good, a font that arrives later going unused.
+use std::time::{Duration, Instant};
+use style::font_face::FontDisplay as StyloFontDisplay;
+use style::values::computed::font::{FontFamily, SingleFontFamily};
+use crate::dom::Dom;
+
+/// Block period of `font-display: swap`, `fallback` and `optional`, long enough for a cached font
+const SHORT_BLOCK_PERIOD: Duration = Duration::from_millis(100);
+/// Block period of `font-display: block`, and `auto`, which browsers treat the same way
+const LONG_BLOCK_PERIOD: Duration = Duration::from_secs(3);
+/// Swap period of `font-display: fallback`
+const FALLBACK_SWAP_PERIOD: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FontDisplay {
+    #[default]
+    Auto,
+    Block,
+    Swap,
+    Fallback,
+    Optional,
+}
+
+impl FontDisplay {
+    pub(crate) fn from_stylo(display: Option<&StyloFontDisplay>) -> Self {
+        match display {
+            None | Some(StyloFontDisplay::Auto) => Self::Auto,
+            Some(StyloFontDisplay::Block) => Self::Block,
+            Some(StyloFontDisplay::Swap) => Self::Swap,
+            Some(StyloFontDisplay::Fallback) => Self::Fallback,
+            Some(StyloFontDisplay::Optional) => Self::Optional,
+        }
+    }
+
+    /// How long text in the font is hidden while it loads
+    pub fn block_period(self) -> Duration {
+        match self {
+            Self::Auto | Self::Block => LONG_BLOCK_PERIOD,
+            Self::Swap | Self::Fallback | Self::Optional => SHORT_BLOCK_PERIOD,
+        }
+    }
+
+    /// How long after the block period the font still replaces the fallback when it arrives, or
+    /// `None` for however long it takes
+    pub fn swap_period(self) -> Option<Duration> {
+        match self {
+            Self::Auto | Self::Block | Self::Swap => None,
+            Self::Fallback => Some(FALLBACK_SWAP_PERIOD),
+            Self::Optional => Some(Duration::ZERO),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Block => "block",
+            Self::Swap => "swap",
+            Self::Fallback => "fallback",
+            Self::Optional => "optional",
+        }
+    }
+}
+
+/// A `FontFace`'s status, as script sees it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontFaceStatus {
+    Loading,
+    Loaded,
+    Error,
+}
+
+impl FontFaceStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Loading => "loading",
+            Self::Loaded => "loaded",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// One `@font-face` the document fetched
+#[derive(Debug, Clone)]
+pub struct FontFaceLoad {
+    pub family: String,
+    pub url: String,
+    pub display: FontDisplay,
+    pub status: FontFaceStatus,
+    started: Instant,
+    /// Whether the block period ended with the font still loading, so its text shows in the fallback
+    blocking_over: bool,
+}
+
+impl FontFaceLoad {
+    fn block_ends(&self) -> Instant {
+        self.started + self.display.block_period()
+    }
+
+    /// Whether a font arriving at `now` is still swapped in
+    fn swappable(&self, now: Instant) -> bool {
+        self.display.swap_period().is_none_or(|swap| now < self.block_ends() + swap)
+    }
+
+    fn blocks_text(&self) -> bool {
+        self.status == FontFaceStatus::Loading && !self.blocking_over
+    }
+}
+
+/// The document's web fonts and where their loads are
+#[derive(Debug, Default)]
+pub struct FontLoads {
+    faces: Vec<FontFaceLoad>,
+    /// Bumped when a face is added or its status changes, for script to catch up with
+    generation: u64,
+}
+
+impl FontLoads {
+    /// Track a face whose fetch just started. A face the document already has is left as it is.
+    pub fn start(&mut self, family: String, url: String, display: FontDisplay, now: Instant) {
+        if self.faces.iter().any(|face| face.url == url && face.family.eq_ignore_ascii_case(&family)) {
+            return;
+        }
+        self.faces.push(FontFaceLoad {
+            family,
+            url,
+            display,
+            status: FontFaceStatus::Loading,
+            started: now,
+            blocking_over: false,
+        });
+        self.generation += 1;
+    }
+
+    /// Record the font at `url` as loaded, or as failed. Returns whether it arrived in time to be
+    /// used, which a failed font never is.
+    pub fn finish(&mut self, url: &str, loaded: bool, now: Instant) -> bool {
+        let mut used = false;
+        for face in self.faces.iter_mut().filter(|face| face.url == url && face.status == FontFaceStatus::Loading) {
+            face.status = if loaded { FontFaceStatus::Loaded } else { FontFaceStatus::Error };
+            used |= loaded && face.swappable(now);
+        }
+        self.generation += 1;
+        used
+    }
+
+    /// End the block periods that are over at `now`. Returns whether any did, so text they hid
+    /// shows in its fallback font.
+    pub fn end_block_periods(&mut self, now: Instant) -> bool {
+        let mut ended = false;
+        for face in self.faces.iter_mut().filter(|face| face.blocks_text() && now >= face.block_ends()) {
+            face.blocking_over = true;
+            ended = true;
+        }
+        ended
+    }
+
+    /// When the next block period ends
+    pub fn next_block_end(&self) -> Option<Instant> {
+        self.faces.iter().filter(|face| face.blocks_text()).map(FontFaceLoad::block_ends).min()
+    }
+
+    /// Whether text in `families` is hidden: the first family that can be drawn from is a web font
+    /// still in its block period
+    pub fn hides(&self, families: &FontFamily) -> bool {
+        for family in families.families.iter() {
+            let SingleFontFamily::FamilyName(name) = family else {
+                return false;
+            };
+            let name: &str = name.name.as_ref();
+            let mut faces = self.faces.iter().filter(|face| face.family.eq_ignore_ascii_case(name)).peekable();
+            if faces.peek().is_none() {
+                // A local font
+                return false;
+            }
+            let mut usable = false;
+            for face in faces {
+                if face.blocks_text() {
+                    return true;
+                }
+                usable |= face.status == FontFaceStatus::Loaded;
+            }
+            if usable {
+                return false;
+            }
+            // Every face of the family failed or is past its block period, so the next family is used
+        }
+        false
+    }
+
+    pub fn faces(&self) -> &[FontFaceLoad] {
+        &self.faces
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+impl Dom {
+    /// Show text hidden by web fonts whose block period ran out before they loaded
+    pub(crate) fn end_font_block_periods(&mut self) {
+        if self.font_loads.end_block_periods(Instant::now()) {
+            self.shell_provider.request_redraw();
+        }
+    }
+
+    /// When `end_font_block_periods` will have something to do
+    pub(crate) fn font_block_period_due(&self) -> Option<Instant> {
+        self.font_loads.next_block_end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://example.com/font.woff2";
+
+    fn loads(display: FontDisplay, started: Instant) -> FontLoads {
+        let mut loads = FontLoads::default();
+        loads.start("Web Font".into(), URL.into(), display, started);
+        loads
+    }
+
+    #[test]
+    fn text_is_hidden_only_during_the_block_period() {
+        let started = Instant::now();
+        let mut loads = loads(FontDisplay::Swap, started);
+        assert_eq!(loads.next_block_end(), Some(started + SHORT_BLOCK_PERIOD));
+        assert!(!loads.end_block_periods(started + Duration::from_millis(50)));
+        assert!(loads.end_block_periods(started + SHORT_BLOCK_PERIOD));
+        assert_eq!(loads.next_block_end(), None);
+
+        // Swap fonts are used however late they are
+        assert!(loads.finish(URL, true, started + Duration::from_secs(60)));
+        assert_eq!(loads.faces()[0].status, FontFaceStatus::Loaded);
+    }
+
+    #[test]
+    fn late_fonts_are_dropped_after_the_swap_period() {
+        let started = Instant::now();
+        let mut fallback = loads(FontDisplay::Fallback, started);
+        assert!(!fallback.finish(URL, true, started + SHORT_BLOCK_PERIOD + FALLBACK_SWAP_PERIOD));
+
+        let mut optional = loads(FontDisplay::Optional, started);
+        assert!(optional.finish(URL, true, started + Duration::from_millis(10)));
+        let mut optional = loads(FontDisplay::Optional, started);
+        assert!(!optional.finish(URL, true, started + SHORT_BLOCK_PERIOD));
+
+        let mut failed = loads(FontDisplay::Block, started);
+        assert!(!failed.finish(URL, false, started));
+        assert_eq!(failed.faces()[0].status, FontFaceStatus::Error);
+    }
+}
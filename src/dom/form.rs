@@ -123,6 +123,7 @@ impl Dom {
             return;
         };
 
+        self.notify_login_submitted(node_id);
         let entry = construct_entry_list(self, node_id, submitter_id);
 
         let method = get_form_attr(
@@ -135,6 +136,12 @@ impl Dom {
             .and_then(|method| method.parse::<FormMethod>().ok())
             .unwrap_or(FormMethod::Get);
 
+        // https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#submit-dialog
+        if method == FormMethod::Dialog {
+            self.close_form_dialog(node_id, submitter_id);
+            return;
+        }
+
         let action = get_form_attr(
             self,
             element,
@@ -211,6 +218,23 @@ impl Dom {
 
         self.nav_provider.navigate_to(navigation_options)
     }
+
+    /// Close the dialog a `method="dialog"` form is in, with the submit button's value as its
+    /// returnValue. The dialog's state lives in its JS shim, which also fires `close`.
+    fn close_form_dialog(&self, form_id: usize, submitter_id: usize) {
+        let Some(dialog_id) = AncestorTraverser::new(self, form_id).find(|id| {
+            self.nodes[*id].data.is_element_with_tag_name(&local_name!("dialog"))
+        }) else {
+            return;
+        };
+        let return_value = (submitter_id != form_id)
+            .then(|| self.nodes[submitter_id].attr(local_name!("value")))
+            .flatten();
+        let return_value = serde_json::to_string(&return_value).unwrap_or_else(|_| "null".to_string());
+        self.js_provider.execute_script(format!(
+            "if (typeof __stokesCloseDialog === 'function') {{ __stokesCloseDialog({dialog_id}, {return_value}); }}"
+        ));
+    }
 }
 
 /// Constructs a list of form entries from form controls
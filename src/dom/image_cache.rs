@@ -0,0 +1,280 @@
+// Decoded images of a document, shared by every <img> and background image that uses them.
+//
+// Decoded pixels are large, so the cache has a memory budget. When it is exceeded the least
+// recently used images are dropped, except those used by something within a screen of the
+// viewport. Their nodes let go of the pixels too and are remembered, and the image is fetched and
+// decoded again once one of them scrolls near the viewport.
+
+use std::collections::{HashMap, HashSet};
+use blitz_traits::net::Request;
+use markup5ever::local_name;
+use crate::dom::node::{SpecialElementData, Status};
+use crate::dom::{Dom, ImageData};
+use crate::networking::{ImageHandler, ImageType, ResourceHandler};
+
+pub const DEFAULT_IMAGE_MEMORY_BUDGET: usize = 256 * 1024 * 1024;
+
+struct Entry {
+    image: ImageData,
+    bytes: usize,
+    last_used: u64,
+}
+
+pub(crate) struct ImageCache {
+    entries: HashMap<String, Entry>,
+    budget: usize,
+    used: usize,
+    clock: u64,
+}
+
+impl Default for ImageCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_IMAGE_MEMORY_BUDGET)
+    }
+}
+
+impl ImageCache {
+    pub(crate) fn new(budget: usize) -> Self {
+        Self { entries: HashMap::new(), budget, used: 0, clock: 0 }
+    }
+
+    pub(crate) fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+    }
+
+    /// Bytes of decoded image data held by the cache
+    pub(crate) fn used_bytes(&self) -> usize {
+        self.used
+    }
+
+    pub(crate) fn is_over_budget(&self) -> bool {
+        self.used > self.budget
+    }
+
+    pub(crate) fn get(&mut self, url: &str) -> Option<ImageData> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(url)?;
+        entry.last_used = self.clock;
+        Some(entry.image.clone())
+    }
+
+    pub(crate) fn insert(&mut self, url: String, image: ImageData) {
+        self.clock += 1;
+        let bytes = image_bytes(&image);
+        self.used += bytes;
+        let entry = Entry { image, bytes, last_used: self.clock };
+        if let Some(old) = self.entries.insert(url, entry) {
+            self.used -= old.bytes;
+        }
+    }
+
+    /// Number of decoded images held
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Drop every decoded image, when the document is torn down
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.used = 0;
+    }
+
+    pub(crate) fn remove(&mut self, url: &str) {
+        if let Some(old) = self.entries.remove(url) {
+            self.used -= old.bytes;
+        }
+    }
+
+    /// Least recently used images to drop to get back within budget, skipping `keep`
+    fn eviction_candidates(&self, keep: impl Fn(&str) -> bool) -> Vec<String> {
+        let mut entries: Vec<_> = self.entries.iter()
+            .filter(|(url, _)| !keep(url))
+            .map(|(url, entry)| (entry.last_used, entry.bytes, url))
+            .collect();
+        entries.sort_unstable_by_key(|&(last_used, _, _)| last_used);
+
+        let mut used = self.used;
+        let mut evict = Vec::new();
+        for (_, bytes, url) in entries {
+            if used <= self.budget {
+                break;
+            }
+            used -= bytes;
+            evict.push(url.clone());
+        }
+        evict
+    }
+}
+
+/// Memory an image takes once decoded. SVGs count as the bitmap they are rasterized into.
+fn image_bytes(image: &ImageData) -> usize {
+    match image {
+        ImageData::Raster(data) => data.data.len(),
+        ImageData::Svg(tree) => {
+            let size = tree.size();
+            (size.width() * size.height() * 4.0) as usize
+        }
+        ImageData::Evicted { .. } | ImageData::Blocked(_) | ImageData::None => 0,
+    }
+}
+
+impl Dom {
+    /// Drop least recently used images until the cache is back within its budget
+    pub(crate) fn evict_images(&mut self) {
+        if !self.image_cache.is_over_budget() {
+            return;
+        }
+        let users = self.image_users();
+        let near_viewport: HashSet<&str> = users.iter()
+            .filter(|(_, node_id, _)| self.is_near_viewport(*node_id))
+            .map(|(url, _, _)| url.as_str())
+            .collect();
+        let evict = self.image_cache.eviction_candidates(|url| near_viewport.contains(url));
+        if evict.is_empty() {
+            return;
+        }
+
+        let evict: HashSet<String> = evict.into_iter().collect();
+        for url in &evict {
+            self.image_cache.remove(url);
+        }
+        for (url, node_id, image_type) in users {
+            if !evict.contains(&url) {
+                continue;
+            }
+            let Some(element) = self.nodes[node_id].element_data_mut() else {
+                continue;
+            };
+            match image_type {
+                ImageType::Image => {
+                    if let SpecialElementData::Image(image) = &mut element.special_data {
+                        **image = image.evicted();
+                    }
+                }
+                ImageType::Background(idx) => {
+                    if let Some(Some(background)) = element.background_images.get_mut(idx) {
+                        background.image = background.image.evicted();
+                        background.status = Status::Loading;
+                    }
+                }
+            }
+            self.evicted_images.entry(url).or_default().push((node_id, image_type));
+        }
+        tracing::debug!(
+            "Evicted {} images, {} of {} bytes in use",
+            evict.len(), self.image_cache.used_bytes(), self.image_cache.budget,
+        );
+    }
+
+    /// Fetch evicted images again once a node using them comes near the viewport
+    pub(crate) fn reload_evicted_images(&mut self) {
+        if self.evicted_images.is_empty() {
+            return;
+        }
+        let reload: Vec<String> = self.evicted_images.iter()
+            .filter(|(_, users)| users.iter().any(|(node_id, _)| self.is_near_viewport(*node_id)))
+            .map(|(url, _)| url.clone())
+            .collect();
+
+        for url in reload {
+            let Some(users) = self.evicted_images.remove(&url) else {
+                continue;
+            };
+            let Ok(parsed) = url::Url::parse(&url) else {
+                continue;
+            };
+            let Some(&(_, handler_type)) = users.first() else {
+                continue;
+            };
+            let already_pending = self.pending_images.contains_key(&url);
+            self.pending_images.entry(url).or_default().extend(users);
+            if already_pending {
+                continue;
+            }
+            self.net_provider.fetch_image(
+                self.id(),
+                Request::get(parsed),
+                ResourceHandler::boxed(
+                    self.tx.clone(),
+                    self.id(),
+                    None,
+                    self.shell_provider.clone(),
+                    ImageHandler::new(handler_type),
+                ),
+            );
+        }
+    }
+
+    /// Every node showing a loaded image, with the image's URL
+    fn image_users(&self) -> Vec<(String, usize, ImageType)> {
+        let mut users = Vec::new();
+        for (node_id, node) in self.nodes.iter() {
+            let Some(element) = node.element_data() else {
+                continue;
+            };
+            if let SpecialElementData::Image(image) = &element.special_data {
+                if image.is_loaded() {
+                    let src = element.attr(local_name!("src")).and_then(|src| self.url.resolve_relative(src));
+                    if let Some(src) = src {
+                        users.push((src.to_string(), node_id, ImageType::Image));
+                    }
+                }
+            }
+            for (idx, background) in element.background_images.iter().enumerate() {
+                if let Some(background) = background {
+                    if background.image.is_loaded() {
+                        users.push((background.url.as_str().to_string(), node_id, ImageType::Background(idx)));
+                    }
+                }
+            }
+        }
+        users
+    }
+
+    /// Whether a node is laid out within one viewport height of the visible area
+    fn is_near_viewport(&self, node_id: usize) -> bool {
+        let Some(node) = self.nodes.get(node_id) else {
+            return false;
+        };
+        let height = self.viewport.window_size.1 as f64 / self.viewport.scale_f64();
+        let top = node.absolute_position(0.0, 0.0).y as f64;
+        let bottom = top + node.final_layout.size.height as f64;
+        let visible_top = self.viewport_scroll.y;
+        bottom >= visible_top - height && top <= visible_top + 2.0 * height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ImageCache;
+    use crate::dom::ImageData;
+    use crate::dom::node::RasterImageData;
+    use std::sync::Arc;
+
+    fn image(bytes: usize) -> ImageData {
+        ImageData::Raster(RasterImageData::new(1, 1, Arc::new(vec![0; bytes])))
+    }
+
+    #[test]
+    fn evicts_least_recently_used_until_within_budget() {
+        let mut cache = ImageCache::new(250);
+        cache.insert("a".into(), image(100));
+        cache.insert("b".into(), image(100));
+        cache.insert("c".into(), image(100));
+        cache.get("a");
+
+        assert!(cache.is_over_budget());
+        assert_eq!(cache.eviction_candidates(|_| false), vec!["b".to_string()]);
+        assert_eq!(cache.eviction_candidates(|url| url == "b"), vec!["c".to_string()]);
+    }
+
+    #[test]
+    fn replacing_an_entry_keeps_accounting_right() {
+        let mut cache = ImageCache::new(1000);
+        cache.insert("a".into(), image(100));
+        cache.insert("a".into(), image(40));
+        assert_eq!(cache.used_bytes(), 40);
+        cache.remove("a");
+        assert_eq!(cache.used_bytes(), 0);
+    }
+}
@@ -0,0 +1,129 @@
+// Interactive elements: the details disclosure widget and dialogs shown modally in the top layer
+use blitz_traits::events::HitResult;
+use blitz_traits::shell::ShellProvider;
+use markup5ever::local_name;
+use style::invalidation::element::restyle_hints::RestyleHint;
+use crate::dom::Dom;
+use crate::dom::node::DomNodeFlags;
+use crate::qual_name;
+
+impl Dom {
+    /// Open or close the details element a `<summary>` belongs to, when it is that details'
+    /// first summary child. Returns whether the click toggled anything.
+    pub(crate) fn activate_summary(&mut self, summary_id: usize) -> bool {
+        let Some(details_id) = self.nodes[summary_id].parent else {
+            return false;
+        };
+        let details = &self.nodes[details_id];
+        if !details.data.is_element_with_tag_name(&local_name!("details")) {
+            return false;
+        }
+        let first_summary = details
+            .children
+            .iter()
+            .copied()
+            .find(|id| self.nodes[*id].data.is_element_with_tag_name(&local_name!("summary")));
+        if first_summary != Some(summary_id) {
+            return false;
+        }
+
+        if details.attr(local_name!("open")).is_some() {
+            self.clear_attribute(details_id, qual_name!("open"));
+        } else {
+            self.set_attribute(details_id, qual_name!("open"), "");
+        }
+        self.shell_provider.request_redraw();
+        true
+    }
+
+    /// Queue a `toggle` event for a details element that opened or closed. Like scroll events
+    /// they are fired once per frame, so toggling twice in one task still gives one event.
+    pub(crate) fn queue_toggle_event(&mut self, target: usize) {
+        if !self.pending_toggle_events.contains(&target) {
+            self.pending_toggle_events.push(target);
+        }
+    }
+
+    /// The details elements toggled since the last frame, with whether each is open now
+    pub(crate) fn take_toggle_events(&mut self) -> Vec<(usize, bool)> {
+        std::mem::take(&mut self.pending_toggle_events)
+            .into_iter()
+            .filter_map(|id| {
+                let node = self.get_node(id)?;
+                Some((id, node.attr(local_name!("open")).is_some()))
+            })
+            .collect()
+    }
+
+    /// Put a dialog in the top layer as the topmost modal, or take it out. Modal dialogs are
+    /// painted above everything else over a backdrop, centred in the viewport, and while one is
+    /// open the rest of the page doesn't receive pointer events.
+    pub(crate) fn set_modal(&mut self, node_id: usize, modal: bool) {
+        let Some(node) = self.nodes.get_mut(node_id) else {
+            return;
+        };
+        if node.flags.is_modal() != modal {
+            node.flags.set(DomNodeFlags::IS_MODAL, modal);
+            // `:modal` changes the dialog's position and size
+            node.set_restyle_hint(RestyleHint::restyle_subtree());
+        }
+        self.top_layer.retain(|id| *id != node_id);
+        if modal {
+            self.top_layer.push(node_id);
+        }
+        self.shell_provider.request_redraw();
+    }
+
+    /// The modal dialogs still in the document, bottom first
+    pub(crate) fn modal_dialogs(&self) -> impl Iterator<Item = usize> + '_ {
+        self.top_layer.iter().copied().filter(|id| {
+            self.get_node(*id)
+                .is_some_and(|node| node.flags.is_modal() && node.flags.is_in_document())
+        })
+    }
+
+    /// Centre each modal dialog in the viewport. Layout places it in its containing block like
+    /// any fixed box; the top layer isn't part of that, so its location is moved after layout
+    /// to where it is painted and hit tested.
+    pub(crate) fn place_top_layer(&mut self) {
+        let scale = self.viewport.scale() as f64;
+        let viewport_width = self.viewport.window_size.0 as f64 / scale;
+        let viewport_height = self.viewport.window_size.1 as f64 / scale;
+        let scroll = self.viewport_scroll;
+
+        let modals: Vec<usize> = self.modal_dialogs().collect();
+        for id in modals {
+            let node = &self.nodes[id];
+            let size = node.final_layout.size;
+            let x = scroll.x + ((viewport_width - size.width as f64) / 2.0).max(0.0);
+            let y = scroll.y + ((viewport_height - size.height as f64) / 2.0).max(0.0);
+            let (parent_x, parent_y) = match node.layout_parent.get() {
+                Some(parent_id) => {
+                    let parent = &self.nodes[parent_id];
+                    let origin = parent.page_border_origin();
+                    (origin.x as f64 - parent.scroll_offset.x, origin.y as f64 - parent.scroll_offset.y)
+                }
+                None => (0.0, 0.0),
+            };
+            let node = &mut self.nodes[id];
+            node.final_layout.location.x = (x - parent_x) as f32;
+            node.final_layout.location.y = (y - parent_y) as f32;
+        }
+    }
+
+    /// Hit test the topmost modal dialog, which makes the rest of the page inert: a point
+    /// outside it lands on the dialog itself, as its backdrop does. `None` without a modal.
+    pub(crate) fn hit_top_layer(&self, x: f32, y: f32) -> Option<HitResult> {
+        let modal = self.modal_dialogs().last()?;
+        let node = &self.nodes[modal];
+        node.hit(x, y).or_else(|| {
+            let origin = node.page_border_origin();
+            Some(HitResult {
+                node_id: modal,
+                x: x - origin.x,
+                y: y - origin.y,
+                is_text: false,
+            })
+        })
+    }
+}
@@ -0,0 +1,306 @@
+// Geometry behind IntersectionObserver: where elements are relative to the viewport and how much
+// of them a root shows. Rects are in CSS pixels relative to the viewport, like
+// getBoundingClientRect().
+use serde::Serialize;
+use style::values::computed::Overflow;
+use crate::dom::Dom;
+
+/// An axis-aligned rectangle in viewport coordinates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub(crate) struct ClientRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl ClientRect {
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self { x, y, width, height }
+    }
+
+    fn right(&self) -> f64 {
+        self.x + self.width
+    }
+
+    fn bottom(&self) -> f64 {
+        self.y + self.height
+    }
+
+    fn area(&self) -> f64 {
+        self.width * self.height
+    }
+
+    /// Grow the rect by root margins given as (top, right, bottom, left); negative margins shrink it
+    pub fn expand(&self, [top, right, bottom, left]: [f64; 4]) -> Self {
+        Self::new(
+            self.x - left,
+            self.y - top,
+            (self.width + left + right).max(0.0),
+            (self.height + top + bottom).max(0.0),
+        )
+    }
+
+    /// The overlap of two rects. Rects that only touch still intersect, with an empty overlap,
+    /// so zero-sized targets sitting inside the root count as visible.
+    pub fn intersect(&self, other: &Self) -> Option<Self> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        (right >= x && bottom >= y).then(|| Self::new(x, y, right - x, bottom - y))
+    }
+}
+
+/// One side of an observer's rootMargin
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum MarginValue {
+    Px(f64),
+    /// Percent of the root's width for left and right, of its height for top and bottom
+    Percent(f64),
+}
+
+impl MarginValue {
+    fn resolve(self, length: f64) -> f64 {
+        match self {
+            Self::Px(px) => px,
+            Self::Percent(percent) => length * percent / 100.0,
+        }
+    }
+
+    fn serialize(self) -> String {
+        match self {
+            Self::Px(px) => format!("{px}px"),
+            Self::Percent(percent) => format!("{percent}%"),
+        }
+    }
+}
+
+/// An observer's rootMargin as (top, right, bottom, left)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct RootMargin([MarginValue; 4]);
+
+impl Default for RootMargin {
+    fn default() -> Self {
+        Self([MarginValue::Px(0.0); 4])
+    }
+}
+
+impl RootMargin {
+    /// Parse a rootMargin like the margin shorthand: one to four lengths in px or percentages,
+    /// where a bare 0 is allowed. `None` for anything else, which the constructor reports as a
+    /// SyntaxError.
+    pub fn parse(text: &str) -> Option<Self> {
+        let values = text
+            .split_ascii_whitespace()
+            .map(|token| {
+                if let Some(number) = token.strip_suffix("px") {
+                    number.parse().ok().map(MarginValue::Px)
+                } else if let Some(number) = token.strip_suffix('%') {
+                    number.parse().ok().map(MarginValue::Percent)
+                } else {
+                    (token.parse::<f64>().ok() == Some(0.0)).then_some(MarginValue::Px(0.0))
+                }
+            })
+            .collect::<Option<Vec<MarginValue>>>()?;
+        let [top, right, bottom, left] = match values[..] {
+            [] => return Some(Self::default()),
+            [all] => [all; 4],
+            [vertical, horizontal] => [vertical, horizontal, vertical, horizontal],
+            [top, horizontal, bottom] => [top, horizontal, bottom, horizontal],
+            [top, right, bottom, left] => [top, right, bottom, left],
+            _ => return None,
+        };
+        [top, right, bottom, left]
+            .iter()
+            .all(|value| matches!(value, MarginValue::Px(n) | MarginValue::Percent(n) if n.is_finite()))
+            .then_some(Self([top, right, bottom, left]))
+    }
+
+    /// The margins in px for a root of the given size, for `ClientRect::expand`
+    pub fn resolve(&self, root: &ClientRect) -> [f64; 4] {
+        let [top, right, bottom, left] = self.0;
+        [top.resolve(root.height), right.resolve(root.width), bottom.resolve(root.height), left.resolve(root.width)]
+    }
+
+    /// The four sides in the form observer.rootMargin reports them
+    pub fn serialize(&self) -> String {
+        self.0.iter().map(|value| value.serialize()).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// One observation of a target, the numbers of an IntersectionObserverEntry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Intersection {
+    pub bounding_client_rect: ClientRect,
+    pub intersection_rect: ClientRect,
+    pub root_bounds: ClientRect,
+    pub is_intersecting: bool,
+    pub intersection_ratio: f64,
+}
+
+impl Intersection {
+    /// Intersect `target` with `root` after the target was clipped to `clip`, the part of it its
+    /// scrolling ancestors leave visible
+    pub fn compute(target: ClientRect, clip: Option<ClientRect>, root: ClientRect) -> Self {
+        let overlap = clip.and_then(|clip| clip.intersect(&root)).and_then(|visible| visible.intersect(&target));
+        let Some(intersection_rect) = overlap else {
+            return Self { bounding_client_rect: target, root_bounds: root, ..Self::default() };
+        };
+        let intersection_ratio = if target.area() > 0.0 {
+            (intersection_rect.area() / target.area()).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        Self {
+            bounding_client_rect: target,
+            intersection_rect,
+            root_bounds: root,
+            is_intersecting: true,
+            intersection_ratio,
+        }
+    }
+}
+
+impl Dom {
+    /// The border box of an element relative to the viewport, or `None` when it isn't in the
+    /// document or isn't rendered
+    pub(crate) fn client_rect(&self, node_id: usize) -> Option<ClientRect> {
+        let node = self.get_node(node_id)?;
+        if !node.flags.is_in_document() {
+            return None;
+        }
+        // Nothing inside a display: none subtree gets a box
+        let mut current = Some(node_id);
+        while let Some(id) = current {
+            let ancestor = self.get_node(id)?;
+            if matches!(ancestor.taffy_style.display, taffy::Display::None) {
+                return None;
+            }
+            current = ancestor.layout_parent.get();
+        }
+        let origin = node.page_border_origin();
+        let size = node.final_layout.size;
+        Some(ClientRect::new(
+            origin.x as f64 - self.viewport_scroll.x,
+            origin.y as f64 - self.viewport_scroll.y,
+            size.width as f64,
+            size.height as f64,
+        ))
+    }
+
+    /// The viewport in its own coordinates
+    pub(crate) fn viewport_client_rect(&self) -> ClientRect {
+        let scale = self.viewport.scale_f64();
+        ClientRect::new(
+            0.0,
+            0.0,
+            self.viewport.window_size.0 as f64 / scale,
+            self.viewport.window_size.1 as f64 / scale,
+        )
+    }
+
+    /// How much of `target` shows inside `root` (an element, or the viewport for `None`) grown by
+    /// `margin`. Ancestors between the two that clip their overflow hide what they cut off.
+    pub(crate) fn intersection(&self, target: usize, root: Option<usize>, margin: &RootMargin) -> Option<Intersection> {
+        let target_rect = self.client_rect(target)?;
+        let root_rect = match root {
+            Some(root) => self.client_rect(root)?,
+            None => self.viewport_client_rect(),
+        };
+        let root_rect = root_rect.expand(margin.resolve(&root_rect));
+
+        let mut clip = Some(target_rect);
+        let mut ancestor = self.get_node(target)?.layout_parent.get();
+        let mut reached_root = root.is_none();
+        while let Some(id) = ancestor {
+            if Some(id) == root {
+                reached_root = true;
+                break;
+            }
+            let Some(node) = self.get_node(id) else {
+                break;
+            };
+            let is_html_or_body = node.data.element().is_some_and(|e| e.name.local.as_ref() == "html" || e.name.local.as_ref() == "body");
+            let clips = node.primary_styles().is_some_and(|styles| {
+                styles.clone_overflow_x() != Overflow::Visible || styles.clone_overflow_y() != Overflow::Visible
+            });
+            if clips && !is_html_or_body {
+                clip = match (clip, self.client_rect(id)) {
+                    (Some(clip), Some(rect)) => clip.intersect(&rect),
+                    _ => None,
+                };
+            }
+            ancestor = node.layout_parent.get();
+        }
+
+        // Targets outside an explicit root are never intersecting it
+        if !reached_root {
+            return Some(Intersection { bounding_client_rect: target_rect, root_bounds: root_rect, ..Intersection::default() });
+        }
+        Some(Intersection::compute(target_rect, clip, root_rect))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClientRect, Intersection, MarginValue, RootMargin};
+
+    #[test]
+    fn ratio_is_the_visible_share_of_the_target() {
+        let root = ClientRect::new(0.0, 0.0, 100.0, 100.0);
+        let target = ClientRect::new(50.0, 80.0, 20.0, 40.0);
+        let entry = Intersection::compute(target, Some(target), root);
+        assert!(entry.is_intersecting);
+        assert_eq!(entry.intersection_rect, ClientRect::new(50.0, 80.0, 20.0, 20.0));
+        assert_eq!(entry.intersection_ratio, 0.5);
+
+        let below = ClientRect::new(0.0, 150.0, 10.0, 10.0);
+        let entry = Intersection::compute(below, Some(below), root);
+        assert!(!entry.is_intersecting);
+        assert_eq!(entry.intersection_ratio, 0.0);
+    }
+
+    #[test]
+    fn edge_adjacent_and_empty_targets_intersect() {
+        let root = ClientRect::new(0.0, 0.0, 100.0, 100.0);
+        let touching = ClientRect::new(0.0, 100.0, 10.0, 10.0);
+        let entry = Intersection::compute(touching, Some(touching), root);
+        assert!(entry.is_intersecting);
+        assert_eq!(entry.intersection_ratio, 0.0);
+
+        let empty = ClientRect::new(10.0, 10.0, 0.0, 0.0);
+        let entry = Intersection::compute(empty, Some(empty), root);
+        assert!(entry.is_intersecting);
+        assert_eq!(entry.intersection_ratio, 1.0);
+    }
+
+    #[test]
+    fn root_margin_grows_the_root_and_clips_hide_the_target() {
+        let root = ClientRect::new(0.0, 0.0, 100.0, 100.0);
+        let margin = RootMargin::parse("0px 0px 50%").unwrap();
+        let root = root.expand(margin.resolve(&root));
+        assert_eq!(root, ClientRect::new(0.0, 0.0, 100.0, 150.0));
+        let target = ClientRect::new(0.0, 120.0, 10.0, 10.0);
+        assert!(Intersection::compute(target, Some(target), root).is_intersecting);
+
+        // A scroller that ends above the target hides it even inside the root
+        let scroller = ClientRect::new(0.0, 0.0, 100.0, 50.0);
+        assert!(!Intersection::compute(target, scroller.intersect(&target), root).is_intersecting);
+    }
+
+    #[test]
+    fn root_margin_parses_like_the_margin_shorthand() {
+        assert_eq!(RootMargin::parse("").unwrap(), RootMargin::default());
+        assert_eq!(RootMargin::parse("0").unwrap().serialize(), "0px 0px 0px 0px");
+        assert_eq!(RootMargin::parse("10px 5%").unwrap().serialize(), "10px 5% 10px 5%");
+        let margin = RootMargin::parse("-20px 0px 200px").unwrap();
+        assert_eq!(margin.0[3], MarginValue::Px(0.0));
+        assert_eq!(margin.resolve(&ClientRect::new(0.0, 0.0, 100.0, 100.0)), [-20.0, 0.0, 200.0, 0.0]);
+
+        assert!(RootMargin::parse("10").is_none());
+        assert!(RootMargin::parse("1em").is_none());
+        assert!(RootMargin::parse("1px 2px 3px 4px 5px").is_none());
+    }
+}
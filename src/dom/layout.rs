@@ -1,5 +1,5 @@
 use crate::dom::damage::{HoistedPaintChild, HoistedPaintChildren, ALL_DAMAGE, CONSTRUCT_BOX, CONSTRUCT_DESCENDENT, CONSTRUCT_FC};
-use crate::dom::node::{BackgroundImageData, DomNodeFlags, ListItemLayout, ListItemLayoutPosition, Marker, NodeKind, SpecialElementData, Status, TextInputData, TextLayout};
+use crate::dom::node::{BackgroundImageData, DomNodeFlags, ListItemLayout, ListItemLayoutPosition, Marker, NodeKind, ShapingInput, ShapingKey, SpecialElementData, Status, TextInputData, TextLayout};
 use crate::dom::{stylo_to_parley, AttributeMap, Dom, DomNode, ElementData, NodeData};
 use crate::layout::table::build_table_context;
 use crate::networking::{parse_svg, ImageHandler, ImageType, ResourceHandler};
@@ -11,12 +11,14 @@ use slab::Slab;
 use std::borrow::Cow;
 use std::cell::RefCell;
 use std::sync::Arc;
-use blitz_traits::net::{NetProvider, Request};
+use blitz_traits::net::Request;
 use style::data::ElementData as StyloElementData;
 use style::properties::generated::longhands::position::computed_value::T as Position;
 use style::selector_parser::RestyleDamage;
 use style::servo::url::ComputedUrl;
 use style::shared_lock::StylesheetGuards;
+use style::computed_values::white_space_collapse::T as StyloWhiteSpaceCollapse;
+use style::properties::ComputedValues;
 use style::values::computed::{Content, ContentItem, Display, Float, Image, PositionProperty, TextTransform};
 use style::values::specified::box_::{DisplayInside, DisplayOutside};
 use style_traits::ToCss;
@@ -29,6 +31,13 @@ thread_local! {
     pub static FONT_CTX: RefCell<Option<Box<FontContext>>> = const { RefCell::new(None) };
 }
 
+/// Drop this thread's inline layout contexts and the font data they cache. They are rebuilt on
+/// the next layout, so this is only worth doing when the thread's documents are gone.
+pub(crate) fn release_layout_contexts() {
+    LAYOUT_CTX.take();
+    FONT_CTX.take();
+}
+
 #[macro_export]
 macro_rules! qual_name {
     ($local:tt $(, $ns:ident)?) => {
@@ -132,6 +141,14 @@ pub(crate) fn collect_layout_children(
             }
         }
 
+        // A frame's content is its nested document, laid out and painted on its own
+        if matches!(tag_name, "iframe") {
+            dom.iter_subtree_mut(node_id, |id: usize, dom: &mut Dom| {
+                dom.nodes[id].remove_damage(CONSTRUCT_BOX | CONSTRUCT_DESCENDENT | CONSTRUCT_FC);
+            });
+            return;
+        }
+
         if matches!(tag_name, "svg") {
             let mut outer_html = dom.get_node(node_id).unwrap().outer_html();
 
@@ -152,9 +169,7 @@ pub(crate) fn collect_layout_children(
                         .special_data = SpecialElementData::Image(Box::new(svg.into()));
                 }
                 Err(e) => {
-                    println!("{node_id} SVG parse failed");
-                    print!("{outer_html}");
-
+                    tracing::debug!("Inline SVG {node_id} that failed to parse: {outer_html}");
                     log::error!("Failed to parse inline SVG: {}", e);
                 }
             };
@@ -620,6 +635,9 @@ fn create_text_editor(dom: &mut Dom, input_element_id: usize, is_multiline: bool
     styles.retain(|_| false);
     styles.insert(StyleProperty::FontSize(parley_style.font_size));
     styles.insert(StyleProperty::LineHeight(parley_style.line_height));
+    styles.insert(StyleProperty::LetterSpacing(parley_style.letter_spacing));
+    styles.insert(StyleProperty::WordSpacing(parley_style.word_spacing));
+    styles.insert(StyleProperty::FontFeatures(parley_style.font_features));
     styles.insert(StyleProperty::Brush(parley_style.brush));
 
     editor.refresh_layout(&mut dom.font_ctx.lock().unwrap(), &mut dom.layout_ctx);
@@ -729,6 +747,7 @@ pub(crate) fn find_inline_layout_embedded_boxes(
 
                         if *tag_name == local_name!("img")
                             || *tag_name == local_name!("svg")
+                            || *tag_name == local_name!("iframe")
                             || *tag_name == local_name!("input")
                             || *tag_name == local_name!("textarea")
                             || *tag_name == local_name!("button")
@@ -811,15 +830,20 @@ pub(crate) fn build_inline_layout(
     let root_line_height = resolve_line_height(parley_style.line_height, parley_style.font_size);
 
     // Create a parley tree builder
-    let mut builder = layout_ctx.tree_builder(font_ctx, scale, true, &parley_style);
+    let mut builder = RecordingTreeBuilder {
+        inputs: vec![ShapingInput::Style(parley_style.clone())],
+        builder: layout_ctx.tree_builder(font_ctx, scale, true, &parley_style),
+    };
 
     // Set whitespace collapsing mode
-    let collapse_mode = root_node_style
+    let white_space = root_node_style
         .as_ref()
-        .map(|s| s.get_inherited_text().white_space_collapse)
-        .map(stylo_to_parley::white_space_collapse)
-        .unwrap_or(WhiteSpaceCollapse::Collapse);
-    builder.set_white_space_mode(collapse_mode);
+        .map(|s| InlineWhiteSpace::from_style(s))
+        .unwrap_or_default();
+    builder.set_white_space_mode(stylo_to_parley::white_space_collapse(white_space.collapse));
+
+    // The column of the next character since the last preserved newline, for expanding tabs
+    let mut tab_column = 0;
 
     let text_transform = root_node_style
         .as_ref()
@@ -842,7 +866,8 @@ pub(crate) fn build_inline_layout(
             nodes,
             inline_context_root_node_id,
             before_id,
-            collapse_mode,
+            white_space,
+            &mut tab_column,
             text_transform,
             root_line_height,
         );
@@ -853,7 +878,8 @@ pub(crate) fn build_inline_layout(
             nodes,
             inline_context_root_node_id,
             child_id,
-            collapse_mode,
+            white_space,
+            &mut tab_column,
             text_transform,
             root_line_height,
         );
@@ -864,7 +890,8 @@ pub(crate) fn build_inline_layout(
             nodes,
             inline_context_root_node_id,
             shadow_root_id,
-            collapse_mode,
+            white_space,
+            &mut tab_column,
             text_transform,
             root_line_height,
         );
@@ -875,21 +902,34 @@ pub(crate) fn build_inline_layout(
             nodes,
             inline_context_root_node_id,
             after_id,
-            collapse_mode,
+            white_space,
+            &mut tab_column,
             text_transform,
             root_line_height,
         );
     }
 
-    text_layout.text = builder.build_into(&mut text_layout.layout);
+    // Shaping is the expensive part, so an inline context whose text, styles and boxes are all
+    // unchanged keeps the layout it already has
+    let shaping_key = ShapingKey {
+        scale,
+        font_generation: crate::dom::FONT_GENERATION.load(std::sync::atomic::Ordering::Relaxed),
+        inputs: builder.inputs,
+    };
+    if text_layout.shaping_key.as_ref() != Some(&shaping_key) {
+        text_layout.text = builder.builder.build_into(&mut text_layout.layout);
+        text_layout.shaping_key = Some(shaping_key);
+        text_layout.invalidate_measurements();
+    }
     return;
 
     fn build_inline_layout_recursive(
-        builder: &mut TreeBuilder<TextBrush>,
+        builder: &mut RecordingTreeBuilder,
         nodes: &Slab<DomNode>,
         parent_id: usize,
         node_id: usize,
-        collapse_mode: WhiteSpaceCollapse,
+        white_space: InlineWhiteSpace,
+        tab_column: &mut usize,
         parent_text_transform: TextTransform,
         root_line_height: f32,
     ) {
@@ -904,10 +944,8 @@ pub(crate) fn build_inline_layout(
             .unwrap_or(TextTransform::NONE);
 
         // Set whitespace collapsing mode
-        let collapse_mode = style
-            .map(|s| s.clone_white_space_collapse())
-            .map(stylo_to_parley::white_space_collapse)
-            .unwrap_or(collapse_mode);
+        let white_space = style.map(|s| InlineWhiteSpace::from_style(s)).unwrap_or(white_space);
+        let collapse_mode = stylo_to_parley::white_space_collapse(white_space.collapse);
         builder.set_white_space_mode(collapse_mode);
 
         match &node.data {
@@ -944,7 +982,8 @@ pub(crate) fn build_inline_layout(
                                 nodes,
                                 parent_id,
                                 child_id,
-                                collapse_mode,
+                                white_space,
+                                tab_column,
                                 text_transform,
                                 root_line_height,
                             );
@@ -955,7 +994,8 @@ pub(crate) fn build_inline_layout(
                                 nodes,
                                 parent_id,
                                 shadow_root_id,
-                                collapse_mode,
+                                white_space,
+                                tab_column,
                                 text_transform,
                                 root_line_height,
                             );
@@ -966,6 +1006,7 @@ pub(crate) fn build_inline_layout(
 
                         if *tag_name == local_name!("img")
                             || *tag_name == local_name!("svg")
+                            || *tag_name == local_name!("iframe")
                             || *tag_name == local_name!("input")
                             || *tag_name == local_name!("textarea")
                             || *tag_name == local_name!("button")
@@ -987,6 +1028,7 @@ pub(crate) fn build_inline_layout(
                             builder.push_text("\n");
                             builder.pop_style_span();
                             builder.set_white_space_mode(collapse_mode);
+                            *tab_column = 0;
                         } else {
                             // node.remove_damage(CONSTRUCT_DESCENDENT | CONSTRUCT_FC | CONSTRUCT_BOX);
                             let mut style = node
@@ -1016,7 +1058,8 @@ pub(crate) fn build_inline_layout(
                                     nodes,
                                     node_id,
                                     before_id,
-                                    collapse_mode,
+                                    white_space,
+                                    tab_column,
                                     text_transform,
                                     root_line_height,
                                 );
@@ -1028,7 +1071,8 @@ pub(crate) fn build_inline_layout(
                                     nodes,
                                     node_id,
                                     child_id,
-                                    collapse_mode,
+                                    white_space,
+                                    tab_column,
                                     text_transform,
                                     root_line_height,
                                 );
@@ -1039,7 +1083,8 @@ pub(crate) fn build_inline_layout(
                                     nodes,
                                     node_id,
                                     after_id,
-                                    collapse_mode,
+                                    white_space,
+                                    tab_column,
                                     text_transform,
                                     root_line_height,
                                 );
@@ -1069,7 +1114,8 @@ pub(crate) fn build_inline_layout(
                         nodes,
                         parent_id,
                         child_id,
-                        collapse_mode,
+                        white_space,
+                        tab_column,
                         text_transform,
                         root_line_height,
                     );
@@ -1080,13 +1126,9 @@ pub(crate) fn build_inline_layout(
                 // dbg!(&data.content);
 
                 // TODO optimize capitalize
-                match parent_text_transform {
-                    TextTransform::UPPERCASE => {
-                        builder.push_text(&text.content.to_uppercase());
-                    }
-                    TextTransform::LOWERCASE => {
-                        builder.push_text(&text.content.to_lowercase());
-                    }
+                let content = match parent_text_transform {
+                    TextTransform::UPPERCASE => Cow::Owned(text.content.to_uppercase()),
+                    TextTransform::LOWERCASE => Cow::Owned(text.content.to_lowercase()),
                     TextTransform::CAPITALIZE => {
                         let text = &text.content;
                         let mut out = String::with_capacity(text.len());
@@ -1102,10 +1144,31 @@ pub(crate) fn build_inline_layout(
                             }
                         }
 
-                        builder.push_text(&out);
+                        Cow::Owned(out)
                     }
-                    _ => {
-                        builder.push_text(&text.content);
+                    _ => Cow::Borrowed(text.content.as_str()),
+                };
+
+                match white_space.collapse {
+                    // pre-line: parley has no mode that keeps newlines but collapses spaces, so
+                    // collapse each line on its own and push the newlines between them preserved
+                    StyloWhiteSpaceCollapse::PreserveBreaks => {
+                        let last_line = content.matches('\n').count();
+                        for (i, line) in content.split('\n').enumerate() {
+                            if i > 0 {
+                                builder.set_white_space_mode(WhiteSpaceCollapse::Preserve);
+                                builder.push_text("\n");
+                                builder.set_white_space_mode(WhiteSpaceCollapse::Collapse);
+                            }
+                            builder.push_text(trim_segment_break_spaces(line, i > 0, i < last_line));
+                        }
+                        builder.set_white_space_mode(collapse_mode);
+                    }
+                    StyloWhiteSpaceCollapse::Preserve | StyloWhiteSpaceCollapse::BreakSpaces => {
+                        builder.push_text(&expand_tabs(&content, white_space.tab_size, tab_column));
+                    }
+                    StyloWhiteSpaceCollapse::Collapse => {
+                        builder.push_text(&content);
                     }
                 }
             }
@@ -1118,6 +1181,109 @@ pub(crate) fn build_inline_layout(
     }
 }
 
+/// parley's tree builder, recording each call so an unchanged inline context can skip shaping
+struct RecordingTreeBuilder<'a> {
+    builder: TreeBuilder<'a, TextBrush>,
+    inputs: Vec<ShapingInput>,
+}
+
+impl RecordingTreeBuilder<'_> {
+    fn push_style_span(&mut self, style: TextStyle<'static, 'static, TextBrush>) {
+        self.inputs.push(ShapingInput::Style(style.clone()));
+        self.builder.push_style_span(style);
+    }
+
+    fn push_style_modification_span(&mut self, properties: &[StyleProperty<'static, TextBrush>]) {
+        debug_assert!(properties.is_empty(), "only empty modification spans are recorded");
+        self.inputs.push(ShapingInput::StyleModification);
+        self.builder.push_style_modification_span(properties);
+    }
+
+    fn pop_style_span(&mut self) {
+        self.inputs.push(ShapingInput::PopStyle);
+        self.builder.pop_style_span();
+    }
+
+    fn set_white_space_mode(&mut self, mode: WhiteSpaceCollapse) {
+        self.inputs.push(ShapingInput::WhiteSpace(mode));
+        self.builder.set_white_space_mode(mode);
+    }
+
+    fn push_text(&mut self, text: &str) {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        text.hash(&mut hasher);
+        self.inputs.push(ShapingInput::Text(hasher.finish()));
+        self.builder.push_text(text);
+    }
+
+    fn push_inline_box(&mut self, inline_box: InlineBox) {
+        self.inputs.push(ShapingInput::InlineBox { id: inline_box.id, kind: inline_box.kind });
+        self.builder.push_inline_box(inline_box);
+    }
+}
+
+/// The inherited `white-space` properties that inline layout handles before text reaches parley
+#[derive(Clone, Copy)]
+struct InlineWhiteSpace {
+    collapse: StyloWhiteSpaceCollapse,
+    /// `tab-size` in columns
+    tab_size: usize,
+}
+
+impl Default for InlineWhiteSpace {
+    fn default() -> Self {
+        Self { collapse: StyloWhiteSpaceCollapse::Collapse, tab_size: 8 }
+    }
+}
+
+impl InlineWhiteSpace {
+    fn from_style(style: &ComputedValues) -> Self {
+        Self {
+            collapse: style.clone_white_space_collapse(),
+            tab_size: stylo_to_parley::tab_size(style),
+        }
+    }
+}
+
+/// Replaces tabs with spaces up to the next multiple of `tab_size` columns, keeping `column`
+/// up to date so tab stops line up across text nodes.
+fn expand_tabs<'a>(text: &'a str, tab_size: usize, column: &mut usize) -> Cow<'a, str> {
+    if !text.contains('\t') {
+        *column = match text.rfind('\n') {
+            Some(newline) => text[newline + 1..].chars().count(),
+            None => *column + text.chars().count(),
+        };
+        return Cow::Borrowed(text);
+    }
+
+    let mut out = String::with_capacity(text.len() + tab_size);
+    for ch in text.chars() {
+        match ch {
+            '\t' => {
+                let spaces = if tab_size == 0 { 0 } else { tab_size - *column % tab_size };
+                out.extend(std::iter::repeat_n(' ', spaces));
+                *column += spaces;
+            }
+            '\n' => {
+                out.push(ch);
+                *column = 0;
+            }
+            _ => {
+                out.push(ch);
+                *column += 1;
+            }
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Removes the spaces and tabs that `white-space: pre-line` drops around a preserved newline
+fn trim_segment_break_spaces(line: &str, after_break: bool, before_break: bool) -> &str {
+    let line = if after_break { line.trim_start_matches([' ', '\t']) } else { line };
+    if before_break { line.trim_end_matches([' ', '\t']) } else { line }
+}
+
 impl Dom {
     pub fn compute_layout(&mut self) {
         let size = self.stylist.device().au_viewport_size();
@@ -1179,11 +1345,14 @@ impl Dom {
 
                             // Check cache first
                             let url_str = new_url.as_str();
-                            if let Some(cached_image) = self.image_cache.get(url_str) {
+                            if self.block_images {
+                                // Decorative, so there is nothing to show in its place
+                                None
+                            } else if let Some(cached_image) = self.image_cache.get(url_str) {
                                 Some(BackgroundImageData {
                                     url: new_url.clone(),
                                     status: Status::Ok,
-                                    image: cached_image.clone(),
+                                    image: cached_image,
                                 })
                             } else if let Some(waiting_list) = self.pending_images.get_mut(url_str)
                             {
@@ -1196,7 +1365,7 @@ impl Dom {
                                     vec![(node_id, ImageType::Background(idx))],
                                 );
 
-                                self.net_provider.fetch(
+                                self.net_provider.fetch_image(
                                     doc_id,
                                     Request::get((**new_url).clone()),
                                     ResourceHandler::boxed(
@@ -1336,3 +1505,32 @@ fn node_to_paint_order(node: &DomNode, is_flex_or_grid: bool) -> i32 {
         position_to_order(style.clone_position()) + float_to_order(style.clone_float())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tabs_expand_to_the_next_tab_stop() {
+        let mut column = 0;
+        assert_eq!(expand_tabs("\tif x {\n\t\treturn;", 4, &mut column), "    if x {\n        return;");
+        assert_eq!(column, 15);
+
+        // The column carries over from earlier text nodes
+        let mut column = 0;
+        assert_eq!(expand_tabs("ab", 4, &mut column), "ab");
+        assert_eq!(expand_tabs("\tc", 4, &mut column), "  c");
+        assert_eq!(expand_tabs("x\n", 4, &mut column), "x\n");
+        assert_eq!(expand_tabs("\ty", 4, &mut column), "    y");
+
+        let mut column = 0;
+        assert_eq!(expand_tabs("a\tb", 0, &mut column), "ab");
+    }
+
+    #[test]
+    fn pre_line_drops_spaces_around_newlines() {
+        assert_eq!(trim_segment_break_spaces("  one  ", false, true), "  one");
+        assert_eq!(trim_segment_break_spaces(" \ttwo  ", true, true), "two");
+        assert_eq!(trim_segment_break_spaces("  three  ", true, false), "three  ");
+    }
+}
@@ -0,0 +1,77 @@
+// Where a followed link opens and what the page it leads to learns about this one: a `target`
+// naming another window opens a new tab, and rel=noopener, noreferrer and opener decide whether
+// that tab gets `window.opener` and whether the navigation sends a Referer.
+use url::Url;
+
+/// How following a link goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LinkOpening {
+    /// Open the link in a new tab rather than this one
+    pub(crate) new_tab: bool,
+    /// Let the new tab reach this page through `window.opener`
+    pub(crate) opener: bool,
+    /// Send a Referer with the navigation
+    pub(crate) referrer: bool,
+}
+
+impl LinkOpening {
+    /// How following a link with these `rel` and `target` attributes from `document_url` to `url`
+    /// goes. `force_new_tab` is a middle or Ctrl click, which the user asked for, so the page
+    /// gets no opener from it. A tab the link's target opens only gets one when the link is
+    /// same-origin or says rel=opener, and never with rel=noopener or noreferrer.
+    pub(crate) fn for_link(rel: Option<&str>, target: Option<&str>, force_new_tab: bool, document_url: &Url, url: &Url) -> Self {
+        let has_rel = |name: &str| rel.is_some_and(|rels| rels.split_ascii_whitespace().any(|rel| rel.eq_ignore_ascii_case(name)));
+        let target = target.map(str::trim).unwrap_or_default();
+        let targets_this_tab = target.is_empty()
+            || ["_self", "_parent", "_top"].iter().any(|name| target.eq_ignore_ascii_case(name));
+        let referrer = !has_rel("noreferrer");
+        let opener = !force_new_tab
+            && !targets_this_tab
+            && referrer
+            && !has_rel("noopener")
+            && (has_rel("opener") || document_url.origin() == url.origin());
+        Self { new_tab: force_new_tab || !targets_this_tab, opener, referrer }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opening(rel: Option<&str>, target: Option<&str>, url: &str) -> LinkOpening {
+        let document_url = Url::parse("https://example.com/page").unwrap();
+        LinkOpening::for_link(rel, target, false, &document_url, &Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn links_without_a_target_open_in_this_tab() {
+        let plain = opening(None, None, "https://other.example/");
+        assert_eq!(plain, LinkOpening { new_tab: false, opener: false, referrer: true });
+        assert!(!opening(None, Some("_self"), "https://example.com/next").new_tab);
+        assert!(!opening(None, Some(" _TOP "), "https://example.com/next").new_tab);
+    }
+
+    #[test]
+    fn cross_origin_new_tabs_get_no_opener_unless_asked() {
+        assert!(opening(None, Some("_blank"), "https://example.com/next").opener);
+        assert!(!opening(None, Some("_blank"), "https://other.example/").opener);
+        assert!(opening(Some("opener"), Some("_blank"), "https://other.example/").opener);
+        assert!(opening(None, Some("results"), "https://other.example/").new_tab);
+    }
+
+    #[test]
+    fn noopener_and_noreferrer_drop_the_opener() {
+        assert!(!opening(Some("noopener"), Some("_blank"), "https://example.com/next").opener);
+        let noreferrer = opening(Some("external NoReferrer"), Some("_blank"), "https://example.com/next");
+        assert_eq!(noreferrer, LinkOpening { new_tab: true, opener: false, referrer: false });
+        assert!(!opening(Some("noreferrer"), None, "https://other.example/").referrer);
+    }
+
+    #[test]
+    fn tabs_the_user_opens_get_no_opener() {
+        let document_url = Url::parse("https://example.com/page").unwrap();
+        let url = Url::parse("https://example.com/next").unwrap();
+        let opening = LinkOpening::for_link(None, Some("_blank"), true, &document_url, &url);
+        assert!(opening.new_tab && !opening.opener);
+    }
+}
@@ -2,10 +2,11 @@
 mod parser;
 pub(crate) mod node;
 pub mod events;
-mod config;
+pub(crate) mod config;
 pub(crate) mod damage;
 mod url;
 mod layout;
+pub(crate) use layout::release_layout_contexts;
 mod traverse;
 pub mod stylo_to_parley;
 mod attr;
@@ -19,6 +20,22 @@ pub(crate) mod form;
 mod sub_dom;
 pub mod stylo_to_kurbo;
 mod stylo_data;
+mod serialize;
+mod color_scheme;
+pub(crate) mod scroll;
+mod image_cache;
+pub(crate) mod intersection;
+mod interactive;
+pub(crate) mod scrollbar;
+pub(crate) mod renamed_properties;
+mod autofill;
+mod speculation;
+pub(crate) mod link_opening;
+mod ranges;
+pub(crate) mod style_cache;
+pub(crate) mod font_loading;
+mod activation;
+pub(crate) mod find;
 
 use html5ever::ns;
 pub use events::{EventDispatcher, EventType};
@@ -39,13 +56,17 @@ use crate::dom::config::DomConfig;
 use crate::dom::damage::{ALL_DAMAGE, CONSTRUCT_BOX, CONSTRUCT_DESCENDENT, CONSTRUCT_FC};
 use crate::dom::layout::collect_layout_children;
 use crate::dom::node::{Attribute, DomNodeFlags, SpecialElementData, TextData};
+use crate::dom::renamed_properties::translate_renamed_properties;
 use crate::dom::url::DocUrl;
 use crate::events::UiEvent;
 use crate::networking::{ImageType, ResourceLoadResponse, StylesheetLoader};
+use crate::networking::referrer::{self, ReferrerPolicy};
+use crate::networking::refresh::{self, Refresh};
+use crate::networking::request_policy::RequestPolicy;
 use crate::ui::TextBrush;
 use blitz_traits::events::HitResult;
 use blitz_traits::net::NetProvider;
-use blitz_traits::shell::{ShellProvider, Viewport};
+use blitz_traits::shell::{ColorScheme, ShellProvider, Viewport};
 use euclid::Size2D;
 use markup5ever::{local_name, QualName};
 use parley::fontique::{Attributes, Blob, Query, QueryFont, QueryStatus};
@@ -63,7 +84,7 @@ use std::mem;
 use std::ops::{Deref, DerefMut};
 use std::rc::Rc;
 use std::str::FromStr;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex, MutexGuard, RwLockReadGuard, RwLockWriteGuard};
 use std::task::Context;
@@ -91,20 +112,28 @@ use style::thread_state::ThreadState;
 use style::traversal::DomTraversal;
 use style::traversal_flags::TraversalFlags;
 use style::values::computed::font::{GenericFontFamily, QueryFontMetricsFlags};
+use style_traits::ToCss;
 use style::values::computed::{Au, CSSPixelLength, Length, Overflow};
 use stylo_atoms::Atom;
 use taffy::Point;
 use crate::dom::events::pointer::{DragMode, ScrollAnimationState};
 use crate::dom::selection::TextSelection;
+pub(crate) use crate::dom::resolve::ResolveStats;
+use crate::dom::image_cache::ImageCache;
+pub use crate::dom::image_cache::DEFAULT_IMAGE_MEMORY_BUDGET;
 use crate::dom::stylo_to_cursor::stylo_to_cursor_icon;
 use crate::dom::traverse::TreeTraverser;
 use crate::engine::nav_provider::StokesNavigationProvider;
 use crate::engine::net_provider::StokesNetProvider;
-use crate::events::{BlitzScrollEvent, DomEventData};
+use crate::events::{BlitzScrollEvent, BlitzTouch, DomEventData};
 use crate::qual_name;
 use crate::shell_provider::{ShellProviderMessage, StokesShellProvider};
+use crate::site_settings::{AutoplayPolicy, ColorSchemeOverride};
+use crate::visited_links;
+use stylo_dom::ElementState;
 use crate::dom::events::EventDriver;
 use crate::js::bindings::event_listeners::JsEventHandler;
+use crate::js::bindings::registry;
 use crate::dom::parser::HtmlProvider;
 use crate::engine::js_provider::StokesJsProvider;
 
@@ -226,6 +255,44 @@ pub struct Dom {
     pub(crate) viewport: Viewport,
     // Scroll position in the viewport
     pub(crate) viewport_scroll: Point<f64>,
+    // Whether the user asked for reduced motion (prefers-reduced-motion: reduce)
+    pub(crate) prefers_reduced_motion: bool,
+    // The user's color scheme choice for this site
+    pub(crate) color_scheme_override: ColorSchemeOverride,
+    // Scheme the UA defaults are currently drawn in (see color_scheme.rs)
+    pub(crate) used_color_scheme: ColorScheme,
+    // Whether light pages get their colors flipped at paint time (see renderer/force_dark.rs)
+    pub(crate) force_dark: bool,
+    // Whether images are left unfetched and drawn as placeholders (see resource.rs)
+    pub(crate) block_images: bool,
+    // The user's autoplay choice for this site, asked by media elements' play()
+    pub(crate) autoplay: AutoplayPolicy,
+    // Whether the user has pressed a mouse button or key on this document (sticky activation)
+    pub(crate) user_activated: bool,
+    // When the user last clicked or pressed a key, until a popup uses it up (transient activation)
+    pub(crate) last_activation: Option<std::time::Instant>,
+    // Whether the user lets this site open popups without a click or key press first
+    pub(crate) popups_allowed: bool,
+    // Whether an IntersectionObserver is watching elements, so each frame has to update it
+    pub(crate) observing_intersections: bool,
+    // Nodes that scrolled since the last frame; the root node stands for the viewport
+    pub(crate) pending_scroll_events: Vec<usize>,
+    // Details elements that opened or closed since the last frame, to fire `toggle` at
+    pub(crate) pending_toggle_events: Vec<usize>,
+    // Dialogs shown with showModal(), bottom first; the last one is the one that's interactive
+    pub(crate) top_layer: Vec<usize>,
+    // Whether the document declared a refresh, after which later declarations are ignored
+    pub(crate) refresh_declared: bool,
+    // A declared refresh the tab process hasn't scheduled yet: the delay and where to go
+    pub(crate) pending_refresh: Option<(std::time::Duration, ::url::Url)>,
+    // Target of the document's `<link rel="prerender">`, which the parent loads in a hidden tab
+    pub(crate) prerender_url: Option<::url::Url>,
+    // The link under the pointer, prefetched once the pointer rests on it
+    pub(crate) hovered_link: Option<speculation::HoveredLink>,
+    // The web fonts the document fetched and where their loads are
+    pub(crate) font_loads: font_loading::FontLoads,
+    // Matches of the browser's find-in-page search
+    pub(crate) find: find::FindState,
 
     pub(crate) tx: Sender<DomEvent>,
     pub(crate) rx: Option<Receiver<DomEvent>>,
@@ -255,12 +322,20 @@ pub struct Dom {
     pub(crate) quick_clicks: u16,
     pub(crate) drag_mode: DragMode,
     pub(crate) scroll_animation: ScrollAnimationState,
+    /// Fingers on the page, in the order they landed
+    pub(crate) touches: Vec<BlitzTouch>,
+    /// The page cancelled the touchstart of the fingers on it, so they neither scroll nor click
+    pub(crate) touch_prevented: bool,
 
     pub(crate) text_selection: TextSelection,
+    /// The selection changed since the last `selectionchange`
+    pub(crate) selection_changed: bool,
 
     pub(crate) has_active_animations: bool,
     pub(crate) has_canvas: bool,
     pub(crate) subdom_is_animating: bool,
+    /// How much of the document the last `resolve` restyled and relaid out
+    pub(crate) resolve_stats: ResolveStats,
 
     pub(crate) nodes_to_id: HashMap<String, usize>,
     pub(crate) nodes_by_tag: HashMap<String, Vec<usize>>,
@@ -270,7 +345,9 @@ pub struct Dom {
     pub(crate) controls_to_form: HashMap<usize, usize>,
     pub(crate) sub_dom_nodes: HashSet<usize>,
 
-    pub(crate) image_cache: HashMap<String, ImageData>,
+    pub(crate) image_cache: ImageCache,
+    /// Users of images evicted from `image_cache`, to load them again when they are needed
+    pub(crate) evicted_images: HashMap<String, Vec<(usize, ImageType)>>,
     pub(crate) pending_images: HashMap<String, Vec<(usize, ImageType)>>,
 
     pub net_provider: Arc<StokesNetProvider>,
@@ -278,10 +355,41 @@ pub struct Dom {
     pub nav_provider: Arc<StokesNavigationProvider>,
     pub html_provider: Arc<HtmlProvider>,
     pub js_provider: Arc<StokesJsProvider>,
+
+    _live: LiveDocument,
+}
+
+/// Documents created and not yet dropped in this process
+static LIVE_DOCUMENTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts its document in `LIVE_DOCUMENTS` for as long as the document exists, which lets a tab
+/// check that closing or navigating really dropped its documents
+struct LiveDocument;
+
+impl LiveDocument {
+    fn new() -> Self {
+        LIVE_DOCUMENTS.fetch_add(1, Ordering::SeqCst);
+        LiveDocument
+    }
+}
+
+impl Drop for LiveDocument {
+    fn drop(&mut self) {
+        LIVE_DOCUMENTS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// How many documents, subdocuments included, are alive in this process
+pub(crate) fn live_documents() -> usize {
+    LIVE_DOCUMENTS.load(Ordering::SeqCst)
 }
 
 pub enum DomEvent {
-    ResourceLoad(ResourceLoadResponse)
+    ResourceLoad(ResourceLoadResponse),
+    /// A stylesheet's `@font-face` started fetching its font
+    FontLoadStarted { family: String, url: String, display: font_loading::FontDisplay },
+    /// A font fetch failed, or gave something that isn't a font
+    FontLoadFailed(String),
 }
 
 pub(crate) fn device(viewport: &Viewport, font_ctx: Arc<Mutex<FontContext>>) -> Device {
@@ -295,14 +403,32 @@ pub(crate) fn device(viewport: &Viewport, font_ctx: Arc<Mutex<FontContext>>) ->
         QuirksMode::NoQuirks,
         size,
         pixel_ratio,
-        Box::new(StokesFontMetricsProvider { font_ctx }),
+        Box::new(StokesFontMetricsProvider { font_ctx, cache: Default::default() }),
         ComputedValues::initial_values_with_font_override(Font::initial_values()),
-        PrefersColorScheme::Dark // TODO detect color scheme preference
+        match viewport.color_scheme {
+            ColorScheme::Light => PrefersColorScheme::Light,
+            ColorScheme::Dark => PrefersColorScheme::Dark,
+        },
     )
 }
 
+/// Bumped when a web font is added to a font collection, as metrics measured before it arrived may
+/// come from a fallback font
+pub(crate) static FONT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// A font as far as its metrics go: its families, and the weight, width, style and variations
+/// parley picks a face with, at a size in px
+#[derive(PartialEq, Eq, Hash)]
+struct FontMetricsKey {
+    families: String,
+    attributes: String,
+    size_bits: u32,
+}
+
 struct StokesFontMetricsProvider {
     font_ctx: Arc<Mutex<FontContext>>,
+    /// Metrics measured so far, and the `FONT_GENERATION` they were measured in
+    cache: Mutex<(u64, HashMap<FontMetricsKey, FontMetrics>)>,
 }
 
 impl Debug for StokesFontMetricsProvider {
@@ -312,20 +438,55 @@ impl Debug for StokesFontMetricsProvider {
 }
 
 impl FontMetricsProvider for StokesFontMetricsProvider {
-    fn query_font_metrics(&self, vertical: bool, font: &Font, font_size: CSSPixelLength, flags: QueryFontMetricsFlags) -> FontMetrics {
+    fn query_font_metrics(&self, _vertical: bool, font: &Font, font_size: CSSPixelLength, _flags: QueryFontMetricsFlags) -> FontMetrics {
+        let attributes = Attributes {
+            width: stylo_to_parley::font_width(font.font_stretch),
+            weight: stylo_to_parley::font_weight(font.font_weight),
+            style: stylo_to_parley::font_style(font.font_style),
+        };
+        let variations = stylo_to_parley::font_variations(&font.font_variation_settings);
+
+        // Style resolution asks for the same few fonts over and over, for every element using
+        // ex, ch, cap or ic units
+        let key = FontMetricsKey {
+            families: font.font_family.to_css_string(),
+            attributes: format!("{attributes:?} {variations:?}"),
+            size_bits: font_size.px().to_bits(),
+        };
+        let generation = FONT_GENERATION.load(Ordering::Relaxed);
+        {
+            let mut cache = self.cache.lock().unwrap();
+            if cache.0 != generation {
+                *cache = (generation, HashMap::new());
+            }
+            if let Some(metrics) = cache.1.get(&key) {
+                return metrics.clone();
+            }
+        }
+
+        let metrics = self.measure_font_metrics(font, attributes, &variations, font_size);
+        self.cache.lock().unwrap().1.insert(key, metrics.clone());
+        metrics
+    }
+
+    fn base_size_for_generic(&self, generic: GenericFontFamily) -> Length {
+        let size = match generic {
+            GenericFontFamily::Monospace => 13.0,
+            _ => 16.0,
+        };
+        Length::from(Au::from_f32_px(size))
+    }
+}
+
+impl StokesFontMetricsProvider {
+    fn measure_font_metrics(&self, font: &Font, attributes: Attributes, variations: &[FontVariation], font_size: CSSPixelLength) -> FontMetrics {
         let mut font_ctx = self.font_ctx.lock().unwrap();
         let font_ctx = &mut *font_ctx;
 
         let mut query = font_ctx.collection.query(&mut font_ctx.source_cache);
         let families = font.font_family.families.iter().map(stylo_to_parley::query_font_family);
         query.set_families(families);
-        query.set_attributes(Attributes {
-            width: stylo_to_parley::font_width(font.font_stretch),
-            weight: stylo_to_parley::font_weight(font.font_weight),
-            style: stylo_to_parley::font_style(font.font_style),
-        });
-
-        let variations = stylo_to_parley::font_variations(&font.font_variation_settings);
+        query.set_attributes(attributes);
 
         fn find_font_for(query: &mut Query, ch: char) -> Option<QueryFont> {
             let mut font = None;
@@ -387,10 +548,10 @@ impl FontMetricsProvider for StokesFontMetricsProvider {
         }
 
         let font_size = Size::new(font_size.px());
-        let zero_advance = advance_of(&mut query, '0', font_size, &variations);
-        let ic_advance = advance_of(&mut query, '\u{6C34}', font_size, &variations);
+        let zero_advance = advance_of(&mut query, '0', font_size, variations);
+        let ic_advance = advance_of(&mut query, '\u{6C34}', font_size, variations);
         let (ascent, x_height, cap_height) =
-            metrics_of(&mut query, ' ', font_size, &variations).unwrap_or((0.0, None, None));
+            metrics_of(&mut query, ' ', font_size, variations).unwrap_or((0.0, None, None));
 
         FontMetrics {
             ascent: CSSPixelLength::new(ascent),
@@ -402,14 +563,6 @@ impl FontMetricsProvider for StokesFontMetricsProvider {
             script_script_percent_scale_down: None,
         }
     }
-
-    fn base_size_for_generic(&self, generic: GenericFontFamily) -> Length {
-        let size = match generic {
-            GenericFontFamily::Monospace => 13.0,
-            _ => 16.0,
-        };
-        Length::from(Au::from_f32_px(size))
-    }
 }
 
 pub(crate) const DEFAULT_CSS: &str = include_str!("../../assets/default.css");
@@ -439,7 +592,10 @@ impl Dom {
         let font_ctx = Arc::new(Mutex::new(font_ctx));
         let device = device(&viewport, font_ctx.clone());
 
-        let base_url = config.base_url.and_then(|url| DocUrl::from_str(&url).ok()).unwrap_or_default();
+        let mut base_url = config.base_url.and_then(|url| DocUrl::from_str(&url).ok()).unwrap_or_default();
+        if let Some(fallback) = config.fallback_base_url.and_then(|url| url::Url::parse(&url).ok()) {
+            base_url.set_fallback_base_url(fallback);
+        }
         let net_provider = config.net_provider.unwrap();
         let shell_provider = config.shell_provider.unwrap();
         let nav_provider = config.nav_provider.unwrap();
@@ -452,13 +608,32 @@ impl Dom {
             url: base_url,
             viewport,
             viewport_scroll: ZERO,
+            prefers_reduced_motion: false,
+            color_scheme_override: ColorSchemeOverride::Auto,
+            used_color_scheme: ColorScheme::Light,
+            force_dark: false,
+            block_images: config.block_images,
+            autoplay: AutoplayPolicy::default(),
+            user_activated: false,
+            last_activation: None,
+            popups_allowed: false,
+            observing_intersections: false,
+            pending_scroll_events: Vec::new(),
+            pending_toggle_events: Vec::new(),
+            top_layer: Vec::new(),
+            refresh_declared: false,
+            pending_refresh: None,
+            prerender_url: None,
+            hovered_link: None,
+            font_loads: Default::default(),
+            find: Default::default(),
             tx,
             rx: Some(rx),
             nodes: Box::new(Slab::new()),
             //root: Rc::new(RefCell::from(DomNode::new(NodeData::Document, None))),
             stylist: Stylist::new(device, QuirksMode::NoQuirks),
             animations: Default::default(),
-            lock: SharedRwLock::new(),
+            lock: style_cache::shared_lock(),
             snapshots: SnapshotMap::new(),
             font_ctx,
             layout_ctx: LayoutContext::new(),
@@ -472,10 +647,14 @@ impl Dom {
             quick_clicks: 0,
             drag_mode: DragMode::None,
             scroll_animation: ScrollAnimationState::None,
+            touches: Vec::new(),
+            touch_prevented: false,
             text_selection: TextSelection::default(),
+            selection_changed: false,
             has_active_animations: false,
             has_canvas: false,
             subdom_is_animating: false,
+            resolve_stats: ResolveStats::default(),
             nodes_to_id: Default::default(),
             nodes_by_tag: Default::default(),
             nodes_by_class: Default::default(),
@@ -483,13 +662,15 @@ impl Dom {
             stylesheets: Default::default(),
             controls_to_form: HashMap::new(),
             sub_dom_nodes: HashSet::new(),
-            image_cache: HashMap::new(),
+            image_cache: ImageCache::default(),
+            evicted_images: HashMap::new(),
             pending_images: HashMap::new(),
             net_provider,
             shell_provider,
             nav_provider,
             html_provider: Arc::new(HtmlProvider),
             js_provider,
+            _live: LiveDocument::new(),
         };
 
         // Create the root document node
@@ -655,21 +836,46 @@ impl Dom {
         user_agent: String,
         debug_net: bool,
         block_ads: bool,
+        upgrade_mixed_content: bool,
+        block_images: bool,
+        request_policy: RequestPolicy,
         viewport: Viewport,
         shell_provider: Arc<StokesShellProvider>,
         nav_provider: Arc<StokesNavigationProvider>,
         js_provider: Arc<StokesJsProvider>,
     ) -> Self {
+        let net_provider = StokesNetProvider::new(
+            user_agent,
+            debug_net,
+            block_ads,
+            ::url::Url::parse(url).ok(),
+            upgrade_mixed_content,
+            request_policy,
+            shell_provider.clone(),
+        );
+        if let Some(policy) = referrer::take_document_policy(url) {
+            net_provider.set_referrer_policy(policy);
+        }
+
+        // The Refresh header takes precedence over a <meta http-equiv="refresh"> in the document
+        let header_refresh = refresh::take_document_refresh(url);
+
         let parser = HtmlParser::new();
-        parser.parse(html, DomConfig {
+        let mut dom = parser.parse(html, DomConfig {
             viewport: Some(viewport),
             base_url: Some(url.to_string()),
-            net_provider: Some(Arc::new(StokesNetProvider::new(user_agent, debug_net, block_ads))),
+            net_provider: Some(Arc::new(net_provider)),
             shell_provider: Some(shell_provider),
             nav_provider: Some(nav_provider),
             js_provider: Some(js_provider),
+            block_images,
             ..Default::default()
-        })
+        });
+        if let Some(refresh) = header_refresh {
+            dom.refresh_declared = false;
+            dom.declare_refresh(refresh);
+        }
+        dom
     }
 
     pub fn add_stylesheet(&mut self, css: &str) {
@@ -733,6 +939,40 @@ impl Dom {
         }
     }
 
+    /// Apply `<meta name="referrer">` to the requests this document makes from now on
+    pub fn process_meta_element(&mut self, target_id: usize) {
+        let node = &self.nodes[target_id];
+        if node.attr(local_name!("http-equiv")).is_some_and(|value| value.eq_ignore_ascii_case("refresh")) {
+            if let Some(refresh) = node.attr(local_name!("content")).and_then(Refresh::parse) {
+                self.declare_refresh(refresh);
+            }
+            return;
+        }
+        let is_referrer = node.attr(local_name!("name")).is_some_and(|name| name.eq_ignore_ascii_case("referrer"));
+        if !is_referrer {
+            return;
+        }
+        if let Some(policy) = node.attr(local_name!("content")).and_then(ReferrerPolicy::parse) {
+            self.net_provider.set_referrer_policy(policy);
+        }
+    }
+
+    /// Schedule the document's refresh. Only the first declaration counts, whether it came from
+    /// the Refresh header or a meta element; the tab process runs it once the delay is up.
+    pub(crate) fn declare_refresh(&mut self, refresh: Refresh) {
+        if self.refresh_declared {
+            return;
+        }
+        let url = match &refresh.url {
+            Some(url) => self.url.resolve_relative(url),
+            None => Some((*self.url).clone()),
+        };
+        if let Some(url) = url {
+            self.refresh_declared = true;
+            self.pending_refresh = Some((refresh.delay, url));
+        }
+    }
+
     pub fn process_style_element(&mut self, target_id: usize) {
         let css = self.nodes[target_id].text_content();
         let css = html_escape::decode_html_entities(&css);
@@ -741,24 +981,27 @@ impl Dom {
     }
 
     pub fn make_stylesheet(&self, css: impl AsRef<str>, origin: Origin) -> DocumentStyleSheet {
-        let data = Stylesheet::from_str(
-            css.as_ref(),
-            self.url.url_extra_data(),
-            origin,
-            style::servo_arc::Arc::new(self.lock.wrap(MediaList::empty())),
-            self.lock.clone(),
-            Some(&StylesheetLoader {
-                tx: self.tx.clone(),
-                dom_id: self.id,
-                net_provider: self.net_provider.clone(),
-                shell_provider: self.shell_provider.clone(),
-            }),
-            None,
-            QuirksMode::NoQuirks,
-            AllowImportRules::Yes
-        );
-
-        DocumentStyleSheet(style::servo_arc::Arc::new(data))
+        let css = css.as_ref();
+        style_cache::stylesheet(self.url.as_str(), css, origin, || {
+            let translated = scrollbar::translate_webkit_scrollbar_rules(css);
+            let translated = translate_renamed_properties(translated.as_deref().unwrap_or(css)).or(translated);
+            Stylesheet::from_str(
+                translated.as_deref().unwrap_or(css),
+                self.url.url_extra_data(),
+                origin,
+                style::servo_arc::Arc::new(self.lock.wrap(MediaList::empty())),
+                self.lock.clone(),
+                Some(&StylesheetLoader {
+                    tx: self.tx.clone(),
+                    dom_id: self.id,
+                    net_provider: self.net_provider.clone(),
+                    shell_provider: self.shell_provider.clone(),
+                }),
+                None,
+                QuirksMode::NoQuirks,
+                AllowImportRules::Yes
+            )
+        })
     }
 
     pub fn flush_styles(&mut self, now: f64) {
@@ -821,7 +1064,7 @@ impl Dom {
         {
             let context = SharedStyleContext {
                 stylist: &self.stylist,
-                visited_styles_enabled: false,
+                visited_styles_enabled: true,
                 options: GLOBAL_STYLE_DATA.options.clone(),
                 guards: guards,
                 animations: self.animations.clone(),
@@ -974,6 +1217,13 @@ impl Dom {
         })
     }
 
+    /// Whether the document changed since it was last resolved, so a frame would look different
+    pub fn needs_frame(&self) -> bool {
+        // Documents without a root element aren't resolved, so they would never stop needing one
+        let has_root = TDocument::as_node(&&self.nodes[0]).first_element_child().is_some();
+        has_root && (!self.snapshots.is_empty() || self.nodes[0].has_dirty_descendants())
+    }
+
     pub fn animating(&self) -> bool {
         self.has_canvas
             | self.has_active_animations
@@ -1010,17 +1260,46 @@ impl Dom {
 
     /// Extract the page title
     pub fn get_title(&self) -> String {
-        // Find the title element in the head
-        let title_nodes = self.query_selector("title");
-        if let Some(title_node) = title_nodes.first() {
-            // Get text content of the title
-            title_node.text_content()
-        } else {
+        let title = self.document_title();
+        if title.is_empty() {
             // Default title if not found
             "Untitled".to_string()
+        } else {
+            title
         }
     }
 
+    /// `document.title`: the first title element's text with whitespace collapsed, or empty
+    pub fn document_title(&self) -> String {
+        self.query_selector("title")
+            .first()
+            .map(|title_node| title_node.text_content().split_ascii_whitespace().collect::<Vec<_>>().join(" "))
+            .unwrap_or_default()
+    }
+
+    /// Set `document.title`: replace the first title element's text, adding a title to the head
+    /// when there isn't one. The new title reaches the tab strip through `set_window_title`.
+    pub fn set_document_title(&mut self, title: &str) {
+        let existing = self.query_selector("title").first().map(|title_node| title_node.id);
+        let title_id = match existing {
+            Some(title_id) => title_id,
+            None => {
+                let Some(head_id) = self.head_id() else {
+                    return;
+                };
+                let title_id = self.create_element(qual_name!("title", html), AttributeMap::empty());
+                self.append_children(head_id, &[title_id]);
+                title_id
+            }
+        };
+        self.set_text_content(title_id, title.to_string());
+    }
+
+    /// Tell the shell the document's title, e.g. after its title element changed
+    fn notify_title_changed(&self) {
+        self.shell_provider.set_window_title(self.get_title());
+    }
+
     /// Clear the layout cache for a node and all its ancestors.
     /// This is necessary when a node's intrinsic size changes (e.g., when an image loads)
     /// so that layout will be recomputed correctly.
@@ -1128,7 +1407,7 @@ impl Dom {
     /// For text nodes, replaces the text content
     /// For element nodes, removes all children and creates a single text node child
     pub fn set_text_content(&mut self, node_id: usize, value: String) {
-        println!("Setting text content of node {} to '{}'", node_id, value);
+        tracing::debug!("Setting text content of node {} to '{}'", node_id, value);
 
         match self.nodes[node_id].data {
             NodeData::Text(ref mut text) => {
@@ -1197,6 +1476,7 @@ impl Dom {
 
         self.hover_node_id = hover_node_id;
         self.hover_node_is_text = new_is_text;
+        self.update_hovered_link();
 
         let cursor = self.get_cursor().unwrap_or_default();
         self.shell_provider.set_cursor(cursor);
@@ -1219,6 +1499,7 @@ impl Dom {
 
         self.hover_node_id = None;
         self.hover_node_is_text = false;
+        self.hovered_link = None;
 
         // Update the cursor
         let cursor = self.get_cursor().unwrap_or_default();
@@ -1236,10 +1517,17 @@ impl Dom {
 
     pub fn set_viewport(&mut self, viewport: Viewport) {
         let scale_changed = viewport.scale_f64() != self.viewport.scale_f64();
+        let scheme_changed = viewport.color_scheme != self.viewport.color_scheme;
         self.viewport = viewport;
         self.set_stylist_device(device(&self.viewport, self.font_ctx.clone()));
         // todo clamp scroll
 
+        if scheme_changed {
+            // prefers-color-scheme rules can apply anywhere in the tree
+            self.restyle_root_subtree();
+            self.shell_provider.request_redraw();
+        }
+
         if scale_changed {
             self.invalidate_inline_contexts();
             self.shell_provider.request_redraw();
@@ -1372,6 +1660,7 @@ impl Dom {
         }
 
         let has_changed = node.scroll_offset != initial;
+        let parent = node.parent;
 
         if has_changed {
             let layout = node.final_layout;
@@ -1385,10 +1674,13 @@ impl Dom {
             };
 
             dispatch_event(crate::events::DomEvent::new(node_id, DomEventData::Scroll(event)));
+            // The root scroller's events go to the document, like the viewport's
+            let target = if is_html_or_body { self.root_node().id } else { node_id };
+            self.queue_scroll_event(target);
         }
 
         if bubble_x != 0.0 || bubble_y != 0.0 {
-            if let Some(parent) = node.parent {
+            if let Some(parent) = parent {
                 return self.scroll_node_by_has_changed(parent, bubble_x, bubble_y, dispatch_event)
                     | has_changed;
             } else {
@@ -1422,6 +1714,7 @@ impl Dom {
 
         let result = self.viewport_scroll.x != initial_x || self.viewport_scroll.y != inital_y;
         if result {
+            self.queue_scroll_event(self.root_node().id);
             let _ = self.shell_provider.sender.send(ShellProviderMessage::ViewportScroll((self.viewport_scroll.x, self.viewport_scroll.y)));
         }
         result
@@ -1458,6 +1751,10 @@ impl Dom {
             return None;
         }
 
+        if let Some(hit) = self.hit_top_layer(x, y) {
+            return Some(hit);
+        }
+
         self.root_element().hit(x, y)
     }
 
@@ -1472,6 +1769,48 @@ impl Dom {
         self.hit_client(x, y)
     }
 
+    /// The elements at a point in client coordinates, topmost first: the element that was hit
+    /// and its ancestors. Text hits report their element, anonymous boxes and pseudo-elements the
+    /// element that generated them, and anything in a shadow tree its host.
+    pub(crate) fn elements_from_point(&self, x: f32, y: f32) -> Vec<usize> {
+        let width = self.viewport.window_size.0 as f32 / self.viewport.scale();
+        let height = self.viewport.window_size.1 as f32 / self.viewport.scale();
+        if x < 0.0 || y < 0.0 || x > width || y > height {
+            return Vec::new();
+        }
+
+        let mut elements = Vec::new();
+        let mut current = self.hit_client(x, y).map(|hit| hit.node_id);
+        while let Some(node_id) = current {
+            let Some(node) = self.get_node(node_id) else {
+                break;
+            };
+            current = node.parent.or_else(|| node.layout_parent.get());
+            match &node.data {
+                NodeData::ShadowRoot(_) => elements.clear(),
+                NodeData::Element(_) => {
+                    let pseudo = current
+                        .and_then(|parent| self.get_node(parent))
+                        .is_some_and(|parent| parent.before == Some(node_id) || parent.after == Some(node_id));
+                    if !pseudo {
+                        elements.push(node_id);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if elements.is_empty() {
+            elements.extend(self.document_element_id());
+        }
+        elements
+    }
+
+    /// The topmost element at a point in client coordinates
+    pub(crate) fn element_from_point(&self, x: f32, y: f32) -> Option<usize> {
+        self.elements_from_point(x, y).first().copied()
+    }
+
     pub fn try_root_element(&self) -> Option<&DomNode> {
         TDocument::as_node(&self.root_node()).first_element_child()
     }
@@ -1550,6 +1889,7 @@ impl Dom {
             let shell_provider = self.shell_provider.clone();
             self.snapshot_and(id, |node| node.blur(shell_provider));
             self.focus_node_id = None;
+            let _ = self.shell_provider.sender.send(ShellProviderMessage::AutofillFieldBlurred);
         }
     }
 
@@ -1572,6 +1912,7 @@ impl Dom {
         self.snapshot_and(focus_node_id, |node| node.focus(shell_provider));
 
         self.focus_node_id = Some(focus_node_id);
+        self.notify_autofill_focus(focus_node_id);
 
         true
     }
@@ -1649,6 +1990,7 @@ impl Dom {
     ) {
         self.text_selection =
             TextSelection::new(anchor_node, anchor_offset, focus_node, focus_offset);
+        self.selection_changed = true;
 
         // For anonymous blocks, switch to storing parent+sibling_index (stable reference)
         if let (Some(parent), Some(idx)) = self.anonymous_block_location(anchor_node) {
@@ -1700,11 +2042,13 @@ impl Dom {
     }
 
     pub fn clear_text_selection(&mut self) {
+        self.selection_changed |= self.text_selection.anchor.is_some();
         self.text_selection.clear();
     }
 
     /// Update the selection focus point (used during mouse drag to extend selection).
     pub fn update_selection_focus(&mut self, focus_node: usize, focus_offset: usize) {
+        self.selection_changed = true;
         // For anonymous blocks, store parent+sibling_index; otherwise store node directly
         if let (Some(parent), Some(idx)) = self.anonymous_block_location(focus_node) {
             self.text_selection
@@ -2039,10 +2383,7 @@ impl Dom {
         };
 
         match tag_name.as_ref() {
-            "title" => {
-                let title = self.nodes[node_id].text_content();
-                self.shell_provider.set_window_title(title);
-            },
+            "title" => self.notify_title_changed(),
             "style" => {
                 self.process_style_element(node_id);
             }
@@ -2092,11 +2433,18 @@ impl Dom {
             // TODO Custom post-processing by element tag name
             let tag = element.name.local.as_ref();
             match tag {
-                "title" => dom.shell_provider.set_window_title(dom.nodes[node_id].text_content()),
-                "link" => dom.load_linked_stylesheet(node_id),
+                "title" => dom.notify_title_changed(),
+                "link" => {
+                    dom.load_linked_stylesheet(node_id);
+                    dom.process_link_hints(node_id);
+                }
                 "img" => dom.load_image(node_id),
                 "canvas" => dom.load_custom_paint_src(node_id),
                 "style" => dom.process_style_element(node_id),
+                "meta" => dom.process_meta_element(node_id),
+                "base" => dom.update_base_url(),
+                "iframe" => dom.load_iframe(node_id),
+                "a" | "area" => dom.update_link_state(node_id),
                 "button" | "fieldset" | "input" | "select" | "textarea" | "object" | "output" => {
                     dom.process_button_input(node_id);
                     dom.reset_form_owner(node_id);
@@ -2117,6 +2465,59 @@ impl Dom {
         });
     }
 
+    /// Check every link against the tab's visited-link filter, after the filter changed
+    pub(crate) fn update_visited_links(&mut self) {
+        let links: Vec<usize> = ["a", "area"]
+            .iter()
+            .filter_map(|tag| self.nodes_by_tag.get(*tag))
+            .flatten()
+            .copied()
+            .collect();
+        for node_id in links {
+            self.update_link_state(node_id);
+        }
+    }
+
+    /// Resolve relative URLs against the first `<base href>` in the document, or against the
+    /// fallback base (usually the document's URL) when there is none. A base with a `data:` or
+    /// `javascript:` URL is ignored.
+    pub(crate) fn update_base_url(&mut self) {
+        let fallback_base_url = self.url.fallback_base_url().clone();
+        let base = TreeTraverser::new(self)
+            .find_map(|node_id| {
+                let element = self.nodes[node_id].element_data()?;
+                if element.name.local != local_name!("base") {
+                    return None;
+                }
+                element.attr(local_name!("href"))
+            })
+            .and_then(|href| fallback_base_url.join(href.trim()).ok())
+            .filter(|base| !matches!(base.scheme(), "data" | "javascript"));
+        if self.url.base_url() == base.as_ref().unwrap_or(&fallback_base_url) {
+            return;
+        }
+        self.url.set_base_url(base);
+        // Links may now point somewhere visited, or no longer
+        self.update_visited_links();
+    }
+
+    /// Mark an `a` or `area` element as a visited or unvisited link, or as no link without an href
+    pub(crate) fn update_link_state(&mut self, node_id: usize) {
+        let Some(node) = self.nodes.get(node_id) else {
+            return;
+        };
+        let state = match node.attr(local_name!("href")) {
+            Some(href) => match self.url.resolve_relative(href) {
+                Some(url) if visited_links::is_visited(&url) => ElementState::VISITED,
+                _ => ElementState::UNVISITED,
+            },
+            None => ElementState::empty(),
+        };
+        if node.element_state & (ElementState::VISITED | ElementState::UNVISITED) != state {
+            self.snapshot_and(node_id, |node| node.set_link_state(state));
+        }
+    }
+
     fn autofocus(&mut self, node_id: usize) {
         if self.get_node(node_id).is_some() {
             self.set_focus_to(node_id);
@@ -2156,7 +2557,9 @@ impl Dom {
         let children = mem::take(&mut parent.children);
         for child_id in children {
             self.process_removed_subtree(child_id);
-            let _ = self.drop_node_ignoring_parent(child_id);
+            // A child script still holds is kept, detached, until its wrappers are collected
+            self.nodes[child_id].parent = None;
+            registry::drop_subtree_unless_referenced(self, child_id);
         }
         self.maybe_record_node(node_id);
     }
@@ -2166,10 +2569,18 @@ impl Dom {
         self.html_provider.clone().parse_inner_html(self, node_id, html);
     }
 
+    /// Parse `source` as a separate HTML or XML document whose nodes live in this DOM without
+    /// being attached to it, returning the id of its `Document` node
+    pub(crate) fn parse_detached_document(&mut self, source: &str, is_xml: bool) -> usize {
+        self.html_provider.clone().parse_detached_document(self, source, is_xml)
+    }
+
     fn process_removed_subtree(&mut self, node_id: usize) {
         let mut compute_canvas: bool = false;
         let mut stylesheets_to_unload = Vec::new();
         let mut removed_form = false;
+        let mut removed_base = false;
+        let mut sub_doms_to_unload = Vec::new();
         self.iter_subtree_mut(node_id, |node_id, doc| {
             let node = &mut doc.nodes[node_id];
             node.flags.set(DomNodeFlags::IS_IN_DOCUMENT, false);
@@ -2242,9 +2653,14 @@ impl Dom {
             if element.name.local == local_name!("form") {
                 removed_form = true;
             }
+            if element.name.local == local_name!("base") {
+                removed_base = true;
+            }
 
             match &element.special_data {
-                SpecialElementData::SubDom(_) => {}
+                SpecialElementData::SubDom(_) => {
+                    sub_doms_to_unload.push(node_id);
+                }
                 SpecialElementData::Stylesheet(_) => {
                     stylesheets_to_unload.push(node_id);
                 }
@@ -2263,6 +2679,9 @@ impl Dom {
         if removed_form {
             self.reset_all_form_owners();
         }
+        if removed_base {
+            self.update_base_url();
+        }
 
         if compute_canvas {
             self.has_canvas = self.compute_has_canvas();
@@ -2270,6 +2689,9 @@ impl Dom {
         for node_id in stylesheets_to_unload {
             self.unload_stylesheet(node_id);
         }
+        for node_id in sub_doms_to_unload {
+            self.remove_sub_dom(node_id);
+        }
     }
 
     fn process_button_input(&mut self, target_id: usize) {
@@ -2325,6 +2747,47 @@ impl Dom {
         self.sub_dom_nodes.remove(&node_id);
     }
 
+    /// Give an `<iframe>` the nested document it can have without a fetch: its `srcdoc` markup,
+    /// or an empty about:blank document when `src` is missing, empty or about:blank. Both share
+    /// this document's providers, so they inherit its origin, and resolve relative URLs against
+    /// its base. Frames that would load from the network stay empty.
+    pub(crate) fn load_iframe(&mut self, node_id: usize) {
+        let node = &self.nodes[node_id];
+        let source = match node.attr(local_name!("srcdoc")) {
+            Some(srcdoc) => Some(("about:srcdoc", srcdoc.to_string())),
+            None => match node.attr(local_name!("src")).map(str::trim) {
+                None | Some("") => Some(("about:blank", String::new())),
+                Some(src) if src.eq_ignore_ascii_case("about:blank") => Some(("about:blank", String::new())),
+                Some(_) => None,
+            },
+        };
+        let Some((url, html)) = source else {
+            if self.sub_dom_nodes.contains(&node_id) {
+                self.remove_sub_dom(node_id);
+                self.nodes[node_id].insert_damage(ALL_DAMAGE);
+            }
+            return;
+        };
+
+        // The frame's real size is only known after layout, which resizes it
+        let font_ctx = self.font_ctx.lock().unwrap().clone();
+        let sub_dom = HtmlParser::new().parse(&html, DomConfig {
+            viewport: Some(self.viewport),
+            base_url: Some(url.to_string()),
+            fallback_base_url: Some(self.url.base_url().to_string()),
+            net_provider: Some(self.net_provider.clone()),
+            shell_provider: Some(self.shell_provider.clone()),
+            nav_provider: Some(self.nav_provider.clone()),
+            js_provider: Some(self.js_provider.clone()),
+            font_ctx: Some(font_ctx),
+            block_images: self.block_images,
+            ..Default::default()
+        });
+        self.set_sub_dom(node_id, Box::new(PlainDom(sub_dom)));
+        self.nodes[node_id].insert_damage(ALL_DAMAGE);
+        self.shell_provider.request_redraw();
+    }
+
     pub fn append_text_to_node(&mut self, node_id: usize, text: &str) -> Result<(), AppendTextErr> {
         let node = &mut self.nodes[node_id];
         node.insert_damage(ALL_DAMAGE);
@@ -2,6 +2,8 @@ use crate::dom::damage::{HoistedPaintChildren, ALL_DAMAGE};
 use crate::dom::events::EventListenerRegistry;
 use crate::dom::{AbstractDom, ZERO};
 use crate::layout::table::TableContext;
+use crate::dom::renamed_properties::translate_renamed_properties;
+use crate::dom::stylo_to_kurbo::{resolve_2d_transform, resolve_clip_path};
 use crate::ui::TextBrush;
 use atomic_refcell::{AtomicRef, AtomicRefCell, AtomicRefMut};
 use bitflags::bitflags;
@@ -24,7 +26,7 @@ use std::{fmt, ptr};
 use blitz_traits::shell::ShellProvider;
 use cssparser::ParserInput;
 use keyboard_types::Modifiers;
-use kurbo::Affine;
+use kurbo::{Affine, Shape};
 use style::data::ElementData as StyleElementData;
 use style::data::ElementData as StyloElementData;
 use style::invalidation::element::restyle_hints::RestyleHint;
@@ -37,7 +39,7 @@ use style::servo_arc::{Arc as ServoArc, Arc};
 use style::shared_lock::{Locked, SharedRwLock};
 use style::stylesheets::{CssRuleType, DocumentStyleSheet, Origin, UrlExtraData};
 use style::stylist::CascadeData;
-use style::values::computed::{Display, PositionProperty};
+use style::values::computed::{CSSPixelLength, Display, PositionProperty};
 use style::values::specified::box_::{DisplayInside, DisplayOutside};
 use style::properties::generated::longhands::position::computed_value::T as Position;
 use style_traits::{ParsingMode, ToCss};
@@ -220,9 +222,53 @@ impl TextData {
 pub struct TextLayout {
     pub text: String,
     pub content_widths: Option<ContentWidths>,
+    /// Inline box sizes `content_widths` was measured with
+    pub content_widths_boxes: u64,
+    /// Inputs of the last line breaking pass. While they stay the same the lines already in
+    /// `layout` are reused, so relayouts that don't change this text's width skip breaking.
+    pub line_break_key: Option<LineBreakKey>,
+    /// What `layout` was shaped from. Rebuilding the inline context with the same inputs keeps
+    /// the shaped layout instead of shaping the text again.
+    pub shaping_key: Option<ShapingKey>,
     pub layout: parley::layout::Layout<TextBrush>,
 }
 
+/// Everything shaping an inline layout depends on: the scale, the fonts that were loaded and what
+/// was pushed to the tree builder, in order
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShapingKey {
+    pub scale: f32,
+    /// `FONT_GENERATION` when the layout was shaped
+    pub font_generation: u64,
+    pub inputs: Vec<ShapingInput>,
+}
+
+/// One call made on the tree builder
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShapingInput {
+    Style(parley::TextStyle<'static, 'static, TextBrush>),
+    /// An empty style modification span, as pushed around `<br>`
+    StyleModification,
+    PopStyle,
+    WhiteSpace(parley::WhiteSpaceCollapse),
+    /// Hash of the pushed text
+    Text(u64),
+    InlineBox { id: u64, kind: parley::InlineBoxKind },
+}
+
+/// Everything line breaking depends on besides the shaped text
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineBreakKey {
+    pub max_advance: f32,
+    pub first_line_max_advance: f32,
+    pub line_x: f32,
+    pub line_y: f32,
+    pub text_indent: f32,
+    pub alignment: parley::layout::Alignment,
+    /// Hash of the inline boxes' sizes
+    pub inline_boxes: u64,
+}
+
 impl TextLayout {
     pub fn new() -> Self {
         Default::default()
@@ -233,6 +279,31 @@ impl TextLayout {
             .content_widths
             .get_or_insert_with(|| self.layout.calculate_content_widths())
     }
+
+    /// Content widths for the current inline box sizes, measured again only when those changed
+    pub fn content_widths_for_boxes(&mut self) -> ContentWidths {
+        let boxes = self.inline_boxes_hash();
+        if self.content_widths_boxes != boxes {
+            self.content_widths = None;
+            self.content_widths_boxes = boxes;
+        }
+        self.content_widths()
+    }
+
+    pub fn inline_boxes_hash(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        for ibox in self.layout.inline_boxes() {
+            (ibox.id, ibox.width.to_bits(), ibox.height.to_bits()).hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Forget measurements made on the previous contents, after the layout was rebuilt or restyled
+    pub fn invalidate_measurements(&mut self) {
+        self.content_widths = None;
+        self.line_break_key = None;
+    }
 }
 
 impl std::fmt::Debug for TextLayout {
@@ -400,8 +471,9 @@ impl ElementData {
 
     pub fn flush_style_attribute(&mut self, guard: &SharedRwLock, url_extra_data: &UrlExtraData) {
         self.style_attribute = self.attr(local_name!("style")).map(|style| {
+            let translated = translate_renamed_properties(style);
             ServoArc::new(guard.wrap(parse_style_attribute(
-                style,
+                translated.as_deref().unwrap_or(style),
                 url_extra_data,
                 None,
                 QuirksMode::NoQuirks,
@@ -606,6 +678,22 @@ impl ElementData {
         }
     }
 
+    /// How far a `<progress>` bar is filled, from 0 to 1. `None` when it is indeterminate (no
+    /// valid `value` attribute) or this isn't a progress element.
+    pub fn progress_position(&self) -> Option<f64> {
+        if self.name.local != local_name!("progress") {
+            return None;
+        }
+        let parse = |name: LocalName| {
+            self.attr(name)
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .filter(|value| value.is_finite())
+        };
+        let value = parse(local_name!("value"))?;
+        let max = parse(local_name!("max")).filter(|max| *max > 0.0).unwrap_or(1.0);
+        Some(value.clamp(0.0, max) / max)
+    }
+
     pub fn file_data(&self) -> Option<&FileData> {
         match &self.special_data {
             SpecialElementData::FileInput(data) => Some(data),
@@ -701,9 +789,24 @@ impl From<Vec<PathBuf>> for FileData {
 pub enum ImageData {
     Raster(RasterImageData),
     Svg(std::sync::Arc<usvg::Tree>),
+    /// Dropped from the image cache to save memory; keeps its size so layout doesn't change
+    Evicted { width: f32, height: f32 },
+    /// Not fetched because the user turned images off; drawn as a placeholder with the alt text,
+    /// which is laid out once the element has styles
+    Blocked(Option<AltTextLayout>),
     None
 }
 
+/// The alt text drawn in place of a blocked image
+#[derive(Clone)]
+pub struct AltTextLayout(pub Box<parley::Layout<TextBrush>>);
+
+impl fmt::Debug for AltTextLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AltTextLayout")
+    }
+}
+
 impl From<usvg::Tree> for ImageData {
     fn from(value: usvg::Tree) -> Self {
         ImageData::Svg(std::sync::Arc::new(value))
@@ -711,11 +814,25 @@ impl From<usvg::Tree> for ImageData {
 }
 
 impl ImageData {
-    fn is_some(&self) -> bool {
+    pub(crate) fn is_some(&self) -> bool {
         !matches!(self, Self::None)
     }
 
-    fn is_none(&self) -> bool {
+    /// Whether there are pixels or vector data to draw
+    pub(crate) fn is_loaded(&self) -> bool {
+        matches!(self, Self::Raster(_) | Self::Svg(_))
+    }
+
+    /// What is left of the image after evicting it
+    pub(crate) fn evicted(&self) -> Self {
+        match self {
+            Self::Raster(data) => Self::Evicted { width: data.width as f32, height: data.height as f32 },
+            Self::Svg(tree) => Self::Evicted { width: tree.size().width(), height: tree.size().height() },
+            other => other.clone(),
+        }
+    }
+
+    pub(crate) fn is_none(&self) -> bool {
         matches!(self, Self::None)
     }
 }
@@ -773,6 +890,8 @@ bitflags! {
         const IS_INLINE_ROOT = 0b00000001;
         const IS_TABLE_ROOT = 0b00000010;
         const IS_IN_DOCUMENT = 0b00000100;
+        /// A dialog opened with showModal(), painted in the top layer
+        const IS_MODAL = 0b00001000;
     }
 }
 
@@ -792,6 +911,11 @@ impl DomNodeFlags {
         self.contains(DomNodeFlags::IS_IN_DOCUMENT)
     }
 
+    #[inline]
+    pub fn is_modal(&self) -> bool {
+        self.contains(DomNodeFlags::IS_MODAL)
+    }
+
     #[inline]
     pub fn reset_reconstruction_flags(&mut self) {
         self.remove(DomNodeFlags::IS_INLINE_ROOT | DomNodeFlags::IS_TABLE_ROOT);
@@ -1084,6 +1208,13 @@ impl DomNode {
         self.element_state.contains(ElementState::HOVER)
     }
 
+    /// Set a link's `VISITED` or `UNVISITED` state, or neither for an element that isn't a link
+    pub fn set_link_state(&mut self, state: ElementState) {
+        self.element_state.remove(ElementState::VISITED | ElementState::UNVISITED);
+        self.element_state.insert(state);
+        self.set_restyle_hint(RestyleHint::restyle_subtree())
+    }
+
     pub fn attrs(&self) -> Option<&AttributeMap> {
         Some(&self.element_data()?.attributes)
     }
@@ -1690,7 +1821,7 @@ impl DomNode {
     ///
     /// Unlike `absolute_position`, this keeps the node's own scroll offset out
     /// of the border-box position and only applies ancestor scroll offsets.
-    fn page_border_origin(&self) -> Point<f32> {
+    pub(crate) fn page_border_origin(&self) -> Point<f32> {
         match self.layout_parent.get() {
             Some(parent_id) => {
                 let parent = self.get_node(parent_id);
@@ -1707,6 +1838,26 @@ impl DomNode {
         }
     }
 
+    /// Map a point in the border box's coordinates back through the element's 2D transform, to
+    /// where it is in the untransformed box
+    fn untransform_local_point(&self, x: f32, y: f32) -> (f32, f32) {
+        let Some(style) = self.primary_styles() else {
+            return (x, y);
+        };
+        let size = self.final_layout.size;
+        let reference_box = euclid::Rect::new(
+            euclid::Point2D::new(CSSPixelLength::new(0.0), CSSPixelLength::new(0.0)),
+            euclid::Size2D::new(CSSPixelLength::new(size.width), CSSPixelLength::new(size.height)),
+        );
+        match resolve_2d_transform(style.get_box(), reference_box, 1.0) {
+            Some(transform) if transform.determinant() != 0.0 => {
+                let point = transform.inverse() * kurbo::Point::new(x as f64, y as f64);
+                (point.x as f32, point.y as f32)
+            }
+            _ => (x, y),
+        }
+    }
+
     fn hit_page_space(&self, page_x: f32, page_y: f32) -> Option<HitResult> {
         use style::computed_values::visibility::T as Visibility;
 
@@ -1726,12 +1877,25 @@ impl DomNode {
         // and scrolled local for descendant/content hit testing.
         let local_x = page_x - origin.x;
         let local_y = page_y - origin.y;
+
+        // What clip-path cuts away can't be hit, on this element or its descendants
+        if let Some(style) = self.primary_styles()
+            && let Some(clip_path) = resolve_clip_path(&style, &self.final_layout, 1.0)
+            && !clip_path.contains(kurbo::Point::new(local_x as f64, local_y as f64))
+        {
+            return None;
+        }
+
         let scrolled_x = local_x + self.scroll_offset.x as f32;
         let scrolled_y = local_y + self.scroll_offset.y as f32;
 
+        // The renderer draws the element's own box and text through its transform, but not yet
+        // its child boxes, so only hits on this element go back through it
+        let (own_x, own_y) = self.untransform_local_point(local_x, local_y);
+
         let size = self.final_layout.size;
         let matches_self =
-            !(local_x < 0.0 || local_x > size.width || local_y < 0.0 || local_y > size.height);
+            !(own_x < 0.0 || own_x > size.width || own_y < 0.0 || own_y > size.height);
 
         let content_size = self.final_layout.content_size;
         let matches_content = !(scrolled_x < 0.0
@@ -1775,7 +1939,10 @@ impl DomNode {
                 x: self.final_layout.padding.left + self.final_layout.border.left,
                 y: self.final_layout.padding.top + self.final_layout.border.top,
             };
-            (scrolled_x - content_box_offset.x, scrolled_y - content_box_offset.y)
+            (
+                own_x + self.scroll_offset.x as f32 - content_box_offset.x,
+                own_y + self.scroll_offset.y as f32 - content_box_offset.y,
+            )
         } else {
             (scrolled_x, scrolled_y)
         };
@@ -1801,11 +1968,12 @@ impl DomNode {
             let child_scrolled_x = child_local_x + child.scroll_offset.x as f32;
             let child_scrolled_y = child_local_y + child.scroll_offset.y as f32;
 
+            let (child_own_x, child_own_y) = child.untransform_local_point(child_local_x, child_local_y);
             let child_size = child.final_layout.size;
-            let child_matches_self = !(child_local_x < 0.0
-                || child_local_x > child_size.width
-                || child_local_y < 0.0
-                || child_local_y > child_size.height);
+            let child_matches_self = !(child_own_x < 0.0
+                || child_own_x > child_size.width
+                || child_own_y < 0.0
+                || child_own_y > child_size.height);
 
             let child_content_size = child.final_layout.content_size;
             let child_matches_content = !(child_scrolled_x < 0.0
@@ -1869,8 +2037,8 @@ impl DomNode {
                     let node_id = layout.styles()[style_index].brush.id;
                     return Some(HitResult {
                         node_id,
-                        x: local_x,
-                        y: local_y,
+                        x: own_x,
+                        y: own_y,
                         is_text: true,
                     });
                 }
@@ -1881,8 +2049,8 @@ impl DomNode {
         if matches_self && !ignores_pointer_events {
             return Some(HitResult {
                 node_id: self.id,
-                x: local_x,
-                y: local_y,
+                x: own_x,
+                y: own_y,
                 is_text: false,
             });
         }
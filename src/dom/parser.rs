@@ -32,6 +32,10 @@ impl HtmlProvider {
     ) {
         DomHtmlParser::parse_inner_html(dom, element_id, html);
     }
+
+    pub fn parse_detached_document(&self, dom: &mut Dom, source: &str, is_xml: bool) -> usize {
+        DomHtmlParser::parse_detached_document(dom, source, is_xml)
+    }
 }
 
 impl HtmlParser {
@@ -56,6 +60,9 @@ pub struct DomHtmlParser<'m> {
 
     pub quirks_mode: Cell<QuirksMode>,
     pub is_xml: bool,
+
+    /// The node the parser treats as the document, 0 unless parsing a detached document
+    document: usize,
 }
 
 impl<'m> DomHtmlParser<'m> {
@@ -65,6 +72,7 @@ impl<'m> DomHtmlParser<'m> {
             errors: RefCell::new(Vec::new()),
             quirks_mode: Cell::new(QuirksMode::NoQuirks),
             is_xml: false,
+            document: 0,
         }
     }
 
@@ -102,6 +110,40 @@ impl<'m> DomHtmlParser<'m> {
         }
     }
 
+    /// Parse a whole document into a new `Document` node that isn't attached to this DOM, for
+    /// `DOMParser`. Nothing in it is connected, so no resources are loaded, and HTML is parsed
+    /// with scripting disabled like the spec's parser for documents without a browsing context.
+    pub fn parse_detached_document(dom: &mut Dom, source: &str, is_xml: bool) -> usize {
+        let document = dom.create_node(NodeData::Document);
+        let mut sink = DomHtmlParser::new(dom);
+        sink.document = document;
+        sink.is_xml = is_xml;
+
+        if is_xml {
+            xml5ever::driver::parse_document(sink, Default::default())
+                .from_utf8()
+                .read_from(&mut source.as_bytes())
+                .unwrap();
+        } else {
+            let opts = ParseOpts {
+                tokenizer: TokenizerOpts::default(),
+                tree_builder: TreeBuilderOpts {
+                    exact_errors: true,
+                    scripting_enabled: false,
+                    iframe_srcdoc: false,
+                    drop_doctype: false,
+                    quirks_mode: QuirksMode::NoQuirks,
+                },
+            };
+            parse_document(sink, opts)
+                .from_utf8()
+                .read_from(&mut source.as_bytes())
+                .unwrap();
+        }
+
+        document
+    }
+
     pub fn parse_inner_html(
         mutr: &mut Dom,
         element_id: usize,
@@ -150,7 +192,7 @@ impl<'m> TreeSink for DomHtmlParser<'m> {
 
     fn finish(self) -> Self::Output {
         for error in self.errors.borrow().iter() {
-            println!("PARSE ERROR: {error}");
+            tracing::debug!("HTML parse error: {error}");
         }
     }
 
@@ -159,7 +201,7 @@ impl<'m> TreeSink for DomHtmlParser<'m> {
     }
 
     fn get_document(&self) -> Self::Handle {
-        0
+        self.document
     }
 
     fn elem_name<'a>(&'a self, target: &'a Self::Handle) -> Self::ElemName<'a> {
@@ -230,7 +272,7 @@ impl<'m> TreeSink for DomHtmlParser<'m> {
         let doctype = dom.create_node(NodeData::Doctype {
             name,
         });
-        dom.append_children(0, &[doctype]);
+        dom.append_children(self.document, &[doctype]);
     }
 
     fn get_template_contents(&self, target: &Self::Handle) -> Self::Handle {
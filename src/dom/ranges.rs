@@ -0,0 +1,344 @@
+// Ranges and the selection as scripts see them: boundary points are a node and an offset in it, a
+// UTF-16 offset for text and a child index for other nodes. `TextSelection` keeps byte offsets
+// into the laid-out text of inline roots instead, which has its whitespace collapsed, may be
+// text-transformed and may start with a list marker, so the two are lined up character by
+// character.
+use std::cmp::Ordering;
+use parley::Cursor;
+use crate::dom::traverse::TreeTraverser;
+use crate::dom::{Dom, NodeData};
+
+/// A boundary point: a node and an offset in it
+pub(crate) type BoundaryPoint = (usize, usize);
+
+/// Where a character of an inline root's laid-out text came from
+#[derive(Debug, Clone, Copy)]
+struct SourcePosition {
+    /// Byte offset of the character in the laid-out text
+    byte: usize,
+    /// The text node it came from and its UTF-16 offset there
+    node: usize,
+    offset: usize,
+}
+
+impl Dom {
+    /// The selection's anchor and focus as text positions, when there is a selection
+    pub(crate) fn selection_endpoints(&self) -> Option<(BoundaryPoint, BoundaryPoint)> {
+        let lookup = |parent_id, idx| self.find_anonymous_block_by_index(parent_id, idx);
+        let selection = &self.text_selection;
+        let anchor_root = selection.anchor.resolve_node_id(lookup)?;
+        let focus_root = selection.focus.resolve_node_id(lookup)?;
+        Some((
+            self.inline_offset_to_text_position(anchor_root, selection.anchor.offset)?,
+            self.inline_offset_to_text_position(focus_root, selection.focus.offset)?,
+        ))
+    }
+
+    /// Select from `anchor` to `focus`. Returns false, leaving the selection as it was, when
+    /// either isn't in laid-out text.
+    pub(crate) fn set_selection_endpoints(&mut self, anchor: BoundaryPoint, focus: BoundaryPoint) -> bool {
+        let (Some(anchor), Some(focus)) = (self.inline_offset_of(anchor), self.inline_offset_of(focus)) else {
+            return false;
+        };
+        self.set_text_selection(anchor.0, anchor.1, focus.0, focus.1);
+        self.shell_provider.request_redraw();
+        true
+    }
+
+    /// Whether the selection changed since the last call, for `selectionchange`
+    pub(crate) fn take_selection_change(&mut self) -> bool {
+        std::mem::take(&mut self.selection_changed)
+    }
+
+    /// Where `a` is relative to `b` in tree order
+    pub(crate) fn compare_boundary_points(&self, a: BoundaryPoint, b: BoundaryPoint) -> Ordering {
+        if a.0 == b.0 {
+            return a.1.cmp(&b.1);
+        }
+        // A point in an ancestor is before its descendants up to its offset and after them past it
+        for (outer, inner, flip) in [(a, b, false), (b, a, true)] {
+            let mut child = inner.0;
+            while let Some(parent) = self.nodes[child].parent {
+                if parent == outer.0 {
+                    let index = self.nodes[parent].index_of_child(child).unwrap_or(0);
+                    let order = if index < outer.1 { Ordering::Greater } else { Ordering::Less };
+                    return if flip { order.reverse() } else { order };
+                }
+                child = parent;
+            }
+        }
+        self.compare_document_order(a.0, b.0)
+    }
+
+    /// The text of the text nodes between `start` and `end`
+    pub(crate) fn range_text(&self, start: BoundaryPoint, end: BoundaryPoint) -> String {
+        let mut text = String::new();
+        for node_id in TreeTraverser::new(self) {
+            let NodeData::Text(data) = &self.nodes[node_id].data else {
+                continue;
+            };
+            if start.0 != node_id && self.compare_boundary_points((node_id, 0), start) == Ordering::Less {
+                continue;
+            }
+            if end.0 != node_id && self.compare_boundary_points((node_id, 0), end) == Ordering::Greater {
+                break;
+            }
+            let units: Vec<u16> = data.content.encode_utf16().collect();
+            let from = if start.0 == node_id { start.1.min(units.len()) } else { 0 };
+            let to = if end.0 == node_id { end.1.min(units.len()) } else { units.len() };
+            if from < to {
+                text.push_str(&String::from_utf16_lossy(&units[from..to]));
+            }
+        }
+        text
+    }
+
+    /// The caret position at a point in client coordinates: a point in laid-out text, an offset
+    /// into the value of a text field, or else the start of the element there
+    pub(crate) fn caret_position_from_point(&self, x: f32, y: f32) -> Option<BoundaryPoint> {
+        let element = self.element_from_point(x, y)?;
+        if let Some(position) = self.text_input_position_at(x, y) {
+            return Some(position);
+        }
+        self.find_text_position_client(x, y)
+            .and_then(|(root, byte)| self.inline_offset_to_text_position(root, byte))
+            .or(Some((element, 0)))
+    }
+
+    /// The UTF-16 offset into a text field's value at a point over it
+    fn text_input_position_at(&self, x: f32, y: f32) -> Option<BoundaryPoint> {
+        let hit = self.hit_client(x, y)?;
+        let node = &self.nodes[hit.node_id];
+        let text_input_data = node.element_data()?.text_input_data()?;
+        let layout = text_input_data.editor.try_layout()?;
+
+        let mut content_box_offset = taffy::Point {
+            x: node.final_layout.padding.left + node.final_layout.border.left,
+            y: node.final_layout.padding.top + node.final_layout.border.top,
+        };
+        if !text_input_data.is_multiline {
+            let input_height = layout.height() / layout.scale();
+            content_box_offset.y += ((node.final_layout.content_box_height() - input_height) / 2.0).max(0.0);
+        }
+        let scale = self.viewport.scale();
+        let cursor = Cursor::from_point(
+            layout,
+            (hit.x - content_box_offset.x) * scale,
+            (hit.y - content_box_offset.y) * scale,
+        );
+        let text = text_input_data.editor.raw_text();
+        let before = text.get(..cursor.index()).unwrap_or(text);
+        Some((hit.node_id, before.encode_utf16().count()))
+    }
+
+    fn inline_offset_to_text_position(&self, root: usize, byte: usize) -> Option<BoundaryPoint> {
+        let (positions, end) = self.inline_source_positions(root);
+        positions
+            .iter()
+            .find(|position| position.byte >= byte)
+            .map(|position| (position.node, position.offset))
+            .or(end)
+    }
+
+    /// The inline root `point` is laid out in, and the byte offset it is at there
+    fn inline_offset_of(&self, point: BoundaryPoint) -> Option<(usize, usize)> {
+        let (node_id, offset) = self.text_position_of(point)?;
+        let root = self.inline_root_of(node_id)?;
+        let text_len = self.nodes[root].element_data()?.inline_layout_data.as_ref()?.text.len();
+        let (positions, _) = self.inline_source_positions(root);
+        let mut seen = false;
+        for position in &positions {
+            if position.node == node_id {
+                seen = true;
+                if position.offset >= offset {
+                    return Some((root, position.byte));
+                }
+            } else if seen {
+                return Some((root, position.byte));
+            }
+        }
+        seen.then_some((root, text_len))
+    }
+
+    /// `point` as an offset in a text node. A point between an element's children moves to the
+    /// start of the next text after it, or the end of the last text inside the element.
+    fn text_position_of(&self, (node_id, offset): BoundaryPoint) -> Option<BoundaryPoint> {
+        let node = self.get_node(node_id)?;
+        if let NodeData::Text(data) = &node.data {
+            return Some((node_id, offset.min(data.content.encode_utf16().count())));
+        }
+        let children = node.children.get(offset.min(node.children.len())..).unwrap_or_default();
+        let after = children.iter().find_map(|&child| self.first_visible_text(child));
+        after.map(|text| (text, 0)).or_else(|| {
+            let last = self.last_visible_text(node_id)?;
+            let NodeData::Text(data) = &self.nodes[last].data else {
+                return None;
+            };
+            Some((last, data.content.encode_utf16().count()))
+        })
+    }
+
+    fn first_visible_text(&self, node_id: usize) -> Option<usize> {
+        TreeTraverser::new_with_root(self, node_id).find(|&id| self.is_visible_text(id))
+    }
+
+    fn last_visible_text(&self, node_id: usize) -> Option<usize> {
+        TreeTraverser::new_with_root(self, node_id).filter(|&id| self.is_visible_text(id)).last()
+    }
+
+    fn is_visible_text(&self, node_id: usize) -> bool {
+        matches!(&self.nodes[node_id].data, NodeData::Text(data) if !data.content.trim().is_empty())
+    }
+
+    /// The inline root a text node is laid out in: its nearest inline root ancestor, or the
+    /// anonymous block its inline content was wrapped in next to blocks
+    fn inline_root_of(&self, node_id: usize) -> Option<usize> {
+        let mut child = node_id;
+        let mut ancestor = self.nodes[node_id].parent?;
+        loop {
+            let node = &self.nodes[ancestor];
+            if node.flags.is_inline_root() {
+                return Some(ancestor);
+            }
+            let anonymous = node.layout_children.borrow().as_ref().and_then(|children| {
+                children.iter().copied().find(|&id| {
+                    let block = &self.nodes[id];
+                    block.is_anonymous()
+                        && block.layout_children.borrow().as_ref().is_some_and(|inline| inline.contains(&child))
+                })
+            });
+            if anonymous.is_some() {
+                return anonymous;
+            }
+            child = ancestor;
+            ancestor = node.parent?;
+        }
+    }
+
+    /// Line the laid-out text of `root` up with the text nodes it was built from. Characters
+    /// that came from no text node, like a list marker, are left out. Also returns the position
+    /// just after the last character that was lined up.
+    fn inline_source_positions(&self, root: usize) -> (Vec<SourcePosition>, Option<BoundaryPoint>) {
+        let Some(text) = self.nodes[root].element_data().and_then(|element| element.inline_layout_data.as_ref()) else {
+            return (Vec::new(), None);
+        };
+        let mut text_nodes = Vec::new();
+        let node = &self.nodes[root];
+        let children = if node.is_anonymous() {
+            node.layout_children.borrow().clone().unwrap_or_default()
+        } else {
+            node.children.clone()
+        };
+        for child in children {
+            self.collect_inline_text_nodes(child, &mut text_nodes);
+        }
+
+        let mut source = Vec::new();
+        for node_id in text_nodes {
+            let NodeData::Text(data) = &self.nodes[node_id].data else {
+                continue;
+            };
+            let mut offset = 0;
+            for c in data.content.chars() {
+                source.push((node_id, offset, c));
+                offset += c.len_utf16();
+            }
+        }
+
+        align_source_text(&text.text, &source)
+    }
+
+    /// The text nodes laid out in the same inline root as `node_id`, in order. Nested inline
+    /// roots, like inline blocks, lay out their own text.
+    fn collect_inline_text_nodes(&self, node_id: usize, text_nodes: &mut Vec<usize>) {
+        let node = &self.nodes[node_id];
+        match &node.data {
+            NodeData::Text(_) => text_nodes.push(node_id),
+            NodeData::Element(_) => {
+                let hidden = node.primary_styles().is_some_and(|style| style.get_box().display.is_none());
+                if node.flags.is_inline_root() || hidden {
+                    return;
+                }
+                for &child in &node.children {
+                    self.collect_inline_text_nodes(child, text_nodes);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Line up `text`, laid out from the `(node, offset, char)` characters of `source`, with them.
+/// A collapsed run of whitespace lines up with its first character, and characters are matched
+/// ignoring case, since text-transform may have changed it.
+fn align_source_text(text: &str, source: &[(usize, usize, char)]) -> (Vec<SourcePosition>, Option<BoundaryPoint>) {
+    let whitespace_next = |next: usize| source.get(next).is_some_and(|&(_, _, s)| s.is_whitespace());
+    let mut positions = Vec::new();
+    let mut next = 0;
+    for (byte, c) in text.char_indices() {
+        if c.is_whitespace() {
+            if whitespace_next(next) {
+                let (node, offset, _) = source[next];
+                positions.push(SourcePosition { byte, node, offset });
+                next += 1;
+            }
+            continue;
+        }
+        while whitespace_next(next) {
+            next += 1;
+        }
+        if source.get(next).is_some_and(|&(_, _, s)| s == c || s.to_lowercase().eq(c.to_lowercase())) {
+            let (node, offset, _) = source[next];
+            positions.push(SourcePosition { byte, node, offset });
+            next += 1;
+        }
+    }
+    let end = next.checked_sub(1).map(|last| {
+        let (node, offset, c) = source[last];
+        (node, offset + c.len_utf16())
+    });
+    (positions, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::align_source_text;
+
+    fn source(nodes: &[(usize, &str)]) -> Vec<(usize, usize, char)> {
+        let mut source = Vec::new();
+        for &(node, text) in nodes {
+            let mut offset = 0;
+            for c in text.chars() {
+                source.push((node, offset, c));
+                offset += c.len_utf16();
+            }
+        }
+        source
+    }
+
+    fn aligned(text: &str, nodes: &[(usize, &str)]) -> Vec<(usize, usize, usize)> {
+        let (positions, _) = align_source_text(text, &source(nodes));
+        positions.iter().map(|position| (position.byte, position.node, position.offset)).collect()
+    }
+
+    #[test]
+    fn collapsed_whitespace_lines_up_with_its_first_character() {
+        let positions = aligned("a b", &[(1, "\n  a \n b")]);
+        assert_eq!(positions, vec![(0, 1, 3), (1, 1, 4), (2, 1, 7)]);
+        let (_, end) = align_source_text("a b", &source(&[(1, "\n  a \n b"), (2, "  ")]));
+        assert_eq!(end, Some((1, 8)));
+    }
+
+    #[test]
+    fn text_spans_nodes_and_ignores_markers_and_case() {
+        // A list marker comes from no text node, and text-transform uppercased the second node
+        let positions = aligned("• ab CD", &[(4, "ab "), (6, "cd")]);
+        assert_eq!(positions, vec![(4, 4, 0), (5, 4, 1), (6, 4, 2), (7, 6, 0), (8, 6, 1)]);
+    }
+
+    #[test]
+    fn offsets_count_utf16_code_units() {
+        let positions = aligned("😀x", &[(2, "😀x")]);
+        assert_eq!(positions, vec![(0, 2, 0), (4, 2, 2)]);
+    }
+}
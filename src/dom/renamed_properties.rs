@@ -0,0 +1,100 @@
+// Properties that Stylo's Servo build doesn't parse. Stylesheets and style attributes have their
+// declarations renamed to custom properties before parsing, and the code that uses them reads the
+// computed text back from the custom property.
+use style::Atom;
+use style::properties::ComputedValues;
+use style_traits::ToCss;
+
+/// `backdrop-filter`. It doesn't inherit, so the UA stylesheet resets it on every element.
+pub(crate) const BACKDROP_FILTER: &str = "--stokes-backdrop-filter";
+pub(crate) const FONT_VARIANT_LIGATURES: &str = "--stokes-font-variant-ligatures";
+pub(crate) const FONT_VARIANT_NUMERIC: &str = "--stokes-font-variant-numeric";
+
+const RENAMED: [(&str, &str); 3] = [
+    ("backdrop-filter", BACKDROP_FILTER),
+    ("font-variant-ligatures", FONT_VARIANT_LIGATURES),
+    ("font-variant-numeric", FONT_VARIANT_NUMERIC),
+];
+
+/// Rename declarations of the properties above, with or without a `-webkit-` prefix. `None` when
+/// there is nothing to rename.
+pub(crate) fn translate_renamed_properties(css: &str) -> Option<String> {
+    let mut translated = None;
+    for (name, replacement) in RENAMED {
+        let source: &str = translated.as_deref().unwrap_or(css);
+        if let Some(renamed) = rename_property(source, name, replacement) {
+            translated = Some(renamed);
+        }
+    }
+    translated
+}
+
+fn rename_property(css: &str, name: &str, replacement: &str) -> Option<String> {
+    if !css.contains(name) {
+        return None;
+    }
+
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    let mut out = String::with_capacity(css.len());
+    let mut copied = 0;
+    let mut search = 0;
+    while let Some(found) = css[search..].find(name) {
+        let mut start = search + found;
+        let end = start + name.len();
+        search = end;
+        if css[..start].ends_with("-webkit-") {
+            start -= "-webkit-".len();
+        }
+        let before = css[..start].chars().next_back();
+        let is_declaration = css[end..].trim_start().starts_with(':');
+        if before.is_some_and(is_ident) || !is_declaration {
+            continue;
+        }
+        out.push_str(&css[copied..start]);
+        out.push_str(replacement);
+        copied = end;
+    }
+    if copied == 0 {
+        return None;
+    }
+    out.push_str(&css[copied..]);
+    Some(out)
+}
+
+/// The computed text of a renamed property, if it is set on the element or inherited
+pub(crate) fn renamed_property_value(style: &ComputedValues, property: &str) -> Option<String> {
+    let name = Atom::from(&property[2..]);
+    style.custom_properties().inherited.get(&name).map(|value| value.to_css_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renames_backdrop_filter_declarations() {
+        assert_eq!(
+            translate_renamed_properties(".bar { -webkit-backdrop-filter: blur(8px); backdrop-filter : blur(8px) }")
+                .unwrap(),
+            ".bar { --stokes-backdrop-filter: blur(8px); --stokes-backdrop-filter : blur(8px) }"
+        );
+        assert_eq!(
+            translate_renamed_properties("@supports (backdrop-filter: none) { a { color: red } }").unwrap(),
+            "@supports (--stokes-backdrop-filter: none) { a { color: red } }"
+        );
+        // Other properties and values that merely mention it are left alone
+        assert_eq!(translate_renamed_properties("a { --my-backdrop-filter: 1; transition: backdrop-filter 1s }"), None);
+        assert_eq!(translate_renamed_properties("a { color: red }"), None);
+    }
+
+    #[test]
+    fn renames_font_variant_declarations() {
+        assert_eq!(
+            translate_renamed_properties("td { font-variant-numeric: tabular-nums; font-variant-ligatures: none }")
+                .unwrap(),
+            "td { --stokes-font-variant-numeric: tabular-nums; --stokes-font-variant-ligatures: none }"
+        );
+        // The shorthand is parsed by stylo
+        assert_eq!(translate_renamed_properties("h1 { font-variant: small-caps }"), None);
+    }
+}
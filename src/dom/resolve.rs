@@ -1,42 +1,121 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use selectors::Element;
+use blitz_traits::shell::Viewport;
 use style::dom::TDocument;
 use style::selector_parser::RestyleDamage;
 use crate::dom::{Dom, DomEvent};
 use crate::dom::events::pointer::ScrollAnimationState;
 
+/// What one `resolve` had to redo. Mutations only restyle the elements their snapshots
+/// invalidate and only clear the layout caches of the boxes they damage, so on a large document
+/// these stay small compared to `nodes` after a class or attribute change.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResolveStats {
+    pub nodes: usize,
+    /// Nodes whose styles changed
+    pub restyled: usize,
+    /// Nodes whose layout cache was cleared
+    pub relaid_out: usize,
+    pub style_time: Duration,
+    pub layout_time: Duration,
+}
+
 impl Dom {
+    /// Size each frame's document to its frame's content box, then resolve it
+    fn resolve_sub_documents(&mut self, now: f64) {
+        let scale = self.viewport.scale();
+        let frames: Vec<usize> = self.sub_dom_nodes.iter().copied().collect();
+        for node_id in frames {
+            let taffy::Layout { size, border, padding, .. } = self.nodes[node_id].final_layout;
+            let width = (size.width - padding.left - padding.right - border.left - border.right).max(0.0);
+            let height = (size.height - padding.top - padding.bottom - border.top - border.bottom).max(0.0);
+            let viewport = Viewport {
+                window_size: ((width * scale).round() as u32, (height * scale).round() as u32),
+                ..self.viewport
+            };
+            let Some(sub_dom) = self.nodes[node_id].element_data_mut().and_then(|element| element.sub_dom_data_mut()) else {
+                continue;
+            };
+            let mut sub_dom = sub_dom.inner_mut();
+            let current = sub_dom.viewport;
+            if current.window_size != viewport.window_size
+                || current.scale() != viewport.scale()
+                || current.color_scheme != viewport.color_scheme
+            {
+                sub_dom.set_viewport(viewport);
+            }
+            sub_dom.resolve(now);
+        }
+    }
+
     pub(crate) fn resolve(&mut self, now: f64) {
         if TDocument::as_node(&&self.nodes[0])
             .first_element_child()
             .is_none()
         {
-            eprintln!("ERROR: No DOM - not resolving");
+            tracing::error!("No DOM - not resolving");
             return;
         }
 
+        let _span = tracing::debug_span!("resolve").entered();
+
         self.handle_messages();
 
         self.resolve_scroll_animation();
 
         let root_node_id = self.root_element().id;
 
-        self.flush_styles(now);
+        self.resolve_stats = ResolveStats::default();
+        let style_start = Instant::now();
+        {
+            let _span = tracing::debug_span!(target: "style", "style").entered();
+            self.flush_styles(now);
+            if self.update_used_color_scheme() {
+                self.flush_styles(now);
+            }
+
+            self.propagate_damage_flags(root_node_id, RestyleDamage::empty());
+        }
+        self.resolve_stats.style_time = style_start.elapsed();
+
+        let layout_start = Instant::now();
+        {
+            let _span = tracing::debug_span!(target: "layout", "layout").entered();
+            self.get_layout_children();
 
-        self.propagate_damage_flags(root_node_id, RestyleDamage::empty());
+            self.flush_styles_to_layout(root_node_id);
 
-        self.get_layout_children();
+            self.compute_layout();
 
-        self.flush_styles_to_layout(root_node_id);
+            self.place_top_layer();
+        }
+        self.resolve_stats.layout_time = layout_start.elapsed();
 
-        self.compute_layout();
+        self.resolve_sub_documents(now);
+
+        self.reload_evicted_images();
+        self.evict_images();
 
         {
+            let stats = &mut self.resolve_stats;
             for (_, node) in self.nodes.iter_mut() {
+                stats.nodes += 1;
+                if node.damage().is_some_and(|damage| !damage.is_empty()) {
+                    stats.restyled += 1;
+                }
                 node.clear_damage_mut();
                 node.unset_dirty_descendants();
             }
         }
+
+        let stats = self.resolve_stats;
+        if stats.restyled > 0 || stats.relaid_out > 0 {
+            tracing::debug!(
+                target: "style",
+                "Restyled {} and relaid out {} of {} nodes (style {:?}, layout {:?})",
+                stats.restyled, stats.relaid_out, stats.nodes, stats.style_time, stats.layout_time,
+            );
+        }
     }
 
     pub fn resolve_scroll_animation(&mut self) {
@@ -65,6 +144,9 @@ impl Dom {
                     self.scroll_animation = ScrollAnimationState::None;
                 }
             }
+            ScrollAnimationState::Smooth(_) => {
+                self.step_smooth_scroll();
+            }
             ScrollAnimationState::None => {
                 // Do nothing
             }
@@ -86,6 +168,14 @@ impl Dom {
             DomEvent::ResourceLoad(resource) => {
                 self.load_resource(resource);
             }
+            DomEvent::FontLoadStarted { family, url, display } => {
+                self.font_loads.start(family, url, display, Instant::now());
+            }
+            DomEvent::FontLoadFailed(url) => {
+                self.font_loads.finish(&url, false, Instant::now());
+                // Text the font hid shows in its fallback
+                self.shell_provider.request_redraw();
+            }
         }
     }
 }
\ No newline at end of file
@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Instant;
 use blitz_traits::net::{NetProvider, Request};
 use markup5ever::local_name;
 use peniko::Blob;
@@ -23,6 +24,15 @@ impl Dom {
         let node = &self.nodes[node_id];
         if let Some(raw_src) = node.attr(local_name!("src")) {
             if !raw_src.is_empty() {
+                if self.block_images {
+                    let node = &mut self.nodes[node_id];
+                    node.element_data_mut().unwrap().special_data =
+                        SpecialElementData::Image(Box::new(ImageData::Blocked(None)));
+                    node.cache.clear();
+                    node.insert_damage(ALL_DAMAGE);
+                    return;
+                }
+
                 let src = self.resolve_url(raw_src);
                 let src_string = src.as_str();
 
@@ -30,7 +40,7 @@ impl Dom {
                 if let Some(cached_image) = self.image_cache.get(src_string) {
                     let node = &mut self.nodes[node_id];
                     node.element_data_mut().unwrap().special_data =
-                        SpecialElementData::Image(Box::new(cached_image.clone()));
+                        SpecialElementData::Image(Box::new(cached_image));
                     node.cache.clear();
                     node.insert_damage(ALL_DAMAGE);
                     return;
@@ -45,7 +55,7 @@ impl Dom {
                 self.pending_images
                     .insert(src_string.to_string(), vec![(node_id, ImageType::Image)]);
 
-                self.net_provider.fetch(
+                self.net_provider.fetch_image(
                     self.id(),
                     Request::get(src),
                     ResourceHandler::boxed(
@@ -130,7 +140,7 @@ impl Dom {
 
     pub(crate) fn load_resource(&mut self, res: ResourceLoadResponse) {
         let Ok(resource) = res.result else {
-            eprintln!("Failed to load resource: {:?}", res.resolved_url);
+            tracing::warn!("Failed to load resource: {:?}", res.resolved_url);
             return;
         };
 
@@ -216,8 +226,13 @@ impl Dom {
                     }
                 }
             },
-            Resource::Font(bytes) => {
+            Resource::Font(url, bytes) => {
                 //println!("Loaded Font resource: {:?}", res.resolved_url);
+                if !self.font_loads.finish(&url, true, Instant::now()) {
+                    // Past its swap period, so the fallback stays. Text the font hid still shows.
+                    self.shell_provider.request_redraw();
+                    return;
+                }
                 let font = Blob::new(Arc::new(bytes));
 
                 // TODO: Implement FontInfoOveride
@@ -228,12 +243,13 @@ impl Dom {
                     .register_fonts(font.clone(), None);
 
                 drop(global_font_ctx);
+                crate::dom::FONT_GENERATION.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
 
                 // TODO: see if we can only invalidate if resolved fonts may have changed
                 self.invalidate_inline_contexts();
             }
             Resource::None => {
-                println!("Loaded resource with no data: {:?}", res.resolved_url);
+                tracing::debug!("Loaded resource with no data: {:?}", res.resolved_url);
                 // Do nothing
             }
         }
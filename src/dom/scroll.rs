@@ -0,0 +1,551 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use blitz_traits::shell::ShellProvider;
+use kurbo::Rect;
+use markup5ever::local_name;
+use style::computed_values::position::T as Position;
+use style::dom::{TDocument, TNode};
+use style::computed_values::scroll_behavior::T as ComputedScrollBehavior;
+use style::values::computed::Overflow;
+use taffy::MaybeResolve;
+use crate::dom::Dom;
+use crate::dom::intersection::ClientRect;
+use crate::layout::taffy::resolve_calc_value;
+use crate::dom::url::DocUrl;
+use crate::dom::events::pointer::{ScrollAnimationState, SmoothScrollState};
+
+/// How long an animated scroll takes to reach its destination
+const SMOOTH_SCROLL_DURATION_MS: f64 = 150.0;
+
+/// The `behavior` member of ScrollOptions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum ScrollBehavior {
+    /// Use the scroll container's `scroll-behavior` property
+    #[default]
+    Auto,
+    Instant,
+    Smooth,
+}
+
+impl ScrollBehavior {
+    pub(crate) fn parse(value: &str) -> Self {
+        match value {
+            "smooth" => ScrollBehavior::Smooth,
+            "instant" => ScrollBehavior::Instant,
+            _ => ScrollBehavior::Auto,
+        }
+    }
+}
+
+/// Where scrollIntoView puts the element along an axis (ScrollLogicalPosition)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ScrollAlignment {
+    Start,
+    Center,
+    End,
+    Nearest,
+}
+
+impl ScrollAlignment {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        match value {
+            "start" => Some(ScrollAlignment::Start),
+            "center" => Some(ScrollAlignment::Center),
+            "end" => Some(ScrollAlignment::End),
+            "nearest" => Some(ScrollAlignment::Nearest),
+            _ => None,
+        }
+    }
+
+    /// The scroll offset that shows `start..start + size` within a scrollport at `scroll` that is
+    /// `viewport` long
+    fn scroll_offset(self, start: f64, size: f64, scroll: f64, viewport: f64) -> f64 {
+        match self {
+            ScrollAlignment::Start => start,
+            ScrollAlignment::Center => start + size / 2.0 - viewport / 2.0,
+            ScrollAlignment::End => start + size - viewport,
+            ScrollAlignment::Nearest => {
+                let end = start + size;
+                if start >= scroll && end <= scroll + viewport {
+                    scroll
+                } else if start < scroll || size > viewport {
+                    start
+                } else {
+                    end - viewport
+                }
+            }
+        }
+    }
+}
+
+/// How far a sticky box at `start..start + size` moves along one axis to keep `inset_start` from
+/// the scrollport's start and `inset_end` from its end, without leaving its containing block
+/// (`limit_start..limit_end`). A side whose inset is `auto` doesn't stick.
+#[allow(clippy::too_many_arguments)]
+fn sticky_shift(
+    start: f64,
+    size: f64,
+    port_start: f64,
+    port_size: f64,
+    inset_start: Option<f64>,
+    inset_end: Option<f64>,
+    limit_start: f64,
+    limit_end: f64,
+) -> f64 {
+    let end = start + size;
+    if let Some(inset) = inset_start {
+        let shift = port_start + inset - start;
+        if shift > 0.0 {
+            return shift.min((limit_end - end).max(0.0));
+        }
+    }
+    if let Some(inset) = inset_end {
+        let shift = port_start + port_size - inset - end;
+        if shift < 0.0 {
+            return shift.max((limit_start - start).min(0.0));
+        }
+    }
+    0.0
+}
+
+/// Ease-out cubic: moves quickly at first and settles gently on the destination
+fn ease_out(progress: f64) -> f64 {
+    1.0 - (1.0 - progress).powi(3)
+}
+
+fn now_ms() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |elapsed| elapsed.as_secs_f64() * 1000.0)
+}
+
+impl Dom {
+    /// Whether the scroll container (the viewport when `None`) has `scroll-behavior: smooth`
+    fn has_smooth_scroll_behavior(&self, node_id: Option<usize>) -> bool {
+        let node_id = node_id.unwrap_or_else(|| self.root_element().id);
+        self.get_node(node_id)
+            .and_then(|node| node.primary_styles())
+            .is_some_and(|styles| styles.clone_scroll_behavior() == ComputedScrollBehavior::Smooth)
+    }
+
+    /// Scroll by a programmatic delta, animating it when `behavior` (or, for `Auto`, the scroll
+    /// container's `scroll-behavior`) asks for it
+    pub(crate) fn scroll_with_behavior(&mut self, target: Option<usize>, dx: f64, dy: f64, behavior: ScrollBehavior) {
+        let smooth = match behavior {
+            ScrollBehavior::Smooth => true,
+            ScrollBehavior::Instant => false,
+            ScrollBehavior::Auto => self.has_smooth_scroll_behavior(target),
+        };
+        if smooth {
+            self.smooth_scroll_by(target, dx, dy);
+        } else {
+            self.scroll_animation = ScrollAnimationState::None;
+            if self.scroll_by(target, dx, dy, &mut |_| {}) {
+                self.shell_provider.request_redraw();
+            }
+        }
+    }
+
+    /// Animate a scroll of `dx`, `dy` (same sign convention as `scroll_by`). Scrolling again while
+    /// an animation of the same target is running carries its remaining distance into the new one,
+    /// so repeated wheel notches and key presses accelerate instead of stuttering.
+    pub(crate) fn smooth_scroll_by(&mut self, target: Option<usize>, dx: f64, dy: f64) {
+        if self.prefers_reduced_motion {
+            self.scroll_animation = ScrollAnimationState::None;
+            if self.scroll_by(target, dx, dy, &mut |_| {}) {
+                self.shell_provider.request_redraw();
+            }
+            return;
+        }
+
+        let (mut dx, mut dy) = (dx, dy);
+        if let ScrollAnimationState::Smooth(running) = &self.scroll_animation
+            && running.target == target
+        {
+            dx += running.dx - running.applied_x;
+            dy += running.dy - running.applied_y;
+        }
+
+        self.scroll_animation = ScrollAnimationState::Smooth(SmoothScrollState {
+            target,
+            start_time: now_ms(),
+            dx,
+            dy,
+            applied_x: 0.0,
+            applied_y: 0.0,
+        });
+        self.shell_provider.request_redraw();
+    }
+
+    /// Move a smooth scroll on to where it should be by now
+    pub(crate) fn step_smooth_scroll(&mut self) {
+        let ScrollAnimationState::Smooth(state) = &mut self.scroll_animation else {
+            return;
+        };
+
+        let progress = ((now_ms() - state.start_time) / SMOOTH_SCROLL_DURATION_MS).clamp(0.0, 1.0);
+        let eased = ease_out(progress);
+        let step_x = state.dx * eased - state.applied_x;
+        let step_y = state.dy * eased - state.applied_y;
+        state.applied_x += step_x;
+        state.applied_y += step_y;
+        let target = state.target;
+
+        let has_changed = self.scroll_by(target, step_x, step_y, &mut |_| {});
+        // Stop early when the scroll hit the end of the scroll range
+        if progress >= 1.0 || !has_changed && (step_x != 0.0 || step_y != 0.0) {
+            self.scroll_animation = ScrollAnimationState::None;
+        }
+    }
+
+    /// Scroll the element's scroll containers and then the viewport so the element is visible,
+    /// aligned along each axis as asked. Only one scroll animates at a time, so the containers
+    /// jump and `behavior` applies to the viewport.
+    pub(crate) fn scroll_into_view(&mut self, node_id: usize, block: ScrollAlignment, inline: ScrollAlignment, behavior: ScrollBehavior) {
+        let Some(node) = self.get_node(node_id) else {
+            return;
+        };
+        let mut ancestor = node.layout_parent.get();
+        while let Some(container_id) = ancestor {
+            if self.is_scroll_container(container_id) {
+                let rect = self.page_rect(node_id);
+                let container = &self.nodes[container_id];
+                let position = container.absolute_position(0.0, 0.0);
+                let layout = container.final_layout;
+                let (scroll, port) = (container.scroll_offset, self.scrollport_size(container_id));
+                // Where the element is in the container's content; the container's position is
+                // already moved by its scrolling
+                let x0 = rect.x0 - (position.x + layout.border.left) as f64;
+                let y0 = rect.y0 - (position.y + layout.border.top) as f64;
+                let x = inline.scroll_offset(x0, rect.width(), scroll.x, port.0);
+                let y = block.scroll_offset(y0, rect.height(), scroll.y, port.1);
+                self.scroll_element_to(container_id, Some(x), Some(y), ScrollBehavior::Instant);
+            }
+            ancestor = self.nodes[container_id].layout_parent.get();
+        }
+        let rect = self.page_rect(node_id);
+        self.scroll_rect_into_view(rect, block, inline, behavior);
+    }
+
+    /// The node's border box in page coordinates
+    fn page_rect(&self, node_id: usize) -> Rect {
+        let node = &self.nodes[node_id];
+        // absolute_position is where the node's content is, moved by its own scrolling
+        let position = node.absolute_position(0.0, 0.0);
+        let (x, y) = (position.x as f64 + node.scroll_offset.x, position.y as f64 + node.scroll_offset.y);
+        let size = node.final_layout.size;
+        Rect::from_origin_size((x, y), (size.width as f64, size.height as f64))
+    }
+
+    /// Whether the node is the root element, which the viewport scrolls for. Scripts reach it
+    /// through the document node too.
+    fn is_root_scroller(&self, node_id: usize) -> bool {
+        let root_id = self.root_node().id;
+        node_id == root_id
+            || self.get_node(node_id).is_some_and(|node| {
+                node.parent == Some(root_id) && node.data.is_element_with_tag_name(&local_name!("html"))
+            })
+    }
+
+    /// Whether the element clips its overflow, so scripts can scroll it even when the user can't.
+    /// The root element and body pass theirs to the viewport.
+    fn is_scroll_container(&self, node_id: usize) -> bool {
+        let Some(node) = self.get_node(node_id) else {
+            return false;
+        };
+        let is_html_or_body = node.data.element().is_some_and(|e| e.name.local.as_ref() == "html" || e.name.local.as_ref() == "body");
+        !is_html_or_body
+            && node.primary_styles().is_some_and(|styles| {
+                styles.clone_overflow_x() != Overflow::Visible || styles.clone_overflow_y() != Overflow::Visible
+            })
+    }
+
+    /// The size of the element's padding box, which is what it shows of its content when it
+    /// scrolls, or the viewport's for the root element
+    fn scrollport_size(&self, node_id: usize) -> (f64, f64) {
+        if self.is_root_scroller(node_id) {
+            let viewport = self.viewport_client_rect();
+            return (viewport.width, viewport.height);
+        }
+        let layout = self.nodes[node_id].final_layout;
+        (
+            (layout.size.width - layout.border.left - layout.border.right).max(0.0) as f64,
+            (layout.size.height - layout.border.top - layout.border.bottom).max(0.0) as f64,
+        )
+    }
+
+    /// How far the element can scroll along each axis, or the viewport for the root element
+    fn scroll_range(&self, node_id: usize) -> (f64, f64) {
+        if self.is_root_scroller(node_id) {
+            let root_element = TDocument::as_node(&self.root_node()).first_element_child().and_then(|node| node.as_element());
+            let Some(root_element) = root_element else {
+                return (0.0, 0.0);
+            };
+            let content = root_element.final_layout.size;
+            let viewport = self.viewport_client_rect();
+            return (
+                (content.width as f64 - viewport.width).max(0.0),
+                (content.height as f64 - viewport.height).max(0.0),
+            );
+        }
+        if !self.is_scroll_container(node_id) {
+            return (0.0, 0.0);
+        }
+        let layout = self.nodes[node_id].final_layout;
+        (layout.scroll_width().max(0.0) as f64, layout.scroll_height().max(0.0) as f64)
+    }
+
+    /// The element's clientWidth and clientHeight, and its scrollWidth and scrollHeight, which add
+    /// the distance it can scroll. The root element reports the viewport's.
+    pub(crate) fn scroll_geometry(&self, node_id: usize) -> Option<(f64, f64, f64, f64)> {
+        if !self.is_root_scroller(node_id) {
+            self.client_rect(node_id)?;
+        }
+        let (client_width, client_height) = self.scrollport_size(node_id);
+        let (range_x, range_y) = self.scroll_range(node_id);
+        Some((client_width, client_height, client_width + range_x, client_height + range_y))
+    }
+
+    /// The element's scrollLeft and scrollTop, the viewport's for the root element
+    pub(crate) fn element_scroll_offset(&self, node_id: usize) -> (f64, f64) {
+        if self.is_root_scroller(node_id) {
+            return (self.viewport_scroll.x, self.viewport_scroll.y);
+        }
+        self.get_node(node_id).map_or((0.0, 0.0), |node| (node.scroll_offset.x, node.scroll_offset.y))
+    }
+
+    /// Scroll an element, or the viewport for the root element, to `x`, `y` in its scroll range,
+    /// keeping the current position on an axis given `None`. Elements that don't clip their
+    /// overflow don't scroll.
+    pub(crate) fn scroll_element_to(&mut self, node_id: usize, x: Option<f64>, y: Option<f64>, behavior: ScrollBehavior) {
+        let (scroll_x, scroll_y) = self.element_scroll_offset(node_id);
+        let (range_x, range_y) = self.scroll_range(node_id);
+        let x = x.filter(|x| x.is_finite()).map_or(scroll_x, |x| x.clamp(0.0, range_x));
+        let y = y.filter(|y| y.is_finite()).map_or(scroll_y, |y| y.clamp(0.0, range_y));
+        // scroll_by takes how far the content moves, which is the opposite of the offset change
+        let (dx, dy) = (scroll_x - x, scroll_y - y);
+        if self.is_root_scroller(node_id) {
+            self.scroll_with_behavior(None, dx, dy, behavior);
+            return;
+        }
+        if dx == 0.0 && dy == 0.0 {
+            return;
+        }
+
+        // Scrolling an element as the user would passes what it can't take on to its ancestors,
+        // which an overflow: hidden axis takes none of, so only animate when both axes scroll
+        let user_scrollable = self.get_node(node_id).and_then(|node| node.primary_styles()).is_some_and(|styles| {
+            matches!(styles.clone_overflow_x(), Overflow::Scroll | Overflow::Auto)
+                && matches!(styles.clone_overflow_y(), Overflow::Scroll | Overflow::Auto)
+        });
+        let smooth = match behavior {
+            ScrollBehavior::Smooth => true,
+            ScrollBehavior::Instant => false,
+            ScrollBehavior::Auto => self.has_smooth_scroll_behavior(Some(node_id)),
+        };
+        if smooth && user_scrollable {
+            self.smooth_scroll_by(Some(node_id), dx, dy);
+            return;
+        }
+
+        if matches!(&self.scroll_animation, ScrollAnimationState::Smooth(running) if running.target == Some(node_id)) {
+            self.scroll_animation = ScrollAnimationState::None;
+        }
+        self.nodes[node_id].scroll_offset = taffy::Point { x, y };
+        self.queue_scroll_event(node_id);
+        self.shell_provider.request_redraw();
+    }
+
+    /// Scroll the viewport so `rect`, in page coordinates, is visible, aligned along each axis as asked
+    pub(crate) fn scroll_rect_into_view(&mut self, rect: Rect, block: ScrollAlignment, inline: ScrollAlignment, behavior: ScrollBehavior) {
+        let scale = self.viewport.scale() as f64;
+        let viewport_width = self.viewport.window_size.0 as f64 / scale;
+        let viewport_height = self.viewport.window_size.1 as f64 / scale;
+
+        let scroll = self.viewport_scroll;
+        let x = inline.scroll_offset(rect.x0, rect.width(), scroll.x, viewport_width);
+        let y = block.scroll_offset(rect.y0, rect.height(), scroll.y, viewport_height);
+
+        // scroll_by takes how far the content moves, which is the opposite of the offset change
+        self.scroll_with_behavior(None, scroll.x - x, scroll.y - y, behavior);
+    }
+
+    /// Scroll the viewport to an absolute position, clamped to the scroll range
+    pub(crate) fn scroll_viewport_to(&mut self, x: f64, y: f64) {
+        self.scroll_animation = ScrollAnimationState::None;
+        let scroll = self.viewport_scroll;
+        if self.scroll_viewport_by_has_changed(scroll.x - x, scroll.y - y) {
+            self.shell_provider.request_redraw();
+        }
+    }
+
+    /// Queue a `scroll` event for `target`, the root node standing for the viewport. Events are
+    /// fired once per frame, so however often a target scrolls in between, it gets one.
+    pub(crate) fn queue_scroll_event(&mut self, target: usize) {
+        if !self.pending_scroll_events.contains(&target) {
+            self.pending_scroll_events.push(target);
+        }
+    }
+
+    /// The targets that scrolled since the last frame, in the order they first scrolled
+    pub(crate) fn take_scroll_events(&mut self) -> Vec<usize> {
+        std::mem::take(&mut self.pending_scroll_events)
+    }
+
+    /// How far a `position: sticky` element is painted from its laid out position in CSS px: it
+    /// is pushed to stay within its insets of the nearest scrollport (the viewport when no
+    /// ancestor clips its overflow) for as long as its parent's content box has room for it
+    pub(crate) fn sticky_offset(&self, node_id: usize) -> (f64, f64) {
+        let Some(node) = self.get_node(node_id) else {
+            return (0.0, 0.0);
+        };
+        if node.primary_styles().is_none_or(|styles| styles.clone_position() != Position::Sticky) {
+            return (0.0, 0.0);
+        }
+        let (Some(rect), Some(parent_id)) = (self.client_rect(node_id), node.layout_parent.get()) else {
+            return (0.0, 0.0);
+        };
+        let Some(parent) = self.get_node(parent_id) else {
+            return (0.0, 0.0);
+        };
+        let Some(parent_rect) = self.client_rect(parent_id) else {
+            return (0.0, 0.0);
+        };
+        let edges = parent.final_layout.padding + parent.final_layout.border;
+        let limit = ClientRect::new(
+            parent_rect.x + edges.left as f64,
+            parent_rect.y + edges.top as f64,
+            parent_rect.width - (edges.left + edges.right) as f64,
+            parent_rect.height - (edges.top + edges.bottom) as f64,
+        );
+
+        // The nearest ancestor that clips its overflow scrolls it, otherwise the viewport does
+        let mut port = self.viewport_client_rect();
+        let mut ancestor = Some(parent_id);
+        while let Some(id) = ancestor {
+            let Some(ancestor_node) = self.get_node(id) else {
+                break;
+            };
+            let is_html_or_body = ancestor_node.data.element().is_some_and(|e| e.name.local.as_ref() == "html" || e.name.local.as_ref() == "body");
+            let clips = ancestor_node.primary_styles().is_some_and(|styles| {
+                styles.clone_overflow_x() != Overflow::Visible || styles.clone_overflow_y() != Overflow::Visible
+            });
+            if clips && !is_html_or_body {
+                if let Some(ancestor_rect) = self.client_rect(id) {
+                    let edges = ancestor_node.final_layout.padding + ancestor_node.final_layout.border;
+                    port = ClientRect::new(
+                        ancestor_rect.x + edges.left as f64,
+                        ancestor_rect.y + edges.top as f64,
+                        ancestor_rect.width - (edges.left + edges.right) as f64,
+                        ancestor_rect.height - (edges.top + edges.bottom) as f64,
+                    );
+                }
+                break;
+            }
+            ancestor = ancestor_node.layout_parent.get();
+        }
+
+        let inset = &node.taffy_style.inset;
+        let resolve = |value: taffy::LengthPercentageAuto, basis: f64| {
+            value.maybe_resolve(basis as f32, resolve_calc_value).map(f64::from)
+        };
+        let x = sticky_shift(
+            rect.x,
+            rect.width,
+            port.x,
+            port.width,
+            resolve(inset.left, port.width),
+            resolve(inset.right, port.width),
+            limit.x,
+            limit.x + limit.width,
+        );
+        let y = sticky_shift(
+            rect.y,
+            rect.height,
+            port.y,
+            port.height,
+            resolve(inset.top, port.height),
+            resolve(inset.bottom, port.height),
+            limit.y,
+            limit.y + limit.height,
+        );
+        (x, y)
+    }
+
+    /// Point the document at `url` after a fragment navigation, which keeps the document
+    pub(crate) fn set_url_fragment(&mut self, url: url::Url) {
+        self.url = DocUrl::from(url);
+        self.update_base_url();
+    }
+
+    /// The element a URL fragment indicates: the element with that id, else an `<a>` with that
+    /// name. The raw fragment is tried before its percent-decoded form.
+    fn fragment_target(&self, fragment: &str) -> Option<usize> {
+        let decoded = percent_encoding::percent_decode_str(fragment).decode_utf8_lossy();
+        [fragment, decoded.as_ref()].into_iter().find_map(|name| {
+            self.nodes_to_id.get(name).copied().or_else(|| {
+                self.candidate_nodes_for_tag("a").into_iter().find(|&id| {
+                    self.get_node(id).and_then(|node| node.attr(local_name!("name"))) == Some(name)
+                })
+            })
+        })
+    }
+
+    /// Scroll to the part of the document `fragment` indicates. An empty fragment or `#top`
+    /// without a matching element scrolls to the top. Returns whether anything was found.
+    pub(crate) fn scroll_to_fragment(&mut self, fragment: &str, behavior: ScrollBehavior) -> bool {
+        if let Some(node_id) = self.fragment_target(fragment) {
+            self.scroll_into_view(node_id, ScrollAlignment::Start, ScrollAlignment::Nearest, behavior);
+            return true;
+        }
+        if fragment.is_empty() || fragment.eq_ignore_ascii_case("top") {
+            let scroll = self.viewport_scroll;
+            self.scroll_with_behavior(None, scroll.x, scroll.y, behavior);
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ease_out_starts_fast_and_ends_at_one() {
+        assert_eq!(ease_out(0.0), 0.0);
+        assert_eq!(ease_out(1.0), 1.0);
+        assert!(ease_out(0.25) > 0.25);
+    }
+
+    #[test]
+    fn nearest_alignment_only_scrolls_when_needed() {
+        // Already visible
+        assert_eq!(ScrollAlignment::Nearest.scroll_offset(150.0, 50.0, 100.0, 500.0), 100.0);
+        // Above the scrollport: align to the start
+        assert_eq!(ScrollAlignment::Nearest.scroll_offset(20.0, 50.0, 100.0, 500.0), 20.0);
+        // Below the scrollport: align to the end
+        assert_eq!(ScrollAlignment::Nearest.scroll_offset(700.0, 50.0, 100.0, 500.0), 250.0);
+    }
+
+    #[test]
+    fn sticky_boxes_stay_inside_the_scrollport_and_their_parent() {
+        // top: 10px in a 100px scrollport scrolled past the box: held 10px from the top
+        assert_eq!(sticky_shift(-30.0, 20.0, 0.0, 100.0, Some(10.0), None, -50.0, 200.0), 40.0);
+        // ...until the parent's end pushes it out again
+        assert_eq!(sticky_shift(-30.0, 20.0, 0.0, 100.0, Some(10.0), None, -50.0, 15.0), 25.0);
+        // Still below its inset: stays in flow
+        assert_eq!(sticky_shift(50.0, 20.0, 0.0, 100.0, Some(10.0), None, 0.0, 200.0), 0.0);
+        // bottom: 0 holds a box that would be below the scrollport at its end
+        assert_eq!(sticky_shift(150.0, 20.0, 0.0, 100.0, None, Some(0.0), 0.0, 200.0), -70.0);
+        // Auto insets never stick
+        assert_eq!(sticky_shift(-30.0, 20.0, 0.0, 100.0, None, None, -50.0, 200.0), 0.0);
+    }
+
+    #[test]
+    fn parses_scroll_options() {
+        assert_eq!(ScrollBehavior::parse("smooth"), ScrollBehavior::Smooth);
+        assert_eq!(ScrollBehavior::parse("bogus"), ScrollBehavior::Auto);
+        assert_eq!(ScrollAlignment::parse("center"), Some(ScrollAlignment::Center));
+        assert_eq!(ScrollAlignment::parse("middle"), None);
+    }
+}
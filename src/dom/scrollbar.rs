@@ -0,0 +1,461 @@
+// Scrollbars for the viewport and overflow containers. They are overlays: layout reserves no
+// space for them and they are painted over the edge of the scrollport. The geometry lives here
+// so the renderer and pointer handling agree on where the thumbs are, which makes them draggable.
+use kurbo::{Point, Rect};
+use style::computed_values::scrollbar_width::T as ScrollbarWidth;
+use style::properties::ComputedValues;
+use style::values::computed::Overflow;
+use crate::dom::Dom;
+
+/// Thickness of a `scrollbar-width: auto` scrollbar, in CSS px
+pub(crate) const SCROLLBAR_SIZE: f64 = 8.0;
+/// Thickness of a `scrollbar-width: thin` scrollbar
+pub(crate) const THIN_SCROLLBAR_SIZE: f64 = 5.0;
+/// Gap between a scrollbar and the edge of its scrollport
+const SCROLLBAR_MARGIN: f64 = 2.0;
+/// Thumbs don't shrink below this however long the content is
+const MIN_THUMB_LENGTH: f64 = 20.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ScrollbarAxis {
+    Horizontal,
+    Vertical,
+}
+
+/// One scrollbar. Rects are in the scroller's border box space, or client space for the viewport.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Scrollbar {
+    /// The scroll container, `None` for the viewport
+    pub(crate) scroller: Option<usize>,
+    pub(crate) axis: ScrollbarAxis,
+    pub(crate) track: Rect,
+    pub(crate) thumb: Rect,
+    /// How far the scroller can scroll along the axis
+    pub(crate) max_scroll: f64,
+}
+
+/// A thumb being dragged. `grab` is where along the thumb the pointer went down.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ScrollbarDrag {
+    pub(crate) scrollbar: Scrollbar,
+    pub(crate) grab: f64,
+}
+
+impl Scrollbar {
+    /// Lay out a scrollbar along one edge of `port`. `leave_corner` keeps it clear of the other
+    /// axis' scrollbar.
+    pub(crate) fn new(
+        scroller: Option<usize>,
+        axis: ScrollbarAxis,
+        port: Rect,
+        scroll: f64,
+        max_scroll: f64,
+        thickness: f64,
+        leave_corner: bool,
+    ) -> Self {
+        let corner = if leave_corner { thickness + SCROLLBAR_MARGIN } else { 0.0 };
+        let track = match axis {
+            ScrollbarAxis::Vertical => Rect::new(
+                port.x1 - SCROLLBAR_MARGIN - thickness,
+                port.y0 + SCROLLBAR_MARGIN,
+                port.x1 - SCROLLBAR_MARGIN,
+                port.y1 - SCROLLBAR_MARGIN - corner,
+            ),
+            ScrollbarAxis::Horizontal => Rect::new(
+                port.x0 + SCROLLBAR_MARGIN,
+                port.y1 - SCROLLBAR_MARGIN - thickness,
+                port.x1 - SCROLLBAR_MARGIN - corner,
+                port.y1 - SCROLLBAR_MARGIN,
+            ),
+        };
+
+        let port_length = match axis {
+            ScrollbarAxis::Vertical => port.height(),
+            ScrollbarAxis::Horizontal => port.width(),
+        };
+        let mut scrollbar = Scrollbar { scroller, axis, track, thumb: track, max_scroll };
+        let track_length = scrollbar.track_length();
+        let thumb_length = (track_length * port_length / (port_length + max_scroll))
+            .max(MIN_THUMB_LENGTH)
+            .min(track_length);
+        let progress = if max_scroll > 0.0 { (scroll / max_scroll).clamp(0.0, 1.0) } else { 0.0 };
+        let thumb_start = scrollbar.track_start() + (track_length - thumb_length) * progress;
+        scrollbar.thumb = match axis {
+            ScrollbarAxis::Vertical => Rect::new(track.x0, thumb_start, track.x1, thumb_start + thumb_length),
+            ScrollbarAxis::Horizontal => Rect::new(thumb_start, track.y0, thumb_start + thumb_length, track.y1),
+        };
+        scrollbar
+    }
+
+    /// The coordinate of `point` along the scrollbar's axis
+    pub(crate) fn along(&self, point: Point) -> f64 {
+        match self.axis {
+            ScrollbarAxis::Vertical => point.y,
+            ScrollbarAxis::Horizontal => point.x,
+        }
+    }
+
+    fn track_start(&self) -> f64 {
+        self.along(Point::new(self.track.x0, self.track.y0))
+    }
+
+    fn track_length(&self) -> f64 {
+        match self.axis {
+            ScrollbarAxis::Vertical => self.track.height(),
+            ScrollbarAxis::Horizontal => self.track.width(),
+        }
+    }
+
+    pub(crate) fn thumb_start(&self) -> f64 {
+        self.along(Point::new(self.thumb.x0, self.thumb.y0))
+    }
+
+    pub(crate) fn thumb_length(&self) -> f64 {
+        match self.axis {
+            ScrollbarAxis::Vertical => self.thumb.height(),
+            ScrollbarAxis::Horizontal => self.thumb.width(),
+        }
+    }
+
+    /// Where along the track a point `fraction` of the way through the scroller's content lies
+    pub(crate) fn track_point(&self, fraction: f64) -> f64 {
+        self.track_start() + self.track_length() * fraction.clamp(0.0, 1.0)
+    }
+
+    /// The scroll offset that puts the start of the thumb at `thumb_start` along the track
+    pub(crate) fn offset_for_thumb(&self, thumb_start: f64) -> f64 {
+        let travel = self.track_length() - self.thumb_length();
+        if travel <= 0.0 {
+            return 0.0;
+        }
+        ((thumb_start - self.track_start()) / travel).clamp(0.0, 1.0) * self.max_scroll
+    }
+}
+
+/// The thickness `scrollbar-width` asks for, `None` when scrollbars are hidden
+fn scrollbar_thickness(style: &ComputedValues) -> Option<f64> {
+    match style.get_ui().clone_scrollbar_width() {
+        ScrollbarWidth::Auto => Some(SCROLLBAR_SIZE),
+        ScrollbarWidth::Thin => Some(THIN_SCROLLBAR_SIZE),
+        ScrollbarWidth::None => None,
+    }
+}
+
+impl Dom {
+    /// The scrollbars shown on `scroller`, `None` being the viewport. An axis gets one when its
+    /// content overflows and it scrolls: `overflow: auto | scroll` on an element, anything but
+    /// `hidden` or `clip` on the root and body for the viewport.
+    pub(crate) fn scrollbars(&self, scroller: Option<usize>) -> Vec<Scrollbar> {
+        let (style, port, scroll, max_x, max_y, scrolls_x, scrolls_y) = match scroller {
+            None => {
+                let Some(root) = self.try_root_element() else {
+                    return Vec::new();
+                };
+                let Some(style) = root.primary_styles().map(|style| (*style).clone()) else {
+                    return Vec::new();
+                };
+                let scale = self.viewport.scale() as f64;
+                let width = self.viewport.window_size.0 as f64 / scale;
+                let height = self.viewport.window_size.1 as f64 / scale;
+                let content = root.final_layout.size;
+                let body_style = self
+                    .body_id()
+                    .and_then(|id| self.nodes[id].primary_styles().map(|style| (*style).clone()));
+                let clipped = |overflow: fn(&ComputedValues) -> Overflow| {
+                    [Some(&style), body_style.as_ref()]
+                        .into_iter()
+                        .flatten()
+                        .any(|style| matches!(overflow(style), Overflow::Hidden | Overflow::Clip))
+                };
+                let scrolls_x = !clipped(|style| style.clone_overflow_x());
+                let scrolls_y = !clipped(|style| style.clone_overflow_y());
+                (
+                    style,
+                    Rect::new(0.0, 0.0, width, height),
+                    self.viewport_scroll,
+                    content.width as f64 - width,
+                    content.height as f64 - height,
+                    scrolls_x,
+                    scrolls_y,
+                )
+            }
+            Some(node_id) => {
+                let Some(node) = self.get_node(node_id) else {
+                    return Vec::new();
+                };
+                let Some(style) = node.primary_styles() else {
+                    return Vec::new();
+                };
+                let layout = node.final_layout;
+                let port = Rect::new(
+                    layout.border.left as f64,
+                    layout.border.top as f64,
+                    (layout.size.width - layout.border.right) as f64,
+                    (layout.size.height - layout.border.bottom) as f64,
+                );
+                (
+                    (*style).clone(),
+                    port,
+                    node.scroll_offset,
+                    layout.scroll_width() as f64,
+                    layout.scroll_height() as f64,
+                    matches!(style.clone_overflow_x(), Overflow::Auto | Overflow::Scroll),
+                    matches!(style.clone_overflow_y(), Overflow::Auto | Overflow::Scroll),
+                )
+            }
+        };
+
+        let Some(thickness) = scrollbar_thickness(&style) else {
+            return Vec::new();
+        };
+        // Subpixel overflow from rounding doesn't get a scrollbar
+        let has_x = scrolls_x && max_x >= 1.0;
+        let has_y = scrolls_y && max_y >= 1.0;
+
+        let mut scrollbars = Vec::new();
+        if has_y {
+            scrollbars.push(Scrollbar::new(scroller, ScrollbarAxis::Vertical, port, scroll.y, max_y, thickness, has_x));
+        }
+        if has_x {
+            scrollbars.push(Scrollbar::new(scroller, ScrollbarAxis::Horizontal, port, scroll.x, max_x, thickness, has_y));
+        }
+        scrollbars
+    }
+
+    /// Where a client point is in a scrollbar's coordinate space
+    fn scrollbar_space_point(&self, scroller: Option<usize>, client_x: f32, client_y: f32) -> Point {
+        match scroller {
+            None => Point::new(client_x as f64, client_y as f64),
+            Some(node_id) => {
+                let (page_x, page_y) = self.client_to_page_coords(client_x, client_y);
+                let origin = self.nodes[node_id].page_border_origin();
+                Point::new((page_x - origin.x) as f64, (page_y - origin.y) as f64)
+            }
+        }
+    }
+
+    /// The scrollbar under a client point: the viewport's, which are on top, then those of the
+    /// scroll containers around the element hit there, innermost first
+    pub(crate) fn hit_scrollbar(&self, client_x: f32, client_y: f32) -> Option<Scrollbar> {
+        let point = Point::new(client_x as f64, client_y as f64);
+        if let Some(scrollbar) = self.scrollbars(None).into_iter().find(|bar| bar.track.contains(point)) {
+            return Some(scrollbar);
+        }
+
+        let hit = self.hit_client(client_x, client_y)?;
+        let mut current = Some(hit.node_id);
+        while let Some(node_id) = current {
+            let point = self.scrollbar_space_point(Some(node_id), client_x, client_y);
+            if let Some(scrollbar) = self.scrollbars(Some(node_id)).into_iter().find(|bar| bar.track.contains(point)) {
+                return Some(scrollbar);
+            }
+            current = self.nodes[node_id].parent;
+        }
+        None
+    }
+
+    /// Start dragging a scrollbar's thumb from a client point. Pressing the track outside the
+    /// thumb first jumps the thumb's middle to the pointer.
+    pub(crate) fn start_scrollbar_drag(&mut self, scrollbar: Scrollbar, client_x: f32, client_y: f32) -> ScrollbarDrag {
+        let point = self.scrollbar_space_point(scrollbar.scroller, client_x, client_y);
+        let drag = if scrollbar.thumb.contains(point) {
+            ScrollbarDrag { scrollbar, grab: scrollbar.along(point) - scrollbar.thumb_start() }
+        } else {
+            ScrollbarDrag { scrollbar, grab: scrollbar.thumb_length() / 2.0 }
+        };
+        self.drag_scrollbar(&drag, client_x, client_y);
+        drag
+    }
+
+    /// Scroll so the dragged thumb follows the pointer. Returns whether anything scrolled.
+    pub(crate) fn drag_scrollbar(&mut self, drag: &ScrollbarDrag, client_x: f32, client_y: f32) -> bool {
+        let scrollbar = &drag.scrollbar;
+        let point = self.scrollbar_space_point(scrollbar.scroller, client_x, client_y);
+        let offset = scrollbar.offset_for_thumb(scrollbar.along(point) - drag.grab);
+
+        let current = match scrollbar.scroller {
+            None => self.viewport_scroll,
+            Some(node_id) => self.nodes[node_id].scroll_offset,
+        };
+        let (dx, dy) = match scrollbar.axis {
+            ScrollbarAxis::Horizontal => (current.x - offset, 0.0),
+            ScrollbarAxis::Vertical => (0.0, current.y - offset),
+        };
+        let changed = match scrollbar.scroller {
+            None => self.scroll_viewport_by_has_changed(dx, dy),
+            Some(node_id) => self.scroll_node_by_has_changed(node_id, dx, dy, |_| {}),
+        };
+        if changed {
+            self.shell_provider.request_redraw();
+        }
+        changed
+    }
+}
+
+/// Stylo drops rules for the `::-webkit-scrollbar` pseudo-elements, which many sites use to
+/// style or hide scrollbars. Rewrite the basics into the standard properties on the originating
+/// element, in place so the cascade order and any enclosing `@media` are kept:
+/// `::-webkit-scrollbar { display: none }` or a zero width becomes `scrollbar-width: none`, a
+/// small width `thin`, and a `::-webkit-scrollbar-thumb` background becomes `scrollbar-color`.
+/// Anything else in those rules is dropped, as before. `None` when there is nothing to rewrite.
+pub(crate) fn translate_webkit_scrollbar_rules(css: &str) -> Option<String> {
+    if !css.contains("::-webkit-scrollbar") {
+        return None;
+    }
+
+    let mut out = String::with_capacity(css.len());
+    let mut prelude_start = 0;
+    let mut chars = css.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '/' if chars.peek().is_some_and(|(_, next)| *next == '*') => {
+                // Skip comments so braces inside them don't count
+                let end = css[i + 2..].find("*/").map_or(css.len(), |end| i + 2 + end + 2);
+                out.push_str(&css[prelude_start..end]);
+                prelude_start = end;
+                while chars.peek().is_some_and(|(j, _)| *j < end) {
+                    chars.next();
+                }
+            }
+            '{' => {
+                let prelude = &css[prelude_start..i];
+                if prelude.contains("::-webkit-scrollbar") {
+                    let end = css[i..].find('}').map_or(css.len(), |end| i + end);
+                    let body = &css[i + 1..end];
+                    out.push_str(&prelude[..prelude.len() - prelude.trim_start().len()]);
+                    out.push_str(&translate_webkit_scrollbar_rule(prelude, body));
+                    prelude_start = (end + 1).min(css.len());
+                    while chars.peek().is_some_and(|(j, _)| *j < prelude_start) {
+                        chars.next();
+                    }
+                } else {
+                    out.push_str(&css[prelude_start..=i]);
+                    prelude_start = i + 1;
+                }
+            }
+            '}' | ';' => {
+                out.push_str(&css[prelude_start..=i]);
+                prelude_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out.push_str(&css[prelude_start..]);
+    Some(out)
+}
+
+/// The standard rule for one `::-webkit-scrollbar*` rule, or nothing when it has no equivalent
+fn translate_webkit_scrollbar_rule(prelude: &str, body: &str) -> String {
+    let declarations: Vec<(String, &str)> = body
+        .split(';')
+        .filter_map(|declaration| {
+            let (name, value) = declaration.split_once(':')?;
+            let value = value.trim().trim_end_matches("!important").trim();
+            Some((name.trim().to_ascii_lowercase(), value))
+        })
+        .collect();
+    let value_of = |names: &[&str]| {
+        declarations
+            .iter()
+            .rev()
+            .find(|(name, _)| names.contains(&name.as_str()))
+            .map(|(_, value)| *value)
+    };
+
+    let mut selectors = Vec::new();
+    let mut declaration = None;
+    for selector in prelude.split(',') {
+        let Some((element, pseudo)) = selector.trim().split_once("::-webkit-scrollbar") else {
+            continue;
+        };
+        let translated = match pseudo {
+            "" => {
+                let width = value_of(&["width", "height"]).and_then(|value| {
+                    value.strip_suffix("px").unwrap_or(value).trim().parse::<f64>().ok()
+                });
+                if value_of(&["display"]) == Some("none") || width == Some(0.0) {
+                    Some("scrollbar-width: none".to_string())
+                } else if width.is_some_and(|width| width <= 2.0 * THIN_SCROLLBAR_SIZE) {
+                    Some("scrollbar-width: thin".to_string())
+                } else {
+                    None
+                }
+            }
+            "-thumb" => value_of(&["background-color", "background"])
+                .filter(|color| !color.contains('(') || color.starts_with("rgb") || color.starts_with("hsl"))
+                .map(|color| format!("scrollbar-color: {color} transparent")),
+            _ => None,
+        };
+        if let Some(translated) = translated {
+            let element = element.trim();
+            selectors.push(if element.is_empty() { "*" } else { element });
+            declaration = Some(translated);
+        }
+    }
+
+    match declaration {
+        Some(declaration) => format!("{} {{ {declaration} }}", selectors.join(", ")),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertical(scroll: f64) -> Scrollbar {
+        // A 100px tall scrollport over 400px of content
+        Scrollbar::new(None, ScrollbarAxis::Vertical, Rect::new(0.0, 0.0, 200.0, 100.0), scroll, 300.0, SCROLLBAR_SIZE, false)
+    }
+
+    #[test]
+    fn thumb_tracks_the_scroll_position() {
+        let top = vertical(0.0);
+        assert_eq!(top.track, Rect::new(190.0, 2.0, 198.0, 98.0));
+        assert_eq!(top.thumb_length(), 24.0);
+        assert_eq!(top.thumb_start(), 2.0);
+
+        let bottom = vertical(300.0);
+        assert_eq!(bottom.thumb.y1, bottom.track.y1);
+    }
+
+    #[test]
+    fn dragging_the_thumb_maps_back_to_the_scroll_offset() {
+        let scrollbar = vertical(0.0);
+        assert_eq!(scrollbar.offset_for_thumb(scrollbar.thumb_start()), 0.0);
+        assert_eq!(scrollbar.offset_for_thumb(vertical(150.0).thumb_start()), 150.0);
+        // Past either end of the track clamps to the scroll range
+        assert_eq!(scrollbar.offset_for_thumb(-50.0), 0.0);
+        assert_eq!(scrollbar.offset_for_thumb(500.0), 300.0);
+    }
+
+    #[test]
+    fn long_content_keeps_a_usable_thumb() {
+        let scrollbar = Scrollbar::new(None, ScrollbarAxis::Horizontal, Rect::new(0.0, 0.0, 100.0, 50.0), 0.0, 100_000.0, THIN_SCROLLBAR_SIZE, true);
+        assert_eq!(scrollbar.thumb_length(), MIN_THUMB_LENGTH);
+        // Room is left for the vertical scrollbar in the corner
+        assert_eq!(scrollbar.track.x1, 100.0 - SCROLLBAR_MARGIN * 2.0 - THIN_SCROLLBAR_SIZE);
+    }
+
+    #[test]
+    fn translates_webkit_scrollbar_rules() {
+        let css = "@media screen { .list::-webkit-scrollbar { display: none } } p { color: red }";
+        assert_eq!(
+            translate_webkit_scrollbar_rules(css).unwrap(),
+            "@media screen { .list { scrollbar-width: none } } p { color: red }"
+        );
+
+        let css = "::-webkit-scrollbar { width: 6px; } div::-webkit-scrollbar-thumb { background: #888 }";
+        assert_eq!(
+            translate_webkit_scrollbar_rules(css).unwrap(),
+            "* { scrollbar-width: thin } div { scrollbar-color: #888 transparent }"
+        );
+
+        // Rules with no standard equivalent are still dropped
+        let css = "a::-webkit-scrollbar-track { box-shadow: inset 0 0 6px grey } a { color: blue }";
+        assert_eq!(translate_webkit_scrollbar_rules(css).unwrap(), " a { color: blue }");
+
+        assert_eq!(translate_webkit_scrollbar_rules("/* ::-webkit-scrollbar */ p {}").unwrap(), "/* ::-webkit-scrollbar */ p {}");
+        assert_eq!(translate_webkit_scrollbar_rules("p { color: red }"), None);
+    }
+}
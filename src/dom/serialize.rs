@@ -0,0 +1,122 @@
+// HTML serialization of the live DOM, used by "Save Page As…", innerHTML/outerHTML and XMLSerializer
+use crate::dom::{Dom, DomNode, ElementData, NodeData};
+use html_escape::{encode_double_quoted_attribute_to_string, encode_text_to_string};
+
+/// Elements that never have an end tag
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+
+/// Elements whose text children are written out without escaping
+const RAW_TEXT_ELEMENTS: &[&str] = &[
+    "script", "style", "xmp", "iframe", "noembed", "noframes", "plaintext", "noscript",
+];
+
+impl Dom {
+    /// Serialize the whole document back to HTML
+    pub fn serialize_html(&self) -> String {
+        self.serialize_html_with(|_, _, _| None)
+    }
+
+    /// Serialize the whole document back to HTML. `rewrite_attr` is called with the element,
+    /// attribute name and value of every attribute, and may return a replacement value.
+    pub fn serialize_html_with(&self, mut rewrite_attr: impl FnMut(&ElementData, &str, &str) -> Option<String>) -> String {
+        let mut output = String::new();
+        write_node(self, self.root_node(), &mut rewrite_attr, &mut output);
+        output
+    }
+
+    /// Serialize a node and its subtree, as `outerHTML` and `XMLSerializer` do
+    pub(crate) fn serialize_node(&self, node_id: usize) -> String {
+        let mut output = String::new();
+        if let Some(node) = self.get_node(node_id) {
+            write_node(self, node, &mut |_, _, _| None, &mut output);
+        }
+        output
+    }
+
+    /// Serialize the children of a node, as `innerHTML` does
+    pub(crate) fn serialize_children(&self, node_id: usize) -> String {
+        let mut output = String::new();
+        if let Some(node) = self.get_node(node_id) {
+            write_children(self, node, &mut |_, _, _| None, &mut output);
+        }
+        output
+    }
+}
+
+fn write_node(
+    dom: &Dom,
+    node: &DomNode,
+    rewrite_attr: &mut impl FnMut(&ElementData, &str, &str) -> Option<String>,
+    output: &mut String,
+) {
+    match &node.data {
+        NodeData::Document => write_children(dom, node, rewrite_attr, output),
+        // Shadow trees and anonymous blocks are not part of the document markup
+        NodeData::ShadowRoot(_) | NodeData::AnonymousBlock(_) => {}
+        // Comment contents are not kept by the parser
+        NodeData::Comment => output.push_str("<!---->"),
+        NodeData::Doctype { name, .. } => {
+            output.push_str("<!DOCTYPE ");
+            output.push_str(name);
+            output.push_str(">\n");
+        }
+        NodeData::Text(text) => {
+            if parent_is_raw_text(dom, node) {
+                output.push_str(&text.content);
+            } else {
+                encode_text_to_string(&text.content, output);
+            }
+        }
+        NodeData::Element(data) => {
+            let name: &str = &data.name.local;
+            output.push('<');
+            output.push_str(name);
+            for attr in data.attrs().iter() {
+                let attr_name: &str = &attr.name.local;
+                output.push(' ');
+                output.push_str(attr_name);
+                output.push_str("=\"");
+                match rewrite_attr(data, attr_name, &attr.value) {
+                    Some(value) => {
+                        encode_double_quoted_attribute_to_string(&value, output);
+                    }
+                    None => {
+                        encode_double_quoted_attribute_to_string(&attr.value, output);
+                    }
+                }
+                output.push('"');
+            }
+            output.push('>');
+
+            if VOID_ELEMENTS.contains(&name) {
+                return;
+            }
+            write_children(dom, node, rewrite_attr, output);
+            output.push_str("</");
+            output.push_str(name);
+            output.push('>');
+        }
+    }
+}
+
+fn write_children(
+    dom: &Dom,
+    node: &DomNode,
+    rewrite_attr: &mut impl FnMut(&ElementData, &str, &str) -> Option<String>,
+    output: &mut String,
+) {
+    for &child_id in &node.children {
+        if let Some(child) = dom.get_node(child_id) {
+            write_node(dom, child, rewrite_attr, output);
+        }
+    }
+}
+
+fn parent_is_raw_text(dom: &Dom, node: &DomNode) -> bool {
+    node.parent
+        .and_then(|parent_id| dom.get_node(parent_id))
+        .and_then(|parent| parent.element_data())
+        .is_some_and(|data| RAW_TEXT_ELEMENTS.contains(&&*data.name.local))
+}
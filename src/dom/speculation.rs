@@ -0,0 +1,100 @@
+// Speculative loads of the document the user is likely to go to next: `<link rel="prefetch">`
+// targets and links the pointer rests on are prefetched into the net provider, and the first
+// `<link rel="prerender">` target is loaded in full by a hidden tab process the parent swaps in
+// when the link is followed.
+use std::time::{Duration, Instant};
+use markup5ever::local_name;
+use url::Url;
+use crate::dom::Dom;
+use crate::shell_provider::ShellProviderMessage;
+
+/// How long the pointer has to rest on a link before its target is prefetched
+const HOVER_PREFETCH_DELAY: Duration = Duration::from_millis(200);
+
+/// The link under the pointer and when the pointer got there
+pub(crate) struct HoveredLink {
+    url: Url,
+    since: Instant,
+    prefetched: bool,
+}
+
+impl Dom {
+    /// Act on a `<link>` hinting at the next navigation
+    pub(crate) fn process_link_hints(&mut self, node_id: usize) {
+        let node = &self.nodes[node_id];
+        let (Some(rels), Some(href)) = (node.attr(local_name!("rel")), node.attr(local_name!("href"))) else {
+            return;
+        };
+        let has_rel = |name: &str| rels.split_ascii_whitespace().any(|rel| rel.eq_ignore_ascii_case(name));
+        let prerender = has_rel("prerender");
+        if !prerender && !has_rel("prefetch") {
+            return;
+        }
+        let Some(url) = self.url.resolve_relative(href).filter(is_speculative_target) else {
+            return;
+        };
+
+        // A page gets one prerender; other hints still save it the network round trip
+        if prerender && self.prerender_url.is_none() {
+            self.prerender_url = Some(url.clone());
+            let _ = self.shell_provider.sender.send(ShellProviderMessage::Prerender(url.into()));
+        } else {
+            self.net_provider.prefetch(url);
+        }
+    }
+
+    /// Start timing the pointer's rest on the link under it, when it moved onto another one
+    pub(crate) fn update_hovered_link(&mut self) {
+        let url = self.link_under_pointer();
+        if self.hovered_link.as_ref().map(|link| &link.url) == url.as_ref() {
+            return;
+        }
+        self.hovered_link = url.map(|url| HoveredLink { url, since: Instant::now(), prefetched: false });
+    }
+
+    /// Prefetch the target of the link the pointer has rested on for `HOVER_PREFETCH_DELAY`
+    pub(crate) fn prefetch_hovered_link(&mut self) {
+        let Some(link) = &mut self.hovered_link else {
+            return;
+        };
+        if link.prefetched || link.since.elapsed() < HOVER_PREFETCH_DELAY {
+            return;
+        }
+        link.prefetched = true;
+        if self.prerender_url.as_ref() != Some(&link.url) {
+            self.net_provider.prefetch(link.url.clone());
+        }
+    }
+
+    /// When `prefetch_hovered_link` will have something to do
+    pub(crate) fn hover_prefetch_due(&self) -> Option<Instant> {
+        let link = self.hovered_link.as_ref().filter(|link| !link.prefetched)?;
+        Some(link.since + HOVER_PREFETCH_DELAY)
+    }
+
+    /// Target of the `a` or `area` the pointer is over, when following it loads another document
+    fn link_under_pointer(&self) -> Option<Url> {
+        let mut node = self.get_node(self.hover_node_id?)?;
+        loop {
+            if let Some(element) = node.element_data()
+                && matches!(element.name.local, local_name!("a") | local_name!("area"))
+            {
+                let href = element.attr(local_name!("href"))?;
+                if element.has_attr(local_name!("download")) {
+                    return None;
+                }
+                let url = self.url.resolve_relative(href).filter(is_speculative_target)?;
+                let mut current = Url::from(&self.url);
+                current.set_fragment(None);
+                let mut target = url.clone();
+                target.set_fragment(None);
+                return (target != current).then_some(url);
+            }
+            node = self.get_node(node.parent?)?;
+        }
+    }
+}
+
+fn is_speculative_target(url: &Url) -> bool {
+    matches!(url.scheme(), "http" | "https")
+}
@@ -0,0 +1,152 @@
+// Parsed stylesheets shared by the documents of a tab process. Pages on a site tend to use the
+// same large stylesheets, and going back to a page shouldn't parse them again, so the rules parsed
+// for a sheet are kept by its URL, cascade origin and text, and later documents wrap them in a
+// stylesheet of their own. Stylo ties parsed rules to the lock they were parsed under, so every
+// document in the process uses one lock, as Servo does.
+//
+// Sheets with @import rules aren't kept: their imports are fetched for, and reported to, the
+// document that parsed them.
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::AtomicBool;
+use std::sync::{LazyLock, Mutex};
+use style::media_queries::MediaList;
+use style::servo_arc::Arc as ServoArc;
+use style::shared_lock::SharedRwLock;
+use style::stylesheets::{CssRule, DocumentStyleSheet, Origin, Stylesheet, StylesheetContents};
+
+/// Source text of the sheets kept, past which the least recently used are dropped
+const MAX_CACHED_CSS_BYTES: usize = 32 * 1024 * 1024;
+
+/// Smaller sheets parse quickly enough not to be worth keeping
+const MIN_CACHED_CSS_BYTES: usize = 4 * 1024;
+
+static STYLE_LOCK: LazyLock<SharedRwLock> = LazyLock::new(SharedRwLock::new);
+
+static CACHE: LazyLock<Mutex<StyleCache>> = LazyLock::new(Default::default);
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct SheetKey {
+    /// The sheet's own URL, or the document's for a `<style>` element
+    url: String,
+    origin: u8,
+    text_hash: u64,
+    text_len: usize,
+}
+
+struct Entry {
+    contents: ServoArc<StylesheetContents>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct StyleCache {
+    entries: HashMap<SheetKey, Entry>,
+    used: usize,
+    clock: u64,
+}
+
+impl StyleCache {
+    fn get(&mut self, key: &SheetKey) -> Option<ServoArc<StylesheetContents>> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(key)?;
+        entry.last_used = self.clock;
+        Some(entry.contents.clone())
+    }
+
+    fn insert(&mut self, key: SheetKey, contents: ServoArc<StylesheetContents>) {
+        self.clock += 1;
+        let bytes = key.text_len;
+        if self.entries.insert(key, Entry { contents, last_used: self.clock }).is_none() {
+            self.used += bytes;
+        }
+
+        while self.used > MAX_CACHED_CSS_BYTES {
+            let Some(oldest) = self.entries.iter().min_by_key(|(_, entry)| entry.last_used).map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.entries.remove(&oldest);
+            self.used -= oldest.text_len;
+        }
+    }
+}
+
+/// The lock every document in the process parses its stylesheets under
+pub(crate) fn shared_lock() -> SharedRwLock {
+    STYLE_LOCK.clone()
+}
+
+/// A stylesheet for `css` from `url`, reusing the rules of an identical sheet when one was parsed
+/// before and calling `parse` otherwise
+pub(crate) fn stylesheet(url: &str, css: &str, origin: Origin, parse: impl FnOnce() -> Stylesheet) -> DocumentStyleSheet {
+    if css.len() < MIN_CACHED_CSS_BYTES {
+        return DocumentStyleSheet(ServoArc::new(parse()));
+    }
+
+    let mut hasher = DefaultHasher::new();
+    css.hash(&mut hasher);
+    let key = SheetKey { url: url.to_string(), origin: origin as u8, text_hash: hasher.finish(), text_len: css.len() };
+
+    let lock = shared_lock();
+    let cached = CACHE.lock().ok().and_then(|mut cache| cache.get(&key));
+    if let Some(contents) = cached {
+        return DocumentStyleSheet(ServoArc::new(Stylesheet {
+            contents: lock.wrap(contents),
+            shared_lock: lock.clone(),
+            media: ServoArc::new(lock.wrap(MediaList::empty())),
+            disabled: AtomicBool::new(false),
+        }));
+    }
+
+    let sheet = parse();
+    let contents = {
+        let guard = lock.read();
+        let contents = sheet.contents.read_with(&guard).clone();
+        let has_imports = contents.rules(&guard).iter().any(|rule| matches!(rule, CssRule::Import(_)));
+        (!has_imports).then_some(contents)
+    };
+    if let Some(contents) = contents
+        && let Ok(mut cache) = CACHE.lock()
+    {
+        cache.insert(key, contents);
+    }
+    DocumentStyleSheet(ServoArc::new(sheet))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use selectors::context::QuirksMode;
+    use style::stylesheets::AllowImportRules;
+
+    const SHEET_URL: &str = "https://example.com/site.css";
+
+    fn parse(css: &str) -> Stylesheet {
+        let lock = shared_lock();
+        Stylesheet::from_str(
+            css,
+            url::Url::parse(SHEET_URL).unwrap().into(),
+            Origin::Author,
+            ServoArc::new(lock.wrap(MediaList::empty())),
+            lock,
+            None,
+            None,
+            QuirksMode::NoQuirks,
+            AllowImportRules::Yes,
+        )
+    }
+
+    #[test]
+    fn reuses_the_rules_of_identical_sheets() {
+        let css = ".card { color: red; margin: 4px }\n".repeat(256);
+        let first = stylesheet(SHEET_URL, &css, Origin::Author, || parse(&css));
+        let second = stylesheet(SHEET_URL, &css, Origin::Author, || panic!("parsed again"));
+
+        // Each document gets its own sheet around the shared rules
+        assert!(!ServoArc::ptr_eq(&first.0, &second.0));
+        let guard = STYLE_LOCK.read();
+        assert!(ServoArc::ptr_eq(first.0.contents.read_with(&guard), second.0.contents.read_with(&guard)));
+    }
+}
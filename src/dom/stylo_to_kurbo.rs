@@ -2,11 +2,15 @@
 // Licensed under the Apache License, Version 2.0 or the MIT license.
 
 use euclid::default::Rect;
-use kurbo::{Affine, Vec2};
+use kurbo::{Affine, BezPath, Circle, Ellipse, Point, RoundedRect, RoundedRectRadii, Shape, Vec2};
 use style::{
-    properties::generated::style_structs::Box as BoxStyleStruct,
+    properties::{generated::style_structs::Box as BoxStyleStruct, ComputedValues},
     values::{
-        computed::{CSSPixelLength, Rotate},
+        computed::{CSSPixelLength, LengthPercentage, NonNegativeLengthPercentage, Position, Rotate},
+        generics::basic_shape::{
+            GenericBasicShape, GenericClipPath, GenericShapeRadius, ShapeBox, ShapeGeometryBox,
+        },
+        generics::position::GenericPositionOrAuto,
         generics::transform::{Scale, Translate},
     },
 };
@@ -127,4 +131,134 @@ pub fn resolve_2d_transform(
     } else {
         None
     }
-}
\ No newline at end of file
+}
+/// The outline `clip-path` cuts an element to, in its border box space scaled by `scale`.
+/// `None` when nothing is clipped; `url()` references and `path()`/`shape()` aren't supported.
+///
+/// <https://drafts.fxtf.org/css-masking/#the-clip-path>
+pub fn resolve_clip_path(style: &ComputedValues, layout: &taffy::Layout, scale: f64) -> Option<BezPath> {
+    let (shape, geometry_box) = match &style.get_svg().clip_path {
+        GenericClipPath::Shape(shape, geometry_box) => (Some(&**shape), geometry_box),
+        GenericClipPath::Box(geometry_box) => (None, geometry_box),
+        _ => return None,
+    };
+
+    let size = layout.size;
+    let border_box = kurbo::Rect::new(0.0, 0.0, size.width as f64, size.height as f64);
+    let inset = |rect: kurbo::Rect, edges: taffy::Rect<f32>| {
+        kurbo::Rect::new(
+            rect.x0 + edges.left as f64,
+            rect.y0 + edges.top as f64,
+            rect.x1 - edges.right as f64,
+            rect.y1 - edges.bottom as f64,
+        )
+    };
+    let reference = match geometry_box {
+        ShapeGeometryBox::ShapeBox(ShapeBox::MarginBox) => {
+            inset(border_box, layout.margin.map(|margin| -margin))
+        }
+        ShapeGeometryBox::ShapeBox(ShapeBox::PaddingBox) => inset(border_box, layout.border),
+        ShapeGeometryBox::ShapeBox(ShapeBox::ContentBox) => {
+            inset(inset(border_box, layout.border), layout.padding)
+        }
+        // The SVG boxes are the border box for HTML elements
+        _ => border_box,
+    };
+
+    let width = CSSPixelLength::new(reference.width() as f32);
+    let height = CSSPixelLength::new(reference.height() as f32);
+    let horizontal = |length: &LengthPercentage| length.resolve(width).px() as f64;
+    let vertical = |length: &LengthPercentage| length.resolve(height).px() as f64;
+    let center = |position: &GenericPositionOrAuto<Position>| match position {
+        GenericPositionOrAuto::Position(position) => Point::new(
+            reference.x0 + horizontal(&position.horizontal),
+            reference.y0 + vertical(&position.vertical),
+        ),
+        GenericPositionOrAuto::Auto => reference.center(),
+    };
+    // Distances from a center to the closest and farthest edges of the reference box
+    let sides = |center: Point| {
+        let x = [center.x - reference.x0, reference.x1 - center.x].map(f64::abs);
+        let y = [center.y - reference.y0, reference.y1 - center.y].map(f64::abs);
+        (x[0].min(x[1]), x[0].max(x[1]), y[0].min(y[1]), y[0].max(y[1]))
+    };
+    let radius = |radius: &GenericShapeRadius<NonNegativeLengthPercentage>,
+                  basis: CSSPixelLength,
+                  closest: f64,
+                  farthest: f64| match radius {
+        GenericShapeRadius::Length(length) => length.0.resolve(basis).px() as f64,
+        GenericShapeRadius::ClosestSide => closest,
+        GenericShapeRadius::FarthestSide => farthest,
+    };
+
+    let path = match shape {
+        None => reference.to_path(0.1),
+        Some(GenericBasicShape::Rect(inset_rect)) => {
+            let edges = &inset_rect.rect;
+            let rect = kurbo::Rect::new(
+                reference.x0 + horizontal(&edges.3),
+                reference.y0 + vertical(&edges.0),
+                reference.x1 - horizontal(&edges.1),
+                reference.y1 - vertical(&edges.2),
+            );
+            if rect.width() <= 0.0 || rect.height() <= 0.0 {
+                return Some(BezPath::new());
+            }
+            // Elliptical corners are approximated with circular ones
+            let round = &inset_rect.round;
+            let corner = |corner: &style::values::computed::BorderCornerRadius| {
+                (corner.0.width.0.resolve(width).px() + corner.0.height.0.resolve(height).px()) as f64
+                    / 2.0
+            };
+            let radii = RoundedRectRadii::new(
+                corner(&round.top_left),
+                corner(&round.top_right),
+                corner(&round.bottom_right),
+                corner(&round.bottom_left),
+            );
+            RoundedRect::from_rect(rect, radii).to_path(0.1)
+        }
+        Some(GenericBasicShape::Circle(circle)) => {
+            let center = center(&circle.position);
+            let (closest_x, farthest_x, closest_y, farthest_y) = sides(center);
+            // Percentages resolve against the normalized diagonal of the reference box
+            let diagonal = (reference.width().powi(2) + reference.height().powi(2)) / 2.0;
+            let basis = CSSPixelLength::new(diagonal.sqrt() as f32);
+            let radius = radius(
+                &circle.radius,
+                basis,
+                closest_x.min(closest_y),
+                farthest_x.max(farthest_y),
+            );
+            Circle::new(center, radius).to_path(0.1)
+        }
+        Some(GenericBasicShape::Ellipse(ellipse)) => {
+            let center = center(&ellipse.position);
+            let (closest_x, farthest_x, closest_y, farthest_y) = sides(center);
+            let radii = Vec2::new(
+                radius(&ellipse.semiaxis_x, width, closest_x, farthest_x),
+                radius(&ellipse.semiaxis_y, height, closest_y, farthest_y),
+            );
+            Ellipse::new(center, radii, 0.0).to_path(0.1)
+        }
+        Some(GenericBasicShape::Polygon(polygon)) => {
+            let mut path = BezPath::new();
+            for (i, point) in polygon.coordinates.iter().enumerate() {
+                let point = Point::new(
+                    reference.x0 + horizontal(&point.0),
+                    reference.y0 + vertical(&point.1),
+                );
+                if i == 0 {
+                    path.move_to(point);
+                } else {
+                    path.line_to(point);
+                }
+            }
+            path.close_path();
+            path
+        }
+        Some(_) => return None,
+    };
+
+    Some(Affine::scale(scale) * path)
+}
@@ -6,6 +6,7 @@ use ::parley::setting::Tag;
 use std::borrow::Cow;
 use style::values::computed::Length;
 
+use crate::dom::renamed_properties::{self, renamed_property_value};
 use crate::ui::TextBrush;
 
 // Module of type aliases so we can refer to stylo types with nicer names
@@ -13,6 +14,8 @@ pub(crate) mod stylo {
     pub(crate) use style::computed_values::text_wrap_mode::T as TextWrapMode;
     pub(crate) use style::computed_values::white_space_collapse::T as WhiteSpaceCollapse;
     pub(crate) use style::properties::ComputedValues;
+    pub(crate) use style::values::generics::length::GenericLengthOrNumber as LengthOrNumber;
+    pub(crate) use style::computed_values::font_variant_caps::T as FontVariantCaps;
     pub(crate) use style::values::computed::font::FontStretch;
     pub(crate) use style::values::computed::font::FontStyle;
     pub(crate) use style::values::computed::font::FontVariationSettings;
@@ -27,6 +30,7 @@ pub(crate) mod stylo {
 pub(crate) mod parley {
     pub(crate) use parley::fontique::QueryFamily;
     pub(crate) use parley::style::*;
+    pub(crate) use parley::FontFeature;
     pub(crate) use parley::FontVariation;
 }
 
@@ -94,17 +98,104 @@ pub(crate) fn font_variations(input: &stylo::FontVariationSettings) -> Vec<parle
         .collect()
 }
 
+/// OpenType features from `font-variant-*`, then `font-feature-settings`, which overrides them.
+/// Optional ligatures would glue together letters that letter-spacing is meant to pull apart, so
+/// they are turned off while it is non-zero
+/// <https://drafts.csswg.org/css-text-3/#letter-spacing-property>
+pub(crate) fn font_features(style: &stylo::ComputedValues, letter_spacing: f32) -> Vec<parley::FontFeature> {
+    let font_styles = style.get_font();
+    let mut features = Vec::new();
+    let mut set = |tag: &[u8; 4], value: u16| {
+        features.push(parley::FontFeature {
+            tag: Tag::from_bytes(*tag),
+            value,
+        })
+    };
+
+    if font_styles.font_variant_caps == stylo::FontVariantCaps::SmallCaps {
+        set(b"smcp", 1);
+    }
+    if letter_spacing != 0.0 {
+        for tag in [b"liga", b"clig", b"dlig", b"hlig"] {
+            set(tag, 0);
+        }
+    }
+    if let Some(ligatures) = renamed_property_value(style, renamed_properties::FONT_VARIANT_LIGATURES) {
+        for keyword in ligatures.split_ascii_whitespace() {
+            match keyword {
+                "none" => {
+                    for tag in [b"liga", b"clig", b"dlig", b"hlig", b"calt"] {
+                        set(tag, 0);
+                    }
+                }
+                "common-ligatures" => {
+                    set(b"liga", 1);
+                    set(b"clig", 1);
+                }
+                "no-common-ligatures" => {
+                    set(b"liga", 0);
+                    set(b"clig", 0);
+                }
+                "discretionary-ligatures" => set(b"dlig", 1),
+                "no-discretionary-ligatures" => set(b"dlig", 0),
+                "historical-ligatures" => set(b"hlig", 1),
+                "no-historical-ligatures" => set(b"hlig", 0),
+                "contextual" => set(b"calt", 1),
+                "no-contextual" => set(b"calt", 0),
+                _ => {}
+            }
+        }
+    }
+    if let Some(numeric) = renamed_property_value(style, renamed_properties::FONT_VARIANT_NUMERIC) {
+        for keyword in numeric.split_ascii_whitespace() {
+            let tag = match keyword {
+                "lining-nums" => b"lnum",
+                "oldstyle-nums" => b"onum",
+                "proportional-nums" => b"pnum",
+                "tabular-nums" => b"tnum",
+                "diagonal-fractions" => b"frac",
+                "stacked-fractions" => b"afrc",
+                "ordinal" => b"ordn",
+                "slashed-zero" => b"zero",
+                _ => continue,
+            };
+            set(tag, 1);
+        }
+    }
+
+    for setting in font_styles.font_feature_settings.0.iter() {
+        features.push(parley::FontFeature {
+            tag: Tag::from_bytes(setting.tag.0.to_be_bytes()),
+            value: setting.value.clamp(0, u16::MAX as i32) as u16,
+        });
+    }
+    features
+}
+
 pub(crate) fn white_space_collapse(input: stylo::WhiteSpaceCollapse) -> parley::WhiteSpaceCollapse {
     match input {
         stylo::WhiteSpaceCollapse::Collapse => parley::WhiteSpaceCollapse::Collapse,
         stylo::WhiteSpaceCollapse::Preserve => parley::WhiteSpaceCollapse::Preserve,
 
-        // TODO: Implement PreserveBreaks and BreakSpaces modes
-        stylo::WhiteSpaceCollapse::PreserveBreaks => parley::WhiteSpaceCollapse::Preserve,
+        // Inline layout keeps the newlines of PreserveBreaks text itself
+        stylo::WhiteSpaceCollapse::PreserveBreaks => parley::WhiteSpaceCollapse::Collapse,
+        // TODO: Wrap after preserved spaces for BreakSpaces
         stylo::WhiteSpaceCollapse::BreakSpaces => parley::WhiteSpaceCollapse::Preserve,
     }
 }
 
+/// Resolves `tab-size` to a number of columns. Lengths are measured in the advance of a
+/// monospace space, which is what `tab-size` is nearly always set for.
+pub(crate) fn tab_size(style: &stylo::ComputedValues) -> usize {
+    match style.clone_tab_size() {
+        stylo::LengthOrNumber::Number(spaces) => spaces.0.round() as usize,
+        stylo::LengthOrNumber::Length(length) => {
+            let space_width = style.get_font().font_size.used_size.0.px() * 0.6;
+            if space_width > 0.0 { (length.0.px() / space_width).round() as usize } else { 0 }
+        }
+    }
+}
+
 pub(crate) fn style(
     span_id: usize,
     style: &stylo::ComputedValues,
@@ -191,7 +282,7 @@ pub(crate) fn style(
         font_style,
         font_weight,
         font_variations: parley::FontVariations::List(Cow::Owned(font_variations)),
-        font_features: parley::FontFeatures::List(Cow::Borrowed(&[])),
+        font_features: parley::FontFeatures::List(Cow::Owned(font_features(style, letter_spacing))),
         locale: Default::default(),
         line_height,
         word_spacing,
@@ -545,7 +545,7 @@ impl Dom {
     }
 
     /// Recursively collect all inline roots from a node's layout_children subtree
-    fn collect_all_inline_roots_in_subtree(&self, node_id: usize, result: &mut Vec<usize>) {
+    pub(crate) fn collect_all_inline_roots_in_subtree(&self, node_id: usize, result: &mut Vec<usize>) {
         let node = &self.nodes[node_id];
         let layout_children = node.layout_children.borrow();
         let Some(children) = layout_children.as_ref() else {
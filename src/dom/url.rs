@@ -3,8 +3,13 @@ use std::str::FromStr;
 use style::stylesheets::UrlExtraData;
 use url::Url;
 
+/// The document's URL, which it dereferences to, and the base URL its relative URLs resolve
+/// against: the fallback base (the document's URL, or the parent's base for a srcdoc frame)
+/// unless a `<base href>` sets another
 #[derive(Clone)]
 pub(crate) struct DocUrl {
+    url: style::servo_arc::Arc<Url>,
+    fallback_base_url: style::servo_arc::Arc<Url>,
     base_url: style::servo_arc::Arc<Url>,
 }
 
@@ -16,6 +21,31 @@ impl DocUrl {
     pub(crate) fn resolve_relative(&self, raw: &str) -> Option<Url> {
         self.base_url.join(raw).ok()
     }
+
+    pub(crate) fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    pub(crate) fn fallback_base_url(&self) -> &Url {
+        &self.fallback_base_url
+    }
+
+    /// Resolve relative URLs against `base`, or against the fallback base again for `None`
+    pub(crate) fn set_base_url(&mut self, base: Option<Url>) {
+        self.base_url = match base {
+            Some(base) => style::servo_arc::Arc::new(base),
+            None => style::servo_arc::Arc::clone(&self.fallback_base_url),
+        };
+    }
+
+    /// Resolve relative URLs against `fallback` when no `<base href>` says otherwise
+    pub(crate) fn set_fallback_base_url(&mut self, fallback: Url) {
+        let using_fallback = self.base_url == self.fallback_base_url;
+        self.fallback_base_url = style::servo_arc::Arc::new(fallback);
+        if using_fallback {
+            self.base_url = style::servo_arc::Arc::clone(&self.fallback_base_url);
+        }
+    }
 }
 
 impl Default for DocUrl {
@@ -28,28 +58,29 @@ impl FromStr for DocUrl {
     type Err = <Url as FromStr>::Err;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let base_url = style::servo_arc::Arc::new(Url::parse(s)?);
-        Ok(Self { base_url })
+        Ok(Self::from(Url::parse(s)?))
     }
 }
 
 impl From<Url> for DocUrl {
-    fn from(base_url: Url) -> Self {
-        Self {
-            base_url: style::servo_arc::Arc::new(base_url),
-        }
+    fn from(url: Url) -> Self {
+        Self::from(style::servo_arc::Arc::new(url))
     }
 }
 
 impl From<style::servo_arc::Arc<Url>> for DocUrl {
-    fn from(base_url: style::servo_arc::Arc<Url>) -> Self {
-        Self { base_url }
+    fn from(url: style::servo_arc::Arc<Url>) -> Self {
+        Self {
+            fallback_base_url: style::servo_arc::Arc::clone(&url),
+            base_url: style::servo_arc::Arc::clone(&url),
+            url,
+        }
     }
 }
 
 impl From<&DocUrl> for Url {
     fn from(value: &DocUrl) -> Self {
-        (*value.base_url).clone()
+        (*value.url).clone()
     }
 }
 
@@ -57,6 +88,6 @@ impl Deref for DocUrl {
     type Target = Url;
 
     fn deref(&self) -> &Self::Target {
-        &self.base_url
+        &self.url
     }
-}
\ No newline at end of file
+}
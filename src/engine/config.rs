@@ -1,4 +1,6 @@
 // Engine configuration
+use crate::networking::request_policy::RequestPolicy;
+use crate::dom::DEFAULT_IMAGE_MEMORY_BUDGET;
 
 /// Configuration for the browser engine
 #[derive(Clone, Debug)]
@@ -11,6 +13,14 @@ pub struct EngineConfig {
     pub enable_javascript: bool,
     /// Whether to block ads (stub for now)
     pub block_ads: bool,
+    /// Try https:// before loading http:// pages, with a warning when only http works
+    pub https_first: bool,
+    /// Upgrade insecure images on https:// pages instead of loading them insecurely
+    pub upgrade_mixed_content: bool,
+    /// Connect/stall timeouts and retries for network requests
+    pub request_policy: RequestPolicy,
+    /// Bytes of decoded images a page may keep before the least recently used are dropped
+    pub image_memory_budget: usize,
     /// Debug: Show hitboxes for clickable elements
     pub debug_hitboxes: bool,
     /// Debug: log js scripts that have eval error and save in debug_js/
@@ -30,6 +40,10 @@ impl Default for EngineConfig {
             homepage: "https://example.com".to_string(),
             enable_javascript: true,
             block_ads: true,
+            https_first: true,
+            upgrade_mixed_content: true,
+            request_policy: RequestPolicy::default(),
+            image_memory_budget: DEFAULT_IMAGE_MEMORY_BUDGET,
             debug_hitboxes: false, // Enable for debugging click issues
             debug_js,
             debug_net: false,
@@ -0,0 +1,72 @@
+// HTTPS-first navigation: http:// pages are tried over https:// first. When the secure connection
+// fails, a warning interstitial is shown instead of silently loading the page insecurely; following
+// its "continue" link allows plain http for that host for the rest of the session.
+use crate::engine::mixed_content;
+use std::collections::HashSet;
+use std::sync::{LazyLock, Mutex};
+use url::Url;
+
+const INTERSTITIAL_HTML: &str = include_str!("../../assets/https-first.html");
+
+#[derive(Default)]
+struct HttpsFirstState {
+    /// Hosts the user chose to visit over http
+    allowed_hosts: HashSet<String>,
+    /// The http URL the interstitial currently offers to continue to
+    interstitial_for: Option<String>,
+}
+
+static STATE: LazyLock<Mutex<HttpsFirstState>> = LazyLock::new(|| Mutex::new(HttpsFirstState::default()));
+
+/// The https:// URL to try before loading `url`, or None when it should be loaded as is. Navigating
+/// to the URL the interstitial was shown for counts as the user's choice to continue insecurely.
+pub fn navigation_target(url: &Url) -> Option<Url> {
+    if url.scheme() != "http" || mixed_content::is_loopback(url) {
+        return None;
+    }
+    let host = url.host_str()?.to_ascii_lowercase();
+
+    let mut state = STATE.lock().unwrap();
+    if state.interstitial_for.as_deref() == Some(url.as_str()) {
+        state.interstitial_for = None;
+        state.allowed_hosts.insert(host);
+        return None;
+    }
+    if state.allowed_hosts.contains(&host) {
+        return None;
+    }
+    mixed_content::upgrade(url)
+}
+
+/// The warning page shown when the https:// attempt for `http_url` failed
+pub fn interstitial(http_url: &Url) -> String {
+    STATE.lock().unwrap().interstitial_for = Some(http_url.to_string());
+    INTERSTITIAL_HTML
+        .replace("{{HOST}}", &html_escape::encode_text(http_url.host_str().unwrap_or_default()))
+        .replace("{{URL}}", &html_escape::encode_double_quoted_attribute(http_url.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{interstitial, navigation_target};
+    use url::Url;
+
+    #[test]
+    fn http_is_upgraded_until_the_user_continues() {
+        let http = Url::parse("http://insecure.example/page").unwrap();
+        assert_eq!(navigation_target(&http), Some(Url::parse("https://insecure.example/page").unwrap()));
+
+        let page = interstitial(&http);
+        assert!(page.contains("href=\"http://insecure.example/page\""));
+
+        assert_eq!(navigation_target(&http), None);
+        let other_page = Url::parse("http://insecure.example/other").unwrap();
+        assert_eq!(navigation_target(&other_page), None);
+    }
+
+    #[test]
+    fn local_and_secure_urls_are_left_alone() {
+        assert_eq!(navigation_target(&Url::parse("http://localhost:3000/").unwrap()), None);
+        assert_eq!(navigation_target(&Url::parse("https://example.com/").unwrap()), None);
+    }
+}
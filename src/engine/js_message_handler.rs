@@ -1,4 +1,5 @@
 use crate::engine::Engine;
+use tracing::{debug, warn};
 use crate::engine::js_provider::{JsProviderMessage, ScriptKind};
 
 impl Engine {
@@ -20,7 +21,7 @@ impl Engine {
                 source_url,
                 node_id,
             } => {
-                println!("Executing script ({} bytes)", script.len());
+                debug!("Executing script ({} bytes)", script.len());
                 if self.config.debug_js {
                     write_debug_script_copy(&script);
                 }
@@ -38,6 +39,11 @@ impl Engine {
                     }
                 }
             }
+            JsProviderMessage::Extension(call) => {
+                if let Some(runtime) = &mut self.js_runtime {
+                    crate::js::bindings::extension_api::call(runtime, call);
+                }
+            }
         }
     }
 }
@@ -50,7 +56,7 @@ fn write_debug_script_copy(script: &str) {
     let debug_dir = Path::new("debug_js");
     if !debug_dir.exists() {
         if let Err(e) = fs::create_dir_all(debug_dir) {
-            eprintln!("Failed to create debug_js directory: {}", e);
+            warn!("Failed to create debug_js directory: {}", e);
             return;
         }
     }
@@ -63,9 +69,9 @@ fn write_debug_script_copy(script: &str) {
     let filepath = debug_dir.join(format!("inline_script_{millis}.js"));
 
     if let Err(e) = fs::write(&filepath, script) {
-        eprintln!("Failed to write inline script to {}: {}", filepath.display(), e);
+        warn!("Failed to write inline script to {}: {}", filepath.display(), e);
     } else {
-        println!("Saved inline script to {}", filepath.display());
+        debug!("Saved inline script to {}", filepath.display());
     }
 }
 
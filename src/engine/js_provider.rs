@@ -1,3 +1,4 @@
+use crate::js::ExtensionCall;
 use std::sync::mpsc::Sender;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -16,6 +17,8 @@ pub enum JsProviderMessage {
         /// Node ID of the `<script>` element being executed, for `document.currentScript`.
         node_id: Option<usize>,
     },
+    /// Run a content script or deliver an answer through the WebExtension API
+    Extension(ExtensionCall),
 }
 
 pub struct StokesJsProvider {
@@ -27,8 +30,14 @@ impl StokesJsProvider {
         Self { sender }
     }
 
+    /// Queue a script for the main loop, waking it, as scripts come from network and socket threads
+    fn send(&self, message: JsProviderMessage) {
+        let _ = self.sender.send(message);
+        crate::ipc::wake_main_loop();
+    }
+
     pub fn execute_script(&self, script: String) {
-        let _ = self.sender.send(JsProviderMessage::ExecuteScript {
+        self.send(JsProviderMessage::ExecuteScript {
             script,
             script_kind: ScriptKind::Classic,
             source_url: None,
@@ -37,7 +46,7 @@ impl StokesJsProvider {
     }
 
     pub fn execute_script_with_node_id(&self, script: String, node_id: usize) {
-        let _ = self.sender.send(JsProviderMessage::ExecuteScript {
+        self.send(JsProviderMessage::ExecuteScript {
             script,
             script_kind: ScriptKind::Classic,
             source_url: None,
@@ -46,12 +55,16 @@ impl StokesJsProvider {
     }
 
     pub fn execute_module_script_with_node_id(&self, script: String, node_id: usize, source_url: Option<String>) {
-        let _ = self.sender.send(JsProviderMessage::ExecuteScript {
+        self.send(JsProviderMessage::ExecuteScript {
             script,
             script_kind: ScriptKind::Module,
             source_url,
             node_id: Some(node_id),
         });
     }
+
+    pub fn call_extension(&self, call: ExtensionCall) {
+        self.send(JsProviderMessage::Extension(call));
+    }
 }
 
@@ -0,0 +1,134 @@
+// Mixed content: insecure (http://, ws://) subresources requested by a page that was loaded over
+// https://. Anything that can change the page — scripts, stylesheets, fonts, frames, fetch/XHR — is
+// blocked outright. Images are "passive" and are either upgraded to https:// or loaded as is and
+// reported, depending on EngineConfig::upgrade_mixed_content.
+use serde::{Deserialize, Serialize};
+use url::{Host, Url};
+
+/// How much a subresource can affect the page that loads it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentKind {
+    /// Only displayed (images)
+    Passive,
+    /// Runs or restyles the page, or hands data to scripts
+    Active,
+}
+
+/// What to do with a subresource request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MixedContentAction {
+    Allow,
+    /// Fetch this https:// URL instead
+    Upgrade(Url),
+    /// Load over an insecure connection; the page is no longer fully secure
+    AllowInsecure,
+    Block,
+}
+
+/// Mixed content seen on the current page, shown next to the address bar
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MixedContentStatus {
+    /// Number of insecure requests that were blocked
+    pub blocked: u32,
+    /// Whether insecure passive content was displayed
+    pub insecure_displayed: bool,
+}
+
+impl MixedContentStatus {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Decide how `request_url`, requested by the document at `document_url`, may be loaded
+pub fn check(document_url: &Url, request_url: &Url, kind: ContentKind, upgrade_passive: bool) -> MixedContentAction {
+    if document_url.scheme() != "https" || is_potentially_trustworthy(request_url) {
+        return MixedContentAction::Allow;
+    }
+    match kind {
+        ContentKind::Active => MixedContentAction::Block,
+        ContentKind::Passive if upgrade_passive => match upgrade(request_url) {
+            Some(upgraded) => MixedContentAction::Upgrade(upgraded),
+            None => MixedContentAction::AllowInsecure,
+        },
+        ContentKind::Passive => MixedContentAction::AllowInsecure,
+    }
+}
+
+/// Whether content from `url` is delivered securely: encrypted schemes, local schemes and the
+/// loopback host
+pub fn is_potentially_trustworthy(url: &Url) -> bool {
+    match url.scheme() {
+        "https" | "wss" | "file" | "data" | "blob" | "about" | "stokes" => true,
+        "http" | "ws" => is_loopback(url),
+        _ => false,
+    }
+}
+
+/// Whether `url` points at this machine, which is never upgraded or treated as insecure
+pub fn is_loopback(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            domain == "localhost" || domain.ends_with(".localhost")
+        }
+        Some(Host::Ipv4(address)) => address.is_loopback(),
+        Some(Host::Ipv6(address)) => address.is_loopback(),
+        None => false,
+    }
+}
+
+/// The secure equivalent of an http:// or ws:// URL. The default port follows the scheme; an
+/// explicit port is kept, since dropping it would change the target.
+pub fn upgrade(url: &Url) -> Option<Url> {
+    let secure_scheme = match url.scheme() {
+        "http" => "https",
+        "ws" => "wss",
+        _ => return None,
+    };
+    let mut upgraded = url.clone();
+    upgraded.set_scheme(secure_scheme).ok()?;
+    Some(upgraded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, upgrade, ContentKind, MixedContentAction};
+    use url::Url;
+
+    fn url(input: &str) -> Url {
+        Url::parse(input).unwrap()
+    }
+
+    #[test]
+    fn active_content_on_secure_pages_is_blocked() {
+        let page = url("https://example.com/");
+        assert_eq!(check(&page, &url("http://cdn.example/app.js"), ContentKind::Active, true), MixedContentAction::Block);
+        assert_eq!(check(&page, &url("ws://example.com/socket"), ContentKind::Active, true), MixedContentAction::Block);
+        assert_eq!(check(&page, &url("https://cdn.example/app.js"), ContentKind::Active, true), MixedContentAction::Allow);
+        assert_eq!(check(&page, &url("http://localhost:8080/app.js"), ContentKind::Active, true), MixedContentAction::Allow);
+    }
+
+    #[test]
+    fn passive_content_is_upgraded_or_reported() {
+        let page = url("https://example.com/");
+        let image = url("http://img.example:80/a.png");
+        assert_eq!(
+            check(&page, &image, ContentKind::Passive, true),
+            MixedContentAction::Upgrade(url("https://img.example/a.png")),
+        );
+        assert_eq!(check(&page, &image, ContentKind::Passive, false), MixedContentAction::AllowInsecure);
+    }
+
+    #[test]
+    fn insecure_pages_load_anything() {
+        let page = url("http://example.com/");
+        assert_eq!(check(&page, &url("http://cdn.example/app.js"), ContentKind::Active, true), MixedContentAction::Allow);
+    }
+
+    #[test]
+    fn upgrade_keeps_custom_ports() {
+        assert_eq!(upgrade(&url("http://example.com:8080/x?y")), Some(url("https://example.com:8080/x?y")));
+        assert_eq!(upgrade(&url("ftp://example.com/")), None);
+    }
+}
@@ -1,6 +1,8 @@
 // The core browser engine that coordinates between components
 mod config;
 pub(crate) mod adblock;
+pub(crate) mod https_first;
+pub(crate) mod mixed_content;
 pub mod net_provider;
 pub mod nav_provider;
 pub mod resolve;
@@ -8,19 +10,27 @@ pub mod js_provider;
 pub(crate) mod js_message_handler;
 pub(crate) mod script_type;
 pub(crate) mod script_executor;
+pub mod teardown;
 
 pub use self::config::EngineConfig;
 use crate::dom::node::{RasterImageData, SpecialElementData};
 use crate::dom::{Dom, ImageData, NodeData};
+use crate::dom::scroll::ScrollBehavior;
 use crate::dom::{EventDispatcher, EventType};
-use crate::js::JsRuntime;
+use crate::ipc::PendingNavigation;
+use crate::js::{ExtensionCall, JsRuntime};
 use crate::js::runtime::RUNTIME;
 use crate::networking;
 use crate::networking::{NetworkError, HttpClient};
 use crate::renderer::painter::ScenePainter;
 use crate::renderer::HtmlRenderer;
 use crate::shell_provider::StokesShellProvider;
-use blitz_traits::shell::Viewport;
+use crate::media_preferences::MediaPreferences;
+use crate::visited_links::{self, VisitedLinkFilter};
+use crate::site_settings::{ColorSchemeOverride, SiteSettings, SiteSettingsStore};
+use crate::visual_viewport::VisualViewport;
+use crate::extensions::{extension_id_of, ExtensionKind, ExtensionStore, RunAt, UserExtension, EXTENSION_PAGE_PRELUDE};
+use blitz_traits::shell::{ColorScheme, Viewport};
 use markup5ever::local_name;
 use std::cell::RefCell;
 use std::collections::HashMap;
@@ -34,12 +44,41 @@ use style::thread_state::ThreadState;
 use crate::engine::js_provider::{JsProviderMessage, StokesJsProvider};
 use crate::engine::nav_provider::StokesNavigationProvider;
 use crate::engine::script_executor::{collect_pending_scripts, dispatch_script, resolve_script_fetch_context};
+use tracing::{debug, error, info, trace, warn};
 
 thread_local! {
     pub(crate) static ENGINE_REF: RefCell<Option<*mut Engine>> = RefCell::new(None);
     pub(crate) static USER_AGENT_REF: RefCell<Option<String>> = RefCell::new(None);
 }
 
+/// Document loaded by `Engine::warm_up`; it has text so fonts get loaded and a script so the
+/// JavaScript runtime is set up
+const WARM_UP_DOCUMENT: &str = "<!DOCTYPE html><html><body><p>Stokes</p><script>void 0;</script></body></html>";
+
+/// A session history entry: the request that loaded it, the document it was shown in and where
+/// the viewport was scrolled when the user last left it
+#[derive(Clone)]
+struct HistoryEntry {
+    request: Request,
+    /// Id of the Dom showing this entry, shared by entries that only differ by fragment. `None`
+    /// for entries restored from a closed tab.
+    document_id: Option<usize>,
+    scroll: taffy::Point<f64>,
+}
+
+impl HistoryEntry {
+    fn new(request: Request, document_id: Option<usize>) -> Self {
+        Self { request, document_id, scroll: taffy::Point { x: 0.0, y: 0.0 } }
+    }
+}
+
+/// `url` without its fragment, for telling fragment navigations apart from document loads
+fn without_fragment(url: &url::Url) -> url::Url {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    url
+}
+
 /// The core browser engine that coordinates all browser activities
 pub struct Engine {
     pub config: EngineConfig,
@@ -53,10 +92,25 @@ pub struct Engine {
     content_height: f32,
     content_width: f32,
     pub(crate) viewport: Viewport,
+    media_preferences: MediaPreferences,
+    // Whether the tab is the one shown in its window
+    visible: bool,
+    // Whether the user muted the tab; media elements check this before producing sound
+    muted: bool,
+    // The part of the page the user magnified by pinching, which the browser applies at composite
+    visual_viewport: VisualViewport,
+    // The generation of the document's font loads that `document.fonts` last heard about
+    reported_font_loads: Option<u64>,
+    // The user's settings for the current document's origin
+    site_settings: SiteSettings,
+    // Whether the user lets the current document's origin run JavaScript
+    javascript_allowed: bool,
+    // The URL of the page that opened this tab, when it let the tab reach it as `window.opener`
+    opener: Option<String>,
     // JavaScript runtime
     js_runtime: Option<JsRuntime>,
     // Navigation history
-    history: Vec<Request>,
+    history: Vec<HistoryEntry>,
     history_index: Option<usize>,
     shell_provider: Arc<StokesShellProvider>,
     pub(crate) navigation_provider: Arc<StokesNavigationProvider>,
@@ -81,6 +135,14 @@ impl Engine {
             content_height: 0.0,
             content_width: 0.0,
             viewport,
+            media_preferences: MediaPreferences::default(),
+            visible: true,
+            muted: false,
+            visual_viewport: VisualViewport::default(),
+            reported_font_loads: None,
+            site_settings: SiteSettings::default(),
+            javascript_allowed: true,
+            opener: None,
             js_runtime: None,
             history: Vec::new(),
             history_index: None,
@@ -100,22 +162,35 @@ impl Engine {
     }
 
     /// Navigate to a new URL
+    #[tracing::instrument(name = "navigate", skip_all, fields(url = %url))]
     pub async fn navigate(&mut self, url: &str, contents: String, invalidate_js: bool, history: bool, history_request: Option<Request>) -> Result<(), NetworkError> {
-        println!("Navigating to: {}", url);
+        info!("Navigating to: {}", url);
+        if history {
+            self.remember_scroll_position();
+        }
         self.is_loading = true;
         self.current_url = url.to_string();
 
         // Fetch the page content
         let result = async {
+            // Let go of the previous document before building the next one
+            self.teardown_document();
 
+            self.site_settings = site_settings_for(url);
+            self.javascript_allowed = site_store_allows(url, SiteSettingsStore::javascript_allowed);
+            let block_images = !site_store_allows(url, SiteSettingsStore::images_allowed);
+            self.viewport.color_scheme = self.preferred_color_scheme();
 
             // Parse the HTML into our DOM
-            let dom = Dom::parse_html(
+            let mut dom = Dom::parse_html(
                 url,
                 &contents,
                 self.config.user_agent.clone(),
                 self.config.debug_net,
                 self.config.block_ads,
+                self.config.upgrade_mixed_content,
+                block_images,
+                self.config.request_policy.clone(),
                 self.viewport.clone(),
                 self.shell_provider.clone(),
                 self.navigation_provider.clone(),
@@ -134,9 +209,20 @@ impl Engine {
             });
 
             // Store the DOM
+            dom.prefers_reduced_motion = self.media_preferences.reduced_motion;
+            dom.image_cache.set_budget(self.config.image_memory_budget);
+            dom.color_scheme_override = self.site_settings.color_scheme;
+            dom.force_dark = self.site_settings.force_dark;
+            dom.autoplay = self.site_settings.autoplay;
+            dom.popups_allowed = self.site_settings.popups;
             self.dom = Some(dom);
-            if invalidate_js && self.config.enable_javascript {
-                self.prepare_js_runtime_for_navigation();
+            if invalidate_js {
+                if self.scripts_enabled() {
+                    self.prepare_js_runtime_for_navigation();
+                } else {
+                    // Don't leave the previous page's timers and handlers running against this one
+                    self.js_runtime = None;
+                }
             }
 
             // Reset scroll position
@@ -146,15 +232,52 @@ impl Engine {
             // Parse and apply CSS styles from the document
             self.parse_document_styles().await;
 
-            if self.config.enable_javascript {
+            // User styles come after the page's own so they win ties; user scripts are queued
+            // around the page's scripts according to their run-at
+            let extensions = ExtensionStore::load_from_disk();
+            let extensions = extensions.for_url(url);
+            for style in extensions.iter().filter(|extension| extension.kind == ExtensionKind::Style) {
+                debug!("Applying user style {}", style.file_name);
+                self.add_author_stylesheet(&style.source);
+            }
+            // Only the WebExtensions with scripts on this page, or whose page this is, may use
+            // their storage and messaging
+            let extension_page = url::Url::parse(url).ok().and_then(|url| extension_id_of(&url).map(str::to_string));
+            let mut allowed_extensions: Vec<String> = extensions.iter().filter_map(|extension| extension.web_extension.clone()).collect();
+            allowed_extensions.extend(extension_page.clone());
+            allowed_extensions.sort();
+            allowed_extensions.dedup();
+            crate::js::set_allowed_extensions(allowed_extensions);
+            crate::js::set_opener(self.opener.clone());
+            let user_scripts = |run_at: RunAt| {
+                extensions
+                    .iter()
+                    .filter(move |extension| extension.kind == ExtensionKind::Script && extension.run_at == run_at)
+            };
+
+            if self.scripts_enabled() {
                 style::thread_state::enter(ThreadState::SCRIPT);
+                if let Some(id) = &extension_page {
+                    self.js_provider.call_extension(ExtensionCall::RunScript {
+                        extension_id: id.clone(),
+                        source: EXTENSION_PAGE_PRELUDE.to_string(),
+                    });
+                }
+                for script in user_scripts(RunAt::DocumentStart) {
+                    debug!("Running user script {} at document start", script.file_name);
+                    self.run_user_script(script);
+                }
                 self.execute_document_scripts().await;
+                for script in user_scripts(RunAt::DocumentEnd) {
+                    debug!("Running user script {} at document end", script.file_name);
+                    self.run_user_script(script);
+                }
                 style::thread_state::exit(ThreadState::SCRIPT);
             }
 
             self.resolve(0.0);
 
-            if self.config.enable_javascript {
+            if self.scripts_enabled() {
                 // Fire DOMContentLoaded/load only after parser scripts have actually executed.
                 if let Some(dom) = self.dom.as_ref() {
                     crate::js::bindings::event_listeners::fire_load_events(dom);
@@ -164,6 +287,10 @@ impl Engine {
             // Calculate layout with CSS styles applied
             self.update_content_dimensions();
 
+            if let Some(fragment) = url::Url::parse(url).ok().and_then(|url| url.fragment().map(str::to_string)) {
+                self.dom_mut().scroll_to_fragment(&fragment, ScrollBehavior::Instant);
+            }
+
             Ok(())
         }.await;
 
@@ -177,13 +304,25 @@ impl Engine {
             } else if let Ok(parsed_url) = url::Url::parse(url) {
                 self.add_to_history(Request::get(parsed_url));
             } else {
-                eprintln!("Skipping history entry for unparsable URL: {url}");
+                warn!("Skipping history entry for unparsable URL: {url}");
+            }
+        } else if result.is_ok() {
+            // Reloads and history traversals show the current entry in the new document
+            let document_id = self.dom.as_ref().map(|dom| dom.id);
+            if let Some(entry) = self.history_index.and_then(|index| self.history.get_mut(index)) {
+                entry.document_id = document_id;
             }
         }
 
         result
     }
 
+    /// Whether the current document may run JavaScript, which needs both the engine and the user's
+    /// settings for its origin to allow it
+    fn scripts_enabled(&self) -> bool {
+        self.config.enable_javascript && self.javascript_allowed
+    }
+
     fn prepare_js_runtime_for_navigation(&mut self) {
         let Some(dom_ptr) = self.dom.as_mut().map(|dom| dom as *mut Dom) else {
             return;
@@ -192,13 +331,21 @@ impl Engine {
 
         if let Some(runtime) = self.js_runtime.as_mut() {
             if let Err(err) = runtime.reset_for_navigation(dom_ptr, user_agent.clone()) {
-                eprintln!("JavaScript runtime reset failed during navigation: {err}. Recreating runtime.");
+                warn!("JavaScript runtime reset failed during navigation: {err}. Recreating runtime.");
                 self.js_runtime = None;
                 self.initialize_js_runtime();
             }
         } else {
             self.initialize_js_runtime();
         }
+
+        // A document loaded in a background tab starts out hidden
+        if !self.visible {
+            self.apply_visibility(false);
+        }
+        // and every document starts out unmagnified; the browser resets its copy likewise
+        self.visual_viewport = VisualViewport::default();
+        self.reported_font_loads = None;
     }
 
     /// Update the viewport size
@@ -231,6 +378,149 @@ impl Engine {
         style::thread_state::exit(ThreadState::LAYOUT);
     }
 
+    /// Apply the desktop's dark mode and reduced-motion settings, restyling the page and
+    /// notifying matchMedia listeners
+    pub fn set_media_preferences(&mut self, preferences: MediaPreferences) {
+        if preferences == self.media_preferences {
+            return;
+        }
+        self.media_preferences = preferences;
+        if let Some(dom) = &mut self.dom {
+            dom.prefers_reduced_motion = preferences.reduced_motion;
+        }
+        self.set_viewport(Viewport {
+            color_scheme: self.preferred_color_scheme(),
+            ..self.viewport
+        });
+    }
+
+    /// Warm up a connection to `url`'s origin, which the user is likely about to navigate to
+    pub fn preconnect(&self, url: &str) {
+        let Ok(url) = url::Url::parse(url) else {
+            return;
+        };
+        let net_provider = self
+            .new_http_client
+            .as_ref()
+            .map(|client| client.net_provider.clone())
+            .or_else(|| self.dom.as_ref().map(|dom| dom.net_provider.clone()));
+        if let Some(net_provider) = net_provider {
+            net_provider.preconnect(url);
+        }
+    }
+
+    /// Replace the visited-link filter and restyle the page's links against it
+    pub fn set_visited_links(&mut self, filter: VisitedLinkFilter) {
+        visited_links::set_filter(filter);
+        if let Some(dom) = &mut self.dom {
+            dom.update_visited_links();
+        }
+    }
+
+    /// Use the user's new language list for requests and navigator.languages, firing
+    /// `languagechange` on the page's window
+    pub fn set_languages(&mut self, languages: Vec<String>) {
+        if languages.is_empty() || languages == crate::languages::current() {
+            return;
+        }
+        let json = serde_json::to_string(&languages).unwrap_or_else(|_| "[]".to_string());
+        crate::languages::set(languages);
+        let Some(runtime) = &mut self.js_runtime else {
+            return;
+        };
+        let script = format!("if (typeof __stokesSetLanguages === 'function') {{ __stokesSetLanguages({json}); }}");
+        if let Err(e) = runtime.execute(&script, false) {
+            warn!("Failed to update navigator.languages: {}", e);
+        }
+    }
+
+    /// Show or hide the tab: hidden tabs clamp their timers to one second and tell the page
+    /// through document.visibilityState and a visibilitychange event
+    pub fn set_visibility(&mut self, visible: bool) {
+        if visible == self.visible {
+            return;
+        }
+        self.visible = visible;
+        self.apply_visibility(true);
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Remember the page that opened this tab, for `window.opener` in the pages it shows
+    pub fn set_opener(&mut self, url: String) {
+        self.opener = Some(url);
+    }
+
+    /// Tell the page where the browser now shows the visual viewport, firing `resize` on
+    /// `window.visualViewport` when it was magnified and `scroll` when it was panned
+    pub fn set_visual_viewport(&mut self, visual_viewport: VisualViewport) {
+        let previous = std::mem::replace(&mut self.visual_viewport, visual_viewport);
+        let Some(runtime) = &mut self.js_runtime else {
+            return;
+        };
+        // The page measures in CSS pixels, which page zoom makes larger
+        let zoom = self.viewport.zoom;
+        let script = format!(
+            "if (typeof __stokesSetVisualViewport === 'function') {{ __stokesSetVisualViewport({}, {}, {}, {}, {}); }}",
+            visual_viewport.scale,
+            visual_viewport.offset_x / zoom,
+            visual_viewport.offset_y / zoom,
+            visual_viewport.scale != previous.scale,
+            visual_viewport.offset_x != previous.offset_x || visual_viewport.offset_y != previous.offset_y,
+        );
+        if let Err(e) = runtime.execute(&script, false) {
+            warn!("Failed to update the visual viewport: {}", e);
+        }
+    }
+
+    fn apply_visibility(&mut self, notify: bool) {
+        if let Some(runtime) = &mut self.js_runtime {
+            runtime.timer_manager().set_throttled(!self.visible);
+            let script = format!(
+                "if (typeof __stokesSetVisibility === 'function') {{ __stokesSetVisibility({}, {}); }}",
+                self.visible, notify,
+            );
+            if let Err(e) = runtime.execute(&script, false) {
+                warn!("Failed to update document visibility: {}", e);
+            }
+        }
+    }
+
+    /// Re-read the current site's settings after the user changed them in the parent
+    pub fn reload_site_settings(&mut self) {
+        self.site_settings = site_settings_for(&self.current_url);
+        if let Some(dom) = &mut self.dom {
+            dom.color_scheme_override = self.site_settings.color_scheme;
+            dom.force_dark = self.site_settings.force_dark;
+            dom.autoplay = self.site_settings.autoplay;
+            dom.popups_allowed = self.site_settings.popups;
+            dom.shell_provider.request_redraw();
+        }
+        self.set_viewport(Viewport {
+            color_scheme: self.preferred_color_scheme(),
+            ..self.viewport
+        });
+    }
+
+    /// The scheme pages are asked for: the user's choice for this site, otherwise the desktop's
+    fn preferred_color_scheme(&self) -> ColorScheme {
+        match self.site_settings.color_scheme {
+            ColorSchemeOverride::Auto => self.media_preferences.color_scheme(),
+            ColorSchemeOverride::Light => ColorScheme::Light,
+            ColorSchemeOverride::Dark => ColorScheme::Dark,
+        }
+    }
+
     /// Get the viewport size
     #[inline]
     pub fn viewport_size(&self) -> (u32, u32) {
@@ -266,7 +556,112 @@ impl Engine {
     /// Render the current page to a canvas
     pub fn render(&mut self, painter: &mut ScenePainter, now: f64) {
         self.resolve(now);
+        self.fire_scroll_events();
+        self.fire_toggle_events();
+        self.fire_selection_change();
+        self.update_font_faces();
+        let height = self.viewport_height() as u32;
+        self.paint(painter, height);
+        self.update_intersection_observers();
+    }
+
+    /// Fire one `scroll` event per target that scrolled since the last frame, so listeners run at
+    /// most once a frame however fast the page scrolls, and see where it is now
+    fn fire_scroll_events(&mut self) {
+        let Some(dom) = self.dom.as_mut() else {
+            return;
+        };
+        let targets = dom.take_scroll_events();
+        if targets.is_empty() || self.js_runtime.is_none() {
+            return;
+        }
+        crate::js::bindings::event_listeners::fire_scroll_events(dom, &targets);
+    }
+
+    /// Fire `toggle` at the details elements opened or closed since the last frame
+    fn fire_toggle_events(&mut self) {
+        let Some(dom) = self.dom.as_mut() else {
+            return;
+        };
+        let targets = dom.take_toggle_events();
+        if targets.is_empty() || self.js_runtime.is_none() {
+            return;
+        }
+        crate::js::bindings::event_listeners::fire_toggle_events(&targets);
+    }
+
+    /// Fire `selectionchange` at the document if the selection moved since the last frame
+    fn fire_selection_change(&mut self) {
+        let Some(dom) = self.dom.as_mut() else {
+            return;
+        };
+        if dom.take_selection_change() && self.js_runtime.is_some() {
+            crate::js::bindings::event_listeners::fire_selection_change();
+        }
+    }
+
+    /// Tell `document.fonts` about web fonts the document started loading or finished loading
+    /// since the last frame. The page first hears once it has finished loading, so
+    /// `document.fonts.ready` doesn't resolve before its stylesheets have asked for any fonts.
+    fn update_font_faces(&mut self) {
+        let Some(dom) = self.dom.as_ref() else {
+            return;
+        };
+        let generation = dom.font_loads.generation();
+        if self.reported_font_loads == Some(generation) || (self.is_loading && generation == 0) {
+            return;
+        }
+        let Some(runtime) = &mut self.js_runtime else {
+            return;
+        };
+        self.reported_font_loads = Some(generation);
+        let faces: Vec<_> = dom
+            .font_loads
+            .faces()
+            .iter()
+            .map(|face| {
+                serde_json::json!({
+                    "family": face.family,
+                    "url": face.url,
+                    "display": face.display.as_str(),
+                    "status": face.status.as_str(),
+                })
+            })
+            .collect();
+        let script = format!(
+            "if (typeof __stokesUpdateFontFaces === 'function') {{ __stokesUpdateFontFaces({}); }}",
+            serde_json::Value::Array(faces),
+        );
+        if let Err(e) = runtime.execute(&script, false) {
+            warn!("Failed to update document.fonts: {}", e);
+        }
+    }
+
+    /// Let IntersectionObservers compare their targets against the frame just painted; their
+    /// callbacks run as microtasks and redraw the page if they change it
+    fn update_intersection_observers(&mut self) {
+        if !self.dom.as_ref().is_some_and(|dom| dom.observing_intersections) {
+            return;
+        }
+        if let Some(runtime) = &mut self.js_runtime {
+            let script = "if (typeof __stokesUpdateIntersectionObservers === 'function') { __stokesUpdateIntersectionObservers(); }";
+            if let Err(e) = runtime.execute(script, false) {
+                warn!("Failed to update intersection observers: {}", e);
+            }
+        }
+    }
+
+    /// Render the whole page, scrolled to the top, onto a canvas `height` physical pixels tall
+    pub fn render_full_page(&mut self, painter: &mut ScenePainter, now: f64, height: u32) {
+        self.resolve(now);
+        let scroll = self.dom().viewport_scroll;
+        self.dom_mut().viewport_scroll.y = 0.0;
+        self.paint(painter, height);
+        self.dom_mut().viewport_scroll = scroll;
+    }
 
+    fn paint(&self, painter: &mut ScenePainter, height: u32) {
+        let _span = tracing::debug_span!(target: "paint", "paint").entered();
         let dom = self.dom.as_ref().unwrap();
         let node = dom.root_node();
 
@@ -280,10 +675,11 @@ impl Engine {
             dom,
             scale_factor: self.viewport.scale_f64(),
             width: self.viewport_width() as u32,
-            height: self.viewport_height() as u32,
+            height,
             initial_x: 0.0,
             initial_y: 0.0,
             selection_ranges: selection,
+            find_highlights: dom.find_highlights(),
             debug_hitboxes: self.config.debug_hitboxes,
         };
 
@@ -330,6 +726,11 @@ impl Engine {
         &self.page_title
     }
 
+    /// Record a title the page set after it was parsed
+    pub fn set_page_title(&mut self, title: String) {
+        self.page_title = title;
+    }
+
     /// Get the current URL
     pub fn current_url(&self) -> &str {
         &self.current_url
@@ -400,8 +801,11 @@ impl Engine {
         let y = self.scroll_y as f64;
 
         let dom = self.dom_mut();
-        dom.viewport_scroll.x = x;
-        dom.viewport_scroll.y = y;
+        if dom.viewport_scroll.x != x || dom.viewport_scroll.y != y {
+            dom.viewport_scroll.x = x;
+            dom.viewport_scroll.y = y;
+            dom.queue_scroll_event(dom.root_node().id);
+        }
     }
 
     /// Set scroll position directly
@@ -422,14 +826,32 @@ impl Engine {
         }
     }
 
+    /// Do the slow first-time setup (the JavaScript engine, system fonts, the style engine) on a
+    /// blank document, so the first real page loads without it. The tab still reads as a new tab
+    /// afterwards.
+    #[tracing::instrument(name = "warm_up", skip_all)]
+    pub async fn warm_up(&mut self) {
+        if let Err(e) = self.navigate("about:blank", WARM_UP_DOCUMENT.to_string(), true, false, None).await {
+            warn!("Warm-up failed: {}", e);
+        }
+        self.current_url.clear();
+        self.page_title = "New Tab".to_string();
+    }
+
     /// Initialize JavaScript runtime for the current document
     pub fn initialize_js_runtime(&mut self) {
+        // WebGL contexts belong to the previous document's canvases
+        crate::renderer::webgl::release_all();
+        if !self.scripts_enabled() {
+            debug!("JavaScript is disabled for {}, not starting a runtime", self.current_url);
+            return;
+        }
         let user_agent = self.config.user_agent.clone();
         let dom = self.dom_mut();
         let dom = dom as *mut Dom;
         match JsRuntime::new(dom, user_agent) {
             Ok(runtime) => {
-                println!("JavaScript runtime initialized successfully");
+                debug!("JavaScript runtime initialized successfully");
                 self.js_runtime = Some(runtime);
                 // Now that the JsRuntime is at its final stable address inside
                 // self.js_runtime, update the thread-local so that code paths
@@ -439,37 +861,55 @@ impl Engine {
                 }
             }
             Err(e) => {
-                eprintln!("Failed to initialize JavaScript runtime: {}", e);
+                error!("Failed to initialize JavaScript runtime: {}", e);
             }
         }
     }
 
     /// Execute JavaScript code in the current context
+    /// Queue a user script. Content scripts of WebExtensions go through the extension API, which
+    /// gives them their `browser` object without putting it on the page's global.
+    fn run_user_script(&self, script: &UserExtension) {
+        match &script.web_extension {
+            Some(id) => self.js_provider.call_extension(ExtensionCall::RunScript {
+                extension_id: id.clone(),
+                source: script.source.clone(),
+            }),
+            None => self.js_provider.execute_script(script.wrapped_script()),
+        }
+    }
+
     pub fn execute_javascript(&mut self, code: &str, print_eval_error: bool) {
         if let Some(runtime) = &mut self.js_runtime {
             if let Err(e) = runtime.execute_script(code, print_eval_error) {
-                eprintln!("JavaScript execution error: {}", e);
+                error!("JavaScript execution error: {}", e);
             }
         } else {
-            eprintln!("JavaScript runtime not initialized");
+            error!("JavaScript runtime not initialized");
         }
     }
 
     pub fn execute_module_javascript(&mut self, code: &str, source_url: Option<&str>, print_eval_error: bool) {
         if let Some(runtime) = &mut self.js_runtime {
             if let Err(e) = runtime.execute_module_script(code, source_url, print_eval_error) {
-                eprintln!("JavaScript module execution error: {}", e);
+                error!("JavaScript module execution error: {}", e);
             }
         } else {
-            eprintln!("JavaScript runtime not initialized");
+            error!("JavaScript runtime not initialized");
         }
     }
 
     /// Extract and execute JavaScript from <script> tags in the current DOM
     pub async fn execute_document_scripts(&mut self) {
+        if !self.scripts_enabled() {
+            return;
+        }
         // Initialize JS runtime if not already done
         if self.js_runtime.is_none() {
             self.initialize_js_runtime();
+            if !self.visible {
+                self.apply_visibility(false);
+            }
         }
         let pending_scripts = {
             let dom = self.dom();
@@ -482,14 +922,14 @@ impl Engine {
                 inline_script
             } else if let Some(external_url) = pending.external_url {
                 let Some(fetch_context) = fetch_context.as_ref() else {
-                    eprintln!("[JS] Failed to load external script '{}': Network provider unavailable", external_url);
+                    warn!("Failed to load external script '{}': Network provider unavailable", external_url);
                     continue;
                 };
 
                 match fetch_context.fetch_external_script(Request::get(external_url.clone())).await {
                     Ok(script) => script,
                     Err(error) => {
-                        eprintln!("[JS] Failed to load external script '{}': {}", external_url, error);
+                        warn!("Failed to load external script '{}': {}", external_url, error);
                         continue;
                     }
                 }
@@ -508,7 +948,7 @@ impl Engine {
         if let Some(runtime) = &mut self.js_runtime {
             let context = runtime.cx();
 
-            println!("[Event] Firing click event at ({}, {}) on node {}", x, y, node_id);
+            trace!("Firing click event at ({}, {}) on node {}", x, y, node_id);
 
             if let Err(e) = EventDispatcher::dispatch_mouse_event(
                 node,
@@ -517,7 +957,7 @@ impl Engine {
                 y,
                 context,
             ) {
-                eprintln!("Error dispatching click event: {}", e);
+                warn!("Error dispatching click event: {}", e);
             }
         }
     }
@@ -559,7 +999,7 @@ impl Engine {
                 y,
                 context,
             ) {
-                eprintln!("Error dispatching mouse move event: {}", e);
+                warn!("Error dispatching mouse move event: {}", e);
             }
         }
     }
@@ -591,7 +1031,7 @@ impl Engine {
         if let Some(runtime) = &mut self.js_runtime {
             let context = runtime.cx();
 
-            println!("[Event] Firing {:?} event at ({}, {}) on node {}", event_type, x, y, node_id);
+            trace!("Firing {:?} event at ({}, {}) on node {}", event_type, x, y, node_id);
 
             if let Err(e) = EventDispatcher::dispatch_mouse_event(
                 &node,
@@ -600,7 +1040,7 @@ impl Engine {
                 y,
                 context,
             ) {
-                eprintln!("Error dispatching mouse event: {}", e);
+                warn!("Error dispatching mouse event: {}", e);
             }
         }
     }
@@ -616,7 +1056,7 @@ impl Engine {
         if let Some(runtime) = &mut self.js_runtime {
             let context = runtime.cx();
 
-            println!("[Event] Firing {:?} event with key: {} (code: {})", event_type, key, key_code);
+            trace!("Firing {:?} event with key: {} (code: {})", event_type, key, key_code);
 
             if let Err(e) = EventDispatcher::dispatch_keyboard_event(
                 root,
@@ -625,7 +1065,7 @@ impl Engine {
                 key_code,
                 context,
             ) {
-                eprintln!("Error dispatching keyboard event: {}", e);
+                warn!("Error dispatching keyboard event: {}", e);
             }
         }
     }
@@ -638,14 +1078,14 @@ impl Engine {
         if let Some(runtime) = &mut self.js_runtime {
             let context = runtime.cx();
 
-            println!("[Event] Firing scroll event");
+            trace!("Firing scroll event");
 
             if let Err(e) = EventDispatcher::dispatch_simple_event(
                 root,
                 EventType::Scroll,
                 context,
             ) {
-                eprintln!("Error dispatching scroll event: {}", e);
+                warn!("Error dispatching scroll event: {}", e);
             }
         }
     }
@@ -659,14 +1099,14 @@ impl Engine {
         if let Some(runtime) = &mut self.js_runtime {
             let context = runtime.cx();
 
-            println!("[Event] Firing resize event");
+            trace!("Firing resize event");
 
             if let Err(e) = EventDispatcher::dispatch_simple_event(
                 &root,
                 EventType::Resize,
                 context,
             ) {
-                eprintln!("Error dispatching resize event: {}", e);
+                warn!("Error dispatching resize event: {}", e);
             }
         }
     }
@@ -680,14 +1120,14 @@ impl Engine {
         if let Some(runtime) = &mut self.js_runtime {
             let context = runtime.cx();
 
-            println!("[Event] Firing load event");
+            trace!("Firing load event");
 
             if let Err(e) = EventDispatcher::dispatch_simple_event(
                 &root,
                 EventType::Load,
                 context,
             ) {
-                eprintln!("Error dispatching load event: {}", e);
+                warn!("Error dispatching load event: {}", e);
             }
         }
     }
@@ -777,18 +1217,20 @@ impl Engine {
         }
         
         // Add the new request
-        self.history.push(request);
+        let document_id = self.dom.as_ref().map(|dom| dom.id);
+        self.history.push(HistoryEntry::new(request, document_id));
         self.history_index = Some(self.history.len() - 1);
     }
 
     /// Replace the current history entry with `url` without pushing a new entry.
     /// This is used by `location.replace()`.
     pub fn replace_current_history_entry(&mut self, request: Request) {
+        let entry = HistoryEntry::new(request, self.dom.as_ref().map(|dom| dom.id));
         if let Some(index) = self.history_index {
-            self.history[index] = request;
+            self.history[index] = entry;
         } else {
             // No existing history; establish an initial entry.
-            self.history.push(request);
+            self.history.push(entry);
             self.history_index = Some(0);
         }
     }
@@ -796,7 +1238,131 @@ impl Engine {
     /// Return the request at the current history position, if one exists.
     pub fn current_history_request(&self) -> Option<Request> {
         self.history_index
-            .and_then(|index| self.history.get(index).cloned())
+            .and_then(|index| self.history.get(index))
+            .map(|entry| entry.request.clone())
+    }
+
+    /// Return the URLs of every history entry along with the current position.
+    pub fn history_entries(&self) -> (Vec<String>, Option<usize>) {
+        let entries = self.history.iter().map(|entry| entry.request.url.to_string()).collect();
+        (entries, self.history_index)
+    }
+
+    /// Replace the navigation history with `entries`, e.g. when restoring a closed tab.
+    pub fn restore_history(&mut self, entries: &[String], index: usize) {
+        self.history = entries
+            .iter()
+            .filter_map(|entry| url::Url::parse(entry).ok())
+            .map(|url| HistoryEntry::new(Request::get(url), None))
+            .collect();
+        self.history_index = self.history.len().checked_sub(1).map(|last| index.min(last));
+    }
+
+    /// Put `entries` before the navigation history, when a prerendered page was swapped into a tab
+    /// that had been somewhere before
+    pub fn prepend_history(&mut self, entries: &[String]) {
+        let entries: Vec<HistoryEntry> = entries
+            .iter()
+            .filter_map(|entry| url::Url::parse(entry).ok())
+            .map(|url| HistoryEntry::new(Request::get(url), None))
+            .collect();
+        let count = entries.len();
+        self.history.splice(0..0, entries);
+        self.history_index = self.history_index.map(|index| index + count);
+    }
+
+    /// Store the viewport's scroll offset in the current history entry, to restore it when the
+    /// user comes back to it
+    fn remember_scroll_position(&mut self) {
+        let Some(dom) = self.dom.as_ref() else {
+            return;
+        };
+        if let Some(entry) = self.history_index.and_then(|index| self.history.get_mut(index)) {
+            entry.scroll = dom.viewport_scroll;
+        }
+    }
+
+    /// Scroll the viewport back to where it was when the user left the current history entry
+    fn restore_scroll_position(&mut self) {
+        let Some(scroll) = self.history_index.and_then(|index| self.history.get(index)).map(|entry| entry.scroll) else {
+            return;
+        };
+        if let Some(dom) = self.dom.as_mut() {
+            dom.scroll_viewport_to(scroll.x, scroll.y);
+        }
+    }
+
+    /// Whether following `request` only moves to a fragment of the current document, which
+    /// scrolls instead of loading the page again
+    pub fn is_fragment_navigation(&self, request: &Request) -> bool {
+        if self.dom.is_none() || request.method.as_str() != "GET" || request.url.fragment().is_none() {
+            return false;
+        }
+        url::Url::parse(&self.current_url)
+            .is_ok_and(|current| without_fragment(&current) == without_fragment(&request.url))
+    }
+
+    /// Navigate to a fragment of the current document: push a history entry for the same
+    /// document and scroll to the indicated element, honoring `scroll-behavior`
+    pub fn navigate_to_fragment(&mut self, request: Request) {
+        self.remember_scroll_position();
+        let url = request.url.clone();
+        self.add_to_history(request);
+        self.show_fragment(url, ScrollBehavior::Auto);
+    }
+
+    /// Make `url`, a fragment of the current document, the current URL and scroll to its
+    /// fragment. Script sees the new `location` and a `hashchange` event.
+    fn show_fragment(&mut self, url: url::Url, behavior: ScrollBehavior) {
+        let old_url = std::mem::replace(&mut self.current_url, url.to_string());
+        let fragment = url.fragment().unwrap_or_default().to_string();
+        if let Some(dom) = self.dom.as_mut() {
+            dom.set_url_fragment(url.clone());
+            dom.scroll_to_fragment(&fragment, behavior);
+        }
+
+        if old_url == self.current_url {
+            return;
+        }
+        if let Some(runtime) = &mut self.js_runtime {
+            let href = serde_json::to_string(url.as_str()).unwrap_or_default();
+            let hash = serde_json::to_string(&url.fragment().map(|fragment| format!("#{fragment}")).unwrap_or_default()).unwrap_or_default();
+            let old_href = serde_json::to_string(&old_url).unwrap_or_default();
+            let script = format!(
+                "(function() {{\
+                    if (typeof globalThis === 'undefined' || !globalThis.location) {{ return; }}\
+                    globalThis.location.href = {href};\
+                    globalThis.location.hash = {hash};\
+                    if (typeof globalThis.dispatchEvent === 'function' && typeof globalThis.Event === 'function') {{\
+                        const event = new globalThis.Event('hashchange');\
+                        event.oldURL = {old_href};\
+                        event.newURL = {href};\
+                        globalThis.dispatchEvent(event);\
+                    }}\
+                }})();"
+            );
+            let _ = runtime.execute_script(&script, self.config.debug_js);
+        }
+    }
+
+    /// Move to history entry `index`. Entries of the current document are shown by scrolling;
+    /// others are fetched and loaded. Either way the entry's scroll offset is restored.
+    async fn traverse_history(&mut self, index: usize) -> Result<(), NetworkError> {
+        self.remember_scroll_position();
+        let entry = self.history[index].clone();
+        let current_document = self.dom.as_ref().map(|dom| dom.id);
+        self.history_index = Some(index);
+
+        if entry.document_id.is_some() && entry.document_id == current_document {
+            self.show_fragment(entry.request.url, ScrollBehavior::Instant);
+            self.restore_scroll_position();
+            return Ok(());
+        }
+
+        let (url, contents) = self.fetch_request_for_history(entry.request).await?;
+        self.navigate(&url, contents, true, false, None).await?;
+        self.restore_scroll_position();
+        Ok(())
     }
 
     /// Reload the active document without pushing a new history entry.
@@ -810,8 +1376,31 @@ impl Engine {
             return Err(NetworkError::Curl("Cannot reload: no current URL".to_string()));
         };
 
+        self.remember_scroll_position();
         let (url, contents) = self.fetch_request_for_history(request).await?;
-        self.navigate(&url, contents, true, false, None).await
+        self.navigate(&url, contents, true, false, None).await?;
+        self.restore_scroll_position();
+        Ok(())
+    }
+
+    /// Carry on a navigation a previous process of this tab had started: rebuild the history it
+    /// had, then load the navigation's page after the current entry, or in its place
+    pub async fn resume_navigation(&mut self, history: &[String], history_index: usize, navigation: &PendingNavigation) -> Result<(), NetworkError> {
+        let request = navigation
+            .to_request()
+            .ok_or_else(|| NetworkError::Engine(format!("Cannot resume navigation to {}", navigation.url)))?;
+        self.restore_history(history, history_index);
+        let (url, contents) = self.fetch_request_for_history(request.clone()).await?;
+        if navigation.replace {
+            self.navigate(&url, contents, true, false, None).await?;
+            self.replace_current_history_entry(request);
+        } else {
+            self.navigate(&url, contents, true, true, Some(request)).await?;
+        }
+        if let Some((x, y)) = navigation.scroll {
+            self.set_scroll_position(x as f32, y as f32);
+        }
+        Ok(())
     }
 
     async fn fetch_request_for_history(&self, request: Request) -> Result<(String, String), NetworkError> {
@@ -825,17 +1414,17 @@ impl Engine {
         let fallback_url = request.url.to_string();
         let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<(String, String)>();
 
-        net_provider.fetch_with_callback(
+        net_provider.fetch_navigation_with_callback(
             request,
             Box::new(move |result| {
                 let payload = match result {
                     Ok((url, bytes)) => {
-                        let contents = std::str::from_utf8(&bytes)
-                            .map(str::to_string)
-                            .unwrap_or_else(|_| include_str!("../../assets/404.html").to_string());
-                        (url, contents)
+                        (url, crate::networking::decode_text(&bytes).into_owned())
+                    }
+                    Err(e) => {
+                        let page = e.error_page(&fallback_url);
+                        (fallback_url, page)
                     }
-                    Err(_) => (fallback_url, include_str!("../../assets/404.html").to_string()),
                 };
                 let _ = tx.send(payload);
             }),
@@ -871,10 +1460,7 @@ impl Engine {
         }
 
         if let Some(index) = self.history_index {
-            self.history_index = Some(index - 1);
-            let request = self.history[index - 1].clone();
-            let (url, contents) = self.fetch_request_for_history(request).await?;
-            self.navigate(&url, contents, true, false, None).await
+            self.traverse_history(index - 1).await
         } else {
             Err(NetworkError::Curl("Invalid history state".to_string()))
         }
@@ -887,12 +1473,26 @@ impl Engine {
         }
 
         if let Some(index) = self.history_index {
-            self.history_index = Some(index + 1);
-            let request = self.history[index + 1].clone();
-            let (url, contents) = self.fetch_request_for_history(request).await?;
-            self.navigate(&url, contents, true, false, None).await
+            self.traverse_history(index + 1).await
         } else {
             Err(NetworkError::Curl("Invalid history state".to_string()))
         }
     }
 }
+
+/// Site settings for the origin of `url`, read fresh from disk since the parent may have changed them
+fn site_settings_for(url: &str) -> SiteSettings {
+    match url::Url::parse(url) {
+        Ok(url) => SiteSettingsStore::load_from_disk().get(&url.origin().ascii_serialization()),
+        Err(_) => SiteSettings::default(),
+    }
+}
+
+/// Ask the site settings store, read fresh from disk like `site_settings_for`, whether the origin of
+/// `url` may do something. Unparsable URLs get the global setting.
+fn site_store_allows(url: &str, allowed: fn(&SiteSettingsStore, &str) -> bool) -> bool {
+    let origin = url::Url::parse(url)
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or_default();
+    allowed(&SiteSettingsStore::load_from_disk(), &origin)
+}
@@ -5,7 +5,8 @@ use tokio::sync::mpsc::UnboundedSender;
 #[derive(Debug, Clone)]
 pub enum NavigationProviderMessage {
     NavigateTo(NavigationOptions),
-    NavigateToInNewTab(NavigationOptions),
+    /// Open a new tab; `opener` lets it reach this page through `window.opener`
+    NavigateToInNewTab { options: NavigationOptions, opener: bool },
     Reload,
     Navigate {
         navigation_id: u64,
@@ -40,8 +41,8 @@ impl StokesNavigationProvider {
         let _ = self.sender.send(NavigationProviderMessage::NavigateReplace(options));
     }
 
-    pub fn navigate_to_in_new_tab(&self, options: NavigationOptions) {
-        let _ = self.sender.send(NavigationProviderMessage::NavigateToInNewTab(options));
+    pub fn navigate_to_in_new_tab(&self, options: NavigationOptions, opener: bool) {
+        let _ = self.sender.send(NavigationProviderMessage::NavigateToInNewTab { options, opener });
     }
 
     pub fn reload(&self) {
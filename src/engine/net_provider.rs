@@ -1,15 +1,30 @@
 use std::marker::PhantomData;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock, Mutex};
 use std::task::Poll;
+use std::time::{Duration, Instant};
 use crate::engine::adblock;
+use crate::engine::mixed_content::{self, ContentKind, MixedContentAction, MixedContentStatus};
+use crate::networking::{self, blob_store, hsts, http_auth, refresh};
+use crate::networking::hsts::RedirectError;
+use crate::networking::request_policy::{self, RequestPolicy};
+use crate::networking::referrer::{self, ReferrerPolicy, RequestReferrer};
+use crate::networking::security::{self, TlsInfoCollector};
+use crate::languages;
+use crate::passwords;
+use crate::extensions;
+use crate::cookies;
+use crate::site_storage;
+use crate::shell_provider::{ShellProviderMessage, StokesShellProvider};
 use blitz_traits::net::{AbortSignal, Body, Entry, NetHandler, NetProvider, Request};
 use bytes::Bytes;
-use curl::easy::{Easy2, Handler, List, WriteError};
+use curl::easy::{Easy2, Handler, InfoType, WriteError};
 use curl::Error;
 use data_url::DataUrl;
 use log::warn;
 use tokio::runtime::Handle;
+use tokio::task::AbortHandle;
+use url::Url;
 
 #[derive(Debug)]
 pub enum ProviderError {
@@ -20,6 +35,8 @@ pub enum ProviderError {
     DataUrlBase64(data_url::forgiving_base64::InvalidBase64),
     ReqwestError(Error),
     HttpError(u32),
+    /// The server did not answer in time or the transfer stalled
+    Timeout,
     #[cfg(feature = "cache")]
     ReqwestMiddlewareError(reqwest_middleware::Error),
 }
@@ -44,27 +61,202 @@ impl From<data_url::forgiving_base64::InvalidBase64> for ProviderError {
 
 impl From<Error> for ProviderError {
     fn from(value: Error) -> Self {
+        if request_policy::is_timeout(&value) {
+            return Self::Timeout;
+        }
         Self::ReqwestError(value)
     }
 }
 
+impl From<RedirectError> for ProviderError {
+    fn from(value: RedirectError) -> Self {
+        match value {
+            RedirectError::Blocked(_) => Self::Blocked,
+            RedirectError::Curl(err) => err.into(),
+            too_many @ RedirectError::TooMany => Self::Io(std::io::Error::other(too_many.to_string())),
+        }
+    }
+}
+
+impl ProviderError {
+    /// The page shown for a document at `url` that failed with this error
+    pub fn error_page(&self, url: &str) -> String {
+        networking::error_page(url, matches!(self, Self::Timeout))
+    }
+}
+
 pub struct StokesNetProvider {
     rt: Handle,
     user_agent: String,
     debug_net: bool,
     block_ads: bool,
+    /// URL of the document this provider loads resources for, used for mixed content checks
+    document_url: Option<Url>,
+    upgrade_mixed_content: bool,
+    mixed_content: Arc<Mutex<MixedContentStatus>>,
+    /// The document's referrer policy, from its Referrer-Policy header or <meta name="referrer">
+    referrer_policy: Mutex<ReferrerPolicy>,
+    /// A navigation to go without a Referer, set when a rel=noreferrer link is followed
+    navigation_without_referrer: Mutex<Option<Url>>,
+    request_policy: RequestPolicy,
+    shell_provider: Arc<StokesShellProvider>,
+    /// Fetches started for this document, so they can be cancelled when it goes away
+    in_flight: Mutex<Vec<AbortHandle>>,
 }
 
 impl StokesNetProvider {
-    pub fn new(user_agent: String, debug_net: bool, block_ads: bool) -> Self {
+    pub fn new(
+        user_agent: String,
+        debug_net: bool,
+        block_ads: bool,
+        document_url: Option<Url>,
+        upgrade_mixed_content: bool,
+        request_policy: RequestPolicy,
+        shell_provider: Arc<StokesShellProvider>,
+    ) -> Self {
         Self {
             rt: Handle::current(),
             user_agent,
             debug_net,
             block_ads,
+            document_url,
+            upgrade_mixed_content,
+            mixed_content: Arc::new(Mutex::new(MixedContentStatus::default())),
+            referrer_policy: Mutex::new(ReferrerPolicy::default()),
+            navigation_without_referrer: Mutex::new(None),
+            request_policy,
+            shell_provider,
+            in_flight: Mutex::new(Vec::new()),
         }
     }
 
+    /// Run a fetch on the runtime, remembering it so `cancel_pending` can stop it
+    fn spawn_fetch(&self, fetch: impl Future<Output = ()> + Send + 'static) {
+        let handle = self.rt.spawn(fetch).abort_handle();
+        let mut in_flight = self.in_flight.lock().unwrap();
+        in_flight.retain(|handle| !handle.is_finished());
+        in_flight.push(handle);
+    }
+
+    /// Resolve `url`'s host and complete the TLS handshake ahead of a likely navigation. Curl keeps
+    /// the DNS answer and the TLS session in the handle, and the next request to the origin takes
+    /// the handle over, so it skips the lookup and resumes the session. Curl never reuses a
+    /// connect-only connection, so that request still opens its own.
+    pub fn preconnect(&self, mut url: Url) {
+        if let Some(secure_url) = hsts::upgrade(&url) {
+            url = secure_url;
+        }
+        if !matches!(url.scheme(), "http" | "https") || self.should_block_url(url.as_str(), None, "other") {
+            return;
+        }
+        let origin = url.origin().ascii_serialization();
+        if WARM_HANDLES.lock().is_ok_and(|handles| handles.iter().any(|handle| handle.origin == origin)) {
+            return;
+        }
+
+        let policy = self.request_policy.clone();
+        let debug_net = self.debug_net;
+        self.rt.spawn_blocking(move || {
+            let mut easy = Easy2::new(Collector::default());
+            let connected = (|| -> Result<(), Error> {
+                easy.url(&origin)?;
+                easy.connect_only(true)?;
+                policy.configure_easy2(&mut easy)?;
+                easy.perform()
+            })();
+            if let Err(e) = connected {
+                if debug_net {
+                    tracing::debug!("Preconnect to {origin} failed: {e}");
+                }
+                return;
+            }
+
+            let Ok(mut handles) = WARM_HANDLES.lock() else {
+                return;
+            };
+            handles.retain(|handle| handle.warmed_at.elapsed() < WARM_HANDLE_LIFETIME && handle.origin != origin);
+            if handles.len() >= MAX_WARM_HANDLES {
+                handles.remove(0);
+            }
+            handles.push(WarmHandle { origin, easy, warmed_at: Instant::now() });
+        });
+    }
+
+    /// Fetch the document at `url` ahead of a likely navigation to it. The response is kept for
+    /// `PREFETCH_LIFETIME`, and following a link to it then commits without waiting on the network.
+    pub fn prefetch(&self, mut url: Url) {
+        if let Some(secure_url) = hsts::upgrade(&url) {
+            url = secure_url;
+        }
+        url.set_fragment(None);
+        if !matches!(url.scheme(), "http" | "https") || self.should_block_url(url.as_str(), None, "document") {
+            return;
+        }
+        {
+            let Ok(mut prefetched) = PREFETCHED.lock() else {
+                return;
+            };
+            prefetched.retain(|document| document.fetched_at.elapsed() < PREFETCH_LIFETIME);
+            if prefetched.iter().any(|document| document.url == url) {
+                return;
+            }
+            if prefetched.len() >= MAX_PREFETCHED {
+                prefetched.remove(0);
+            }
+            // Claimed while the fetch runs, so hovering the link again doesn't fetch it twice
+            prefetched.push(PrefetchedDocument { url: url.clone(), response: None, fetched_at: Instant::now() });
+        }
+
+        let request = Request::get(url.clone());
+        let user_agent = self.user_agent.clone();
+        let referrer = self.request_referrer(false, None);
+        let policy = self.request_policy.clone();
+        let debug_net = self.debug_net;
+        self.rt.spawn(async move {
+            let result = Self::fetch_inner(request, &user_agent, referrer, None, "document", &policy).await;
+            let Ok(mut prefetched) = PREFETCHED.lock() else {
+                return;
+            };
+            let Some(index) = prefetched.iter().position(|document| document.url == url && document.response.is_none()) else {
+                return;
+            };
+            match result {
+                Ok(response) => {
+                    prefetched[index].response = Some(response);
+                    prefetched[index].fetched_at = Instant::now();
+                }
+                Err(e) => {
+                    if debug_net {
+                        tracing::debug!("Prefetch of {url} failed: {e:?}");
+                    }
+                    prefetched.remove(index);
+                }
+            }
+        });
+    }
+
+    /// Fetches still running
+    pub fn pending_fetches(&self) -> usize {
+        self.in_flight.lock().unwrap().iter().filter(|handle| !handle.is_finished()).count()
+    }
+
+    /// Abort every fetch still running for this document. Their handlers are dropped without
+    /// being called, so nothing is delivered to a document that is being torn down. Returns how
+    /// many were cancelled.
+    pub fn cancel_pending(&self) -> usize {
+        let in_flight = std::mem::take(&mut *self.in_flight.lock().unwrap());
+        in_flight
+            .into_iter()
+            .filter(|handle| !handle.is_finished())
+            .inspect(AbortHandle::abort)
+            .count()
+    }
+
+    /// Timeouts and retries for this document's requests
+    pub fn request_policy(&self) -> &RequestPolicy {
+        &self.request_policy
+    }
+
     pub fn is_adblock_enabled(&self) -> bool {
         self.block_ads
     }
@@ -76,10 +268,85 @@ impl StokesNetProvider {
 
         adblock::should_block(request_url, source_url, request_type)
     }
+
+    /// Apply HSTS and the mixed content policy to a subresource of this document. Returns the URL
+    /// to fetch, or None when the request is blocked.
+    pub fn filter_mixed_content(&self, url: &Url, kind: ContentKind) -> Option<Url> {
+        let hsts_url = hsts::upgrade(url);
+        let url = hsts_url.as_ref().unwrap_or(url);
+        match self.mixed_content_check(kind) {
+            Some(check) => check.filter(url),
+            None => Some(url.clone()),
+        }
+    }
+
+    /// The mixed content check for `kind` requests of this document, to run again on every
+    /// redirect hop. `None` for documents without a URL.
+    pub fn mixed_content_check(&self, kind: ContentKind) -> Option<MixedContentCheck> {
+        Some(MixedContentCheck {
+            document_url: self.document_url.clone()?,
+            kind,
+            upgrade: self.upgrade_mixed_content,
+            debug_net: self.debug_net,
+            status: Arc::clone(&self.mixed_content),
+            shell_provider: Arc::clone(&self.shell_provider),
+        })
+    }
+
+    pub fn set_referrer_policy(&self, policy: ReferrerPolicy) {
+        *self.referrer_policy.lock().unwrap() = policy;
+    }
+
+    pub fn referrer_policy(&self) -> ReferrerPolicy {
+        *self.referrer_policy.lock().unwrap()
+    }
+
+    /// Send no Referer or Origin with the next navigation to `url`, for a rel=noreferrer link
+    pub fn omit_referrer_for_navigation(&self, url: &Url) {
+        *self.navigation_without_referrer.lock().unwrap() = Some(url.clone());
+    }
+
+    /// What the Referer and Origin headers of a request from this document, and of each of its
+    /// redirect hops, are made from. `from_script` marks fetch/XHR requests, which are CORS
+    /// requests when cross-origin; `policy` overrides the document's policy for one request.
+    pub fn request_referrer(&self, from_script: bool, policy: Option<ReferrerPolicy>) -> Option<RequestReferrer> {
+        Some(RequestReferrer {
+            document_url: self.document_url.clone()?,
+            policy: policy.unwrap_or_else(|| self.referrer_policy()),
+            from_script,
+        })
+    }
+
+    /// Insecure content blocked or displayed so far in this document
+    pub fn mixed_content_status(&self) -> MixedContentStatus {
+        *self.mixed_content.lock().unwrap()
+    }
+
+    /// Fetch an image. Images are passive content, so on secure pages insecure ones may be
+    /// upgraded or displayed instead of blocked.
+    pub fn fetch_image(&self, _doc_id: usize, request: Request, handler: Box<dyn NetHandler>) {
+        self.fetch_subresource(request, handler, ContentKind::Passive);
+    }
+
+    fn fetch_subresource(&self, mut request: Request, handler: Box<dyn NetHandler>, kind: ContentKind) {
+        match self.filter_mixed_content(&request.url, kind) {
+            Some(url) => {
+                request.url = url;
+                self.fetch_resource(request, handler, kind);
+            }
+            None => handler.bytes(request.url.to_string(), Bytes::new()),
+        }
+    }
 }
 
 impl NetProvider for StokesNetProvider {
-    fn fetch(&self, doc_id: usize, mut request: Request, handler: Box<dyn NetHandler>) {
+    fn fetch(&self, _doc_id: usize, request: Request, handler: Box<dyn NetHandler>) {
+        self.fetch_subresource(request, handler, ContentKind::Active);
+    }
+}
+
+impl StokesNetProvider {
+    fn fetch_resource(&self, mut request: Request, handler: Box<dyn NetHandler>, kind: ContentKind) {
         //println!("STOKES NET PROVIDER: fetching url {}", request.url.to_string());
         if request.url.scheme() == "stokes" {
             match dioxus_asset_resolver::native::serve_asset(request.url.path()) {
@@ -94,7 +361,7 @@ impl NetProvider for StokesNetProvider {
             let request_url = request.url.to_string();
             if self.should_block_url(&request_url, None, "other") {
                 if self.debug_net {
-                    println!("[adblock] Blocked resource: {request_url}");
+                    tracing::debug!("Blocked resource: {request_url}");
                 }
                 // Notify handler with empty bytes so pending resource state can settle.
                 handler.bytes(request_url, Bytes::new());
@@ -103,26 +370,29 @@ impl NetProvider for StokesNetProvider {
 
             let user_agent = self.user_agent.clone();
             let debug_net = self.debug_net;
-            self.rt.spawn(async move {
+            let referrer = self.request_referrer(false, None);
+            let mixed_content = self.mixed_content_check(kind);
+            let policy = self.request_policy.clone();
+            self.spawn_fetch(async move {
                 let url = request.url.to_string();
 
                 let signal = request.signal.take();
                 let result = if let Some(signal) = signal {
-                    AbortFetch::new(signal, Box::pin(async move { Self::fetch_inner(request, &user_agent).await })).await
+                    AbortFetch::new(signal, Box::pin(async move { Self::fetch_inner(request, &user_agent, referrer, mixed_content, "other", &policy).await })).await
                 } else {
-                    Self::fetch_inner(request, &user_agent).await
+                    Self::fetch_inner(request, &user_agent, referrer, mixed_content, "other", &policy).await
                 };
 
                 match result {
                     Ok((response_url, bytes)) => {
                         handler.bytes(response_url, bytes);
                         if debug_net {
-                            println!("Success {url}");
+                            tracing::debug!("Success {url}");
                         }
                     }
                     Err(e) => {
                         if debug_net {
-                            eprintln!("Error fetching {url}: {e:?}");
+                            tracing::warn!("Error fetching {url}: {e:?}");
                         }
                     }
                 }
@@ -131,13 +401,163 @@ impl NetProvider for StokesNetProvider {
     }
 }
 
-struct Collector(Vec<u8>);
+/// Handles warmed up by `preconnect` and not yet taken by a request, oldest first
+static WARM_HANDLES: LazyLock<Mutex<Vec<WarmHandle>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+/// Warm handles kept at most, as the user only ever heads to one of them
+const MAX_WARM_HANDLES: usize = 4;
+
+/// How long a warm handle is kept for, well within the DNS TTLs of most sites
+const WARM_HANDLE_LIFETIME: Duration = Duration::from_secs(30);
+
+struct WarmHandle {
+    origin: String,
+    easy: Easy2<Collector>,
+    warmed_at: Instant,
+}
+
+/// Take the handle a preconnect warmed up for `url`'s origin, if there is one
+fn take_warm_handle(url: &Url) -> Option<Easy2<Collector>> {
+    let origin = url.origin().ascii_serialization();
+    let mut handles = WARM_HANDLES.lock().ok()?;
+    handles.retain(|handle| handle.warmed_at.elapsed() < WARM_HANDLE_LIFETIME);
+    let index = handles.iter().position(|handle| handle.origin == origin)?;
+    let mut easy = handles.remove(index).easy;
+    easy.reset();
+    Some(easy)
+}
+
+/// Documents fetched by `prefetch`, oldest first. A response is used by one navigation at most.
+static PREFETCHED: LazyLock<Mutex<Vec<PrefetchedDocument>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+/// Prefetched documents kept at most
+const MAX_PREFETCHED: usize = 8;
+
+/// How long a prefetched response may stand in for fetching the document again
+const PREFETCH_LIFETIME: Duration = Duration::from_secs(5 * 60);
+
+struct PrefetchedDocument {
+    url: Url,
+    /// The final URL and body, or `None` while the fetch is still running
+    response: Option<(String, Bytes)>,
+    fetched_at: Instant,
+}
+
+/// Take the prefetched response for a GET of `request`'s URL, if one arrived and is still fresh
+fn take_prefetched(request: &Request) -> Option<(String, Bytes)> {
+    if request.method.as_str() != "GET" || !matches!(request.body, Body::Empty) {
+        return None;
+    }
+    let mut url = hsts::upgrade(&request.url).unwrap_or_else(|| request.url.clone());
+    let fragment = url.fragment().map(str::to_string);
+    url.set_fragment(None);
+
+    let mut prefetched = PREFETCHED.lock().ok()?;
+    prefetched.retain(|document| document.fetched_at.elapsed() < PREFETCH_LIFETIME);
+    let index = prefetched.iter().position(|document| document.url == url && document.response.is_some())?;
+    let (mut final_url, body) = prefetched.remove(index).response?;
+    // The document is the same for every fragment, but the page scrolls to the one followed
+    if let (Some(fragment), Ok(mut parsed)) = (fragment, Url::parse(&final_url)) {
+        parsed.set_fragment(Some(&fragment));
+        final_url = parsed.into();
+    }
+    Some((final_url, body))
+}
+
+/// The mixed content check of a subresource request. It runs on the request's URL and again on
+/// each redirect hop, as an https:// URL may redirect to http://.
+#[derive(Clone)]
+pub struct MixedContentCheck {
+    document_url: Url,
+    kind: ContentKind,
+    upgrade: bool,
+    debug_net: bool,
+    status: Arc<Mutex<MixedContentStatus>>,
+    shell_provider: Arc<StokesShellProvider>,
+}
+
+impl MixedContentCheck {
+    /// The URL to fetch for `url`, or `None` when it is blocked
+    pub fn filter(&self, url: &Url) -> Option<Url> {
+        let document_url = &self.document_url;
+        match mixed_content::check(document_url, url, self.kind, self.upgrade) {
+            MixedContentAction::Allow => Some(url.clone()),
+            MixedContentAction::Upgrade(upgraded) => {
+                if self.debug_net {
+                    tracing::debug!("Upgraded {url} to {upgraded}");
+                }
+                Some(upgraded)
+            }
+            MixedContentAction::AllowInsecure => {
+                warn!("Mixed Content: the page at '{document_url}' was loaded over HTTPS, but displayed insecure content '{url}'");
+                self.record(|status| status.insecure_displayed = true);
+                Some(url.clone())
+            }
+            MixedContentAction::Block => {
+                warn!("Mixed Content: the page at '{document_url}' was loaded over HTTPS, but requested an insecure resource '{url}'. This request has been blocked");
+                self.record(|status| status.blocked += 1);
+                None
+            }
+        }
+    }
+
+    fn record(&self, update: impl FnOnce(&mut MixedContentStatus)) {
+        {
+            let mut status = self.status.lock().unwrap();
+            let before = *status;
+            update(&mut status);
+            if *status == before {
+                return;
+            }
+        }
+        let _ = self.shell_provider.sender.send(ShellProviderMessage::MixedContentChanged);
+    }
+}
+
+#[derive(Default)]
+struct Collector {
+    body: Vec<u8>,
+    tls: TlsInfoCollector,
+    referrer_policy: Option<ReferrerPolicy>,
+    strict_transport_security: Option<String>,
+    auth_challenge: Option<http_auth::AuthChallenge>,
+    refresh: Option<String>,
+}
 
 impl Handler for Collector {
     fn write(&mut self, data: &[u8]) -> Result<usize, WriteError> {
-        self.0.extend_from_slice(data);
+        self.body.extend_from_slice(data);
         Ok(data.len())
     }
+
+    fn header(&mut self, data: &[u8]) -> bool {
+        let line = String::from_utf8_lossy(data);
+        // Headers of informational responses are seen too; only keep those of the final response
+        if line.starts_with("HTTP/") {
+            self.referrer_policy = None;
+            self.strict_transport_security = None;
+            self.auth_challenge = None;
+            self.refresh = None;
+        } else if let Some((name, value)) = line.split_once(':') {
+            let name = name.trim();
+            if name.eq_ignore_ascii_case("referrer-policy") {
+                self.referrer_policy = ReferrerPolicy::from_header(value).or(self.referrer_policy);
+            } else if name.eq_ignore_ascii_case("strict-transport-security") {
+                self.strict_transport_security = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case("www-authenticate") && self.auth_challenge.is_none() {
+                self.auth_challenge = http_auth::AuthChallenge::parse(value);
+            } else if name.eq_ignore_ascii_case("refresh") {
+                self.refresh = Some(value.trim().to_string());
+            }
+        }
+        true
+    }
+
+    fn debug(&mut self, kind: InfoType, data: &[u8]) {
+        if matches!(kind, InfoType::Text) {
+            self.tls.feed(data);
+        }
+    }
 }
 
 impl StokesNetProvider {
@@ -183,7 +603,22 @@ impl StokesNetProvider {
         }
     }
 
-    async fn fetch_inner(request: Request, user_agent: &str) -> Result<(String, Bytes), ProviderError> {
+    /// Fetch `request` with curl. `referrer` makes the Referer and Origin headers of the request and
+    /// each of its redirect hops, and `mixed_content` checks every hop of a subresource request.
+    /// `request_type` is the adblock request type, and "document" responses have their referrer
+    /// policy and any authentication challenge recorded.
+    /// Idempotent requests that fail to connect or time out are retried as `policy` allows.
+    async fn fetch_inner(
+        mut request: Request,
+        user_agent: &str,
+        referrer: Option<RequestReferrer>,
+        mixed_content: Option<MixedContentCheck>,
+        request_type: &str,
+        policy: &RequestPolicy,
+    ) -> Result<(String, Bytes), ProviderError> {
+        if let Some(secure_url) = hsts::upgrade(&request.url) {
+            request.url = secure_url;
+        }
         Ok(match request.url.scheme() {
             "data" => {
                 let data_url = DataUrl::process(request.url.as_str())?;
@@ -194,72 +629,189 @@ impl StokesNetProvider {
                 let file_content = std::fs::read(request.url.path())?;
                 (request.url.to_string(), Bytes::from(file_content))
             },
+            "blob" => {
+                let entry = blob_store::resolve(request.url.as_str()).ok_or_else(|| {
+                    std::io::Error::new(std::io::ErrorKind::NotFound, "blob URL is not registered")
+                })?;
+                (request.url.to_string(), Bytes::from(entry.bytes.as_ref().clone()))
+            },
             _ => {
-                let mut easy = Easy2::new(Collector(Vec::new()));
+                let mut easy = take_warm_handle(&request.url).unwrap_or_else(|| Easy2::new(Collector::default()));
                 easy.url(request.url.as_str())?;
+                // The TLS handshake details only show up in curl's verbose output
+                easy.verbose(request.url.scheme() == "https")?;
 
-                let mut headers = List::new();
                 // Forward any request-level headers first.
-                for (name, value) in &request.headers {
-                    headers.append(&format!("{}: {}", name.as_str(), value.to_str().unwrap()))?;
-                }
+                let mut headers: Vec<String> = request.headers.iter()
+                    .map(|(name, value)| format!("{}: {}", name.as_str(), value.to_str().unwrap()))
+                    .collect();
                 // Add browser-like headers so servers such as Google do not
                 // reject the request with a 4xx response.
-                headers.append("Accept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")?;
-                headers.append("Accept-Language: en-US,en;q=0.5")?;
-                easy.http_headers(headers)?;
+                headers.push("Accept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8".to_string());
+                headers.push(languages::accept_language_header());
+                let mut redirects = hsts::Redirects::new(&request.url, request.method.as_str(), headers, referrer);
+                if let Some(check) = mixed_content {
+                    redirects = redirects.with_filter(move |url| check.filter(url));
+                }
+                easy.http_headers(redirects.header_list()?)?;
 
-                easy.follow_location(true)?;
+                // Redirects are followed below rather than by curl (see hsts::Redirects)
+                easy.follow_location(false)?;
                 easy.useragent(user_agent)?;
                 // Enable automatic decompression for gzip/deflate/br responses.
                 easy.accept_encoding("")?;
+                policy.configure_easy2(&mut easy)?;
+                http_auth::configure_easy2(&mut easy, request.url.as_str())?;
                 Self::apply_request_method(&mut easy, &request);
-                match easy.perform() {
-                    Ok(_) => {}
-                    Err(err) => {
-                        return Err(err.into());
+                loop {
+                    let mut attempt = 0;
+                    loop {
+                        *easy.get_mut() = Collector::default();
+                        match easy.perform() {
+                            Ok(_) => break,
+                            Err(err) if policy.should_retry(redirects.method(), attempt, &err) => {
+                                let delay = policy.retry_delay(attempt);
+                                warn!("Fetching {} failed ({err}), retrying in {delay:?}", request.url);
+                                tokio::time::sleep(delay).await;
+                                attempt += 1;
+                            }
+                            Err(err) => {
+                                return Err(err.into());
+                            }
+                        }
+                    }
+
+                    let hop_hsts = easy.get_ref().strict_transport_security.clone();
+                    if redirects.follow(&mut easy, hop_hsts.as_deref())?.is_none() {
+                        break;
                     }
                 }
 
+                // Use the final URL after any redirects as the canonical URL
+                let final_url = match easy.effective_url() {
+                    Ok(Some(u)) if !u.is_empty() => u.to_string(),
+                    _ => request.url.to_string(),
+                };
                 let status_code = easy.response_code().unwrap_or(0);
+                // The tab asks for credentials once the 401 page (or error page) is shown
+                if let (401, "document", Some(challenge)) = (status_code, request_type, easy.get_mut().auth_challenge.take()) {
+                    http_auth::record_challenge(&final_url, challenge);
+                }
                 // Only treat a non-2xx response as a hard failure when the
                 // body is empty.  If the server sent content (e.g. Google's
                 // sorry/CAPTCHA page on 429), render it instead of falling
                 // back to our own 404 page.
-                let body = easy.get_ref().0.clone();
+                let body = easy.get_ref().body.clone();
                 if !(200..300).contains(&status_code) && body.is_empty() {
                     return Err(ProviderError::HttpError(status_code));
                 }
-
-                // Use the final URL after any redirects as the canonical URL
-                let final_url = match easy.effective_url() {
-                    Ok(Some(u)) if !u.is_empty() => u.to_string(),
-                    _ => request.url.to_string(),
-                };
+                if let Some(info) = std::mem::take(&mut easy.get_mut().tls).finish() {
+                    security::record(&final_url, info);
+                }
+                if let Some(value) = &easy.get_ref().strict_transport_security {
+                    hsts::record(&final_url, value);
+                }
+                if let (Some(policy), "document") = (easy.get_ref().referrer_policy, request_type) {
+                    referrer::record_document_policy(&final_url, policy);
+                }
+                if let (Some(value), "document") = (&easy.get_ref().refresh, request_type) {
+                    refresh::record_document_refresh(&final_url, value);
+                }
 
                 (final_url, Bytes::from(body))
             }
         })
     }
 
+    /// Fetch a script or other active subresource of this document
     pub fn fetch_with_callback(
+        &self,
+        mut request: Request,
+        callback: Box<dyn FnOnce(Result<(String, Bytes), ProviderError>) + Send + Sync + 'static>,
+    ) {
+        let Some(url) = self.filter_mixed_content(&request.url, ContentKind::Active) else {
+            callback(Err(ProviderError::Blocked));
+            return;
+        };
+        request.url = url;
+        self.fetch_callback_inner(request, "script", true, Some(ContentKind::Active), callback);
+    }
+
+    /// Fetch a document to navigate to. Navigations leave the current page, so they are not
+    /// subject to its mixed content policy.
+    pub fn fetch_navigation_with_callback(
         &self,
         request: Request,
         callback: Box<dyn FnOnce(Result<(String, Bytes), ProviderError>) + Send + Sync + 'static>,
+    ) {
+        if passwords::is_management_url(&request.url) {
+            let page = passwords::management_page(&request.url);
+            callback(Ok((request.url.to_string(), page.into())));
+            return;
+        }
+        if extensions::is_management_url(&request.url) {
+            let from_management_page = self.document_url.as_ref().is_some_and(extensions::is_management_url);
+            let page = extensions::management_page(&request.url, from_management_page);
+            callback(Ok((request.url.to_string(), page.into())));
+            return;
+        }
+        if cookies::is_management_url(&request.url) {
+            let from_management_page = self.document_url.as_ref().is_some_and(cookies::is_management_url);
+            let page = cookies::management_page(&request.url, from_management_page);
+            callback(Ok((request.url.to_string(), page.into())));
+            return;
+        }
+        if site_storage::is_management_url(&request.url) {
+            let from_management_page = self.document_url.as_ref().is_some_and(site_storage::is_management_url);
+            let page = site_storage::management_page(&request.url, from_management_page);
+            callback(Ok((request.url.to_string(), page.into())));
+            return;
+        }
+        if extensions::extension_id_of(&request.url).is_some() {
+            match extensions::extension_page(&request.url) {
+                Some(page) => callback(Ok((request.url.to_string(), page.into()))),
+                None => callback(Err(ProviderError::HttpError(404))),
+            }
+            return;
+        }
+        let send_referrer = self
+            .navigation_without_referrer
+            .lock()
+            .unwrap()
+            .take_if(|url| *url == request.url)
+            .is_none();
+        if let Some(response) = take_prefetched(&request) {
+            callback(Ok(response));
+            return;
+        }
+        self.fetch_callback_inner(request, "document", send_referrer, None, callback);
+    }
+
+    fn fetch_callback_inner(
+        &self,
+        request: Request,
+        request_type: &str,
+        send_referrer: bool,
+        mixed_content: Option<ContentKind>,
+        callback: Box<dyn FnOnce(Result<(String, Bytes), ProviderError>) + Send + Sync + 'static>,
     ) {
         let request_url = request.url.to_string();
-        if self.should_block_url(&request_url, None, "script") {
+        if self.should_block_url(&request_url, None, request_type) {
             if self.debug_net {
-                println!("[adblock] Blocked callback fetch: {request_url}");
+                tracing::debug!("Blocked callback fetch: {request_url}");
             }
             callback(Err(ProviderError::Blocked));
             return;
         }
 
         let user_agent = self.user_agent.clone();
+        let referrer = if send_referrer { self.request_referrer(false, None) } else { None };
+        let mixed_content = mixed_content.and_then(|kind| self.mixed_content_check(kind));
+        let request_type = request_type.to_string();
+        let policy = self.request_policy.clone();
 
-        self.rt.spawn(async move {
-            let result = Self::fetch_inner(request, &user_agent).await;
+        self.spawn_fetch(async move {
+            let result = Self::fetch_inner(request, &user_agent, referrer, mixed_content, &request_type, &policy).await;
 
             callback(result);
         });
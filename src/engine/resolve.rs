@@ -10,6 +10,13 @@ impl Engine {
         self.tick_js_tasks();
     }
 
+    /// Run scripts of a hidden tab without producing a frame
+    pub fn tick_background(&mut self) {
+        if self.dom.is_some() {
+            self.tick_js_tasks();
+        }
+    }
+
     fn tick_js_tasks(&mut self) {
         self.handle_messages();
 
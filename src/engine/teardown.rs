@@ -0,0 +1,109 @@
+// Releasing what a tab holds when it navigates away or closes, and the report that checks it
+// all went away
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use crate::dom;
+use crate::engine::Engine;
+
+/// What a tab process holds, for the resource report debug command and the check that follows
+/// a closing tab's teardown
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceReport {
+    /// Documents alive in the process, subdocuments included
+    pub documents: usize,
+    /// Nodes of the current document
+    pub nodes: usize,
+    /// Decoded images held by the current document
+    pub images: usize,
+    pub image_bytes: usize,
+    /// Fetches the current document still has running
+    pub pending_fetches: usize,
+    /// Whether the JavaScript runtime, and with it the JS heap, exists
+    pub js_runtime: bool,
+    /// Fonts, typefaces and image shaders cached by the tab's painter
+    pub paint_cache_entries: usize,
+}
+
+impl ResourceReport {
+    /// Whether nothing is held, as it should be once a closing tab is torn down
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl fmt::Display for ResourceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} documents, {} nodes, {} images ({:.1} MB), {} pending fetches, JS runtime {}, {} paint cache entries",
+            self.documents,
+            self.nodes,
+            self.images,
+            self.image_bytes as f64 / (1024.0 * 1024.0),
+            self.pending_fetches,
+            if self.js_runtime { "alive" } else { "gone" },
+            self.paint_cache_entries,
+        )
+    }
+}
+
+impl Engine {
+    /// Drop the current document deterministically: cancel its fetches so no response arrives
+    /// for it, free its decoded images, then the document itself. Navigation does this before
+    /// parsing the next document so two heavy pages are never held at once.
+    pub(crate) fn teardown_document(&mut self) {
+        // The HTTP client shares the document's net provider and sender
+        self.new_http_client = None;
+        let Some(mut dom) = self.dom.take() else {
+            return;
+        };
+        let cancelled = dom.net_provider.cancel_pending();
+        dom.image_cache.clear();
+        dom.evicted_images.clear();
+        dom.pending_images.clear();
+        debug!("Tore down document {} ({cancelled} fetches cancelled)", dom.id);
+    }
+
+    /// Release everything the engine holds before the tab process exits: the JavaScript runtime
+    /// and its heap first, since it points into the document, then the document, the session
+    /// history and this thread's layout caches
+    pub(crate) fn shutdown(&mut self) {
+        self.js_runtime = None;
+        self.teardown_document();
+        self.history.clear();
+        self.history_index = None;
+        dom::release_layout_contexts();
+    }
+
+    /// What the engine holds right now. The painter's cache belongs to the tab process, which
+    /// fills in `paint_cache_entries`.
+    pub(crate) fn resource_report(&self) -> ResourceReport {
+        let current = self.dom.as_ref();
+        ResourceReport {
+            documents: dom::live_documents(),
+            nodes: current.map_or(0, |dom| dom.nodes.len()),
+            images: current.map_or(0, |dom| dom.image_cache.len()),
+            image_bytes: current.map_or(0, |dom| dom.image_cache.used_bytes()),
+            pending_fetches: current.map_or(0, |dom| dom.net_provider.pending_fetches()),
+            js_runtime: self.js_runtime.is_some(),
+            paint_cache_entries: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_a_report_of_nothing_is_empty() {
+        assert!(ResourceReport::default().is_empty());
+        let report = ResourceReport { documents: 1, image_bytes: 3 * 1024 * 1024, ..Default::default() };
+        assert!(!report.is_empty());
+        assert_eq!(
+            report.to_string(),
+            "1 documents, 0 nodes, 0 images (3.0 MB), 0 pending fetches, JS runtime gone, 0 paint cache entries"
+        );
+    }
+}
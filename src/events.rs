@@ -68,6 +68,34 @@ pub enum UiEvent {
     Ime(BlitzImeEvent),
 }
 impl UiEvent {
+    /// Fold `next` into this event when both are moves of the same pointer with the same buttons
+    /// held, or wheel turns in the same unit. Returns `next` when they cannot be combined.
+    pub fn coalesce(&mut self, next: UiEvent) -> Option<UiEvent> {
+        match (self, next) {
+            (UiEvent::PointerMove(current), UiEvent::PointerMove(event))
+                if current.id == event.id && current.buttons == event.buttons =>
+            {
+                let (movement_x, movement_y) = (current.details.movement_x, current.details.movement_y);
+                *current = event;
+                current.details.movement_x += movement_x;
+                current.details.movement_y += movement_y;
+                None
+            }
+            (UiEvent::Wheel(current), UiEvent::Wheel(event)) => match (&mut current.delta, &event.delta) {
+                (BlitzWheelDelta::Lines(x, y), BlitzWheelDelta::Lines(dx, dy))
+                | (BlitzWheelDelta::Pixels(x, y), BlitzWheelDelta::Pixels(dx, dy)) => {
+                    *x += dx;
+                    *y += dy;
+                    current.coords = event.coords;
+                    current.timestamp = event.timestamp;
+                    None
+                }
+                _ => Some(UiEvent::Wheel(event)),
+            },
+            (_, next) => Some(next),
+        }
+    }
+
     pub fn discriminant(&self) -> u8 {
         // SAFETY: Because `Self` is marked `repr(u8)`, its layout is a `repr(C)` `union`
         // between `repr(C)` structs, each of which has the `u8` discriminant as its first
@@ -143,6 +171,11 @@ pub enum DomEventKind {
     Blur,
     FocusIn,
     FocusOut,
+
+    TouchStart,
+    TouchMove,
+    TouchEnd,
+    TouchCancel,
 }
 impl DomEventKind {
     pub fn discriminant(self) -> u8 {
@@ -187,6 +220,11 @@ impl FromStr for DomEventKind {
             "blur" => Ok(Self::Blur),
             "focusin" => Ok(Self::FocusIn),
             "focusout" => Ok(Self::FocusOut),
+
+            "touchstart" => Ok(Self::TouchStart),
+            "touchmove" => Ok(Self::TouchMove),
+            "touchend" => Ok(Self::TouchEnd),
+            "touchcancel" => Ok(Self::TouchCancel),
             _ => Err(()),
         }
     }
@@ -229,6 +267,11 @@ pub enum DomEventData {
     Blur(BlitzFocusEvent),
     FocusIn(BlitzFocusEvent),
     FocusOut(BlitzFocusEvent),
+
+    TouchStart(BlitzTouchEvent),
+    TouchMove(BlitzTouchEvent),
+    TouchEnd(BlitzTouchEvent),
+    TouchCancel(BlitzTouchEvent),
 }
 impl DomEventData {
     pub fn discriminant(&self) -> u8 {
@@ -277,6 +320,11 @@ impl DomEventData {
             Self::Blur { .. } => "blur",
             Self::FocusIn { .. } => "focusin",
             Self::FocusOut { .. } => "focusout",
+
+            Self::TouchStart { .. } => "touchstart",
+            Self::TouchMove { .. } => "touchmove",
+            Self::TouchEnd { .. } => "touchend",
+            Self::TouchCancel { .. } => "touchcancel",
         }
     }
 
@@ -316,6 +364,11 @@ impl DomEventData {
             Self::Blur { .. } => DomEventKind::Blur,
             Self::FocusIn { .. } => DomEventKind::FocusIn,
             Self::FocusOut { .. } => DomEventKind::FocusOut,
+
+            Self::TouchStart { .. } => DomEventKind::TouchStart,
+            Self::TouchMove { .. } => DomEventKind::TouchMove,
+            Self::TouchEnd { .. } => DomEventKind::TouchEnd,
+            Self::TouchCancel { .. } => DomEventKind::TouchCancel,
         }
     }
 
@@ -355,6 +408,11 @@ impl DomEventData {
             Self::Blur { .. } => false,
             Self::FocusIn { .. } => false,
             Self::FocusOut { .. } => false,
+
+            Self::TouchStart { .. } => true,
+            Self::TouchMove { .. } => true,
+            Self::TouchEnd { .. } => true,
+            Self::TouchCancel { .. } => false,
         }
     }
 
@@ -394,6 +452,11 @@ impl DomEventData {
             Self::Blur { .. } => false,
             Self::FocusIn { .. } => true,
             Self::FocusOut { .. } => true,
+
+            Self::TouchStart { .. } => true,
+            Self::TouchMove { .. } => true,
+            Self::TouchEnd { .. } => true,
+            Self::TouchCancel { .. } => true,
         }
     }
 }
@@ -436,6 +499,10 @@ pub struct PointerDetails {
     pub twist: u16,
     pub altitude: f64,
     pub azimuth: f64,
+    /// How far the pointer moved since the last event, in CSS pixels. Still reported while the
+    /// pointer is locked and its position stays put.
+    pub movement_x: f32,
+    pub movement_y: f32,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -489,6 +556,24 @@ impl BlitzPointerEvent {
     }
 }
 
+/// A finger on the page, as a touch event's `Touch`
+#[derive(Clone, Debug)]
+pub struct BlitzTouch {
+    pub identifier: u64,
+    /// The node the finger first touched. The finger's events go there wherever it moves.
+    pub target: usize,
+    pub coords: PointerCoords,
+    pub force: f64,
+}
+
+/// A touch event: every finger still on the page, and the ones this event is about
+#[derive(Clone, Debug)]
+pub struct BlitzTouchEvent {
+    pub touches: Vec<BlitzTouch>,
+    pub changed_touches: Vec<BlitzTouch>,
+    pub mods: Modifiers,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum BlitzWheelDelta {
     Lines(f64, f64),
@@ -501,6 +586,10 @@ pub struct BlitzWheelEvent {
     pub coords: PointerCoords,
     pub buttons: MouseEventButtons,
     pub mods: Modifiers,
+    /// When the (last) wheel turn happened, in milliseconds since the Unix epoch. Wheel turns
+    /// are summed before they are sent, so this lets the tab spread a scroll over the time the
+    /// turns took.
+    pub timestamp: f64,
 }
 
 impl BlitzWheelEvent {
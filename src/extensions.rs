@@ -0,0 +1,572 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use url::Url;
+use self::manifest::{ContentScript, WebExtension, MANIFEST_FILE};
+
+pub mod manifest;
+pub mod storage;
+
+const STORAGE_VERSION: u32 = 1;
+const EXTENSIONS_DIR: &str = "extensions";
+const EXTENSIONS_STATE_FILE: &str = "extensions.json";
+const MANAGEMENT_PAGE_HTML: &str = include_str!("../assets/extensions.html");
+/// The page listing user scripts and user styles
+pub const MANAGEMENT_URL: &str = "stokes://extensions";
+/// Scheme of the pages and files WebExtensions bring along
+pub const EXTENSION_SCHEME: &str = "stokes-extension";
+const BACKGROUND_PAGE_PATH: &str = "/_generated_background_page.html";
+
+/// What an extension file adds to the pages it matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtensionKind {
+    /// A `.user.js` file with a `==UserScript==` header
+    Script,
+    /// A `.user.css` file with a `==UserStyle==` header
+    Style,
+}
+
+/// When a user script runs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RunAt {
+    /// Before the page's own scripts
+    DocumentStart,
+    /// After the page's own scripts
+    #[default]
+    DocumentEnd,
+}
+
+impl RunAt {
+    fn label(self) -> &'static str {
+        match self {
+            Self::DocumentStart => "document-start",
+            Self::DocumentEnd => "document-end",
+        }
+    }
+}
+
+/// A user script or user style loaded from the profile's extensions directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserExtension {
+    /// Name of the file it was loaded from, which identifies it
+    pub file_name: String,
+    pub kind: ExtensionKind,
+    pub name: String,
+    pub description: String,
+    /// `@match` patterns and `@include` globs; an extension without any matches every web page
+    matches: Vec<String>,
+    includes: Vec<String>,
+    /// `@exclude-match` patterns and `@exclude` globs
+    exclude_matches: Vec<String>,
+    excludes: Vec<String>,
+    pub run_at: RunAt,
+    /// The file's contents
+    pub source: String,
+    /// Id of the WebExtension this is a content script of, which gets the `browser` API
+    pub web_extension: Option<String>,
+}
+
+impl UserExtension {
+    /// Parse an extension file, taking the kind from its name. Files that aren't `.user.js` or
+    /// `.user.css` give `None`.
+    pub fn parse(file_name: &str, source: String) -> Option<Self> {
+        let (kind, block) = if file_name.ends_with(".user.js") {
+            (ExtensionKind::Script, "UserScript")
+        } else if file_name.ends_with(".user.css") {
+            (ExtensionKind::Style, "UserStyle")
+        } else {
+            return None;
+        };
+
+        let mut extension = Self {
+            file_name: file_name.to_string(),
+            kind,
+            name: file_name.trim_end_matches(".user.js").trim_end_matches(".user.css").to_string(),
+            description: String::new(),
+            matches: Vec::new(),
+            includes: Vec::new(),
+            exclude_matches: Vec::new(),
+            excludes: Vec::new(),
+            run_at: RunAt::default(),
+            source: String::new(),
+            web_extension: None,
+        };
+
+        // Metadata lines sit between "==UserScript==" and "==/UserScript==", each commented out
+        // with "//" in scripts or inside a "/* */" block in styles
+        let mut in_header = false;
+        for line in source.lines() {
+            let line = line.trim().trim_start_matches("/*").trim_start_matches("//").trim_start_matches('*').trim();
+            let delimiter = line.trim_end_matches("*/").trim();
+            if delimiter == format!("=={block}==") {
+                in_header = true;
+                continue;
+            }
+            if delimiter == format!("==/{block}==") {
+                break;
+            }
+            if !in_header {
+                continue;
+            }
+            let Some((key, value)) = line.strip_prefix('@').and_then(|line| line.split_once(char::is_whitespace)) else {
+                continue;
+            };
+            let value = value.trim().to_string();
+            match key {
+                "name" => extension.name = value,
+                "description" => extension.description = value,
+                "match" => extension.matches.push(value),
+                "include" => extension.includes.push(value),
+                "exclude-match" => extension.exclude_matches.push(value),
+                "exclude" => extension.excludes.push(value),
+                "run-at" => extension.run_at = if value == "document-start" { RunAt::DocumentStart } else { RunAt::DocumentEnd },
+                _ => {}
+            }
+        }
+        extension.source = source;
+        Some(extension)
+    }
+
+    /// Whether the extension applies to a document at `url`. Only web pages and local files get
+    /// extensions, never the browser's own pages.
+    pub fn applies_to(&self, url: &Url) -> bool {
+        if !matches!(url.scheme(), "http" | "https" | "file") {
+            return false;
+        }
+        let mut target = url.clone();
+        target.set_fragment(None);
+        let target = target.as_str();
+
+        let included = if self.matches.is_empty() && self.includes.is_empty() {
+            true
+        } else {
+            self.matches.iter().any(|pattern| match_pattern_matches(pattern, url))
+                || self.includes.iter().any(|pattern| glob_matches(pattern, target))
+        };
+        let excluded = self.exclude_matches.iter().any(|pattern| match_pattern_matches(pattern, url))
+            || self.excludes.iter().any(|pattern| glob_matches(pattern, target));
+        included && !excluded
+    }
+
+    /// A WebExtension's `content_scripts` entry as a user style for its CSS and a user script for
+    /// its JavaScript. Both are identified by the extension's id, so they are turned on and off
+    /// together with it.
+    fn from_content_script(extension: &WebExtension, content_script: &ContentScript) -> Vec<Self> {
+        let base = Self {
+            file_name: extension.id.clone(),
+            kind: ExtensionKind::Script,
+            name: extension.manifest.name.clone(),
+            description: extension.manifest.description.clone(),
+            matches: content_script.matches.clone(),
+            includes: Vec::new(),
+            exclude_matches: content_script.exclude_matches.clone(),
+            excludes: content_script.exclude_globs.clone(),
+            run_at: match content_script.run_at.as_deref() {
+                Some("document_start") => RunAt::DocumentStart,
+                _ => RunAt::DocumentEnd,
+            },
+            source: String::new(),
+            web_extension: Some(extension.id.clone()),
+        };
+        // Content scripts need a match; without one they apply nowhere rather than everywhere
+        if base.matches.is_empty() {
+            return Vec::new();
+        }
+
+        let mut parts = Vec::new();
+        if !content_script.css.is_empty() {
+            parts.push(Self { kind: ExtensionKind::Style, source: extension.read_files(&content_script.css), ..base.clone() });
+        }
+        if !content_script.js.is_empty() {
+            parts.push(Self { source: extension.read_files(&content_script.js), ..base });
+        }
+        parts
+    }
+
+    /// The script to run, wrapped so its variables don't leak into the page's globals. Content
+    /// scripts of WebExtensions are run through the extension API instead, which passes them their
+    /// extension's `browser` (and `chrome`) object.
+    pub fn wrapped_script(&self) -> String {
+        format!("(function() {{\n{}\n}})();", self.source)
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` stands for any run of characters
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = text.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard at all
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Whether `url` matches a match pattern such as `*://*.example.com/*` or `<all_urls>`
+fn match_pattern_matches(pattern: &str, url: &Url) -> bool {
+    if pattern == "<all_urls>" {
+        return true;
+    }
+    let Some((scheme, rest)) = pattern.split_once("://") else {
+        return false;
+    };
+    let scheme_matches = match scheme {
+        "*" => matches!(url.scheme(), "http" | "https"),
+        scheme => scheme.eq_ignore_ascii_case(url.scheme()),
+    };
+    let Some(path_start) = rest.find('/') else {
+        return false;
+    };
+    let (host, path) = rest.split_at(path_start);
+
+    let url_host = url.host_str().unwrap_or_default();
+    let host_matches = match host {
+        "*" => true,
+        host => match host.strip_prefix("*.") {
+            Some(domain) => {
+                url_host.eq_ignore_ascii_case(domain)
+                    || url_host.to_ascii_lowercase().ends_with(&format!(".{}", domain.to_ascii_lowercase()))
+            }
+            None => url_host.eq_ignore_ascii_case(host),
+        },
+    };
+
+    let mut target = url.path().to_string();
+    if let Some(query) = url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    scheme_matches && host_matches && glob_matches(path, &target)
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PersistedExtensions {
+    #[serde(default = "default_storage_version")]
+    version: u32,
+    /// File names of the extensions the user turned off
+    #[serde(default)]
+    disabled: Vec<String>,
+}
+
+const fn default_storage_version() -> u32 {
+    STORAGE_VERSION
+}
+
+/// The user scripts, styles and unpacked WebExtensions in the profile's extensions directory, and
+/// which of them are turned off. Tab processes read it when a document is created, so changes
+/// apply to the next page load.
+#[derive(Debug, Clone)]
+pub struct ExtensionStore {
+    extensions: Vec<UserExtension>,
+    web_extensions: Vec<WebExtension>,
+    disabled: Vec<String>,
+}
+
+impl ExtensionStore {
+    pub fn load_from_disk() -> Self {
+        let mut extensions = Vec::new();
+        let mut web_extensions = Vec::new();
+        for entry in std::fs::read_dir(extensions_dir()).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.join(MANIFEST_FILE).is_file() {
+                match WebExtension::load(&path) {
+                    Ok(extension) => web_extensions.push(extension),
+                    Err(err) => tracing::warn!("Skipping extension {}: {}", path.display(), err),
+                }
+                continue;
+            }
+            let Ok(file_name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if let Some(extension) = std::fs::read_to_string(&path).ok().and_then(|source| UserExtension::parse(&file_name, source)) {
+                extensions.push(extension);
+            }
+        }
+        web_extensions.sort_by(|a, b| a.id.cmp(&b.id));
+        for extension in &web_extensions {
+            for content_script in &extension.manifest.content_scripts {
+                extensions.extend(UserExtension::from_content_script(extension, content_script));
+            }
+        }
+        extensions.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+
+        let disabled = std::fs::read_to_string(state_file_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str::<PersistedExtensions>(&contents).ok())
+            .map(|persisted| persisted.disabled)
+            .unwrap_or_default();
+        Self { extensions, web_extensions, disabled }
+    }
+
+    pub fn save_to_disk(&self) {
+        let payload = PersistedExtensions {
+            version: STORAGE_VERSION,
+            disabled: self.disabled.clone(),
+        };
+
+        let Ok(json) = serde_json::to_string_pretty(&payload) else {
+            return;
+        };
+
+        let path = state_file_path();
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::write(path, json);
+    }
+
+    pub fn is_enabled(&self, file_name: &str) -> bool {
+        !self.disabled.iter().any(|disabled| disabled == file_name)
+    }
+
+    pub fn set_enabled(&mut self, file_name: &str, enabled: bool) {
+        self.disabled.retain(|disabled| disabled != file_name);
+        if !enabled {
+            self.disabled.push(file_name.to_string());
+        }
+    }
+
+    /// The enabled extensions that apply to a document at `url`, in file name order
+    pub fn for_url(&self, url: &str) -> Vec<&UserExtension> {
+        let Ok(url) = Url::parse(url) else {
+            return Vec::new();
+        };
+        self.extensions
+            .iter()
+            .filter(|extension| self.is_enabled(&extension.file_name) && extension.applies_to(&url))
+            .collect()
+    }
+
+    /// The WebExtension with this id, if it is installed and turned on
+    pub fn web_extension(&self, id: &str) -> Option<&WebExtension> {
+        self.web_extensions.iter().find(|extension| extension.id == id && self.is_enabled(&extension.id))
+    }
+}
+
+/// Where the user puts `.user.js` and `.user.css` files
+fn extensions_dir() -> PathBuf {
+    crate::profile::data_dir().join(EXTENSIONS_DIR)
+}
+
+fn state_file_path() -> PathBuf {
+    crate::profile::data_dir().join(EXTENSIONS_STATE_FILE)
+}
+
+/// The id of the WebExtension a stokes-extension:// URL belongs to
+pub fn extension_id_of(url: &Url) -> Option<&str> {
+    (url.scheme() == EXTENSION_SCHEME).then(|| url.host_str()).flatten()
+}
+
+/// Where an extension's background scripts are loaded
+pub fn background_page_url(id: &str) -> String {
+    format!("{EXTENSION_SCHEME}://{id}{BACKGROUND_PAGE_PATH}")
+}
+
+/// A stokes-extension:// page: the generated background page, or a file of the extension.
+/// `None` when the extension isn't installed and on, or has no such file.
+pub fn extension_page(url: &Url) -> Option<String> {
+    let store = ExtensionStore::load_from_disk();
+    let extension = store.web_extension(extension_id_of(url)?)?;
+    if url.path() != BACKGROUND_PAGE_PATH {
+        return extension.read_file(url.path());
+    }
+
+    let scripts: String = extension
+        .manifest
+        .background
+        .iter()
+        .flat_map(|background| background.script_paths())
+        .filter_map(|path| extension.read_file(path))
+        .map(|source| format!("<script>\n{}\n</script>\n", source.replace("</script", "<\\/script")))
+        .collect();
+    Some(format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"UTF-8\">\n<title>{}</title>\n</head>\n<body>\n{scripts}</body>\n</html>\n",
+        html_escape::encode_text(&extension.manifest.name),
+    ))
+}
+
+/// Script run with the extension API before a WebExtension's own pages load, giving them its
+/// `browser` object. The page is the extension's own, so it may keep the object on its global.
+pub const EXTENSION_PAGE_PRELUDE: &str = "globalThis.browser = globalThis.chrome = browser;";
+
+pub fn is_management_url(url: &Url) -> bool {
+    url.scheme() == "stokes" && url.host_str() == Some("extensions")
+}
+
+/// The stokes://extensions page. Its links turn extensions on and off through the query string,
+/// which is only acted on when `from_management_page` (the link was followed from the page itself)
+/// so that other pages can't use them.
+pub fn management_page(url: &Url, from_management_page: bool) -> String {
+    let query = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+
+    let dir = extensions_dir();
+    let _ = std::fs::create_dir_all(&dir);
+    let mut store = ExtensionStore::load_from_disk();
+    if from_management_page {
+        let mut changed = false;
+        if let Some(file_name) = query("enable") {
+            store.set_enabled(&file_name, true);
+            changed = true;
+        }
+        if let Some(file_name) = query("disable") {
+            store.set_enabled(&file_name, false);
+            changed = true;
+        }
+        if changed {
+            store.save_to_disk();
+        }
+    }
+    render_management_page(&store, &dir.display().to_string())
+}
+
+fn management_link(params: &[(&str, &str)]) -> String {
+    let mut query = url::form_urlencoded::Serializer::new(String::new());
+    query.extend_pairs(params);
+    format!("{MANAGEMENT_URL}?{}", query.finish())
+}
+
+fn render_management_page(store: &ExtensionStore, dir: &str) -> String {
+    let user_extensions: Vec<&UserExtension> = store.extensions.iter().filter(|extension| extension.web_extension.is_none()).collect();
+    let rows: String = user_extensions.iter().map(|extension| {
+        let enabled = store.is_enabled(&extension.file_name);
+        let kind = match extension.kind {
+            ExtensionKind::Script => format!("Script, {}", extension.run_at.label()),
+            ExtensionKind::Style => "Style".to_string(),
+        };
+        let mut sites: Vec<&str> = extension.matches.iter().chain(&extension.includes).map(String::as_str).collect();
+        if sites.is_empty() {
+            sites.push("All sites");
+        }
+        let (toggle, label) = if enabled { ("disable", "Turn off") } else { ("enable", "Turn on") };
+        format!(
+            "<tr class=\"{}\"><td><strong>{}</strong><div class=\"description\">{}</div><div class=\"file\">{}</div></td><td>{}</td><td>{}</td><td><a href=\"{}\">{}</a></td></tr>\n",
+            if enabled { "enabled" } else { "disabled" },
+            html_escape::encode_text(&extension.name),
+            html_escape::encode_text(&extension.description),
+            html_escape::encode_text(&extension.file_name),
+            kind,
+            html_escape::encode_text(&sites.join(", ")),
+            html_escape::encode_double_quoted_attribute(&management_link(&[(toggle, &extension.file_name)])),
+            label,
+        )
+    }).collect();
+    let rows = if rows.is_empty() {
+        "<tr><td class=\"empty\" colspan=\"4\">No user scripts or styles installed yet</td></tr>".to_string()
+    } else {
+        rows
+    };
+
+    let web_extension_rows: String = store.web_extensions.iter().map(|extension| {
+        let enabled = store.is_enabled(&extension.id);
+        let manifest = &extension.manifest;
+        let mut parts = Vec::new();
+        match manifest.content_scripts.len() {
+            0 => {}
+            1 => parts.push("1 content script".to_string()),
+            count => parts.push(format!("{count} content scripts")),
+        }
+        if extension.has_background() {
+            parts.push("background script".to_string());
+        }
+        let (toggle, label) = if enabled { ("disable", "Turn off") } else { ("enable", "Turn on") };
+        format!(
+            "<tr class=\"{}\"><td><strong>{}</strong> {}<div class=\"description\">{}</div><div class=\"file\">{}</div></td><td>{}</td><td><a href=\"{}\">{}</a></td></tr>\n",
+            if enabled { "enabled" } else { "disabled" },
+            html_escape::encode_text(&manifest.name),
+            html_escape::encode_text(&manifest.version),
+            html_escape::encode_text(&manifest.description),
+            html_escape::encode_text(&extension.id),
+            html_escape::encode_text(&parts.join(", ")),
+            html_escape::encode_double_quoted_attribute(&management_link(&[(toggle, &extension.id)])),
+            label,
+        )
+    }).collect();
+    let web_extension_rows = if web_extension_rows.is_empty() {
+        "<tr><td class=\"empty\" colspan=\"3\">No WebExtensions installed yet</td></tr>".to_string()
+    } else {
+        web_extension_rows
+    };
+
+    let summary = match user_extensions.len() + store.web_extensions.len() {
+        1 => "1 extension.".to_string(),
+        count => format!("{count} extensions."),
+    };
+    MANAGEMENT_PAGE_HTML
+        .replace("{{SUMMARY}}", &summary)
+        .replace("{{DIRECTORY}}", &html_escape::encode_text(dir))
+        .replace("{{ROWS}}", &rows)
+        .replace("{{WEB_EXTENSION_ROWS}}", &web_extension_rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob_matches, match_pattern_matches, ExtensionKind, RunAt, UserExtension};
+    use url::Url;
+
+    const SCRIPT: &str = "// ==UserScript==\n\
+        // @name        Hide banners\n\
+        // @description Removes the cookie banner\n\
+        // @match       *://*.example.com/*\n\
+        // @exclude     *://example.com/admin*\n\
+        // @run-at      document-start\n\
+        // ==/UserScript==\n\
+        document.documentElement.classList.add('no-banner');\n";
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn parses_user_script_headers() {
+        let script = UserExtension::parse("banners.user.js", SCRIPT.to_string()).unwrap();
+        assert_eq!(script.kind, ExtensionKind::Script);
+        assert_eq!(script.name, "Hide banners");
+        assert_eq!(script.description, "Removes the cookie banner");
+        assert_eq!(script.run_at, RunAt::DocumentStart);
+        assert!(script.applies_to(&url("https://example.com/news")));
+        assert!(script.applies_to(&url("http://www.example.com/")));
+        assert!(!script.applies_to(&url("https://example.com/admin/users")));
+        assert!(!script.applies_to(&url("https://example.org/")));
+        assert!(!script.applies_to(&url("stokes://extensions")));
+
+        assert_eq!(UserExtension::parse("notes.txt", SCRIPT.to_string()), None);
+    }
+
+    #[test]
+    fn parses_user_style_headers() {
+        let source = "/* ==UserStyle==\n@name Wide pages\n@match https://docs.example.com/*\n==/UserStyle== */\nbody { max-width: none !important; }\n";
+        let style = UserExtension::parse("wide.user.css", source.to_string()).unwrap();
+        assert_eq!(style.kind, ExtensionKind::Style);
+        assert_eq!(style.name, "Wide pages");
+        assert!(style.applies_to(&url("https://docs.example.com/guide")));
+        assert!(!style.applies_to(&url("http://docs.example.com/guide")));
+
+        // Without any @match or @include it applies to every page
+        let everywhere = UserExtension::parse("plain.user.css", "a { color: red; }".to_string()).unwrap();
+        assert_eq!(everywhere.name, "plain");
+        assert!(everywhere.applies_to(&url("https://anything.test/")));
+    }
+
+    #[test]
+    fn matches_patterns_and_globs() {
+        assert!(glob_matches("https://*.test/*", "https://a.test/b"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("https://a.test/", "https://a.test/b"));
+        assert!(glob_matches("a*b*c", "abbbc"));
+        assert!(!glob_matches("a*bc", "abc_"));
+
+        assert!(match_pattern_matches("<all_urls>", &url("file:///home/user/page.html")));
+        assert!(match_pattern_matches("file:///*", &url("file:///home/user/page.html")));
+        assert!(match_pattern_matches("https://example.com/search?*", &url("https://example.com/search?q=1")));
+        assert!(!match_pattern_matches("*://example.com/*", &url("ftp://example.com/")));
+        assert!(!match_pattern_matches("*://*.example.com/*", &url("https://badexample.com/")));
+    }
+}
@@ -0,0 +1,159 @@
+// The subset of the WebExtension manifest.json format the browser understands: content scripts and
+// a background script. Other keys (browser_action, web_accessible_resources, ...) are ignored.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub manifest_version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub content_scripts: Vec<ContentScript>,
+    #[serde(default)]
+    pub background: Option<Background>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+/// An entry of `content_scripts`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContentScript {
+    #[serde(default)]
+    pub matches: Vec<String>,
+    #[serde(default)]
+    pub exclude_matches: Vec<String>,
+    #[serde(default)]
+    pub exclude_globs: Vec<String>,
+    #[serde(default)]
+    pub js: Vec<String>,
+    #[serde(default)]
+    pub css: Vec<String>,
+    /// "document_start", "document_end" or "document_idle" (the default)
+    #[serde(default)]
+    pub run_at: Option<String>,
+}
+
+/// `background` in either manifest version: Manifest V2 `scripts` or a Manifest V3
+/// `service_worker`, which is run like a single background script
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Background {
+    #[serde(default)]
+    pub scripts: Vec<String>,
+    #[serde(default)]
+    pub service_worker: Option<String>,
+}
+
+impl Background {
+    pub fn script_paths(&self) -> Vec<&str> {
+        self.scripts.iter().map(String::as_str).chain(self.service_worker.as_deref()).collect()
+    }
+}
+
+/// A WebExtension unpacked into a directory of the profile's extensions directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebExtension {
+    /// The directory's name, which identifies the extension
+    pub id: String,
+    pub dir: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl WebExtension {
+    /// Read `dir/manifest.json`
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let id = dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .filter(|name| is_valid_id(name))
+            .ok_or_else(|| format!("{} is not a usable extension directory name", dir.display()))?
+            .to_string();
+        let contents = std::fs::read_to_string(dir.join(MANIFEST_FILE)).map_err(|err| err.to_string())?;
+        let manifest = serde_json::from_str::<Manifest>(&contents).map_err(|err| format!("{MANIFEST_FILE}: {err}"))?;
+        Ok(Self { id, dir: dir.to_path_buf(), manifest })
+    }
+
+    pub fn has_background(&self) -> bool {
+        self.manifest.background.as_ref().is_some_and(|background| !background.script_paths().is_empty())
+    }
+
+    /// The contents of a file of the extension, by its path in the manifest. Paths that would leave
+    /// the extension's directory give `None`.
+    pub fn read_file(&self, path: &str) -> Option<String> {
+        let relative = Path::new(path.trim_start_matches('/'));
+        if !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+            return None;
+        }
+        std::fs::read_to_string(self.dir.join(relative)).ok()
+    }
+
+    /// Read and join the files at `paths`, skipping (and logging) those that are missing
+    pub fn read_files(&self, paths: &[String]) -> String {
+        paths
+            .iter()
+            .filter_map(|path| {
+                let contents = self.read_file(path);
+                if contents.is_none() {
+                    tracing::warn!("Extension {} is missing {}", self.id, path);
+                }
+                contents
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Extension ids are the host of their stokes-extension:// URLs and name their storage file, so
+/// they are kept to characters that are safe in both
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && !id.starts_with('.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_valid_id, Manifest};
+
+    #[test]
+    fn parses_manifest_v2_and_v3() {
+        let v2: Manifest = serde_json::from_str(r#"{
+            "manifest_version": 2,
+            "name": "Highlighter",
+            "version": "1.2",
+            "content_scripts": [{ "matches": ["*://*.example.com/*"], "js": ["a.js", "b.js"], "css": ["c.css"], "run_at": "document_start" }],
+            "background": { "scripts": ["background.js"] },
+            "permissions": ["storage"],
+            "browser_action": { "default_title": "ignored" }
+        }"#).unwrap();
+        assert_eq!(v2.name, "Highlighter");
+        assert_eq!(v2.content_scripts[0].js, ["a.js", "b.js"]);
+        assert_eq!(v2.content_scripts[0].run_at.as_deref(), Some("document_start"));
+        assert_eq!(v2.background.unwrap().script_paths(), ["background.js"]);
+
+        let v3: Manifest = serde_json::from_str(r#"{
+            "manifest_version": 3,
+            "name": "Worker",
+            "background": { "service_worker": "worker.js" }
+        }"#).unwrap();
+        assert_eq!(v3.background.unwrap().script_paths(), ["worker.js"]);
+        assert!(v3.content_scripts.is_empty());
+    }
+
+    #[test]
+    fn ids_stay_inside_the_extensions_directory() {
+        assert!(is_valid_id("highlighter.example"));
+        assert!(!is_valid_id("highlighter@example.com"));
+        assert!(!is_valid_id(".."));
+        assert!(!is_valid_id("a/b"));
+        assert!(!is_valid_id(""));
+    }
+}
@@ -0,0 +1,52 @@
+// Backing store for `browser.storage.local`: one JSON object per extension in the profile
+// directory. Every tab process and the extension's background host read and write the same file,
+// so it is re-read for each operation.
+
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+
+const STORAGE_DIR: &str = "extension_storage";
+
+fn storage_file_path(extension_id: &str) -> PathBuf {
+    crate::profile::data_dir().join(STORAGE_DIR).join(format!("{extension_id}.json"))
+}
+
+/// Everything the extension has stored
+pub fn read(extension_id: &str) -> Map<String, Value> {
+    std::fs::read_to_string(storage_file_path(extension_id))
+        .ok()
+        .and_then(|contents| serde_json::from_str::<Map<String, Value>>(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn write(extension_id: &str, items: &Map<String, Value>) {
+    let path = storage_file_path(extension_id);
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let Ok(json) = serde_json::to_string(items) else {
+        return;
+    };
+    if let Err(err) = std::fs::write(&path, json) {
+        tracing::warn!("Failed to save storage for extension {}: {}", extension_id, err);
+    }
+}
+
+/// Store each of `items`, replacing earlier values under the same keys
+pub fn set(extension_id: &str, items: Map<String, Value>) {
+    let mut stored = read(extension_id);
+    stored.extend(items);
+    write(extension_id, &stored);
+}
+
+pub fn remove(extension_id: &str, keys: &[String]) {
+    let mut stored = read(extension_id);
+    for key in keys {
+        stored.remove(key);
+    }
+    write(extension_id, &stored);
+}
+
+pub fn clear(extension_id: &str) {
+    write(extension_id, &Map::new());
+}
@@ -0,0 +1,265 @@
+// Frames timed to the display's refresh. The browser presents with vsync and notes when each
+// present completes, which gives it the refresh interval and the phase of the next vsync. After a
+// present it tells the active tab both, and the tab starts painting a frame it owes so that it
+// finishes just before the composite, rather than on a timer of its own that beats against the
+// display's refresh and shows as uneven steps while scrolling.
+//
+// Holding a frame back for its vsync adds latency. The user's max-latency setting caps how many
+// refreshes a frame may wait; a tab that can't make a vsync within it paints at once.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const SETTINGS_FILE: &str = "frame_timing.json";
+
+/// Refresh interval assumed until the monitor or presents tell otherwise
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_micros(16_667);
+
+/// Time the browser needs after a tab's frame arrives to composite it before the vsync
+const COMPOSITE_MARGIN: Duration = Duration::from_millis(3);
+
+/// Weight of the newest sample in the smoothed refresh interval and frame cost
+const SMOOTHING: f64 = 0.1;
+
+/// The choices for `max_latency_frames`
+const MAX_LATENCY_CHOICES: [u32; 3] = [1, 2, 3];
+
+/// When the display refreshes, as the browser sends it to the active tab after a present
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FrameTiming {
+    /// The display's refresh interval, in microseconds
+    pub interval_us: u64,
+    /// Microseconds from when this was sent until the next vsync
+    pub next_vsync_in_us: u64,
+    /// Most refreshes a frame may be held back for to meet a vsync
+    pub max_latency_frames: u32,
+}
+
+/// The browser's record of its presents
+pub struct PresentClock {
+    interval: Duration,
+    last_present: Option<Instant>,
+}
+
+impl Default for PresentClock {
+    fn default() -> Self {
+        Self { interval: DEFAULT_REFRESH_INTERVAL, last_present: None }
+    }
+}
+
+impl PresentClock {
+    /// Go by the refresh interval the window's monitor reports, when it reports one
+    pub fn set_refresh_interval(&mut self, interval: Option<Duration>) {
+        if let Some(interval) = interval.filter(|interval| !interval.is_zero()) {
+            self.interval = interval;
+        }
+    }
+
+    /// Note that a present completed at `at`. Presents one refresh apart refine the interval;
+    /// longer gaps only mean nothing was drawn in between.
+    pub fn presented(&mut self, at: Instant) {
+        if let Some(last) = self.last_present {
+            let gap = at.saturating_duration_since(last);
+            if gap > self.interval / 2 && gap < self.interval * 3 / 2 {
+                self.interval = self.interval.mul_f64(1.0 - SMOOTHING) + gap.mul_f64(SMOOTHING);
+            }
+        }
+        self.last_present = Some(at);
+    }
+
+    /// The first vsync after `now`
+    pub fn next_vsync(&self, now: Instant) -> Instant {
+        let Some(last) = self.last_present else {
+            return now + self.interval;
+        };
+        let elapsed = now.saturating_duration_since(last);
+        let refreshes = elapsed.as_nanos() / self.interval.as_nanos().max(1) + 1;
+        last + self.interval * refreshes as u32
+    }
+
+    pub fn timing(&self, now: Instant, max_latency_frames: u32) -> FrameTiming {
+        FrameTiming {
+            interval_us: self.interval.as_micros() as u64,
+            next_vsync_in_us: self.next_vsync(now).saturating_duration_since(now).as_micros() as u64,
+            max_latency_frames,
+        }
+    }
+}
+
+/// A tab's plan for when to paint
+#[derive(Default)]
+pub struct FrameScheduler {
+    /// The last timing from the browser and when it arrived
+    timing: Option<(FrameTiming, Instant)>,
+    /// Smoothed time painting a frame takes
+    frame_cost: Duration,
+    /// When the last frame started, to pace frames while there is no timing
+    last_start: Option<Instant>,
+}
+
+impl FrameScheduler {
+    pub fn set_timing(&mut self, timing: FrameTiming) {
+        self.timing = Some((timing, Instant::now()));
+    }
+
+    /// Forget the display's timing, when the tab is hidden and its frames aren't composited
+    pub fn clear_timing(&mut self) {
+        self.timing = None;
+    }
+
+    /// Note a frame that started at `started` and took `took` to paint
+    pub fn record_frame(&mut self, started: Instant, took: Duration) {
+        self.last_start = Some(started);
+        self.frame_cost = if self.frame_cost.is_zero() {
+            took
+        } else {
+            self.frame_cost.mul_f64(1.0 - SMOOTHING) + took.mul_f64(SMOOTHING)
+        };
+    }
+
+    /// When to start painting a frame asked for at `now`: late enough to finish just before the
+    /// browser composites for a vsync, but no later than the latency cap allows. Without timing
+    /// from the browser, a refresh after the last frame.
+    pub fn frame_start(&self, now: Instant) -> Instant {
+        let Some((timing, received)) = self.timing else {
+            return self.last_start.map_or(now, |last| (last + DEFAULT_REFRESH_INTERVAL).max(now));
+        };
+        let interval = Duration::from_micros(timing.interval_us);
+        if interval.is_zero() {
+            return now;
+        }
+        let lead = self.frame_cost + COMPOSITE_MARGIN;
+
+        // The first vsync the frame can still make, counting on from the one the browser named
+        let ready_by = now + lead;
+        let mut vsync = received + Duration::from_micros(timing.next_vsync_in_us);
+        if vsync < ready_by {
+            let behind = ready_by - vsync;
+            let refreshes = behind.as_nanos().div_ceil(interval.as_nanos());
+            vsync += interval * refreshes as u32;
+        }
+
+        if vsync.saturating_duration_since(now) > interval * timing.max_latency_frames.max(1) {
+            return now;
+        }
+        vsync.checked_sub(lead).map_or(now, |start| start.max(now))
+    }
+}
+
+/// The user's frame latency choice, kept in frame_timing.json in the profile
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FrameTimingSettings {
+    pub max_latency_frames: u32,
+}
+
+impl Default for FrameTimingSettings {
+    fn default() -> Self {
+        Self { max_latency_frames: 2 }
+    }
+}
+
+impl FrameTimingSettings {
+    fn path() -> PathBuf {
+        crate::profile::data_dir().join(SETTINGS_FILE)
+    }
+
+    pub fn load_from_disk() -> Self {
+        std::fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Self>(&contents).ok())
+            .map(|settings| Self { max_latency_frames: settings.max_latency_frames.clamp(1, 3) })
+            .unwrap_or_default()
+    }
+
+    pub fn save_to_disk(&self) {
+        let Ok(json) = serde_json::to_string_pretty(self) else {
+            return;
+        };
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::write(path, json);
+    }
+
+    /// The next choice of max latency, wrapping around
+    pub fn cycled(self) -> Self {
+        let index = MAX_LATENCY_CHOICES.iter().position(|&frames| frames == self.max_latency_frames).unwrap_or(0);
+        Self { max_latency_frames: MAX_LATENCY_CHOICES[(index + 1) % MAX_LATENCY_CHOICES.len()] }
+    }
+
+    pub fn label(&self) -> String {
+        match self.max_latency_frames {
+            1 => "1 frame".to_string(),
+            frames => format!("{frames} frames"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(16);
+
+    fn scheduler(next_vsync_in: Duration, max_latency_frames: u32, frame_cost: Duration) -> (FrameScheduler, Instant) {
+        let mut scheduler = FrameScheduler::default();
+        scheduler.set_timing(FrameTiming {
+            interval_us: INTERVAL.as_micros() as u64,
+            next_vsync_in_us: next_vsync_in.as_micros() as u64,
+            max_latency_frames,
+        });
+        let received = scheduler.timing.unwrap().1;
+        scheduler.record_frame(received - INTERVAL, frame_cost);
+        (scheduler, received)
+    }
+
+    #[test]
+    fn frames_finish_just_before_the_composite() {
+        let (scheduler, now) = scheduler(Duration::from_millis(12), 2, Duration::from_millis(4));
+        // Vsync at 12ms, less 4ms of painting and 3ms for the browser
+        assert_eq!(scheduler.frame_start(now), now + Duration::from_millis(5));
+
+        // Too late for that vsync, so aim for the one after
+        let late = now + Duration::from_millis(8);
+        assert_eq!(scheduler.frame_start(late), now + Duration::from_millis(21));
+    }
+
+    #[test]
+    fn the_latency_cap_paints_at_once() {
+        // A frame costing more than a refresh can't wait for a vsync within one frame of latency
+        let (scheduler, now) = scheduler(Duration::from_millis(2), 1, Duration::from_millis(20));
+        assert_eq!(scheduler.frame_start(now), now);
+    }
+
+    #[test]
+    fn frames_without_timing_are_a_refresh_apart() {
+        let mut scheduler = FrameScheduler::default();
+        let now = Instant::now();
+        assert_eq!(scheduler.frame_start(now), now);
+        scheduler.record_frame(now, Duration::from_millis(2));
+        assert_eq!(scheduler.frame_start(now + Duration::from_millis(1)), now + DEFAULT_REFRESH_INTERVAL);
+    }
+
+    #[test]
+    fn presents_refine_the_refresh_interval() {
+        let mut clock = PresentClock::default();
+        clock.set_refresh_interval(Some(INTERVAL));
+        let start = Instant::now();
+        clock.presented(start);
+        // A long gap is idle time, not a refresh
+        clock.presented(start + Duration::from_secs(1));
+        assert_eq!(clock.interval, INTERVAL);
+        assert_eq!(clock.next_vsync(start + Duration::from_millis(1005)), start + Duration::from_millis(1016));
+    }
+
+    #[test]
+    fn max_latency_cycles_through_its_choices() {
+        let settings = FrameTimingSettings::default();
+        assert_eq!(settings.cycled().max_latency_frames, 3);
+        assert_eq!(settings.cycled().cycled().max_latency_frames, 1);
+        assert_eq!(settings.cycled().cycled().label(), "1 frame");
+    }
+}
@@ -0,0 +1,103 @@
+// Entry points for the cargo-fuzz targets in fuzz/. Each feeds untrusted text to one parser the way
+// a page would, without a window, GL context or JavaScript runtime. Documents are built with
+// providers whose receiving ends are gone, so whatever they ask for goes nowhere.
+use crate::css::parse::parse_color;
+use crate::dom::scrollbar::translate_webkit_scrollbar_rules;
+use crate::dom::config::DomConfig;
+use crate::dom::HtmlParser;
+use crate::engine::js_provider::StokesJsProvider;
+use crate::engine::nav_provider::StokesNavigationProvider;
+use crate::engine::net_provider::StokesNetProvider;
+use crate::js::bindings::cookie::{parse_cookie_date_to_millis, Cookie};
+use crate::networking::request_policy::RequestPolicy;
+use crate::shell_provider::StokesShellProvider;
+use blitz_traits::shell::Viewport;
+use std::sync::Arc;
+
+const FUZZ_URL: &str = "https://fuzz.example/dir/page.html";
+
+/// Parse `html` as a document and style and lay it out, then parse it again as the body's
+/// innerHTML and as detached HTML and XML documents the way DOMParser does, serializing each
+pub fn parse_html(html: &str) {
+    with_runtime(|| {
+        let mut dom = HtmlParser::new().parse(html, detached_config(None));
+        dom.resolve(0.0);
+        if let Some(body) = dom.body_id() {
+            dom.set_inner_html(body, html);
+            dom.resolve(0.0);
+            let _ = dom.serialize_children(body);
+        }
+        for is_xml in [false, true] {
+            let document = dom.parse_detached_document(html, is_xml);
+            let _ = dom.serialize_node(document);
+        }
+        dom.resolve(0.0);
+    });
+}
+
+/// Parse `css` as an author stylesheet, style a small document with it, and run the hand-written
+/// parsers that see CSS values
+pub fn parse_stylesheet(css: &str) {
+    let _ = translate_webkit_scrollbar_rules(css);
+    let _ = parse_color(css);
+    with_runtime(|| {
+        let mut dom = HtmlParser::new().parse(
+            "<div id=a class=b><p>text <span>more</span></p><img alt=x></div>",
+            detached_config(Some(Vec::new())),
+        );
+        dom.add_author_stylesheet(css);
+        dom.resolve(0.0);
+    });
+}
+
+/// Parse `header` as both a `document.cookie` assignment and a Set-Cookie header
+pub fn parse_cookie(header: &str) {
+    let _ = Cookie::parse(header, "fuzz.example", "/dir/page.html");
+    for secure in [false, true] {
+        let _ = Cookie::parse_set_cookie(header, "fuzz.example", "/dir/page.html", secure);
+    }
+}
+
+/// Parse `date` the way a cookie's Expires attribute is
+pub fn parse_cookie_date(date: &str) {
+    let _ = parse_cookie_date_to_millis(date);
+}
+
+/// Net providers schedule fetches on the current Tokio runtime. This one is never driven, so
+/// nothing is fetched, and dropping it drops whatever was scheduled.
+fn with_runtime(parse: impl FnOnce()) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .expect("Failed to build a Tokio runtime");
+    let _guard = runtime.enter();
+    parse();
+}
+
+/// A document config whose providers lead nowhere. `stylesheets` of `None` loads the user agent
+/// stylesheet.
+fn detached_config(stylesheets: Option<Vec<String>>) -> DomConfig {
+    let (shell_tx, _) = tokio::sync::mpsc::unbounded_channel();
+    let (nav_tx, _) = tokio::sync::mpsc::unbounded_channel();
+    let (js_tx, _) = std::sync::mpsc::channel();
+    let shell_provider = Arc::new(StokesShellProvider::new(shell_tx));
+    let net_provider = StokesNetProvider::new(
+        String::from("stokes-fuzz"),
+        false,
+        false,
+        url::Url::parse(FUZZ_URL).ok(),
+        false,
+        RequestPolicy::default(),
+        shell_provider.clone(),
+    );
+    DomConfig {
+        viewport: Some(Viewport { window_size: (800, 600), hidpi_scale: 1.0, ..Default::default() }),
+        base_url: Some(FUZZ_URL.to_string()),
+        stylesheets,
+        net_provider: Some(Arc::new(net_provider)),
+        shell_provider: Some(shell_provider),
+        nav_provider: Some(Arc::new(StokesNavigationProvider::new(nav_tx))),
+        js_provider: Some(Arc::new(StokesJsProvider::new(js_tx))),
+        block_images: true,
+        ..Default::default()
+    }
+}
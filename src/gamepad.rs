@@ -0,0 +1,128 @@
+// Game controllers for the Gamepad API. The browser process reads them with gilrs and hands the
+// active tab their state whenever it changes; pages read it with navigator.getGamepads().
+use gilrs::{Axis, Button, Gilrs};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// The buttons of the "standard" gamepad mapping, in the order pages expect them
+const STANDARD_BUTTONS: [Button; 17] = [
+    Button::South,
+    Button::East,
+    Button::West,
+    Button::North,
+    Button::LeftTrigger,
+    Button::RightTrigger,
+    Button::LeftTrigger2,
+    Button::RightTrigger2,
+    Button::Select,
+    Button::Start,
+    Button::LeftThumb,
+    Button::RightThumb,
+    Button::DPadUp,
+    Button::DPadDown,
+    Button::DPadLeft,
+    Button::DPadRight,
+    Button::Mode,
+];
+
+/// How often controllers are read while one is connected, about once a frame
+const CONNECTED_POLL_INTERVAL: Duration = Duration::from_millis(16);
+
+/// How often gilrs is asked whether a controller was plugged in while none is
+const HOTPLUG_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The sticks of the standard mapping, left then right, each horizontal then vertical
+const STANDARD_AXES: [Axis; 4] = [Axis::LeftStickX, Axis::LeftStickY, Axis::RightStickX, Axis::RightStickY];
+
+/// One connected controller as a page sees it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GamepadState {
+    /// Slot in `navigator.getGamepads()`
+    pub index: usize,
+    pub id: String,
+    /// -1.0 to 1.0, with down and right positive
+    pub axes: Vec<f64>,
+    pub buttons: Vec<GamepadButtonState>,
+    /// Milliseconds since the browser started reading controllers, as of the last change
+    pub timestamp: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GamepadButtonState {
+    pub pressed: bool,
+    /// 0.0 to 1.0; only the analog triggers stop in between
+    pub value: f64,
+}
+
+/// The controllers connected to the machine
+pub struct Gamepads {
+    /// None when the platform's controller support could not be started
+    gilrs: Option<Gilrs>,
+    started: Instant,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            // Without udev, for one, gilrs still comes up but sees no controllers
+            Err(gilrs::Error::NotImplemented(gilrs)) => {
+                debug!("Gamepads are not supported on this platform");
+                Some(gilrs)
+            }
+            Err(e) => {
+                warn!("Failed to start reading gamepads: {}", e);
+                None
+            }
+        };
+        Self { gilrs, started: Instant::now() }
+    }
+
+    /// How long the browser may go without calling `poll`, or `None` when there is nothing to read
+    pub fn poll_interval(&self) -> Option<Duration> {
+        let gilrs = self.gilrs.as_ref()?;
+        let connected = gilrs.gamepads().any(|(_, gamepad)| gamepad.is_connected());
+        Some(if connected { CONNECTED_POLL_INTERVAL } else { HOTPLUG_POLL_INTERVAL })
+    }
+
+    /// Read what the controllers did since the last call. Returns the state of every connected
+    /// controller when any of them changed, was plugged in or was unplugged.
+    pub fn poll(&mut self) -> Option<Vec<GamepadState>> {
+        let gilrs = self.gilrs.as_mut()?;
+        let mut changed = false;
+        while gilrs.next_event().is_some() {
+            changed = true;
+        }
+        if !changed {
+            return None;
+        }
+
+        let timestamp = self.started.elapsed().as_secs_f64() * 1000.0;
+        let states = gilrs
+            .gamepads()
+            .filter(|(_, gamepad)| gamepad.is_connected())
+            .map(|(id, gamepad)| GamepadState {
+                index: usize::from(id),
+                id: gamepad.name().to_string(),
+                axes: STANDARD_AXES
+                    .iter()
+                    .map(|&axis| {
+                        let value = gamepad.value(axis) as f64;
+                        // gilrs puts up positive, the web down
+                        if matches!(axis, Axis::LeftStickY | Axis::RightStickY) { -value } else { value }
+                    })
+                    .collect(),
+                buttons: STANDARD_BUTTONS
+                    .iter()
+                    .map(|&button| match gamepad.button_data(button) {
+                        Some(data) => GamepadButtonState { pressed: data.is_pressed(), value: data.value() as f64 },
+                        None => GamepadButtonState { pressed: false, value: 0.0 },
+                    })
+                    .collect(),
+                timestamp,
+            })
+            .collect();
+        Some(states)
+    }
+}
@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const LOCATION_SETTINGS_FILE: &str = "location.json";
+
+/// A position handed to `navigator.geolocation` callbacks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeoPosition {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Accuracy radius in meters
+    pub accuracy: f64,
+    pub altitude: Option<f64>,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: f64,
+}
+
+/// Mirrors the `GeolocationPositionError` codes. Timeouts are enforced by the page's options in JS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GeolocationError {
+    PermissionDenied = 1,
+    PositionUnavailable = 2,
+}
+
+/// Source of the user's location
+pub trait LocationProvider: Send + Sync {
+    /// Blocks until a position is known, so callers run it off the UI thread
+    fn current_position(&self) -> Result<GeoPosition, GeolocationError>;
+}
+
+/// Always reports the coordinates entered in the settings panel
+pub struct ManualLocationProvider {
+    latitude: f64,
+    longitude: f64,
+}
+
+impl LocationProvider for ManualLocationProvider {
+    fn current_position(&self) -> Result<GeoPosition, GeolocationError> {
+        Ok(GeoPosition {
+            latitude: self.latitude,
+            longitude: self.longitude,
+            accuracy: 10.0,
+            altitude: None,
+            timestamp: now_millis(),
+        })
+    }
+}
+
+/// Used when there is neither an override nor an OS location service
+#[cfg(not(target_os = "linux"))]
+pub struct UnavailableLocationProvider;
+
+#[cfg(not(target_os = "linux"))]
+impl LocationProvider for UnavailableLocationProvider {
+    fn current_position(&self) -> Result<GeoPosition, GeolocationError> {
+        Err(GeolocationError::PositionUnavailable)
+    }
+}
+
+/// Asks GeoClue over the system bus, which is what desktop Linux uses for location
+#[cfg(target_os = "linux")]
+pub struct GeoClueLocationProvider;
+
+#[cfg(target_os = "linux")]
+impl GeoClueLocationProvider {
+    const SERVICE: &'static str = "org.freedesktop.GeoClue2";
+    const CLIENT_INTERFACE: &'static str = "org.freedesktop.GeoClue2.Client";
+    const LOCATION_INTERFACE: &'static str = "org.freedesktop.GeoClue2.Location";
+    /// GClueAccuracyLevel::Exact
+    const ACCURACY_EXACT: u32 = 8;
+
+    fn query(&self) -> Result<GeoPosition, dbus::Error> {
+        use dbus::blocking::Connection;
+        use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+        use std::time::{Duration, Instant};
+
+        let timeout = Duration::from_secs(2);
+        let conn = Connection::new_system()?;
+        let manager = conn.with_proxy(Self::SERVICE, "/org/freedesktop/GeoClue2/Manager", timeout);
+        let (client_path,): (dbus::Path<'static>,) =
+            manager.method_call("org.freedesktop.GeoClue2.Manager", "GetClient", ())?;
+
+        let client = conn.with_proxy(Self::SERVICE, client_path, timeout);
+        client.set(Self::CLIENT_INTERFACE, "DesktopId", "stokes-browser".to_string())?;
+        client.set(Self::CLIENT_INTERFACE, "RequestedAccuracyLevel", Self::ACCURACY_EXACT)?;
+        client.method_call::<(), _, _, _>(Self::CLIENT_INTERFACE, "Start", ())?;
+
+        // The Location property stays "/" until the first fix arrives
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let location_path = loop {
+            let path: dbus::Path<'static> = client.get(Self::CLIENT_INTERFACE, "Location")?;
+            if &*path != "/" {
+                break Some(path);
+            }
+            if Instant::now() >= deadline {
+                break None;
+            }
+            conn.process(Duration::from_millis(250))?;
+        };
+        let _ = client.method_call::<(), _, _, _>(Self::CLIENT_INTERFACE, "Stop", ());
+
+        let Some(location_path) = location_path else {
+            return Err(dbus::Error::new_failed("timed out waiting for a location fix"));
+        };
+        let location = conn.with_proxy(Self::SERVICE, location_path, timeout);
+        let latitude: f64 = location.get(Self::LOCATION_INTERFACE, "Latitude")?;
+        let longitude: f64 = location.get(Self::LOCATION_INTERFACE, "Longitude")?;
+        let accuracy: f64 = location.get(Self::LOCATION_INTERFACE, "Accuracy")?;
+        let altitude: f64 = location.get(Self::LOCATION_INTERFACE, "Altitude")?;
+
+        Ok(GeoPosition {
+            latitude,
+            longitude,
+            accuracy,
+            // GeoClue reports -DBL_MAX when the altitude is unknown
+            altitude: (altitude > f64::MIN).then_some(altitude),
+            timestamp: now_millis(),
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl LocationProvider for GeoClueLocationProvider {
+    fn current_position(&self) -> Result<GeoPosition, GeolocationError> {
+        self.query().map_err(|err| {
+            tracing::warn!("GeoClue location lookup failed: {}", err);
+            GeolocationError::PositionUnavailable
+        })
+    }
+}
+
+/// Location settings persisted next to the bookmarks
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LocationSettings {
+    /// Manual (latitude, longitude) that replaces the OS location service
+    #[serde(default)]
+    pub manual_override: Option<(f64, f64)>,
+}
+
+impl LocationSettings {
+    pub fn load_from_disk() -> Self {
+        std::fs::read_to_string(location_settings_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save_to_disk(&self) {
+        let path = location_settings_path();
+        let Ok(json) = serde_json::to_string_pretty(self) else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::write(path, json);
+    }
+
+    /// Pick the provider these settings ask for
+    pub fn provider(&self) -> Box<dyn LocationProvider> {
+        if let Some((latitude, longitude)) = self.manual_override {
+            return Box::new(ManualLocationProvider { latitude, longitude });
+        }
+        #[cfg(target_os = "linux")]
+        {
+            Box::new(GeoClueLocationProvider)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Box::new(UnavailableLocationProvider)
+        }
+    }
+}
+
+/// Parse a "latitude, longitude" pair as typed into the settings prompt
+pub fn parse_coordinates(input: &str) -> Option<(f64, f64)> {
+    let (latitude, longitude) = input.split_once(',')?;
+    let latitude: f64 = latitude.trim().parse().ok()?;
+    let longitude: f64 = longitude.trim().parse().ok()?;
+    ((-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude)).then_some((latitude, longitude))
+}
+
+fn now_millis() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs_f64() * 1000.0)
+        .unwrap_or_default()
+}
+
+fn location_settings_path() -> PathBuf {
+    crate::profile::data_dir().join(LOCATION_SETTINGS_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_coordinates, LocationSettings};
+
+    #[test]
+    fn parses_coordinate_pairs() {
+        assert_eq!(parse_coordinates("51.5, -0.12"), Some((51.5, -0.12)));
+        assert_eq!(parse_coordinates(" -33.86 ,151.2 "), Some((-33.86, 151.2)));
+        assert_eq!(parse_coordinates("91, 0"), None);
+        assert_eq!(parse_coordinates("north"), None);
+    }
+
+    #[test]
+    fn manual_override_wins() {
+        let settings = LocationSettings { manual_override: Some((1.0, 2.0)) };
+        let position = settings.provider().current_position().expect("manual provider always answers");
+        assert_eq!((position.latitude, position.longitude), (1.0, 2.0));
+    }
+}
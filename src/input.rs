@@ -1,11 +1,21 @@
 use crate::engine::Engine;
+use crate::keymap::{Command, Keymap};
+use crate::passwords;
+use crate::cookies;
+use crate::site_storage;
+use crate::extensions;
 use crate::ui::{BookmarkUiAction, BrowserUI};
-use arboard::Clipboard;
+use crate::clipboard::{self, Selection};
 use smol_str::SmolStr;
+use std::path::{Path, PathBuf};
+use url::Url;
 use winit::event::{ElementState, KeyEvent, Modifiers, MouseScrollDelta};
 use winit::keyboard::{Key, NamedKey};
 use winit::window::Window;
 
+/// Factor each zoom-in shortcut multiplies the page zoom by
+const PAGE_ZOOM_STEP: f32 = 1.1;
+
 #[cfg(target_os = "macos")]
 fn action_mod_pressed(modifiers: &Modifiers) -> bool {
     modifiers.state().meta_key()
@@ -23,17 +33,79 @@ pub enum InputAction {
     RequestRedraw,
     QuitApp,
     Navigate(String),
+    OpenInNewTab(String),
     AddTab,
+    NewWindow,
     CloseTab(usize),
     SwitchTab(usize),
     ReorderTab { from_index: usize, to_index: usize },
+    TogglePinTab(usize),
+    ToggleTabMute(usize),
+    /// Reopen a recently closed tab; index 0 is the most recently closed one
+    RestoreClosedTab(usize),
     ReloadPage,
+    ToggleFullscreen,
+    ExitFullscreen,
     GoBack,
     GoForward,
     GoHome,
     ForwardToTab(KeyboardInput),
     OpenSettings,
     SetDefaultBrowser,
+    SavePage,
+    /// Save a PNG of the visible viewport, or of the whole page when `full_page` is set
+    CaptureScreenshot { full_page: bool },
+    /// Ask the active tab's process what it holds and show the answer
+    ReportResources,
+    /// Prompt for a manual location to report instead of the OS location service
+    SetLocationOverride,
+    /// Prompt for the languages to ask sites for instead of the desktop's
+    SetLanguagesOverride,
+    /// Prompt for network conditions to emulate in the active tab
+    EmulateNetworkConditions,
+    /// Switch the active site between automatic, light and dark colors
+    CycleSiteColorScheme,
+    /// Toggle forced dark colors for the active tab's site
+    ToggleSiteForceDark,
+    /// Switch the active site's autoplay between limiting sound, allowing and blocking it
+    CycleSiteAutoplay,
+    /// Allow or block JavaScript for the active tab's site
+    ToggleSiteJavaScript,
+    /// Show the find bar and search for what it holds
+    OpenFindBar,
+    /// Search the active tab for the find bar's query, moving to the next or previous match
+    FindInPage { forward: bool },
+    /// Switch find in page between painting every match and only the current one
+    ToggleFindHighlightAll,
+    CloseFindBar,
+    /// Allow the active tab's site to open popups, and open the ones it was stopped from opening
+    AllowSitePopups,
+    /// Turn JavaScript on or off for every site without an exception
+    ToggleGlobalJavaScript,
+    /// Switch the active tab's site between loading images and showing placeholders
+    ToggleSiteImages,
+    /// Turn image loading on or off for every site without an exception
+    ToggleGlobalImages,
+    /// Switch the chrome between the light, dark and custom themes
+    CycleUiTheme,
+    /// Switch the chrome between the normal and compact density
+    ToggleUiDensity,
+    /// Step the most frames a page's frame may wait for its vsync through 1, 2 and 3
+    CycleMaxLatency,
+    /// Multiply the active tab's page zoom by this factor, or set it back to 100% for `None`
+    ZoomPage(Option<f32>),
+    /// Answer the permission prompt shown for the active tab
+    PermissionDecision { granted: bool },
+    /// Answer the active tab's alert(), confirm() or prompt() dialog with OK or Cancel
+    AnswerPageDialog { accepted: bool },
+    /// Fill the active tab's focused form with the entry at this index of the autofill popup
+    Autofill(usize),
+    /// Add, edit or remove saved autofill profiles
+    ManageAutofillProfiles,
+    /// Open a window for another browser profile, or create one
+    OpenProfile,
+    /// Install the active tab's page as a web app with a desktop shortcut
+    InstallWebApp,
     AddCurrentPageBookmark { parent_id: Option<String> },
     ToggleCurrentPageBookmark,
     MoveBookmark { id: String, parent_id: Option<String>, index: Option<usize> },
@@ -68,6 +140,63 @@ pub fn handle_mouse_click_ui(
     active_tab_index: usize,
     shift_held: bool,
 ) -> InputAction {
+    // The chrome is hidden in fullscreen, so every click belongs to the page
+    if ui.is_fullscreen() {
+        return InputAction::None;
+    }
+
+    if let Some(action_id) = ui.handle_permission_prompt_click(x, y).filter(|_| !ui.show_settings) {
+        return match action_id.as_str() {
+            "permission_allow" => InputAction::PermissionDecision { granted: true },
+            "permission_block" => InputAction::PermissionDecision { granted: false },
+            _ => InputAction::RequestRedraw,
+        };
+    }
+
+    // A page dialog blocks its tab's content, so clicks on the page behind it go nowhere
+    if let Some(action_id) = ui.handle_page_dialog_click(x, y).filter(|_| !ui.show_settings) {
+        return match action_id.as_str() {
+            "page_dialog_ok" => InputAction::AnswerPageDialog { accepted: true },
+            "page_dialog_cancel" => InputAction::AnswerPageDialog { accepted: false },
+            "page_dialog_input" => {
+                ui.begin_text_selection_drag("page_dialog_input", x, shift_held);
+                InputAction::RequestRedraw
+            }
+            "page_dialog_mute" => {
+                ui.toggle_page_dialog_mute();
+                InputAction::RequestRedraw
+            }
+            _ => InputAction::RequestRedraw,
+        };
+    }
+
+    if let Some(action_id) = ui.handle_find_bar_click(x, y).filter(|_| !ui.show_settings) {
+        return match action_id.as_str() {
+            "find_previous" => InputAction::FindInPage { forward: false },
+            "find_next" => InputAction::FindInPage { forward: true },
+            "find_highlight_all" => InputAction::ToggleFindHighlightAll,
+            "find_close" => InputAction::CloseFindBar,
+            "find_input" => {
+                ui.begin_text_selection_drag("find_input", x, shift_held);
+                InputAction::RequestRedraw
+            }
+            _ => InputAction::RequestRedraw,
+        };
+    }
+
+    // The autofill popup closes on any click; one on a profile fills the form with it
+    let autofill_choice = ui.handle_autofill_popup_click(x, y);
+    if ui.hide_autofill_popup() {
+        if let Some(index) = autofill_choice.filter(|_| !ui.show_settings) {
+            return InputAction::Autofill(index);
+        }
+    }
+
+    // App windows have no chrome, so the rest of the window is the page
+    if ui.is_app_mode() {
+        return InputAction::None;
+    }
+
     if let Some(bookmark_action) = ui.handle_bookmark_click(x, y) {
         return match bookmark_action {
             BookmarkUiAction::Navigate(url) => InputAction::Navigate(url),
@@ -82,6 +211,17 @@ pub fn handle_mouse_click_ui(
         };
     }
 
+    // The site information panel closes on any click outside of it
+    if let Some(action_id) = ui.handle_site_info_panel_click(x, y) {
+        if action_id == "site_info_panel_noop" {
+            return InputAction::RequestRedraw;
+        }
+        ui.show_site_info = false;
+        if ui.handle_click(x, y).as_deref() == Some("site_info") {
+            return InputAction::RequestRedraw;
+        }
+    }
+
     // If settings panel is open, route clicks to it first
     if ui.show_settings {
         if let Some(action_id) = ui.handle_settings_panel_click(x, y) {
@@ -90,10 +230,96 @@ pub fn handle_mouse_click_ui(
                     ui.show_settings = false;
                     return InputAction::SetDefaultBrowser;
                 }
+                "save_page" => {
+                    ui.show_settings = false;
+                    return InputAction::SavePage;
+                }
+                "screenshot_viewport" | "screenshot_full_page" => {
+                    ui.show_settings = false;
+                    return InputAction::CaptureScreenshot { full_page: action_id == "screenshot_full_page" };
+                }
+                "set_location" => {
+                    ui.show_settings = false;
+                    return InputAction::SetLocationOverride;
+                }
+                "set_languages" => {
+                    ui.show_settings = false;
+                    return InputAction::SetLanguagesOverride;
+                }
+                "emulate_network_conditions" => {
+                    ui.show_settings = false;
+                    return InputAction::EmulateNetworkConditions;
+                }
+                "cycle_site_color_scheme" => {
+                    // Keep the panel open so the new choice is visible
+                    return InputAction::CycleSiteColorScheme;
+                }
+                "toggle_site_force_dark" => {
+                    return InputAction::ToggleSiteForceDark;
+                }
+                "cycle_site_autoplay" => {
+                    return InputAction::CycleSiteAutoplay;
+                }
+                "toggle_site_javascript" => {
+                    return InputAction::ToggleSiteJavaScript;
+                }
+                "toggle_global_javascript" => {
+                    return InputAction::ToggleGlobalJavaScript;
+                }
+                "toggle_site_images" => {
+                    return InputAction::ToggleSiteImages;
+                }
+                "toggle_global_images" => {
+                    return InputAction::ToggleGlobalImages;
+                }
+                "cycle_ui_theme" => {
+                    return InputAction::CycleUiTheme;
+                }
+                "toggle_ui_density" => {
+                    return InputAction::ToggleUiDensity;
+                }
+                "cycle_max_latency" => {
+                    return InputAction::CycleMaxLatency;
+                }
+                "manage_autofill" => {
+                    ui.show_settings = false;
+                    return InputAction::ManageAutofillProfiles;
+                }
+                "open_passwords" => {
+                    ui.show_settings = false;
+                    return InputAction::Navigate(passwords::MANAGEMENT_URL.to_string());
+                }
+                "open_extensions" => {
+                    ui.show_settings = false;
+                    return InputAction::Navigate(extensions::MANAGEMENT_URL.to_string());
+                }
+                "open_cookies" => {
+                    ui.show_settings = false;
+                    return InputAction::Navigate(cookies::MANAGEMENT_URL.to_string());
+                }
+                "open_site_storage" => {
+                    ui.show_settings = false;
+                    return InputAction::Navigate(site_storage::MANAGEMENT_URL.to_string());
+                }
+                "open_profile" => {
+                    ui.show_settings = false;
+                    return InputAction::OpenProfile;
+                }
+                "install_web_app" => {
+                    ui.show_settings = false;
+                    return InputAction::InstallWebApp;
+                }
                 "settings_panel_close" => {
                     ui.show_settings = false;
                     return InputAction::RequestRedraw;
                 }
+                id if id.starts_with("restore_closed:") => {
+                    ui.show_settings = false;
+                    if let Ok(index) = id["restore_closed:".len()..].parse() {
+                        return InputAction::RestoreClosedTab(index);
+                    }
+                    return InputAction::RequestRedraw;
+                }
                 _ => {
                     // noop - click consumed inside panel
                     return InputAction::RequestRedraw;
@@ -102,35 +328,55 @@ pub fn handle_mouse_click_ui(
         }
     }
 
+    // A tab's speaker mutes it rather than selecting it
+    if let Some(tab_id) = ui.check_tab_audio_button_click(x, y) {
+        if let Some(tab_index) = tabs.iter().position(|(id, _)| id == &tab_id) {
+            return InputAction::ToggleTabMute(tab_index);
+        }
+    }
+
     // Check if close button was clicked first
     if let Some(tab_id) = ui.check_close_button_click(x, y) {
-        println!("Close button clicked for tab: {}", tab_id);
+        tracing::debug!("Close button clicked for tab: {}", tab_id);
         if let Some(tab_index) = tabs.iter().position(|(id, _)| id == &tab_id) {
             return InputAction::CloseTab(tab_index);
         }
     }
 
+    // The "JavaScript blocked" badge covers the end of the address bar
+    if ui.handle_javascript_badge_click(x, y) {
+        return InputAction::ToggleSiteJavaScript;
+    }
+
+    // As does the "Popup blocked" badge, next to it
+    if ui.handle_popup_badge_click(x, y) {
+        return InputAction::AllowSitePopups;
+    }
+
     // UI now uses pixel coordinates directly
     if let Some(component_id) = ui.handle_click(x, y) {
         // Handle based on component
         if component_id == "back" {
-            println!("Back button clicked");
+            tracing::debug!("Back button clicked");
             return InputAction::GoBack;
         } else if component_id == "forward" {
-            println!("Forward button clicked");
+            tracing::debug!("Forward button clicked");
             return InputAction::GoForward;
         } else if component_id == "refresh" {
-            println!("Refresh button clicked");
+            tracing::debug!("Refresh button clicked");
             return InputAction::ReloadPage;
         } else if component_id == "home" {
-            println!("Home button clicked");
+            tracing::debug!("Home button clicked");
             return InputAction::GoHome;
         } else if component_id == "new_tab" {
-            println!("New tab button clicked");
+            tracing::debug!("New tab button clicked");
             return InputAction::AddTab;
         } else if component_id == "settings" {
-            println!("Settings button clicked");
+            tracing::debug!("Settings button clicked");
             return InputAction::OpenSettings;
+        } else if component_id == "site_info" {
+            ui.toggle_site_info();
+            return InputAction::RequestRedraw;
         } else if component_id == "bookmark_toggle" {
             return InputAction::ToggleCurrentPageBookmark;
         } else if component_id == "address_bar" {
@@ -159,10 +405,21 @@ pub fn handle_middle_click(
 ) -> InputAction {
     // Check if a tab was clicked
     if let Some(component_id) = ui.handle_click(x, y) {
+        // Middle-clicking a text field pastes the primary selection where it was clicked
+        if ui.is_mouse_over_text_field(x as f64, y as f64) {
+            if let Some(text) = clipboard::get_text(Selection::Primary) {
+                ui.paste_at_click(&component_id, x, &text);
+            }
+            return InputAction::RequestRedraw;
+        }
         if component_id.starts_with("tab") {
             // Find the tab index by ID
+            // Pinned tabs only close from their context menu or the close tab shortcut
+            if ui.is_tab_pinned(&component_id) {
+                return InputAction::RequestRedraw;
+            }
             if let Some(tab_index) = tabs.iter().position(|(id, _)| id == &component_id) {
-                println!("Middle-click closing tab: {}", component_id);
+                tracing::debug!("Middle-click closing tab: {}", component_id);
                 return InputAction::CloseTab(tab_index);
             }
         }
@@ -170,6 +427,69 @@ pub fn handle_middle_click(
     InputAction::None
 }
 
+/// File extensions that can be opened directly in a tab when dropped onto the window
+const DROPPABLE_EXTENSIONS: &[&str] = &[
+    "html", "htm", "xhtml", "svg", "txt", "md",
+    "png", "jpg", "jpeg", "gif", "webp", "bmp", "ico", "avif",
+];
+
+/// Convert a dropped file into a URL that can be loaded by a tab.
+///
+/// Regular documents and images become `file://` URLs, while link files
+/// (`.url`, `.desktop`, `.webloc`) are read and resolve to the URL they point at.
+pub fn dropped_path_to_url(path: &Path) -> Option<String> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "url" | "desktop" => {
+            // Windows internet shortcuts and freedesktop link entries both store the target as `URL=...`
+            let contents = std::fs::read_to_string(path).ok()?;
+            contents
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("URL="))
+                .map(|url| url.trim().to_string())
+                .filter(|url| Url::parse(url).is_ok())
+        }
+        "webloc" => {
+            // macOS link files are a small plist with the URL as the first <string> value
+            let contents = std::fs::read_to_string(path).ok()?;
+            let start = contents.find("<string>")? + "<string>".len();
+            let end = contents[start..].find("</string>")? + start;
+            let url = html_escape::decode_html_entities(contents[start..end].trim()).to_string();
+            Url::parse(&url).ok().map(|_| url)
+        }
+        ext if DROPPABLE_EXTENSIONS.contains(&ext) => {
+            let absolute = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+            Url::from_file_path(absolute).ok().map(|url| url.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Handles files dropped onto the browser window.
+///
+/// Dropping onto the tab strip opens every supported file in a new tab, dropping onto the
+/// page opens the first supported file in the active tab and any others in new tabs.
+pub fn handle_file_drop(paths: &[PathBuf], y: f32, ui: &BrowserUI) -> Vec<InputAction> {
+    let urls: Vec<String> = paths.iter().filter_map(|path| dropped_path_to_url(path)).collect();
+    let on_tab_strip = y < ui.tab_row_height();
+
+    urls.into_iter()
+        .enumerate()
+        .map(|(index, url)| {
+            if on_tab_strip || index > 0 {
+                InputAction::OpenInNewTab(url)
+            } else {
+                InputAction::Navigate(url)
+            }
+        })
+        .collect()
+}
+
 /// Handles mouse wheel/scroll events
 pub fn handle_mouse_wheel(
     delta: MouseScrollDelta,
@@ -224,10 +544,78 @@ pub fn handle_mouse_wheel(
     }
 }
 
+/// What running a keymap command does
+fn command_action(command: Command, ui: &mut BrowserUI, active_tab_index: usize, num_tabs: usize) -> InputAction {
+    let next_index = (active_tab_index + 1) % num_tabs.max(1);
+    let previous_index = if active_tab_index == 0 { num_tabs.saturating_sub(1) } else { active_tab_index - 1 };
+
+    // App windows hold a single tab and have no chrome to focus or open
+    if ui.is_app_mode() {
+        match command {
+            Command::NewTab
+            | Command::ReopenClosedTab
+            | Command::NextTab
+            | Command::PreviousTab
+            | Command::MoveTabLeft
+            | Command::MoveTabRight
+            | Command::SelectTab(_)
+            | Command::SelectLastTab
+            | Command::FocusAddressBar
+            | Command::BookmarkPage
+            | Command::OpenSettings => return InputAction::None,
+            _ => {}
+        }
+    }
+
+    match command {
+        Command::NewTab => InputAction::AddTab,
+        Command::NewWindow => InputAction::NewWindow,
+        Command::CloseTab => InputAction::CloseTab(active_tab_index),
+        Command::ReopenClosedTab => InputAction::RestoreClosedTab(0),
+        Command::NextTab => InputAction::SwitchTab(next_index),
+        Command::PreviousTab => InputAction::SwitchTab(previous_index),
+        // Moving doesn't wrap around the ends of the tab strip
+        Command::MoveTabLeft if active_tab_index > 0 => {
+            InputAction::ReorderTab { from_index: active_tab_index, to_index: previous_index }
+        }
+        Command::MoveTabRight if active_tab_index + 1 < num_tabs => {
+            InputAction::ReorderTab { from_index: active_tab_index, to_index: next_index }
+        }
+        Command::MoveTabLeft | Command::MoveTabRight => InputAction::None,
+        Command::SelectTab(index) if index < num_tabs => InputAction::SwitchTab(index),
+        Command::SelectTab(_) => InputAction::None,
+        Command::SelectLastTab => InputAction::SwitchTab(num_tabs.saturating_sub(1)),
+        Command::FocusAddressBar => {
+            ui.set_focus("address_bar");
+            ui.select_all();
+            InputAction::RequestRedraw
+        }
+        Command::Reload => InputAction::ReloadPage,
+        Command::GoBack => InputAction::GoBack,
+        Command::GoForward => InputAction::GoForward,
+        Command::GoHome => InputAction::GoHome,
+        Command::BookmarkPage => InputAction::AddCurrentPageBookmark { parent_id: None },
+        Command::SavePage => InputAction::SavePage,
+        Command::Screenshot => InputAction::CaptureScreenshot { full_page: false },
+        Command::FullPageScreenshot => InputAction::CaptureScreenshot { full_page: true },
+        Command::ToggleFullscreen => InputAction::ToggleFullscreen,
+        Command::OpenSettings => InputAction::OpenSettings,
+        Command::ResourceReport => InputAction::ReportResources,
+        Command::EmulateNetworkConditions => InputAction::EmulateNetworkConditions,
+        Command::FindInPage => InputAction::OpenFindBar,
+        Command::FindNext => InputAction::FindInPage { forward: true },
+        Command::FindPrevious => InputAction::FindInPage { forward: false },
+        Command::ZoomIn => InputAction::ZoomPage(Some(PAGE_ZOOM_STEP)),
+        Command::ZoomOut => InputAction::ZoomPage(Some(1.0 / PAGE_ZOOM_STEP)),
+        Command::ResetZoom => InputAction::ZoomPage(None),
+    }
+}
+
 /// Handles keyboard input events (multi-process version)
 pub fn handle_keyboard_input(
     event: &KeyEvent,
     modifiers: &Modifiers,
+    keymap: &Keymap,
     ui: &mut BrowserUI,
     active_tab_index: usize,
     num_tabs: usize,
@@ -243,135 +631,120 @@ pub fn handle_keyboard_input(
         return InputAction::None;
     }
 
+    // Escape leaves fullscreen before the page sees the key
+    if event.logical_key == Key::Named(NamedKey::Escape) && ui.is_fullscreen() {
+        return InputAction::ExitFullscreen;
+    }
 
-    // Handle keyboard shortcuts with modifiers (browser-level)
-    if action_mod_pressed(modifiers) {
+    // Enter and Escape answer a page dialog unless another text field has the focus
+    if ui.is_page_dialog_shown() && (!has_focused_text_field || ui.is_page_dialog_input_focused()) {
         match &event.logical_key {
-            Key::Character(text) => {
-                let lower = text.to_lowercase();
-                match lower.as_str() {
-                    "d" => {
-                        // Ctrl+D: Add current page to bookmarks.
-                        return InputAction::AddCurrentPageBookmark { parent_id: None };
-                    }
-                    "a" => {
-                        // Ctrl+A: Select all text in address bar
-                        if has_focused_text_field {
-                            println!("Select all shortcut (Ctrl+A)");
-                            ui.select_all();
-                            return InputAction::RequestRedraw;
-                        }
-                        // Forward to tab for page content selection
-                        return InputAction::ForwardToTab(KeyboardInput::Character("ctrl+a".to_string()));
+            Key::Named(NamedKey::Enter) => return InputAction::AnswerPageDialog { accepted: true },
+            Key::Named(NamedKey::Escape) => return InputAction::AnswerPageDialog { accepted: false },
+            _ => {}
+        }
+    }
+
+    // In the find bar Enter steps to the next match, Shift+Enter to the previous one, and Escape
+    // closes it
+    if ui.is_find_input_focused() {
+        match &event.logical_key {
+            Key::Named(NamedKey::Enter) => return InputAction::FindInPage { forward: !modifiers.state().shift_key() },
+            Key::Named(NamedKey::Escape) => return InputAction::CloseFindBar,
+            _ => {}
+        }
+    }
+
+    // While the autofill popup is shown the arrow keys pick an entry, Enter fills it and Escape
+    // dismisses the popup
+    if !has_focused_text_field && ui.is_autofill_popup_shown() {
+        match &event.logical_key {
+            Key::Named(NamedKey::ArrowDown) => {
+                ui.move_autofill_selection(1);
+                return InputAction::RequestRedraw;
+            }
+            Key::Named(NamedKey::ArrowUp) => {
+                ui.move_autofill_selection(-1);
+                return InputAction::RequestRedraw;
+            }
+            Key::Named(NamedKey::Enter) => {
+                if let Some(index) = ui.autofill_selection() {
+                    ui.hide_autofill_popup();
+                    return InputAction::Autofill(index);
+                }
+            }
+            Key::Named(NamedKey::Escape) => {
+                ui.hide_autofill_popup();
+                return InputAction::RequestRedraw;
+            }
+            _ => {}
+        }
+    }
+
+    // Browser shortcuts from the keymap win over the page and the address bar
+    if let Some(command) = keymap.lookup(&event.logical_key, modifiers.state()) {
+        return command_action(command, ui, active_tab_index, num_tabs);
+    }
+
+    // Clipboard shortcuts depend on where the focus is
+    if action_mod_pressed(modifiers) {
+        if let Key::Character(text) = &event.logical_key {
+            let lower = text.to_lowercase();
+            match lower.as_str() {
+                "a" => {
+                    // Ctrl+A: Select all text in address bar
+                    if has_focused_text_field {
+                        tracing::debug!("Select all shortcut (Ctrl+A)");
+                        ui.select_all();
+                        return InputAction::RequestRedraw;
                     }
-                    "c" => {
-                        // Ctrl+C: Copy selected text to clipboard (UI only)
-                        if has_focused_text_field {
-                            if let Some(selected_text) = ui.get_selected_text() {
-                                if !selected_text.is_empty() {
-                                    println!("Copy shortcut (Ctrl+C): {}", selected_text);
-                                    if let Ok(mut clipboard) = Clipboard::new() {
-                                        if let Err(e) = clipboard.set_text(&selected_text) {
-                                            eprintln!("Failed to copy to clipboard: {}", e);
-                                        }
-                                    }
-                                }
+                    // Forward to tab for page content selection
+                    return InputAction::ForwardToTab(KeyboardInput::Character("ctrl+a".to_string()));
+                }
+                "c" => {
+                    // Ctrl+C: Copy selected text to clipboard (UI only)
+                    if has_focused_text_field {
+                        if let Some(selected_text) = ui.get_selected_text() {
+                            if !selected_text.is_empty() {
+                                tracing::debug!("Copy shortcut (Ctrl+C): {}", selected_text);
+                                clipboard::set_text(Selection::Clipboard, &selected_text);
                             }
-                            return InputAction::RequestRedraw;
                         }
-                        // Forward to tab for page content copying
-                        return InputAction::ForwardToTab(KeyboardInput::Character("ctrl+c".to_string()));
+                        return InputAction::RequestRedraw;
                     }
-                    "v" => {
-                        // Ctrl+V: Paste text from clipboard
-                        if has_focused_text_field {
-                            println!("Paste shortcut (Ctrl+V)");
-                            match Clipboard::new() {
-                                Ok(mut clipboard) => {
-                                    match clipboard.get_text() {
-                                        Ok(clipboard_text) => {
-                                            println!("Pasted text: {}", clipboard_text);
-                                            ui.insert_text_at_cursor(&clipboard_text);
-                                        }
-                                        Err(e) => {
-                                            eprintln!("Failed to read from clipboard: {:?}", e);
-                                        }
-                                    }
-                                }
-                                Err(_e) => {}
-                            }
-                            return InputAction::RequestRedraw;
+                    // Forward to tab for page content copying
+                    return InputAction::ForwardToTab(KeyboardInput::Character("ctrl+c".to_string()));
+                }
+                "v" => {
+                    // Ctrl+V: Paste text from clipboard
+                    if has_focused_text_field {
+                        tracing::debug!("Paste shortcut (Ctrl+V)");
+                        if let Some(clipboard_text) = clipboard::get_text(Selection::Clipboard) {
+                            tracing::debug!("Pasted text: {}", clipboard_text);
+                            ui.insert_text_at_cursor(&clipboard_text);
                         }
-                        // Forward to tab for page content pasting
-                        return InputAction::ForwardToTab(KeyboardInput::Character("ctrl+v".to_string()));
+                        return InputAction::RequestRedraw;
                     }
-                    "x" => {
-                        // Ctrl+X: Cut selected text to clipboard
-                        if has_focused_text_field {
-                            if let Some(selected_text) = ui.get_selected_text() {
-                                if !selected_text.is_empty() {
-                                    println!("Cut shortcut (Ctrl+X): {}", selected_text);
-                                    if let Ok(mut clipboard) = Clipboard::new() {
-                                        if let Err(e) = clipboard.set_text(&selected_text) {
-                                            eprintln!("Failed to copy to clipboard: {}", e);
-                                        } else {
-                                            ui.delete_selection();
-                                        }
-                                    }
+                    // Forward to tab for page content pasting
+                    return InputAction::ForwardToTab(KeyboardInput::Character("ctrl+v".to_string()));
+                }
+                "x" => {
+                    // Ctrl+X: Cut selected text to clipboard
+                    if has_focused_text_field {
+                        if let Some(selected_text) = ui.get_selected_text() {
+                            if !selected_text.is_empty() {
+                                tracing::debug!("Cut shortcut (Ctrl+X): {}", selected_text);
+                                if clipboard::set_text(Selection::Clipboard, &selected_text) {
+                                    ui.delete_selection();
                                 }
                             }
-                            return InputAction::RequestRedraw;
                         }
-                        // Forward to tab for page content cutting
-                        return InputAction::ForwardToTab(KeyboardInput::Character("ctrl+x".to_string()));
-                    }
-                    "t" => {
-                        // Ctrl+T: New tab (always browser-level)
-                        println!("New tab shortcut (Ctrl+T)");
-                        return InputAction::AddTab;
-                    }
-                    "w" => {
-                        // Ctrl+W: Close current tab (always browser-level)
-                        println!("Close tab shortcut (Ctrl+W)");
-                        return InputAction::CloseTab(active_tab_index);
-                    }
-                    "l" => {
-                        // Ctrl+L: Focus address bar (always browser-level)
-                        println!("Focus address bar shortcut (Ctrl+L)");
-                        ui.set_focus("address_bar");
                         return InputAction::RequestRedraw;
                     }
-                    "r" => {
-                        // Ctrl+R: Reload page (always browser-level)
-                        println!("Reload shortcut (Ctrl+R)");
-                        return InputAction::ReloadPage;
-                    }
-                    "f" => {
-                        // Ctrl+F: Find in page (forward to tab)
-                        println!("Find in page shortcut (Ctrl+F)");
-                        return InputAction::ForwardToTab(KeyboardInput::Character("ctrl+f".to_string()));
-                    }
-                    _ => {}
-                }
-            }
-            Key::Named(NamedKey::Tab) => {
-                // Ctrl+Tab: Switch to next tab (always browser-level)
-                // Ctrl+Shift+Tab: Switch to previous tab
-                if modifiers.state().shift_key() {
-                    println!("Switch tab shortcut (Ctrl+Shift+Tab)");
-                    let next_index = if active_tab_index == 0 {
-                        num_tabs - 1
-                    } else {
-                        active_tab_index - 1
-                    };
-                    return InputAction::SwitchTab(next_index);
-                } else {
-                    println!("Switch tab shortcut (Ctrl+Tab)");
-                    let next_index = (active_tab_index + 1) % num_tabs;
-                    return InputAction::SwitchTab(next_index);
+                    // Forward to tab for page content cutting
+                    return InputAction::ForwardToTab(KeyboardInput::Character("ctrl+x".to_string()));
                 }
-            }
-            _ => {}
+                _ => {}
         }
     }
 
@@ -413,21 +786,6 @@ pub fn handle_keyboard_input(
         }
     }
 
-    // Handle number keys for tab switching (Ctrl+1, Ctrl+2, etc.)
-    if action_mod_pressed(modifiers) {
-        if let Key::Character(text) = &event.logical_key {
-            if let Ok(num) = text.parse::<usize>() {
-                if num >= 1 && num <= 9 {
-                    let tab_index = num - 1;
-                    if tab_index < num_tabs {
-                        println!("Switch to tab {} shortcut (Ctrl+{})", tab_index + 1, num);
-                        return InputAction::SwitchTab(tab_index);
-                    }
-                }
-            }
-        }
-    }
-
     // Handle text input and navigation keys
     match &event.logical_key {
         Key::Named(NamedKey::Escape) => {
@@ -580,3 +938,32 @@ pub fn handle_keyboard_input(
 
     InputAction::None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::dropped_path_to_url;
+
+    #[test]
+    fn dropped_internet_shortcut_resolves_to_target_url() {
+        let dir = std::env::temp_dir().join(format!("stokes-drop-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let shortcut = dir.join("rust.url");
+        std::fs::write(&shortcut, "[InternetShortcut]\r\nURL=https://www.rust-lang.org/\r\n").unwrap();
+
+        assert_eq!(dropped_path_to_url(&shortcut), Some("https://www.rust-lang.org/".to_string()));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn dropped_unsupported_file_is_ignored() {
+        assert_eq!(dropped_path_to_url(std::path::Path::new("/tmp/archive.zip")), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn dropped_html_file_becomes_file_url() {
+        let url = dropped_path_to_url(std::path::Path::new("/tmp/page.html")).expect("html should be accepted");
+        assert!(url.starts_with("file://"));
+        assert!(url.ends_with("page.html"));
+    }
+}
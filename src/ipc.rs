@@ -9,16 +9,42 @@
 //     fcntl; the kernel notifies readiness through the OS IPC mechanism.
 //   • Efficient large-message handling – the OS can use kernel buffers and
 //     avoids user-space copies for messages that fit in the socket buffer.
-//   • A clean `IpcReceiverSet` API for polling *many* tab receivers at once
-//     without spawning per-tab threads.
+//   • Blocking receives on dedicated threads, so neither process has to poll
+//     its channels: each queues what arrives and wakes its main loop.
+//
+// ipc-channel frames every message itself, so a receiver only ever sees whole
+// messages. What it cannot catch is a tab binary built from different message
+// definitions (e.g. after an update while the browser kept running), so the
+// bootstrap carries a protocol version that the parent checks.
 
+use std::collections::VecDeque;
 use std::io;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
 use ipc_channel::ipc::{
-    self, IpcOneShotServer, IpcReceiver, IpcSender,
+    self, IpcError, IpcOneShotServer, IpcReceiver, IpcSender,
 };
-use ipc_channel::TryRecvError;
+use blitz_traits::navigation::NavigationOptions;
+use blitz_traits::net::{Body, Entry, EntryValue, FormData, Method, Request};
 use serde::{Deserialize, Serialize};
+use url::Url;
+use crate::autofill::AutofillProfile;
+use crate::engine::teardown::ResourceReport;
+use crate::media_preferences::MediaPreferences;
 use crate::events::{MouseEventButtons, UiEvent};
+use crate::frame_timing::FrameTiming;
+use crate::gamepad::GamepadState;
+use crate::geolocation::{GeoPosition, GeolocationError};
+use crate::js::JsDialog;
+use crate::logging::LogRecord;
+use crate::networking::conditions::NetworkConditions;
+use crate::networking::http_auth::AuthChallenge;
+use crate::networking::security::SecurityState;
+use crate::permissions::PermissionKind;
+use crate::visited_links::VisitedLinkFilter;
+use crate::visual_viewport::VisualViewport;
+use crate::web_apps::WebAppManifest;
 
 // ── Wire message types ────────────────────────────────────────────────────────
 
@@ -34,6 +60,85 @@ pub enum ParentToTabMessage {
     RequestFrame,
     SetScaleFactor(f32),
     SetZoom(f32),
+    /// The desktop's dark mode or reduced-motion setting changed
+    SetMediaPreferences(MediaPreferences),
+    /// The user's preferred languages, most preferred first, for navigator.languages and
+    /// Accept-Language
+    SetLanguages(Vec<String>),
+    /// The user changed site settings; re-read them for the current document
+    SiteSettingsChanged,
+    /// The user answered a permission prompt; re-read the saved decisions
+    PermissionsChanged,
+    /// The user left fullscreen from the browser (Escape/F11) while the page was fullscreen
+    ExitFullscreen,
+    /// The browser released the pointer the page had locked (Escape, switching tabs or windows)
+    ExitPointerLock,
+    /// The connected game controllers changed; sent to the active tab only
+    Gamepads(Vec<GamepadState>),
+    /// The visited-link filter, sent to new tabs and again whenever a new page is visited
+    SetVisitedLinks(VisitedLinkFilter),
+    /// The user is typing an address that most likely leads to this origin; connect to it ahead
+    /// of the navigation
+    Preconnect(String),
+    /// The tab was opened by the page at this URL, which its pages see as `window.opener`
+    SetOpener(String),
+    /// This process's prerendered page was swapped into a tab: the tab's history up to the page it
+    /// left, to go before the prerendered entry
+    PrependHistory(Vec<String>),
+    /// Reopen a previously closed tab: load `history[history_index]` and rebuild its history stack
+    RestoreSession { history: Vec<String>, history_index: usize, scroll: (f64, f64) },
+    /// The tab's previous process died during `navigation`: rebuild its history stack and carry
+    /// the navigation on from there
+    ResumeNavigation { history: Vec<String>, history_index: usize, navigation: PendingNavigation },
+    /// Serialize the current page to `path`; `complete` also saves its images and stylesheets
+    SavePage { path: String, complete: bool },
+    /// Render the visible viewport, or the full content height, offscreen and save it as a PNG
+    CaptureScreenshot { full_page: bool },
+    /// The user answered the permission prompt for `request_id`
+    PermissionDecision { request_id: u64, granted: bool },
+    /// Answer to a `navigator.geolocation` lookup
+    GeolocationPosition { request_id: u64, result: Result<GeoPosition, GeolocationError> },
+    /// Fill the focused field's form from a saved autofill profile
+    Autofill(AutofillProfile),
+    /// Fill the focused field's login form with a username and password saved for `origin`; the
+    /// tab ignores it if it has navigated away from that origin since
+    FillLogin { origin: String, username: String, password: String },
+    /// Credentials the user entered for `origin` after an authentication challenge; reload with them
+    HttpAuthCredentials { origin: String, username: String, password: String },
+    /// The user answered page dialog `request_id`: `Some` with the entered text (empty for alert
+    /// and confirm) when accepted, `None` when dismissed
+    DialogAnswer { request_id: u64, value: Option<String> },
+    /// The stokes://passwords page asked for with `PasswordsPageRequest`
+    PasswordsPage { request_id: u64, html: String },
+    /// The tab became the shown tab of its window, or stopped being it
+    SetVisibility(bool),
+    /// The user muted or unmuted the tab; a muted tab's media plays silently
+    SetMuted(bool),
+    /// Network conditions to emulate for the tab's requests, set from the settings panel
+    SetNetworkConditions(NetworkConditions),
+    /// When the display next refreshes, sent to the active tab after each present
+    FrameTiming(FrameTiming),
+    /// The user pinched or panned the magnified page; for `window.visualViewport`
+    SetVisualViewport(VisualViewport),
+    /// Hand a content script's `runtime.sendMessage` to the extension background page this tab
+    /// hosts; `message` and `sender` are JSON
+    ExtensionMessage { request_id: u64, message: String, sender: String },
+    /// The background page's answer to `runtime.sendMessage` call `request_id` of this tab
+    ExtensionResponse { request_id: u64, response: Result<Option<String>, String> },
+    /// Whether the user installed the page's web app, for its beforeinstallprompt event's
+    /// userChoice and the appinstalled event
+    AppInstallResult { accepted: bool },
+    /// Search the page for `query` and move to its next match, or the previous one when `forward`
+    /// is false; a changed query starts over. `highlight_all` paints every match, not just the
+    /// current one.
+    Find { query: String, forward: bool, highlight_all: bool },
+    /// The user switched find-in-page's "highlight all" on or off
+    SetFindHighlightAll(bool),
+    /// The find bar was closed; clear the matches
+    StopFinding,
+    /// Answer with a `ResourceReport` of what the tab holds, for tracking down leaks
+    ReportResources,
+    /// Tear the tab down and exit
     Shutdown,
 }
 
@@ -66,11 +171,95 @@ pub enum TabToParentMessage {
     FrameRendered { shmem_name: String, width: u32, height: u32 },
     Ready,
     NavigateRequest(String),
-    NavigateRequestInNewTab(String),
-    Alert(String),
+    /// A link or `window.open` asked for `url` in a new tab. `opener` is the URL of the page
+    /// when it lets the new tab reach it through `window.opener`.
+    NavigateRequestInNewTab { url: String, opener: Option<String> },
+    /// A page called alert(), confirm() or prompt(); its script waits for `DialogAnswer`
+    Dialog { request_id: u64, origin: String, dialog: JsDialog },
+    /// The tab is loading stokes://passwords at `url`. Only the browser reads saved passwords, so
+    /// it builds the page and answers with `PasswordsPage`; the tab waits for it.
+    PasswordsPageRequest { request_id: u64, url: String },
     ShellProvider(crate::shell_provider::ShellProviderMessage),
     UpdateButtons(MouseEventButtons),
     FaviconUpdated(Option<Vec<u8>>),
+    HistoryChanged { entries: Vec<String>, index: usize },
+    PageSaved { path: String, error: Option<String> },
+    /// Path of the saved screenshot, or why it could not be taken
+    ScreenshotSaved(Result<String, String>),
+    /// Ask the user whether the tab's origin may use `kind`
+    PermissionRequest { request_id: u64, kind: PermissionKind },
+    /// Connection security of the current page, sent after navigations and when it has mixed content
+    SecurityState(SecurityState),
+    /// The current page requires HTTP authentication. The browser takes the origin from the tab's
+    /// committed URL. `retry` is set when the credentials already sent were refused.
+    AuthRequired { challenge: AuthChallenge, retry: bool },
+    /// Answer to `Find`: the current match's index, if there is a match, and how many there are
+    FindResult { active: Option<usize>, count: usize },
+    /// Decoded image memory held by the current page changed
+    MemoryUsage { image_bytes: usize },
+    /// A log event from the tab process, written to the browser's log alongside its own
+    Log(LogRecord),
+    /// The page started or stopped playing sound
+    AudioStateChanged { audible: bool },
+    /// The web app manifest the current page links to, with the bytes of its best icon when the
+    /// app can be installed
+    WebAppManifest { manifest: Option<WebAppManifest>, icon: Option<Vec<u8>> },
+    /// What the tab holds, answering `ReportResources`
+    ResourceReport(ResourceReport),
+    /// The user followed a link to the page this tab asked to prerender. The parent swaps the
+    /// prerendering process in for this one, or answers with `Navigate` when it has none.
+    ActivatePrerender(String),
+    /// Sent after `NavigationStarted` for a navigation the parent can carry on in a new process,
+    /// should this one die before it completes
+    PendingNavigation(PendingNavigation),
+}
+
+/// A navigation a tab has started but not committed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingNavigation {
+    pub url: String,
+    /// Fields of the form a POST navigation submits
+    pub form: Option<Vec<(String, String)>>,
+    /// Whether the page takes the place of the current history entry rather than following it
+    pub replace: bool,
+    /// Where to scroll the loaded page to, for reloads that keep the scroll position
+    pub scroll: Option<(f64, f64)>,
+}
+
+impl PendingNavigation {
+    /// What is needed to send `request` again. POSTs are only kept for forms of text fields,
+    /// since a form's files may be gone by the time the request is resent.
+    pub fn from_request(request: &Request, replace: bool, scroll: Option<(f64, f64)>) -> Option<Self> {
+        let form = match &request.body {
+            Body::Empty if request.method == Method::GET => None,
+            Body::Form(form) if request.method == Method::POST => Some(
+                form.iter()
+                    .map(|Entry { name, value }| match value {
+                        EntryValue::String(value) => Some((name.clone(), value.clone())),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?,
+            ),
+            _ => return None,
+        };
+        Some(Self { url: request.url.to_string(), form, replace, scroll })
+    }
+
+    /// The request to send again
+    pub fn to_request(&self) -> Option<Request> {
+        let url = Url::parse(&self.url).ok()?;
+        let Some(fields) = &self.form else {
+            return Some(Request::get(url));
+        };
+        let mut form = FormData::new();
+        for (name, value) in fields {
+            form.0.push(Entry { name: name.clone(), value: EntryValue::String(value.clone()) });
+        }
+        let options = NavigationOptions::new(url, String::from("application/x-www-form-urlencoded"), 0)
+            .set_document_resource(Body::Form(form))
+            .set_method(Method::POST);
+        Some(options.into_request())
+    }
 }
 
 /// Keyboard modifier key state
@@ -92,9 +281,15 @@ pub struct KeyModifiers {
 //   - tab_to_parent_rx: the parent's receiver for messages from the tab
 //   - parent_to_tab_tx: the parent's sender for messages to the tab
 
+/// Version of the parent/tab message definitions. Bump it whenever a message in this file
+/// changes shape.
+pub const PROTOCOL_VERSION: u32 = 2;
+
 /// Sent once by the child over the one-shot bootstrap channel.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ChannelBootstrap {
+    /// `PROTOCOL_VERSION` of the tab process
+    pub protocol_version: u32,
     /// The parent's receiver for tab→parent messages
     pub tab_to_parent_rx: IpcReceiver<TabToParentMessage>,
     /// The parent's sender for parent→tab messages
@@ -102,10 +297,26 @@ pub struct ChannelBootstrap {
 }
 
 // ── IpcChannel (tab/child side) ───────────────────────────────────────────────
+//
+// The tab process spends long stretches in style, layout and script. Messages
+// from the parent are therefore received on a dedicated thread and queued, so
+// the OS channel keeps draining and a slow relayout never stalls the parent.
+// While the queue waits for the main loop, messages that only describe the
+// latest state (size, zoom, frame requests) replace their queued predecessors,
+// so a page that took 500ms to lay out handles one resize afterwards instead
+// of replaying every intermediate size.
+
+/// Unreadable messages in a row after which the channel is treated as broken
+const MAX_FAILED_RECEIVES: u32 = 16;
+
+/// Queue length past which stale pointer moves and wheel events are merged or dropped.
+/// Other messages are never dropped, so the queue may still grow beyond it.
+const MAX_QUEUED_MESSAGES: usize = 256;
 
+#[derive(Clone)]
 pub struct IpcChannel {
     sender: IpcSender<TabToParentMessage>,
-    receiver: IpcReceiver<ParentToTabMessage>,
+    receiver: ParentMessageQueue,
 }
 
 impl IpcChannel {
@@ -116,17 +327,253 @@ impl IpcChannel {
     }
 
     pub fn try_receive(&self) -> io::Result<Option<ParentToTabMessage>> {
-        match self.receiver.try_recv() {
-            Ok(msg) => Ok(Some(msg)),
-            Err(TryRecvError::Empty) => Ok(None),
-            Err(e) => Err(io::Error::new(io::ErrorKind::BrokenPipe, e)),
-        }
+        self.receiver.try_receive()
     }
 
     pub fn receive(&self) -> io::Result<ParentToTabMessage> {
-        self.receiver
-            .recv()
-            .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))
+        self.receiver.receive()
+    }
+
+    /// Block until a message is waiting, the main loop is woken or `timeout` passes, without
+    /// taking the message. Without a timeout this waits for as long as it takes.
+    pub fn wait_for_message(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.receiver.wait(timeout)
+    }
+
+    /// Have `wake_main_loop` end the current or next `wait_for_message`
+    pub fn install_main_loop_waker(&self) {
+        let receiver = self.receiver.clone();
+        set_main_loop_waker(move || receiver.wake());
+    }
+
+    /// Block until the parent answers dialog `request_id`, leaving every other message queued for
+    /// the main loop. A shutdown or lost parent dismisses the dialog.
+    pub fn wait_for_dialog_answer(&self, request_id: u64) -> Option<String> {
+        let answer = self.receiver.wait_for_reply(|message| {
+            matches!(message, ParentToTabMessage::DialogAnswer { request_id: id, .. } if *id == request_id)
+        });
+        match answer {
+            Some(ParentToTabMessage::DialogAnswer { value, .. }) => value,
+            _ => None,
+        }
+    }
+
+    /// Block until the parent sends the passwords page asked for with `request_id`, the same way
+    pub fn wait_for_passwords_page(&self, request_id: u64) -> Option<String> {
+        let page = self.receiver.wait_for_reply(|message| {
+            matches!(message, ParentToTabMessage::PasswordsPage { request_id: id, .. } if *id == request_id)
+        });
+        match page {
+            Some(ParentToTabMessage::PasswordsPage { html, .. }) => Some(html),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    messages: VecDeque<ParentToTabMessage>,
+    disconnected: bool,
+    /// Something other than a parent message needs the main loop
+    woken: bool,
+}
+
+/// Parent messages received by the receiver thread, waiting for the tab's main loop
+#[derive(Clone)]
+struct ParentMessageQueue {
+    state: Arc<(Mutex<QueueState>, Condvar)>,
+}
+
+impl ParentMessageQueue {
+    fn spawn(receiver: IpcReceiver<ParentToTabMessage>) -> io::Result<Self> {
+        let state = Arc::new((Mutex::new(QueueState::default()), Condvar::new()));
+        let thread_state = state.clone();
+        thread::Builder::new()
+            .name("ipc-receiver".to_string())
+            .spawn(move || {
+                let (lock, ready) = &*thread_state;
+                let mut failed_receives = 0;
+                loop {
+                    let message = receiver.recv();
+                    if let Err(e) = &message {
+                        if !matches!(e, IpcError::Disconnected) && failed_receives < MAX_FAILED_RECEIVES {
+                            failed_receives += 1;
+                            tracing::warn!("Skipping unreadable message from the browser process: {:?}", e);
+                            continue;
+                        }
+                    }
+                    failed_receives = 0;
+                    let Ok(mut queue) = lock.lock() else {
+                        return;
+                    };
+                    match message {
+                        Ok(message) => {
+                            let shutdown = matches!(message, ParentToTabMessage::Shutdown);
+                            enqueue(&mut queue.messages, message);
+                            ready.notify_one();
+                            // The main loop tears the tab down; a tab stuck past the parent's
+                            // shutdown timeout is killed by the parent
+                            if shutdown {
+                                return;
+                            }
+                        }
+                        Err(_) => {
+                            queue.disconnected = true;
+                            ready.notify_one();
+                            return;
+                        }
+                    }
+                }
+            })?;
+        Ok(Self { state })
+    }
+
+    fn try_receive(&self) -> io::Result<Option<ParentToTabMessage>> {
+        let mut queue = self.state.0.lock().map_err(|_| io::Error::other("IPC queue poisoned"))?;
+        match queue.messages.pop_front() {
+            Some(message) => Ok(Some(message)),
+            None if queue.disconnected => Err(io::Error::new(io::ErrorKind::BrokenPipe, "parent disconnected")),
+            None => Ok(None),
+        }
+    }
+
+    fn wait(&self, timeout: Option<Duration>) -> io::Result<()> {
+        let (lock, ready) = &*self.state;
+        let queue = lock.lock().map_err(|_| io::Error::other("IPC queue poisoned"))?;
+        let idle = |queue: &mut QueueState| queue.messages.is_empty() && !queue.disconnected && !queue.woken;
+        let mut queue = match timeout {
+            Some(timeout) => ready.wait_timeout_while(queue, timeout, idle).map(|(queue, _)| queue),
+            None => ready.wait_while(queue, idle),
+        }
+        .map_err(|_| io::Error::other("IPC queue poisoned"))?;
+        queue.woken = false;
+        Ok(())
+    }
+
+    fn wake(&self) {
+        let (lock, ready) = &*self.state;
+        if let Ok(mut queue) = lock.lock() {
+            queue.woken = true;
+            ready.notify_one();
+        }
+    }
+
+    /// Take the first message `is_reply` accepts, waiting for it to arrive. None on shutdown or
+    /// when the parent is gone.
+    fn wait_for_reply(&self, is_reply: impl Fn(&ParentToTabMessage) -> bool) -> Option<ParentToTabMessage> {
+        let (lock, ready) = &*self.state;
+        let mut queue = lock.lock().ok()?;
+        loop {
+            if let Some(index) = queue.messages.iter().position(&is_reply) {
+                return queue.messages.remove(index);
+            }
+            let shutdown = queue.messages.iter().any(|message| matches!(message, ParentToTabMessage::Shutdown));
+            if shutdown || queue.disconnected {
+                return None;
+            }
+            queue = ready.wait(queue).ok()?;
+        }
+    }
+
+    fn receive(&self) -> io::Result<ParentToTabMessage> {
+        let (lock, ready) = &*self.state;
+        let mut queue = lock.lock().map_err(|_| io::Error::other("IPC queue poisoned"))?;
+        loop {
+            if let Some(message) = queue.messages.pop_front() {
+                return Ok(message);
+            }
+            if queue.disconnected {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "parent disconnected"));
+            }
+            queue = ready.wait(queue).map_err(|_| io::Error::other("IPC queue poisoned"))?;
+        }
+    }
+}
+
+/// Queue `message`, dropping queued messages it supersedes
+pub(crate) fn enqueue(queue: &mut VecDeque<ParentToTabMessage>, message: ParentToTabMessage) {
+    match &message {
+        ParentToTabMessage::RequestFrame => {
+            if queue.iter().any(|queued| matches!(queued, ParentToTabMessage::RequestFrame)) {
+                return;
+            }
+        }
+        ParentToTabMessage::Resize { .. } => {
+            queue.retain(|queued| !matches!(queued, ParentToTabMessage::Resize { .. }));
+        }
+        ParentToTabMessage::SetScaleFactor(_) => {
+            queue.retain(|queued| !matches!(queued, ParentToTabMessage::SetScaleFactor(_)));
+        }
+        ParentToTabMessage::SetZoom(_) => {
+            queue.retain(|queued| !matches!(queued, ParentToTabMessage::SetZoom(_)));
+        }
+        ParentToTabMessage::SetVisibility(_) => {
+            queue.retain(|queued| !matches!(queued, ParentToTabMessage::SetVisibility(_)));
+        }
+        ParentToTabMessage::SetMuted(_) => {
+            queue.retain(|queued| !matches!(queued, ParentToTabMessage::SetMuted(_)));
+        }
+        ParentToTabMessage::SetNetworkConditions(_) => {
+            queue.retain(|queued| !matches!(queued, ParentToTabMessage::SetNetworkConditions(_)));
+        }
+        ParentToTabMessage::FrameTiming(_) => {
+            queue.retain(|queued| !matches!(queued, ParentToTabMessage::FrameTiming(_)));
+        }
+        ParentToTabMessage::SetVisualViewport(_) => {
+            queue.retain(|queued| !matches!(queued, ParentToTabMessage::SetVisualViewport(_)));
+        }
+        ParentToTabMessage::Gamepads(_) => {
+            queue.retain(|queued| !matches!(queued, ParentToTabMessage::Gamepads(_)));
+        }
+        ParentToTabMessage::SetVisitedLinks(_) => {
+            queue.retain(|queued| !matches!(queued, ParentToTabMessage::SetVisitedLinks(_)));
+        }
+        ParentToTabMessage::SetLanguages(_) => {
+            queue.retain(|queued| !matches!(queued, ParentToTabMessage::SetLanguages(_)));
+        }
+        _ => {}
+    }
+    if queue.len() < MAX_QUEUED_MESSAGES {
+        queue.push_back(message);
+        return;
+    }
+
+    // The tab is falling behind: fold the new event into the one queued just before it if both
+    // are moves of the same pointer or wheel turns, otherwise make room by dropping the oldest
+    // stale input
+    let message = match (queue.back_mut(), message) {
+        (Some(ParentToTabMessage::UI(queued)), ParentToTabMessage::UI(event)) => match queued.coalesce(event) {
+            None => return,
+            Some(event) => ParentToTabMessage::UI(event),
+        },
+        (_, message) => message,
+    };
+    let stale = queue.iter().position(|queued| {
+        matches!(queued, ParentToTabMessage::UI(UiEvent::PointerMove(_) | UiEvent::Wheel(_)))
+    });
+    if let Some(index) = stale {
+        queue.remove(index);
+    }
+    queue.push_back(message);
+}
+
+// ── Main loop waking ──────────────────────────────────────────────────────────
+//
+// Both processes' main loops sleep until there is something to do. Threads that
+// hand them work outside the IPC channel (network callbacks, provider messages)
+// wake them through this.
+
+static MAIN_LOOP_WAKER: OnceLock<Box<dyn Fn() + Send + Sync>> = OnceLock::new();
+
+/// Set how `wake_main_loop` reaches this process's main loop. Only the first call counts.
+pub fn set_main_loop_waker(wake: impl Fn() + Send + Sync + 'static) {
+    let _ = MAIN_LOOP_WAKER.set(Box::new(wake));
+}
+
+/// Wake this process's main loop, if it has set a waker
+pub fn wake_main_loop() {
+    if let Some(wake) = MAIN_LOOP_WAKER.get() {
+        wake();
     }
 }
 
@@ -134,7 +581,7 @@ impl IpcChannel {
 
 pub struct ParentIpcChannel {
     pub sender: IpcSender<ParentToTabMessage>,
-    pub receiver: IpcReceiver<TabToParentMessage>,
+    receiver: TabMessageQueue,
 }
 
 impl ParentIpcChannel {
@@ -144,15 +591,57 @@ impl ParentIpcChannel {
             .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))
     }
 
+    /// Take the next message, if any. `InvalidData` means one message could not be decoded and
+    /// was skipped; `BrokenPipe` means the tab process is gone.
     pub fn try_receive(&self) -> io::Result<Option<TabToParentMessage>> {
-        match self.receiver.try_recv() {
-            Ok(msg) => Ok(Some(msg)),
-            Err(TryRecvError::Empty) => Ok(None),
-            Err(e) => Err(io::Error::new(io::ErrorKind::BrokenPipe, e)),
+        let mut queue = self.receiver.lock().map_err(|_| io::Error::other("IPC queue poisoned"))?;
+        match queue.pop_front() {
+            Some(Ok(message)) => Ok(Some(message)),
+            Some(Err(IpcError::Disconnected)) => {
+                // Keep reporting the disconnect to later calls
+                queue.push_front(Err(IpcError::Disconnected));
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "tab process disconnected"))
+            }
+            Some(Err(e)) => Err(io::Error::new(io::ErrorKind::InvalidData, format!("{e:?}"))),
+            None => Ok(None),
         }
     }
 }
 
+/// What a tab sent, received on a thread of its own so the parent's event loop can sleep until
+/// one of its tabs has something for it
+type TabMessageQueue = Arc<Mutex<VecDeque<Result<TabToParentMessage, IpcError>>>>;
+
+fn spawn_tab_receiver(receiver: IpcReceiver<TabToParentMessage>) -> io::Result<TabMessageQueue> {
+    let queue = TabMessageQueue::default();
+    let thread_queue = queue.clone();
+    thread::Builder::new()
+        .name("tab-ipc-receiver".to_string())
+        .spawn(move || {
+            let mut failed_receives = 0;
+            loop {
+                let mut message = receiver.recv();
+                match &message {
+                    Ok(_) => failed_receives = 0,
+                    Err(IpcError::Disconnected) => {}
+                    Err(_) if failed_receives < MAX_FAILED_RECEIVES => failed_receives += 1,
+                    Err(_) => message = Err(IpcError::Disconnected),
+                }
+                let disconnected = matches!(message, Err(IpcError::Disconnected));
+                let Ok(mut queue) = thread_queue.lock() else {
+                    return;
+                };
+                queue.push_back(message);
+                drop(queue);
+                wake_main_loop();
+                if disconnected {
+                    return;
+                }
+            }
+        })?;
+    Ok(queue)
+}
+
 // ── IpcServer (parent side) ───────────────────────────────────────────────────
 
 /// Listens for a single incoming bootstrap connection from a tab process.
@@ -184,10 +673,19 @@ impl IpcServer {
         let (_, bootstrap) = self.server
             .accept()
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        if bootstrap.protocol_version != PROTOCOL_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "tab process speaks IPC protocol v{}, expected v{}",
+                    bootstrap.protocol_version, PROTOCOL_VERSION,
+                ),
+            ));
+        }
 
         Ok(ParentIpcChannel {
             sender: bootstrap.parent_to_tab_tx,
-            receiver: bootstrap.tab_to_parent_rx,
+            receiver: spawn_tab_receiver(bootstrap.tab_to_parent_rx)?,
         })
     }
 }
@@ -213,6 +711,7 @@ pub fn connect(server_name: &str) -> io::Result<IpcChannel> {
 
     bootstrap_tx
         .send(ChannelBootstrap {
+            protocol_version: PROTOCOL_VERSION,
             tab_to_parent_rx,
             parent_to_tab_tx,
         })
@@ -220,6 +719,143 @@ pub fn connect(server_name: &str) -> io::Result<IpcChannel> {
 
     Ok(IpcChannel {
         sender: tab_to_parent_tx,
-        receiver: parent_to_tab_rx,
+        receiver: ParentMessageQueue::spawn(parent_to_tab_rx)?,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{enqueue, ParentMessageQueue, ParentToTabMessage, PendingNavigation, MAX_QUEUED_MESSAGES};
+    use blitz_traits::navigation::NavigationOptions;
+    use blitz_traits::net::{Body, Entry, EntryValue, FormData, Method, Request};
+    use crate::events::{
+        BlitzPointerEvent, BlitzPointerId, BlitzWheelDelta, BlitzWheelEvent, MouseEventButtons, PointerCoords,
+        PointerDetails, UiEvent,
+    };
+    use keyboard_types::Modifiers;
+    use std::collections::VecDeque;
+    use url::Url;
+
+    #[test]
+    fn state_messages_replace_their_queued_predecessors() {
+        let mut queue = VecDeque::new();
+        enqueue(&mut queue, ParentToTabMessage::Resize { width: 100.0, height: 100.0 });
+        enqueue(&mut queue, ParentToTabMessage::RequestFrame);
+        enqueue(&mut queue, ParentToTabMessage::Reload);
+        enqueue(&mut queue, ParentToTabMessage::Resize { width: 200.0, height: 150.0 });
+        enqueue(&mut queue, ParentToTabMessage::RequestFrame);
+
+        assert_eq!(queue.len(), 3);
+        assert!(matches!(queue[0], ParentToTabMessage::RequestFrame));
+        assert!(matches!(queue[1], ParentToTabMessage::Reload));
+        assert!(matches!(queue[2], ParentToTabMessage::Resize { width: 200.0, height: 150.0 }));
+    }
+
+    #[test]
+    fn stale_input_is_merged_once_the_queue_is_full() {
+        let mut queue = VecDeque::new();
+        for _ in 0..MAX_QUEUED_MESSAGES {
+            enqueue(&mut queue, ParentToTabMessage::GoBack);
+        }
+        enqueue(&mut queue, wheel(0.0, 10.0));
+        enqueue(&mut queue, wheel(0.0, 5.0));
+        assert_eq!(queue.len(), MAX_QUEUED_MESSAGES + 1);
+        assert!(matches!(
+            queue.back(),
+            Some(ParentToTabMessage::UI(UiEvent::Wheel(BlitzWheelEvent { delta: BlitzWheelDelta::Pixels(_, 15.0), .. })))
+        ));
+
+        // Nothing else is dropped; the stale wheel event makes room instead
+        enqueue(&mut queue, ParentToTabMessage::Reload);
+        assert_eq!(queue.len(), MAX_QUEUED_MESSAGES + 1);
+        assert!(matches!(queue.back(), Some(ParentToTabMessage::Reload)));
+        assert!(!queue.iter().any(|message| matches!(message, ParentToTabMessage::UI(_))));
+    }
+
+    #[test]
+    fn pending_navigations_keep_text_forms_only() {
+        let url = Url::parse("https://example.com/submit").unwrap();
+        let mut form = FormData::new();
+        form.0.push(Entry { name: "q".to_string(), value: EntryValue::String("rust".to_string()) });
+        let post = NavigationOptions::new(url.clone(), String::from("application/x-www-form-urlencoded"), 0)
+            .set_document_resource(Body::Form(form.clone()))
+            .set_method(Method::POST)
+            .into_request();
+
+        let pending = PendingNavigation::from_request(&post, false, None).unwrap();
+        assert_eq!(pending.form, Some(vec![("q".to_string(), "rust".to_string())]));
+        let resent = pending.to_request().unwrap();
+        assert_eq!((resent.method, resent.url), (Method::POST, url.clone()));
+
+        // A form with a file can't be sent again as it was
+        form.0.push(Entry { name: "upload".to_string(), value: EntryValue::EmptyFile });
+        let with_file = NavigationOptions::new(url.clone(), String::from("multipart/form-data"), 0)
+            .set_document_resource(Body::Form(form))
+            .set_method(Method::POST)
+            .into_request();
+        assert_eq!(PendingNavigation::from_request(&with_file, false, None), None);
+
+        let get = PendingNavigation::from_request(&Request::get(url), true, Some((0.0, 120.0))).unwrap();
+        assert_eq!((get.form, get.replace, get.scroll), (None, true, Some((0.0, 120.0))));
+    }
+
+    fn wheel(x: f64, y: f64) -> ParentToTabMessage {
+        ParentToTabMessage::UI(UiEvent::Wheel(BlitzWheelEvent {
+            delta: BlitzWheelDelta::Pixels(x, y),
+            coords: PointerCoords { page_x: 0.0, page_y: 0.0, screen_x: 0.0, screen_y: 0.0, client_x: 0.0, client_y: 0.0 },
+            buttons: MouseEventButtons::empty(),
+            mods: Modifiers::empty(),
+            timestamp: 0.0,
+        }))
+    }
+
+    #[test]
+    fn merged_pointer_moves_keep_their_movement() {
+        let mut queue = VecDeque::new();
+        for _ in 0..MAX_QUEUED_MESSAGES {
+            enqueue(&mut queue, ParentToTabMessage::GoBack);
+        }
+        // A locked pointer reports the same position with every move
+        enqueue(&mut queue, pointer_move(3.0, -1.0));
+        enqueue(&mut queue, pointer_move(4.0, 2.5));
+        let Some(ParentToTabMessage::UI(UiEvent::PointerMove(event))) = queue.back() else {
+            panic!("expected the moves to be merged");
+        };
+        assert_eq!((event.details.movement_x, event.details.movement_y), (7.0, 1.5));
+    }
+
+    fn pointer_move(movement_x: f32, movement_y: f32) -> ParentToTabMessage {
+        ParentToTabMessage::UI(UiEvent::PointerMove(BlitzPointerEvent {
+            id: BlitzPointerId::Mouse,
+            is_primary: true,
+            coords: PointerCoords { page_x: 10.0, page_y: 10.0, screen_x: 10.0, screen_y: 10.0, client_x: 10.0, client_y: 10.0 },
+            button: Default::default(),
+            buttons: MouseEventButtons::empty(),
+            mods: Modifiers::empty(),
+            details: PointerDetails { movement_x, movement_y, ..PointerDetails::default() },
+        }))
+    }
+
+    #[test]
+    fn other_messages_keep_their_order() {
+        let mut queue = VecDeque::new();
+        enqueue(&mut queue, ParentToTabMessage::GoBack);
+        enqueue(&mut queue, ParentToTabMessage::GoBack);
+        enqueue(&mut queue, ParentToTabMessage::Shutdown);
+        assert_eq!(queue.len(), 3);
+    }
+
+    #[test]
+    fn waking_ends_a_wait_without_a_message() {
+        let (sender, receiver) = ipc_channel::ipc::channel::<ParentToTabMessage>().unwrap();
+        let queue = ParentMessageQueue::spawn(receiver).unwrap();
+        queue.wake();
+        queue.wait(None).unwrap();
+        assert!(queue.try_receive().unwrap().is_none());
+
+        // The wake was used up, so this wait lasts until the message arrives
+        sender.send(ParentToTabMessage::Reload).unwrap();
+        queue.wait(None).unwrap();
+        assert!(matches!(queue.try_receive().unwrap(), Some(ParentToTabMessage::Reload)));
+    }
+}
@@ -197,7 +197,7 @@ pub fn setup_abort_signal(runtime: &mut JsRuntime) -> Result<(), String> {
 "#;
 
     runtime.execute(script, false).map_err(|e| {
-        eprintln!("[JS] Warning: Failed to setup AbortSignal/AbortController: {}", e);
+        tracing::warn!("Failed to setup AbortSignal/AbortController: {}", e);
         e
     })?;
 
@@ -0,0 +1,500 @@
+// Blob, File and FormData, plus URL.createObjectURL. The objects themselves are a JS polyfill;
+// object URLs are registered in networking::blob_store so fetch() and the resource loader can
+// read them back through the blob: scheme.
+use crate::js::bindings::dom_bindings::DOM_REF;
+use crate::js::helpers::{ToSafeCx, create_js_string, define_function, js_value_to_string};
+use crate::js::{JsResult, JsRuntime};
+use crate::networking::blob_store;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use mozjs::jsapi::{CallArgs, JSContext};
+use mozjs::jsval::{JSVal, NullValue, UndefinedValue};
+use std::os::raw::c_uint;
+
+/// Install Blob, File, FormData and the object URL statics on URL
+pub fn setup_blob(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesBlobUrlCreate", Some(stokes_blob_url_create), 2)?;
+        define_function(cx, global.get(), "__stokesBlobUrlRevoke", Some(stokes_blob_url_revoke), 1)
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            const BYTES = Symbol('bytes');
+            const TYPE = Symbol('type');
+            const NAME = Symbol('name');
+            const LAST_MODIFIED = Symbol('lastModified');
+            const ENTRIES = Symbol('entries');
+
+            function utf8Encode(text) {
+                if (typeof root.TextEncoder === 'function') {
+                    return new root.TextEncoder().encode(text);
+                }
+                const binary = unescape(encodeURIComponent(text));
+                const bytes = new Uint8Array(binary.length);
+                for (let i = 0; i < binary.length; i++) {
+                    bytes[i] = binary.charCodeAt(i);
+                }
+                return bytes;
+            }
+
+            function utf8Decode(bytes) {
+                if (typeof root.TextDecoder === 'function') {
+                    return new root.TextDecoder().decode(bytes);
+                }
+                let binary = '';
+                for (let i = 0; i < bytes.length; i++) {
+                    binary += String.fromCharCode(bytes[i]);
+                }
+                return decodeURIComponent(escape(binary));
+            }
+
+            function toBase64(bytes) {
+                let binary = '';
+                const CHUNK = 0x8000;
+                for (let i = 0; i < bytes.length; i += CHUNK) {
+                    binary += String.fromCharCode.apply(null, bytes.subarray(i, i + CHUNK));
+                }
+                return btoa(binary);
+            }
+
+            function concat(chunks) {
+                let length = 0;
+                for (const chunk of chunks) {
+                    length += chunk.length;
+                }
+                const out = new Uint8Array(length);
+                let offset = 0;
+                for (const chunk of chunks) {
+                    out.set(chunk, offset);
+                    offset += chunk.length;
+                }
+                return out;
+            }
+
+            function partBytes(part, endings) {
+                if (part instanceof Blob) {
+                    return part[BYTES];
+                }
+                if (part instanceof ArrayBuffer) {
+                    return new Uint8Array(part.slice(0));
+                }
+                if (ArrayBuffer.isView(part)) {
+                    return new Uint8Array(part.buffer.slice(part.byteOffset, part.byteOffset + part.byteLength));
+                }
+                let text = String(part);
+                if (endings === 'native') {
+                    text = text.replace(/\r\n|\r/g, '\n');
+                }
+                return utf8Encode(text);
+            }
+
+            // Types are lowercased, and dropped entirely if they contain non-printable ASCII
+            function normalizeType(type) {
+                if (type === undefined) {
+                    return '';
+                }
+                type = String(type);
+                return /^[\x20-\x7e]*$/.test(type) ? type.toLowerCase() : '';
+            }
+
+            function relativeIndex(value, size, fallback) {
+                if (value === undefined) {
+                    return fallback;
+                }
+                value = Math.trunc(Number(value)) || 0;
+                return value < 0 ? Math.max(size + value, 0) : Math.min(value, size);
+            }
+
+            class Blob {
+                constructor(blobParts, options) {
+                    options = options || {};
+                    const chunks = [];
+                    if (blobParts !== undefined) {
+                        if (blobParts === null || typeof blobParts[Symbol.iterator] !== 'function') {
+                            throw new TypeError("Failed to construct 'Blob': The provided value cannot be converted to a sequence.");
+                        }
+                        for (const part of blobParts) {
+                            chunks.push(partBytes(part, options.endings));
+                        }
+                    }
+                    this[BYTES] = concat(chunks);
+                    this[TYPE] = normalizeType(options.type);
+                }
+
+                get size() {
+                    return this[BYTES].length;
+                }
+
+                get type() {
+                    return this[TYPE];
+                }
+
+                slice(start, end, contentType) {
+                    const size = this.size;
+                    const from = relativeIndex(start, size, 0);
+                    const to = relativeIndex(end, size, size);
+                    const blob = new Blob([], { type: contentType });
+                    blob[BYTES] = this[BYTES].slice(from, Math.max(from, to));
+                    return blob;
+                }
+
+                text() {
+                    return Promise.resolve(utf8Decode(this[BYTES]));
+                }
+
+                arrayBuffer() {
+                    return Promise.resolve(this[BYTES].slice().buffer);
+                }
+
+                bytes() {
+                    return Promise.resolve(this[BYTES].slice());
+                }
+
+                __stokesStructuredClone() {
+                    return new Blob([this], { type: this.type });
+                }
+
+                get [Symbol.toStringTag]() {
+                    return 'Blob';
+                }
+            }
+
+            class File extends Blob {
+                constructor(fileBits, fileName, options) {
+                    if (arguments.length < 2) {
+                        throw new TypeError("Failed to construct 'File': 2 arguments required, but only " + arguments.length + ' present.');
+                    }
+                    options = options || {};
+                    super(fileBits, options);
+                    this[NAME] = String(fileName);
+                    this[LAST_MODIFIED] = options.lastModified === undefined ? Date.now() : Number(options.lastModified);
+                }
+
+                get name() {
+                    return this[NAME];
+                }
+
+                get lastModified() {
+                    return this[LAST_MODIFIED];
+                }
+
+                get webkitRelativePath() {
+                    return '';
+                }
+
+                __stokesStructuredClone() {
+                    return new File([this], this.name, { type: this.type, lastModified: this.lastModified });
+                }
+
+                get [Symbol.toStringTag]() {
+                    return 'File';
+                }
+            }
+
+            function formEntry(method, args) {
+                if (args.length < 2) {
+                    throw new TypeError("Failed to execute '" + method + "' on 'FormData': 2 arguments required, but only " + args.length + ' present.');
+                }
+                const name = String(args[0]);
+                let value = args[1];
+                if (value instanceof Blob) {
+                    // Blobs are stored as Files; an explicit filename always makes a new one
+                    if (!(value instanceof File) || args.length > 2) {
+                        const filename = args.length > 2 ? String(args[2]) : (value instanceof File ? value.name : 'blob');
+                        const lastModified = value instanceof File ? value.lastModified : undefined;
+                        value = new File([value], filename, { type: value.type, lastModified: lastModified });
+                    }
+                } else {
+                    value = String(value);
+                }
+                return [name, value];
+            }
+
+            function collectFormControls(form, submitter) {
+                const entries = [];
+                const controls = form.querySelectorAll('input, select, textarea, button');
+                for (let i = 0; i < controls.length; i++) {
+                    const control = controls[i];
+                    const name = control.getAttribute('name');
+                    if (!name || control.hasAttribute('disabled')) {
+                        continue;
+                    }
+                    const tag = String(control.tagName).toLowerCase();
+                    const type = String(control.getAttribute('type') || (tag === 'button' ? 'submit' : 'text')).toLowerCase();
+                    if (tag === 'button' || type === 'submit' || type === 'button' || type === 'reset' || type === 'image') {
+                        if (control === submitter && type !== 'button' && type !== 'reset') {
+                            entries.push([name, String(control.value || '')]);
+                        }
+                        continue;
+                    }
+                    if (type === 'checkbox' || type === 'radio') {
+                        if (control.checked) {
+                            entries.push([name, control.hasAttribute('value') ? control.getAttribute('value') : 'on']);
+                        }
+                        continue;
+                    }
+                    if (type === 'file') {
+                        const files = control.files ? Array.from(control.files) : [];
+                        if (files.length === 0) {
+                            entries.push([name, new File([], '', { type: 'application/octet-stream' })]);
+                        }
+                        for (const file of files) {
+                            entries.push([name, file]);
+                        }
+                        continue;
+                    }
+                    if (tag === 'select') {
+                        const options = control.querySelectorAll('option');
+                        let selected = false;
+                        for (let j = 0; j < options.length; j++) {
+                            if (options[j].selected || options[j].hasAttribute('selected')) {
+                                entries.push([name, options[j].hasAttribute('value') ? options[j].getAttribute('value') : options[j].textContent]);
+                                selected = true;
+                            }
+                        }
+                        // A single-select submits its first option when nothing is marked selected
+                        if (!selected && options.length > 0 && !control.hasAttribute('multiple')) {
+                            const first = options[0];
+                            entries.push([name, first.hasAttribute('value') ? first.getAttribute('value') : first.textContent]);
+                        }
+                        continue;
+                    }
+                    entries.push([name, String(control.value === undefined || control.value === null ? '' : control.value)]);
+                }
+                return entries;
+            }
+
+            class FormData {
+                constructor(form, submitter) {
+                    this[ENTRIES] = [];
+                    if (form !== undefined) {
+                        if (!form || typeof form.tagName !== 'string' || form.tagName.toLowerCase() !== 'form') {
+                            throw new TypeError("Failed to construct 'FormData': parameter 1 is not of type 'HTMLFormElement'.");
+                        }
+                        this[ENTRIES] = collectFormControls(form, submitter);
+                    }
+                }
+
+                append(name, value, filename) {
+                    this[ENTRIES].push(formEntry('append', arguments));
+                }
+
+                set(name, value, filename) {
+                    const entry = formEntry('set', arguments);
+                    const index = this[ENTRIES].findIndex(function(existing) { return existing[0] === entry[0]; });
+                    if (index < 0) {
+                        this[ENTRIES].push(entry);
+                        return;
+                    }
+                    this[ENTRIES][index] = entry;
+                    this[ENTRIES] = this[ENTRIES].filter(function(existing, i) { return i <= index || existing[0] !== entry[0]; });
+                }
+
+                get(name) {
+                    name = String(name);
+                    const entry = this[ENTRIES].find(function(existing) { return existing[0] === name; });
+                    return entry ? entry[1] : null;
+                }
+
+                getAll(name) {
+                    name = String(name);
+                    return this[ENTRIES].filter(function(existing) { return existing[0] === name; }).map(function(entry) { return entry[1]; });
+                }
+
+                has(name) {
+                    name = String(name);
+                    return this[ENTRIES].some(function(existing) { return existing[0] === name; });
+                }
+
+                delete(name) {
+                    name = String(name);
+                    this[ENTRIES] = this[ENTRIES].filter(function(existing) { return existing[0] !== name; });
+                }
+
+                *entries() {
+                    for (const entry of this[ENTRIES].slice()) {
+                        yield [entry[0], entry[1]];
+                    }
+                }
+
+                *keys() {
+                    for (const entry of this[ENTRIES].slice()) {
+                        yield entry[0];
+                    }
+                }
+
+                *values() {
+                    for (const entry of this[ENTRIES].slice()) {
+                        yield entry[1];
+                    }
+                }
+
+                forEach(callback, thisArg) {
+                    for (const entry of this[ENTRIES].slice()) {
+                        callback.call(thisArg, entry[1], entry[0], this);
+                    }
+                }
+
+                [Symbol.iterator]() {
+                    return this.entries();
+                }
+
+                get [Symbol.toStringTag]() {
+                    return 'FormData';
+                }
+            }
+
+            function randomBoundary() {
+                const ALPHABET = 'ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789';
+                const random = new Uint8Array(16);
+                if (root.crypto && typeof root.crypto.getRandomValues === 'function') {
+                    root.crypto.getRandomValues(random);
+                } else {
+                    for (let i = 0; i < random.length; i++) {
+                        random[i] = Math.floor(Math.random() * 256);
+                    }
+                }
+                let suffix = '';
+                for (let i = 0; i < random.length; i++) {
+                    suffix += ALPHABET[random[i] % ALPHABET.length];
+                }
+                return '----StokesFormBoundary' + suffix;
+            }
+
+            function escapeHeaderValue(value) {
+                return value.replace(/\n/g, '%0A').replace(/\r/g, '%0D').replace(/"/g, '%22');
+            }
+
+            // multipart/form-data as browsers send it (RFC 7578)
+            function encodeMultipart(formData) {
+                const boundary = randomBoundary();
+                const chunks = [];
+                for (const entry of formData[ENTRIES]) {
+                    const name = escapeHeaderValue(entry[0].replace(/\r\n|\r|\n/g, '\r\n'));
+                    let header = '--' + boundary + '\r\nContent-Disposition: form-data; name="' + name + '"';
+                    let body;
+                    if (entry[1] instanceof File) {
+                        header += '; filename="' + escapeHeaderValue(entry[1].name) + '"\r\n';
+                        header += 'Content-Type: ' + (entry[1].type || 'application/octet-stream') + '\r\n';
+                        body = entry[1][BYTES];
+                    } else {
+                        header += '\r\n';
+                        body = utf8Encode(entry[1].replace(/\r\n|\r|\n/g, '\r\n'));
+                    }
+                    chunks.push(utf8Encode(header + '\r\n'), body, utf8Encode('\r\n'));
+                }
+                chunks.push(utf8Encode('--' + boundary + '--\r\n'));
+                return { bytes: concat(chunks), contentType: 'multipart/form-data; boundary=' + boundary };
+            }
+
+            // Returns null for bodies the native fetch already understands as text
+            function extractBody(body) {
+                if (body instanceof FormData) {
+                    return encodeMultipart(body);
+                }
+                if (body instanceof Blob) {
+                    return { bytes: body[BYTES], contentType: body.type || null };
+                }
+                if (body instanceof ArrayBuffer || ArrayBuffer.isView(body)) {
+                    return { bytes: partBytes(body), contentType: null };
+                }
+                return null;
+            }
+
+            function headersWithContentType(headers, contentType) {
+                const plain = {};
+                if (headers && typeof headers.forEach === 'function' && !Array.isArray(headers)) {
+                    headers.forEach(function(value, key) { plain[key] = value; });
+                } else if (Array.isArray(headers)) {
+                    for (const pair of headers) {
+                        plain[pair[0]] = pair[1];
+                    }
+                } else if (headers && typeof headers === 'object') {
+                    Object.assign(plain, headers);
+                }
+                const present = Object.keys(plain).some(function(key) { return key.toLowerCase() === 'content-type'; });
+                if (contentType && !present) {
+                    plain['Content-Type'] = contentType;
+                }
+                return plain;
+            }
+
+            const nativeFetch = root.fetch;
+            if (typeof nativeFetch === 'function') {
+                root.fetch = function fetch(input, init) {
+                    if (init && init.body !== undefined && init.body !== null) {
+                        const encoded = extractBody(init.body);
+                        if (encoded) {
+                            init = Object.assign({}, init, {
+                                headers: headersWithContentType(init.headers, encoded.contentType),
+                                __stokesBodyBase64: toBase64(encoded.bytes),
+                            });
+                            delete init.body;
+                        }
+                    }
+                    return nativeFetch.call(this, input, init);
+                };
+            }
+
+            if (typeof root.URL === 'function') {
+                root.URL.createObjectURL = function createObjectURL(obj) {
+                    if (!(obj instanceof Blob)) {
+                        throw new TypeError("Failed to execute 'createObjectURL' on 'URL': Overload resolution failed.");
+                    }
+                    const url = root.__stokesBlobUrlCreate(toBase64(obj[BYTES]), obj.type);
+                    if (url === null) {
+                        throw new TypeError("Failed to execute 'createObjectURL' on 'URL': no document.");
+                    }
+                    return url;
+                };
+                root.URL.revokeObjectURL = function revokeObjectURL(url) {
+                    root.__stokesBlobUrlRevoke(String(url));
+                };
+            }
+
+            for (const [name, value] of [['Blob', Blob], ['File', File], ['FormData', FormData]]) {
+                Object.defineProperty(root, name, { value: value, writable: true, enumerable: false, configurable: true });
+            }
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+/// Forget the current document's object URLs
+pub fn revoke_all_object_urls() {
+    blob_store::revoke_all();
+}
+
+unsafe extern "C" fn stokes_blob_url_create(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let encoded = if argc > 0 { js_value_to_string(safe_cx, *args.get(0)) } else { String::new() };
+    let content_type = if argc > 1 { js_value_to_string(safe_cx, *args.get(1)) } else { String::new() };
+    let Ok(bytes) = STANDARD.decode(encoded.as_bytes()) else {
+        args.rval().set(NullValue());
+        return true;
+    };
+
+    let origin = DOM_REF.with(|dom| (*dom.borrow()).map(|dom| (*dom).url.origin().ascii_serialization()));
+    match origin {
+        Some(origin) => {
+            let url = blob_store::register(&origin, bytes, content_type);
+            args.rval().set(create_js_string(safe_cx, &url));
+        }
+        None => args.rval().set(NullValue()),
+    }
+    true
+}
+
+unsafe extern "C" fn stokes_blob_url_revoke(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    if argc > 0 {
+        blob_store::revoke(&js_value_to_string(safe_cx, *args.get(0)));
+    }
+    args.rval().set(UndefinedValue());
+    true
+}
@@ -0,0 +1,399 @@
+// Cache API (caches / Cache) with the Headers, Request and Response constructors it hands out
+use crate::js::bindings::storage::storage_origin;
+use crate::js::helpers::{create_js_string, define_function, js_value_to_string, ToSafeCx};
+use crate::js::{JsResult, JsRuntime};
+use crate::site_storage::{self, Area};
+use mozjs::jsapi::{CallArgs, JSContext};
+use mozjs::jsval::{BooleanValue, JSVal, NullValue, UndefinedValue};
+use std::os::raw::c_uint;
+
+/// Install `caches`. Each origin's caches are saved with its other site data and count against its
+/// quota. Bodies are kept as text, so binary responses don't survive being cached.
+pub fn setup_cache_storage(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesSiteData", Some(stokes_site_data), 2)
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            if (typeof root.__stokesSiteData !== 'function') {
+                return;
+            }
+
+            function domError(message, name) {
+                if (typeof root.DOMException === 'function') {
+                    return new root.DOMException(message, name);
+                }
+                const error = new Error(message);
+                error.name = name;
+                return error;
+            }
+
+            // [name, value] pairs of a Headers object, a sequence of pairs or a plain object
+            function headerPairs(init) {
+                if (!init) {
+                    return [];
+                }
+                if (typeof init.forEach === 'function' && !Array.isArray(init)) {
+                    const pairs = [];
+                    init.forEach(function(value, name) { pairs.push([name, value]); });
+                    return pairs;
+                }
+                if (Array.isArray(init)) {
+                    return init.map(function(pair) { return [pair[0], pair[1]]; });
+                }
+                return Object.keys(init).map(function(name) { return [name, init[name]]; });
+            }
+
+            function bodyText(body) {
+                if (body === null || body === undefined) {
+                    return '';
+                }
+                if (typeof body === 'string') {
+                    return body;
+                }
+                if ((body instanceof ArrayBuffer || ArrayBuffer.isView(body)) && typeof root.TextDecoder === 'function') {
+                    return new root.TextDecoder().decode(body);
+                }
+                return String(body);
+            }
+
+            if (typeof root.Headers !== 'function') {
+                class Headers {
+                    constructor(init) {
+                        this._map = new Map();
+                        for (const [name, value] of headerPairs(init)) {
+                            this.append(name, value);
+                        }
+                    }
+                    append(name, value) {
+                        const key = String(name).toLowerCase();
+                        const current = this._map.get(key);
+                        this._map.set(key, current === undefined ? String(value) : current + ', ' + value);
+                    }
+                    set(name, value) { this._map.set(String(name).toLowerCase(), String(value)); }
+                    get(name) {
+                        const value = this._map.get(String(name).toLowerCase());
+                        return value === undefined ? null : value;
+                    }
+                    has(name) { return this._map.has(String(name).toLowerCase()); }
+                    delete(name) { this._map.delete(String(name).toLowerCase()); }
+                    forEach(callback, thisArg) {
+                        for (const [name, value] of this.entries()) {
+                            callback.call(thisArg, value, name, this);
+                        }
+                    }
+                    entries() { return Array.from(this._map.entries()).sort(function(a, b) { return a[0] < b[0] ? -1 : 1; })[Symbol.iterator](); }
+                    keys() { return Array.from(this.entries(), function(pair) { return pair[0]; })[Symbol.iterator](); }
+                    values() { return Array.from(this.entries(), function(pair) { return pair[1]; })[Symbol.iterator](); }
+                    [Symbol.iterator]() { return this.entries(); }
+                }
+                root.Headers = Headers;
+            }
+
+            if (typeof root.Response !== 'function') {
+                class Response {
+                    constructor(body, init) {
+                        init = init || {};
+                        this._body = bodyText(body);
+                        this.status = init.status === undefined ? 200 : Number(init.status);
+                        this.statusText = init.statusText === undefined ? '' : String(init.statusText);
+                        this.headers = new root.Headers(init.headers);
+                        this.ok = this.status >= 200 && this.status < 300;
+                        this.redirected = false;
+                        this.type = 'default';
+                        this.url = '';
+                        this.bodyUsed = false;
+                    }
+                    static error() {
+                        const response = new Response(null, { status: 0 });
+                        response.type = 'error';
+                        return response;
+                    }
+                    static json(data, init) {
+                        const response = new Response(JSON.stringify(data), init);
+                        if (!response.headers.has('content-type')) {
+                            response.headers.set('content-type', 'application/json');
+                        }
+                        return response;
+                    }
+                    _consume() {
+                        if (this.bodyUsed) {
+                            return Promise.reject(new TypeError('Body has already been consumed'));
+                        }
+                        this.bodyUsed = true;
+                        return Promise.resolve(this._body);
+                    }
+                    text() { return this._consume(); }
+                    json() { return this._consume().then(JSON.parse); }
+                    arrayBuffer() {
+                        return this._consume().then(function(text) { return new root.TextEncoder().encode(text).buffer; });
+                    }
+                    blob() {
+                        const type = this.headers.get('content-type') || '';
+                        return this._consume().then(function(text) { return new root.Blob([text], { type: type }); });
+                    }
+                    clone() {
+                        if (this.bodyUsed) {
+                            throw new TypeError('Response body has already been consumed');
+                        }
+                        const copy = new Response(this._body, this);
+                        copy.type = this.type;
+                        copy.url = this.url;
+                        return copy;
+                    }
+                }
+                root.Response = Response;
+            }
+
+            if (typeof root.Request !== 'function') {
+                class Request {
+                    constructor(input, init) {
+                        init = init || {};
+                        const base = root.location ? root.location.href : undefined;
+                        const source = input instanceof Request ? input : null;
+                        this.url = new URL(source ? source.url : String(input), base).href;
+                        this.method = String(init.method || (source ? source.method : 'GET')).toUpperCase();
+                        this.headers = new root.Headers(init.headers || (source ? source.headers : undefined));
+                        this.mode = init.mode || (source ? source.mode : 'cors');
+                        this.credentials = init.credentials || (source ? source.credentials : 'same-origin');
+                        this.cache = init.cache || 'default';
+                        this.redirect = init.redirect || 'follow';
+                        this.destination = '';
+                        this._body = init.body !== undefined ? bodyText(init.body) : (source ? source._body : '');
+                        this.bodyUsed = false;
+                    }
+                    text() {
+                        this.bodyUsed = true;
+                        return Promise.resolve(this._body);
+                    }
+                    json() { return this.text().then(JSON.parse); }
+                    clone() { return new Request(this); }
+                }
+                root.Request = Request;
+            }
+
+            // The origin's caches, saved as { name: [{ url, status, statusText, headers, body }] }
+            function loadCaches() {
+                const json = root.__stokesSiteData('caches');
+                return typeof json === 'string' ? JSON.parse(json) : {};
+            }
+
+            function saveCaches(caches) {
+                const json = Object.keys(caches).length === 0 ? null : JSON.stringify(caches);
+                if (root.__stokesSiteData('caches', json) === false) {
+                    throw domError('The quota has been exceeded.', 'QuotaExceededError');
+                }
+            }
+
+            function requestUrl(request, ignoreSearch) {
+                const base = root.location ? root.location.href : undefined;
+                const url = new URL(request instanceof root.Request ? request.url : String(request && request.url || request), base);
+                url.hash = '';
+                if (ignoreSearch) {
+                    url.search = '';
+                }
+                return url.href;
+            }
+
+            function requestMethod(request) {
+                return request instanceof root.Request ? request.method : 'GET';
+            }
+
+            function findEntries(entries, request, options) {
+                options = options || {};
+                if (!options.ignoreMethod && requestMethod(request) !== 'GET') {
+                    return [];
+                }
+                const url = requestUrl(request, options.ignoreSearch);
+                return entries.filter(function(entry) { return requestUrl(entry.url, options.ignoreSearch) === url; });
+            }
+
+            function toResponse(entry) {
+                const response = new root.Response(entry.body, { status: entry.status, statusText: entry.statusText, headers: entry.headers });
+                response.url = entry.url;
+                response.type = 'basic';
+                return response;
+            }
+
+            class Cache {
+                constructor(name) {
+                    this._name = name;
+                }
+                _entries() {
+                    return loadCaches()[this._name] || [];
+                }
+                match(request, options) {
+                    return this.matchAll(request, options).then(function(responses) { return responses[0]; });
+                }
+                matchAll(request, options) {
+                    const entries = this._entries();
+                    const found = request === undefined ? entries : findEntries(entries, request, options);
+                    return Promise.resolve(found.map(toResponse));
+                }
+                add(request) {
+                    return this.addAll([request]);
+                }
+                addAll(requests) {
+                    const cache = this;
+                    return Promise.all(requests.map(function(request) {
+                        return root.fetch(request).then(function(response) {
+                            if (!response.ok) {
+                                throw new TypeError('Request for ' + requestUrl(request) + ' failed with status ' + response.status);
+                            }
+                            // Read the body now; a fetched response's body is only there until the next fetch
+                            return response.text().then(function(body) {
+                                return { request: request, response: response, body: body };
+                            });
+                        });
+                    })).then(function(fetched) {
+                        return Promise.all(fetched.map(function(item) {
+                            return cache._store(item.request, item.response, item.body);
+                        }));
+                    }).then(function() {});
+                }
+                put(request, response) {
+                    if (requestMethod(request) !== 'GET') {
+                        return Promise.reject(new TypeError('Only GET requests can be cached'));
+                    }
+                    if (!response || response.status === 206) {
+                        return Promise.reject(new TypeError('Partial or missing responses can\'t be cached'));
+                    }
+                    const cache = this;
+                    return Promise.resolve(response.text()).then(function(body) {
+                        return cache._store(request, response, body);
+                    });
+                }
+                _store(request, response, body) {
+                    const caches = loadCaches();
+                    const url = requestUrl(request);
+                    const entries = (caches[this._name] || []).filter(function(entry) { return entry.url !== url; });
+                    entries.push({
+                        url: url,
+                        status: response.status,
+                        statusText: response.statusText || '',
+                        headers: headerPairs(response.headers),
+                        body: body,
+                    });
+                    caches[this._name] = entries;
+                    saveCaches(caches);
+                }
+                delete(request, options) {
+                    const caches = loadCaches();
+                    const entries = caches[this._name] || [];
+                    const found = findEntries(entries, request, options);
+                    if (found.length === 0) {
+                        return Promise.resolve(false);
+                    }
+                    caches[this._name] = entries.filter(function(entry) { return found.indexOf(entry) < 0; });
+                    saveCaches(caches);
+                    return Promise.resolve(true);
+                }
+                keys(request, options) {
+                    const entries = this._entries();
+                    const found = request === undefined ? entries : findEntries(entries, request, options);
+                    return Promise.resolve(found.map(function(entry) { return new root.Request(entry.url); }));
+                }
+            }
+
+            class CacheStorage {
+                open(name) {
+                    name = String(name);
+                    return new Promise(function(resolve) {
+                        const caches = loadCaches();
+                        if (!caches[name]) {
+                            caches[name] = [];
+                            saveCaches(caches);
+                        }
+                        resolve(new Cache(name));
+                    });
+                }
+                has(name) {
+                    return Promise.resolve(Object.prototype.hasOwnProperty.call(loadCaches(), String(name)));
+                }
+                delete(name) {
+                    return new Promise(function(resolve) {
+                        const caches = loadCaches();
+                        if (!Object.prototype.hasOwnProperty.call(caches, String(name))) {
+                            resolve(false);
+                            return;
+                        }
+                        delete caches[String(name)];
+                        saveCaches(caches);
+                        resolve(true);
+                    });
+                }
+                keys() {
+                    return Promise.resolve(Object.keys(loadCaches()));
+                }
+                match(request, options) {
+                    options = options || {};
+                    const caches = loadCaches();
+                    const names = options.cacheName !== undefined ? [String(options.cacheName)] : Object.keys(caches);
+                    for (const name of names) {
+                        const found = findEntries(caches[name] || [], request, options);
+                        if (found.length > 0) {
+                            return Promise.resolve(toResponse(found[0]));
+                        }
+                    }
+                    return Promise.resolve(undefined);
+                }
+            }
+
+            root.Cache = Cache;
+            root.CacheStorage = CacheStorage;
+            Object.defineProperty(root, 'caches', {
+                value: new CacheStorage(),
+                configurable: true,
+                enumerable: true,
+            });
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+fn area_named(name: &str) -> Option<Area> {
+    match name {
+        "caches" => Some(Area::Caches),
+        "serviceWorkers" => Some(Area::ServiceWorkers),
+        _ => None,
+    }
+}
+
+/// `__stokesSiteData(area)` returns the JSON the document's origin keeps in `area`, or null.
+/// `__stokesSiteData(area, json)` replaces it (null clears it) and returns false when that is
+/// over the origin's quota or the document's origin saves nothing.
+unsafe extern "C" fn stokes_site_data(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let area = if argc > 0 { area_named(&js_value_to_string(safe_cx, *args.get(0))) } else { None };
+    let Some(area) = area else {
+        args.rval().set(UndefinedValue());
+        return true;
+    };
+    let origin = storage_origin();
+
+    if argc < 2 || args.get(1).is_undefined() {
+        match origin.and_then(|origin| site_storage::read_area(area, &origin)) {
+            Some(json) => args.rval().set(create_js_string(safe_cx, &json)),
+            None => args.rval().set(NullValue()),
+        }
+        return true;
+    }
+
+    let Some(origin) = origin else {
+        args.rval().set(BooleanValue(false));
+        return true;
+    };
+    let saved = if args.get(1).is_null() {
+        site_storage::clear_area(area, &origin);
+        true
+    } else {
+        let json = js_value_to_string(safe_cx, *args.get(1));
+        site_storage::write_area(area, &origin, &json).is_ok()
+    };
+    args.rval().set(BooleanValue(saved));
+    true
+}
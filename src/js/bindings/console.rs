@@ -9,6 +9,7 @@ use std::os::raw::c_uint;
 use std::ptr::NonNull;
 use mozjs::rust::wrappers2::{JS_DefineFunction, JS_DefineProperty, JS_NewPlainObject, JS_ValueToSource};
 use crate::js::helpers::ToSafeCx;
+use tracing::{debug, error, info, warn};
 
 /// Set up the console object in the JavaScript context
 pub fn setup_console(runtime: &mut JsRuntime) -> Result<(), String> {
@@ -122,7 +123,7 @@ unsafe fn js_value_to_string(cx: &mut SafeJSContext, val: JSVal) -> String {
 unsafe extern "C" fn console_log(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let safe_cx = &mut raw_cx.to_safe_cx();
     let message = unsafe { format_args(safe_cx, argc, vp) };
-    println!("[JS] {}", message);
+    info!(target: "console", "{}", message);
 
     let args = unsafe { CallArgs::from_vp(vp, argc) };
     args.rval().set(UndefinedValue());
@@ -133,7 +134,7 @@ unsafe extern "C" fn console_log(raw_cx: *mut JSContext, argc: c_uint, vp: *mut
 unsafe extern "C" fn console_error(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let safe_cx = &mut raw_cx.to_safe_cx();
     let message = unsafe { format_args(safe_cx, argc, vp) };
-    eprintln!("[JS Error] {}", message);
+    error!(target: "console", "{}", message);
 
     let args = unsafe { CallArgs::from_vp(vp, argc) };
     args.rval().set(UndefinedValue());
@@ -144,7 +145,7 @@ unsafe extern "C" fn console_error(raw_cx: *mut JSContext, argc: c_uint, vp: *mu
 unsafe extern "C" fn console_warn(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let safe_cx = &mut raw_cx.to_safe_cx();
     let message = unsafe { format_args(safe_cx, argc, vp) };
-    println!("[JS Warning] {}", message);
+    warn!(target: "console", "{}", message);
 
     let args = unsafe { CallArgs::from_vp(vp, argc) };
     args.rval().set(UndefinedValue());
@@ -155,7 +156,7 @@ unsafe extern "C" fn console_warn(raw_cx: *mut JSContext, argc: c_uint, vp: *mut
 unsafe extern "C" fn console_info(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let safe_cx = &mut raw_cx.to_safe_cx();
     let message = unsafe { format_args(safe_cx, argc, vp) };
-    println!("[JS Info] {}", message);
+    info!(target: "console", "{}", message);
 
     let args = unsafe { CallArgs::from_vp(vp, argc) };
     args.rval().set(UndefinedValue());
@@ -166,7 +167,7 @@ unsafe extern "C" fn console_info(raw_cx: *mut JSContext, argc: c_uint, vp: *mut
 unsafe extern "C" fn console_debug(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let safe_cx = &mut raw_cx.to_safe_cx();
     let message = unsafe { format_args(safe_cx, argc, vp) };
-    println!("[JS Debug] {}", message);
+    debug!(target: "console", "{}", message);
 
     let args = unsafe { CallArgs::from_vp(vp, argc) };
     args.rval().set(UndefinedValue());
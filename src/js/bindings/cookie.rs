@@ -14,7 +14,7 @@ use dbus::blocking::Connection as DbusConnection;
 #[cfg(target_os = "linux")]
 use std::time::Duration;
 use rand::Rng;
-use tracing::error;
+use tracing::{error, warn};
 
 const STORAGE_VERSION: u32 = 3;
 const SQLITE_SCHEMA_VERSION: u32 = 1;
@@ -24,6 +24,7 @@ const COOKIE_DB_FILE: &str = "cookies.sqlite";
 const LEGACY_COOKIE_FILE: &str = "cookies.json";
 const COOKIE_KEYRING_SERVICE: &str = "stokes-browser";
 const COOKIE_KEYRING_USERNAME: &str = "cookie-encryption-key-v1";
+const CLEAR_ON_EXIT_META_KEY: &str = "clear_on_exit";
 #[cfg(target_os = "linux")]
 const KWALLET_FOLDER: &str = "StokesBrowser";
 
@@ -44,13 +45,11 @@ fn get_cookies_config_dir() -> PathBuf {
     static COOKIES_CONFIG_DIR: OnceLock<PathBuf> = OnceLock::new();
     COOKIES_CONFIG_DIR
         .get_or_init(|| {
-            let config_dir = dirs::config_dir()
-                .unwrap_or_else(|| PathBuf::from("."))
-                .join("stokes-browser");
+            let config_dir = crate::profile::data_dir();
 
             if let Err(err) = std::fs::create_dir_all(&config_dir) {
                 error!(
-                    "[Cookies] failed to create config directory {}: {}",
+                    "failed to create config directory {}: {}",
                     config_dir.display(),
                     err
                 );
@@ -140,7 +139,7 @@ fn domain_matches(host: &str, cookie_domain: &str, host_only: bool) -> bool {
     host.ends_with(&format!(".{cookie_domain}"))
 }
 
-fn parse_cookie_date_to_millis(raw: &str) -> Option<u64> {
+pub(crate) fn parse_cookie_date_to_millis(raw: &str) -> Option<u64> {
     let parsed = httpdate::parse_http_date(raw).ok()?;
     parsed
         .duration_since(UNIX_EPOCH)
@@ -413,6 +412,24 @@ impl Cookie {
     pub fn parse(cookie_str: &str, document_domain: &str, document_path: &str) -> Option<Self> {
         Self::parse_with_context(cookie_str, document_domain, document_path, true)
     }
+
+    /// Parse a Set-Cookie header received for a request to `request_domain` and `request_path`
+    pub fn parse_set_cookie(
+        set_cookie_header: &str,
+        request_domain: &str,
+        request_path: &str,
+        is_secure_origin: bool,
+    ) -> Option<Self> {
+        let input = split_cookie_kv(set_cookie_header)?;
+        Cookie::from_cookie_input(
+            input,
+            request_domain,
+            request_path,
+            is_secure_origin,
+            CookieSource::Response,
+            now_millis(),
+        )
+    }
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -718,7 +735,7 @@ impl CookieCrypto {
         }
 
         let Some(key) = active_key else {
-            eprintln!("[Cookies] Warning: cookie encryption key unavailable; persistence is disabled");
+            warn!("cookie encryption key unavailable; persistence is disabled");
             return Self {
                 key: None,
                 decrypt_keys: Vec::new(),
@@ -728,7 +745,7 @@ impl CookieCrypto {
         #[cfg(target_os = "linux")]
         {
             if !Self::kwallet_write_key(&key) {
-                eprintln!("[Cookies] Warning: cookie encryption key unavailable; persistence is disabled");
+                warn!("cookie encryption key unavailable; persistence is disabled");
                 return Self {
                     key: None,
                     decrypt_keys: Vec::new(),
@@ -798,8 +815,8 @@ impl CookieStore {
         let conn = match Connection::open(&db_path) {
             Ok(conn) => conn,
             Err(err) => {
-                eprintln!(
-                    "[Cookies] Failed to open SQLite cookie DB {}: {}",
+                warn!(
+                    "Failed to open SQLite cookie DB {}: {}",
                     db_path.display(),
                     err
                 );
@@ -816,7 +833,7 @@ impl CookieStore {
             PRAGMA synchronous = NORMAL;
             ",
         ) {
-            eprintln!("[Cookies] Failed to configure SQLite pragmas: {err}");
+            warn!("Failed to configure SQLite pragmas: {err}");
         }
 
         let mut store = Self {
@@ -825,12 +842,12 @@ impl CookieStore {
         };
 
         if let Err(err) = store.initialize_schema() {
-            eprintln!("[Cookies] Failed to initialize cookie DB schema: {err}");
+            warn!("Failed to initialize cookie DB schema: {err}");
             return None;
         }
 
         if let Err(err) = store.import_legacy_json_once() {
-            eprintln!("[Cookies] Legacy cookie import failed: {err}");
+            warn!("Legacy cookie import failed: {err}");
         }
 
         Some(store)
@@ -917,8 +934,8 @@ impl CookieStore {
         let contents = match std::fs::read_to_string(&path) {
             Ok(contents) => contents,
             Err(err) => {
-                eprintln!(
-                    "[Cookies] Failed to read legacy cookie file {}: {}",
+                warn!(
+                    "Failed to read legacy cookie file {}: {}",
                     path.display(),
                     err
                 );
@@ -957,7 +974,7 @@ impl CookieStore {
             return Ok(());
         }
 
-        eprintln!("[Cookies] Failed to parse legacy cookie storage {}", path.display());
+        warn!("Failed to parse legacy cookie storage {}", path.display());
         Ok(())
     }
 
@@ -969,14 +986,14 @@ impl CookieStore {
         let mut stmt = match self.conn.prepare("DELETE FROM cookies WHERE rowid = ?1") {
             Ok(stmt) => stmt,
             Err(err) => {
-                eprintln!("[Cookies] Failed to prepare invalid-cookie cleanup statement: {err}");
+                warn!("Failed to prepare invalid-cookie cleanup statement: {err}");
                 return;
             }
         };
 
         for rowid in rowids {
             if let Err(err) = stmt.execute(params![rowid]) {
-                eprintln!("[Cookies] Failed to delete invalid cookie row {rowid}: {err}");
+                warn!("Failed to delete invalid cookie row {rowid}: {err}");
             }
         }
     }
@@ -1022,7 +1039,7 @@ impl CookieStore {
                     (Some(ciphertext), Some(nonce)) => match self.crypto.decrypt_value(ciphertext, nonce) {
                         Some(value) => value,
                         None => {
-                            eprintln!("[Cookies] Skipping encrypted cookie that could not be decrypted");
+                            warn!("Skipping encrypted cookie that could not be decrypted");
                             invalid_rowids.push(rowid);
                             continue;
                         }
@@ -1199,7 +1216,7 @@ impl CookieJar {
                     .collect(),
             ),
             Err(err) => {
-                eprintln!("[Cookies] Failed to load cookies from SQLite: {err}");
+                warn!("Failed to load cookies from SQLite: {err}");
                 Vec::new()
             }
         };
@@ -1232,7 +1249,7 @@ impl CookieJar {
         }
 
         if let Err(err) = store.replace_all(&persisted) {
-            eprintln!("[Cookies] Failed to persist cookies to SQLite: {err}");
+            warn!("Failed to persist cookies to SQLite: {err}");
         }
     }
 
@@ -1357,11 +1374,49 @@ impl CookieJar {
         self.cookies.clear();
         if let Some(store) = self.store.as_mut() {
             if let Err(err) = store.clear() {
-                eprintln!("[Cookies] Failed to clear SQLite cookie storage: {err}");
+                warn!("Failed to clear SQLite cookie storage: {err}");
             }
         }
     }
 
+    /// Every cookie in the jar that hasn't expired
+    pub fn cookies(&mut self) -> &[Cookie] {
+        self.remove_expired();
+        &self.cookies
+    }
+
+    /// Delete the cookie named `name` set for exactly `domain` and `path`
+    pub fn remove_cookie(&mut self, domain: &str, name: &str, path: &str) {
+        self.cookies
+            .retain(|cookie| !(cookie.domain == domain && cookie.name == name && cookie.path == path));
+        self.save_to_disk();
+    }
+
+    /// Delete every cookie set for `domain` or one of its subdomains
+    pub fn remove_site(&mut self, domain: &str) {
+        let domain = normalize_host(domain);
+        self.cookies
+            .retain(|cookie| !domain_matches(&cookie.domain, &domain, false));
+        self.save_to_disk();
+    }
+
+    /// Whether all cookies are deleted when the browser exits
+    pub fn clear_on_exit(&self) -> bool {
+        self.store
+            .as_ref()
+            .and_then(|store| store.meta_value(CLEAR_ON_EXIT_META_KEY).ok().flatten())
+            .is_some_and(|value| value == "1")
+    }
+
+    pub fn set_clear_on_exit(&mut self, enabled: bool) {
+        let Some(store) = self.store.as_ref() else {
+            return;
+        };
+        if let Err(err) = store.set_meta_value(CLEAR_ON_EXIT_META_KEY, if enabled { "1" } else { "0" }) {
+            warn!("Failed to save the clear-on-exit cookie setting: {err}");
+        }
+    }
+
     pub fn get_cookie_header(&mut self, domain: &str, path: &str, is_secure: bool) -> String {
         self.get_cookies(domain, path, true, is_secure)
             .into_iter()
@@ -1377,23 +1432,9 @@ impl CookieJar {
         request_path: &str,
         is_secure_origin: bool,
     ) {
-        let now = now_millis();
-        let Some(input) = split_cookie_kv(set_cookie_header) else {
-            return;
-        };
-
-        let Some(cookie) = Cookie::from_cookie_input(
-            input,
-            request_domain,
-            request_path,
-            is_secure_origin,
-            CookieSource::Response,
-            now,
-        ) else {
-            return;
-        };
-
-        self.set_cookie(cookie);
+        if let Some(cookie) = Cookie::parse_set_cookie(set_cookie_header, request_domain, request_path, is_secure_origin) {
+            self.set_cookie(cookie);
+        }
     }
 }
 
@@ -1445,6 +1486,41 @@ pub fn clear_all_cookies() {
     });
 }
 
+/// Apply a change made from the stokes://cookies page. Other tabs save cookies to the same
+/// database, so the change is made to a jar freshly loaded from it; this thread's jar gets it too,
+/// so it doesn't write the removed cookies back.
+pub fn manage_cookies(change: impl Fn(&mut CookieJar)) {
+    let mut saved = CookieJar::load_from_disk();
+    change(&mut saved);
+
+    COOKIE_JAR_INITIALIZED.with(|initialized| {
+        if *initialized.borrow() {
+            COOKIE_JAR.with(|jar| {
+                let mut jar = jar.borrow_mut();
+                // Only the in-memory list: saving it would undo other tabs' cookies
+                let store = jar.store.take();
+                change(&mut jar);
+                jar.store = store;
+            });
+        }
+    });
+}
+
+/// The cookies saved to disk, for the stokes://cookies page. Session cookies only live in the tab
+/// process that received them, so they aren't included.
+pub fn saved_cookies() -> Vec<Cookie> {
+    CookieJar::load_from_disk().cookies().to_vec()
+}
+
+/// Delete every cookie when the browser exits, if the user asked for that on stokes://cookies
+pub fn clear_if_requested_on_exit() {
+    let mut jar = CookieJar::load_from_disk();
+    if jar.clear_on_exit() {
+        tracing::info!("Clearing cookies on exit");
+        jar.clear();
+    }
+}
+
 pub fn set_document_url(url: url::Url) {
     let effective_url = if url.scheme() == "data" || url.host_str().is_none() {
         url::Url::parse("http://localhost/").expect("localhost URL should parse")
@@ -1570,6 +1646,19 @@ mod tests {
         assert!(header.is_empty());
     }
 
+    #[test]
+    fn test_remove_site_removes_subdomain_cookies() {
+        let mut jar = CookieJar::new();
+        jar.set_from_header("a=1; Path=/", "example.com", "/", true);
+        jar.set_from_header("b=2; Path=/", "mail.example.com", "/", true);
+        jar.set_from_header("c=3; Path=/", "other.test", "/", true);
+        jar.remove_cookie("other.test", "c", "/");
+        assert_eq!(jar.cookies().len(), 2);
+
+        jar.remove_site("example.com");
+        assert!(jar.cookies().is_empty());
+    }
+
     #[test]
     fn test_secure_cookie_not_sent_on_http() {
         let mut jar = CookieJar::new();
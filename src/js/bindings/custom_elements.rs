@@ -1,22 +1,24 @@
-use crate::dom::NodeData;
+use crate::dom::{AttributeMap, NodeData};
 use crate::js::bindings::dom_bindings::DOM_REF;
-use crate::js::bindings::element_bindings;
+use crate::js::bindings::{element_bindings, registry};
 use crate::js::helpers::{create_js_string, define_function, get_node_id_from_value, js_value_to_string, ToSafeCx};
 use crate::js::jsapi::promise::PersistentRooted;
-use crate::js::JsRuntime;
+use crate::js::{JsResult, JsRuntime};
+use html5ever::ns;
+use markup5ever::{LocalName, QualName};
 use mozjs::context::JSContext as SafeJSContext;
 use mozjs::jsapi::{
     CallArgs, HandleValueArray, JSContext, JSObject, JS_DefineProperty, JS_GetProperty,
     JS_NewPlainObject, JSPROP_ENUMERATE,
 };
-use mozjs::jsval::{BooleanValue, JSVal, ObjectValue, UndefinedValue};
+use mozjs::jsval::{BooleanValue, JSVal, NullValue, ObjectValue, UndefinedValue};
 use mozjs::rooted;
 use mozjs::rust::ValueArray;
 use mozjs::rust::wrappers2::{
     JS_CallFunctionValue, JS_ClearPendingException, NewPromiseObject, ResolvePromise,
 };
 use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::os::raw::c_uint;
 use std::ptr;
 
@@ -32,7 +34,8 @@ struct CustomElementDefinition {
 
 struct CustomElementsState {
     definitions_by_name: HashMap<String, CustomElementDefinition>,
-    ctor_ptrs: HashSet<usize>,
+    /// Defined constructors and the names they were defined with
+    ctor_ptrs: HashMap<usize, String>,
     pending_when_defined: HashMap<String, Vec<PersistentRooted>>,
 }
 
@@ -40,7 +43,7 @@ impl CustomElementsState {
     fn new() -> Self {
         Self {
             definitions_by_name: HashMap::new(),
-            ctor_ptrs: HashSet::new(),
+            ctor_ptrs: HashMap::new(),
             pending_when_defined: HashMap::new(),
         }
     }
@@ -209,6 +212,26 @@ unsafe fn set_object_prototype(cx: &mut SafeJSContext, obj: *mut JSObject, proto
     }
 }
 
+/// Whether `target` is `obj` or one of its prototypes
+unsafe fn prototype_chain_contains(cx: &mut SafeJSContext, obj: *mut JSObject, target: *mut JSObject) -> bool {
+    let raw_cx = cx.raw_cx();
+    let proto_name = std::ffi::CString::new("__proto__").unwrap();
+    rooted!(in(raw_cx) let mut current = obj);
+    for _ in 0..64 {
+        if current.get() == target {
+            return true;
+        }
+        rooted!(in(raw_cx) let mut next = UndefinedValue());
+        if !JS_GetProperty(raw_cx, current.handle().into(), proto_name.as_ptr(), next.handle_mut().into())
+            || !next.get().is_object()
+        {
+            return false;
+        }
+        current.set(next.get().to_object());
+    }
+    false
+}
+
 unsafe fn bridge_custom_prototype_to_wrapper_prototype(
     cx: &mut SafeJSContext,
     element_obj: *mut JSObject,
@@ -228,7 +251,7 @@ unsafe fn bridge_custom_prototype_to_wrapper_prototype(
     }
 
     let wrapper_proto = wrapper_proto_val.get().to_object();
-    if wrapper_proto == custom_proto {
+    if prototype_chain_contains(cx, custom_proto, wrapper_proto) {
         return;
     }
 
@@ -333,20 +356,26 @@ unsafe fn set_hidden_definition_name(cx: &mut SafeJSContext, obj: *mut JSObject,
     JS_DefineProperty(raw_cx, rooted_obj.handle().into(), prop.as_ptr(), name_val.handle().into(), 0);
 }
 
-unsafe fn invoke_connected_callback(cx: &mut SafeJSContext, element: *mut JSObject) {
+/// Call one of the global hooks the lifecycle script installs, with the global as `this`
+unsafe fn call_lifecycle_hook<const N: usize>(cx: &mut SafeJSContext, hook: &str, args: [JSVal; N]) {
+    use mozjs::jsapi::CurrentGlobalOrNull;
+
     let raw_cx = cx.raw_cx();
-    rooted!(in(raw_cx) let element_rooted = element);
-    rooted!(in(raw_cx) let mut callback = UndefinedValue());
-    let callback_name = std::ffi::CString::new("connectedCallback").unwrap();
-    if !JS_GetProperty(raw_cx, element_rooted.handle().into(), callback_name.as_ptr(), callback.handle_mut().into()) || !callback.get().is_object() {
+    rooted!(in(raw_cx) let args = ValueArray::<N>::new(args));
+    rooted!(in(raw_cx) let global = CurrentGlobalOrNull(raw_cx));
+    if global.get().is_null() {
+        return;
+    }
+    rooted!(in(raw_cx) let mut hook_val = UndefinedValue());
+    let hook_name = std::ffi::CString::new(hook).unwrap();
+    if !JS_GetProperty(raw_cx, global.handle().into(), hook_name.as_ptr(), hook_val.handle_mut().into()) || !hook_val.get().is_object() {
         return;
     }
-    rooted!(in(raw_cx) let args = ValueArray::<0usize>::new([]));
     rooted!(in(raw_cx) let mut rval = UndefinedValue());
     if !JS_CallFunctionValue(
         cx,
-        element_rooted.handle().into(),
-        callback.handle().into(),
+        global.handle().into(),
+        hook_val.handle().into(),
         &HandleValueArray::from(&args),
         rval.handle_mut().into(),
     ) {
@@ -354,6 +383,25 @@ unsafe fn invoke_connected_callback(cx: &mut SafeJSContext, element: *mut JSObje
     }
 }
 
+unsafe fn invoke_lifecycle_callback(cx: &mut SafeJSContext, element: *mut JSObject, callback: &str) {
+    let raw_cx = cx.raw_cx();
+    rooted!(in(raw_cx) let element = element);
+    rooted!(in(raw_cx) let callback = create_js_string(cx, callback));
+    call_lifecycle_hook(cx, "__stokesCustomElementCallback", [ObjectValue(element.get()), callback.get()]);
+}
+
+/// Run connectedCallback or disconnectedCallback when an upgraded element's connectedness changed
+/// since it was last seen
+unsafe fn update_connected_state(cx: &mut SafeJSContext, element: *mut JSObject, connected_now: bool) {
+    let was_connected = get_hidden_connected(cx, element);
+    if connected_now == was_connected {
+        return;
+    }
+    set_hidden_connected(cx, element, connected_now);
+    let callback = if connected_now { "connectedCallback" } else { "disconnectedCallback" };
+    invoke_lifecycle_callback(cx, element, callback);
+}
+
 unsafe fn upgrade_node_by_id(cx: &mut SafeJSContext, node_id: usize, forced_definition: Option<&str>) {
     let def_name = forced_definition
         .map(|v| v.to_string())
@@ -388,13 +436,7 @@ unsafe fn upgrade_node_by_id(cx: &mut SafeJSContext, node_id: usize, forced_defi
     let connected_now = node_is_connected(node_id);
     let previous_name = get_hidden_definition_name(cx, element_obj.get());
     if previous_name.as_deref() == Some(resolved_name.as_str()) {
-        let was_connected = get_hidden_connected(cx, element_obj.get());
-        if connected_now && !was_connected {
-            invoke_connected_callback(cx, element_obj.get());
-            set_hidden_connected(cx, element_obj.get(), true);
-        } else if !connected_now && was_connected {
-            set_hidden_connected(cx, element_obj.get(), false);
-        }
+        update_connected_state(cx, element_obj.get(), connected_now);
         return;
     }
 
@@ -411,21 +453,14 @@ unsafe fn upgrade_node_by_id(cx: &mut SafeJSContext, node_id: usize, forced_defi
         JSPROP_ENUMERATE as u32,
     );
 
-    rooted!(in(raw_cx) let args = ValueArray::<0usize>::new([]));
-    rooted!(in(raw_cx) let mut call_rval = UndefinedValue());
-    if !JS_CallFunctionValue(
-        cx,
-        element_obj.handle().into(),
-        ctor_val.handle().into(),
-        &HandleValueArray::from(&args),
-        call_rval.handle_mut().into(),
-    ) {
-        JS_ClearPendingException(cx);
-    }
-
+    // Mark the element first, so it isn't upgraded again if its constructor moves it
     set_hidden_definition_name(cx, element_obj.get(), &resolved_name);
-    invoke_connected_callback(cx, element_obj.get());
     set_hidden_connected(cx, element_obj.get(), connected_now);
+    call_lifecycle_hook(
+        cx,
+        "__stokesUpgradeCustomElement",
+        [ObjectValue(element_obj.get()), ctor_val.get(), BooleanValue(connected_now)],
+    );
 }
 
 unsafe fn upgrade_subtree_by_node_id(cx: &mut SafeJSContext, root_id: usize, forced_definition: Option<&str>) {
@@ -439,6 +474,65 @@ pub(crate) unsafe fn custom_elements_upgrade_for_node(cx: &mut SafeJSContext, ro
     upgrade_subtree_by_node_id(cx, root_id, None);
 }
 
+/// Run disconnectedCallback for the upgraded custom elements in a subtree that was just removed
+/// from the document
+pub(crate) unsafe fn custom_elements_disconnect_for_node(cx: &mut SafeJSContext, root_id: usize) {
+    for node_id in collect_element_subtree_ids(root_id) {
+        if definition_name_for_node(node_id).is_none() {
+            continue;
+        }
+        let Some(element) = registry::node_wrapper(node_id) else {
+            continue;
+        };
+        rooted!(in(cx.raw_cx()) let element = element);
+        if get_hidden_definition_name(cx, element.get()).is_some() {
+            update_connected_state(cx, element.get(), node_is_connected(node_id));
+        }
+    }
+}
+
+/// The value of an attribute, to pass as the old value to `custom_elements_attribute_changed`
+pub(crate) fn custom_elements_attribute_value(node_id: usize, name: &str) -> Option<String> {
+    DOM_REF.with(|dom_ref| {
+        let dom = unsafe { &*(*dom_ref.borrow())? };
+        dom.get_node(node_id)?.data.element()?.attr(LocalName::from(name)).map(str::to_string)
+    })
+}
+
+/// Run attributeChangedCallback on an upgraded custom element when one of its observed
+/// attributes was set or removed
+pub(crate) unsafe fn custom_elements_attribute_changed(cx: &mut SafeJSContext, node_id: usize, name: &str, old_value: Option<String>) {
+    let Some(def_name) = definition_name_for_node(node_id) else {
+        return;
+    };
+    let Some(ctor) = CUSTOM_ELEMENTS_STATE.with(|state| state.borrow().definitions_by_name.get(&def_name).map(|def| def.ctor.get())) else {
+        return;
+    };
+    let Some(element) = registry::node_wrapper(node_id) else {
+        return;
+    };
+    let raw_cx = cx.raw_cx();
+    rooted!(in(raw_cx) let element = element);
+    rooted!(in(raw_cx) let ctor = ctor);
+    if get_hidden_definition_name(cx, element.get()).as_deref() != Some(def_name.as_str()) {
+        return;
+    }
+
+    let new_value = custom_elements_attribute_value(node_id, name);
+    let string_or_null = |cx: &mut SafeJSContext, value: Option<String>| match value {
+        Some(value) => create_js_string(cx, &value),
+        None => NullValue(),
+    };
+    rooted!(in(raw_cx) let name = create_js_string(cx, name));
+    rooted!(in(raw_cx) let old_value = string_or_null(cx, old_value));
+    rooted!(in(raw_cx) let new_value = string_or_null(cx, new_value));
+    call_lifecycle_hook(
+        cx,
+        "__stokesCustomElementAttributeChanged",
+        [ObjectValue(element.get()), ObjectValue(ctor.get()), name.get(), old_value.get(), new_value.get()],
+    );
+}
+
 unsafe extern "C" fn custom_element_registry_constructor(_raw_cx: *mut JSContext, _argc: c_uint, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, 0);
     args.rval().set(UndefinedValue());
@@ -492,7 +586,7 @@ unsafe extern "C" fn custom_elements_define(raw_cx: *mut JSContext, argc: c_uint
 
     CUSTOM_ELEMENTS_STATE.with(|state| {
         let mut state = state.borrow_mut();
-        if state.definitions_by_name.contains_key(&name) || state.ctor_ptrs.contains(&ctor_ptr) {
+        if state.definitions_by_name.contains_key(&name) || state.ctor_ptrs.contains_key(&ctor_ptr) {
             return;
         }
 
@@ -510,7 +604,7 @@ unsafe extern "C" fn custom_elements_define(raw_cx: *mut JSContext, argc: c_uint
                 prototype: prototype_root,
             },
         );
-        state.ctor_ptrs.insert(ctor_ptr);
+        state.ctor_ptrs.insert(ctor_ptr, name.clone());
         pending_promises = state.pending_when_defined.remove(&name).unwrap_or_default();
         did_insert = true;
     });
@@ -608,6 +702,59 @@ unsafe extern "C" fn custom_elements_upgrade(raw_cx: *mut JSContext, argc: c_uin
     true
 }
 
+/// `__stokesCreateCustomElement(constructor)`: a new element for an autonomous custom element
+/// constructor called with `new` rather than to upgrade an element, or null when it isn't defined
+unsafe extern "C" fn custom_elements_create(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    args.rval().set(NullValue());
+    if argc == 0 || !args.get(0).is_object() {
+        return true;
+    }
+
+    let ctor_ptr = args.get(0).to_object() as usize;
+    let definition = CUSTOM_ELEMENTS_STATE.with(|state| {
+        let state = state.borrow();
+        let def = state.definitions_by_name.get(state.ctor_ptrs.get(&ctor_ptr)?)?;
+        def.extends_tag.is_none().then(|| (def.name.clone(), def.prototype.get()))
+    });
+    let Some((name, prototype_obj)) = definition else {
+        return true;
+    };
+    let node_id = DOM_REF.with(|dom_ref| {
+        let dom = &mut *(*dom_ref.borrow())?;
+        Some(dom.create_element(QualName::new(None, ns!(html), LocalName::from(name.as_str())), AttributeMap::empty()))
+    });
+    let Some(node_id) = node_id else {
+        return true;
+    };
+    let Ok(element_val) = element_bindings::create_js_element_by_dom_id(safe_cx, node_id) else {
+        return true;
+    };
+    if !element_val.is_object() {
+        return true;
+    }
+
+    rooted!(in(raw_cx) let element_obj = element_val.to_object());
+    rooted!(in(raw_cx) let prototype_obj = prototype_obj);
+    ensure_instance_attribute_methods(safe_cx, element_obj.get());
+    bridge_custom_prototype_to_wrapper_prototype(safe_cx, element_obj.get(), prototype_obj.get());
+
+    rooted!(in(raw_cx) let ctor_val = *args.get(0));
+    let ctor_prop = std::ffi::CString::new("constructor").unwrap();
+    JS_DefineProperty(
+        raw_cx,
+        element_obj.handle().into(),
+        ctor_prop.as_ptr(),
+        ctor_val.handle().into(),
+        JSPROP_ENUMERATE as u32,
+    );
+    set_hidden_definition_name(safe_cx, element_obj.get(), &name);
+    set_hidden_connected(safe_cx, element_obj.get(), false);
+    args.rval().set(ObjectValue(element_obj.get()));
+    true
+}
+
 pub(crate) fn setup_custom_elements(runtime: &mut JsRuntime) -> Result<(), String> {
     runtime.do_with_jsapi(|cx, global| unsafe {
         let raw_cx = cx.raw_cx();
@@ -626,6 +773,7 @@ pub(crate) fn setup_custom_elements(runtime: &mut JsRuntime) -> Result<(), Strin
         define_function(cx, registry.get(), "get", Some(custom_elements_get), 1)?;
         define_function(cx, registry.get(), "whenDefined", Some(custom_elements_when_defined), 1)?;
         define_function(cx, registry.get(), "upgrade", Some(custom_elements_upgrade), 1)?;
+        define_function(cx, global_obj, "__stokesCreateCustomElement", Some(custom_elements_create), 1)?;
 
         rooted!(in(raw_cx) let registry_val = ObjectValue(registry.get()));
         rooted!(in(raw_cx) let global_rooted = global_obj);
@@ -644,3 +792,179 @@ pub(crate) fn setup_custom_elements(runtime: &mut JsRuntime) -> Result<(), Strin
     })
 }
 
+
+/// Install the JS side of custom elements, once the element constructors are in place: a
+/// constructible HTMLElement for custom element classes to extend, define() argument checks and
+/// observedAttributes, and the hooks upgrades and lifecycle callbacks run through. Customized
+/// built-ins (`extends`) get their prototype and callbacks but not their constructor, since the
+/// built-in interfaces they'd extend can't be constructed. Only setAttribute and removeAttribute
+/// run attributeChangedCallback, and moving a connected element doesn't disconnect it first.
+pub(crate) fn setup_custom_element_lifecycle_deferred(runtime: &mut JsRuntime) -> JsResult<()> {
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            const registry = root.customElements;
+            if (!registry || typeof registry.define !== 'function' || typeof root.__stokesUpgradeCustomElement === 'function') {
+                return;
+            }
+
+            function domError(message, name) {
+                if (typeof root.DOMException === 'function') {
+                    return new root.DOMException(message, name);
+                }
+                const error = new Error(message);
+                error.name = name;
+                return error;
+            }
+
+            const RESERVED_NAMES = [
+                'annotation-xml', 'color-profile', 'font-face', 'font-face-src',
+                'font-face-uri', 'font-face-format', 'font-face-name', 'missing-glyph',
+            ];
+            function isValidName(name) {
+                return /^[a-z][a-z0-9._]*-[a-z0-9._-]*$/.test(name) && RESERVED_NAMES.indexOf(name) === -1;
+            }
+
+            // Definitions by constructor: { name, extends, observed }
+            const definitions = new Map();
+            // Elements being upgraded, which HTMLElement() hands back instead of creating one
+            const constructing = [];
+            const ALREADY_CONSTRUCTED = {};
+
+            const defineNative = registry.define;
+            registry.define = function define(name, constructor, options) {
+                const fail = "Failed to execute 'define' on 'CustomElementRegistry': ";
+                if (arguments.length < 2) {
+                    throw new TypeError(fail + '2 arguments required, but only ' + arguments.length + ' present.');
+                }
+                name = String(name);
+                if (typeof constructor !== 'function') {
+                    throw new TypeError(fail + "parameter 2 is not of type 'CustomElementConstructor'.");
+                }
+                if (!isValidName(name)) {
+                    throw domError(fail + '"' + name + '" is not a valid custom element name', 'SyntaxError');
+                }
+                if (registry.get(name) !== undefined) {
+                    throw domError(fail + 'the name "' + name + '" has already been used with this registry', 'NotSupportedError');
+                }
+                if (definitions.has(constructor)) {
+                    throw domError(fail + 'this constructor has already been used with this registry', 'NotSupportedError');
+                }
+                const extendsName = options && options.extends != null ? String(options.extends).toLowerCase() : null;
+                if (extendsName !== null && isValidName(extendsName)) {
+                    throw domError(fail + '"' + extendsName + '" is a valid custom element name', 'NotSupportedError');
+                }
+                const prototype = constructor.prototype;
+                if (prototype === null || typeof prototype !== 'object') {
+                    throw new TypeError(fail + "The prototype of the constructor is not an object.");
+                }
+                let observed = [];
+                if (typeof prototype.attributeChangedCallback === 'function' && constructor.observedAttributes != null) {
+                    observed = Array.from(constructor.observedAttributes, String);
+                }
+                definitions.set(constructor, { name, extends: extendsName, observed: new Set(observed) });
+                defineNative.call(registry, name, constructor, options);
+            };
+
+            const BaseHTMLElement = root.HTMLElement;
+            function HTMLElement() {
+                const target = new.target;
+                if (!target || !definitions.has(target) || definitions.get(target).extends !== null) {
+                    throw new TypeError('Illegal constructor');
+                }
+                const top = constructing[constructing.length - 1];
+                let element;
+                if (top && top.constructor === target) {
+                    if (top.element === ALREADY_CONSTRUCTED) {
+                        throw domError("Failed to construct 'HTMLElement': This instance is already constructed", 'InvalidStateError');
+                    }
+                    element = top.element;
+                    top.element = ALREADY_CONSTRUCTED;
+                } else {
+                    element = root.__stokesCreateCustomElement(target);
+                    if (!element) {
+                        throw new TypeError('Illegal constructor');
+                    }
+                }
+                Object.setPrototypeOf(element, target.prototype);
+                return element;
+            }
+            if (typeof BaseHTMLElement === 'function') {
+                HTMLElement.prototype = BaseHTMLElement.prototype;
+                Object.defineProperty(HTMLElement.prototype, 'constructor', {
+                    value: HTMLElement,
+                    writable: true,
+                    configurable: true,
+                    enumerable: false,
+                });
+                // Constructors that inherited statics from the old HTMLElement do from this one
+                for (const key of Object.getOwnPropertyNames(root)) {
+                    if (!/^HTML\w*Element$/.test(key)) {
+                        continue;
+                    }
+                    try {
+                        const value = root[key];
+                        if (typeof value === 'function' && Object.getPrototypeOf(value) === BaseHTMLElement) {
+                            Object.setPrototypeOf(value, HTMLElement);
+                        }
+                    } catch (_) {}
+                }
+            }
+            root.HTMLElement = HTMLElement;
+
+            function invoke(element, callbackName, args) {
+                const callback = element[callbackName];
+                if (typeof callback !== 'function') {
+                    return;
+                }
+                try {
+                    callback.apply(element, args);
+                } catch (error) {
+                    console.error(error);
+                }
+            }
+
+            root.__stokesUpgradeCustomElement = function(element, constructor, connected) {
+                const definition = definitions.get(constructor);
+                if (definition && definition.extends === null) {
+                    const entry = { constructor, element };
+                    constructing.push(entry);
+                    try {
+                        const result = Reflect.construct(constructor, []);
+                        if (result !== element) {
+                            throw domError("Failed to upgrade '" + definition.name + "': the constructor didn't return the element being upgraded", 'InvalidStateError');
+                        }
+                    } catch (error) {
+                        console.error(error);
+                        return;
+                    } finally {
+                        constructing.splice(constructing.indexOf(entry), 1);
+                    }
+                } else {
+                    Object.setPrototypeOf(element, constructor.prototype);
+                }
+                if (definition) {
+                    for (const name of definition.observed) {
+                        if (element.hasAttribute(name)) {
+                            invoke(element, 'attributeChangedCallback', [name, null, element.getAttribute(name), null]);
+                        }
+                    }
+                }
+                if (connected) {
+                    invoke(element, 'connectedCallback', []);
+                }
+            };
+            root.__stokesCustomElementCallback = function(element, callbackName) {
+                invoke(element, callbackName, []);
+            };
+            root.__stokesCustomElementAttributeChanged = function(element, constructor, name, oldValue, newValue) {
+                const definition = definitions.get(constructor);
+                if (definition && definition.observed.has(name)) {
+                    invoke(element, 'attributeChangedCallback', [name, oldValue, newValue, null]);
+                }
+            };
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
@@ -0,0 +1,44 @@
+// Dialog callback system for JavaScript alert(), confirm() and prompt()
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A dialog a page opened
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum JsDialog {
+    Alert(String),
+    Confirm(String),
+    Prompt { message: String, default: String },
+}
+
+/// Callback function type for dialogs. It is given the page's origin and blocks until the user
+/// answers: `Some` with the entered text (empty for alert and confirm) when they accept, `None`
+/// when they dismiss the dialog.
+pub type DialogCallback = Box<dyn Fn(&str, JsDialog) -> Option<String>>;
+
+/// Global dialog callback storage
+thread_local! {
+    static DIALOG_CALLBACK: RefCell<Option<Rc<DialogCallback>>> = RefCell::new(None);
+}
+
+/// Set the dialog callback function
+pub fn set_dialog_callback<F>(callback: F)
+where
+    F: Fn(&str, JsDialog) -> Option<String> + 'static,
+{
+    DIALOG_CALLBACK.set(Some(Rc::new(Box::new(callback))));
+}
+
+/// Show `dialog` for a page at `origin` and wait for the user's answer
+pub fn run_dialog(origin: &str, dialog: JsDialog) -> Option<String> {
+    // Cloned out so the storage isn't borrowed while the callback blocks
+    let callback = DIALOG_CALLBACK.with(|cb| cb.borrow().clone());
+    match callback {
+        Some(callback) => callback(origin, dialog),
+        None => {
+            // Without a callback there is nobody to ask, so dialogs are dismissed
+            tracing::debug!("Dismissing {:?} with no dialog callback set", dialog);
+            None
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::js::bindings::custom_elements::custom_elements_upgrade_for_node;
+use crate::js::bindings::registry;
 use crate::js::bindings::dom_bindings::{CURRENT_SCRIPT_NODE_ID, DOM_REF};
 use crate::js::bindings::cookie::{
     ensure_cookie_jar_initialized, COOKIE_JAR, DOCUMENT_URL,
@@ -62,6 +63,9 @@ const DOCUMENT_INTERNAL_METHODS: &[DocumentMethodBinding] = &[
     ("__getBody", Some(document_get_body), 0),
     ("__setBody", Some(document_set_body), 1),
     ("__getCurrentScript", Some(document_get_current_script), 0),
+    ("__getTitle", Some(document_get_title), 0),
+    ("__getBaseURI", Some(document_get_base_uri), 0),
+    ("__setTitle", Some(document_set_title), 1),
 ];
 
 unsafe fn define_methods(
@@ -145,6 +149,8 @@ unsafe fn setup_document_property_accessors(
     define_js_property_getter(cx, document_obj, "head", "__getHead")?;
     define_js_property_accessor(cx, document_obj, "body", "__getBody", "__setBody")?;
     define_js_property_getter(cx, document_obj, "currentScript", "__getCurrentScript")?;
+    define_js_property_accessor(cx, document_obj, "title", "__getTitle", "__setTitle")?;
+    define_js_property_getter(cx, document_obj, "baseURI", "__getBaseURI")?;
     Ok(())
 }
 
@@ -254,7 +260,7 @@ pub(crate) unsafe fn setup_document_bindings(
         JSPROP_ENUMERATE as u32,
     );
 
-    let base_url_str = DOM_REF.with(|dom_ref| {
+    let document_url = DOM_REF.with(|dom_ref| {
         dom_ref
             .borrow()
             .as_ref()
@@ -265,9 +271,8 @@ pub(crate) unsafe fn setup_document_bindings(
             })
             .unwrap_or_default()
     });
-    set_string_property(cx, document.get(), "baseURI", &base_url_str)?;
-    set_string_property(cx, document.get(), "URL", &base_url_str)?;
-    set_string_property(cx, document.get(), "documentURI", &base_url_str)?;
+    set_string_property(cx, document.get(), "URL", &document_url)?;
+    set_string_property(cx, document.get(), "documentURI", &document_url)?;
     set_int_property(cx, document.get(), "nodeType", 9)?;
     set_string_property(cx, document.get(), "nodeName", "#document")?;
     set_string_property(cx, document.get(), "readyState", "complete")?;
@@ -480,6 +485,53 @@ pub(crate) unsafe extern "C" fn document_set_body(raw_cx: *mut mozjs::jsapi::JSC
     true
 }
 
+pub(crate) unsafe extern "C" fn document_get_title(raw_cx: *mut mozjs::jsapi::JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+
+    let title = DOM_REF.with(|dom_ref| {
+        let dom_ptr = (*dom_ref.borrow())?;
+        let dom = unsafe { &*dom_ptr };
+        Some(dom.document_title())
+    });
+
+    args.rval().set(create_js_string(safe_cx, &title.unwrap_or_default()));
+    true
+}
+
+/// `document.baseURI`: the URL relative URLs resolve against, which a `<base href>` can change
+pub(crate) unsafe extern "C" fn document_get_base_uri(raw_cx: *mut mozjs::jsapi::JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+
+    let base_uri = DOM_REF.with(|dom_ref| {
+        let dom_ptr = (*dom_ref.borrow())?;
+        let dom = unsafe { &*dom_ptr };
+        Some(dom.url.base_url().to_string())
+    });
+
+    args.rval().set(create_js_string(safe_cx, &base_uri.unwrap_or_default()));
+    true
+}
+
+pub(crate) unsafe extern "C" fn document_set_title(raw_cx: *mut mozjs::jsapi::JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+
+    let title = if argc > 0 { js_value_to_string(safe_cx, *args.get(0)) } else { "undefined".to_string() };
+    trace!("[JS] document.title = '{}'", title);
+
+    DOM_REF.with(|dom_ref| {
+        if let Some(dom_ptr) = *dom_ref.borrow() {
+            let dom = unsafe { &mut *dom_ptr };
+            dom.set_document_title(&title);
+        }
+    });
+
+    args.rval().set(UndefinedValue());
+    true
+}
+
 pub(crate) unsafe extern "C" fn document_get_current_script(raw_cx: *mut mozjs::jsapi::JSContext, _argc: c_uint, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, 0);
     let safe_cx = &mut raw_cx.to_safe_cx();
@@ -826,16 +878,7 @@ pub(crate) unsafe extern "C" fn document_create_text_node(raw_cx: *mut mozjs::js
         let _ = set_string_property(safe_cx, text_node.get(), "nodeValue", &text);
 
         if let Some(node_id) = text_node_id {
-            rooted!(in(raw_cx) let node_id_val = mozjs::jsval::DoubleValue(node_id as f64));
-            rooted!(in(raw_cx) let text_rooted = text_node.get());
-            let node_id_name = std::ffi::CString::new("__nodeId").unwrap();
-            JS_DefineProperty(
-                raw_cx,
-                text_rooted.handle().into(),
-                node_id_name.as_ptr(),
-                node_id_val.handle().into(),
-                0,
-            );
+            registry::set_node_id(safe_cx, text_node.get(), node_id);
         }
 
         rooted!(in(raw_cx) let null_val = NullValue());
@@ -907,16 +950,7 @@ pub(crate) unsafe extern "C" fn document_create_comment(raw_cx: *mut mozjs::jsap
     let _ = set_string_property(safe_cx, comment_node.get(), "textContent", &comment_text);
 
     if let Some(node_id) = comment_node_id {
-        rooted!(in(raw_cx) let node_id_val = mozjs::jsval::DoubleValue(node_id as f64));
-        rooted!(in(raw_cx) let comment_rooted = comment_node.get());
-        let node_id_name = std::ffi::CString::new("__nodeId").unwrap();
-        JS_DefineProperty(
-            raw_cx,
-            comment_rooted.handle().into(),
-            node_id_name.as_ptr(),
-            node_id_val.handle().into(),
-            0,
-        );
+        registry::set_node_id(safe_cx, comment_node.get(), node_id);
     }
 
     rooted!(in(raw_cx) let null_val = NullValue());
@@ -44,6 +44,7 @@ pub fn setup_dom_bindings(
     unsafe {
         let dom = &*document_root;
         let url: url::Url = (&dom.url).into();
+        storage::load_local_storage(&url);
         set_document_url(url);
     }
 
@@ -135,27 +136,41 @@ unsafe fn setup_data_layer(cx: &mut SafeJSContext, global: *mut JSObject) -> Res
 
 #[cfg(test)]
 mod tests {
-    use crate::js::bindings::window::{decode_atob_binary_string, evaluate_media_query, normalize_atob_input};
+    use crate::js::bindings::window::{decode_atob_binary_string, evaluate_media_query, normalize_atob_input, MediaContext};
+
+    fn screen(width: f32, height: f32) -> MediaContext {
+        MediaContext { width, height, dpr: 1.0, dark: false, reduced_motion: false }
+    }
 
     #[test]
     fn width_queries_match_expected_ranges() {
-        assert!(evaluate_media_query("(min-width: 600px)", 800.0, 600.0, 1.0));
-        assert!(evaluate_media_query("(max-width: 1024px)", 800.0, 600.0, 1.0));
-        assert!(!evaluate_media_query("(min-width: 1200px)", 800.0, 600.0, 1.0));
+        assert!(evaluate_media_query("(min-width: 600px)", &screen(800.0, 600.0)));
+        assert!(evaluate_media_query("(max-width: 1024px)", &screen(800.0, 600.0)));
+        assert!(!evaluate_media_query("(min-width: 1200px)", &screen(800.0, 600.0)));
     }
 
     #[test]
     fn orientation_and_or_list_work() {
-        assert!(evaluate_media_query("screen and (orientation: landscape)", 900.0, 700.0, 1.0));
-        assert!(evaluate_media_query("(max-width: 500px), (orientation: landscape)", 900.0, 700.0, 1.0));
-        assert!(!evaluate_media_query("print and (min-width: 1px)", 900.0, 700.0, 1.0));
+        assert!(evaluate_media_query("screen and (orientation: landscape)", &screen(900.0, 700.0)));
+        assert!(evaluate_media_query("(max-width: 500px), (orientation: landscape)", &screen(900.0, 700.0)));
+        assert!(!evaluate_media_query("print and (min-width: 1px)", &screen(900.0, 700.0)));
     }
 
     #[test]
     fn resolution_units_are_supported() {
-        assert!(evaluate_media_query("(min-resolution: 96dpi)", 800.0, 600.0, 1.0));
-        assert!(evaluate_media_query("(resolution: 1dppx)", 800.0, 600.0, 1.0));
-        assert!(!evaluate_media_query("(min-resolution: 2dppx)", 800.0, 600.0, 1.0));
+        assert!(evaluate_media_query("(min-resolution: 96dpi)", &screen(800.0, 600.0)));
+        assert!(evaluate_media_query("(resolution: 1dppx)", &screen(800.0, 600.0)));
+        assert!(!evaluate_media_query("(min-resolution: 2dppx)", &screen(800.0, 600.0)));
+    }
+
+    #[test]
+    fn user_preferences_follow_the_media_context() {
+        let dark = MediaContext { dark: true, reduced_motion: true, ..screen(800.0, 600.0) };
+        assert!(evaluate_media_query("(prefers-color-scheme: dark)", &dark));
+        assert!(!evaluate_media_query("(prefers-color-scheme: dark)", &screen(800.0, 600.0)));
+        assert!(evaluate_media_query("(prefers-reduced-motion: reduce)", &dark));
+        assert!(evaluate_media_query("(prefers-reduced-motion)", &dark));
+        assert!(evaluate_media_query("not (prefers-reduced-motion)", &screen(800.0, 600.0)));
     }
 
     #[test]
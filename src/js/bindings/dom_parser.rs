@@ -0,0 +1,375 @@
+// DOMParser, XMLSerializer and the innerHTML/outerHTML accessors
+use crate::dom::{AttributeMap, Dom, NodeData};
+use crate::js::bindings::dom_bindings::DOM_REF;
+use crate::js::bindings::element_bindings;
+use crate::js::bindings::event_listeners::DOCUMENT_NODE_ID;
+use crate::js::bindings::node::node_create_js_node_wrapper_by_id;
+use crate::js::helpers::{
+    create_empty_array, create_js_string, define_function, get_node_id_from_value, js_value_to_string, ToSafeCx,
+};
+use crate::js::{JsResult, JsRuntime};
+use html5ever::ns;
+use markup5ever::{local_name, QualName};
+use mozjs::context::JSContext as SafeJSContext;
+use mozjs::jsapi::{CallArgs, JSContext};
+use mozjs::jsval::{DoubleValue, JSVal, NullValue, ObjectValue};
+use mozjs::rooted;
+use mozjs::rust::wrappers::JS_SetElement;
+use std::os::raw::c_uint;
+
+/// Install DOMParser and XMLSerializer, and make innerHTML and outerHTML serialize and parse
+/// through the DOM. Parsed documents are plain objects over a `Document` node that is never
+/// attached, so nothing in them loads or runs. Markup is always written with HTML syntax, even
+/// for documents parsed as XML.
+pub fn setup_dom_parser(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesParseDocument", Some(stokes_parse_document), 2)?;
+        define_function(cx, global.get(), "__stokesParseHTMLFragment", Some(stokes_parse_html_fragment), 2)?;
+        define_function(cx, global.get(), "__stokesSerializeNode", Some(stokes_serialize_node), 2)?;
+        // The accessors below go on the prototype every element wrapper shares
+        element_bindings::ensure_element_shared_prototype(cx).map(|_| ())
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            const doc = root.document;
+            if (typeof root.__stokesParseDocument !== 'function' || !doc) {
+                return;
+            }
+
+            function domError(message, name) {
+                if (typeof root.DOMException === 'function') {
+                    return new root.DOMException(message, name);
+                }
+                const error = new Error(message);
+                error.name = name;
+                return error;
+            }
+
+            function escapeIdent(value) {
+                return root.CSS && typeof root.CSS.escape === 'function' ? root.CSS.escape(value) : value;
+            }
+
+            function childElement(parent, localName) {
+                const children = parent ? parent.childNodes || [] : [];
+                for (let i = 0; i < children.length; i++) {
+                    if (children[i].nodeType === 1 && children[i].localName === localName) {
+                        return children[i];
+                    }
+                }
+                return null;
+            }
+
+            // Nodes of every document share one DOM, so importing copies and adopting just
+            // takes the node out of its old parent.
+            function importNode(node, deep) {
+                return node.cloneNode(!!deep);
+            }
+            function adoptNode(node) {
+                if (node.parentNode && node.parentNode.nodeType !== 9) {
+                    node.parentNode.removeChild(node);
+                }
+                return node;
+            }
+            if (typeof doc.importNode !== 'function') {
+                doc.importNode = importNode;
+            }
+            if (typeof doc.adoptNode !== 'function') {
+                doc.adoptNode = adoptNode;
+            }
+
+            function makeParsedDocument(parsed, contentType) {
+                const [documentId, doctypeName, documentElement] = parsed;
+                const isHtml = contentType === 'text/html';
+
+                function query(selector, all) {
+                    if (!documentElement) {
+                        return all ? [] : null;
+                    }
+                    const matchesRoot = documentElement.matches(selector);
+                    if (!all) {
+                        return matchesRoot ? documentElement : documentElement.querySelector(selector);
+                    }
+                    const found = Array.from(documentElement.querySelectorAll(selector));
+                    return matchesRoot ? [documentElement].concat(found) : found;
+                }
+
+                const result = {
+                    nodeType: 9,
+                    nodeName: '#document',
+                    contentType: contentType,
+                    compatMode: !isHtml || doctypeName ? 'CSS1Compat' : 'BackCompat',
+                    characterSet: 'UTF-8',
+                    charset: 'UTF-8',
+                    inputEncoding: 'UTF-8',
+                    readyState: 'complete',
+                    URL: doc.URL,
+                    documentURI: doc.URL,
+                    baseURI: doc.baseURI || doc.URL,
+                    defaultView: null,
+                    documentElement: documentElement,
+                    head: isHtml ? childElement(documentElement, 'head') : null,
+                    body: isHtml ? childElement(documentElement, 'body') : null,
+                    doctype: doctypeName === null ? null : {
+                        name: doctypeName,
+                        nodeName: doctypeName,
+                        publicId: '',
+                        systemId: '',
+                        nodeType: 10,
+                        ownerDocument: null,
+                    },
+                    childNodes: documentElement ? [documentElement] : [],
+                    firstChild: documentElement,
+                    lastChild: documentElement,
+                };
+                Object.defineProperty(result, '__nodeId', { value: documentId });
+                if (result.doctype) {
+                    result.doctype.ownerDocument = result;
+                }
+
+                Object.defineProperty(result, 'title', {
+                    get: function() {
+                        const title = query('title', false);
+                        return title ? title.textContent.replace(/[\t\n\f\r ]+/g, ' ').trim() : '';
+                    },
+                    configurable: true,
+                    enumerable: true,
+                });
+
+                result.getElementById = function(id) {
+                    return id ? query('#' + escapeIdent(String(id)), false) : null;
+                };
+                result.getElementsByTagName = function(tagName) {
+                    return query(String(tagName), true);
+                };
+                result.getElementsByClassName = function(classNames) {
+                    const names = String(classNames).split(/[\t\n\f\r ]+/).filter(Boolean);
+                    return names.length ? query(names.map(name => '.' + escapeIdent(name)).join(''), true) : [];
+                };
+                result.querySelector = function(selector) {
+                    return query(selector, false);
+                };
+                result.querySelectorAll = function(selector) {
+                    return query(selector, true);
+                };
+                result.hasChildNodes = function() {
+                    return !!documentElement;
+                };
+                result.createElement = doc.createElement.bind(doc);
+                result.createElementNS = doc.createElementNS.bind(doc);
+                result.createTextNode = doc.createTextNode.bind(doc);
+                result.createDocumentFragment = doc.createDocumentFragment.bind(doc);
+                result.importNode = importNode;
+                result.adoptNode = adoptNode;
+                if (doc.implementation) {
+                    result.implementation = doc.implementation;
+                }
+                return result;
+            }
+
+            const PARSEABLE_TYPES = ['text/html', 'text/xml', 'application/xml', 'application/xhtml+xml', 'image/svg+xml'];
+
+            function DOMParser() {
+                if (!new.target) {
+                    throw new TypeError("Failed to construct 'DOMParser': Please use the 'new' operator, this DOM object constructor cannot be called as a function.");
+                }
+            }
+            DOMParser.prototype.parseFromString = function(string, type) {
+                if (arguments.length < 2) {
+                    throw new TypeError("Failed to execute 'parseFromString' on 'DOMParser': 2 arguments required, but only " + arguments.length + ' present.');
+                }
+                const contentType = String(type);
+                if (PARSEABLE_TYPES.indexOf(contentType) === -1) {
+                    throw new TypeError("Failed to execute 'parseFromString' on 'DOMParser': The provided value '" + contentType + "' is not a valid enum value of type DOMParserSupportedType.");
+                }
+                const parsed = root.__stokesParseDocument(String(string), contentType !== 'text/html');
+                return makeParsedDocument(parsed, contentType);
+            };
+            Object.defineProperty(DOMParser.prototype, Symbol.toStringTag, { value: 'DOMParser', configurable: true });
+
+            function XMLSerializer() {
+                if (!new.target) {
+                    throw new TypeError("Failed to construct 'XMLSerializer': Please use the 'new' operator, this DOM object constructor cannot be called as a function.");
+                }
+            }
+            XMLSerializer.prototype.serializeToString = function(node) {
+                if (arguments.length < 1) {
+                    throw new TypeError("Failed to execute 'serializeToString' on 'XMLSerializer': 1 argument required, but only 0 present.");
+                }
+                if (node === null || typeof node !== 'object' || typeof node.nodeType !== 'number') {
+                    throw new TypeError("Failed to execute 'serializeToString' on 'XMLSerializer': parameter 1 is not of type 'Node'.");
+                }
+                const markup = root.__stokesSerializeNode(node, true);
+                if (markup !== null) {
+                    return markup;
+                }
+                // Documents from document.implementation and fragments aren't nodes in the DOM
+                // themselves, so serialize what they hold.
+                if (node.nodeType === 9) {
+                    const doctype = node.doctype ? '<!DOCTYPE ' + node.doctype.name + '>\n' : '';
+                    return doctype + (node.documentElement ? this.serializeToString(node.documentElement) : '');
+                }
+                return Array.prototype.map.call(node.childNodes || [], child => this.serializeToString(child)).join('');
+            };
+            Object.defineProperty(XMLSerializer.prototype, Symbol.toStringTag, { value: 'XMLSerializer', configurable: true });
+
+            for (const [name, ctor] of [['DOMParser', DOMParser], ['XMLSerializer', XMLSerializer]]) {
+                Object.defineProperty(root, name, {
+                    value: ctor,
+                    writable: true,
+                    configurable: true,
+                    enumerable: false,
+                });
+            }
+
+            const elementPrototype = root.__stokesElementPrototype;
+
+            // Parsed children are moved in one at a time through appendChild and insertBefore,
+            // so mutation observers and custom elements see them like any other insertion.
+            Object.defineProperty(elementPrototype, 'innerHTML', {
+                get: function() {
+                    const markup = root.__stokesSerializeNode(this, false);
+                    return markup === null ? '' : markup;
+                },
+                set: function(value) {
+                    const container = root.__stokesParseHTMLFragment(this, value === null ? '' : String(value));
+                    while (this.firstChild) {
+                        this.removeChild(this.firstChild);
+                    }
+                    while (container && container.firstChild) {
+                        this.appendChild(container.firstChild);
+                    }
+                },
+                configurable: true,
+                enumerable: true,
+            });
+            Object.defineProperty(elementPrototype, 'outerHTML', {
+                get: function() {
+                    const markup = root.__stokesSerializeNode(this, true);
+                    return markup === null ? '' : markup;
+                },
+                set: function(value) {
+                    const parent = this.parentNode;
+                    if (!parent) {
+                        return;
+                    }
+                    if (parent.nodeType === 9) {
+                        throw domError("Failed to set the 'outerHTML' property on 'Element': This element's parent is of type '#document'.", 'NoModificationAllowedError');
+                    }
+                    const context = parent.nodeType === 1 ? parent : doc.createElement('body');
+                    const container = root.__stokesParseHTMLFragment(context, value === null ? '' : String(value));
+                    while (container && container.firstChild) {
+                        parent.insertBefore(container.firstChild, this);
+                    }
+                    parent.removeChild(this);
+                },
+                configurable: true,
+                enumerable: true,
+            });
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+fn with_dom<R>(f: impl FnOnce(&mut Dom) -> Option<R>) -> Option<R> {
+    DOM_REF.with(|dom| {
+        let dom = unsafe { &mut *(*dom.borrow())? };
+        f(dom)
+    })
+}
+
+/// The node a JS value wraps, with the document as its root node
+unsafe fn node_arg(cx: &mut SafeJSContext, val: JSVal) -> Option<usize> {
+    let node_id = get_node_id_from_value(cx, val)?;
+    Some(if node_id == DOCUMENT_NODE_ID { 0 } else { node_id })
+}
+
+/// `__stokesParseDocument(source, isXml)`: parse a detached document, returned as
+/// `[documentNodeId, doctypeName, documentElement]` with null for a missing doctype or element
+unsafe extern "C" fn stokes_parse_document(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let source = js_value_to_string(safe_cx, *args.get(0));
+    let is_xml = args.get(1).is_boolean() && args.get(1).to_boolean();
+
+    let Some((document_id, doctype_name, document_element)) = with_dom(|dom| {
+        let document_id = dom.parse_detached_document(&source, is_xml);
+        let document = dom.get_node(document_id)?;
+        let mut doctype_name = None;
+        let mut document_element = None;
+        for &child_id in &document.children {
+            match &dom.get_node(child_id)?.data {
+                NodeData::Doctype { name } => doctype_name = Some(name.to_string()),
+                NodeData::Element(_) if document_element.is_none() => document_element = Some(child_id),
+                _ => {}
+            }
+        }
+        Some((document_id, doctype_name, document_element))
+    }) else {
+        args.rval().set(NullValue());
+        return true;
+    };
+
+    rooted!(in(raw_cx) let array = create_empty_array(safe_cx));
+    rooted!(in(raw_cx) let document_id = DoubleValue(document_id as f64));
+    rooted!(in(raw_cx) let doctype_name = match doctype_name {
+        Some(name) => create_js_string(safe_cx, &name),
+        None => NullValue(),
+    });
+    rooted!(in(raw_cx) let document_element = document_element
+        .and_then(|node_id| node_create_js_node_wrapper_by_id(safe_cx, node_id))
+        .unwrap_or(NullValue()));
+    JS_SetElement(raw_cx, array.handle().into(), 0, document_id.handle().into());
+    JS_SetElement(raw_cx, array.handle().into(), 1, doctype_name.handle().into());
+    JS_SetElement(raw_cx, array.handle().into(), 2, document_element.handle().into());
+    args.rval().set(ObjectValue(array.get()));
+    true
+}
+
+/// `__stokesParseHTMLFragment(context, html)`: parse `html` as the contents of an element like
+/// `context` and return a new detached element holding the result
+unsafe extern "C" fn stokes_parse_html_fragment(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let context_id = node_arg(safe_cx, *args.get(0));
+    let html = js_value_to_string(safe_cx, *args.get(1));
+
+    let container_id = with_dom(|dom| {
+        let name = context_id
+            .and_then(|node_id| dom.get_node(node_id))
+            .and_then(|node| node.element_data())
+            .map(|data| data.name.clone())
+            .unwrap_or_else(|| QualName::new(None, ns!(html), local_name!("body")));
+        let container_id = dom.create_element(name, AttributeMap::empty());
+        dom.set_inner_html(container_id, &html);
+        Some(container_id)
+    });
+
+    match container_id.and_then(|node_id| node_create_js_node_wrapper_by_id(safe_cx, node_id)) {
+        Some(container) => args.rval().set(container),
+        None => args.rval().set(NullValue()),
+    }
+    true
+}
+
+/// `__stokesSerializeNode(node, includeSelf)`: the markup of a node and its subtree, or of just
+/// its children, or null when the value isn't a node in the DOM
+unsafe extern "C" fn stokes_serialize_node(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let include_self = args.get(1).is_boolean() && args.get(1).to_boolean();
+    let markup = node_arg(safe_cx, *args.get(0)).and_then(|node_id| {
+        with_dom(|dom| {
+            dom.get_node(node_id)?;
+            Some(if include_self { dom.serialize_node(node_id) } else { dom.serialize_children(node_id) })
+        })
+    });
+
+    match markup {
+        Some(markup) => args.rval().set(create_js_string(safe_cx, &markup)),
+        None => args.rval().set(NullValue()),
+    }
+    true
+}
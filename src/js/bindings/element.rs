@@ -17,7 +17,8 @@ use crate::js::bindings::element_bindings::{
     element_remove, element_remove_attribute,
     element_replace_with, element_scroll_by, element_scroll_into_view,
     element_scroll_to, element_set_async_attr, element_set_attribute, element_set_checked_attr,
-    element_set_class_name, element_set_id, element_set_object_property_noop,
+    element_set_class_name, element_set_id, element_set_object_property_noop, element_set_scroll_left,
+    element_set_scroll_top,
     element_set_shadow_root_noop, element_set_src, element_set_text_content, element_set_type_attr,
     element_set_value_attr, ensure_element_shared_prototype,
 };
@@ -109,7 +110,9 @@ const ELEMENT_INTERNAL_METHODS: &[ElementMethodBinding] = &[
     ("__getScrollWidth", Some(element_get_scroll_width), 0),
     ("__getScrollHeight", Some(element_get_scroll_height), 0),
     ("__getScrollLeft", Some(element_get_scroll_left), 0),
+    ("__setScrollLeft", Some(element_set_scroll_left), 1),
     ("__getScrollTop", Some(element_get_scroll_top), 0),
+    ("__setScrollTop", Some(element_set_scroll_top), 1),
 ];
 
 const ELEMENT_ACCESSORS: &[ElementAccessorBinding] = &[
@@ -133,8 +136,8 @@ const ELEMENT_ACCESSORS: &[ElementAccessorBinding] = &[
     ("clientHeight", "__getClientHeight", "__setObjectPropertyNoop"),
     ("scrollWidth", "__getScrollWidth", "__setObjectPropertyNoop"),
     ("scrollHeight", "__getScrollHeight", "__setObjectPropertyNoop"),
-    ("scrollLeft", "__getScrollLeft", "__setObjectPropertyNoop"),
-    ("scrollTop", "__getScrollTop", "__setObjectPropertyNoop"),
+    ("scrollLeft", "__getScrollLeft", "__setScrollLeft"),
+    ("scrollTop", "__getScrollTop", "__setScrollTop"),
 ];
 
 unsafe fn define_int_constants(
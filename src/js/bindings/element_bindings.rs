@@ -2,12 +2,17 @@
 use blitz_traits::net::Request;
 use crate::dom::{AttributeMap, NodeData, ShadowRootMode};
 use crate::dom::events::focus::generate_focus_events;
+use crate::dom::scroll::{ScrollAlignment, ScrollBehavior};
 use crate::engine::js_provider::ScriptKind;
 use crate::engine::script_type::executable_script_kind;
 use crate::events::DomEvent;
-use crate::js::bindings::custom_elements::custom_elements_upgrade_for_node;
+use crate::js::bindings::custom_elements::{
+    custom_elements_attribute_changed, custom_elements_attribute_value, custom_elements_disconnect_for_node,
+    custom_elements_upgrade_for_node,
+};
+use crate::js::bindings::registry;
 use crate::js::bindings::dom_bindings::DOM_REF;
-use crate::js::helpers::{create_empty_array, create_js_string, define_function, define_js_property_accessor, define_js_property_getter, get_node_id_from_this, get_node_id_from_value, js_value_to_string, set_int_property, set_string_property, to_css_property_name, ToSafeCx};
+use crate::js::helpers::{create_empty_array, create_js_string, define_function, define_js_property_accessor, define_js_property_getter, get_dictionary_member, get_node_id_from_this, get_node_id_from_value, js_value_to_f64, js_value_to_string, set_int_property, set_string_property, to_css_property_name, ToSafeCx};
 use crate::js::selectors::{matches_parsed_selector, parse_selector, selector_seed, SelectorSeed};
 use crate::js::bindings::element;
 pub(crate) use crate::js::bindings::element::{
@@ -17,79 +22,17 @@ use html5ever::ns;
 use html5ever::local_name;
 use markup5ever::QualName;
 use mozjs::jsapi::{CallArgs, HandleValueArray, JSContext, JSObject, JSPROP_ENUMERATE};
-use mozjs::rust::wrappers2::{AddRawValueRoot, CurrentGlobalOrNull, JS_CallFunctionValue, JS_DefineProperty, JS_GetProperty, JS_NewPlainObject, JS_SetElement, JS_SetProperty, RemoveRawValueRoot};
+use mozjs::rust::wrappers2::{CurrentGlobalOrNull, JS_CallFunctionValue, JS_DefineProperty, JS_GetProperty, JS_NewPlainObject, JS_SetElement, JS_SetProperty};
 use mozjs::context::JSContext as SafeJSContext;
-use mozjs::jsapi::Heap;
 use mozjs::jsval::{BooleanValue, JSVal, NullValue, ObjectValue, UndefinedValue};
 use mozjs::rooted;
-use mozjs::rust::Runtime;
 use mozjs::rust::ValueArray;
-use std::cell::RefCell;
-use std::collections::HashMap;
 use std::os::raw::c_uint;
 use blitz_traits::shell::ShellProvider;
 use tracing::{trace, warn};
 use crate::js::bindings::event_listeners;
 use crate::js::bindings::warnings::warn_stubbed_binding;
-
-struct PinnedElementWrapper {
-    rooted_value: Box<Heap<JSVal>>,
-}
-
-impl PinnedElementWrapper {
-    unsafe fn new(cx: &mut SafeJSContext, obj: *mut JSObject) -> Self {
-        let rooted_value: Box<Heap<JSVal>> = Box::new(Heap::default());
-        rooted_value.set(ObjectValue(obj));
-        let name = std::ffi::CString::new("PinnedElementWrapper").unwrap();
-        AddRawValueRoot(cx, rooted_value.get_unsafe(), name.as_ptr());
-        Self { rooted_value }
-    }
-
-    #[inline]
-    fn get_object(&self) -> Option<*mut JSObject> {
-        let val = self.rooted_value.get();
-        if val.is_object() {
-            Some(val.to_object())
-        } else {
-            None
-        }
-    }
-}
-
-impl Drop for PinnedElementWrapper {
-    fn drop(&mut self) {
-        unsafe {
-            if let Some(cx) = Runtime::get() {
-                RemoveRawValueRoot(&cx.to_safe_cx(), self.rooted_value.get_unsafe());
-            }
-        }
-    }
-}
-
-thread_local! {
-    static ELEMENT_WRAPPER_CACHE: RefCell<HashMap<usize, PinnedElementWrapper>> = RefCell::new(HashMap::new());
-}
-
-pub fn clear_element_wrapper_cache() {
-    ELEMENT_WRAPPER_CACHE.with(|cache| cache.borrow_mut().clear());
-}
-
-unsafe fn get_cached_element_wrapper(node_id: usize) -> Option<*mut JSObject> {
-    ELEMENT_WRAPPER_CACHE.with(|cache| {
-        cache
-            .borrow()
-            .get(&node_id)
-            .and_then(PinnedElementWrapper::get_object)
-    })
-}
-
-unsafe fn cache_element_wrapper(cx: &mut SafeJSContext, node_id: usize, obj: *mut JSObject) {
-    ELEMENT_WRAPPER_CACHE.with(|cache| {
-        cache
-            .borrow_mut()
-            .insert(node_id, PinnedElementWrapper::new(cx, obj));
-    });
-}
+use crate::js::bindings::window::scroll_arguments;
 
 fn has_backing_dom_node(node_id: usize) -> bool {
     DOM_REF.with(|dom_ref| {
@@ -289,6 +232,9 @@ fn constructor_name_for_element(is_svg: bool, local_name: &str) -> &'static str
         if local_name.eq_ignore_ascii_case("img") {
             return "HTMLImageElement";
         }
+        if local_name.eq_ignore_ascii_case("canvas") {
+            return "HTMLCanvasElement";
+        }
         return "HTMLElement";
     }
 
@@ -335,7 +281,7 @@ unsafe fn create_js_element_impl(
 
     let cacheable_node = has_backing_dom_node(node_id);
     if cacheable_node {
-        if let Some(cached_obj) = get_cached_element_wrapper(node_id) {
+        if let Some(cached_obj) = registry::node_wrapper(node_id) {
             return Ok(ObjectValue(cached_obj));
         }
     }
@@ -392,24 +338,12 @@ unsafe fn create_js_element_impl(
     set_string_property(cx, element.get(), "localName", &resolved_local_name)?;
     set_int_property(cx, element.get(), "nodeType", 1)?; // ELEMENT_NODE
     // id/className are exposed through reflected accessors defined below.
-    // FIXME: innerHTML always returns "" instead of serializing the element's child nodes as HTML.
-    set_string_property(cx, element.get(), "innerHTML", "")?;
-    // FIXME: outerHTML returns a stub "<tag></tag>" instead of the element's full serialized HTML
-    // including its attributes and child subtree.
-    set_string_property(cx, element.get(), "outerHTML", &format!("<{0}></{0}>", resolved_local_name))?;
+    // innerHTML/outerHTML are accessors on the shared element prototype (see dom_parser.rs)
     // Note: textContent will be defined as a property accessor below
 
     // Store the backing DOM node id.
-    rooted!(in(raw_cx) let ptr_val = mozjs::jsval::DoubleValue(node_id as f64));
+    registry::set_node_id(cx, element.get(), node_id);
     rooted!(in(raw_cx) let element_rooted = element.get());
-    let cname = std::ffi::CString::new("__nodeId").unwrap();
-    JS_DefineProperty(
-        cx,
-        element_rooted.handle().into(),
-        cname.as_ptr(),
-        ptr_val.handle().into(),
-        0, // Hidden property
-    );
 
     // Expose namespaceURI as null for no-namespace nodes and as a URI string otherwise.
     let namespace_name = std::ffi::CString::new("namespaceURI").unwrap();
@@ -513,7 +447,7 @@ unsafe fn create_js_element_impl(
     // Keep one stable JS wrapper per DOM node so framework internals attached
     // to element objects survive across lookups and event dispatch.
     if cacheable_node {
-        cache_element_wrapper(cx, node_id, element.get());
+        registry::set_node_wrapper(node_id, element.get());
     }
 
     if resolved_local_name.eq_ignore_ascii_case("form") {
@@ -550,16 +484,8 @@ unsafe fn create_js_text_node_by_id(
     set_string_property(cx, text_node.get(), "textContent", text)?;
     define_function(cx, text_node.get(), "hasChildNodes", Some(element_has_child_nodes), 0)?;
 
-    rooted!(in(raw_cx) let ptr_val = mozjs::jsval::DoubleValue(node_id as f64));
+    registry::set_node_id(cx, text_node.get(), node_id);
     rooted!(in(raw_cx) let text_rooted = text_node.get());
-    let cname = std::ffi::CString::new("__nodeId").unwrap();
-    JS_DefineProperty(
-        cx,
-        text_rooted.handle().into(),
-        cname.as_ptr(),
-        ptr_val.handle().into(),
-        0,
-    );
 
     rooted!(in(raw_cx) let null_val = NullValue());
     for name in &["parentNode", "parentElement", "firstChild", "lastChild", "previousSibling", "nextSibling"] {
@@ -602,16 +528,8 @@ unsafe fn create_js_comment_node_by_id(cx: &mut SafeJSContext, node_id: usize) -
     set_string_property(cx, comment_node.get(), "textContent", "")?;
     define_function(cx, comment_node.get(), "hasChildNodes", Some(element_has_child_nodes), 0)?;
 
-    rooted!(in(raw_cx) let ptr_val = mozjs::jsval::DoubleValue(node_id as f64));
+    registry::set_node_id(cx, comment_node.get(), node_id);
     rooted!(in(raw_cx) let comment_rooted = comment_node.get());
-    let cname = std::ffi::CString::new("__nodeId").unwrap();
-    JS_DefineProperty(
-        cx,
-        comment_rooted.handle().into(),
-        cname.as_ptr(),
-        ptr_val.handle().into(),
-        0,
-    );
 
     rooted!(in(raw_cx) let null_val = NullValue());
     for name in &["parentNode", "parentElement", "firstChild", "lastChild", "previousSibling", "nextSibling"] {
@@ -702,16 +620,7 @@ unsafe fn create_style_object_for_node(cx: &mut SafeJSContext, node_id: usize) -
         return Err("Failed to create style object".to_string());
     }
 
-    rooted!(in(raw_cx) let style_ptr_val = mozjs::jsval::DoubleValue(node_id as f64));
-    rooted!(in(raw_cx) let style_rooted = style.get());
-    let style_id_name = std::ffi::CString::new("__nodeId").unwrap();
-    JS_DefineProperty(
-        cx,
-        style_rooted.handle().into(),
-        style_id_name.as_ptr(),
-        style_ptr_val.handle().into(),
-        0,
-    );
+    registry::set_node_id(cx, style.get(), node_id);
 
     define_function(cx, style.get(), "getPropertyValue", Some(style_get_property_value), 1)?;
     define_function(cx, style.get(), "setProperty", Some(style_set_property), 3)?;
@@ -727,16 +636,7 @@ unsafe fn create_class_list_object_for_node(cx: &mut SafeJSContext, node_id: usi
         return Err("Failed to create classList object".to_string());
     }
 
-    rooted!(in(raw_cx) let cl_ptr_val = mozjs::jsval::DoubleValue(node_id as f64));
-    rooted!(in(raw_cx) let class_list_rooted = class_list.get());
-    let cl_id_name = std::ffi::CString::new("__nodeId").unwrap();
-    JS_DefineProperty(
-        cx,
-        class_list_rooted.handle().into(),
-        cl_id_name.as_ptr(),
-        cl_ptr_val.handle().into(),
-        0,
-    );
+    registry::set_node_id(cx, class_list.get(), node_id);
 
     define_function(cx, class_list.get(), "add", Some(class_list_add), 1)?;
     define_function(cx, class_list.get(), "remove", Some(class_list_remove), 1)?;
@@ -795,16 +695,7 @@ pub(crate) unsafe fn create_js_shadow_root_by_id(cx: &mut SafeJSContext, node_id
     set_int_property(cx, shadow_root.get(), "nodeType", 11)?; // DOCUMENT_FRAGMENT_NODE
     set_string_property(cx, shadow_root.get(), "nodeName", "#document-fragment")?;
 
-    rooted!(in(raw_cx) let node_id_val = mozjs::jsval::DoubleValue(node_id as f64));
-    rooted!(in(raw_cx) let shadow_root_rooted = shadow_root.get());
-    let cname = std::ffi::CString::new("__nodeId").unwrap();
-    JS_DefineProperty(
-        cx,
-        shadow_root_rooted.handle().into(),
-        cname.as_ptr(),
-        node_id_val.handle().into(),
-        0,
-    );
+    registry::set_node_id(cx, shadow_root.get(), node_id);
 
     define_function(cx, shadow_root.get(), "appendChild", Some(element_append_child), 1)?;
     define_function(cx, shadow_root.get(), "querySelector", Some(element_query_selector), 1)?;
@@ -1344,6 +1235,7 @@ pub(crate) unsafe extern "C" fn element_set_attribute(raw_cx: *mut JSContext, ar
     trace!("[JS] element.setAttribute('{}', '{}') called", attr_name, attr_value);
 
     if let Some(node_id) = get_node_id_from_this(safe_cx, &args) {
+        let old_value = custom_elements_attribute_value(node_id, &attr_name);
         DOM_REF.with(|dom_ref| {
             if let Some(dom_ptr) = *dom_ref.borrow() {
                 let dom = &mut *dom_ptr;
@@ -1356,6 +1248,7 @@ pub(crate) unsafe extern "C" fn element_set_attribute(raw_cx: *mut JSContext, ar
                 dom.set_attribute(node_id, qname, &attr_value);
             }
         });
+        custom_elements_attribute_changed(safe_cx, node_id, &attr_name, old_value);
     }
 
     args.rval().set(UndefinedValue());
@@ -1376,6 +1269,7 @@ pub(crate) unsafe extern "C" fn element_remove_attribute(raw_cx: *mut JSContext,
     trace!("[JS] element.removeAttribute('{}') called", attr_name);
 
     if let Some(node_id) = get_node_id_from_this(safe_cx, &args) {
+        let old_value = custom_elements_attribute_value(node_id, &attr_name);
         DOM_REF.with(|dom_ref| {
             if let Some(dom_ptr) = *dom_ref.borrow() {
                 let dom = &mut *dom_ptr;
@@ -1387,6 +1281,9 @@ pub(crate) unsafe extern "C" fn element_remove_attribute(raw_cx: *mut JSContext,
                 dom.clear_attribute(node_id, qname);
             }
         });
+        if old_value.is_some() {
+            custom_elements_attribute_changed(safe_cx, node_id, &attr_name, old_value);
+        }
     }
 
     args.rval().set(UndefinedValue());
@@ -1460,7 +1357,7 @@ fn trigger_script_load_if_needed(child_id: usize) {
         None
     });
     if let Some((url, net_provider, js_provider, script_node_id, script_kind)) = script_load_info {
-        println!("[JS] Dynamically loading script: {}", url);
+        tracing::debug!("Dynamically loading script: {}", url);
         let url_str = url.to_string();
         let module_source_url = (script_kind == ScriptKind::Module).then(|| url_str.clone());
         net_provider.fetch_with_callback(
@@ -1476,10 +1373,10 @@ fn trigger_script_load_if_needed(child_id: usize) {
                                     js_provider.execute_script_with_node_id(script, script_node_id);
                                 }
                             }
-                            Err(e) => eprintln!("[JS] Dynamic script at '{}' is not valid UTF-8: {}", url_str, e),
+                            Err(e) => tracing::warn!("Dynamic script at '{}' is not valid UTF-8: {}", url_str, e),
                         }
                     }
-                    Err(e) => eprintln!("[JS] Failed to load dynamic script '{}': {:?}", url_str, e),
+                    Err(e) => tracing::warn!("Failed to load dynamic script '{}': {:?}", url_str, e),
                 }
             }),
         );
@@ -1603,6 +1500,7 @@ pub(crate) unsafe extern "C" fn element_remove_child(raw_cx: *mut JSContext, arg
             }
         }
     });
+    custom_elements_disconnect_for_node(safe_cx, child_id);
     if argc > 0 {
         // Return the child that was appended
         args.rval().set(*args.get(0));
@@ -1690,6 +1588,7 @@ pub(crate) unsafe extern "C" fn element_replace_child(raw_cx: *mut JSContext, ar
                 }
             });
 
+            custom_elements_disconnect_for_node(safe_cx, old_child_id);
             custom_elements_upgrade_for_node(safe_cx, new_child_id);
 
             trigger_script_load_if_needed(new_child_id);
@@ -2485,6 +2384,7 @@ pub(crate) unsafe extern "C" fn element_remove(raw_cx: *mut JSContext, argc: c_u
                 dom.remove_node(node_id);
             }
         });
+        custom_elements_disconnect_for_node(safe_cx, node_id);
     }
     args.rval().set(UndefinedValue());
     true
@@ -2593,6 +2493,10 @@ pub(crate) unsafe extern "C" fn element_replace_with(raw_cx: *mut JSContext, arg
                 }
             }
         });
+        custom_elements_disconnect_for_node(safe_cx, self_id);
+        for new_id in new_ids {
+            custom_elements_upgrade_for_node(safe_cx, new_id);
+        }
     }
     args.rval().set(UndefinedValue());
     true
@@ -2646,69 +2550,109 @@ pub(crate) unsafe extern "C" fn element_get_offset_top(raw_cx: *mut JSContext, a
     true
 }
 
-/// element.clientWidth getter - warns that this is hardcoded to 0
+/// The element's clientWidth, clientHeight, scrollWidth and scrollHeight, all 0 without a box
+unsafe fn scroll_geometry_of_this(cx: &mut SafeJSContext, args: &CallArgs) -> (f64, f64, f64, f64) {
+    let Some(node_id) = get_node_id_from_this(cx, args) else {
+        return (0.0, 0.0, 0.0, 0.0);
+    };
+    DOM_REF.with(|dom_ref| {
+        let dom = &*(*dom_ref.borrow())?;
+        dom.scroll_geometry(node_id)
+    })
+    .unwrap_or((0.0, 0.0, 0.0, 0.0))
+}
+
+/// The element's scrollLeft and scrollTop
+unsafe fn scroll_offset_of_this(cx: &mut SafeJSContext, args: &CallArgs) -> (f64, f64) {
+    let Some(node_id) = get_node_id_from_this(cx, args) else {
+        return (0.0, 0.0);
+    };
+    DOM_REF.with(|dom_ref| {
+        let dom = &*(*dom_ref.borrow())?;
+        Some(dom.element_scroll_offset(node_id))
+    })
+    .unwrap_or((0.0, 0.0))
+}
+
+/// Scroll the element to `left`, `top`, keeping its position on an axis given `None`
+unsafe fn scroll_this_to(cx: &mut SafeJSContext, args: &CallArgs, left: Option<f64>, top: Option<f64>, behavior: ScrollBehavior) {
+    if let Some(node_id) = get_node_id_from_this(cx, args) {
+        DOM_REF.with(|dom_ref| {
+            if let Some(dom_ptr) = *dom_ref.borrow() {
+                let dom = &mut *dom_ptr;
+                dom.scroll_element_to(node_id, left, top, behavior);
+            }
+        });
+    }
+}
+
+/// element.clientWidth getter - the width of the element's padding box, or the viewport's for the
+/// root element
 pub(crate) unsafe extern "C" fn element_get_client_width(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    warn_stubbed_binding(
-        "Element.clientWidth",
-        "hardcoded to 0 (layout not yet interactive)",
-    );
-    args.rval().set(mozjs::jsval::Int32Value(0));
+    let (width, _, _, _) = scroll_geometry_of_this(&mut raw_cx.to_safe_cx(), &args);
+    args.rval().set(mozjs::jsval::Int32Value(width.round() as i32));
     true
 }
 
-/// element.clientHeight getter - warns that this is hardcoded to 0
+/// element.clientHeight getter - the height of the element's padding box, or the viewport's for
+/// the root element
 pub(crate) unsafe extern "C" fn element_get_client_height(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    warn_stubbed_binding(
-        "Element.clientHeight",
-        "hardcoded to 0 (layout not yet interactive)",
-    );
-    args.rval().set(mozjs::jsval::Int32Value(0));
+    let (_, height, _, _) = scroll_geometry_of_this(&mut raw_cx.to_safe_cx(), &args);
+    args.rval().set(mozjs::jsval::Int32Value(height.round() as i32));
     true
 }
 
-/// element.scrollWidth getter - warns that this is hardcoded to 0
+/// element.scrollWidth getter - clientWidth plus how far the element scrolls horizontally
 pub(crate) unsafe extern "C" fn element_get_scroll_width(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    warn_stubbed_binding(
-        "Element.scrollWidth",
-        "hardcoded to 0 (layout not yet interactive)",
-    );
-    args.rval().set(mozjs::jsval::Int32Value(0));
+    let (_, _, width, _) = scroll_geometry_of_this(&mut raw_cx.to_safe_cx(), &args);
+    args.rval().set(mozjs::jsval::Int32Value(width.round() as i32));
     true
 }
 
-/// element.scrollHeight getter - warns that this is hardcoded to 0
+/// element.scrollHeight getter - clientHeight plus how far the element scrolls vertically
 pub(crate) unsafe extern "C" fn element_get_scroll_height(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    warn_stubbed_binding(
-        "Element.scrollHeight",
-        "hardcoded to 0 (layout not yet interactive)",
-    );
-    args.rval().set(mozjs::jsval::Int32Value(0));
+    let (_, _, _, height) = scroll_geometry_of_this(&mut raw_cx.to_safe_cx(), &args);
+    args.rval().set(mozjs::jsval::Int32Value(height.round() as i32));
     true
 }
 
-/// element.scrollLeft getter - warns that this is hardcoded to 0
+/// element.scrollLeft getter - how far the element is scrolled horizontally, the viewport's
+/// scrollX for the root element
 pub(crate) unsafe extern "C" fn element_get_scroll_left(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    warn_stubbed_binding(
-        "Element.scrollLeft",
-        "hardcoded to 0 (layout not yet interactive)",
-    );
-    args.rval().set(mozjs::jsval::Int32Value(0));
+    let (left, _) = scroll_offset_of_this(&mut raw_cx.to_safe_cx(), &args);
+    args.rval().set(mozjs::jsval::DoubleValue(left));
     true
 }
 
-/// element.scrollTop getter - warns that this is hardcoded to 0
+/// element.scrollLeft setter - scrolls the element horizontally, within its scroll range
+pub(crate) unsafe extern "C" fn element_set_scroll_left(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let left = if argc > 0 { js_value_to_f64(*args.get(0)) } else { None };
+    scroll_this_to(&mut raw_cx.to_safe_cx(), &args, Some(left.unwrap_or(0.0)), None, ScrollBehavior::Auto);
+    args.rval().set(UndefinedValue());
+    true
+}
+
+/// element.scrollTop getter - how far the element is scrolled vertically, the viewport's scrollY
+/// for the root element
 pub(crate) unsafe extern "C" fn element_get_scroll_top(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    warn_stubbed_binding(
-        "Element.scrollTop",
-        "hardcoded to 0 (layout not yet interactive)",
-    );
-    args.rval().set(mozjs::jsval::Int32Value(0));
+    let (_, top) = scroll_offset_of_this(&mut raw_cx.to_safe_cx(), &args);
+    args.rval().set(mozjs::jsval::DoubleValue(top));
+    true
+}
+
+/// element.scrollTop setter - scrolls the element vertically, within its scroll range
+pub(crate) unsafe extern "C" fn element_set_scroll_top(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let top = if argc > 0 { js_value_to_f64(*args.get(0)) } else { None };
+    scroll_this_to(&mut raw_cx.to_safe_cx(), &args, None, Some(top.unwrap_or(0.0)), ScrollBehavior::Auto);
+    args.rval().set(UndefinedValue());
     true
 }
 
@@ -2880,35 +2824,64 @@ pub(crate) unsafe extern "C" fn element_has_attributes(raw_cx: *mut JSContext, a
 }
 
 // ============================================================================
-// Scroll stubs
+// Scrolling
 // ============================================================================
 
-/// element.scrollIntoView() — no-op stub (layout is not yet interactive).
+/// element.scrollIntoView(alignToTop | options) — scrolls the viewport so the element is visible,
+/// honoring the `behavior`, `block` and `inline` options.
 pub(crate) unsafe extern "C" fn element_scroll_into_view(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    // FIXME: Should scroll the nearest scrollable ancestor (or the viewport) so that this element
-    // is visible, respecting the scrollIntoViewOptions (behavior, block, inline).
-    warn!("[JS] element.scrollIntoView() called on partial binding (no scroll performed)");
+    let safe_cx = &mut raw_cx.to_safe_cx();
+
+    let arg = if argc > 0 { *args.get(0) } else { UndefinedValue() };
+    let (block, inline, behavior) = if arg.is_object() {
+        let alignment = |cx: &mut SafeJSContext, name: &str, default: ScrollAlignment| {
+            get_dictionary_member(cx, arg, name)
+                .and_then(|val| ScrollAlignment::parse(&js_value_to_string(cx, val)))
+                .unwrap_or(default)
+        };
+        let block = alignment(safe_cx, "block", ScrollAlignment::Start);
+        let inline = alignment(safe_cx, "inline", ScrollAlignment::Nearest);
+        let behavior = get_dictionary_member(safe_cx, arg, "behavior")
+            .map(|val| ScrollBehavior::parse(&js_value_to_string(safe_cx, val)))
+            .unwrap_or_default();
+        (block, inline, behavior)
+    } else if arg.is_boolean() && !arg.to_boolean() {
+        (ScrollAlignment::End, ScrollAlignment::Nearest, ScrollBehavior::Auto)
+    } else {
+        (ScrollAlignment::Start, ScrollAlignment::Nearest, ScrollBehavior::Auto)
+    };
+
+    if let Some(node_id) = get_node_id_from_this(safe_cx, &args) {
+        DOM_REF.with(|dom_ref| {
+            if let Some(dom_ptr) = *dom_ref.borrow() {
+                let dom = &mut *dom_ptr;
+                dom.scroll_into_view(node_id, block, inline, behavior);
+            }
+        });
+    }
     args.rval().set(UndefinedValue());
     true
 }
 
-/// element.scrollTo() / element.scroll() — no-op stub.
+/// element.scrollTo(x, y | options) / element.scroll() — scrolls the element to a position in
+/// its scroll range
 pub(crate) unsafe extern "C" fn element_scroll_to(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    // FIXME: Should update the element's scroll position to the given (x, y) coordinates and fire
-    // a scroll event.
-    warn!("[JS] element.scrollTo()/scroll() called on partial binding (no scroll performed)");
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let (left, top, behavior) = scroll_arguments(safe_cx, &args, argc);
+    scroll_this_to(safe_cx, &args, left, top, behavior);
     args.rval().set(UndefinedValue());
     true
 }
 
-/// element.scrollBy() — no-op stub.
+/// element.scrollBy(dx, dy | options) — scrolls the element by an offset
 pub(crate) unsafe extern "C" fn element_scroll_by(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    // FIXME: Should offset the element's current scroll position by the given (dx, dy) delta and
-    // fire a scroll event.
-    warn!("[JS] element.scrollBy() called on partial binding (no scroll performed)");
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let (dx, dy, behavior) = scroll_arguments(safe_cx, &args, argc);
+    let (left, top) = scroll_offset_of_this(safe_cx, &args);
+    scroll_this_to(safe_cx, &args, Some(left + dx.unwrap_or(0.0)), Some(top + dy.unwrap_or(0.0)), behavior);
     args.rval().set(UndefinedValue());
     true
 }
@@ -3538,12 +3511,17 @@ pub(crate) unsafe extern "C" fn element_set_text_content(raw_cx: *mut JSContext,
     }
 
     if let Some(node_id) = get_node_id_from_this(safe_cx, &args) {
+        let mut removed = Vec::new();
         DOM_REF.with(|dom_ref| {
             if let Some(dom_ptr) = *dom_ref.borrow() {
                 let dom = &mut *dom_ptr;
+                removed = dom.get_node(node_id).map(|node| node.children.clone()).unwrap_or_default();
                 dom.set_text_content(node_id, text);
             }
         });
+        for child_id in removed {
+            custom_elements_disconnect_for_node(safe_cx, child_id);
+        }
     }
 
     args.rval().set(UndefinedValue());
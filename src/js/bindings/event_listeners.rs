@@ -19,15 +19,17 @@ use mozjs::jsapi::{CallArgs, HandleValueArray, Heap, JSContext, JSObject, JSPROP
 use mozjs::jsval::{DoubleValue, JSVal, NullValue, ObjectValue, UndefinedValue};
 use mozjs::rooted;
 use mozjs::rust::Runtime;
+use mozjs::rust::wrappers::JS_SetElement;
 use tracing::warn;
 use crate::dom::events::EventHandler;
 use crate::dom::{Dom, NodeData};
 use crate::events::{
-    BlitzPointerId, BlitzWheelDelta, DomEvent, DomEventData, EventState,
+    BlitzPointerId, BlitzTouch, BlitzWheelDelta, DomEvent, DomEventData, EventState,
 };
 use crate::js::bindings::dom_bindings::DOM_REF;
 use crate::js::bindings::element_bindings::create_js_element_by_dom_id;
-use crate::js::helpers::{define_function, set_bool_property, set_int_property, set_string_property, ToSafeCx};
+use crate::js::bindings::registry;
+use crate::js::helpers::{create_empty_array, define_function, set_bool_property, set_int_property, set_string_property, ToSafeCx};
 use crate::js::runtime::RUNTIME;
 
 // ── Constants ─────────────────────────────────────────────────────────────────
@@ -443,8 +445,8 @@ pub unsafe fn build_event_object_with_type(
             set_double_property(cx, obj.get(), "y",        ev.client_y() as f64);
             set_double_property(cx, obj.get(), "offsetX",  ev.client_x() as f64);
             set_double_property(cx, obj.get(), "offsetY",  ev.client_y() as f64);
-            set_double_property(cx, obj.get(), "movementX", 0.0);
-            set_double_property(cx, obj.get(), "movementY", 0.0);
+            set_double_property(cx, obj.get(), "movementX", ev.details.movement_x as f64);
+            set_double_property(cx, obj.get(), "movementY", ev.details.movement_y as f64);
             let _ = set_int_property(cx, obj.get(), "button",  ev.button as i32);
             let _ = set_int_property(cx, obj.get(), "buttons", ev.buttons.bits() as i32);
             let _ = set_bool_property(cx, obj.get(), "ctrlKey",  ev.mods.contains(Modifiers::CONTROL));
@@ -512,6 +514,35 @@ pub unsafe fn build_event_object_with_type(
             let _ = set_bool_property(cx, obj.get(), "isComposing", false);
             let _ = set_string_property(cx, obj.get(), "inputType", "insertText");
         }
+        DomEventData::TouchStart(tev)
+        | DomEventData::TouchMove(tev)
+        | DomEventData::TouchEnd(tev)
+        | DomEventData::TouchCancel(tev) => {
+            // targetTouches are the fingers that landed on the same element as the one that changed
+            let target = tev.changed_touches.first().map(|touch| touch.target);
+            let target_touches: Vec<BlitzTouch> = tev.touches.iter()
+                .filter(|touch| Some(touch.target) == target)
+                .cloned()
+                .collect();
+            for (name, touches) in [
+                ("touches", tev.touches.as_slice()),
+                ("targetTouches", target_touches.as_slice()),
+                ("changedTouches", tev.changed_touches.as_slice()),
+            ] {
+                rooted!(in(raw_cx) let list = make_touch_list(cx, touches));
+                if list.get().is_null() { continue; }
+                rooted!(in(raw_cx) let list_val = ObjectValue(list.get()));
+                rooted!(in(raw_cx) let obj_r = obj.get());
+                let cname = CString::new(name).unwrap();
+                JS_DefineProperty(cx, obj_r.handle().into(), cname.as_ptr(),
+                    list_val.handle().into(), JSPROP_ENUMERATE as u32);
+            }
+            let _ = set_bool_property(cx, obj.get(), "ctrlKey",  tev.mods.contains(Modifiers::CONTROL));
+            let _ = set_bool_property(cx, obj.get(), "shiftKey", tev.mods.contains(Modifiers::SHIFT));
+            let _ = set_bool_property(cx, obj.get(), "altKey",   tev.mods.contains(Modifiers::ALT));
+            let _ = set_bool_property(cx, obj.get(), "metaKey",  tev.mods.contains(Modifiers::META));
+            let _ = set_int_property(cx, obj.get(), "detail", 0);
+        }
         _ => {}
     }
 
@@ -532,6 +563,43 @@ unsafe extern "C" fn noop_init_event(
 
 // ── Low-level dispatch helpers ─────────────────────────────────────────────────
 
+/// Build an array of `Touch` objects for a touch event's `touches`, `targetTouches` or
+/// `changedTouches`.
+unsafe fn make_touch_list(cx: &mut SafeJSContext, touches: &[BlitzTouch]) -> *mut JSObject {
+    let raw_cx = cx.raw_cx();
+    rooted!(in(raw_cx) let list = create_empty_array(cx));
+    if list.get().is_null() { return std::ptr::null_mut(); }
+
+    for (index, touch) in touches.iter().enumerate() {
+        rooted!(in(raw_cx) let touch_obj = JS_NewPlainObject(cx));
+        if touch_obj.get().is_null() { continue; }
+        let coords = &touch.coords;
+        set_double_property(cx, touch_obj.get(), "identifier", touch.identifier as f64);
+        set_double_property(cx, touch_obj.get(), "clientX",  coords.client_x as f64);
+        set_double_property(cx, touch_obj.get(), "clientY",  coords.client_y as f64);
+        set_double_property(cx, touch_obj.get(), "pageX",    coords.page_x as f64);
+        set_double_property(cx, touch_obj.get(), "pageY",    coords.page_y as f64);
+        set_double_property(cx, touch_obj.get(), "screenX",  coords.screen_x as f64);
+        set_double_property(cx, touch_obj.get(), "screenY",  coords.screen_y as f64);
+        set_double_property(cx, touch_obj.get(), "force",    touch.force);
+        set_double_property(cx, touch_obj.get(), "radiusX",  1.0);
+        set_double_property(cx, touch_obj.get(), "radiusY",  1.0);
+        set_double_property(cx, touch_obj.get(), "rotationAngle", 0.0);
+
+        rooted!(in(raw_cx) let target = make_target_proxy(cx, touch.target));
+        if !target.get().is_null() {
+            rooted!(in(raw_cx) let target_val = ObjectValue(target.get()));
+            let cname = CString::new("target").unwrap();
+            JS_DefineProperty(cx, touch_obj.handle().into(), cname.as_ptr(),
+                target_val.handle().into(), JSPROP_ENUMERATE as u32);
+        }
+
+        rooted!(in(raw_cx) let touch_val = ObjectValue(touch_obj.get()));
+        JS_SetElement(raw_cx, list.handle().into(), index as u32, touch_val.handle().into());
+    }
+    list.get()
+}
+
 /// Set an object-valued property with `__nodeId` as a target/currentTarget stub.
 unsafe fn make_target_proxy(cx: &mut SafeJSContext, node_id: usize) -> *mut JSObject {
     let raw_cx = cx.raw_cx();
@@ -545,10 +613,7 @@ unsafe fn make_target_proxy(cx: &mut SafeJSContext, node_id: usize) -> *mut JSOb
     }
 
     if t.get().is_null() { return std::ptr::null_mut(); }
-    rooted!(in(raw_cx) let id_val = DoubleValue(node_id as f64));
-    let cname = CString::new("__nodeId").unwrap();
-    JS_DefineProperty(cx, t.handle().into(), cname.as_ptr(),
-        id_val.handle().into(), 0);
+    registry::set_node_id(cx, t.get(), node_id);
 
     // Expose common target fields (notably `value`) expected by form/input listeners.
     DOM_REF.with(|dom_ref| {
@@ -923,6 +988,107 @@ pub fn fire_load_events(dom: &Dom) {
     });
 }
 
+/// Fire a `scroll` event on each target that scrolled since the last frame. The root node stands
+/// for the viewport, whose event goes to the document and then window; element scroll events
+/// don't bubble.
+pub fn fire_scroll_events(dom: &Dom, targets: &[usize]) {
+    let rt_ptr = RUNTIME.with(|cell| *cell.borrow());
+    let Some(rt_ptr) = rt_ptr else { return; };
+    let rt = unsafe { &mut *rt_ptr };
+    let root_id = dom.root_node().id;
+
+    rt.do_with_jsapi(|cx, global| unsafe {
+        let raw_cx = cx.raw_cx();
+        for &target in targets {
+            EVENT_DEFAULT_PREVENTED.set(false);
+            EVENT_PROPAGATION_STOPPED.set(false);
+            EVENT_IMMEDIATE_STOPPED.set(false);
+            rooted!(in(raw_cx) let scroll_obj = JS_NewPlainObject(cx));
+            if scroll_obj.get().is_null() {
+                continue;
+            }
+            let is_document = target == root_id;
+            let _ = set_string_property(cx, scroll_obj.get(), "type",    "scroll");
+            let _ = set_bool_property(cx, scroll_obj.get(),   "bubbles", is_document);
+            let _ = set_bool_property(cx, scroll_obj.get(),   "cancelable", false);
+            let _ = set_bool_property(cx, scroll_obj.get(),   "isTrusted", true);
+            let _ = define_function(cx, scroll_obj.get(), "stopPropagation",         Some(js_stop_propagation), 0);
+            let _ = define_function(cx, scroll_obj.get(), "stopImmediatePropagation",Some(js_stop_immediate_propagation), 0);
+            let _ = define_function(cx, scroll_obj.get(), "preventDefault",          Some(js_prevent_default), 0);
+            if is_document {
+                set_event_target(cx, scroll_obj.get(), DOCUMENT_NODE_ID);
+                fire_on_node(cx, global.get(), DOCUMENT_NODE_ID, scroll_obj.get(), "scroll", false, true);
+                if !EVENT_PROPAGATION_STOPPED.get() {
+                    fire_on_node(cx, global.get(), WINDOW_NODE_ID, scroll_obj.get(), "scroll", false, false);
+                }
+            } else {
+                set_event_target(cx, scroll_obj.get(), target);
+                fire_on_node(cx, global.get(), target, scroll_obj.get(), "scroll", false, true);
+            }
+        }
+    });
+}
+
+/// Fire `selectionchange` at the document once a frame when the selection moved during it
+pub fn fire_selection_change() {
+    let rt_ptr = RUNTIME.with(|cell| *cell.borrow());
+    let Some(rt_ptr) = rt_ptr else { return; };
+    let rt = unsafe { &mut *rt_ptr };
+
+    rt.do_with_jsapi(|cx, global| unsafe {
+        let raw_cx = cx.raw_cx();
+        EVENT_DEFAULT_PREVENTED.set(false);
+        EVENT_PROPAGATION_STOPPED.set(false);
+        EVENT_IMMEDIATE_STOPPED.set(false);
+        rooted!(in(raw_cx) let change_obj = JS_NewPlainObject(cx));
+        if change_obj.get().is_null() {
+            return;
+        }
+        let _ = set_string_property(cx, change_obj.get(), "type",    "selectionchange");
+        let _ = set_bool_property(cx, change_obj.get(),   "bubbles", false);
+        let _ = set_bool_property(cx, change_obj.get(),   "cancelable", false);
+        let _ = set_bool_property(cx, change_obj.get(),   "isTrusted", true);
+        let _ = define_function(cx, change_obj.get(), "stopPropagation",         Some(js_stop_propagation), 0);
+        let _ = define_function(cx, change_obj.get(), "stopImmediatePropagation",Some(js_stop_immediate_propagation), 0);
+        let _ = define_function(cx, change_obj.get(), "preventDefault",          Some(js_prevent_default), 0);
+        set_event_target(cx, change_obj.get(), DOCUMENT_NODE_ID);
+        fire_on_node(cx, global.get(), DOCUMENT_NODE_ID, change_obj.get(), "selectionchange", false, true);
+    });
+}
+
+/// Fire `toggle` at each details element that opened or closed since the last frame, with
+/// `newState` and `oldState` saying which way it went. Like scroll events these don't bubble.
+pub fn fire_toggle_events(targets: &[(usize, bool)]) {
+    let rt_ptr = RUNTIME.with(|cell| *cell.borrow());
+    let Some(rt_ptr) = rt_ptr else { return; };
+    let rt = unsafe { &mut *rt_ptr };
+
+    rt.do_with_jsapi(|cx, global| unsafe {
+        let raw_cx = cx.raw_cx();
+        for &(target, open) in targets {
+            EVENT_DEFAULT_PREVENTED.set(false);
+            EVENT_PROPAGATION_STOPPED.set(false);
+            EVENT_IMMEDIATE_STOPPED.set(false);
+            rooted!(in(raw_cx) let toggle_obj = JS_NewPlainObject(cx));
+            if toggle_obj.get().is_null() {
+                continue;
+            }
+            let (new_state, old_state) = if open { ("open", "closed") } else { ("closed", "open") };
+            let _ = set_string_property(cx, toggle_obj.get(), "type",     "toggle");
+            let _ = set_string_property(cx, toggle_obj.get(), "newState", new_state);
+            let _ = set_string_property(cx, toggle_obj.get(), "oldState", old_state);
+            let _ = set_bool_property(cx, toggle_obj.get(),   "bubbles", false);
+            let _ = set_bool_property(cx, toggle_obj.get(),   "cancelable", false);
+            let _ = set_bool_property(cx, toggle_obj.get(),   "isTrusted", true);
+            let _ = define_function(cx, toggle_obj.get(), "stopPropagation",         Some(js_stop_propagation), 0);
+            let _ = define_function(cx, toggle_obj.get(), "stopImmediatePropagation",Some(js_stop_immediate_propagation), 0);
+            let _ = define_function(cx, toggle_obj.get(), "preventDefault",          Some(js_prevent_default), 0);
+            set_event_target(cx, toggle_obj.get(), target);
+            fire_on_node(cx, global.get(), target, toggle_obj.get(), "toggle", false, true);
+        }
+    });
+}
+
 // ── JsEventHandler ─────────────────────────────────────────────────────────────
 
 /// An [`EventHandler`] that fires registered JavaScript event listeners for
@@ -937,6 +1103,11 @@ impl EventHandler for JsEventHandler {
         _doc: &mut Dom,
         event_state: &mut EventState,
     ) {
+        // Scroll events are batched and fired once per frame by `fire_scroll_events`
+        if matches!(event.data, DomEventData::Scroll(_)) {
+            return;
+        }
+
         // Extract the runtime pointer without keeping the borrow alive.
         let rt_ptr = RUNTIME.with(|cell| *cell.borrow());
         let Some(rt_ptr) = rt_ptr else { return; };
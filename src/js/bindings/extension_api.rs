@@ -0,0 +1,486 @@
+// The `browser` (and `chrome`) object WebExtension content scripts and background pages get:
+// runtime.id/getManifest/getURL, runtime.sendMessage/onMessage and storage.local
+//
+// Content scripts share the page's global, so nothing of the API is left on it. The setup script
+// runs before any page script, keeps the natives and the built-ins it relies on in a closure, and
+// hands the closure's entry point to Rust. Content scripts are then run by calling that entry
+// point, which passes them their `browser` object as an argument.
+use crate::extensions::{storage, ExtensionStore};
+use crate::js::bindings::dom_bindings::DOM_REF;
+use crate::js::helpers::{ToSafeCx, create_js_string, define_function, js_value_to_string};
+use crate::js::jsapi::promise::PersistentRooted;
+use crate::js::{JsResult, JsRuntime};
+use crate::shell_provider::ShellProviderMessage;
+use mozjs::context::JSContext as SafeJSContext;
+use mozjs::jsapi::{CallArgs, CurrentGlobalOrNull, HandleValueArray, JSContext};
+use mozjs::jsval::{JSVal, ObjectValue, UndefinedValue};
+use mozjs::rooted;
+use mozjs::rust::ValueArray;
+use mozjs::rust::wrappers2::{JS_CallFunctionValue, JS_ClearPendingException};
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::os::raw::c_uint;
+
+thread_local! {
+    /// Extensions whose content scripts were injected into the current document, or whose page it
+    /// is. Only these get a `browser` object in this document.
+    static ALLOWED_EXTENSIONS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+
+    /// The setup script's entry point, `entry(method, first, second, third)`. Only Rust holds it.
+    static ENTRY: RefCell<Option<PersistentRooted>> = const { RefCell::new(None) };
+}
+
+/// Let the current document's scripts use the API of these extensions
+pub fn set_allowed_extensions(ids: Vec<String>) {
+    ALLOWED_EXTENSIONS.set(ids);
+}
+
+fn is_allowed(id: &str) -> bool {
+    ALLOWED_EXTENSIONS.with(|allowed| allowed.borrow().iter().any(|allowed| allowed == id))
+}
+
+/// Work for the extension API of the current document, queued like scripts so it runs in order
+/// with them
+#[derive(Debug, Clone)]
+pub enum ExtensionCall {
+    /// Run a content script (or an extension page's prelude) with its extension's `browser` object
+    RunScript { extension_id: String, source: String },
+    /// The background page's answer to this document's `runtime.sendMessage` call `request_id`
+    Resolve { request_id: u64, response: Result<Option<String>, String> },
+    /// A message from a content script, for this background page's `runtime.onMessage` listeners
+    Dispatch { request_id: u64, message: String, sender: String },
+}
+
+/// Install the extension API in a closure the page can't reach. Messages go to the parent, which
+/// hands them to the extension's background page; answers come back through [`call`].
+pub fn setup_extension_api(runtime: &mut JsRuntime) -> JsResult<()> {
+    ENTRY.with(|entry| *entry.borrow_mut() = None);
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesExtensionRegister", Some(stokes_extension_register), 1)?;
+        define_function(cx, global.get(), "__stokesExtensionManifest", Some(stokes_extension_manifest), 1)?;
+        define_function(cx, global.get(), "__stokesExtensionStorage", Some(stokes_extension_storage), 3)?;
+        define_function(cx, global.get(), "__stokesExtensionSendMessage", Some(stokes_extension_send_message), 3)?;
+        define_function(cx, global.get(), "__stokesExtensionRespond", Some(stokes_extension_respond), 3)
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            const register = root.__stokesExtensionRegister;
+            const natives = {
+                manifest: root.__stokesExtensionManifest,
+                storage: root.__stokesExtensionStorage,
+                sendMessage: root.__stokesExtensionSendMessage,
+                respond: root.__stokesExtensionRespond,
+            };
+            delete root.__stokesExtensionRegister;
+            delete root.__stokesExtensionManifest;
+            delete root.__stokesExtensionStorage;
+            delete root.__stokesExtensionSendMessage;
+            delete root.__stokesExtensionRespond;
+            if (typeof register !== 'function') {
+                return;
+            }
+
+            // The page may replace globals and prototype methods later, so use the originals
+            const apply = Reflect.apply;
+            const FunctionCtor = Function;
+            const PromiseCtor = Promise;
+            const promiseThen = Promise.prototype.then;
+            const jsonParse = JSON.parse;
+            const jsonStringify = JSON.stringify;
+            const objectKeys = Object.keys;
+            const hasOwn = Object.prototype.hasOwnProperty;
+            const isArray = Array.isArray;
+            const ErrorCtor = Error;
+            const StringCtor = String;
+
+            // Plain objects without a prototype and index loops, so no lookup goes through
+            // anything the page can patch
+            const apis = Object.create(null);
+            const pending = Object.create(null);
+            const listeners = [];
+            let nextRequestId = 1;
+
+            function toJson(value) {
+                return jsonStringify(value === undefined ? null : value);
+            }
+
+            function fromJson(json) {
+                return typeof json === 'string' ? jsonParse(json) : undefined;
+            }
+
+            function then(promise, onFulfilled, onRejected) {
+                return apply(promiseThen, promise, [onFulfilled, onRejected]);
+            }
+
+            function has(object, key) {
+                return apply(hasOwn, object, [key]);
+            }
+
+            function indexOf(list, item) {
+                for (let i = 0; i < list.length; i++) {
+                    if (list[i] === item) {
+                        return i;
+                    }
+                }
+                return -1;
+            }
+
+            // Call `callback` with the result instead of returning the promise, for chrome.* style callers
+            function withCallback(promise, callback) {
+                if (typeof callback !== 'function') {
+                    return promise;
+                }
+                then(promise, function(value) { callback(value); }, function(error) {
+                    console.error(error);
+                    callback();
+                });
+                return undefined;
+            }
+
+            function resolve(requestId, response, error) {
+                const request = pending[requestId];
+                if (!request) {
+                    return;
+                }
+                delete pending[requestId];
+                if (typeof error === 'string') {
+                    request.reject(new ErrorCtor(error));
+                } else {
+                    request.resolve(fromJson(response));
+                }
+            }
+
+            // A message from a content script, in a background page. The first listener to call
+            // sendResponse or settle the promise it returned answers it.
+            function dispatch(requestId, message, sender) {
+                let responded = false;
+                let waiting = false;
+                const respond = function(value) {
+                    if (!responded) {
+                        responded = true;
+                        natives.respond(requestId, value === undefined ? null : toJson(value));
+                    }
+                };
+                const fail = function(error) {
+                    if (!responded) {
+                        responded = true;
+                        natives.respond(requestId, null, StringCtor(error && error.message || error));
+                    }
+                };
+                const parsedMessage = fromJson(message);
+                const parsedSender = fromJson(sender);
+                const current = [];
+                for (let i = 0; i < listeners.length; i++) {
+                    current[i] = listeners[i];
+                }
+                for (let i = 0; i < current.length; i++) {
+                    let result;
+                    try {
+                        result = current[i](parsedMessage, parsedSender, respond);
+                    } catch (error) {
+                        console.error(error);
+                        continue;
+                    }
+                    if (result && typeof result.then === 'function') {
+                        waiting = true;
+                        then(result, respond, fail);
+                    } else if (result === true) {
+                        // The listener will call sendResponse later
+                        waiting = true;
+                    }
+                }
+                if (!waiting) {
+                    respond(undefined);
+                }
+            }
+
+            function apiFor(id) {
+                if (has(apis, id)) {
+                    return apis[id];
+                }
+                const manifestJson = natives.manifest(id);
+                if (typeof manifestJson !== 'string') {
+                    return undefined;
+                }
+
+                function storedItems() {
+                    return fromJson(natives.storage(id, 'get', null)) || {};
+                }
+
+                function pick(items, keys) {
+                    if (keys === null || keys === undefined) {
+                        return items;
+                    }
+                    const result = {};
+                    if (typeof keys === 'string') {
+                        keys = [keys];
+                    }
+                    if (isArray(keys)) {
+                        for (let i = 0; i < keys.length; i++) {
+                            if (has(items, keys[i])) {
+                                result[keys[i]] = items[keys[i]];
+                            }
+                        }
+                        return result;
+                    }
+                    // An object gives defaults for missing keys
+                    const names = objectKeys(keys);
+                    for (let i = 0; i < names.length; i++) {
+                        const key = names[i];
+                        result[key] = has(items, key) ? items[key] : keys[key];
+                    }
+                    return result;
+                }
+
+                function storageCall(callback, work) {
+                    return withCallback(new PromiseCtor(function(resolve) { resolve(work()); }), callback);
+                }
+
+                const local = {
+                    get(keys, callback) {
+                        if (typeof keys === 'function') {
+                            callback = keys;
+                            keys = null;
+                        }
+                        return storageCall(callback, function() { return pick(storedItems(), keys); });
+                    },
+                    set(items, callback) {
+                        return storageCall(callback, function() {
+                            natives.storage(id, 'set', toJson(items || {}));
+                        });
+                    },
+                    remove(keys, callback) {
+                        return storageCall(callback, function() {
+                            natives.storage(id, 'remove', toJson(typeof keys === 'string' ? [keys] : (keys || [])));
+                        });
+                    },
+                    clear(callback) {
+                        return storageCall(callback, function() {
+                            natives.storage(id, 'clear', null);
+                        });
+                    },
+                };
+
+                const runtime = {
+                    id: id,
+                    getManifest() {
+                        return jsonParse(manifestJson);
+                    },
+                    getURL(path) {
+                        return 'stokes-extension://' + id + '/' + StringCtor(path || '').replace(/^\/+/, '');
+                    },
+                    // sendMessage([extensionId,] message [, options] [, callback])
+                    sendMessage(...args) {
+                        const callback = typeof args[args.length - 1] === 'function' ? args[args.length - 1] : undefined;
+                        const count = callback ? args.length - 1 : args.length;
+                        const message = count >= 2 && typeof args[0] === 'string' ? args[1] : args[0];
+                        const promise = new PromiseCtor(function(resolve, reject) {
+                            const requestId = nextRequestId++;
+                            pending[requestId] = { resolve: resolve, reject: reject };
+                            natives.sendMessage(id, requestId, toJson(message));
+                        });
+                        return withCallback(promise, callback);
+                    },
+                    onMessage: {
+                        addListener(listener) {
+                            if (typeof listener === 'function' && indexOf(listeners, listener) < 0) {
+                                listeners[listeners.length] = listener;
+                            }
+                        },
+                        removeListener(listener) {
+                            const index = indexOf(listeners, listener);
+                            if (index >= 0) {
+                                for (let i = index; i < listeners.length - 1; i++) {
+                                    listeners[i] = listeners[i + 1];
+                                }
+                                listeners.length -= 1;
+                            }
+                        },
+                        hasListener(listener) {
+                            return indexOf(listeners, listener) >= 0;
+                        },
+                    },
+                };
+
+                const api = { runtime: runtime, storage: { local: local } };
+                apis[id] = api;
+                return api;
+            }
+
+            register(function(method, first, second, third) {
+                switch (method) {
+                    case 'run': {
+                        const api = apiFor(first);
+                        if (api !== undefined) {
+                            apply(new FunctionCtor('browser', 'chrome', second), root, [api, api]);
+                        }
+                        break;
+                    }
+                    case 'resolve':
+                        resolve(+first, second, third);
+                        break;
+                    case 'dispatch':
+                        dispatch(+first, second, third);
+                        break;
+                }
+            });
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+/// Hand `call` to the current document's extension API, then run the jobs it queued
+pub fn call(runtime: &mut JsRuntime, call: ExtensionCall) {
+    let (method, first, second, third) = match call {
+        ExtensionCall::RunScript { extension_id, source } => ("run", Some(extension_id), Some(source), None),
+        ExtensionCall::Resolve { request_id, response } => match response {
+            Ok(response) => ("resolve", Some(request_id.to_string()), response, None),
+            Err(error) => ("resolve", Some(request_id.to_string()), None, Some(error)),
+        },
+        ExtensionCall::Dispatch { request_id, message, sender } => ("dispatch", Some(request_id.to_string()), Some(message), Some(sender)),
+    };
+    let Some(entry) = ENTRY.with(|entry| entry.borrow().as_ref().map(PersistentRooted::get)) else {
+        return;
+    };
+
+    runtime.do_with_jsapi(|cx, _global| unsafe {
+        let raw_cx = cx.raw_cx();
+        rooted!(in(raw_cx) let entry = ObjectValue(entry));
+        rooted!(in(raw_cx) let method = create_js_string(cx, method));
+        rooted!(in(raw_cx) let first = optional_string(cx, first));
+        rooted!(in(raw_cx) let second = optional_string(cx, second));
+        rooted!(in(raw_cx) let third = optional_string(cx, third));
+        rooted!(in(raw_cx) let args = ValueArray::<4usize>::new([method.get(), first.get(), second.get(), third.get()]));
+        rooted!(in(raw_cx) let global = CurrentGlobalOrNull(raw_cx));
+        rooted!(in(raw_cx) let mut rval = UndefinedValue());
+        if !JS_CallFunctionValue(
+            cx,
+            global.handle().into(),
+            entry.handle().into(),
+            &HandleValueArray::from(&args),
+            rval.handle_mut().into(),
+        ) {
+            tracing::warn!("Extension script failed");
+            JS_ClearPendingException(cx);
+        }
+    });
+    runtime.run_pending_jobs();
+}
+
+unsafe fn optional_string(cx: &mut SafeJSContext, value: Option<String>) -> JSVal {
+    value.map_or(UndefinedValue(), |value| create_js_string(cx, &value))
+}
+
+unsafe fn string_arg(raw_cx: *mut JSContext, args: &CallArgs, index: u32) -> Option<String> {
+    if index >= args.argc_ {
+        return None;
+    }
+    let value = *args.get(index);
+    if value.is_null() || value.is_undefined() {
+        return None;
+    }
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    Some(js_value_to_string(safe_cx, value))
+}
+
+fn send_to_parent(message: ShellProviderMessage) {
+    DOM_REF.with(|dom| {
+        if let Some(dom) = *dom.borrow() {
+            let dom = unsafe { &*dom };
+            let _ = dom.shell_provider.sender.send(message);
+        }
+    });
+}
+
+unsafe extern "C" fn stokes_extension_manifest(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let manifest = string_arg(raw_cx, &args, 0)
+        .filter(|id| is_allowed(id))
+        .and_then(|id| ExtensionStore::load_from_disk().web_extension(&id).and_then(|extension| serde_json::to_string(&extension.manifest).ok()));
+    match manifest {
+        Some(manifest) => {
+            let safe_cx = &mut raw_cx.to_safe_cx();
+            args.rval().set(create_js_string(safe_cx, &manifest));
+        }
+        None => args.rval().set(UndefinedValue()),
+    }
+    true
+}
+
+unsafe extern "C" fn stokes_extension_storage(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let Some(id) = string_arg(raw_cx, &args, 0).filter(|id| is_allowed(id)) else {
+        args.rval().set(UndefinedValue());
+        return true;
+    };
+    let operation = string_arg(raw_cx, &args, 1).unwrap_or_default();
+    let argument = string_arg(raw_cx, &args, 2).unwrap_or_default();
+    match operation.as_str() {
+        "get" => {
+            let items = Value::Object(storage::read(&id)).to_string();
+            let safe_cx = &mut raw_cx.to_safe_cx();
+            args.rval().set(create_js_string(safe_cx, &items));
+            return true;
+        }
+        "set" => {
+            if let Ok(items) = serde_json::from_str::<Map<String, Value>>(&argument) {
+                storage::set(&id, items);
+            }
+        }
+        "remove" => {
+            if let Ok(keys) = serde_json::from_str::<Vec<String>>(&argument) {
+                storage::remove(&id, &keys);
+            }
+        }
+        "clear" => storage::clear(&id),
+        _ => {}
+    }
+    args.rval().set(UndefinedValue());
+    true
+}
+
+unsafe extern "C" fn stokes_extension_send_message(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    if argc < 3 || !args.get(1).is_number() {
+        args.rval().set(UndefinedValue());
+        return true;
+    }
+    let request_id = args.get(1).to_number() as u64;
+    if let Some(extension_id) = string_arg(raw_cx, &args, 0).filter(|id| is_allowed(id)) {
+        let message = string_arg(raw_cx, &args, 2).unwrap_or_else(|| "null".to_string());
+        send_to_parent(ShellProviderMessage::ExtensionMessage { extension_id, request_id, message });
+    }
+    args.rval().set(UndefinedValue());
+    true
+}
+
+unsafe extern "C" fn stokes_extension_respond(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    if argc < 1 || !args.get(0).is_number() {
+        args.rval().set(UndefinedValue());
+        return true;
+    }
+    let request_id = args.get(0).to_number() as u64;
+    let response = match string_arg(raw_cx, &args, 2) {
+        Some(error) => Err(error),
+        None => Ok(string_arg(raw_cx, &args, 1)),
+    };
+    send_to_parent(ShellProviderMessage::ExtensionResponse { request_id, response });
+    args.rval().set(UndefinedValue());
+    true
+}
+
+unsafe extern "C" fn stokes_extension_register(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    if argc > 0 && args.get(0).is_object() {
+        let safe_cx = &mut raw_cx.to_safe_cx();
+        let mut entry = PersistentRooted::new();
+        entry.init(safe_cx, args.get(0).to_object());
+        ENTRY.with(|slot| *slot.borrow_mut() = Some(entry));
+    }
+    args.rval().set(UndefinedValue());
+    true
+}
@@ -1,12 +1,19 @@
 // Fetch API implementation for JavaScript using mozjs
 // Provides the global fetch() function and Response object
 
+use crate::engine::mixed_content::ContentKind;
 use crate::js::bindings::dom_bindings::{DOM_REF, USER_AGENT};
 use crate::js::helpers::{js_value_to_string, ToSafeCx};
 use crate::js::jsapi::js_promise::{JsPromise, JsPromiseBuilder};
 use crate::js::runtime_context::{current_document_base_url, current_net_provider_and_source_url, current_user_agent};
 use crate::js::JsRuntime;
-use curl::easy::{Easy, List};
+use crate::languages;
+use crate::engine::net_provider::StokesNetProvider;
+use crate::networking::{blob_store, hsts, http_auth, request_policy};
+use crate::networking::referrer::{ReferrerPolicy, RequestReferrer};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use curl::easy::Easy;
 use mozjs::context::JSContext as SafeJSContext;
 use mozjs::conversions::jsstr_to_string;
 use mozjs::gc::Handle;
@@ -24,7 +31,7 @@ use std::collections::HashMap;
 use std::ffi::CString;
 use std::os::raw::c_uint;
 use std::ptr::NonNull;
-use std::time::Duration;
+use std::sync::Arc;
 use tracing::warn;
 use url::Url;
 
@@ -104,11 +111,15 @@ unsafe extern "C" fn js_fetch(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSV
         Ok(v) => v,
         Err(msg) => return create_rejected_promise(safe_cx, MutableHandleValue::from_raw(args.rval()), &msg),
     };
+    if is_blocked_mixed_content(&url) {
+        return create_rejected_promise(safe_cx, MutableHandleValue::from_raw(args.rval()), "TypeError: Failed to fetch (blocked:mixed-content)");
+    }
 
     // Parse request options (method, headers, body)
     let mut method = String::from("GET");
     let mut request_headers: HashMap<String, String> = HashMap::new();
-    let mut request_body: Option<String> = None;
+    let mut request_body: Option<Vec<u8>> = None;
+    let mut referrer_policy = None;
 
     if argc > 1 && args.get(1).is_object() {
         let options_obj = args.get(1).to_object();
@@ -128,17 +139,28 @@ unsafe extern "C" fn js_fetch(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSV
             }
         }
 
-        // Get body
-        if let Some(body) = get_string_property(safe_cx, options.handle(), "body") {
-            request_body = Some(body);
+        // Get body. Binary bodies (Blob, FormData, buffers) arrive base64-encoded from the JS wrapper.
+        if let Some(encoded) = get_string_property(safe_cx, options.handle(), "__stokesBodyBase64") {
+            match STANDARD.decode(encoded.as_bytes()) {
+                Ok(bytes) => request_body = Some(bytes),
+                Err(_) => return create_rejected_promise(safe_cx, MutableHandleValue::from_raw(args.rval()), "fetch: invalid request body"),
+            }
+        } else if let Some(body) = get_string_property(safe_cx, options.handle(), "body") {
+            request_body = Some(body.into_bytes());
+        }
+
+        // An empty or unknown referrerPolicy falls back to the document's policy
+        if let Some(policy) = get_string_property(safe_cx, options.handle(), "referrerPolicy") {
+            referrer_policy = ReferrerPolicy::parse(&policy);
         }
     }
+    let referrer = take_referrer(referrer_policy, &mut request_headers);
 
     // Create a Promise for the fetch operation using shared Promise helpers.
     let promise = match JsPromise::new(safe_cx) {
         Ok(promise) => promise,
         Err(e) => {
-            eprintln!("fetch: failed to create promise: {}", e.message);
+            tracing::warn!("fetch: failed to create promise: {}", e.message);
             args.rval().set(UndefinedValue());
             return false;
         }
@@ -148,7 +170,7 @@ unsafe extern "C" fn js_fetch(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSV
     let user_agent = current_user_agent().unwrap_or_else(|| USER_AGENT.with(|ua| ua.borrow().clone()));
 
     // Perform the fetch synchronously (for now - could be made async later)
-    let result = perform_fetch(&url, &method, &request_headers, request_body.as_deref(), &user_agent);
+    let result = perform_fetch(&url, &method, &request_headers, request_body.as_deref(), &user_agent, referrer);
 
     // Resolve or reject the promise using the current context
     match result {
@@ -428,11 +450,16 @@ fn perform_fetch(
     url: &str,
     method: &str,
     headers: &HashMap<String, String>,
-    body: Option<&str>,
+    body: Option<&[u8]>,
     user_agent: &str,
+    referrer: Option<RequestReferrer>,
 ) -> Result<FetchResponse, String> {
-    // Parse URL
-    let parsed_url = Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+    // Parse URL; HSTS hosts are only contacted over https
+    let mut parsed_url = Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+    if let Some(secure_url) = hsts::upgrade(&parsed_url) {
+        parsed_url = secure_url;
+    }
+    let url = parsed_url.as_str();
 
     if matches!(parsed_url.scheme(), "http" | "https") && is_fetch_blocked(url) {
         return Err(format!("Blocked by adblock filter: {url}"));
@@ -456,6 +483,22 @@ fn perform_fetch(
         });
     }
 
+    // blob: URLs are served from memory
+    if parsed_url.scheme() == "blob" {
+        let entry = blob_store::resolve(url).ok_or_else(|| format!("Failed to fetch: {url} is not a valid blob URL"))?;
+        let mut headers = HashMap::new();
+        headers.insert("content-type".to_string(), entry.content_type.clone());
+        headers.insert("content-length".to_string(), entry.bytes.len().to_string());
+        return Ok(FetchResponse {
+            status: 200,
+            status_text: "OK".to_string(),
+            headers,
+            body: String::from_utf8_lossy(&entry.bytes).into_owned(),
+            url: url.to_string(),
+            ok: true,
+        });
+    }
+
     // Perform HTTP request using curl
     let mut easy = Easy::new();
     let mut response_data = Vec::new();
@@ -464,9 +507,11 @@ fn perform_fetch(
     // Configure curl
     easy.url(url).map_err(|e| format!("Curl error: {}", e))?;
     easy.useragent(user_agent).map_err(|e| format!("Curl error: {}", e))?;
-    easy.timeout(Duration::from_secs(30)).map_err(|e| format!("Curl error: {}", e))?;
-    easy.follow_location(true).map_err(|e| format!("Curl error: {}", e))?;
-    easy.max_redirections(5).map_err(|e| format!("Curl error: {}", e))?;
+    let policy = current_net_provider().map(|net_provider| net_provider.request_policy().clone()).unwrap_or_default();
+    policy.configure(&mut easy).map_err(|e| format!("Curl error: {}", e))?;
+    http_auth::configure(&mut easy, url).map_err(|e| format!("Curl error: {}", e))?;
+    // Redirects are followed below rather than by curl (see hsts::Redirects)
+    easy.follow_location(false).map_err(|e| format!("Curl error: {}", e))?;
 
     // Set method
     match method {
@@ -475,7 +520,7 @@ fn perform_fetch(
             easy.post(true).map_err(|e| format!("Curl error: {}", e))?;
         },
         "PUT" => {
-            easy.put(true).map_err(|e| format!("Curl error: {}", e))?;
+            easy.custom_request("PUT").map_err(|e| format!("Curl error: {}", e))?;
         },
         "DELETE" => {
             easy.custom_request("DELETE").map_err(|e| format!("Curl error: {}", e))?;
@@ -495,39 +540,69 @@ fn perform_fetch(
     }
 
     // Set request headers
-    let mut header_list = List::new();
-    for (key, value) in headers {
-        let header = format!("{}: {}", key, value);
-        header_list.append(&header).map_err(|e| format!("Curl error: {}", e))?;
+    let mut header_lines: Vec<String> = headers.iter().map(|(key, value)| format!("{}: {}", key, value)).collect();
+    // Scripts may pick their own Accept-Language; otherwise it's the user's
+    if !headers.keys().any(|key| key.eq_ignore_ascii_case("accept-language")) {
+        header_lines.push(languages::accept_language_header());
+    }
+    let mut redirects = hsts::Redirects::new(&parsed_url, method, header_lines, referrer);
+    if let Some(check) = current_net_provider().and_then(|net_provider| net_provider.mixed_content_check(ContentKind::Active)) {
+        redirects = redirects.with_filter(move |url| check.filter(url));
     }
+    let header_list = redirects.header_list().map_err(|e| format!("Curl error: {}", e))?;
     easy.http_headers(header_list).map_err(|e| format!("Curl error: {}", e))?;
 
-    // Set request body
+    // Set request body. Methods other than POST keep their verb through custom_request above.
     if let Some(body_data) = body {
-        let body_bytes = body_data.as_bytes().to_vec();
-        easy.post_field_size(body_bytes.len() as u64).map_err(|e| format!("Curl error: {}", e))?;
+        easy.post_fields_copy(body_data).map_err(|e| format!("Curl error: {}", e))?;
     }
 
-    // Perform the transfer
-    {
-        let mut transfer = easy.transfer();
-
-        transfer.write_function(|data| {
-            response_data.extend_from_slice(data);
-            Ok(data.len())
-        }).map_err(|e| format!("Curl error: {}", e))?;
-
-        transfer.header_function(|header| {
-            let header_str = String::from_utf8_lossy(header).trim().to_string();
-            if let Some(colon_pos) = header_str.find(':') {
-                let key = header_str[..colon_pos].trim().to_lowercase();
-                let value = header_str[colon_pos + 1..].trim().to_string();
-                response_headers.insert(key, value);
+    // Perform the transfer; idempotent requests are retried after connection failures
+    loop {
+        let mut attempt = 0;
+        loop {
+            response_data.clear();
+            response_headers.clear();
+            let result = {
+                let mut transfer = easy.transfer();
+
+                transfer.write_function(|data| {
+                    response_data.extend_from_slice(data);
+                    Ok(data.len())
+                }).map_err(|e| format!("Curl error: {}", e))?;
+
+                transfer.header_function(|header| {
+                    let header_str = String::from_utf8_lossy(header).trim().to_string();
+                    if let Some(colon_pos) = header_str.find(':') {
+                        let key = header_str[..colon_pos].trim().to_lowercase();
+                        let value = header_str[colon_pos + 1..].trim().to_string();
+                        response_headers.insert(key, value);
+                    }
+                    true
+                }).map_err(|e| format!("Curl error: {}", e))?;
+
+                transfer.perform()
+            };
+            match result {
+                Ok(()) => break,
+                Err(e) if policy.should_retry(redirects.method(), attempt, &e) => {
+                    std::thread::sleep(policy.retry_delay(attempt));
+                    attempt += 1;
+                }
+                Err(e) if request_policy::is_timeout(&e) => return Err(format!("Network error: timed out ({e})")),
+                Err(e) => return Err(format!("Network error: {}", e)),
             }
-            true
-        }).map_err(|e| format!("Curl error: {}", e))?;
+        }
 
-        transfer.perform().map_err(|e| format!("Network error: {}", e))?;
+        let hop_hsts = response_headers.get("strict-transport-security").map(String::as_str);
+        if redirects.follow(&mut easy, hop_hsts).map_err(|e| format!("Network error: {}", e))?.is_none() {
+            break;
+        }
+    }
+
+    if let Some(value) = response_headers.get("strict-transport-security") {
+        let final_url = easy.effective_url().ok().flatten().unwrap_or(url).to_string();
+        hsts::record(&final_url, value);
     }
 
     // Get response status
@@ -576,6 +651,38 @@ fn is_fetch_blocked(url: &str) -> bool {
     })
 }
 
+/// The net provider of the document the running script belongs to
+fn current_net_provider() -> Option<Arc<StokesNetProvider>> {
+    current_net_provider_and_source_url()
+        .map(|(net_provider, _)| net_provider)
+        .or_else(|| {
+            DOM_REF.with(|dom_ref| {
+                let dom_ptr = (*dom_ref.borrow())?;
+                if dom_ptr.is_null() {
+                    return None;
+                }
+                let dom = unsafe { &*dom_ptr };
+                Some(dom.net_provider.clone())
+            })
+        })
+}
+
+/// Whether fetching `url` from the current page is blocked as mixed content
+fn is_blocked_mixed_content(url: &str) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return false;
+    };
+    current_net_provider().is_some_and(|net_provider| net_provider.filter_mixed_content(&url, ContentKind::Active).is_none())
+}
+
+/// Referer and Origin come from the page's referrer policy, for the request and each of its
+/// redirect hops. They are forbidden request headers, so values passed in by the script are dropped.
+fn take_referrer(policy: Option<ReferrerPolicy>, headers: &mut HashMap<String, String>) -> Option<RequestReferrer> {
+    headers.remove("referer");
+    headers.remove("origin");
+    current_net_provider()?.request_referrer(true, policy)
+}
+
 /// Create a Response object from FetchResponse
 unsafe fn create_response_object(cx: &mut SafeJSContext, response: &FetchResponse) -> *mut JSObject {
     let raw_cx = cx.raw_cx();
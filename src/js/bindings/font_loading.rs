@@ -0,0 +1,306 @@
+// CSS Font Loading API (document.fonts, FontFace)
+use crate::js::{JsResult, JsRuntime};
+
+/// Install document.fonts and the FontFace constructor. The tab process reports the document's
+/// `@font-face` loads through `__stokesUpdateFontFaces` as they start and finish, and
+/// `document.fonts.ready` resolves once none are loading. Faces made from script are fetched to
+/// track their status, but aren't drawn with.
+pub fn setup_font_loading(runtime: &mut JsRuntime) -> JsResult<()> {
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            const doc = root.document;
+            if (!doc) {
+                return;
+            }
+
+            const faceState = new WeakMap();
+
+            function networkError(message) {
+                try {
+                    return new DOMException(message, 'NetworkError');
+                } catch (_err) {
+                    return new Error(message);
+                }
+            }
+
+            function FontFace(family, source, descriptors) {
+                if (!(this instanceof FontFace)) {
+                    throw new TypeError("Constructor FontFace requires 'new'");
+                }
+                descriptors = descriptors || {};
+                const state = {
+                    family: String(family),
+                    style: descriptors.style || 'normal',
+                    weight: descriptors.weight || 'normal',
+                    stretch: descriptors.stretch || 'normal',
+                    display: descriptors.display || 'auto',
+                    unicodeRange: descriptors.unicodeRange || 'U+0-10FFFF',
+                    status: 'unloaded',
+                    source: source,
+                };
+                state.loaded = new Promise(function(resolve, reject) {
+                    state.resolve = resolve;
+                    state.reject = reject;
+                });
+                // A face that fails without anyone waiting on it isn't an unhandled rejection
+                state.loaded.catch(function() {});
+                faceState.set(this, state);
+
+                // Binary data is there already; nothing is checked that it's really a font
+                if (source && typeof source !== 'string') {
+                    setFaceStatus(this, 'loaded');
+                }
+            }
+
+            ['family', 'style', 'weight', 'stretch', 'display', 'unicodeRange', 'status', 'loaded'].forEach(function(name) {
+                Object.defineProperty(FontFace.prototype, name, {
+                    get() { return faceState.get(this)[name]; },
+                    configurable: true,
+                });
+            });
+
+            FontFace.prototype.load = function() {
+                const face = this;
+                const state = faceState.get(face);
+                if (state.status !== 'unloaded') {
+                    return state.loaded;
+                }
+                const match = /url\(\s*(['"]?)([^'")]+)\1\s*\)/.exec(String(state.source));
+                if (!match || typeof root.fetch !== 'function') {
+                    setFaceStatus(face, 'error');
+                    return state.loaded;
+                }
+                setFaceStatus(face, 'loading');
+                root.fetch(match[2]).then(function(response) {
+                    setFaceStatus(face, response.ok ? 'loaded' : 'error');
+                }, function() {
+                    setFaceStatus(face, 'error');
+                });
+                return state.loaded;
+            };
+
+            // `quiet` leaves settling the set to the caller, which is changing several faces at once
+            function setFaceStatus(face, status, quiet) {
+                const state = faceState.get(face);
+                if (state.status === status) {
+                    return false;
+                }
+                state.status = status;
+                if (status === 'loaded') {
+                    state.resolve(face);
+                } else if (status === 'error') {
+                    state.reject(networkError('Failed to load font ' + state.family));
+                }
+                if (!quiet && members.has(face)) {
+                    settle(status === 'loaded' ? [face] : [], status === 'error' ? [face] : []);
+                }
+                return true;
+            }
+
+            // The set itself
+            function FontFaceSet() {
+                throw new TypeError('Illegal constructor');
+            }
+            const fonts = Object.create(FontFaceSet.prototype);
+            const members = new Set();
+            const cssFaces = new Map();
+            const listeners = {};
+            // 'loading' until the tab process first reports, which it does once the page has loaded
+            let status = 'loading';
+            let resolveReady = null;
+            let ready = new Promise(function(resolve) { resolveReady = resolve; });
+
+            Object.defineProperties(FontFaceSet.prototype, {
+                status: { get() { return status; }, configurable: true },
+                ready: { get() { return ready; }, configurable: true },
+                size: { get() { return members.size; }, configurable: true },
+            });
+            fonts.onloading = null;
+            fonts.onloadingdone = null;
+            fonts.onloadingerror = null;
+
+            fonts.add = function(face) {
+                if (!(face instanceof FontFace)) {
+                    throw new TypeError('FontFaceSet.add: argument is not a FontFace');
+                }
+                members.add(face);
+                if (face.status === 'loading') {
+                    settle([], []);
+                }
+                return fonts;
+            };
+            fonts.delete = function(face) {
+                // Faces from the document's stylesheets stay
+                for (const cssFace of cssFaces.values()) {
+                    if (cssFace === face) {
+                        return false;
+                    }
+                }
+                return members.delete(face);
+            };
+            fonts.clear = function() {
+                for (const face of Array.from(members)) {
+                    fonts.delete(face);
+                }
+            };
+            fonts.has = function(face) { return members.has(face); };
+            fonts.forEach = function(callback, thisArg) {
+                for (const face of Array.from(members)) {
+                    callback.call(thisArg, face, face, fonts);
+                }
+            };
+            fonts.values = function() { return Array.from(members)[Symbol.iterator](); };
+            fonts.keys = fonts.values;
+            fonts.entries = function() {
+                return Array.from(members).map(function(face) { return [face, face]; })[Symbol.iterator]();
+            };
+            fonts[Symbol.iterator] = fonts.values;
+
+            // The families in a CSS font shorthand such as `bold 16px "Open Sans", serif`
+            function familiesOf(font) {
+                const match = /(?:^|\s)[\d.]+(?:px|pt|pc|em|rem|ex|ch|%|in|cm|mm|q|vw|vh)(?:\s*\/\s*\S+)?\s+(.+)$/i.exec(String(font).trim());
+                if (!match) {
+                    throw new SyntaxError("Could not parse font '" + font + "'");
+                }
+                return match[1].split(',').map(function(family) {
+                    return family.trim().replace(/^(['"])(.*)\1$/, '$2').toLowerCase();
+                });
+            }
+            function matching(font) {
+                const families = familiesOf(font);
+                return Array.from(members).filter(function(face) {
+                    return families.indexOf(face.family.toLowerCase()) >= 0;
+                });
+            }
+
+            fonts.check = function(font, _text) {
+                return matching(font).every(function(face) { return face.status === 'loaded'; });
+            };
+            fonts.load = function(font, _text) {
+                let faces;
+                try {
+                    faces = matching(font);
+                } catch (error) {
+                    return Promise.reject(error);
+                }
+                return Promise.all(faces.map(function(face) {
+                    return faceState.has(face) && face.status === 'unloaded' ? face.load() : face.loaded;
+                }));
+            };
+
+            fonts.addEventListener = function(type, listener) {
+                if (typeof listener !== 'function' && !(listener && typeof listener.handleEvent === 'function')) {
+                    return;
+                }
+                const list = listeners[type] = listeners[type] || [];
+                if (list.indexOf(listener) < 0) {
+                    list.push(listener);
+                }
+            };
+            fonts.removeEventListener = function(type, listener) {
+                listeners[type] = (listeners[type] || []).filter(function(item) { return item !== listener; });
+            };
+            fonts.dispatchEvent = function(event) {
+                const handlers = (listeners[event.type] || []).slice();
+                if (typeof fonts['on' + event.type] === 'function') {
+                    handlers.push(fonts['on' + event.type]);
+                }
+                for (const handler of handlers) {
+                    try {
+                        if (typeof handler === 'function') {
+                            handler.call(fonts, event);
+                        } else {
+                            handler.handleEvent(event);
+                        }
+                    } catch (error) {
+                        console.error(error);
+                    }
+                }
+                return true;
+            };
+
+            function fire(type, faces) {
+                let event;
+                try {
+                    event = new Event(type);
+                } catch (_err) {
+                    event = { type: type };
+                }
+                try {
+                    Object.defineProperty(event, 'target', { value: fonts, configurable: true });
+                    Object.defineProperty(event, 'fontfaces', { value: faces, configurable: true });
+                } catch (_err) {}
+                fonts.dispatchEvent(event);
+            }
+
+            let loadedSinceReady = [];
+            let failedSinceReady = [];
+            let reported = false;
+
+            // Move the set between loading and loaded after its faces changed
+            function settle(loaded, failed) {
+                loadedSinceReady = loadedSinceReady.concat(loaded);
+                failedSinceReady = failedSinceReady.concat(failed);
+                const loading = Array.from(members).some(function(face) { return face.status === 'loading'; });
+                if (loading) {
+                    if (status === 'loaded') {
+                        status = 'loading';
+                        ready = new Promise(function(resolve) { resolveReady = resolve; });
+                        fire('loading', []);
+                    }
+                    return;
+                }
+                if (!reported || !resolveReady) {
+                    return;
+                }
+                status = 'loaded';
+                const done = loadedSinceReady;
+                const errored = failedSinceReady;
+                loadedSinceReady = [];
+                failedSinceReady = [];
+                fire('loadingdone', done);
+                if (errored.length > 0) {
+                    fire('loadingerror', errored);
+                }
+                const resolve = resolveReady;
+                resolveReady = null;
+                resolve(fonts);
+            }
+
+            root.FontFace = FontFace;
+            root.FontFaceSet = FontFaceSet;
+            Object.defineProperty(doc, 'fonts', {
+                get() { return fonts; },
+                configurable: true,
+            });
+
+            // Called by the tab process with every @font-face the document fetched:
+            // [{ family, url, display, status }]
+            root.__stokesUpdateFontFaces = function(faces) {
+                reported = true;
+                const loaded = [];
+                const failed = [];
+                for (const info of faces) {
+                    const key = info.family + '\n' + info.url;
+                    let face = cssFaces.get(key);
+                    if (!face) {
+                        face = new FontFace(info.family, 'url(' + info.url + ')', { display: info.display });
+                        cssFaces.set(key, face);
+                        members.add(face);
+                    }
+                    if (setFaceStatus(face, info.status, true)) {
+                        if (info.status === 'loaded') {
+                            loaded.push(face);
+                        } else if (info.status === 'error') {
+                            failed.push(face);
+                        }
+                    }
+                }
+                settle(loaded, failed);
+            };
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
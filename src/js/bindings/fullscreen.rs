@@ -0,0 +1,149 @@
+// Fullscreen API (element.requestFullscreen / document.exitFullscreen)
+use crate::js::bindings::dom_bindings::DOM_REF;
+use crate::js::helpers::define_function;
+use crate::js::{JsResult, JsRuntime};
+use crate::shell_provider::ShellProviderMessage;
+use mozjs::jsapi::{CallArgs, JSContext};
+use mozjs::jsval::{JSVal, UndefinedValue};
+use std::os::raw::c_uint;
+
+/// Install the Fullscreen API. The browser chrome is hidden through the shell provider,
+/// while the fullscreen element itself is stretched over the viewport with inline styles.
+pub fn setup_fullscreen(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesSetFullscreen", Some(stokes_set_fullscreen), 1)
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            const doc = root.document;
+            if (!doc || typeof root.__stokesSetFullscreen !== 'function') {
+                return;
+            }
+
+            const FULLSCREEN_STYLE = 'position:fixed !important;inset:0 !important;left:0 !important;top:0 !important;'
+                + 'width:100% !important;height:100% !important;margin:0 !important;z-index:2147483647 !important;'
+                + 'background:black;object-fit:contain;';
+
+            let fullscreenElement = null;
+            let savedStyle = null;
+
+            function fireChange(target) {
+                const dispatch = function(node) {
+                    if (!node || typeof node.dispatchEvent !== 'function') {
+                        return;
+                    }
+                    try {
+                        node.dispatchEvent(new Event('fullscreenchange', { bubbles: true }));
+                    } catch (_err) {}
+                };
+                dispatch(target);
+                if (target !== doc) {
+                    dispatch(doc);
+                }
+                const handler = doc.onfullscreenchange;
+                if (typeof handler === 'function') {
+                    try { handler.call(doc, { type: 'fullscreenchange', target: target }); } catch (_err) {}
+                }
+            }
+
+            function leave(notifyShell) {
+                const previous = fullscreenElement;
+                if (!previous) {
+                    return false;
+                }
+                fullscreenElement = null;
+                if (typeof previous.setAttribute === 'function') {
+                    if (savedStyle === null) {
+                        previous.removeAttribute('style');
+                    } else {
+                        previous.setAttribute('style', savedStyle);
+                    }
+                }
+                savedStyle = null;
+                if (notifyShell) {
+                    root.__stokesSetFullscreen(false);
+                }
+                fireChange(previous);
+                return true;
+            }
+
+            function requestFullscreen() {
+                const element = this;
+                if (!element || element.nodeType !== 1) {
+                    return Promise.reject(new TypeError('requestFullscreen called on a non-element'));
+                }
+                if (fullscreenElement === element) {
+                    return Promise.resolve();
+                }
+                if (fullscreenElement) {
+                    leave(false);
+                }
+                fullscreenElement = element;
+                savedStyle = element.getAttribute('style');
+                element.setAttribute('style', (savedStyle ? savedStyle + ';' : '') + FULLSCREEN_STYLE);
+                root.__stokesSetFullscreen(true);
+                fireChange(element);
+                return Promise.resolve();
+            }
+
+            function exitFullscreen() {
+                if (!fullscreenElement) {
+                    return Promise.reject(new TypeError('Document not in fullscreen'));
+                }
+                leave(true);
+                return Promise.resolve();
+            }
+
+            const elementProto = root.Element && root.Element.prototype;
+            if (elementProto) {
+                elementProto.requestFullscreen = requestFullscreen;
+                elementProto.webkitRequestFullscreen = requestFullscreen;
+            }
+            if (typeof root.HTMLElement === 'function' && root.HTMLElement.prototype) {
+                root.HTMLElement.prototype.requestFullscreen = requestFullscreen;
+                root.HTMLElement.prototype.webkitRequestFullscreen = requestFullscreen;
+            }
+
+            doc.exitFullscreen = exitFullscreen;
+            doc.webkitExitFullscreen = exitFullscreen;
+            Object.defineProperty(doc, 'fullscreenElement', {
+                get() { return fullscreenElement; },
+                configurable: true,
+            });
+            Object.defineProperty(doc, 'webkitFullscreenElement', {
+                get() { return fullscreenElement; },
+                configurable: true,
+            });
+            Object.defineProperty(doc, 'fullscreen', {
+                get() { return fullscreenElement !== null; },
+                configurable: true,
+            });
+            Object.defineProperty(doc, 'fullscreenEnabled', {
+                value: true,
+                configurable: true,
+            });
+
+            // Called by the tab process when the user leaves fullscreen from the browser (Escape/F11)
+            root.__stokesExitFullscreenFromShell = function() {
+                leave(false);
+            };
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+unsafe extern "C" fn stokes_set_fullscreen(_raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let enabled = argc > 0 && args.get(0).is_boolean() && args.get(0).to_boolean();
+    DOM_REF.with(|dom| {
+        if let Some(dom) = *dom.borrow() {
+            let dom = &*dom;
+            let _ = dom.shell_provider.sender.send(ShellProviderMessage::SetFullscreen(enabled));
+        }
+    });
+    args.rval().set(UndefinedValue());
+    true
+}
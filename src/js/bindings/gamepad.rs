@@ -0,0 +1,107 @@
+// Gamepad API (navigator.getGamepads / gamepadconnected / gamepaddisconnected)
+use crate::js::{JsResult, JsRuntime};
+
+/// Install navigator.getGamepads(). The browser reads the controllers and the tab process passes
+/// their state in through `__stokesUpdateGamepads` whenever it changes while the tab is active.
+/// As in other browsers, a page sees no controllers until a button is pressed on one.
+pub fn setup_gamepad(runtime: &mut JsRuntime) -> JsResult<()> {
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            const nav = root.navigator;
+            if (!nav) {
+                return;
+            }
+
+            // Connected controllers by index, as plain state from the browser
+            let states = [];
+            let exposed = false;
+
+            function makeGamepad(state) {
+                return Object.freeze({
+                    id: state.id,
+                    index: state.index,
+                    connected: true,
+                    mapping: 'standard',
+                    timestamp: state.timestamp,
+                    axes: Object.freeze(state.axes.slice()),
+                    buttons: Object.freeze(state.buttons.map(function(button) {
+                        return Object.freeze({
+                            pressed: button.pressed,
+                            touched: button.pressed || button.value > 0,
+                            value: button.value,
+                        });
+                    })),
+                    vibrationActuator: null,
+                });
+            }
+
+            function fire(type, gamepad) {
+                let event;
+                if (typeof root.Event === 'function') {
+                    event = new root.Event(type);
+                } else {
+                    event = { type: type };
+                }
+                event.gamepad = gamepad;
+                if (typeof root.dispatchEvent === 'function') {
+                    try { root.dispatchEvent(event); } catch (_err) {}
+                }
+                const handler = root['on' + type];
+                if (typeof handler === 'function') {
+                    try { handler.call(root, event); } catch (_err) {}
+                }
+            }
+
+            nav.getGamepads = function() {
+                const length = Math.max(4, states.length);
+                const gamepads = [];
+                for (let i = 0; i < length; i++) {
+                    gamepads.push(exposed && states[i] ? makeGamepad(states[i]) : null);
+                }
+                return gamepads;
+            };
+
+            function GamepadEvent() {
+                throw new TypeError('Illegal constructor');
+            }
+            root.GamepadEvent = GamepadEvent;
+
+            // Called by the tab process with every connected controller
+            root.__stokesUpdateGamepads = function(connected) {
+                const previous = states;
+                states = [];
+                connected.forEach(function(state) {
+                    states[state.index] = state;
+                });
+                if (!exposed) {
+                    exposed = states.some(function(state) {
+                        return state && state.buttons.some(function(button) { return button.pressed; });
+                    });
+                    if (!exposed) {
+                        return;
+                    }
+                    // The press that exposes the controllers connects all of them at once
+                    states.forEach(function(state) {
+                        if (state) {
+                            fire('gamepadconnected', makeGamepad(state));
+                        }
+                    });
+                    return;
+                }
+                previous.forEach(function(state, index) {
+                    if (state && !states[index]) {
+                        fire('gamepaddisconnected', Object.assign({}, makeGamepad(state), { connected: false }));
+                    }
+                });
+                states.forEach(function(state, index) {
+                    if (state && !previous[index]) {
+                        fire('gamepadconnected', makeGamepad(state));
+                    }
+                });
+            };
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
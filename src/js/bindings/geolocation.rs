@@ -0,0 +1,215 @@
+// Geolocation API (navigator.geolocation)
+use crate::js::bindings::dom_bindings::DOM_REF;
+use crate::js::helpers::define_function;
+use crate::js::{JsResult, JsRuntime};
+use crate::shell_provider::ShellProviderMessage;
+use mozjs::jsapi::{CallArgs, JSContext};
+use mozjs::jsval::{JSVal, UndefinedValue};
+use std::os::raw::c_uint;
+
+/// Install `navigator.geolocation`. Consent goes through the permission prompt; positions come
+/// from the parent's location provider and are handed to callbacks as promise reactions, while
+/// `timeout` and `watchPosition` polling run on the page's timers.
+pub fn setup_geolocation(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesRequestGeolocation", Some(stokes_request_geolocation), 1)
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            const nav = root.navigator;
+            if (!nav || typeof root.__stokesRequestGeolocation !== 'function' || typeof root.__stokesEnsurePermission !== 'function') {
+                return;
+            }
+
+            const WATCH_INTERVAL_MS = 5000;
+            const ERROR_MESSAGES = {
+                1: 'User denied Geolocation',
+                2: 'Position unavailable',
+                3: 'Timeout expired',
+            };
+
+            function GeolocationPositionError(code) {
+                this.code = code;
+                this.message = ERROR_MESSAGES[code] || '';
+            }
+            GeolocationPositionError.PERMISSION_DENIED = 1;
+            GeolocationPositionError.POSITION_UNAVAILABLE = 2;
+            GeolocationPositionError.TIMEOUT = 3;
+            GeolocationPositionError.prototype.PERMISSION_DENIED = 1;
+            GeolocationPositionError.prototype.POSITION_UNAVAILABLE = 2;
+            GeolocationPositionError.prototype.TIMEOUT = 3;
+            root.GeolocationPositionError = GeolocationPositionError;
+
+            function makePosition(data) {
+                const coords = {
+                    latitude: data.latitude,
+                    longitude: data.longitude,
+                    accuracy: data.accuracy,
+                    altitude: data.altitude === undefined ? null : data.altitude,
+                    altitudeAccuracy: null,
+                    heading: null,
+                    speed: null,
+                };
+                coords.toJSON = function() {
+                    return {
+                        latitude: coords.latitude,
+                        longitude: coords.longitude,
+                        accuracy: coords.accuracy,
+                        altitude: coords.altitude,
+                        altitudeAccuracy: null,
+                        heading: null,
+                        speed: null,
+                    };
+                };
+                const position = { coords: coords, timestamp: data.timestamp };
+                position.toJSON = function() {
+                    return { coords: coords.toJSON(), timestamp: position.timestamp };
+                };
+                return position;
+            }
+
+            const requests = new Map();
+            const watches = new Map();
+            let nextRequestId = 1;
+            let nextWatchId = 1;
+            let lastPosition = null;
+
+            // Called by the tab process with either a position or { error: code }
+            root.__stokesDeliverPosition = function(id, data) {
+                const request = requests.get(id);
+                if (!request) {
+                    return;
+                }
+                requests.delete(id);
+                if (request.timer !== null) {
+                    clearTimeout(request.timer);
+                }
+                if (!data || data.error) {
+                    request.reject(new GeolocationPositionError(data ? data.error : 2));
+                    return;
+                }
+                lastPosition = makePosition(data);
+                request.resolve(lastPosition);
+            };
+
+            function locate(options) {
+                options = options || {};
+                const maximumAge = Number(options.maximumAge) || 0;
+                const timeout = options.timeout === undefined ? Infinity : Math.max(0, Number(options.timeout));
+
+                return root.__stokesEnsurePermission('geolocation').then(function(state) {
+                    if (state !== 'granted') {
+                        throw new GeolocationPositionError(1);
+                    }
+                    if (lastPosition && Date.now() - lastPosition.timestamp <= maximumAge) {
+                        return lastPosition;
+                    }
+                    return new Promise(function(resolve, reject) {
+                        const id = nextRequestId++;
+                        const request = { resolve: resolve, reject: reject, timer: null };
+                        if (Number.isFinite(timeout)) {
+                            request.timer = setTimeout(function() {
+                                if (requests.delete(id)) {
+                                    reject(new GeolocationPositionError(3));
+                                }
+                            }, timeout);
+                        }
+                        requests.set(id, request);
+                        root.__stokesRequestGeolocation(id);
+                    });
+                });
+            }
+
+            function report(callback, value) {
+                if (typeof callback !== 'function') {
+                    return;
+                }
+                try { callback(value); } catch (err) { console.error(err); }
+            }
+
+            const geolocation = {
+                getCurrentPosition(success, error, options) {
+                    if (typeof success !== 'function') {
+                        throw new TypeError("Failed to execute 'getCurrentPosition' on 'Geolocation': parameter 1 is not a function.");
+                    }
+                    locate(options).then(function(position) {
+                        report(success, position);
+                    }, function(err) {
+                        report(error, err);
+                    });
+                },
+                watchPosition(success, error, options) {
+                    if (typeof success !== 'function') {
+                        throw new TypeError("Failed to execute 'watchPosition' on 'Geolocation': parameter 1 is not a function.");
+                    }
+                    const id = nextWatchId++;
+                    const watch = { timer: null, last: null };
+                    watches.set(id, watch);
+
+                    const poll = function() {
+                        if (!watches.has(id)) {
+                            return;
+                        }
+                        locate(options).then(function(position) {
+                            const last = watch.last;
+                            if (watches.has(id) && (!last
+                                || last.coords.latitude !== position.coords.latitude
+                                || last.coords.longitude !== position.coords.longitude
+                                || last.coords.accuracy !== position.coords.accuracy)) {
+                                watch.last = position;
+                                report(success, position);
+                            }
+                        }, function(err) {
+                            if (!watches.has(id)) {
+                                return;
+                            }
+                            report(error, err);
+                            if (err.code === 1) {
+                                watches.delete(id);
+                            }
+                        }).then(function() {
+                            if (watches.has(id)) {
+                                watch.timer = setTimeout(poll, WATCH_INTERVAL_MS);
+                            }
+                        });
+                    };
+                    poll();
+                    return id;
+                },
+                clearWatch(id) {
+                    const watch = watches.get(id);
+                    if (!watch) {
+                        return;
+                    }
+                    if (watch.timer !== null) {
+                        clearTimeout(watch.timer);
+                    }
+                    watches.delete(id);
+                },
+            };
+            Object.defineProperty(nav, 'geolocation', { value: geolocation, configurable: true, enumerable: true });
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+unsafe extern "C" fn stokes_request_geolocation(_raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    if argc < 1 || !args.get(0).is_number() {
+        args.rval().set(UndefinedValue());
+        return true;
+    }
+    let request_id = args.get(0).to_number() as u64;
+    DOM_REF.with(|dom| {
+        if let Some(dom) = *dom.borrow() {
+            let dom = &*dom;
+            let origin = dom.url.origin().ascii_serialization();
+            let _ = dom.shell_provider.sender.send(ShellProviderMessage::RequestGeolocation { request_id, origin });
+        }
+    });
+    args.rval().set(UndefinedValue());
+    true
+}
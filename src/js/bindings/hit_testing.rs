@@ -0,0 +1,153 @@
+// Hit testing from scripts (document.elementFromPoint, elementsFromPoint and caretPositionFromPoint)
+use crate::dom::Dom;
+use crate::js::bindings::dom_bindings::DOM_REF;
+use crate::js::bindings::node::node_create_js_node_wrapper_by_id;
+use crate::js::helpers::{create_empty_array, define_function, js_value_to_f64, ToSafeCx};
+use crate::js::{JsResult, JsRuntime};
+use mozjs::jsapi::{CallArgs, JSContext};
+use mozjs::jsval::{Int32Value, JSVal, NullValue, ObjectValue};
+use mozjs::rooted;
+use mozjs::rust::wrappers::JS_SetElement;
+use std::os::raw::c_uint;
+
+/// Install document.elementFromPoint, document.elementsFromPoint, document.caretPositionFromPoint
+/// and document.caretRangeFromPoint. They go through the same hit testing as the mouse, so
+/// scrolling, transforms, pointer-events and open modal dialogs count, and take client
+/// coordinates. elementsFromPoint lists the element that was hit and its ancestors; boxes painted
+/// underneath it in other branches of the tree aren't included.
+pub fn setup_hit_testing(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesElementsFromPoint", Some(stokes_elements_from_point), 2)?;
+        define_function(cx, global.get(), "__stokesCaretPositionFromPoint", Some(stokes_caret_position_from_point), 2)
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            if (typeof root.__stokesElementsFromPoint !== 'function' || !root.document) {
+                return;
+            }
+
+            function coordinates(args, method) {
+                if (args.length < 2) {
+                    throw new TypeError("Failed to execute '" + method + "' on 'Document': 2 arguments required, but only " + args.length + ' present.');
+                }
+                const x = Number(args[0]);
+                const y = Number(args[1]);
+                if (!Number.isFinite(x) || !Number.isFinite(y)) {
+                    throw new TypeError("Failed to execute '" + method + "' on 'Document': The provided double value is non-finite.");
+                }
+                return [x, y];
+            }
+
+            function CaretPosition() {
+                throw new TypeError('Illegal constructor');
+            }
+            Object.defineProperties(CaretPosition.prototype, {
+                offsetNode: { get() { return this._offsetNode; }, configurable: true },
+                offset: { get() { return this._offset; }, configurable: true },
+            });
+            root.CaretPosition = CaretPosition;
+
+            root.document.elementFromPoint = function(x, y) {
+                [x, y] = coordinates(arguments, 'elementFromPoint');
+                const elements = root.__stokesElementsFromPoint(x, y);
+                return elements.length ? elements[0] : null;
+            };
+            root.document.elementsFromPoint = function(x, y) {
+                [x, y] = coordinates(arguments, 'elementsFromPoint');
+                return root.__stokesElementsFromPoint(x, y);
+            };
+            root.document.caretPositionFromPoint = function(x, y) {
+                [x, y] = coordinates(arguments, 'caretPositionFromPoint');
+                const point = root.__stokesCaretPositionFromPoint(x, y);
+                if (!point) {
+                    return null;
+                }
+                const position = Object.create(CaretPosition.prototype);
+                position._offsetNode = point[0];
+                position._offset = point[1];
+                return position;
+            };
+            // The older WebKit name, which answers with a collapsed range. A text field's
+            // value isn't made of nodes, so a point in one gives a range before the field.
+            root.document.caretRangeFromPoint = function(x, y) {
+                [x, y] = coordinates(arguments, 'caretRangeFromPoint');
+                const point = root.__stokesCaretPositionFromPoint(x, y);
+                if (!point || typeof root.Range !== 'function') {
+                    return null;
+                }
+                const range = new root.Range();
+                const [node, offset] = point;
+                if (node.nodeType === 1 && offset > 0 && node.parentNode) {
+                    range.setStartBefore(node);
+                } else {
+                    range.setStart(node, offset);
+                }
+                range.collapse(true);
+                return range;
+            };
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+fn with_dom<R>(f: impl FnOnce(&Dom) -> R) -> Option<R> {
+    DOM_REF.with(|dom| {
+        let dom = unsafe { &*(*dom.borrow())? };
+        Some(f(dom))
+    })
+}
+
+/// The point passed as the first two arguments
+unsafe fn point_args(args: &CallArgs) -> (f32, f32) {
+    let x = js_value_to_f64(*args.get(0)).unwrap_or(0.0);
+    let y = js_value_to_f64(*args.get(1)).unwrap_or(0.0);
+    (x as f32, y as f32)
+}
+
+/// `__stokesElementsFromPoint(x, y)`: the elements at a point in client coordinates, topmost first
+unsafe extern "C" fn stokes_elements_from_point(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let (x, y) = point_args(&args);
+    let elements = with_dom(|dom| dom.elements_from_point(x, y)).unwrap_or_default();
+
+    rooted!(in(raw_cx) let array = create_empty_array(safe_cx));
+    let mut index = 0;
+    for node_id in elements {
+        let Some(element) = node_create_js_node_wrapper_by_id(safe_cx, node_id) else {
+            continue;
+        };
+        rooted!(in(raw_cx) let element = element);
+        JS_SetElement(raw_cx, array.handle().into(), index, element.handle().into());
+        index += 1;
+    }
+    args.rval().set(ObjectValue(array.get()));
+    true
+}
+
+/// `__stokesCaretPositionFromPoint(x, y)`: the caret position at a point in client coordinates
+/// as `[node, offset]`, or null outside the viewport
+unsafe extern "C" fn stokes_caret_position_from_point(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let (x, y) = point_args(&args);
+    let Some((node_id, offset)) = with_dom(|dom| dom.caret_position_from_point(x, y)).flatten() else {
+        args.rval().set(NullValue());
+        return true;
+    };
+    let Some(node) = node_create_js_node_wrapper_by_id(safe_cx, node_id) else {
+        args.rval().set(NullValue());
+        return true;
+    };
+
+    rooted!(in(raw_cx) let node = node);
+    rooted!(in(raw_cx) let array = create_empty_array(safe_cx));
+    rooted!(in(raw_cx) let offset = Int32Value(offset as i32));
+    JS_SetElement(raw_cx, array.handle().into(), 0, node.handle().into());
+    JS_SetElement(raw_cx, array.handle().into(), 1, offset.handle().into());
+    args.rval().set(ObjectValue(array.get()));
+    true
+}
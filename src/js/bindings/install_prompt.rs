@@ -0,0 +1,104 @@
+// Web app installation events (beforeinstallprompt / appinstalled)
+use crate::js::bindings::dom_bindings::DOM_REF;
+use crate::js::helpers::define_function;
+use crate::js::{JsResult, JsRuntime};
+use crate::shell_provider::ShellProviderMessage;
+use mozjs::jsapi::{CallArgs, JSContext};
+use mozjs::jsval::{JSVal, UndefinedValue};
+use std::os::raw::c_uint;
+
+/// Install `BeforeInstallPromptEvent`. The tab process calls `__stokesBeforeInstallPrompt()` once
+/// a page whose manifest makes it installable has loaded; the event's prompt() asks the browser to
+/// show its install dialog, and the answer comes back through `__stokesAppInstallResult`, which
+/// also fires appinstalled when the app was installed from the settings panel.
+pub fn setup_install_prompt(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesRequestAppInstall", Some(stokes_request_app_install), 0)
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            if (typeof root.__stokesRequestAppInstall !== 'function') {
+                return;
+            }
+
+            function domError(message, name) {
+                if (typeof root.DOMException === 'function') {
+                    return new root.DOMException(message, name);
+                }
+                const error = new Error(message);
+                error.name = name;
+                return error;
+            }
+
+            function fire(event) {
+                if (typeof root.dispatchEvent === 'function') {
+                    root.dispatchEvent(event);
+                }
+                const handler = root['on' + event.type];
+                if (typeof handler === 'function') {
+                    handler.call(root, event);
+                }
+            }
+
+            function makeEvent(type) {
+                if (typeof root.Event === 'function') {
+                    return new root.Event(type, { cancelable: type === 'beforeinstallprompt' });
+                }
+                return { type: type, defaultPrevented: false, preventDefault: function() { this.defaultPrevented = true; } };
+            }
+
+            // The prompt waiting for the user's answer, if prompt() was called
+            let pending = null;
+
+            function BeforeInstallPromptEvent() {
+                throw new TypeError('Illegal constructor');
+            }
+            root.BeforeInstallPromptEvent = BeforeInstallPromptEvent;
+
+            root.__stokesBeforeInstallPrompt = function() {
+                const event = makeEvent('beforeinstallprompt');
+                let resolveChoice;
+                const userChoice = new Promise(function(resolve) { resolveChoice = resolve; });
+                let prompted = false;
+                event.platforms = ['web'];
+                event.userChoice = userChoice;
+                event.prompt = function() {
+                    if (prompted) {
+                        return Promise.reject(domError('The prompt has already been shown.', 'InvalidStateError'));
+                    }
+                    prompted = true;
+                    pending = resolveChoice;
+                    root.__stokesRequestAppInstall();
+                    return userChoice;
+                };
+                fire(event);
+            };
+
+            root.__stokesAppInstallResult = function(accepted) {
+                if (pending) {
+                    pending({ outcome: accepted ? 'accepted' : 'dismissed', platform: accepted ? 'web' : '' });
+                    pending = null;
+                }
+                if (accepted) {
+                    fire(makeEvent('appinstalled'));
+                }
+            };
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+unsafe extern "C" fn stokes_request_app_install(_raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    DOM_REF.with(|dom| {
+        if let Some(dom) = *dom.borrow() {
+            let dom = &*dom;
+            let _ = dom.shell_provider.sender.send(ShellProviderMessage::RequestAppInstall);
+        }
+    });
+    args.rval().set(UndefinedValue());
+    true
+}
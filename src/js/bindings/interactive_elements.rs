@@ -0,0 +1,312 @@
+// HTMLDetailsElement, HTMLDialogElement and HTMLProgressElement
+use crate::js::bindings::dom_bindings::DOM_REF;
+use crate::js::helpers::{define_function, js_value_to_f64};
+use crate::js::{JsResult, JsRuntime};
+use mozjs::jsapi::{CallArgs, JSContext};
+use markup5ever::local_name;
+use mozjs::jsval::{JSVal, UndefinedValue};
+use std::os::raw::c_uint;
+
+/// Install details.open, the dialog methods (show, showModal, close, requestClose and
+/// returnValue) and progress.position / max. Clicking a summary and the `toggle` event are
+/// handled natively; a modal dialog is put in the document's top layer through
+/// `__stokesSetModal`, and Escape or a `method="dialog"` form closes it from here.
+pub fn setup_interactive_elements(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesSetModal", Some(stokes_set_modal), 2)
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            const doc = root.document;
+            if (!doc || typeof root.__stokesSetModal !== 'function') {
+                return;
+            }
+
+            function domError(message, name) {
+                if (typeof root.DOMException === 'function') {
+                    return new root.DOMException(message, name);
+                }
+                const error = new Error(message);
+                error.name = name;
+                return error;
+            }
+
+            function isElement(element, names) {
+                if (!element || element.nodeType !== 1) {
+                    return false;
+                }
+                return names.indexOf(String(element.localName).toLowerCase()) !== -1;
+            }
+
+            function isDialog(element) {
+                return isElement(element, ['dialog']);
+            }
+
+            function makeEvent(type, cancelable) {
+                try {
+                    return new Event(type, { cancelable: !!cancelable });
+                } catch (_err) {
+                    let prevented = false;
+                    return {
+                        type: type,
+                        cancelable: !!cancelable,
+                        get defaultPrevented() { return prevented; },
+                        preventDefault() { if (cancelable) { prevented = true; } },
+                    };
+                }
+            }
+
+            // Fire an event and its on<type> handler; false when a listener canceled it
+            function fire(element, type, cancelable) {
+                const event = makeEvent(type, cancelable);
+                let proceed = true;
+                try {
+                    proceed = element.dispatchEvent(event) !== false;
+                } catch (_err) {}
+                const handler = element['on' + type];
+                if (typeof handler === 'function') {
+                    try { handler.call(element, event); } catch (_err) {}
+                }
+                return proceed && !event.defaultPrevented;
+            }
+
+            const dialogs = new WeakMap();
+            function stateOf(dialog) {
+                let state = dialogs.get(dialog);
+                if (!state) {
+                    state = { returnValue: '', modal: false, previouslyFocused: null };
+                    dialogs.set(dialog, state);
+                }
+                return state;
+            }
+
+            // Dialogs opened with showModal(), topmost last
+            const modals = [];
+
+            function focusDialog(dialog) {
+                const target = dialog.querySelector('[autofocus]')
+                    || dialog.querySelector('button, input, select, textarea, a[href], [tabindex]');
+                try {
+                    if (target && typeof target.focus === 'function') {
+                        target.focus();
+                    } else if (typeof dialog.focus === 'function') {
+                        dialog.focus();
+                    }
+                } catch (_err) {}
+            }
+
+            function closeDialog(dialog, returnValue) {
+                if (!dialog.hasAttribute('open')) {
+                    return;
+                }
+                const state = stateOf(dialog);
+                // Removing `open` also takes a modal dialog out of the top layer
+                dialog.removeAttribute('open');
+                const index = modals.indexOf(dialog);
+                if (index !== -1) {
+                    modals.splice(index, 1);
+                }
+                if (returnValue !== undefined && returnValue !== null) {
+                    state.returnValue = String(returnValue);
+                }
+                const previous = state.previouslyFocused;
+                state.modal = false;
+                state.previouslyFocused = null;
+                if (previous && typeof previous.focus === 'function') {
+                    try { previous.focus(); } catch (_err) {}
+                }
+                setTimeout(function() {
+                    fire(dialog, 'close', false);
+                }, 0);
+            }
+
+            const methods = {
+                show: function() {
+                    if (!isDialog(this)) {
+                        throw new TypeError('show called on an element that is not a dialog');
+                    }
+                    if (this.hasAttribute('open')) {
+                        if (stateOf(this).modal) {
+                            throw domError("Failed to execute 'show' on 'HTMLDialogElement': The dialog is already open as a modal dialog.", 'InvalidStateError');
+                        }
+                        return;
+                    }
+                    stateOf(this).modal = false;
+                    this.setAttribute('open', '');
+                    focusDialog(this);
+                },
+                showModal: function() {
+                    if (!isDialog(this)) {
+                        throw new TypeError('showModal called on an element that is not a dialog');
+                    }
+                    const state = stateOf(this);
+                    if (this.hasAttribute('open')) {
+                        if (state.modal) {
+                            return;
+                        }
+                        throw domError("Failed to execute 'showModal' on 'HTMLDialogElement': The dialog is already open as a non-modal dialog.", 'InvalidStateError');
+                    }
+                    const connected = doc.documentElement && typeof doc.documentElement.contains === 'function'
+                        ? doc.documentElement.contains(this)
+                        : true;
+                    if (!connected) {
+                        throw domError("Failed to execute 'showModal' on 'HTMLDialogElement': The element is not in a Document.", 'InvalidStateError');
+                    }
+                    state.modal = true;
+                    state.previouslyFocused = doc.activeElement || null;
+                    this.setAttribute('open', '');
+                    root.__stokesSetModal(this.__nodeId, true);
+                    modals.push(this);
+                    focusDialog(this);
+                },
+                close: function(returnValue) {
+                    if (!isDialog(this)) {
+                        throw new TypeError('close called on an element that is not a dialog');
+                    }
+                    closeDialog(this, returnValue);
+                },
+                requestClose: function(returnValue) {
+                    if (!isDialog(this)) {
+                        throw new TypeError('requestClose called on an element that is not a dialog');
+                    }
+                    if (this.hasAttribute('open') && fire(this, 'cancel', true)) {
+                        closeDialog(this, returnValue);
+                    }
+                },
+            };
+
+            function progressMax(element) {
+                const max = parseFloat(element.getAttribute('max'));
+                return isFinite(max) && max > 0 ? max : 1;
+            }
+
+            const properties = {
+                open: {
+                    applies: ['details', 'dialog'],
+                    get() { return this.hasAttribute('open'); },
+                    set(value) {
+                        if (value) {
+                            this.setAttribute('open', '');
+                        } else {
+                            this.removeAttribute('open');
+                        }
+                    },
+                },
+                returnValue: {
+                    applies: ['dialog'],
+                    get() { return stateOf(this).returnValue; },
+                    set(value) { stateOf(this).returnValue = String(value); },
+                },
+                position: {
+                    applies: ['progress'],
+                    get() {
+                        const value = parseFloat(this.getAttribute('value'));
+                        if (!isFinite(value)) {
+                            return -1;
+                        }
+                        const max = progressMax(this);
+                        return Math.min(Math.max(value, 0), max) / max;
+                    },
+                },
+                max: {
+                    applies: ['progress'],
+                    get() { return progressMax(this); },
+                    set(value) {
+                        const max = Number(value);
+                        if (!isFinite(max)) {
+                            throw new TypeError("Failed to set the 'max' property on 'HTMLProgressElement': The provided double value is non-finite.");
+                        }
+                        if (max > 0) {
+                            this.setAttribute('max', String(max));
+                        }
+                    },
+                },
+            };
+
+            const prototypes = [root.Element && root.Element.prototype];
+            if (typeof root.HTMLElement === 'function') {
+                prototypes.push(root.HTMLElement.prototype);
+            }
+            for (const proto of prototypes) {
+                if (!proto) {
+                    continue;
+                }
+                for (const name of Object.keys(methods)) {
+                    proto[name] = methods[name];
+                }
+                for (const name of Object.keys(properties)) {
+                    const descriptor = properties[name];
+                    Object.defineProperty(proto, name, {
+                        configurable: true,
+                        get() {
+                            return isElement(this, descriptor.applies) ? descriptor.get.call(this) : undefined;
+                        },
+                        // Other elements keep these names free for their own expandos
+                        set(value) {
+                            if (isElement(this, descriptor.applies)) {
+                                if (descriptor.set) {
+                                    descriptor.set.call(this, value);
+                                }
+                            } else {
+                                Object.defineProperty(this, name, { value: value, writable: true, enumerable: true, configurable: true });
+                            }
+                        },
+                    });
+                }
+            }
+
+            // Escape asks the topmost modal dialog to close, which its cancel listeners can refuse
+            doc.addEventListener('keydown', function(event) {
+                if (event.key !== 'Escape') {
+                    return;
+                }
+                while (modals.length > 0 && !modals[modals.length - 1].hasAttribute('open')) {
+                    modals.pop();
+                }
+                const dialog = modals[modals.length - 1];
+                if (dialog && fire(dialog, 'cancel', true)) {
+                    closeDialog(dialog);
+                }
+            });
+
+            // Submitting a method="dialog" form closes its dialog
+            root.__stokesCloseDialog = function(nodeId, returnValue) {
+                const all = doc.querySelectorAll('dialog');
+                for (let i = 0; i < all.length; i++) {
+                    if (all[i].__nodeId === nodeId) {
+                        closeDialog(all[i], returnValue);
+                        return;
+                    }
+                }
+            };
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+/// `__stokesSetModal(nodeId, modal)`: put a dialog in the top layer as the topmost modal, or
+/// take it out
+unsafe extern "C" fn stokes_set_modal(_raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let node_id = (argc > 0)
+        .then(|| js_value_to_f64(*args.get(0)))
+        .flatten()
+        .filter(|id| *id >= 0.0)
+        .map(|id| id as usize);
+    let modal = argc > 1 && args.get(1).is_boolean() && args.get(1).to_boolean();
+    if let Some(node_id) = node_id {
+        DOM_REF.with(|dom| {
+            if let Some(dom) = *dom.borrow() {
+                let dom = &mut *dom;
+                if dom.get_node(node_id).is_some_and(|node| node.data.is_element_with_tag_name(&local_name!("dialog"))) {
+                    dom.set_modal(node_id, modal);
+                }
+            }
+        });
+    }
+    args.rval().set(UndefinedValue());
+    true
+}
@@ -0,0 +1,288 @@
+// IntersectionObserver / IntersectionObserverEntry
+use crate::dom::intersection::RootMargin;
+use crate::js::bindings::dom_bindings::DOM_REF;
+use crate::js::helpers::{create_js_string, define_function, js_value_to_f64, js_value_to_string, ToSafeCx};
+use crate::js::{JsResult, JsRuntime};
+use mozjs::jsapi::{CallArgs, JSContext};
+use mozjs::jsval::{JSVal, NullValue, UndefinedValue};
+use std::os::raw::c_uint;
+
+/// Install IntersectionObserver. Observers are updated after every frame the tab paints (the
+/// engine calls `__stokesUpdateIntersectionObservers()` while any are observing), from geometry
+/// the layout tree gives `__stokesIntersection`. Entries for a target are queued when it crosses
+/// a threshold or starts or stops intersecting, and delivered in one callback per observer from a
+/// microtask.
+pub fn setup_intersection_observer(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesIntersection", Some(stokes_intersection), 3)?;
+        define_function(cx, global.get(), "__stokesParseRootMargin", Some(stokes_parse_root_margin), 1)?;
+        define_function(cx, global.get(), "__stokesObserveIntersections", Some(stokes_observe_intersections), 1)
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            if (typeof root.__stokesIntersection !== 'function') {
+                return;
+            }
+
+            function domError(message, name) {
+                if (typeof root.DOMException === 'function') {
+                    return new root.DOMException(message, name);
+                }
+                const error = new Error(message);
+                error.name = name;
+                return error;
+            }
+
+            function nodeIdOf(element) {
+                const id = element && element.__nodeId;
+                return typeof id === 'number' ? id : -1;
+            }
+
+            function makeRect(rect) {
+                const x = rect ? rect.x : 0;
+                const y = rect ? rect.y : 0;
+                const width = rect ? rect.width : 0;
+                const height = rect ? rect.height : 0;
+                if (typeof root.DOMRectReadOnly === 'function') {
+                    return new root.DOMRectReadOnly(x, y, width, height);
+                }
+                return Object.freeze({
+                    x: x, y: y, width: width, height: height,
+                    top: y, right: x + width, bottom: y + height, left: x,
+                    toJSON() { return { x: x, y: y, width: width, height: height }; },
+                });
+            }
+
+            function IntersectionObserverEntry(init) {
+                if (!(this instanceof IntersectionObserverEntry)) {
+                    throw new TypeError("Constructor IntersectionObserverEntry requires 'new'");
+                }
+                init = init || {};
+                this.time = Number(init.time) || 0;
+                this.rootBounds = init.rootBounds ? makeRect(init.rootBounds) : null;
+                this.boundingClientRect = makeRect(init.boundingClientRect);
+                this.intersectionRect = makeRect(init.intersectionRect);
+                this.isIntersecting = !!init.isIntersecting;
+                this.isVisible = false;
+                this.intersectionRatio = Number(init.intersectionRatio) || 0;
+                this.target = init.target || null;
+            }
+
+            // Observers with at least one target, updated each frame
+            const active = new Set();
+            let deliveryQueued = false;
+
+            function setActive(observer, observing) {
+                const wasEmpty = active.size === 0;
+                if (observing) {
+                    active.add(observer);
+                } else {
+                    active.delete(observer);
+                }
+                if (wasEmpty !== (active.size === 0)) {
+                    root.__stokesObserveIntersections(active.size > 0);
+                }
+            }
+
+            function queueDelivery() {
+                if (deliveryQueued) {
+                    return;
+                }
+                deliveryQueued = true;
+                Promise.resolve().then(function() {
+                    deliveryQueued = false;
+                    for (const observer of Array.from(active)) {
+                        const records = observer.takeRecords();
+                        if (records.length > 0) {
+                            try {
+                                observer._callback.call(observer, records, observer);
+                            } catch (err) {
+                                if (typeof console !== 'undefined' && console.error) {
+                                    console.error(err);
+                                }
+                            }
+                        }
+                    }
+                });
+            }
+
+            function parseThresholds(value) {
+                const list = value === undefined ? [0] : (Array.isArray(value) ? value : [value]);
+                const thresholds = list.map(Number);
+                for (const threshold of thresholds) {
+                    if (!(threshold >= 0 && threshold <= 1)) {
+                        throw new RangeError('Threshold values must be numbers between 0 and 1');
+                    }
+                }
+                thresholds.sort(function(a, b) { return a - b; });
+                return thresholds.length > 0 ? thresholds : [0];
+            }
+
+            function IntersectionObserver(callback, options) {
+                if (!(this instanceof IntersectionObserver)) {
+                    throw new TypeError("Constructor IntersectionObserver requires 'new'");
+                }
+                if (typeof callback !== 'function') {
+                    throw new TypeError("Failed to construct 'IntersectionObserver': The callback provided as parameter 1 is not a function.");
+                }
+                options = options || {};
+                const observerRoot = options.root === undefined ? null : options.root;
+                if (observerRoot !== null && !(observerRoot.nodeType === 1 || observerRoot.nodeType === 9)) {
+                    throw new TypeError("Failed to construct 'IntersectionObserver': root must be an Element or Document");
+                }
+                const rootMargin = root.__stokesParseRootMargin(options.rootMargin === undefined ? '0px' : String(options.rootMargin));
+                if (rootMargin === null) {
+                    throw domError("Failed to construct 'IntersectionObserver': rootMargin must be specified in pixels or percent.", 'SyntaxError');
+                }
+                Object.defineProperty(this, '_callback', { value: callback });
+                Object.defineProperty(this, '_targets', { value: [] });
+                Object.defineProperty(this, '_records', { value: [], writable: true });
+                Object.defineProperty(this, 'root', { value: observerRoot, enumerable: true });
+                Object.defineProperty(this, 'rootMargin', { value: rootMargin, enumerable: true });
+                Object.defineProperty(this, 'thresholds', { value: Object.freeze(parseThresholds(options.threshold)), enumerable: true });
+            }
+
+            IntersectionObserver.prototype.observe = function(target) {
+                if (!target || target.nodeType !== 1) {
+                    throw new TypeError("Failed to execute 'observe' on 'IntersectionObserver': parameter 1 is not of type 'Element'.");
+                }
+                if (this._targets.some(function(entry) { return entry.target === target; })) {
+                    return;
+                }
+                // -1 makes the first update always report the target
+                this._targets.push({ target: target, thresholdIndex: -1, isIntersecting: false });
+                setActive(this, true);
+            };
+
+            IntersectionObserver.prototype.unobserve = function(target) {
+                const index = this._targets.findIndex(function(entry) { return entry.target === target; });
+                if (index !== -1) {
+                    this._targets.splice(index, 1);
+                }
+                if (this._targets.length === 0) {
+                    setActive(this, false);
+                }
+            };
+
+            IntersectionObserver.prototype.disconnect = function() {
+                this._targets.length = 0;
+                this._records = [];
+                setActive(this, false);
+            };
+
+            IntersectionObserver.prototype.takeRecords = function() {
+                const records = this._records;
+                this._records = [];
+                return records;
+            };
+
+            IntersectionObserver.prototype._update = function(time) {
+                // A Document root is its viewport, like the implicit root
+                const rootId = this.root && this.root.nodeType === 1 ? nodeIdOf(this.root) : -1;
+                for (const state of this._targets) {
+                    const json = root.__stokesIntersection(nodeIdOf(state.target), rootId, this.rootMargin);
+                    const geometry = json ? JSON.parse(json) : null;
+                    const isIntersecting = !!(geometry && geometry.isIntersecting);
+                    const ratio = geometry ? geometry.intersectionRatio : 0;
+                    let thresholdIndex = 0;
+                    if (isIntersecting) {
+                        while (thresholdIndex < this.thresholds.length && this.thresholds[thresholdIndex] <= ratio) {
+                            thresholdIndex++;
+                        }
+                    }
+                    if (thresholdIndex === state.thresholdIndex && isIntersecting === state.isIntersecting) {
+                        continue;
+                    }
+                    state.thresholdIndex = thresholdIndex;
+                    state.isIntersecting = isIntersecting;
+                    this._records.push(new IntersectionObserverEntry({
+                        time: time,
+                        rootBounds: geometry ? geometry.rootBounds : null,
+                        boundingClientRect: geometry ? geometry.boundingClientRect : null,
+                        intersectionRect: isIntersecting ? geometry.intersectionRect : null,
+                        isIntersecting: isIntersecting,
+                        intersectionRatio: ratio,
+                        target: state.target,
+                    }));
+                }
+                return this._records.length > 0;
+            };
+
+            root.__stokesUpdateIntersectionObservers = function() {
+                const time = root.performance && typeof root.performance.now === 'function' ? root.performance.now() : Date.now();
+                let queued = false;
+                for (const observer of Array.from(active)) {
+                    queued = observer._update(time) || queued;
+                }
+                if (queued) {
+                    queueDelivery();
+                }
+            };
+
+            root.IntersectionObserver = IntersectionObserver;
+            root.IntersectionObserverEntry = IntersectionObserverEntry;
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+/// `__stokesIntersection(targetId, rootId, rootMargin)`: the target's geometry against the root
+/// element, or the viewport when `rootId` is -1, as JSON; null when the target isn't rendered
+unsafe extern "C" fn stokes_intersection(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let node_id = |index: u32| {
+        (argc > index)
+            .then(|| js_value_to_f64(*args.get(index)))
+            .flatten()
+            .filter(|id| *id >= 0.0)
+            .map(|id| id as usize)
+    };
+    let target = node_id(0);
+    let observer_root = node_id(1);
+    let margin = if argc > 2 { RootMargin::parse(&js_value_to_string(safe_cx, *args.get(2))) } else { None };
+
+    let intersection = DOM_REF.with(|dom| {
+        let dom = &*(*dom.borrow())?;
+        dom.intersection(target?, observer_root, &margin.unwrap_or_default())
+    });
+    match intersection.and_then(|intersection| serde_json::to_string(&intersection).ok()) {
+        Some(json) => args.rval().set(create_js_string(safe_cx, &json)),
+        None => args.rval().set(NullValue()),
+    }
+    true
+}
+
+/// `__stokesParseRootMargin(text)`: the rootMargin normalized to four sides, or null when it
+/// isn't valid
+unsafe extern "C" fn stokes_parse_root_margin(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let text = if argc > 0 { js_value_to_string(safe_cx, *args.get(0)) } else { String::new() };
+    match RootMargin::parse(&text) {
+        Some(margin) => args.rval().set(create_js_string(safe_cx, &margin.serialize())),
+        None => args.rval().set(NullValue()),
+    }
+    true
+}
+
+/// `__stokesObserveIntersections(observing)`: start or stop updating observers after each frame.
+/// Starting asks for a frame so new targets get their first entry.
+unsafe extern "C" fn stokes_observe_intersections(_raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let observing = argc > 0 && args.get(0).is_boolean() && args.get(0).to_boolean();
+    DOM_REF.with(|dom| {
+        if let Some(dom) = *dom.borrow() {
+            let dom = &mut *dom;
+            dom.observing_intersections = observing;
+            if observing {
+                dom.shell_provider.request_redraw();
+            }
+        }
+    });
+    args.rval().set(UndefinedValue());
+    true
+}
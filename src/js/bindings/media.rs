@@ -0,0 +1,279 @@
+// HTMLMediaElement playback state (play / pause / muted / autoplay) and the autoplay policy
+use crate::js::bindings::dom_bindings::DOM_REF;
+use crate::js::helpers::define_function;
+use crate::js::{JsResult, JsRuntime};
+use crate::shell_provider::ShellProviderMessage;
+use mozjs::jsapi::{CallArgs, JSContext};
+use mozjs::jsval::{BooleanValue, JSVal, UndefinedValue};
+use std::os::raw::c_uint;
+
+/// Install play(), pause(), paused, muted, volume and autoplay on audio and video elements.
+/// Nothing is decoded or output yet, so a playing element stays at its start; what is enforced is
+/// the site's autoplay policy: play() without the user having clicked or typed on the page rejects
+/// with a NotAllowedError when the policy forbids it, and the autoplay attribute only starts
+/// media the policy lets play. The tab is told when the page starts or stops playing sound, for its
+/// speaker icon.
+pub fn setup_media(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesAutoplayAllowed", Some(stokes_autoplay_allowed), 1)?;
+        define_function(cx, global.get(), "__stokesSetAudible", Some(stokes_set_audible), 1)
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            const doc = root.document;
+            if (!doc || typeof root.__stokesAutoplayAllowed !== 'function') {
+                return;
+            }
+
+            function domError(message, name) {
+                if (typeof root.DOMException === 'function') {
+                    return new root.DOMException(message, name);
+                }
+                const error = new Error(message);
+                error.name = name;
+                return error;
+            }
+
+            function isMedia(element) {
+                if (!element || element.nodeType !== 1) {
+                    return false;
+                }
+                const name = String(element.localName).toLowerCase();
+                return name === 'video' || name === 'audio';
+            }
+
+            const states = new WeakMap();
+            function stateOf(element) {
+                let state = states.get(element);
+                if (!state) {
+                    state = { paused: true, muted: element.hasAttribute('muted'), volume: 1 };
+                    states.set(element, state);
+                }
+                return state;
+            }
+
+            function fire(element, type) {
+                try {
+                    element.dispatchEvent(new Event(type));
+                } catch (_err) {}
+                const handler = element['on' + type];
+                if (typeof handler === 'function') {
+                    try { handler.call(element, { type: type, target: element }); } catch (_err) {}
+                }
+            }
+
+            function audible(state) {
+                return !state.muted && state.volume > 0;
+            }
+
+            // Elements playing with sound; the tab shows a speaker icon while there are any
+            let sounding = 0;
+            function updateSound(state) {
+                const now = !state.paused && audible(state);
+                if (now === !!state.sounding) {
+                    return;
+                }
+                state.sounding = now;
+                sounding += now ? 1 : -1;
+                if (sounding === (now ? 1 : 0) && typeof root.__stokesSetAudible === 'function') {
+                    root.__stokesSetAudible(now);
+                }
+            }
+
+            // Start playback if the policy lets it; returns whether the element is playing
+            function start(element) {
+                const state = stateOf(element);
+                if (!root.__stokesAutoplayAllowed(audible(state))) {
+                    return false;
+                }
+                if (state.paused) {
+                    state.paused = false;
+                    updateSound(state);
+                    fire(element, 'play');
+                    fire(element, 'playing');
+                }
+                return true;
+            }
+
+            function stop(element) {
+                const state = stateOf(element);
+                if (!state.paused) {
+                    state.paused = true;
+                    updateSound(state);
+                    fire(element, 'pause');
+                }
+            }
+
+            function requireMedia(element, name) {
+                if (!isMedia(element)) {
+                    throw new TypeError(name + ' called on an element that is not audio or video');
+                }
+            }
+
+            const methods = {
+                play: function() {
+                    if (!isMedia(this)) {
+                        return Promise.reject(new TypeError('play called on an element that is not audio or video'));
+                    }
+                    if (!start(this)) {
+                        return Promise.reject(domError(
+                            "play() failed because the user didn't interact with the document first.",
+                            'NotAllowedError'
+                        ));
+                    }
+                    return Promise.resolve();
+                },
+                pause: function() {
+                    requireMedia(this, 'pause');
+                    stop(this);
+                },
+                canPlayType: function(_type) {
+                    return '';
+                },
+                load: function() {
+                    requireMedia(this, 'load');
+                    stop(this);
+                },
+            };
+
+            const properties = {
+                paused: {
+                    get() { return isMedia(this) ? stateOf(this).paused : undefined; },
+                },
+                muted: {
+                    get() { return isMedia(this) ? stateOf(this).muted : undefined; },
+                    set(value) {
+                        const state = stateOf(this);
+                        const muted = !!value;
+                        if (state.muted === muted) {
+                            return;
+                        }
+                        state.muted = muted;
+                        updateSound(state);
+                        fire(this, 'volumechange');
+                        // Unmuting media that only autoplayed because it was silent pauses it
+                        if (!state.paused && !root.__stokesAutoplayAllowed(audible(state))) {
+                            stop(this);
+                        }
+                    },
+                },
+                volume: {
+                    get() { return isMedia(this) ? stateOf(this).volume : undefined; },
+                    set(value) {
+                        const volume = Number(value);
+                        if (!(volume >= 0 && volume <= 1)) {
+                            throw domError('The volume provided is outside the range [0, 1].', 'IndexSizeError');
+                        }
+                        const state = stateOf(this);
+                        if (state.volume === volume) {
+                            return;
+                        }
+                        state.volume = volume;
+                        updateSound(state);
+                        fire(this, 'volumechange');
+                        if (!state.paused && !root.__stokesAutoplayAllowed(audible(state))) {
+                            stop(this);
+                        }
+                    },
+                },
+                autoplay: {
+                    get() { return isMedia(this) ? this.hasAttribute('autoplay') : undefined; },
+                    set(value) {
+                        if (value) {
+                            this.setAttribute('autoplay', '');
+                        } else {
+                            this.removeAttribute('autoplay');
+                        }
+                    },
+                },
+                defaultMuted: {
+                    get() { return isMedia(this) ? this.hasAttribute('muted') : undefined; },
+                    set(value) {
+                        if (value) {
+                            this.setAttribute('muted', '');
+                        } else {
+                            this.removeAttribute('muted');
+                        }
+                    },
+                },
+                currentTime: {
+                    get() { return isMedia(this) ? 0 : undefined; },
+                    set(_value) {},
+                },
+            };
+
+            const prototypes = [root.Element && root.Element.prototype];
+            if (typeof root.HTMLElement === 'function') {
+                prototypes.push(root.HTMLElement.prototype);
+            }
+            for (const proto of prototypes) {
+                if (!proto) {
+                    continue;
+                }
+                for (const name of Object.keys(methods)) {
+                    proto[name] = methods[name];
+                }
+                for (const name of Object.keys(properties)) {
+                    const descriptor = properties[name];
+                    Object.defineProperty(proto, name, {
+                        configurable: true,
+                        get: descriptor.get,
+                        // Other elements keep these names free for their own expandos
+                        set(value) {
+                            if (isMedia(this)) {
+                                if (descriptor.set) {
+                                    descriptor.set.call(this, value);
+                                }
+                            } else {
+                                Object.defineProperty(this, name, { value: value, writable: true, enumerable: true, configurable: true });
+                            }
+                        },
+                    });
+                }
+            }
+
+            // Media with the autoplay attribute starts once the document is parsed, if the
+            // policy lets it; blocked media just stays paused
+            doc.addEventListener('DOMContentLoaded', function() {
+                const elements = doc.querySelectorAll('video[autoplay], audio[autoplay]');
+                for (let i = 0; i < elements.length; i++) {
+                    start(elements[i]);
+                }
+            });
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+/// `__stokesAutoplayAllowed(audible)`: whether the document's autoplay policy lets media start
+/// playing now
+unsafe extern "C" fn stokes_autoplay_allowed(_raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let audible = argc == 0 || !args.get(0).is_boolean() || args.get(0).to_boolean();
+    let allowed = DOM_REF.with(|dom| match *dom.borrow() {
+        Some(dom) => {
+            let dom = &*dom;
+            dom.autoplay.allows_playback(audible, dom.user_activated)
+        }
+        None => false,
+    });
+    args.rval().set(BooleanValue(allowed));
+    true
+}
+
+/// `__stokesSetAudible(audible)`: the page started or stopped playing sound
+unsafe extern "C" fn stokes_set_audible(_raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let audible = argc > 0 && args.get(0).is_boolean() && args.get(0).to_boolean();
+    DOM_REF.with(|dom| {
+        if let Some(dom) = *dom.borrow() {
+            let dom = &*dom;
+            let _ = dom.shell_provider.sender.send(ShellProviderMessage::AudioStateChanged { audible });
+        }
+    });
+    args.rval().set(UndefinedValue());
+    true
+}
@@ -12,6 +12,7 @@ pub(crate) mod cookie;
 pub(crate) mod custom_elements;
 pub(crate) mod dom_bindings;
 pub(crate) mod dom_implementation;
+pub(crate) mod dom_parser;
 pub(crate) mod document;
 pub(crate) mod document_fragment;
 pub(crate) mod element;
@@ -31,20 +32,43 @@ pub(crate) mod registry;
 pub(crate) mod storage;
 pub(crate) mod timers;
 pub(crate) mod window;
-pub(crate) mod alert_callback;
+pub(crate) mod dialog_callback;
 pub(crate) mod warnings;
 pub(crate) mod interface_registry;
 
 pub mod abort_signal;
+pub mod blob;
+pub mod cache_storage;
 pub mod console;
 pub mod css;
 pub mod crypto;
 pub mod event_listeners;
 pub mod event_target;
 pub mod fetch;
+pub mod font_loading;
+pub mod fullscreen;
+pub mod gamepad;
+pub mod geolocation;
+pub mod hit_testing;
+pub mod install_prompt;
+pub mod interactive_elements;
+pub mod intersection_observer;
+pub mod media;
+pub mod extension_api;
 pub mod performance;
+pub mod permissions;
+pub mod pointer_lock;
+pub mod selection;
+pub mod service_worker;
+pub mod storage_manager;
+pub mod structured_clone;
 pub mod text_encoding;
 pub mod url;
+pub mod visibility;
+pub mod visual_viewport;
+pub mod webgl;
+pub mod websocket;
+pub mod window_open;
 pub mod xhr;
 
 /// Initialize JavaScript bindings for the browser
@@ -79,9 +103,15 @@ pub fn initialize_bindings(runtime: &mut JsRuntime, document_root: *mut Dom, use
     // Set up Web Crypto API (crypto.getRandomValues/randomUUID/subtle.digest)
     crypto::setup_crypto(runtime)?;
 
+    // Set up structuredClone
+    structured_clone::setup_structured_clone(runtime)?;
+
     // Set up TextEncoder (UTF-8 encoding support)
     text_encoding::setup_text_encoder(runtime)?;
 
+    // Set up Blob, File, FormData and object URLs (after fetch, URL and TextEncoder)
+    blob::setup_blob(runtime)?;
+
     // Set up DOM bindings
     dom_bindings::setup_dom_bindings(runtime, document_root, user_agent)?;
 
@@ -92,6 +122,9 @@ pub fn initialize_bindings(runtime: &mut JsRuntime, document_root: *mut Dom, use
     // Set up MutationObserver / MutationRecord polyfill and node patch hooks
     mutation_observer::setup_mutation_observer(runtime)?;
 
+    // Set up IntersectionObserver, updated by the engine after each painted frame
+    intersection_observer::setup_intersection_observer(runtime)?;
+
     // Set up window.matchMedia and MediaQueryList behavior
     window::setup_match_media_deferred(runtime)?;
 
@@ -105,12 +138,81 @@ pub fn initialize_bindings(runtime: &mut JsRuntime, document_root: *mut Dom, use
     // Set up HTMLInputElement constructor/prototype wiring
     html_input_element::setup_html_input_element_constructor_deferred(runtime)?;
 
+    // Set up custom element constructors, define() checks and lifecycle callbacks
+    custom_elements::setup_custom_element_lifecycle_deferred(runtime)?;
+
+    // Set up DOMParser, XMLSerializer and innerHTML/outerHTML
+    dom_parser::setup_dom_parser(runtime)?;
+
     // Set up XMLHttpRequest constructor (full polyfill)
     xhr::setup_xhr(runtime)?;
 
+    // Set up the WebSocket constructor
+    websocket::setup_websocket(runtime)?;
+
     // Set up AbortSignal and AbortController
     abort_signal::setup_abort_signal(runtime)?;
 
+    // Set up element.requestFullscreen / document.exitFullscreen
+    fullscreen::setup_fullscreen(runtime)?;
+
+    // Set up element.requestPointerLock / document.exitPointerLock
+    pointer_lock::setup_pointer_lock(runtime)?;
+
+    // Set up navigator.language, navigator.languages and languagechange
+    navigator::setup_navigator_languages(runtime)?;
+
+    // Set up navigator.getGamepads and the gamepad connection events
+    gamepad::setup_gamepad(runtime)?;
+
+    // Set up HTMLCanvasElement and canvas.getContext('webgl')
+    webgl::setup_webgl(runtime)?;
+
+    // Set up details.open, dialog show()/showModal()/close() and progress.position
+    interactive_elements::setup_interactive_elements(runtime)?;
+
+    // Set up play()/pause() and the autoplay policy on audio and video elements
+    media::setup_media(runtime)?;
+
+    // Set up document.visibilityState / document.hidden
+    visibility::setup_visibility(runtime)?;
+
+    // Set up window.visualViewport
+    visual_viewport::setup_visual_viewport(runtime)?;
+
+    // Set up window.getSelection, Selection and Range
+    selection::setup_selection(runtime)?;
+
+    // Set up document.elementFromPoint, elementsFromPoint and caretPositionFromPoint
+    hit_testing::setup_hit_testing(runtime)?;
+
+    // Set up document.fonts and FontFace
+    font_loading::setup_font_loading(runtime)?;
+
+    // Set up window.open and the popup blocker
+    window_open::setup_window_open(runtime)?;
+
+    // Set up navigator.permissions, Notification and navigator.clipboard
+    permissions::setup_permissions(runtime)?;
+
+    // Set up navigator.geolocation (needs the permission helpers above)
+    geolocation::setup_geolocation(runtime)?;
+
+    // Set up navigator.storage and the localStorage quota
+    storage_manager::setup_storage_manager(runtime)?;
+
+    // Set up caches and the Headers / Request / Response constructors it needs
+    cache_storage::setup_cache_storage(runtime)?;
+
+    // Set up navigator.serviceWorker (wraps fetch, so after everything that captures it)
+    service_worker::setup_service_worker(runtime)?;
+
+    // Set up beforeinstallprompt and appinstalled for installable web apps
+    install_prompt::setup_install_prompt(runtime)?;
+
+    // Set up the `browser` object of WebExtension scripts, out of the page's reach
+    extension_api::setup_extension_api(runtime)?;
+
 
     Ok(())
 }
\ No newline at end of file
@@ -1,4 +1,6 @@
 use crate::js::helpers::{set_bool_property, set_string_property};
+use crate::js::{JsResult, JsRuntime};
+use crate::languages;
 use mozjs::context::JSContext as SafeJSContext;
 use mozjs::jsapi::{JS_DefineProperty, JS_NewPlainObject, JSObject, JSPROP_ENUMERATE};
 use mozjs::jsval::ObjectValue;
@@ -17,7 +19,6 @@ pub(crate) unsafe fn setup_navigator_bindings(
     }
 
     set_string_property(cx, navigator.get(), "userAgent", user_agent)?;
-    set_string_property(cx, navigator.get(), "language", "en-US")?;
     set_string_property(cx, navigator.get(), "platform", std::env::consts::OS)?;
     set_string_property(cx, navigator.get(), "appName", "Stokes Browser")?;
     set_string_property(cx, navigator.get(), "appVersion", "1.0")?;
@@ -38,3 +39,51 @@ pub(crate) unsafe fn setup_navigator_bindings(
 
     Ok(())
 }
+
+/// Install navigator.language and navigator.languages from the user's languages, most preferred
+/// first. The tab process passes a new list in through `__stokesSetLanguages` when the user
+/// changes theirs, which fires `languagechange` on the window.
+pub(crate) fn setup_navigator_languages(runtime: &mut JsRuntime) -> JsResult<()> {
+    let languages = serde_json::to_string(&languages::current()).unwrap_or_else(|_| "[]".to_string());
+    let script = format!(r#"
+        (function() {{
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            const nav = root.navigator;
+            if (!nav) {{
+                return;
+            }}
+
+            // One frozen array until the list changes, so navigator.languages === navigator.languages
+            let languages = Object.freeze({languages});
+
+            Object.defineProperty(nav, 'language', {{
+                get() {{ return languages[0]; }},
+                configurable: true,
+                enumerable: true,
+            }});
+            Object.defineProperty(nav, 'languages', {{
+                get() {{ return languages; }},
+                configurable: true,
+                enumerable: true,
+            }});
+
+            // Called by the tab process with the new list
+            root.__stokesSetLanguages = function(list) {{
+                if (!Array.isArray(list) || !list.length) {{
+                    return;
+                }}
+                languages = Object.freeze(list.slice());
+                const event = typeof root.Event === 'function' ? new root.Event('languagechange') : {{ type: 'languagechange' }};
+                if (typeof root.dispatchEvent === 'function') {{
+                    try {{ root.dispatchEvent(event); }} catch (_err) {{}}
+                }}
+                const handler = root.onlanguagechange;
+                if (typeof handler === 'function') {{
+                    try {{ handler.call(root, event); }} catch (_err) {{}}
+                }}
+            }};
+        }})();
+    "#);
+
+    runtime.execute(&script, false)
+}
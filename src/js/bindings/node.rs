@@ -2,7 +2,8 @@ use blitz_traits::net::Request;
 use crate::dom::NodeData;
 use crate::engine::js_provider::ScriptKind;
 use crate::engine::script_type::executable_script_kind;
-use crate::js::bindings::custom_elements::custom_elements_upgrade_for_node;
+use crate::js::bindings::custom_elements::{custom_elements_disconnect_for_node, custom_elements_upgrade_for_node};
+use crate::js::bindings::registry;
 use crate::js::bindings::dom_bindings::DOM_REF;
 use crate::js::bindings::element_bindings::{
     create_js_element_by_dom_id, create_js_element_by_id, create_js_shadow_root_by_id,
@@ -172,16 +173,8 @@ unsafe fn node_create_js_text_node_by_id(
     set_string_property(cx, text_node.get(), "nodeValue", text)?;
     set_string_property(cx, text_node.get(), "textContent", text)?;
 
-    rooted!(in(raw_cx) let ptr_val = mozjs::jsval::DoubleValue(node_id as f64));
+    registry::set_node_id(cx, text_node.get(), node_id);
     rooted!(in(raw_cx) let text_rooted = text_node.get());
-    let cname = std::ffi::CString::new("__nodeId").unwrap();
-    JS_DefineProperty(
-        raw_cx,
-        text_rooted.handle().into(),
-        cname.as_ptr(),
-        ptr_val.handle().into(),
-        0,
-    );
 
     rooted!(in(raw_cx) let null_val = NullValue());
     for name in &["parentNode", "parentElement", "firstChild", "lastChild", "previousSibling", "nextSibling"] {
@@ -226,16 +219,8 @@ unsafe fn node_create_js_comment_node_by_id(
     set_string_property(cx, comment_node.get(), "nodeValue", "")?;
     set_string_property(cx, comment_node.get(), "textContent", "")?;
 
-    rooted!(in(raw_cx) let ptr_val = mozjs::jsval::DoubleValue(node_id as f64));
+    registry::set_node_id(cx, comment_node.get(), node_id);
     rooted!(in(raw_cx) let comment_rooted = comment_node.get());
-    let cname = std::ffi::CString::new("__nodeId").unwrap();
-    JS_DefineProperty(
-        raw_cx,
-        comment_rooted.handle().into(),
-        cname.as_ptr(),
-        ptr_val.handle().into(),
-        0,
-    );
 
     rooted!(in(raw_cx) let null_val = NullValue());
     for name in &["parentNode", "parentElement", "firstChild", "lastChild", "previousSibling", "nextSibling"] {
@@ -265,7 +250,7 @@ unsafe fn node_create_js_comment_node_by_id(
     Ok(ObjectValue(comment_node.get()))
 }
 
-unsafe fn node_create_js_node_wrapper_by_id(
+pub(crate) unsafe fn node_create_js_node_wrapper_by_id(
     cx: &mut SafeJSContext,
     node_id: usize,
 ) -> Option<JSVal> {
@@ -351,7 +336,7 @@ fn node_trigger_script_load_if_needed(child_id: usize) {
     });
 
     if let Some((url, net_provider, js_provider, script_node_id, script_kind)) = script_load_info {
-        println!("[JS] Dynamically loading script: {}", url);
+        tracing::debug!("Dynamically loading script: {}", url);
         let url_str = url.to_string();
         let module_source_url = (script_kind == ScriptKind::Module).then(|| url_str.clone());
         net_provider.fetch_with_callback(
@@ -372,15 +357,15 @@ fn node_trigger_script_load_if_needed(child_id: usize) {
                                 }
                             }
                             Err(e) => {
-                                eprintln!(
-                                    "[JS] Dynamic script at '{}' is not valid UTF-8: {}",
+                                tracing::warn!(
+                                    "Dynamic script at '{}' is not valid UTF-8: {}",
                                     url_str, e
                                 )
                             }
                         }
                     }
                     Err(e) => {
-                        eprintln!("[JS] Failed to load dynamic script '{}': {:?}", url_str, e)
+                        tracing::warn!("Failed to load dynamic script '{}': {:?}", url_str, e)
                     }
                 }
             }),
@@ -494,6 +479,7 @@ pub(crate) unsafe extern "C" fn node_remove_child(raw_cx: *mut JSContext, argc:
             }
         }
     });
+    custom_elements_disconnect_for_node(safe_cx, child_id);
 
     args.rval().set(*args.get(0));
     true
@@ -565,6 +551,7 @@ pub(crate) unsafe extern "C" fn node_replace_child(raw_cx: *mut JSContext, argc:
                 }
             });
 
+            custom_elements_disconnect_for_node(safe_cx, old_child_id);
             custom_elements_upgrade_for_node(safe_cx, new_child_id);
             node_trigger_script_load_if_needed(new_child_id);
             args.rval().set(*args.get(1));
@@ -0,0 +1,325 @@
+// Permissions API (navigator.permissions) and the features that go through it:
+// Notification and navigator.clipboard
+use crate::js::bindings::dom_bindings::DOM_REF;
+use crate::js::helpers::{ToSafeCx, create_js_string, define_function, js_value_to_string};
+use crate::js::{JsResult, JsRuntime};
+use crate::permissions::{PermissionKind, PermissionState, PermissionStore};
+use crate::shell_provider::ShellProviderMessage;
+use blitz_traits::shell::ShellProvider;
+use mozjs::jsapi::{CallArgs, JSContext};
+use mozjs::jsval::{JSVal, UndefinedValue};
+use std::cell::RefCell;
+use std::os::raw::c_uint;
+
+thread_local! {
+    /// Decisions the parent has saved, read once and re-read when it sends `PermissionsChanged`
+    static PERMISSIONS: RefCell<Option<PermissionStore>> = const { RefCell::new(None) };
+}
+
+/// Drop the cached decisions so the next query reads the ones the parent just saved
+pub fn reload_permissions() {
+    PERMISSIONS.with(|permissions| *permissions.borrow_mut() = None);
+}
+
+fn permission_state(origin: &str, kind: PermissionKind) -> PermissionState {
+    PERMISSIONS.with(|permissions| {
+        permissions.borrow_mut()
+            .get_or_insert_with(PermissionStore::load_from_disk)
+            .state(origin, kind)
+    })
+}
+
+/// Install `navigator.permissions`, `Notification` and `navigator.clipboard`. Permission state is
+/// read from a cached copy of the store the parent process persists; undecided requests are forwarded to the
+/// parent, which prompts the user and answers through `__stokesResolvePermission`.
+pub fn setup_permissions(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesPermissionState", Some(stokes_permission_state), 1)?;
+        define_function(cx, global.get(), "__stokesRequestPermission", Some(stokes_request_permission), 2)?;
+        define_function(cx, global.get(), "__stokesShowNotification", Some(stokes_show_notification), 2)?;
+        define_function(cx, global.get(), "__stokesClipboardReadText", Some(stokes_clipboard_read_text), 0)?;
+        define_function(cx, global.get(), "__stokesClipboardWriteText", Some(stokes_clipboard_write_text), 1)
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            const nav = root.navigator;
+            if (!nav || typeof root.__stokesPermissionState !== 'function') {
+                return;
+            }
+
+            const pending = new Map();
+            const statuses = [];
+            let nextRequestId = 1;
+
+            function makeError(message, name) {
+                if (typeof root.DOMException === 'function') {
+                    return new root.DOMException(message, name);
+                }
+                const error = new Error(message);
+                error.name = name;
+                return error;
+            }
+
+            function defer(callback) {
+                if (typeof root.queueMicrotask === 'function') {
+                    root.queueMicrotask(callback);
+                } else {
+                    Promise.resolve().then(callback);
+                }
+            }
+
+            function PermissionStatus(name, state) {
+                this.name = name;
+                this.state = state;
+                this.onchange = null;
+                this._listeners = [];
+            }
+            PermissionStatus.prototype.addEventListener = function(type, listener) {
+                if (type === 'change' && typeof listener === 'function' && this._listeners.indexOf(listener) < 0) {
+                    this._listeners.push(listener);
+                }
+            };
+            PermissionStatus.prototype.removeEventListener = function(type, listener) {
+                const index = this._listeners.indexOf(listener);
+                if (type === 'change' && index >= 0) {
+                    this._listeners.splice(index, 1);
+                }
+            };
+            PermissionStatus.prototype.dispatchEvent = function(event) {
+                const listeners = this._listeners.slice();
+                if (typeof this.onchange === 'function') {
+                    listeners.unshift(this.onchange);
+                }
+                for (const listener of listeners) {
+                    try { listener.call(this, event); } catch (err) { console.error(err); }
+                }
+                return true;
+            };
+
+            function notifyChange(name, state) {
+                for (const status of statuses) {
+                    if (status.name === name && status.state !== state) {
+                        status.state = state;
+                        status.dispatchEvent({ type: 'change', target: status });
+                    }
+                }
+            }
+
+            // Resolves with the final state, prompting the user if the origin has not decided yet
+            function requestPermission(name) {
+                const state = root.__stokesPermissionState(name);
+                if (state !== 'prompt') {
+                    return Promise.resolve(state);
+                }
+                return new Promise(function(resolve) {
+                    const id = nextRequestId++;
+                    pending.set(id, { name: name, resolve: resolve });
+                    root.__stokesRequestPermission(name, id);
+                });
+            }
+
+            // Shared with other bindings (e.g. geolocation) that need consent first
+            root.__stokesEnsurePermission = requestPermission;
+
+            // Called by the tab process once the user answered a prompt
+            root.__stokesResolvePermission = function(id, granted) {
+                const entry = pending.get(id);
+                if (!entry) {
+                    return;
+                }
+                pending.delete(id);
+                const state = granted ? 'granted' : 'denied';
+                notifyChange(entry.name, state);
+                entry.resolve(state);
+            };
+
+            const permissions = {
+                query(descriptor) {
+                    const name = descriptor && descriptor.name;
+                    const state = typeof name === 'string' ? root.__stokesPermissionState(name) : undefined;
+                    if (state === undefined) {
+                        return Promise.reject(new TypeError("The provided value '" + name + "' is not a valid permission name."));
+                    }
+                    const status = new PermissionStatus(name, state);
+                    statuses.push(status);
+                    return Promise.resolve(status);
+                },
+            };
+            Object.defineProperty(nav, 'permissions', { value: permissions, configurable: true, enumerable: true });
+
+            function notificationPermission() {
+                const state = root.__stokesPermissionState('notifications');
+                return state === 'prompt' ? 'default' : state;
+            }
+
+            function Notification(title, options) {
+                if (!(this instanceof Notification)) {
+                    throw new TypeError("Failed to construct 'Notification': Please use the 'new' operator.");
+                }
+                options = options || {};
+                this.title = String(title);
+                this.body = options.body === undefined ? '' : String(options.body);
+                this.tag = options.tag === undefined ? '' : String(options.tag);
+                this.icon = options.icon === undefined ? '' : String(options.icon);
+                this.data = options.data === undefined ? null : options.data;
+                this.onshow = null;
+                this.onclick = null;
+                this.onclose = null;
+                this.onerror = null;
+                this._listeners = {};
+
+                const notification = this;
+                if (notificationPermission() === 'granted') {
+                    root.__stokesShowNotification(this.title, this.body);
+                    defer(function() { notification._fire('show'); });
+                } else {
+                    defer(function() { notification._fire('error'); });
+                }
+            }
+            Notification.prototype.addEventListener = function(type, listener) {
+                if (typeof listener !== 'function') {
+                    return;
+                }
+                (this._listeners[type] = this._listeners[type] || []).push(listener);
+            };
+            Notification.prototype.removeEventListener = function(type, listener) {
+                const listeners = this._listeners[type];
+                const index = listeners ? listeners.indexOf(listener) : -1;
+                if (index >= 0) {
+                    listeners.splice(index, 1);
+                }
+            };
+            Notification.prototype._fire = function(type) {
+                const event = { type: type, target: this };
+                const handler = this['on' + type];
+                const listeners = (this._listeners[type] || []).slice();
+                if (typeof handler === 'function') {
+                    listeners.unshift(handler);
+                }
+                for (const listener of listeners) {
+                    try { listener.call(this, event); } catch (err) { console.error(err); }
+                }
+            };
+            Notification.prototype.close = function() {
+                this._fire('close');
+            };
+            Object.defineProperty(Notification, 'permission', {
+                get() { return notificationPermission(); },
+                configurable: true,
+            });
+            Notification.maxActions = 0;
+            Notification.requestPermission = function(callback) {
+                return requestPermission('notifications').then(function(state) {
+                    const result = state === 'prompt' ? 'default' : state;
+                    if (typeof callback === 'function') {
+                        callback(result);
+                    }
+                    return result;
+                });
+            };
+            root.Notification = Notification;
+
+            const clipboard = {
+                readText() {
+                    return requestPermission('clipboard-read').then(function(state) {
+                        if (state !== 'granted') {
+                            throw makeError('Read permission denied.', 'NotAllowedError');
+                        }
+                        return root.__stokesClipboardReadText();
+                    });
+                },
+                writeText(text) {
+                    if (!root.__stokesClipboardWriteText(String(text))) {
+                        return Promise.reject(makeError('Failed to write to the clipboard.', 'NotAllowedError'));
+                    }
+                    return Promise.resolve();
+                },
+            };
+            Object.defineProperty(nav, 'clipboard', { value: clipboard, configurable: true, enumerable: true });
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+/// Origin of the current document, as used to key permission decisions
+fn document_origin() -> String {
+    DOM_REF.with(|dom| {
+        (*dom.borrow())
+            .map(|dom| unsafe { &*dom }.url.origin().ascii_serialization())
+            .unwrap_or_else(|| "null".to_string())
+    })
+}
+
+unsafe extern "C" fn stokes_permission_state(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let name = if argc > 0 { js_value_to_string(safe_cx, *args.get(0)) } else { String::new() };
+    match PermissionKind::from_name(&name) {
+        Some(kind) => {
+            let state = permission_state(&document_origin(), kind);
+            args.rval().set(create_js_string(safe_cx, state.as_str()));
+        }
+        None => args.rval().set(UndefinedValue()),
+    }
+    true
+}
+
+unsafe extern "C" fn stokes_request_permission(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    if argc < 2 || !args.get(1).is_number() {
+        args.rval().set(UndefinedValue());
+        return true;
+    }
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let name = js_value_to_string(safe_cx, *args.get(0));
+    let request_id = args.get(1).to_number() as u64;
+    if let Some(kind) = PermissionKind::from_name(&name) {
+        DOM_REF.with(|dom| {
+            if let Some(dom) = *dom.borrow() {
+                let dom = &*dom;
+                let _ = dom.shell_provider.sender.send(ShellProviderMessage::RequestPermission { request_id, kind });
+            }
+        });
+    }
+    args.rval().set(UndefinedValue());
+    true
+}
+
+unsafe extern "C" fn stokes_show_notification(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let title = if argc > 0 { js_value_to_string(safe_cx, *args.get(0)) } else { String::new() };
+    let body = if argc > 1 { js_value_to_string(safe_cx, *args.get(1)) } else { String::new() };
+    DOM_REF.with(|dom| {
+        if let Some(dom) = *dom.borrow() {
+            let dom = &*dom;
+            let _ = dom.shell_provider.sender.send(ShellProviderMessage::ShowNotification { title, body });
+        }
+    });
+    args.rval().set(UndefinedValue());
+    true
+}
+
+unsafe extern "C" fn stokes_clipboard_read_text(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let text = DOM_REF.with(|dom| {
+        (*dom.borrow()).and_then(|dom| unsafe { &*dom }.shell_provider.get_clipboard_text().ok())
+    });
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    args.rval().set(create_js_string(safe_cx, &text.unwrap_or_default()));
+    true
+}
+
+unsafe extern "C" fn stokes_clipboard_write_text(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let text = if argc > 0 { js_value_to_string(safe_cx, *args.get(0)) } else { String::new() };
+    let written = DOM_REF.with(|dom| {
+        (*dom.borrow()).is_some_and(|dom| unsafe { &*dom }.shell_provider.set_clipboard_text(text).is_ok())
+    });
+    args.rval().set(mozjs::jsval::BooleanValue(written));
+    true
+}
@@ -0,0 +1,121 @@
+// Pointer Lock API (element.requestPointerLock / document.exitPointerLock)
+use crate::js::bindings::dom_bindings::DOM_REF;
+use crate::js::helpers::define_function;
+use crate::js::{JsResult, JsRuntime};
+use crate::shell_provider::ShellProviderMessage;
+use mozjs::jsapi::{CallArgs, JSContext};
+use mozjs::jsval::{BooleanValue, JSVal};
+use std::os::raw::c_uint;
+
+/// Install the Pointer Lock API. The browser grabs and hides the cursor through the shell
+/// provider, then reports the mouse's relative motion as movementX/movementY of mousemove events.
+pub fn setup_pointer_lock(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesSetPointerLock", Some(stokes_set_pointer_lock), 1)
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            const doc = root.document;
+            if (!doc || typeof root.__stokesSetPointerLock !== 'function') {
+                return;
+            }
+
+            let lockElement = null;
+
+            function domError(message, name) {
+                if (typeof root.DOMException === 'function') {
+                    return new root.DOMException(message, name);
+                }
+                const error = new Error(message);
+                error.name = name;
+                return error;
+            }
+
+            function fire(type) {
+                if (typeof doc.dispatchEvent === 'function') {
+                    try {
+                        doc.dispatchEvent(new Event(type, { bubbles: true }));
+                    } catch (_err) {}
+                }
+                const handler = doc['on' + type];
+                if (typeof handler === 'function') {
+                    try { handler.call(doc, { type: type, target: doc }); } catch (_err) {}
+                }
+            }
+
+            function requestPointerLock() {
+                const element = this;
+                if (!element || element.nodeType !== 1) {
+                    return Promise.reject(new TypeError('requestPointerLock called on a non-element'));
+                }
+                if (lockElement === element) {
+                    return Promise.resolve();
+                }
+                // Only a page the user has clicked or typed on may take the pointer
+                if (!root.__stokesSetPointerLock(true)) {
+                    fire('pointerlockerror');
+                    return Promise.reject(domError('The pointer cannot be locked now', 'NotAllowedError'));
+                }
+                lockElement = element;
+                fire('pointerlockchange');
+                return Promise.resolve();
+            }
+
+            function release(notifyShell) {
+                if (!lockElement) {
+                    return;
+                }
+                lockElement = null;
+                if (notifyShell) {
+                    root.__stokesSetPointerLock(false);
+                }
+                fire('pointerlockchange');
+            }
+
+            const elementProto = root.Element && root.Element.prototype;
+            if (elementProto) {
+                elementProto.requestPointerLock = requestPointerLock;
+            }
+            if (typeof root.HTMLElement === 'function' && root.HTMLElement.prototype) {
+                root.HTMLElement.prototype.requestPointerLock = requestPointerLock;
+            }
+
+            doc.exitPointerLock = function() {
+                release(true);
+            };
+            Object.defineProperty(doc, 'pointerLockElement', {
+                get() { return lockElement; },
+                configurable: true,
+            });
+
+            // Called by the tab process when the browser released the pointer (Escape, another
+            // tab or window taking over)
+            root.__stokesExitPointerLockFromShell = function() {
+                release(false);
+            };
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+/// `__stokesSetPointerLock(locked)`: ask the browser to lock or release the pointer. Returns
+/// false when a lock is refused because the user has not interacted with the page.
+unsafe extern "C" fn stokes_set_pointer_lock(_raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let locked = argc > 0 && args.get(0).is_boolean() && args.get(0).to_boolean();
+    let sent = DOM_REF.with(|dom| {
+        let Some(dom) = *dom.borrow() else {
+            return false;
+        };
+        let dom = &*dom;
+        if locked && !dom.user_activated {
+            return false;
+        }
+        dom.shell_provider.sender.send(ShellProviderMessage::SetPointerLock(locked)).is_ok()
+    });
+    args.rval().set(BooleanValue(sent));
+    true
+}
@@ -1,28 +1,209 @@
-use crate::dom::DomNode;
+// Lifecycle of the JS objects that stand for DOM nodes. Every object carrying a `__nodeId` is
+// registered here: the element wrapper `create_js_element_by_id` hands out for a node, and the
+// snapshots, style and classList objects made for it. They hold the node alive, and the node's
+// wrapper keeps its expando properties while the node is in the document.
+//
+// The registry is weak towards the GC. Wrappers of nodes in the document are traced as roots; the
+// rest live only as long as script holds them. When the last object for a detached node is
+// collected, the node's subtree is dropped from the DOM, and a node that script still holds is
+// detached rather than dropped, so a node id seen by script never comes to mean another node.
+use crate::dom::{Dom, NodeData};
+use crate::js::bindings::dom_bindings::DOM_REF;
+use crate::js::bindings::event_listeners::{self, DOCUMENT_NODE_ID, WINDOW_NODE_ID};
+use mozjs::context::JSContext as SafeJSContext;
+use mozjs::glue::CallObjectTracer;
+use mozjs::jsapi::{Heap, JSContext, JSObject, JSTracer, JS_AddExtraGCRootsTracer, JS_AddWeakPointerZonesCallback, JS_UpdateWeakPointerAfterGC};
+use mozjs::jsval::DoubleValue;
+use mozjs::rooted;
+use mozjs::rust::wrappers2::JS_DefineProperty;
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::os::raw::c_void;
+use std::ptr;
+
+#[derive(Default)]
+struct NodeWrappers {
+    /// The one wrapper script sees for the node, so properties it sets on it stick
+    wrapper: Option<Box<Heap<*mut JSObject>>>,
+    /// Other objects carrying the node's id
+    others: Vec<Box<Heap<*mut JSObject>>>,
+}
+
+impl NodeWrappers {
+    fn is_empty(&self) -> bool {
+        self.wrapper.is_none() && self.others.is_empty()
+    }
+}
 
 thread_local! {
-    // Map from pointer address to Rc<RefCell<DomNode>>
-    static REGISTRY: RefCell<HashMap<i64, Rc<RefCell<DomNode>>>> = RefCell::new(HashMap::new());
+    static WRAPPERS: RefCell<HashMap<usize, NodeWrappers>> = RefCell::new(HashMap::new());
+    /// Nodes whose last object was collected, for `release_collected_nodes`
+    static COLLECTED: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Hook the registry into the runtime's GC. Called once per runtime.
+pub unsafe fn install_gc_hooks(cx: *mut JSContext) {
+    JS_AddExtraGCRootsTracer(cx, Some(trace_attached_wrappers), ptr::null_mut());
+    JS_AddWeakPointerZonesCallback(cx, Some(sweep_wrappers), ptr::null_mut());
+}
+
+/// Store `node_id` on `obj` as its hidden `__nodeId`, and remember that script holds the node
+pub unsafe fn set_node_id(cx: &mut SafeJSContext, obj: *mut JSObject, node_id: usize) {
+    let raw_cx = cx.raw_cx();
+    rooted!(in(raw_cx) let obj_rooted = obj);
+    rooted!(in(raw_cx) let node_id_val = DoubleValue(node_id as f64));
+    let name = c"__nodeId";
+    JS_DefineProperty(cx, obj_rooted.handle().into(), name.as_ptr(), node_id_val.handle().into(), 0);
+
+    if node_id == WINDOW_NODE_ID || node_id == DOCUMENT_NODE_ID {
+        return;
+    }
+    WRAPPERS.with(|wrappers| {
+        let heap: Box<Heap<*mut JSObject>> = Box::default();
+        heap.set(obj);
+        wrappers.borrow_mut().entry(node_id).or_default().others.push(heap);
+    });
+}
+
+/// The node's wrapper, when it still has one
+pub fn node_wrapper(node_id: usize) -> Option<*mut JSObject> {
+    WRAPPERS.with(|wrappers| {
+        let wrappers = wrappers.borrow();
+        let object = wrappers.get(&node_id)?.wrapper.as_ref()?.get();
+        (!object.is_null()).then_some(object)
+    })
+}
+
+/// Make `obj`, which already carries the node's id, the wrapper handed out for the node from now on
+pub fn set_node_wrapper(node_id: usize, obj: *mut JSObject) {
+    WRAPPERS.with(|wrappers| {
+        let mut wrappers = wrappers.borrow_mut();
+        let entry = wrappers.entry(node_id).or_default();
+        entry.others.retain(|other| other.get() != obj);
+        let heap: Box<Heap<*mut JSObject>> = Box::default();
+        heap.set(obj);
+        entry.wrapper = Some(heap);
+    });
+}
+
+/// Forget every wrapper, when the document goes away with the global that held them
+pub fn clear_node_wrappers() {
+    WRAPPERS.with(|wrappers| wrappers.borrow_mut().clear());
+    COLLECTED.with(|collected| collected.borrow_mut().clear());
+}
+
+fn has_wrappers(node_id: usize) -> bool {
+    WRAPPERS.with(|wrappers| wrappers.borrow().get(&node_id).is_some_and(|entry| !entry.is_empty()))
+}
+
+/// Drop the detached subtree at `root_id`, unless script holds an object for a node in it. The
+/// subtree is then kept, and dropped once those objects are collected.
+pub fn drop_subtree_unless_referenced(dom: &mut Dom, root_id: usize) -> bool {
+    let mut subtree = Vec::new();
+    let mut pending = vec![root_id];
+    while let Some(node_id) = pending.pop() {
+        let Some(node) = dom.get_node(node_id) else {
+            continue;
+        };
+        subtree.push(node_id);
+        pending.extend(node.children.iter().copied());
+        pending.extend(node.before);
+        pending.extend(node.after);
+    }
+    if subtree.iter().any(|&node_id| has_wrappers(node_id)) {
+        return false;
+    }
+
+    for &node_id in &subtree {
+        event_listeners::clear_listeners_for_node(node_id);
+    }
+    let _ = dom.drop_node_ignoring_parent(root_id);
+    true
+}
+
+/// Drop the detached subtrees whose last script-held object the GC collected. Run between tasks,
+/// as the GC itself can't touch the DOM.
+pub fn release_collected_nodes(dom: &mut Dom) {
+    let collected = COLLECTED.with(|collected| std::mem::take(&mut *collected.borrow_mut()));
+    for node_id in collected {
+        if dom.get_node(node_id).is_none() {
+            continue;
+        }
+        let mut root_id = node_id;
+        while let Some(parent) = dom.get_node(root_id).and_then(|node| node.parent) {
+            root_id = parent;
+        }
+
+        // Only trees script took out of the document and documents DOMParser made, which are
+        // held through their document element; template contents and shadow roots are held by
+        // their elements
+        let root = &dom.nodes[root_id];
+        let detached = matches!(root.data, NodeData::Element(_) | NodeData::Text(_) | NodeData::Comment | NodeData::Document);
+        if root.flags.is_in_document() || !detached {
+            continue;
+        }
+        drop_subtree_unless_referenced(dom, root_id);
+    }
 }
 
-/// Register a node with the given pointer value
-pub fn register_node(ptr: i64, node: &Rc<RefCell<DomNode>>) {
-    REGISTRY.with(|map| {
-        map.borrow_mut().insert(ptr, Rc::clone(node));
+/// Check that a node id script handed back still names a node. Every id script can see belongs
+/// to a node the registry keeps alive, so a missing one means an object escaped registration.
+pub fn debug_check_node_id(node_id: usize) {
+    if !cfg!(debug_assertions) || node_id == WINDOW_NODE_ID || node_id == DOCUMENT_NODE_ID {
+        return;
+    }
+    DOM_REF.with(|dom_ref| {
+        if let Some(dom_ptr) = *dom_ref.borrow() {
+            let dom = unsafe { &*dom_ptr };
+            debug_assert!(dom.get_node(node_id).is_some(), "script used node id {node_id}, which names no node");
+        }
     });
 }
 
-/// Unregister a node by pointer
-pub fn unregister_node(ptr: i64) {
-    REGISTRY.with(|map| {
-        map.borrow_mut().remove(&ptr);
+fn is_in_document(node_id: usize) -> bool {
+    DOM_REF.with(|dom_ref| {
+        dom_ref
+            .borrow()
+            .is_some_and(|dom_ptr| unsafe { (*dom_ptr).get_node(node_id).is_some_and(|node| node.flags.is_in_document()) })
+    })
+}
+
+/// Trace the wrappers of nodes in the document, so script finds them as it left them
+unsafe extern "C" fn trace_attached_wrappers(trc: *mut JSTracer, _data: *mut c_void) {
+    WRAPPERS.with(|wrappers| {
+        let Ok(wrappers) = wrappers.try_borrow() else {
+            return;
+        };
+        for (&node_id, entry) in wrappers.iter() {
+            if let Some(wrapper) = &entry.wrapper
+                && is_in_document(node_id)
+            {
+                CallObjectTracer(trc, &**wrapper as *const Heap<*mut JSObject> as *mut _, c"node wrapper".as_ptr());
+            }
+        }
     });
 }
 
-/// Attempt to return an Rc to the DomNode for a given pointer
-pub fn get_node(ptr: i64) -> Option<Rc<RefCell<DomNode>>> {
-    REGISTRY.with(|map| map.borrow().get(&ptr).cloned())
+/// Let go of collected objects, and note the nodes left without any
+unsafe extern "C" fn sweep_wrappers(trc: *mut JSTracer, _data: *mut c_void) {
+    WRAPPERS.with(|wrappers| {
+        let Ok(mut wrappers) = wrappers.try_borrow_mut() else {
+            return;
+        };
+        let update = |heap: &Heap<*mut JSObject>| {
+            JS_UpdateWeakPointerAfterGC(trc, heap as *const Heap<*mut JSObject> as *mut _);
+            !heap.get().is_null()
+        };
+        wrappers.retain(|&node_id, entry| {
+            if entry.wrapper.as_ref().is_some_and(|wrapper| !update(&**wrapper)) {
+                entry.wrapper = None;
+            }
+            entry.others.retain(|other| update(&**other));
+            if entry.is_empty() {
+                COLLECTED.with(|collected| collected.borrow_mut().push(node_id));
+                return false;
+            }
+            true
+        });
+    });
 }
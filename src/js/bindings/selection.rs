@@ -0,0 +1,544 @@
+// Selection API (window.getSelection, Selection and Range)
+use crate::dom::{Dom, NodeData};
+use crate::js::bindings::dom_bindings::DOM_REF;
+use crate::js::bindings::event_listeners::DOCUMENT_NODE_ID;
+use crate::js::bindings::node::node_create_js_node_wrapper_by_id;
+use crate::js::helpers::{
+    create_empty_array, create_js_string, define_function, get_node_id_from_value, js_value_to_f64, ToSafeCx,
+};
+use crate::js::{JsResult, JsRuntime};
+use mozjs::context::JSContext as SafeJSContext;
+use mozjs::jsapi::{CallArgs, JSContext};
+use mozjs::jsval::{BooleanValue, Int32Value, JSVal, NullValue, ObjectValue, UndefinedValue};
+use mozjs::rooted;
+use mozjs::rust::wrappers::JS_SetElement;
+use std::cmp::Ordering;
+use std::os::raw::c_uint;
+
+/// Install window.getSelection, document.getSelection, Selection, Range and
+/// document.createRange. The selection is the one the user makes with the mouse: reading it maps
+/// the selected laid-out text back to text nodes, and setting it selects the text a script's
+/// boundary points fall in. Points in text that isn't laid out, like in display:none elements,
+/// can't be shown, so the selection keeps them without drawing anything until the user selects.
+/// Ranges are plain boundary points and don't follow later changes to the document.
+/// `selectionchange` fires at the document once a frame whenever the selection moved.
+pub fn setup_selection(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesSelectionEndpoints", Some(stokes_selection_endpoints), 0)?;
+        define_function(cx, global.get(), "__stokesSetSelection", Some(stokes_set_selection), 4)?;
+        define_function(cx, global.get(), "__stokesClearSelection", Some(stokes_clear_selection), 0)?;
+        define_function(cx, global.get(), "__stokesSelectedText", Some(stokes_selected_text), 0)?;
+        define_function(cx, global.get(), "__stokesCompareBoundaryPoints", Some(stokes_compare_boundary_points), 4)?;
+        define_function(cx, global.get(), "__stokesRangeText", Some(stokes_range_text), 4)?;
+        define_function(cx, global.get(), "__stokesNodeLength", Some(stokes_node_length), 1)?;
+        define_function(cx, global.get(), "__stokesNodeIndex", Some(stokes_node_index), 1)
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            if (typeof root.__stokesSelectionEndpoints !== 'function') {
+                return;
+            }
+
+            function domError(message, name) {
+                if (typeof root.DOMException === 'function') {
+                    return new root.DOMException(message, name);
+                }
+                const error = new Error(message);
+                error.name = name;
+                return error;
+            }
+
+            function checkNode(node, method, type) {
+                if (node === null || typeof node !== 'object') {
+                    throw new TypeError("Failed to execute '" + method + "' on '" + type + "': parameter 1 is not of type 'Node'.");
+                }
+            }
+
+            function checkOffset(node, offset, method, type) {
+                offset = offset === undefined ? 0 : Number(offset) >>> 0;
+                if (offset > root.__stokesNodeLength(node)) {
+                    throw domError("Failed to execute '" + method + "' on '" + type + "': There is no child at offset " + offset + '.', 'IndexSizeError');
+                }
+                return offset;
+            }
+
+            function compare(aNode, aOffset, bNode, bOffset) {
+                return root.__stokesCompareBoundaryPoints(aNode, aOffset, bNode, bOffset);
+            }
+
+            function Range() {
+                this._startContainer = root.document;
+                this._startOffset = 0;
+                this._endContainer = root.document;
+                this._endOffset = 0;
+            }
+            const START_TO_START = 0;
+            const START_TO_END = 1;
+            const END_TO_END = 2;
+            const END_TO_START = 3;
+            for (const target of [Range, Range.prototype]) {
+                target.START_TO_START = START_TO_START;
+                target.START_TO_END = START_TO_END;
+                target.END_TO_END = END_TO_END;
+                target.END_TO_START = END_TO_START;
+            }
+            Object.defineProperties(Range.prototype, {
+                startContainer: { get() { return this._startContainer; }, configurable: true },
+                startOffset: { get() { return this._startOffset; }, configurable: true },
+                endContainer: { get() { return this._endContainer; }, configurable: true },
+                endOffset: { get() { return this._endOffset; }, configurable: true },
+                collapsed: {
+                    get() { return compare(this._startContainer, this._startOffset, this._endContainer, this._endOffset) === 0; },
+                    configurable: true,
+                },
+                commonAncestorContainer: {
+                    get() {
+                        let node = this._startContainer;
+                        while (node && node !== root.document && !node.contains(this._endContainer)) {
+                            node = node.parentNode;
+                        }
+                        return node || root.document;
+                    },
+                    configurable: true,
+                },
+            });
+            Range.prototype.setStart = function(node, offset) {
+                checkNode(node, 'setStart', 'Range');
+                offset = checkOffset(node, offset, 'setStart', 'Range');
+                this._startContainer = node;
+                this._startOffset = offset;
+                if (compare(node, offset, this._endContainer, this._endOffset) > 0) {
+                    this._endContainer = node;
+                    this._endOffset = offset;
+                }
+            };
+            Range.prototype.setEnd = function(node, offset) {
+                checkNode(node, 'setEnd', 'Range');
+                offset = checkOffset(node, offset, 'setEnd', 'Range');
+                this._endContainer = node;
+                this._endOffset = offset;
+                if (compare(this._startContainer, this._startOffset, node, offset) > 0) {
+                    this._startContainer = node;
+                    this._startOffset = offset;
+                }
+            };
+            function parentAndIndex(node, method) {
+                checkNode(node, method, 'Range');
+                const parent = node.parentNode;
+                if (!parent) {
+                    throw domError("Failed to execute '" + method + "' on 'Range': the given Node has no parent.", 'InvalidNodeTypeError');
+                }
+                return [parent, root.__stokesNodeIndex(node)];
+            }
+            Range.prototype.setStartBefore = function(node) {
+                const [parent, index] = parentAndIndex(node, 'setStartBefore');
+                this.setStart(parent, index);
+            };
+            Range.prototype.setStartAfter = function(node) {
+                const [parent, index] = parentAndIndex(node, 'setStartAfter');
+                this.setStart(parent, index + 1);
+            };
+            Range.prototype.setEndBefore = function(node) {
+                const [parent, index] = parentAndIndex(node, 'setEndBefore');
+                this.setEnd(parent, index);
+            };
+            Range.prototype.setEndAfter = function(node) {
+                const [parent, index] = parentAndIndex(node, 'setEndAfter');
+                this.setEnd(parent, index + 1);
+            };
+            Range.prototype.collapse = function(toStart) {
+                if (toStart) {
+                    this._endContainer = this._startContainer;
+                    this._endOffset = this._startOffset;
+                } else {
+                    this._startContainer = this._endContainer;
+                    this._startOffset = this._endOffset;
+                }
+            };
+            Range.prototype.selectNode = function(node) {
+                const [parent, index] = parentAndIndex(node, 'selectNode');
+                this._startContainer = this._endContainer = parent;
+                this._startOffset = index;
+                this._endOffset = index + 1;
+            };
+            Range.prototype.selectNodeContents = function(node) {
+                checkNode(node, 'selectNodeContents', 'Range');
+                this._startContainer = this._endContainer = node;
+                this._startOffset = 0;
+                this._endOffset = root.__stokesNodeLength(node);
+            };
+            Range.prototype.compareBoundaryPoints = function(how, sourceRange) {
+                if (!(sourceRange instanceof Range)) {
+                    throw new TypeError("Failed to execute 'compareBoundaryPoints' on 'Range': parameter 2 is not of type 'Range'.");
+                }
+                switch (Number(how)) {
+                    case START_TO_START:
+                        return compare(this._startContainer, this._startOffset, sourceRange._startContainer, sourceRange._startOffset);
+                    case START_TO_END:
+                        return compare(this._endContainer, this._endOffset, sourceRange._startContainer, sourceRange._startOffset);
+                    case END_TO_END:
+                        return compare(this._endContainer, this._endOffset, sourceRange._endContainer, sourceRange._endOffset);
+                    case END_TO_START:
+                        return compare(this._startContainer, this._startOffset, sourceRange._endContainer, sourceRange._endOffset);
+                    default:
+                        throw domError("Failed to execute 'compareBoundaryPoints' on 'Range': The comparison method provided must be one of 'START_TO_START', 'START_TO_END', 'END_TO_END', or 'END_TO_START'.", 'NotSupportedError');
+                }
+            };
+            Range.prototype.comparePoint = function(node, offset) {
+                checkNode(node, 'comparePoint', 'Range');
+                offset = checkOffset(node, offset, 'comparePoint', 'Range');
+                if (compare(node, offset, this._startContainer, this._startOffset) < 0) {
+                    return -1;
+                }
+                return compare(node, offset, this._endContainer, this._endOffset) > 0 ? 1 : 0;
+            };
+            Range.prototype.isPointInRange = function(node, offset) {
+                try {
+                    return this.comparePoint(node, offset) === 0;
+                } catch (_err) {
+                    return false;
+                }
+            };
+            Range.prototype.intersectsNode = function(node) {
+                checkNode(node, 'intersectsNode', 'Range');
+                return compare(node, 0, this._endContainer, this._endOffset) < 0
+                    && compare(node, root.__stokesNodeLength(node), this._startContainer, this._startOffset) > 0;
+            };
+            Range.prototype.cloneRange = function() {
+                const range = new Range();
+                range._startContainer = this._startContainer;
+                range._startOffset = this._startOffset;
+                range._endContainer = this._endContainer;
+                range._endOffset = this._endOffset;
+                return range;
+            };
+            Range.prototype.toString = function() {
+                return root.__stokesRangeText(this._startContainer, this._startOffset, this._endContainer, this._endOffset);
+            };
+            Range.prototype.detach = function() {};
+
+            function rangeBetween(startNode, startOffset, endNode, endOffset) {
+                const range = new Range();
+                range._startContainer = startNode;
+                range._startOffset = startOffset;
+                range._endContainer = endNode;
+                range._endOffset = endOffset;
+                return range;
+            }
+
+            // [anchorNode, anchorOffset, focusNode, focusOffset] of a selection set in text that
+            // isn't laid out, which the page can't show
+            let unshown = null;
+
+            function endpoints() {
+                return unshown || root.__stokesSelectionEndpoints();
+            }
+
+            function select(anchorNode, anchorOffset, focusNode, focusOffset) {
+                unshown = null;
+                if (!root.__stokesSetSelection(anchorNode, anchorOffset, focusNode, focusOffset)) {
+                    root.__stokesClearSelection();
+                    unshown = [anchorNode, anchorOffset, focusNode, focusOffset];
+                }
+            }
+
+            function Selection() {
+                throw new TypeError('Illegal constructor');
+            }
+            const selection = Object.create(Selection.prototype);
+            Object.defineProperties(Selection.prototype, {
+                anchorNode: { get() { const points = endpoints(); return points ? points[0] : null; }, configurable: true },
+                anchorOffset: { get() { const points = endpoints(); return points ? points[1] : 0; }, configurable: true },
+                focusNode: { get() { const points = endpoints(); return points ? points[2] : null; }, configurable: true },
+                focusOffset: { get() { const points = endpoints(); return points ? points[3] : 0; }, configurable: true },
+                isCollapsed: {
+                    get() {
+                        const points = endpoints();
+                        return !points || compare(points[0], points[1], points[2], points[3]) === 0;
+                    },
+                    configurable: true,
+                },
+                rangeCount: { get() { return endpoints() ? 1 : 0; }, configurable: true },
+                type: {
+                    get() {
+                        const points = endpoints();
+                        if (!points) {
+                            return 'None';
+                        }
+                        return compare(points[0], points[1], points[2], points[3]) === 0 ? 'Caret' : 'Range';
+                    },
+                    configurable: true,
+                },
+                direction: {
+                    get() {
+                        const points = endpoints();
+                        const order = points ? compare(points[0], points[1], points[2], points[3]) : 0;
+                        return order === 0 ? 'none' : order < 0 ? 'forward' : 'backward';
+                    },
+                    configurable: true,
+                },
+            });
+            Selection.prototype.getRangeAt = function(index) {
+                const points = endpoints();
+                if (!points || Number(index) !== 0) {
+                    throw domError("Failed to execute 'getRangeAt' on 'Selection': " + index + ' is not a valid index.', 'IndexSizeError');
+                }
+                if (compare(points[0], points[1], points[2], points[3]) <= 0) {
+                    return rangeBetween(points[0], points[1], points[2], points[3]);
+                }
+                return rangeBetween(points[2], points[3], points[0], points[1]);
+            };
+            // Like other browsers, only one range is selected at a time
+            Selection.prototype.addRange = function(range) {
+                if (!(range instanceof Range)) {
+                    throw new TypeError("Failed to execute 'addRange' on 'Selection': parameter 1 is not of type 'Range'.");
+                }
+                if (!endpoints()) {
+                    select(range._startContainer, range._startOffset, range._endContainer, range._endOffset);
+                }
+            };
+            Selection.prototype.removeAllRanges = function() {
+                unshown = null;
+                root.__stokesClearSelection();
+            };
+            Selection.prototype.empty = Selection.prototype.removeAllRanges;
+            Selection.prototype.removeRange = function(range) {
+                if (!(range instanceof Range)) {
+                    throw new TypeError("Failed to execute 'removeRange' on 'Selection': parameter 1 is not of type 'Range'.");
+                }
+                this.removeAllRanges();
+            };
+            Selection.prototype.collapse = function(node, offset) {
+                if (node === null) {
+                    this.removeAllRanges();
+                    return;
+                }
+                checkNode(node, 'collapse', 'Selection');
+                offset = checkOffset(node, offset, 'collapse', 'Selection');
+                select(node, offset, node, offset);
+            };
+            Selection.prototype.setPosition = Selection.prototype.collapse;
+            Selection.prototype.collapseToStart = function() {
+                if (!endpoints()) {
+                    throw domError("Failed to execute 'collapseToStart' on 'Selection': there is no selection.", 'InvalidStateError');
+                }
+                const range = this.getRangeAt(0);
+                select(range._startContainer, range._startOffset, range._startContainer, range._startOffset);
+            };
+            Selection.prototype.collapseToEnd = function() {
+                if (!endpoints()) {
+                    throw domError("Failed to execute 'collapseToEnd' on 'Selection': there is no selection.", 'InvalidStateError');
+                }
+                const range = this.getRangeAt(0);
+                select(range._endContainer, range._endOffset, range._endContainer, range._endOffset);
+            };
+            Selection.prototype.extend = function(node, offset) {
+                const points = endpoints();
+                if (!points) {
+                    throw domError("Failed to execute 'extend' on 'Selection': This Selection object doesn't have any Ranges.", 'InvalidStateError');
+                }
+                checkNode(node, 'extend', 'Selection');
+                offset = checkOffset(node, offset, 'extend', 'Selection');
+                select(points[0], points[1], node, offset);
+            };
+            Selection.prototype.setBaseAndExtent = function(anchorNode, anchorOffset, focusNode, focusOffset) {
+                checkNode(anchorNode, 'setBaseAndExtent', 'Selection');
+                checkNode(focusNode, 'setBaseAndExtent', 'Selection');
+                anchorOffset = checkOffset(anchorNode, anchorOffset, 'setBaseAndExtent', 'Selection');
+                focusOffset = checkOffset(focusNode, focusOffset, 'setBaseAndExtent', 'Selection');
+                select(anchorNode, anchorOffset, focusNode, focusOffset);
+            };
+            Selection.prototype.selectAllChildren = function(node) {
+                checkNode(node, 'selectAllChildren', 'Selection');
+                select(node, 0, node, root.__stokesNodeLength(node));
+            };
+            Selection.prototype.containsNode = function(node, allowPartialContainment) {
+                if (!endpoints() || node === null || typeof node !== 'object') {
+                    return false;
+                }
+                const range = this.getRangeAt(0);
+                const length = root.__stokesNodeLength(node);
+                if (allowPartialContainment) {
+                    return range.intersectsNode(node);
+                }
+                return compare(range._startContainer, range._startOffset, node, 0) <= 0
+                    && compare(node, length, range._endContainer, range._endOffset) <= 0;
+            };
+            Selection.prototype.toString = function() {
+                if (unshown) {
+                    return this.getRangeAt(0).toString();
+                }
+                return root.__stokesSelectedText() || '';
+            };
+
+            root.Range = Range;
+            root.Selection = Selection;
+            root.getSelection = function() {
+                return selection;
+            };
+            if (root.document) {
+                root.document.getSelection = root.getSelection;
+                root.document.createRange = function() {
+                    return new Range();
+                };
+                // The user selecting something replaces a selection the page couldn't show
+                root.document.addEventListener('selectionchange', function() {
+                    if (unshown && root.__stokesSelectionEndpoints()) {
+                        unshown = null;
+                    }
+                });
+            }
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+/// The node a JS value wraps, with the document as its root node
+unsafe fn node_arg(cx: &mut SafeJSContext, val: JSVal) -> Option<usize> {
+    let node_id = get_node_id_from_value(cx, val)?;
+    Some(if node_id == DOCUMENT_NODE_ID { 0 } else { node_id })
+}
+
+/// The boundary point passed as a node and an offset starting at argument `index`
+unsafe fn boundary_point_arg(cx: &mut SafeJSContext, args: &CallArgs, index: u32) -> Option<(usize, usize)> {
+    let node_id = node_arg(cx, *args.get(index))?;
+    let offset = js_value_to_f64(*args.get(index + 1)).unwrap_or(0.0).max(0.0) as usize;
+    Some((node_id, offset))
+}
+
+fn with_dom<R>(f: impl FnOnce(&mut Dom) -> Option<R>) -> Option<R> {
+    DOM_REF.with(|dom| {
+        let dom = unsafe { &mut *(*dom.borrow())? };
+        f(dom)
+    })
+}
+
+/// `__stokesSelectionEndpoints()`: the selection's anchor and focus as
+/// `[anchorNode, anchorOffset, focusNode, focusOffset]`, or null when nothing is selected
+unsafe extern "C" fn stokes_selection_endpoints(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let Some(((anchor_node, anchor_offset), (focus_node, focus_offset))) = with_dom(|dom| dom.selection_endpoints()) else {
+        args.rval().set(NullValue());
+        return true;
+    };
+
+    rooted!(in(raw_cx) let array = create_empty_array(safe_cx));
+    let points = [(anchor_node, anchor_offset), (focus_node, focus_offset)];
+    for (index, (node_id, offset)) in points.into_iter().enumerate() {
+        let Some(node) = node_create_js_node_wrapper_by_id(safe_cx, node_id) else {
+            args.rval().set(NullValue());
+            return true;
+        };
+        rooted!(in(raw_cx) let node = node);
+        rooted!(in(raw_cx) let offset = Int32Value(offset as i32));
+        JS_SetElement(raw_cx, array.handle().into(), 2 * index as u32, node.handle().into());
+        JS_SetElement(raw_cx, array.handle().into(), 2 * index as u32 + 1, offset.handle().into());
+    }
+    args.rval().set(ObjectValue(array.get()));
+    true
+}
+
+/// `__stokesSetSelection(anchorNode, anchorOffset, focusNode, focusOffset)`: select between the
+/// two points. Returns false when either isn't in laid-out text.
+unsafe extern "C" fn stokes_set_selection(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let (Some(anchor), Some(focus)) = (boundary_point_arg(safe_cx, &args, 0), boundary_point_arg(safe_cx, &args, 2)) else {
+        args.rval().set(BooleanValue(false));
+        return true;
+    };
+    let selected = with_dom(|dom| Some(dom.set_selection_endpoints(anchor, focus))).unwrap_or(false);
+    args.rval().set(BooleanValue(selected));
+    true
+}
+
+/// `__stokesClearSelection()`: select nothing
+unsafe extern "C" fn stokes_clear_selection(_raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    with_dom(|dom| {
+        if dom.text_selection.anchor.is_some() {
+            dom.clear_text_selection();
+            dom.shell_provider.request_redraw();
+        }
+        Some(())
+    });
+    args.rval().set(UndefinedValue());
+    true
+}
+
+/// `__stokesSelectedText()`: the selected text as shown, or null when nothing is selected
+unsafe extern "C" fn stokes_selected_text(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    match with_dom(|dom| dom.get_selected_text()) {
+        Some(text) => args.rval().set(create_js_string(safe_cx, &text)),
+        None => args.rval().set(NullValue()),
+    }
+    true
+}
+
+/// `__stokesCompareBoundaryPoints(aNode, aOffset, bNode, bOffset)`: -1, 0 or 1 as the first point
+/// is before, at or after the second
+unsafe extern "C" fn stokes_compare_boundary_points(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let order = match (boundary_point_arg(safe_cx, &args, 0), boundary_point_arg(safe_cx, &args, 2)) {
+        (Some(a), Some(b)) => with_dom(|dom| Some(dom.compare_boundary_points(a, b))).unwrap_or(Ordering::Equal),
+        _ => Ordering::Equal,
+    };
+    args.rval().set(Int32Value(order as i32));
+    true
+}
+
+/// `__stokesRangeText(startNode, startOffset, endNode, endOffset)`: the text in the range
+unsafe extern "C" fn stokes_range_text(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let text = match (boundary_point_arg(safe_cx, &args, 0), boundary_point_arg(safe_cx, &args, 2)) {
+        (Some(start), Some(end)) => with_dom(|dom| Some(dom.range_text(start, end))).unwrap_or_default(),
+        _ => String::new(),
+    };
+    args.rval().set(create_js_string(safe_cx, &text));
+    true
+}
+
+/// `__stokesNodeLength(node)`: the largest offset in `node`, which is its length in UTF-16 code
+/// units for text and its number of children otherwise
+unsafe extern "C" fn stokes_node_length(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let node_id = if argc > 0 { node_arg(safe_cx, *args.get(0)) } else { None };
+    let length = node_id
+        .and_then(|node_id| {
+            with_dom(|dom| {
+                let node = dom.get_node(node_id)?;
+                Some(match &node.data {
+                    NodeData::Text(data) => data.content.encode_utf16().count(),
+                    NodeData::Comment => 0,
+                    _ => node.children.len(),
+                })
+            })
+        })
+        .unwrap_or(0);
+    args.rval().set(Int32Value(length as i32));
+    true
+}
+
+/// `__stokesNodeIndex(node)`: where `node` is among its parent's children, or -1 without a parent
+unsafe extern "C" fn stokes_node_index(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let node_id = if argc > 0 { node_arg(safe_cx, *args.get(0)) } else { None };
+    let index = node_id.and_then(|node_id| {
+        with_dom(|dom| {
+            let parent = dom.get_node(node_id)?.parent?;
+            dom.get_node(parent)?.index_of_child(node_id)
+        })
+    });
+    args.rval().set(Int32Value(index.map_or(-1, |index| index as i32)));
+    true
+}
@@ -0,0 +1,378 @@
+// Service workers (navigator.serviceWorker) and fetch interception
+use crate::js::{JsResult, JsRuntime};
+
+/// Install `navigator.serviceWorker`. Registrations are saved with the origin's other site data,
+/// including the worker's script, so a page that was registered can be served from its worker's
+/// caches while offline.
+///
+/// This is a minimal implementation: the worker doesn't get a thread or runtime of its own but
+/// runs in the page's runtime, in a function whose parameters stand in for the worker's global
+/// scope. Only the page's fetch() calls are passed to its fetch handlers; navigations,
+/// subresources and XMLHttpRequest still go to the network. There is a worker per page rather
+/// than one shared by the origin's pages, and response bodies are text.
+pub fn setup_service_worker(runtime: &mut JsRuntime) -> JsResult<()> {
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            const nav = root.navigator;
+            const location = root.location;
+            if (!nav || !location || typeof root.__stokesSiteData !== 'function' || typeof root.caches !== 'object') {
+                return;
+            }
+
+            function domError(message, name) {
+                if (typeof root.DOMException === 'function') {
+                    return new root.DOMException(message, name);
+                }
+                const error = new Error(message);
+                error.name = name;
+                return error;
+            }
+
+            function isSecure(url) {
+                return url.protocol === 'https:'
+                    || url.hostname === 'localhost'
+                    || url.hostname === '127.0.0.1'
+                    || url.hostname === '[::1]';
+            }
+
+            // addEventListener / removeEventListener / dispatchEvent and on<type> handlers
+            function eventTarget(target) {
+                const listeners = {};
+                target.addEventListener = function(type, listener) {
+                    if (typeof listener !== 'function' && !(listener && typeof listener.handleEvent === 'function')) {
+                        return;
+                    }
+                    (listeners[type] = listeners[type] || []).push(listener);
+                };
+                target.removeEventListener = function(type, listener) {
+                    listeners[type] = (listeners[type] || []).filter(function(item) { return item !== listener; });
+                };
+                target.dispatchEvent = function(event) {
+                    const handlers = (listeners[event.type] || []).slice();
+                    if (typeof target['on' + event.type] === 'function') {
+                        handlers.push(target['on' + event.type]);
+                    }
+                    for (const handler of handlers) {
+                        try {
+                            if (typeof handler === 'function') {
+                                handler.call(target, event);
+                            } else {
+                                handler.handleEvent(event);
+                            }
+                        } catch (error) {
+                            console.error(error);
+                        }
+                    }
+                    return true;
+                };
+                return target;
+            }
+
+            // Fetches the page and the worker make without going through a worker
+            const networkFetch = root.fetch;
+
+            // The origin's registrations, saved as [{ scope, scriptURL, source }]
+            function loadRegistrations() {
+                const json = root.__stokesSiteData('serviceWorkers');
+                return typeof json === 'string' ? JSON.parse(json) : [];
+            }
+
+            function saveRegistrations(records) {
+                const json = records.length === 0 ? null : JSON.stringify(records);
+                if (root.__stokesSiteData('serviceWorkers', json) === false) {
+                    throw domError('The quota has been exceeded.', 'QuotaExceededError');
+                }
+            }
+
+            // The registration with the longest scope that `url` is in
+            function recordFor(records, url) {
+                let found = null;
+                for (const record of records) {
+                    if (url.indexOf(record.scope) === 0 && (!found || record.scope.length > found.scope.length)) {
+                        found = record;
+                    }
+                }
+                return found;
+            }
+
+            class ServiceWorker {
+                constructor(scriptURL) {
+                    eventTarget(this);
+                    this.scriptURL = scriptURL;
+                    this.state = 'parsed';
+                    this.onstatechange = null;
+                    this.onerror = null;
+                }
+                postMessage() {}
+                _setState(state) {
+                    this.state = state;
+                    this.dispatchEvent({ type: 'statechange', target: this });
+                }
+            }
+
+            class ServiceWorkerRegistration {
+                constructor(scope) {
+                    eventTarget(this);
+                    this.scope = scope;
+                    this.installing = null;
+                    this.waiting = null;
+                    this.active = null;
+                    this.updateViaCache = 'imports';
+                    this.onupdatefound = null;
+                }
+                update() {
+                    const record = recordFor(loadRegistrations(), this.scope);
+                    if (!record || record.scope !== this.scope) {
+                        return Promise.reject(domError('The registration has been removed.', 'InvalidStateError'));
+                    }
+                    return install(record.scriptURL, this.scope).then(function() {});
+                }
+                unregister() {
+                    const scope = this.scope;
+                    const records = loadRegistrations();
+                    const remaining = records.filter(function(record) { return record.scope !== scope; });
+                    if (remaining.length === records.length) {
+                        return Promise.resolve(false);
+                    }
+                    saveRegistrations(remaining);
+                    delete registrations[scope];
+                    return Promise.resolve(true);
+                }
+            }
+
+            const registrations = {};
+
+            function registrationFor(scope) {
+                if (!registrations[scope]) {
+                    registrations[scope] = new ServiceWorkerRegistration(scope);
+                }
+                return registrations[scope];
+            }
+
+            // A running worker: the listeners its script added to its global scope
+            function startWorker(source, scriptURL, registration) {
+                const scope = eventTarget({});
+                scope.self = scope;
+                scope.globalThis = scope;
+                scope.registration = registration;
+                scope.caches = root.caches;
+                scope.location = new URL(scriptURL);
+                scope.fetch = function(input, init) { return networkFetch.call(root, input, init); };
+                scope.skipWaiting = function() { return Promise.resolve(); };
+                scope.importScripts = function() {
+                    throw domError('importScripts() isn\'t supported by this browser\'s service workers.', 'NotSupportedError');
+                };
+                scope.clients = {
+                    claim: function() {
+                        claimed = scope;
+                        return Promise.resolve();
+                    },
+                    get: function() { return Promise.resolve(undefined); },
+                    matchAll: function() { return Promise.resolve([]); },
+                    openWindow: function() { return Promise.resolve(null); },
+                };
+
+                // The parameters shadow the page's globals that a worker doesn't have
+                const names = ['self', 'globalThis', 'addEventListener', 'removeEventListener', 'registration',
+                    'caches', 'fetch', 'location', 'skipWaiting', 'importScripts', 'clients',
+                    'window', 'document', 'localStorage', 'sessionStorage', 'navigator'];
+                const values = [scope, scope, scope.addEventListener, scope.removeEventListener, registration,
+                    scope.caches, scope.fetch, scope.location, scope.skipWaiting, scope.importScripts, scope.clients,
+                    undefined, undefined, undefined, undefined, undefined];
+                const run = new Function(names.join(', '), source + '\n//# sourceURL=' + scriptURL);
+                run.apply(scope, values);
+                return scope;
+            }
+
+            // Dispatch an install or activate event and wait for the promises given to waitUntil
+            function dispatchExtendable(scope, type) {
+                const waits = [];
+                scope.dispatchEvent({
+                    type: type,
+                    waitUntil: function(promise) { waits.push(Promise.resolve(promise)); },
+                });
+                return Promise.all(waits);
+            }
+
+            // The worker that handles this page's fetches and the scope it controls
+            let controller = null;
+            let controllerScope = null;
+            let controllerScopeUrl = null;
+            let claimed = null;
+            let readyResolve;
+            const ready = new Promise(function(resolve) { readyResolve = resolve; });
+
+            function control(scope, worker, registration) {
+                const changed = controller !== null && controller !== worker;
+                controller = worker;
+                controllerScope = scope;
+                controllerScopeUrl = registration.scope;
+                readyResolve(registration);
+                if (changed) {
+                    container.dispatchEvent({ type: 'controllerchange', target: container });
+                }
+            }
+
+            // Run the script at `scriptURL` through install and activate, then save the registration
+            function install(scriptURL, scopeUrl) {
+                return networkFetch.call(root, scriptURL).then(function(response) {
+                    if (!response.ok) {
+                        throw new TypeError('Failed to fetch the service worker script ' + scriptURL + ' (status ' + response.status + ')');
+                    }
+                    return response.text();
+                }).then(function(source) {
+                    const registration = registrationFor(scopeUrl);
+                    const worker = new ServiceWorker(scriptURL);
+                    registration.installing = worker;
+                    registration.dispatchEvent({ type: 'updatefound', target: registration });
+                    claimed = null;
+                    const scope = startWorker(source, scriptURL, registration);
+                    worker._setState('installing');
+                    return dispatchExtendable(scope, 'install').then(function() {
+                        registration.installing = null;
+                        registration.waiting = worker;
+                        worker._setState('installed');
+                        registration.waiting = null;
+                        registration.active = worker;
+                        worker._setState('activating');
+                        return dispatchExtendable(scope, 'activate');
+                    }).then(function() {
+                        worker._setState('activated');
+                        const records = loadRegistrations().filter(function(record) { return record.scope !== scopeUrl; });
+                        records.push({ scope: scopeUrl, scriptURL: scriptURL, source: source });
+                        saveRegistrations(records);
+                        // Like a browser, the page that registered a worker is only controlled by it
+                        // once the worker claims its clients
+                        if (claimed === scope && location.href.indexOf(scopeUrl) === 0) {
+                            control(scope, worker, registration);
+                        } else if (location.href.indexOf(scopeUrl) === 0) {
+                            readyResolve(registration);
+                        }
+                        return registration;
+                    }, function(error) {
+                        registration.installing = null;
+                        if (registration.active === worker) {
+                            registration.active = null;
+                        }
+                        worker._setState('redundant');
+                        throw error;
+                    });
+                });
+            }
+
+            const container = eventTarget({});
+            container.oncontrollerchange = null;
+            container.onmessage = null;
+            Object.defineProperty(container, 'controller', {
+                get: function() { return controller; },
+                enumerable: true,
+            });
+            Object.defineProperty(container, 'ready', {
+                get: function() { return ready; },
+                enumerable: true,
+            });
+            container.register = function(scriptURL, options) {
+                return new Promise(function(resolve) {
+                    const page = new URL(location.href);
+                    if (!isSecure(page)) {
+                        throw domError('Service workers need a secure context.', 'SecurityError');
+                    }
+                    const script = new URL(String(scriptURL), page.href);
+                    if (script.origin !== page.origin) {
+                        throw domError('The script ' + script.href + ' isn\'t from the page\'s origin.', 'SecurityError');
+                    }
+                    const maxScope = new URL('./', script.href).href;
+                    const scope = options && options.scope !== undefined
+                        ? new URL(String(options.scope), page.href).href
+                        : maxScope;
+                    if (new URL(scope).origin !== page.origin || scope.indexOf(maxScope) !== 0) {
+                        throw domError('The scope ' + scope + ' isn\'t within the script\'s directory ' + maxScope + '.', 'SecurityError');
+                    }
+                    const existing = loadRegistrations().find(function(record) { return record.scope === scope; });
+                    if (existing && existing.scriptURL === script.href && registrations[scope] && registrations[scope].active) {
+                        resolve(registrations[scope]);
+                        return;
+                    }
+                    resolve(install(script.href, scope));
+                });
+            };
+            container.getRegistration = function(clientURL) {
+                const url = new URL(clientURL === undefined ? location.href : String(clientURL), location.href).href;
+                const record = recordFor(loadRegistrations(), url);
+                return Promise.resolve(record ? registrationFor(record.scope) : undefined);
+            };
+            container.getRegistrations = function() {
+                return Promise.resolve(loadRegistrations().map(function(record) { return registrationFor(record.scope); }));
+            };
+            container.startMessages = function() {};
+
+            root.ServiceWorker = ServiceWorker;
+            root.ServiceWorkerRegistration = ServiceWorkerRegistration;
+            Object.defineProperty(nav, 'serviceWorker', {
+                value: container,
+                configurable: true,
+                enumerable: true,
+            });
+
+            // A page loaded within a registration's scope is controlled by its worker from the start
+            const record = recordFor(loadRegistrations(), location.href);
+            if (record && isSecure(new URL(location.href))) {
+                try {
+                    const registration = registrationFor(record.scope);
+                    const worker = new ServiceWorker(record.scriptURL);
+                    worker.state = 'activated';
+                    registration.active = worker;
+                    control(startWorker(record.source, record.scriptURL, registration), worker, registration);
+                } catch (error) {
+                    console.error('Service worker ' + record.scriptURL + ' failed to start:', error);
+                }
+            }
+
+            // Same-origin fetches within the controlling worker's scope go to its fetch handlers first
+            root.fetch = function(input, init) {
+                const scope = controllerScope;
+                if (!scope) {
+                    return networkFetch.apply(root, arguments);
+                }
+                let request;
+                try {
+                    request = new root.Request(input, init);
+                } catch (error) {
+                    return networkFetch.apply(root, arguments);
+                }
+                if (new URL(request.url).origin !== location.origin || request.url.indexOf(controllerScopeUrl) !== 0) {
+                    return networkFetch.apply(root, arguments);
+                }
+
+                const args = arguments;
+                let response = null;
+                scope.dispatchEvent({
+                    type: 'fetch',
+                    request: request,
+                    clientId: '',
+                    resultingClientId: '',
+                    preloadResponse: Promise.resolve(undefined),
+                    respondWith: function(promise) {
+                        if (response) {
+                            throw domError('respondWith() has already been called.', 'InvalidStateError');
+                        }
+                        response = Promise.resolve(promise);
+                    },
+                    waitUntil: function() {},
+                });
+                if (!response) {
+                    return networkFetch.apply(root, args);
+                }
+                return response.then(function(result) {
+                    if (!result || typeof result.status !== 'number') {
+                        throw new TypeError('Failed to fetch: the service worker\'s respondWith() didn\'t give a Response');
+                    }
+                    return result;
+                });
+            };
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
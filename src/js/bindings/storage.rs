@@ -1,10 +1,52 @@
 use crate::js::bindings::dom_bindings::{LOCAL_STORAGE, SESSION_STORAGE};
 use crate::js::helpers::{create_js_string, define_function, define_js_property_getter, js_value_to_string, ToSafeCx};
+use crate::site_storage;
 use mozjs::jsapi::{CallArgs, JSContext, JS_DefineProperty, JS_NewPlainObject, JSObject, JSPROP_ENUMERATE};
-use mozjs::jsval::{JSVal, ObjectValue, UInt32Value, UndefinedValue};
+use mozjs::jsval::{BooleanValue, JSVal, ObjectValue, UInt32Value, UndefinedValue};
 use mozjs::rooted;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::os::raw::c_uint;
 
+thread_local! {
+    /// Origin whose saved localStorage the document uses; `None` for opaque origins, whose
+    /// localStorage is never saved
+    static STORAGE_ORIGIN: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Load the saved localStorage of `url`'s origin for a new document
+pub(crate) fn load_local_storage(url: &url::Url) {
+    let origin = site_storage::origin_of(url);
+    let items = origin.as_deref().map(site_storage::read).unwrap_or_default();
+    LOCAL_STORAGE.set(items);
+    STORAGE_ORIGIN.set(origin);
+}
+
+/// The origin whose saved data the document uses, if it has a saving origin
+pub(crate) fn storage_origin() -> Option<String> {
+    STORAGE_ORIGIN.with(|origin| origin.borrow().clone())
+}
+
+/// Bytes the document's localStorage takes, for navigator.storage.estimate()
+pub(crate) fn local_storage_usage() -> u64 {
+    LOCAL_STORAGE.with(|storage| site_storage::size_of(&storage.borrow()))
+}
+
+/// Change localStorage and save it. Returns false, leaving it unchanged, when the change would
+/// take the origin over its quota.
+fn update_local_storage(update: impl FnOnce(&mut HashMap<String, String>)) -> bool {
+    let mut items = LOCAL_STORAGE.with(|storage| storage.borrow().clone());
+    update(&mut items);
+    let saved = match storage_origin() {
+        Some(origin) => site_storage::write(&origin, &items).is_ok(),
+        None => site_storage::size_of(&items) <= site_storage::ORIGIN_QUOTA,
+    };
+    if saved {
+        LOCAL_STORAGE.set(items);
+    }
+    saved
+}
+
 pub(crate) unsafe fn setup_storage_bindings(
     cx: &mut mozjs::context::JSContext,
     global: *mut JSObject,
@@ -96,11 +138,12 @@ pub(crate) unsafe extern "C" fn local_storage_set_item(raw_cx: *mut JSContext, a
         String::new()
     };
 
-    LOCAL_STORAGE.with(|storage| {
-        storage.borrow_mut().insert(key, value);
-    });
-
-    args.rval().set(UndefinedValue());
+    // A refused change returns false, which the StorageManager script turns into a QuotaExceededError
+    if update_local_storage(|items| { items.insert(key, value); }) {
+        args.rval().set(UndefinedValue());
+    } else {
+        args.rval().set(BooleanValue(false));
+    }
     true
 }
 
@@ -114,9 +157,7 @@ pub(crate) unsafe extern "C" fn local_storage_remove_item(raw_cx: *mut JSContext
         String::new()
     };
 
-    LOCAL_STORAGE.with(|storage| {
-        storage.borrow_mut().remove(&key);
-    });
+    update_local_storage(|items| { items.remove(&key); });
 
     args.rval().set(UndefinedValue());
     true
@@ -125,9 +166,7 @@ pub(crate) unsafe extern "C" fn local_storage_remove_item(raw_cx: *mut JSContext
 pub(crate) unsafe extern "C" fn local_storage_clear(_raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, argc);
 
-    LOCAL_STORAGE.with(|storage| {
-        storage.borrow_mut().clear();
-    });
+    update_local_storage(HashMap::clear);
 
     args.rval().set(UndefinedValue());
     true
@@ -0,0 +1,97 @@
+// StorageManager API (navigator.storage.estimate / persisted / persist)
+use crate::js::bindings::storage::{local_storage_usage, storage_origin};
+use crate::js::helpers::{create_js_string, define_function, ToSafeCx};
+use crate::js::{JsResult, JsRuntime};
+use crate::site_storage::{self, Area, ORIGIN_QUOTA};
+use mozjs::jsapi::{CallArgs, JSContext};
+use mozjs::jsval::JSVal;
+use serde_json::{Map, Value};
+use std::os::raw::c_uint;
+
+/// Install `navigator.storage`. The estimate is what the origin's localStorage, caches and
+/// service worker registrations take on disk. localStorage writes that would take the origin over
+/// its quota throw a QuotaExceededError.
+pub fn setup_storage_manager(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesStorageUsage", Some(stokes_storage_usage), 0)
+    })?;
+
+    let script = format!(r#"
+        (function() {{
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            const nav = root.navigator;
+            if (!nav || typeof root.__stokesStorageUsage !== 'function') {{
+                return;
+            }}
+
+            const QUOTA = {quota};
+
+            function StorageManager() {{
+                throw new TypeError('Illegal constructor');
+            }}
+            StorageManager.prototype.estimate = function() {{
+                const usageDetails = JSON.parse(root.__stokesStorageUsage());
+                let usage = 0;
+                for (const name of Object.keys(usageDetails)) {{
+                    usage += usageDetails[name];
+                }}
+                return Promise.resolve({{ quota: QUOTA, usage: usage, usageDetails: usageDetails }});
+            }};
+            // Storage is never persisted beyond the quota manager's eviction
+            StorageManager.prototype.persisted = function() {{
+                return Promise.resolve(false);
+            }};
+            StorageManager.prototype.persist = function() {{
+                return Promise.resolve(false);
+            }};
+            root.StorageManager = StorageManager;
+
+            const storage = Object.create(StorageManager.prototype);
+            Object.defineProperty(nav, 'storage', {{
+                value: storage,
+                configurable: true,
+                enumerable: true,
+            }});
+
+            const localStorage = root.localStorage;
+            if (localStorage && typeof localStorage.setItem === 'function') {{
+                const setItem = localStorage.setItem;
+                localStorage.setItem = function(key, value) {{
+                    if (setItem.call(localStorage, String(key), String(value)) === false) {{
+                        const message = "Failed to execute 'setItem' on 'Storage': Setting the value of '" + key + "' exceeded the quota.";
+                        if (typeof root.DOMException === 'function') {{
+                            throw new root.DOMException(message, 'QuotaExceededError');
+                        }}
+                        const error = new Error(message);
+                        error.name = 'QuotaExceededError';
+                        throw error;
+                    }}
+                }};
+            }}
+        }})();
+    "#, quota = ORIGIN_QUOTA);
+
+    runtime.execute(&script, false)
+}
+
+/// Bytes used in each area as a JSON object keyed by the names of usageDetails
+unsafe extern "C" fn stokes_storage_usage(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let mut details = Map::new();
+    match storage_origin() {
+        Some(origin) => {
+            for area in Area::ALL {
+                let bytes = site_storage::area_usage(area, &origin);
+                if bytes > 0 {
+                    details.insert(area.usage_name().to_string(), Value::from(bytes));
+                }
+            }
+        }
+        None => {
+            details.insert(Area::LocalStorage.usage_name().to_string(), Value::from(local_storage_usage()));
+        }
+    }
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    args.rval().set(create_js_string(safe_cx, &Value::Object(details).to_string()));
+    true
+}
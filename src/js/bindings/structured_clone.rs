@@ -0,0 +1,169 @@
+// structuredClone(): the HTML structured clone algorithm as a JS polyfill
+use crate::js::{JsResult, JsRuntime};
+
+/// Install the global `structuredClone(value, { transfer })`
+pub fn setup_structured_clone(runtime: &mut JsRuntime) -> JsResult<()> {
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+
+            const ERROR_TYPES = ['Error', 'EvalError', 'RangeError', 'ReferenceError', 'SyntaxError', 'TypeError', 'URIError'];
+            const TYPED_ARRAYS = [
+                'Int8Array', 'Uint8Array', 'Uint8ClampedArray', 'Int16Array', 'Uint16Array',
+                'Int32Array', 'Uint32Array', 'Float32Array', 'Float64Array', 'BigInt64Array', 'BigUint64Array',
+            ];
+            // Objects with internal state that can't be serialized
+            const UNCLONEABLE = ['WeakMap', 'WeakSet', 'WeakRef', 'Promise', 'Symbol', 'Proxy', 'FinalizationRegistry'];
+
+            function dataCloneError(what) {
+                const message = "Failed to execute 'structuredClone' on 'Window': " + what + ' could not be cloned.';
+                if (typeof root.DOMException === 'function') {
+                    return new root.DOMException(message, 'DataCloneError');
+                }
+                const error = new Error(message);
+                error.name = 'DataCloneError';
+                return error;
+            }
+
+            function describe(value) {
+                if (typeof value === 'function') {
+                    return value.name ? 'function ' + value.name : 'function';
+                }
+                if (typeof value === 'symbol') {
+                    return String(value);
+                }
+                return '#<' + Object.prototype.toString.call(value).slice(8, -1) + '>';
+            }
+
+            function structuredClone(value, options) {
+                if (arguments.length < 1) {
+                    throw new TypeError("Failed to execute 'structuredClone' on 'Window': 1 argument required, but only 0 present.");
+                }
+
+                const transfer = options && options.transfer !== undefined ? Array.from(options.transfer) : [];
+                for (let i = 0; i < transfer.length; i++) {
+                    if (!(transfer[i] instanceof ArrayBuffer)) {
+                        throw dataCloneError('Value at index ' + i + ' of the transfer list');
+                    }
+                    if (transfer.indexOf(transfer[i]) !== i) {
+                        throw dataCloneError('ArrayBuffer at index ' + i + ' is a duplicate and');
+                    }
+                    if (transfer[i].detached) {
+                        throw dataCloneError('ArrayBuffer at index ' + i + ' is already detached and');
+                    }
+                }
+
+                // Original -> copy, so shared and cyclic references stay shared and cyclic
+                const memory = new Map();
+
+                function clone(input) {
+                    if (input === null || (typeof input !== 'object' && typeof input !== 'function')) {
+                        if (typeof input === 'symbol') {
+                            throw dataCloneError(describe(input));
+                        }
+                        return input;
+                    }
+                    if (typeof input === 'function') {
+                        throw dataCloneError(describe(input));
+                    }
+                    if (memory.has(input)) {
+                        return memory.get(input);
+                    }
+
+                    const tag = Object.prototype.toString.call(input).slice(8, -1);
+                    let output;
+
+                    if (UNCLONEABLE.indexOf(tag) >= 0 || input === root
+                        || (typeof root.Node === 'function' && input instanceof root.Node)) {
+                        throw dataCloneError(describe(input));
+                    } else if (tag === 'Boolean' || tag === 'Number' || tag === 'String' || tag === 'BigInt') {
+                        output = Object(input.valueOf());
+                    } else if (tag === 'Date') {
+                        output = new Date(input.getTime());
+                    } else if (tag === 'RegExp') {
+                        output = new RegExp(input.source, input.flags);
+                    } else if (tag === 'ArrayBuffer') {
+                        if (input.detached) {
+                            throw dataCloneError('A detached ArrayBuffer');
+                        }
+                        output = input.slice(0);
+                    } else if (tag === 'DataView') {
+                        const buffer = clone(input.buffer);
+                        output = new DataView(buffer, input.byteOffset, input.byteLength);
+                    } else if (TYPED_ARRAYS.indexOf(tag) >= 0) {
+                        const buffer = clone(input.buffer);
+                        output = new root[tag](buffer, input.byteOffset, input.length);
+                    } else if (tag === 'Map') {
+                        output = new Map();
+                        memory.set(input, output);
+                        for (const [key, entry] of Array.from(input.entries())) {
+                            output.set(clone(key), clone(entry));
+                        }
+                        return output;
+                    } else if (tag === 'Set') {
+                        output = new Set();
+                        memory.set(input, output);
+                        for (const entry of Array.from(input.values())) {
+                            output.add(clone(entry));
+                        }
+                        return output;
+                    } else if (tag === 'Error') {
+                        const name = ERROR_TYPES.indexOf(input.name) >= 0 ? input.name : 'Error';
+                        output = new root[name]();
+                        if (Object.prototype.hasOwnProperty.call(input, 'message')) {
+                            output.message = String(input.message);
+                        }
+                        if (typeof input.stack === 'string') {
+                            output.stack = input.stack;
+                        }
+                        memory.set(input, output);
+                        if ('cause' in input) {
+                            output.cause = clone(input.cause);
+                        }
+                        return output;
+                    } else if (Array.isArray(input)) {
+                        output = new Array(input.length);
+                        memory.set(input, output);
+                        for (const key of Object.keys(input)) {
+                            output[key] = clone(input[key]);
+                        }
+                        return output;
+                    } else if (typeof input.__stokesStructuredClone === 'function') {
+                        // Platform objects implemented in JS (e.g. Blob) copy themselves
+                        output = input.__stokesStructuredClone();
+                    } else {
+                        // Ordinary objects lose their prototype and keep own enumerable string keys
+                        output = {};
+                        memory.set(input, output);
+                        for (const key of Object.keys(input)) {
+                            output[key] = clone(input[key]);
+                        }
+                        return output;
+                    }
+
+                    memory.set(input, output);
+                    return output;
+                }
+
+                const result = clone(value);
+
+                // Transferred buffers are unusable in the source afterwards
+                for (const buffer of transfer) {
+                    if (typeof buffer.transfer === 'function') {
+                        buffer.transfer();
+                    }
+                }
+                return result;
+            }
+
+            Object.defineProperty(root, 'structuredClone', {
+                value: structuredClone,
+                writable: true,
+                enumerable: false,
+                configurable: true,
+            });
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
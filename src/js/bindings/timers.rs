@@ -1,6 +1,6 @@
 use crate::js::JsRuntime;
 use crate::js::helpers::ToSafeCx;
-use crate::js::jsapi::promise::PersistentRooted;
+use crate::js::jsapi::promise::{enqueue_microtask, PersistentRooted};
 // Timer implementation for setTimeout and setInterval using mozjs
 use mozjs::context::RawJSContext;
 use mozjs::conversions::jsstr_to_string;
@@ -10,13 +10,16 @@ use mozjs::realm::AutoRealm;
 use mozjs::rooted;
 use mozjs::rust::ValueArray;
 use mozjs::rust::wrappers2::{CurrentGlobalOrNull, JS_CallFunctionValue, JS_ClearPendingException};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::ptr::NonNull;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 use tracing::warn;
 
+/// Shortest delay a timer gets while its tab is in the background
+const BACKGROUND_MIN_DELAY: Duration = Duration::from_secs(1);
+
 /// A pending timer that will execute a callback after a delay
 struct Timer {
     id: u32,
@@ -26,6 +29,13 @@ struct Timer {
     repeating: bool,
 }
 
+impl Timer {
+    /// The timer's delay, raised to `min_delay` while throttled
+    fn delay(&self, min_delay: Duration) -> Duration {
+        self.duration.max(min_delay)
+    }
+}
+
 enum TimerCallback {
     Script(String),
     Function(PersistentRooted),
@@ -41,6 +51,7 @@ enum ReadyTimerCallback {
 pub struct TimerManager {
     timers: Rc<RefCell<HashMap<u32, Timer>>>,
     next_id: Rc<RefCell<u32>>,
+    min_delay: Rc<Cell<Duration>>,
 }
 
 impl TimerManager {
@@ -48,9 +59,15 @@ impl TimerManager {
         Self {
             timers: Rc::new(RefCell::new(HashMap::new())),
             next_id: Rc::new(RefCell::new(1)),
+            min_delay: Rc::new(Cell::new(Duration::ZERO)),
         }
     }
 
+    /// Clamp every timer to at least one second while the tab is in the background
+    pub fn set_throttled(&self, throttled: bool) {
+        self.min_delay.set(if throttled { BACKGROUND_MIN_DELAY } else { Duration::ZERO });
+    }
+
     /// Register a new timeout
     fn set_timeout(&self, callback: TimerCallback, delay: u32) -> u32 {
         let id = {
@@ -107,6 +124,7 @@ impl TimerManager {
     /// Returns true if any timers were executed
     pub fn process_timers(&self, runtime: &mut JsRuntime) -> bool {
         let now = Instant::now();
+        let min_delay = self.min_delay.get();
         let mut ready_timers: Vec<(u32, ReadyTimerCallback, bool)> = Vec::new();
         let mut timers_to_reschedule = Vec::new();
 
@@ -114,7 +132,7 @@ impl TimerManager {
         {
             let timers = self.timers.borrow();
             for (id, timer) in timers.iter() {
-                if now.duration_since(timer.start_time) >= timer.duration {
+                if now.duration_since(timer.start_time) >= timer.delay(min_delay) {
                     let callback = match &timer.callback {
                         TimerCallback::Script(code) => ReadyTimerCallback::Script(code.clone()),
                         TimerCallback::Function(func) => ReadyTimerCallback::Function(func.get()),
@@ -131,7 +149,7 @@ impl TimerManager {
             match callback {
                 ReadyTimerCallback::Script(callback_code) => {
                     if let Err(e) = runtime.execute(&callback_code, false) {
-                        eprintln!("Timer callback error: {}", e);
+                        tracing::warn!("Timer callback error: {}", e);
                     }
                 }
                 ReadyTimerCallback::Function(callback_obj) => unsafe {
@@ -166,16 +184,13 @@ impl TimerManager {
     /// Get the time until the next timer should fire
     pub fn time_until_next_timer(&self) -> Option<Duration> {
         let now = Instant::now();
+        let min_delay = self.min_delay.get();
         let timers = self.timers.borrow();
 
         timers.values()
             .map(|timer| {
                 let elapsed = now.duration_since(timer.start_time);
-                if elapsed >= timer.duration {
-                    Duration::from_millis(0)
-                } else {
-                    timer.duration - elapsed
-                }
+                timer.delay(min_delay).saturating_sub(elapsed)
             })
             .min()
     }
@@ -347,7 +362,36 @@ pub fn setup_timers(runtime: &mut JsRuntime, timer_manager: Rc<TimerManager>) ->
         true
     });
 
-    Ok(())
+    // queueMicrotask: the native half only queues; the wrapper below validates and reports errors
+    runtime.add_global_function("__stokesQueueMicrotask", move |cx, args| {
+        unsafe {
+            let callback_val = *args.get(0);
+            if args.argc_ > 0 && callback_val.is_object() {
+                let mut safe_cx = cx.to_safe_cx();
+                enqueue_microtask(&mut safe_cx, callback_val.to_object());
+            }
+        }
+        args.rval().set(UndefinedValue());
+        true
+    });
+
+    runtime.execute(
+        r#"
+        globalThis.queueMicrotask = function queueMicrotask(callback) {
+            if (typeof callback !== 'function') {
+                throw new TypeError("Failed to execute 'queueMicrotask' on 'Window': The callback provided as parameter 1 is not a function.");
+            }
+            __stokesQueueMicrotask(function() {
+                try {
+                    callback();
+                } catch (err) {
+                    console.error(err);
+                }
+            });
+        };
+        "#,
+        false,
+    )
 }
 
 /// Convert a JS value to a Rust string
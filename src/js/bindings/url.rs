@@ -101,7 +101,7 @@ unsafe extern "C" fn url_constructor(raw_cx: *mut JSContext, argc: c_uint, vp: *
     let resolved = match resolve_url(&input, base.as_deref()) {
         Ok(url) => url,
         Err(err) => {
-            eprintln!("[JS] URL parse error: {}", err);
+            tracing::warn!("URL parse error: {}", err);
             args.rval().set(UndefinedValue());
             return false;
         }
@@ -363,7 +363,7 @@ fn resolve_url(input: &str, base: Option<&str>) -> Result<Url, String> {
     }
 }
 
-/// Return the current document URL as a string, used as the default base for relative URL resolution.
+/// Return the current document's base URL as a string, the default base for relative URL resolution.
 fn document_base_url() -> Option<String> {
     if let Some(url) = current_document_base_url() {
         return Some(url);
@@ -372,8 +372,7 @@ fn document_base_url() -> Option<String> {
     DOM_REF.with(|dom_ref| {
         dom_ref.borrow().as_ref().map(|dom_ptr| {
             let dom = unsafe { &**dom_ptr };
-            let url: Url = (&dom.url).into();
-            url.as_str().to_string()
+            dom.url.base_url().to_string()
         })
     })
 }
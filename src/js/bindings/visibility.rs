@@ -0,0 +1,50 @@
+// Page Visibility API (document.visibilityState / document.hidden / visibilitychange)
+use crate::js::{JsResult, JsRuntime};
+
+/// Install document.visibilityState and document.hidden. The tab process flips them through
+/// `__stokesSetVisibility` when the browser shows or hides the tab.
+pub fn setup_visibility(runtime: &mut JsRuntime) -> JsResult<()> {
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            const doc = root.document;
+            if (!doc) {
+                return;
+            }
+
+            let visibilityState = 'visible';
+
+            Object.defineProperty(doc, 'visibilityState', {
+                get() { return visibilityState; },
+                configurable: true,
+            });
+            Object.defineProperty(doc, 'hidden', {
+                get() { return visibilityState === 'hidden'; },
+                configurable: true,
+            });
+
+            // Called by the tab process; `notify` is false when a new document starts out hidden
+            root.__stokesSetVisibility = function(visible, notify) {
+                const state = visible ? 'visible' : 'hidden';
+                if (state === visibilityState) {
+                    return;
+                }
+                visibilityState = state;
+                if (!notify) {
+                    return;
+                }
+                if (typeof doc.dispatchEvent === 'function') {
+                    try {
+                        doc.dispatchEvent(new Event('visibilitychange', { bubbles: true }));
+                    } catch (_err) {}
+                }
+                const handler = doc.onvisibilitychange;
+                if (typeof handler === 'function') {
+                    try { handler.call(doc, { type: 'visibilitychange', target: doc }); } catch (_err) {}
+                }
+            };
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
@@ -0,0 +1,101 @@
+// Visual Viewport API (window.visualViewport)
+use crate::js::{JsResult, JsRuntime};
+
+/// Install window.visualViewport. The browser magnifies the page when the user pinches it, and the
+/// tab process passes the scale and offset in through `__stokesSetVisualViewport`. Sizes follow
+/// the layout viewport (innerWidth/innerHeight and the scroll position) divided by the scale.
+pub fn setup_visual_viewport(runtime: &mut JsRuntime) -> JsResult<()> {
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+
+            let scale = 1;
+            let offsetLeft = 0;
+            let offsetTop = 0;
+            const listeners = {};
+
+            function VisualViewport() {
+                throw new TypeError('Illegal constructor');
+            }
+            const viewport = Object.create(VisualViewport.prototype);
+            Object.defineProperties(VisualViewport.prototype, {
+                offsetLeft: { get() { return offsetLeft; }, configurable: true },
+                offsetTop: { get() { return offsetTop; }, configurable: true },
+                pageLeft: { get() { return (root.scrollX || 0) + offsetLeft; }, configurable: true },
+                pageTop: { get() { return (root.scrollY || 0) + offsetTop; }, configurable: true },
+                width: { get() { return (root.innerWidth || 0) / scale; }, configurable: true },
+                height: { get() { return (root.innerHeight || 0) / scale; }, configurable: true },
+                scale: { get() { return scale; }, configurable: true },
+            });
+            viewport.onresize = null;
+            viewport.onscroll = null;
+            viewport.onscrollend = null;
+
+            viewport.addEventListener = function(type, listener) {
+                if (typeof listener !== 'function' && !(listener && typeof listener.handleEvent === 'function')) {
+                    return;
+                }
+                const list = listeners[type] = listeners[type] || [];
+                if (list.indexOf(listener) < 0) {
+                    list.push(listener);
+                }
+            };
+            viewport.removeEventListener = function(type, listener) {
+                listeners[type] = (listeners[type] || []).filter(function(item) { return item !== listener; });
+            };
+            viewport.dispatchEvent = function(event) {
+                const handlers = (listeners[event.type] || []).slice();
+                if (typeof viewport['on' + event.type] === 'function') {
+                    handlers.push(viewport['on' + event.type]);
+                }
+                for (const handler of handlers) {
+                    try {
+                        if (typeof handler === 'function') {
+                            handler.call(viewport, event);
+                        } else {
+                            handler.handleEvent(event);
+                        }
+                    } catch (error) {
+                        console.error(error);
+                    }
+                }
+                return true;
+            };
+
+            function fire(type) {
+                let event;
+                try {
+                    event = new Event(type);
+                } catch (_err) {
+                    event = { type: type };
+                }
+                try {
+                    Object.defineProperty(event, 'target', { value: viewport, configurable: true });
+                } catch (_err) {}
+                viewport.dispatchEvent(event);
+            }
+
+            root.VisualViewport = VisualViewport;
+            Object.defineProperty(root, 'visualViewport', {
+                get() { return viewport; },
+                configurable: true,
+            });
+
+            // Called by the tab process with the new scale and the offset into the layout
+            // viewport in CSS pixels
+            root.__stokesSetVisualViewport = function(newScale, left, top, resized, scrolled) {
+                scale = newScale;
+                offsetLeft = left;
+                offsetTop = top;
+                if (resized) {
+                    fire('resize');
+                }
+                if (scrolled) {
+                    fire('scroll');
+                }
+            };
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
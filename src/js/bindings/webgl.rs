@@ -0,0 +1,1037 @@
+// WebGL 1 (canvas.getContext('webgl') / WebGLRenderingContext)
+use crate::dom::node::{CanvasData, SpecialElementData};
+use crate::js::bindings::dom_bindings::DOM_REF;
+use crate::js::helpers::{ToSafeCx, create_js_string, define_function, get_node_id_from_value, js_value_to_string};
+use crate::js::{JsResult, JsRuntime};
+use crate::renderer::webgl::{self, ActiveInfo, ContextAttributes, ObjectKind, Parameter, SourcePixels, WebGlContext};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use mozjs::jsapi::{CallArgs, JSContext};
+use mozjs::jsval::{DoubleValue, JSVal, NullValue, UndefinedValue};
+use serde_json::{Value, json};
+use std::os::raw::c_uint;
+
+/// Install HTMLCanvasElement.getContext('webgl') and WebGLRenderingContext. Contexts draw with the
+/// tab's GL context through `renderer::webgl`; calls reach it through `__stokesWebgl` with their
+/// arguments as JSON and binary data as base64. Without GPU rendering getContext returns null.
+pub fn setup_webgl(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesWebglCreate", Some(stokes_webgl_create), 4)?;
+        define_function(cx, global.get(), "__stokesWebgl", Some(stokes_webgl), 4)
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            if (!root.document || typeof root.__stokesWebgl !== 'function') {
+                return;
+            }
+
+            const CONSTANTS = {
+                DEPTH_BUFFER_BIT: 0x0100, STENCIL_BUFFER_BIT: 0x0400, COLOR_BUFFER_BIT: 0x4000,
+                POINTS: 0, LINES: 1, LINE_LOOP: 2, LINE_STRIP: 3, TRIANGLES: 4, TRIANGLE_STRIP: 5, TRIANGLE_FAN: 6,
+                ZERO: 0, ONE: 1, SRC_COLOR: 0x0300, ONE_MINUS_SRC_COLOR: 0x0301, SRC_ALPHA: 0x0302,
+                ONE_MINUS_SRC_ALPHA: 0x0303, DST_ALPHA: 0x0304, ONE_MINUS_DST_ALPHA: 0x0305, DST_COLOR: 0x0306,
+                ONE_MINUS_DST_COLOR: 0x0307, SRC_ALPHA_SATURATE: 0x0308,
+                FUNC_ADD: 0x8006, BLEND_EQUATION: 0x8009, BLEND_EQUATION_RGB: 0x8009, BLEND_EQUATION_ALPHA: 0x883D,
+                FUNC_SUBTRACT: 0x800A, FUNC_REVERSE_SUBTRACT: 0x800B,
+                BLEND_DST_RGB: 0x80C8, BLEND_SRC_RGB: 0x80C9, BLEND_DST_ALPHA: 0x80CA, BLEND_SRC_ALPHA: 0x80CB,
+                CONSTANT_COLOR: 0x8001, ONE_MINUS_CONSTANT_COLOR: 0x8002, CONSTANT_ALPHA: 0x8003,
+                ONE_MINUS_CONSTANT_ALPHA: 0x8004, BLEND_COLOR: 0x8005,
+                ARRAY_BUFFER: 0x8892, ELEMENT_ARRAY_BUFFER: 0x8893, ARRAY_BUFFER_BINDING: 0x8894,
+                ELEMENT_ARRAY_BUFFER_BINDING: 0x8895, STREAM_DRAW: 0x88E0, STATIC_DRAW: 0x88E4, DYNAMIC_DRAW: 0x88E8,
+                BUFFER_SIZE: 0x8764, BUFFER_USAGE: 0x8765, CURRENT_VERTEX_ATTRIB: 0x8626,
+                FRONT: 0x0404, BACK: 0x0405, FRONT_AND_BACK: 0x0408,
+                CULL_FACE: 0x0B44, BLEND: 0x0BE2, DITHER: 0x0BD0, STENCIL_TEST: 0x0B90, DEPTH_TEST: 0x0B71,
+                SCISSOR_TEST: 0x0C11, POLYGON_OFFSET_FILL: 0x8037, SAMPLE_ALPHA_TO_COVERAGE: 0x809E,
+                SAMPLE_COVERAGE: 0x80A0,
+                NO_ERROR: 0, INVALID_ENUM: 0x0500, INVALID_VALUE: 0x0501, INVALID_OPERATION: 0x0502,
+                OUT_OF_MEMORY: 0x0505, INVALID_FRAMEBUFFER_OPERATION: 0x0506, CONTEXT_LOST_WEBGL: 0x9242,
+                CW: 0x0900, CCW: 0x0901,
+                LINE_WIDTH: 0x0B21, ALIASED_POINT_SIZE_RANGE: 0x846D, ALIASED_LINE_WIDTH_RANGE: 0x846E,
+                CULL_FACE_MODE: 0x0B45, FRONT_FACE: 0x0B46, DEPTH_RANGE: 0x0B70, DEPTH_WRITEMASK: 0x0B72,
+                DEPTH_CLEAR_VALUE: 0x0B73, DEPTH_FUNC: 0x0B74, STENCIL_CLEAR_VALUE: 0x0B91, STENCIL_FUNC: 0x0B92,
+                STENCIL_FAIL: 0x0B94, STENCIL_PASS_DEPTH_FAIL: 0x0B95, STENCIL_PASS_DEPTH_PASS: 0x0B96,
+                STENCIL_REF: 0x0B97, STENCIL_VALUE_MASK: 0x0B93, STENCIL_WRITEMASK: 0x0B98,
+                STENCIL_BACK_FUNC: 0x8800, STENCIL_BACK_FAIL: 0x8801, STENCIL_BACK_PASS_DEPTH_FAIL: 0x8802,
+                STENCIL_BACK_PASS_DEPTH_PASS: 0x8803, STENCIL_BACK_REF: 0x8CA3, STENCIL_BACK_VALUE_MASK: 0x8CA4,
+                STENCIL_BACK_WRITEMASK: 0x8CA5, VIEWPORT: 0x0BA2, SCISSOR_BOX: 0x0C10, COLOR_CLEAR_VALUE: 0x0C22,
+                COLOR_WRITEMASK: 0x0C23, UNPACK_ALIGNMENT: 0x0CF5, PACK_ALIGNMENT: 0x0D05, MAX_TEXTURE_SIZE: 0x0D33,
+                MAX_VIEWPORT_DIMS: 0x0D3A, SUBPIXEL_BITS: 0x0D50, RED_BITS: 0x0D52, GREEN_BITS: 0x0D53,
+                BLUE_BITS: 0x0D54, ALPHA_BITS: 0x0D55, DEPTH_BITS: 0x0D56, STENCIL_BITS: 0x0D57,
+                POLYGON_OFFSET_UNITS: 0x2A00, POLYGON_OFFSET_FACTOR: 0x8038, TEXTURE_BINDING_2D: 0x8069,
+                SAMPLE_BUFFERS: 0x80A8, SAMPLES: 0x80A9, SAMPLE_COVERAGE_VALUE: 0x80AA,
+                SAMPLE_COVERAGE_INVERT: 0x80AB, COMPRESSED_TEXTURE_FORMATS: 0x86A3,
+                DONT_CARE: 0x1100, FASTEST: 0x1101, NICEST: 0x1102, GENERATE_MIPMAP_HINT: 0x8192,
+                BYTE: 0x1400, UNSIGNED_BYTE: 0x1401, SHORT: 0x1402, UNSIGNED_SHORT: 0x1403, INT: 0x1404,
+                UNSIGNED_INT: 0x1405, FLOAT: 0x1406,
+                DEPTH_COMPONENT: 0x1902, ALPHA: 0x1906, RGB: 0x1907, RGBA: 0x1908, LUMINANCE: 0x1909,
+                LUMINANCE_ALPHA: 0x190A,
+                UNSIGNED_SHORT_4_4_4_4: 0x8033, UNSIGNED_SHORT_5_5_5_1: 0x8034, UNSIGNED_SHORT_5_6_5: 0x8363,
+                FRAGMENT_SHADER: 0x8B30, VERTEX_SHADER: 0x8B31, MAX_VERTEX_ATTRIBS: 0x8869,
+                MAX_VERTEX_UNIFORM_VECTORS: 0x8DFB, MAX_VARYING_VECTORS: 0x8DFC,
+                MAX_COMBINED_TEXTURE_IMAGE_UNITS: 0x8B4D, MAX_VERTEX_TEXTURE_IMAGE_UNITS: 0x8B4C,
+                MAX_TEXTURE_IMAGE_UNITS: 0x8872, MAX_FRAGMENT_UNIFORM_VECTORS: 0x8DFD, SHADER_TYPE: 0x8B4F,
+                DELETE_STATUS: 0x8B80, LINK_STATUS: 0x8B82, VALIDATE_STATUS: 0x8B83, ATTACHED_SHADERS: 0x8B85,
+                ACTIVE_UNIFORMS: 0x8B86, ACTIVE_ATTRIBUTES: 0x8B89, SHADING_LANGUAGE_VERSION: 0x8B8C,
+                CURRENT_PROGRAM: 0x8B8D,
+                NEVER: 0x0200, LESS: 0x0201, EQUAL: 0x0202, LEQUAL: 0x0203, GREATER: 0x0204, NOTEQUAL: 0x0205,
+                GEQUAL: 0x0206, ALWAYS: 0x0207,
+                KEEP: 0x1E00, REPLACE: 0x1E01, INCR: 0x1E02, DECR: 0x1E03, INVERT: 0x150A, INCR_WRAP: 0x8507,
+                DECR_WRAP: 0x8508,
+                VENDOR: 0x1F00, RENDERER: 0x1F01, VERSION: 0x1F02,
+                NEAREST: 0x2600, LINEAR: 0x2601, NEAREST_MIPMAP_NEAREST: 0x2700, LINEAR_MIPMAP_NEAREST: 0x2701,
+                NEAREST_MIPMAP_LINEAR: 0x2702, LINEAR_MIPMAP_LINEAR: 0x2703, TEXTURE_MAG_FILTER: 0x2800,
+                TEXTURE_MIN_FILTER: 0x2801, TEXTURE_WRAP_S: 0x2802, TEXTURE_WRAP_T: 0x2803,
+                TEXTURE_2D: 0x0DE1, TEXTURE: 0x1702, TEXTURE_CUBE_MAP: 0x8513, TEXTURE_BINDING_CUBE_MAP: 0x8514,
+                TEXTURE_CUBE_MAP_POSITIVE_X: 0x8515, TEXTURE_CUBE_MAP_NEGATIVE_X: 0x8516,
+                TEXTURE_CUBE_MAP_POSITIVE_Y: 0x8517, TEXTURE_CUBE_MAP_NEGATIVE_Y: 0x8518,
+                TEXTURE_CUBE_MAP_POSITIVE_Z: 0x8519, TEXTURE_CUBE_MAP_NEGATIVE_Z: 0x851A,
+                MAX_CUBE_MAP_TEXTURE_SIZE: 0x851C, ACTIVE_TEXTURE: 0x84E0,
+                REPEAT: 0x2901, CLAMP_TO_EDGE: 0x812F, MIRRORED_REPEAT: 0x8370,
+                FLOAT_VEC2: 0x8B50, FLOAT_VEC3: 0x8B51, FLOAT_VEC4: 0x8B52, INT_VEC2: 0x8B53, INT_VEC3: 0x8B54,
+                INT_VEC4: 0x8B55, BOOL: 0x8B56, BOOL_VEC2: 0x8B57, BOOL_VEC3: 0x8B58, BOOL_VEC4: 0x8B59,
+                FLOAT_MAT2: 0x8B5A, FLOAT_MAT3: 0x8B5B, FLOAT_MAT4: 0x8B5C, SAMPLER_2D: 0x8B5E, SAMPLER_CUBE: 0x8B60,
+                VERTEX_ATTRIB_ARRAY_ENABLED: 0x8622, VERTEX_ATTRIB_ARRAY_SIZE: 0x8623,
+                VERTEX_ATTRIB_ARRAY_STRIDE: 0x8624, VERTEX_ATTRIB_ARRAY_TYPE: 0x8625,
+                VERTEX_ATTRIB_ARRAY_NORMALIZED: 0x886A, VERTEX_ATTRIB_ARRAY_POINTER: 0x8645,
+                VERTEX_ATTRIB_ARRAY_BUFFER_BINDING: 0x889F,
+                IMPLEMENTATION_COLOR_READ_TYPE: 0x8B9A, IMPLEMENTATION_COLOR_READ_FORMAT: 0x8B9B,
+                COMPILE_STATUS: 0x8B81,
+                LOW_FLOAT: 0x8DF0, MEDIUM_FLOAT: 0x8DF1, HIGH_FLOAT: 0x8DF2, LOW_INT: 0x8DF3, MEDIUM_INT: 0x8DF4,
+                HIGH_INT: 0x8DF5,
+                FRAMEBUFFER: 0x8D40, RENDERBUFFER: 0x8D41, RGBA4: 0x8056, RGB5_A1: 0x8057, RGB565: 0x8D62,
+                DEPTH_COMPONENT16: 0x81A5, STENCIL_INDEX8: 0x8D48, DEPTH_STENCIL: 0x84F9,
+                RENDERBUFFER_WIDTH: 0x8D42, RENDERBUFFER_HEIGHT: 0x8D43, RENDERBUFFER_INTERNAL_FORMAT: 0x8D44,
+                RENDERBUFFER_RED_SIZE: 0x8D50, RENDERBUFFER_GREEN_SIZE: 0x8D51, RENDERBUFFER_BLUE_SIZE: 0x8D52,
+                RENDERBUFFER_ALPHA_SIZE: 0x8D53, RENDERBUFFER_DEPTH_SIZE: 0x8D54, RENDERBUFFER_STENCIL_SIZE: 0x8D55,
+                FRAMEBUFFER_ATTACHMENT_OBJECT_TYPE: 0x8CD0, FRAMEBUFFER_ATTACHMENT_OBJECT_NAME: 0x8CD1,
+                FRAMEBUFFER_ATTACHMENT_TEXTURE_LEVEL: 0x8CD2, FRAMEBUFFER_ATTACHMENT_TEXTURE_CUBE_MAP_FACE: 0x8CD3,
+                COLOR_ATTACHMENT0: 0x8CE0, DEPTH_ATTACHMENT: 0x8D00, STENCIL_ATTACHMENT: 0x8D20,
+                DEPTH_STENCIL_ATTACHMENT: 0x821A, NONE: 0,
+                FRAMEBUFFER_COMPLETE: 0x8CD5, FRAMEBUFFER_INCOMPLETE_ATTACHMENT: 0x8CD6,
+                FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT: 0x8CD7, FRAMEBUFFER_INCOMPLETE_DIMENSIONS: 0x8CD9,
+                FRAMEBUFFER_UNSUPPORTED: 0x8CDD, FRAMEBUFFER_BINDING: 0x8CA6, RENDERBUFFER_BINDING: 0x8CA7,
+                MAX_RENDERBUFFER_SIZE: 0x84E8,
+                UNPACK_FLIP_Y_WEBGL: 0x9240, UNPACK_PREMULTIPLY_ALPHA_WEBGL: 0x9241,
+                UNPACK_COLORSPACE_CONVERSION_WEBGL: 0x9243, BROWSER_DEFAULT_WEBGL: 0x9244,
+            };
+            for (let i = 0; i < 32; i++) {
+                CONSTANTS['TEXTURE' + i] = 0x84C0 + i;
+            }
+
+            function illegalConstructor(name) {
+                const ctor = function() {
+                    throw new TypeError('Illegal constructor');
+                };
+                Object.defineProperty(ctor, 'name', { value: name });
+                root[name] = ctor;
+                return ctor;
+            }
+
+            const WebGLRenderingContext = illegalConstructor('WebGLRenderingContext');
+            const WebGLBuffer = illegalConstructor('WebGLBuffer');
+            const WebGLTexture = illegalConstructor('WebGLTexture');
+            const WebGLShader = illegalConstructor('WebGLShader');
+            const WebGLProgram = illegalConstructor('WebGLProgram');
+            const WebGLFramebuffer = illegalConstructor('WebGLFramebuffer');
+            const WebGLRenderbuffer = illegalConstructor('WebGLRenderbuffer');
+            const WebGLUniformLocation = illegalConstructor('WebGLUniformLocation');
+            const WebGLActiveInfo = illegalConstructor('WebGLActiveInfo');
+            const WebGLShaderPrecisionFormat = illegalConstructor('WebGLShaderPrecisionFormat');
+            const proto = WebGLRenderingContext.prototype;
+            Object.keys(CONSTANTS).forEach(function(name) {
+                proto[name] = CONSTANTS[name];
+                WebGLRenderingContext[name] = CONSTANTS[name];
+            });
+
+            function bytesToBase64(bytes) {
+                let binary = '';
+                for (let i = 0; i < bytes.length; i += 0x8000) {
+                    binary += String.fromCharCode.apply(null, bytes.subarray(i, i + 0x8000));
+                }
+                return btoa(binary);
+            }
+
+            function viewBytes(data) {
+                if (data instanceof ArrayBuffer) {
+                    return new Uint8Array(data);
+                }
+                if (data && ArrayBuffer.isView(data)) {
+                    return new Uint8Array(data.buffer, data.byteOffset, data.byteLength);
+                }
+                return null;
+            }
+
+            function dataToBase64(data) {
+                const bytes = viewBytes(data);
+                return bytes ? bytesToBase64(bytes) : null;
+            }
+
+            function numbers(values) {
+                return Array.prototype.slice.call(values || []).map(Number);
+            }
+
+            // Page objects travel as their ids; the context maps them back to the same wrappers
+            function encode(arg) {
+                if (arg && typeof arg === 'object' && '__webglId' in arg) {
+                    return arg.__webglId;
+                }
+                return arg === undefined || arg === null ? 0 : arg;
+            }
+
+            function decode(context, value) {
+                if (!value || typeof value !== 'object' || Array.isArray(value)) {
+                    return value;
+                }
+                if ('object' in value) {
+                    return context.__objects.get(value.object) || null;
+                }
+                if ('objects' in value) {
+                    return value.objects.map(function(id) { return context.__objects.get(id) || null; });
+                }
+                if ('ints' in value) {
+                    return new Int32Array(value.ints);
+                }
+                if ('floats' in value) {
+                    return new Float32Array(value.floats);
+                }
+                if ('bools' in value) {
+                    return value.bools;
+                }
+                if ('info' in value) {
+                    const info = Object.create(WebGLActiveInfo.prototype);
+                    info.size = value.info.size;
+                    info.type = value.info.type;
+                    info.name = value.info.name;
+                    return info;
+                }
+                return value;
+            }
+
+            function call(context, op, args, source) {
+                if (context.__lost) {
+                    return null;
+                }
+                const result = root.__stokesWebgl(context.__id, op, JSON.stringify(args || []), source);
+                if (result === undefined) {
+                    return undefined;
+                }
+                if (result === null) {
+                    context.__lost = true;
+                    return null;
+                }
+                return decode(context, JSON.parse(result));
+            }
+
+            // Methods whose arguments are numbers, booleans, strings and page objects
+            [
+                'activeTexture', 'attachShader', 'bindAttribLocation', 'bindBuffer', 'bindFramebuffer',
+                'bindRenderbuffer', 'bindTexture', 'blendColor', 'blendEquation', 'blendEquationSeparate',
+                'blendFunc', 'blendFuncSeparate', 'checkFramebufferStatus', 'clear', 'clearColor', 'clearDepth',
+                'clearStencil', 'colorMask', 'compileShader', 'copyTexImage2D', 'copyTexSubImage2D', 'cullFace',
+                'deleteBuffer', 'deleteFramebuffer', 'deleteProgram', 'deleteRenderbuffer', 'deleteShader',
+                'deleteTexture', 'depthFunc', 'depthMask', 'depthRange', 'detachShader', 'disable',
+                'disableVertexAttribArray', 'drawArrays', 'drawElements', 'enable', 'enableVertexAttribArray',
+                'finish', 'flush', 'framebufferRenderbuffer', 'framebufferTexture2D', 'frontFace',
+                'generateMipmap', 'getActiveAttrib', 'getActiveUniform', 'getAttachedShaders',
+                'getAttribLocation', 'getBufferParameter', 'getError', 'getFramebufferAttachmentParameter',
+                'getParameter', 'getProgramInfoLog', 'getProgramParameter', 'getRenderbufferParameter',
+                'getShaderInfoLog', 'getShaderParameter', 'getShaderSource', 'getTexParameter', 'getVertexAttrib',
+                'getVertexAttribOffset', 'hint', 'isBuffer', 'isEnabled', 'isFramebuffer', 'isProgram',
+                'isRenderbuffer', 'isShader', 'isTexture', 'lineWidth', 'linkProgram', 'pixelStorei',
+                'polygonOffset', 'renderbufferStorage', 'sampleCoverage', 'scissor', 'shaderSource',
+                'stencilFunc', 'stencilFuncSeparate', 'stencilMask', 'stencilMaskSeparate', 'stencilOp',
+                'stencilOpSeparate', 'texParameterf', 'texParameteri', 'useProgram', 'validateProgram',
+                'vertexAttribPointer', 'viewport',
+            ].forEach(function(name) {
+                proto[name] = function() {
+                    return call(this, name, Array.prototype.map.call(arguments, encode));
+                };
+            });
+
+            [
+                ['createBuffer', WebGLBuffer], ['createFramebuffer', WebGLFramebuffer],
+                ['createProgram', WebGLProgram], ['createRenderbuffer', WebGLRenderbuffer],
+                ['createShader', WebGLShader], ['createTexture', WebGLTexture],
+            ].forEach(function(entry) {
+                const name = entry[0];
+                const ctor = entry[1];
+                proto[name] = function() {
+                    const id = call(this, name, Array.prototype.map.call(arguments, encode));
+                    if (!id) {
+                        return null;
+                    }
+                    const object = Object.create(ctor.prototype);
+                    Object.defineProperty(object, '__webglId', { value: id });
+                    this.__objects.set(id, object);
+                    return object;
+                };
+            });
+
+            proto.isContextLost = function() {
+                return !!this.__lost || call(this, 'isContextLost', []) === null;
+            };
+            proto.getContextAttributes = function() {
+                if (this.isContextLost()) {
+                    return null;
+                }
+                return Object.assign({
+                    antialias: false,
+                    powerPreference: 'default',
+                    failIfMajorPerformanceCaveat: false,
+                    desynchronized: false,
+                }, this.__attributes);
+            };
+            proto.getExtension = function(_name) {
+                return null;
+            };
+            proto.getSupportedExtensions = function() {
+                return this.isContextLost() ? null : [];
+            };
+            proto.getShaderPrecisionFormat = function(_shaderType, precisionType) {
+                const format = Object.create(WebGLShaderPrecisionFormat.prototype);
+                const isInt = precisionType >= CONSTANTS.LOW_INT;
+                format.rangeMin = isInt ? 31 : 127;
+                format.rangeMax = isInt ? 30 : 127;
+                format.precision = isInt ? 0 : 23;
+                return format;
+            };
+            Object.defineProperty(proto, 'drawingBufferWidth', {
+                get() {
+                    const size = call(this, 'drawingBufferSize', []);
+                    return size ? size[0] : 0;
+                },
+                configurable: true,
+            });
+            Object.defineProperty(proto, 'drawingBufferHeight', {
+                get() {
+                    const size = call(this, 'drawingBufferSize', []);
+                    return size ? size[1] : 0;
+                },
+                configurable: true,
+            });
+
+            proto.bufferData = function(target, data, usage) {
+                if (typeof data === 'number') {
+                    call(this, 'bufferData', [target, null, data, usage]);
+                } else {
+                    call(this, 'bufferData', [target, dataToBase64(data), 0, usage]);
+                }
+            };
+            proto.bufferSubData = function(target, offset, data) {
+                call(this, 'bufferSubData', [target, offset, dataToBase64(data)]);
+            };
+
+            // The overloads taking an image element or ImageData rather than a size and an array
+            function uploadSource(context, target, level, x, y, format, type, source) {
+                if (source && source.__nodeId !== undefined) {
+                    call(context, 'texImageSource', [target, level, x, y, format, type], source);
+                } else if (source && source.data && source.width !== undefined) {
+                    call(context, 'texImageData', [
+                        target, level, x, y, format, type, source.width, source.height, dataToBase64(source.data),
+                    ]);
+                }
+            }
+            proto.texImage2D = function(target, level, internalformat) {
+                if (arguments.length >= 9) {
+                    const a = arguments;
+                    call(this, 'texImage', [target, level, null, null, a[3], a[4], a[6], a[7], dataToBase64(a[8])]);
+                } else {
+                    uploadSource(this, target, level, null, null, arguments[3], arguments[4], arguments[5]);
+                }
+            };
+            proto.texSubImage2D = function(target, level, xoffset, yoffset) {
+                if (arguments.length >= 9) {
+                    const a = arguments;
+                    call(this, 'texImage', [target, level, xoffset, yoffset, a[4], a[5], a[6], a[7], dataToBase64(a[8])]);
+                } else {
+                    uploadSource(this, target, level, xoffset, yoffset, arguments[4], arguments[5], arguments[6]);
+                }
+            };
+            proto.compressedTexImage2D = function() {
+                call(this, 'compressedTexImage2D', []);
+            };
+            proto.compressedTexSubImage2D = proto.compressedTexImage2D;
+
+            proto.readPixels = function(x, y, width, height, format, type, pixels) {
+                const data = call(this, 'readPixels', [x, y, width, height, format, type]);
+                const bytes = viewBytes(pixels);
+                if (typeof data !== 'string' || !bytes) {
+                    return;
+                }
+                const binary = atob(data);
+                const length = Math.min(binary.length, bytes.length);
+                for (let i = 0; i < length; i++) {
+                    bytes[i] = binary.charCodeAt(i);
+                }
+            };
+
+            proto.getUniformLocation = function(program, name) {
+                const location = call(this, 'getUniformLocation', [encode(program), String(name)]);
+                if (typeof location !== 'number') {
+                    return null;
+                }
+                const wrapper = Object.create(WebGLUniformLocation.prototype);
+                Object.defineProperty(wrapper, '__program', { value: encode(program) });
+                Object.defineProperty(wrapper, '__location', { value: location });
+                return wrapper;
+            };
+            proto.getUniform = function(_program, _location) {
+                return null;
+            };
+
+            function setUniform(context, op, location, size, values) {
+                if (!location) {
+                    return;
+                }
+                call(context, op, [location.__program, location.__location, size, values]);
+            }
+            [1, 2, 3, 4].forEach(function(size) {
+                proto['uniform' + size + 'f'] = function(location) {
+                    setUniform(this, 'uniformFloats', location, size, numbers(Array.prototype.slice.call(arguments, 1, size + 1)));
+                };
+                proto['uniform' + size + 'i'] = function(location) {
+                    setUniform(this, 'uniformInts', location, size, numbers(Array.prototype.slice.call(arguments, 1, size + 1)));
+                };
+                proto['uniform' + size + 'fv'] = function(location, values) {
+                    setUniform(this, 'uniformFloats', location, size, numbers(values));
+                };
+                proto['uniform' + size + 'iv'] = function(location, values) {
+                    setUniform(this, 'uniformInts', location, size, numbers(values));
+                };
+                if (size > 1) {
+                    proto['uniformMatrix' + size + 'fv'] = function(location, transpose, values) {
+                        if (location) {
+                            call(this, 'uniformMatrix', [location.__program, location.__location, size, !!transpose, numbers(values)]);
+                        }
+                    };
+                }
+                proto['vertexAttrib' + size + 'f'] = function(index) {
+                    const value = [0, 0, 0, 1];
+                    for (let i = 0; i < size; i++) {
+                        value[i] = Number(arguments[i + 1]);
+                    }
+                    call(this, 'vertexAttrib', [index, value]);
+                };
+                proto['vertexAttrib' + size + 'fv'] = function(index, values) {
+                    const value = [0, 0, 0, 1];
+                    const given = numbers(values);
+                    for (let i = 0; i < size; i++) {
+                        value[i] = given[i];
+                    }
+                    call(this, 'vertexAttrib', [index, value]);
+                };
+            });
+
+            // Canvas elements
+
+            const contexts = new Map();
+
+            let HTMLCanvasElement = root.HTMLCanvasElement;
+            if (typeof HTMLCanvasElement !== 'function') {
+                HTMLCanvasElement = illegalConstructor('HTMLCanvasElement');
+            }
+            const parentProto = typeof root.HTMLElement === 'function' && root.HTMLElement.prototype
+                ? root.HTMLElement.prototype
+                : Object.prototype;
+            HTMLCanvasElement.prototype = Object.create(parentProto);
+            Object.defineProperty(HTMLCanvasElement.prototype, 'constructor', {
+                value: HTMLCanvasElement,
+                writable: true,
+                configurable: true,
+                enumerable: false,
+            });
+            const canvasProto = HTMLCanvasElement.prototype;
+
+            function dimension(canvas, name, fallback) {
+                const value = parseInt(canvas.getAttribute(name), 10);
+                return value >= 0 ? value : fallback;
+            }
+
+            function resizeContext(canvas) {
+                const context = contexts.get(canvas.__nodeId);
+                if (context) {
+                    call(context, 'resize', [dimension(canvas, 'width', 300), dimension(canvas, 'height', 150)]);
+                }
+            }
+
+            ['width', 'height'].forEach(function(name) {
+                Object.defineProperty(canvasProto, name, {
+                    get() { return dimension(this, name, name === 'width' ? 300 : 150); },
+                    set(value) {
+                        this.setAttribute(name, String(Math.max(0, Math.floor(Number(value)) || 0)));
+                        resizeContext(this);
+                    },
+                    configurable: true,
+                    enumerable: true,
+                });
+            });
+
+            canvasProto.getContext = function(type, options) {
+                if (type !== 'webgl' && type !== 'experimental-webgl') {
+                    return null;
+                }
+                const existing = contexts.get(this.__nodeId);
+                if (existing) {
+                    return existing;
+                }
+                options = options || {};
+                const attributes = {
+                    alpha: options.alpha !== false,
+                    depth: options.depth !== false,
+                    stencil: !!options.stencil,
+                    premultipliedAlpha: options.premultipliedAlpha !== false,
+                    preserveDrawingBuffer: !!options.preserveDrawingBuffer,
+                };
+                const id = root.__stokesWebglCreate(
+                    this,
+                    dimension(this, 'width', 300),
+                    dimension(this, 'height', 150),
+                    JSON.stringify(attributes),
+                );
+                if (id === null || id === undefined) {
+                    return null;
+                }
+                const context = Object.create(proto);
+                Object.defineProperty(context, 'canvas', { value: this, enumerable: true });
+                Object.defineProperty(context, '__id', { value: id });
+                Object.defineProperty(context, '__objects', { value: new Map() });
+                Object.defineProperty(context, '__attributes', { value: attributes });
+                context.__lost = false;
+                contexts.set(this.__nodeId, context);
+                return context;
+            };
+            canvasProto.toDataURL = function() {
+                return 'data:,';
+            };
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+/// `__stokesWebglCreate(canvas, width, height, attributesJson)`: create a WebGL context and make
+/// it what the canvas element paints. Returns the context id, or null without GPU rendering.
+unsafe extern "C" fn stokes_webgl_create(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let node_id = if argc > 0 { get_node_id_from_value(safe_cx, *args.get(0)) } else { None };
+    let dimension =
+        |index: u32| if argc > index && args.get(index).is_number() { args.get(index).to_number() } else { 0.0 };
+    let (width, height) = (dimension(1) as u32, dimension(2) as u32);
+    let attributes = if argc > 3 {
+        parse_attributes(&js_value_to_string(safe_cx, *args.get(3)))
+    } else {
+        ContextAttributes::default()
+    };
+
+    let id = node_id.and_then(|node_id| {
+        DOM_REF.with(|dom| {
+            let Some(dom) = *dom.borrow() else {
+                return None;
+            };
+            let dom = unsafe { &mut *dom };
+            let id = webgl::create_context(width, height, attributes)?;
+            if let Some(element) = dom.get_node_mut(node_id).and_then(|node| node.element_data_mut()) {
+                element.special_data = SpecialElementData::Canvas(CanvasData { custom_paint_source_id: id });
+            }
+            dom.shell_provider.request_redraw();
+            Some(id)
+        })
+    });
+    args.rval().set(id.map_or(NullValue(), |id| DoubleValue(id as f64)));
+    true
+}
+
+fn parse_attributes(json: &str) -> ContextAttributes {
+    let value: Value = serde_json::from_str(json).unwrap_or_default();
+    let defaults = ContextAttributes::default();
+    let flag = |name: &str, default: bool| value.get(name).and_then(Value::as_bool).unwrap_or(default);
+    ContextAttributes {
+        alpha: flag("alpha", defaults.alpha),
+        depth: flag("depth", defaults.depth),
+        stencil: flag("stencil", defaults.stencil),
+        premultiplied_alpha: flag("premultipliedAlpha", defaults.premultiplied_alpha),
+        preserve_drawing_buffer: flag("preserveDrawingBuffer", defaults.preserve_drawing_buffer),
+    }
+}
+
+/// `__stokesWebgl(id, op, argsJson, element)`: run one context call. Returns its result as JSON,
+/// undefined for calls without one, or null when the context was lost.
+unsafe extern "C" fn stokes_webgl(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    if argc < 2 || !args.get(0).is_number() {
+        args.rval().set(UndefinedValue());
+        return true;
+    }
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let id = args.get(0).to_number() as u64;
+    let op = js_value_to_string(safe_cx, *args.get(1));
+    let call_args = if argc > 2 { js_value_to_string(safe_cx, *args.get(2)) } else { String::new() };
+    let call_args = Args(serde_json::from_str(&call_args).unwrap_or_default());
+
+    // Pixels of an image element passed to texImage2D or texSubImage2D
+    let source_node = if argc > 3 { get_node_id_from_value(safe_cx, *args.get(3)) } else { None };
+    let source = source_node.and_then(|node_id| {
+        DOM_REF.with(|dom| {
+            let dom = unsafe { &*(*dom.borrow())? };
+            let image = dom.get_node(node_id)?.element_data()?.raster_image_data()?;
+            Some((image.width as i32, image.height as i32, image.data.data().to_vec()))
+        })
+    });
+
+    let result = webgl::with_context(id, |context| call(context, &op, &call_args, source));
+    if webgl::take_redraw_request() {
+        DOM_REF.with(|dom| {
+            if let Some(dom) = *dom.borrow() {
+                unsafe { &*dom }.shell_provider.request_redraw();
+            }
+        });
+    }
+    match result {
+        None => args.rval().set(NullValue()),
+        Some(None) => args.rval().set(UndefinedValue()),
+        Some(Some(value)) => args.rval().set(create_js_string(safe_cx, &value.to_string())),
+    }
+    true
+}
+
+/// Call arguments as the page passed them, with page objects as their ids
+struct Args(Vec<Value>);
+
+impl Args {
+    fn number(&self, index: usize) -> f64 {
+        match self.0.get(index) {
+            Some(Value::Bool(value)) => *value as u8 as f64,
+            Some(value) => value.as_f64().unwrap_or(0.0),
+            None => 0.0,
+        }
+    }
+
+    fn u32(&self, index: usize) -> u32 {
+        self.number(index) as i64 as u32
+    }
+
+    fn i32(&self, index: usize) -> i32 {
+        self.number(index) as i32
+    }
+
+    fn f32(&self, index: usize) -> f32 {
+        self.number(index) as f32
+    }
+
+    fn bool(&self, index: usize) -> bool {
+        self.number(index) != 0.0
+    }
+
+    fn string(&self, index: usize) -> String {
+        match self.0.get(index) {
+            Some(Value::String(value)) => value.clone(),
+            Some(Value::Null) | None => String::new(),
+            Some(value) => value.to_string(),
+        }
+    }
+
+    /// An x/y offset pair, None for the texImage2D form of an upload
+    fn offset(&self, index: usize) -> Option<(i32, i32)> {
+        match self.0.get(index) {
+            Some(Value::Null) | None => None,
+            Some(_) => Some((self.i32(index), self.i32(index + 1))),
+        }
+    }
+
+    fn bytes(&self, index: usize) -> Option<Vec<u8>> {
+        self.0.get(index)?.as_str().and_then(|data| STANDARD.decode(data).ok())
+    }
+
+    fn floats(&self, index: usize) -> Vec<f32> {
+        self.0
+            .get(index)
+            .and_then(Value::as_array)
+            .map(|values| values.iter().map(|value| value.as_f64().unwrap_or(0.0) as f32).collect())
+            .unwrap_or_default()
+    }
+}
+
+fn parameter_json(parameter: Parameter) -> Value {
+    match parameter {
+        Parameter::Null => Value::Null,
+        Parameter::Bool(value) => json!(value),
+        Parameter::Int(value) => json!(value),
+        Parameter::Float(value) => json!(value),
+        Parameter::Text(value) => json!(value),
+        Parameter::Ints(values) => json!({ "ints": values }),
+        Parameter::Floats(values) => json!({ "floats": values }),
+        Parameter::Bools(values) => json!({ "bools": values }),
+        Parameter::Object(0) => Value::Null,
+        Parameter::Object(id) => json!({ "object": id }),
+    }
+}
+
+fn active_info_json(info: Option<ActiveInfo>) -> Value {
+    info.map_or(Value::Null, |info| json!({ "info": { "size": info.size, "type": info.kind, "name": info.name } }))
+}
+
+fn object_kind(op: &str) -> Option<ObjectKind> {
+    let kind = match op.trim_start_matches("create").trim_start_matches("delete").trim_start_matches("is") {
+        "Buffer" => ObjectKind::Buffer,
+        "Texture" => ObjectKind::Texture,
+        "Shader" => ObjectKind::Shader,
+        "Program" => ObjectKind::Program,
+        "Framebuffer" => ObjectKind::Framebuffer,
+        "Renderbuffer" => ObjectKind::Renderbuffer,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// Run context method `op`. Returns None for methods that return nothing.
+fn call(context: &mut WebGlContext, op: &str, args: &Args, source: Option<(i32, i32, Vec<u8>)>) -> Option<Value> {
+    let result = match op {
+        "isContextLost" => json!(false),
+        "drawingBufferSize" => {
+            let (width, height) = context.drawing_buffer_size();
+            json!([width, height])
+        }
+        "resize" => {
+            context.resize(args.u32(0), args.u32(1));
+            return None;
+        }
+        "getError" => json!(context.get_error()),
+
+        "createShader" => json!(context.create_shader(args.u32(0))),
+        _ if op.starts_with("create") => json!(object_kind(op).map_or(0, |kind| context.create_object(kind))),
+        _ if op.starts_with("delete") => {
+            if let Some(kind) = object_kind(op) {
+                context.delete_object(args.u32(0), kind);
+            }
+            return None;
+        }
+        _ if op.starts_with("is") && object_kind(op).is_some() => {
+            json!(object_kind(op).is_some_and(|kind| context.is_object(args.u32(0), kind)))
+        }
+
+        "enable" | "disable" => {
+            context.enable(args.u32(0), op == "enable");
+            return None;
+        }
+        "isEnabled" => json!(context.is_enabled(args.u32(0))),
+        "viewport" => {
+            context.viewport(args.i32(0), args.i32(1), args.i32(2), args.i32(3));
+            return None;
+        }
+        "scissor" => {
+            context.scissor(args.i32(0), args.i32(1), args.i32(2), args.i32(3));
+            return None;
+        }
+        "clearColor" => {
+            context.clear_color([args.f32(0), args.f32(1), args.f32(2), args.f32(3)]);
+            return None;
+        }
+        "clearDepth" => {
+            context.clear_depth(args.f32(0));
+            return None;
+        }
+        "clearStencil" => {
+            context.clear_stencil(args.i32(0));
+            return None;
+        }
+        "blendColor" => {
+            context.blend_color([args.f32(0), args.f32(1), args.f32(2), args.f32(3)]);
+            return None;
+        }
+        "blendEquation" => {
+            context.blend_equation(args.u32(0), args.u32(0));
+            return None;
+        }
+        "blendEquationSeparate" => {
+            context.blend_equation(args.u32(0), args.u32(1));
+            return None;
+        }
+        "blendFunc" => {
+            context.blend_func(args.u32(0), args.u32(1), args.u32(0), args.u32(1));
+            return None;
+        }
+        "blendFuncSeparate" => {
+            context.blend_func(args.u32(0), args.u32(1), args.u32(2), args.u32(3));
+            return None;
+        }
+        "colorMask" => {
+            context.color_mask([args.bool(0), args.bool(1), args.bool(2), args.bool(3)]);
+            return None;
+        }
+        "depthMask" => {
+            context.depth_mask(args.bool(0));
+            return None;
+        }
+        "depthFunc" => {
+            context.depth_func(args.u32(0));
+            return None;
+        }
+        "depthRange" => {
+            context.depth_range(args.f32(0), args.f32(1));
+            return None;
+        }
+        "cullFace" => {
+            context.cull_face(args.u32(0));
+            return None;
+        }
+        "frontFace" => {
+            context.front_face(args.u32(0));
+            return None;
+        }
+        "stencilFunc" => {
+            context.stencil_func(gl::FRONT_AND_BACK, args.u32(0), args.i32(1), args.u32(2));
+            return None;
+        }
+        "stencilFuncSeparate" => {
+            context.stencil_func(args.u32(0), args.u32(1), args.i32(2), args.u32(3));
+            return None;
+        }
+        "stencilOp" => {
+            context.stencil_op(gl::FRONT_AND_BACK, args.u32(0), args.u32(1), args.u32(2));
+            return None;
+        }
+        "stencilOpSeparate" => {
+            context.stencil_op(args.u32(0), args.u32(1), args.u32(2), args.u32(3));
+            return None;
+        }
+        "stencilMask" => {
+            context.stencil_mask(gl::FRONT_AND_BACK, args.u32(0));
+            return None;
+        }
+        "stencilMaskSeparate" => {
+            context.stencil_mask(args.u32(0), args.u32(1));
+            return None;
+        }
+        "polygonOffset" => {
+            context.polygon_offset(args.f32(0), args.f32(1));
+            return None;
+        }
+        "lineWidth" => {
+            context.line_width(args.f32(0));
+            return None;
+        }
+        "sampleCoverage" => {
+            context.sample_coverage(args.f32(0), args.bool(1));
+            return None;
+        }
+        "hint" => {
+            context.hint(args.u32(0), args.u32(1));
+            return None;
+        }
+        "pixelStorei" => {
+            context.pixel_store(args.u32(0), args.i32(1));
+            return None;
+        }
+        "getParameter" => parameter_json(context.parameter(args.u32(0))),
+
+        "bindBuffer" => {
+            context.bind_buffer(args.u32(0), args.u32(1));
+            return None;
+        }
+        "bufferData" => {
+            context.buffer_data(args.u32(0), args.bytes(1), args.number(2).max(0.0) as usize, args.u32(3));
+            return None;
+        }
+        "bufferSubData" => {
+            context.buffer_sub_data(args.u32(0), args.number(1).max(0.0) as usize, &args.bytes(2).unwrap_or_default());
+            return None;
+        }
+        "getBufferParameter" => parameter_json(context.buffer_parameter(args.u32(0), args.u32(1))),
+
+        "enableVertexAttribArray" | "disableVertexAttribArray" => {
+            context.enable_vertex_attrib_array(args.u32(0), op == "enableVertexAttribArray");
+            return None;
+        }
+        "vertexAttribPointer" => {
+            let offset = args.number(5).max(0.0) as usize;
+            context.vertex_attrib_pointer(args.u32(0), args.i32(1), args.u32(2), args.bool(3), args.i32(4), offset);
+            return None;
+        }
+        "vertexAttrib" => {
+            let values = args.floats(1);
+            let value = [0, 1, 2, 3].map(|i| values.get(i).copied().unwrap_or(if i == 3 { 1.0 } else { 0.0 }));
+            context.vertex_attrib(args.u32(0), value);
+            return None;
+        }
+        "getVertexAttrib" => parameter_json(context.vertex_attrib_parameter(args.u32(0), args.u32(1))),
+        "getVertexAttribOffset" => json!(context.vertex_attrib_offset(args.u32(0))),
+
+        "clear" => {
+            context.clear(args.u32(0));
+            return None;
+        }
+        "drawArrays" => {
+            context.draw_arrays(args.u32(0), args.i32(1), args.i32(2));
+            return None;
+        }
+        "drawElements" => {
+            context.draw_elements(args.u32(0), args.i32(1), args.u32(2), args.number(3).max(0.0) as usize);
+            return None;
+        }
+        "flush" => {
+            context.flush();
+            return None;
+        }
+        "finish" => {
+            context.finish();
+            return None;
+        }
+
+        "shaderSource" => {
+            context.shader_source(args.u32(0), args.string(1));
+            return None;
+        }
+        "getShaderSource" => json!(context.get_shader_source(args.u32(0))),
+        "compileShader" => {
+            context.compile_shader(args.u32(0));
+            return None;
+        }
+        "getShaderParameter" => parameter_json(context.shader_parameter(args.u32(0), args.u32(1))),
+        "getShaderInfoLog" => json!(context.shader_info_log(args.u32(0))),
+        "attachShader" | "detachShader" => {
+            context.attach_shader(args.u32(0), args.u32(1), op == "attachShader");
+            return None;
+        }
+        "getAttachedShaders" => {
+            json!(context.attached_shaders(args.u32(0)).map(|shaders| json!({ "objects": shaders })))
+        }
+        "linkProgram" => {
+            context.link_program(args.u32(0));
+            return None;
+        }
+        "validateProgram" => {
+            context.validate_program(args.u32(0));
+            return None;
+        }
+        "useProgram" => {
+            context.use_program(args.u32(0));
+            return None;
+        }
+        "getProgramParameter" => parameter_json(context.program_parameter(args.u32(0), args.u32(1))),
+        "getProgramInfoLog" => json!(context.program_info_log(args.u32(0))),
+        "getActiveAttrib" => active_info_json(context.active_info(args.u32(0), args.u32(1), false)),
+        "getActiveUniform" => active_info_json(context.active_info(args.u32(0), args.u32(1), true)),
+        "getUniformLocation" => json!(context.uniform_location(args.u32(0), &args.string(1))),
+        "getAttribLocation" => json!(context.attrib_location(args.u32(0), &args.string(1))),
+        "bindAttribLocation" => {
+            context.bind_attrib_location(args.u32(0), args.u32(1), &args.string(2));
+            return None;
+        }
+        "uniformFloats" => {
+            context.uniform_floats(args.u32(0), args.i32(1), args.u32(2).clamp(1, 4) as usize, &args.floats(3));
+            return None;
+        }
+        "uniformInts" => {
+            let values: Vec<i32> = args.floats(3).into_iter().map(|value| value as i32).collect();
+            context.uniform_ints(args.u32(0), args.i32(1), args.u32(2).clamp(1, 4) as usize, &values);
+            return None;
+        }
+        "uniformMatrix" => {
+            let size = args.u32(2).clamp(2, 4) as usize;
+            context.uniform_matrix(args.u32(0), args.i32(1), size, args.bool(3), &args.floats(4));
+            return None;
+        }
+
+        "activeTexture" => {
+            context.active_texture(args.u32(0));
+            return None;
+        }
+        "bindTexture" => {
+            context.bind_texture(args.u32(0), args.u32(1));
+            return None;
+        }
+        "texParameteri" | "texParameterf" => {
+            context.tex_parameter(args.u32(0), args.u32(1), args.i32(2));
+            return None;
+        }
+        "getTexParameter" => parameter_json(context.tex_parameter_value(args.u32(0), args.u32(1))),
+        "generateMipmap" => {
+            context.generate_mipmap(args.u32(0));
+            return None;
+        }
+        "texImage" => {
+            let size = (args.i32(4), args.i32(5));
+            context.tex_image_2d(
+                args.u32(0),
+                args.i32(1),
+                args.offset(2),
+                size,
+                args.u32(6),
+                args.u32(7),
+                args.bytes(8),
+            );
+            return None;
+        }
+        "texImageSource" | "texImageData" => {
+            let (width, height, rgba) = if op == "texImageData" {
+                (args.i32(6), args.i32(7), args.bytes(8).unwrap_or_default())
+            } else {
+                source?
+            };
+            if width <= 0 || height <= 0 || rgba.len() < width as usize * height as usize * 4 {
+                return None;
+            }
+            let source = SourcePixels { width, height, rgba: &rgba };
+            context.tex_image_2d_from_source(
+                args.u32(0),
+                args.i32(1),
+                args.offset(2),
+                args.u32(4),
+                args.u32(5),
+                source,
+            );
+            return None;
+        }
+        "copyTexImage2D" => {
+            let rect = (args.i32(3), args.i32(4), args.i32(5), args.i32(6));
+            context.copy_tex_image_2d(args.u32(0), args.i32(1), args.u32(2), None, rect);
+            return None;
+        }
+        "copyTexSubImage2D" => {
+            let rect = (args.i32(4), args.i32(5), args.i32(6), args.i32(7));
+            context.copy_tex_image_2d(args.u32(0), args.i32(1), 0, Some((args.i32(2), args.i32(3))), rect);
+            return None;
+        }
+        "compressedTexImage2D" => {
+            // No compressed texture extensions are offered, so no format is valid
+            context.synthesize_error(gl::INVALID_ENUM);
+            return None;
+        }
+
+        "bindFramebuffer" => {
+            context.bind_framebuffer(args.u32(0), args.u32(1));
+            return None;
+        }
+        "bindRenderbuffer" => {
+            context.bind_renderbuffer(args.u32(0), args.u32(1));
+            return None;
+        }
+        "framebufferTexture2D" => {
+            context.framebuffer_texture_2d(args.u32(1), args.u32(2), args.u32(3), args.i32(4));
+            return None;
+        }
+        "framebufferRenderbuffer" => {
+            context.framebuffer_renderbuffer(args.u32(1), args.u32(3));
+            return None;
+        }
+        "checkFramebufferStatus" => json!(context.check_framebuffer_status()),
+        "renderbufferStorage" => {
+            context.renderbuffer_storage(args.u32(1), args.i32(2), args.i32(3));
+            return None;
+        }
+        "getRenderbufferParameter" => parameter_json(context.renderbuffer_parameter(args.u32(1))),
+        "getFramebufferAttachmentParameter" => {
+            parameter_json(context.framebuffer_attachment_parameter(args.u32(1), args.u32(2)))
+        }
+        "readPixels" => {
+            let pixels =
+                context.read_pixels(args.i32(0), args.i32(1), args.i32(2), args.i32(3), args.u32(4), args.u32(5));
+            json!(pixels.map(|pixels| STANDARD.encode(pixels)))
+        }
+        _ => return None,
+    };
+    Some(result)
+}
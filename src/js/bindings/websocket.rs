@@ -0,0 +1,413 @@
+// WebSocket API. The protocol lives in networking::websocket; this binds it to a JS polyfill
+// that owns readyState, events and binary conversion.
+use crate::engine::mixed_content::ContentKind;
+use crate::js::bindings::dom_bindings::{DOM_REF, USER_AGENT};
+use crate::js::helpers::{ToSafeCx, create_js_string, define_function, js_value_to_string};
+use crate::js::runtime_context::current_document_base_url;
+use crate::js::{JsResult, JsRuntime};
+use crate::networking::websocket::{self, WebSocketEvent, WebSocketHandle};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use mozjs::jsapi::{CallArgs, JSContext};
+use mozjs::jsval::{DoubleValue, JSVal, UndefinedValue};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::os::raw::c_uint;
+use std::sync::atomic::{AtomicU64, Ordering};
+use url::Url;
+
+thread_local! {
+    /// Open connections of the current document, by id
+    static SOCKETS: RefCell<HashMap<u64, WebSocketHandle>> = RefCell::new(HashMap::new());
+}
+
+/// Ids are never reused, so events from a previous document's sockets can't reach new ones
+static NEXT_SOCKET_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Set up the WebSocket constructor
+pub fn setup_websocket(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesWebSocketResolve", Some(stokes_websocket_resolve), 1)?;
+        define_function(cx, global.get(), "__stokesWebSocketConnect", Some(stokes_websocket_connect), 2)?;
+        define_function(cx, global.get(), "__stokesWebSocketSend", Some(stokes_websocket_send), 3)?;
+        define_function(cx, global.get(), "__stokesWebSocketClose", Some(stokes_websocket_close), 3)?;
+        define_function(cx, global.get(), "__stokesWebSocketRelease", Some(stokes_websocket_release), 1)
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            if (typeof root.__stokesWebSocketConnect !== 'function') {
+                return;
+            }
+
+            const CONNECTING = 0;
+            const OPEN = 1;
+            const CLOSING = 2;
+            const CLOSED = 3;
+            const sockets = new Map();
+
+            function makeError(message, name) {
+                if (typeof root.DOMException === 'function') {
+                    return new root.DOMException(message, name);
+                }
+                const error = new Error(message);
+                error.name = name;
+                return error;
+            }
+
+            function utf8Length(text) {
+                if (typeof root.TextEncoder === 'function') {
+                    return new root.TextEncoder().encode(text).length;
+                }
+                return unescape(encodeURIComponent(text)).length;
+            }
+
+            function bytesToBase64(bytes) {
+                let binary = '';
+                for (let i = 0; i < bytes.length; i += 0x8000) {
+                    binary += String.fromCharCode.apply(null, bytes.subarray(i, i + 0x8000));
+                }
+                return btoa(binary);
+            }
+
+            function base64ToBuffer(data) {
+                const binary = atob(data);
+                const bytes = new Uint8Array(binary.length);
+                for (let i = 0; i < binary.length; i++) {
+                    bytes[i] = binary.charCodeAt(i);
+                }
+                return bytes.buffer;
+            }
+
+            function WebSocket(url, protocols) {
+                if (!(this instanceof WebSocket)) {
+                    throw new TypeError("Failed to construct 'WebSocket': Please use the 'new' operator.");
+                }
+                if (arguments.length < 1) {
+                    throw new TypeError("Failed to construct 'WebSocket': 1 argument required, but only 0 present.");
+                }
+
+                const resolved = root.__stokesWebSocketResolve(String(url));
+                if (!resolved) {
+                    throw makeError("Failed to construct 'WebSocket': The URL '" + url + "' is invalid.", 'SyntaxError');
+                }
+
+                if (protocols === undefined) {
+                    protocols = [];
+                } else if (typeof protocols === 'string') {
+                    protocols = [protocols];
+                } else {
+                    protocols = Array.from(protocols, String);
+                }
+                const seen = new Set();
+                for (const protocol of protocols) {
+                    // Subprotocols are HTTP tokens
+                    if (!/^[!#$%&'*+\-.^_`|~0-9A-Za-z]+$/.test(protocol) || seen.has(protocol)) {
+                        throw makeError("Failed to construct 'WebSocket': The subprotocol '" + protocol + "' is invalid.", 'SyntaxError');
+                    }
+                    seen.add(protocol);
+                }
+
+                this.url = resolved;
+                this.readyState = CONNECTING;
+                this.protocol = '';
+                this.extensions = '';
+                this.bufferedAmount = 0;
+                this.binaryType = 'blob';
+                this.onopen = null;
+                this.onmessage = null;
+                this.onerror = null;
+                this.onclose = null;
+                this._listeners = {};
+                this._id = root.__stokesWebSocketConnect(resolved, protocols.join(','));
+                if (this._id === undefined) {
+                    const socket = this;
+                    setTimeout(function() {
+                        socket.readyState = CLOSED;
+                        socket.dispatchEvent({ type: 'error' });
+                        socket.dispatchEvent({ type: 'close', code: 1006, reason: '', wasClean: false });
+                    }, 0);
+                    return;
+                }
+                sockets.set(this._id, this);
+            }
+
+            WebSocket.CONNECTING = WebSocket.prototype.CONNECTING = CONNECTING;
+            WebSocket.OPEN = WebSocket.prototype.OPEN = OPEN;
+            WebSocket.CLOSING = WebSocket.prototype.CLOSING = CLOSING;
+            WebSocket.CLOSED = WebSocket.prototype.CLOSED = CLOSED;
+
+            WebSocket.prototype.addEventListener = function(type, listener) {
+                if (typeof listener !== 'function' && !(listener && typeof listener.handleEvent === 'function')) {
+                    return;
+                }
+                const listeners = this._listeners[type] = this._listeners[type] || [];
+                if (listeners.indexOf(listener) < 0) {
+                    listeners.push(listener);
+                }
+            };
+            WebSocket.prototype.removeEventListener = function(type, listener) {
+                const listeners = this._listeners[type];
+                const index = listeners ? listeners.indexOf(listener) : -1;
+                if (index >= 0) {
+                    listeners.splice(index, 1);
+                }
+            };
+            WebSocket.prototype.dispatchEvent = function(event) {
+                event.target = event.currentTarget = this;
+                const handler = this['on' + event.type];
+                const listeners = (this._listeners[event.type] || []).slice();
+                if (typeof handler === 'function') {
+                    listeners.unshift(handler);
+                }
+                for (const listener of listeners) {
+                    try {
+                        if (typeof listener === 'function') {
+                            listener.call(this, event);
+                        } else {
+                            listener.handleEvent(event);
+                        }
+                    } catch (err) {
+                        console.error(err);
+                    }
+                }
+                return true;
+            };
+
+            WebSocket.prototype.send = function(data) {
+                if (this.readyState === CONNECTING) {
+                    throw makeError("Failed to execute 'send' on 'WebSocket': Still in CONNECTING state.", 'InvalidStateError');
+                }
+                let binary = null;
+                if (data instanceof ArrayBuffer) {
+                    binary = new Uint8Array(data);
+                } else if (ArrayBuffer.isView(data)) {
+                    binary = new Uint8Array(data.buffer, data.byteOffset, data.byteLength);
+                }
+                const size = binary ? binary.length : utf8Length(String(data));
+                if (this.readyState !== OPEN) {
+                    // Data sent after closing only counts towards bufferedAmount
+                    this.bufferedAmount += size;
+                    return;
+                }
+                if (binary) {
+                    root.__stokesWebSocketSend(this._id, bytesToBase64(binary), true);
+                } else {
+                    root.__stokesWebSocketSend(this._id, String(data), false);
+                }
+            };
+
+            WebSocket.prototype.close = function(code, reason) {
+                if (code !== undefined) {
+                    code = Number(code);
+                    if (code !== 1000 && !(code >= 3000 && code <= 4999)) {
+                        throw makeError("Failed to execute 'close' on 'WebSocket': The close code must be either 1000, or between 3000 and 4999.", 'InvalidAccessError');
+                    }
+                }
+                reason = reason === undefined ? '' : String(reason);
+                if (utf8Length(reason) > 123) {
+                    throw makeError("Failed to execute 'close' on 'WebSocket': The close reason must not be greater than 123 UTF-8 bytes.", 'SyntaxError');
+                }
+                if (this.readyState === CLOSING || this.readyState === CLOSED) {
+                    return;
+                }
+                this.readyState = CLOSING;
+                root.__stokesWebSocketClose(this._id, code === undefined ? -1 : code, reason);
+            };
+
+            // Called by the tab process for each event on connection `id`
+            root.__stokesWebSocketEvent = function(id, event) {
+                const socket = sockets.get(id);
+                if (!socket) {
+                    return;
+                }
+                switch (event.type) {
+                    case 'open':
+                        socket.readyState = OPEN;
+                        socket.protocol = event.protocol;
+                        socket.dispatchEvent({ type: 'open' });
+                        break;
+                    case 'message': {
+                        if (socket.readyState !== OPEN) {
+                            return;
+                        }
+                        let data = event.text;
+                        if (event.binary !== undefined) {
+                            data = base64ToBuffer(event.binary);
+                            if (socket.binaryType === 'blob' && typeof root.Blob === 'function') {
+                                data = new root.Blob([data]);
+                            }
+                        }
+                        socket.dispatchEvent({ type: 'message', data: data, origin: new URL(socket.url).origin, lastEventId: '', ports: [] });
+                        break;
+                    }
+                    case 'error':
+                        socket.dispatchEvent({ type: 'error' });
+                        break;
+                    case 'close':
+                        sockets.delete(id);
+                        root.__stokesWebSocketRelease(id);
+                        socket.readyState = CLOSED;
+                        socket.dispatchEvent({ type: 'close', code: event.code, reason: event.reason, wasClean: event.wasClean });
+                        break;
+                }
+            };
+
+            root.WebSocket = WebSocket;
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+/// Drop every connection of the current document; called when navigating away
+pub fn close_all_websockets() {
+    SOCKETS.with(|sockets| sockets.borrow_mut().clear());
+}
+
+/// Resolve a WebSocket URL against the document, mapping http(s) to ws(s) as the spec allows
+fn resolve_websocket_url(input: &str, base: Option<&Url>) -> Option<Url> {
+    let mut url = Url::options().base_url(base).parse(input).ok()?;
+    match url.scheme() {
+        "http" => url.set_scheme("ws").ok()?,
+        "https" => url.set_scheme("wss").ok()?,
+        "ws" | "wss" => {}
+        _ => return None,
+    }
+    url.fragment().is_none().then_some(url)
+}
+
+/// Script that hands `event` to the page's `__stokesWebSocketEvent`
+fn event_script(id: u64, event: WebSocketEvent) -> String {
+    let data = match event {
+        WebSocketEvent::Open { protocol } => serde_json::json!({ "type": "open", "protocol": protocol }),
+        WebSocketEvent::Text(text) => serde_json::json!({ "type": "message", "text": text }),
+        WebSocketEvent::Binary(bytes) => serde_json::json!({ "type": "message", "binary": STANDARD.encode(bytes) }),
+        WebSocketEvent::Error(_) => serde_json::json!({ "type": "error" }),
+        WebSocketEvent::Closed { code, reason, was_clean } => {
+            serde_json::json!({ "type": "close", "code": code, "reason": reason, "wasClean": was_clean })
+        }
+    };
+    format!("if (typeof __stokesWebSocketEvent === 'function') {{ __stokesWebSocketEvent({id}, {data}); }}")
+}
+
+unsafe extern "C" fn stokes_websocket_resolve(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let input = if argc > 0 { js_value_to_string(safe_cx, *args.get(0)) } else { String::new() };
+    let base = current_document_base_url().and_then(|base| Url::parse(&base).ok());
+    let resolved = resolve_websocket_url(&input, base.as_ref()).map(String::from).unwrap_or_default();
+    args.rval().set(create_js_string(safe_cx, &resolved));
+    true
+}
+
+unsafe extern "C" fn stokes_websocket_connect(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let url = if argc > 0 { js_value_to_string(safe_cx, *args.get(0)) } else { String::new() };
+    let protocols = if argc > 1 { js_value_to_string(safe_cx, *args.get(1)) } else { String::new() };
+    let protocols: Vec<String> = protocols.split(',').filter(|p| !p.is_empty()).map(str::to_string).collect();
+
+    let id = NEXT_SOCKET_ID.fetch_add(1, Ordering::Relaxed);
+    let (Ok(url), Some((origin, js_provider, net_provider))) = (
+        Url::parse(&url),
+        DOM_REF.with(|dom| {
+            (*dom.borrow()).map(|dom| {
+                let dom = unsafe { &*dom };
+                (dom.url.origin().ascii_serialization(), dom.js_provider.clone(), dom.net_provider.clone())
+            })
+        }),
+    ) else {
+        args.rval().set(UndefinedValue());
+        return true;
+    };
+    // ws:// from a secure page is blocked mixed content; the socket fails like a refused connection
+    let Some(url) = net_provider.filter_mixed_content(&url, ContentKind::Active) else {
+        args.rval().set(UndefinedValue());
+        return true;
+    };
+    let user_agent = USER_AGENT.with(|ua| ua.borrow().clone());
+
+    let handle = websocket::connect(url, protocols, origin, user_agent, move |event| {
+        js_provider.execute_script(event_script(id, event));
+    });
+    SOCKETS.with(|sockets| sockets.borrow_mut().insert(id, handle));
+    args.rval().set(DoubleValue(id as f64));
+    true
+}
+
+unsafe extern "C" fn stokes_websocket_send(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    if argc < 3 || !args.get(0).is_number() {
+        args.rval().set(UndefinedValue());
+        return true;
+    }
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let id = args.get(0).to_number() as u64;
+    let data = js_value_to_string(safe_cx, *args.get(1));
+    let binary = args.get(2).is_boolean() && args.get(2).to_boolean();
+    SOCKETS.with(|sockets| {
+        let sockets = sockets.borrow();
+        let Some(socket) = sockets.get(&id) else {
+            return;
+        };
+        if !binary {
+            socket.send_text(data);
+        } else if let Ok(bytes) = STANDARD.decode(data.as_bytes()) {
+            socket.send_binary(bytes);
+        }
+    });
+    args.rval().set(UndefinedValue());
+    true
+}
+
+unsafe extern "C" fn stokes_websocket_close(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    if argc < 1 || !args.get(0).is_number() {
+        args.rval().set(UndefinedValue());
+        return true;
+    }
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let id = args.get(0).to_number() as u64;
+    let code = (argc > 1 && args.get(1).is_number())
+        .then(|| args.get(1).to_number())
+        .filter(|code| *code >= 0.0)
+        .map(|code| code as u16);
+    let reason = if argc > 2 { js_value_to_string(safe_cx, *args.get(2)) } else { String::new() };
+    SOCKETS.with(|sockets| {
+        if let Some(socket) = sockets.borrow().get(&id) {
+            socket.close(code, reason);
+        }
+    });
+    args.rval().set(UndefinedValue());
+    true
+}
+
+unsafe extern "C" fn stokes_websocket_release(_raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    if argc > 0 && args.get(0).is_number() {
+        let id = args.get(0).to_number() as u64;
+        SOCKETS.with(|sockets| sockets.borrow_mut().remove(&id));
+    }
+    args.rval().set(UndefinedValue());
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolve_websocket_url;
+    use url::Url;
+
+    #[test]
+    fn websocket_urls_resolve_against_the_document() {
+        let base = Url::parse("https://example.com/app/index.html").unwrap();
+        let resolve = |input| resolve_websocket_url(input, Some(&base)).map(String::from);
+
+        assert_eq!(resolve("/live").as_deref(), Some("wss://example.com/live"));
+        assert_eq!(resolve("ws://example.com:8080/feed").as_deref(), Some("ws://example.com:8080/feed"));
+        assert_eq!(resolve("http://other.example/socket").as_deref(), Some("ws://other.example/socket"));
+        assert_eq!(resolve("wss://example.com/#fragment"), None);
+        assert_eq!(resolve("ftp://example.com/"), None);
+    }
+}
@@ -1,11 +1,14 @@
 use crate::js::bindings::dom_bindings::DOM_REF;
 use crate::js::bindings::event_listeners;
 use crate::js::helpers::ToSafeCx;
-use crate::js::helpers::{define_function, define_js_property_getter, js_value_to_string};
+use crate::dom::scroll::ScrollBehavior;
+use crate::js::bindings::dialog_callback::{run_dialog, JsDialog};
+use crate::js::helpers::{create_js_string, define_function, define_js_property_getter, get_dictionary_member, js_value_to_f64, js_value_to_string};
 use crate::js::JsRuntime;
+use blitz_traits::shell::ColorScheme;
 use mozjs::context::JSContext as SafeJSContext;
 use mozjs::jsapi::{CallArgs, JS_DefineProperty, JS_GetProperty, JS_NewPlainObject, JSObject, JSPROP_ENUMERATE};
-use mozjs::jsval::{BooleanValue, Int32Value, JSVal, NullValue, ObjectValue, UndefinedValue};
+use mozjs::jsval::{BooleanValue, DoubleValue, Int32Value, JSVal, NullValue, ObjectValue, UndefinedValue};
 use mozjs::rooted;
 use std::os::raw::c_uint;
 use tracing::trace;
@@ -145,6 +148,7 @@ pub(crate) unsafe fn setup_window_bindings(
         3,
     )?;
     define_function(cx, global, "scrollTo", Some(window_scroll_to), 2)?;
+    define_function(cx, global, "scroll", Some(window_scroll_to), 2)?;
     define_function(cx, global, "scrollBy", Some(window_scroll_by), 2)?;
     define_function(
         cx,
@@ -184,11 +188,16 @@ pub(crate) unsafe fn setup_window_bindings(
     Ok(())
 }
 
+/// Origin of the current document, shown in the dialog so users know which page is asking
+unsafe fn dialog_origin() -> String {
+    DOM_REF.with(|dom| (*dom.borrow()).map(|dom| (*dom).url.origin().ascii_serialization())).unwrap_or_default()
+}
+
 pub(crate) unsafe extern "C" fn window_alert(raw_cx: *mut mozjs::jsapi::JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, argc);
     let safe_cx = &mut raw_cx.to_safe_cx();
     let message = if argc > 0 { js_value_to_string(safe_cx, *args.get(0)) } else { String::new() };
-    super::alert_callback::trigger_alert(message);
+    run_dialog(&dialog_origin(), JsDialog::Alert(message));
     args.rval().set(UndefinedValue());
     true
 }
@@ -197,8 +206,8 @@ pub(crate) unsafe extern "C" fn window_confirm(raw_cx: *mut mozjs::jsapi::JSCont
     let args = CallArgs::from_vp(vp, argc);
     let safe_cx = &mut raw_cx.to_safe_cx();
     let message = if argc > 0 { js_value_to_string(safe_cx, *args.get(0)) } else { String::new() };
-    warn!("[JS] window.confirm('{}') called on partial binding (always returns false)", message);
-    args.rval().set(BooleanValue(false));
+    let accepted = run_dialog(&dialog_origin(), JsDialog::Confirm(message)).is_some();
+    args.rval().set(BooleanValue(accepted));
     true
 }
 
@@ -206,8 +215,12 @@ pub(crate) unsafe extern "C" fn window_prompt(raw_cx: *mut mozjs::jsapi::JSConte
     let args = CallArgs::from_vp(vp, argc);
     let safe_cx = &mut raw_cx.to_safe_cx();
     let message = if argc > 0 { js_value_to_string(safe_cx, *args.get(0)) } else { String::new() };
-    warn!("[JS] window.prompt('{}') called on partial binding (always returns null)", message);
-    args.rval().set(NullValue());
+    // prompt(message, undefined) has an empty default rather than "undefined"
+    let default = if argc > 1 && !args.get(1).is_undefined() { js_value_to_string(safe_cx, *args.get(1)) } else { String::new() };
+    match run_dialog(&dialog_origin(), JsDialog::Prompt { message, default }) {
+        Some(value) => args.rval().set(create_js_string(safe_cx, &value)),
+        None => args.rval().set(NullValue()),
+    }
     true
 }
 
@@ -293,16 +306,49 @@ pub(crate) unsafe extern "C" fn window_remove_event_listener(raw_cx: *mut mozjs:
     true
 }
 
-pub(crate) unsafe extern "C" fn window_scroll_to(_raw_cx: *mut mozjs::jsapi::JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+/// The target of scrollTo/scrollBy: either `(x, y)` or a ScrollToOptions dictionary
+pub(crate) unsafe fn scroll_arguments(cx: &mut SafeJSContext, args: &CallArgs, argc: c_uint) -> (Option<f64>, Option<f64>, ScrollBehavior) {
+    let first = if argc > 0 { *args.get(0) } else { UndefinedValue() };
+    if first.is_object() {
+        let left = get_dictionary_member(cx, first, "left").and_then(js_value_to_f64);
+        let top = get_dictionary_member(cx, first, "top").and_then(js_value_to_f64);
+        let behavior = get_dictionary_member(cx, first, "behavior")
+            .map(|val| ScrollBehavior::parse(&js_value_to_string(cx, val)))
+            .unwrap_or_default();
+        return (left, top, behavior);
+    }
+    let x = js_value_to_f64(first);
+    let y = if argc > 1 { js_value_to_f64(*args.get(1)) } else { None };
+    (x, y, ScrollBehavior::Auto)
+}
+
+pub(crate) unsafe extern "C" fn window_scroll_to(raw_cx: *mut mozjs::jsapi::JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    warn!("[JS] window.scrollTo() called on partial binding (scroll state is not updated)");
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let (left, top, behavior) = scroll_arguments(safe_cx, &args, argc);
+    DOM_REF.with(|dom| {
+        if let Some(dom_ptr) = *dom.borrow() {
+            let dom = &mut *dom_ptr;
+            let scroll = dom.viewport_scroll;
+            let dx = left.map_or(0.0, |left| scroll.x - left);
+            let dy = top.map_or(0.0, |top| scroll.y - top);
+            dom.scroll_with_behavior(None, dx, dy, behavior);
+        }
+    });
     args.rval().set(UndefinedValue());
     true
 }
 
-pub(crate) unsafe extern "C" fn window_scroll_by(_raw_cx: *mut mozjs::jsapi::JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+pub(crate) unsafe extern "C" fn window_scroll_by(raw_cx: *mut mozjs::jsapi::JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    warn!("[JS] window.scrollBy() called on partial binding (scroll state is not updated)");
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let (left, top, behavior) = scroll_arguments(safe_cx, &args, argc);
+    DOM_REF.with(|dom| {
+        if let Some(dom_ptr) = *dom.borrow() {
+            let dom = &mut *dom_ptr;
+            dom.scroll_with_behavior(None, -left.unwrap_or(0.0), -top.unwrap_or(0.0), behavior);
+        }
+    });
     args.rval().set(UndefinedValue());
     true
 }
@@ -560,23 +606,23 @@ fn get_window_height() -> i32 {
     })
 }
 
-fn get_scroll_x() -> i32 {
+fn get_scroll_x() -> f64 {
     DOM_REF.with(|dom| {
         if let Some(ref dom) = *dom.borrow() {
             let dom = unsafe { &**dom };
-            return dom.viewport_scroll.x as i32;
+            return dom.viewport_scroll.x;
         }
-        0
+        0.0
     })
 }
 
-fn get_scroll_y() -> i32 {
+fn get_scroll_y() -> f64 {
     DOM_REF.with(|dom| {
         if let Some(ref dom) = *dom.borrow() {
             let dom = unsafe { &**dom };
-            return dom.viewport_scroll.y as i32;
+            return dom.viewport_scroll.y;
         }
-        0
+        0.0
     })
 }
 
@@ -628,19 +674,19 @@ pub(crate) unsafe extern "C" fn window_get_screen_y(raw_cx: *mut mozjs::jsapi::J
 
 pub(crate) unsafe extern "C" fn window_get_scroll_x(raw_cx: *mut mozjs::jsapi::JSContext, argc: u32, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    args.rval().set(Int32Value(get_scroll_x()));
+    args.rval().set(DoubleValue(get_scroll_x()));
     true
 }
 
 pub(crate) unsafe extern "C" fn window_get_scroll_y(raw_cx: *mut mozjs::jsapi::JSContext, argc: u32, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    args.rval().set(Int32Value(get_scroll_y()));
+    args.rval().set(DoubleValue(get_scroll_y()));
     true
 }
 
 pub(crate) unsafe extern "C" fn window_get_device_pixel_ratio(raw_cx: *mut mozjs::jsapi::JSContext, argc: u32, vp: *mut JSVal) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    args.rval().set(mozjs::jsval::DoubleValue(get_device_pixel_ratio() as f64));
+    args.rval().set(DoubleValue(get_device_pixel_ratio() as f64));
     true
 }
 
@@ -653,12 +699,50 @@ pub(crate) unsafe extern "C" fn window_evaluate_media_query(raw_cx: *mut mozjs::
         String::new()
     };
 
-    let matches = evaluate_media_query(&query, get_window_width() as f32, get_window_height() as f32, get_device_pixel_ratio());
+    let matches = evaluate_media_query(&query, &MediaContext::current());
     args.rval().set(BooleanValue(matches));
     true
 }
 
-pub(crate) fn evaluate_media_query(query: &str, width: f32, height: f32, dpr: f32) -> bool {
+/// What media queries from script are evaluated against
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MediaContext {
+    /// Viewport size in CSS pixels
+    pub width: f32,
+    pub height: f32,
+    pub dpr: f32,
+    pub dark: bool,
+    pub reduced_motion: bool,
+}
+
+impl MediaContext {
+    /// Read the document's media context, using the stylist's device so matchMedia agrees with
+    /// the @media rules applied to the page
+    fn current() -> Self {
+        DOM_REF.with(|dom| {
+            if let Some(ref dom) = *dom.borrow() {
+                let dom = unsafe { &**dom };
+                let size = dom.stylist_device().au_viewport_size();
+                return Self {
+                    width: size.width.to_f32_px(),
+                    height: size.height.to_f32_px(),
+                    dpr: dom.viewport.scale(),
+                    dark: dom.viewport.color_scheme == ColorScheme::Dark,
+                    reduced_motion: dom.prefers_reduced_motion,
+                };
+            }
+            Self {
+                width: get_window_width() as f32,
+                height: get_window_height() as f32,
+                dpr: get_device_pixel_ratio(),
+                dark: false,
+                reduced_motion: false,
+            }
+        })
+    }
+}
+
+pub(crate) fn evaluate_media_query(query: &str, media: &MediaContext) -> bool {
     let trimmed = query.trim();
     if trimmed.is_empty() {
         return false;
@@ -666,7 +750,7 @@ pub(crate) fn evaluate_media_query(query: &str, width: f32, height: f32, dpr: f3
 
     split_media_query_list(trimmed)
         .into_iter()
-        .any(|part| evaluate_single_media_condition(part, width, height, dpr))
+        .any(|part| evaluate_single_media_condition(part, media))
 }
 
 fn split_media_query_list(query: &str) -> Vec<&str> {
@@ -697,7 +781,7 @@ fn split_media_query_list(query: &str) -> Vec<&str> {
     out
 }
 
-fn evaluate_single_media_condition(input: &str, width: f32, height: f32, dpr: f32) -> bool {
+fn evaluate_single_media_condition(input: &str, media: &MediaContext) -> bool {
     let mut remaining = input.trim().to_ascii_lowercase();
     if remaining.is_empty() {
         return false;
@@ -748,7 +832,7 @@ fn evaluate_single_media_condition(input: &str, width: f32, height: f32, dpr: f3
         };
 
         let feature = &remaining[1..closing];
-        let matches = evaluate_media_feature(feature.trim(), width, height, dpr);
+        let matches = evaluate_media_feature(feature.trim(), media);
         all_features_match &= matches;
 
         remaining = remaining[closing + 1..].to_string();
@@ -789,7 +873,8 @@ fn find_matching_paren(input: &str) -> Option<usize> {
     None
 }
 
-fn evaluate_media_feature(feature: &str, width: f32, height: f32, dpr: f32) -> bool {
+fn evaluate_media_feature(feature: &str, media: &MediaContext) -> bool {
+    let MediaContext { width, height, dpr, .. } = *media;
     if feature.is_empty() {
         return false;
     }
@@ -809,8 +894,10 @@ fn evaluate_media_feature(feature: &str, width: f32, height: f32, dpr: f32) -> b
             let orientation = if width >= height { "landscape" } else { "portrait" };
             orientation == v
         }
-        ("prefers-color-scheme", Some(v)) => v == "light",
-        ("prefers-reduced-motion", Some(v)) => v == "no-preference",
+        ("prefers-color-scheme", Some(v)) => v == if media.dark { "dark" } else { "light" },
+        ("prefers-color-scheme", None) => true,
+        ("prefers-reduced-motion", Some(v)) => v == if media.reduced_motion { "reduce" } else { "no-preference" },
+        ("prefers-reduced-motion", None) => media.reduced_motion,
         ("resolution", Some(v)) => parse_resolution_dppx(v).is_some_and(|target| approx_eq(dpr, target)),
         ("min-resolution", Some(v)) => parse_resolution_dppx(v).is_some_and(|target| dpr >= target),
         ("max-resolution", Some(v)) => parse_resolution_dppx(v).is_some_and(|target| dpr <= target),
@@ -0,0 +1,159 @@
+// window.open, and the popup blocker behind it
+use crate::js::bindings::dom_bindings::DOM_REF;
+use crate::js::helpers::{ToSafeCx, create_js_string, define_function, js_value_to_string};
+use crate::js::{JsResult, JsRuntime};
+use blitz_traits::navigation::NavigationOptions;
+use mozjs::jsapi::{CallArgs, JSContext};
+use mozjs::jsval::{BooleanValue, JSVal, NullValue};
+use std::cell::RefCell;
+use std::os::raw::c_uint;
+
+thread_local! {
+    /// URL of the page that opened this tab, when it let the tab reach it through `window.opener`
+    static OPENER: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Give the current document's `window.opener` a stand-in for the page at `url`, or null
+pub fn set_opener(url: Option<String>) {
+    OPENER.set(url);
+}
+
+/// Install window.open and window.opener. Windows open as tabs in the browser, and only when the
+/// user clicked or typed on the page just before or the site is allowed popups; otherwise open()
+/// returns null and the browser shows the popup as blocked. The page gets a stand-in for the new
+/// window, since it lives in another process, and so does a tab for the page that opened it.
+/// The noopener and noreferrer features open the tab without an opener and return null.
+pub fn setup_window_open(runtime: &mut JsRuntime) -> JsResult<()> {
+    runtime.do_with_jsapi(|cx, global| unsafe {
+        define_function(cx, global.get(), "__stokesOpenWindow", Some(stokes_open_window), 2)?;
+        define_function(cx, global.get(), "__stokesOpener", Some(stokes_opener), 0)
+    })?;
+
+    let script = r#"
+        (function() {
+            const root = typeof globalThis !== 'undefined' ? globalThis : window;
+            if (typeof root.__stokesOpenWindow !== 'function') {
+                return;
+            }
+
+            function syntaxError(message) {
+                if (typeof root.DOMException === 'function') {
+                    return new root.DOMException(message, 'SyntaxError');
+                }
+                return new SyntaxError(message);
+            }
+
+            function openedWindow(url) {
+                let closed = false;
+                return {
+                    get closed() { return closed; },
+                    opener: root,
+                    location: { href: url },
+                    close() { closed = true; },
+                    focus() {},
+                    blur() {},
+                    postMessage() {},
+                };
+            }
+
+            // A window feature is on when named alone or given yes, true or a non-zero number
+            function hasFeature(features, name) {
+                return String(features === undefined ? '' : features).split(/[\s,]+/).some(function(feature) {
+                    const parts = feature.split('=');
+                    if (parts[0].trim().toLowerCase() !== name) {
+                        return false;
+                    }
+                    const value = parts.length > 1 ? parts[1].trim().toLowerCase() : '';
+                    return value === '' || value === 'yes' || value === 'true' || (parseInt(value, 10) || 0) !== 0;
+                });
+            }
+
+            root.open = function(url, target, features) {
+                url = url === undefined || url === null || url === '' ? 'about:blank' : String(url);
+                target = target === undefined ? '_blank' : String(target);
+                // These name this window, which needs no new one
+                if (target === '_self' || target === '_parent' || target === '_top') {
+                    root.location.href = url;
+                    return root;
+                }
+                const noopener = hasFeature(features, 'noopener') || hasFeature(features, 'noreferrer');
+                const opened = root.__stokesOpenWindow(url, !noopener);
+                if (opened === null) {
+                    throw syntaxError("Failed to execute 'open' on 'Window': Unable to open a window with invalid URL '" + url + "'.");
+                }
+                return opened && !noopener ? openedWindow(url) : null;
+            };
+
+            // The opener lives in another tab's process, so this is a stand-in too. Pages may
+            // set window.opener to null to let go of it.
+            let opener;
+            let disowned = false;
+            Object.defineProperty(root, 'opener', {
+                configurable: true,
+                enumerable: true,
+                get() {
+                    if (disowned) {
+                        return null;
+                    }
+                    if (opener === undefined) {
+                        const url = root.__stokesOpener();
+                        opener = url === null ? null : {
+                            closed: false,
+                            opener: null,
+                            location: { href: url },
+                            close() {},
+                            focus() {},
+                            blur() {},
+                            postMessage() {},
+                        };
+                    }
+                    return opener;
+                },
+                set(value) {
+                    if (value === null) {
+                        disowned = true;
+                    }
+                },
+            });
+        })();
+    "#;
+
+    runtime.execute(script, false)
+}
+
+/// `__stokesOpenWindow(url, opener)`: open `url`, resolved against the document, in a new tab,
+/// which may reach this page through `window.opener` when `opener` is true. Returns false when
+/// the popup blocker stopped it and null when the URL is invalid.
+unsafe extern "C" fn stokes_open_window(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    let raw_url = if argc > 0 { js_value_to_string(safe_cx, *args.get(0)) } else { String::new() };
+    let opener = argc > 1 && args.get(1).is_boolean() && args.get(1).to_boolean();
+    let opened = DOM_REF.with(|dom| {
+        let dom = &mut *(*dom.borrow())?;
+        let url = dom.url.resolve_relative(&raw_url)?;
+        if !dom.may_open_popup(&url) {
+            return Some(false);
+        }
+        let options = NavigationOptions::new(url, String::from("text/plain"), dom.id());
+        dom.nav_provider.navigate_to_in_new_tab(options, opener);
+        Some(true)
+    });
+    match opened {
+        Some(opened) => args.rval().set(BooleanValue(opened)),
+        None => args.rval().set(NullValue()),
+    }
+    true
+}
+
+/// `__stokesOpener()`: the URL of the page that opened this tab, or null when there is none or
+/// it opened the tab with noopener
+unsafe extern "C" fn stokes_opener(raw_cx: *mut JSContext, argc: c_uint, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let safe_cx = &mut raw_cx.to_safe_cx();
+    match OPENER.with(|opener| opener.borrow().clone()) {
+        Some(url) => args.rval().set(create_js_string(safe_cx, &url)),
+        None => args.rval().set(NullValue()),
+    }
+    true
+}
@@ -257,7 +257,8 @@ pub fn setup_xhr(runtime: &mut JsRuntime) -> Result<(), String> {
         // Attach body for methods that carry one
         if (body !== undefined && body !== null &&
             this._method !== 'GET' && this._method !== 'HEAD') {
-            options.body = String(body);
+            // Blob, FormData and buffers are encoded by the fetch wrapper
+            options.body = typeof body === 'object' ? body : String(body);
         }
 
         // Fire loadstart
@@ -354,7 +355,7 @@ pub fn setup_xhr(runtime: &mut JsRuntime) -> Result<(), String> {
 "#;
 
     runtime.execute(script, false).map_err(|e| {
-        eprintln!("[JS] Warning: Failed to set up XMLHttpRequest: {}", e);
+        tracing::warn!("Failed to set up XMLHttpRequest: {}", e);
         e
     })?;
 
@@ -9,6 +9,7 @@ use std::ptr::NonNull;
 use mozjs::rust::ValueArray;
 use mozjs::context::JSContext as SafeJSContext;
 use mozjs::jsapi::{CallArgs, HandleValueArray, JSContext, JSNative, JSObject, JSPROP_ENUMERATE};
+use crate::js::bindings::registry;
 
 /// Create an empty JavaScript array
 pub unsafe fn create_empty_array(cx: &mut SafeJSContext) -> *mut JSObject {
@@ -196,13 +197,14 @@ pub unsafe fn get_node_id_from_this(cx: &mut SafeJSContext, args: &CallArgs) ->
         return None;
     }
 
-    if ptr_val.get().is_double() {
+    let node_id = if ptr_val.get().is_double() {
         Some(ptr_val.get().to_double() as usize)
     } else if ptr_val.get().is_int32() {
         Some(ptr_val.get().to_int32() as usize)
     } else {
         None
-    }
+    };
+    node_id.inspect(|&node_id| registry::debug_check_node_id(node_id))
 }
 
 /// Define a JavaScript property with both getter and setter using `Object.defineProperty`.
@@ -419,6 +421,33 @@ pub unsafe fn define_js_property_getter(
     }
 }
 
+/// Read a member of a JS dictionary argument (e.g. an options object). Returns `None` when the
+/// value is not an object or the member is undefined.
+pub unsafe fn get_dictionary_member(cx: &mut SafeJSContext, dict: JSVal, name: &str) -> Option<JSVal> {
+    if !dict.is_object() {
+        return None;
+    }
+    let raw_cx = cx.raw_cx();
+    rooted!(in(raw_cx) let obj = dict.to_object());
+    rooted!(in(raw_cx) let mut val = UndefinedValue());
+    let cname = std::ffi::CString::new(name).ok()?;
+    if !JS_GetProperty(cx, obj.handle().into(), cname.as_ptr(), val.handle_mut().into()) || val.get().is_undefined() {
+        return None;
+    }
+    Some(val.get())
+}
+
+/// Convert a numeric JS value to f64, `None` for non-numbers
+pub fn js_value_to_f64(val: JSVal) -> Option<f64> {
+    if val.is_int32() {
+        Some(val.to_int32() as f64)
+    } else if val.is_double() {
+        Some(val.to_double())
+    } else {
+        None
+    }
+}
+
 /// Get the node ID from an arbitrary JS value (e.g. an argument object) by reading its `__nodeId` property.
 pub unsafe fn get_node_id_from_value(cx: &mut SafeJSContext, val: JSVal) -> Option<usize> {
     let raw_cx = cx.raw_cx();
@@ -434,13 +463,14 @@ pub unsafe fn get_node_id_from_value(cx: &mut SafeJSContext, val: JSVal) -> Opti
         return None;
     }
 
-    if ptr_val.get().is_double() {
+    let node_id = if ptr_val.get().is_double() {
         Some(ptr_val.get().to_double() as usize)
     } else if ptr_val.get().is_int32() {
         Some(ptr_val.get().to_int32() as usize)
     } else {
         None
-    }
+    };
+    node_id.inspect(|&node_id| registry::debug_check_node_id(node_id))
 }
 
 /// Convert JavaScript camelCase property name to CSS kebab-case
@@ -592,7 +622,7 @@ unsafe extern "C" fn create_stubbed_property_getter(
     let raw_cx = safe_cx.raw_cx();
 
     // Log the warning
-    eprintln!("[JS][stubbed-property-accessed] Accessing a stubbed/polyfill property. This property may not be fully implemented.");
+    tracing::debug!("Accessing a stubbed/polyfill property. This property may not be fully implemented.");
 
     // Return an empty object
     rooted!(in(raw_cx) let empty_obj = JS_NewPlainObject(safe_cx));
@@ -109,6 +109,15 @@ pub(crate) unsafe extern "C" fn enqueue_promise_job(
     result
 }
 
+/// Queue a plain function as a microtask (`queueMicrotask`). It shares the promise job queue,
+/// so it runs in order with promise reactions at the next checkpoint.
+pub(crate) unsafe fn enqueue_microtask(cx: &mut JSContext, callback: *mut JSObject) {
+    let cb = PromiseJobCallback::new(cx, callback);
+    PROMISE_JOB_QUEUE.with(|queue| {
+        queue.borrow_mut().push_back(cb);
+    });
+}
+
 /// Run one full microtask checkpoint.
 ///
 /// This drains queued promise jobs and then reports any rejection transitions.
@@ -7,7 +7,9 @@ mod selectors;
 pub(crate) mod bindings;
 mod jsapi;
 
-pub use bindings::alert_callback::set_alert_callback;
+pub use bindings::dialog_callback::{set_dialog_callback, JsDialog};
+pub use bindings::extension_api::{set_allowed_extensions, ExtensionCall};
+pub use bindings::window_open::set_opener;
 pub use runtime::JsRuntime;
 /// JavaScript execution result
 pub type JsResult<T> = Result<T, String>;
@@ -72,7 +72,7 @@ impl DefaultModuleLoader {
 
         let base = referencing_url
             .and_then(|raw| Url::parse(raw).ok())
-            .or_else(|| unsafe { Url::parse((&*dom).url.to_string().as_str()).ok() })
+            .or_else(|| unsafe { Some((&*dom).url.base_url().clone()) })
             .ok_or_else(|| "Unable to resolve module base URL".to_string())?;
 
         base.join(specifier)
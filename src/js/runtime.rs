@@ -13,13 +13,13 @@ use mozjs::context::{JSContext, RawJSContext};
 use mozjs::conversions::jsstr_to_string;
 use mozjs::gc::HandleObject;
 use mozjs::glue::JobQueueTraps;
-use mozjs::jsapi::{CallArgs, JSContext as ApiJSContext, SetModuleDynamicImportHook, SetModuleMetadataHook, SetModuleResolveHook, SetScriptPrivate, SourceText};
+use mozjs::jsapi::{CallArgs, GCReason, JSContext as ApiJSContext, SetModuleDynamicImportHook, SetModuleMetadataHook, SetModuleResolveHook, SetScriptPrivate, SourceText};
 use mozjs::jsapi::{Heap, JSObject, JSScript, OnNewGlobalHookOption};
 // JavaScript runtime management using Mozilla's SpiderMonkey (mozjs)
 use mozjs::jsval::{ObjectValue, PrivateValue, StringValue, UndefinedValue};
 use mozjs::panic::{maybe_resume_unwind};
 use mozjs::rooted;
-use mozjs::rust::wrappers2::{Compile1, GetModuleNamespace, JS_ClearPendingException, JS_DefineProperty, JS_ExecuteScript, JS_GetPendingException, JS_GetScriptPrivate, JS_IsExceptionPending, JS_NewGlobalObject, JS_NewUCStringCopyN, JS_SetPendingException, JS_ValueToSource, ModuleEvaluate, ModuleLink, RejectPromise, ResolvePromise};
+use mozjs::rust::wrappers2::{Compile1, GetModuleNamespace, JS_ClearPendingException, JS_GC, JS_DefineProperty, JS_ExecuteScript, JS_GetPendingException, JS_GetScriptPrivate, JS_IsExceptionPending, JS_NewGlobalObject, JS_NewUCStringCopyN, JS_SetPendingException, JS_ValueToSource, ModuleEvaluate, ModuleLink, RejectPromise, ResolvePromise};
 use mozjs::rust::{transform_str_to_source_text, CompileOptionsWrapper, JSEngine, MutableHandleValue, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
 use std::cell::RefCell;
 use std::collections::HashMap;
@@ -33,7 +33,9 @@ use tracing::error;
 use url::Url;
 use crate::js::bindings::initialize_bindings;
 use crate::js::bindings::event_listeners::clear_all_listeners;
-use crate::js::bindings::element_bindings::clear_element_wrapper_cache;
+use crate::js::bindings::websocket::close_all_websockets;
+use crate::js::bindings::blob::revoke_all_object_urls;
+use crate::js::bindings::registry::{self, clear_node_wrappers};
 use crate::js::helpers::ToSafeCx;
 use crate::js::runtime_context::RuntimeContext;
 
@@ -121,6 +123,7 @@ impl JsRuntime {
         let mut runtime = Runtime::new(
             ENGINE_HANDLER_PRODUCER.exe(|| ENGINE.with(|engine| engine.borrow().handle()))
         );
+        unsafe { registry::install_gc_hooks(runtime.cx().raw_cx()) };
 
         // Create and set up timer manager
         let timer_manager = Rc::new(TimerManager::new());
@@ -177,7 +180,9 @@ impl JsRuntime {
         self.timer_manager.clear_all();
         clear_all_listeners();
         clear_pending_jobs_for_navigation();
-        clear_element_wrapper_cache();
+        close_all_websockets();
+        revoke_all_object_urls();
+        clear_node_wrappers();
         self.module_loader.clear();
 
         // Create a new global so top-level lexical bindings from the previous
@@ -188,9 +193,18 @@ impl JsRuntime {
 
         // Refresh thread-local runtime pointer after navigation reset.
         RUNTIME.with(|cell| *cell.borrow_mut() = Some(self as *mut JsRuntime));
+
+        // The previous document's global and everything reachable only from it are garbage now;
+        // free them before the next page starts allocating rather than whenever a GC next runs
+        self.collect_garbage();
         Ok(())
     }
 
+    /// Run a full garbage collection
+    pub fn collect_garbage(&mut self) {
+        unsafe { JS_GC(self.runtime.cx(), GCReason::API) };
+    }
+
     /// Get the raw JSContext pointer
     pub fn cx(&mut self) -> &mut JSContext {
         self.runtime.cx()
@@ -203,6 +217,7 @@ impl JsRuntime {
 
     /// Execute JavaScript code
     pub fn execute(&mut self, code: &str, print_error: bool) -> JsResult<()> {
+        let _span = tracing::debug_span!("script").entered();
         let cx = self.runtime.cx();
         let raw_cx = unsafe { cx.raw_cx() };
         let global_ptr = self.global.get();
@@ -335,7 +350,7 @@ impl JsRuntime {
                 Ok(())
             },
             Err(e) => {
-                eprintln!("Script execution error: {}", e);
+                error!("Script execution error: {}", e);
                 Err(e)
             }
         }
@@ -343,6 +358,7 @@ impl JsRuntime {
 
     /// Execute JavaScript that originated from `<script type=\"module\">`.
     pub fn execute_module_script(&mut self, code: &str, source_url: Option<&str>, print_eval_error: bool) -> JsResult<()> {
+        let _span = tracing::debug_span!("module").entered();
         let source_name = self
             .module_loader
             .effective_module_source_url(source_url, self.context.dom_ptr());
@@ -372,7 +388,7 @@ impl JsRuntime {
             ) {
                 Ok(module) => module,
                 Err(msg) => {
-                    eprintln!("Module script execution error: {}", msg);
+                    error!("Module script execution error: {}", msg);
                     return Err(msg);
                 }
             };
@@ -381,20 +397,20 @@ impl JsRuntime {
 
             if module.get().is_null() {
                 let msg = "JavaScript MODULE COMPILE error".to_string();
-                eprintln!("Module script execution error: {}", msg);
+                error!("Module script execution error: {}", msg);
                 return Err(msg);
             }
 
             if !ModuleLink(cx, module.handle().into()) {
                 let msg = extract_module_exception(cx, raw_cx, "JavaScript MODULE INSTANTIATE error", code, print_eval_error);
-                eprintln!("Module script execution error: {}", msg);
+                error!("Module script execution error: {}", msg);
                 return Err(msg);
             }
 
             rooted!(in(raw_cx) let mut eval_result = UndefinedValue());
             if !ModuleEvaluate(cx, module.handle().into(), eval_result.handle_mut().into()) {
                 let msg = extract_module_exception(cx, raw_cx, "JavaScript MODULE EVAL error", code, print_eval_error);
-                eprintln!("Module script execution error: {}", msg);
+                error!("Module script execution error: {}", msg);
                 return Err(msg);
             }
 
@@ -410,6 +426,7 @@ impl JsRuntime {
     pub fn run_pending_jobs(&mut self) {
         use crate::js::jsapi::promise::perform_microtask_checkpoint;
 
+        let _span = tracing::debug_span!("microtasks").entered();
         self.do_with_jsapi(|cx, _global| {
             let executed = perform_microtask_checkpoint(cx);
             if executed > 0 {
@@ -436,6 +453,7 @@ impl JsRuntime {
     /// Process pending timers (setTimeout/setInterval callbacks)
     /// Returns true if any timers were executed
     pub fn process_timers(&mut self) -> bool {
+        let _span = tracing::debug_span!("timers").entered();
         let timer_manager = self.timer_manager.clone();
         timer_manager.process_timers(self)
     }
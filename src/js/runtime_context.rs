@@ -67,7 +67,7 @@ pub(crate) fn current_document_base_url() -> Option<String> {
             return None;
         }
         let dom = unsafe { &*dom_ptr };
-        Some(dom.url.to_string())
+        Some(dom.url.base_url().to_string())
     })?
 }
 
@@ -0,0 +1,399 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tracing::warn;
+use winit::keyboard::{Key, ModifiersState, NamedKey};
+
+const KEYBINDINGS_FILE: &str = "keybindings.json";
+
+/// A browser-level command a keyboard shortcut can run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    NewTab,
+    NewWindow,
+    CloseTab,
+    ReopenClosedTab,
+    NextTab,
+    PreviousTab,
+    MoveTabLeft,
+    MoveTabRight,
+    /// Switch to the tab at this (0-based) position
+    SelectTab(usize),
+    SelectLastTab,
+    FocusAddressBar,
+    Reload,
+    GoBack,
+    GoForward,
+    GoHome,
+    BookmarkPage,
+    SavePage,
+    Screenshot,
+    FullPageScreenshot,
+    ToggleFullscreen,
+    OpenSettings,
+    /// Log and show what the active tab's process holds, for tracking down leaks
+    ResourceReport,
+    /// Choose network conditions to emulate in the active tab
+    EmulateNetworkConditions,
+    /// Open the find bar, and step to the next or previous match of what it searches for
+    FindInPage,
+    FindNext,
+    FindPrevious,
+    /// Change the active tab's page zoom, which lays the page out again at the new size
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+}
+
+impl Command {
+    /// Parse the name used for the command in keybindings.json
+    pub fn from_name(name: &str) -> Option<Self> {
+        let command = match name {
+            "new_tab" => Self::NewTab,
+            "new_window" => Self::NewWindow,
+            "close_tab" => Self::CloseTab,
+            "reopen_closed_tab" => Self::ReopenClosedTab,
+            "next_tab" => Self::NextTab,
+            "previous_tab" => Self::PreviousTab,
+            "move_tab_left" => Self::MoveTabLeft,
+            "move_tab_right" => Self::MoveTabRight,
+            "select_last_tab" => Self::SelectLastTab,
+            "focus_address_bar" => Self::FocusAddressBar,
+            "reload" => Self::Reload,
+            "go_back" => Self::GoBack,
+            "go_forward" => Self::GoForward,
+            "go_home" => Self::GoHome,
+            "bookmark_page" => Self::BookmarkPage,
+            "save_page" => Self::SavePage,
+            "screenshot" => Self::Screenshot,
+            "full_page_screenshot" => Self::FullPageScreenshot,
+            "toggle_fullscreen" => Self::ToggleFullscreen,
+            "open_settings" => Self::OpenSettings,
+            "resource_report" => Self::ResourceReport,
+            "emulate_network_conditions" => Self::EmulateNetworkConditions,
+            "find_in_page" => Self::FindInPage,
+            "find_next" => Self::FindNext,
+            "find_previous" => Self::FindPrevious,
+            "zoom_in" => Self::ZoomIn,
+            "zoom_out" => Self::ZoomOut,
+            "reset_zoom" => Self::ResetZoom,
+            _ => {
+                // select_tab_1 .. select_tab_8
+                let position = name.strip_prefix("select_tab_")?.parse::<usize>().ok()?;
+                return (1..=8).contains(&position).then(|| Self::SelectTab(position - 1));
+            }
+        };
+        Some(command)
+    }
+}
+
+/// The key of a chord, without its modifiers
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ChordKey {
+    /// A printable key, lowercased so Shift does not change it
+    Character(String),
+    Named(NamedKey),
+}
+
+/// A key together with the modifiers that must be held, and no others
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chord {
+    key: ChordKey,
+    modifiers: ModifiersState,
+}
+
+/// The platform's main shortcut modifier: Cmd on macOS, Ctrl elsewhere. Written `Mod` in chords.
+#[cfg(target_os = "macos")]
+const ACTION_MODIFIER: ModifiersState = ModifiersState::META;
+#[cfg(not(target_os = "macos"))]
+const ACTION_MODIFIER: ModifiersState = ModifiersState::CONTROL;
+
+const NAMED_KEYS: &[(&str, NamedKey)] = &[
+    ("Tab", NamedKey::Tab),
+    ("Enter", NamedKey::Enter),
+    ("Escape", NamedKey::Escape),
+    ("Space", NamedKey::Space),
+    ("Backspace", NamedKey::Backspace),
+    ("Delete", NamedKey::Delete),
+    ("Home", NamedKey::Home),
+    ("End", NamedKey::End),
+    ("PageUp", NamedKey::PageUp),
+    ("PageDown", NamedKey::PageDown),
+    ("Left", NamedKey::ArrowLeft),
+    ("Right", NamedKey::ArrowRight),
+    ("Up", NamedKey::ArrowUp),
+    ("Down", NamedKey::ArrowDown),
+    ("BrowserBack", NamedKey::BrowserBack),
+    ("BrowserForward", NamedKey::BrowserForward),
+    ("BrowserRefresh", NamedKey::BrowserRefresh),
+    ("BrowserHome", NamedKey::BrowserHome),
+    ("F1", NamedKey::F1),
+    ("F2", NamedKey::F2),
+    ("F3", NamedKey::F3),
+    ("F4", NamedKey::F4),
+    ("F5", NamedKey::F5),
+    ("F6", NamedKey::F6),
+    ("F7", NamedKey::F7),
+    ("F8", NamedKey::F8),
+    ("F9", NamedKey::F9),
+    ("F10", NamedKey::F10),
+    ("F11", NamedKey::F11),
+    ("F12", NamedKey::F12),
+];
+
+impl Chord {
+    /// Parse a chord like "Mod+Shift+T", "Alt+Left" or "F5". Modifier names are `Mod`, `Ctrl`,
+    /// `Shift`, `Alt` and `Meta` (Cmd/Super), in any order and case.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        // "Mod++" binds the plus key
+        if text.ends_with("++") {
+            parts.truncate(parts.len().saturating_sub(2));
+            parts.push("+");
+        }
+        let key = parts.pop().filter(|key| !key.is_empty())?;
+
+        let mut modifiers = ModifiersState::empty();
+        for part in parts {
+            modifiers |= match part.to_ascii_lowercase().as_str() {
+                "mod" => ACTION_MODIFIER,
+                "ctrl" | "control" => ModifiersState::CONTROL,
+                "shift" => ModifiersState::SHIFT,
+                "alt" | "option" => ModifiersState::ALT,
+                "meta" | "cmd" | "super" => ModifiersState::META,
+                _ => return None,
+            };
+        }
+
+        let key = match NAMED_KEYS.iter().find(|(name, _)| name.eq_ignore_ascii_case(key)) {
+            Some((_, named)) => ChordKey::Named(*named),
+            None if key.chars().count() == 1 => ChordKey::Character(key.to_lowercase()),
+            None => return None,
+        };
+        Some(Self { key, modifiers })
+    }
+
+    fn matches(&self, key: &Key, modifiers: ModifiersState) -> bool {
+        if self.modifiers != modifiers {
+            return false;
+        }
+        match (&self.key, key) {
+            (ChordKey::Character(expected), Key::Character(text)) => text.to_lowercase() == *expected,
+            (ChordKey::Named(expected), Key::Named(named)) => expected == named,
+            _ => false,
+        }
+    }
+}
+
+/// User changes to the default bindings, read from keybindings.json in the config directory:
+///
+/// ```json
+/// { "bindings": { "Mod+Shift+B": "bookmark_page", "Alt+Home": "go_home", "Mod+N": null } }
+/// ```
+///
+/// A command name binds the chord (replacing its default), `null` unbinds it.
+#[derive(Debug, Default, Deserialize)]
+struct KeybindingsFile {
+    #[serde(default)]
+    bindings: BTreeMap<String, Option<String>>,
+}
+
+/// Maps key chords to browser commands
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Chord, Command)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut bindings = Vec::new();
+        let mut bind = |chord: &str, command: Command| {
+            bindings.push((Chord::parse(chord).expect("default chords are valid"), command));
+        };
+
+        bind("Mod+T", Command::NewTab);
+        bind("Mod+N", Command::NewWindow);
+        bind("Mod+W", Command::CloseTab);
+        bind("Mod+Shift+T", Command::ReopenClosedTab);
+        bind("Ctrl+Tab", Command::NextTab);
+        bind("Ctrl+Shift+Tab", Command::PreviousTab);
+        bind("Mod+PageDown", Command::NextTab);
+        bind("Mod+PageUp", Command::PreviousTab);
+        bind("Mod+Shift+PageUp", Command::MoveTabLeft);
+        bind("Mod+Shift+PageDown", Command::MoveTabRight);
+        for position in 1..=8 {
+            bind(&format!("Mod+{position}"), Command::SelectTab(position - 1));
+        }
+        bind("Mod+9", Command::SelectLastTab);
+        bind("Mod+L", Command::FocusAddressBar);
+        bind("F6", Command::FocusAddressBar);
+        bind("Mod+R", Command::Reload);
+        bind("F5", Command::Reload);
+        bind("BrowserRefresh", Command::Reload);
+        bind("BrowserBack", Command::GoBack);
+        bind("BrowserForward", Command::GoForward);
+        bind("BrowserHome", Command::GoHome);
+        bind("Alt+Home", Command::GoHome);
+        bind("Mod+D", Command::BookmarkPage);
+        bind("Mod+S", Command::SavePage);
+        bind("Mod+Shift+S", Command::Screenshot);
+        bind("Mod+Alt+Shift+S", Command::FullPageScreenshot);
+        bind("F11", Command::ToggleFullscreen);
+        bind("Mod+F", Command::FindInPage);
+        bind("Mod+G", Command::FindNext);
+        bind("F3", Command::FindNext);
+        bind("Mod+Shift+G", Command::FindPrevious);
+        bind("Shift+F3", Command::FindPrevious);
+        bind("Mod+=", Command::ZoomIn);
+        bind("Mod++", Command::ZoomIn);
+        bind("Mod+Shift++", Command::ZoomIn);
+        bind("Mod+-", Command::ZoomOut);
+        bind("Mod+0", Command::ResetZoom);
+        // A debugging aid; release builds can still bind it in keybindings.json
+        if cfg!(debug_assertions) {
+            bind("Mod+Alt+Shift+R", Command::ResourceReport);
+        }
+
+        // History navigation and a few others follow each platform's convention
+        if cfg!(target_os = "macos") {
+            bind("Mod+[", Command::GoBack);
+            bind("Mod+]", Command::GoForward);
+            bind("Mod+Left", Command::GoBack);
+            bind("Mod+Right", Command::GoForward);
+            bind("Mod+,", Command::OpenSettings);
+        } else {
+            bind("Alt+Left", Command::GoBack);
+            bind("Alt+Right", Command::GoForward);
+            bind("Mod+F4", Command::CloseTab);
+            bind("Alt+D", Command::FocusAddressBar);
+        }
+
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// The default bindings with the user's keybindings.json applied
+    pub fn load() -> Self {
+        let mut keymap = Self::default();
+        let Ok(contents) = std::fs::read_to_string(keybindings_file_path()) else {
+            return keymap;
+        };
+        match serde_json::from_str::<KeybindingsFile>(&contents) {
+            Ok(file) => keymap.apply_overrides(&file.bindings),
+            Err(e) => warn!("Ignoring {KEYBINDINGS_FILE}: {e}"),
+        }
+        keymap
+    }
+
+    fn apply_overrides(&mut self, overrides: &BTreeMap<String, Option<String>>) {
+        for (chord_text, command_name) in overrides {
+            let Some(chord) = Chord::parse(chord_text) else {
+                warn!("Ignoring key binding with an unknown chord: {chord_text}");
+                continue;
+            };
+            let command = match command_name.as_deref().map(Command::from_name) {
+                Some(None) => {
+                    warn!("Ignoring key binding for {chord_text}: unknown command {:?}", command_name);
+                    continue;
+                }
+                Some(Some(command)) => Some(command),
+                None => None,
+            };
+
+            self.bindings.retain(|(bound, _)| *bound != chord);
+            if let Some(command) = command {
+                self.bindings.push((chord, command));
+            }
+        }
+    }
+
+    /// The command bound to `key` pressed with exactly `modifiers`
+    pub fn lookup(&self, key: &Key, modifiers: ModifiersState) -> Option<Command> {
+        self.bindings
+            .iter()
+            .find(|(chord, _)| chord.matches(key, modifiers))
+            .map(|(_, command)| *command)
+    }
+}
+
+fn keybindings_file_path() -> PathBuf {
+    crate::profile::data_dir().join(KEYBINDINGS_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Chord, Command, Keymap, ACTION_MODIFIER};
+    use std::collections::BTreeMap;
+    use winit::keyboard::{Key, ModifiersState, NamedKey};
+
+    fn character(text: &str) -> Key {
+        Key::Character(text.into())
+    }
+
+    #[test]
+    fn chords_need_exactly_their_modifiers() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.lookup(&character("t"), ACTION_MODIFIER), Some(Command::NewTab));
+        assert_eq!(
+            keymap.lookup(&character("T"), ACTION_MODIFIER | ModifiersState::SHIFT),
+            Some(Command::ReopenClosedTab)
+        );
+        assert_eq!(keymap.lookup(&character("t"), ACTION_MODIFIER | ModifiersState::ALT), None);
+        assert_eq!(keymap.lookup(&character("t"), ModifiersState::empty()), None);
+    }
+
+    #[test]
+    fn standard_navigation_shortcuts_are_bound() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.lookup(&character("l"), ACTION_MODIFIER), Some(Command::FocusAddressBar));
+        assert_eq!(keymap.lookup(&character("w"), ACTION_MODIFIER), Some(Command::CloseTab));
+        #[cfg(not(target_os = "macos"))]
+        {
+            assert_eq!(keymap.lookup(&Key::Named(NamedKey::ArrowLeft), ModifiersState::ALT), Some(Command::GoBack));
+            assert_eq!(keymap.lookup(&Key::Named(NamedKey::ArrowRight), ModifiersState::ALT), Some(Command::GoForward));
+        }
+        assert_eq!(keymap.lookup(&character("3"), ACTION_MODIFIER), Some(Command::SelectTab(2)));
+        assert_eq!(keymap.lookup(&character("f"), ACTION_MODIFIER), Some(Command::FindInPage));
+        assert_eq!(
+            keymap.lookup(&Key::Named(NamedKey::F3), ModifiersState::SHIFT),
+            Some(Command::FindPrevious)
+        );
+    }
+
+    #[test]
+    fn overrides_rebind_and_unbind_chords() {
+        let mut keymap = Keymap::default();
+        let overrides = BTreeMap::from([
+            ("Mod+Shift+B".to_string(), Some("bookmark_page".to_string())),
+            ("Mod+N".to_string(), None),
+            ("Mod+W".to_string(), Some("reload".to_string())),
+            ("Hyper+Q".to_string(), Some("new_tab".to_string())),
+            ("Mod+Q".to_string(), Some("launch_rockets".to_string())),
+        ]);
+        keymap.apply_overrides(&overrides);
+
+        assert_eq!(
+            keymap.lookup(&character("B"), ACTION_MODIFIER | ModifiersState::SHIFT),
+            Some(Command::BookmarkPage)
+        );
+        assert_eq!(keymap.lookup(&character("n"), ACTION_MODIFIER), None);
+        assert_eq!(keymap.lookup(&character("w"), ACTION_MODIFIER), Some(Command::Reload));
+        assert_eq!(keymap.lookup(&character("q"), ACTION_MODIFIER), None);
+    }
+
+    #[test]
+    fn parses_chords_and_command_names() {
+        assert_eq!(Chord::parse("mod+shift+t"), Chord::parse("Shift+Mod+T"));
+        assert!(Chord::parse("Mod++").is_some());
+        assert!(Chord::parse("Mod+").is_none());
+        assert!(Chord::parse("Mod+Bogus").is_none());
+        assert_eq!(Command::from_name("select_tab_4"), Some(Command::SelectTab(3)));
+        assert_eq!(Command::from_name("select_tab_9"), None);
+        assert_eq!(Command::from_name("resource_report"), Some(Command::ResourceReport));
+        assert_eq!(Command::from_name("emulate_network_conditions"), Some(Command::EmulateNetworkConditions));
+        assert_eq!(Command::from_name("zoom_in"), Some(Command::ZoomIn));
+        assert_eq!(Command::from_name("reset_zoom"), Some(Command::ResetZoom));
+        assert_eq!(Command::from_name("find_previous"), Some(Command::FindPrevious));
+    }
+}
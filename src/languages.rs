@@ -0,0 +1,276 @@
+// The user's preferred languages, which pages see as navigator.language(s) and servers as the
+// Accept-Language header. The parent process reads them from the desktop's locale settings, or
+// takes the list the user typed into the settings panel, and pushes them to every tab.
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{LazyLock, RwLock};
+
+const LANGUAGE_SETTINGS_FILE: &str = "languages.json";
+
+/// Used when the desktop names no language we can read
+const DEFAULT_LANGUAGE: &str = "en-US";
+
+/// Language settings persisted next to the bookmarks
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LanguageSettings {
+    /// Language tags, most preferred first, that replace the desktop's
+    #[serde(default)]
+    pub manual_override: Option<Vec<String>>,
+}
+
+impl LanguageSettings {
+    pub fn load_from_disk() -> Self {
+        std::fs::read_to_string(language_settings_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save_to_disk(&self) {
+        let path = language_settings_path();
+        let Ok(json) = serde_json::to_string_pretty(self) else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::write(path, json);
+    }
+
+    /// The languages these settings ask for
+    pub fn languages(&self) -> Vec<String> {
+        match &self.manual_override {
+            Some(languages) if !languages.is_empty() => languages.clone(),
+            _ => detect(),
+        }
+    }
+}
+
+/// The desktop's preferred languages as BCP 47 tags, most preferred first. Never empty.
+pub fn detect() -> Vec<String> {
+    let mut languages = Vec::new();
+    for tag in system_languages().iter().filter_map(|tag| normalize_tag(tag)) {
+        if !languages.contains(&tag) {
+            languages.push(tag);
+        }
+    }
+    if languages.is_empty() {
+        languages.push(DEFAULT_LANGUAGE.to_string());
+    }
+    languages
+}
+
+/// Parse a comma-separated list of language tags as typed into the settings prompt. None when
+/// any of them isn't a language tag.
+pub fn parse_language_list(input: &str) -> Option<Vec<String>> {
+    let mut languages = Vec::new();
+    for tag in input.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+        let tag = normalize_tag(tag)?;
+        if !languages.contains(&tag) {
+            languages.push(tag);
+        }
+    }
+    Some(languages)
+}
+
+/// Turn a locale name such as `en_GB.UTF-8` or `zh-hans-cn` into a BCP 47 tag with the usual
+/// casing (`en-GB`, `zh-Hans-CN`). None for the C/POSIX locale and for anything that isn't a tag.
+fn normalize_tag(locale: &str) -> Option<String> {
+    // POSIX names carry a codeset after '.' and a modifier after '@'
+    let locale = locale.trim().split(['.', '@']).next()?;
+    if locale.is_empty() || locale == "C" || locale == "POSIX" {
+        return None;
+    }
+
+    let mut subtags = Vec::new();
+    for (index, subtag) in locale.split(['-', '_']).enumerate() {
+        if subtag.is_empty() || subtag.len() > 8 || !subtag.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        let is_alpha = subtag.chars().all(|c| c.is_ascii_alphabetic());
+        let subtag = if index == 0 {
+            if !is_alpha || subtag.len() < 2 {
+                return None;
+            }
+            subtag.to_ascii_lowercase()
+        } else if is_alpha && subtag.len() == 4 {
+            // Script
+            subtag[..1].to_ascii_uppercase() + &subtag[1..].to_ascii_lowercase()
+        } else if (is_alpha && subtag.len() == 2) || (subtag.len() == 3 && subtag.chars().all(|c| c.is_ascii_digit())) {
+            // Region
+            subtag.to_ascii_uppercase()
+        } else {
+            subtag.to_ascii_lowercase()
+        };
+        subtags.push(subtag);
+    }
+    Some(subtags.join("-"))
+}
+
+/// The Accept-Language value for `languages`: each tag followed by its bare language when the list
+/// doesn't name that separately, weighted down by 0.1 a step the way Chrome does
+pub fn accept_language(languages: &[String]) -> String {
+    let mut expanded: Vec<&str> = Vec::new();
+    for tag in languages {
+        if !expanded.contains(&tag.as_str()) {
+            expanded.push(tag);
+        }
+        let base = tag.split('-').next().unwrap_or(tag);
+        if base != tag && !languages.iter().any(|other| other == base) && !expanded.contains(&base) {
+            expanded.push(base);
+        }
+    }
+
+    let mut header = String::new();
+    for (index, tag) in expanded.iter().enumerate() {
+        if index == 0 {
+            header.push_str(tag);
+        } else {
+            let quality = (10 - index.min(9)) as f32 / 10.0;
+            header.push_str(&format!(",{tag};q={quality:.1}"));
+        }
+    }
+    header
+}
+
+/// This process's languages, from the settings in the browser and the last `SetLanguages`
+/// message in a tab
+static LANGUAGES: LazyLock<RwLock<Vec<String>>> = LazyLock::new(|| RwLock::new(vec![DEFAULT_LANGUAGE.to_string()]));
+
+pub fn set(languages: Vec<String>) {
+    if languages.is_empty() {
+        return;
+    }
+    if let Ok(mut current) = LANGUAGES.write() {
+        *current = languages;
+    }
+}
+
+pub fn current() -> Vec<String> {
+    LANGUAGES.read().map(|languages| languages.clone()).unwrap_or_else(|_| vec![DEFAULT_LANGUAGE.to_string()])
+}
+
+/// The Accept-Language header line to send with every request
+pub fn accept_language_header() -> String {
+    format!("Accept-Language: {}", accept_language(&current()))
+}
+
+fn language_settings_path() -> PathBuf {
+    crate::profile::data_dir().join(LANGUAGE_SETTINGS_FILE)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Linux and other Unix desktops
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn system_languages() -> Vec<String> {
+    let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+    let locale = var("LC_ALL").or_else(|| var("LC_MESSAGES")).or_else(|| var("LANG"));
+    posix_languages(var("LANGUAGE").as_deref(), locale.as_deref())
+}
+
+/// gettext's `LANGUAGE` priority list, which it ignores under the C locale, then the locale itself
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn posix_languages(language: Option<&str>, locale: Option<&str>) -> Vec<String> {
+    let mut languages = Vec::new();
+    if let Some(language) = language.filter(|_| locale.and_then(normalize_tag).is_some()) {
+        languages.extend(language.split(':').map(str::to_string));
+    }
+    languages.extend(locale.map(str::to_string));
+    languages
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// macOS
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(target_os = "macos")]
+fn system_languages() -> Vec<String> {
+    use std::process::Command;
+
+    // A property list array such as ( "en-GB", fr )
+    let Ok(output) = Command::new("defaults").args(["read", "-g", "AppleLanguages"]).output() else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim().trim_end_matches(',').trim_matches('"').to_string())
+        .filter(|line| !line.is_empty() && line != "(" && line != ")")
+        .collect()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Windows
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(target_os = "windows")]
+fn registry_value(key: &str, value: &str) -> Option<String> {
+    use std::process::Command;
+
+    let output = Command::new("reg").args(["query", key, "/v", value]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().find(|line| line.contains("REG_"))?;
+    Some(line.split_whitespace().last()?.to_string())
+}
+
+#[cfg(target_os = "windows")]
+fn system_languages() -> Vec<String> {
+    // The display language list from Settings, which reg prints joined by a literal "\0"
+    if let Some(list) = registry_value(r"HKCU\Control Panel\International\User Profile", "Languages") {
+        return list.split("\\0").map(str::to_string).collect();
+    }
+    registry_value(r"HKCU\Control Panel\International", "LocaleName").into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{accept_language, normalize_tag, parse_language_list};
+
+    #[test]
+    fn normalizes_locale_names() {
+        assert_eq!(normalize_tag("en_GB.UTF-8").as_deref(), Some("en-GB"));
+        assert_eq!(normalize_tag("de_DE@euro").as_deref(), Some("de-DE"));
+        assert_eq!(normalize_tag("zh-hans-cn").as_deref(), Some("zh-Hans-CN"));
+        assert_eq!(normalize_tag("es-419").as_deref(), Some("es-419"));
+        assert_eq!(normalize_tag("FR").as_deref(), Some("fr"));
+        assert_eq!(normalize_tag("C.UTF-8"), None);
+        assert_eq!(normalize_tag("POSIX"), None);
+        assert_eq!(normalize_tag("en GB"), None);
+        assert_eq!(normalize_tag("1en"), None);
+    }
+
+    #[test]
+    fn accept_language_adds_base_languages() {
+        let languages = vec!["en-GB".to_string(), "fr".to_string()];
+        assert_eq!(accept_language(&languages), "en-GB,en;q=0.9,fr;q=0.8");
+        let languages = vec!["en-US".to_string(), "de-DE".to_string(), "en".to_string()];
+        assert_eq!(accept_language(&languages), "en-US,de-DE;q=0.9,de;q=0.8,en;q=0.7");
+        let many: Vec<String> = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k"].iter().map(|tag| format!("{tag}{tag}")).collect();
+        assert!(accept_language(&many).ends_with(",jj;q=0.1,kk;q=0.1"));
+    }
+
+    #[test]
+    fn parses_typed_language_lists() {
+        assert_eq!(parse_language_list(" en-gb, fr ,,en-GB"), Some(vec!["en-GB".to_string(), "fr".to_string()]));
+        assert_eq!(parse_language_list("en, not a tag"), None);
+        assert_eq!(parse_language_list(""), Some(Vec::new()));
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    #[test]
+    fn posix_language_list_comes_before_the_locale() {
+        use super::posix_languages;
+
+        assert_eq!(posix_languages(Some("fr:de"), Some("en_US.UTF-8")), vec!["fr", "de", "en_US.UTF-8"]);
+        // gettext ignores LANGUAGE under the C locale
+        assert_eq!(posix_languages(Some("fr"), Some("C")), vec!["C"]);
+        assert_eq!(posix_languages(None, None), Vec::<String>::new());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::dom::Dom;
+use crate::dom::node::LineBreakKey;
 use crate::layout::taffy::resolve_calc_value;
 use parley::{AlignmentOptions, IndentOptions, YieldData};
 use style::values::computed::{CSSPixelLength, LengthPercentage};
@@ -324,12 +325,9 @@ impl Dom {
             .width
             .map(|w| (w * scale) - pbw)
             .unwrap_or_else(|| {
-                // TODO: Cache content widths.
-                //
-                // This is a little tricky as the size of the inline boxes may depend on whether we are sizing under
-                // and a min-content or max-content constraint. So if we want to compute both widths in one pass then
-                // we need to store both a min-content and max-content size on each box.
-                let content_sizes = inline_layout.layout.calculate_content_widths();
+                // The size of the inline boxes may depend on whether we are sizing under a min-content or
+                // max-content constraint, so cached widths are only reused for the same box sizes.
+                let content_sizes = inline_layout.content_widths_for_boxes();
                 let min_content_width = content_sizes.min;
                 let max_content_width = content_sizes.max;
 
@@ -421,10 +419,47 @@ impl Dom {
         //     };
         // }
 
+        let alignment = self.nodes[node_id]
+            .primary_styles()
+            .map(|s| {
+                use parley::layout::Alignment;
+                use style::values::specified::TextAlignKeyword;
+
+                match s.clone_text_align() {
+                    TextAlignKeyword::Start => Alignment::Start,
+                    TextAlignKeyword::Left => Alignment::Left,
+                    TextAlignKeyword::Right => Alignment::Right,
+                    TextAlignKeyword::Center => Alignment::Center,
+                    TextAlignKeyword::Justify => Alignment::Justify,
+                    TextAlignKeyword::End => Alignment::End,
+                    TextAlignKeyword::MozCenter => Alignment::Center,
+                    TextAlignKeyword::MozLeft => Alignment::Left,
+                    TextAlignKeyword::MozRight => Alignment::Right,
+                }
+            })
+            .unwrap_or(parley::layout::Alignment::Start);
+
+        // Lines broken with exactly the same inputs are still valid. Floats place themselves while
+        // lines are broken, so layouts next to or containing floats are always broken again.
+        let initial_slot = block_ctx.find_content_slot(0.0, Clear::None, None);
+        let has_floated_boxes = inline_layout
+            .layout
+            .inline_boxes()
+            .iter()
+            .any(|ibox| self.nodes[ibox.id as usize].taffy_style.float.is_floated());
+        let line_break_key = (initial_slot.segment_id.is_none() && !has_floated_boxes).then(|| LineBreakKey {
+            max_advance: width,
+            first_line_max_advance: initial_slot.width * scale,
+            line_x: initial_slot.x * scale,
+            line_y: initial_slot.y * scale,
+            text_indent: resolved_text_indent,
+            alignment,
+            inline_boxes: inline_layout.inline_boxes_hash(),
+        });
+
         // Perform inline layout
-        {
+        if line_break_key.is_none() || inline_layout.line_break_key != line_break_key {
             let mut breaker = inline_layout.layout.break_lines();
-            let initial_slot = block_ctx.find_content_slot(0.0, Clear::None, None);
             let mut has_active_floats = initial_slot.segment_id.is_some();
             let state = breaker.state_mut();
             state.set_layout_max_advance(width);
@@ -528,34 +563,17 @@ impl Dom {
                 }
             }
             breaker.finish();
-        }
 
-        let alignment = self.nodes[node_id]
-            .primary_styles()
-            .map(|s| {
-                use parley::layout::Alignment;
-                use style::values::specified::TextAlignKeyword;
+            inline_layout.layout.align(
+                alignment,
+                AlignmentOptions {
+                    align_when_overflowing: false,
+                },
+            );
+            inline_layout.line_break_key = line_break_key;
+        }
 
-                match s.clone_text_align() {
-                    TextAlignKeyword::Start => Alignment::Start,
-                    TextAlignKeyword::Left => Alignment::Left,
-                    TextAlignKeyword::Right => Alignment::Right,
-                    TextAlignKeyword::Center => Alignment::Center,
-                    TextAlignKeyword::Justify => Alignment::Justify,
-                    TextAlignKeyword::End => Alignment::End,
-                    TextAlignKeyword::MozCenter => Alignment::Center,
-                    TextAlignKeyword::MozLeft => Alignment::Left,
-                    TextAlignKeyword::MozRight => Alignment::Right,
-                }
-            })
-            .unwrap_or(parley::layout::Alignment::Start);
 
-        inline_layout.layout.align(
-            alignment,
-            AlignmentOptions {
-                align_when_overflowing: false,
-            },
-        );
 
         #[allow(unused_mut)]
         let mut height = inline_layout.layout.height();
@@ -1,5 +1,5 @@
 // Layout engine for computing element positions and sizes
-mod taffy;
+pub(crate) mod taffy;
 mod inline;
 pub(crate) mod table;
 mod replaced;
@@ -161,7 +161,7 @@ pub(crate) fn collect_table_cells(
     }
 
     let Some(display) = node.primary_styles().map(|s| s.clone_display()) else {
-        println!("Ignoring table descendent because it has no styles");
+        tracing::debug!("Ignoring table descendent because it has no styles");
         return;
     };
 
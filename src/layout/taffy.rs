@@ -1,5 +1,5 @@
-use crate::dom::node::SpecialElementData;
-use crate::dom::{Dom, ImageData, NodeData};
+use crate::dom::node::{AltTextLayout, SpecialElementData};
+use crate::dom::{stylo_to_parley, Dom, ImageData, NodeData};
 use crate::layout::replaced::{replaced_measure_function, ReplacedContext};
 use crate::layout::table::{TableContext, TableTreeWrapper};
 use markup5ever::local_name;
@@ -10,13 +10,51 @@ use style::values::computed::{CSSPixelLength, LineHeight};
 use stylo_atoms::Atom;
 pub(crate) use taffy::{compute_block_layout, compute_cached_layout, compute_flexbox_layout, compute_grid_layout, compute_leaf_layout, AvailableSpace, BlockContext, CacheTree, CollapsibleMarginSet, Display, Layout, LayoutBlockContainer, LayoutFlexboxContainer, LayoutGridContainer, LayoutInput, LayoutOutput, LayoutPartialTree, NodeId, PrintTree, ResolveOrZero, RoundTree, RunMode, Size, Style, TraversePartialTree, TraverseTree};
 
+/// Space between a blocked image's border and its alt text
+const BLOCKED_IMAGE_PADDING: f32 = 4.0;
+/// Size of a blocked image that has neither alt text nor dimensions
+const BLOCKED_IMAGE_SIZE: f32 = 16.0;
+
 impl Dom {
+    /// Lay out the alt text a blocked image shows in place of its pixels
+    fn layout_blocked_image_alt(&mut self, node_id: usize) {
+        let node = &self.nodes[node_id];
+        let Some(styles) = node.primary_styles() else {
+            return;
+        };
+        let Some(alt) = node.attr(local_name!("alt")).filter(|alt| !alt.trim().is_empty()) else {
+            return;
+        };
+
+        let parley_style = stylo_to_parley::style(node_id, &styles);
+        let mut font_ctx = self.font_ctx.lock().unwrap();
+        let mut builder = self.layout_ctx.tree_builder(&mut font_ctx, self.viewport.scale(), true, &parley_style);
+        builder.push_text(alt);
+        let mut layout = builder.build().0;
+        let width = layout.calculate_content_widths().max;
+        layout.break_all_lines(Some(width));
+        drop(font_ctx);
+        drop(styles);
+
+        if let Some(ImageData::Blocked(alt_layout)) = self.nodes[node_id].element_data_mut().and_then(|element| element.image_data_mut()) {
+            *alt_layout = Some(AltTextLayout(Box::new(layout)));
+        }
+    }
+
     fn compute_child_layout_internal(
         &mut self,
         node_id: NodeId,
         inputs: taffy::tree::LayoutInput,
         block_ctx: Option<&mut BlockContext<'_>>,
     ) -> taffy::tree::LayoutOutput {
+        let needs_alt_layout = self.nodes[node_id.into()]
+            .element_data()
+            .and_then(|element_data| element_data.image_data())
+            .is_some_and(|image| matches!(image, ImageData::Blocked(None)));
+        if needs_alt_layout {
+            self.layout_blocked_image_alt(node_id.into());
+        }
+
         let node = &mut self.nodes[node_id.into()];
 
         let font_styles = node.primary_styles().map(|style| {
@@ -132,12 +170,13 @@ impl Dom {
                 if *element_data.name.local == *"img"
                     || *element_data.name.local == *"canvas"
                     || *element_data.name.local == *"svg"
+                    || *element_data.name.local == *"iframe"
                 {
                     // Get width and height attributes on image element
                     //
                     // TODO: smarter sizing using these (depending on object-fit, they shouldn't
                     // necessarily just override the native size)
-                    let attr_size = taffy::Size {
+                    let mut attr_size = taffy::Size {
                         width: element_data
                             .attr(local_name!("width"))
                             .and_then(|val| val.parse::<f32>().ok()),
@@ -160,13 +199,47 @@ impl Dom {
                                     height: size.height(),
                                 }
                             }
+                            ImageData::Evicted { width, height } => taffy::Size {
+                                width: *width,
+                                height: *height,
+                            },
+                            ImageData::Blocked(alt) => match alt {
+                                Some(AltTextLayout(layout)) => taffy::Size {
+                                    width: layout.full_width() / layout.scale() + 2.0 * BLOCKED_IMAGE_PADDING,
+                                    height: layout.height() / layout.scale() + 2.0 * BLOCKED_IMAGE_PADDING,
+                                },
+                                None => taffy::Size {
+                                    width: BLOCKED_IMAGE_SIZE,
+                                    height: BLOCKED_IMAGE_SIZE,
+                                },
+                            },
                             ImageData::None => taffy::Size::ZERO,
                         },
-                        SpecialElementData::Canvas(_) => taffy::Size::ZERO,
+                        // A canvas's bitmap is 300x150 until its width and height attributes say otherwise
+                        SpecialElementData::Canvas(_) => taffy::Size { width: 300.0, height: 150.0 },
+                        SpecialElementData::None if *element_data.name.local == *"canvas" => {
+                            taffy::Size { width: 300.0, height: 150.0 }
+                        }
+                        // Frames default to 300x150 too, with or without a document inside
+                        SpecialElementData::SubDom(_) => taffy::Size { width: 300.0, height: 150.0 },
+                        SpecialElementData::None if *element_data.name.local == *"iframe" => {
+                            taffy::Size { width: 300.0, height: 150.0 }
+                        }
                         SpecialElementData::None => taffy::Size::ZERO,
                         _ => unreachable!(),
                     };
 
+                    // A placeholder or a frame has no aspect ratio worth keeping, so a lone width
+                    // or height attribute is paired with the default size for the other axis
+                    let is_blocked = matches!(element_data.image_data(), Some(ImageData::Blocked(_)));
+                    let is_frame = *element_data.name.local == *"iframe";
+                    if (is_blocked || is_frame) && (attr_size.width.is_some() || attr_size.height.is_some()) {
+                        attr_size = taffy::Size {
+                            width: attr_size.width.or(Some(inherent_size.width)),
+                            height: attr_size.height.or(Some(inherent_size.height)),
+                        };
+                    }
+
                     let replaced_context = ReplacedContext {
                         inherent_size,
                         attr_size,
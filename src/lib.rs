@@ -0,0 +1,110 @@
+// The browser, its tab processes and the engine they share. The executable in main.rs only
+// calls `run`; the library exists so fuzz targets can reach the parsers through `fuzz`.
+mod engine;
+mod networking;
+mod ui;
+mod dom;
+mod layout;
+mod renderer;
+mod css;
+mod js;
+pub mod convert_events;
+pub mod events;
+mod input;
+mod ipc;
+mod tab_process;
+mod tab_manager;
+mod browser;
+mod window;
+mod window_placement;
+mod shell_provider;
+mod default_browser;
+mod bookmarks;
+mod visited_links;
+mod clipboard;
+mod gamepad;
+mod save_page;
+mod permissions;
+mod geolocation;
+mod languages;
+mod media_preferences;
+mod site_settings;
+mod logging;
+mod keymap;
+mod secure_storage;
+mod autofill;
+mod passwords;
+mod profile;
+mod extensions;
+mod cookies;
+mod site_storage;
+mod web_apps;
+mod wpt;
+mod frame_timing;
+mod visual_viewport;
+mod color_management;
+mod url_display;
+#[doc(hidden)]
+pub mod fuzz;
+
+use crate::browser::BrowserApp;
+use winit::event_loop::EventLoop;
+use winit_core::event_loop::ControlFlow;
+
+/// Run the browser, a tab process or a headless test run, depending on the command line
+pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    // Check if this is a tab process
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() >= 4 && args[1] == "--tab-process" {
+        let tab_id = args[2].clone();
+        let server_name = args[3].clone();
+        // The browser passes the directory of the profile it runs with
+        if args.len() >= 6 && args[4] == "--profile-dir" {
+            profile::init(args[5].clone().into());
+        }
+        return tab_process::tab_process_main(tab_id, server_name).await.map_err(|e| e.into());
+    }
+
+    // Headless conformance run over the curated web-platform-tests
+    if args.len() >= 2 && args[1] == "--wpt" {
+        logging::init_browser_process();
+        let passed = wpt::run(args.get(2).map(String::as_str)).await?;
+        return if passed { Ok(()) } else { Err("web-platform-tests regressed".into()) };
+    }
+
+    // Main browser process
+    logging::init_browser_process();
+    tracing::info!("Starting Stokes Browser...");
+
+    // Pick the profile before anything is read from disk
+    let profile_name = match profile::from_args(&args) {
+        Some(name) if profile::is_valid_name(&name) => name,
+        Some(name) => {
+            tracing::error!("Invalid profile name {:?}", name);
+            return Err(format!("invalid profile name {name:?}").into());
+        }
+        None => match browser::pick_profile(&profile::list()) {
+            Some(name) => name,
+            None => return Ok(()),
+        },
+    };
+    profile::init(profile::dir_for(&profile_name));
+    tracing::info!("Using profile {}", profile_name);
+
+    // Check for a URL passed as a command-line argument (e.g. when launched as the default browser)
+    // or an installed web app's start URL (when launched from its shortcut)
+    let app_url = web_apps::app_url_from_args(&args);
+    let app_mode = app_url.is_some();
+    let startup_url: Option<String> = app_url.or_else(|| args.iter().skip(1).find(|a| {
+        a.starts_with("http://") || a.starts_with("https://") || a.starts_with("about:")
+    }).cloned());
+    tracing::debug!("Arguments: {:?}", args);
+
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Wait);
+    let app = BrowserApp::new(&event_loop, startup_url, app_mode).await;
+
+    event_loop.run_app(app)?;
+    js::bindings::cookie::clear_if_requested_on_exit();
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,345 @@
+// Logging for the browser and its tab processes, built on `tracing`.
+//
+// Which messages are shown is decided per subsystem by a filter such as
+// "warn,net=debug,js=info,stokes_browser::dom::layout=trace": a default level followed by
+// `subsystem=level` or `module::path=level` directives. The filter comes from the STOKES_LOG
+// environment variable, or else from logging.json in the config directory. Spans are timed when
+// they close, so e.g. "paint=debug" logs how long every frame took to paint.
+//
+// Tab processes do not write logs themselves. Their events are forwarded to the browser process as
+// `TabToParentMessage::Log`, which prints them to stderr and appends them to stokes.log next to
+// its own, so one file holds the whole session in order.
+//
+// Built with the `profiling` feature, every process also records its spans as Tracy zones, whatever
+// the filter says: style, layout and paint in the tab processes, JavaScript, IPC on both ends and
+// compositing in the browser. Each process is its own Tracy client, so the profiler lists the
+// browser and every tab separately.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, Layer};
+
+const LOG_ENV_VAR: &str = "STOKES_LOG";
+const SETTINGS_FILE: &str = "logging.json";
+const LOG_FILE: &str = "stokes.log";
+/// Messages from page scripts stay visible by default, like they were before logging was filtered
+const DEFAULT_FILTER: &str = "warn,console=info";
+/// Target that forwarded tab process events are re-emitted under in the browser process
+const TAB_TARGET: &str = "tab";
+
+/// Subsystem names accepted in filters and the module paths they stand for
+const SUBSYSTEMS: &[(&str, &[&str])] = &[
+    ("engine", &["stokes_browser::engine"]),
+    ("net", &["stokes_browser::networking", "stokes_browser::engine::net_provider"]),
+    ("cookies", &["stokes_browser::js::bindings::cookie"]),
+    ("js", &["stokes_browser::js", "stokes_browser::engine::js_message_handler"]),
+    ("console", &["console"]),
+    ("dom", &["stokes_browser::dom"]),
+    ("style", &["stokes_browser::css", "style"]),
+    ("layout", &["layout", "stokes_browser::layout", "stokes_browser::dom::layout", "taffy", "parley"]),
+    ("paint", &["paint", "stokes_browser::renderer"]),
+    ("ipc", &["stokes_browser::ipc", "stokes_browser::tab_process", "stokes_browser::tab_manager"]),
+    ("ui", &["stokes_browser::browser", "stokes_browser::ui", "stokes_browser::input", "stokes_browser::keymap", "stokes_browser::window"]),
+];
+
+/// Logging preferences saved in logging.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogSettings {
+    /// Filter used when STOKES_LOG is not set
+    #[serde(default)]
+    pub filter: Option<String>,
+    /// Append everything to stokes.log in the config directory
+    #[serde(default = "default_write_file")]
+    pub write_file: bool,
+}
+
+const fn default_write_file() -> bool {
+    true
+}
+
+impl LogSettings {
+    pub fn load() -> Self {
+        std::fs::read_to_string(config_path(SETTINGS_FILE))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or(Self { filter: None, write_file: true })
+    }
+
+    /// The filter spec in effect: the environment wins over the settings file
+    pub fn filter_spec(&self) -> String {
+        std::env::var(LOG_ENV_VAR)
+            .ok()
+            .filter(|spec| !spec.trim().is_empty())
+            .or_else(|| self.filter.clone())
+            .unwrap_or_else(|| DEFAULT_FILTER.to_string())
+    }
+}
+
+/// Build a per-target filter from a spec like "warn,net=debug". Unknown levels are ignored so a
+/// typo never silences everything.
+pub fn parse_filter(spec: &str) -> Targets {
+    let mut targets = Targets::new().with_default(LevelFilter::WARN);
+    for directive in spec.split(',').map(str::trim).filter(|directive| !directive.is_empty()) {
+        let Some((target, level)) = directive.split_once('=') else {
+            if let Ok(level) = LevelFilter::from_str(directive) {
+                targets = targets.with_default(level);
+            }
+            continue;
+        };
+        let Ok(level) = LevelFilter::from_str(level.trim()) else {
+            continue;
+        };
+        let target = target.trim();
+        if target.is_empty() {
+            continue;
+        }
+        match SUBSYSTEMS.iter().find(|(name, _)| name.eq_ignore_ascii_case(target)) {
+            Some((_, modules)) => {
+                for module in modules.iter() {
+                    targets = targets.with_target(*module, level);
+                }
+            }
+            None => targets = targets.with_target(target, level),
+        }
+    }
+    targets
+}
+
+fn config_path(file: &str) -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("stokes-browser")
+        .join(file)
+}
+
+static LOG_FILE_HANDLE: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// Writes to stokes.log, if it could be opened
+struct LogFileWriter;
+
+impl Write for LogFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match LOG_FILE_HANDLE.get() {
+            Some(file) => file.lock().map_err(|_| io::Error::other("log file lock poisoned"))?.write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match LOG_FILE_HANDLE.get() {
+            Some(file) => file.lock().map_err(|_| io::Error::other("log file lock poisoned"))?.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn open_log_file() -> Option<File> {
+    let path = config_path(LOG_FILE);
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    OpenOptions::new().create(true).append(true).open(path).ok()
+}
+
+/// Set up logging in the browser process: stderr plus stokes.log
+pub fn init_browser_process() {
+    let settings = LogSettings::load();
+    // Tab processes already applied the filter before forwarding
+    let filter = parse_filter(&settings.filter_spec()).with_target(TAB_TARGET, LevelFilter::TRACE);
+
+    let file_opened = settings.write_file
+        && open_log_file().is_some_and(|file| LOG_FILE_HANDLE.set(Mutex::new(file)).is_ok());
+    let file_layer = file_opened.then(|| {
+        fmt::layer()
+            .with_ansi(false)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_writer(|| LogFileWriter)
+            .with_filter(filter.clone())
+    });
+    let stderr_layer = fmt::layer()
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(io::stderr)
+        .with_filter(filter);
+
+    let _ = tracing_subscriber::registry()
+        .with(stderr_layer)
+        .with(file_layer)
+        .with(profiling_layer())
+        .try_init();
+}
+
+/// Set up logging in a tab process. Events are queued on the returned receiver; the tab process
+/// sends them on to the browser process once its IPC channel is up.
+pub fn init_tab_process() -> UnboundedReceiver<LogRecord> {
+    let (sender, receiver) = unbounded_channel();
+    let filter = parse_filter(&LogSettings::load().filter_spec());
+    let _ = tracing_subscriber::registry()
+        .with(ForwardingLayer { sender }.with_filter(filter))
+        .with(profiling_layer())
+        .try_init();
+    receiver
+}
+
+/// Tracy zones for every span up to debug level, in builds with the `profiling` feature
+fn profiling_layer<S>() -> Option<Box<dyn Layer<S> + Send + Sync>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    #[cfg(feature = "profiling")]
+    return Some(Box::new(tracing_tracy::TracyLayer::default().with_filter(LevelFilter::DEBUG)));
+    #[cfg(not(feature = "profiling"))]
+    None
+}
+
+/// Mark the end of a frame in the Tracy capture, so zones are grouped by the frame they were in
+pub fn frame_mark() {
+    #[cfg(feature = "profiling")]
+    if let Some(client) = tracing_tracy::client::Client::running() {
+        client.frame_mark();
+    }
+}
+
+/// A tab process log event on its way to the browser process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub level: String,
+    pub target: String,
+    /// Names of the spans the event happened in, outermost first, like "navigate:resolve"
+    pub spans: String,
+    pub message: String,
+}
+
+impl LogRecord {
+    /// Log a record forwarded by tab `tab_id` through this process's subscriber
+    pub fn emit(&self, tab_id: &str) {
+        let level = Level::from_str(&self.level).unwrap_or(Level::INFO);
+        let (target, spans, message) = (&self.target, &self.spans, &self.message);
+        macro_rules! forward {
+            ($level:expr) => {
+                tracing::event!(target: "tab", $level, tab = tab_id, spans = %spans, "{target}: {message}")
+            };
+        }
+        match level {
+            Level::ERROR => forward!(Level::ERROR),
+            Level::WARN => forward!(Level::WARN),
+            Level::INFO => forward!(Level::INFO),
+            Level::DEBUG => forward!(Level::DEBUG),
+            Level::TRACE => forward!(Level::TRACE),
+        }
+    }
+}
+
+/// Turns events into `LogRecord`s for the browser process
+struct ForwardingLayer {
+    sender: UnboundedSender<LogRecord>,
+}
+
+/// When a span was created, to report its duration when it closes
+struct SpanStart(Instant);
+
+impl<S> Layer<S> for ForwardingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanStart(Instant::now()));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let spans = ctx
+            .event_scope(event)
+            .map(|scope| scope.from_root().map(|span| span.name()).collect::<Vec<_>>().join(":"))
+            .unwrap_or_default();
+        let metadata = event.metadata();
+        let _ = self.sender.send(LogRecord {
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            spans,
+            message: visitor.message + &visitor.fields,
+        });
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(elapsed) = span.extensions().get::<SpanStart>().map(|start| start.0.elapsed()) else {
+            return;
+        };
+        let spans = span.scope().from_root().map(|span| span.name()).collect::<Vec<_>>().join(":");
+        let metadata = span.metadata();
+        let _ = self.sender.send(LogRecord {
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            spans,
+            message: format!("close time={elapsed:?}"),
+        });
+    }
+}
+
+/// Collects an event's message followed by its other fields
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={value}", field.name());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_filter;
+    use tracing::Level;
+
+    #[test]
+    fn subsystems_expand_to_their_modules() {
+        let filter = parse_filter("error,net=debug,stokes_browser::dom::layout=trace");
+        assert!(filter.would_enable("stokes_browser::networking::hsts", &Level::DEBUG));
+        assert!(filter.would_enable("stokes_browser::engine::net_provider", &Level::DEBUG));
+        assert!(!filter.would_enable("stokes_browser::engine", &Level::WARN));
+        assert!(filter.would_enable("stokes_browser::dom::layout", &Level::TRACE));
+        assert!(!filter.would_enable("stokes_browser::dom", &Level::INFO));
+    }
+
+    #[test]
+    fn bad_directives_are_ignored() {
+        let filter = parse_filter("info,js=loud,,=debug");
+        assert!(filter.would_enable("stokes_browser::js::runtime", &Level::INFO));
+        assert!(!filter.would_enable("stokes_browser::js::runtime", &Level::DEBUG));
+    }
+}
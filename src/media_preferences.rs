@@ -0,0 +1,117 @@
+// User preferences exposed to pages through media queries (prefers-color-scheme,
+// prefers-reduced-motion). The parent process reads them from the desktop and pushes them to every
+// tab, where they feed both the stylist's device and window.matchMedia.
+use blitz_traits::shell::ColorScheme;
+use serde::{Deserialize, Serialize};
+use winit::window::Theme;
+
+/// What the user's desktop asks pages to adapt to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaPreferences {
+    pub dark: bool,
+    pub reduced_motion: bool,
+}
+
+impl MediaPreferences {
+    /// Detect the current preferences. `theme` is what the windowing system reports for our
+    /// window, when it knows; otherwise the desktop settings are asked directly.
+    pub fn detect(theme: Option<Theme>) -> Self {
+        let dark = match theme {
+            Some(theme) => theme == Theme::Dark,
+            None => system_prefers_dark(),
+        };
+        Self { dark, reduced_motion: system_prefers_reduced_motion() }
+    }
+
+    pub fn color_scheme(&self) -> ColorScheme {
+        if self.dark { ColorScheme::Dark } else { ColorScheme::Light }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Linux
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(target_os = "linux")]
+fn gsettings_get(schema: &str, key: &str) -> Option<String> {
+    use std::process::Command;
+
+    let output = Command::new("gsettings").args(["get", schema, key]).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().trim_matches('\'').to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn system_prefers_dark() -> bool {
+    if let Some(scheme) = gsettings_get("org.gnome.desktop.interface", "color-scheme") {
+        return scheme == "prefer-dark";
+    }
+    // Older desktops only expose the theme name
+    gsettings_get("org.gnome.desktop.interface", "gtk-theme")
+        .or_else(|| std::env::var("GTK_THEME").ok())
+        .is_some_and(|theme| theme.to_ascii_lowercase().contains("dark"))
+}
+
+#[cfg(target_os = "linux")]
+fn system_prefers_reduced_motion() -> bool {
+    gsettings_get("org.gnome.desktop.interface", "enable-animations").is_some_and(|value| value == "false")
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// macOS
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(target_os = "macos")]
+fn defaults_read(args: &[&str]) -> Option<String> {
+    use std::process::Command;
+
+    let output = Command::new("defaults").arg("read").args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn system_prefers_dark() -> bool {
+    defaults_read(&["-g", "AppleInterfaceStyle"]).is_some_and(|style| style == "Dark")
+}
+
+#[cfg(target_os = "macos")]
+fn system_prefers_reduced_motion() -> bool {
+    defaults_read(&["com.apple.universalaccess", "reduceMotion"]).is_some_and(|value| value == "1")
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Windows
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(target_os = "windows")]
+fn registry_dword(key: &str, value: &str) -> Option<u32> {
+    use std::process::Command;
+
+    let output = Command::new("reg").args(["query", key, "/v", value]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let hex = stdout.lines().find(|line| line.contains("REG_"))?.split_whitespace().last()?;
+    u32::from_str_radix(hex.trim_start_matches("0x"), 16).ok()
+}
+
+#[cfg(target_os = "windows")]
+fn system_prefers_dark() -> bool {
+    registry_dword(r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize", "AppsUseLightTheme") == Some(0)
+}
+
+#[cfg(target_os = "windows")]
+fn system_prefers_reduced_motion() -> bool {
+    // "Show animations in Windows" off clears MinAnimate
+    registry_dword(r"HKCU\Control Panel\Desktop\WindowMetrics", "MinAnimate") == Some(0)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn system_prefers_dark() -> bool {
+    false
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn system_prefers_reduced_motion() -> bool {
+    false
+}
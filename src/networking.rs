@@ -1,17 +1,20 @@
 use crate::dom::DomEvent;
+use crate::dom::font_loading::FontDisplay;
+use crate::dom::scrollbar::translate_webkit_scrollbar_rules;
+use crate::dom::renamed_properties::translate_renamed_properties;
+use crate::dom::style_cache;
 use crate::engine::adblock;
 use blitz_traits::net::{NetHandler, NetProvider, Request};
 use blitz_traits::shell::ShellProvider;
 use bytes::Bytes;
 // Networking module for handling HTTP requests
-use curl::easy::{Easy, List};
+use curl::easy::{Easy, InfoType};
 use selectors::context::QuirksMode;
 use std::io::Cursor;
 use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc::Sender;
 use std::sync::{Arc, LazyLock};
-use std::time::Duration;
 use style::font_face::{FontFaceSourceFormat, FontFaceSourceFormatKeyword, Source};
 use style::media_queries::MediaList;
 use style::servo_arc::Arc as ServoArc;
@@ -23,14 +26,32 @@ use style::values::{CssUrl, SourceLocation};
 use url::Url;
 use usvg::fontdb;
 use crate::engine::net_provider::StokesNetProvider;
+use crate::networking::request_policy::RequestPolicy;
+use crate::languages;
+use crate::passwords;
+use crate::cookies;
+use crate::site_storage;
+use crate::extensions;
 use crate::shell_provider::StokesShellProvider;
 
+pub mod blob_store;
+pub mod conditions;
+pub mod hsts;
+pub mod http_auth;
+pub mod referrer;
+pub mod refresh;
+pub mod request_policy;
+pub mod security;
+pub mod websocket;
+
 #[derive(Debug)]
 pub enum NetworkError {
     Curl(String),
     Utf8(String),
     Engine(String),
     Http(u32),
+    /// The server did not answer in time or the transfer stalled
+    Timeout(String),
     Blocked(String),
     Empty,
     FileNotFound(String),
@@ -44,6 +65,7 @@ impl std::fmt::Display for NetworkError {
             NetworkError::Utf8(msg) => write!(f, "UTF-8 error: {}", msg),
             NetworkError::Engine(msg) => write!(f, "Engine error: {}", msg),
             NetworkError::Http(code) => write!(f, "HTTP error: {}", code),
+            NetworkError::Timeout(url) => write!(f, "Timed out: {}", url),
             NetworkError::Blocked(url) => write!(f, "Blocked by adblock: {}", url),
             NetworkError::Empty => write!(f, "Empty response body"),
             NetworkError::FileNotFound(path) => write!(f, "File not found: {}", path),
@@ -54,6 +76,35 @@ impl std::fmt::Display for NetworkError {
 
 impl std::error::Error for NetworkError {}
 
+const NOT_FOUND_PAGE: &str = include_str!("../assets/404.html");
+const TIMED_OUT_PAGE: &str = include_str!("../assets/timed-out.html");
+
+/// Text from the network as a string. Pages and stylesheets are not always the UTF-8 they should
+/// be; invalid sequences become U+FFFD rather than failing the load.
+pub fn decode_text(bytes: &[u8]) -> std::borrow::Cow<'_, str> {
+    String::from_utf8_lossy(bytes)
+}
+
+/// The page shown in place of a document that failed to load
+pub fn error_page(url: &str, timed_out: bool) -> String {
+    if timed_out {
+        TIMED_OUT_PAGE.replace("{{URL}}", &html_escape::encode_text(url))
+    } else {
+        NOT_FOUND_PAGE.to_string()
+    }
+}
+
+impl NetworkError {
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, NetworkError::Timeout(_))
+    }
+
+    /// The page shown for a document that failed with this error
+    pub fn error_page(&self, url: &str) -> String {
+        error_page(url, self.is_timeout())
+    }
+}
+
 pub(crate) static FONT_DB: LazyLock<Arc<fontdb::Database>> = LazyLock::new(|| {
     let mut db = fontdb::Database::new();
     db.load_system_fonts();
@@ -70,7 +121,7 @@ pub(crate) fn parse_svg(source: &[u8]) -> Result<usvg::Tree, usvg::Error> {
     Ok(tree)
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug)]
 pub enum ImageType {
     Image,
     Background(usize)
@@ -81,7 +132,8 @@ pub enum Resource {
     Image(ImageType, u32, u32, Arc<Vec<u8>>),
     Svg(ImageType, Arc<usvg::Tree>),
     Css(DocumentStyleSheet),
-    Font(Bytes),
+    /// A web font, and the URL it was requested from
+    Font(String, Bytes),
     None,
 }
 
@@ -154,45 +206,45 @@ pub struct StylesheetHandler {
 
 impl NetHandler for ResourceHandler<StylesheetHandler> {
     fn bytes(self: Box<Self>, resolved_url: String, bytes: Bytes) {
-        let Ok(css) = std::str::from_utf8(&bytes) else {
-            return self.respond(resolved_url, Err(String::from("Invalid UTF8")));
-        };
+        let css = decode_text(&bytes);
 
         // NOTE(Nico): I don't *think* external stylesheets should have HTML entities escaped
         // let escaped_css = html_escape::decode_html_entities(css);
-
-        let sheet = Stylesheet::from_str(
-            css,
-            self.data.source_url.clone().into(),
-            Origin::Author,
-            ServoArc::new(self.data.guard.wrap(MediaList::empty())),
-            self.data.guard.clone(),
-            Some(&StylesheetLoader {
-                tx: self.tx.clone(),
-                dom_id: self.dom_id,
-                net_provider: self.data.net_provider.clone(),
-                shell_provider: self.shell_provider.clone(),
-            }),
-            None, // error_reporter
-            QuirksMode::NoQuirks,
-            AllowImportRules::Yes,
-        );
+        // Pages on the same site usually share their stylesheets, so an identical sheet parsed for
+        // an earlier document is reused
+        let sheet = style_cache::stylesheet(self.data.source_url.as_str(), &css, Origin::Author, || {
+            let translated = translate_webkit_scrollbar_rules(&css);
+            let translated = translate_renamed_properties(translated.as_deref().unwrap_or(&css)).or(translated);
+            Stylesheet::from_str(
+                translated.as_deref().unwrap_or(&css),
+                self.data.source_url.clone().into(),
+                Origin::Author,
+                ServoArc::new(self.data.guard.wrap(MediaList::empty())),
+                self.data.guard.clone(),
+                Some(&StylesheetLoader {
+                    tx: self.tx.clone(),
+                    dom_id: self.dom_id,
+                    net_provider: self.data.net_provider.clone(),
+                    shell_provider: self.shell_provider.clone(),
+                }),
+                None, // error_reporter
+                QuirksMode::NoQuirks,
+                AllowImportRules::Yes,
+            )
+        });
 
         // Fetch @font-face fonts
         fetch_font_face(
             self.tx.clone(),
             self.dom_id,
             self.node_id,
-            &sheet,
+            &sheet.0,
             &self.data.net_provider,
             &self.shell_provider,
             &self.data.guard.read(),
         );
 
-        self.respond(
-            resolved_url,
-            Ok(Resource::Css(DocumentStyleSheet(ServoArc::new(sheet)))),
-        );
+        self.respond(resolved_url, Ok(Resource::Css(sheet)));
     }
 }
 
@@ -268,15 +320,15 @@ struct StylesheetLoaderInner {
 
 impl NetHandler for ResourceHandler<StylesheetLoaderInner> {
     fn bytes(self: Box<Self>, resolved_url: String, bytes: Bytes) {
-        let Ok(css) = std::str::from_utf8(&bytes) else {
-            return self.respond(resolved_url, Err(String::from("Invalid UTF8")));
-        };
+        let css = decode_text(&bytes);
 
         // NOTE(Nico): I don't *think* external stylesheets should have HTML entities escaped
         // let escaped_css = html_escape::decode_html_entities(css);
+        let translated = translate_webkit_scrollbar_rules(&css);
+        let translated = translate_renamed_properties(translated.as_deref().unwrap_or(&css)).or(translated);
 
         let sheet = ServoArc::new(Stylesheet::from_str(
-            css,
+            translated.as_deref().unwrap_or(&css),
             UrlExtraData(self.data.url.clone()),
             Origin::Author,
             self.data.media.clone(),
@@ -306,17 +358,39 @@ impl NetHandler for ResourceHandler<StylesheetLoaderInner> {
     }
 }
 
-struct FontFaceHandler(FontFaceSourceFormatKeyword);
+struct FontFaceHandler {
+    format: FontFaceSourceFormatKeyword,
+    /// The URL the font was requested from, which the document tracks its load by
+    url: String,
+    /// Set until the font arrives, to report the load as failed when the handler is dropped
+    /// without one, as it is when the fetch fails
+    failure: Option<(Sender<DomEvent>, Arc<StokesShellProvider>)>,
+}
+impl Drop for FontFaceHandler {
+    fn drop(&mut self) {
+        if let Some((tx, shell_provider)) = self.failure.take() {
+            let _ = tx.send(DomEvent::FontLoadFailed(self.url.clone()));
+            shell_provider.request_redraw();
+        }
+    }
+}
 impl NetHandler for ResourceHandler<FontFaceHandler> {
     fn bytes(mut self: Box<Self>, resolved_url: String, bytes: Bytes) {
         let result = self.data.parse(bytes);
+        if matches!(result, Ok(Resource::Font(..))) {
+            self.data.failure = None;
+        }
         self.respond(resolved_url, result)
     }
 }
 impl FontFaceHandler {
     fn parse(&mut self, bytes: Bytes) -> Result<Resource, String> {
-        if self.0 == FontFaceSourceFormatKeyword::None && bytes.len() >= 4 {
-            self.0 = match &bytes.as_ref()[0..4] {
+        // Blocked requests come back empty
+        if bytes.is_empty() {
+            return Err(String::from("Empty font"));
+        }
+        if self.format == FontFaceSourceFormatKeyword::None && bytes.len() >= 4 {
+            self.format = match &bytes.as_ref()[0..4] {
                 // WOFF (v1) files begin with 0x774F4646 ('wOFF' in ascii)
                 // See: <https://w3c.github.io/woff/woff1/spec/Overview.html#WOFFHeader>
                 b"wOFF" => FontFaceSourceFormatKeyword::Woff,
@@ -338,7 +412,7 @@ impl FontFaceHandler {
 
         let mut bytes = bytes;
 
-        match self.0 {
+        match self.format {
             FontFaceSourceFormatKeyword::Woff => {
                 tracing::info!("Decompressing woff1 font");
 
@@ -370,7 +444,7 @@ impl FontFaceHandler {
             _ => {}
         }
 
-        Ok(Resource::Font(bytes))
+        Ok(Resource::Font(self.url.clone(), bytes))
     }
 }
 
@@ -422,11 +496,13 @@ pub(crate) fn fetch_font_face(
         .filter_map(|rule| match rule {
             CssRule::FontFace(font_face) => {
                 let descriptor = &font_face.read_with(read_guard).descriptors;
-                descriptor.src.as_ref().filter(|_| descriptor.font_family.is_some())
+                let family = descriptor.font_family.as_ref()?;
+                let display = FontDisplay::from_stylo(descriptor.font_display.as_ref());
+                Some((descriptor.src.as_ref()?, family.name.to_string(), display))
             },
             _ => None,
         })
-        .for_each(|source_list| {
+        .for_each(|(source_list, family, display)| {
             let preferred_source = source_list
                 .0
                 .iter()
@@ -460,16 +536,20 @@ pub(crate) fn fetch_font_face(
                 });
 
             if let Some((url, format)) = preferred_source {
+                let _ = tx.send(DomEvent::FontLoadStarted {
+                    family,
+                    url: url.to_string(),
+                    display,
+                });
+                let handler = FontFaceHandler {
+                    format,
+                    url: url.to_string(),
+                    failure: Some((tx.clone(), shell_provider.clone())),
+                };
                 network_provider.fetch(
                     doc_id,
                     Request::get(url),
-                    ResourceHandler::boxed(
-                        tx.clone(),
-                        doc_id,
-                        node_id,
-                        shell_provider.clone(),
-                        FontFaceHandler(format),
-                    ),
+                    ResourceHandler::boxed(tx.clone(), doc_id, node_id, shell_provider.clone(), handler),
                 );
             }
         })
@@ -493,13 +573,20 @@ impl NetHandler for ResourceHandler<ImageHandler> {
 
 impl ImageHandler {
     fn parse(&self, bytes: Bytes) -> Result<Resource, String> {
-        // Try parse image
-        if let Ok(image) = image::ImageReader::new(Cursor::new(&bytes))
+        // Try parse image, keeping its ICC profile to convert its colors to sRGB
+        let decoded = image::ImageReader::new(Cursor::new(&bytes))
             .with_guessed_format()
             .expect("IO errors impossible with Cursor")
-            .decode()
-        {
-            let raw_rgba8_data = image.clone().into_rgba8().into_raw();
+            .into_decoder()
+            .and_then(|mut decoder| {
+                let icc = decoder.icc_profile().ok().flatten();
+                image::DynamicImage::from_decoder(decoder).map(|image| (image, icc))
+            });
+        if let Ok((image, icc)) = decoded {
+            let mut raw_rgba8_data = image.clone().into_rgba8().into_raw();
+            if let Some(icc) = icc {
+                crate::color_management::convert_to_srgb(&icc, &mut raw_rgba8_data);
+            }
             return Ok(Resource::Image(
                 self.kind.clone(),
                 image.width(),
@@ -533,8 +620,13 @@ impl HttpClient {
         }
     }
 
+    /// Timeouts and retries applied to this client's requests
+    pub fn request_policy(&self) -> &RequestPolicy {
+        self.net_provider.request_policy()
+    }
+
     pub fn fetch_image(&self, url: Url, user_agent: &str, node_id: usize) {
-        self.net_provider.fetch(
+        self.net_provider.fetch_image(
             self.dom_id,
             Request::get(url),
             ResourceHandler::boxed(
@@ -578,7 +670,7 @@ fn url_to_file_path(input: &str) -> String {
 
 /// Read a local HTML file
 fn read_local_file(path: &str) -> Result<String, NetworkError> {
-    println!("Reading local file: {}", path);
+    tracing::debug!("Reading local file: {}", path);
 
     let path = path.to_string();
     // Check if file exists
@@ -587,26 +679,60 @@ fn read_local_file(path: &str) -> Result<String, NetworkError> {
         return Err(NetworkError::FileNotFound(path.clone()));
     }
 
+    // Images opened directly (e.g. dropped onto the window) are wrapped in a
+    // minimal document so the engine can lay them out like any other page.
+    let is_image = file_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| matches!(ext.to_ascii_lowercase().as_str(), "png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp" | "ico" | "avif"))
+        .unwrap_or(false);
+    if is_image {
+        let src = Url::from_file_path(file_path)
+            .map(|url| url.to_string())
+            .unwrap_or_else(|_| path.clone());
+        let name = file_path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        return Ok(format!(
+            "<!DOCTYPE html><html><head><title>{}</title></head><body style=\"margin:0;background:#222;display:flex;align-items:center;justify-content:center;min-height:100vh\"><img src=\"{}\" style=\"max-width:100%\"></body></html>",
+            html_escape::encode_text(&name),
+            html_escape::encode_double_quoted_attribute(&src),
+        ));
+    }
+
     // Read the file
     std::fs::read_to_string(file_path)
         .map_err(|e| NetworkError::FileRead(e.to_string()))
         .map_err(|e| NetworkError::FileRead(e.to_string()))
 }
 
+/// Value of the last `name` header among raw header lines
+fn response_header<'a>(headers: &'a [String], name: &str) -> Option<&'a str> {
+    headers.iter()
+        .filter_map(|h| h.split_once(':'))
+        .filter(|(header_name, _)| header_name.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+        .last()
+}
+
 /// Fetch HTML content from a URL or local file
-pub fn fetch(url: &str, user_agent: &str, block_ads: bool) -> Result<String, NetworkError> {
-    println!("Fetching: {}", url);
+pub fn fetch(url: &str, user_agent: &str, block_ads: bool, policy: &RequestPolicy) -> Result<String, NetworkError> {
+    tracing::debug!("Fetching: {}", url);
 
     // Parse only for scheme detection. We intentionally pass the *original* URL
     // string to curl below so that Url::parse() normalization (e.g. percent-
     // encoding of '*', reordering, etc.) cannot corrupt signed query parameters
     // such as those used by Google Search.
-    let parsed_url = match Url::parse(url) {
+    let mut parsed_url = match Url::parse(url) {
         Ok(u) => u,
         Err(err) => {
             return Err(NetworkError::Curl(err.to_string()))
         }
     };
+    // HSTS hosts are only contacted over https, so the original string is replaced for them
+    let hsts_url = hsts::upgrade(&parsed_url);
+    if let Some(secure_url) = &hsts_url {
+        parsed_url = secure_url.clone();
+    }
+    let url = hsts_url.as_ref().map(Url::as_str).unwrap_or(url);
 
     if block_ads
         && matches!(parsed_url.scheme(), "http" | "https")
@@ -615,6 +741,23 @@ pub fn fetch(url: &str, user_agent: &str, block_ads: bool) -> Result<String, Net
         return Err(NetworkError::Blocked(url.to_string()));
     }
 
+    // Browser pages are generated rather than fetched. A typed URL never acts on the page's links.
+    if passwords::is_management_url(&parsed_url) {
+        return Ok(passwords::management_page(&parsed_url));
+    }
+    if extensions::is_management_url(&parsed_url) {
+        return Ok(extensions::management_page(&parsed_url, false));
+    }
+    if cookies::is_management_url(&parsed_url) {
+        return Ok(cookies::management_page(&parsed_url, false));
+    }
+    if site_storage::is_management_url(&parsed_url) {
+        return Ok(site_storage::management_page(&parsed_url, false));
+    }
+    if extensions::extension_id_of(&parsed_url).is_some() {
+        return extensions::extension_page(&parsed_url).ok_or_else(|| NetworkError::FileNotFound(url.to_string()));
+    }
+
     // Check if it's a local file
     if parsed_url.scheme() == "file" {
         let file_path = url_to_file_path(url);
@@ -627,41 +770,92 @@ pub fn fetch(url: &str, user_agent: &str, block_ads: bool) -> Result<String, Net
     let mut easy = Easy::new();
     let mut data = Vec::new();
     let mut headers = Vec::new();
+    let mut tls = security::TlsInfoCollector::default();
+    let is_https = parsed_url.scheme() == "https";
 
     // Configure curl — use the original URL string to avoid any normalization.
     easy.url(url).map_err(|e| NetworkError::Curl(e.to_string()))?;
     easy.useragent(&user_agent).map_err(|e| NetworkError::Curl(e.to_string()))?;
-    easy.timeout(Duration::from_secs(30)).map_err(|e| NetworkError::Curl(e.to_string()))?;
-    easy.follow_location(true).map_err(|e| NetworkError::Curl(e.to_string()))?;
-    easy.max_redirections(10).map_err(|e| NetworkError::Curl(e.to_string()))?;
+    // Connect and stall timeouts instead of a total one, so large pages on slow links still load
+    policy.configure(&mut easy).map_err(|e| NetworkError::Curl(e.to_string()))?;
+    http_auth::configure(&mut easy, url).map_err(|e| NetworkError::Curl(e.to_string()))?;
+    // Redirects are followed below rather than by curl (see hsts::Redirects)
+    easy.follow_location(false).map_err(|e| NetworkError::Curl(e.to_string()))?;
     // Enable automatic decompression (gzip, deflate, br) so compressed responses
     // such as those returned by Google Search are transparently decoded before
     // we attempt the UTF-8 conversion below.
     easy.accept_encoding("").map_err(|e| NetworkError::Curl(e.to_string()))?;
+    // The TLS handshake details only show up in curl's verbose output
+    easy.verbose(is_https).map_err(|e| NetworkError::Curl(e.to_string()))?;
 
     // Send browser-like request headers so servers such as Google do not treat
     // this as a plain bot request and return 4xx responses.
-    let mut req_headers = List::new();
-    req_headers.append("Accept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
+    let req_headers = vec![
+        "Accept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8".to_string(),
+        languages::accept_language_header(),
+    ];
+    let mut redirects = hsts::Redirects::new(&parsed_url, "GET", req_headers, None);
+    easy.http_headers(redirects.header_list().map_err(|e| NetworkError::Curl(e.to_string()))?)
         .map_err(|e| NetworkError::Curl(e.to_string()))?;
-    req_headers.append("Accept-Language: en-US,en;q=0.5")
-        .map_err(|e| NetworkError::Curl(e.to_string()))?;
-    easy.http_headers(req_headers).map_err(|e| NetworkError::Curl(e.to_string()))?;
 
-    // Set up data collection
-    {
-        let mut transfer = easy.transfer();
-        transfer.write_function(|new_data| {
-            data.extend_from_slice(new_data);
-            Ok(new_data.len())
-        }).map_err(|e| NetworkError::Curl(e.to_string()))?;
-                
-        transfer.header_function(|header| {
-            headers.push(String::from_utf8_lossy(header).to_string());
-            true
-        }).map_err(|e| NetworkError::Curl(e.to_string()))?;
-                
-        transfer.perform().map_err(|e| NetworkError::Curl(e.to_string()))?;
+    // Set up data collection; failed attempts are retried from scratch
+    loop {
+        let mut attempt = 0;
+        loop {
+            data.clear();
+            headers.clear();
+            tls = security::TlsInfoCollector::default();
+
+            let result = {
+                let mut transfer = easy.transfer();
+                transfer.write_function(|new_data| {
+                    data.extend_from_slice(new_data);
+                    Ok(new_data.len())
+                }).map_err(|e| NetworkError::Curl(e.to_string()))?;
+
+                transfer.header_function(|header| {
+                    headers.push(String::from_utf8_lossy(header).to_string());
+                    true
+                }).map_err(|e| NetworkError::Curl(e.to_string()))?;
+
+                transfer.debug_function(|kind, data| {
+                    if matches!(kind, InfoType::Text) {
+                        tls.feed(data);
+                    }
+                }).map_err(|e| NetworkError::Curl(e.to_string()))?;
+
+                transfer.perform()
+            };
+
+            match result {
+                Ok(()) => break,
+                Err(err) if policy.should_retry("GET", attempt, &err) => {
+                    let delay = policy.retry_delay(attempt);
+                    tracing::warn!("Fetching {url} failed ({err}), retrying in {delay:?}");
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(err) if request_policy::is_timeout(&err) => return Err(NetworkError::Timeout(url.to_string())),
+                Err(err) => return Err(NetworkError::Curl(err.to_string())),
+            }
+        }
+
+        let hop_hsts = response_header(&headers, "strict-transport-security");
+        if redirects.follow(&mut easy, hop_hsts).map_err(|e| NetworkError::Curl(e.to_string()))?.is_none() {
+            break;
+        }
+    }
+
+    let final_url = easy.effective_url().ok().flatten().unwrap_or(url).to_string();
+    if let Some(info) = tls.finish() {
+        security::record(&final_url, info);
+    }
+    // Headers of informational responses are collected too; only the final response's count
+    let final_headers = headers.iter()
+        .rposition(|h| h.starts_with("HTTP/"))
+        .map_or(&headers[..], |start| &headers[start..]);
+    if let Some(value) = response_header(final_headers, "strict-transport-security") {
+        hsts::record(&final_url, value);
     }
 
     // Check response code — but don't discard the body just because the status
@@ -671,10 +865,29 @@ pub fn fetch(url: &str, user_agent: &str, block_ads: bool) -> Result<String, Net
     // as a hard failure when the response is completely empty AND the status
     // indicates an error.
     let response_code = easy.response_code().map_err(|e| NetworkError::Curl(e.to_string()))?;
+    if response_code == 401 {
+        let challenge = final_headers.iter()
+            .filter_map(|h| h.split_once(':'))
+            .filter(|(name, _)| name.trim().eq_ignore_ascii_case("www-authenticate"))
+            .find_map(|(_, value)| http_auth::AuthChallenge::parse(value));
+        if let Some(challenge) = challenge {
+            http_auth::record_challenge(&final_url, challenge);
+        }
+    }
     if response_code >= 400 && data.is_empty() {
         return Err(NetworkError::Http(response_code));
     }
 
+    // The document's referrer policy is applied once its Dom exists (see Dom::parse_html)
+    let referrer_policy = response_header(final_headers, "referrer-policy")
+        .and_then(referrer::ReferrerPolicy::from_header);
+    if let Some(policy) = referrer_policy {
+        referrer::record_document_policy(url, policy);
+    }
+    if let Some(value) = response_header(final_headers, "refresh") {
+        refresh::record_document_refresh(url, value);
+    }
+
     // Check content type
     let content_type = headers.iter()
         .find(|h| h.to_lowercase().starts_with("content-type:"))
@@ -683,7 +896,7 @@ pub fn fetch(url: &str, user_agent: &str, block_ads: bool) -> Result<String, Net
         .unwrap_or("text/html");
 
     if !content_type.contains("text/html") {
-        println!("Warning: Content type is {}, not HTML", content_type);
+        tracing::debug!("Content type is {}, not HTML", content_type);
     }
 
     // Convert to string
@@ -0,0 +1,74 @@
+// Backing store for blob: URLs created with URL.createObjectURL.
+//
+// Each tab process hosts a single document, so the store is process-wide and cleared when the
+// document goes away. Both the page's fetch() and the resource loader resolve blob: URLs here.
+
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+
+/// Contents of a registered Blob
+#[derive(Debug, Clone)]
+pub struct BlobEntry {
+    pub bytes: Arc<Vec<u8>>,
+    pub content_type: String,
+}
+
+static BLOB_URLS: LazyLock<Mutex<HashMap<String, BlobEntry>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Register `bytes` and return a fresh `blob:<origin>/<uuid>` URL for them
+pub fn register(origin: &str, bytes: Vec<u8>, content_type: String) -> String {
+    let url = format!("blob:{}/{}", origin, random_uuid());
+    let entry = BlobEntry { bytes: Arc::new(bytes), content_type };
+    if let Ok(mut urls) = BLOB_URLS.lock() {
+        urls.insert(url.clone(), entry);
+    }
+    url
+}
+
+/// Look up a blob: URL. Fragments are ignored, as they are for any other fetch.
+pub fn resolve(url: &str) -> Option<BlobEntry> {
+    let key = url.split_once('#').map_or(url, |(key, _)| key);
+    BLOB_URLS.lock().ok()?.get(key).cloned()
+}
+
+pub fn revoke(url: &str) {
+    if let Ok(mut urls) = BLOB_URLS.lock() {
+        urls.remove(url);
+    }
+}
+
+/// Forget every URL; called when the document that created them is replaced
+pub fn revoke_all() {
+    if let Ok(mut urls) = BLOB_URLS.lock() {
+        urls.clear();
+    }
+}
+
+fn random_uuid() -> String {
+    let mut bytes = [0u8; 16];
+    rand::rng().fill_bytes(&mut bytes);
+    // Version 4, RFC 4122 variant
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{register, resolve, revoke};
+
+    #[test]
+    fn registered_blobs_resolve_until_revoked() {
+        let url = register("https://example.com", b"hello".to_vec(), "text/plain".to_string());
+        assert!(url.starts_with("blob:https://example.com/"));
+        assert_eq!(url.len(), "blob:https://example.com/".len() + 36);
+
+        let entry = resolve(&format!("{url}#section")).expect("fragment is ignored");
+        assert_eq!((entry.bytes.as_slice(), entry.content_type.as_str()), (&b"hello"[..], "text/plain"));
+
+        revoke(&url);
+        assert!(resolve(&url).is_none());
+    }
+}
@@ -0,0 +1,204 @@
+// Network condition emulation, for testing loading states and error pages without touching the
+// real network. The conditions take the parameters of the DevTools protocol's
+// Network.emulateNetworkConditions command. Each tab process holds its own, set by the browser,
+// and every curl transfer it starts follows them through `RequestPolicy`.
+
+use curl::easy::{Easy, Easy2, Handler};
+use serde::{Deserialize, Serialize};
+use std::sync::{LazyLock, RwLock};
+use std::time::Duration;
+
+/// The DevTools protocol command these conditions come from
+const EMULATE_COMMAND: &str = "Network.emulateNetworkConditions";
+
+/// curl's CURLE_COULDNT_CONNECT, which offline transfers fail with
+const COULDNT_CONNECT: i32 = 7;
+
+/// Emulated network conditions. Throughputs are in bytes per second, with zero or less meaning
+/// no limit, as in the DevTools protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkConditions {
+    #[serde(default)]
+    pub offline: bool,
+    /// Milliseconds added before every request
+    #[serde(default)]
+    pub latency: f64,
+    #[serde(default = "no_limit")]
+    pub download_throughput: f64,
+    #[serde(default = "no_limit")]
+    pub upload_throughput: f64,
+}
+
+const fn no_limit() -> f64 {
+    -1.0
+}
+
+impl Default for NetworkConditions {
+    fn default() -> Self {
+        Self { offline: false, latency: 0.0, download_throughput: no_limit(), upload_throughput: no_limit() }
+    }
+}
+
+/// The presets DevTools offers, with its numbers
+const PRESETS: [(&str, NetworkConditions); 3] = [
+    (
+        "Offline",
+        NetworkConditions { offline: true, latency: 0.0, download_throughput: no_limit(), upload_throughput: no_limit() },
+    ),
+    (
+        "Slow 3G",
+        NetworkConditions { offline: false, latency: 2000.0, download_throughput: 50_000.0, upload_throughput: 50_000.0 },
+    ),
+    (
+        "Fast 3G",
+        NetworkConditions { offline: false, latency: 562.5, download_throughput: 180_000.0, upload_throughput: 84_375.0 },
+    ),
+];
+
+impl NetworkConditions {
+    /// Conditions from what the user entered: blank or "none" for the real network, a preset
+    /// name, or a `Network.emulateNetworkConditions` command or just its params, as JSON
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        if input.is_empty() || input.eq_ignore_ascii_case("none") {
+            return Ok(Self::default());
+        }
+        if let Some((_, conditions)) = PRESETS.iter().find(|(name, _)| name.eq_ignore_ascii_case(input)) {
+            return Ok(*conditions);
+        }
+        if !input.starts_with('{') {
+            return Err(format!("Unknown network conditions \"{input}\""));
+        }
+
+        let command: serde_json::Value = serde_json::from_str(input).map_err(|e| e.to_string())?;
+        let params = match command.get("method").and_then(serde_json::Value::as_str) {
+            Some(EMULATE_COMMAND) => command.get("params").cloned().unwrap_or_else(|| serde_json::json!({})),
+            Some(method) => return Err(format!("Unsupported command {method}; use {EMULATE_COMMAND}")),
+            None => command,
+        };
+        serde_json::from_value(params).map_err(|e| e.to_string())
+    }
+
+    /// Whether these differ from the real network
+    pub fn is_emulated(&self) -> bool {
+        self.offline || self.latency > 0.0 || self.download_throughput > 0.0 || self.upload_throughput > 0.0
+    }
+
+    /// The preset name, or a short description of custom conditions
+    pub fn label(&self) -> String {
+        if !self.is_emulated() {
+            return "No throttling".to_string();
+        }
+        if let Some((name, _)) = PRESETS.iter().find(|(_, preset)| preset == self) {
+            return name.to_string();
+        }
+        if self.offline {
+            return "Offline".to_string();
+        }
+        let rate = |bytes_per_second: f64| {
+            if bytes_per_second > 0.0 { format!("{:.0} kB/s", bytes_per_second / 1000.0) } else { "unlimited".to_string() }
+        };
+        format!(
+            "{:.0} ms, {} down, {} up",
+            self.latency,
+            rate(self.download_throughput),
+            rate(self.upload_throughput)
+        )
+    }
+
+    /// The text `parse` reads back as these conditions, to prefill the prompt with
+    pub fn to_input(&self) -> String {
+        if !self.is_emulated() {
+            return String::new();
+        }
+        if let Some((name, _)) = PRESETS.iter().find(|(_, preset)| preset == self) {
+            return name.to_string();
+        }
+        serde_json::json!({ "method": EMULATE_COMMAND, "params": self }).to_string()
+    }
+
+    /// Hold a transfer back for the latency, then fail it when offline. Otherwise returns the
+    /// download and upload speed caps to set on it.
+    fn start_transfer(&self) -> Result<(Option<u64>, Option<u64>), curl::Error> {
+        if self.latency > 0.0 {
+            // Transfers block their thread in perform() anyway, so waiting here delays the
+            // request the way a slow round trip would
+            std::thread::sleep(Duration::from_secs_f64(self.latency / 1000.0));
+        }
+        if self.offline {
+            return Err(curl::Error::new(COULDNT_CONNECT));
+        }
+        let cap = |bytes_per_second: f64| (bytes_per_second > 0.0).then_some(bytes_per_second as u64);
+        Ok((cap(self.download_throughput), cap(self.upload_throughput)))
+    }
+}
+
+/// This process's conditions, from the last `SetNetworkConditions` message
+static CONDITIONS: LazyLock<RwLock<NetworkConditions>> = LazyLock::new(|| RwLock::new(NetworkConditions::default()));
+
+pub fn set(conditions: NetworkConditions) {
+    if let Ok(mut current) = CONDITIONS.write() {
+        *current = conditions;
+    }
+}
+
+pub fn current() -> NetworkConditions {
+    CONDITIONS.read().map(|conditions| *conditions).unwrap_or_default()
+}
+
+pub(crate) fn configure(easy: &mut Easy) -> Result<(), curl::Error> {
+    let (download, upload) = current().start_transfer()?;
+    if let Some(speed) = download {
+        easy.max_recv_speed(speed)?;
+    }
+    if let Some(speed) = upload {
+        easy.max_send_speed(speed)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn configure_easy2<H: Handler>(easy: &mut Easy2<H>) -> Result<(), curl::Error> {
+    let (download, upload) = current().start_transfer()?;
+    if let Some(speed) = download {
+        easy.max_recv_speed(speed)?;
+    }
+    if let Some(speed) = upload {
+        easy.max_send_speed(speed)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NetworkConditions;
+
+    #[test]
+    fn parses_presets_and_devtools_commands() {
+        assert_eq!(NetworkConditions::parse("").unwrap(), NetworkConditions::default());
+        assert!(NetworkConditions::parse("offline").unwrap().offline);
+        assert_eq!(NetworkConditions::parse("Slow 3G").unwrap().latency, 2000.0);
+
+        let command = r#"{"id": 3, "method": "Network.emulateNetworkConditions",
+            "params": {"offline": false, "latency": 150, "downloadThroughput": 25000, "uploadThroughput": -1}}"#;
+        let conditions = NetworkConditions::parse(command).unwrap();
+        assert_eq!(conditions.latency, 150.0);
+        assert_eq!(conditions.download_throughput, 25_000.0);
+        assert!(conditions.is_emulated());
+        assert_eq!(NetworkConditions::parse(&conditions.to_input()).unwrap(), conditions);
+
+        // Just the params work too
+        assert_eq!(NetworkConditions::parse(r#"{"latency": 150}"#).unwrap().latency, 150.0);
+        assert!(NetworkConditions::parse(r#"{"method": "Network.enable"}"#).is_err());
+        assert!(NetworkConditions::parse("dial-up").is_err());
+    }
+
+    #[test]
+    fn labels_name_presets() {
+        assert_eq!(NetworkConditions::default().label(), "No throttling");
+        assert_eq!(NetworkConditions::parse("fast 3g").unwrap().label(), "Fast 3G");
+        assert_eq!(NetworkConditions::parse("Fast 3G").unwrap().to_input(), "Fast 3G");
+        let custom = NetworkConditions { latency: 100.0, download_throughput: 64_000.0, ..Default::default() };
+        assert_eq!(custom.label(), "100 ms, 64 kB/s down, unlimited up");
+    }
+}
@@ -0,0 +1,521 @@
+// HTTP Strict Transport Security: hosts that asked (or are preloaded) to only be reached over
+// https://. Strict-Transport-Security headers of secure responses are remembered in hsts.json in
+// the config directory, which every tab process shares; http:// and ws:// URLs for those hosts are
+// upgraded before any request is made. Redirects are followed by hand (see `Redirects`), so the
+// hops of a redirect chain are upgraded too.
+
+use super::http_auth;
+use super::referrer::RequestReferrer;
+use crate::engine::mixed_content;
+use curl::easy::{Easy, Easy2, Handler, List};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+const STORAGE_VERSION: u32 = 1;
+const HSTS_FILE: &str = "hsts.json";
+
+/// A subset of the browser HSTS preload list: (host, include subdomains). Whole TLDs such as
+/// .dev are preloaded by their registries.
+const PRELOADED: &[(&str, bool)] = &[
+    ("app", true),
+    ("dev", true),
+    ("page", true),
+    ("bank", true),
+    ("insurance", true),
+    ("foo", true),
+    ("google", true),
+    ("google.com", true),
+    ("youtube.com", true),
+    ("gmail.com", true),
+    ("github.com", true),
+    ("githubusercontent.com", true),
+    ("gitlab.com", true),
+    ("crates.io", true),
+    ("docs.rs", true),
+    ("rust-lang.org", true),
+    ("mozilla.org", true),
+    ("wikipedia.org", true),
+    ("twitter.com", true),
+    ("x.com", true),
+    ("facebook.com", true),
+    ("paypal.com", false),
+    ("dropbox.com", true),
+    ("stripe.com", true),
+    ("cloudflare.com", true),
+    ("duckduckgo.com", true),
+];
+
+/// A Strict-Transport-Security policy learned from a response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HstsEntry {
+    /// Unix time (seconds) the policy expires at
+    pub expires: u64,
+    pub include_subdomains: bool,
+}
+
+/// The directives of a Strict-Transport-Security header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StsDirectives {
+    pub max_age: u64,
+    pub include_subdomains: bool,
+}
+
+impl StsDirectives {
+    /// Parse a Strict-Transport-Security header value. Headers without a valid max-age or with
+    /// a repeated directive are ignored, as the RFC requires.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut max_age = None;
+        let mut include_subdomains = false;
+        let mut seen = Vec::new();
+        for directive in value.split(';') {
+            let (name, argument) = match directive.split_once('=') {
+                Some((name, argument)) => (name.trim(), Some(argument.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+            if name.is_empty() {
+                continue;
+            }
+            let name = name.to_ascii_lowercase();
+            if seen.contains(&name) {
+                return None;
+            }
+            match name.as_str() {
+                "max-age" => max_age = Some(argument?.parse::<u64>().ok()?),
+                "includesubdomains" => include_subdomains = true,
+                _ => {}
+            }
+            seen.push(name);
+        }
+        Some(Self { max_age: max_age?, include_subdomains })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PersistedHsts {
+    #[serde(default = "default_storage_version")]
+    version: u32,
+    #[serde(default)]
+    hosts: BTreeMap<String, HstsEntry>,
+}
+
+const fn default_storage_version() -> u32 {
+    STORAGE_VERSION
+}
+
+/// Known HSTS hosts, keyed by lowercase host name
+#[derive(Debug, Clone, Default)]
+pub struct HstsStore {
+    hosts: BTreeMap<String, HstsEntry>,
+}
+
+impl HstsStore {
+    /// Apply a Strict-Transport-Security header received from `host` over https. max-age=0
+    /// removes the host.
+    pub fn apply(&mut self, host: &str, directives: StsDirectives, now: u64) {
+        let Some(host) = normalize_host(host) else {
+            return;
+        };
+        if directives.max_age == 0 {
+            self.hosts.remove(&host);
+            return;
+        }
+        let entry = HstsEntry {
+            expires: now.saturating_add(directives.max_age),
+            include_subdomains: directives.include_subdomains,
+        };
+        self.hosts.insert(host, entry);
+    }
+
+    /// Whether `host` must only be contacted over https at time `now`
+    pub fn is_known(&self, host: &str, now: u64) -> bool {
+        let Some(host) = normalize_host(host) else {
+            return false;
+        };
+        superdomains(&host).enumerate().any(|(depth, domain)| {
+            let exact = depth == 0;
+            let learned = self.hosts.get(domain)
+                .is_some_and(|entry| entry.expires > now && (exact || entry.include_subdomains));
+            let preloaded = PRELOADED.iter()
+                .any(|&(preloaded, include_subdomains)| preloaded == domain && (exact || include_subdomains));
+            learned || preloaded
+        })
+    }
+
+    fn remove_expired(&mut self, now: u64) {
+        self.hosts.retain(|_, entry| entry.expires > now);
+    }
+}
+
+/// `host` and each of its parent domains, most specific first
+fn superdomains(host: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(host), |domain| domain.split_once('.').map(|(_, parent)| parent))
+}
+
+/// Lowercase domain name without a trailing dot; IP addresses never get HSTS
+fn normalize_host(host: &str) -> Option<String> {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    let is_ip = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>().is_ok();
+    (!host.is_empty() && !is_ip).then_some(host)
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn hsts_file_path() -> PathBuf {
+    crate::profile::data_dir().join(HSTS_FILE)
+}
+
+/// The store as last read from disk, with the file's modification time so changes made by other
+/// tab processes are picked up
+struct SharedStore {
+    store: HstsStore,
+    modified: Option<SystemTime>,
+}
+
+static STORE: LazyLock<Mutex<SharedStore>> = LazyLock::new(|| Mutex::new(SharedStore { store: HstsStore::default(), modified: None }));
+
+impl SharedStore {
+    fn refresh(&mut self) {
+        let path = hsts_file_path();
+        let modified = std::fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+        if modified.is_none() || modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+        if let Ok(contents) = std::fs::read_to_string(&path) {
+            if let Ok(persisted) = serde_json::from_str::<PersistedHsts>(&contents) {
+                self.store.hosts = persisted.hosts;
+            }
+        }
+    }
+
+    fn save(&mut self) {
+        self.store.remove_expired(now());
+        let payload = PersistedHsts { version: STORAGE_VERSION, hosts: self.store.hosts.clone() };
+        let Ok(json) = serde_json::to_string_pretty(&payload) else {
+            return;
+        };
+        let path = hsts_file_path();
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if std::fs::write(&path, json).is_ok() {
+            self.modified = std::fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+        }
+    }
+}
+
+/// Remember the Strict-Transport-Security header of a response from `url`. Only headers sent
+/// over https count, so a network attacker cannot set or clear them.
+pub fn record(url: &str, header_value: &str) {
+    let Ok(url) = Url::parse(url) else {
+        return;
+    };
+    let (Some(host), Some(directives)) = (url.host_str(), StsDirectives::parse(header_value)) else {
+        return;
+    };
+    if url.scheme() != "https" {
+        return;
+    }
+    let Ok(mut shared) = STORE.lock() else {
+        return;
+    };
+    shared.refresh();
+    let before = shared.store.hosts.clone();
+    shared.store.apply(host, directives, now());
+    // Sites send the header on every response; only write when something changed noticeably
+    let changed = shared.store.hosts.len() != before.len()
+        || shared.store.hosts.iter().any(|(host, entry)| {
+            before.get(host).is_none_or(|old| {
+                old.include_subdomains != entry.include_subdomains || entry.expires.abs_diff(old.expires) > 24 * 60 * 60
+            })
+        });
+    if changed {
+        shared.save();
+    }
+}
+
+/// The https:// (or wss://) URL to use instead of `url` when its host is an HSTS host
+pub fn upgrade(url: &Url) -> Option<Url> {
+    if !matches!(url.scheme(), "http" | "ws") {
+        return None;
+    }
+    let host = url.host_str()?;
+    let known = {
+        let mut shared = STORE.lock().ok()?;
+        shared.refresh();
+        shared.store.is_known(host, now())
+    };
+    if !known {
+        return None;
+    }
+    mixed_content::upgrade(url)
+}
+
+/// Like `upgrade`, for URL strings; returns the input unchanged when nothing applies
+pub fn upgrade_str(url: &str) -> String {
+    Url::parse(url).ok().as_ref().and_then(upgrade).map(String::from).unwrap_or_else(|| url.to_string())
+}
+
+/// Most redirects a request follows, as in the Fetch standard
+pub const MAX_REDIRECTS: u32 = 20;
+
+/// Where a redirect response with `status` sends the request next, given curl's resolved
+/// redirect URL
+fn redirect_target(status: u32, redirect_url: Option<&str>) -> Option<Url> {
+    if !matches!(status, 301 | 302 | 303 | 307 | 308) {
+        return None;
+    }
+    Url::parse(redirect_url?).ok()
+}
+
+/// Whether following a redirect with `status` turns a `method` request into a GET without a body,
+/// as curl (and every browser) does when it follows redirects itself
+fn redirect_becomes_get(status: u32, method: &str) -> bool {
+    (status == 303 && method != "HEAD") || (matches!(status, 301 | 302) && method == "POST")
+}
+
+fn is_header(line: &str, name: &str) -> bool {
+    line.split_once(':').is_some_and(|(line_name, _)| line_name.trim().eq_ignore_ascii_case(name))
+}
+
+/// Why a redirect was not followed
+#[derive(Debug)]
+pub enum RedirectError {
+    /// The request was redirected more than `MAX_REDIRECTS` times
+    TooMany,
+    /// A hop to a URL that may not be fetched, such as a file:// URL or another protocol curl speaks
+    Blocked(Url),
+    Curl(curl::Error),
+}
+
+impl From<curl::Error> for RedirectError {
+    fn from(value: curl::Error) -> Self {
+        Self::Curl(value)
+    }
+}
+
+impl fmt::Display for RedirectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooMany => write!(f, "Maximum ({MAX_REDIRECTS}) redirects followed"),
+            Self::Blocked(url) => write!(f, "Redirect to {url} blocked"),
+            Self::Curl(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+/// A curl handle whose redirects are followed by `Redirects`
+pub trait RedirectHandle {
+    fn response_status(&mut self) -> Result<u32, curl::Error>;
+    fn location(&mut self) -> Result<Option<String>, curl::Error>;
+    fn hop_url(&mut self) -> Result<Option<String>, curl::Error>;
+    /// Point the handle at the next hop, with that hop's headers and credentials
+    fn prepare_hop(&mut self, url: &Url, headers: List, becomes_get: bool) -> Result<(), curl::Error>;
+}
+
+impl RedirectHandle for Easy {
+    fn response_status(&mut self) -> Result<u32, curl::Error> {
+        self.response_code()
+    }
+
+    fn location(&mut self) -> Result<Option<String>, curl::Error> {
+        Ok(self.redirect_url()?.map(str::to_string))
+    }
+
+    fn hop_url(&mut self) -> Result<Option<String>, curl::Error> {
+        Ok(self.effective_url()?.map(str::to_string))
+    }
+
+    fn prepare_hop(&mut self, url: &Url, headers: List, becomes_get: bool) -> Result<(), curl::Error> {
+        if becomes_get {
+            self.get(true)?;
+        }
+        self.url(url.as_str())?;
+        self.http_headers(headers)?;
+        http_auth::configure(self, url.as_str())
+    }
+}
+
+impl<H: Handler> RedirectHandle for Easy2<H> {
+    fn response_status(&mut self) -> Result<u32, curl::Error> {
+        self.response_code()
+    }
+
+    fn location(&mut self) -> Result<Option<String>, curl::Error> {
+        Ok(self.redirect_url()?.map(str::to_string))
+    }
+
+    fn hop_url(&mut self) -> Result<Option<String>, curl::Error> {
+        Ok(self.effective_url()?.map(str::to_string))
+    }
+
+    fn prepare_hop(&mut self, url: &Url, headers: List, becomes_get: bool) -> Result<(), curl::Error> {
+        if becomes_get {
+            self.get(true)?;
+        }
+        self.url(url.as_str())?;
+        self.http_headers(headers)?;
+        http_auth::configure_easy2(self, url.as_str())
+    }
+}
+
+/// Follows the redirects of one request by hand. curl's own redirect following never looks at the
+/// HSTS store, so fetches turn it off and call `follow` after every response instead. Each hop to
+/// an HSTS host is upgraded, not just the first URL, and each hop gets its own credentials and
+/// Referer and Origin headers.
+pub struct Redirects {
+    url: Url,
+    method: String,
+    headers: Vec<String>,
+    referrer: Option<RequestReferrer>,
+    /// Run on every hop's URL after the HSTS upgrade; see `with_filter`
+    filter: Option<Box<dyn Fn(&Url) -> Option<Url> + Send>>,
+    count: u32,
+}
+
+impl Redirects {
+    /// `headers` are the request's own header lines, without Referer and Origin
+    pub fn new(url: &Url, method: &str, headers: Vec<String>, referrer: Option<RequestReferrer>) -> Self {
+        Self {
+            url: url.clone(),
+            method: method.to_string(),
+            headers,
+            referrer,
+            filter: None,
+            count: 0,
+        }
+    }
+
+    /// Check every hop with `filter`, which returns the URL to fetch instead (such as a mixed
+    /// content upgrade) or `None` to fail the request as blocked
+    pub fn with_filter(mut self, filter: impl Fn(&Url) -> Option<Url> + Send + 'static) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Method of the current hop
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Header lines for the current hop: the request's own, then its Referer and Origin
+    pub fn header_list(&self) -> Result<List, curl::Error> {
+        let mut list = List::new();
+        for header in &self.headers {
+            list.append(header)?;
+        }
+        let referrer_headers = self.referrer.iter().flat_map(|referrer| referrer.headers(&self.url, &self.method));
+        for (name, value) in referrer_headers {
+            list.append(&format!("{name}: {value}"))?;
+        }
+        Ok(list)
+    }
+
+    /// Call once a response has arrived, with its Strict-Transport-Security header. When it is a
+    /// redirect, `easy` is set up for the next hop and that hop's URL is returned; `None` means
+    /// the response is the final one.
+    pub fn follow(&mut self, easy: &mut impl RedirectHandle, strict_transport_security: Option<&str>) -> Result<Option<Url>, RedirectError> {
+        let status = easy.response_status()?;
+        let Some(next_url) = redirect_target(status, easy.location()?.as_deref()) else {
+            return Ok(None);
+        };
+        // curl would fetch file://, gopher:// and the rest just as readily
+        if !matches!(next_url.scheme(), "http" | "https") {
+            return Err(RedirectError::Blocked(next_url));
+        }
+        if self.count == MAX_REDIRECTS {
+            return Err(RedirectError::TooMany);
+        }
+        self.count += 1;
+
+        // Recorded first, so a host redirecting its own http:// URL is upgraded straight away
+        if let (Some(value), Some(hop_url)) = (strict_transport_security, easy.hop_url()?) {
+            record(&hop_url, value);
+        }
+        let next_url = upgrade(&next_url).unwrap_or(next_url);
+        let next_url = match &self.filter {
+            Some(filter) => match filter(&next_url) {
+                Some(allowed) => allowed,
+                None => return Err(RedirectError::Blocked(next_url)),
+            },
+            None => next_url,
+        };
+
+        let becomes_get = redirect_becomes_get(status, &self.method);
+        if becomes_get {
+            // The body is not sent again, so neither is the header describing it
+            self.method = "GET".to_string();
+            self.headers.retain(|header| !is_header(header, "content-type"));
+        }
+        if next_url.origin() != self.url.origin() {
+            self.headers.retain(|header| !is_header(header, "authorization"));
+        }
+        self.url = next_url.clone();
+        easy.prepare_hop(&next_url, self.header_list()?, becomes_get)?;
+        Ok(Some(next_url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{redirect_becomes_get, redirect_target, HstsStore, StsDirectives};
+
+    #[test]
+    fn parses_header_directives() {
+        assert_eq!(
+            StsDirectives::parse("max-age=31536000; includeSubDomains; preload"),
+            Some(StsDirectives { max_age: 31536000, include_subdomains: true }),
+        );
+        assert_eq!(StsDirectives::parse("max-age=\"600\""), Some(StsDirectives { max_age: 600, include_subdomains: false }));
+        assert_eq!(StsDirectives::parse("includeSubDomains"), None);
+        assert_eq!(StsDirectives::parse("max-age=1; max-age=2"), None);
+    }
+
+    #[test]
+    fn learned_hosts_expire_and_cover_subdomains_on_request() {
+        let mut store = HstsStore::default();
+        store.apply("Secure.Example.", StsDirectives { max_age: 100, include_subdomains: false }, 1000);
+        store.apply("wide.example", StsDirectives { max_age: 100, include_subdomains: true }, 1000);
+
+        assert!(store.is_known("secure.example", 1050));
+        assert!(!store.is_known("www.secure.example", 1050));
+        assert!(store.is_known("a.b.wide.example", 1050));
+        assert!(!store.is_known("secure.example", 1100));
+
+        store.apply("wide.example", StsDirectives { max_age: 0, include_subdomains: true }, 1050);
+        assert!(!store.is_known("wide.example", 1050));
+    }
+
+    #[test]
+    fn preloaded_hosts_and_ip_addresses() {
+        let store = HstsStore::default();
+        assert!(store.is_known("github.com", 0));
+        assert!(store.is_known("gist.github.com", 0));
+        assert!(store.is_known("anything.dev", 0));
+        assert!(!store.is_known("www.paypal.com", 0));
+        assert!(!store.is_known("127.0.0.1", 0));
+        assert!(!store.is_known("example.com", 0));
+    }
+
+    #[test]
+    fn only_redirect_statuses_have_a_target() {
+        assert_eq!(redirect_target(200, Some("https://example.com/next")), None);
+        assert_eq!(redirect_target(302, None), None);
+        assert_eq!(
+            redirect_target(307, Some("https://example.com/next")).as_ref().map(|url| url.as_str()),
+            Some("https://example.com/next"),
+        );
+        assert!(redirect_becomes_get(303, "PUT"));
+        assert!(redirect_becomes_get(302, "POST"));
+        assert!(!redirect_becomes_get(307, "POST"));
+        assert!(!redirect_becomes_get(303, "HEAD"));
+    }
+}
@@ -0,0 +1,168 @@
+// HTTP authentication: when a document is answered with 401 and a Basic or Digest challenge, the
+// challenge is recorded here for its origin. Once the response is shown the tab asks the browser for
+// credentials and reloads with them. Credentials are kept per origin for the life of the tab process
+// and handed to curl, which answers the challenge itself.
+
+use curl::easy::{Auth, Easy, Easy2, Handler};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use url::Url;
+
+/// The authentication schemes curl can answer for us
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuthScheme {
+    Basic,
+    Digest,
+}
+
+/// What a 401 response's WWW-Authenticate header asks for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthChallenge {
+    pub scheme: AuthScheme,
+    /// The server's description of the protected area, shown in the dialog
+    pub realm: String,
+}
+
+impl AuthChallenge {
+    /// Parse a WWW-Authenticate header value, taking the first Basic or Digest challenge. Other
+    /// schemes (Negotiate, Bearer, ...) are not supported and give `None` on their own.
+    pub fn parse(value: &str) -> Option<Self> {
+        let lower = value.to_ascii_lowercase();
+        let (start, scheme) = ["basic", "digest"]
+            .iter()
+            .filter_map(|name| {
+                let start = lower.match_indices(name).map(|(index, _)| index).find(|&index| {
+                    let at_token_start = lower[..index].trim_end().is_empty() || lower[..index].trim_end().ends_with(',');
+                    let followed_by_space = lower[index + name.len()..].chars().next().is_none_or(char::is_whitespace);
+                    at_token_start && followed_by_space
+                })?;
+                Some((start, if *name == "basic" { AuthScheme::Basic } else { AuthScheme::Digest }))
+            })
+            .min_by_key(|(start, _)| *start)?;
+
+        let params = &value[start..];
+        let realm = params
+            .to_ascii_lowercase()
+            .find("realm=")
+            .map(|index| {
+                let rest = &params[index + "realm=".len()..];
+                match rest.strip_prefix('"') {
+                    Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+                    None => rest.split(',').next().unwrap_or_default().trim(),
+                }
+            })
+            .unwrap_or_default()
+            .to_string();
+        Some(Self { scheme, realm })
+    }
+}
+
+struct Credentials {
+    username: String,
+    password: String,
+}
+
+static CHALLENGES: LazyLock<Mutex<HashMap<String, AuthChallenge>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static CREDENTIALS: LazyLock<Mutex<HashMap<String, Credentials>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn origin_of(url: &str) -> Option<String> {
+    let origin = Url::parse(url).ok()?.origin();
+    origin.is_tuple().then(|| origin.ascii_serialization())
+}
+
+/// Remember the challenge a document at `url` was answered with
+pub fn record_challenge(url: &str, challenge: AuthChallenge) {
+    if let (Some(origin), Ok(mut challenges)) = (origin_of(url), CHALLENGES.lock()) {
+        challenges.insert(origin, challenge);
+    }
+}
+
+/// The challenge recorded for the document at `url`, handed out once
+pub fn take_challenge(url: &str) -> Option<AuthChallenge> {
+    CHALLENGES.lock().ok()?.remove(&origin_of(url)?)
+}
+
+/// Use these credentials for every request to `origin` from now on
+pub fn set_credentials(origin: &str, username: String, password: String) {
+    if let Ok(mut credentials) = CREDENTIALS.lock() {
+        credentials.insert(origin.to_string(), Credentials { username, password });
+    }
+}
+
+/// Whether credentials were already sent to `url`'s origin, so a challenge means they were wrong
+pub fn has_credentials(url: &str) -> bool {
+    origin_of(url).is_some_and(|origin| CREDENTIALS.lock().is_ok_and(|credentials| credentials.contains_key(&origin)))
+}
+
+fn credentials_for(url: &str) -> Option<(String, String)> {
+    let origin = origin_of(url)?;
+    let credentials = CREDENTIALS.lock().ok()?;
+    let found = credentials.get(&origin)?;
+    Some((found.username.clone(), found.password.clone()))
+}
+
+fn auth_methods() -> Auth {
+    let mut auth = Auth::new();
+    auth.basic(true).digest(true);
+    auth
+}
+
+/// Send the credentials saved for `url`'s origin, if any. Those set for an earlier URL on the same
+/// handle (a redirect hop or a reused connection) are dropped, so they never reach another origin.
+pub fn configure(easy: &mut Easy, url: &str) -> Result<(), curl::Error> {
+    if let Some((username, password)) = credentials_for(url) {
+        easy.username(&username)?;
+        easy.password(&password)?;
+        easy.http_auth(&auth_methods())?;
+    } else {
+        easy.username("")?;
+        easy.password("")?;
+        easy.http_auth(&Auth::new())?;
+    }
+    Ok(())
+}
+
+pub fn configure_easy2<H: Handler>(easy: &mut Easy2<H>, url: &str) -> Result<(), curl::Error> {
+    if let Some((username, password)) = credentials_for(url) {
+        easy.username(&username)?;
+        easy.password(&password)?;
+        easy.http_auth(&auth_methods())?;
+    } else {
+        easy.username("")?;
+        easy.password("")?;
+        easy.http_auth(&Auth::new())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuthChallenge, AuthScheme};
+
+    #[test]
+    fn parses_basic_and_digest_challenges() {
+        assert_eq!(
+            AuthChallenge::parse("Basic realm=\"Staff only\", charset=\"UTF-8\""),
+            Some(AuthChallenge { scheme: AuthScheme::Basic, realm: "Staff only".to_string() }),
+        );
+        assert_eq!(
+            AuthChallenge::parse("Digest realm=\"api@example.com\", qop=\"auth\", nonce=\"abc\""),
+            Some(AuthChallenge { scheme: AuthScheme::Digest, realm: "api@example.com".to_string() }),
+        );
+        assert_eq!(
+            AuthChallenge::parse("basic realm=intranet"),
+            Some(AuthChallenge { scheme: AuthScheme::Basic, realm: "intranet".to_string() }),
+        );
+    }
+
+    #[test]
+    fn skips_unsupported_schemes() {
+        assert_eq!(
+            AuthChallenge::parse("Negotiate, Basic realm=\"fallback\""),
+            Some(AuthChallenge { scheme: AuthScheme::Basic, realm: "fallback".to_string() }),
+        );
+        assert_eq!(AuthChallenge::parse("Bearer realm=\"api\""), None);
+        assert_eq!(AuthChallenge::parse("Negotiate"), None);
+    }
+}
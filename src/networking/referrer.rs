@@ -0,0 +1,194 @@
+// Referrer policy: what the Referer and Origin request headers reveal about the page that made a
+// request. The policy of a document comes from its Referrer-Policy response header, replaced by a
+// later <meta name="referrer">, and defaults to strict-origin-when-cross-origin.
+//
+// Navigation responses are fetched before their Dom exists, so the header policy is kept here per
+// document URL until Dom::parse_html hands it to the document's StokesNetProvider.
+
+use crate::engine::mixed_content;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use url::Url;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReferrerPolicy {
+    NoReferrer,
+    NoReferrerWhenDowngrade,
+    SameOrigin,
+    Origin,
+    StrictOrigin,
+    OriginWhenCrossOrigin,
+    #[default]
+    StrictOriginWhenCrossOrigin,
+    UnsafeUrl,
+}
+
+impl ReferrerPolicy {
+    /// Parse a single policy token, as used by the meta tag and the `referrerPolicy` fetch option
+    pub fn parse(token: &str) -> Option<Self> {
+        Some(match token.trim().to_ascii_lowercase().as_str() {
+            "no-referrer" | "never" => Self::NoReferrer,
+            "no-referrer-when-downgrade" | "default" => Self::NoReferrerWhenDowngrade,
+            "same-origin" => Self::SameOrigin,
+            "origin" => Self::Origin,
+            "strict-origin" => Self::StrictOrigin,
+            "origin-when-cross-origin" => Self::OriginWhenCrossOrigin,
+            "strict-origin-when-cross-origin" => Self::StrictOriginWhenCrossOrigin,
+            "unsafe-url" | "always" => Self::UnsafeUrl,
+            _ => return None,
+        })
+    }
+
+    /// Parse a Referrer-Policy header. It may list several policies for older browsers; the last
+    /// one that is understood wins.
+    pub fn from_header(value: &str) -> Option<Self> {
+        value.split(',').filter_map(Self::parse).last()
+    }
+}
+
+/// The Referer header to send when the document at `document_url` requests `target`
+pub fn referrer(document_url: &Url, target: &Url, policy: ReferrerPolicy) -> Option<String> {
+    if !matches!(document_url.scheme(), "http" | "https") {
+        return None;
+    }
+    let same_origin = document_url.origin() == target.origin();
+    let downgrade = mixed_content::is_potentially_trustworthy(document_url)
+        && !mixed_content::is_potentially_trustworthy(target);
+
+    let full = || {
+        let mut url = document_url.clone();
+        url.set_fragment(None);
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+        url.to_string()
+    };
+    let origin_only = || format!("{}/", document_url.origin().ascii_serialization());
+
+    match policy {
+        ReferrerPolicy::NoReferrer => None,
+        ReferrerPolicy::NoReferrerWhenDowngrade => (!downgrade).then(full),
+        ReferrerPolicy::SameOrigin => same_origin.then(full),
+        ReferrerPolicy::Origin => Some(origin_only()),
+        ReferrerPolicy::StrictOrigin => (!downgrade).then(origin_only),
+        ReferrerPolicy::OriginWhenCrossOrigin => Some(if same_origin { full() } else { origin_only() }),
+        ReferrerPolicy::StrictOriginWhenCrossOrigin if same_origin => Some(full()),
+        ReferrerPolicy::StrictOriginWhenCrossOrigin => (!downgrade).then(origin_only),
+        ReferrerPolicy::UnsafeUrl => Some(full()),
+    }
+}
+
+/// The Origin header for a request. It is sent on CORS requests (cross-origin fetch/XHR) and on
+/// anything that is not a GET or HEAD, such as form posts. Requests that would not reveal a
+/// referrer under `policy` send "null" instead of the origin.
+pub fn origin_header(document_url: &Url, target: &Url, method: &str, cors: bool, policy: ReferrerPolicy) -> Option<String> {
+    let is_safe_method = method.eq_ignore_ascii_case("GET") || method.eq_ignore_ascii_case("HEAD");
+    if !cors && is_safe_method {
+        return None;
+    }
+    let origin = document_url.origin();
+    if !origin.is_tuple() {
+        return Some("null".to_string());
+    }
+    if cors {
+        return Some(origin.ascii_serialization());
+    }
+
+    let same_origin = origin == target.origin();
+    let downgrade = mixed_content::is_potentially_trustworthy(document_url)
+        && !mixed_content::is_potentially_trustworthy(target);
+    let hidden = match policy {
+        ReferrerPolicy::NoReferrer => true,
+        ReferrerPolicy::NoReferrerWhenDowngrade
+        | ReferrerPolicy::StrictOrigin
+        | ReferrerPolicy::StrictOriginWhenCrossOrigin => downgrade,
+        ReferrerPolicy::SameOrigin => !same_origin,
+        ReferrerPolicy::Origin | ReferrerPolicy::OriginWhenCrossOrigin | ReferrerPolicy::UnsafeUrl => false,
+    };
+    Some(if hidden { "null".to_string() } else { origin.ascii_serialization() })
+}
+
+/// What the Referer and Origin headers of a document's request are made from. Every redirect hop
+/// gets its own headers from this rather than those built for the first URL.
+#[derive(Debug, Clone)]
+pub struct RequestReferrer {
+    pub document_url: Url,
+    pub policy: ReferrerPolicy,
+    /// fetch/XHR requests, which are CORS requests when cross-origin
+    pub from_script: bool,
+}
+
+impl RequestReferrer {
+    /// Referer and Origin headers for a `method` request to `url`
+    pub fn headers(&self, url: &Url, method: &str) -> Vec<(&'static str, String)> {
+        let cors = self.from_script && self.document_url.origin() != url.origin();
+        let referer = referrer(&self.document_url, url, self.policy).map(|value| ("Referer", value));
+        let origin = origin_header(&self.document_url, url, method, cors, self.policy).map(|value| ("Origin", value));
+        referer.into_iter().chain(origin).collect()
+    }
+}
+
+static DOCUMENT_POLICIES: LazyLock<Mutex<HashMap<String, ReferrerPolicy>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Remember the Referrer-Policy header a navigation response for `url` was sent with
+pub fn record_document_policy(url: &str, policy: ReferrerPolicy) {
+    if let Ok(mut policies) = DOCUMENT_POLICIES.lock() {
+        policies.insert(url.to_string(), policy);
+    }
+}
+
+/// The header policy recorded for the document at `url`, if any. Each recorded policy is only
+/// handed out once, to the document it was sent with.
+pub fn take_document_policy(url: &str) -> Option<ReferrerPolicy> {
+    DOCUMENT_POLICIES.lock().ok()?.remove(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{origin_header, referrer, ReferrerPolicy};
+    use url::Url;
+
+    fn url(input: &str) -> Url {
+        Url::parse(input).unwrap()
+    }
+
+    #[test]
+    fn default_policy_trims_cross_origin_referrers() {
+        let page = url("https://user:pw@example.com/articles/1?q=2#top");
+        let policy = ReferrerPolicy::default();
+        assert_eq!(
+            referrer(&page, &url("https://example.com/style.css"), policy).as_deref(),
+            Some("https://example.com/articles/1?q=2"),
+        );
+        assert_eq!(referrer(&page, &url("https://cdn.example/lib.js"), policy).as_deref(), Some("https://example.com/"));
+        assert_eq!(referrer(&page, &url("http://cdn.example/lib.js"), policy), None);
+    }
+
+    #[test]
+    fn explicit_policies() {
+        let page = url("https://example.com/private/page");
+        let other = url("https://other.example/");
+        assert_eq!(referrer(&page, &other, ReferrerPolicy::NoReferrer), None);
+        assert_eq!(referrer(&page, &other, ReferrerPolicy::Origin).as_deref(), Some("https://example.com/"));
+        assert_eq!(referrer(&page, &other, ReferrerPolicy::SameOrigin), None);
+        assert_eq!(referrer(&page, &other, ReferrerPolicy::UnsafeUrl).as_deref(), Some("https://example.com/private/page"));
+        assert_eq!(referrer(&url("stokes://newtab"), &other, ReferrerPolicy::UnsafeUrl), None);
+    }
+
+    #[test]
+    fn header_lists_use_the_last_known_policy() {
+        assert_eq!(ReferrerPolicy::from_header("no-referrer, strict-origin"), Some(ReferrerPolicy::StrictOrigin));
+        assert_eq!(ReferrerPolicy::from_header("origin, made-up-policy"), Some(ReferrerPolicy::Origin));
+        assert_eq!(ReferrerPolicy::from_header("made-up-policy"), None);
+    }
+
+    #[test]
+    fn origin_is_sent_for_cors_and_unsafe_methods() {
+        let page = url("https://example.com/form");
+        let api = url("https://api.example/submit");
+        let policy = ReferrerPolicy::default();
+        assert_eq!(origin_header(&page, &api, "GET", false, policy), None);
+        assert_eq!(origin_header(&page, &api, "GET", true, policy).as_deref(), Some("https://example.com"));
+        assert_eq!(origin_header(&page, &api, "POST", false, policy).as_deref(), Some("https://example.com"));
+        assert_eq!(origin_header(&page, &api, "POST", false, ReferrerPolicy::NoReferrer).as_deref(), Some("null"));
+    }
+}
@@ -0,0 +1,116 @@
+// Declarative refresh: the Refresh response header and <meta http-equiv="refresh"> reload the page
+// or move on to another URL after a delay. Legacy sites still use it for redirects.
+//
+// Navigation responses are fetched before their Dom exists, so the header value is kept here per
+// document URL until Dom::parse_html hands it to the document, like the referrer policy.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+/// A parsed refresh declaration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Refresh {
+    pub delay: Duration,
+    /// Where to go, relative to the document; `None` reloads the document itself
+    pub url: Option<String>,
+}
+
+impl Refresh {
+    /// Parse a Refresh header or meta content value such as `5; url=/next`, following the
+    /// HTML spec's shared declarative refresh steps. `None` when it doesn't start with a delay.
+    pub fn parse(value: &str) -> Option<Self> {
+        let input = value.trim_start_matches(|c: char| c.is_ascii_whitespace());
+        let digits = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+        let (seconds, rest) = input.split_at(digits);
+        if seconds.is_empty() && !rest.starts_with('.') {
+            return None;
+        }
+        let delay = Duration::from_secs(seconds.parse().unwrap_or(if seconds.is_empty() { 0 } else { u64::MAX }));
+        // Fractions of a second are allowed but ignored
+        let rest = rest.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+        if rest.is_empty() {
+            return Some(Self { delay, url: None });
+        }
+        if !rest.starts_with([';', ',']) && !rest.starts_with(|c: char| c.is_ascii_whitespace()) {
+            return None;
+        }
+        let rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace());
+        let rest = rest.strip_prefix([';', ',']).unwrap_or(rest);
+        let rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace());
+        if rest.is_empty() {
+            return Some(Self { delay, url: None });
+        }
+
+        // An optional `url=` prefix; `url` without `=` is part of the URL itself
+        let mut url = rest;
+        if rest.get(..3).is_some_and(|prefix| prefix.eq_ignore_ascii_case("url")) {
+            let after = rest[3..].trim_start_matches(|c: char| c.is_ascii_whitespace());
+            if let Some(after) = after.strip_prefix('=') {
+                url = after.trim_start_matches(|c: char| c.is_ascii_whitespace());
+            }
+        }
+        let url = match url.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let quoted = &url[1..];
+                quoted.find(quote).map_or(quoted, |end| &quoted[..end])
+            }
+            _ => url,
+        };
+        let url = url.trim_end_matches(|c: char| c.is_ascii_whitespace());
+        Some(Self { delay, url: Some(url.to_string()) })
+    }
+}
+
+static DOCUMENT_REFRESHES: LazyLock<Mutex<HashMap<String, Refresh>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Remember the Refresh header a navigation response for `url` was sent with
+pub fn record_document_refresh(url: &str, value: &str) {
+    let Some(refresh) = Refresh::parse(value) else {
+        return;
+    };
+    if let Ok(mut refreshes) = DOCUMENT_REFRESHES.lock() {
+        refreshes.insert(url.to_string(), refresh);
+    }
+}
+
+/// The header refresh recorded for the document at `url`, if any. Each is only handed out once,
+/// to the document it was sent with.
+pub fn take_document_refresh(url: &str) -> Option<Refresh> {
+    DOCUMENT_REFRESHES.lock().ok()?.remove(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Refresh;
+    use std::time::Duration;
+
+    fn refresh(seconds: u64, url: Option<&str>) -> Option<Refresh> {
+        Some(Refresh { delay: Duration::from_secs(seconds), url: url.map(str::to_string) })
+    }
+
+    #[test]
+    fn parses_delay_and_url() {
+        assert_eq!(Refresh::parse("5"), refresh(5, None));
+        assert_eq!(Refresh::parse(" 0; url=https://example.com/next"), refresh(0, Some("https://example.com/next")));
+        assert_eq!(Refresh::parse("3,URL = /other "), refresh(3, Some("/other")));
+        assert_eq!(Refresh::parse("1.5 next.html"), refresh(1, Some("next.html")));
+        assert_eq!(Refresh::parse(".5;"), refresh(0, None));
+    }
+
+    #[test]
+    fn quotes_end_the_url() {
+        assert_eq!(Refresh::parse("0; url='/a b' trailing"), refresh(0, Some("/a b")));
+        assert_eq!(Refresh::parse("0; url=\"/open"), refresh(0, Some("/open")));
+        // Without `=`, "url" is where the URL starts
+        assert_eq!(Refresh::parse("0; urlish.html"), refresh(0, Some("urlish.html")));
+    }
+
+    #[test]
+    fn rejects_values_without_a_delay() {
+        assert_eq!(Refresh::parse("url=/next"), None);
+        assert_eq!(Refresh::parse(""), None);
+        assert_eq!(Refresh::parse("5x"), None);
+    }
+}
@@ -0,0 +1,102 @@
+// Timeouts and retries shared by every curl transfer (navigations, subresources and fetch/XHR).
+// Stalled downloads are detected with curl's low speed limit: a transfer that stays below
+// `stall_bytes_per_second` for `stall_timeout` fails with a timeout, like a connect timeout does.
+// Emulated network conditions are applied here too, as every transfer passes through.
+
+use crate::networking::conditions;
+use curl::easy::{Easy, Easy2, Handler};
+use std::time::Duration;
+
+/// How long requests may take and how failed ones are retried, set through EngineConfig
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestPolicy {
+    /// Time allowed for DNS resolution and the TCP/TLS handshake
+    pub connect_timeout: Duration,
+    /// A transfer slower than `stall_bytes_per_second` for this long is abandoned
+    pub stall_timeout: Duration,
+    pub stall_bytes_per_second: u32,
+    /// How often idempotent requests are retried after a connection failure or timeout
+    pub max_retries: u32,
+    /// Delay before the first retry; it doubles for every further attempt
+    pub retry_backoff: Duration,
+}
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(15),
+            stall_timeout: Duration::from_secs(30),
+            stall_bytes_per_second: 1,
+            max_retries: 2,
+            retry_backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+impl RequestPolicy {
+    pub fn configure(&self, easy: &mut Easy) -> Result<(), curl::Error> {
+        easy.connect_timeout(self.connect_timeout)?;
+        easy.low_speed_limit(self.stall_bytes_per_second)?;
+        easy.low_speed_time(self.stall_timeout)?;
+        conditions::configure(easy)
+    }
+
+    pub fn configure_easy2<H: Handler>(&self, easy: &mut Easy2<H>) -> Result<(), curl::Error> {
+        easy.connect_timeout(self.connect_timeout)?;
+        easy.low_speed_limit(self.stall_bytes_per_second)?;
+        easy.low_speed_time(self.stall_timeout)?;
+        conditions::configure_easy2(easy)
+    }
+
+    /// Whether attempt number `attempt` (starting at 0) of a `method` request that failed with
+    /// `error` should be tried again
+    pub fn should_retry(&self, method: &str, attempt: u32, error: &curl::Error) -> bool {
+        attempt < self.max_retries && is_idempotent(method) && is_transient(error)
+    }
+
+    /// How long to wait before retrying after attempt number `attempt` failed
+    pub fn retry_delay(&self, attempt: u32) -> Duration {
+        self.retry_backoff.saturating_mul(1 << attempt.min(16))
+    }
+}
+
+/// Requests that can be sent twice without changing their effect
+pub fn is_idempotent(method: &str) -> bool {
+    matches!(method.to_ascii_uppercase().as_str(), "GET" | "HEAD" | "OPTIONS" | "PUT" | "DELETE" | "TRACE")
+}
+
+/// Connect, read and stall timeouts all surface as curl's operation timeout
+pub fn is_timeout(error: &curl::Error) -> bool {
+    error.is_operation_timedout()
+}
+
+/// Failures that may go away on their own, as opposed to e.g. bad URLs or certificate errors
+fn is_transient(error: &curl::Error) -> bool {
+    error.is_operation_timedout()
+        || error.is_couldnt_connect()
+        || error.is_send_error()
+        || error.is_recv_error()
+        || error.is_got_nothing()
+        || error.is_partial_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_idempotent, RequestPolicy};
+    use std::time::Duration;
+
+    #[test]
+    fn backoff_doubles_per_attempt() {
+        let policy = RequestPolicy { retry_backoff: Duration::from_millis(250), ..Default::default() };
+        assert_eq!(policy.retry_delay(0), Duration::from_millis(250));
+        assert_eq!(policy.retry_delay(2), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn only_idempotent_methods_are_retried() {
+        assert!(is_idempotent("get"));
+        assert!(is_idempotent("PUT"));
+        assert!(!is_idempotent("POST"));
+        assert!(!is_idempotent("PATCH"));
+    }
+}
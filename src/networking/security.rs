@@ -0,0 +1,194 @@
+// Connection security of the pages a tab shows, for the site information panel in the address bar.
+//
+// curl reports the negotiated TLS parameters and the server certificate in its verbose output, so
+// both fetch paths (networking::fetch and StokesNetProvider) feed that text into a TlsInfoCollector
+// for https:// requests and record the result per host. After a navigation the tab process looks
+// the page's host up and sends a SecurityState to the parent.
+
+use crate::engine::mixed_content::{self, MixedContentStatus};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use url::Url;
+
+/// Server certificate details, as printed by curl
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub valid_from: String,
+    pub valid_until: String,
+}
+
+/// Negotiated parameters of a TLS connection
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsInfo {
+    /// Protocol version, e.g. "TLSv1.3"
+    pub version: String,
+    pub cipher: String,
+    pub certificate: Option<CertificateInfo>,
+    /// Whether curl verified the certificate chain
+    pub verified: bool,
+}
+
+/// How the active page was delivered, summarized for the address bar icon
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SecurityLevel {
+    /// Browser and local pages (stokes:, about:, file:, data:)
+    #[default]
+    Internal,
+    /// Loaded over https:// without insecure content
+    Secure,
+    /// Loaded over https://, but insecure content was displayed
+    PartiallySecure,
+    /// Loaded over http://
+    NotSecure,
+}
+
+/// Security details of a tab's current page
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecurityState {
+    pub level: SecurityLevel,
+    /// Host the page was loaded from, if it has one
+    pub host: Option<String>,
+    pub tls: Option<TlsInfo>,
+    pub mixed_content: MixedContentStatus,
+}
+
+impl SecurityState {
+    pub fn for_page(url: &str, mixed_content: MixedContentStatus) -> Self {
+        let Ok(url) = Url::parse(url) else {
+            return Self::default();
+        };
+        let level = match url.scheme() {
+            "https" if mixed_content.insecure_displayed => SecurityLevel::PartiallySecure,
+            "https" => SecurityLevel::Secure,
+            "http" if mixed_content::is_loopback(&url) => SecurityLevel::Internal,
+            "http" => SecurityLevel::NotSecure,
+            _ => SecurityLevel::Internal,
+        };
+        let tls = (url.scheme() == "https").then(|| lookup(&url)).flatten();
+        Self { level, host: url.host_str().map(str::to_string), tls, mixed_content }
+    }
+
+    /// One-line summary shown at the top of the site information panel
+    pub fn summary(&self) -> &'static str {
+        match self.level {
+            SecurityLevel::Internal => "This is a local page",
+            SecurityLevel::Secure => "Connection is secure",
+            SecurityLevel::PartiallySecure => "Parts of this page are not secure",
+            SecurityLevel::NotSecure => "Connection is not secure",
+        }
+    }
+}
+
+/// Parses curl's verbose text output for one transfer
+#[derive(Debug, Default)]
+pub struct TlsInfoCollector {
+    info: TlsInfo,
+    certificate: Option<CertificateInfo>,
+    handshake_seen: bool,
+}
+
+impl TlsInfoCollector {
+    /// Feed a chunk of curl's informational text (curl::easy::InfoType::Text)
+    pub fn feed(&mut self, data: &[u8]) {
+        for line in String::from_utf8_lossy(data).lines() {
+            self.feed_line(line.trim_start_matches(['*', ' ']).trim_end());
+        }
+    }
+
+    fn feed_line(&mut self, line: &str) {
+        if let Some(parameters) = line.strip_prefix("SSL connection using ") {
+            let mut parts = parameters.split(" / ");
+            self.info.version = parts.next().unwrap_or_default().to_string();
+            self.info.cipher = parts.next().unwrap_or_default().to_string();
+            self.handshake_seen = true;
+        } else if line == "Server certificate:" {
+            self.certificate = Some(CertificateInfo::default());
+        } else if line.starts_with("SSL certificate verify ok") {
+            self.info.verified = true;
+        } else if let Some(certificate) = self.certificate.as_mut() {
+            let Some((key, value)) = line.split_once(": ") else {
+                return;
+            };
+            let value = value.trim().to_string();
+            match key {
+                "subject" => certificate.subject = value,
+                "issuer" => certificate.issuer = value,
+                "start date" => certificate.valid_from = value,
+                "expire date" => certificate.valid_until = value,
+                _ => {}
+            }
+        }
+    }
+
+    /// The collected details, if a TLS handshake happened
+    pub fn finish(self) -> Option<TlsInfo> {
+        self.handshake_seen.then(|| TlsInfo { certificate: self.certificate, ..self.info })
+    }
+}
+
+static CONNECTIONS: LazyLock<Mutex<HashMap<String, TlsInfo>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn connection_key(url: &Url) -> Option<String> {
+    Some(format!("{}:{}", url.host_str()?, url.port_or_known_default()?))
+}
+
+/// Remember the TLS details of the latest connection to the host of `url`
+pub fn record(url: &str, info: TlsInfo) {
+    let Some(key) = Url::parse(url).ok().as_ref().and_then(connection_key) else {
+        return;
+    };
+    if let Ok(mut connections) = CONNECTIONS.lock() {
+        connections.insert(key, info);
+    }
+}
+
+pub fn lookup(url: &Url) -> Option<TlsInfo> {
+    let key = connection_key(url)?;
+    CONNECTIONS.lock().ok()?.get(&key).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SecurityLevel, SecurityState, TlsInfoCollector};
+    use crate::engine::mixed_content::MixedContentStatus;
+
+    #[test]
+    fn parses_curl_verbose_output() {
+        let mut collector = TlsInfoCollector::default();
+        collector.feed(b"  Trying 93.184.215.14:443...\n");
+        collector.feed(b"SSL connection using TLSv1.3 / TLS_AES_256_GCM_SHA384 / X25519 / RSASSA-PSS\n");
+        collector.feed(b"Server certificate:\n subject: CN=www.example.org\n start date: Jan 30 00:00:00 2024 GMT\n");
+        collector.feed(b" expire date: Mar  1 23:59:59 2025 GMT\n issuer: C=US; O=DigiCert Inc; CN=DigiCert Global G2 TLS RSA SHA256 2020 CA1\n");
+        collector.feed(b" SSL certificate verify ok.\n");
+
+        let info = collector.finish().unwrap();
+        assert_eq!(info.version, "TLSv1.3");
+        assert_eq!(info.cipher, "TLS_AES_256_GCM_SHA384");
+        assert!(info.verified);
+        let certificate = info.certificate.unwrap();
+        assert_eq!(certificate.subject, "CN=www.example.org");
+        assert_eq!(certificate.issuer, "C=US; O=DigiCert Inc; CN=DigiCert Global G2 TLS RSA SHA256 2020 CA1");
+        assert_eq!(certificate.valid_until, "Mar  1 23:59:59 2025 GMT");
+    }
+
+    #[test]
+    fn plain_connections_have_no_tls_info() {
+        let mut collector = TlsInfoCollector::default();
+        collector.feed(b"Connected to example.com (93.184.215.14) port 80\n");
+        assert_eq!(collector.finish(), None);
+    }
+
+    #[test]
+    fn security_level_follows_scheme_and_mixed_content() {
+        let clean = MixedContentStatus::default();
+        let mixed = MixedContentStatus { blocked: 0, insecure_displayed: true };
+        assert_eq!(SecurityState::for_page("https://example.com/", clean).level, SecurityLevel::Secure);
+        assert_eq!(SecurityState::for_page("https://example.com/", mixed).level, SecurityLevel::PartiallySecure);
+        assert_eq!(SecurityState::for_page("http://example.com/", clean).level, SecurityLevel::NotSecure);
+        assert_eq!(SecurityState::for_page("http://localhost:8000/", clean).level, SecurityLevel::Internal);
+        assert_eq!(SecurityState::for_page("stokes://newtab", clean).level, SecurityLevel::Internal);
+    }
+}
@@ -0,0 +1,638 @@
+// WebSocket client (RFC 6455) on top of a curl connect-only transfer.
+//
+// curl takes care of DNS, proxies and TLS for wss://; once the socket is connected we speak
+// the opening handshake and the framing protocol ourselves through `Easy::send`/`Easy::recv`.
+// Each connection runs on its own thread and reports back through a callback.
+
+use crate::languages;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use curl::easy::Easy;
+use rand::Rng;
+use sha1::{Digest, Sha1};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+use url::Url;
+
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long to wait for the server to answer our close frame
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+const IDLE_SLEEP: Duration = Duration::from_millis(5);
+const MAX_HANDSHAKE_SIZE: usize = 16 * 1024;
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+const CLOSE_GOING_AWAY: u16 = 1001;
+const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+/// Reserved: the peer sent a close frame without a status code
+const CLOSE_NO_STATUS: u16 = 1005;
+/// Reserved: the connection dropped without a close frame
+const CLOSE_ABNORMAL: u16 = 1006;
+const CLOSE_INVALID_DATA: u16 = 1007;
+const CLOSE_TOO_BIG: u16 = 1009;
+
+/// Something that happened on a connection, in the order the page should see it
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebSocketEvent {
+    Open { protocol: String },
+    Text(String),
+    Binary(Vec<u8>),
+    /// Always followed by `Closed`
+    Error(String),
+    Closed { code: u16, reason: String, was_clean: bool },
+}
+
+enum WebSocketCommand {
+    Text(String),
+    Binary(Vec<u8>),
+    Close { code: Option<u16>, reason: String },
+}
+
+/// Page-side end of a connection. Dropping it tears the connection down with 1001 (going away).
+pub struct WebSocketHandle {
+    commands: Sender<WebSocketCommand>,
+}
+
+impl WebSocketHandle {
+    pub fn send_text(&self, text: String) {
+        let _ = self.commands.send(WebSocketCommand::Text(text));
+    }
+
+    pub fn send_binary(&self, data: Vec<u8>) {
+        let _ = self.commands.send(WebSocketCommand::Binary(data));
+    }
+
+    /// Start the closing handshake; `Closed` is reported once the server answers
+    pub fn close(&self, code: Option<u16>, reason: String) {
+        let _ = self.commands.send(WebSocketCommand::Close { code, reason });
+    }
+}
+
+/// Open a connection to a ws:// or wss:// URL on a background thread
+pub fn connect(
+    url: Url,
+    protocols: Vec<String>,
+    origin: String,
+    user_agent: String,
+    on_event: impl FnMut(WebSocketEvent) + Send + 'static,
+) -> WebSocketHandle {
+    let (commands, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut on_event = on_event;
+        let mut connection = match Connection::open(&url, &protocols, &origin, &user_agent) {
+            Ok(connection) => connection,
+            Err(failure) => {
+                tracing::warn!("{} failed: {}", url, failure.message);
+                on_event(WebSocketEvent::Error(failure.message));
+                on_event(WebSocketEvent::Closed { code: CLOSE_ABNORMAL, reason: String::new(), was_clean: false });
+                return;
+            }
+        };
+        on_event(WebSocketEvent::Open { protocol: connection.protocol.clone() });
+        connection.run(receiver, &mut on_event);
+    });
+    WebSocketHandle { commands }
+}
+
+/// A fatal problem with a connection, and the close code to report for it
+#[derive(Debug, PartialEq)]
+struct Failure {
+    code: u16,
+    message: String,
+}
+
+impl Failure {
+    fn new(code: u16, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+impl From<curl::Error> for Failure {
+    fn from(err: curl::Error) -> Self {
+        Self::new(CLOSE_ABNORMAL, err.to_string())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+/// Encode a client frame; RFC 6455 requires every client frame to be masked
+fn encode_frame(opcode: u8, payload: &[u8], mask: [u8; 4]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        len @ 0..=125 => frame.push(0x80 | len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(0x80 | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ mask[i % 4]));
+    frame
+}
+
+/// Buffers bytes from the server and splits them into frames
+#[derive(Default)]
+struct FrameDecoder {
+    buffer: Vec<u8>,
+}
+
+impl FrameDecoder {
+    fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn next_frame(&mut self) -> Result<Option<Frame>, Failure> {
+        let buffer = &self.buffer;
+        if buffer.len() < 2 {
+            return Ok(None);
+        }
+        let fin = buffer[0] & 0x80 != 0;
+        if buffer[0] & 0x70 != 0 {
+            return Err(Failure::new(CLOSE_PROTOCOL_ERROR, "reserved bits set without a negotiated extension"));
+        }
+        let opcode = buffer[0] & 0x0F;
+        if buffer[1] & 0x80 != 0 {
+            return Err(Failure::new(CLOSE_PROTOCOL_ERROR, "server frames must not be masked"));
+        }
+
+        let (len, header_len) = match buffer[1] & 0x7F {
+            126 => {
+                let Some(bytes) = buffer.get(2..4) else { return Ok(None) };
+                (u16::from_be_bytes([bytes[0], bytes[1]]) as u64, 4)
+            }
+            127 => {
+                let Some(bytes) = buffer.get(2..10) else { return Ok(None) };
+                (u64::from_be_bytes(bytes.try_into().unwrap_or_default()), 10)
+            }
+            len => (len as u64, 2),
+        };
+
+        match opcode {
+            OPCODE_CLOSE | OPCODE_PING | OPCODE_PONG if !fin || len > 125 => {
+                return Err(Failure::new(CLOSE_PROTOCOL_ERROR, "fragmented or oversized control frame"));
+            }
+            OPCODE_CONTINUATION | OPCODE_TEXT | OPCODE_BINARY | OPCODE_CLOSE | OPCODE_PING | OPCODE_PONG => {}
+            other => return Err(Failure::new(CLOSE_PROTOCOL_ERROR, format!("unknown opcode {other:#x}"))),
+        }
+        if len > MAX_MESSAGE_SIZE as u64 {
+            return Err(Failure::new(CLOSE_TOO_BIG, "frame exceeds the maximum message size"));
+        }
+
+        let end = header_len + len as usize;
+        if buffer.len() < end {
+            return Ok(None);
+        }
+        let payload = buffer[header_len..end].to_vec();
+        self.buffer.drain(..end);
+        Ok(Some(Frame { fin, opcode, payload }))
+    }
+}
+
+/// Value the server must echo in Sec-WebSocket-Accept for our key
+fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(GUID.as_bytes());
+    STANDARD.encode(hasher.finalize())
+}
+
+fn handshake_request(url: &Url, key: &str, protocols: &[String], origin: &str, user_agent: &str) -> String {
+    let mut target = url.path().to_string();
+    if let Some(query) = url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    let mut host = url.host_str().unwrap_or_default().to_string();
+    if let Some(port) = url.port() {
+        host = format!("{host}:{port}");
+    }
+
+    let accept_language = languages::accept_language_header();
+
+    let mut request = format!(
+        "GET {target} HTTP/1.1\r\n\
+         Host: {host}\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Key: {key}\r\n\
+         Sec-WebSocket-Version: 13\r\n\
+         Origin: {origin}\r\n\
+         User-Agent: {user_agent}\r\n\
+         {accept_language}\r\n"
+    );
+    if !protocols.is_empty() {
+        request.push_str(&format!("Sec-WebSocket-Protocol: {}\r\n", protocols.join(", ")));
+    }
+    request.push_str("\r\n");
+    request
+}
+
+/// Check the server's handshake response and return the negotiated subprotocol
+fn validate_handshake_response(head: &str, key: &str, protocols: &[String]) -> Result<String, Failure> {
+    let mut lines = head.split("\r\n");
+    let status_line = lines.next().unwrap_or_default();
+    let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+    if status != "101" {
+        return Err(Failure::new(CLOSE_ABNORMAL, format!("unexpected handshake response: {status_line}")));
+    }
+
+    let mut upgrade = false;
+    let mut connection = false;
+    let mut accept = None;
+    let mut protocol = String::new();
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else { continue };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "upgrade" => upgrade = value.eq_ignore_ascii_case("websocket"),
+            "connection" => connection = value.split(',').any(|token| token.trim().eq_ignore_ascii_case("upgrade")),
+            "sec-websocket-accept" => accept = Some(value.to_string()),
+            "sec-websocket-protocol" => protocol = value.to_string(),
+            "sec-websocket-extensions" if !value.is_empty() => {
+                return Err(Failure::new(CLOSE_ABNORMAL, "server selected an extension that was not offered"));
+            }
+            _ => {}
+        }
+    }
+
+    if !upgrade || !connection {
+        return Err(Failure::new(CLOSE_ABNORMAL, "response is missing the websocket upgrade headers"));
+    }
+    if accept.as_deref() != Some(accept_key(key).as_str()) {
+        return Err(Failure::new(CLOSE_ABNORMAL, "Sec-WebSocket-Accept does not match"));
+    }
+    if !protocol.is_empty() && !protocols.contains(&protocol) {
+        return Err(Failure::new(CLOSE_ABNORMAL, format!("server selected unrequested subprotocol {protocol}")));
+    }
+    Ok(protocol)
+}
+
+fn random_mask() -> [u8; 4] {
+    let mut mask = [0u8; 4];
+    rand::rng().fill_bytes(&mut mask);
+    mask
+}
+
+fn close_payload(code: Option<u16>, reason: &str) -> Vec<u8> {
+    let Some(code) = code else {
+        return Vec::new();
+    };
+    let mut payload = code.to_be_bytes().to_vec();
+    payload.extend_from_slice(reason.as_bytes());
+    payload
+}
+
+fn parse_close_payload(payload: &[u8]) -> Result<(u16, String), Failure> {
+    match payload {
+        [] => Ok((CLOSE_NO_STATUS, String::new())),
+        [_] => Err(Failure::new(CLOSE_PROTOCOL_ERROR, "close frame with a one-byte payload")),
+        [high, low, reason @ ..] => {
+            let reason = std::str::from_utf8(reason)
+                .map_err(|_| Failure::new(CLOSE_INVALID_DATA, "close reason is not valid UTF-8"))?;
+            Ok((u16::from_be_bytes([*high, *low]), reason.to_string()))
+        }
+    }
+}
+
+/// A data message being reassembled from continuation frames
+struct PartialMessage {
+    opcode: u8,
+    data: Vec<u8>,
+}
+
+struct Connection {
+    easy: Easy,
+    decoder: FrameDecoder,
+    protocol: String,
+    partial: Option<PartialMessage>,
+    /// Set once we have sent our close frame
+    close_sent_at: Option<Instant>,
+}
+
+impl Connection {
+    fn open(url: &Url, protocols: &[String], origin: &str, user_agent: &str) -> Result<Self, Failure> {
+        let secure = match url.scheme() {
+            "ws" => false,
+            "wss" => true,
+            scheme => return Err(Failure::new(CLOSE_ABNORMAL, format!("unsupported scheme {scheme}"))),
+        };
+        let mut transport_url = url.clone();
+        let _ = transport_url.set_scheme(if secure { "https" } else { "http" });
+        transport_url.set_fragment(None);
+
+        let mut easy = Easy::new();
+        easy.url(transport_url.as_str())?;
+        easy.connect_only(true)?;
+        easy.connect_timeout(CONNECT_TIMEOUT)?;
+        easy.perform()?;
+
+        let mut key_bytes = [0u8; 16];
+        rand::rng().fill_bytes(&mut key_bytes);
+        let key = STANDARD.encode(key_bytes);
+
+        let mut connection = Self {
+            easy,
+            decoder: FrameDecoder::default(),
+            protocol: String::new(),
+            partial: None,
+            close_sent_at: None,
+        };
+        connection.write_all(handshake_request(url, &key, protocols, origin, user_agent).as_bytes())?;
+
+        // Read until the end of the response headers; anything after them is already frame data
+        let deadline = Instant::now() + CONNECT_TIMEOUT;
+        let mut response = Vec::new();
+        let head_len = loop {
+            if let Some(index) = response.windows(4).position(|window| window == b"\r\n\r\n") {
+                break index + 4;
+            }
+            if response.len() > MAX_HANDSHAKE_SIZE {
+                return Err(Failure::new(CLOSE_ABNORMAL, "handshake response too large"));
+            }
+            if Instant::now() >= deadline {
+                return Err(Failure::new(CLOSE_ABNORMAL, "timed out waiting for the handshake response"));
+            }
+            match connection.read_available()? {
+                Some(bytes) => response.extend_from_slice(&bytes),
+                None => thread::sleep(IDLE_SLEEP),
+            }
+        };
+
+        let head = String::from_utf8_lossy(&response[..head_len]);
+        connection.protocol = validate_handshake_response(&head, &key, protocols)?;
+        connection.decoder.push(&response[head_len..]);
+        Ok(connection)
+    }
+
+    /// Non-blocking read. `Ok(None)` means nothing is available yet.
+    fn read_available(&mut self) -> Result<Option<Vec<u8>>, Failure> {
+        let mut buffer = [0u8; 16 * 1024];
+        match self.easy.recv(&mut buffer) {
+            Ok(0) => Err(Failure::new(CLOSE_ABNORMAL, "connection closed by the server")),
+            Ok(read) => Ok(Some(buffer[..read].to_vec())),
+            Err(err) if err.is_again() => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn write_all(&mut self, mut bytes: &[u8]) -> Result<(), Failure> {
+        while !bytes.is_empty() {
+            match self.easy.send(bytes) {
+                Ok(written) => bytes = &bytes[written..],
+                Err(err) if err.is_again() => thread::sleep(Duration::from_millis(1)),
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(())
+    }
+
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<(), Failure> {
+        self.write_all(&encode_frame(opcode, payload, random_mask()))
+    }
+
+    fn send_close(&mut self, code: Option<u16>, reason: &str) -> Result<(), Failure> {
+        if self.close_sent_at.is_some() {
+            return Ok(());
+        }
+        self.close_sent_at = Some(Instant::now());
+        self.send_frame(OPCODE_CLOSE, &close_payload(code, reason))
+    }
+
+    /// Pump commands and incoming frames until the connection is closed
+    fn run(&mut self, commands: Receiver<WebSocketCommand>, on_event: &mut impl FnMut(WebSocketEvent)) {
+        let mut page_gone = false;
+        loop {
+            match self.step(&commands, &mut page_gone, on_event) {
+                Ok(Some((code, reason))) => {
+                    on_event(WebSocketEvent::Closed { code, reason, was_clean: true });
+                    return;
+                }
+                Ok(None) => {}
+                Err(failure) => {
+                    // Tell the server why, unless the transport itself is gone
+                    if failure.code != CLOSE_ABNORMAL {
+                        let _ = self.send_close(Some(failure.code), "");
+                    }
+                    // Pages always see 1006 when the connection is failed
+                    on_event(WebSocketEvent::Error(failure.message));
+                    on_event(WebSocketEvent::Closed { code: CLOSE_ABNORMAL, reason: String::new(), was_clean: false });
+                    return;
+                }
+            }
+        }
+    }
+
+    /// One turn of the connection loop. Returns the close code and reason once both sides have closed.
+    fn step(
+        &mut self,
+        commands: &Receiver<WebSocketCommand>,
+        page_gone: &mut bool,
+        on_event: &mut impl FnMut(WebSocketEvent),
+    ) -> Result<Option<(u16, String)>, Failure> {
+        let mut idle = true;
+
+        while !*page_gone {
+            match commands.try_recv() {
+                Ok(_) if self.close_sent_at.is_some() => {}
+                Ok(WebSocketCommand::Text(text)) => self.send_frame(OPCODE_TEXT, text.as_bytes())?,
+                Ok(WebSocketCommand::Binary(data)) => self.send_frame(OPCODE_BINARY, &data)?,
+                Ok(WebSocketCommand::Close { code, reason }) => self.send_close(code, &reason)?,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    // The page navigated away or the socket was garbage collected
+                    *page_gone = true;
+                    self.send_close(Some(CLOSE_GOING_AWAY), "")?;
+                }
+            }
+            idle = false;
+        }
+
+        if let Some(bytes) = self.read_available()? {
+            self.decoder.push(&bytes);
+            idle = false;
+        }
+        while let Some(frame) = self.decoder.next_frame()? {
+            if let Some(closed) = self.handle_frame(frame, on_event)? {
+                return Ok(Some(closed));
+            }
+        }
+
+        if let Some(sent_at) = self.close_sent_at {
+            if sent_at.elapsed() >= CLOSE_TIMEOUT {
+                return Err(Failure::new(CLOSE_ABNORMAL, "server did not answer the close frame"));
+            }
+        }
+        if idle {
+            thread::sleep(IDLE_SLEEP);
+        }
+        Ok(None)
+    }
+
+    fn handle_frame(&mut self, frame: Frame, on_event: &mut impl FnMut(WebSocketEvent)) -> Result<Option<(u16, String)>, Failure> {
+        match frame.opcode {
+            OPCODE_PING => {
+                if self.close_sent_at.is_none() {
+                    self.send_frame(OPCODE_PONG, &frame.payload)?;
+                }
+            }
+            OPCODE_PONG => {}
+            OPCODE_CLOSE => {
+                let (code, reason) = parse_close_payload(&frame.payload)?;
+                // Echo the server's code back to complete the closing handshake
+                let echo = (code != CLOSE_NO_STATUS).then_some(code);
+                self.send_close(echo, "")?;
+                return Ok(Some((code, reason)));
+            }
+            OPCODE_TEXT | OPCODE_BINARY => {
+                if self.partial.is_some() {
+                    return Err(Failure::new(CLOSE_PROTOCOL_ERROR, "new message started before the previous one finished"));
+                }
+                let message = PartialMessage { opcode: frame.opcode, data: frame.payload };
+                if frame.fin {
+                    self.deliver(message, on_event)?;
+                } else {
+                    self.partial = Some(message);
+                }
+            }
+            _ => {
+                let Some(partial) = self.partial.as_mut() else {
+                    return Err(Failure::new(CLOSE_PROTOCOL_ERROR, "continuation frame without a message"));
+                };
+                if partial.data.len() + frame.payload.len() > MAX_MESSAGE_SIZE {
+                    return Err(Failure::new(CLOSE_TOO_BIG, "message exceeds the maximum message size"));
+                }
+                partial.data.extend_from_slice(&frame.payload);
+                if frame.fin {
+                    let message = self.partial.take().expect("partial message checked above");
+                    self.deliver(message, on_event)?;
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn deliver(&mut self, message: PartialMessage, on_event: &mut impl FnMut(WebSocketEvent)) -> Result<(), Failure> {
+        // Messages that arrive after we started closing are dropped, as the spec requires
+        if self.close_sent_at.is_some() {
+            return Ok(());
+        }
+        if message.opcode == OPCODE_TEXT {
+            let text = String::from_utf8(message.data)
+                .map_err(|_| Failure::new(CLOSE_INVALID_DATA, "text message is not valid UTF-8"))?;
+            on_event(WebSocketEvent::Text(text));
+        } else {
+            on_event(WebSocketEvent::Binary(message.data));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Server frames are unmasked, so build them by hand
+    fn server_frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![if fin { 0x80 | opcode } else { opcode }];
+        if payload.len() < 126 {
+            frame.push(payload.len() as u8);
+        } else {
+            frame.push(126);
+            frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        }
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn accept_key_matches_rfc_example() {
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn client_frames_are_masked() {
+        let frame = encode_frame(OPCODE_TEXT, b"Hello", [0x37, 0xfa, 0x21, 0x3d]);
+        // Example from RFC 6455 section 5.7
+        assert_eq!(frame, vec![0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58]);
+
+        let long = encode_frame(OPCODE_BINARY, &[0u8; 300], [0; 4]);
+        assert_eq!(&long[..4], &[0x82, 0x80 | 126, 0x01, 0x2c]);
+        assert_eq!(long.len(), 4 + 4 + 300);
+    }
+
+    #[test]
+    fn decoder_waits_for_complete_frames() {
+        let mut decoder = FrameDecoder::default();
+        let bytes = server_frame(true, OPCODE_TEXT, &[b'a'; 200]);
+        decoder.push(&bytes[..3]);
+        assert_eq!(decoder.next_frame(), Ok(None));
+        decoder.push(&bytes[3..]);
+        decoder.push(&server_frame(true, OPCODE_PING, b"hi"));
+
+        let frame = decoder.next_frame().unwrap().unwrap();
+        assert_eq!((frame.fin, frame.opcode, frame.payload.len()), (true, OPCODE_TEXT, 200));
+        let ping = decoder.next_frame().unwrap().unwrap();
+        assert_eq!((ping.opcode, ping.payload), (OPCODE_PING, b"hi".to_vec()));
+        assert_eq!(decoder.next_frame(), Ok(None));
+    }
+
+    #[test]
+    fn decoder_rejects_masked_and_fragmented_control_frames() {
+        let mut decoder = FrameDecoder::default();
+        decoder.push(&[0x81, 0x80, 0, 0, 0, 0]);
+        assert_eq!(decoder.next_frame().unwrap_err().code, CLOSE_PROTOCOL_ERROR);
+
+        let mut decoder = FrameDecoder::default();
+        decoder.push(&server_frame(false, OPCODE_PING, b""));
+        assert_eq!(decoder.next_frame().unwrap_err().code, CLOSE_PROTOCOL_ERROR);
+    }
+
+    #[test]
+    fn handshake_response_is_validated() {
+        let key = "dGhlIHNhbXBsZSBub25jZQ==";
+        let protocols = vec!["chat".to_string()];
+        let ok = "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                  Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\nSec-WebSocket-Protocol: chat\r\n\r\n";
+        assert_eq!(validate_handshake_response(ok, key, &protocols), Ok("chat".to_string()));
+
+        let wrong_protocol = ok.replace("Protocol: chat", "Protocol: superchat");
+        assert!(validate_handshake_response(&wrong_protocol, key, &protocols).is_err());
+        let wrong_accept = ok.replace("s3pPLMBiTxaQ9kYGzzhZRbK+xOo=", "bogus");
+        assert!(validate_handshake_response(&wrong_accept, key, &protocols).is_err());
+        assert!(validate_handshake_response("HTTP/1.1 200 OK\r\n\r\n", key, &protocols).is_err());
+    }
+
+    #[test]
+    fn handshake_request_includes_port_and_protocols() {
+        let url = Url::parse("wss://example.com:8443/chat?room=1").unwrap();
+        let request = handshake_request(&url, "key", &["a".to_string(), "b".to_string()], "https://example.com", "ua");
+        assert!(request.starts_with("GET /chat?room=1 HTTP/1.1\r\nHost: example.com:8443\r\n"));
+        assert!(request.contains("Sec-WebSocket-Protocol: a, b\r\n"));
+        assert!(request.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn close_payloads_round_trip() {
+        assert_eq!(parse_close_payload(&close_payload(Some(4000), "bye")), Ok((4000, "bye".to_string())));
+        assert_eq!(parse_close_payload(&close_payload(None, "")), Ok((CLOSE_NO_STATUS, String::new())));
+        assert!(parse_close_payload(&[0x03]).is_err());
+    }
+}
@@ -0,0 +1,257 @@
+use crate::permissions::display_origin;
+use crate::secure_storage::EncryptedFile;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use url::Url;
+
+const PASSWORDS_FILE: &str = "passwords.json";
+const PASSWORDS_KEY_NAME: &str = "password-encryption-key-v1";
+const MANAGEMENT_PAGE_HTML: &str = include_str!("../assets/passwords.html");
+/// Shown in place of the management page when the browser doesn't send it
+const UNAVAILABLE_PAGE_HTML: &str = "<!DOCTYPE html><html><head><title>Passwords</title></head>\
+    <body><p>Saved passwords are not available.</p></body></html>";
+/// The page listing saved passwords
+pub const MANAGEMENT_URL: &str = "stokes://passwords";
+
+/// A username and password saved for an origin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedLogin {
+    pub origin: String,
+    #[serde(default)]
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PersistedPasswords {
+    #[serde(default)]
+    logins: Vec<SavedLogin>,
+    /// Origins the user never wants to be asked about
+    #[serde(default)]
+    never_save: Vec<String>,
+}
+
+/// Saved logins, kept in an encrypted file. Only the browser process opens it: tabs get the
+/// management page from the browser over IPC. It is read from disk whenever it's needed.
+#[derive(Debug, Clone)]
+pub struct PasswordStore {
+    data: PersistedPasswords,
+    file: EncryptedFile,
+}
+
+impl PasswordStore {
+    pub fn load_from_disk() -> Self {
+        let file = EncryptedFile::open(PASSWORDS_FILE, PASSWORDS_KEY_NAME);
+        let data = file.read::<PersistedPasswords>().unwrap_or_default();
+        Self { data, file }
+    }
+
+    pub fn save_to_disk(&self) {
+        self.file.write(&self.data);
+    }
+
+    pub fn logins_for(&self, origin: &str) -> Vec<SavedLogin> {
+        self.data.logins.iter().filter(|login| login.origin == origin).cloned().collect()
+    }
+
+    /// Whether submitting `login` should offer to save it: not when it is already saved as is, or
+    /// when the user chose never to save passwords for its origin
+    pub fn should_offer(&self, login: &SavedLogin) -> bool {
+        !self.data.never_save.contains(&login.origin) && !self.data.logins.contains(login)
+    }
+
+    /// Save `login`, replacing the password saved for the same origin and username
+    pub fn save_login(&mut self, login: SavedLogin) {
+        match self.data.logins.iter_mut().find(|saved| saved.origin == login.origin && saved.username == login.username) {
+            Some(saved) => saved.password = login.password,
+            None => self.data.logins.push(login),
+        }
+    }
+
+    pub fn remove_login(&mut self, origin: &str, username: &str) {
+        self.data.logins.retain(|login| login.origin != origin || login.username != username);
+    }
+
+    pub fn never_save(&mut self, origin: &str) {
+        if !self.data.never_save.iter().any(|never| never == origin) {
+            self.data.never_save.push(origin.to_string());
+        }
+    }
+
+    pub fn allow_saving(&mut self, origin: &str) {
+        self.data.never_save.retain(|never| never != origin);
+    }
+}
+
+pub fn is_management_url(url: &Url) -> bool {
+    url.scheme() == "stokes" && url.host_str() == Some("passwords")
+}
+
+/// What a stokes://passwords URL's query string asks for. Its links remove entries and reveal
+/// passwords this way; the browser confirms those with the user before acting on them, since any
+/// page (or a compromised tab) can load such a URL.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManagementRequest {
+    /// Origin and username of the login to remove
+    pub remove: Option<(String, String)>,
+    /// Origin to offer saving passwords for again
+    pub allow: Option<String>,
+    pub reveal: bool,
+}
+
+impl ManagementRequest {
+    pub fn parse(url: &Url) -> Self {
+        let query = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+        Self {
+            remove: query("remove").map(|origin| (origin, query("user").unwrap_or_default())),
+            allow: query("allow"),
+            reveal: query("reveal").is_some(),
+        }
+    }
+}
+
+impl PasswordStore {
+    pub fn has_login(&self, origin: &str, username: &str) -> bool {
+        self.data.logins.iter().any(|login| login.origin == origin && login.username == username)
+    }
+
+    /// The stokes://passwords page listing these logins, with the passwords shown when `reveal`
+    pub fn management_page(&self, reveal: bool) -> String {
+        render_management_page(&self.data, reveal)
+    }
+}
+
+/// Asks the browser for the management page in a tab process. It is given the page's URL and
+/// blocks until the page arrives.
+pub type ManagementPageSource = Box<dyn Fn(&Url) -> Option<String>>;
+
+thread_local! {
+    static MANAGEMENT_PAGE_SOURCE: RefCell<Option<ManagementPageSource>> = RefCell::new(None);
+}
+
+/// Set how this process gets the management page
+pub fn set_management_page_source<F>(source: F)
+where
+    F: Fn(&Url) -> Option<String> + 'static,
+{
+    MANAGEMENT_PAGE_SOURCE.set(Some(Box::new(source)));
+}
+
+/// The stokes://passwords page at `url`, built by the browser process
+pub fn management_page(url: &Url) -> String {
+    MANAGEMENT_PAGE_SOURCE
+        .with(|source| source.borrow().as_ref().and_then(|source| source(url)))
+        .unwrap_or_else(|| UNAVAILABLE_PAGE_HTML.to_string())
+}
+
+fn management_link(reveal: bool, params: &[(&str, &str)]) -> String {
+    let mut query = url::form_urlencoded::Serializer::new(String::new());
+    query.extend_pairs(params);
+    if reveal {
+        query.append_pair("reveal", "1");
+    }
+    let query = query.finish();
+    if query.is_empty() {
+        MANAGEMENT_URL.to_string()
+    } else {
+        format!("{MANAGEMENT_URL}?{query}")
+    }
+}
+
+fn render_management_page(data: &PersistedPasswords, reveal: bool) -> String {
+    let mut logins: Vec<&SavedLogin> = data.logins.iter().collect();
+    logins.sort_by(|a, b| (&a.origin, &a.username).cmp(&(&b.origin, &b.username)));
+
+    let rows: String = logins.iter().map(|login| {
+        let password = if reveal { login.password.clone() } else { "•".repeat(8) };
+        let remove = management_link(reveal, &[("remove", &login.origin), ("user", &login.username)]);
+        format!(
+            "<tr><td>{}</td><td>{}</td><td class=\"password\">{}</td><td><a class=\"remove\" href=\"{}\">Remove</a></td></tr>\n",
+            html_escape::encode_text(display_origin(&login.origin)),
+            html_escape::encode_text(&login.username),
+            html_escape::encode_text(&password),
+            html_escape::encode_double_quoted_attribute(&remove),
+        )
+    }).collect();
+    let rows = if rows.is_empty() {
+        "<tr><td class=\"empty\" colspan=\"4\">No passwords saved yet</td></tr>".to_string()
+    } else {
+        rows
+    };
+
+    let never_saved = if data.never_save.is_empty() {
+        String::new()
+    } else {
+        let items: String = data.never_save.iter().map(|origin| {
+            format!(
+                "<li>{} <a href=\"{}\">Offer to save again</a></li>\n",
+                html_escape::encode_text(display_origin(origin)),
+                html_escape::encode_double_quoted_attribute(&management_link(reveal, &[("allow", origin)])),
+            )
+        }).collect();
+        format!("<h2>Never saved</h2>\n<ul>\n{items}</ul>")
+    };
+
+    let summary = match logins.len() {
+        1 => "1 saved password.".to_string(),
+        count => format!("{count} saved passwords."),
+    };
+    let (toggle_url, toggle_label) = if reveal {
+        (management_link(false, &[]), "Hide passwords")
+    } else {
+        (management_link(true, &[]), "Show passwords")
+    };
+    MANAGEMENT_PAGE_HTML
+        .replace("{{SUMMARY}}", &summary)
+        .replace("{{TOGGLE_URL}}", &html_escape::encode_double_quoted_attribute(&toggle_url))
+        .replace("{{TOGGLE_LABEL}}", toggle_label)
+        .replace("{{ROWS}}", &rows)
+        .replace("{{NEVER_SAVED}}", &never_saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{management_link, render_management_page, ManagementRequest, PersistedPasswords, SavedLogin, MANAGEMENT_URL};
+    use url::Url;
+
+    fn data() -> PersistedPasswords {
+        PersistedPasswords {
+            logins: vec![SavedLogin {
+                origin: "https://example.com".to_string(),
+                username: "ada<script>".to_string(),
+                password: "hunter2".to_string(),
+            }],
+            never_save: vec!["https://bank.example".to_string()],
+        }
+    }
+
+    #[test]
+    fn passwords_are_masked_until_revealed() {
+        let page = render_management_page(&data(), false);
+        assert!(!page.contains("hunter2"));
+        assert!(page.contains("ada&lt;script&gt;"));
+        assert!(page.contains("bank.example"));
+
+        assert!(render_management_page(&data(), true).contains("hunter2"));
+    }
+
+    #[test]
+    fn links_encode_their_parameters() {
+        assert_eq!(
+            management_link(true, &[("remove", "https://example.com"), ("user", "a&b")]),
+            "stokes://passwords?remove=https%3A%2F%2Fexample.com&user=a%26b&reveal=1"
+        );
+        assert_eq!(management_link(false, &[]), "stokes://passwords");
+    }
+
+    #[test]
+    fn parses_management_requests() {
+        let url = Url::parse(&management_link(true, &[("remove", "https://example.com"), ("user", "a&b")])).unwrap();
+        assert_eq!(
+            ManagementRequest::parse(&url),
+            ManagementRequest { remove: Some(("https://example.com".to_string(), "a&b".to_string())), allow: None, reveal: true }
+        );
+        let url = Url::parse(MANAGEMENT_URL).unwrap();
+        assert_eq!(ManagementRequest::parse(&url), ManagementRequest::default());
+    }
+}
@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const STORAGE_VERSION: u32 = 1;
+const PERMISSIONS_FILE: &str = "permissions.json";
+
+/// Powerful features a page has to ask the user for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PermissionKind {
+    Notifications,
+    ClipboardRead,
+    Geolocation,
+}
+
+impl PermissionKind {
+    /// Parse the name used by `navigator.permissions.query({ name })`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "notifications" => Some(Self::Notifications),
+            "clipboard-read" => Some(Self::ClipboardRead),
+            "geolocation" => Some(Self::Geolocation),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Notifications => "notifications",
+            Self::ClipboardRead => "clipboard-read",
+            Self::Geolocation => "geolocation",
+        }
+    }
+
+    /// What the prompt says the site wants to do
+    pub fn prompt_text(self) -> &'static str {
+        match self {
+            Self::Notifications => "show notifications",
+            Self::ClipboardRead => "see text and images copied to the clipboard",
+            Self::Geolocation => "know your location",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionState {
+    Granted,
+    Denied,
+    Prompt,
+}
+
+impl PermissionState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Granted => "granted",
+            Self::Denied => "denied",
+            Self::Prompt => "prompt",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PersistedPermissions {
+    #[serde(default = "default_storage_version")]
+    version: u32,
+    #[serde(default)]
+    origins: BTreeMap<String, BTreeMap<PermissionKind, bool>>,
+}
+
+const fn default_storage_version() -> u32 {
+    STORAGE_VERSION
+}
+
+/// Per-origin permission decisions. The parent process writes it when the user answers a prompt,
+/// tab processes read it to answer `navigator.permissions.query` without a round trip.
+#[derive(Debug, Clone)]
+pub struct PermissionStore {
+    origins: BTreeMap<String, BTreeMap<PermissionKind, bool>>,
+    path: PathBuf,
+}
+
+impl Default for PermissionStore {
+    fn default() -> Self {
+        Self {
+            origins: BTreeMap::new(),
+            path: permissions_file_path(),
+        }
+    }
+}
+
+impl PermissionStore {
+    pub fn load_from_disk() -> Self {
+        let mut store = Self::default();
+        if let Ok(contents) = std::fs::read_to_string(&store.path) {
+            if let Ok(persisted) = serde_json::from_str::<PersistedPermissions>(&contents) {
+                store.origins = persisted.origins;
+            }
+        }
+        store
+    }
+
+    pub fn save_to_disk(&self) {
+        let payload = PersistedPermissions {
+            version: STORAGE_VERSION,
+            origins: self.origins.clone(),
+        };
+
+        let Ok(json) = serde_json::to_string_pretty(&payload) else {
+            return;
+        };
+
+        if let Some(parent) = self.path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::write(&self.path, json);
+    }
+
+    pub fn state(&self, origin: &str, kind: PermissionKind) -> PermissionState {
+        match self.origins.get(origin).and_then(|decisions| decisions.get(&kind)) {
+            Some(true) => PermissionState::Granted,
+            Some(false) => PermissionState::Denied,
+            None => PermissionState::Prompt,
+        }
+    }
+
+    /// Remember the user's answer. Opaque origins ("null") are never persisted.
+    pub fn set(&mut self, origin: &str, kind: PermissionKind, granted: bool) {
+        if !is_persistable_origin(origin) {
+            return;
+        }
+        self.origins.entry(origin.to_string()).or_default().insert(kind, granted);
+    }
+}
+
+/// Short form of an origin for the prompt bar, e.g. `example.com` for `https://example.com`
+pub fn display_origin(origin: &str) -> &str {
+    match origin.strip_prefix("https://") {
+        Some(host) => host,
+        None if is_persistable_origin(origin) => origin,
+        None => "This page",
+    }
+}
+
+fn is_persistable_origin(origin: &str) -> bool {
+    !origin.is_empty() && origin != "null"
+}
+
+fn permissions_file_path() -> PathBuf {
+    crate::profile::data_dir().join(PERMISSIONS_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{display_origin, PermissionKind, PermissionState, PermissionStore};
+
+    #[test]
+    fn decisions_are_per_origin() {
+        let mut store = PermissionStore::default();
+        store.set("https://a.example", PermissionKind::Notifications, true);
+        store.set("https://b.example", PermissionKind::Notifications, false);
+
+        assert_eq!(store.state("https://a.example", PermissionKind::Notifications), PermissionState::Granted);
+        assert_eq!(store.state("https://b.example", PermissionKind::Notifications), PermissionState::Denied);
+        assert_eq!(store.state("https://a.example", PermissionKind::ClipboardRead), PermissionState::Prompt);
+    }
+
+    #[test]
+    fn opaque_origins_are_not_remembered() {
+        let mut store = PermissionStore::default();
+        store.set("null", PermissionKind::ClipboardRead, true);
+        assert_eq!(store.state("null", PermissionKind::ClipboardRead), PermissionState::Prompt);
+    }
+
+    #[test]
+    fn kind_names_round_trip() {
+        for kind in [PermissionKind::Notifications, PermissionKind::ClipboardRead, PermissionKind::Geolocation] {
+            assert_eq!(PermissionKind::from_name(kind.name()), Some(kind));
+        }
+        assert_eq!(PermissionKind::from_name("camera"), None);
+    }
+
+    #[test]
+    fn display_origin_hides_https() {
+        assert_eq!(display_origin("https://example.com"), "example.com");
+        assert_eq!(display_origin("http://example.com:8080"), "http://example.com:8080");
+        assert_eq!(display_origin("null"), "This page");
+    }
+}
@@ -0,0 +1,128 @@
+// User profiles: each profile keeps its cookies, bookmarks, site settings, saved passwords and the
+// rest of the browser's data in a directory of its own. The browser process picks the profile at
+// startup and passes its directory to every tab process it spawns.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// The profile used when none is asked for. Its data lives directly in the app's config
+/// directory, where it was kept before profiles existed.
+pub const DEFAULT_PROFILE: &str = "Default";
+const PROFILES_DIR: &str = "profiles";
+const MAX_NAME_LEN: usize = 64;
+
+static PROFILE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// The app's config directory, which holds the default profile and the other profiles' directories
+fn app_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("stokes-browser")
+}
+
+/// Whether `name` can be used as a profile name, and so as a directory name
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name != "."
+        && name != ".."
+        && name.chars().all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.'))
+        && name.trim() == name
+}
+
+/// The directory a profile keeps its data in
+pub fn dir_for(name: &str) -> PathBuf {
+    if name.eq_ignore_ascii_case(DEFAULT_PROFILE) {
+        app_dir()
+    } else {
+        app_dir().join(PROFILES_DIR).join(name)
+    }
+}
+
+/// Use `dir` for this process's data. Only the first call has an effect, and it has to come before
+/// anything is loaded from disk.
+pub fn init(dir: PathBuf) {
+    if let Err(err) = std::fs::create_dir_all(&dir) {
+        tracing::error!("Failed to create profile directory {}: {}", dir.display(), err);
+    }
+    let _ = PROFILE_DIR.set(dir);
+}
+
+/// The directory of the profile this process runs with
+pub fn data_dir() -> PathBuf {
+    PROFILE_DIR.get().cloned().unwrap_or_else(app_dir)
+}
+
+/// The name of the profile this process runs with
+pub fn current_name() -> String {
+    let dir = data_dir();
+    if dir == app_dir() {
+        return DEFAULT_PROFILE.to_string();
+    }
+    dir.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+/// Every profile that has been created, the default one first
+pub fn list() -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(app_dir().join(PROFILES_DIR))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| is_valid_name(name) && !name.eq_ignore_ascii_case(DEFAULT_PROFILE))
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort_by_key(|name| name.to_lowercase());
+    names.insert(0, DEFAULT_PROFILE.to_string());
+    names
+}
+
+/// The profile named on the command line with `--profile <name>` or `--profile=<name>`
+pub fn from_args(args: &[String]) -> Option<String> {
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--profile" {
+            return args.next().cloned();
+        }
+        if let Some(name) = arg.strip_prefix("--profile=") {
+            return Some(name.to_string());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dir_for, from_args, is_valid_name, DEFAULT_PROFILE};
+
+    #[test]
+    fn rejects_names_that_escape_the_profiles_directory() {
+        assert!(is_valid_name("Work"));
+        assert!(is_valid_name("Guest 2"));
+        assert!(is_valid_name("test-profile_1.0"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name(".."));
+        assert!(!is_valid_name("a/b"));
+        assert!(!is_valid_name("a\\b"));
+        assert!(!is_valid_name(" padded "));
+        assert!(!is_valid_name(&"x".repeat(65)));
+    }
+
+    #[test]
+    fn default_profile_keeps_the_existing_directory() {
+        assert_eq!(dir_for(DEFAULT_PROFILE), dir_for("default"));
+        assert_eq!(dir_for("Work").parent().unwrap().parent().unwrap(), dir_for(DEFAULT_PROFILE));
+    }
+
+    #[test]
+    fn reads_the_profile_flag() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(from_args(&args(&["stokes", "--profile", "Work"])), Some("Work".to_string()));
+        assert_eq!(from_args(&args(&["stokes", "--profile=Work", "https://example.com"])), Some("Work".to_string()));
+        assert_eq!(from_args(&args(&["stokes", "https://example.com"])), None);
+    }
+}
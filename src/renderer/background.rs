@@ -20,11 +20,13 @@ use style::{
     },
 };
 use tracing::warn;
+use crate::visited_links;
 use crate::dom::ImageData;
 use crate::renderer::Element;
 use crate::renderer::gradient::to_peniko_gradient;
 use crate::renderer::layers::maybe_with_layer;
 use crate::renderer::painter::ToColorColor;
+use crate::renderer::force_dark::{self, ColorRole};
 
 impl Element<'_> {
     pub(super) fn draw_background(&self, scene: &mut impl PaintScene) {
@@ -123,6 +125,7 @@ impl Element<'_> {
             let bg_color = background_color
                 .resolve_to_absolute(&current_color)
                 .as_color_color();
+            let bg_color = force_dark::adjust(self.context.dom, bg_color, ColorRole::Background);
 
             if bg_color != AlphaColor::TRANSPARENT {
                 // Fill the color
@@ -134,11 +137,13 @@ impl Element<'_> {
     }
 
     fn draw_solid_bg(&self, scene: &mut impl PaintScene, shape: &BezPath) {
-        let current_color = self.style.clone_color();
-        let background_color = &self.style.get_background().background_color;
+        let style = visited_links::color_style(&self.style);
+        let current_color = style.clone_color();
+        let background_color = &style.get_background().background_color;
         let bg_color = background_color
             .resolve_to_absolute(&current_color)
             .as_color_color();
+        let bg_color = force_dark::adjust(self.context.dom, bg_color, ColorRole::Background);
 
         if bg_color != AlphaColor::TRANSPARENT {
             // Fill the color
@@ -52,6 +52,14 @@ where
         self.current_generation = self.current_generation.wrapping_add(1);
     }
 
+    pub(crate) fn len(&self) -> usize {
+        self.resources.len()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.resources.clear();
+    }
+
     #[allow(unused)]
     pub(crate) fn contains_key<Q>(&mut self, key: &Q) -> bool
     where
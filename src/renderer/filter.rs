@@ -0,0 +1,379 @@
+// CSS filter and backdrop-filter, drawn with Skia image filters. `filter` comes from stylo, while
+// backdrop-filter is read back from the custom property it is renamed to (see `renamed_properties`).
+use crate::dom::renamed_properties::{self, renamed_property_value};
+use crate::renderer::painter::ToColorColor;
+use color::{AlphaColor, Srgb};
+use skia_safe::{ImageFilter, TileMode, color_filters, image_filters};
+use style::properties::ComputedValues;
+use style::values::computed::Filter;
+
+/// One filter function, with lengths in CSS pixels and angles in degrees
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum FilterOp {
+    Blur(f32),
+    Brightness(f32),
+    Contrast(f32),
+    Grayscale(f32),
+    HueRotate(f32),
+    Invert(f32),
+    Opacity(f32),
+    Saturate(f32),
+    Sepia(f32),
+    DropShadow { x: f32, y: f32, blur: f32, color: AlphaColor<Srgb> },
+}
+
+/// The element's `filter` list
+pub(crate) fn element_filters(style: &ComputedValues) -> Vec<FilterOp> {
+    let current_color = style.clone_color();
+    style
+        .get_effects()
+        .filter
+        .0
+        .iter()
+        .filter_map(|filter| {
+            let op = match filter {
+                Filter::Blur(radius) => FilterOp::Blur(radius.px()),
+                Filter::Brightness(amount) => FilterOp::Brightness(amount.0),
+                Filter::Contrast(amount) => FilterOp::Contrast(amount.0),
+                Filter::Grayscale(amount) => FilterOp::Grayscale(amount.0),
+                Filter::HueRotate(angle) => FilterOp::HueRotate(angle.degrees()),
+                Filter::Invert(amount) => FilterOp::Invert(amount.0),
+                Filter::Opacity(amount) => FilterOp::Opacity(amount.0),
+                Filter::Saturate(amount) => FilterOp::Saturate(amount.0),
+                Filter::Sepia(amount) => FilterOp::Sepia(amount.0),
+                Filter::DropShadow(shadow) => FilterOp::DropShadow {
+                    x: shadow.horizontal.px(),
+                    y: shadow.vertical.px(),
+                    blur: shadow.blur.px(),
+                    color: shadow.color.resolve_to_absolute(&current_color).as_color_color(),
+                },
+                // url() references to SVG filters aren't supported
+                _ => return None,
+            };
+            Some(op)
+        })
+        .collect()
+}
+
+/// The element's `backdrop-filter` list, read back from the custom property it was renamed to
+pub(crate) fn element_backdrop_filters(style: &ComputedValues) -> Vec<FilterOp> {
+    let Some(value) = renamed_property_value(style, renamed_properties::BACKDROP_FILTER) else {
+        return Vec::new();
+    };
+    let font_size = style.clone_font_size().used_size().px();
+    let current_color = style.clone_color().as_color_color();
+    parse_filter_list(&value, font_size, current_color)
+}
+
+/// Parse a `<filter-value-list>` or `none`. Like an invalid declaration, anything that doesn't
+/// parse gives no filters at all.
+pub(crate) fn parse_filter_list(text: &str, font_size: f32, current_color: AlphaColor<Srgb>) -> Vec<FilterOp> {
+    let mut ops = Vec::new();
+    let mut rest = text.trim();
+    if rest.eq_ignore_ascii_case("none") {
+        return ops;
+    }
+    while !rest.is_empty() {
+        let Some(open) = rest.find('(') else {
+            return Vec::new();
+        };
+        let name = rest[..open].trim().to_ascii_lowercase();
+        let Some(close) = matching_paren(rest, open) else {
+            return Vec::new();
+        };
+        let argument = rest[open + 1..close].trim();
+        let op = match name.as_str() {
+            "blur" if argument.is_empty() => Some(FilterOp::Blur(0.0)),
+            "blur" => parse_length(argument, font_size).filter(|radius| *radius >= 0.0).map(FilterOp::Blur),
+            "brightness" => parse_amount(argument, false).map(FilterOp::Brightness),
+            "contrast" => parse_amount(argument, false).map(FilterOp::Contrast),
+            "grayscale" => parse_amount(argument, true).map(FilterOp::Grayscale),
+            "invert" => parse_amount(argument, true).map(FilterOp::Invert),
+            "opacity" => parse_amount(argument, true).map(FilterOp::Opacity),
+            "saturate" => parse_amount(argument, false).map(FilterOp::Saturate),
+            "sepia" => parse_amount(argument, true).map(FilterOp::Sepia),
+            "hue-rotate" => parse_angle(argument).map(FilterOp::HueRotate),
+            "drop-shadow" => parse_drop_shadow(argument, font_size, current_color),
+            _ => None,
+        };
+        let Some(op) = op else {
+            return Vec::new();
+        };
+        ops.push(op);
+        rest = rest[close + 1..].trim_start();
+    }
+    ops
+}
+
+fn matching_paren(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_length(text: &str, font_size: f32) -> Option<f32> {
+    let text = text.trim().to_ascii_lowercase();
+    if let Some(value) = text.strip_suffix("px") {
+        return value.parse().ok();
+    }
+    if let Some(value) = text.strip_suffix("rem") {
+        return value.parse::<f32>().ok().map(|value| value * 16.0);
+    }
+    if let Some(value) = text.strip_suffix("em") {
+        return value.parse::<f32>().ok().map(|value| value * font_size);
+    }
+    // Unitless lengths are only allowed for zero
+    text.parse::<f32>().ok().filter(|value| *value == 0.0)
+}
+
+/// A number or percentage, 1 when left out. Amounts past 100% are clamped where the spec says so.
+fn parse_amount(text: &str, clamp: bool) -> Option<f32> {
+    let amount = if text.is_empty() {
+        1.0
+    } else if let Some(value) = text.strip_suffix('%') {
+        value.trim().parse::<f32>().ok()? / 100.0
+    } else {
+        text.parse::<f32>().ok()?
+    };
+    if amount < 0.0 {
+        return None;
+    }
+    Some(if clamp { amount.min(1.0) } else { amount })
+}
+
+fn parse_angle(text: &str) -> Option<f32> {
+    let text = text.trim().to_ascii_lowercase();
+    if text.is_empty() {
+        return Some(0.0);
+    }
+    let units = [("deg", 1.0), ("grad", 0.9), ("rad", 180.0 / std::f32::consts::PI), ("turn", 360.0)];
+    for (unit, degrees) in units {
+        if let Some(value) = text.strip_suffix(unit) {
+            return value.parse::<f32>().ok().map(|value| value * degrees);
+        }
+    }
+    text.parse::<f32>().ok().filter(|value| *value == 0.0)
+}
+
+/// `drop-shadow(<color>? <x> <y> <blur>?)`, with the color also allowed last
+fn parse_drop_shadow(text: &str, font_size: f32, current_color: AlphaColor<Srgb>) -> Option<FilterOp> {
+    let mut lengths = Vec::new();
+    let mut color = None;
+    let mut lengths_done = false;
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        // Colors can be functions with spaces inside, so split on whitespace outside parentheses
+        let end = match rest.find(|c: char| c.is_whitespace() || c == '(') {
+            Some(i) if rest[i..].starts_with('(') => matching_paren(rest, i)? + 1,
+            Some(i) => i,
+            None => rest.len(),
+        };
+        let token = &rest[..end];
+        match parse_length(token, font_size) {
+            Some(_) if lengths_done => return None,
+            Some(length) => lengths.push(length),
+            None if color.is_some() => return None,
+            None => {
+                color = Some(parse_shadow_color(token)?);
+                lengths_done = !lengths.is_empty();
+            }
+        }
+        rest = rest[end..].trim_start();
+    }
+    let (x, y, blur) = match lengths[..] {
+        [x, y] => (x, y, 0.0),
+        [x, y, blur] if blur >= 0.0 => (x, y, blur),
+        _ => return None,
+    };
+    Some(FilterOp::DropShadow { x, y, blur, color: color.unwrap_or(current_color) })
+}
+
+fn parse_shadow_color(text: &str) -> Option<AlphaColor<Srgb>> {
+    Some(color::parse_color(text).ok()?.to_alpha_color::<Srgb>())
+}
+
+/// How far the filters can draw outside what they are given, in CSS pixels
+pub(crate) fn filter_outset(ops: &[FilterOp]) -> f32 {
+    ops.iter().fold(0.0, |outset, op| match op {
+        FilterOp::Blur(radius) => outset + radius * 3.0,
+        FilterOp::DropShadow { x, y, blur, .. } => outset + x.abs().max(y.abs()) + blur * 3.0,
+        _ => outset,
+    })
+}
+
+/// The Skia image filter applying `ops` in order, None when there is nothing to apply
+pub(crate) fn image_filter(ops: &[FilterOp], scale_factor: f64, tile_mode: TileMode) -> Option<ImageFilter> {
+    let scale = scale_factor as f32;
+    ops.iter().try_fold(None, |input: Option<ImageFilter>, op| {
+        let filter = match *op {
+            FilterOp::Blur(radius) => image_filters::blur((radius * scale, radius * scale), tile_mode, input, None),
+            FilterOp::DropShadow { x, y, blur, color } => {
+                let color = skia_safe::Color4f::new(
+                    color.components[0],
+                    color.components[1],
+                    color.components[2],
+                    color.components[3],
+                );
+                image_filters::drop_shadow(
+                    (x * scale, y * scale),
+                    (blur * scale, blur * scale),
+                    color,
+                    None,
+                    input,
+                    None,
+                )
+            }
+            _ => image_filters::color_filter(color_filters::matrix_row_major(&color_matrix(op), None), input, None),
+        };
+        filter.map(Some)
+    })?
+}
+
+/// The filter function's color matrix from the Filter Effects spec, row-major with the
+/// offsets in the last column
+fn color_matrix(op: &FilterOp) -> [f32; 20] {
+    let rgb = |m: [f32; 9], offset: f32| {
+        [
+            m[0], m[1], m[2], 0.0, offset, //
+            m[3], m[4], m[5], 0.0, offset, //
+            m[6], m[7], m[8], 0.0, offset, //
+            0.0, 0.0, 0.0, 1.0, 0.0,
+        ]
+    };
+    match *op {
+        FilterOp::Brightness(a) => rgb([a, 0.0, 0.0, 0.0, a, 0.0, 0.0, 0.0, a], 0.0),
+        FilterOp::Contrast(a) => rgb([a, 0.0, 0.0, 0.0, a, 0.0, 0.0, 0.0, a], 0.5 - 0.5 * a),
+        FilterOp::Invert(a) => {
+            let d = 1.0 - 2.0 * a;
+            rgb([d, 0.0, 0.0, 0.0, d, 0.0, 0.0, 0.0, d], a)
+        }
+        FilterOp::Grayscale(a) => {
+            let s = 1.0 - a;
+            rgb(
+                [
+                    0.2126 + 0.7874 * s,
+                    0.7152 - 0.7152 * s,
+                    0.0722 - 0.0722 * s,
+                    0.2126 - 0.2126 * s,
+                    0.7152 + 0.2848 * s,
+                    0.0722 - 0.0722 * s,
+                    0.2126 - 0.2126 * s,
+                    0.7152 - 0.7152 * s,
+                    0.0722 + 0.9278 * s,
+                ],
+                0.0,
+            )
+        }
+        FilterOp::Sepia(a) => {
+            let s = 1.0 - a;
+            rgb(
+                [
+                    0.393 + 0.607 * s,
+                    0.769 - 0.769 * s,
+                    0.189 - 0.189 * s,
+                    0.349 - 0.349 * s,
+                    0.686 + 0.314 * s,
+                    0.168 - 0.168 * s,
+                    0.272 - 0.272 * s,
+                    0.534 - 0.534 * s,
+                    0.131 + 0.869 * s,
+                ],
+                0.0,
+            )
+        }
+        FilterOp::Saturate(s) => rgb(
+            [
+                0.213 + 0.787 * s,
+                0.715 - 0.715 * s,
+                0.072 - 0.072 * s,
+                0.213 - 0.213 * s,
+                0.715 + 0.285 * s,
+                0.072 - 0.072 * s,
+                0.213 - 0.213 * s,
+                0.715 - 0.715 * s,
+                0.072 + 0.928 * s,
+            ],
+            0.0,
+        ),
+        FilterOp::HueRotate(degrees) => {
+            let (sin, cos) = degrees.to_radians().sin_cos();
+            rgb(
+                [
+                    0.213 + cos * 0.787 - sin * 0.213,
+                    0.715 - cos * 0.715 - sin * 0.715,
+                    0.072 - cos * 0.072 + sin * 0.928,
+                    0.213 - cos * 0.213 + sin * 0.143,
+                    0.715 + cos * 0.285 + sin * 0.140,
+                    0.072 - cos * 0.072 - sin * 0.283,
+                    0.213 - cos * 0.213 - sin * 0.787,
+                    0.715 - cos * 0.715 + sin * 0.715,
+                    0.072 + cos * 0.928 + sin * 0.072,
+                ],
+                0.0,
+            )
+        }
+        FilterOp::Opacity(a) => {
+            let mut m = rgb([1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0], 0.0);
+            m[18] = a;
+            m
+        }
+        FilterOp::Blur(_) | FilterOp::DropShadow { .. } => rgb([1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0], 0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: AlphaColor<Srgb> = AlphaColor::new([0.0, 0.0, 0.0, 1.0]);
+
+    #[test]
+    fn parses_filter_lists() {
+        assert_eq!(
+            parse_filter_list("blur(4px) saturate(180%) hue-rotate(0.5turn)", 16.0, BLACK),
+            vec![FilterOp::Blur(4.0), FilterOp::Saturate(1.8), FilterOp::HueRotate(180.0)]
+        );
+        assert_eq!(
+            parse_filter_list("grayscale(2) brightness()", 16.0, BLACK),
+            vec![FilterOp::Grayscale(1.0), FilterOp::Brightness(1.0)]
+        );
+        assert_eq!(parse_filter_list("blur(0.5em)", 20.0, BLACK), vec![FilterOp::Blur(10.0)]);
+        assert_eq!(parse_filter_list("none", 16.0, BLACK), vec![]);
+        // One bad function invalidates the whole list
+        assert_eq!(parse_filter_list("blur(4px) brightness(-1)", 16.0, BLACK), vec![]);
+        assert_eq!(parse_filter_list("blur(4px", 16.0, BLACK), vec![]);
+        assert_eq!(parse_filter_list("blur(4)", 16.0, BLACK), vec![]);
+    }
+
+    #[test]
+    fn parses_drop_shadows() {
+        let red = AlphaColor::new([1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(
+            parse_filter_list("drop-shadow(2px 3px 4px red)", 16.0, BLACK),
+            vec![FilterOp::DropShadow { x: 2.0, y: 3.0, blur: 4.0, color: red }]
+        );
+        assert_eq!(
+            parse_filter_list("drop-shadow(rgb(255 0 0) 2px 3px)", 16.0, BLACK),
+            vec![FilterOp::DropShadow { x: 2.0, y: 3.0, blur: 0.0, color: red }]
+        );
+        assert_eq!(parse_filter_list("drop-shadow(2px)", 16.0, BLACK), vec![]);
+    }
+
+    #[test]
+    fn outset_covers_blur_and_shadow_offsets() {
+        assert_eq!(filter_outset(&[FilterOp::Blur(2.0), FilterOp::Grayscale(1.0)]), 6.0);
+        let shadow = FilterOp::DropShadow { x: -5.0, y: 2.0, blur: 1.0, color: BLACK };
+        assert_eq!(filter_outset(&[shadow]), 8.0);
+    }
+}
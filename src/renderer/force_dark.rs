@@ -0,0 +1,81 @@
+// Forced dark mode: a color transform applied to resolved styles at paint time, for sites that
+// have no dark theme of their own. Light backgrounds are darkened and dark text is lightened in
+// Oklch, keeping hue and chroma; colors that already fit a dark page and all images are left alone.
+use blitz_traits::shell::ColorScheme;
+use color::{AlphaColor, Oklch, Srgb};
+use crate::dom::Dom;
+
+/// What a color is painted as, which decides which way it gets flipped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColorRole {
+    /// Page, box and table backgrounds
+    Background,
+    /// Text, decorations, borders and outlines
+    Foreground,
+}
+
+/// Lightness range light backgrounds are mapped into (white becomes the darkest value)
+const BACKGROUND_LIGHTNESS: (f32, f32) = (0.18, 0.5);
+/// Lightness range dark foregrounds are mapped into (black becomes the lightest value)
+const FOREGROUND_LIGHTNESS: (f32, f32) = (0.5, 0.9);
+
+/// Whether the page in `dom` is painted with forced dark colors. Pages already drawn in a dark
+/// color scheme have a native dark theme and are left alone.
+pub(crate) fn is_active(dom: &Dom) -> bool {
+    dom.force_dark && dom.used_color_scheme == ColorScheme::Light
+}
+
+/// The color to paint for `color` in `role`
+pub(crate) fn adjust(dom: &Dom, color: AlphaColor<Srgb>, role: ColorRole) -> AlphaColor<Srgb> {
+    if is_active(dom) { force_dark(color, role) } else { color }
+}
+
+fn force_dark(color: AlphaColor<Srgb>, role: ColorRole) -> AlphaColor<Srgb> {
+    if color.components[3] == 0.0 {
+        return color;
+    }
+    let mut oklch = color.convert::<Oklch>();
+    let lightness = oklch.components[0].clamp(0.0, 1.0);
+    oklch.components[0] = match role {
+        ColorRole::Background if lightness > BACKGROUND_LIGHTNESS.1 => {
+            let (low, high) = BACKGROUND_LIGHTNESS;
+            low + (1.0 - lightness) / (1.0 - high) * (high - low)
+        }
+        ColorRole::Foreground if lightness < FOREGROUND_LIGHTNESS.0 => {
+            let (low, high) = FOREGROUND_LIGHTNESS;
+            high - lightness / low * (high - low)
+        }
+        _ => return color,
+    };
+    oklch.convert::<Srgb>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{force_dark, ColorRole};
+    use color::{AlphaColor, Oklch, Srgb};
+
+    fn lightness(color: AlphaColor<Srgb>) -> f32 {
+        color.convert::<Oklch>().components[0]
+    }
+
+    #[test]
+    fn light_backgrounds_and_dark_text_are_flipped() {
+        let white = AlphaColor::<Srgb>::new([1.0, 1.0, 1.0, 1.0]);
+        let black = AlphaColor::<Srgb>::new([0.0, 0.0, 0.0, 1.0]);
+
+        assert!((lightness(force_dark(white, ColorRole::Background)) - 0.18).abs() < 0.01);
+        assert!((lightness(force_dark(black, ColorRole::Foreground)) - 0.9).abs() < 0.01);
+    }
+
+    #[test]
+    fn colors_that_already_suit_a_dark_page_are_kept() {
+        let dark = AlphaColor::<Srgb>::new([0.1, 0.1, 0.12, 1.0]);
+        let light = AlphaColor::<Srgb>::new([0.9, 0.9, 0.9, 1.0]);
+        let transparent = AlphaColor::<Srgb>::new([1.0, 1.0, 1.0, 0.0]);
+
+        assert_eq!(force_dark(dark, ColorRole::Background), dark);
+        assert_eq!(force_dark(light, ColorRole::Foreground), light);
+        assert_eq!(force_dark(transparent, ColorRole::Background), transparent);
+    }
+}
@@ -1,6 +1,8 @@
+use crate::renderer::painter::ScenePainter;
 use anyrender::PaintScene;
 use kurbo::{Affine, Shape};
 use peniko::Mix;
+use skia_safe::ImageFilter;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 const LAYER_LIMIT: usize = 1024;
@@ -60,6 +62,29 @@ pub(crate) fn maybe_push_layer(
     true
 }
 
+/// Like `maybe_with_layer`, for a layer drawn through a `filter` image filter. The layer isn't
+/// clipped, so blurs and drop shadows can spread past the element.
+pub(crate) fn maybe_with_filter_layer<'a, F: FnOnce(&mut ScenePainter<'a>)>(
+    scene: &mut ScenePainter<'a>,
+    filter: Option<&ImageFilter>,
+    transform: Affine,
+    paint_layer: F,
+) {
+    let layer_used = filter.is_some_and(|filter| {
+        LAYERS_WANTED.fetch_add(1, Ordering::SeqCst);
+        if LAYERS_USED.load(Ordering::SeqCst) > LAYER_LIMIT {
+            return false;
+        }
+        scene.push_filter_layer(filter, transform);
+        LAYERS_USED.fetch_add(1, Ordering::SeqCst);
+        let depth = LAYER_DEPTH.fetch_add(1, Ordering::SeqCst) + 1;
+        LAYER_DEPTH_USED.fetch_max(depth, Ordering::SeqCst);
+        true
+    });
+    paint_layer(scene);
+    maybe_pop_layer(scene, layer_used);
+}
+
 pub(crate) fn maybe_pop_layer(scene: &mut impl PaintScene, condition: bool) {
     if condition {
         scene.pop_layer();
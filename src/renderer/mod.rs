@@ -4,27 +4,34 @@ pub(crate) mod background;
 mod cache;
 mod kurbo_css;
 mod layers;
+pub(crate) mod filter;
 mod shadow;
 mod gradient;
 mod sizing;
 mod svg;
 pub mod painter;
-mod form;
+mod widget;
+mod scrollbar;
+pub(crate) mod force_dark;
+pub(crate) mod webgl;
 
 use std::any::Any;
 use std::collections::HashMap;
-use crate::dom::node::{ListItemLayout, ListItemLayoutPosition, Marker, SpecialElementData, TextInputData};
-use crate::dom::{Dom, DomNode, ElementData, NodeData};
+use crate::visited_links;
+use crate::dom::node::{AltTextLayout, ListItemLayout, ListItemLayoutPosition, Marker, SpecialElementData, TextInputData};
+use crate::dom::{AbstractDom, Dom, DomNode, ElementData, ImageData, NodeData};
 use crate::renderer::kurbo_css::{CssBox, Edge, NonUniformRoundedRectRadii};
-use crate::renderer::layers::{maybe_with_layer, reset_layer_stats};
-use crate::renderer::text::{draw_text_selection, stroke_text, SELECTION_COLOR};
+use crate::renderer::layers::{maybe_with_filter_layer, maybe_with_layer, reset_layer_stats};
+use crate::renderer::text::{draw_text_highlight, draw_text_selection, stroke_text, FIND_CURRENT_COLOR, FIND_MATCH_COLOR, SELECTION_COLOR};
 use crate::renderer::painter::ToColorColor;
+use crate::renderer::force_dark::ColorRole;
 use anyrender::{CustomPaint, Paint, PaintScene};
 use color::{AlphaColor, Srgb};
 use kurbo::{Affine, BezPath, Insets, Point, Rect, Stroke, Vec2};
 use markup5ever::local_name;
 use parley::PositionedLayoutItem;
 use peniko::Fill;
+use skia_safe::TileMode;
 use style::dom::TElement;
 use style::properties::generated::longhands::border_collapse::computed_value::T as BorderCollapse;
 use style::properties::generated::longhands::visibility::computed_value::T as Visibility;
@@ -35,7 +42,7 @@ use style::values::computed::{BorderCornerRadius, BorderStyle, CSSPixelLength, O
 use style::values::generics::color::{GenericColor, GenericColorOrAuto};
 use taffy::Layout;
 use painter::ScenePainter;
-use crate::dom::stylo_to_kurbo::resolve_2d_transform;
+use crate::dom::stylo_to_kurbo::{resolve_2d_transform, resolve_clip_path};
 use crate::renderer::background::{to_image_quality, to_peniko_image};
 use crate::renderer::sizing::compute_object_fit;
 
@@ -48,6 +55,8 @@ pub struct HtmlRenderer<'dom> {
     pub(crate) initial_x: f64,
     pub(crate) initial_y: f64,
     pub(crate) selection_ranges: HashMap<usize, (usize, usize)>,
+    /// Find-in-page matches to paint, by inline root: byte ranges and whether each is the current match
+    pub(crate) find_highlights: HashMap<usize, Vec<(usize, usize, bool)>>,
     /// Debug: Show hitboxes for all elements
     pub(crate) debug_hitboxes: bool,
 }
@@ -71,6 +80,19 @@ impl HtmlRenderer<'_> {
     ) {
         reset_layer_stats();
 
+        self.render_document(painter);
+
+        self.render_top_layer(painter);
+        self.render_viewport_scrollbars(painter);
+
+        // Draw debug hitboxes if enabled
+        if self.debug_hitboxes {
+            self.render_debug_hitboxes(painter, self.dom.root_element().id, 0.0, 0.0);
+        }
+    }
+
+    /// Paint the canvas background and the document's boxes
+    fn render_document(&self, painter: &mut ScenePainter) {
         let scroll = self.dom.viewport_scroll;
 
         let root_element = self.dom.root_element();
@@ -105,8 +127,12 @@ impl HtmlRenderer<'_> {
             }
         };
 
+        // Forced dark paints a dark canvas under pages that leave it white
+        let background_color = background_color
+            .map(|color| color.as_color_color())
+            .or_else(|| force_dark::is_active(self.dom).then_some(AlphaColor::WHITE));
         if let Some(bg_color) = background_color {
-            let bg_color = bg_color.as_color_color();
+            let bg_color = force_dark::adjust(self.dom, bg_color, ColorRole::Background);
             let rect = Rect::from_origin_size(
                 (self.initial_x * self.scale_factor, self.initial_y * self.scale_factor),
                 (bg_width as f64, bg_height as f64),
@@ -122,11 +148,6 @@ impl HtmlRenderer<'_> {
                 y: self.initial_y - scroll.y,
             },
         );
-
-        // Draw debug hitboxes if enabled
-        if self.debug_hitboxes {
-            self.render_debug_hitboxes(painter, root_id, 0.0, 0.0);
-        }
     }
 
     /// Render debug hitboxes for all elements (showing click target areas)
@@ -356,6 +377,42 @@ impl HtmlRenderer<'_> {
         painter: &mut ScenePainter,
         node_id: usize,
         location: Point,
+    ) {
+        // Modal dialogs are painted last, by `render_top_layer`
+        if self.dom.tree()[node_id].flags.is_modal() {
+            return;
+        }
+        self.paint_element(painter, node_id, location);
+    }
+
+    /// Paint the modal dialogs above the rest of the page, each over a backdrop that dims
+    /// everything below it
+    fn render_top_layer(&self, painter: &mut ScenePainter) {
+        let scroll = self.dom.viewport_scroll;
+        for node_id in self.dom.modal_dialogs() {
+            let backdrop = Rect::from_origin_size(
+                (self.initial_x * self.scale_factor, self.initial_y * self.scale_factor),
+                (self.width as f64, self.height as f64),
+            );
+            painter.fill(Fill::NonZero, Affine::IDENTITY, AlphaColor::<Srgb>::from_rgba8(0, 0, 0, 26), None, &backdrop);
+
+            // Where the dialog's containing block would be painted, so the location layout's
+            // top layer pass gave the dialog puts it in the middle of the viewport
+            let node = &self.dom.tree()[node_id];
+            let origin = node.page_border_origin();
+            let location = Point {
+                x: self.initial_x - scroll.x + (origin.x - node.final_layout.location.x) as f64,
+                y: self.initial_y - scroll.y + (origin.y - node.final_layout.location.y) as f64,
+            };
+            self.paint_element(painter, node_id, location);
+        }
+    }
+
+    fn paint_element(
+        &self,
+        painter: &mut ScenePainter,
+        node_id: usize,
+        location: Point,
     ) {
         let node = &self.dom.tree()[node_id];
 
@@ -388,6 +445,9 @@ impl HtmlRenderer<'_> {
         let should_clip = is_image || is_text_input || !matches!(overflow_x, Overflow::Visible) || !matches!(overflow_y, Overflow::Visible);
 
         let (layout, position) = self.node_position(node_id, location);
+        // Sticky boxes are shifted with everything they contain to stay in view
+        let (sticky_x, sticky_y) = self.dom.sticky_offset(node_id);
+        let position = position + Vec2::new(sticky_x, sticky_y);
         let taffy::Layout {
             size,
             border,
@@ -406,9 +466,12 @@ impl HtmlRenderer<'_> {
             height: (size.height as f64 - scaled_padding_border.top - scaled_padding_border.bottom) * self.scale_factor,
         };
 
+        let filters = filter::element_filters(&styles);
+        let filter_outset = filter::filter_outset(&filters) as f64 * self.scale_factor;
+
         let scaled_y = (position.y - self.initial_y) * self.scale_factor;
         let scaled_content_height = content_size.height.max(size.height) as f64 * self.scale_factor;
-        if scaled_y > self.height as f64 || scaled_y + scaled_content_height < 0.0 {
+        if scaled_y - filter_outset > self.height as f64 || scaled_y + scaled_content_height + filter_outset < 0.0 {
             return; // Skip rendering boxes outside viewport
         }
 
@@ -418,53 +481,80 @@ impl HtmlRenderer<'_> {
         }
 
         let mut element = self.element(node, layout, position);
+        // The scrollbars stay put while the content is offset by the scroll position
+        let scrollbar_transform = element.transform;
 
-        element.draw_outline(painter);
-        element.draw_outset_box_shadow(painter);
+        // clip-path cuts everything the element draws, its shadows, outline and filters included
+        let clip_path = resolve_clip_path(&styles, &node.final_layout, self.scale_factor);
+        if let Some(clip_path) = &clip_path {
+            painter.push_clip_layer(element.transform, clip_path);
+        }
 
-        maybe_with_layer(
-            painter,
-            has_opacity,
-            opacity,
-            element.transform,
-            &element.frame.border_box_path(),
-            |painter| {
-                element.draw_background(painter);
-                element.draw_inset_box_shadow(painter);
-                element.draw_table_row_backgrounds(painter);
-                element.draw_table_borders(painter);
-                element.draw_border(painter);
-
-                //let wants_layer = should_clip | has_opacity;
-                let clip = if is_text_input {
-                    &element.frame.content_box_path()
-                } else {
-                    &element.frame.padding_box_path()
-                };
-                maybe_with_layer(painter, should_clip, 1.0, element.transform, clip, |painter| {
-                    let position = Point {
-                        x: content_pos.x - node.scroll_offset.x,
-                        y: content_pos.y - node.scroll_offset.y,
-                    };
-                    element.position = Point {
-                        x: element.position.x - node.scroll_offset.x,
-                        y: element.position.y - node.scroll_offset.y,
+        // backdrop-filter redraws what is behind the border box before anything of the element
+        let backdrop_filters = filter::element_backdrop_filters(&styles);
+        if let Some(backdrop) = filter::image_filter(&backdrop_filters, self.scale_factor, TileMode::Clamp) {
+            painter.draw_backdrop_filter(&backdrop, opacity, element.transform, &element.frame.border_box_path());
+        }
+
+        let filter = filter::image_filter(&filters, self.scale_factor, TileMode::Decal);
+        maybe_with_filter_layer(painter, filter.as_ref(), element.transform, |painter| {
+            element.draw_outline(painter);
+            element.draw_focus_ring(painter);
+            element.draw_outset_box_shadow(painter);
+
+            maybe_with_layer(
+                painter,
+                has_opacity,
+                opacity,
+                element.transform,
+                &element.frame.border_box_path(),
+                |painter| {
+                    element.draw_background(painter);
+                    element.draw_inset_box_shadow(painter);
+                    element.draw_table_row_backgrounds(painter);
+                    element.draw_table_borders(painter);
+                    element.draw_border(painter);
+                    element.draw_widget(painter);
+
+                    //let wants_layer = should_clip | has_opacity;
+                    let clip = if is_text_input {
+                        &element.frame.content_box_path()
+                    } else {
+                        &element.frame.padding_box_path()
                     };
-                    element.transform = element.transform.then_translate(Vec2 {
-                        x: -node.scroll_offset.x,
-                        y: -node.scroll_offset.y
+                    maybe_with_layer(painter, should_clip, 1.0, element.transform, clip, |painter| {
+                        let position = Point {
+                            x: content_pos.x - node.scroll_offset.x,
+                            y: content_pos.y - node.scroll_offset.y,
+                        };
+                        element.position = Point {
+                            x: element.position.x - node.scroll_offset.x,
+                            y: element.position.y - node.scroll_offset.y,
+                        };
+                        element.transform = element.transform.then_translate(Vec2 {
+                            x: -node.scroll_offset.x,
+                            y: -node.scroll_offset.y
+                        });
+                        element.draw_image(painter);
+                        element.draw_blocked_image(painter);
+                        element.draw_svg(painter);
+                        element.draw_canvas(painter);
+                        element.draw_sub_document(painter);
+                        element.draw_input(painter);
+                        element.draw_progress(painter);
+                        element.draw_text_input_text(painter, position);
+                        element.draw_inline_layout(painter, position);
+                        element.draw_marker(painter, position);
+                        element.draw_children(painter);
                     });
-                    element.draw_image(painter);
-                    element.draw_svg(painter);
-                    element.draw_canvas(painter);
-                    element.draw_input(painter);
-                    element.draw_text_input_text(painter, position);
-                    element.draw_inline_layout(painter, position);
-                    element.draw_marker(painter, position);
-                    element.draw_children(painter);
-                });
-            }
-        );
+                    element.draw_scrollbars(painter, scrollbar_transform);
+                }
+            );
+        });
+
+        if clip_path.is_some() {
+            painter.pop_layer();
+        }
     }
 
     fn render_node(&self, scene: &mut ScenePainter, node_id: usize, location: Point) {
@@ -512,8 +602,7 @@ impl HtmlRenderer<'_> {
 
         // Apply CSS transform property (where transforms are 2d)
         //
-        // TODO: Handle hit testing correctly for transformed nodes
-        // TODO: Implement nested transforms
+        // TODO: Implement nested transforms (hit testing mirrors this, see `DomNode::hit`)
         if let Some(style_transform) = resolve_2d_transform(style.get_box(), reference_box, scale) {
             transform *= style_transform;
         }
@@ -686,6 +775,11 @@ impl Element<'_> {
             let transform =
                 Affine::translate((pos.x * self.scale_factor, pos.y * self.scale_factor)) * self.transform;
 
+            for &(start, end, current) in self.context.find_highlights.get(&self.node.id).into_iter().flatten() {
+                let color = if current { FIND_CURRENT_COLOR } else { FIND_MATCH_COLOR };
+                draw_text_highlight(painter, &text_layout.layout, transform, start, end, color);
+            }
+
             if let Some(&(start, end)) = self.context.selection_ranges.get(&self.node.id) {
                 draw_text_selection(
                     painter,
@@ -738,7 +832,7 @@ impl Element<'_> {
                     painter.fill(
                         Fill::NonZero,
                         transform,
-                        caret_color.as_color_color(),
+                        force_dark::adjust(self.context.dom, caret_color.as_color_color(), ColorRole::Foreground),
                         None,
                         &convert_rect(&cursor),
                     );
@@ -756,7 +850,7 @@ impl Element<'_> {
     }
 
     fn draw_border(&self, painter: &mut ScenePainter) {
-        let style = &*self.style;
+        let style = visited_links::color_style(&self.style);
         let border = style.get_border();
         let current_color = style.clone_color();
 
@@ -777,6 +871,7 @@ impl Element<'_> {
             }
                 .resolve_to_absolute(&current_color)
                 .as_color_color();
+            let color = force_dark::adjust(self.context.dom, color, ColorRole::Foreground);
 
             if color.components[3] > 0.0 {
                 borders[count] = (color, Some(self.frame.border_edge_shape(edge)));
@@ -825,8 +920,10 @@ impl Element<'_> {
     fn draw_outline(&self, painter: &mut ScenePainter) {
         let outline = self.style.get_outline();
 
-        let current_color = self.style.clone_color();
-        let color = outline.outline_color.resolve_to_absolute(&current_color).as_color_color();
+        let colors = visited_links::color_style(&self.style);
+        let current_color = colors.clone_color();
+        let color = colors.get_outline().outline_color.resolve_to_absolute(&current_color).as_color_color();
+        let color = force_dark::adjust(self.context.dom, color, ColorRole::Foreground);
 
         let style = match outline.outline_style {
             OutlineStyle::Auto => return,
@@ -882,6 +979,7 @@ impl Element<'_> {
             .border_top_color
             .resolve_to_absolute(&current_color)
             .as_color_color();
+        let border_color = force_dark::adjust(self.context.dom, border_color, ColorRole::Foreground);
 
         // No need to draw transparent borders (as they won't be visible anyway)
         if border_color == AlphaColor::TRANSPARENT {
@@ -1030,6 +1128,53 @@ impl Element<'_> {
         }
     }
 
+    /// Draw the placeholder of an image that wasn't fetched: a box with the alt text inside
+    fn draw_blocked_image(&self, painter: &mut ScenePainter) {
+        let Some(ImageData::Blocked(alt)) = self.element.image_data() else {
+            return;
+        };
+        let content_box = self.frame.content_box;
+        let fill = force_dark::adjust(self.context.dom, AlphaColor::from_rgb8(240, 240, 240), ColorRole::Background);
+        let border = force_dark::adjust(self.context.dom, AlphaColor::from_rgb8(180, 180, 180), ColorRole::Foreground);
+        painter.fill(Fill::NonZero, self.transform, fill, None, &content_box);
+        painter.stroke(&Stroke::new(self.scale_factor), self.transform, border, None, &content_box);
+
+        if let Some(AltTextLayout(layout)) = alt {
+            let padding = 4.0 * self.scale_factor;
+            let transform = self.transform.pre_translate(Vec2 {
+                x: content_box.x0 + padding,
+                y: content_box.y0 + padding,
+            });
+            maybe_with_layer(painter, true, 1.0, self.transform, &content_box, |painter| {
+                stroke_text(painter, layout.lines(), self.context.dom, transform, self.scale_factor);
+            });
+        }
+    }
+
+    /// Draw a frame's nested document inside its content box. The frame doesn't scroll, so
+    /// whatever overflows it is cut off.
+    fn draw_sub_document(&self, painter: &mut ScenePainter) {
+        let Some(sub_dom) = self.element.sub_dom_data() else {
+            return;
+        };
+        let sub_dom = sub_dom.inner();
+        let content_box = self.frame.content_box;
+        let renderer = HtmlRenderer {
+            dom: &sub_dom,
+            scale_factor: self.scale_factor,
+            width: content_box.width() as u32,
+            height: content_box.height() as u32,
+            initial_x: self.position.x + content_box.x0 / self.scale_factor,
+            initial_y: self.position.y + content_box.y0 / self.scale_factor,
+            selection_ranges: HashMap::new(),
+            find_highlights: HashMap::new(),
+            debug_hitboxes: false,
+        };
+        maybe_with_layer(painter, true, 1.0, self.transform, &content_box, |painter| {
+            renderer.render_document(painter);
+        });
+    }
+
     fn draw_canvas(&self, painter: &mut ScenePainter) {
         let Some(custom_paint_source) = self.element.canvas_data() else {
             return;
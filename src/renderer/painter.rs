@@ -10,7 +10,7 @@ use skia_safe::canvas::{GlyphPositions, SaveLayerRec};
 use skia_safe::font::Edging;
 use skia_safe::font_arguments::variation_position::Coordinate;
 use skia_safe::font_arguments::VariationPosition;
-use skia_safe::{BlurStyle, Canvas, Color, ColorSpace, Font, FontArguments, FontHinting, FontMgr, GlyphId, MaskFilter, Paint, PaintCap, PaintJoin, PaintStyle, RRect, Rect, Shader, Typeface};
+use skia_safe::{BlurStyle, Canvas, Color, ColorSpace, Font, FontArguments, FontHinting, FontMgr, GlyphId, ImageFilter, MaskFilter, Paint, PaintCap, PaintJoin, PaintStyle, RRect, Rect, Shader, Typeface};
 use style::color::AbsoluteColor;
 use tracing::error;
 
@@ -34,6 +34,25 @@ impl SkiaCache {
         self.image_shader.next_gen();
         self.font.next_gen();
     }
+
+    /// Number of cached fonts, typefaces and image shaders
+    pub(crate) fn len(&self) -> usize {
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        let extracted = self.extracted_font_data.len();
+        #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+        let extracted = 0;
+        extracted + self.typeface.len() + self.normalized_typeface.len() + self.image_shader.len() + self.font.len()
+    }
+
+    /// Drop everything cached, when the tab is torn down
+    pub(crate) fn clear(&mut self) {
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        self.extracted_font_data.clear();
+        self.typeface.clear();
+        self.normalized_typeface.clear();
+        self.image_shader.clear();
+        self.font.clear();
+    }
 }
 
 impl Default for SkiaCache {
@@ -82,6 +101,38 @@ impl ScenePainter<'_> {
         self.inner.concat(&sk_kurbo::matrix_from_affine(transform));
     }
 
+    /// Start a layer that goes through `filter` when it is popped with `pop_layer`
+    pub(crate) fn push_filter_layer(&mut self, filter: &ImageFilter, transform: kurbo::Affine) {
+        self.reset_paint();
+        self.cache.paint.set_image_filter(filter.clone());
+
+        self.inner.save();
+        self.set_matrix(transform);
+        self.inner
+            .save_layer(&SaveLayerRec::default().paint(&self.cache.paint));
+    }
+
+    /// Replace what has been drawn under `clip` with itself through `filter`, mixed in by `alpha`
+    pub(crate) fn draw_backdrop_filter(
+        &mut self,
+        filter: &ImageFilter,
+        alpha: f32,
+        transform: kurbo::Affine,
+        clip: &impl kurbo::Shape,
+    ) {
+        self.reset_paint();
+        self.set_paint_alpha(alpha);
+
+        self.inner.save();
+        self.set_matrix(transform);
+        self.clip(clip);
+        // The layer starts out as the filtered backdrop and is composited straight back
+        self.inner
+            .save_layer(&SaveLayerRec::default().paint(&self.cache.paint).backdrop(filter));
+        self.inner.restore();
+        self.inner.restore();
+    }
+
     pub(crate) fn clip(&self, shape: &impl kurbo::Shape) {
         if let Some(rect) = shape.as_rect() {
             self.inner.clip_rect(sk_kurbo::rect_from(rect), None, true);
@@ -128,7 +179,23 @@ impl ScenePainter<'_> {
 
                 self.cache.paint.set_shader(image_shader);
             }
-            anyrender::Paint::Custom(_) => unreachable!(), // ToDo: figure out what to do with this
+            anyrender::Paint::Custom(custom) => {
+                // The only custom paint is a canvas, which shows its WebGL context's last frame
+                // stretched over the element. A canvas without one stays transparent.
+                let frame = custom
+                    .downcast_ref::<anyrender::CustomPaint>()
+                    .and_then(|canvas| Some((canvas, crate::renderer::webgl::frame(canvas.source_id)?)));
+                let Some((canvas, frame)) = frame else {
+                    self.cache.paint.set_color(Color::TRANSPARENT);
+                    return;
+                };
+                let local_matrix = skia_safe::Matrix::scale((
+                    canvas.width as f32 / frame.width() as f32,
+                    canvas.height as f32 / frame.height() as f32,
+                ));
+                let sampling = skia_safe::SamplingOptions::new(skia_safe::FilterMode::Linear, skia_safe::MipmapMode::None);
+                self.cache.paint.set_shader(frame.to_shader(None, sampling, &local_matrix));
+            }
         }
     }
 
@@ -373,8 +440,8 @@ impl ScenePainter<'_> {
                         .get_font(font.index)
                         .and_then(|font| font.copy_data())
                     else {
-                        eprintln!(
-                            "WARNING: failed to extract font {} {}",
+                        tracing::warn!(
+                            "Failed to extract font {} {}",
                             cache_key.0, cache_key.1
                         );
                         return None;
@@ -604,7 +671,8 @@ mod sk_peniko {
                 ImageAlphaType::Alpha => SkAlphaType::Unpremul,
                 ImageAlphaType::AlphaPremultiplied => SkAlphaType::Premul,
             },
-            None,
+            // Decoding converted the image to sRGB; Skia takes it on to the surface's color space
+            Some(skia_safe::ColorSpace::new_srgb()),
         );
         let pixels = unsafe {
             SkData::new_bytes(image_data.data.data()) // We have to ensure the src image data lives long enough
@@ -775,13 +843,11 @@ mod sk_peniko {
         )
     }
 
+    /// Gradient stops may be in any CSS color space, while Skia reads them as sRGB. Converting
+    /// them without clamping keeps colors outside sRGB, such as `color(display-p3 1 0 0)`, for
+    /// wide-gamut surfaces.
     pub(in crate::renderer) fn color4f_from_dynamic_color(color: DynamicColor) -> SkColor4f {
-        SkColor4f::new(
-            color.components[0],
-            color.components[1],
-            color.components[2],
-            color.components[3],
-        )
+        color4f_from_alpha_color(color.to_alpha_color::<Srgb>())
     }
 
     pub(in crate::renderer) fn gradient_shader_cs_from_cs_tag(
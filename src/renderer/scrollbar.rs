@@ -0,0 +1,105 @@
+// Overlay scrollbars, laid out by dom/scrollbar.rs. `scrollbar-color` paints the thumb and
+// track; without it the track is left clear and the thumb is a translucent grey that darkens
+// while it is dragged. The viewport's vertical scrollbar also carries a tick for each
+// find-in-page match, at its place in the document.
+use anyrender::PaintScene;
+use kurbo::{Affine, Rect};
+use peniko::{Color, Fill};
+use style::properties::ComputedValues;
+use style::values::generics::ui::GenericScrollbarColor;
+use crate::dom::Dom;
+use crate::dom::events::pointer::DragMode;
+use crate::dom::scrollbar::{Scrollbar, ScrollbarAxis};
+use crate::renderer::{Element, HtmlRenderer};
+use crate::renderer::painter::ToColorColor;
+use crate::renderer::force_dark::{self, ColorRole};
+use crate::renderer::text::{FIND_CURRENT_COLOR, FIND_MATCH_COLOR};
+
+const THUMB_COLOR: Color = Color::from_rgba8(0, 0, 0, 100);
+const DRAGGED_THUMB_COLOR: Color = Color::from_rgba8(0, 0, 0, 160);
+/// Thickness of a find match's tick, and how far it reaches past each side of the track, in CSS px
+const MARKER_THICKNESS: f64 = 2.0;
+const MARKER_OVERHANG: f64 = 1.0;
+
+/// The thumb and track colors `scrollbar-color` asks for, `None` for `auto`
+fn scrollbar_colors(style: &ComputedValues) -> Option<(Color, Color)> {
+    match style.clone_scrollbar_color() {
+        GenericScrollbarColor::Auto => None,
+        GenericScrollbarColor::Colors { thumb, track } => {
+            let current_color = style.clone_color();
+            Some((
+                thumb.resolve_to_absolute(&current_color).as_color_color(),
+                track.resolve_to_absolute(&current_color).as_color_color(),
+            ))
+        }
+    }
+}
+
+/// Paint `scrollbars`, whose rects are in CSS px, with `transform` taking them to the scene
+fn paint_scrollbars(
+    scene: &mut impl PaintScene,
+    dom: &Dom,
+    style: &ComputedValues,
+    scrollbars: &[Scrollbar],
+    transform: Affine,
+) {
+    let colors = scrollbar_colors(style);
+    for scrollbar in scrollbars {
+        let dragged = matches!(
+            &dom.drag_mode,
+            DragMode::Scrollbar(drag)
+                if drag.scrollbar.scroller == scrollbar.scroller && drag.scrollbar.axis == scrollbar.axis
+        );
+        let radius = scrollbar.track.width().min(scrollbar.track.height()) / 2.0;
+        let thumb_color = match colors {
+            Some((thumb, track)) => {
+                scene.fill(Fill::NonZero, transform, force_dark::adjust(dom, track, ColorRole::Background), None, &scrollbar.track.to_rounded_rect(radius));
+                thumb
+            }
+            None if dragged => DRAGGED_THUMB_COLOR,
+            None => THUMB_COLOR,
+        };
+        let thumb_color = force_dark::adjust(dom, thumb_color, ColorRole::Foreground);
+        scene.fill(Fill::NonZero, transform, thumb_color, None, &scrollbar.thumb.to_rounded_rect(radius));
+    }
+}
+
+impl HtmlRenderer<'_> {
+    /// Paint the viewport's scrollbars over everything else, top layer included
+    pub(super) fn render_viewport_scrollbars(&self, scene: &mut impl PaintScene) {
+        let scrollbars = self.dom.scrollbars(None);
+        let Some(style) = self.dom.try_root_element().and_then(|root| root.primary_styles()) else {
+            return;
+        };
+        let transform = Affine::translate((self.initial_x * self.scale_factor, self.initial_y * self.scale_factor))
+            * Affine::scale(self.scale_factor);
+        paint_scrollbars(scene, self.dom, &style, &scrollbars, transform);
+
+        let Some(scrollbar) = scrollbars.iter().find(|scrollbar| scrollbar.axis == ScrollbarAxis::Vertical) else {
+            return;
+        };
+        for (fraction, current) in self.dom.find_markers() {
+            let y = scrollbar.track_point(fraction);
+            let marker = Rect::new(
+                scrollbar.track.x0 - MARKER_OVERHANG,
+                y - MARKER_THICKNESS / 2.0,
+                scrollbar.track.x1 + MARKER_OVERHANG,
+                y + MARKER_THICKNESS / 2.0,
+            );
+            let color = if current { FIND_CURRENT_COLOR } else { FIND_MATCH_COLOR };
+            scene.fill(Fill::NonZero, transform, color, None, &marker);
+        }
+    }
+}
+
+impl Element<'_> {
+    /// Paint an overflow container's scrollbars. `transform` is the element's own, before its
+    /// content was offset by the scroll position.
+    pub(super) fn draw_scrollbars(&self, scene: &mut impl PaintScene, transform: Affine) {
+        let scrollbars = self.context.dom.scrollbars(Some(self.node.id));
+        if scrollbars.is_empty() {
+            return;
+        }
+        paint_scrollbars(scene, self.context.dom, &self.style, &scrollbars, transform * Affine::scale(self.scale_factor));
+    }
+}
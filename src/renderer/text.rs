@@ -1,4 +1,5 @@
 use crate::renderer::gradient::to_peniko_gradient;
+use crate::visited_links;
 use crate::renderer::painter::ScenePainter;
 use crate::dom::Dom;
 use crate::ui::TextBrush;
@@ -10,6 +11,7 @@ use std::collections::HashMap;
 use style::values::generics::image::GenericImage;
 use style::values::specified::TextDecorationLine;
 use crate::renderer::painter::ToColorColor;
+use crate::renderer::force_dark::{self, ColorRole};
 
 pub fn stroke_text<'a>(
     painter: &mut ScenePainter,
@@ -79,13 +81,19 @@ pub fn stroke_text<'a>(
                     .unwrap()
                     .primary_styles()
                     .unwrap();
-                let itext_styles = styles.get_inherited_text();
+                // Text set in a web font still in its block period takes up its space unseen
+                if dom.font_loads.hides(&styles.get_font().font_family) {
+                    continue;
+                }
+                let colors = visited_links::color_style(&styles);
                 let text_styles = styles.get_text();
-                let text_color = itext_styles.color.as_color_color();
-                let text_decoration_color = text_styles
+                let text_color =
+                    force_dark::adjust(dom, colors.get_inherited_text().color.as_color_color(), ColorRole::Foreground);
+                let text_decoration_color = colors
+                    .get_text()
                     .text_decoration_color
                     .as_absolute()
-                    .map(ToColorColor::as_color_color)
+                    .map(|color| force_dark::adjust(dom, color.as_color_color(), ColorRole::Foreground))
                     .unwrap_or(text_color);
                 let text_decoration_brush = anyrender::Paint::from(text_decoration_color);
                 let text_decoration_line = text_styles.text_decoration_line;
@@ -177,6 +185,9 @@ pub fn stroke_text<'a>(
 }
 
 pub const SELECTION_COLOR: Color = Color::from_rgb8(180, 213, 255);
+/// Find-in-page matches, and the current one
+pub const FIND_MATCH_COLOR: Color = Color::from_rgb8(255, 255, 0);
+pub const FIND_CURRENT_COLOR: Color = Color::from_rgb8(255, 150, 50);
 
 pub(crate) fn draw_text_selection(
     scene: &mut impl PaintScene,
@@ -185,12 +196,24 @@ pub(crate) fn draw_text_selection(
     selection_start: usize,
     selection_end: usize,
 ) {
-    let anchor = Cursor::from_byte_index(layout, selection_start, Affinity::Downstream);
-    let focus = Cursor::from_byte_index(layout, selection_end, Affinity::Downstream);
+    draw_text_highlight(scene, layout, transform, selection_start, selection_end, SELECTION_COLOR);
+}
+
+/// Fill the background of a byte range of the text in `color`
+pub(crate) fn draw_text_highlight(
+    scene: &mut impl PaintScene,
+    layout: &Layout<TextBrush>,
+    transform: Affine,
+    start: usize,
+    end: usize,
+    color: Color,
+) {
+    let anchor = Cursor::from_byte_index(layout, start, Affinity::Downstream);
+    let focus = Cursor::from_byte_index(layout, end, Affinity::Downstream);
     let selection = Selection::new(anchor, focus);
 
     selection.geometry_with(layout, |rect, _line_idx| {
         let rect = kurbo::Rect::new(rect.x0, rect.y0, rect.x1, rect.y1);
-        scene.fill(Fill::NonZero, transform, SELECTION_COLOR, None, &rect);
+        scene.fill(Fill::NonZero, transform, color, None, &rect);
     });
 }
\ No newline at end of file
@@ -0,0 +1,2372 @@
+// WebGL 1 contexts for <canvas>. Pages draw with the tab's own GL context, each WebGL context into
+// an offscreen framebuffer of its own. Skia shares that GL context, so every context keeps a copy
+// of the GL state it set and puts it back before its first call after Skia or another context has
+// used GL. Finished frames are read back once per painted frame and drawn like any other canvas.
+use gl::types::{GLboolean, GLchar, GLenum, GLfloat, GLint, GLintptr, GLsizei, GLsizeiptr, GLuint};
+use skia_safe::{AlphaType, ColorType, Data, Image, ImageInfo};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::ptr;
+use tracing::{debug, warn};
+
+/// Texture units a context can use. Skia binds textures to the same units, so all of them are
+/// put back whenever a context takes GL over again.
+const MAX_TEXTURE_UNITS: usize = 16;
+/// Largest drawing buffer side a page can ask for
+const MAX_DRAWING_BUFFER_SIZE: i32 = 4096;
+
+// WebGL-only pixel store parameters, which are applied while uploading rather than by GL
+pub(crate) const UNPACK_FLIP_Y_WEBGL: GLenum = 0x9240;
+pub(crate) const UNPACK_PREMULTIPLY_ALPHA_WEBGL: GLenum = 0x9241;
+pub(crate) const UNPACK_COLORSPACE_CONVERSION_WEBGL: GLenum = 0x9243;
+const BROWSER_DEFAULT_WEBGL: GLenum = 0x9244;
+/// WebGL's name for a combined depth and stencil renderbuffer format
+const DEPTH_STENCIL: GLenum = 0x84F9;
+const RGB565: GLenum = 0x8D62;
+const MAX_VERTEX_UNIFORM_VECTORS: GLenum = 0x8DFB;
+const MAX_VARYING_VECTORS: GLenum = 0x8DFC;
+const MAX_FRAGMENT_UNIFORM_VECTORS: GLenum = 0x8DFD;
+
+/// Capabilities a page can enable and disable
+const CAPABILITIES: [GLenum; 9] = [
+    gl::BLEND,
+    gl::CULL_FACE,
+    gl::DEPTH_TEST,
+    gl::DITHER,
+    gl::POLYGON_OFFSET_FILL,
+    gl::SAMPLE_ALPHA_TO_COVERAGE,
+    gl::SAMPLE_COVERAGE,
+    gl::SCISSOR_TEST,
+    gl::STENCIL_TEST,
+];
+
+/// Words GLSL 1.50 reserves that GLSL ES 1.00 leaves free for shaders to use as names. Shaders
+/// that use them get them renamed with `RENAMED_PREFIX`.
+const RESERVED_IN_GLSL_150: [&str; 14] = [
+    "texture",
+    "sample",
+    "smooth",
+    "flat",
+    "noperspective",
+    "centroid",
+    "layout",
+    "patch",
+    "resource",
+    "filter",
+    "common",
+    "partition",
+    "active",
+    "buffer",
+];
+const RENAMED_PREFIX: &str = "_stokes_";
+/// Fragment shader outputs standing in for gl_FragColor and gl_FragData on GLSL 1.50
+const FRAG_COLOR: &str = "_stokes_FragColor";
+const FRAG_DATA: &str = "_stokes_FragData";
+
+thread_local! {
+    static WEBGL: RefCell<WebGl> = RefCell::new(WebGl::default());
+}
+
+#[derive(Default)]
+struct WebGl {
+    /// The shading language the tab's GL context compiles. None while the tab renders without GL.
+    dialect: Option<ShaderDialect>,
+    contexts: HashMap<u64, WebGlContext>,
+    next_id: u64,
+    /// The context whose state GL holds. None once Skia has drawn.
+    current: Option<u64>,
+    /// GL was used since the last frame, so Skia's idea of the GL state is stale
+    touched: bool,
+    /// A context has a new frame to show
+    redraw: bool,
+}
+
+/// The shading language GLSL ES 1.00 shaders are translated to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ShaderDialect {
+    /// OpenGL ES, which compiles WebGL shaders as they are
+    Es,
+    /// Desktop GL 3.2 and later
+    Glsl150,
+    /// Desktop GL 2.1
+    Glsl120,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ShaderStage {
+    Vertex,
+    Fragment,
+}
+
+/// The `getContext('webgl', attributes)` options that change the drawing buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ContextAttributes {
+    pub alpha: bool,
+    pub depth: bool,
+    pub stencil: bool,
+    pub premultiplied_alpha: bool,
+    pub preserve_drawing_buffer: bool,
+}
+
+impl Default for ContextAttributes {
+    fn default() -> Self {
+        Self { alpha: true, depth: true, stencil: false, premultiplied_alpha: true, preserve_drawing_buffer: false }
+    }
+}
+
+/// GL objects a page can create
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ObjectKind {
+    Buffer,
+    Texture,
+    Shader,
+    Program,
+    Framebuffer,
+    Renderbuffer,
+}
+
+/// What `getParameter` answers with
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Parameter {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Ints(Vec<i32>),
+    Floats(Vec<f32>),
+    Bools(Vec<bool>),
+    /// A page object id, or 0 for none
+    Object(u32),
+}
+
+/// A uniform or attribute as `getActiveUniform` and `getActiveAttrib` describe it
+pub(crate) struct ActiveInfo {
+    pub size: i32,
+    pub kind: GLenum,
+    pub name: String,
+}
+
+/// Pixels of an image element or ImageData to upload, as unpremultiplied RGBA
+pub(crate) struct SourcePixels<'a> {
+    pub width: i32,
+    pub height: i32,
+    pub rgba: &'a [u8],
+}
+
+struct GlObject {
+    kind: ObjectKind,
+    name: GLuint,
+}
+
+#[derive(Default)]
+struct BufferInfo {
+    size: usize,
+    usage: GLenum,
+    /// WebGL 1 binds a buffer to one target for its whole life
+    target: Option<GLenum>,
+    /// A copy of index data, to check drawElements against the vertex buffers
+    indices: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct VertexAttrib {
+    enabled: bool,
+    buffer: GLuint,
+    size: GLint,
+    kind: GLenum,
+    normalized: bool,
+    stride: GLsizei,
+    offset: usize,
+}
+
+/// The GL state a context has set, put back whenever it takes GL over again
+struct GlState {
+    /// 0 for the drawing buffer
+    framebuffer: GLuint,
+    renderbuffer: GLuint,
+    array_buffer: GLuint,
+    element_array_buffer: GLuint,
+    program: GLuint,
+    active_texture: GLenum,
+    /// 2D and cube map binding of each texture unit
+    textures: [[GLuint; 2]; MAX_TEXTURE_UNITS],
+    viewport: [GLint; 4],
+    scissor: [GLint; 4],
+    enabled: Vec<GLenum>,
+    clear_color: [GLfloat; 4],
+    clear_depth: GLfloat,
+    clear_stencil: GLint,
+    blend_color: [GLfloat; 4],
+    blend_equation: [GLenum; 2],
+    /// Source and destination RGB factors, then source and destination alpha
+    blend_func: [GLenum; 4],
+    color_mask: [bool; 4],
+    depth_mask: bool,
+    depth_func: GLenum,
+    depth_range: [GLfloat; 2],
+    cull_face: GLenum,
+    front_face: GLenum,
+    /// Front then back: function, reference and mask
+    stencil_func: [(GLenum, GLint, GLuint); 2],
+    /// Front then back: fail, depth fail and pass
+    stencil_op: [[GLenum; 3]; 2],
+    stencil_mask: [GLuint; 2],
+    polygon_offset: [GLfloat; 2],
+    line_width: GLfloat,
+    sample_coverage: (GLfloat, bool),
+    pack_alignment: GLint,
+    unpack_alignment: GLint,
+    unpack_flip_y: bool,
+    unpack_premultiply_alpha: bool,
+    generate_mipmap_hint: GLenum,
+    /// Values of attributes with no array enabled
+    generic_attribs: Vec<[GLfloat; 4]>,
+}
+
+impl GlState {
+    fn new(width: i32, height: i32, max_vertex_attribs: usize) -> Self {
+        Self {
+            framebuffer: 0,
+            renderbuffer: 0,
+            array_buffer: 0,
+            element_array_buffer: 0,
+            program: 0,
+            active_texture: gl::TEXTURE0,
+            textures: [[0; 2]; MAX_TEXTURE_UNITS],
+            viewport: [0, 0, width, height],
+            scissor: [0, 0, width, height],
+            enabled: vec![gl::DITHER],
+            clear_color: [0.0; 4],
+            clear_depth: 1.0,
+            clear_stencil: 0,
+            blend_color: [0.0; 4],
+            blend_equation: [gl::FUNC_ADD; 2],
+            blend_func: [gl::ONE, gl::ZERO, gl::ONE, gl::ZERO],
+            color_mask: [true; 4],
+            depth_mask: true,
+            depth_func: gl::LESS,
+            depth_range: [0.0, 1.0],
+            cull_face: gl::BACK,
+            front_face: gl::CCW,
+            stencil_func: [(gl::ALWAYS, 0, !0); 2],
+            stencil_op: [[gl::KEEP; 3]; 2],
+            stencil_mask: [!0; 2],
+            polygon_offset: [0.0; 2],
+            line_width: 1.0,
+            sample_coverage: (1.0, false),
+            pack_alignment: 4,
+            unpack_alignment: 4,
+            unpack_flip_y: false,
+            unpack_premultiply_alpha: false,
+            generate_mipmap_hint: gl::DONT_CARE,
+            generic_attribs: vec![[0.0, 0.0, 0.0, 1.0]; max_vertex_attribs],
+        }
+    }
+}
+
+/// One page's WebGL context
+pub(crate) struct WebGlContext {
+    dialect: ShaderDialect,
+    attributes: ContextAttributes,
+    width: i32,
+    height: i32,
+    /// The drawing buffer: a framebuffer with a color texture and, if asked for, a depth and
+    /// stencil renderbuffer
+    fbo: GLuint,
+    color: GLuint,
+    depth_stencil: GLuint,
+    /// Holds the context's vertex attribute arrays and index buffer binding away from Skia's
+    vao: GLuint,
+    objects: HashMap<u32, GlObject>,
+    next_object: u32,
+    shader_sources: HashMap<u32, String>,
+    buffers: HashMap<GLuint, BufferInfo>,
+    attribs: Vec<VertexAttrib>,
+    state: GlState,
+    max_texture_size: GLint,
+    /// An error found before reaching GL, reported by the next getError
+    error: GLenum,
+    /// The drawing buffer changed since it was last shown
+    dirty: bool,
+    /// The last frame shown, as drawn into the page
+    frame: Option<Image>,
+}
+
+/// Set up WebGL for a GL context that was just created and made current. Contexts made in an
+/// earlier GL context are lost.
+pub(crate) fn gl_context_created() {
+    let dialect = unsafe { detect_dialect() };
+    if dialect.is_none() {
+        debug!("[webgl] GL context cannot back WebGL");
+    }
+    WEBGL.with(|webgl| {
+        let mut webgl = webgl.borrow_mut();
+        webgl.dialect = dialect;
+        webgl.contexts.clear();
+        webgl.current = None;
+        webgl.touched = false;
+    });
+}
+
+/// The tab renders without GL, so WebGL is unavailable and existing contexts are lost
+pub(crate) fn gl_context_unavailable() {
+    WEBGL.with(|webgl| {
+        let mut webgl = webgl.borrow_mut();
+        webgl.dialect = None;
+        webgl.contexts.clear();
+        webgl.current = None;
+    });
+}
+
+/// Delete every context, when the document that made them goes away
+pub(crate) fn release_all() {
+    WEBGL.with(|webgl| {
+        let mut webgl = webgl.borrow_mut();
+        if webgl.contexts.is_empty() {
+            return;
+        }
+        if webgl.dialect.is_some() {
+            for context in webgl.contexts.values_mut() {
+                context.delete_gl_objects();
+            }
+            webgl.touched = true;
+        }
+        webgl.contexts.clear();
+        webgl.current = None;
+    });
+}
+
+/// Create a context with a `width` by `height` drawing buffer. None when the tab has no GL or
+/// the drawing buffer cannot be made.
+pub(crate) fn create_context(width: u32, height: u32, attributes: ContextAttributes) -> Option<u64> {
+    WEBGL.with(|webgl| {
+        let mut webgl = webgl.borrow_mut();
+        let dialect = webgl.dialect?;
+        webgl.touched = true;
+        webgl.current = None;
+        let context = WebGlContext::new(dialect, width, height, attributes)?;
+        webgl.next_id += 1;
+        let id = webgl.next_id;
+        webgl.contexts.insert(id, context);
+        webgl.current = Some(id);
+        webgl.redraw = true;
+        Some(id)
+    })
+}
+
+/// Run `f` on context `id` with its GL state in place. None when the context was lost.
+pub(crate) fn with_context<R>(id: u64, f: impl FnOnce(&mut WebGlContext) -> R) -> Option<R> {
+    WEBGL.with(|webgl| {
+        let mut webgl = webgl.borrow_mut();
+        let webgl = &mut *webgl;
+        let context = webgl.contexts.get_mut(&id)?;
+        if webgl.current != Some(id) {
+            context.restore_state();
+            webgl.current = Some(id);
+        }
+        webgl.touched = true;
+        let was_dirty = context.dirty;
+        let result = f(context);
+        if context.dirty && !was_dirty {
+            webgl.redraw = true;
+        }
+        Some(result)
+    })
+}
+
+/// GL was used outside a frame, so the next context call has to put its state back
+pub(crate) fn invalidate_state() {
+    WEBGL.with(|webgl| webgl.borrow_mut().current = None);
+}
+
+/// Whether a context got something new to show since the last call
+pub(crate) fn take_redraw_request() -> bool {
+    WEBGL.with(|webgl| std::mem::take(&mut webgl.borrow_mut().redraw))
+}
+
+/// Read back what contexts drew since the last frame, before the frame is painted. Returns true
+/// when WebGL used GL since the last call, in which case Skia's cached GL state must be reset.
+pub(crate) fn present() -> bool {
+    WEBGL.with(|webgl| {
+        let mut webgl = webgl.borrow_mut();
+        if !webgl.touched {
+            return false;
+        }
+        for context in webgl.contexts.values_mut() {
+            if context.dirty {
+                context.read_frame();
+            }
+        }
+        webgl.current = None;
+        webgl.touched = false;
+        true
+    })
+}
+
+/// The last frame of the context painted as custom paint source `source_id`
+pub(crate) fn frame(source_id: u64) -> Option<Image> {
+    WEBGL.with(|webgl| webgl.borrow().contexts.get(&source_id)?.frame.clone())
+}
+
+impl WebGlContext {
+    fn new(dialect: ShaderDialect, width: u32, height: u32, attributes: ContextAttributes) -> Option<Self> {
+        let (width, height) = clamp_size(width, height);
+        unsafe {
+            drain_gl_errors();
+            let max_vertex_attribs = get_integer(gl::MAX_VERTEX_ATTRIBS).clamp(8, 32) as usize;
+            let max_texture_size = get_integer(gl::MAX_TEXTURE_SIZE).max(64);
+
+            let mut context = Self {
+                dialect,
+                attributes,
+                width,
+                height,
+                fbo: 0,
+                color: 0,
+                depth_stencil: 0,
+                vao: 0,
+                objects: HashMap::new(),
+                next_object: 0,
+                shader_sources: HashMap::new(),
+                buffers: HashMap::new(),
+                attribs: vec![VertexAttrib::default(); max_vertex_attribs],
+                state: GlState::new(width, height, max_vertex_attribs),
+                max_texture_size,
+                error: gl::NO_ERROR,
+                dirty: true,
+                frame: None,
+            };
+            gl::GenFramebuffers(1, &mut context.fbo);
+            gl::GenTextures(1, &mut context.color);
+            if attributes.depth || attributes.stencil {
+                gl::GenRenderbuffers(1, &mut context.depth_stencil);
+            }
+            gl::GenVertexArrays(1, &mut context.vao);
+
+            if !context.allocate_drawing_buffer() {
+                warn!("[webgl] Could not create a {}x{} drawing buffer", width, height);
+                context.delete_gl_objects();
+                return None;
+            }
+            context.restore_state();
+            Some(context)
+        }
+    }
+
+    /// Give the drawing buffer storage for its current size and clear it. Returns false when
+    /// GL cannot render to it.
+    unsafe fn allocate_drawing_buffer(&mut self) -> bool {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.color);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as GLint,
+                self.width,
+                self.height,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, self.color, 0);
+            if self.depth_stencil != 0 {
+                gl::BindRenderbuffer(gl::RENDERBUFFER, self.depth_stencil);
+                gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH24_STENCIL8, self.width, self.height);
+                gl::FramebufferRenderbuffer(
+                    gl::FRAMEBUFFER,
+                    gl::DEPTH_STENCIL_ATTACHMENT,
+                    gl::RENDERBUFFER,
+                    self.depth_stencil,
+                );
+            }
+            let complete = gl::CheckFramebufferStatus(gl::FRAMEBUFFER) == gl::FRAMEBUFFER_COMPLETE;
+            if complete {
+                self.clear_drawing_buffer();
+            }
+            drain_gl_errors();
+            complete
+        }
+    }
+
+    /// Clear the whole drawing buffer to transparent black, whatever the page's masks and scissor
+    unsafe fn clear_drawing_buffer(&mut self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+            gl::DepthMask(gl::TRUE);
+            gl::StencilMask(!0);
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            self.set_clear_depth(1.0);
+            gl::ClearStencil(0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
+        }
+        self.dirty = true;
+    }
+
+    fn delete_gl_objects(&mut self) {
+        unsafe {
+            for object in self.objects.values() {
+                delete_object(object.kind, object.name);
+            }
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteTextures(1, &self.color);
+            if self.depth_stencil != 0 {
+                gl::DeleteRenderbuffers(1, &self.depth_stencil);
+            }
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+        self.objects.clear();
+    }
+
+    /// Put the context's GL state back after Skia or another context changed it
+    fn restore_state(&self) {
+        let state = &self.state;
+        unsafe {
+            drain_gl_errors();
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer_name(state.framebuffer));
+            gl::BindRenderbuffer(gl::RENDERBUFFER, state.renderbuffer);
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, state.array_buffer);
+            gl::UseProgram(state.program);
+            for (unit, [texture_2d, cube_map]) in state.textures.iter().enumerate() {
+                gl::ActiveTexture(gl::TEXTURE0 + unit as GLenum);
+                gl::BindTexture(gl::TEXTURE_2D, *texture_2d);
+                gl::BindTexture(gl::TEXTURE_CUBE_MAP, *cube_map);
+            }
+            gl::ActiveTexture(state.active_texture);
+
+            let [x, y, width, height] = state.viewport;
+            gl::Viewport(x, y, width, height);
+            let [x, y, width, height] = state.scissor;
+            gl::Scissor(x, y, width, height);
+            for capability in CAPABILITIES {
+                if state.enabled.contains(&capability) {
+                    gl::Enable(capability);
+                } else {
+                    gl::Disable(capability);
+                }
+            }
+
+            let [r, g, b, a] = state.clear_color;
+            gl::ClearColor(r, g, b, a);
+            self.set_clear_depth(state.clear_depth);
+            gl::ClearStencil(state.clear_stencil);
+            let [r, g, b, a] = state.blend_color;
+            gl::BlendColor(r, g, b, a);
+            gl::BlendEquationSeparate(state.blend_equation[0], state.blend_equation[1]);
+            let [src_rgb, dst_rgb, src_alpha, dst_alpha] = state.blend_func;
+            gl::BlendFuncSeparate(src_rgb, dst_rgb, src_alpha, dst_alpha);
+            let [r, g, b, a] = state.color_mask.map(gl_bool);
+            gl::ColorMask(r, g, b, a);
+            gl::DepthMask(gl_bool(state.depth_mask));
+            gl::DepthFunc(state.depth_func);
+            self.set_depth_range(state.depth_range);
+            gl::CullFace(state.cull_face);
+            gl::FrontFace(state.front_face);
+            for (face, index) in [(gl::FRONT, 0), (gl::BACK, 1)] {
+                let (func, reference, mask) = state.stencil_func[index];
+                gl::StencilFuncSeparate(face, func, reference, mask);
+                let [fail, depth_fail, pass] = state.stencil_op[index];
+                gl::StencilOpSeparate(face, fail, depth_fail, pass);
+                gl::StencilMaskSeparate(face, state.stencil_mask[index]);
+            }
+            gl::PolygonOffset(state.polygon_offset[0], state.polygon_offset[1]);
+            gl::LineWidth(state.line_width);
+            gl::SampleCoverage(state.sample_coverage.0, gl_bool(state.sample_coverage.1));
+            gl::Hint(gl::GENERATE_MIPMAP_HINT, state.generate_mipmap_hint);
+            for (index, value) in state.generic_attribs.iter().enumerate() {
+                gl::VertexAttrib4fv(index as GLuint, value.as_ptr());
+            }
+
+            gl::PixelStorei(gl::PACK_ALIGNMENT, state.pack_alignment);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, state.unpack_alignment);
+            if self.dialect != ShaderDialect::Es {
+                // Skia may leave these set. GLES 2 has none of them, so they are left alone there.
+                gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+                gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+                gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 0);
+                gl::PixelStorei(gl::UNPACK_SKIP_ROWS, 0);
+                gl::PixelStorei(gl::UNPACK_SKIP_PIXELS, 0);
+                gl::PixelStorei(gl::PACK_ROW_LENGTH, 0);
+                // Desktop GL only honors gl_PointSize with this on
+                gl::Enable(gl::PROGRAM_POINT_SIZE);
+                gl::Disable(gl::FRAMEBUFFER_SRGB);
+            }
+            drain_gl_errors();
+        }
+    }
+
+    /// Copy the drawing buffer into `frame` for the next painted frame
+    fn read_frame(&mut self) {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let row_bytes = width * 4;
+        let mut pixels = vec![0u8; row_bytes * height];
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            if self.dialect != ShaderDialect::Es {
+                gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+            }
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+            gl::ReadPixels(0, 0, self.width, self.height, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr() as *mut _);
+        }
+        // GL reads bottom row first
+        let mut flipped = vec![0u8; pixels.len()];
+        for (y, row) in pixels.chunks_exact(row_bytes).enumerate() {
+            let dst = (height - 1 - y) * row_bytes;
+            flipped[dst..dst + row_bytes].copy_from_slice(row);
+        }
+
+        let alpha_type = if !self.attributes.alpha {
+            AlphaType::Opaque
+        } else if self.attributes.premultiplied_alpha {
+            AlphaType::Premul
+        } else {
+            AlphaType::Unpremul
+        };
+        // A WebGL drawing buffer holds sRGB unless the page asks otherwise, which isn't supported
+        let info = ImageInfo::new((self.width, self.height), ColorType::RGBA8888, alpha_type, Some(skia_safe::ColorSpace::new_srgb()));
+        self.frame = skia_safe::images::raster_from_data(&info, Data::new_copy(&flipped), row_bytes);
+
+        if !self.attributes.preserve_drawing_buffer {
+            unsafe { self.clear_drawing_buffer() };
+        }
+        self.dirty = false;
+    }
+
+    fn framebuffer_name(&self, framebuffer: GLuint) -> GLuint {
+        if framebuffer == 0 { self.fbo } else { framebuffer }
+    }
+
+    fn set_clear_depth(&self, depth: GLfloat) {
+        unsafe {
+            if self.dialect == ShaderDialect::Es {
+                gl::ClearDepthf(depth);
+            } else {
+                gl::ClearDepth(depth as f64);
+            }
+        }
+    }
+
+    fn set_depth_range(&self, [near, far]: [GLfloat; 2]) {
+        unsafe {
+            if self.dialect == ShaderDialect::Es {
+                gl::DepthRangef(near, far);
+            } else {
+                gl::DepthRange(near as f64, far as f64);
+            }
+        }
+    }
+
+    /// Note an error to report from getError, as GL would have
+    pub(crate) fn synthesize_error(&mut self, error: GLenum) {
+        if self.error == gl::NO_ERROR {
+            self.error = error;
+        }
+    }
+
+    /// The GL name of page object `id`, 0 for `id` 0. Records INVALID_OPERATION and returns None
+    /// for objects that were deleted or are of another kind.
+    fn object_name(&mut self, id: u32, kind: ObjectKind) -> Option<GLuint> {
+        if id == 0 {
+            return Some(0);
+        }
+        match self.objects.get(&id) {
+            Some(object) if object.kind == kind => Some(object.name),
+            _ => {
+                self.synthesize_error(gl::INVALID_OPERATION);
+                None
+            }
+        }
+    }
+
+    /// The page id of GL object `name`, 0 for none
+    fn object_id(&self, name: GLuint, kind: ObjectKind) -> u32 {
+        if name == 0 {
+            return 0;
+        }
+        self.objects.iter().find(|(_, object)| object.kind == kind && object.name == name).map_or(0, |(id, _)| *id)
+    }
+
+    fn insert_object(&mut self, kind: ObjectKind, name: GLuint) -> u32 {
+        if name == 0 {
+            return 0;
+        }
+        self.next_object += 1;
+        self.objects.insert(self.next_object, GlObject { kind, name });
+        self.next_object
+    }
+
+    // Drawing buffer
+
+    pub(crate) fn drawing_buffer_size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    pub(crate) fn attributes(&self) -> ContextAttributes {
+        self.attributes
+    }
+
+    /// Resize the drawing buffer, which clears it, as setting canvas.width or canvas.height does
+    pub(crate) fn resize(&mut self, width: u32, height: u32) {
+        let (width, height) = clamp_size(width, height);
+        if (width, height) == (self.width, self.height) {
+            return;
+        }
+        self.width = width;
+        self.height = height;
+        unsafe {
+            self.allocate_drawing_buffer();
+        }
+        self.restore_state();
+    }
+
+    // Objects
+
+    pub(crate) fn create_object(&mut self, kind: ObjectKind) -> u32 {
+        let mut name = 0;
+        unsafe {
+            match kind {
+                ObjectKind::Buffer => gl::GenBuffers(1, &mut name),
+                ObjectKind::Texture => gl::GenTextures(1, &mut name),
+                ObjectKind::Framebuffer => gl::GenFramebuffers(1, &mut name),
+                ObjectKind::Renderbuffer => gl::GenRenderbuffers(1, &mut name),
+                ObjectKind::Program => name = gl::CreateProgram(),
+                ObjectKind::Shader => return 0,
+            }
+        }
+        if kind == ObjectKind::Buffer {
+            self.buffers.insert(name, BufferInfo::default());
+        }
+        self.insert_object(kind, name)
+    }
+
+    pub(crate) fn create_shader(&mut self, kind: GLenum) -> u32 {
+        if kind != gl::VERTEX_SHADER && kind != gl::FRAGMENT_SHADER {
+            self.synthesize_error(gl::INVALID_ENUM);
+            return 0;
+        }
+        let name = unsafe { gl::CreateShader(kind) };
+        self.insert_object(ObjectKind::Shader, name)
+    }
+
+    pub(crate) fn delete_object(&mut self, id: u32, kind: ObjectKind) {
+        if id == 0 {
+            return;
+        }
+        let Some(name) = self.object_name(id, kind) else {
+            return;
+        };
+        self.objects.remove(&id);
+        self.shader_sources.remove(&id);
+        let state = &mut self.state;
+        match kind {
+            ObjectKind::Buffer => {
+                self.buffers.remove(&name);
+                if state.array_buffer == name {
+                    state.array_buffer = 0;
+                }
+                if state.element_array_buffer == name {
+                    state.element_array_buffer = 0;
+                }
+                for attrib in &mut self.attribs {
+                    if attrib.buffer == name {
+                        attrib.buffer = 0;
+                    }
+                }
+            }
+            ObjectKind::Texture => {
+                for unit in &mut state.textures {
+                    for binding in unit.iter_mut() {
+                        if *binding == name {
+                            *binding = 0;
+                        }
+                    }
+                }
+            }
+            ObjectKind::Renderbuffer if state.renderbuffer == name => state.renderbuffer = 0,
+            ObjectKind::Framebuffer if state.framebuffer == name => {
+                // GL would fall back to the pbuffer; the page falls back to its drawing buffer
+                state.framebuffer = 0;
+                unsafe {
+                    delete_object(kind, name);
+                    gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+                }
+                return;
+            }
+            _ => {}
+        }
+        unsafe { delete_object(kind, name) };
+    }
+
+    pub(crate) fn is_object(&self, id: u32, kind: ObjectKind) -> bool {
+        self.objects.get(&id).is_some_and(|object| object.kind == kind)
+    }
+
+    // State
+
+    pub(crate) fn enable(&mut self, capability: GLenum, enabled: bool) {
+        if !CAPABILITIES.contains(&capability) {
+            self.synthesize_error(gl::INVALID_ENUM);
+            return;
+        }
+        self.state.enabled.retain(|&c| c != capability);
+        if enabled {
+            self.state.enabled.push(capability);
+            unsafe { gl::Enable(capability) };
+        } else {
+            unsafe { gl::Disable(capability) };
+        }
+    }
+
+    pub(crate) fn is_enabled(&self, capability: GLenum) -> bool {
+        self.state.enabled.contains(&capability)
+    }
+
+    pub(crate) fn viewport(&mut self, x: GLint, y: GLint, width: GLint, height: GLint) {
+        if width < 0 || height < 0 {
+            self.synthesize_error(gl::INVALID_VALUE);
+            return;
+        }
+        self.state.viewport = [x, y, width, height];
+        unsafe { gl::Viewport(x, y, width, height) };
+    }
+
+    pub(crate) fn scissor(&mut self, x: GLint, y: GLint, width: GLint, height: GLint) {
+        if width < 0 || height < 0 {
+            self.synthesize_error(gl::INVALID_VALUE);
+            return;
+        }
+        self.state.scissor = [x, y, width, height];
+        unsafe { gl::Scissor(x, y, width, height) };
+    }
+
+    pub(crate) fn clear_color(&mut self, color: [GLfloat; 4]) {
+        self.state.clear_color = color;
+        unsafe { gl::ClearColor(color[0], color[1], color[2], color[3]) };
+    }
+
+    pub(crate) fn clear_depth(&mut self, depth: GLfloat) {
+        let depth = depth.clamp(0.0, 1.0);
+        self.state.clear_depth = depth;
+        self.set_clear_depth(depth);
+    }
+
+    pub(crate) fn clear_stencil(&mut self, stencil: GLint) {
+        self.state.clear_stencil = stencil;
+        unsafe { gl::ClearStencil(stencil) };
+    }
+
+    pub(crate) fn blend_color(&mut self, color: [GLfloat; 4]) {
+        self.state.blend_color = color;
+        unsafe { gl::BlendColor(color[0], color[1], color[2], color[3]) };
+    }
+
+    pub(crate) fn blend_equation(&mut self, rgb: GLenum, alpha: GLenum) {
+        let valid = |mode| matches!(mode, gl::FUNC_ADD | gl::FUNC_SUBTRACT | gl::FUNC_REVERSE_SUBTRACT);
+        if !valid(rgb) || !valid(alpha) {
+            self.synthesize_error(gl::INVALID_ENUM);
+            return;
+        }
+        self.state.blend_equation = [rgb, alpha];
+        unsafe { gl::BlendEquationSeparate(rgb, alpha) };
+    }
+
+    pub(crate) fn blend_func(&mut self, src_rgb: GLenum, dst_rgb: GLenum, src_alpha: GLenum, dst_alpha: GLenum) {
+        self.state.blend_func = [src_rgb, dst_rgb, src_alpha, dst_alpha];
+        unsafe { gl::BlendFuncSeparate(src_rgb, dst_rgb, src_alpha, dst_alpha) };
+    }
+
+    pub(crate) fn color_mask(&mut self, mask: [bool; 4]) {
+        self.state.color_mask = mask;
+        let [r, g, b, a] = mask.map(gl_bool);
+        unsafe { gl::ColorMask(r, g, b, a) };
+    }
+
+    pub(crate) fn depth_mask(&mut self, mask: bool) {
+        self.state.depth_mask = mask;
+        unsafe { gl::DepthMask(gl_bool(mask)) };
+    }
+
+    pub(crate) fn depth_func(&mut self, func: GLenum) {
+        self.state.depth_func = func;
+        unsafe { gl::DepthFunc(func) };
+    }
+
+    pub(crate) fn depth_range(&mut self, near: GLfloat, far: GLfloat) {
+        if near > far {
+            self.synthesize_error(gl::INVALID_OPERATION);
+            return;
+        }
+        let range = [near.clamp(0.0, 1.0), far.clamp(0.0, 1.0)];
+        self.state.depth_range = range;
+        self.set_depth_range(range);
+    }
+
+    pub(crate) fn cull_face(&mut self, mode: GLenum) {
+        self.state.cull_face = mode;
+        unsafe { gl::CullFace(mode) };
+    }
+
+    pub(crate) fn front_face(&mut self, mode: GLenum) {
+        self.state.front_face = mode;
+        unsafe { gl::FrontFace(mode) };
+    }
+
+    /// The state index of each face `face` names
+    fn faces(face: GLenum) -> &'static [usize] {
+        match face {
+            gl::FRONT => &[0],
+            gl::BACK => &[1],
+            gl::FRONT_AND_BACK => &[0, 1],
+            _ => &[],
+        }
+    }
+
+    pub(crate) fn stencil_func(&mut self, face: GLenum, func: GLenum, reference: GLint, mask: GLuint) {
+        let faces = Self::faces(face);
+        if faces.is_empty() {
+            self.synthesize_error(gl::INVALID_ENUM);
+            return;
+        }
+        for &index in faces {
+            self.state.stencil_func[index] = (func, reference, mask);
+        }
+        unsafe { gl::StencilFuncSeparate(face, func, reference, mask) };
+    }
+
+    pub(crate) fn stencil_op(&mut self, face: GLenum, fail: GLenum, depth_fail: GLenum, pass: GLenum) {
+        let faces = Self::faces(face);
+        if faces.is_empty() {
+            self.synthesize_error(gl::INVALID_ENUM);
+            return;
+        }
+        for &index in faces {
+            self.state.stencil_op[index] = [fail, depth_fail, pass];
+        }
+        unsafe { gl::StencilOpSeparate(face, fail, depth_fail, pass) };
+    }
+
+    pub(crate) fn stencil_mask(&mut self, face: GLenum, mask: GLuint) {
+        let faces = Self::faces(face);
+        if faces.is_empty() {
+            self.synthesize_error(gl::INVALID_ENUM);
+            return;
+        }
+        for &index in faces {
+            self.state.stencil_mask[index] = mask;
+        }
+        unsafe { gl::StencilMaskSeparate(face, mask) };
+    }
+
+    pub(crate) fn polygon_offset(&mut self, factor: GLfloat, units: GLfloat) {
+        self.state.polygon_offset = [factor, units];
+        unsafe { gl::PolygonOffset(factor, units) };
+    }
+
+    pub(crate) fn line_width(&mut self, width: GLfloat) {
+        if !(width > 0.0) {
+            self.synthesize_error(gl::INVALID_VALUE);
+            return;
+        }
+        self.state.line_width = width;
+        unsafe { gl::LineWidth(width) };
+    }
+
+    pub(crate) fn sample_coverage(&mut self, value: GLfloat, invert: bool) {
+        self.state.sample_coverage = (value.clamp(0.0, 1.0), invert);
+        unsafe { gl::SampleCoverage(value.clamp(0.0, 1.0), gl_bool(invert)) };
+    }
+
+    pub(crate) fn hint(&mut self, target: GLenum, mode: GLenum) {
+        if target != gl::GENERATE_MIPMAP_HINT {
+            self.synthesize_error(gl::INVALID_ENUM);
+            return;
+        }
+        self.state.generate_mipmap_hint = mode;
+        unsafe { gl::Hint(target, mode) };
+    }
+
+    pub(crate) fn pixel_store(&mut self, parameter: GLenum, value: GLint) {
+        match parameter {
+            gl::PACK_ALIGNMENT | gl::UNPACK_ALIGNMENT => {
+                if !matches!(value, 1 | 2 | 4 | 8) {
+                    self.synthesize_error(gl::INVALID_VALUE);
+                    return;
+                }
+                if parameter == gl::PACK_ALIGNMENT {
+                    self.state.pack_alignment = value;
+                } else {
+                    self.state.unpack_alignment = value;
+                }
+                unsafe { gl::PixelStorei(parameter, value) };
+            }
+            UNPACK_FLIP_Y_WEBGL => self.state.unpack_flip_y = value != 0,
+            UNPACK_PREMULTIPLY_ALPHA_WEBGL => self.state.unpack_premultiply_alpha = value != 0,
+            // Images are uploaded as decoded, which is all the browser default conversion does
+            UNPACK_COLORSPACE_CONVERSION_WEBGL => {}
+            _ => self.synthesize_error(gl::INVALID_ENUM),
+        }
+    }
+
+    pub(crate) fn get_error(&mut self) -> GLenum {
+        let error = std::mem::replace(&mut self.error, gl::NO_ERROR);
+        if error != gl::NO_ERROR {
+            return error;
+        }
+        unsafe { gl::GetError() }
+    }
+
+    // Buffers
+
+    pub(crate) fn bind_buffer(&mut self, target: GLenum, id: u32) {
+        if target != gl::ARRAY_BUFFER && target != gl::ELEMENT_ARRAY_BUFFER {
+            self.synthesize_error(gl::INVALID_ENUM);
+            return;
+        }
+        let Some(name) = self.object_name(id, ObjectKind::Buffer) else {
+            return;
+        };
+        if let Some(info) = self.buffers.get_mut(&name) {
+            match info.target {
+                Some(bound) if bound != target => {
+                    self.synthesize_error(gl::INVALID_OPERATION);
+                    return;
+                }
+                _ => info.target = Some(target),
+            }
+        }
+        if target == gl::ARRAY_BUFFER {
+            self.state.array_buffer = name;
+        } else {
+            self.state.element_array_buffer = name;
+        }
+        unsafe { gl::BindBuffer(target, name) };
+    }
+
+    fn bound_buffer(&mut self, target: GLenum) -> Option<GLuint> {
+        let name = match target {
+            gl::ARRAY_BUFFER => self.state.array_buffer,
+            gl::ELEMENT_ARRAY_BUFFER => self.state.element_array_buffer,
+            _ => {
+                self.synthesize_error(gl::INVALID_ENUM);
+                return None;
+            }
+        };
+        if name == 0 {
+            self.synthesize_error(gl::INVALID_OPERATION);
+            return None;
+        }
+        Some(name)
+    }
+
+    /// bufferData with either `data` or, without it, `size` zeroed bytes
+    pub(crate) fn buffer_data(&mut self, target: GLenum, data: Option<Vec<u8>>, size: usize, usage: GLenum) {
+        let Some(name) = self.bound_buffer(target) else {
+            return;
+        };
+        let data = data.unwrap_or_else(|| vec![0; size]);
+        unsafe {
+            gl::BufferData(target, data.len() as GLsizeiptr, data.as_ptr() as *const _, usage);
+        }
+        let info = self.buffers.entry(name).or_default();
+        info.size = data.len();
+        info.usage = usage;
+        info.indices = (target == gl::ELEMENT_ARRAY_BUFFER).then_some(data);
+    }
+
+    pub(crate) fn buffer_sub_data(&mut self, target: GLenum, offset: usize, data: &[u8]) {
+        let Some(name) = self.bound_buffer(target) else {
+            return;
+        };
+        let info = self.buffers.entry(name).or_default();
+        let Some(end) = offset.checked_add(data.len()).filter(|&end| end <= info.size) else {
+            self.synthesize_error(gl::INVALID_VALUE);
+            return;
+        };
+        if let Some(indices) = info.indices.as_mut() {
+            indices[offset..end].copy_from_slice(data);
+        }
+        unsafe {
+            gl::BufferSubData(target, offset as GLintptr, data.len() as GLsizeiptr, data.as_ptr() as *const _);
+        }
+    }
+
+    pub(crate) fn buffer_parameter(&mut self, target: GLenum, parameter: GLenum) -> Parameter {
+        let Some(name) = self.bound_buffer(target) else {
+            return Parameter::Null;
+        };
+        let info = self.buffers.entry(name).or_default();
+        match parameter {
+            gl::BUFFER_SIZE => Parameter::Int(info.size as i64),
+            gl::BUFFER_USAGE => Parameter::Int(info.usage as i64),
+            _ => {
+                self.synthesize_error(gl::INVALID_ENUM);
+                Parameter::Null
+            }
+        }
+    }
+
+    // Vertex attributes
+
+    fn attrib_mut(&mut self, index: GLuint) -> Option<&mut VertexAttrib> {
+        if (index as usize) < self.attribs.len() {
+            self.attribs.get_mut(index as usize)
+        } else {
+            self.synthesize_error(gl::INVALID_VALUE);
+            None
+        }
+    }
+
+    pub(crate) fn enable_vertex_attrib_array(&mut self, index: GLuint, enabled: bool) {
+        let Some(attrib) = self.attrib_mut(index) else {
+            return;
+        };
+        attrib.enabled = enabled;
+        unsafe {
+            if enabled {
+                gl::EnableVertexAttribArray(index);
+            } else {
+                gl::DisableVertexAttribArray(index);
+            }
+        }
+    }
+
+    pub(crate) fn vertex_attrib_pointer(
+        &mut self,
+        index: GLuint,
+        size: GLint,
+        kind: GLenum,
+        normalized: bool,
+        stride: GLsizei,
+        offset: usize,
+    ) {
+        // WebGL has no client-side arrays: the data always comes from a buffer
+        let buffer = self.state.array_buffer;
+        if buffer == 0 {
+            self.synthesize_error(gl::INVALID_OPERATION);
+            return;
+        }
+        if !(1..=4).contains(&size) || !(0..=255).contains(&stride) {
+            self.synthesize_error(gl::INVALID_VALUE);
+            return;
+        }
+        let Some(type_size) = type_size(kind) else {
+            self.synthesize_error(gl::INVALID_ENUM);
+            return;
+        };
+        if offset % type_size != 0 || stride as usize % type_size != 0 {
+            self.synthesize_error(gl::INVALID_OPERATION);
+            return;
+        }
+        let Some(attrib) = self.attrib_mut(index) else {
+            return;
+        };
+        *attrib = VertexAttrib { enabled: attrib.enabled, buffer, size, kind, normalized, stride, offset };
+        unsafe {
+            gl::VertexAttribPointer(index, size, kind, gl_bool(normalized), stride, offset as *const _);
+        }
+    }
+
+    pub(crate) fn vertex_attrib(&mut self, index: GLuint, value: [GLfloat; 4]) {
+        if index as usize >= self.state.generic_attribs.len() {
+            self.synthesize_error(gl::INVALID_VALUE);
+            return;
+        }
+        self.state.generic_attribs[index as usize] = value;
+        unsafe { gl::VertexAttrib4fv(index, value.as_ptr()) };
+    }
+
+    pub(crate) fn vertex_attrib_parameter(&mut self, index: GLuint, parameter: GLenum) -> Parameter {
+        let Some(attrib) = self.attrib_mut(index).copied() else {
+            return Parameter::Null;
+        };
+        match parameter {
+            gl::VERTEX_ATTRIB_ARRAY_ENABLED => Parameter::Bool(attrib.enabled),
+            gl::VERTEX_ATTRIB_ARRAY_SIZE => Parameter::Int(if attrib.buffer == 0 { 4 } else { attrib.size as i64 }),
+            gl::VERTEX_ATTRIB_ARRAY_STRIDE => Parameter::Int(attrib.stride as i64),
+            gl::VERTEX_ATTRIB_ARRAY_TYPE => {
+                Parameter::Int(if attrib.buffer == 0 { gl::FLOAT } else { attrib.kind } as i64)
+            }
+            gl::VERTEX_ATTRIB_ARRAY_NORMALIZED => Parameter::Bool(attrib.normalized),
+            gl::VERTEX_ATTRIB_ARRAY_BUFFER_BINDING => {
+                Parameter::Object(self.object_id(attrib.buffer, ObjectKind::Buffer))
+            }
+            gl::CURRENT_VERTEX_ATTRIB => Parameter::Floats(self.state.generic_attribs[index as usize].to_vec()),
+            _ => {
+                self.synthesize_error(gl::INVALID_ENUM);
+                Parameter::Null
+            }
+        }
+    }
+
+    pub(crate) fn vertex_attrib_offset(&mut self, index: GLuint) -> usize {
+        self.attrib_mut(index).map_or(0, |attrib| attrib.offset)
+    }
+
+    /// Whether every enabled attribute array holds `vertices` vertices
+    fn vertices_in_range(&self, vertices: usize) -> bool {
+        self.attribs.iter().filter(|attrib| attrib.enabled).all(|attrib| {
+            let Some(buffer) = self.buffers.get(&attrib.buffer) else {
+                return false;
+            };
+            if vertices == 0 {
+                return true;
+            }
+            let element = attrib.size as usize * type_size(attrib.kind).unwrap_or(4);
+            let stride = if attrib.stride == 0 { element } else { attrib.stride as usize };
+            // The offset comes from the page, so this must not wrap
+            (vertices - 1)
+                .checked_mul(stride)
+                .and_then(|last| last.checked_add(attrib.offset))
+                .and_then(|start| start.checked_add(element))
+                .is_some_and(|end| end <= buffer.size)
+        })
+    }
+
+    // Drawing
+
+    fn before_draw(&mut self, mode: GLenum) -> bool {
+        if mode > gl::TRIANGLE_FAN {
+            self.synthesize_error(gl::INVALID_ENUM);
+            return false;
+        }
+        if self.state.program == 0 {
+            self.synthesize_error(gl::INVALID_OPERATION);
+            return false;
+        }
+        true
+    }
+
+    fn after_draw(&mut self) {
+        if self.state.framebuffer == 0 {
+            self.dirty = true;
+        }
+    }
+
+    pub(crate) fn clear(&mut self, mask: GLenum) {
+        let all = gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT;
+        if mask & !all != 0 {
+            self.synthesize_error(gl::INVALID_VALUE);
+            return;
+        }
+        unsafe { gl::Clear(mask) };
+        self.after_draw();
+    }
+
+    pub(crate) fn draw_arrays(&mut self, mode: GLenum, first: GLint, count: GLsizei) {
+        if first < 0 || count < 0 {
+            self.synthesize_error(gl::INVALID_VALUE);
+            return;
+        }
+        if !self.before_draw(mode) {
+            return;
+        }
+        if !self.vertices_in_range(if count == 0 { 0 } else { first as usize + count as usize }) {
+            self.synthesize_error(gl::INVALID_OPERATION);
+            return;
+        }
+        unsafe { gl::DrawArrays(mode, first, count) };
+        self.after_draw();
+    }
+
+    pub(crate) fn draw_elements(&mut self, mode: GLenum, count: GLsizei, kind: GLenum, offset: usize) {
+        if count < 0 {
+            self.synthesize_error(gl::INVALID_VALUE);
+            return;
+        }
+        let index_size = match kind {
+            gl::UNSIGNED_BYTE => 1,
+            gl::UNSIGNED_SHORT => 2,
+            _ => {
+                self.synthesize_error(gl::INVALID_ENUM);
+                return;
+            }
+        };
+        if !self.before_draw(mode) {
+            return;
+        }
+        let Some(indices) =
+            self.buffers.get(&self.state.element_array_buffer).and_then(|buffer| buffer.indices.as_ref())
+        else {
+            self.synthesize_error(gl::INVALID_OPERATION);
+            return;
+        };
+        let end = (count as usize).checked_mul(index_size).and_then(|len| offset.checked_add(len));
+        let Some(end) = end.filter(|&end| offset % index_size == 0 && end <= indices.len()) else {
+            self.synthesize_error(gl::INVALID_OPERATION);
+            return;
+        };
+        let max_index = if index_size == 1 {
+            indices[offset..end].iter().map(|&index| index as usize).max()
+        } else {
+            indices[offset..end].chunks_exact(2).map(|index| u16::from_ne_bytes([index[0], index[1]]) as usize).max()
+        };
+        if !self.vertices_in_range(max_index.map_or(0, |index| index + 1)) {
+            self.synthesize_error(gl::INVALID_OPERATION);
+            return;
+        }
+        unsafe { gl::DrawElements(mode, count, kind, offset as *const _) };
+        self.after_draw();
+    }
+
+    pub(crate) fn flush(&self) {
+        unsafe { gl::Flush() };
+    }
+
+    pub(crate) fn finish(&self) {
+        unsafe { gl::Finish() };
+    }
+
+    // Shaders and programs
+
+    pub(crate) fn shader_source(&mut self, shader: u32, source: String) {
+        if self.object_name(shader, ObjectKind::Shader).is_some() {
+            self.shader_sources.insert(shader, source);
+        }
+    }
+
+    pub(crate) fn get_shader_source(&mut self, shader: u32) -> Option<String> {
+        self.object_name(shader, ObjectKind::Shader)?;
+        Some(self.shader_sources.get(&shader).cloned().unwrap_or_default())
+    }
+
+    pub(crate) fn compile_shader(&mut self, shader: u32) {
+        let Some(name) = self.object_name(shader, ObjectKind::Shader) else {
+            return;
+        };
+        let source = self.shader_sources.get(&shader).map_or("", String::as_str);
+        let stage = if get_shader_integer(name, gl::SHADER_TYPE) as GLenum == gl::VERTEX_SHADER {
+            ShaderStage::Vertex
+        } else {
+            ShaderStage::Fragment
+        };
+        let translated = translate_shader(source, stage, self.dialect);
+        // Sources are not nul-terminated here, so GL is given the length
+        let length = translated.len() as GLint;
+        let pointer = translated.as_ptr() as *const GLchar;
+        unsafe {
+            gl::ShaderSource(name, 1, &pointer, &length);
+            gl::CompileShader(name);
+        }
+    }
+
+    pub(crate) fn shader_parameter(&mut self, shader: u32, parameter: GLenum) -> Parameter {
+        let Some(name) = self.object_name(shader, ObjectKind::Shader) else {
+            return Parameter::Null;
+        };
+        match parameter {
+            gl::SHADER_TYPE => Parameter::Int(get_shader_integer(name, parameter) as i64),
+            gl::COMPILE_STATUS | gl::DELETE_STATUS => Parameter::Bool(get_shader_integer(name, parameter) != 0),
+            _ => {
+                self.synthesize_error(gl::INVALID_ENUM);
+                Parameter::Null
+            }
+        }
+    }
+
+    pub(crate) fn shader_info_log(&mut self, shader: u32) -> Option<String> {
+        let name = self.object_name(shader, ObjectKind::Shader)?;
+        let length = get_shader_integer(name, gl::INFO_LOG_LENGTH);
+        let mut log = vec![0u8; length.max(1) as usize];
+        let mut written = 0;
+        unsafe { gl::GetShaderInfoLog(name, log.len() as GLsizei, &mut written, log.as_mut_ptr() as *mut GLchar) };
+        log.truncate(written.max(0) as usize);
+        Some(String::from_utf8_lossy(&log).into_owned())
+    }
+
+    pub(crate) fn attach_shader(&mut self, program: u32, shader: u32, attach: bool) {
+        let (Some(program), Some(shader)) =
+            (self.object_name(program, ObjectKind::Program), self.object_name(shader, ObjectKind::Shader))
+        else {
+            return;
+        };
+        unsafe {
+            if attach {
+                gl::AttachShader(program, shader);
+            } else {
+                gl::DetachShader(program, shader);
+            }
+        }
+    }
+
+    pub(crate) fn attached_shaders(&mut self, program: u32) -> Option<Vec<u32>> {
+        let name = self.object_name(program, ObjectKind::Program)?;
+        let mut shaders = [0; 2];
+        let mut count = 0;
+        unsafe { gl::GetAttachedShaders(name, shaders.len() as GLsizei, &mut count, shaders.as_mut_ptr()) };
+        Some(
+            shaders[..count.clamp(0, 2) as usize]
+                .iter()
+                .map(|&shader| self.object_id(shader, ObjectKind::Shader))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn link_program(&mut self, program: u32) {
+        if let Some(name) = self.object_name(program, ObjectKind::Program) {
+            unsafe { gl::LinkProgram(name) };
+        }
+    }
+
+    pub(crate) fn validate_program(&mut self, program: u32) {
+        if let Some(name) = self.object_name(program, ObjectKind::Program) {
+            unsafe { gl::ValidateProgram(name) };
+        }
+    }
+
+    pub(crate) fn use_program(&mut self, program: u32) {
+        let Some(name) = self.object_name(program, ObjectKind::Program) else {
+            return;
+        };
+        self.state.program = name;
+        unsafe { gl::UseProgram(name) };
+    }
+
+    pub(crate) fn program_parameter(&mut self, program: u32, parameter: GLenum) -> Parameter {
+        let Some(name) = self.object_name(program, ObjectKind::Program) else {
+            return Parameter::Null;
+        };
+        let mut value = 0;
+        match parameter {
+            gl::LINK_STATUS | gl::VALIDATE_STATUS | gl::DELETE_STATUS => {
+                unsafe { gl::GetProgramiv(name, parameter, &mut value) };
+                Parameter::Bool(value != 0)
+            }
+            gl::ATTACHED_SHADERS | gl::ACTIVE_ATTRIBUTES | gl::ACTIVE_UNIFORMS => {
+                unsafe { gl::GetProgramiv(name, parameter, &mut value) };
+                Parameter::Int(value as i64)
+            }
+            _ => {
+                self.synthesize_error(gl::INVALID_ENUM);
+                Parameter::Null
+            }
+        }
+    }
+
+    pub(crate) fn program_info_log(&mut self, program: u32) -> Option<String> {
+        let name = self.object_name(program, ObjectKind::Program)?;
+        let mut length = 0;
+        unsafe { gl::GetProgramiv(name, gl::INFO_LOG_LENGTH, &mut length) };
+        let mut log = vec![0u8; length.max(1) as usize];
+        let mut written = 0;
+        unsafe { gl::GetProgramInfoLog(name, log.len() as GLsizei, &mut written, log.as_mut_ptr() as *mut GLchar) };
+        log.truncate(written.max(0) as usize);
+        Some(String::from_utf8_lossy(&log).into_owned())
+    }
+
+    /// getActiveAttrib when `uniform` is false, getActiveUniform when it is true
+    pub(crate) fn active_info(&mut self, program: u32, index: GLuint, uniform: bool) -> Option<ActiveInfo> {
+        let name = self.object_name(program, ObjectKind::Program)?;
+        let mut count = 0;
+        let count_parameter = if uniform { gl::ACTIVE_UNIFORMS } else { gl::ACTIVE_ATTRIBUTES };
+        unsafe { gl::GetProgramiv(name, count_parameter, &mut count) };
+        if index as GLint >= count {
+            self.synthesize_error(gl::INVALID_VALUE);
+            return None;
+        }
+        let mut buffer = [0u8; 256];
+        let (mut length, mut size, mut kind) = (0, 0, 0);
+        unsafe {
+            let get = if uniform { gl::GetActiveUniform } else { gl::GetActiveAttrib };
+            get(
+                name,
+                index,
+                buffer.len() as GLsizei,
+                &mut length,
+                &mut size,
+                &mut kind,
+                buffer.as_mut_ptr() as *mut GLchar,
+            );
+        }
+        let glsl_name = String::from_utf8_lossy(&buffer[..length.clamp(0, 255) as usize]).into_owned();
+        Some(ActiveInfo { size, kind, name: page_name(&glsl_name, self.dialect) })
+    }
+
+    fn program_location(&mut self, program: u32, name: &str, uniform: bool) -> Option<GLint> {
+        let program = self.object_name(program, ObjectKind::Program)?;
+        let name = CString::new(shader_name(name, self.dialect)).ok()?;
+        let location = unsafe {
+            if uniform {
+                gl::GetUniformLocation(program, name.as_ptr())
+            } else {
+                gl::GetAttribLocation(program, name.as_ptr())
+            }
+        };
+        Some(location)
+    }
+
+    /// getUniformLocation, None for a uniform the program does not have
+    pub(crate) fn uniform_location(&mut self, program: u32, name: &str) -> Option<GLint> {
+        self.program_location(program, name, true).filter(|&location| location >= 0)
+    }
+
+    pub(crate) fn attrib_location(&mut self, program: u32, name: &str) -> GLint {
+        self.program_location(program, name, false).unwrap_or(-1)
+    }
+
+    pub(crate) fn bind_attrib_location(&mut self, program: u32, index: GLuint, name: &str) {
+        let Some(program) = self.object_name(program, ObjectKind::Program) else {
+            return;
+        };
+        if let Ok(name) = CString::new(shader_name(name, self.dialect)) {
+            unsafe { gl::BindAttribLocation(program, index, name.as_ptr()) };
+        }
+    }
+
+    /// Whether a location from `program` may be set now: uniforms are set on the program in use
+    fn uniform_target(&mut self, program: u32) -> bool {
+        match self.object_name(program, ObjectKind::Program) {
+            Some(name) if name == self.state.program && name != 0 => true,
+            Some(_) => {
+                self.synthesize_error(gl::INVALID_OPERATION);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// uniform[1234]f[v]: `values` holds whole vectors of `components` each
+    pub(crate) fn uniform_floats(&mut self, program: u32, location: GLint, components: usize, values: &[GLfloat]) {
+        if !self.uniform_target(program) || values.is_empty() || values.len() % components != 0 {
+            return;
+        }
+        let count = (values.len() / components) as GLsizei;
+        unsafe {
+            match components {
+                1 => gl::Uniform1fv(location, count, values.as_ptr()),
+                2 => gl::Uniform2fv(location, count, values.as_ptr()),
+                3 => gl::Uniform3fv(location, count, values.as_ptr()),
+                _ => gl::Uniform4fv(location, count, values.as_ptr()),
+            }
+        }
+    }
+
+    /// uniform[1234]i[v], also used for samplers and booleans
+    pub(crate) fn uniform_ints(&mut self, program: u32, location: GLint, components: usize, values: &[GLint]) {
+        if !self.uniform_target(program) || values.is_empty() || values.len() % components != 0 {
+            return;
+        }
+        let count = (values.len() / components) as GLsizei;
+        unsafe {
+            match components {
+                1 => gl::Uniform1iv(location, count, values.as_ptr()),
+                2 => gl::Uniform2iv(location, count, values.as_ptr()),
+                3 => gl::Uniform3iv(location, count, values.as_ptr()),
+                _ => gl::Uniform4iv(location, count, values.as_ptr()),
+            }
+        }
+    }
+
+    /// uniformMatrix[234]fv. WebGL 1 does not transpose.
+    pub(crate) fn uniform_matrix(
+        &mut self,
+        program: u32,
+        location: GLint,
+        size: usize,
+        transpose: bool,
+        values: &[GLfloat],
+    ) {
+        if transpose {
+            self.synthesize_error(gl::INVALID_VALUE);
+            return;
+        }
+        let components = size * size;
+        if !self.uniform_target(program) || values.is_empty() || values.len() % components != 0 {
+            return;
+        }
+        let count = (values.len() / components) as GLsizei;
+        unsafe {
+            match size {
+                2 => gl::UniformMatrix2fv(location, count, gl::FALSE, values.as_ptr()),
+                3 => gl::UniformMatrix3fv(location, count, gl::FALSE, values.as_ptr()),
+                _ => gl::UniformMatrix4fv(location, count, gl::FALSE, values.as_ptr()),
+            }
+        }
+    }
+
+    // Textures
+
+    pub(crate) fn active_texture(&mut self, unit: GLenum) {
+        if unit < gl::TEXTURE0 || unit >= gl::TEXTURE0 + MAX_TEXTURE_UNITS as GLenum {
+            self.synthesize_error(gl::INVALID_ENUM);
+            return;
+        }
+        self.state.active_texture = unit;
+        unsafe { gl::ActiveTexture(unit) };
+    }
+
+    pub(crate) fn bind_texture(&mut self, target: GLenum, id: u32) {
+        let slot = match target {
+            gl::TEXTURE_2D => 0,
+            gl::TEXTURE_CUBE_MAP => 1,
+            _ => {
+                self.synthesize_error(gl::INVALID_ENUM);
+                return;
+            }
+        };
+        let Some(name) = self.object_name(id, ObjectKind::Texture) else {
+            return;
+        };
+        let unit = (self.state.active_texture - gl::TEXTURE0) as usize;
+        self.state.textures[unit][slot] = name;
+        unsafe { gl::BindTexture(target, name) };
+    }
+
+    pub(crate) fn tex_parameter(&mut self, target: GLenum, parameter: GLenum, value: GLint) {
+        unsafe { gl::TexParameteri(target, parameter, value) };
+    }
+
+    pub(crate) fn tex_parameter_value(&mut self, target: GLenum, parameter: GLenum) -> Parameter {
+        let mut value = 0;
+        unsafe { gl::GetTexParameteriv(target, parameter, &mut value) };
+        Parameter::Int(value as i64)
+    }
+
+    pub(crate) fn generate_mipmap(&mut self, target: GLenum) {
+        unsafe { gl::GenerateMipmap(target) };
+    }
+
+    /// The format GL gets for a WebGL upload format, with the swizzle that makes it read like the
+    /// WebGL one. Core profiles dropped the luminance and alpha formats.
+    fn upload_format(&self, format: GLenum) -> (GLenum, Option<[GLenum; 4]>) {
+        if self.dialect != ShaderDialect::Glsl150 {
+            return (format, None);
+        }
+        match format {
+            gl::LUMINANCE => (gl::RED, Some([gl::RED, gl::RED, gl::RED, gl::ONE])),
+            gl::LUMINANCE_ALPHA => (gl::RG, Some([gl::RED, gl::RED, gl::RED, gl::GREEN])),
+            gl::ALPHA => (gl::RED, Some([gl::ZERO, gl::ZERO, gl::ZERO, gl::RED])),
+            _ => (format, None),
+        }
+    }
+
+    fn upload_internal_format(&self, format: GLenum) -> GLenum {
+        match self.upload_format(format).0 {
+            gl::RED if self.dialect == ShaderDialect::Glsl150 => gl::R8,
+            gl::RG if self.dialect == ShaderDialect::Glsl150 => gl::RG8,
+            other => other,
+        }
+    }
+
+    /// texImage2D and texSubImage2D from an ArrayBufferView or null. `offset` is Some for
+    /// texSubImage2D.
+    pub(crate) fn tex_image_2d(
+        &mut self,
+        target: GLenum,
+        level: GLint,
+        offset: Option<(GLint, GLint)>,
+        (width, height): (GLsizei, GLsizei),
+        format: GLenum,
+        kind: GLenum,
+        pixels: Option<Vec<u8>>,
+    ) {
+        if width < 0 || height < 0 || width > self.max_texture_size || height > self.max_texture_size {
+            self.synthesize_error(gl::INVALID_VALUE);
+            return;
+        }
+        let Some(bytes_per_pixel) = bytes_per_pixel(format, kind) else {
+            self.synthesize_error(gl::INVALID_ENUM);
+            return;
+        };
+        let alignment = self.state.unpack_alignment as usize;
+        let row_bytes = (width as usize * bytes_per_pixel).div_ceil(alignment) * alignment;
+        let needed = if height == 0 { 0 } else { row_bytes * (height as usize - 1) + width as usize * bytes_per_pixel };
+        let pixels = match pixels {
+            Some(mut pixels) => {
+                if pixels.len() < needed {
+                    self.synthesize_error(gl::INVALID_OPERATION);
+                    return;
+                }
+                if self.state.unpack_premultiply_alpha && format == gl::RGBA && kind == gl::UNSIGNED_BYTE {
+                    for row in pixels.chunks_mut(row_bytes) {
+                        premultiply(&mut row[..(width as usize * 4).min(row.len())]);
+                    }
+                }
+                if self.state.unpack_flip_y {
+                    flip_rows(&mut pixels[..needed.max(row_bytes * height as usize).min(pixels.len())], row_bytes);
+                }
+                Some(pixels)
+            }
+            None if offset.is_some() => {
+                self.synthesize_error(gl::INVALID_VALUE);
+                return;
+            }
+            None => None,
+        };
+        self.upload(target, level, offset, (width, height), format, kind, pixels.as_deref());
+    }
+
+    /// texImage2D and texSubImage2D from an image element or ImageData
+    pub(crate) fn tex_image_2d_from_source(
+        &mut self,
+        target: GLenum,
+        level: GLint,
+        offset: Option<(GLint, GLint)>,
+        format: GLenum,
+        kind: GLenum,
+        source: SourcePixels,
+    ) {
+        if kind != gl::UNSIGNED_BYTE {
+            // Packed 16-bit types are only taken from ArrayBufferViews here
+            self.synthesize_error(gl::INVALID_OPERATION);
+            return;
+        }
+        let mut rgba = source.rgba.to_vec();
+        if self.state.unpack_premultiply_alpha {
+            premultiply(&mut rgba);
+        }
+        let channels: &[usize] = match format {
+            gl::RGBA => &[0, 1, 2, 3],
+            gl::RGB => &[0, 1, 2],
+            gl::LUMINANCE_ALPHA => &[0, 3],
+            gl::LUMINANCE => &[0],
+            gl::ALPHA => &[3],
+            _ => {
+                self.synthesize_error(gl::INVALID_ENUM);
+                return;
+            }
+        };
+        let mut pixels: Vec<u8> =
+            rgba.chunks_exact(4).flat_map(|pixel| channels.iter().map(move |&channel| pixel[channel])).collect();
+        let row_bytes = source.width as usize * channels.len();
+        if self.state.unpack_flip_y {
+            flip_rows(&mut pixels, row_bytes);
+        }
+        // These rows are tightly packed, whatever alignment the page set
+        unsafe { gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1) };
+        self.upload(target, level, offset, (source.width, source.height), format, kind, Some(&pixels));
+        unsafe { gl::PixelStorei(gl::UNPACK_ALIGNMENT, self.state.unpack_alignment) };
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn upload(
+        &mut self,
+        target: GLenum,
+        level: GLint,
+        offset: Option<(GLint, GLint)>,
+        (width, height): (GLsizei, GLsizei),
+        format: GLenum,
+        kind: GLenum,
+        pixels: Option<&[u8]>,
+    ) {
+        let (gl_format, swizzle) = self.upload_format(format);
+        let data = pixels.map_or(ptr::null(), |pixels| pixels.as_ptr() as *const _);
+        unsafe {
+            match offset {
+                Some((x, y)) => gl::TexSubImage2D(target, level, x, y, width, height, gl_format, kind, data),
+                None => {
+                    let internal = self.upload_internal_format(format);
+                    gl::TexImage2D(target, level, internal as GLint, width, height, 0, gl_format, kind, data);
+                    if let Some(swizzle) = swizzle {
+                        let swizzle = swizzle.map(|channel| channel as GLint);
+                        let swizzle_target = if target == gl::TEXTURE_2D { target } else { gl::TEXTURE_CUBE_MAP };
+                        gl::TexParameteriv(swizzle_target, gl::TEXTURE_SWIZZLE_RGBA, swizzle.as_ptr());
+                    }
+                }
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn copy_tex_image_2d(
+        &mut self,
+        target: GLenum,
+        level: GLint,
+        internal_format: GLenum,
+        offset: Option<(GLint, GLint)>,
+        (x, y, width, height): (GLint, GLint, GLsizei, GLsizei),
+    ) {
+        unsafe {
+            match offset {
+                Some((x_offset, y_offset)) => {
+                    gl::CopyTexSubImage2D(target, level, x_offset, y_offset, x, y, width, height)
+                }
+                None => gl::CopyTexImage2D(target, level, internal_format, x, y, width, height, 0),
+            }
+        }
+    }
+
+    // Framebuffers and renderbuffers
+
+    pub(crate) fn bind_framebuffer(&mut self, target: GLenum, id: u32) {
+        if target != gl::FRAMEBUFFER {
+            self.synthesize_error(gl::INVALID_ENUM);
+            return;
+        }
+        let Some(name) = self.object_name(id, ObjectKind::Framebuffer) else {
+            return;
+        };
+        self.state.framebuffer = name;
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer_name(name)) };
+    }
+
+    pub(crate) fn bind_renderbuffer(&mut self, target: GLenum, id: u32) {
+        if target != gl::RENDERBUFFER {
+            self.synthesize_error(gl::INVALID_ENUM);
+            return;
+        }
+        let Some(name) = self.object_name(id, ObjectKind::Renderbuffer) else {
+            return;
+        };
+        self.state.renderbuffer = name;
+        unsafe { gl::BindRenderbuffer(gl::RENDERBUFFER, name) };
+    }
+
+    /// The page's own framebuffer, which is where attachments go. Recording INVALID_OPERATION
+    /// when the drawing buffer is bound keeps pages off it.
+    fn bound_page_framebuffer(&mut self) -> bool {
+        if self.state.framebuffer == 0 {
+            self.synthesize_error(gl::INVALID_OPERATION);
+            return false;
+        }
+        true
+    }
+
+    pub(crate) fn framebuffer_texture_2d(
+        &mut self,
+        attachment: GLenum,
+        texture_target: GLenum,
+        texture: u32,
+        level: GLint,
+    ) {
+        if !self.bound_page_framebuffer() {
+            return;
+        }
+        let Some(texture) = self.object_name(texture, ObjectKind::Texture) else {
+            return;
+        };
+        unsafe { gl::FramebufferTexture2D(gl::FRAMEBUFFER, attachment, texture_target, texture, level) };
+    }
+
+    pub(crate) fn framebuffer_renderbuffer(&mut self, attachment: GLenum, renderbuffer: u32) {
+        if !self.bound_page_framebuffer() {
+            return;
+        }
+        let Some(renderbuffer) = self.object_name(renderbuffer, ObjectKind::Renderbuffer) else {
+            return;
+        };
+        unsafe { gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, attachment, gl::RENDERBUFFER, renderbuffer) };
+    }
+
+    pub(crate) fn check_framebuffer_status(&mut self) -> GLenum {
+        unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) }
+    }
+
+    pub(crate) fn renderbuffer_storage(&mut self, format: GLenum, width: GLsizei, height: GLsizei) {
+        if self.state.renderbuffer == 0 {
+            self.synthesize_error(gl::INVALID_OPERATION);
+            return;
+        }
+        let format = match format {
+            DEPTH_STENCIL => gl::DEPTH24_STENCIL8,
+            RGB565 if self.dialect != ShaderDialect::Es => gl::RGB8,
+            other => other,
+        };
+        unsafe { gl::RenderbufferStorage(gl::RENDERBUFFER, format, width, height) };
+    }
+
+    pub(crate) fn renderbuffer_parameter(&mut self, parameter: GLenum) -> Parameter {
+        let mut value = 0;
+        unsafe { gl::GetRenderbufferParameteriv(gl::RENDERBUFFER, parameter, &mut value) };
+        if parameter == gl::RENDERBUFFER_INTERNAL_FORMAT && value as GLenum == gl::DEPTH24_STENCIL8 {
+            value = DEPTH_STENCIL as GLint;
+        }
+        Parameter::Int(value as i64)
+    }
+
+    pub(crate) fn framebuffer_attachment_parameter(&mut self, attachment: GLenum, parameter: GLenum) -> Parameter {
+        if !self.bound_page_framebuffer() {
+            return Parameter::Null;
+        }
+        let mut value = 0;
+        unsafe { gl::GetFramebufferAttachmentParameteriv(gl::FRAMEBUFFER, attachment, parameter, &mut value) };
+        if parameter != gl::FRAMEBUFFER_ATTACHMENT_OBJECT_NAME {
+            return Parameter::Int(value as i64);
+        }
+        let mut kind = 0;
+        unsafe {
+            gl::GetFramebufferAttachmentParameteriv(
+                gl::FRAMEBUFFER,
+                attachment,
+                gl::FRAMEBUFFER_ATTACHMENT_OBJECT_TYPE,
+                &mut kind,
+            )
+        };
+        let kind = if kind as GLenum == gl::TEXTURE { ObjectKind::Texture } else { ObjectKind::Renderbuffer };
+        Parameter::Object(self.object_id(value as GLuint, kind))
+    }
+
+    /// readPixels, as tightly packed rows from the bottom up like GL's
+    pub(crate) fn read_pixels(
+        &mut self,
+        x: GLint,
+        y: GLint,
+        width: GLsizei,
+        height: GLsizei,
+        format: GLenum,
+        kind: GLenum,
+    ) -> Option<Vec<u8>> {
+        if width < 0 || height < 0 {
+            self.synthesize_error(gl::INVALID_VALUE);
+            return None;
+        }
+        if format != gl::RGBA || kind != gl::UNSIGNED_BYTE {
+            self.synthesize_error(gl::INVALID_OPERATION);
+            return None;
+        }
+        // Nothing outside the framebuffer can be read, so the rectangle never needs to be larger
+        // than it. This also keeps the buffer below from growing without bound.
+        let (max_width, max_height) = if self.state.framebuffer == 0 {
+            (self.width, self.height)
+        } else {
+            (self.max_texture_size, self.max_texture_size)
+        };
+        let (width, height) = (width.min(max_width), height.min(max_height));
+        let alignment = self.state.pack_alignment as usize;
+        let size = (width as usize)
+            .checked_mul(4)
+            .map(|bytes| bytes.div_ceil(alignment) * alignment)
+            .and_then(|row_bytes| row_bytes.checked_mul(height as usize));
+        let Some(size) = size else {
+            self.synthesize_error(gl::INVALID_VALUE);
+            return None;
+        };
+        let mut pixels = vec![0u8; size];
+        unsafe {
+            gl::ReadPixels(x, y, width, height, format, kind, pixels.as_mut_ptr() as *mut _);
+        }
+        Some(pixels)
+    }
+
+    // Queries
+
+    pub(crate) fn parameter(&mut self, parameter: GLenum) -> Parameter {
+        let state = &self.state;
+        let color_bits = |bits: i64| Parameter::Int(bits);
+        match parameter {
+            gl::VENDOR => Parameter::Text("Stokes".to_string()),
+            gl::RENDERER => Parameter::Text("Stokes WebGL".to_string()),
+            gl::VERSION => Parameter::Text("WebGL 1.0 (OpenGL ES 2.0 Stokes)".to_string()),
+            gl::SHADING_LANGUAGE_VERSION => {
+                Parameter::Text("WebGL GLSL ES 1.0 (OpenGL ES GLSL ES 1.0 Stokes)".to_string())
+            }
+            gl::VIEWPORT => Parameter::Ints(state.viewport.to_vec()),
+            gl::SCISSOR_BOX => Parameter::Ints(state.scissor.to_vec()),
+            gl::COLOR_CLEAR_VALUE => Parameter::Floats(state.clear_color.to_vec()),
+            gl::BLEND_COLOR => Parameter::Floats(state.blend_color.to_vec()),
+            gl::DEPTH_RANGE => Parameter::Floats(state.depth_range.to_vec()),
+            gl::COLOR_WRITEMASK => Parameter::Bools(state.color_mask.to_vec()),
+            gl::DEPTH_CLEAR_VALUE => Parameter::Float(state.clear_depth as f64),
+            gl::LINE_WIDTH => Parameter::Float(state.line_width as f64),
+            gl::POLYGON_OFFSET_FACTOR => Parameter::Float(state.polygon_offset[0] as f64),
+            gl::POLYGON_OFFSET_UNITS => Parameter::Float(state.polygon_offset[1] as f64),
+            gl::SAMPLE_COVERAGE_VALUE => Parameter::Float(state.sample_coverage.0 as f64),
+            gl::SAMPLE_COVERAGE_INVERT => Parameter::Bool(state.sample_coverage.1),
+            gl::DEPTH_WRITEMASK => Parameter::Bool(state.depth_mask),
+            capability if CAPABILITIES.contains(&capability) => Parameter::Bool(state.enabled.contains(&capability)),
+            gl::STENCIL_CLEAR_VALUE => Parameter::Int(state.clear_stencil as i64),
+            gl::ACTIVE_TEXTURE => Parameter::Int(state.active_texture as i64),
+            gl::BLEND_EQUATION_RGB => Parameter::Int(state.blend_equation[0] as i64),
+            gl::BLEND_EQUATION_ALPHA => Parameter::Int(state.blend_equation[1] as i64),
+            gl::BLEND_SRC_RGB => Parameter::Int(state.blend_func[0] as i64),
+            gl::BLEND_DST_RGB => Parameter::Int(state.blend_func[1] as i64),
+            gl::BLEND_SRC_ALPHA => Parameter::Int(state.blend_func[2] as i64),
+            gl::BLEND_DST_ALPHA => Parameter::Int(state.blend_func[3] as i64),
+            gl::DEPTH_FUNC => Parameter::Int(state.depth_func as i64),
+            gl::CULL_FACE_MODE => Parameter::Int(state.cull_face as i64),
+            gl::FRONT_FACE => Parameter::Int(state.front_face as i64),
+            gl::STENCIL_FUNC => Parameter::Int(state.stencil_func[0].0 as i64),
+            gl::STENCIL_REF => Parameter::Int(state.stencil_func[0].1 as i64),
+            gl::STENCIL_VALUE_MASK => Parameter::Int(state.stencil_func[0].2 as i64),
+            gl::STENCIL_BACK_FUNC => Parameter::Int(state.stencil_func[1].0 as i64),
+            gl::STENCIL_BACK_REF => Parameter::Int(state.stencil_func[1].1 as i64),
+            gl::STENCIL_BACK_VALUE_MASK => Parameter::Int(state.stencil_func[1].2 as i64),
+            gl::STENCIL_FAIL => Parameter::Int(state.stencil_op[0][0] as i64),
+            gl::STENCIL_PASS_DEPTH_FAIL => Parameter::Int(state.stencil_op[0][1] as i64),
+            gl::STENCIL_PASS_DEPTH_PASS => Parameter::Int(state.stencil_op[0][2] as i64),
+            gl::STENCIL_BACK_FAIL => Parameter::Int(state.stencil_op[1][0] as i64),
+            gl::STENCIL_BACK_PASS_DEPTH_FAIL => Parameter::Int(state.stencil_op[1][1] as i64),
+            gl::STENCIL_BACK_PASS_DEPTH_PASS => Parameter::Int(state.stencil_op[1][2] as i64),
+            gl::STENCIL_WRITEMASK => Parameter::Int(state.stencil_mask[0] as i64),
+            gl::STENCIL_BACK_WRITEMASK => Parameter::Int(state.stencil_mask[1] as i64),
+            gl::PACK_ALIGNMENT => Parameter::Int(state.pack_alignment as i64),
+            gl::UNPACK_ALIGNMENT => Parameter::Int(state.unpack_alignment as i64),
+            gl::GENERATE_MIPMAP_HINT => Parameter::Int(state.generate_mipmap_hint as i64),
+            UNPACK_FLIP_Y_WEBGL => Parameter::Bool(state.unpack_flip_y),
+            UNPACK_PREMULTIPLY_ALPHA_WEBGL => Parameter::Bool(state.unpack_premultiply_alpha),
+            UNPACK_COLORSPACE_CONVERSION_WEBGL => Parameter::Int(BROWSER_DEFAULT_WEBGL as i64),
+            gl::RED_BITS | gl::GREEN_BITS | gl::BLUE_BITS => color_bits(8),
+            gl::ALPHA_BITS => color_bits(if self.attributes.alpha { 8 } else { 0 }),
+            gl::DEPTH_BITS => color_bits(if self.attributes.depth { 24 } else { 0 }),
+            gl::STENCIL_BITS => color_bits(if self.attributes.stencil { 8 } else { 0 }),
+            gl::SUBPIXEL_BITS => color_bits(4),
+            gl::SAMPLES | gl::SAMPLE_BUFFERS => Parameter::Int(0),
+            gl::MAX_TEXTURE_SIZE => Parameter::Int(self.max_texture_size as i64),
+            gl::MAX_CUBE_MAP_TEXTURE_SIZE | gl::MAX_RENDERBUFFER_SIZE => {
+                Parameter::Int(unsafe { get_integer(parameter) } as i64)
+            }
+            gl::MAX_VERTEX_ATTRIBS => Parameter::Int(self.attribs.len() as i64),
+            gl::MAX_TEXTURE_IMAGE_UNITS | gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS | gl::MAX_VERTEX_TEXTURE_IMAGE_UNITS => {
+                let units = unsafe { get_integer(parameter) } as i64;
+                Parameter::Int(units.min(MAX_TEXTURE_UNITS as i64))
+            }
+            MAX_VERTEX_UNIFORM_VECTORS => {
+                Parameter::Int(self.vectors(parameter, gl::MAX_VERTEX_UNIFORM_COMPONENTS, 128))
+            }
+            MAX_FRAGMENT_UNIFORM_VECTORS => {
+                Parameter::Int(self.vectors(parameter, gl::MAX_FRAGMENT_UNIFORM_COMPONENTS, 16))
+            }
+            MAX_VARYING_VECTORS => Parameter::Int(self.vectors(parameter, gl::MAX_VARYING_COMPONENTS, 8)),
+            gl::MAX_VIEWPORT_DIMS => {
+                let mut dims = [0; 2];
+                unsafe { gl::GetIntegerv(parameter, dims.as_mut_ptr()) };
+                Parameter::Ints(dims.to_vec())
+            }
+            gl::ALIASED_POINT_SIZE_RANGE | gl::ALIASED_LINE_WIDTH_RANGE => {
+                let mut range = [1.0; 2];
+                unsafe { gl::GetFloatv(parameter, range.as_mut_ptr()) };
+                Parameter::Floats(range.to_vec())
+            }
+            gl::COMPRESSED_TEXTURE_FORMATS => Parameter::Ints(Vec::new()),
+            gl::IMPLEMENTATION_COLOR_READ_FORMAT => Parameter::Int(gl::RGBA as i64),
+            gl::IMPLEMENTATION_COLOR_READ_TYPE => Parameter::Int(gl::UNSIGNED_BYTE as i64),
+            gl::CURRENT_PROGRAM => Parameter::Object(self.object_id(state.program, ObjectKind::Program)),
+            gl::ARRAY_BUFFER_BINDING => Parameter::Object(self.object_id(state.array_buffer, ObjectKind::Buffer)),
+            gl::ELEMENT_ARRAY_BUFFER_BINDING => {
+                Parameter::Object(self.object_id(state.element_array_buffer, ObjectKind::Buffer))
+            }
+            gl::FRAMEBUFFER_BINDING => Parameter::Object(self.object_id(state.framebuffer, ObjectKind::Framebuffer)),
+            gl::RENDERBUFFER_BINDING => Parameter::Object(self.object_id(state.renderbuffer, ObjectKind::Renderbuffer)),
+            gl::TEXTURE_BINDING_2D | gl::TEXTURE_BINDING_CUBE_MAP => {
+                let unit = (state.active_texture - gl::TEXTURE0) as usize;
+                let slot = if parameter == gl::TEXTURE_BINDING_2D { 0 } else { 1 };
+                Parameter::Object(self.object_id(state.textures[unit][slot], ObjectKind::Texture))
+            }
+            _ => {
+                self.synthesize_error(gl::INVALID_ENUM);
+                Parameter::Null
+            }
+        }
+    }
+
+    /// A MAX_*_VECTORS limit. GLES has it directly; desktop GL counts components instead.
+    fn vectors(&self, parameter: GLenum, components: GLenum, minimum: i64) -> i64 {
+        let value = unsafe {
+            if self.dialect == ShaderDialect::Es {
+                get_integer(parameter) as i64
+            } else {
+                get_integer(components) as i64 / 4
+            }
+        };
+        unsafe { drain_gl_errors() };
+        value.max(minimum)
+    }
+}
+
+/// Work out which shading language the current GL context takes, or None when it lacks what
+/// WebGL needs (vertex array objects and framebuffer objects)
+unsafe fn detect_dialect() -> Option<ShaderDialect> {
+    if !gl::GenVertexArrays::is_loaded() || !gl::GenFramebuffers::is_loaded() {
+        return None;
+    }
+    let version = unsafe {
+        let version = gl::GetString(gl::VERSION);
+        if version.is_null() {
+            return None;
+        }
+        CStr::from_ptr(version as *const _).to_string_lossy().into_owned()
+    };
+    if version.starts_with("OpenGL ES") {
+        return Some(ShaderDialect::Es);
+    }
+    let mut numbers = version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse::<u32>().unwrap_or(0));
+    let major = numbers.next()?;
+    let minor = numbers.next().unwrap_or(0);
+    if (major, minor) >= (3, 2) {
+        Some(ShaderDialect::Glsl150)
+    } else if (major, minor) >= (2, 1) {
+        Some(ShaderDialect::Glsl120)
+    } else {
+        None
+    }
+}
+
+fn clamp_size(width: u32, height: u32) -> (i32, i32) {
+    let clamp = |side: u32| (side as i64).clamp(1, MAX_DRAWING_BUFFER_SIZE as i64) as i32;
+    (clamp(width), clamp(height))
+}
+
+fn gl_bool(value: bool) -> GLboolean {
+    if value { gl::TRUE } else { gl::FALSE }
+}
+
+unsafe fn get_integer(parameter: GLenum) -> GLint {
+    let mut value = 0;
+    unsafe { gl::GetIntegerv(parameter, &mut value) };
+    value
+}
+
+fn get_shader_integer(shader: GLuint, parameter: GLenum) -> GLint {
+    let mut value = 0;
+    unsafe { gl::GetShaderiv(shader, parameter, &mut value) };
+    value
+}
+
+/// Throw away GL errors left behind by Skia, so pages only see their own
+unsafe fn drain_gl_errors() {
+    for _ in 0..16 {
+        if unsafe { gl::GetError() } == gl::NO_ERROR {
+            break;
+        }
+    }
+}
+
+unsafe fn delete_object(kind: ObjectKind, name: GLuint) {
+    unsafe {
+        match kind {
+            ObjectKind::Buffer => gl::DeleteBuffers(1, &name),
+            ObjectKind::Texture => gl::DeleteTextures(1, &name),
+            ObjectKind::Framebuffer => gl::DeleteFramebuffers(1, &name),
+            ObjectKind::Renderbuffer => gl::DeleteRenderbuffers(1, &name),
+            ObjectKind::Shader => gl::DeleteShader(name),
+            ObjectKind::Program => gl::DeleteProgram(name),
+        }
+    }
+}
+
+fn type_size(kind: GLenum) -> Option<usize> {
+    match kind {
+        gl::BYTE | gl::UNSIGNED_BYTE => Some(1),
+        gl::SHORT | gl::UNSIGNED_SHORT => Some(2),
+        gl::FLOAT => Some(4),
+        _ => None,
+    }
+}
+
+fn bytes_per_pixel(format: GLenum, kind: GLenum) -> Option<usize> {
+    let channels = match format {
+        gl::RGBA => 4,
+        gl::RGB => 3,
+        gl::LUMINANCE_ALPHA => 2,
+        gl::LUMINANCE | gl::ALPHA => 1,
+        _ => return None,
+    };
+    match kind {
+        gl::UNSIGNED_BYTE => Some(channels),
+        gl::FLOAT => Some(channels * 4),
+        gl::UNSIGNED_SHORT_4_4_4_4 | gl::UNSIGNED_SHORT_5_5_5_1 | gl::UNSIGNED_SHORT_5_6_5 => Some(2),
+        _ => None,
+    }
+}
+
+fn premultiply(rgba: &mut [u8]) {
+    for pixel in rgba.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u16;
+        for channel in &mut pixel[..3] {
+            *channel = ((*channel as u16 * alpha + 127) / 255) as u8;
+        }
+    }
+}
+
+fn flip_rows(pixels: &mut [u8], row_bytes: usize) {
+    if row_bytes == 0 {
+        return;
+    }
+    let rows = pixels.len() / row_bytes;
+    for row in 0..rows / 2 {
+        let (top, bottom) = pixels.split_at_mut((rows - 1 - row) * row_bytes);
+        top[row * row_bytes..(row + 1) * row_bytes].swap_with_slice(&mut bottom[..row_bytes]);
+    }
+}
+
+/// Turn a WebGL (GLSL ES 1.00) shader into one the tab's GL compiles
+pub(crate) fn translate_shader(source: &str, stage: ShaderStage, dialect: ShaderDialect) -> String {
+    if dialect == ShaderDialect::Es {
+        return source.to_string();
+    }
+    // The version is set below, and extensions WebGL shaders ask for are core on desktop
+    let body = source
+        .lines()
+        .filter(|line| {
+            let line = line.trim_start();
+            !line.starts_with("#version") && !line.starts_with("#extension")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if dialect == ShaderDialect::Glsl120 {
+        // GLSL 1.20 has no precision qualifiers. Statements setting a default precision are
+        // dropped, which assumes they sit on lines of their own as they nearly always do.
+        let body = body
+            .lines()
+            .map(|line| if line.trim_start().starts_with("precision ") { "" } else { line })
+            .collect::<Vec<_>>()
+            .join("\n");
+        return format!("#version 120\n#define lowp\n#define mediump\n#define highp\n{body}\n");
+    }
+
+    let mut header = String::from("#version 150\n");
+    let uses_frag_data = source.contains("gl_FragData");
+    if stage == ShaderStage::Fragment {
+        if uses_frag_data {
+            header.push_str(&format!("out vec4 {FRAG_DATA}[1];\n"));
+        } else {
+            header.push_str(&format!("out vec4 {FRAG_COLOR};\n"));
+        }
+    }
+    let body = map_identifiers(&body, |word| {
+        let mapped = match word {
+            "attribute" if stage == ShaderStage::Vertex => "in",
+            "varying" if stage == ShaderStage::Vertex => "out",
+            "varying" => "in",
+            "texture2D" | "textureCube" => "texture",
+            "texture2DProj" => "textureProj",
+            "texture2DLod" | "textureCubeLod" | "texture2DLodEXT" | "textureCubeLodEXT" => "textureLod",
+            "texture2DProjLod" | "texture2DProjLodEXT" => "textureProjLod",
+            "texture2DGradEXT" | "textureCubeGradEXT" => "textureGrad",
+            "gl_FragColor" => FRAG_COLOR,
+            "gl_FragData" => FRAG_DATA,
+            _ if RESERVED_IN_GLSL_150.contains(&word) => return Some(format!("{RENAMED_PREFIX}{word}")),
+            _ => return None,
+        };
+        Some(mapped.to_string())
+    });
+    header + &body + "\n"
+}
+
+/// The name GL knows a page's uniform or attribute by, after `translate_shader`
+fn shader_name(name: &str, dialect: ShaderDialect) -> String {
+    if dialect != ShaderDialect::Glsl150 {
+        return name.to_string();
+    }
+    map_identifiers(name, |word| RESERVED_IN_GLSL_150.contains(&word).then(|| format!("{RENAMED_PREFIX}{word}")))
+}
+
+/// The name a page gave a uniform or attribute GL reports as `name`
+fn page_name(name: &str, dialect: ShaderDialect) -> String {
+    if dialect != ShaderDialect::Glsl150 {
+        return name.to_string();
+    }
+    map_identifiers(name, |word| {
+        word.strip_prefix(RENAMED_PREFIX).filter(|original| RESERVED_IN_GLSL_150.contains(original)).map(str::to_string)
+    })
+}
+
+/// Copy `source`, passing every identifier outside comments through `map`
+fn map_identifiers(source: &str, mut map: impl FnMut(&str) -> Option<String>) -> String {
+    let bytes = source.as_bytes();
+    let mut out = String::with_capacity(source.len() + 64);
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        if source[i..].starts_with("//") {
+            i = source[i..].find('\n').map_or(source.len(), |end| i + end);
+            out.push_str(&source[start..i]);
+        } else if source[i..].starts_with("/*") {
+            i = source[i + 2..].find("*/").map_or(source.len(), |end| i + 2 + end + 2);
+            out.push_str(&source[start..i]);
+        } else if bytes[i].is_ascii_alphabetic() || bytes[i] == b'_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            let word = &source[start..i];
+            match map(word) {
+                Some(mapped) => out.push_str(&mapped),
+                None => out.push_str(word),
+            }
+        } else if bytes[i].is_ascii_digit() {
+            // Numbers, so suffixes and exponents are not taken for identifiers
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.') {
+                i += 1;
+            }
+            out.push_str(&source[start..i]);
+        } else {
+            let c = source[i..].chars().next().unwrap();
+            out.push(c);
+            i += c.len_utf8();
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ShaderDialect, ShaderStage, flip_rows, page_name, shader_name, translate_shader};
+
+    const VERTEX: &str = "#version 100\n\
+        attribute vec2 position;\n\
+        varying vec2 uv;\n\
+        void main() {\n\
+            uv = position * 0.5e0 + 0.5;\n\
+            gl_Position = vec4(position, 0.0, 1.0);\n\
+        }\n";
+
+    const FRAGMENT: &str = "#extension GL_OES_standard_derivatives : enable\n\
+        precision mediump float;\n\
+        uniform sampler2D texture;\n\
+        varying vec2 uv;\n\
+        // varying and texture2D in a comment stay as they are\n\
+        void main() {\n\
+            gl_FragColor = texture2D(texture, uv);\n\
+        }\n";
+
+    #[test]
+    fn gles_takes_shaders_as_they_are() {
+        assert_eq!(translate_shader(FRAGMENT, ShaderStage::Fragment, ShaderDialect::Es), FRAGMENT);
+    }
+
+    #[test]
+    fn vertex_shaders_use_in_and_out_on_glsl_150() {
+        let translated = translate_shader(VERTEX, ShaderStage::Vertex, ShaderDialect::Glsl150);
+        assert!(translated.starts_with("#version 150\n"));
+        assert!(!translated.contains("#version 100"));
+        assert!(translated.contains("in vec2 position;"));
+        assert!(translated.contains("out vec2 uv;"));
+        assert!(translated.contains("position * 0.5e0 + 0.5"));
+    }
+
+    #[test]
+    fn fragment_shaders_get_an_output_and_lose_reserved_names_on_glsl_150() {
+        let translated = translate_shader(FRAGMENT, ShaderStage::Fragment, ShaderDialect::Glsl150);
+        assert!(translated.starts_with("#version 150\nout vec4 _stokes_FragColor;\n"));
+        assert!(!translated.contains("#extension"));
+        assert!(translated.contains("uniform sampler2D _stokes_texture;"));
+        assert!(translated.contains("in vec2 uv;"));
+        assert!(translated.contains("_stokes_FragColor = texture(_stokes_texture, uv);"));
+        assert!(translated.contains("// varying and texture2D in a comment stay as they are"));
+    }
+
+    #[test]
+    fn glsl_120_drops_precision() {
+        let translated = translate_shader(FRAGMENT, ShaderStage::Fragment, ShaderDialect::Glsl120);
+        assert!(translated.starts_with("#version 120\n"));
+        assert!(!translated.contains("precision mediump float;"));
+        assert!(translated.contains("gl_FragColor = texture2D(texture, uv);"));
+    }
+
+    #[test]
+    fn renamed_uniforms_keep_their_page_names() {
+        assert_eq!(shader_name("lights[0].texture", ShaderDialect::Glsl150), "lights[0]._stokes_texture");
+        assert_eq!(page_name("lights[0]._stokes_texture", ShaderDialect::Glsl150), "lights[0].texture");
+        assert_eq!(page_name("_stokes_other", ShaderDialect::Glsl150), "_stokes_other");
+        assert_eq!(shader_name("texture", ShaderDialect::Es), "texture");
+    }
+
+    #[test]
+    fn rows_are_flipped_in_place() {
+        let mut pixels = vec![1, 1, 2, 2, 3, 3];
+        flip_rows(&mut pixels, 2);
+        assert_eq!(pixels, vec![3, 3, 2, 2, 1, 1]);
+    }
+}
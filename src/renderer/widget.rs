@@ -0,0 +1,275 @@
+// Form control theme. Buttons, text fields and selects keep the box their CSS paints (the UA
+// stylesheet gives them platform-like metrics and colors) and get hover, pressed and disabled
+// shading, a dropdown arrow and a focus ring painted over it. Checkboxes, radios and progress
+// bars are drawn entirely here. `appearance: none` opts an element out of all of it.
+use anyrender::PaintScene;
+use kurbo::{Affine, BezPath, Cap, Circle, Join, Point, Rect, RoundedRect, Stroke, Vec2};
+use markup5ever::local_name;
+use blitz_traits::shell::{ColorScheme, ShellProvider};
+use peniko::{Color, Fill};
+use style::dom::TElement;
+use style::values::specified::box_::Appearance;
+use stylo_dom::ElementState;
+use crate::dom::ElementData;
+use crate::renderer::Element;
+use crate::renderer::painter::ToColorColor;
+use crate::renderer::force_dark::{self, ColorRole};
+
+/// Accent used for checked controls, progress bars and focus rings
+const ACCENT_COLOR: Color = Color::from_rgb8(0, 117, 255);
+/// Width of the focus ring and its gap from the border box, in CSS px
+const FOCUS_RING_WIDTH: f64 = 2.0;
+const FOCUS_RING_OFFSET: f64 = 1.0;
+
+/// The kinds of form control that are painted as widgets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Widget {
+    Button,
+    TextField,
+    Select,
+    Checkbox,
+    Radio,
+}
+
+impl Widget {
+    fn for_element(element: &ElementData) -> Option<Self> {
+        match element.name.local {
+            local_name!("button") => Some(Widget::Button),
+            local_name!("select") => Some(Widget::Select),
+            local_name!("textarea") => Some(Widget::TextField),
+            local_name!("input") => match element.attr(local_name!("type")) {
+                Some("submit" | "reset" | "button") => Some(Widget::Button),
+                Some("checkbox") => Some(Widget::Checkbox),
+                Some("radio") => Some(Widget::Radio),
+                Some("hidden" | "file" | "image" | "range" | "color") => None,
+                _ => Some(Widget::TextField),
+            },
+            _ => None,
+        }
+    }
+}
+
+impl Element<'_> {
+    /// The widget this element is painted as, unless it isn't a form control or opted out with
+    /// `appearance: none`
+    fn widget(&self) -> Option<Widget> {
+        if self.style.get_box().clone_appearance() == Appearance::None {
+            return None;
+        }
+        Widget::for_element(self.element)
+    }
+
+    fn is_disabled(&self) -> bool {
+        self.node.element_state.contains(ElementState::DISABLED)
+    }
+
+    fn has_dark_fields(&self) -> bool {
+        self.context.dom.used_color_scheme == ColorScheme::Dark || force_dark::is_active(self.context.dom)
+    }
+
+    /// Shade a button, text field or select for its hover, pressed and disabled states, and
+    /// give a select its dropdown arrow. Painted over the CSS background and border.
+    pub(super) fn draw_widget(&self, scene: &mut impl PaintScene) {
+        let Some(widget) = self.widget() else {
+            return;
+        };
+        if matches!(widget, Widget::Checkbox | Widget::Radio) {
+            return;
+        }
+        let path = self.frame.border_box_path();
+        // Darken light controls and lighten dark ones
+        let shade = |alpha: f32| {
+            if self.has_dark_fields() {
+                Color::WHITE.with_alpha(alpha)
+            } else {
+                Color::BLACK.with_alpha(alpha)
+            }
+        };
+
+        if self.is_disabled() {
+            let veil = if self.has_dark_fields() { Color::BLACK } else { Color::WHITE };
+            scene.fill(Fill::NonZero, self.transform, veil.with_alpha(0.4), None, &path);
+        } else if widget != Widget::TextField {
+            if self.node.is_active() {
+                scene.fill(Fill::NonZero, self.transform, shade(0.12), None, &path);
+            } else if self.node.is_hovered() {
+                scene.fill(Fill::NonZero, self.transform, shade(0.05), None, &path);
+            }
+        }
+
+        if widget == Widget::Select {
+            self.draw_select_arrow(scene);
+        }
+    }
+
+    /// A chevron in the select's right padding, where the UA stylesheet leaves room for it
+    fn draw_select_arrow(&self, scene: &mut impl PaintScene) {
+        let content_box = self.frame.content_box;
+        let size = 4.0 * self.scale_factor;
+        let center = Point::new(
+            (content_box.x1 + self.frame.padding_box.x1) / 2.0,
+            content_box.center().y,
+        );
+        let mut path = BezPath::new();
+        path.move_to((center.x - size, center.y - size / 2.0));
+        path.line_to((center.x, center.y + size / 2.0));
+        path.line_to((center.x + size, center.y - size / 2.0));
+        let stroke = Stroke::new(1.5 * self.scale_factor).with_caps(Cap::Round).with_join(Join::Round);
+        let color = force_dark::adjust(self.context.dom, self.style.clone_color().as_color_color(), ColorRole::Foreground);
+        scene.stroke(&stroke, self.transform, color, None, &path);
+    }
+
+    /// A ring just outside a focused control's border box. Painted before the box so it is never
+    /// clipped by the control's own overflow.
+    pub(super) fn draw_focus_ring(&self, scene: &mut impl PaintScene) {
+        if self.widget().is_none() || !self.node.is_focused() {
+            return;
+        }
+        let offset = (FOCUS_RING_OFFSET + FOCUS_RING_WIDTH / 2.0) * self.scale_factor;
+        let radius = self.frame.border_radii.top_left.x.max(0.0) + offset;
+        let ring = self.frame.border_box.inflate(offset, offset).to_rounded_rect(radius);
+        let color = force_dark::adjust(self.context.dom, ACCENT_COLOR, ColorRole::Foreground);
+        scene.stroke(&Stroke::new(FOCUS_RING_WIDTH * self.scale_factor), self.transform, color, None, &ring);
+    }
+
+    pub(super) fn draw_input(&self, scene: &mut impl PaintScene) {
+        let Some(widget @ (Widget::Checkbox | Widget::Radio)) = self.widget() else {
+            return;
+        };
+        let Some(checked) = self.element.checkbox_input_checked() else {
+            return;
+        };
+
+        let disabled = self.is_disabled();
+
+        // TODO this should be coming from css accent-color, but I couldn't find how to retrieve it
+        let accent_color = if disabled {
+            Color::from_rgba8(209, 209, 209, 255)
+        } else if self.node.is_active() || self.node.is_hovered() && !checked {
+            // Pressed and hovered boxes are outlined with the accent instead of the text color
+            force_dark::adjust(self.context.dom, ACCENT_COLOR, ColorRole::Foreground)
+        } else {
+            force_dark::adjust(self.context.dom, self.style.clone_color().as_color_color(), ColorRole::Foreground)
+        };
+
+        // Field background, also used for the tick so it contrasts with the accent fill
+        let field_color = if self.has_dark_fields() {
+            Color::from_rgb8(59, 59, 59)
+        } else {
+            Color::WHITE
+        };
+
+        let width = self.frame.border_box.width();
+        let height = self.frame.border_box.height();
+        let min_dimension = width.min(height);
+        let scale = (min_dimension - 4.0).max(0.0) / 16.0;
+
+        let frame = self.frame.border_box.to_rounded_rect(scale * 2.0);
+
+        if widget == Widget::Checkbox {
+            draw_checkbox(scene, checked, frame, self.transform, accent_color, field_color, scale);
+        } else {
+            let center = frame.center();
+            draw_radio_button(scene, checked, center, self.transform, accent_color, field_color, scale);
+        }
+    }
+
+    /// Fill a `<progress>` bar's content box up to its position. An indeterminate bar has a
+    /// segment sliding across it instead, which keeps asking for frames while it's painted.
+    pub(super) fn draw_progress(&self, scene: &mut impl PaintScene) {
+        if self.node.local_name() != "progress" || self.style.get_box().clone_appearance() == Appearance::None {
+            return;
+        }
+        let accent_color = force_dark::adjust(self.context.dom, ACCENT_COLOR, ColorRole::Foreground);
+        let content_box = self.frame.content_box;
+        let radius = content_box.height() / 2.0;
+
+        let bar = match self.element.progress_position() {
+            Some(position) => Rect::new(
+                content_box.x0,
+                content_box.y0,
+                content_box.x0 + content_box.width() * position,
+                content_box.y1,
+            ),
+            None => {
+                const PERIOD_MS: u128 = 1500;
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|time| time.as_millis())
+                    .unwrap_or_default();
+                let phase = (now % PERIOD_MS) as f64 / PERIOD_MS as f64;
+                let segment = content_box.width() * 0.3;
+                let start = content_box.x0 - segment + (content_box.width() + segment) * phase;
+                self.context.dom.shell_provider.request_redraw();
+                Rect::new(
+                    start.max(content_box.x0),
+                    content_box.y0,
+                    (start + segment).min(content_box.x1),
+                    content_box.y1,
+                )
+            }
+        };
+        if bar.width() > 0.0 {
+            scene.fill(Fill::NonZero, self.transform, accent_color, None, &bar.to_rounded_rect(radius));
+        }
+    }
+}
+
+fn draw_checkbox(
+    scene: &mut impl PaintScene,
+    checked: bool,
+    frame: RoundedRect,
+    transform: Affine,
+    accent_color: Color,
+    field_color: Color,
+    scale: f64,
+) {
+    if checked {
+        scene.fill(Fill::NonZero, transform, accent_color, None, &frame);
+        //Tick code derived from masonry
+        let mut path = BezPath::new();
+        path.move_to((2.0, 9.0));
+        path.line_to((6.0, 13.0));
+        path.line_to((14.0, 2.0));
+
+        path.apply_affine(Affine::translate(Vec2 { x: 2.0, y: 1.0 }).then_scale(scale));
+
+        let style = Stroke {
+            width: 2.0 * scale,
+            join: Join::Round,
+            miter_limit: 10.0,
+            start_cap: Cap::Round,
+            end_cap: Cap::Round,
+            dash_pattern: Default::default(),
+            dash_offset: 0.0,
+        };
+
+        scene.stroke(&style, transform, field_color, None, &path);
+    } else {
+        scene.fill(Fill::NonZero, transform, field_color, None, &frame);
+        scene.stroke(&Stroke::default(), transform, accent_color, None, &frame);
+    }
+}
+
+fn draw_radio_button(
+    scene: &mut impl PaintScene,
+    checked: bool,
+    center: Point,
+    transform: Affine,
+    accent_color: Color,
+    field_color: Color,
+    scale: f64,
+) {
+    let outer_ring = Circle::new(center, 8.0 * scale);
+    let gap = Circle::new(center, 6.0 * scale);
+    let inner_circle = Circle::new(center, 4.0 * scale);
+    if checked {
+        scene.fill(Fill::NonZero, transform, accent_color, None, &outer_ring);
+        scene.fill(Fill::NonZero, transform, field_color, None, &gap);
+        scene.fill(Fill::NonZero, transform, accent_color, None, &inner_circle);
+    } else {
+        const GRAY: Color = color::palette::css::GRAY;
+        scene.fill(Fill::NonZero, transform, GRAY, None, &outer_ring);
+        scene.fill(Fill::NonZero, transform, field_color, None, &gap);
+    }
+}
@@ -0,0 +1,278 @@
+// "Save Page As…" support - writes the current document to disk, optionally with its resources
+use crate::dom::{Dom, ElementData};
+use markup5ever::local_name;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use url::Url;
+
+/// Save the document to `path`. In complete mode, images, stylesheets, scripts and icons
+/// are downloaded into a `<name>_files` folder next to the page and the markup is rewritten
+/// to point at the local copies. Otherwise only the HTML is written, with URLs made absolute
+/// so the saved page still loads its resources from the web.
+pub(crate) fn save_page(
+    dom: &Dom,
+    path: &Path,
+    complete: bool,
+    fetch: impl Fn(&str) -> io::Result<Vec<u8>>,
+) -> io::Result<()> {
+    let html = if complete {
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "page".to_string());
+        let dir_name = format!("{stem}_files");
+        let dir = path.with_file_name(&dir_name);
+        fs::create_dir_all(&dir)?;
+
+        let mut saver = ResourceSaver::new(dir, &fetch);
+        dom.serialize_html_with(|element, attr, value| {
+            let absolute = dom.url.resolve_relative(value.trim())?;
+            match attribute_kind(element, attr) {
+                AttributeKind::Resource { stylesheet } => saver
+                    .save(&absolute, stylesheet)
+                    .map(|file_name| format!("{dir_name}/{file_name}"))
+                    .or_else(|| Some(absolute.to_string())),
+                AttributeKind::SrcSet => Some(rewrite_srcset(value, |candidate| {
+                    let absolute = dom.url.resolve_relative(candidate)?;
+                    saver
+                        .save(&absolute, false)
+                        .map(|file_name| format!("{dir_name}/{file_name}"))
+                        .or_else(|| Some(absolute.to_string()))
+                })),
+                // Local copies are referenced relative to the saved file
+                AttributeKind::Base => Some("./".to_string()),
+                AttributeKind::Link => Some(absolute.to_string()),
+                AttributeKind::Other => None,
+            }
+        })
+    } else {
+        dom.serialize_html_with(|element, attr, value| match attribute_kind(element, attr) {
+            AttributeKind::Other => None,
+            AttributeKind::SrcSet => Some(rewrite_srcset(value, |candidate| {
+                dom.url.resolve_relative(candidate).map(|url| url.to_string())
+            })),
+            _ => dom.url.resolve_relative(value.trim()).map(|url| url.to_string()),
+        })
+    };
+
+    fs::write(path, html)
+}
+
+enum AttributeKind {
+    /// A subresource that complete mode downloads
+    Resource { stylesheet: bool },
+    /// A `srcset` candidate list
+    SrcSet,
+    /// `<base href>`
+    Base,
+    /// A URL that is kept pointing at the web
+    Link,
+    Other,
+}
+
+fn attribute_kind(element: &ElementData, attr: &str) -> AttributeKind {
+    let tag: &str = &element.name.local;
+    match (tag, attr) {
+        ("img" | "source" | "audio" | "video" | "script" | "embed" | "track" | "input", "src")
+        | ("video", "poster")
+        | ("body" | "table" | "td", "background") => AttributeKind::Resource { stylesheet: false },
+        ("link", "href") => {
+            let rel = element.attr(local_name!("rel")).unwrap_or_default().to_ascii_lowercase();
+            if rel.split_ascii_whitespace().any(|token| token == "stylesheet") {
+                AttributeKind::Resource { stylesheet: true }
+            } else if rel.split_ascii_whitespace().any(|token| token == "icon" || token == "apple-touch-icon") {
+                AttributeKind::Resource { stylesheet: false }
+            } else {
+                AttributeKind::Link
+            }
+        }
+        ("img" | "source", "srcset") => AttributeKind::SrcSet,
+        ("base", "href") => AttributeKind::Base,
+        ("a" | "area", "href") | ("form", "action") | ("iframe" | "frame", "src") => AttributeKind::Link,
+        _ => AttributeKind::Other,
+    }
+}
+
+/// Downloads resources into the `_files` folder, remembering what has already been saved
+struct ResourceSaver<'a, F> {
+    dir: PathBuf,
+    fetch: &'a F,
+    saved: HashMap<String, Option<String>>,
+    used_names: HashSet<String>,
+}
+
+impl<'a, F: Fn(&str) -> io::Result<Vec<u8>>> ResourceSaver<'a, F> {
+    fn new(dir: PathBuf, fetch: &'a F) -> Self {
+        Self {
+            dir,
+            fetch,
+            saved: HashMap::new(),
+            used_names: HashSet::new(),
+        }
+    }
+
+    /// Download `url` and return the local file name, or None if it could not be saved
+    fn save(&mut self, url: &Url, stylesheet: bool) -> Option<String> {
+        if !matches!(url.scheme(), "http" | "https" | "file") {
+            return None;
+        }
+        let key = url.as_str().split('#').next().unwrap_or_default().to_string();
+        if let Some(saved) = self.saved.get(&key) {
+            return saved.clone();
+        }
+
+        let file_name = match (self.fetch)(&key) {
+            Ok(bytes) => {
+                let bytes = if stylesheet {
+                    let css = String::from_utf8_lossy(&bytes).into_owned();
+                    rewrite_css_urls(&css, |reference| {
+                        let absolute = url.join(reference).ok()?;
+                        // Stylesheets live in the same folder as their resources
+                        self.save(&absolute, false)
+                    })
+                    .into_bytes()
+                } else {
+                    bytes
+                };
+                let file_name = self.unique_name(url, stylesheet);
+                match fs::write(self.dir.join(&file_name), bytes) {
+                    Ok(()) => Some(file_name),
+                    Err(e) => {
+                        warn!("[save page] failed to write {}: {}", file_name, e);
+                        None
+                    }
+                }
+            }
+            Err(e) => {
+                warn!("[save page] failed to fetch {}: {}", key, e);
+                None
+            }
+        };
+        self.saved.insert(key, file_name.clone());
+        file_name
+    }
+
+    fn unique_name(&mut self, url: &Url, stylesheet: bool) -> String {
+        let mut name = local_file_name(url);
+        if stylesheet && !name.to_ascii_lowercase().ends_with(".css") {
+            name.push_str(".css");
+        }
+        let (stem, extension) = match name.rfind('.') {
+            Some(dot) if dot > 0 => (name[..dot].to_string(), name[dot..].to_string()),
+            _ => (name.clone(), String::new()),
+        };
+        let mut candidate = name;
+        let mut counter = 1;
+        while !self.used_names.insert(candidate.to_ascii_lowercase()) {
+            candidate = format!("{stem}-{counter}{extension}");
+            counter += 1;
+        }
+        candidate
+    }
+}
+
+/// A filesystem-safe file name derived from the last path segment of `url`
+fn local_file_name(url: &Url) -> String {
+    let segment = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .unwrap_or_default();
+    let decoded = percent_encoding::percent_decode_str(segment).decode_utf8_lossy();
+    let name: String = decoded
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .take(100)
+        .collect();
+    let name = name.trim_start_matches('.');
+    if name.is_empty() {
+        "resource".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Rewrite every `url(...)` reference in a stylesheet. References the callback
+/// returns None for are left untouched.
+fn rewrite_css_urls(css: &str, mut rewrite: impl FnMut(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("url(") {
+        let (before, after) = rest.split_at(start + 4);
+        output.push_str(before);
+        let Some(end) = after.find(')') else {
+            rest = after;
+            break;
+        };
+        let raw = &after[..end];
+        let reference = raw.trim().trim_matches(|c| c == '"' || c == '\'');
+        let local = if reference.is_empty() || reference.starts_with("data:") {
+            None
+        } else {
+            rewrite(reference)
+        };
+        match local {
+            Some(local) => {
+                output.push('"');
+                output.push_str(&local);
+                output.push('"');
+            }
+            None => output.push_str(raw),
+        }
+        rest = &after[end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Rewrite each URL in a `srcset` list, keeping the width/density descriptors
+fn rewrite_srcset(srcset: &str, mut rewrite: impl FnMut(&str) -> Option<String>) -> String {
+    srcset
+        .split(',')
+        .map(str::trim)
+        .filter(|candidate| !candidate.is_empty())
+        .map(|candidate| {
+            let (url, descriptor) = candidate
+                .split_once(char::is_whitespace)
+                .unwrap_or((candidate, ""));
+            let url = rewrite(url).unwrap_or_else(|| url.to_string());
+            if descriptor.is_empty() {
+                url
+            } else {
+                format!("{url} {}", descriptor.trim())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn css_urls_are_rewritten() {
+        let css = "body { background: url('a/bg.png') } .x { background: url(data:image/png;base64,AA) }";
+        let rewritten = rewrite_css_urls(css, |reference| Some(format!("local-{}", reference.replace('/', "_"))));
+        assert_eq!(
+            rewritten,
+            "body { background: url(\"local-a_bg.png\") } .x { background: url(data:image/png;base64,AA) }"
+        );
+    }
+
+    #[test]
+    fn srcset_keeps_descriptors() {
+        let rewritten = rewrite_srcset("small.png 1x, large.png 2x", |url| Some(format!("files/{url}")));
+        assert_eq!(rewritten, "files/small.png 1x, files/large.png 2x");
+    }
+
+    #[test]
+    fn file_names_are_sanitized() {
+        let url = Url::parse("https://example.com/img/my%20photo?.png").unwrap();
+        assert_eq!(local_file_name(&url), "my_photo");
+        let url = Url::parse("https://example.com/").unwrap();
+        assert_eq!(local_file_name(&url), "resource");
+    }
+}
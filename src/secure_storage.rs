@@ -0,0 +1,111 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::Engine;
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing::warn;
+
+const STORAGE_VERSION: u32 = 1;
+const KEYRING_SERVICE: &str = "stokes-browser";
+
+/// What an encrypted file holds: AES-256-GCM ciphertext of the JSON payload and its nonce
+#[derive(Debug, Serialize, Deserialize)]
+struct SealedPayload {
+    #[serde(default = "default_storage_version")]
+    version: u32,
+    nonce: String,
+    ciphertext: String,
+}
+
+const fn default_storage_version() -> u32 {
+    STORAGE_VERSION
+}
+
+/// A JSON file in the profile directory that is encrypted with a key kept in the OS keyring, for
+/// data that shouldn't sit on disk in plain text. Without a usable keyring nothing is persisted.
+#[derive(Debug, Clone)]
+pub struct EncryptedFile {
+    path: PathBuf,
+    key: Option<[u8; 32]>,
+}
+
+impl EncryptedFile {
+    /// Open `file_name`, loading the key stored under `key_name` or creating one
+    pub fn open(file_name: &str, key_name: &str) -> Self {
+        let path = crate::profile::data_dir().join(file_name);
+        let key = load_or_create_key(key_name);
+        if key.is_none() {
+            warn!("No keyring available for {file_name}; its contents won't be saved");
+        }
+        Self { path, key }
+    }
+
+    pub fn read<T: DeserializeOwned>(&self) -> Option<T> {
+        let key = self.key?;
+        let contents = std::fs::read_to_string(&self.path).ok()?;
+        let payload = serde_json::from_str::<SealedPayload>(&contents).ok()?;
+        let engine = base64::engine::general_purpose::STANDARD;
+        let nonce = engine.decode(payload.nonce).ok().filter(|nonce| nonce.len() == 12)?;
+        let ciphertext = engine.decode(payload.ciphertext).ok()?;
+
+        let cipher = Aes256Gcm::new_from_slice(&key).ok()?;
+        let Ok(plaintext) = cipher.decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice()) else {
+            warn!("Could not decrypt {}", self.path.display());
+            return None;
+        };
+        serde_json::from_slice(&plaintext).ok()
+    }
+
+    pub fn write<T: Serialize>(&self, value: &T) {
+        let Some(key) = self.key else {
+            return;
+        };
+        let Ok(plaintext) = serde_json::to_vec(value) else {
+            return;
+        };
+        let Ok(cipher) = Aes256Gcm::new_from_slice(&key) else {
+            return;
+        };
+        let mut nonce = [0_u8; 12];
+        rand::rng().fill_bytes(&mut nonce);
+        let Ok(ciphertext) = cipher.encrypt(Nonce::from_slice(&nonce), plaintext.as_slice()) else {
+            return;
+        };
+
+        let engine = base64::engine::general_purpose::STANDARD;
+        let payload = SealedPayload {
+            version: STORAGE_VERSION,
+            nonce: engine.encode(nonce),
+            ciphertext: engine.encode(ciphertext),
+        };
+        let Ok(json) = serde_json::to_string_pretty(&payload) else {
+            return;
+        };
+        if let Some(parent) = self.path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::write(&self.path, json);
+    }
+}
+
+fn decode_key(encoded: &str) -> Option<[u8; 32]> {
+    let decoded = base64::engine::general_purpose::STANDARD.decode(encoded.as_bytes()).ok()?;
+    decoded.try_into().ok()
+}
+
+/// The key stored in the keyring under `key_name`, or a new one if it can be stored there
+fn load_or_create_key(key_name: &str) -> Option<[u8; 32]> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, key_name).ok()?;
+    if let Some(key) = entry.get_password().ok().and_then(|encoded| decode_key(&encoded)) {
+        return Some(key);
+    }
+
+    let mut key = [0_u8; 32];
+    rand::rng().fill_bytes(&mut key);
+    entry.set_password(&base64::engine::general_purpose::STANDARD.encode(key)).ok()?;
+    // Only trust the keyring once it gives the key back
+    let roundtrip = entry.get_password().ok().and_then(|encoded| decode_key(&encoded))?;
+    (roundtrip == key).then_some(key)
+}
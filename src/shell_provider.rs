@@ -3,6 +3,8 @@ use blitz_traits::shell::{ClipboardError, FileDialogFilter, ShellProvider};
 use tokio::sync::mpsc::UnboundedSender;
 use cursor_icon::CursorIcon;
 use serde::{Deserialize, Serialize};
+use crate::clipboard::{self, Selection};
+use crate::permissions::PermissionKind;
 
 /// Messages sent from child (tab process) to parent (main process) to control the shell
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,6 +15,47 @@ pub enum ShellProviderMessage {
     SetImeEnabled(bool),
     SetImeCursorArea { x: f32, y: f32, width: f32, height: f32 },
     ViewportScroll((f64, f64)),
+    /// The page entered or left fullscreen through the Fullscreen API
+    SetFullscreen(bool),
+    /// The page locked the pointer through the Pointer Lock API, or let it go
+    SetPointerLock(bool),
+    /// The page asked for a permission its origin has not decided on yet
+    RequestPermission { request_id: u64, kind: PermissionKind },
+    /// The page showed a notification it has permission for
+    ShowNotification { title: String, body: String },
+    /// The page wants a position; the parent answers with `ParentToTabMessage::GeolocationPosition`
+    RequestGeolocation { request_id: u64, origin: String },
+    /// The page blocked or displayed insecure content. The tab process turns this into a
+    /// `TabToParentMessage::SecurityState`.
+    MixedContentChanged,
+    /// The page started or stopped playing sound. The tab process turns this into a
+    /// `TabToParentMessage::AudioStateChanged`.
+    AudioStateChanged { audible: bool },
+    /// A field autofill can fill was focused; its border box in physical pixels relative to the
+    /// top left of the viewport
+    AutofillField { x: f32, y: f32, width: f32, height: f32 },
+    /// Focus left the field reported by `AutofillField` or `LoginField`
+    AutofillFieldBlurred,
+    /// The username or password field of a login form was focused; its border box as for
+    /// `AutofillField`. The parent takes the origin from the tab's committed URL.
+    LoginField { x: f32, y: f32, width: f32, height: f32 },
+    /// A login form was submitted with these credentials
+    LoginSubmitted { username: String, password: String },
+    /// A script of extension `extension_id` called `browser.runtime.sendMessage` with `message`
+    /// (JSON); the parent answers with `ParentToTabMessage::ExtensionResponse`
+    ExtensionMessage { extension_id: String, request_id: u64, message: String },
+    /// An extension's background page answered the message it was given as `request_id`: the
+    /// response as JSON (`None` for undefined), or the error a listener's promise rejected with
+    ExtensionResponse { request_id: u64, response: Result<Option<String>, String> },
+    /// The page called prompt() on its beforeinstallprompt event; the parent answers with
+    /// `ParentToTabMessage::AppInstallResult`
+    RequestAppInstall,
+    /// The page named this URL with `<link rel="prerender">` as the likely next navigation; the
+    /// parent may load it in a hidden tab
+    Prerender(String),
+    /// The page called window.open for this URL without the user having clicked or typed just
+    /// before, and its site doesn't allow popups
+    PopupBlocked(String),
 }
 
 pub(crate) struct StokesShellProvider {
@@ -29,6 +72,8 @@ impl ShellProvider for StokesShellProvider {
     // Implement the commonly-used shell provider methods by sending a message to the tab main thread.
     fn request_redraw(&self) {
         let _ = self.sender.send(ShellProviderMessage::RequestRedraw);
+        // Network threads ask for frames when a resource arrives
+        crate::ipc::wake_main_loop();
     }
 
     fn set_cursor(&self, cursor: CursorIcon) {
@@ -50,15 +95,13 @@ impl ShellProvider for StokesShellProvider {
     }
 
     fn get_clipboard_text(&self) -> Result<String, ClipboardError> {
-        let mut cb = arboard::Clipboard::new().unwrap();
-        cb.get_text()
-            .map_err(|_| ClipboardError)
+        clipboard::get_text(Selection::Clipboard).ok_or(ClipboardError)
     }
 
     fn set_clipboard_text(&self, text: String) -> Result<(), ClipboardError> {
-        let mut cb = arboard::Clipboard::new().unwrap();
-        cb.set_text(text)
-            .map_err(|_| ClipboardError)
+        clipboard::set_text(Selection::Clipboard, &text)
+            .then_some(())
+            .ok_or(ClipboardError)
     }
 
     fn open_file_dialog(&self, multiple: bool, filter: Option<FileDialogFilter>) -> Vec<PathBuf> {
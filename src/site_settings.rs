@@ -0,0 +1,324 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const STORAGE_VERSION: u32 = 1;
+const SITE_SETTINGS_FILE: &str = "site_settings.json";
+
+/// Which color scheme a site is shown in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSchemeOverride {
+    /// Follow the page's `color-scheme` and the desktop preference
+    #[default]
+    Auto,
+    Light,
+    Dark,
+}
+
+impl ColorSchemeOverride {
+    /// Next value when cycling through the settings panel button
+    pub fn next(self) -> Self {
+        match self {
+            Self::Auto => Self::Light,
+            Self::Light => Self::Dark,
+            Self::Dark => Self::Auto,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Auto => "Auto",
+            Self::Light => "Light",
+            Self::Dark => "Dark",
+        }
+    }
+}
+
+/// Whether a site may start media playback before the user has interacted with it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AutoplayPolicy {
+    /// Muted media may autoplay; media with sound waits for a click or key press on the page
+    #[default]
+    BlockAudible,
+    Allow,
+    /// No media plays until the user has interacted with the page
+    Block,
+}
+
+impl AutoplayPolicy {
+    /// Next value when cycling through the settings panel button
+    pub fn next(self) -> Self {
+        match self {
+            Self::BlockAudible => Self::Allow,
+            Self::Allow => Self::Block,
+            Self::Block => Self::BlockAudible,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::BlockAudible => "Limit sound",
+            Self::Allow => "Allowed",
+            Self::Block => "Blocked",
+        }
+    }
+
+    /// Whether media may start playing. `audible` is whether it would make sound (not muted, with
+    /// a volume above zero), `user_activated` whether the user has clicked or typed on the page.
+    pub fn allows_playback(self, audible: bool, user_activated: bool) -> bool {
+        match self {
+            Self::Allow => true,
+            Self::BlockAudible => user_activated || !audible,
+            Self::Block => user_activated,
+        }
+    }
+}
+
+/// Preferences the user set for one origin
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SiteSettings {
+    #[serde(default)]
+    pub color_scheme: ColorSchemeOverride,
+    /// Flip the colors of pages that have no dark theme of their own
+    #[serde(default)]
+    pub force_dark: bool,
+    /// Exception to the global JavaScript setting; `None` follows it
+    #[serde(default)]
+    pub javascript: Option<bool>,
+    /// Exception to the global image loading setting; `None` follows it
+    #[serde(default)]
+    pub images: Option<bool>,
+    #[serde(default)]
+    pub autoplay: AutoplayPolicy,
+    /// Let the site open windows without a click or key press just before
+    #[serde(default)]
+    pub popups: bool,
+}
+
+impl SiteSettings {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PersistedSiteSettings {
+    #[serde(default = "default_storage_version")]
+    version: u32,
+    #[serde(default = "default_enabled")]
+    javascript_enabled: bool,
+    #[serde(default = "default_enabled")]
+    images_enabled: bool,
+    #[serde(default)]
+    origins: BTreeMap<String, SiteSettings>,
+}
+
+const fn default_storage_version() -> u32 {
+    STORAGE_VERSION
+}
+
+const fn default_enabled() -> bool {
+    true
+}
+
+/// Per-origin site settings. The parent process writes it from the settings panel, tab processes
+/// read it when a document is created and when the parent says it changed.
+#[derive(Debug, Clone)]
+pub struct SiteSettingsStore {
+    /// Whether sites without an exception may run JavaScript
+    javascript_enabled: bool,
+    /// Whether sites without an exception load images, rather than showing placeholders
+    images_enabled: bool,
+    origins: BTreeMap<String, SiteSettings>,
+    path: PathBuf,
+}
+
+impl Default for SiteSettingsStore {
+    fn default() -> Self {
+        Self {
+            javascript_enabled: true,
+            images_enabled: true,
+            origins: BTreeMap::new(),
+            path: site_settings_file_path(),
+        }
+    }
+}
+
+impl SiteSettingsStore {
+    pub fn load_from_disk() -> Self {
+        let mut store = Self::default();
+        if let Ok(contents) = std::fs::read_to_string(&store.path) {
+            if let Ok(persisted) = serde_json::from_str::<PersistedSiteSettings>(&contents) {
+                store.javascript_enabled = persisted.javascript_enabled;
+                store.images_enabled = persisted.images_enabled;
+                store.origins = persisted.origins;
+            }
+        }
+        store
+    }
+
+    pub fn save_to_disk(&self) {
+        let payload = PersistedSiteSettings {
+            version: STORAGE_VERSION,
+            javascript_enabled: self.javascript_enabled,
+            images_enabled: self.images_enabled,
+            origins: self.origins.clone(),
+        };
+
+        let Ok(json) = serde_json::to_string_pretty(&payload) else {
+            return;
+        };
+
+        if let Some(parent) = self.path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::write(&self.path, json);
+    }
+
+    pub fn get(&self, origin: &str) -> SiteSettings {
+        self.origins.get(origin).cloned().unwrap_or_default()
+    }
+
+    pub fn javascript_enabled(&self) -> bool {
+        self.javascript_enabled
+    }
+
+    pub fn set_javascript_enabled(&mut self, enabled: bool) {
+        self.javascript_enabled = enabled;
+    }
+
+    /// Whether pages from `origin` may run JavaScript: its exception if it has one, otherwise the
+    /// global setting
+    pub fn javascript_allowed(&self, origin: &str) -> bool {
+        self.get(origin).javascript.unwrap_or(self.javascript_enabled)
+    }
+
+    /// Allow or block JavaScript for `origin`, keeping an exception only when it differs from the
+    /// global setting
+    pub fn set_javascript_allowed(&mut self, origin: &str, allowed: bool) {
+        let mut settings = self.get(origin);
+        settings.javascript = (allowed != self.javascript_enabled).then_some(allowed);
+        self.set(origin, settings);
+    }
+
+    pub fn images_enabled(&self) -> bool {
+        self.images_enabled
+    }
+
+    pub fn set_images_enabled(&mut self, enabled: bool) {
+        self.images_enabled = enabled;
+    }
+
+    /// Whether pages from `origin` load images: its exception if it has one, otherwise the global
+    /// setting
+    pub fn images_allowed(&self, origin: &str) -> bool {
+        self.get(origin).images.unwrap_or(self.images_enabled)
+    }
+
+    /// Load or skip images for `origin`, keeping an exception only when it differs from the global
+    /// setting
+    pub fn set_images_allowed(&mut self, origin: &str, allowed: bool) {
+        let mut settings = self.get(origin);
+        settings.images = (allowed != self.images_enabled).then_some(allowed);
+        self.set(origin, settings);
+    }
+
+    /// Store `settings` for `origin`; defaults are dropped so the file only lists real overrides.
+    /// Opaque origins ("null") are never persisted.
+    pub fn set(&mut self, origin: &str, settings: SiteSettings) {
+        if origin.is_empty() || origin == "null" {
+            return;
+        }
+        if settings.is_default() {
+            self.origins.remove(origin);
+        } else {
+            self.origins.insert(origin.to_string(), settings);
+        }
+    }
+}
+
+fn site_settings_file_path() -> PathBuf {
+    crate::profile::data_dir().join(SITE_SETTINGS_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AutoplayPolicy, ColorSchemeOverride, SiteSettings, SiteSettingsStore};
+
+    #[test]
+    fn settings_are_per_origin_and_defaults_are_dropped() {
+        let mut store = SiteSettingsStore::default();
+        let dark = SiteSettings { color_scheme: ColorSchemeOverride::Dark, ..Default::default() };
+        store.set("https://a.example", dark.clone());
+        store.set("null", dark.clone());
+
+        assert_eq!(store.get("https://a.example"), dark);
+        assert_eq!(store.get("https://b.example"), SiteSettings::default());
+        assert_eq!(store.get("null"), SiteSettings::default());
+
+        store.set("https://a.example", SiteSettings::default());
+        assert!(store.origins.is_empty());
+    }
+
+    #[test]
+    fn javascript_exceptions_override_the_global_setting() {
+        let mut store = SiteSettingsStore::default();
+        assert!(store.javascript_allowed("https://a.example"));
+
+        store.set_javascript_allowed("https://a.example", false);
+        assert!(!store.javascript_allowed("https://a.example"));
+        assert!(store.javascript_allowed("https://b.example"));
+
+        store.set_javascript_enabled(false);
+        assert!(!store.javascript_allowed("https://b.example"));
+
+        // Matching the global setting again drops the exception
+        store.set_javascript_allowed("https://a.example", false);
+        assert!(store.origins.is_empty());
+
+        store.set_javascript_allowed("https://b.example", true);
+        assert!(store.javascript_allowed("https://b.example"));
+        assert_eq!(store.get("https://b.example").javascript, Some(true));
+    }
+
+    #[test]
+    fn image_exceptions_are_independent_of_javascript() {
+        let mut store = SiteSettingsStore::default();
+        store.set_images_enabled(false);
+        store.set_images_allowed("https://a.example", true);
+
+        assert!(store.images_allowed("https://a.example"));
+        assert!(!store.images_allowed("https://b.example"));
+        assert!(store.javascript_allowed("https://b.example"));
+        assert_eq!(store.get("https://a.example").javascript, None);
+    }
+
+    #[test]
+    fn color_scheme_override_cycles() {
+        let mut value = ColorSchemeOverride::Auto;
+        for expected in [ColorSchemeOverride::Light, ColorSchemeOverride::Dark, ColorSchemeOverride::Auto] {
+            value = value.next();
+            assert_eq!(value, expected);
+        }
+    }
+
+    #[test]
+    fn autoplay_policy_waits_for_the_user_unless_allowed() {
+        let policy = AutoplayPolicy::default();
+        assert!(policy.allows_playback(false, false));
+        assert!(!policy.allows_playback(true, false));
+        assert!(policy.allows_playback(true, true));
+
+        assert!(AutoplayPolicy::Allow.allows_playback(true, false));
+        assert!(!AutoplayPolicy::Block.allows_playback(false, false));
+        assert!(AutoplayPolicy::Block.allows_playback(true, true));
+
+        let mut value = AutoplayPolicy::BlockAudible;
+        for expected in [AutoplayPolicy::Allow, AutoplayPolicy::Block, AutoplayPolicy::BlockAudible] {
+            value = value.next();
+            assert_eq!(value, expected);
+        }
+    }
+}
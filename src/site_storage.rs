@@ -0,0 +1,363 @@
+// What each origin stores, kept in the profile so it outlives the tab process: its localStorage,
+// its Cache API caches and its service worker registrations, each one JSON file per origin in the
+// area's directory. Together they count against the origin's quota, and an index records when each
+// origin last used its storage so that the least recently used origins are evicted once all of
+// them together go over the total quota. IndexedDB doesn't exist yet.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+const MANAGEMENT_PAGE_HTML: &str = include_str!("../assets/storage.html");
+/// The page listing what each origin stores
+pub const MANAGEMENT_URL: &str = "stokes://storage";
+
+const INDEX_FILE: &str = "storage_index.json";
+const STORAGE_VERSION: u32 = 1;
+/// Bytes one origin may store
+pub const ORIGIN_QUOTA: u64 = 5 * 1024 * 1024;
+/// Bytes all origins together may store before the least recently used are evicted
+const TOTAL_QUOTA: u64 = 100 * 1024 * 1024;
+
+/// An origin tried to store more than `ORIGIN_QUOTA`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaExceeded;
+
+/// A kind of data an origin stores
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Area {
+    LocalStorage,
+    /// The Cache API's caches
+    Caches,
+    ServiceWorkers,
+}
+
+impl Area {
+    pub const ALL: [Area; 3] = [Area::LocalStorage, Area::Caches, Area::ServiceWorkers];
+
+    fn dir_name(self) -> &'static str {
+        match self {
+            Self::LocalStorage => "local_storage",
+            Self::Caches => "cache_storage",
+            Self::ServiceWorkers => "service_workers",
+        }
+    }
+
+    /// Name of the area in navigator.storage.estimate()'s usageDetails
+    pub fn usage_name(self) -> &'static str {
+        match self {
+            Self::LocalStorage => "localStorage",
+            Self::Caches => "caches",
+            Self::ServiceWorkers => "serviceWorkerRegistrations",
+        }
+    }
+}
+
+/// The origin whose storage a document uses, or `None` for opaque origins (file:, data:, about:)
+/// whose storage lives only as long as the document
+pub fn origin_of(url: &Url) -> Option<String> {
+    let origin = url.origin();
+    origin.is_tuple().then(|| origin.ascii_serialization())
+}
+
+fn index_dir() -> PathBuf {
+    crate::profile::data_dir().join(Area::LocalStorage.dir_name())
+}
+
+fn file_name(origin: &str) -> String {
+    let encoded: String = url::form_urlencoded::byte_serialize(origin.as_bytes()).collect();
+    format!("{encoded}.json")
+}
+
+fn storage_file_path(area: Area, origin: &str) -> PathBuf {
+    crate::profile::data_dir().join(area.dir_name()).join(file_name(origin))
+}
+
+/// Bytes `items` take on disk
+pub fn size_of(items: &HashMap<String, String>) -> u64 {
+    serde_json::to_string(items).map_or(0, |json| json.len() as u64)
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct StorageIndex {
+    version: u32,
+    /// When each origin last read or wrote its storage, in milliseconds since the epoch
+    last_used: BTreeMap<String, u64>,
+}
+
+impl StorageIndex {
+    fn path() -> PathBuf {
+        index_dir().join(INDEX_FILE)
+    }
+
+    fn load() -> Self {
+        std::fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&mut self) {
+        self.version = STORAGE_VERSION;
+        let Ok(json) = serde_json::to_string_pretty(self) else {
+            return;
+        };
+        let _ = std::fs::create_dir_all(index_dir());
+        let _ = std::fs::write(Self::path(), json);
+    }
+
+    fn touch(origin: &str) {
+        let mut index = Self::load();
+        index.last_used.insert(origin.to_string(), now_millis());
+        index.save();
+    }
+}
+
+/// The JSON `origin` keeps in `area`
+pub fn read_area(area: Area, origin: &str) -> Option<String> {
+    let contents = std::fs::read_to_string(storage_file_path(area, origin)).ok()?;
+    StorageIndex::touch(origin);
+    Some(contents)
+}
+
+/// Replace what `origin` keeps in `area` with `json`, unless that takes the origin over its
+/// quota. Other origins are evicted, least recently used first, if all origins together are over
+/// the total quota.
+pub fn write_area(area: Area, origin: &str, json: &str) -> Result<(), QuotaExceeded> {
+    let others: u64 = Area::ALL.into_iter().filter(|other| *other != area).map(|other| area_usage(other, origin)).sum();
+    if others + json.len() as u64 > ORIGIN_QUOTA {
+        return Err(QuotaExceeded);
+    }
+    let path = storage_file_path(area, origin);
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Err(err) = std::fs::write(&path, json) {
+        tracing::warn!("Failed to save storage for {}: {}", origin, err);
+    }
+    StorageIndex::touch(origin);
+    enforce_total_quota(origin);
+    Ok(())
+}
+
+/// Delete what `origin` keeps in `area`
+pub fn clear_area(area: Area, origin: &str) {
+    let _ = std::fs::remove_file(storage_file_path(area, origin));
+    if Area::ALL.into_iter().all(|area| area_usage(area, origin) == 0) {
+        forget(origin);
+    }
+}
+
+/// The localStorage items of `origin`
+pub fn read(origin: &str) -> HashMap<String, String> {
+    read_area(Area::LocalStorage, origin)
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Replace the localStorage items of `origin`
+pub fn write(origin: &str, items: &HashMap<String, String>) -> Result<(), QuotaExceeded> {
+    if items.is_empty() {
+        clear_area(Area::LocalStorage, origin);
+        return Ok(());
+    }
+    let Ok(json) = serde_json::to_string(items) else {
+        return Ok(());
+    };
+    write_area(Area::LocalStorage, origin, &json)
+}
+
+/// Bytes `origin` keeps in `area`
+pub fn area_usage(area: Area, origin: &str) -> u64 {
+    std::fs::metadata(storage_file_path(area, origin)).map_or(0, |metadata| metadata.len())
+}
+
+/// Bytes `origin` stores in all areas
+pub fn origin_usage(origin: &str) -> u64 {
+    Area::ALL.into_iter().map(|area| area_usage(area, origin)).sum()
+}
+
+/// Delete everything `origin` has stored
+pub fn clear(origin: &str) {
+    for area in Area::ALL {
+        let _ = std::fs::remove_file(storage_file_path(area, origin));
+    }
+    forget(origin);
+}
+
+fn forget(origin: &str) {
+    let mut index = StorageIndex::load();
+    if index.last_used.remove(origin).is_some() {
+        index.save();
+    }
+}
+
+pub fn clear_all() {
+    for usage in usage_by_origin() {
+        clear(&usage.origin);
+    }
+}
+
+/// What one origin stores
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginUsage {
+    pub origin: String,
+    pub bytes: u64,
+    pub last_used: u64,
+}
+
+/// Every origin with stored data, with the bytes its files take on disk
+pub fn usage_by_origin() -> Vec<OriginUsage> {
+    let index = StorageIndex::load();
+    index
+        .last_used
+        .iter()
+        .map(|(origin, last_used)| OriginUsage { origin: origin.clone(), bytes: origin_usage(origin), last_used: *last_used })
+        .filter(|usage| usage.bytes > 0)
+        .collect()
+}
+
+/// Origins to evict, least recently used first, until the others fit in `quota`. `keep` is the
+/// origin that just stored something and is never evicted.
+fn origins_to_evict(usage: &[OriginUsage], quota: u64, keep: &str) -> Vec<String> {
+    let mut total: u64 = usage.iter().map(|usage| usage.bytes).sum();
+    let mut candidates: Vec<&OriginUsage> = usage.iter().filter(|usage| usage.origin != keep).collect();
+    candidates.sort_by_key(|usage| usage.last_used);
+    let mut evicted = Vec::new();
+    for candidate in candidates {
+        if total <= quota {
+            break;
+        }
+        total -= candidate.bytes;
+        evicted.push(candidate.origin.clone());
+    }
+    evicted
+}
+
+fn enforce_total_quota(keep: &str) {
+    for origin in origins_to_evict(&usage_by_origin(), TOTAL_QUOTA, keep) {
+        tracing::info!("Evicting the storage of {} to stay under the total quota", origin);
+        clear(&origin);
+    }
+}
+
+pub fn is_management_url(url: &Url) -> bool {
+    url.scheme() == "stokes" && url.host_str() == Some("storage")
+}
+
+/// The stokes://storage page. Its links clear origins through the query string, which is only
+/// acted on when `from_management_page` (the link was followed from the page itself) so that
+/// other pages can't use them.
+pub fn management_page(url: &Url, from_management_page: bool) -> String {
+    if from_management_page {
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "clear" => clear(&value),
+                "clear_all" => clear_all(),
+                _ => {}
+            }
+        }
+    }
+    render_management_page(&usage_by_origin())
+}
+
+fn management_link(params: &[(&str, &str)]) -> String {
+    let mut query = url::form_urlencoded::Serializer::new(String::new());
+    query.extend_pairs(params);
+    format!("{MANAGEMENT_URL}?{}", query.finish())
+}
+
+/// `bytes` in the largest unit that keeps it at least 1
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+fn render_management_page(usage: &[OriginUsage]) -> String {
+    let mut usage: Vec<&OriginUsage> = usage.iter().collect();
+    usage.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.origin.cmp(&b.origin)));
+
+    let rows: String = usage.iter().map(|usage| {
+        let clear = management_link(&[("clear", &usage.origin)]);
+        format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td><a class=\"remove\" href=\"{}\">Clear</a></td></tr>\n",
+            html_escape::encode_text(&usage.origin),
+            format_bytes(usage.bytes),
+            html_escape::encode_text(&httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_millis(usage.last_used))),
+            html_escape::encode_double_quoted_attribute(&clear),
+        )
+    }).collect();
+    let sites = if rows.is_empty() {
+        "<p class=\"empty\">No site has stored anything</p>".to_string()
+    } else {
+        format!("<table>\n<tr><th>Site</th><th>Usage</th><th>Last used</th><th></th></tr>\n{rows}</table>\n")
+    };
+
+    let total: u64 = usage.iter().map(|usage| usage.bytes).sum();
+    let summary = match usage.len() {
+        1 => format!("1 site stores {}.", format_bytes(total)),
+        count => format!("{count} sites store {}.", format_bytes(total)),
+    };
+    MANAGEMENT_PAGE_HTML
+        .replace("{{SUMMARY}}", &summary)
+        .replace("{{QUOTA}}", &format_bytes(ORIGIN_QUOTA))
+        .replace("{{CLEAR_ALL_URL}}", &html_escape::encode_double_quoted_attribute(&management_link(&[("clear_all", "1")])))
+        .replace("{{SITES}}", &sites)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OriginUsage, file_name, format_bytes, origin_of, origins_to_evict, render_management_page};
+    use url::Url;
+
+    fn usage(origin: &str, bytes: u64, last_used: u64) -> OriginUsage {
+        OriginUsage { origin: origin.to_string(), bytes, last_used }
+    }
+
+    #[test]
+    fn storage_is_keyed_by_origin() {
+        let url = Url::parse("https://example.com:8443/path?query").unwrap();
+        assert_eq!(origin_of(&url).as_deref(), Some("https://example.com:8443"));
+        assert_eq!(origin_of(&Url::parse("file:///tmp/page.html").unwrap()), None);
+        assert_eq!(origin_of(&Url::parse("data:text/html,hi").unwrap()), None);
+        assert_eq!(file_name("https://example.com:8443"), "https%3A%2F%2Fexample.com%3A8443.json");
+    }
+
+    #[test]
+    fn evicts_least_recently_used_origins_until_under_quota() {
+        let usage = [usage("https://a.test", 40, 3), usage("https://b.test", 40, 1), usage("https://c.test", 40, 2)];
+        assert_eq!(origins_to_evict(&usage, 120, "https://a.test"), Vec::<String>::new());
+        assert_eq!(origins_to_evict(&usage, 80, "https://a.test"), ["https://b.test"]);
+        assert_eq!(origins_to_evict(&usage, 40, "https://a.test"), ["https://b.test", "https://c.test"]);
+        // The origin that is storing something stays even when it alone is over the quota
+        assert_eq!(origins_to_evict(&usage, 10, "https://b.test"), ["https://c.test", "https://a.test"]);
+    }
+
+    #[test]
+    fn page_lists_origins_by_usage() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MB");
+
+        let page = render_management_page(&[usage("https://small.test", 10, 0), usage("https://<big>.test", 2048, 0)]);
+        assert!(page.contains("2 sites store 2.0 KB."));
+        assert!(page.find("&lt;big&gt;").unwrap() < page.find("small.test").unwrap());
+        assert!(page.contains("stokes://storage?clear=https%3A%2F%2Fsmall.test"));
+    }
+}
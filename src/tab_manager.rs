@@ -1,14 +1,45 @@
 // Tab Manager - manages tab processes from the parent process
-use crate::ipc::{IpcServer, ParentIpcChannel, ParentToTabMessage, TabToParentMessage};
+use crate::ipc::{IpcServer, ParentIpcChannel, ParentToTabMessage, PendingNavigation, TabToParentMessage};
+use crate::networking::conditions::NetworkConditions;
+use crate::networking::security::SecurityState;
+use crate::visual_viewport::VisualViewport;
+use crate::web_apps::WebAppManifest;
+use ipc_channel::ipc::IpcSender;
 use shared_memory::{Shmem, ShmemConf};
 use skia_safe::{AlphaType, ColorType, Data, Image, ImageInfo};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::io;
 use std::process::{Child, Command};
-use std::thread;
-use std::time::Instant;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 use taffy::Point;
 
+/// Process label of spare tab processes. It only names the process's shared memory, so a spare
+/// can become any tab.
+const SPARE_TAB_LABEL: &str = "spare";
+/// How often the memory policy looks at system memory
+const MEMORY_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// Hidden tabs are frozen once available memory drops below this fraction of physical memory
+const FREEZE_BELOW_AVAILABLE: f64 = 0.20;
+/// Below this fraction hidden tabs are also discarded, the one hidden longest first
+const DISCARD_BELOW_AVAILABLE: f64 = 0.10;
+/// How long a tab must have been hidden before it is frozen
+const FREEZE_AFTER: Duration = Duration::from_secs(5 * 60);
+/// How long a tab must have been hidden before it is discarded
+const DISCARD_AFTER: Duration = Duration::from_secs(10 * 60);
+/// How long a closing tab gets to tear itself down before its process is killed
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Whether a tab's process is running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabLifecycle {
+    Active,
+    /// The process is suspended; messages for it wait until it is resumed
+    Frozen,
+    /// The process was killed to free its memory. The tab reloads its URL when it is shown.
+    Discarded,
+}
+
 /// Represents a managed tab process
 pub struct ManagedTab {
     pub id: String,
@@ -18,12 +49,116 @@ pub struct ManagedTab {
     pub favicon: Option<Vec<u8>>,
     pub zoom: f32,
     pub viewport_scroll: Point<f64>,
-    process: Child,
-    channel: ParentIpcChannel,
+    /// The part of the page magnified by pinching, applied when the tab's frame is composited
+    pub visual_viewport: VisualViewport,
+    pub is_pinned: bool,
+    /// The page is playing sound, as last reported by the tab process
+    pub is_audible: bool,
+    /// The user muted the tab. Kept here so a restarted process can be told again.
+    pub is_muted: bool,
+    /// Network conditions emulated for the tab, likewise kept for a restarted process
+    pub network_conditions: NetworkConditions,
+    /// URLs of the tab's session history, as last reported by the tab process
+    pub history: Vec<String>,
+    pub history_index: usize,
+    /// Connection security of the current page
+    pub security: SecurityState,
+    /// The web app manifest the current page links to, and its icon when the app can be installed
+    pub web_app: Option<WebAppManifest>,
+    pub web_app_icon: Option<Vec<u8>>,
+    /// URLs of windows the current page tried to open without a user gesture
+    pub blocked_popups: Vec<String>,
+    /// Navigation the tab's process started and has not finished, which a restarted process
+    /// carries on instead of going back to the page before
+    pub pending_navigation: Option<PendingNavigation>,
+    /// alert(), confirm() and prompt() dialogs the current page has opened
+    pub dialogs_shown: usize,
+    /// The user stopped the current page from opening more dialogs; they are answered as if
+    /// dismissed without being shown
+    pub dialogs_muted: bool,
+    /// Bytes of decoded images the tab's page holds, as last reported by the tab process
+    pub image_memory: usize,
+    /// Whether the tab is shown in its window; hidden tabs are throttled and produce no frames
+    pub is_visible: bool,
+    /// When the tab was last hidden, if it is hidden
+    hidden_since: Option<Instant>,
+    /// The tab runs a WebExtension's background page rather than showing a page in a window. It
+    /// is never frozen or discarded, since content scripts may message it at any time.
+    pub is_extension_host: bool,
+    /// The tab prerenders the page another tab's document named as its likely next navigation.
+    /// It stays hidden until the browser swaps its process into that tab.
+    pub is_prerender: bool,
+    pub lifecycle: TabLifecycle,
+    /// Messages sent while the tab was frozen
+    deferred: VecDeque<ParentToTabMessage>,
+    connection: Option<TabConnection>,
     pub rendered_frame: Option<RenderedFrame>,
     frame_source: Option<SharedFrameSource>,
 }
 
+/// A running tab process and its IPC channel
+struct TabConnection {
+    process: Child,
+    channel: ParentIpcChannel,
+}
+
+impl TabConnection {
+    /// Start a tab process and wait for it to connect
+    fn spawn(tab_id: &str) -> io::Result<Self> {
+        // Create a fresh one-shot server for this tab.
+        let server = IpcServer::new()?;
+        let server_name = server.server_name().to_string();
+
+        // Get the current executable path
+        let exe_path = std::env::current_exe()?;
+
+        // Spawn the tab process, passing the server name instead of a path.
+        let process = Command::new(exe_path)
+            .arg("--tab-process")
+            .arg(tab_id)
+            .arg(&server_name)
+            .arg("--profile-dir")
+            .arg(crate::profile::data_dir())
+            .spawn()?;
+
+        // Block until the tab process completes the bootstrap handshake.
+        let channel = server.accept()?;
+        Ok(Self { process, channel })
+    }
+
+    /// Suspend or resume the process. Returns false where processes cannot be suspended.
+    #[cfg(unix)]
+    fn set_suspended(&self, suspended: bool) -> bool {
+        let signal = if suspended { libc::SIGSTOP } else { libc::SIGCONT };
+        unsafe { libc::kill(self.process.id() as libc::pid_t, signal) == 0 }
+    }
+
+    #[cfg(not(unix))]
+    fn set_suspended(&self, _suspended: bool) -> bool {
+        false
+    }
+
+    /// Ask the process to tear its tab down and exit, and kill it if it hasn't within
+    /// `SHUTDOWN_TIMEOUT`
+    fn shut_down(mut self) {
+        let _ = self.channel.send(&ParentToTabMessage::Shutdown);
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        while !self.has_exited() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        self.kill();
+    }
+
+    fn kill(mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+
+    fn has_exited(&mut self) -> bool {
+        !matches!(self.process.try_wait(), Ok(None))
+    }
+}
+
 struct SharedFrameSource {
     shmem_name: String,
     shmem: Shmem,
@@ -36,10 +171,72 @@ pub struct RenderedFrame {
     pub height: u32,
 }
 
+impl ManagedTab {
+    /// Send a message to the tab's process, holding it back while the tab is frozen
+    fn send(&mut self, message: ParentToTabMessage) -> io::Result<()> {
+        match (self.lifecycle, &self.connection) {
+            (TabLifecycle::Frozen, _) => {
+                crate::ipc::enqueue(&mut self.deferred, message);
+                Ok(())
+            }
+            (_, Some(connection)) => connection.channel.send(&message),
+            (_, None) => Ok(()),
+        }
+    }
+
+    /// Resume a frozen tab and deliver what was sent to it in the meantime
+    fn resume(&mut self) -> io::Result<()> {
+        let Some(connection) = &self.connection else {
+            return Ok(());
+        };
+        connection.set_suspended(false);
+        self.lifecycle = TabLifecycle::Active;
+        for message in self.deferred.drain(..) {
+            connection.channel.send(&message)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_discarded(&self) -> bool {
+        self.lifecycle == TabLifecycle::Discarded
+    }
+}
+
+/// Fraction of physical memory still available, where the platform reports it
+fn available_memory_fraction() -> Option<f64> {
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_to_string("/proc/meminfo").ok().and_then(|meminfo| parse_meminfo(&meminfo))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Read MemAvailable / MemTotal out of /proc/meminfo
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_meminfo(meminfo: &str) -> Option<f64> {
+    let field = |name: &str| {
+        meminfo.lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|value| value.trim().trim_end_matches("kB").trim().parse::<f64>().ok())
+    };
+    let total = field("MemTotal")?;
+    let available = field("MemAvailable")?;
+    (total > 0.0).then(|| available / total)
+}
+
 /// Manages all tab processes
 pub struct TabManager {
     tabs: HashMap<String, ManagedTab>,
     next_tab_id: usize,
+    last_memory_check: Instant,
+    /// A tab process started ahead of time, handed to the next tab that needs one
+    spare: Option<JoinHandle<io::Result<TabConnection>>>,
+    /// Tabs discarded since the browser last asked, by the memory policy or because their
+    /// process died
+    newly_discarded: Vec<String>,
 }
 
 impl TabManager {
@@ -48,30 +245,36 @@ impl TabManager {
         Ok(Self {
             tabs: HashMap::new(),
             next_tab_id: 1,
+            last_memory_check: Instant::now(),
+            spare: None,
+            newly_discarded: Vec::new(),
         })
     }
 
     /// Create a new tab process
     pub fn create_tab(&mut self) -> io::Result<String> {
-        let tab_id = format!("tab{}", self.next_tab_id);
-        self.next_tab_id += 1;
+        self.create_managed_tab(false)
+    }
 
-        // Create a fresh one-shot server for this tab.
-        let server = IpcServer::new()?;
-        let server_name = server.server_name().to_string();
+    /// Create a hidden tab process for a WebExtension's background page
+    pub fn create_extension_host(&mut self) -> io::Result<String> {
+        self.create_managed_tab(true)
+    }
 
-        // Get the current executable path
-        let exe_path = std::env::current_exe()?;
+    /// Create a hidden tab process to prerender a page in
+    pub fn create_prerender_host(&mut self) -> io::Result<String> {
+        let host_id = self.create_managed_tab(false)?;
+        if let Some(host) = self.tabs.get_mut(&host_id) {
+            host.is_prerender = true;
+        }
+        Ok(host_id)
+    }
 
-        // Spawn the tab process, passing the server name instead of a path.
-        let child = Command::new(exe_path)
-            .arg("--tab-process")
-            .arg(&tab_id)
-            .arg(&server_name)
-            .spawn()?;
+    fn create_managed_tab(&mut self, is_extension_host: bool) -> io::Result<String> {
+        let tab_id = format!("tab{}", self.next_tab_id);
+        self.next_tab_id += 1;
 
-        // Block until the tab process completes the bootstrap handshake.
-        let channel = server.accept()?;
+        let connection = self.take_connection(&tab_id)?;
 
         let managed_tab = ManagedTab {
             id: tab_id.clone(),
@@ -81,16 +284,61 @@ impl TabManager {
             favicon: None,
             zoom: 1.0,
             viewport_scroll: Point { x: 0.0, y: 0.0 },
-            process: child,
-            channel,
+            visual_viewport: VisualViewport::default(),
+            is_pinned: false,
+            is_audible: false,
+            is_muted: false,
+            network_conditions: NetworkConditions::default(),
+            history: Vec::new(),
+            history_index: 0,
+            security: SecurityState::default(),
+            web_app: None,
+            web_app_icon: None,
+            blocked_popups: Vec::new(),
+            pending_navigation: None,
+            dialogs_shown: 0,
+            dialogs_muted: false,
+            image_memory: 0,
+            is_visible: true,
+            hidden_since: None,
+            is_extension_host,
+            is_prerender: false,
+            lifecycle: TabLifecycle::Active,
+            deferred: VecDeque::new(),
+            connection: Some(connection),
             rendered_frame: None,
             frame_source: None,
         };
 
         self.tabs.insert(tab_id.clone(), managed_tab);
+        self.prewarm();
         Ok(tab_id)
     }
 
+    /// A process for a new tab: the spare if it started fine, otherwise a fresh one
+    fn take_connection(&mut self, tab_id: &str) -> io::Result<TabConnection> {
+        if let Some(spare) = self.spare.take() {
+            match spare.join() {
+                Ok(Ok(mut connection)) if !connection.has_exited() => return Ok(connection),
+                Ok(Ok(_)) => tracing::warn!("Spare tab process exited, starting a new one"),
+                Ok(Err(e)) => tracing::warn!("Spare tab process failed to start: {}", e),
+                Err(_) => tracing::warn!("Spare tab process thread panicked"),
+            }
+        }
+        TabConnection::spawn(tab_id)
+    }
+
+    /// Start a spare tab process in the background, unless one is already there
+    fn prewarm(&mut self) {
+        if self.spare.is_some() {
+            return;
+        }
+        self.spare = thread::Builder::new()
+            .name("tab-prewarm".to_string())
+            .spawn(|| TabConnection::spawn(SPARE_TAB_LABEL))
+            .ok();
+    }
+
     /// Get a tab by ID
     #[inline]
     pub fn get_tab(&self, tab_id: &str) -> Option<&ManagedTab> {
@@ -105,25 +353,261 @@ impl TabManager {
 
     /// Send a message to a tab
     pub fn send_to_tab(&mut self, tab_id: &str, message: ParentToTabMessage) -> io::Result<()> {
-        if let Some(tab) = self.tabs.get(tab_id) {
-            tab.channel.send(&message)?;
+        let Some(tab) = self.tabs.get_mut(tab_id) else {
+            return Ok(());
+        };
+        if let Err(e) = tab.send(message) {
+            tracing::error!("Lost connection to tab {}: {}", tab_id, e);
+            self.discard_tab(tab_id);
+            return Err(e);
         }
         Ok(())
     }
 
+    /// Tell a tab whether it is shown, if that changed
+    pub fn set_tab_visibility(&mut self, tab_id: &str, visible: bool) -> io::Result<()> {
+        let Some(tab) = self.tabs.get_mut(tab_id) else {
+            return Ok(());
+        };
+        if tab.is_visible == visible {
+            return Ok(());
+        }
+        tab.is_visible = visible;
+        tab.hidden_since = (!visible).then(Instant::now);
+        if visible && tab.lifecycle == TabLifecycle::Frozen {
+            tab.resume()?;
+        }
+        tab.send(ParentToTabMessage::SetVisibility(visible))
+    }
+
+    /// Mute or unmute a tab's sound
+    pub fn set_tab_muted(&mut self, tab_id: &str, muted: bool) -> io::Result<()> {
+        let Some(tab) = self.tabs.get_mut(tab_id) else {
+            return Ok(());
+        };
+        tab.is_muted = muted;
+        tab.send(ParentToTabMessage::SetMuted(muted))
+    }
+
+    /// Emulate network conditions for a tab's requests
+    pub fn set_tab_network_conditions(&mut self, tab_id: &str, conditions: NetworkConditions) -> io::Result<()> {
+        let Some(tab) = self.tabs.get_mut(tab_id) else {
+            return Ok(());
+        };
+        tab.network_conditions = conditions;
+        tab.send(ParentToTabMessage::SetNetworkConditions(conditions))
+    }
+
+    /// Move a tab's visual viewport, telling the page when it changed
+    pub fn set_tab_visual_viewport(&mut self, tab_id: &str, visual_viewport: VisualViewport) -> io::Result<()> {
+        let Some(tab) = self.tabs.get_mut(tab_id) else {
+            return Ok(());
+        };
+        if tab.visual_viewport == visual_viewport {
+            return Ok(());
+        }
+        tab.visual_viewport = visual_viewport;
+        tab.send(ParentToTabMessage::SetVisualViewport(visual_viewport))
+    }
+
+    /// Clone a tab's sender so replies can be sent from a worker thread
+    pub fn tab_sender(&self, tab_id: &str) -> Option<IpcSender<ParentToTabMessage>> {
+        self.tabs.get(tab_id)?.connection.as_ref().map(|connection| connection.channel.sender.clone())
+    }
+
     /// Poll messages from all tabs (non-blocking)
     pub fn poll_messages(&mut self) -> Vec<(String, TabToParentMessage)> {
         let mut messages = Vec::new();
 
+        let mut disconnected = Vec::new();
         for (tab_id, tab) in self.tabs.iter() {
-            while let Ok(Some(msg)) = tab.channel.try_receive() {
-                messages.push((tab_id.clone(), msg));
+            let Some(connection) = &tab.connection else {
+                continue;
+            };
+            loop {
+                match connection.channel.try_receive() {
+                    Ok(Some(msg)) => messages.push((tab_id.clone(), msg)),
+                    Ok(None) => break,
+                    Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                        tracing::warn!("Skipping unreadable message from tab {}: {}", tab_id, e);
+                    }
+                    Err(_) => {
+                        disconnected.push(tab_id.clone());
+                        break;
+                    }
+                }
             }
         }
 
+        // A tab whose process died is kept as a discarded tab, so showing it loads it again
+        for tab_id in disconnected {
+            tracing::error!("Tab {} process exited unexpectedly", tab_id);
+            self.discard_tab(&tab_id);
+        }
+
         messages
     }
 
+    /// Freeze or discard long hidden tabs while system memory is tight
+    /// When `apply_memory_policy` next looks at the system's memory
+    pub fn next_memory_check(&self) -> Instant {
+        self.last_memory_check + MEMORY_CHECK_INTERVAL
+    }
+
+    pub fn apply_memory_policy(&mut self) {
+        if self.last_memory_check.elapsed() < MEMORY_CHECK_INTERVAL {
+            return;
+        }
+        self.last_memory_check = Instant::now();
+        let Some(available) = available_memory_fraction() else {
+            return;
+        };
+        if available >= FREEZE_BELOW_AVAILABLE {
+            return;
+        }
+
+        for (tab_id, tab) in self.tabs.iter_mut() {
+            let hidden_for = tab.hidden_since.map_or(Duration::ZERO, |since| since.elapsed());
+            if tab.lifecycle == TabLifecycle::Active && !tab.is_extension_host && !tab.is_audible && hidden_for >= FREEZE_AFTER {
+                if let Some(connection) = &tab.connection {
+                    if connection.set_suspended(true) {
+                        tracing::info!("Froze tab {} to save memory", tab_id);
+                        tab.lifecycle = TabLifecycle::Frozen;
+                    }
+                }
+            }
+        }
+
+        if available >= DISCARD_BELOW_AVAILABLE {
+            return;
+        }
+        // Discard one tab per check, giving the system time to reclaim its memory
+        let Some(tab_id) = self.discard_candidate() else {
+            return;
+        };
+        tracing::info!("Discarding tab {} to save memory ({:.0}% available)", tab_id, available * 100.0);
+        self.discard_tab(&tab_id);
+    }
+
+    /// Tabs discarded since the last call, for the browser to mark
+    pub fn take_newly_discarded(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.newly_discarded)
+    }
+
+    /// The tab that has been hidden longest, if any may be discarded
+    fn discard_candidate(&self) -> Option<String> {
+        self.tabs
+            .values()
+            .filter(|tab| tab.lifecycle != TabLifecycle::Discarded && !tab.is_pinned && !tab.is_loading && !tab.is_audible && !tab.is_extension_host)
+            .filter_map(|tab| Some((tab.hidden_since?, tab)))
+            .filter(|(since, _)| since.elapsed() >= DISCARD_AFTER)
+            .min_by_key(|(since, _)| *since)
+            .map(|(_, tab)| tab.id.clone())
+    }
+
+    /// Kill a tab's process, keeping what is needed to load it again
+    pub fn discard_tab(&mut self, tab_id: &str) {
+        let Some(tab) = self.tabs.get_mut(tab_id) else {
+            return;
+        };
+        if tab.lifecycle == TabLifecycle::Discarded {
+            return;
+        }
+        if let Some(connection) = tab.connection.take() {
+            connection.kill();
+        }
+        tab.lifecycle = TabLifecycle::Discarded;
+        tab.deferred.clear();
+        tab.rendered_frame = None;
+        tab.frame_source = None;
+        tab.image_memory = 0;
+        tab.is_loading = false;
+        tab.is_audible = false;
+        self.newly_discarded.push(tab_id.to_string());
+    }
+
+    /// Start a new process for a discarded tab. The caller configures it and restores its session.
+    pub fn reload_discarded_tab(&mut self, tab_id: &str) -> io::Result<()> {
+        if !self.tabs.get(tab_id).is_some_and(ManagedTab::is_discarded) {
+            return Ok(());
+        }
+        let connection = self.take_connection(tab_id)?;
+        self.prewarm();
+        let Some(tab) = self.tabs.get_mut(tab_id) else {
+            return Ok(());
+        };
+        tab.connection = Some(connection);
+        tab.lifecycle = TabLifecycle::Active;
+        tab.is_visible = true;
+        tab.hidden_since = None;
+        Ok(())
+    }
+
+    /// Move the process of prerender host `host_id` into tab `tab_id`, which takes over its page,
+    /// and shut down the process the tab had. The tab's history goes before the prerendered page's.
+    pub fn swap_in_prerender(&mut self, tab_id: &str, host_id: &str) -> io::Result<()> {
+        if !self.tabs.contains_key(tab_id) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no tab {tab_id}")));
+        }
+        let Some(mut host) = self.tabs.remove(host_id) else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no prerender host {host_id}")));
+        };
+        if host.lifecycle == TabLifecycle::Frozen {
+            host.resume()?;
+        }
+        let Some(connection) = host.connection.take() else {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, format!("prerender host {host_id} was discarded")));
+        };
+
+        let tab = self.tabs.get_mut(tab_id).expect("checked above");
+        if let Some(old) = tab.connection.replace(connection) {
+            if tab.lifecycle == TabLifecycle::Frozen {
+                old.set_suspended(false);
+            }
+            // Don't hold the swap up while the old page tears itself down
+            thread::spawn(move || old.shut_down());
+        }
+        tab.lifecycle = TabLifecycle::Active;
+        tab.deferred.clear();
+        tab.url = host.url;
+        tab.title = host.title;
+        tab.is_loading = host.is_loading;
+        tab.favicon = host.favicon;
+        tab.security = host.security;
+        tab.web_app = host.web_app;
+        tab.web_app_icon = host.web_app_icon;
+        tab.image_memory = host.image_memory;
+        tab.is_audible = host.is_audible;
+        tab.viewport_scroll = Point::default();
+        tab.visual_viewport = VisualViewport::default();
+        tab.rendered_frame = host.rendered_frame;
+        tab.frame_source = host.frame_source;
+
+        let earlier: Vec<String> = tab.history.iter().take(tab.history_index + 1).cloned().collect();
+        tab.history = earlier.iter().cloned().chain(host.history).collect();
+        tab.history_index = (earlier.len() + host.history_index).min(tab.history.len().saturating_sub(1));
+        if !earlier.is_empty() {
+            tab.send(ParentToTabMessage::PrependHistory(earlier))?;
+        }
+        if tab.zoom != 1.0 {
+            let zoom = tab.zoom;
+            tab.send(ParentToTabMessage::SetZoom(zoom))?;
+        }
+        if tab.is_muted {
+            tab.send(ParentToTabMessage::SetMuted(true))?;
+        }
+        if tab.network_conditions.is_emulated() {
+            let conditions = tab.network_conditions;
+            tab.send(ParentToTabMessage::SetNetworkConditions(conditions))?;
+        }
+
+        // The host was hidden; show the page if the tab is shown
+        let visible = tab.is_visible;
+        tab.is_visible = false;
+        tab.hidden_since = Some(Instant::now());
+        self.set_tab_visibility(tab_id, visible)
+    }
+
     /// Process a message from a tab and update state
     pub fn process_tab_message(&mut self, tab_id: &str, message: TabToParentMessage) {
         if let Some(tab) = self.tabs.get_mut(tab_id) {
@@ -132,18 +616,30 @@ impl TabManager {
                     tab.is_loading = true;
                     tab.url = url;
                     tab.favicon = None;
+                    tab.security = SecurityState::default();
+                    tab.web_app = None;
+                    tab.web_app_icon = None;
+                    tab.blocked_popups.clear();
+                    tab.pending_navigation = None;
+                    tab.is_audible = false;
+                    tab.dialogs_shown = 0;
+                    tab.dialogs_muted = false;
                 }
                 TabToParentMessage::NavigationCompleted { url, title } => {
                     tab.is_loading = false;
+                    tab.pending_navigation = None;
                     tab.url = url;
                     tab.title = title;
 
                     // todo conditional reset scroll
                     tab.viewport_scroll = Point::default();
+                    // A new page starts out unmagnified, and its document already thinks it is
+                    tab.visual_viewport = VisualViewport::default();
                 }
                 TabToParentMessage::NavigationFailed(error) => {
                     tab.is_loading = false;
-                    eprintln!("Navigation failed in tab {}: {}", tab_id, error);
+                    tab.pending_navigation = None;
+                    tracing::warn!("Navigation failed in tab {}: {}", tab_id, error);
                 }
                 TabToParentMessage::TitleChanged(title) => {
                     tab.title = title;
@@ -154,6 +650,10 @@ impl TabManager {
                 TabToParentMessage::FaviconUpdated(favicon) => {
                     tab.favicon = favicon;
                 }
+                TabToParentMessage::HistoryChanged { entries, index } => {
+                    tab.history = entries;
+                    tab.history_index = index;
+                }
                 TabToParentMessage::FrameRendered { shmem_name, width, height } => {
                     // Load the frame from shared memory
                     if let Ok(frame) = Self::load_frame_from_shmem(tab, &shmem_name, width, height) {
@@ -161,20 +661,20 @@ impl TabManager {
                     }
                 }
                 TabToParentMessage::Ready => {
-                    println!("Tab {} is ready", tab_id);
+                    tracing::debug!("Tab {} is ready", tab_id);
                 }
                 TabToParentMessage::NavigateRequest(url) => {
                     // Handle navigation request from web content (e.g., link clicks)
-                    println!("Navigation request from tab {}: {}", tab_id, url);
+                    tracing::debug!("Navigation request from tab {}: {}", tab_id, url);
                     tab.url = url.clone();
                     // The actual navigation will be handled by sending Navigate message back to the tab
                 }
-                TabToParentMessage::NavigateRequestInNewTab(_url) => {
+                TabToParentMessage::NavigateRequestInNewTab { .. } => {
                     // Navigate in new tab request is handled by the browser process, not the tab manager
                     // This is just here for exhaustive pattern matching
                 }
-                TabToParentMessage::Alert(_message) => {
-                    // Alert is handled by the browser process, not the tab manager
+                TabToParentMessage::Dialog { .. } => {
+                    // Dialogs are handled by the browser process, not the tab manager
                     // This is just here for exhaustive pattern matching
                 }
                 TabToParentMessage::ShellProvider(_msg) => {
@@ -182,12 +682,44 @@ impl TabManager {
                     // This is just here for exhaustive pattern matching
                 },
                 TabToParentMessage::UpdateButtons(_) => {},
+                TabToParentMessage::PageSaved { .. } => {},
+                TabToParentMessage::ScreenshotSaved(_) => {},
+                TabToParentMessage::PermissionRequest { .. } => {},
+                TabToParentMessage::PasswordsPageRequest { .. } => {},
+                TabToParentMessage::AuthRequired { .. } => {},
+                TabToParentMessage::SecurityState(state) => {
+                    tab.security = state;
+                }
+                TabToParentMessage::MemoryUsage { image_bytes } => {
+                    tab.image_memory = image_bytes;
+                }
+                TabToParentMessage::ResourceReport(report) => {
+                    tab.image_memory = report.image_bytes;
+                }
+                TabToParentMessage::Log(record) => {
+                    record.emit(tab_id);
+                }
+                TabToParentMessage::AudioStateChanged { audible } => {
+                    tab.is_audible = audible;
+                }
+                TabToParentMessage::WebAppManifest { manifest, icon } => {
+                    tab.web_app = manifest;
+                    tab.web_app_icon = icon;
+                }
+                // Swapping the prerendered page in is up to the browser process
+                TabToParentMessage::ActivatePrerender(_) => {}
+                // Shown in the browser's find bar
+                TabToParentMessage::FindResult { .. } => {}
+                TabToParentMessage::PendingNavigation(navigation) => {
+                    tab.pending_navigation = Some(navigation);
+                }
                 TabToParentMessage::Navigate { .. } => todo!(),
             }
         }
     }
 
     /// Load a rendered frame from shared memory
+    #[tracing::instrument(name = "load_frame", level = "debug", skip_all)]
     fn load_frame_from_shmem(
         tab: &mut ManagedTab,
         shmem_name: &str,
@@ -222,12 +754,12 @@ impl TabManager {
             Data::new_copy(slice)
         };
 
-        // Create an image from the data
+        // Create an image from the data. The tab painted it in the display's color space.
         let image_info = ImageInfo::new(
             (width as i32, height as i32),
             ColorType::RGBA8888,
             AlphaType::Premul,
-            None,
+            Some(crate::color_management::display_color_space()),
         );
 
         let row_bytes = width as usize * 4;
@@ -246,11 +778,16 @@ impl TabManager {
 
     /// Close a tab
     pub fn close_tab(&mut self, tab_id: &str) -> io::Result<()> {
-        if let Some(mut tab) = self.tabs.remove(tab_id) {
-            let _ = tab.channel.send(&ParentToTabMessage::Shutdown);
-            thread::sleep(std::time::Duration::from_millis(100));
-            let _ = tab.process.kill();
+        if let Some(tab) = self.tabs.remove(tab_id) {
+            let Some(connection) = tab.connection else {
+                return Ok(());
+            };
+            if tab.lifecycle == TabLifecycle::Frozen {
+                connection.set_suspended(false);
+            }
+            connection.shut_down();
         }
+        self.prewarm();
         Ok(())
     }
 
@@ -269,9 +806,26 @@ impl TabManager {
 impl Drop for TabManager {
     fn drop(&mut self) {
         for (_, tab) in self.tabs.drain() {
-            let _ = tab.channel.send(&ParentToTabMessage::Shutdown);
-            let mut process = tab.process;
-            let _ = process.kill();
+            if let Some(mut connection) = tab.connection {
+                let _ = connection.channel.send(&ParentToTabMessage::Shutdown);
+                let _ = connection.process.kill();
+            }
         }
+        if let Some(Ok(Ok(spare))) = self.spare.take().map(JoinHandle::join) {
+            let _ = spare.channel.send(&ParentToTabMessage::Shutdown);
+            spare.kill();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_meminfo;
+
+    #[test]
+    fn reads_available_fraction_from_meminfo() {
+        let meminfo = "MemTotal:       16000000 kB\nMemFree:         1000000 kB\nMemAvailable:    4000000 kB\n";
+        assert_eq!(parse_meminfo(meminfo), Some(0.25));
+        assert_eq!(parse_meminfo("MemTotal: 100 kB\n"), None);
     }
 }
@@ -2,51 +2,93 @@ use crate::dom::{AbstractDom, Dom};
 use crate::engine::nav_provider::{NavigationProviderMessage, StokesNavigationProvider};
 // Tab process module - runs the browser engine in a separate process
 use crate::engine::{Engine, EngineConfig, ENGINE_REF, USER_AGENT_REF};
+use crate::engine::https_first;
+use crate::engine::teardown::ResourceReport;
+use crate::frame_timing::FrameScheduler;
+use crate::networking::{hsts, http_auth};
 use crate::engine::js_provider::{JsProviderMessage, StokesJsProvider};
-use crate::ipc::{connect, IpcChannel, ParentToTabMessage, TabToParentMessage};
+use crate::ipc::{self, connect, IpcChannel, ParentToTabMessage, PendingNavigation, TabToParentMessage};
+use crate::logging::{self, LogRecord};
+use crate::networking::security::SecurityState;
 use crate::shell_provider::{ShellProviderMessage, StokesShellProvider};
-use crate::{js, networking};
+use crate::{color_management, js, languages, networking, passwords};
 use crate::renderer::painter::{ScenePainter, SkiaCache};
+use crate::renderer::webgl;
+use crate::save_page::save_page;
+use crate::web_apps::{self, WebAppManifest};
+use blitz_traits::navigation::NavigationOptions;
 use blitz_traits::net::Request;
 use blitz_traits::shell::{ShellProvider, Viewport};
 use curl::easy::{Easy, List};
 use gl::types::GLint;
 use glutin::config::{Config, ConfigSurfaceTypes, ConfigTemplateBuilder, GlConfig};
-use glutin::context::{ContextApi, ContextAttributesBuilder, NotCurrentGlContext, PossiblyCurrentContext};
+use glutin::context::{
+    ContextApi, ContextAttributesBuilder, NotCurrentGlContext, PossiblyCurrentContext, PossiblyCurrentGlContext,
+};
 use glutin::display::{Display as GlutinDisplay, DisplayApiPreference, GetGlDisplay, GlDisplay};
 use glutin::surface::{PbufferSurface, Surface as GlutinSurface, SurfaceAttributesBuilder};
+use markup5ever::local_name;
 use raw_window_handle::{RawDisplayHandle, XlibDisplayHandle};
 use shared_memory::{Shmem, ShmemConf};
 use skia_safe::gpu::gl::{Format, FramebufferInfo, Interface};
 use skia_safe::gpu::surfaces::wrap_backend_render_target;
 use skia_safe::gpu::{backend_render_targets, DirectContext};
 use skia_safe::gpu::{self};
-use skia_safe::{Canvas, ColorType, Surface};
+use skia_safe::{Canvas, ColorType, EncodedImageFormat, Surface};
+use std::cell::Cell;
 use std::cmp::Ordering as CmpOrdering;
 use std::ffi::CString;
 use std::io;
 use std::num::NonZeroU32;
+use std::path::Path;
 use std::ptr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::fs;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
-use tracing::{debug, trace, warn};
-use tracing::metadata::LevelFilter;
+use tracing::{debug, info, trace, warn};
 use url::Url;
 
+/// Screenshots taller than this are cut off, to keep the offscreen surface a sane size
+const MAX_SCREENSHOT_HEIGHT: u32 = 16384;
+/// Longest a hidden tab sleeps between running its scripts, so network callbacks and throttled
+/// timers still make progress
+const BACKGROUND_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 /// Tab process that runs in its own OS process
 pub struct TabProcess {
     pub(crate) engine: Engine,
     scene_cache: SkiaCache,
     animation_time: Option<Instant>,
+    /// When the tab was hidden. Animations and transitions are paused until it is shown again.
+    hidden_since: Option<Instant>,
     channel: IpcChannel,
     tab_id: String,
     shared_surface: Option<SharedSurface>,
     surface_generation: u32,
     shell_receiver: UnboundedReceiver<ShellProviderMessage>,
+    /// Log events waiting to be forwarded to the browser process
+    log_receiver: UnboundedReceiver<LogRecord>,
     nav_receiver: UnboundedReceiver<NavigationProviderMessage>,
     redraw_request: AtomicBool,
+    /// Times frames against the browser's vsync
+    frame_scheduler: FrameScheduler,
+    /// When the pending frame is to be painted
+    frame_due: Option<Instant>,
+    navigation_id: u64,
+    /// Image memory last sent in `TabToParentMessage::MemoryUsage`
+    reported_image_memory: usize,
+    /// The current document's meta or header refresh, waiting for its delay
+    scheduled_refresh: Option<ScheduledRefresh>,
+}
+
+/// A declarative refresh due at `at`. It belongs to the document that declared it and the
+/// navigation that loaded that document, so navigating anywhere before then cancels it.
+struct ScheduledRefresh {
+    at: Instant,
+    url: Url,
+    dom_id: usize,
     navigation_id: u64,
 }
 
@@ -66,8 +108,9 @@ enum HeadlessRenderer {
 }
 
 struct HeadlessGlRenderer {
-    _gl_surface: GlutinSurface<PbufferSurface>,
-    _gl_context: PossiblyCurrentContext,
+    gl_surface: GlutinSurface<PbufferSurface>,
+    gl_context: PossiblyCurrentContext,
+    gl_config: Config,
     gr_context: DirectContext,
     surface: Surface,
     fb_info: FramebufferInfo,
@@ -171,7 +214,7 @@ impl SoftwareRenderer {
             (width as i32, height as i32),
             skia_safe::ColorType::RGBA8888,
             skia_safe::AlphaType::Opaque,
-            None,
+            Some(color_management::display_color_space()),
         );
 
         let mut surface = skia_safe::surfaces::raster(&image_info, None, None)
@@ -269,17 +312,12 @@ impl HeadlessGlRenderer {
             gl_config.stencil_size() as usize,
         )?;
 
-        let readback = match AsyncReadback::new((width * height * 4) as usize) {
-            Ok(async_readback) => ReadbackPipeline::Async(async_readback),
-            Err(e) => {
-                eprintln!("[readback] async path unavailable, using sync mode: {e}");
-                ReadbackPipeline::Sync
-            }
-        };
+        let readback = create_readback(width, height);
 
         Ok(Self {
-            _gl_surface: gl_surface,
-            _gl_context: gl_context,
+            gl_surface,
+            gl_context,
+            gl_config,
             gr_context,
             surface,
             fb_info,
@@ -288,6 +326,44 @@ impl HeadlessGlRenderer {
         })
     }
 
+    /// Resize by moving the GL context onto a pbuffer of the new size. Keeping the context keeps
+    /// the page's WebGL contexts, which live in it.
+    fn resize(&mut self, width: u32, height: u32) -> io::Result<()> {
+        let width = width.max(1);
+        let height = height.max(1);
+        let attrs = SurfaceAttributesBuilder::<PbufferSurface>::new().build(
+            NonZeroU32::new(width).ok_or_else(|| io::Error::other("Invalid pbuffer width"))?,
+            NonZeroU32::new(height).ok_or_else(|| io::Error::other("Invalid pbuffer height"))?,
+        );
+        let gl_surface = unsafe {
+            self.gl_config
+                .display()
+                .create_pbuffer_surface(&self.gl_config, &attrs)
+                .map_err(io_other)?
+        };
+        self.gl_context.make_current(&gl_surface).map_err(io_other)?;
+        self.gl_surface = gl_surface;
+        self.gr_context.reset(None);
+
+        let mut fboid: GLint = 0;
+        unsafe { gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut fboid) };
+        self.fb_info = FramebufferInfo {
+            fboid: fboid.try_into().map_err(io_other)?,
+            format: Format::RGBA8.into(),
+            ..Default::default()
+        };
+        self.surface = create_skia_gl_surface(
+            width,
+            height,
+            self.fb_info,
+            &mut self.gr_context,
+            self.gl_config.num_samples() as usize,
+            self.gl_config.stencil_size() as usize,
+        )?;
+        self.readback = create_readback(width, height);
+        Ok(())
+    }
+
     fn readback_into_shmem(&mut self, dst: &mut [u8], width: u32, height: u32) -> io::Result<()> {
         self.readback_stats.total_frames = self.readback_stats.total_frames.saturating_add(1);
         match &mut self.readback {
@@ -513,7 +589,8 @@ fn create_skia_gl_surface(
         &backend_render_target,
         gpu::SurfaceOrigin::BottomLeft,
         ColorType::RGBA8888,
-        None,
+        // Paint in the display's color space, which the browser composites the frame in as is
+        Some(color_management::display_color_space()),
         None,
     )
     .ok_or_else(|| io::Error::other("Failed to wrap backend render target"))
@@ -546,6 +623,16 @@ fn sync_readback(fboid: u32, dst: &mut [u8], width: u32, height: u32) -> io::Res
     Ok(())
 }
 
+fn create_readback(width: u32, height: u32) -> ReadbackPipeline {
+    match AsyncReadback::new((width * height * 4) as usize) {
+        Ok(async_readback) => ReadbackPipeline::Async(async_readback),
+        Err(e) => {
+            warn!("[readback] async path unavailable, using sync mode: {e}");
+            ReadbackPipeline::Sync
+        }
+    }
+}
+
 /// Create a headless renderer, attempting GPU rendering first and falling back to software if needed
 fn create_headless_renderer(width: u32, height: u32) -> io::Result<HeadlessRenderer> {
     // Try GPU rendering first
@@ -590,6 +677,7 @@ fn fetch_binary(url: &str, user_agent: &str) -> io::Result<Vec<u8>> {
     req_headers
         .append("Accept: image/avif,image/webp,image/apng,image/*,*/*;q=0.8")
         .map_err(io_other)?;
+    req_headers.append(&languages::accept_language_header()).map_err(io_other)?;
     easy.http_headers(req_headers).map_err(io_other)?;
 
     {
@@ -606,7 +694,7 @@ fn fetch_binary(url: &str, user_agent: &str) -> io::Result<Vec<u8>> {
     let response_code = easy.response_code().map_err(io_other)?;
     if response_code >= 400 || data.is_empty() {
         return Err(io::Error::other(format!(
-            "favicon fetch failed with status {}",
+            "fetch failed with status {}",
             response_code
         )));
     }
@@ -642,8 +730,9 @@ fn fetch_favicon_for_page(page_url: &str, user_agent: &str) -> Option<Vec<u8>> {
 
 impl TabProcess {
     /// Create a new tab process and connect to the parent
-    pub fn new(tab_id: String, server_name: String) -> io::Result<Self> {
+    pub fn new(tab_id: String, server_name: String, log_receiver: UnboundedReceiver<LogRecord>) -> io::Result<Self> {
         let channel = connect(&server_name)?;
+        channel.install_main_loop_waker();
 
         // Create an unbounded channel for shell provider messages which can be sent from any thread
         let (shell_tx, shell_rx) = unbounded_channel::<ShellProviderMessage>();
@@ -672,18 +761,47 @@ impl TabProcess {
             *agent_ref.borrow_mut() = Some(engine.config.user_agent.clone());
         });
 
+        // alert(), confirm() and prompt() block the script that called them until the browser
+        // answers. Other parent messages wait in the queue meanwhile.
+        let dialog_channel = channel.clone();
+        let next_dialog_id = Cell::new(0u64);
+        js::set_dialog_callback(move |origin, dialog| {
+            let request_id = next_dialog_id.get();
+            next_dialog_id.set(request_id.wrapping_add(1));
+            let message = TabToParentMessage::Dialog { request_id, origin: origin.to_string(), dialog };
+            dialog_channel.send(&message).ok()?;
+            dialog_channel.wait_for_dialog_answer(request_id)
+        });
+
+        // Saved passwords never reach this process except on the page the browser builds
+        let passwords_channel = channel.clone();
+        let next_page_id = Cell::new(0u64);
+        passwords::set_management_page_source(move |url| {
+            let request_id = next_page_id.get();
+            next_page_id.set(request_id.wrapping_add(1));
+            let message = TabToParentMessage::PasswordsPageRequest { request_id, url: url.to_string() };
+            passwords_channel.send(&message).ok()?;
+            passwords_channel.wait_for_passwords_page(request_id)
+        });
+
         Ok(Self {
             engine,
             scene_cache: SkiaCache::default(),
             animation_time: None,
+            hidden_since: None,
             channel,
             tab_id,
             shared_surface: None,
             surface_generation: 0,
             shell_receiver: shell_rx,
             nav_receiver: nav_rx,
+            log_receiver,
             redraw_request: AtomicBool::new(false),
+            frame_scheduler: FrameScheduler::default(),
+            frame_due: None,
             navigation_id: 0,
+            reported_image_memory: 0,
+            scheduled_refresh: None,
         })
     }
 
@@ -702,10 +820,13 @@ impl TabProcess {
         let width = width.max(1);
         let height = height.max(1);
 
-        // Drop the old shared memory surface first to avoid conflicts
+        // Drop the old shared memory surface first to avoid conflicts. A GPU renderer is kept and
+        // resized, so WebGL contexts survive the window being resized.
+        let mut old_renderer = None;
         if let Some(old_surface) = self.shared_surface.take() {
             // Explicitly drop the old shmem to release the OS resource
             drop(old_surface.shmem);
+            old_renderer = Some(old_surface.renderer);
         }
 
         // Increment generation counter for unique ID
@@ -722,7 +843,21 @@ impl TabProcess {
             .create()
             .map_err(io_other)?;
 
-        let renderer = create_headless_renderer(width, height)?;
+        let renderer = match old_renderer {
+            Some(HeadlessRenderer::Gpu(mut gpu)) if gpu.resize(width, height).is_ok() => {
+                webgl::invalidate_state();
+                HeadlessRenderer::Gpu(gpu)
+            }
+            _ => {
+                let renderer = create_headless_renderer(width, height)?;
+                if matches!(renderer, HeadlessRenderer::Gpu(_)) {
+                    webgl::gl_context_created();
+                } else {
+                    webgl::gl_context_unavailable();
+                }
+                renderer
+            }
+        };
 
         self.shared_surface = Some(SharedSurface {
             shmem,
@@ -740,11 +875,34 @@ impl TabProcess {
         // Send ready message
         self.channel.send(&TabToParentMessage::Ready)?;
 
+        // Tabs may be started ahead of time, so get the slow setup out of the way while the
+        // parent has nothing for us yet
+        if self.engine.dom.is_none() {
+            self.engine.warm_up().await;
+        }
+
         loop {
+            while let Ok(record) = self.log_receiver.try_recv() {
+                let _ = self.channel.send(&TabToParentMessage::Log(record));
+            }
             match self.shell_receiver.try_recv() {
+                Ok(ShellProviderMessage::RequestPermission { request_id, kind }) => {
+                    self.send(TabToParentMessage::PermissionRequest { request_id, kind });
+                }
+                Ok(ShellProviderMessage::MixedContentChanged) => {
+                    self.send_security_state();
+                }
+                Ok(ShellProviderMessage::AudioStateChanged { audible }) => {
+                    self.send(TabToParentMessage::AudioStateChanged { audible });
+                }
+                Ok(ShellProviderMessage::SetWindowTitle(title)) => {
+                    // Scripts retitle pages after load, so the tab strip follows every change
+                    self.engine.set_page_title(title.clone());
+                    self.send(TabToParentMessage::TitleChanged(title));
+                }
                 Ok(msg) => {
                     let _ = self.handle_shell_provider_message(&msg).await;
-                    let _ = self.channel.send(&TabToParentMessage::ShellProvider(msg));
+                    self.send(TabToParentMessage::ShellProvider(msg));
                 }
                 Err(tokio::sync::mpsc::error::TryRecvError::Empty) => {},
                 Err(tokio::sync::mpsc::error::TryRecvError::Disconnected) => {},
@@ -757,9 +915,10 @@ impl TabProcess {
                                 self.render_frame()?;
                             }
                         }
-                        NavigationProviderMessage::NavigateToInNewTab(options) => {
+                        NavigationProviderMessage::NavigateToInNewTab { options, opener } => {
                             let url = options.url.as_str().to_string();
-                            let _ = self.channel.send(&TabToParentMessage::NavigateRequestInNewTab(url));
+                            let opener = opener.then(|| self.dom().map(|dom| dom.url.as_str().to_string())).flatten();
+                            self.send(TabToParentMessage::NavigateRequestInNewTab { url, opener });
                         }
                         NavigationProviderMessage::NavigateTo(options) => {
                             if self.engine.dom.is_none() {
@@ -770,23 +929,46 @@ impl TabProcess {
                             self.navigation_id = self.navigation_id.wrapping_add(1);
                             let navigation_id = self.navigation_id;
 
+                            let mut request = options.into_request();
+                            if self.engine.is_fragment_navigation(&request) {
+                                let url = request.url.to_string();
+                                self.engine.navigate_to_fragment(request);
+                                let title = self.engine.page_title().to_string();
+                                self.send(TabToParentMessage::NavigationCompleted { url, title });
+                                self.send_history();
+                                self.render_frame()?;
+                                continue;
+                            }
+
+                            // The parent has this page loading in a hidden tab already
+                            let prerendered = self.dom().and_then(|dom| dom.prerender_url.as_ref()) == Some(&request.url);
+                            if prerendered && request.method.as_str() == "GET" {
+                                self.send(TabToParentMessage::ActivatePrerender(request.url.to_string()));
+                                continue;
+                            }
+
                             let nav_provider = self.engine.navigation_provider.clone();
-                            let _ = self.channel.send(&TabToParentMessage::LoadingStateChanged(true));
-                            let url = options.url.as_str().to_string();
-                            let _ = self.channel.send(&TabToParentMessage::NavigationStarted(url.clone()));
-                            let _ = self.channel.send(&TabToParentMessage::FaviconUpdated(None));
-                            let request = options.into_request();
+                            self.send(TabToParentMessage::LoadingStateChanged(true));
+                            let url = request.url.as_str().to_string();
+                            self.send(TabToParentMessage::NavigationStarted(url.clone()));
+                            self.send_pending_navigation(&request, false, None);
+                            self.send(TabToParentMessage::FaviconUpdated(None));
+                            let http_fallback = self.apply_https_first(&mut request);
                             let history_request = request.clone();
-                            self.dom().unwrap().net_provider.fetch_with_callback(
+                            self.dom().unwrap().net_provider.fetch_navigation_with_callback(
                                 request,
                                 Box::new(move |result| {
-                                    let (url, bytes) = match result {
-                                        Ok(res) => res,
-                                        Err(_) => {
-                                            (url, include_str!("../assets/404.html").into())
+                                    let (url, bytes) = match (result, http_fallback) {
+                                        (Ok(res), _) => res,
+                                        (Err(_), Some(http_url)) => {
+                                            (http_url.to_string(), https_first::interstitial(&http_url).into())
+                                        }
+                                        (Err(e), None) => {
+                                            let page = e.error_page(&url);
+                                            (url, page.into())
                                         }
                                     };
-                                    let contents = std::str::from_utf8(&bytes).unwrap().to_string();
+                                    let contents = networking::decode_text(&bytes).into_owned();
                                     let _ = nav_provider.sender.send(NavigationProviderMessage::Navigate {
                                         navigation_id,
                                         url,
@@ -795,6 +977,7 @@ impl TabProcess {
                                         is_md: false,
                                         retain_scroll_position: false,
                                     });
+                                    ipc::wake_main_loop();
                                 })
                             );
                         }
@@ -813,18 +996,22 @@ impl TabProcess {
                             match self.engine.navigate(&url, contents, true, true, Some(request)).await {
                                 Ok(_) => {
                                     let title = self.engine.page_title().to_string();
-                                    let _ = self.channel.send(&TabToParentMessage::NavigationCompleted {
+                                    self.send(TabToParentMessage::NavigationCompleted {
                                         url: url.clone(),
                                         title: title.clone(),
                                     });
-                                    let _ = self.channel.send(&TabToParentMessage::TitleChanged(title));
+                                    self.send(TabToParentMessage::TitleChanged(title));
                                     self.send_current_favicon();
-                                    let _ = self.channel.send(&TabToParentMessage::LoadingStateChanged(false));
+                                    self.send_web_app_manifest();
+                                    self.send_security_state();
+                                    self.send_auth_challenge();
+                                    self.send_history();
+                                    self.send(TabToParentMessage::LoadingStateChanged(false));
                                     self.render_frame()?;
                                 }
                                 Err(e) => {
-                                    let _ = self.channel.send(&TabToParentMessage::NavigationFailed(e.to_string()));
-                                    let _ = self.channel.send(&TabToParentMessage::LoadingStateChanged(false));
+                                    self.send(TabToParentMessage::NavigationFailed(e.to_string()));
+                                    self.send(TabToParentMessage::LoadingStateChanged(false));
                                 }
                             }
                         }
@@ -837,28 +1024,35 @@ impl TabProcess {
                             let navigation_id = self.navigation_id;
 
                             let nav_provider = self.engine.navigation_provider.clone();
-                            let _ = self.channel.send(&TabToParentMessage::LoadingStateChanged(true));
+                            self.send(TabToParentMessage::LoadingStateChanged(true));
                             let url = options.url.as_str().to_string();
-                            let _ = self.channel.send(&TabToParentMessage::NavigationStarted(url.clone()));
-                            let _ = self.channel.send(&TabToParentMessage::FaviconUpdated(None));
-                            let request = options.into_request();
+                            self.send(TabToParentMessage::NavigationStarted(url.clone()));
+                            let mut request = options.into_request();
+                            self.send_pending_navigation(&request, true, None);
+                            self.send(TabToParentMessage::FaviconUpdated(None));
+                            let http_fallback = self.apply_https_first(&mut request);
                             let history_request = request.clone();
-                            self.dom().unwrap().net_provider.fetch_with_callback(
+                            self.dom().unwrap().net_provider.fetch_navigation_with_callback(
                                 request,
                                 Box::new(move |result| {
-                                    let (url, bytes) = match result {
-                                        Ok(res) => res,
-                                        Err(_) => {
-                                            (url, include_str!("../assets/404.html").into())
+                                    let (url, bytes) = match (result, http_fallback) {
+                                        (Ok(res), _) => res,
+                                        (Err(_), Some(http_url)) => {
+                                            (http_url.to_string(), https_first::interstitial(&http_url).into())
+                                        }
+                                        (Err(e), None) => {
+                                            let page = e.error_page(&url);
+                                            (url, page.into())
                                         }
                                     };
-                                    let contents = std::str::from_utf8(&bytes).unwrap().to_string();
+                                    let contents = networking::decode_text(&bytes).into_owned();
                                     let _ = nav_provider.sender.send(NavigationProviderMessage::NavigateReplaceCommit {
                                         navigation_id,
                                         url,
                                         contents,
                                         request: history_request,
                                     });
+                                    ipc::wake_main_loop();
                                 })
                             );
                         }
@@ -877,18 +1071,22 @@ impl TabProcess {
                                 Ok(_) => {
                                     self.engine.replace_current_history_entry(request);
                                     let title = self.engine.page_title().to_string();
-                                    let _ = self.channel.send(&TabToParentMessage::NavigationCompleted {
+                                    self.send(TabToParentMessage::NavigationCompleted {
                                         url: url.clone(),
                                         title: title.clone(),
                                     });
-                                    let _ = self.channel.send(&TabToParentMessage::TitleChanged(title));
+                                    self.send(TabToParentMessage::TitleChanged(title));
                                     self.send_current_favicon();
-                                    let _ = self.channel.send(&TabToParentMessage::LoadingStateChanged(false));
+                                    self.send_web_app_manifest();
+                                    self.send_security_state();
+                                    self.send_auth_challenge();
+                                    self.send_history();
+                                    self.send(TabToParentMessage::LoadingStateChanged(false));
                                     self.render_frame()?;
                                 }
                                 Err(e) => {
-                                    let _ = self.channel.send(&TabToParentMessage::NavigationFailed(e.to_string()));
-                                    let _ = self.channel.send(&TabToParentMessage::LoadingStateChanged(false));
+                                    self.send(TabToParentMessage::NavigationFailed(e.to_string()));
+                                    self.send(TabToParentMessage::LoadingStateChanged(false));
                                 }
                             }
                         }
@@ -898,6 +1096,13 @@ impl TabProcess {
                 Err(tokio::sync::mpsc::error::TryRecvError::Disconnected) => {},
             }
 
+            self.run_scheduled_refresh();
+            if let Some(dom) = self.dom_mut() {
+                dom.prefetch_hovered_link();
+                dom.end_font_block_periods();
+                crate::js::bindings::registry::release_collected_nodes(dom);
+            }
+
             // Process all pending messages from parent (non-blocking)
             let mut has_messages = true;
             let mut should_render_after_messages = false;
@@ -907,7 +1112,7 @@ impl TabProcess {
                     Some(msg) => {
                         let (should_render, should_continue) = self.handle_message(msg).await?;
                         if !should_continue {
-                            println!("Shutting down");
+                            info!("Shutting down");
                             return Ok(()); // Shutdown requested
                         }
                         if should_render {
@@ -919,17 +1124,93 @@ impl TabProcess {
                     }
                 }
             }
-            if self.redraw_request.load(Ordering::Relaxed) {
-                should_render_after_messages = true;
-                self.redraw_request.store(false, Ordering::Relaxed);
+            // Hidden tabs produce no frames; a pending redraw stays requested until the tab is shown
+            if !self.engine.is_visible() {
+                self.engine.tick_background();
+                let wait = self.engine.time_until_next_timer()
+                    .map_or(BACKGROUND_POLL_INTERVAL, |next| next.min(BACKGROUND_POLL_INTERVAL));
+                self.channel.wait_for_message(Some(wait))?;
+                continue;
             }
 
+            // Timers and script tasks that came due between frames run without painting; a frame
+            // follows only when they changed the page
+            if !should_render_after_messages && self.engine.dom.is_some() {
+                self.engine.tick_background();
+            }
+            if self.dom().is_some_and(Dom::needs_frame) {
+                self.redraw_request.store(true, Ordering::Relaxed);
+            }
+
+            // Every frame, whether for input or the next step of an animation, is painted when the
+            // scheduler has it land just before the browser's next composite
             if should_render_after_messages {
-                self.render_frame()?;
+                self.redraw_request.store(true, Ordering::Relaxed);
+            }
+            if self.redraw_request.load(Ordering::Relaxed) {
+                let now = Instant::now();
+                let due = *self.frame_due.get_or_insert_with(|| self.frame_scheduler.frame_start(now));
+                if now >= due {
+                    self.redraw_request.store(false, Ordering::Relaxed);
+                    self.render_frame()?;
+                    continue;
+                }
             }
 
-            // Small sleep to prevent CPU spinning
-            //tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+            // Messages the providers sent while this iteration ran
+            if !self.shell_receiver.is_empty() || !self.nav_receiver.is_empty() {
+                continue;
+            }
+            self.channel.wait_for_message(self.idle_timeout())?;
+        }
+    }
+
+    /// How long an idle visible tab may sleep: until its next frame when one is pending, its next
+    /// timer, or something else it scheduled. `None` when only a message can give it work.
+    fn idle_timeout(&self) -> Option<Duration> {
+        let now = Instant::now();
+        let deadlines = [
+            self.frame_due,
+            self.engine.time_until_next_timer().map(|wait| now + wait),
+            self.scheduled_refresh.as_ref().map(|refresh| refresh.at),
+            self.dom().and_then(Dom::hover_prefetch_due).map(|due| due.max(now)),
+            self.dom().and_then(Dom::font_block_period_due).map(|due| due.max(now)),
+        ];
+        let deadline = deadlines.into_iter().flatten().min()?;
+        Some(deadline.saturating_duration_since(now))
+    }
+
+    /// Schedule the refresh the document declared, and navigate once it is due. Refreshes replace
+    /// the current history entry, so going back doesn't land on the page that redirected.
+    fn run_scheduled_refresh(&mut self) {
+        let navigation_id = self.navigation_id;
+        if let Some(dom) = self.engine.dom.as_mut()
+            && let Some((delay, url)) = dom.pending_refresh.take()
+        {
+            // Delays too long to represent never come due
+            self.scheduled_refresh = Instant::now()
+                .checked_add(delay)
+                .map(|at| ScheduledRefresh { at, url, dom_id: dom.id(), navigation_id });
+        }
+
+        let Some(refresh) = &self.scheduled_refresh else {
+            return;
+        };
+        let still_current = self.dom().is_some_and(|dom| dom.id() == refresh.dom_id) && refresh.navigation_id == self.navigation_id;
+        if !still_current {
+            self.scheduled_refresh = None;
+            return;
+        }
+        if Instant::now() < refresh.at {
+            return;
+        }
+        if let Some(refresh) = self.scheduled_refresh.take() {
+            info!("Refreshing to {}", refresh.url);
+            self.engine.navigation_provider.navigate_replace(NavigationOptions::new(
+                refresh.url,
+                String::from("text/plain"),
+                refresh.dom_id,
+            ));
         }
     }
 
@@ -947,64 +1228,212 @@ impl TabProcess {
             return Ok(false);
         }
 
-        let _ = self.channel.send(&TabToParentMessage::NavigationStarted(url));
-        let _ = self.channel.send(&TabToParentMessage::FaviconUpdated(None));
+        self.send(TabToParentMessage::NavigationStarted(url));
+        if let Some(request) = self.engine.current_history_request() {
+            let scroll = self.engine.scroll_position();
+            self.send_pending_navigation(&request, true, Some((scroll.x, scroll.y)));
+        }
+        self.send(TabToParentMessage::FaviconUpdated(None));
         self.engine.set_loading_state(true);
 
         match self.engine.reload_current_entry().await {
             Ok(_) => {
                 let title = self.engine.page_title().to_string();
                 let url = self.engine.current_url().to_string();
-                let _ = self.channel.send(&TabToParentMessage::NavigationCompleted { url, title });
+                self.send(TabToParentMessage::NavigationCompleted { url, title });
                 self.send_current_favicon();
-                let _ = self.channel.send(&TabToParentMessage::LoadingStateChanged(false));
+                self.send_web_app_manifest();
+                self.send_security_state();
+                self.send_auth_challenge();
+                self.send_history();
+                self.send(TabToParentMessage::LoadingStateChanged(false));
                 Ok(true)
             }
             Err(e) => {
-                let _ = self.channel.send(&TabToParentMessage::NavigationFailed(e.to_string()));
-                let _ = self.channel.send(&TabToParentMessage::LoadingStateChanged(false));
+                self.send(TabToParentMessage::NavigationFailed(e.to_string()));
+                self.send(TabToParentMessage::LoadingStateChanged(false));
                 Ok(false)
             }
         }
     }
 
+    /// Tell the parent how to carry on `request` if this process dies before it completes
+    fn send_pending_navigation(&self, request: &Request, replace: bool, scroll: Option<(f64, f64)>) {
+        if let Some(navigation) = PendingNavigation::from_request(request, replace, scroll) {
+            self.send(TabToParentMessage::PendingNavigation(navigation));
+        }
+    }
+
+    /// Fetch the document for a top-level navigation to `url`, loading HSTS hosts over https://
+    /// and trying https:// first when HTTPS-first applies. Returns the URL that was loaded along
+    /// with its contents.
+    fn fetch_document(&self, url: &str) -> (String, String) {
+        let config = &self.engine.config;
+        let mut request = match Url::parse(url) {
+            Ok(parsed) => Request::get(parsed),
+            Err(_) => return (url.to_string(), include_str!("../assets/404.html").to_string()),
+        };
+        // HSTS hosts have no insecure fallback
+        if let Some(secure_url) = hsts::upgrade(&request.url) {
+            let contents = networking::fetch(secure_url.as_str(), &config.user_agent, config.block_ads, &config.request_policy).unwrap_or_else(|e| {
+                warn!("[navigate] networking::fetch failed for {secure_url}: {e}");
+                e.error_page(secure_url.as_str())
+            });
+            return (secure_url.to_string(), contents);
+        }
+        if let Some(http_url) = self.apply_https_first(&mut request) {
+            return match networking::fetch(request.url.as_str(), &config.user_agent, config.block_ads, &config.request_policy) {
+                Ok(contents) => (request.url.to_string(), contents),
+                Err(e) => {
+                    warn!("[navigate] HTTPS-first: {} failed, offering {http_url} instead: {e}", request.url);
+                    (http_url.to_string(), https_first::interstitial(&http_url))
+                }
+            };
+        }
+
+        let contents = networking::fetch(url, &config.user_agent, config.block_ads, &config.request_policy).unwrap_or_else(|e| {
+            warn!("[navigate] networking::fetch failed for {url}: {e}");
+            e.error_page(url)
+        });
+        (url.to_string(), contents)
+    }
+
+    /// Point `request` at https:// when HSTS or HTTPS-first applies to it, returning the http://
+    /// URL to offer if the secure attempt fails. HSTS hosts never get that fallback.
+    fn apply_https_first(&self, request: &mut Request) -> Option<Url> {
+        if let Some(secure_url) = hsts::upgrade(&request.url) {
+            request.url = secure_url;
+            return None;
+        }
+        if !self.engine.config.https_first {
+            return None;
+        }
+        let secure_url = https_first::navigation_target(&request.url)?;
+        Some(std::mem::replace(&mut request.url, secure_url))
+    }
+
+    /// Send a message to the browser process. If that fails the browser is gone, and the run loop
+    /// stops once the receiver thread reports the disconnect.
+    fn send(&self, message: TabToParentMessage) {
+        if let Err(e) = self.channel.send(&message) {
+            warn!("Failed to send message to the browser process: {}", e);
+        }
+    }
+
     fn send_current_favicon(&self) {
         let url = self.engine.current_url().to_string();
         let favicon = fetch_favicon_for_page(&url, &self.engine.config.user_agent);
-        let _ = self.channel.send(&TabToParentMessage::FaviconUpdated(favicon));
+        self.send(TabToParentMessage::FaviconUpdated(favicon));
+    }
+
+    /// Let the parent know about the web app manifest the current page links to. When the app can
+    /// be installed and isn't yet, the page gets a beforeinstallprompt event.
+    fn send_web_app_manifest(&self) {
+        let manifest = self.find_web_app_manifest();
+        let installable = manifest.as_ref().filter(|manifest| manifest.is_installable() && !web_apps::is_installed(manifest));
+        let icon = installable
+            .and_then(|manifest| manifest.best_icon())
+            .and_then(|icon| fetch_binary(&icon.src, &self.engine.config.user_agent).ok());
+        if installable.is_some() {
+            self.engine.js_provider.execute_script(
+                "if (typeof __stokesBeforeInstallPrompt === 'function') { __stokesBeforeInstallPrompt(); }".to_string(),
+            );
+        }
+        self.send(TabToParentMessage::WebAppManifest { manifest, icon });
+    }
+
+    /// Fetch and parse the manifest named by the page's `<link rel="manifest">`
+    fn find_web_app_manifest(&self) -> Option<WebAppManifest> {
+        let dom = self.dom()?;
+        let href = dom
+            .query_selector("link")
+            .into_iter()
+            .find(|node| {
+                node.attr(local_name!("rel"))
+                    .is_some_and(|rel| rel.split_ascii_whitespace().any(|token| token.eq_ignore_ascii_case("manifest")))
+            })
+            .and_then(|node| node.attr(local_name!("href")))?;
+        let document_url = Url::parse(self.engine.current_url()).ok()?;
+        let manifest_url = document_url.join(href.trim()).ok()?;
+        if !matches!(manifest_url.scheme(), "http" | "https") {
+            return None;
+        }
+        let bytes = fetch_binary(manifest_url.as_str(), &self.engine.config.user_agent)
+            .inspect_err(|e| debug!("Failed to fetch web app manifest {}: {}", manifest_url, e))
+            .ok()?;
+        web_apps::parse_manifest(&String::from_utf8_lossy(&bytes), &manifest_url, &document_url)
+    }
+
+    /// Let the parent know how the current page was delivered, for the address bar's security icon
+    fn send_security_state(&self) {
+        let mixed_content = self.dom().map(|dom| dom.net_provider.mixed_content_status()).unwrap_or_default();
+        let state = SecurityState::for_page(self.engine.current_url(), mixed_content);
+        self.send(TabToParentMessage::SecurityState(state));
+    }
+
+    /// Ask the parent for credentials when the current page was answered with an authentication
+    /// challenge
+    fn send_auth_challenge(&self) {
+        let url = self.engine.current_url();
+        if let Some(challenge) = http_auth::take_challenge(url) {
+            let retry = http_auth::has_credentials(url);
+            self.send(TabToParentMessage::AuthRequired { challenge, retry });
+        }
+    }
+
+    /// Let the parent know about the current history stack so closed tabs can be restored
+    fn send_history(&self) {
+        let (entries, index) = self.engine.history_entries();
+        if let Some(index) = index {
+            self.send(TabToParentMessage::HistoryChanged { entries, index });
+        }
     }
 
     /// Handle a message from the parent process
+    #[tracing::instrument(name = "handle_message", level = "debug", skip_all)]
     async fn handle_message(&mut self, message: ParentToTabMessage) -> io::Result<(bool, bool)> {
         let mut should_render: bool = false;
         match message {
             ParentToTabMessage::Navigate(url) => {
                 // Invalidate any in-flight async navigation callback.
                 self.navigation_id = self.navigation_id.wrapping_add(1);
-                let _ = self.channel.send(&TabToParentMessage::NavigationStarted(url.clone()));
-                let _ = self.channel.send(&TabToParentMessage::FaviconUpdated(None));
+                if let Some(request) = Url::parse(&url).ok().map(Request::get)
+                    && self.engine.is_fragment_navigation(&request)
+                {
+                    self.engine.navigate_to_fragment(request);
+                    let title = self.engine.page_title().to_string();
+                    self.send(TabToParentMessage::NavigationCompleted { url, title });
+                    self.send_history();
+                    return Ok((true, true));
+                }
+                self.send(TabToParentMessage::NavigationStarted(url.clone()));
+                if let Ok(parsed) = Url::parse(&url) {
+                    self.send_pending_navigation(&Request::get(parsed), false, None);
+                }
+                self.send(TabToParentMessage::FaviconUpdated(None));
                 self.engine.set_loading_state(true);
 
-                let contents = networking::fetch(&url, &self.engine.config.user_agent, self.engine.config.block_ads).unwrap_or_else(|e| {
-                    eprintln!("[navigate] networking::fetch failed for {url}: {e}");
-                    include_str!("../assets/404.html").to_string()
-                });
+                let (url, contents) = self.fetch_document(&url);
                 let history_request = Url::parse(&url).ok().map(Request::get);
                 match self.engine.navigate(&url, contents, true, true, history_request).await {
                     Ok(_) => {
                         let title = self.engine.page_title().to_string();
-                        let _ = self.channel.send(&TabToParentMessage::NavigationCompleted {
+                        self.send(TabToParentMessage::NavigationCompleted {
                             url: url.clone(),
                             title: title.clone(),
                         });
-                        let _ = self.channel.send(&TabToParentMessage::TitleChanged(title));
+                        self.send(TabToParentMessage::TitleChanged(title));
                         self.send_current_favicon();
-                        let _ = self.channel.send(&TabToParentMessage::LoadingStateChanged(false));
+                        self.send_web_app_manifest();
+                        self.send_security_state();
+                        self.send_auth_challenge();
+                        self.send_history();
+                        self.send(TabToParentMessage::LoadingStateChanged(false));
                         should_render = true;
                     }
                     Err(e) => {
-                        let _ = self.channel.send(&TabToParentMessage::NavigationFailed(e.to_string()));
-                        let _ = self.channel.send(&TabToParentMessage::LoadingStateChanged(false));
+                        self.send(TabToParentMessage::NavigationFailed(e.to_string()));
+                        self.send(TabToParentMessage::LoadingStateChanged(false));
                     }
                 }
             }
@@ -1018,21 +1447,25 @@ impl TabProcess {
                 self.navigation_id = self.navigation_id.wrapping_add(1);
                 if self.engine.can_go_back() {
                     let url = self.engine.current_url().to_string();
-                    let _ = self.channel.send(&TabToParentMessage::NavigationStarted(url.clone()));
-                    let _ = self.channel.send(&TabToParentMessage::FaviconUpdated(None));
+                    self.send(TabToParentMessage::NavigationStarted(url.clone()));
+                    self.send(TabToParentMessage::FaviconUpdated(None));
                     self.engine.set_loading_state(true);
                     match self.engine.go_back().await {
                         Ok(_) => {
                             let title = self.engine.page_title().to_string();
                             let url = self.engine.current_url().to_string();
-                            let _ = self.channel.send(&TabToParentMessage::NavigationCompleted { url, title });
+                            self.send(TabToParentMessage::NavigationCompleted { url, title });
                             self.send_current_favicon();
-                            let _ = self.channel.send(&TabToParentMessage::LoadingStateChanged(false));
+                            self.send_web_app_manifest();
+                            self.send_security_state();
+                            self.send_auth_challenge();
+                            self.send_history();
+                            self.send(TabToParentMessage::LoadingStateChanged(false));
                             should_render = true;
                         }
                         Err(e) => {
-                            eprintln!("Go back failed: {}", e);
-                            let _ = self.channel.send(&TabToParentMessage::LoadingStateChanged(false));
+                            warn!("Go back failed: {}", e);
+                            self.send(TabToParentMessage::LoadingStateChanged(false));
                         }
                     }
                 }
@@ -1041,21 +1474,25 @@ impl TabProcess {
                 self.navigation_id = self.navigation_id.wrapping_add(1);
                 if self.engine.can_go_forward() {
                     let url = self.engine.current_url().to_string();
-                    let _ = self.channel.send(&TabToParentMessage::NavigationStarted(url.clone()));
-                    let _ = self.channel.send(&TabToParentMessage::FaviconUpdated(None));
+                    self.send(TabToParentMessage::NavigationStarted(url.clone()));
+                    self.send(TabToParentMessage::FaviconUpdated(None));
                     self.engine.set_loading_state(true);
                     match self.engine.go_forward().await {
                         Ok(_) => {
                             let title = self.engine.page_title().to_string();
                             let url = self.engine.current_url().to_string();
-                            let _ = self.channel.send(&TabToParentMessage::NavigationCompleted { url, title });
+                            self.send(TabToParentMessage::NavigationCompleted { url, title });
                             self.send_current_favicon();
-                            let _ = self.channel.send(&TabToParentMessage::LoadingStateChanged(false));
+                            self.send_web_app_manifest();
+                            self.send_security_state();
+                            self.send_auth_challenge();
+                            self.send_history();
+                            self.send(TabToParentMessage::LoadingStateChanged(false));
                             should_render = true;
                         }
                         Err(e) => {
-                            eprintln!("Go forward failed: {}", e);
-                            let _ = self.channel.send(&TabToParentMessage::LoadingStateChanged(false));
+                            warn!("Go forward failed: {}", e);
+                            self.send(TabToParentMessage::LoadingStateChanged(false));
                         }
                     }
                 }
@@ -1095,6 +1532,29 @@ impl TabProcess {
             ParentToTabMessage::RequestFrame => {
                 should_render = true;
             }
+            ParentToTabMessage::SetVisibility(visible) => {
+                if visible {
+                    // Resume animations where they stopped rather than jumping ahead
+                    if let (Some(hidden_since), Some(start)) = (self.hidden_since.take(), &mut self.animation_time) {
+                        *start += hidden_since.elapsed();
+                    }
+                    should_render = true;
+                } else {
+                    // The browser only sends timing to the shown tab; what it sent is stale by the
+                    // time this one is shown again
+                    self.frame_scheduler.clear_timing();
+                    if self.hidden_since.is_none() {
+                        self.hidden_since = Some(Instant::now());
+                    }
+                }
+                self.engine.set_visibility(visible);
+            }
+            ParentToTabMessage::SetMuted(muted) => {
+                if self.engine.is_muted() != muted {
+                    debug!("Tab {} {}", self.tab_id, if muted { "muted" } else { "unmuted" });
+                    self.engine.set_muted(muted);
+                }
+            }
             ParentToTabMessage::SetScaleFactor(scale) => {
                 self.engine.set_viewport(Viewport {
                     hidpi_scale: scale,
@@ -1108,13 +1568,249 @@ impl TabProcess {
                 });
                 should_render = true;
             }
+            ParentToTabMessage::SetMediaPreferences(preferences) => {
+                self.engine.set_media_preferences(preferences);
+                should_render = true;
+            }
+            ParentToTabMessage::SetLanguages(languages) => {
+                self.engine.set_languages(languages);
+            }
+            ParentToTabMessage::HttpAuthCredentials { origin, username, password } => {
+                http_auth::set_credentials(&origin, username, password);
+                self.navigation_id = self.navigation_id.wrapping_add(1);
+                if self.reload_current_page().await? {
+                    should_render = true;
+                }
+            }
+            ParentToTabMessage::DialogAnswer { request_id, .. } => {
+                // Answers are taken by the dialog waiting for them; this one's script already moved on
+                debug!("Ignoring answer to dialog {request_id}, which is no longer open");
+            }
+            ParentToTabMessage::PasswordsPage { request_id, .. } => {
+                debug!("Ignoring passwords page {request_id}, which is no longer being loaded");
+            }
+            ParentToTabMessage::Autofill(profile) => {
+                if let Some(dom) = self.dom_mut() {
+                    dom.autofill(&profile);
+                    should_render = true;
+                }
+            }
+            ParentToTabMessage::FillLogin { origin, username, password } => {
+                if let Some(dom) = self.dom_mut() {
+                    dom.fill_login(&origin, &username, &password);
+                    should_render = true;
+                }
+            }
+            ParentToTabMessage::SiteSettingsChanged => {
+                self.engine.reload_site_settings();
+                should_render = true;
+            }
+            ParentToTabMessage::PermissionsChanged => {
+                crate::js::bindings::permissions::reload_permissions();
+            }
+            ParentToTabMessage::ExitFullscreen => {
+                self.engine.js_provider.execute_script("if (typeof __stokesExitFullscreenFromShell === 'function') { __stokesExitFullscreenFromShell(); }".to_string());
+                should_render = true;
+            }
+            ParentToTabMessage::ExitPointerLock => {
+                self.engine.js_provider.execute_script("if (typeof __stokesExitPointerLockFromShell === 'function') { __stokesExitPointerLockFromShell(); }".to_string());
+                should_render = true;
+            }
+            ParentToTabMessage::Gamepads(gamepads) => {
+                let gamepads = serde_json::to_string(&gamepads).unwrap_or_else(|_| "[]".to_string());
+                self.engine.js_provider.execute_script(format!(
+                    "if (typeof __stokesUpdateGamepads === 'function') {{ __stokesUpdateGamepads({gamepads}); }}"
+                ));
+                should_render = true;
+            }
+            ParentToTabMessage::Preconnect(origin) => {
+                self.engine.preconnect(&origin);
+            }
+            ParentToTabMessage::SetVisitedLinks(filter) => {
+                self.engine.set_visited_links(filter);
+                should_render = true;
+            }
+            ParentToTabMessage::SetNetworkConditions(conditions) => {
+                networking::conditions::set(conditions);
+            }
+            ParentToTabMessage::FrameTiming(timing) => {
+                self.frame_scheduler.set_timing(timing);
+            }
+            ParentToTabMessage::SetVisualViewport(visual_viewport) => {
+                // The browser magnifies the frame it already has; the page only needs telling
+                self.engine.set_visual_viewport(visual_viewport);
+            }
+            ParentToTabMessage::SetOpener(url) => {
+                self.engine.set_opener(url);
+            }
+            ParentToTabMessage::PrependHistory(entries) => {
+                self.engine.prepend_history(&entries);
+                self.send_history();
+            }
+            ParentToTabMessage::RestoreSession { history, history_index, scroll } => {
+                let Some(url) = history.get(history_index).cloned() else {
+                    return Ok((false, true));
+                };
+                self.navigation_id = self.navigation_id.wrapping_add(1);
+                self.send(TabToParentMessage::NavigationStarted(url.clone()));
+                self.send(TabToParentMessage::FaviconUpdated(None));
+                self.engine.set_loading_state(true);
+
+                let config = &self.engine.config;
+                let contents = networking::fetch(&url, &config.user_agent, config.block_ads, &config.request_policy).unwrap_or_else(|e| {
+                    warn!("[restore] networking::fetch failed for {url}: {e}");
+                    e.error_page(&url)
+                });
+                match self.engine.navigate(&url, contents, true, false, None).await {
+                    Ok(_) => {
+                        self.engine.restore_history(&history, history_index);
+                        self.engine.set_scroll_position(scroll.0 as f32, scroll.1 as f32);
+                        let title = self.engine.page_title().to_string();
+                        self.send(TabToParentMessage::NavigationCompleted {
+                            url: url.clone(),
+                            title: title.clone(),
+                        });
+                        self.send(TabToParentMessage::TitleChanged(title));
+                        self.send_current_favicon();
+                        self.send_web_app_manifest();
+                        self.send_security_state();
+                        self.send_auth_challenge();
+                        self.send_history();
+                        let scroll = self.engine.scroll_position();
+                        self.send(TabToParentMessage::ShellProvider(ShellProviderMessage::ViewportScroll((scroll.x, scroll.y))));
+                        self.send(TabToParentMessage::LoadingStateChanged(false));
+                        should_render = true;
+                    }
+                    Err(e) => {
+                        self.send(TabToParentMessage::NavigationFailed(e.to_string()));
+                        self.send(TabToParentMessage::LoadingStateChanged(false));
+                    }
+                }
+            }
+            ParentToTabMessage::ResumeNavigation { history, history_index, navigation } => {
+                self.navigation_id = self.navigation_id.wrapping_add(1);
+                self.send(TabToParentMessage::NavigationStarted(navigation.url.clone()));
+                self.send(TabToParentMessage::PendingNavigation(navigation.clone()));
+                self.send(TabToParentMessage::FaviconUpdated(None));
+                self.engine.set_loading_state(true);
+
+                match self.engine.resume_navigation(&history, history_index, &navigation).await {
+                    Ok(_) => {
+                        let title = self.engine.page_title().to_string();
+                        self.send(TabToParentMessage::NavigationCompleted {
+                            url: self.engine.current_url().to_string(),
+                            title: title.clone(),
+                        });
+                        self.send(TabToParentMessage::TitleChanged(title));
+                        self.send_current_favicon();
+                        self.send_web_app_manifest();
+                        self.send_security_state();
+                        self.send_auth_challenge();
+                        self.send_history();
+                        let scroll = self.engine.scroll_position();
+                        self.send(TabToParentMessage::ShellProvider(ShellProviderMessage::ViewportScroll((scroll.x, scroll.y))));
+                        self.send(TabToParentMessage::LoadingStateChanged(false));
+                        should_render = true;
+                    }
+                    Err(e) => {
+                        self.send(TabToParentMessage::NavigationFailed(e.to_string()));
+                        self.send(TabToParentMessage::LoadingStateChanged(false));
+                    }
+                }
+            }
+            ParentToTabMessage::SavePage { path, complete } => {
+                let user_agent = self.engine.config.user_agent.clone();
+                let result = match self.dom() {
+                    Some(dom) => save_page(dom, Path::new(&path), complete, |url| fetch_binary(url, &user_agent)),
+                    None => Err(io::Error::other("no page is loaded")),
+                };
+                self.send(TabToParentMessage::PageSaved {
+                    path,
+                    error: result.err().map(|e| e.to_string()),
+                });
+            }
+            ParentToTabMessage::CaptureScreenshot { full_page } => {
+                let result = self.capture_screenshot(full_page).map_err(|e| e.to_string());
+                self.send(TabToParentMessage::ScreenshotSaved(result));
+            }
+            ParentToTabMessage::PermissionDecision { request_id, granted } => {
+                self.engine.js_provider.execute_script(format!(
+                    "if (typeof __stokesResolvePermission === 'function') {{ __stokesResolvePermission({request_id}, {granted}); }}"
+                ));
+            }
+            ParentToTabMessage::GeolocationPosition { request_id, result } => {
+                let data = match result {
+                    Ok(position) => serde_json::to_value(&position).unwrap_or_default(),
+                    Err(error) => serde_json::json!({ "error": error as u8 }),
+                };
+                self.engine.js_provider.execute_script(format!(
+                    "if (typeof __stokesDeliverPosition === 'function') {{ __stokesDeliverPosition({request_id}, {data}); }}"
+                ));
+            }
+            ParentToTabMessage::ExtensionMessage { request_id, message, sender } => {
+                self.engine.js_provider.call_extension(js::ExtensionCall::Dispatch { request_id, message, sender });
+            }
+            ParentToTabMessage::ExtensionResponse { request_id, response } => {
+                self.engine.js_provider.call_extension(js::ExtensionCall::Resolve { request_id, response });
+            }
+            ParentToTabMessage::AppInstallResult { accepted } => {
+                self.engine.js_provider.execute_script(format!(
+                    "if (typeof __stokesAppInstallResult === 'function') {{ __stokesAppInstallResult({accepted}); }}"
+                ));
+            }
+            ParentToTabMessage::Find { query, forward, highlight_all } => {
+                let (active, count) = match self.dom_mut() {
+                    Some(dom) => dom.find_in_page(&query, forward, highlight_all),
+                    None => (None, 0),
+                };
+                self.send(TabToParentMessage::FindResult { active, count });
+            }
+            ParentToTabMessage::SetFindHighlightAll(highlight_all) => {
+                if let Some(dom) = self.dom_mut() {
+                    dom.set_find_highlight_all(highlight_all);
+                }
+            }
+            ParentToTabMessage::StopFinding => {
+                if let Some(dom) = self.dom_mut() {
+                    dom.stop_finding();
+                }
+            }
+            ParentToTabMessage::ReportResources => {
+                let report = self.resource_report();
+                info!("Resources held by tab {}: {report}", self.tab_id);
+                self.send(TabToParentMessage::ResourceReport(report));
+            }
             ParentToTabMessage::Shutdown => {
+                self.teardown();
                 return Ok((false, false));
             }
         }
         Ok((should_render, true))
     }
 
+    /// What the tab holds: the engine's document, runtime and fetches, and the painter's cache
+    fn resource_report(&self) -> ResourceReport {
+        ResourceReport {
+            paint_cache_entries: self.scene_cache.len(),
+            ..self.engine.resource_report()
+        }
+    }
+
+    /// Release everything before the process exits instead of leaving it to the OS, so a
+    /// closing tab's memory is returned promptly. Whatever is still held afterwards is a leak,
+    /// which debug builds refuse to let slide.
+    fn teardown(&mut self) {
+        self.scheduled_refresh = None;
+        self.engine.shutdown();
+        self.scene_cache.clear();
+
+        let report = self.resource_report();
+        if !report.is_empty() {
+            warn!("Tab {} still holds resources after teardown: {report}", self.tab_id);
+        }
+        debug_assert!(report.is_empty(), "tab {} leaked resources on teardown: {report}", self.tab_id);
+    }
+
     async fn handle_shell_provider_message(&mut self, message: &ShellProviderMessage) -> io::Result<()> {
         match message {
             ShellProviderMessage::RequestRedraw => {
@@ -1125,10 +1821,72 @@ impl TabProcess {
         Ok(())
     }
 
+    /// Render the page to an offscreen surface and save it as a PNG in the user's pictures folder
+    fn capture_screenshot(&mut self, full_page: bool) -> io::Result<String> {
+        if self.engine.dom.is_none() {
+            return Err(io::Error::other("no page is loaded"));
+        }
+
+        let width = self.engine.viewport_width() as u32;
+        let mut height = self.engine.viewport_height() as u32;
+        if full_page {
+            let content_height = (self.engine.content_size().1 as f64 * self.engine.viewport.scale_f64()).ceil() as u32;
+            height = height.max(content_height).min(MAX_SCREENSHOT_HEIGHT);
+        }
+
+        let animation_time = self.animation_time();
+        let mut offscreen = SoftwareRenderer::new(width, height)?;
+        {
+            let canvas = offscreen.surface.canvas();
+            canvas.clear(skia_safe::Color::WHITE);
+            let mut painter = ScenePainter {
+                inner: canvas,
+                cache: &mut self.scene_cache,
+            };
+            if full_page {
+                self.engine.render_full_page(&mut painter, animation_time, height);
+            } else {
+                self.engine.render(&mut painter, animation_time);
+            }
+        }
+
+        let png = offscreen
+            .surface
+            .image_snapshot()
+            .encode(None, EncodedImageFormat::PNG, 100)
+            .ok_or_else(|| io::Error::other("failed to encode screenshot"))?;
+
+        let dir = dirs::picture_dir()
+            .or_else(dirs::download_dir)
+            .or_else(dirs::home_dir)
+            .unwrap_or_else(std::env::temp_dir);
+        fs::create_dir_all(&dir)?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let path = dir.join(format!("Stokes Screenshot {timestamp}.png"));
+        fs::write(&path, png.as_bytes())?;
+        Ok(path.to_string_lossy().into_owned())
+    }
+
     /// Render a frame to the shared memory surface
     fn render_frame(&mut self) -> io::Result<()> {
+        self.frame_due = None;
+        if !self.engine.is_visible() {
+            return Ok(());
+        }
+        let _span = tracing::debug_span!("frame").entered();
+        let frame_start = Instant::now();
         let animation_time = self.animation_time();
         if let Some(ref mut shared) = self.shared_surface {
+            // Read back what WebGL drew before painting, and have Skia forget the GL state it
+            // assumed if WebGL changed it
+            if let HeadlessRenderer::Gpu(gpu) = &mut shared.renderer
+                && webgl::present()
+            {
+                gpu.gr_context.reset(None);
+            }
             {
                 let canvas = shared.renderer.get_canvas();
 
@@ -1146,32 +1904,42 @@ impl TabProcess {
                     engine.render(&mut painter, animation_time);
 
                     let dom = engine.dom.as_ref().unwrap();
-                    // todo check if window is visible
                     if dom.animating() {
                         dom.shell_provider.request_redraw();
                     }
                 }
             }
 
-            // Only flush GPU context for GPU rendering
-            if let HeadlessRenderer::Gpu(gpu) = &mut shared.renderer {
-                gpu.gr_context.flush_and_submit();
-            }
+            {
+                let _span = tracing::debug_span!(target: "paint", "readback").entered();
+                // Only flush GPU context for GPU rendering
+                if let HeadlessRenderer::Gpu(gpu) = &mut shared.renderer {
+                    gpu.gr_context.flush_and_submit();
+                }
 
-            let dst = unsafe { shared.shmem.as_slice_mut() };
+                let dst = unsafe { shared.shmem.as_slice_mut() };
 
-            shared
-                .renderer
-                .readback_into_shmem(dst, shared.width, shared.height)?;
+                shared
+                    .renderer
+                    .readback_into_shmem(dst, shared.width, shared.height)?;
+            }
 
             self.scene_cache.next_gen();
 
+            let image_memory = self.engine.dom.as_ref().map_or(0, |dom| dom.image_cache.used_bytes());
+            if image_memory != self.reported_image_memory {
+                self.reported_image_memory = image_memory;
+                self.channel.send(&TabToParentMessage::MemoryUsage { image_bytes: image_memory })?;
+            }
+
             // Notify parent that frame is ready
             self.channel.send(&TabToParentMessage::FrameRendered {
                 shmem_name: shared.shmem_name.clone(),
                 width: shared.width,
                 height: shared.height,
             })?;
+            logging::frame_mark();
+            self.frame_scheduler.record_frame(frame_start, frame_start.elapsed());
         }
         Ok(())
     }
@@ -1179,8 +1947,8 @@ impl TabProcess {
 
 /// Entry point for tab process executable
 pub async fn tab_process_main(tab_id: String, server_name: String) -> io::Result<()> {
-    tracing_subscriber::fmt::fmt().with_max_level(LevelFilter::WARN).init();
+    let log_receiver = logging::init_tab_process();
 
-    let mut process = TabProcess::new(tab_id, server_name)?;
+    let mut process = TabProcess::new(tab_id, server_name, log_receiver)?;
     process.run().await
 }
\ No newline at end of file
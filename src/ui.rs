@@ -13,6 +13,18 @@ use std::f32::consts::PI;
 use std::time::{Duration, Instant};
 use usvg::Tree;
 use crate::browser::VERSION;
+use crate::site_settings::{AutoplayPolicy, ColorSchemeOverride};
+use crate::frame_timing::FrameTimingSettings;
+use crate::networking::security::{SecurityLevel, SecurityState};
+use crate::url_display;
+
+mod layout;
+mod theme;
+
+pub use layout::Density;
+pub use theme::ThemeSettings;
+use layout::ChromeLayout;
+use theme::Theme;
 
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct TextBrush {
@@ -47,6 +59,20 @@ pub enum BookmarkUiAction {
     UiChanged,
 }
 
+/// Commands offered by the context menu of a tab
+#[derive(Debug, Clone, PartialEq)]
+pub enum TabMenuAction {
+    TogglePin(String),
+    Close(String),
+}
+
+#[derive(Debug, Clone)]
+struct TabContextMenuState {
+    x: f32,
+    y: f32,
+    tab_id: String,
+}
+
 #[derive(Debug, Clone)]
 struct BookmarkContextMenuState {
     x: f32,
@@ -55,6 +81,42 @@ struct BookmarkContextMenuState {
     parent_id: Option<String>,
 }
 
+/// Saved autofill profiles or logins offered below a focused form field
+#[derive(Debug, Clone)]
+struct AutofillPopup {
+    /// Bottom left corner of the field, in window coordinates
+    x: f32,
+    y: f32,
+    field_width: f32,
+    labels: Vec<String>,
+    /// Row that Enter picks, moved with the arrow keys
+    selected: Option<usize>,
+}
+
+/// A question shown in the prompt bar below the chrome
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptBar {
+    pub message: String,
+    pub accept_label: &'static str,
+    pub decline_label: &'static str,
+}
+
+/// A page's alert(), confirm() or prompt() dialog, shown over its tab's content
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageDialog {
+    /// Identifies the dialog, so setting the one already shown keeps what was typed into it
+    pub key: String,
+    pub title: String,
+    pub message: String,
+    /// Whether there is a Cancel button next to OK
+    pub cancelable: bool,
+    /// Initial text of the input field, which only prompt() dialogs have
+    pub input: Option<String>,
+    /// Whether to offer a checkbox that stops the page from opening more dialogs, once it has
+    /// opened several
+    pub offer_mute: bool,
+}
+
 #[derive(Debug, Clone, Default)]
 struct BookmarkDragState {
     active: bool,
@@ -128,6 +190,13 @@ pub enum UiComponent {
         close_button_tooltip: Tooltip,
         favicon: Option<Image>,
         is_loading: bool,
+        /// Pinned tabs are drawn compact (favicon only) and cannot be closed accidentally
+        is_pinned: bool,
+        /// The tab's process was closed to save memory; it is drawn faded and reloads when selected
+        is_discarded: bool,
+        /// The page is playing sound; a speaker after the favicon shows it and mutes the tab when clicked
+        is_audible: bool,
+        is_muted: bool,
     }
 }
 
@@ -142,19 +211,22 @@ pub enum IconType {
     NewTab,
     Close,
     Settings,
+    /// Connection security of the active page, drawn as a lock or an info sign
+    SiteInfo,
+    /// Sound of a tab, crossed out when it is muted
+    Speaker { muted: bool },
 }
 
 impl UiComponent {
-    /// Create a navigation button (back, forward, refresh)
-    pub fn navigation_button(id: &str, label: &str, x: f32, icon_type: IconType, tooltip_text: &str, scale_factor: f32) -> Self {
-        let scaled = |v: f32| v * scale_factor;
+    /// Create a toolbar button. `BrowserUI::update_layout` places it and the theme colors it.
+    pub fn navigation_button(id: &str, label: &str, icon_type: IconType, tooltip_text: &str) -> Self {
         UiComponent::Button {
             id: id.to_string(),
             label: label.to_string(),
-            x,
-            y: scaled(48.0),  // Move to second row
-            width: scaled(32.0),
-            height: scaled(32.0),
+            x: 0.0,
+            y: 0.0,
+            width: 0.0,
+            height: 0.0,
             color: [0.95, 0.95, 0.95],
             hover_color: [0.85, 0.9, 1.0],
             pressed_color: [0.75, 0.8, 0.95],
@@ -167,15 +239,14 @@ impl UiComponent {
     }
 
     /// Create an address bar
-    pub fn address_bar(url: &str, x: f32, width: f32, scale_factor: f32) -> Self {
-        let scaled = |v: f32| v * scale_factor;
+    pub fn address_bar(url: &str) -> Self {
         UiComponent::TextField {
             id: "address_bar".to_string(),
             text: url.to_string(),
-            x,
-            y: scaled(48.0),  // Move to second row
-            width,
-            height: scaled(32.0),
+            x: 0.0,
+            y: 0.0,
+            width: 0.0,
+            height: 0.0,
             color: [1.0, 1.0, 1.0],
             border_color: [0.7, 0.7, 0.7],
             has_focus: false,
@@ -187,15 +258,14 @@ impl UiComponent {
     }
 
     /// Create a tab button
-    pub fn tab(id: &str, title: &str, x: f32, scale_factor: f32) -> Self {
-        let scaled = |v: f32| v * scale_factor;
+    pub fn tab(id: &str, title: &str) -> Self {
         UiComponent::TabButton {
             id: id.to_string(),
             title: title.to_string(),
-            x,
-            y: scaled(8.0),  // Move to first row
-            width: scaled(150.0),
-            height: scaled(32.0),
+            x: 0.0,
+            y: 0.0,
+            width: 0.0,
+            height: 0.0,
             color: if title == "New Tab" { [0.95, 0.95, 0.95] } else { [0.8, 0.8, 0.8] },
             hover_color: [0.85, 0.9, 1.0],
             is_active: title == "New Tab",
@@ -205,6 +275,10 @@ impl UiComponent {
             close_button_tooltip: Tooltip::new("Close tab"),
             favicon: None,
             is_loading: false,
+            is_pinned: false,
+            is_discarded: false,
+            is_audible: false,
+            is_muted: false,
         }
     }
 
@@ -256,6 +330,15 @@ fn format_tab_tooltip_text(title: &str) -> String {
     }
 }
 
+/// Area of the window highlighted while files are dragged over it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DropZone {
+    /// Dropping here opens the files in new tabs
+    TabStrip,
+    /// Dropping here opens the file in the active tab
+    Page,
+}
+
 /// State for tab dragging
 #[derive(Debug, Clone, Default)]
 pub struct TabDragState {
@@ -292,6 +375,10 @@ pub struct BrowserUI {
     pub close_tab_svg: Tree,
     pub settings_svg: Tree,
     pub folder_svg: Tree,
+    pub lock_svg: Tree,
+    pub info_svg: Tree,
+    pub speaker_svg: Tree,
+    pub speaker_muted_svg: Tree,
     /// Whether the settings panel is open
     pub show_settings: bool,
     /// Whether we are currently dragging a text selection in a chrome text field.
@@ -305,37 +392,109 @@ pub struct BrowserUI {
     open_bookmark_folder: Option<String>,
     selected_bookmark_id: Option<String>,
     bookmark_context_menu: Option<BookmarkContextMenuState>,
+    tab_context_menu: Option<TabContextMenuState>,
     bookmark_drag: BookmarkDragState,
     bookmark_button_active: bool,
     bookmark_hover_id: Option<String>,
     bookmark_pressed_id: Option<String>,
     mouse_pos: (f32, f32),
+    /// Zone currently hovered by an external file drag, if any
+    drop_zone: Option<DropZone>,
+    /// Titles of recently closed tabs, most recent first
+    recently_closed: Vec<String>,
+    /// Color scheme choice for the active tab's site, shown on the "Page colors" button
+    site_color_scheme: ColorSchemeOverride,
+    /// Whether the active tab's site is painted with forced dark colors, shown on the "Force dark" button
+    site_force_dark: bool,
+    /// Autoplay choice for the active tab's site, shown on the "Autoplay" button
+    site_autoplay: AutoplayPolicy,
+    /// Whether the active tab's site may run JavaScript; the address bar says so when it may not
+    site_javascript: bool,
+    /// Popups the active tab's page was stopped from opening, counted on the address bar's badge
+    popups_blocked: usize,
+    /// Whether sites without an exception may run JavaScript, shown on the "JavaScript (all sites)" button
+    javascript_enabled: bool,
+    /// Whether the active tab's site loads images, shown on the "Images" button
+    site_images: bool,
+    /// Whether sites without an exception load images, shown on the "Images (all sites)" button
+    images_enabled: bool,
+    /// Connection security of the active tab's page, shown by the site information button
+    security: SecurityState,
+    /// Whether the site information panel is open
+    pub show_site_info: bool,
+    /// Whether the window is fullscreen, in which case the chrome is hidden
+    fullscreen: bool,
+    /// Whether the window shows an installed web app, which has no tabs, toolbar or bookmarks bar
+    app_mode: bool,
+    /// Name of the active tab's web app, when its page can be installed, for the "Install" button
+    installable_app: Option<String>,
+    /// Transient notification shown at the bottom of the window, with the time it appeared
+    toast: Option<(String, Instant)>,
+    /// The prompt bar shown below the chrome, if a page is asking for a permission or there is a
+    /// login to save
+    permission_prompt: Option<PromptBar>,
+    /// Autofill profiles offered for the focused form field of the active tab
+    autofill_popup: Option<AutofillPopup>,
+    /// The active tab's page dialog, which blocks the page until it is answered
+    page_dialog: Option<PageDialog>,
+    /// The page dialog's message wrapped to the dialog's width
+    page_dialog_lines: Vec<String>,
+    /// Whether the page dialog's "prevent additional dialogs" checkbox is checked
+    page_dialog_mute: bool,
+    /// Whether the find-in-page bar is shown over the top right of the page
+    find_bar_open: bool,
+    /// Whether find in page paints every match rather than just the current one
+    find_highlight_all: bool,
+    /// The current match and how many there are, once the active tab has searched
+    find_result: Option<(Option<usize>, usize)>,
+    /// What was last searched for, put back in the find bar when it is opened again
+    last_find_query: String,
+    /// Theme and density picked in the settings panel
+    theme_settings: ThemeSettings,
+    /// Colors of the chosen theme
+    theme: Theme,
+    /// Frame latency picked in the settings panel
+    frame_timing_settings: FrameTimingSettings,
 }
 
 impl BrowserUI {
-    // UI layout constants
-    pub const CHROME_HEIGHT: f32 = 124.0;
-    const BUTTON_SIZE: f32 = 32.0;
-    const BUTTON_MARGIN: f32 = 8.0;
-    const ADDRESS_BAR_HEIGHT: f32 = 32.0;
-    const ADDRESS_BAR_MARGIN: f32 = 8.0;
-    const MIN_ADDRESS_BAR_WIDTH: f32 = 200.0;
+    // UI layout constants, in logical pixels. The chrome's rows come from `layout::Metrics`.
     const MAX_TAB_WIDTH: f32 = 200.0;  // Maximum width for a tab
     const MIN_TAB_WIDTH: f32 = 80.0;   // Minimum width before scrolling kicks in
-    const TAB_SPACING: f32 = 4.0;       // Spacing between tabs
-    const BOOKMARKS_ROW_Y: f32 = 88.0;
-    const BOOKMARKS_ROW_HEIGHT: f32 = 32.0;
+    const PINNED_TAB_WIDTH: f32 = 40.0; // Width of a pinned (favicon-only) tab
     const BOOKMARK_ITEM_WIDTH: f32 = 150.0;
     const BOOKMARK_ITEM_SPACING: f32 = 6.0;
     const BOOKMARK_CONTEXT_ROW_HEIGHT: f32 = 28.0;
     const BOOKMARK_CONTEXT_WIDTH: f32 = 190.0;
+    const RECENTLY_CLOSED_ROW_HEIGHT: f32 = 28.0;
+    /// Height of the settings panel above the "Recently closed" section
+    const SETTINGS_BUTTONS_HEIGHT: f32 = 760.0;
+    const MAX_RECENTLY_CLOSED_ROWS: usize = 5;
+    const TOAST_DURATION: Duration = Duration::from_secs(4);
+    const PERMISSION_PROMPT_HEIGHT: f32 = 44.0;
+    const JAVASCRIPT_BADGE_WIDTH: f32 = 132.0;
+    const POPUP_BADGE_WIDTH: f32 = 136.0;
+    const PERMISSION_BUTTON_WIDTH: f32 = 72.0;
+    const AUTOFILL_ROW_HEIGHT: f32 = 28.0;
+    const AUTOFILL_MIN_WIDTH: f32 = 200.0;
+    const PAGE_DIALOG_WIDTH: f32 = 440.0;
+    const FIND_BAR_WIDTH: f32 = 540.0;
+    const FIND_BAR_HEIGHT: f32 = 40.0;
+    const FIND_INPUT_WIDTH: f32 = 200.0;
+    /// The find bar's buttons from left to right, with their labels and widths
+    const FIND_BAR_BUTTONS: [(&'static str, &'static str, f32); 4] = [
+        ("find_previous", "Prev", 48.0),
+        ("find_next", "Next", 48.0),
+        ("find_highlight_all", "Highlight all", 96.0),
+        ("find_close", "x", 28.0),
+    ];
+    const PAGE_DIALOG_PADDING: f32 = 20.0;
+    const PAGE_DIALOG_LINE_HEIGHT: f32 = 20.0;
+    const PAGE_DIALOG_MAX_LINES: usize = 12;
+    const PAGE_DIALOG_BUTTON_WIDTH: f32 = 80.0;
+    const PAGE_DIALOG_BUTTON_HEIGHT: f32 = 32.0;
 
     pub fn new(_skia_context: &skia_safe::gpu::DirectContext, viewport: &Viewport) -> Self {
-        // Default window width, will be updated on first resize
-        let window_width = viewport.window_size.0 as f32;
-        let scale_factor = viewport.hidpi_scale;
-        let scaled = |v: f32| v * scale_factor;
-
         let font_mgr = skia_safe::FontMgr::new();
         let ui_typeface = font_mgr.match_family_style("DejaVu Sans", FontStyle::default())
             .or_else(|| font_mgr.match_family_style("Noto Sans", FontStyle::default()))
@@ -343,66 +502,20 @@ impl BrowserUI {
             .or_else(|| font_mgr.match_family_style("Segoe UI Symbol", FontStyle::default()))
             .or_else(|| font_mgr.legacy_make_typeface(None, FontStyle::default()))
             .unwrap_or_else(|| font_mgr.legacy_make_typeface(None, FontStyle::default()).unwrap());
+        let theme_settings = ThemeSettings::load_from_disk();
 
-        Self {
+        let mut ui = Self {
             components: vec![
-                UiComponent::navigation_button("back", "<", scaled(Self::BUTTON_MARGIN), IconType::Back, "Back", scale_factor),
-                UiComponent::navigation_button("forward", ">", scaled(Self::BUTTON_MARGIN * 2.0 + Self::BUTTON_SIZE), IconType::Forward, "Forward", scale_factor),
-                UiComponent::navigation_button("refresh", "⟳", scaled(Self::BUTTON_MARGIN * 3.0 + Self::BUTTON_SIZE * 2.0), IconType::Refresh, "Refresh", scale_factor),
-                UiComponent::navigation_button("home", "H", scaled(Self::BUTTON_MARGIN * 4.0 + Self::BUTTON_SIZE * 3.0), IconType::Home, "Home", scale_factor),
-                UiComponent::address_bar("",
-                    scaled(Self::BUTTON_MARGIN * 5.0 + Self::BUTTON_SIZE * 4.0),
-                    window_width - scaled(Self::BUTTON_MARGIN * 8.0 + Self::BUTTON_SIZE * 6.0), scale_factor),
-                UiComponent::Button {
-                    id: "bookmark_toggle".to_string(),
-                    label: "*".to_string(),
-                    x: window_width - scaled(Self::BUTTON_MARGIN * 2.0 + Self::BUTTON_SIZE * 2.0),
-                    y: scaled(48.0),
-                    width: scaled(Self::BUTTON_SIZE),
-                    height: scaled(Self::BUTTON_SIZE),
-                    color: [0.95, 0.95, 0.95],
-                    hover_color: [0.97, 0.9, 0.65],
-                    pressed_color: [0.95, 0.84, 0.45],
-                    is_hover: false,
-                    is_pressed: false,
-                    is_active: false,
-                    tooltip: Tooltip::new("Bookmark page"),
-                    icon_type: IconType::Bookmark,
-                },
-                // Settings button - positioned to the right of the address bar
-                UiComponent::Button {
-                    id: "settings".to_string(),
-                    label: "⚙".to_string(),
-                    x: window_width - scaled(Self::BUTTON_MARGIN + Self::BUTTON_SIZE),
-                    y: scaled(48.0),
-                    width: scaled(Self::BUTTON_SIZE),
-                    height: scaled(Self::BUTTON_SIZE),
-                    color: [0.95, 0.95, 0.95],
-                    hover_color: [0.85, 0.9, 1.0],
-                    pressed_color: [0.75, 0.8, 0.95],
-                    is_hover: false,
-                    is_pressed: false,
-                    is_active: false,
-                    tooltip: Tooltip::new("Settings"),
-                    icon_type: IconType::Settings,
-                },
-                // New Tab button - positioned in the tab row, will be updated in update_tab_layout
-                UiComponent::Button {
-                    id: "new_tab".to_string(),
-                    label: "+".to_string(),
-                    x: scaled(Self::BUTTON_MARGIN),
-                    y: scaled(8.0),  // Tab row
-                    width: scaled(Self::BUTTON_SIZE),
-                    height: scaled(Self::BUTTON_SIZE),
-                    color: [0.95, 0.95, 0.95],
-                    hover_color: [0.85, 0.9, 1.0],
-                    pressed_color: [0.75, 0.8, 0.95],
-                    is_hover: false,
-                    is_pressed: false,
-                    is_active: false,
-                    tooltip: Tooltip::new("New Tab"),
-                    icon_type: IconType::NewTab,
-                },
+                UiComponent::navigation_button("back", "<", IconType::Back, "Back"),
+                UiComponent::navigation_button("forward", ">", IconType::Forward, "Forward"),
+                UiComponent::navigation_button("refresh", "⟳", IconType::Refresh, "Refresh"),
+                UiComponent::navigation_button("home", "H", IconType::Home, "Home"),
+                UiComponent::navigation_button("site_info", "i", IconType::SiteInfo, "View site information"),
+                UiComponent::address_bar(""),
+                UiComponent::navigation_button("bookmark_toggle", "*", IconType::Bookmark, "Bookmark page"),
+                UiComponent::navigation_button("settings", "⚙", IconType::Settings, "Settings"),
+                // Placed to the right of the tabs by update_tab_layout
+                UiComponent::navigation_button("new_tab", "+", IconType::NewTab, "New Tab"),
             ],
             viewport: viewport.clone(),
             tab_scroll_offset: 0.0,
@@ -416,6 +529,10 @@ impl BrowserUI {
             close_tab_svg: load_svg(include_str!("../assets/close.svg")).unwrap(),
             settings_svg: load_svg(include_str!("../assets/settings.svg")).unwrap(),
             folder_svg: load_svg(include_str!("../assets/folder.svg")).unwrap(),
+            lock_svg: load_svg(include_str!("../assets/lock.svg")).unwrap(),
+            info_svg: load_svg(include_str!("../assets/info.svg")).unwrap(),
+            speaker_svg: load_svg(include_str!("../assets/speaker.svg")).unwrap(),
+            speaker_muted_svg: load_svg(include_str!("../assets/speaker_muted.svg")).unwrap(),
             show_settings: false,
             text_selection_drag_active: false,
             text_selection_drag_anchor: None,
@@ -425,16 +542,1003 @@ impl BrowserUI {
             open_bookmark_folder: None,
             selected_bookmark_id: None,
             bookmark_context_menu: None,
+            tab_context_menu: None,
             bookmark_drag: BookmarkDragState::default(),
             bookmark_button_active: false,
             bookmark_hover_id: None,
             bookmark_pressed_id: None,
             mouse_pos: (0.0, 0.0),
+            drop_zone: None,
+            recently_closed: Vec::new(),
+            site_color_scheme: ColorSchemeOverride::Auto,
+            site_force_dark: false,
+            site_autoplay: AutoplayPolicy::default(),
+            site_javascript: true,
+            popups_blocked: 0,
+            javascript_enabled: true,
+            site_images: true,
+            images_enabled: true,
+            security: SecurityState::default(),
+            show_site_info: false,
+            fullscreen: false,
+            app_mode: false,
+            installable_app: None,
+            toast: None,
+            permission_prompt: None,
+            autofill_popup: None,
+            page_dialog: None,
+            page_dialog_lines: Vec::new(),
+            page_dialog_mute: false,
+            find_bar_open: false,
+            find_highlight_all: false,
+            find_result: None,
+            last_find_query: String::new(),
+            theme: theme_settings.colors(),
+            theme_settings,
+            frame_timing_settings: FrameTimingSettings::load_from_disk(),
+        };
+        ui.apply_theme();
+        ui.update_layout(viewport);
+        ui
+    }
+
+    /// Sizes of the chrome at the current density and scale factor, in device pixels
+    fn layout(&self) -> ChromeLayout {
+        ChromeLayout::new(&self.theme_settings.density.metrics(), self.viewport.hidpi_scale)
+    }
+
+    pub fn theme_settings(&self) -> &ThemeSettings {
+        &self.theme_settings
+    }
+
+    /// Draw the chrome with a new theme or density. The chrome height changes with the density, so
+    /// the page viewport has to be resized after.
+    pub fn set_theme_settings(&mut self, settings: ThemeSettings) {
+        self.theme = settings.colors();
+        self.theme_settings = settings;
+        self.apply_theme();
+        self.update_layout(&self.viewport.clone());
+    }
+
+    pub fn frame_timing_settings(&self) -> FrameTimingSettings {
+        self.frame_timing_settings
+    }
+
+    pub fn set_frame_timing_settings(&mut self, settings: FrameTimingSettings) {
+        self.frame_timing_settings = settings;
+    }
+
+    /// Give the components the theme's colors
+    fn apply_theme(&mut self) {
+        let theme = &self.theme;
+        for comp in &mut self.components {
+            match comp {
+                UiComponent::Button { id, color, hover_color, pressed_color, .. } => {
+                    *color = theme::to_rgb(theme.button);
+                    *pressed_color = theme::to_rgb(theme.button_pressed);
+                    // The bookmark star lights up the way it does once the page is bookmarked
+                    *hover_color = theme::to_rgb(if id == "bookmark_toggle" { theme.button_active } else { theme.button_hover });
+                }
+                UiComponent::TextField { color, border_color, .. } => {
+                    *color = theme::to_rgb(theme.field);
+                    *border_color = theme::to_rgb(theme.field_border);
+                }
+                UiComponent::TabButton { color, hover_color, is_active, .. } => {
+                    *color = theme::to_rgb(if *is_active { theme.tab_active } else { theme.tab });
+                    *hover_color = theme::to_rgb(theme.button_hover);
+                }
+            }
         }
     }
 
     pub fn tab_row_height(&self) -> f32 {
-        48.0 * self.viewport.hidpi_scale
+        if self.fullscreen || self.app_mode {
+            return 0.0;
+        }
+        self.layout().tab_row_height
+    }
+
+    /// Hide or show the chrome for fullscreen mode
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.fullscreen = fullscreen;
+        if fullscreen {
+            self.show_settings = false;
+            self.show_site_info = false;
+            self.clear_focus();
+        }
+    }
+
+    #[inline]
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    /// Show the window as an app window, without the chrome
+    pub fn set_app_mode(&mut self, app_mode: bool) {
+        self.app_mode = app_mode;
+        self.update_layout(&self.viewport.clone());
+    }
+
+    #[inline]
+    pub fn is_app_mode(&self) -> bool {
+        self.app_mode
+    }
+
+    /// Offer to install the active tab's page as the web app `name`, or nothing when it can't be
+    pub fn set_installable_app(&mut self, name: Option<String>) {
+        self.installable_app = name;
+    }
+
+    /// Update the highlighted drop zone while files are dragged over the window.
+    /// Passing `None` clears the highlight. Returns true if the highlight changed.
+    pub fn update_drop_hover(&mut self, y: Option<f32>) -> bool {
+        let zone = y.map(|y| if y < self.tab_row_height() { DropZone::TabStrip } else { DropZone::Page });
+        let changed = zone != self.drop_zone;
+        self.drop_zone = zone;
+        changed
+    }
+
+    fn render_drop_zone(&self, canvas: &Canvas, canvas_width: f32, canvas_height: f32) {
+        let Some(zone) = self.drop_zone else {
+            return;
+        };
+
+        let s = self.viewport.hidpi_scale;
+        let rect = match zone {
+            DropZone::TabStrip => Rect::from_xywh(0.0, 0.0, canvas_width, self.tab_row_height()),
+            DropZone::Page => Rect::from_xywh(0.0, self.chrome_height(), canvas_width, (canvas_height - self.chrome_height()).max(0.0)),
+        };
+
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+        paint.set_color(Color::from_argb(40, 70, 130, 220));
+        canvas.draw_rect(rect, &paint);
+
+        paint.set_color(Color::from_rgb(70, 130, 220));
+        paint.set_stroke(true);
+        paint.set_stroke_width(2.0 * s);
+        canvas.draw_round_rect(rect.with_inset((3.0 * s, 3.0 * s)), 6.0 * s, 6.0 * s, &paint);
+    }
+
+    /// Show a short notification at the bottom of the window
+    pub fn show_toast(&mut self, message: impl Into<String>) {
+        self.toast = Some((message.into(), Instant::now()));
+    }
+
+    fn render_toast(&self, canvas: &Canvas, font: &Font, canvas_width: f32, canvas_height: f32) {
+        let Some((message, shown_at)) = self.toast.as_ref() else {
+            return;
+        };
+        if shown_at.elapsed() >= Self::TOAST_DURATION {
+            return;
+        }
+
+        let s = self.viewport.hidpi_scale;
+        let max_width = (canvas_width - 32.0 * s).max(0.0);
+        let label = Self::truncate_text_to_width(message, max_width - 32.0 * s, font);
+        let Some(blob) = TextBlob::new(&label, font) else {
+            return;
+        };
+        let bounds = blob.bounds();
+        let width = (bounds.width() + 32.0 * s).min(max_width);
+        let height = 36.0 * s;
+        let x = (canvas_width - width) / 2.0;
+        let y = canvas_height - height - 24.0 * s;
+
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+        paint.set_color(Color::from_argb(230, 40, 40, 44));
+        canvas.draw_round_rect(Rect::from_xywh(x, y, width, height), 8.0 * s, 8.0 * s, &paint);
+
+        paint.set_color(Color::WHITE);
+        let text_y = y + height / 2.0 - (bounds.top + bounds.height() / 2.0);
+        canvas.draw_text_blob(&blob, (x + 16.0 * s, text_y), &paint);
+    }
+
+    /// Draw the URL of the unfocused address bar with its registrable domain standing out from
+    /// the rest, so a deceptive subdomain or path can't pass for the site
+    fn render_address_bar_url(&self, canvas: &Canvas, font: &Font, rect: Rect, text_padding: f32, url: &str) {
+        let display = url_display::for_display(url);
+        let Some(blob) = TextBlob::new(&display.text, font) else {
+            return;
+        };
+        let bounds = blob.bounds();
+        let text_y = rect.top() + (rect.height() / 2.0) - (bounds.top + bounds.height() / 2.0);
+        let domain = display.domain.unwrap_or(0..display.text.len());
+        let parts = [
+            (&display.text[..domain.start], self.theme.muted_text),
+            (&display.text[domain.clone()], self.theme.text),
+            (&display.text[domain.end..], self.theme.muted_text),
+        ];
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+        let mut x = rect.left() + text_padding;
+        for (part, color) in parts {
+            let Some(blob) = TextBlob::new(part, font) else {
+                continue;
+            };
+            paint.set_color(color);
+            canvas.draw_text_blob(&blob, (x, text_y), &paint);
+            x += font.measure_str(part, None).0;
+        }
+    }
+
+    /// Returns (x, y, width, height) of the "JavaScript blocked" badge at the right end of the
+    /// address bar, shown while the active site may not run scripts and the bar isn't being edited
+    fn javascript_badge_rect(&self) -> Option<(f32, f32, f32, f32)> {
+        if self.site_javascript {
+            return None;
+        }
+        let (x, y, width, height) = self.components.iter().find_map(|comp| match comp {
+            UiComponent::TextField { id, x, y, width, height, has_focus: false, .. } if id == "address_bar" => {
+                Some((*x, *y, *width, *height))
+            }
+            _ => None,
+        })?;
+        let s = self.viewport.hidpi_scale;
+        let badge_width = Self::JAVASCRIPT_BADGE_WIDTH * s;
+        Some((x + width - badge_width - 4.0 * s, y + 4.0 * s, badge_width, height - 8.0 * s))
+    }
+
+    /// Check if a click lands on the "JavaScript blocked" badge, which toggles JavaScript for the site
+    pub fn handle_javascript_badge_click(&self, x: f32, y: f32) -> bool {
+        self.javascript_badge_rect()
+            .is_some_and(|(bx, by, bw, bh)| x >= bx && x <= bx + bw && y >= by && y <= by + bh)
+    }
+
+    fn render_javascript_badge(&self, canvas: &Canvas, font: &Font) {
+        let Some((x, y, width, height)) = self.javascript_badge_rect() else {
+            return;
+        };
+        let Some(blob) = TextBlob::new("JavaScript blocked", font) else {
+            return;
+        };
+        let s = self.viewport.hidpi_scale;
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+        paint.set_color(self.theme.panel_button);
+        canvas.draw_round_rect(Rect::from_xywh(x, y, width, height), 4.0 * s, 4.0 * s, &paint);
+        paint.set_color(self.theme.panel_text);
+        let bounds = blob.bounds();
+        let text_x = x + (width - bounds.width()) / 2.0;
+        let text_y = y + height / 2.0 - (bounds.top + bounds.height() / 2.0);
+        canvas.draw_text_blob(&blob, (text_x, text_y), &paint);
+    }
+
+    /// Returns (x, y, width, height) of the "Popup blocked" badge, just left of the JavaScript badge,
+    /// shown while the active page has had popups blocked and the bar isn't being edited
+    fn popup_badge_rect(&self) -> Option<(f32, f32, f32, f32)> {
+        if self.popups_blocked == 0 {
+            return None;
+        }
+        let (x, y, width, height) = self.components.iter().find_map(|comp| match comp {
+            UiComponent::TextField { id, x, y, width, height, has_focus: false, .. } if id == "address_bar" => {
+                Some((*x, *y, *width, *height))
+            }
+            _ => None,
+        })?;
+        let s = self.viewport.hidpi_scale;
+        let right = self.javascript_badge_rect().map_or(x + width, |(badge_x, ..)| badge_x + 4.0 * s);
+        let badge_width = Self::POPUP_BADGE_WIDTH * s;
+        Some((right - badge_width - 4.0 * s, y + 4.0 * s, badge_width, height - 8.0 * s))
+    }
+
+    /// Check if a click lands on the "Popup blocked" badge, which allows popups for the site
+    pub fn handle_popup_badge_click(&self, x: f32, y: f32) -> bool {
+        self.popup_badge_rect()
+            .is_some_and(|(bx, by, bw, bh)| x >= bx && x <= bx + bw && y >= by && y <= by + bh)
+    }
+
+    fn render_popup_badge(&self, canvas: &Canvas, font: &Font) {
+        let Some((x, y, width, height)) = self.popup_badge_rect() else {
+            return;
+        };
+        let label = match self.popups_blocked {
+            1 => "Popup blocked".to_string(),
+            count => format!("Popups blocked ({count})"),
+        };
+        let Some(blob) = TextBlob::new(&label, font) else {
+            return;
+        };
+        let s = self.viewport.hidpi_scale;
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+        paint.set_color(self.theme.panel_button);
+        canvas.draw_round_rect(Rect::from_xywh(x, y, width, height), 4.0 * s, 4.0 * s, &paint);
+        paint.set_color(self.theme.panel_text);
+        let bounds = blob.bounds();
+        let text_x = x + (width - bounds.width()) / 2.0;
+        let text_y = y + height / 2.0 - (bounds.top + bounds.height() / 2.0);
+        canvas.draw_text_blob(&blob, (text_x, text_y), &paint);
+    }
+
+    /// Draw a warning at the right end of the address bar when the page blocked or displayed
+    /// insecure content
+    fn render_mixed_content_badge(&self, canvas: &Canvas, font: &Font, field: Rect) {
+        let status = self.security.mixed_content;
+        let (label, background, foreground) = if status.blocked > 0 {
+            (format!("Blocked insecure content ({})", status.blocked), Color::from_rgb(255, 243, 205), Color::from_rgb(133, 100, 4))
+        } else if status.insecure_displayed {
+            ("Not fully secure".to_string(), Color::from_rgb(252, 232, 230), Color::from_rgb(165, 14, 14))
+        } else {
+            return;
+        };
+        let Some(blob) = TextBlob::new(&label, font) else {
+            return;
+        };
+
+        let s = self.viewport.hidpi_scale;
+        let bounds = blob.bounds();
+        let height = field.height() - 8.0 * s;
+        let width = bounds.width() + 16.0 * s;
+        let x = field.right() - width - 4.0 * s;
+        let y = field.top() + 4.0 * s;
+
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+        paint.set_color(background);
+        canvas.draw_round_rect(Rect::from_xywh(x, y, width, height), 4.0 * s, 4.0 * s, &paint);
+        paint.set_color(foreground);
+        let text_y = y + height / 2.0 - (bounds.top + bounds.height() / 2.0);
+        canvas.draw_text_blob(&blob, (x + 8.0 * s, text_y), &paint);
+    }
+
+    /// Show or hide the permission prompt bar
+    pub fn set_permission_prompt(&mut self, prompt: Option<PromptBar>) {
+        self.permission_prompt = prompt;
+        // The find bar moves down out of its way
+        self.layout_find_bar();
+    }
+
+    /// Returns (x, y, width, height) of the permission prompt bar, which overlays the top of the page
+    fn permission_prompt_rect(&self) -> Option<(f32, f32, f32, f32)> {
+        if self.permission_prompt.is_none() || self.fullscreen {
+            return None;
+        }
+        let s = self.viewport.hidpi_scale;
+        Some((0.0, self.chrome_height(), self.window_width(), Self::PERMISSION_PROMPT_HEIGHT * s))
+    }
+
+    /// Returns (x, y, width, height) of the "Allow" or "Block" button in the prompt bar
+    fn permission_button_rect(&self, allow: bool) -> Option<(f32, f32, f32, f32)> {
+        let (px, py, pw, ph) = self.permission_prompt_rect()?;
+        let s = self.viewport.hidpi_scale;
+        let width = Self::PERMISSION_BUTTON_WIDTH * s;
+        let height = ph - 14.0 * s;
+        let block_x = px + pw - width - 12.0 * s;
+        let x = if allow { block_x - width - 8.0 * s } else { block_x };
+        Some((x, py + 7.0 * s, width, height))
+    }
+
+    pub fn is_over_permission_prompt(&self, x: f32, y: f32) -> bool {
+        self.permission_prompt_rect()
+            .is_some_and(|(px, py, pw, ph)| x >= px && x <= px + pw && y >= py && y <= py + ph)
+    }
+
+    /// Check if a click lands on the permission prompt bar and return the action id
+    pub fn handle_permission_prompt_click(&self, x: f32, y: f32) -> Option<String> {
+        if !self.is_over_permission_prompt(x, y) {
+            return None;
+        }
+        for (allow, id) in [(true, "permission_allow"), (false, "permission_block")] {
+            if let Some((bx, by, bw, bh)) = self.permission_button_rect(allow) {
+                if x >= bx && x <= bx + bw && y >= by && y <= by + bh {
+                    return Some(id.to_string());
+                }
+            }
+        }
+        Some("permission_prompt_noop".to_string())
+    }
+
+    fn render_permission_prompt(&self, canvas: &Canvas, font: &Font) {
+        let (Some(prompt), Some((px, py, pw, ph))) = (self.permission_prompt.as_ref(), self.permission_prompt_rect()) else {
+            return;
+        };
+        let s = self.viewport.hidpi_scale;
+        let theme = &self.theme;
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+
+        paint.set_color(theme.prompt_bar);
+        canvas.draw_rect(Rect::from_xywh(px, py, pw, ph), &paint);
+        paint.set_color(theme.prompt_bar_border);
+        paint.set_stroke(true);
+        paint.set_stroke_width(1.0 * s);
+        canvas.draw_line((px, py + ph), (px + pw, py + ph), &paint);
+        paint.set_stroke(false);
+
+        let text_right = self.permission_button_rect(true).map(|(bx, ..)| bx).unwrap_or(px + pw);
+        paint.set_color(theme.panel_text);
+        let label = Self::truncate_text_to_width(&prompt.message, text_right - px - 28.0 * s, font);
+        if let Some(blob) = TextBlob::new(&label, font) {
+            let bounds = blob.bounds();
+            let text_y = py + ph / 2.0 - (bounds.top + bounds.height() / 2.0);
+            canvas.draw_text_blob(&blob, (px + 16.0 * s, text_y), &paint);
+        }
+
+        for (allow, label) in [(true, prompt.accept_label), (false, prompt.decline_label)] {
+            let Some((bx, by, bw, bh)) = self.permission_button_rect(allow) else {
+                continue;
+            };
+            let rect = Rect::from_xywh(bx, by, bw, bh);
+            if allow {
+                paint.set_color(theme.primary_button);
+                canvas.draw_round_rect(rect, 6.0 * s, 6.0 * s, &paint);
+                paint.set_color(theme.primary_text);
+            } else {
+                paint.set_color(theme.panel);
+                canvas.draw_round_rect(rect, 6.0 * s, 6.0 * s, &paint);
+                paint.set_color(theme.panel_border);
+                paint.set_stroke(true);
+                canvas.draw_round_rect(rect, 6.0 * s, 6.0 * s, &paint);
+                paint.set_stroke(false);
+                paint.set_color(theme.panel_text);
+            }
+            if let Some(blob) = TextBlob::new(label, font) {
+                let bounds = blob.bounds();
+                let text_x = bx + (bw - bounds.width()) / 2.0;
+                let text_y = by + bh / 2.0 - (bounds.top + bounds.height() / 2.0);
+                canvas.draw_text_blob(&blob, (text_x, text_y), &paint);
+            }
+        }
+    }
+
+    /// Offer `labels` below a form field whose border box is `(x, y, width, height)` in page
+    /// coordinates (physical pixels relative to the top left of the page), with `selected` picked
+    /// by Enter
+    pub fn show_autofill_popup(&mut self, (x, y, width, height): (f32, f32, f32, f32), labels: Vec<String>, selected: Option<usize>) {
+        self.autofill_popup = Some(AutofillPopup {
+            x,
+            y: self.chrome_height() + y + height,
+            field_width: width,
+            labels,
+            selected,
+        });
+    }
+
+    /// Hide the autofill popup; returns true if it was shown
+    pub fn hide_autofill_popup(&mut self) -> bool {
+        self.autofill_popup.take().is_some()
+    }
+
+    pub fn is_autofill_popup_shown(&self) -> bool {
+        self.autofill_popup_rect().is_some()
+    }
+
+    /// Move the popup's selection by `delta` rows, wrapping around at either end
+    pub fn move_autofill_selection(&mut self, delta: isize) {
+        let Some(popup) = self.autofill_popup.as_mut() else {
+            return;
+        };
+        let count = popup.labels.len() as isize;
+        if count == 0 {
+            return;
+        }
+        popup.selected = Some(match popup.selected {
+            Some(index) => (index as isize + delta).rem_euclid(count) as usize,
+            None if delta < 0 => (count - 1) as usize,
+            None => 0,
+        });
+    }
+
+    pub fn autofill_selection(&self) -> Option<usize> {
+        self.autofill_popup.as_ref()?.selected
+    }
+
+    /// Returns (x, y, width, height) of the autofill popup, kept inside the window
+    fn autofill_popup_rect(&self) -> Option<(f32, f32, f32, f32)> {
+        let popup = self.autofill_popup.as_ref().filter(|_| !self.fullscreen)?;
+        let s = self.viewport.hidpi_scale;
+        let width = popup.field_width.max(Self::AUTOFILL_MIN_WIDTH * s);
+        let height = popup.labels.len() as f32 * Self::AUTOFILL_ROW_HEIGHT * s;
+        let x = popup.x.min(self.window_width() - width - 4.0 * s).max(0.0);
+        let y = popup.y.max(self.chrome_height());
+        Some((x, y, width, height))
+    }
+
+    pub fn is_over_autofill_popup(&self, x: f32, y: f32) -> bool {
+        self.autofill_popup_rect()
+            .is_some_and(|(px, py, pw, ph)| x >= px && x <= px + pw && y >= py && y <= py + ph)
+    }
+
+    /// Check if a click lands on a profile in the autofill popup and return its index
+    pub fn handle_autofill_popup_click(&self, x: f32, y: f32) -> Option<usize> {
+        if !self.is_over_autofill_popup(x, y) {
+            return None;
+        }
+        let (_, py, ..) = self.autofill_popup_rect()?;
+        let row_h = Self::AUTOFILL_ROW_HEIGHT * self.viewport.hidpi_scale;
+        let index = ((y - py) / row_h).floor() as usize;
+        let count = self.autofill_popup.as_ref()?.labels.len();
+        Some(index.min(count.saturating_sub(1)))
+    }
+
+    fn render_autofill_popup(&self, canvas: &Canvas, font: &Font) {
+        let (Some(popup), Some((x, y, w, h))) = (self.autofill_popup.as_ref(), self.autofill_popup_rect()) else {
+            return;
+        };
+        let s = self.viewport.hidpi_scale;
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+        let panel = Rect::from_xywh(x, y, w, h);
+        paint.set_color(Color::from_argb(50, 0, 0, 0));
+        canvas.draw_round_rect(Rect::from_xywh(x + 2.0 * s, y + 2.0 * s, w, h), 6.0 * s, 6.0 * s, &paint);
+        paint.set_color(self.theme.panel);
+        canvas.draw_round_rect(panel, 6.0 * s, 6.0 * s, &paint);
+        paint.set_color(self.theme.panel_border);
+        paint.set_stroke(true);
+        paint.set_stroke_width(1.0 * s);
+        canvas.draw_round_rect(panel, 6.0 * s, 6.0 * s, &paint);
+        paint.set_stroke(false);
+
+        let row_h = Self::AUTOFILL_ROW_HEIGHT * s;
+        for (index, label) in popup.labels.iter().enumerate() {
+            let row_y = y + index as f32 * row_h;
+            let row = Rect::from_xywh(x + 2.0 * s, row_y + 1.0 * s, w - 4.0 * s, row_h - 2.0 * s);
+            if popup.selected == Some(index) || self.pointer_is_in_rect(row) {
+                paint.set_color(self.theme.row_hover);
+                canvas.draw_round_rect(row, 4.0 * s, 4.0 * s, &paint);
+            }
+            paint.set_color(self.theme.panel_text);
+            let label = Self::truncate_text_to_width(label, w - 16.0 * s, font);
+            if let Some(blob) = TextBlob::new(&label, font) {
+                let bounds = blob.bounds();
+                let text_y = row_y + (row_h / 2.0) - (bounds.top + bounds.height() / 2.0);
+                canvas.draw_text_blob(&blob, (x + 8.0 * s, text_y), &paint);
+            }
+        }
+    }
+
+    /// Show or hide the page dialog. Prompt dialogs get an input field, focused with its text
+    /// selected; the others take the focus away from the address bar so Enter and Escape answer
+    /// them.
+    pub fn set_page_dialog(&mut self, dialog: Option<PageDialog>) {
+        if self.page_dialog.as_ref().map(|shown| &shown.key) == dialog.as_ref().map(|dialog| &dialog.key) {
+            return;
+        }
+        self.components.retain(|comp| comp.id() != "page_dialog_input");
+        self.page_dialog = dialog;
+        self.page_dialog_mute = false;
+        let Some(dialog) = &self.page_dialog else {
+            self.page_dialog_lines.clear();
+            return;
+        };
+
+        match dialog.input.clone() {
+            Some(text) => {
+                self.components.push(UiComponent::TextField {
+                    id: "page_dialog_input".to_string(),
+                    text,
+                    x: 0.0,
+                    y: 0.0,
+                    width: 0.0,
+                    height: 0.0,
+                    color: theme::to_rgb(self.theme.field),
+                    border_color: theme::to_rgb(self.theme.field_border),
+                    has_focus: false,
+                    cursor_position: 0,
+                    selection_start: None,
+                    selection_end: None,
+                    is_flexible: false,
+                });
+                self.set_focus("page_dialog_input");
+            }
+            None => self.clear_focus(),
+        }
+        self.layout_page_dialog();
+    }
+
+    pub fn is_page_dialog_shown(&self) -> bool {
+        self.page_dialog_rect().is_some()
+    }
+
+    pub fn is_page_dialog_input_focused(&self) -> bool {
+        self.components.iter().any(|comp| {
+            matches!(comp, UiComponent::TextField { id, has_focus: true, .. } if id == "page_dialog_input")
+        })
+    }
+
+    /// What was typed into the page dialog's input field
+    pub fn page_dialog_value(&self) -> Option<String> {
+        self.get_text_field_content("page_dialog_input")
+    }
+
+    /// Check or uncheck the page dialog's "prevent additional dialogs" checkbox
+    pub fn toggle_page_dialog_mute(&mut self) {
+        self.page_dialog_mute = !self.page_dialog_mute;
+    }
+
+    /// Whether the user asked that the page open no more dialogs
+    pub fn page_dialog_mute(&self) -> bool {
+        self.page_dialog_mute
+    }
+
+    /// Wrap the page dialog's message and move its input field to match the window size
+    fn layout_page_dialog(&mut self) {
+        let Some(message) = self.page_dialog.as_ref().map(|dialog| dialog.message.clone()) else {
+            return;
+        };
+        let s = self.viewport.hidpi_scale;
+        let font = Font::new(self.ui_typeface.clone(), self.layout().font_size);
+        let text_width = self.page_dialog_width() - 2.0 * Self::PAGE_DIALOG_PADDING * s;
+        self.page_dialog_lines = Self::wrap_text_to_width(&message, text_width, &font, Self::PAGE_DIALOG_MAX_LINES);
+
+        let Some((ix, iy, iw, ih)) = self.page_dialog_input_rect() else {
+            return;
+        };
+        for comp in &mut self.components {
+            if let UiComponent::TextField { id, x, y, width, height, .. } = comp
+                && id == "page_dialog_input"
+            {
+                (*x, *y, *width, *height) = (ix, iy, iw, ih);
+            }
+        }
+    }
+
+    fn page_dialog_width(&self) -> f32 {
+        let s = self.viewport.hidpi_scale;
+        (Self::PAGE_DIALOG_WIDTH * s).min(self.window_width() - 32.0 * s)
+    }
+
+    /// Returns (x, y, width, height) of the page dialog, centered below the chrome
+    fn page_dialog_rect(&self) -> Option<(f32, f32, f32, f32)> {
+        let dialog = self.page_dialog.as_ref().filter(|_| !self.fullscreen)?;
+        let s = self.viewport.hidpi_scale;
+        let width = self.page_dialog_width();
+        let mut height = 2.0 * Self::PAGE_DIALOG_PADDING
+            + Self::PAGE_DIALOG_LINE_HEIGHT * (1 + self.page_dialog_lines.len()) as f32
+            + 16.0
+            + Self::PAGE_DIALOG_BUTTON_HEIGHT;
+        if dialog.input.is_some() {
+            height += 8.0 + Self::PAGE_DIALOG_BUTTON_HEIGHT;
+        }
+        if dialog.offer_mute {
+            height += 8.0 + Self::PAGE_DIALOG_LINE_HEIGHT;
+        }
+        let x = (self.window_width() - width) / 2.0;
+        Some((x, self.chrome_height() + 32.0 * s, width, height * s))
+    }
+
+    /// Returns (x, y, width, height) of the prompt dialog's input field, below the message
+    fn page_dialog_input_rect(&self) -> Option<(f32, f32, f32, f32)> {
+        self.page_dialog.as_ref()?.input.as_ref()?;
+        let (x, y, w, _) = self.page_dialog_rect()?;
+        let s = self.viewport.hidpi_scale;
+        let lines = (1 + self.page_dialog_lines.len()) as f32;
+        let top = y + (Self::PAGE_DIALOG_PADDING + Self::PAGE_DIALOG_LINE_HEIGHT * lines + 8.0) * s;
+        let padding = Self::PAGE_DIALOG_PADDING * s;
+        Some((x + padding, top, w - 2.0 * padding, Self::PAGE_DIALOG_BUTTON_HEIGHT * s))
+    }
+
+    /// Returns (x, y, width, height) of the "prevent additional dialogs" checkbox row, above the
+    /// buttons
+    fn page_dialog_mute_rect(&self) -> Option<(f32, f32, f32, f32)> {
+        self.page_dialog.as_ref().filter(|dialog| dialog.offer_mute)?;
+        let (x, y, w, h) = self.page_dialog_rect()?;
+        let s = self.viewport.hidpi_scale;
+        let padding = Self::PAGE_DIALOG_PADDING * s;
+        let line_height = Self::PAGE_DIALOG_LINE_HEIGHT * s;
+        let top = y + h - padding - Self::PAGE_DIALOG_BUTTON_HEIGHT * s - 8.0 * s - line_height;
+        Some((x + padding, top, w - 2.0 * padding, line_height))
+    }
+
+    /// Returns (x, y, width, height) of the OK or Cancel button in the page dialog's bottom right
+    fn page_dialog_button_rect(&self, ok: bool) -> Option<(f32, f32, f32, f32)> {
+        let (x, y, w, h) = self.page_dialog_rect()?;
+        let s = self.viewport.hidpi_scale;
+        let (width, height) = (Self::PAGE_DIALOG_BUTTON_WIDTH * s, Self::PAGE_DIALOG_BUTTON_HEIGHT * s);
+        let ok_x = x + w - Self::PAGE_DIALOG_PADDING * s - width;
+        let button_y = y + h - Self::PAGE_DIALOG_PADDING * s - height;
+        if ok {
+            Some((ok_x, button_y, width, height))
+        } else {
+            self.page_dialog.as_ref().filter(|dialog| dialog.cancelable)?;
+            Some((ok_x - 8.0 * s - width, button_y, width, height))
+        }
+    }
+
+    /// Whether a point is on the page dialog or the page it blocks
+    pub fn is_over_page_dialog(&self, _x: f32, y: f32) -> bool {
+        self.is_page_dialog_shown() && y >= self.chrome_height()
+    }
+
+    /// Check if a click lands on the page dialog or the page behind it and return the action id
+    pub fn handle_page_dialog_click(&self, x: f32, y: f32) -> Option<String> {
+        if !self.is_over_page_dialog(x, y) {
+            return None;
+        }
+        let inside = |(bx, by, bw, bh): (f32, f32, f32, f32)| x >= bx && x <= bx + bw && y >= by && y <= by + bh;
+        for (ok, id) in [(true, "page_dialog_ok"), (false, "page_dialog_cancel")] {
+            if self.page_dialog_button_rect(ok).is_some_and(inside) {
+                return Some(id.to_string());
+            }
+        }
+        if self.page_dialog_input_rect().is_some_and(inside) {
+            return Some("page_dialog_input".to_string());
+        }
+        if self.page_dialog_mute_rect().is_some_and(inside) {
+            return Some("page_dialog_mute".to_string());
+        }
+        Some("page_dialog_noop".to_string())
+    }
+
+    /// Dim the blocked page and draw the dialog box. The prompt input field is a chrome text field
+    /// drawn on top of it with the other components.
+    fn render_page_dialog(&self, canvas: &Canvas, font: &Font) {
+        let (Some(dialog), Some((x, y, w, h))) = (self.page_dialog.as_ref(), self.page_dialog_rect()) else {
+            return;
+        };
+        let s = self.viewport.hidpi_scale;
+        let theme = &self.theme;
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+
+        let chrome_height = self.chrome_height();
+        paint.set_color(Color::from_argb(90, 0, 0, 0));
+        canvas.draw_rect(Rect::from_xywh(0.0, chrome_height, self.window_width(), self.viewport.window_size.1 as f32 - chrome_height), &paint);
+
+        let panel = Rect::from_xywh(x, y, w, h);
+        paint.set_color(Color::from_argb(60, 0, 0, 0));
+        canvas.draw_round_rect(Rect::from_xywh(x + 3.0 * s, y + 3.0 * s, w, h), 8.0 * s, 8.0 * s, &paint);
+        paint.set_color(theme.panel);
+        canvas.draw_round_rect(panel, 8.0 * s, 8.0 * s, &paint);
+        paint.set_color(theme.panel_border);
+        paint.set_stroke(true);
+        paint.set_stroke_width(1.0 * s);
+        canvas.draw_round_rect(panel, 8.0 * s, 8.0 * s, &paint);
+        paint.set_stroke(false);
+
+        let padding = Self::PAGE_DIALOG_PADDING * s;
+        let line_height = Self::PAGE_DIALOG_LINE_HEIGHT * s;
+        let title = Self::truncate_text_to_width(&dialog.title, w - 2.0 * padding, font);
+        let lines = std::iter::once((&title, theme.muted_text))
+            .chain(self.page_dialog_lines.iter().map(|line| (line, theme.panel_text)));
+        for (index, (line, color)) in lines.enumerate() {
+            paint.set_color(color);
+            if let Some(blob) = TextBlob::new(line, font) {
+                let bounds = blob.bounds();
+                let line_y = y + padding + index as f32 * line_height;
+                let text_y = line_y + line_height / 2.0 - (bounds.top + bounds.height() / 2.0);
+                canvas.draw_text_blob(&blob, (x + padding, text_y), &paint);
+            }
+        }
+
+        if let Some((mx, my, mw, mh)) = self.page_dialog_mute_rect() {
+            let size = 14.0 * s;
+            let box_rect = Rect::from_xywh(mx, my + (mh - size) / 2.0, size, size);
+            if self.page_dialog_mute {
+                paint.set_color(theme.primary_button);
+                canvas.draw_round_rect(box_rect, 3.0 * s, 3.0 * s, &paint);
+                paint.set_color(theme.primary_text);
+                paint.set_stroke(true);
+                paint.set_stroke_width(2.0 * s);
+                let (bx, by) = (box_rect.left, box_rect.top);
+                canvas.draw_line((bx + 3.0 * s, by + 7.0 * s), (bx + 6.0 * s, by + 10.0 * s), &paint);
+                canvas.draw_line((bx + 6.0 * s, by + 10.0 * s), (bx + 11.0 * s, by + 4.0 * s), &paint);
+            } else {
+                paint.set_color(theme.panel_border);
+                paint.set_stroke(true);
+                paint.set_stroke_width(1.0 * s);
+                canvas.draw_round_rect(box_rect, 3.0 * s, 3.0 * s, &paint);
+            }
+            paint.set_stroke(false);
+            paint.set_stroke_width(1.0 * s);
+
+            let label_x = mx + size + 8.0 * s;
+            let label = Self::truncate_text_to_width("Prevent this page from creating additional dialogs", mx + mw - label_x, font);
+            paint.set_color(theme.panel_text);
+            if let Some(blob) = TextBlob::new(&label, font) {
+                let bounds = blob.bounds();
+                let text_y = my + mh / 2.0 - (bounds.top + bounds.height() / 2.0);
+                canvas.draw_text_blob(&blob, (label_x, text_y), &paint);
+            }
+        }
+
+        for (ok, label) in [(true, "OK"), (false, "Cancel")] {
+            let Some((bx, by, bw, bh)) = self.page_dialog_button_rect(ok) else {
+                continue;
+            };
+            let rect = Rect::from_xywh(bx, by, bw, bh);
+            if ok {
+                paint.set_color(theme.primary_button);
+                canvas.draw_round_rect(rect, 6.0 * s, 6.0 * s, &paint);
+                paint.set_color(theme.primary_text);
+            } else {
+                paint.set_color(theme.panel);
+                canvas.draw_round_rect(rect, 6.0 * s, 6.0 * s, &paint);
+                paint.set_color(theme.panel_border);
+                paint.set_stroke(true);
+                canvas.draw_round_rect(rect, 6.0 * s, 6.0 * s, &paint);
+                paint.set_stroke(false);
+                paint.set_color(theme.panel_text);
+            }
+            if let Some(blob) = TextBlob::new(label, font) {
+                let bounds = blob.bounds();
+                let text_x = bx + (bw - bounds.width()) / 2.0;
+                let text_y = by + bh / 2.0 - (bounds.top + bounds.height() / 2.0);
+                canvas.draw_text_blob(&blob, (text_x, text_y), &paint);
+            }
+        }
+    }
+
+    /// Show the find bar with its input field focused and the last query selected
+    pub fn show_find_bar(&mut self) {
+        if !self.find_bar_open {
+            self.find_bar_open = true;
+            self.components.push(UiComponent::TextField {
+                id: "find_input".to_string(),
+                text: self.last_find_query.clone(),
+                x: 0.0,
+                y: 0.0,
+                width: 0.0,
+                height: 0.0,
+                color: theme::to_rgb(self.theme.field),
+                border_color: theme::to_rgb(self.theme.field_border),
+                has_focus: false,
+                cursor_position: 0,
+                selection_start: None,
+                selection_end: None,
+                is_flexible: false,
+            });
+            self.layout_find_bar();
+        }
+        self.set_focus("find_input");
+    }
+
+    /// Hide the find bar, remembering its query for the next time it opens
+    pub fn hide_find_bar(&mut self) {
+        if let Some(query) = self.find_query() {
+            self.last_find_query = query;
+        }
+        self.components.retain(|comp| comp.id() != "find_input");
+        self.find_bar_open = false;
+        self.find_result = None;
+    }
+
+    pub fn is_find_bar_shown(&self) -> bool {
+        self.find_bar_open
+    }
+
+    pub fn is_find_input_focused(&self) -> bool {
+        self.components.iter().any(|comp| {
+            matches!(comp, UiComponent::TextField { id, has_focus: true, .. } if id == "find_input")
+        })
+    }
+
+    /// What is typed into the find bar, while it is shown
+    pub fn find_query(&self) -> Option<String> {
+        self.get_text_field_content("find_input")
+    }
+
+    pub fn find_highlight_all(&self) -> bool {
+        self.find_highlight_all
+    }
+
+    /// Switch "Highlight all" on or off, returning the new setting
+    pub fn toggle_find_highlight_all(&mut self) -> bool {
+        self.find_highlight_all = !self.find_highlight_all;
+        self.find_highlight_all
+    }
+
+    /// Show which match is current and how many the page has, or nothing while there is no answer
+    pub fn set_find_result(&mut self, result: Option<(Option<usize>, usize)>) {
+        self.find_result = result;
+    }
+
+    /// Returns (x, y, width, height) of the find bar, at the top right of the page below any prompt bar
+    fn find_bar_rect(&self) -> Option<(f32, f32, f32, f32)> {
+        if !self.find_bar_open || self.fullscreen {
+            return None;
+        }
+        let s = self.viewport.hidpi_scale;
+        let width = (Self::FIND_BAR_WIDTH * s).min(self.window_width() - 16.0 * s);
+        let top = self.permission_prompt_rect().map_or(self.chrome_height(), |(_, py, _, ph)| py + ph);
+        Some((self.window_width() - width - 8.0 * s, top + 6.0 * s, width, Self::FIND_BAR_HEIGHT * s))
+    }
+
+    /// Returns (x, y, width, height) of one of the find bar's buttons, which sit at its right end
+    fn find_bar_button_rect(&self, button: &str) -> Option<(f32, f32, f32, f32)> {
+        let (x, y, w, h) = self.find_bar_rect()?;
+        let s = self.viewport.hidpi_scale;
+        let mut right = x + w - 6.0 * s;
+        for (id, _, width) in Self::FIND_BAR_BUTTONS.iter().rev() {
+            let width = width * s;
+            if *id == button {
+                return Some((right - width, y + 6.0 * s, width, h - 12.0 * s));
+            }
+            right -= width + 4.0 * s;
+        }
+        None
+    }
+
+    /// Returns (x, y, width, height) of the find bar's input field, at its left end
+    fn find_input_rect(&self) -> Option<(f32, f32, f32, f32)> {
+        let (x, y, _, h) = self.find_bar_rect()?;
+        let s = self.viewport.hidpi_scale;
+        Some((x + 6.0 * s, y + 6.0 * s, Self::FIND_INPUT_WIDTH * s, h - 12.0 * s))
+    }
+
+    /// Move the find bar's input field to match the window size
+    fn layout_find_bar(&mut self) {
+        let Some((ix, iy, iw, ih)) = self.find_input_rect() else {
+            return;
+        };
+        for comp in &mut self.components {
+            if let UiComponent::TextField { id, x, y, width, height, .. } = comp
+                && id == "find_input"
+            {
+                (*x, *y, *width, *height) = (ix, iy, iw, ih);
+            }
+        }
+    }
+
+    /// Check if a click lands on the find bar and return the action id
+    pub fn handle_find_bar_click(&self, x: f32, y: f32) -> Option<String> {
+        let inside = |(bx, by, bw, bh): (f32, f32, f32, f32)| x >= bx && x <= bx + bw && y >= by && y <= by + bh;
+        if !self.find_bar_rect().is_some_and(inside) {
+            return None;
+        }
+        for (id, _, _) in Self::FIND_BAR_BUTTONS {
+            if self.find_bar_button_rect(id).is_some_and(inside) {
+                return Some(id.to_string());
+            }
+        }
+        if self.find_input_rect().is_some_and(inside) {
+            return Some("find_input".to_string());
+        }
+        Some("find_bar_noop".to_string())
+    }
+
+    /// Draw the find bar with its match count and buttons. The input field is a chrome text field
+    /// drawn on top of it with the other components.
+    fn render_find_bar(&self, canvas: &Canvas, font: &Font) {
+        let Some((x, y, w, h)) = self.find_bar_rect() else {
+            return;
+        };
+        let s = self.viewport.hidpi_scale;
+        let theme = &self.theme;
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+
+        let panel = Rect::from_xywh(x, y, w, h);
+        paint.set_color(Color::from_argb(60, 0, 0, 0));
+        canvas.draw_round_rect(Rect::from_xywh(x + 2.0 * s, y + 2.0 * s, w, h), 6.0 * s, 6.0 * s, &paint);
+        paint.set_color(theme.panel);
+        canvas.draw_round_rect(panel, 6.0 * s, 6.0 * s, &paint);
+        paint.set_color(theme.panel_border);
+        paint.set_stroke(true);
+        paint.set_stroke_width(1.0 * s);
+        canvas.draw_round_rect(panel, 6.0 * s, 6.0 * s, &paint);
+        paint.set_stroke(false);
+
+        // The match count goes between the input field and the buttons
+        let count = match self.find_result {
+            Some((Some(active), count)) => format!("{} of {}", active + 1, count),
+            Some((None, _)) => "No results".to_string(),
+            None => String::new(),
+        };
+        if let (Some((ix, _, iw, _)), Some(blob)) = (self.find_input_rect(), TextBlob::new(&count, font)) {
+            let bounds = blob.bounds();
+            paint.set_color(theme.muted_text);
+            let text_y = y + h / 2.0 - (bounds.top + bounds.height() / 2.0);
+            canvas.draw_text_blob(&blob, (ix + iw + 8.0 * s, text_y), &paint);
+        }
+
+        for (id, label, _) in Self::FIND_BAR_BUTTONS {
+            let Some((bx, by, bw, bh)) = self.find_bar_button_rect(id) else {
+                continue;
+            };
+            let pressed = id == "find_highlight_all" && self.find_highlight_all;
+            paint.set_color(if pressed { theme.button_active } else { theme.panel_button });
+            canvas.draw_round_rect(Rect::from_xywh(bx, by, bw, bh), 4.0 * s, 4.0 * s, &paint);
+            paint.set_color(theme.panel_text);
+            if let Some(blob) = TextBlob::new(label, font) {
+                let bounds = blob.bounds();
+                let text_x = bx + (bw - bounds.width()) / 2.0;
+                let text_y = by + bh / 2.0 - (bounds.top + bounds.height() / 2.0);
+                canvas.draw_text_blob(&blob, (text_x, text_y), &paint);
+            }
+        }
     }
 
     pub fn set_bookmarks(&mut self, bookmarks: Vec<BookmarkNode>) {
@@ -740,13 +1844,8 @@ impl BrowserUI {
     }
 
     fn bookmark_row_rect(&self) -> (f32, f32, f32, f32) {
-        let scale = self.viewport.hidpi_scale;
-        (
-            0.0,
-            Self::BOOKMARKS_ROW_Y * scale,
-            self.window_width(),
-            Self::BOOKMARKS_ROW_HEIGHT * scale,
-        )
+        let layout = self.layout();
+        (0.0, layout.bookmarks_y, self.window_width(), layout.bookmarks_height)
     }
 
     fn visible_root_bookmark_layout(&self) -> Vec<(&BookmarkNode, usize, Rect)> {
@@ -831,12 +1930,17 @@ impl BrowserUI {
 
     fn bookmark_context_menu_rect(&self) -> Option<(f32, f32, f32, f32)> {
         let menu = self.bookmark_context_menu.as_ref()?;
-        let scale = self.viewport.hidpi_scale;
         let entries = self.context_menu_entries(menu.target_id.as_deref());
+        Some(self.context_menu_rect(menu.x, menu.y, entries.len()))
+    }
+
+    /// Rectangle of a context menu opened at (x, y), kept inside the window
+    fn context_menu_rect(&self, x: f32, y: f32, rows: usize) -> (f32, f32, f32, f32) {
+        let scale = self.viewport.hidpi_scale;
         let width = Self::BOOKMARK_CONTEXT_WIDTH * scale;
-        let height = entries.len() as f32 * (Self::BOOKMARK_CONTEXT_ROW_HEIGHT * scale);
-        let mut x = menu.x;
-        let mut y = menu.y;
+        let height = rows as f32 * (Self::BOOKMARK_CONTEXT_ROW_HEIGHT * scale);
+        let mut x = x;
+        let mut y = y;
 
         if x + width > self.window_width() {
             x = (self.window_width() - width - 4.0 * scale).max(0.0);
@@ -845,7 +1949,54 @@ impl BrowserUI {
             y = (self.viewport.window_size.1 as f32 - height - 4.0 * scale).max(0.0);
         }
 
-        Some((x, y, width, height))
+        (x, y, width, height)
+    }
+
+    fn tab_context_menu_entries(&self, tab_id: &str) -> Vec<(&'static str, &'static str)> {
+        let pin_label = if self.is_tab_pinned(tab_id) { "Unpin tab" } else { "Pin tab" };
+        vec![
+            ("toggle_pin", pin_label),
+            ("close", "Close tab"),
+        ]
+    }
+
+    fn tab_context_menu_rect(&self) -> Option<(f32, f32, f32, f32)> {
+        let menu = self.tab_context_menu.as_ref()?;
+        let entries = self.tab_context_menu_entries(&menu.tab_id);
+        Some(self.context_menu_rect(menu.x, menu.y, entries.len()))
+    }
+
+    /// Open the context menu of the tab under the given point, if there is one
+    pub fn open_tab_context_menu(&mut self, x: f32, y: f32) -> bool {
+        let Some(tab_id) = self.tab_at_point(x, y) else {
+            return false;
+        };
+        self.bookmark_context_menu = None;
+        self.tab_context_menu = Some(TabContextMenuState { x, y, tab_id });
+        true
+    }
+
+    /// Close the tab context menu, returning whether it was open
+    pub fn close_tab_context_menu(&mut self) -> bool {
+        self.tab_context_menu.take().is_some()
+    }
+
+    /// Any click closes the tab context menu; one on an entry returns its command
+    pub fn handle_tab_context_menu_click(&mut self, x: f32, y: f32) -> Option<TabMenuAction> {
+        let (menu_x, menu_y, menu_w, menu_h) = self.tab_context_menu_rect()?;
+        let menu = self.tab_context_menu.take()?;
+        if x < menu_x || x > menu_x + menu_w || y < menu_y || y > menu_y + menu_h {
+            return None;
+        }
+
+        let row_h = Self::BOOKMARK_CONTEXT_ROW_HEIGHT * self.viewport.hidpi_scale;
+        let idx = ((y - menu_y) / row_h).floor() as usize;
+        let entries = self.tab_context_menu_entries(&menu.tab_id);
+        match entries.get(idx)?.0 {
+            "toggle_pin" => Some(TabMenuAction::TogglePin(menu.tab_id)),
+            "close" => Some(TabMenuAction::Close(menu.tab_id)),
+            _ => None,
+        }
     }
 
     fn handle_bookmark_context_menu_click(&self, x: f32, y: f32) -> Option<BookmarkUiAction> {
@@ -974,36 +2125,36 @@ impl BrowserUI {
         None
     }
 
-    /// Update UI layout when window is resized
+    /// Place the toolbar and the tabs for `viewport`, after the window was resized or its scale
+    /// factor changed
     pub fn update_layout(&mut self, viewport: &Viewport) {
         self.viewport = viewport.clone();
-        let scaled = |v: f32| v * self.viewport.hidpi_scale;
-        let window_width = self.window_width();
+        let placements = self.layout().toolbar(self.window_width());
 
-        // Update address bar width and settings button position
         for comp in &mut self.components {
+            let Some(placement) = placements.iter().find(|placement| placement.id == comp.id()) else {
+                continue;
+            };
             match comp {
-                UiComponent::TextField { id, width, is_flexible: true, .. } if id == "address_bar" => {
-                    let available_width = window_width - scaled(Self::BUTTON_MARGIN * 8.0 + Self::BUTTON_SIZE * 6.0);
-                    *width = available_width.max(scaled(Self::MIN_ADDRESS_BAR_WIDTH));
-                }
-                UiComponent::Button { id, x, .. } if id == "bookmark_toggle" => {
-                    *x = window_width - scaled(Self::BUTTON_MARGIN * 2.0 + Self::BUTTON_SIZE * 2.0);
+                UiComponent::Button { x, y, width, height, .. } | UiComponent::TextField { x, y, width, height, .. } => {
+                    (*x, *y, *width, *height) = (placement.x, placement.y, placement.width, placement.height);
                 }
-                UiComponent::Button { id, x, .. } if id == "settings" => {
-                    *x = window_width - scaled(Self::BUTTON_MARGIN + Self::BUTTON_SIZE);
-                }
-                _ => {}
+                UiComponent::TabButton { .. } => {}
             }
         }
 
         // Update tab layout with dynamic sizing (this will also position the new tab button)
         self.update_tab_layout();
+        self.layout_page_dialog();
+        self.layout_find_bar();
     }
 
     /// Get the height of the chrome bar
     pub fn chrome_height(&self) -> f32 {
-        Self::CHROME_HEIGHT * self.viewport.hidpi_scale
+        if self.fullscreen || self.app_mode {
+            return 0.0;
+        }
+        self.layout().chrome_height
     }
 
     #[inline]
@@ -1019,70 +2170,91 @@ impl BrowserUI {
     /// Calculate the appropriate width for each tab based on the number of tabs
     fn calculate_tab_width(&self) -> f32 {
         let tab_count = self.components.iter()
-            .filter(|c| matches!(c, UiComponent::TabButton { .. }))
+            .filter(|c| matches!(c, UiComponent::TabButton { is_pinned: false, .. }))
             .count();
 
+        let layout = self.layout();
         if tab_count == 0 {
-            return Self::MAX_TAB_WIDTH * self.viewport.hidpi_scale;
+            return layout.px(Self::MAX_TAB_WIDTH);
         }
 
-        // Available width for tabs (use scaled margin and reserve space for new tab button)
-        let scaled_margin = Self::BUTTON_MARGIN * self.viewport.hidpi_scale;
-        let new_tab_button_width = Self::BUTTON_SIZE * self.viewport.hidpi_scale;
-        let available_width = self.window_width() - (scaled_margin * 3.0) - new_tab_button_width;
+        // Available width for tabs (reserve space for the new tab button)
+        let pinned_width = self.pinned_tab_count() as f32 * (layout.px(Self::PINNED_TAB_WIDTH) + layout.tab_spacing);
+        let available_width = self.available_tab_strip_width() - pinned_width;
 
-        // Calculate width that would fit all tabs (use scaled spacing)
-        let scaled_spacing = Self::TAB_SPACING * self.viewport.hidpi_scale;
-        let total_spacing = (tab_count - 1) as f32 * scaled_spacing;
-        let width_per_tab = (available_width - total_spacing) / tab_count as f32;
+        // Calculate width that would fit all tabs
+        let total_spacing = (tab_count - 1) as f32 * layout.tab_spacing;
+        let width_per_tab = ((available_width - total_spacing) / tab_count as f32).floor();
 
-        // Clamp between MIN and MAX (scaled), if it goes below MIN we'll use scrolling
-        let scaled_min = Self::MIN_TAB_WIDTH * self.viewport.hidpi_scale;
-        let scaled_max = Self::MAX_TAB_WIDTH * self.viewport.hidpi_scale;
-        width_per_tab.max(scaled_min).min(scaled_max)
+        // Clamp between MIN and MAX, if it goes below MIN we'll use scrolling
+        width_per_tab.max(layout.px(Self::MIN_TAB_WIDTH)).min(layout.px(Self::MAX_TAB_WIDTH))
+    }
+
+    /// Width of the tab strip left of the new tab button
+    fn available_tab_strip_width(&self) -> f32 {
+        let layout = self.layout();
+        self.window_width() - layout.margin * 3.0 - layout.button_size
+    }
+
+    /// Number of pinned tabs (always laid out before unpinned tabs)
+    pub fn pinned_tab_count(&self) -> usize {
+        self.components.iter()
+            .filter(|c| matches!(c, UiComponent::TabButton { is_pinned: true, .. }))
+            .count()
+    }
+
+    /// Total width of the tab strip, including spacing between tabs
+    fn total_tab_strip_width(&self, tab_width: f32) -> f32 {
+        let layout = self.layout();
+        let pinned_width = layout.px(Self::PINNED_TAB_WIDTH);
+        let widths: Vec<f32> = self.components.iter()
+            .filter_map(|c| match c {
+                UiComponent::TabButton { is_pinned, .. } => Some(if *is_pinned { pinned_width } else { tab_width }),
+                _ => None,
+            })
+            .collect();
+
+        if widths.is_empty() {
+            return 0.0;
+        }
+        widths.iter().sum::<f32>() + (widths.len() - 1) as f32 * layout.tab_spacing
     }
 
     /// Update all tab positions and widths based on current state
     fn update_tab_layout(&mut self) {
-        let scaled_margin = Self::BUTTON_MARGIN * self.viewport.hidpi_scale;
-        let scaled_spacing = Self::TAB_SPACING * self.viewport.hidpi_scale;
-        let new_tab_button_width = Self::BUTTON_SIZE * self.viewport.hidpi_scale;
-
-        // Calculate available width for tabs (reserve space for the new tab button)
-        let available_width_for_tabs = self.window_width() - (scaled_margin * 3.0) - new_tab_button_width;
+        let layout = self.layout();
+        let available_width_for_tabs = self.available_tab_strip_width();
 
         let tab_width = self.calculate_tab_width();
-        let tab_count = self.components.iter()
-            .filter(|c| matches!(c, UiComponent::TabButton { .. }))
-            .count();
+        let pinned_tab_width = layout.px(Self::PINNED_TAB_WIDTH);
 
-        // Calculate total width needed for all tabs (use scaled spacing)
-        let total_tab_width = if tab_count > 0 {
-            tab_count as f32 * tab_width + (tab_count.saturating_sub(1)) as f32 * scaled_spacing
-        } else {
-            0.0
-        };
+        // Calculate total width needed for all tabs
+        let total_tab_width = self.total_tab_strip_width(tab_width);
 
         // Update scroll offset bounds
         let max_scroll = (total_tab_width - available_width_for_tabs).max(0.0);
         self.tab_scroll_offset = self.tab_scroll_offset.min(max_scroll).max(0.0);
 
-        // Update each tab's position and width
-        let mut tab_x = scaled_margin - self.tab_scroll_offset;
+        // Update each tab's position and size; the scroll offset is rounded so tab edges stay on
+        // whole device pixels
+        let scroll_offset = self.tab_scroll_offset.round();
+        let mut tab_x = layout.margin - scroll_offset;
         for comp in &mut self.components {
-            if let UiComponent::TabButton { x, width, .. } = comp {
+            if let UiComponent::TabButton { x, y, width, height, is_pinned, .. } = comp {
                 *x = tab_x;
-                *width = tab_width;
-                tab_x += tab_width + scaled_spacing;
+                *y = layout.tab_y;
+                *width = if *is_pinned { pinned_tab_width } else { tab_width };
+                *height = layout.tab_height;
+                tab_x += *width + layout.tab_spacing;
             }
         }
 
         // Position the "New Tab" button to the right of all tabs
-        let new_tab_button_x = scaled_margin + total_tab_width - self.tab_scroll_offset + scaled_spacing;
+        let new_tab_button_x = layout.margin + total_tab_width - scroll_offset + layout.tab_spacing;
         for comp in &mut self.components {
-            if let UiComponent::Button { id, x, .. } = comp {
+            if let UiComponent::Button { id, x, y, width, height, .. } = comp {
                 if id == "new_tab" {
-                    *x = new_tab_button_x;
+                    (*x, *y, *width, *height) = (new_tab_button_x, layout.tab_y, layout.button_size, layout.button_size);
                 }
             }
         }
@@ -1099,14 +2271,10 @@ impl BrowserUI {
         }
 
         let tab_width = self.calculate_tab_width();
-        let scaled_spacing = Self::TAB_SPACING * self.viewport.hidpi_scale;
-        let total_tab_width = tab_count as f32 * tab_width +
-                              (tab_count.saturating_sub(1)) as f32 * scaled_spacing;
+        let total_tab_width = self.total_tab_strip_width(tab_width);
 
-        // Only allow scrolling if tabs overflow (use scaled margin and account for new tab button)
-        let scaled_margin = Self::BUTTON_MARGIN * self.viewport.hidpi_scale;
-        let new_tab_button_width = Self::BUTTON_SIZE * self.viewport.hidpi_scale;
-        let available_width = self.window_width() - (scaled_margin * 3.0) - new_tab_button_width;
+        // Only allow scrolling if tabs overflow
+        let available_width = self.available_tab_strip_width();
 
         if total_tab_width > available_width {
             // Scroll by a portion of a tab width
@@ -1182,27 +2350,51 @@ impl BrowserUI {
 
         format!("{}{}", &text[..truncate_at], ellipsis)
     }
+
+    /// Break `text` into lines no wider than `max_width`, at spaces and its own line breaks. Past
+    /// `max_lines` the text is cut off with an ellipsis, as are words too long for a line.
+    fn wrap_text_to_width(text: &str, max_width: f32, font: &Font, max_lines: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        for paragraph in text.lines() {
+            let mut line = String::new();
+            for word in paragraph.split_whitespace() {
+                let candidate = if line.is_empty() { word.to_string() } else { format!("{line} {word}") };
+                if line.is_empty() || font.measure_str(&candidate, None).0 <= max_width {
+                    line = candidate;
+                } else {
+                    lines.push(std::mem::replace(&mut line, word.to_string()));
+                }
+            }
+            lines.push(line);
+        }
+        if lines.len() > max_lines {
+            lines.truncate(max_lines);
+            if let Some(last) = lines.last_mut() {
+                last.push_str(" ...");
+            }
+        }
+        lines.into_iter().map(|line| Self::truncate_text_to_width(&line, max_width, font)).collect()
+    }
 }
 
 impl BrowserUI {
     /// Add a new tab
     pub fn add_tab(&mut self, id: &str, title: &str) {
-        let tab_count = self.components.iter().filter(|c| matches!(c, UiComponent::TabButton { .. })).count();
-
         // Set all existing tabs to inactive
+        let (active_color, inactive_color) = (theme::to_rgb(self.theme.tab_active), theme::to_rgb(self.theme.tab));
         for comp in &mut self.components {
             if let UiComponent::TabButton { is_active, color, .. } = comp {
                 *is_active = false;
-                *color = [0.8, 0.8, 0.8]; // Inactive color
+                *color = inactive_color;
             }
         }
 
-        // Add the new tab as active
-        let x = Self::BUTTON_MARGIN + (tab_count as f32 * 158.0); // 150 width + 8 spacing
-        let mut new_tab = UiComponent::tab(id, title, x, self.viewport.hidpi_scale);
-        if let UiComponent::TabButton { is_active, color, .. } = &mut new_tab {
+        // Add the new tab as active; update_tab_layout places it
+        let mut new_tab = UiComponent::tab(id, title);
+        if let UiComponent::TabButton { is_active, color, hover_color, .. } = &mut new_tab {
             *is_active = true;
-            *color = [0.95, 0.95, 0.95];
+            *color = active_color;
+            *hover_color = theme::to_rgb(self.theme.button_hover);
         }
         self.components.push(new_tab);
 
@@ -1212,14 +2404,15 @@ impl BrowserUI {
 
     /// Set active tab
     pub fn set_active_tab(&mut self, tab_id: &str) {
+        let (active_color, inactive_color) = (theme::to_rgb(self.theme.tab_active), theme::to_rgb(self.theme.tab));
         for comp in &mut self.components {
             if let UiComponent::TabButton { id, is_active, color, .. } = comp {
                 if id == tab_id {
                     *is_active = true;
-                    *color = [0.95, 0.95, 0.95]; // Active color
+                    *color = active_color;
                 } else {
                     *is_active = false;
-                    *color = [0.8, 0.8, 0.8]; // Inactive color
+                    *color = inactive_color;
                 }
             }
         }
@@ -1237,6 +2430,9 @@ impl BrowserUI {
         });
 
         let removed = self.components.len() < initial_count;
+        if self.tab_context_menu.as_ref().is_some_and(|menu| menu.tab_id == tab_id) {
+            self.tab_context_menu = None;
+        }
 
         if removed {
             // Update tab layout after removing
@@ -1269,12 +2465,13 @@ impl BrowserUI {
         None
     }
 
-    /// Update the address bar with a new URL
+    /// Update the address bar with a new URL. The field holds the whole URL for editing; while it
+    /// isn't focused it shows the shorter form from `url_display::for_display`.
     pub fn update_address_bar(&mut self, url: &str) {
         for comp in &mut self.components {
             if let UiComponent::TextField { id, text, .. } = comp {
                 if id == "address_bar" {
-                    *text = url.to_string();
+                    *text = url_display::for_editing(url);
                 }
             }
         }
@@ -1303,6 +2500,29 @@ impl BrowserUI {
         }
     }
 
+    pub fn set_tab_audio(&mut self, tab_id: &str, audible: bool, muted: bool) {
+        for comp in &mut self.components {
+            if let UiComponent::TabButton { id, is_audible, is_muted, .. } = comp {
+                if id == tab_id {
+                    *is_audible = audible;
+                    *is_muted = muted;
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn set_tab_discarded(&mut self, tab_id: &str, discarded: bool) {
+        for comp in &mut self.components {
+            if let UiComponent::TabButton { id, is_discarded, .. } = comp {
+                if id == tab_id {
+                    *is_discarded = discarded;
+                    break;
+                }
+            }
+        }
+    }
+
     pub fn update_tab_favicon(&mut self, tab_id: &str, favicon: Option<&[u8]>) {
         for comp in &mut self.components {
             if let UiComponent::TabButton { id, favicon: tab_favicon, .. } = comp {
@@ -1370,8 +2590,8 @@ impl BrowserUI {
     /// Check if click is on close button of active tab, returns tab ID if so
     pub fn check_close_button_click(&self, x: f32, y: f32) -> Option<String> {
         for comp in &self.components {
-            if let UiComponent::TabButton { id, x: tab_x, y: tab_y, width, height, is_active, .. } = comp {
-                if *is_active {
+            if let UiComponent::TabButton { id, x: tab_x, y: tab_y, width, height, is_active, is_pinned, .. } = comp {
+                if *is_active && !*is_pinned {
                     // Calculate close button bounds
                     let close_button_size = 16.0 * self.viewport.hidpi_scale;
                     let close_button_x = tab_x + width - close_button_size - (4.0 * self.viewport.hidpi_scale);
@@ -1388,6 +2608,29 @@ impl BrowserUI {
         None
     }
 
+    /// Where a tab's speaker is drawn, right after its favicon. Only tabs that play sound or were
+    /// muted show one, and pinned tabs are too narrow for it.
+    fn tab_audio_button_rect(tab: Rect, is_pinned: bool, is_audible: bool, is_muted: bool, hidpi_scale: f32) -> Option<Rect> {
+        if is_pinned || !(is_audible || is_muted) {
+            return None;
+        }
+        let size = 16.0 * hidpi_scale;
+        let x = tab.left() + (8.0 + 16.0 + 4.0) * hidpi_scale;
+        Some(Rect::from_xywh(x, tab.center_y() - size / 2.0, size, size))
+    }
+
+    /// Check if click is on a tab's speaker, returns tab ID if so
+    pub fn check_tab_audio_button_click(&self, x: f32, y: f32) -> Option<String> {
+        self.components.iter().find_map(|comp| match comp {
+            UiComponent::TabButton { id, x: tab_x, y: tab_y, width, height, is_pinned, is_audible, is_muted, .. } => {
+                let tab = Rect::from_xywh(*tab_x, *tab_y, *width, *height);
+                let rect = Self::tab_audio_button_rect(tab, *is_pinned, *is_audible, *is_muted, self.viewport.hidpi_scale)?;
+                (x >= rect.left() && x <= rect.right() && y >= rect.top() && y <= rect.bottom()).then(|| id.clone())
+            }
+            _ => None,
+        })
+    }
+
     /// Check if a point is over the close button of an active tab
     fn is_point_over_close_button(&self, x: f32, y: f32, tab_x: f32, tab_y: f32, tab_width: f32, tab_height: f32, is_active: bool) -> bool {
         if !is_active {
@@ -1695,6 +2938,14 @@ impl BrowserUI {
         None
     }
 
+    /// Text in the address bar while the user is editing it
+    pub fn address_bar_input(&self) -> Option<&str> {
+        self.components.iter().find_map(|comp| match comp {
+            UiComponent::TextField { id, text, has_focus: true, .. } if id == "address_bar" => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
     /// Get the current text of a text field
     pub fn get_text_field_content(&self, field_id: &str) -> Option<String> {
         for comp in &self.components {
@@ -1707,36 +2958,12 @@ impl BrowserUI {
         None
     }
 
-    /// Update scale factor for DPI changes
-    pub fn update_scale(&mut self, hidpi_scale: f32, old_hidpi_scale: f32) {
-        // Rescale all components
-        let scale_ratio = hidpi_scale / old_hidpi_scale;
-
-        for comp in &mut self.components {
-            match comp {
-                UiComponent::Button { x, y, width, height, .. } => {
-                    *x *= scale_ratio;
-                    *y *= scale_ratio;
-                    *width *= scale_ratio;
-                    *height *= scale_ratio;
-                }
-                UiComponent::TextField { x, y, width, height, .. } => {
-                    *x *= scale_ratio;
-                    *y *= scale_ratio;
-                    *width *= scale_ratio;
-                    *height *= scale_ratio;
-                }
-                UiComponent::TabButton { x, y, width, height, .. } => {
-                    *x *= scale_ratio;
-                    *y *= scale_ratio;
-                    *width *= scale_ratio;
-                    *height *= scale_ratio
-                }
-            }
-        }
-
-        // Update layout to recalculate positions properly
-        self.update_layout(&self.viewport.clone());
+    /// Lay the chrome out again for a new scale factor. Everything is placed from the logical
+    /// metrics, rather than scaling the old positions, so nothing drifts off the pixel grid.
+    pub fn update_scale(&mut self, hidpi_scale: f32) {
+        let mut viewport = self.viewport.clone();
+        viewport.hidpi_scale = hidpi_scale;
+        self.update_layout(&viewport);
     }
 
     /// Clear focus from all components
@@ -1851,9 +3078,34 @@ impl BrowserUI {
                     text.insert_str(*cursor_position, insert_text);
                     *cursor_position += insert_text.len();
                 }
-                break;
+                break;
+            }
+        }
+    }
+
+    /// Focus a text field and insert `insert_text` where it was clicked, as a middle-click paste
+    /// of the primary selection does
+    pub fn paste_at_click(&mut self, component_id: &str, click_x: f32, insert_text: &str) {
+        let position = self.components.iter().find_map(|comp| match comp {
+            UiComponent::TextField { id, text, x, .. } if id == component_id => {
+                Some(self.calculate_cursor_position_from_click(text, *x, click_x))
+            }
+            _ => None,
+        });
+        let Some(position) = position else {
+            return;
+        };
+        for comp in &mut self.components {
+            if let UiComponent::TextField { id, has_focus, cursor_position, selection_start, selection_end, .. } = comp {
+                *has_focus = id == component_id;
+                if *has_focus {
+                    *cursor_position = position;
+                    *selection_start = None;
+                    *selection_end = None;
+                }
             }
         }
+        self.insert_text_at_cursor(insert_text);
     }
 
     /// Begin a mouse-driven selection gesture for a text field.
@@ -1921,6 +3173,60 @@ impl BrowserUI {
         self.show_settings = !self.show_settings;
     }
 
+    /// Replace the list of recently closed tab titles shown in the settings panel
+    pub fn set_recently_closed(&mut self, titles: Vec<String>) {
+        self.recently_closed = titles;
+    }
+
+    /// Show the active site's color scheme choice in the settings panel
+    pub fn set_site_color_scheme(&mut self, value: ColorSchemeOverride) {
+        self.site_color_scheme = value;
+    }
+
+    /// Show the active tab's connection security in the address bar and site information panel
+    pub fn set_security_state(&mut self, state: SecurityState) {
+        self.security = state;
+    }
+
+    /// Toggle the site information panel, closing the settings panel
+    pub fn toggle_site_info(&mut self) {
+        self.show_site_info = !self.show_site_info;
+        if self.show_site_info {
+            self.show_settings = false;
+        }
+    }
+
+    /// Show whether the active site has forced dark colors in the settings panel
+    pub fn set_site_force_dark(&mut self, value: bool) {
+        self.site_force_dark = value;
+    }
+
+    /// Show the active site's autoplay choice in the settings panel
+    pub fn set_site_autoplay(&mut self, value: AutoplayPolicy) {
+        self.site_autoplay = value;
+    }
+
+    /// Show whether the active site may run JavaScript in the address bar and settings panel
+    pub fn set_site_javascript(&mut self, value: bool) {
+        self.site_javascript = value;
+    }
+
+    /// Show how many popups the active page was stopped from opening in the address bar
+    pub fn set_popups_blocked(&mut self, count: usize) {
+        self.popups_blocked = count;
+    }
+
+    /// Show the global JavaScript setting in the settings panel
+    pub fn set_javascript_enabled(&mut self, value: bool) {
+        self.javascript_enabled = value;
+    }
+
+    /// Show whether the active site loads images, and whether sites do by default, in the settings panel
+    pub fn set_image_settings(&mut self, site: bool, global: bool) {
+        self.site_images = site;
+        self.images_enabled = global;
+    }
+
     /// Check if a click lands inside the settings panel and return the action id
     pub fn handle_settings_panel_click(&self, x: f32, y: f32) -> Option<String> {
         if !self.show_settings {
@@ -1936,6 +3242,87 @@ impl BrowserUI {
         if x >= btn.0 && x <= btn.0 + btn.2 && y >= btn.1 && y <= btn.1 + btn.3 {
             return Some("set_default_browser".to_string());
         }
+        let btn = self.save_page_button_rect();
+        if x >= btn.0 && x <= btn.0 + btn.2 && y >= btn.1 && y <= btn.1 + btn.3 {
+            return Some("save_page".to_string());
+        }
+        for (full_page, id) in [(false, "screenshot_viewport"), (true, "screenshot_full_page")] {
+            let btn = self.screenshot_button_rect(full_page);
+            if x >= btn.0 && x <= btn.0 + btn.2 && y >= btn.1 && y <= btn.1 + btn.3 {
+                return Some(id.to_string());
+            }
+        }
+        for (network, id) in [(false, "set_location"), (true, "emulate_network_conditions")] {
+            let btn = self.location_button_rect(network);
+            if x >= btn.0 && x <= btn.0 + btn.2 && y >= btn.1 && y <= btn.1 + btn.3 {
+                return Some(id.to_string());
+            }
+        }
+        let btn = self.languages_button_rect();
+        if x >= btn.0 && x <= btn.0 + btn.2 && y >= btn.1 && y <= btn.1 + btn.3 {
+            return Some("set_languages".to_string());
+        }
+        let btn = self.site_color_scheme_button_rect();
+        if x >= btn.0 && x <= btn.0 + btn.2 && y >= btn.1 && y <= btn.1 + btn.3 {
+            return Some("cycle_site_color_scheme".to_string());
+        }
+        let btn = self.force_dark_button_rect();
+        if x >= btn.0 && x <= btn.0 + btn.2 && y >= btn.1 && y <= btn.1 + btn.3 {
+            return Some("toggle_site_force_dark".to_string());
+        }
+        let btn = self.autoplay_button_rect();
+        if x >= btn.0 && x <= btn.0 + btn.2 && y >= btn.1 && y <= btn.1 + btn.3 {
+            return Some("cycle_site_autoplay".to_string());
+        }
+        for (global, id) in [(false, "toggle_site_javascript"), (true, "toggle_global_javascript")] {
+            let btn = self.javascript_button_rect(global);
+            if x >= btn.0 && x <= btn.0 + btn.2 && y >= btn.1 && y <= btn.1 + btn.3 {
+                return Some(id.to_string());
+            }
+        }
+        for (global, id) in [(false, "toggle_site_images"), (true, "toggle_global_images")] {
+            let btn = self.images_button_rect(global);
+            if x >= btn.0 && x <= btn.0 + btn.2 && y >= btn.1 && y <= btn.1 + btn.3 {
+                return Some(id.to_string());
+            }
+        }
+        for (passwords, id) in [(false, "manage_autofill"), (true, "open_passwords")] {
+            let btn = self.autofill_button_rect(passwords);
+            if x >= btn.0 && x <= btn.0 + btn.2 && y >= btn.1 && y <= btn.1 + btn.3 {
+                return Some(id.to_string());
+            }
+        }
+        for (cookies, id) in [(false, "open_extensions"), (true, "open_cookies")] {
+            let btn = self.extensions_button_rect(cookies);
+            if x >= btn.0 && x <= btn.0 + btn.2 && y >= btn.1 && y <= btn.1 + btn.3 {
+                return Some(id.to_string());
+            }
+        }
+        for (latency, id) in [(false, "open_profile"), (true, "cycle_max_latency")] {
+            let btn = self.profile_button_rect(latency);
+            if x >= btn.0 && x <= btn.0 + btn.2 && y >= btn.1 && y <= btn.1 + btn.3 {
+                return Some(id.to_string());
+            }
+        }
+        for (density, id) in [(false, "cycle_ui_theme"), (true, "toggle_ui_density")] {
+            let btn = self.theme_button_rect(density);
+            if x >= btn.0 && x <= btn.0 + btn.2 && y >= btn.1 && y <= btn.1 + btn.3 {
+                return Some(id.to_string());
+            }
+        }
+        for (install, id) in [(false, "open_site_storage"), (true, "install_web_app")] {
+            let btn = self.site_storage_button_rect(install);
+            if x >= btn.0 && x <= btn.0 + btn.2 && y >= btn.1 && y <= btn.1 + btn.3 {
+                return Some(id.to_string());
+            }
+        }
+        // Check entries of the "Recently closed" submenu
+        for index in 0..self.recently_closed_row_count() {
+            let (rx, ry, rw, rh) = self.recently_closed_row_rect(index);
+            if x >= rx && x <= rx + rw && y >= ry && y <= ry + rh {
+                return Some(format!("restore_closed:{}", index));
+            }
+        }
         // Click inside panel but not on any button — consume the event
         Some("settings_panel_noop".to_string())
     }
@@ -1944,7 +3331,13 @@ impl BrowserUI {
     fn settings_panel_rect(&self) -> (f32, f32, f32, f32) {
         let s = self.viewport.hidpi_scale;
         let panel_width = 260.0 * s;
-        let panel_height = 120.0 * s;
+        let recently_closed_height = if self.recently_closed.is_empty() {
+            0.0
+        } else {
+            // Section heading plus one row per entry
+            (Self::RECENTLY_CLOSED_ROW_HEIGHT * (self.recently_closed_row_count() + 1) as f32 + 8.0) * s
+        };
+        let panel_height = Self::SETTINGS_BUTTONS_HEIGHT * s + recently_closed_height;
         let window_width = self.window_width();
         let chrome_height = self.chrome_height();
         let x = (window_width - panel_width - 8.0 * s).max(0.0);
@@ -1965,6 +3358,149 @@ impl BrowserUI {
         (btn_x, btn_y, btn_width, btn_height)
     }
 
+    /// Returns (x, y, width, height) for the "Save Page As…" button below the default browser button
+    fn save_page_button_rect(&self) -> (f32, f32, f32, f32) {
+        let s = self.viewport.hidpi_scale;
+        let (bx, by, bw, bh) = self.default_browser_button_rect();
+        (bx, by + bh + 8.0 * s, bw, bh)
+    }
+
+    /// Returns (x, y, width, height) for the "Screenshot" buttons, which share a row below "Save Page As…"
+    fn screenshot_button_rect(&self, full_page: bool) -> (f32, f32, f32, f32) {
+        let s = self.viewport.hidpi_scale;
+        let (bx, by, bw, bh) = self.save_page_button_rect();
+        let gap = 8.0 * s;
+        let width = (bw - gap) / 2.0;
+        let x = if full_page { bx + width + gap } else { bx };
+        (x, by + bh + gap, width, bh)
+    }
+
+    /// Returns (x, y, width, height) for the "Location…" button below the screenshot row, or the
+    /// "Network…" button beside it
+    fn location_button_rect(&self, network: bool) -> (f32, f32, f32, f32) {
+        let s = self.viewport.hidpi_scale;
+        let (bx, by, bw, bh) = self.save_page_button_rect();
+        let gap = 8.0 * s;
+        let width = (bw - gap) / 2.0;
+        let x = if network { bx + width + gap } else { bx };
+        (x, by + (bh + gap) * 2.0, width, bh)
+    }
+
+    /// Returns (x, y, width, height) for the "Languages…" button below "Location…"
+    fn languages_button_rect(&self) -> (f32, f32, f32, f32) {
+        let s = self.viewport.hidpi_scale;
+        let (bx, by, _, bh) = self.location_button_rect(false);
+        let (_, _, bw, _) = self.save_page_button_rect();
+        (bx, by + bh + 8.0 * s, bw, bh)
+    }
+
+    /// Returns (x, y, width, height) for the "Page colors" button below "Languages…"
+    fn site_color_scheme_button_rect(&self) -> (f32, f32, f32, f32) {
+        let s = self.viewport.hidpi_scale;
+        let (bx, by, bw, bh) = self.languages_button_rect();
+        (bx, by + bh + 8.0 * s, bw, bh)
+    }
+
+    /// Returns (x, y, width, height) for the "Force dark" button below "Page colors"
+    fn force_dark_button_rect(&self) -> (f32, f32, f32, f32) {
+        let s = self.viewport.hidpi_scale;
+        let (bx, by, bw, bh) = self.site_color_scheme_button_rect();
+        (bx, by + bh + 8.0 * s, bw, bh)
+    }
+
+    /// Returns (x, y, width, height) for the "Autoplay" button below "Force dark"
+    fn autoplay_button_rect(&self) -> (f32, f32, f32, f32) {
+        let s = self.viewport.hidpi_scale;
+        let (bx, by, bw, bh) = self.force_dark_button_rect();
+        (bx, by + bh + 8.0 * s, bw, bh)
+    }
+
+    /// Returns (x, y, width, height) for the "JavaScript" buttons below "Autoplay": the active
+    /// site's setting, then the one for all sites
+    fn javascript_button_rect(&self, global: bool) -> (f32, f32, f32, f32) {
+        let s = self.viewport.hidpi_scale;
+        let (bx, by, bw, bh) = self.autoplay_button_rect();
+        let rows = if global { 2.0 } else { 1.0 };
+        (bx, by + (bh + 8.0 * s) * rows, bw, bh)
+    }
+
+    /// Returns (x, y, width, height) for the "Images" buttons below the "JavaScript" ones
+    fn images_button_rect(&self, global: bool) -> (f32, f32, f32, f32) {
+        let s = self.viewport.hidpi_scale;
+        let (bx, by, bw, bh) = self.javascript_button_rect(true);
+        let rows = if global { 2.0 } else { 1.0 };
+        (bx, by + (bh + 8.0 * s) * rows, bw, bh)
+    }
+
+    /// Returns (x, y, width, height) for the "Addresses…" and "Passwords…" buttons, which share a
+    /// row below the "Images" ones
+    fn autofill_button_rect(&self, passwords: bool) -> (f32, f32, f32, f32) {
+        let s = self.viewport.hidpi_scale;
+        let (bx, by, bw, bh) = self.images_button_rect(true);
+        let gap = 8.0 * s;
+        let width = (bw - gap) / 2.0;
+        let x = if passwords { bx + width + gap } else { bx };
+        (x, by + bh + gap, width, bh)
+    }
+
+    /// Returns (x, y, width, height) for the "Extensions…" and "Cookies…" buttons, which share a
+    /// row below "Addresses…" and "Passwords…"
+    fn extensions_button_rect(&self, cookies: bool) -> (f32, f32, f32, f32) {
+        let s = self.viewport.hidpi_scale;
+        let (bx, by, bw, bh) = self.images_button_rect(true);
+        let gap = 8.0 * s;
+        let width = (bw - gap) / 2.0;
+        let x = if cookies { bx + width + gap } else { bx };
+        (x, by + (bh + gap) * 2.0, width, bh)
+    }
+
+    /// Returns (x, y, width, height) for the "Profile" and "Latency" buttons, which share a row
+    /// below "Extensions…" and "Cookies…"
+    fn profile_button_rect(&self, latency: bool) -> (f32, f32, f32, f32) {
+        let s = self.viewport.hidpi_scale;
+        let (bx, by, bw, bh) = self.images_button_rect(true);
+        let gap = 8.0 * s;
+        let width = (bw - gap) / 2.0;
+        let x = if latency { bx + width + gap } else { bx };
+        (x, by + (bh + gap) * 3.0, width, bh)
+    }
+
+    /// Returns (x, y, width, height) for the "Theme" and "Density" buttons, which share a row
+    /// below "Profile" and "Latency"
+    fn theme_button_rect(&self, density: bool) -> (f32, f32, f32, f32) {
+        let s = self.viewport.hidpi_scale;
+        let (bx, by, bw, bh) = self.images_button_rect(true);
+        let gap = 8.0 * s;
+        let width = (bw - gap) / 2.0;
+        let x = if density { bx + width + gap } else { bx };
+        (x, by + (bh + gap) * 4.0, width, bh)
+    }
+
+    /// Returns (x, y, width, height) for the "Site storage…" and "Install" buttons, which share a
+    /// row below "Theme" and "Density"
+    fn site_storage_button_rect(&self, install: bool) -> (f32, f32, f32, f32) {
+        let s = self.viewport.hidpi_scale;
+        let (bx, by, bw, bh) = self.images_button_rect(true);
+        let gap = 8.0 * s;
+        let width = (bw - gap) / 2.0;
+        let x = if install { bx + width + gap } else { bx };
+        (x, by + (bh + gap) * 5.0, width, bh)
+    }
+
+    fn recently_closed_row_count(&self) -> usize {
+        self.recently_closed.len().min(Self::MAX_RECENTLY_CLOSED_ROWS)
+    }
+
+    /// Returns (x, y, width, height) for an entry of the "Recently closed" submenu
+    fn recently_closed_row_rect(&self, index: usize) -> (f32, f32, f32, f32) {
+        let s = self.viewport.hidpi_scale;
+        let (px, py, pw, _ph) = self.settings_panel_rect();
+        let row_height = Self::RECENTLY_CLOSED_ROW_HEIGHT * s;
+        // Rows start below the panel buttons and the section heading
+        let top = py + Self::SETTINGS_BUTTONS_HEIGHT * s + row_height;
+        (px + 8.0 * s, top + row_height * index as f32, pw - 16.0 * s, row_height)
+    }
+
     /// Render the settings panel overlay
     pub fn render_settings_panel(&self, canvas: &Canvas, font: &Font) {
         if !self.show_settings {
@@ -1972,6 +3508,7 @@ impl BrowserUI {
         }
 
         let s = self.viewport.hidpi_scale;
+        let theme = &self.theme;
         let mut paint = Paint::default();
         let (px, py, pw, ph) = self.settings_panel_rect();
         let panel_rect = Rect::from_xywh(px, py, pw, ph);
@@ -1981,18 +3518,18 @@ impl BrowserUI {
         canvas.draw_round_rect(Rect::from_xywh(px + 3.0 * s, py + 3.0 * s, pw, ph), 8.0 * s, 8.0 * s, &paint);
 
         // Panel background
-        paint.set_color(Color::from_rgb(250, 250, 252));
+        paint.set_color(theme.panel);
         canvas.draw_round_rect(panel_rect, 8.0 * s, 8.0 * s, &paint);
 
         // Panel border
-        paint.set_color(Color::from_rgb(200, 200, 210));
+        paint.set_color(theme.panel_border);
         paint.set_stroke(true);
         paint.set_stroke_width(1.0 * s);
         canvas.draw_round_rect(panel_rect, 8.0 * s, 8.0 * s, &paint);
         paint.set_stroke(false);
 
         // Title "Settings"
-        paint.set_color(Color::from_rgb(40, 40, 40));
+        paint.set_color(theme.panel_text);
         let title = "Settings";
         if let Some(blob) = TextBlob::new(title, font) {
             let bounds = blob.bounds();
@@ -2001,7 +3538,7 @@ impl BrowserUI {
         }
 
         // Separator line
-        paint.set_color(Color::from_rgb(220, 220, 220));
+        paint.set_color(theme.panel_separator);
         paint.set_stroke(true);
         paint.set_stroke_width(1.0 * s);
         canvas.draw_line((px + 8.0 * s, py + 40.0 * s), (px + pw - 8.0 * s, py + 40.0 * s), &paint);
@@ -2010,11 +3547,11 @@ impl BrowserUI {
         // "Set as Default Browser" button
         let (bx, by, bw, bh) = self.default_browser_button_rect();
         let btn_rect = Rect::from_xywh(bx, by, bw, bh);
-        paint.set_color(Color::from_rgb(70, 130, 220));
+        paint.set_color(theme.primary_button);
         canvas.draw_round_rect(btn_rect, 6.0 * s, 6.0 * s, &paint);
 
         // Button label
-        paint.set_color(Color::WHITE);
+        paint.set_color(theme.primary_text);
         let label = "Set as Default Browser";
         if let Some(blob) = TextBlob::new(label, font) {
             let bounds = blob.bounds();
@@ -2022,6 +3559,234 @@ impl BrowserUI {
             let text_y = by + (bh / 2.0) - (bounds.top + bounds.height() / 2.0);
             canvas.draw_text_blob(&blob, (text_x, text_y), &paint);
         }
+
+        // "Save Page As…", "Screenshot", "Location…", "Languages…", "Page colors", "Force dark", "Autoplay",
+        // "JavaScript", "Images", "Addresses…", "Passwords…", "Extensions…", "Cookies…", "Profile",
+        // "Theme", "Density", "Site storage…" and "Install" buttons
+        let site_color_scheme_label = format!("Page colors: {}", self.site_color_scheme.label());
+        let force_dark_label = format!("Force dark: {}", if self.site_force_dark { "On" } else { "Off" });
+        let site_autoplay_label = format!("Autoplay: {}", self.site_autoplay.label());
+        let site_javascript_label = format!("JavaScript: {}", if self.site_javascript { "Allowed" } else { "Blocked" });
+        let javascript_label = format!("JavaScript (all sites): {}", if self.javascript_enabled { "On" } else { "Off" });
+        let site_images_label = format!("Images: {}", if self.site_images { "Shown" } else { "Placeholders" });
+        let images_label = format!("Images (all sites): {}", if self.images_enabled { "On" } else { "Off" });
+        let profile_label = format!("Profile: {}…", crate::profile::current_name());
+        let latency_label = format!("Latency: {}", self.frame_timing_settings.label());
+        let theme_label = format!("Theme: {}", self.theme_settings.theme.label());
+        let density_label = format!("Density: {}", self.theme_settings.density.label());
+        // The app's name is cut short to fit the half-width button
+        let install_label = match &self.installable_app {
+            Some(name) if name.chars().count() > 12 => format!("Install {}…", name.chars().take(11).collect::<String>()),
+            Some(name) => format!("Install {name}…"),
+            None => "Install app…".to_string(),
+        };
+        let secondary_buttons = [
+            (self.save_page_button_rect(), "Save Page As…"),
+            (self.screenshot_button_rect(false), "Screenshot"),
+            (self.screenshot_button_rect(true), "Full Page"),
+            (self.location_button_rect(false), "Location…"),
+            (self.location_button_rect(true), "Network…"),
+            (self.languages_button_rect(), "Languages…"),
+            (self.site_color_scheme_button_rect(), site_color_scheme_label.as_str()),
+            (self.force_dark_button_rect(), force_dark_label.as_str()),
+            (self.autoplay_button_rect(), site_autoplay_label.as_str()),
+            (self.javascript_button_rect(false), site_javascript_label.as_str()),
+            (self.javascript_button_rect(true), javascript_label.as_str()),
+            (self.images_button_rect(false), site_images_label.as_str()),
+            (self.images_button_rect(true), images_label.as_str()),
+            (self.autofill_button_rect(false), "Addresses…"),
+            (self.autofill_button_rect(true), "Passwords…"),
+            (self.extensions_button_rect(false), "Extensions…"),
+            (self.extensions_button_rect(true), "Cookies…"),
+            (self.profile_button_rect(false), profile_label.as_str()),
+            (self.profile_button_rect(true), latency_label.as_str()),
+            (self.theme_button_rect(false), theme_label.as_str()),
+            (self.theme_button_rect(true), density_label.as_str()),
+            (self.site_storage_button_rect(false), "Site storage…"),
+            (self.site_storage_button_rect(true), install_label.as_str()),
+        ];
+        for ((bx, by, bw, bh), label) in secondary_buttons {
+            let btn_rect = Rect::from_xywh(bx, by, bw, bh);
+            paint.set_color(theme.panel_button);
+            canvas.draw_round_rect(btn_rect, 6.0 * s, 6.0 * s, &paint);
+            paint.set_color(theme.panel_border);
+            paint.set_stroke(true);
+            paint.set_stroke_width(1.0 * s);
+            canvas.draw_round_rect(btn_rect, 6.0 * s, 6.0 * s, &paint);
+            paint.set_stroke(false);
+
+            paint.set_color(theme.panel_text);
+            if let Some(blob) = TextBlob::new(label, font) {
+                let bounds = blob.bounds();
+                let text_x = bx + (bw - bounds.width()) / 2.0;
+                let text_y = by + (bh / 2.0) - (bounds.top + bounds.height() / 2.0);
+                canvas.draw_text_blob(&blob, (text_x, text_y), &paint);
+            }
+        }
+
+        // "Recently closed" submenu
+        if self.recently_closed.is_empty() {
+            return;
+        }
+        let heading_y = py + Self::SETTINGS_BUTTONS_HEIGHT * s;
+        paint.set_color(theme.panel_separator);
+        paint.set_stroke(true);
+        paint.set_stroke_width(1.0 * s);
+        canvas.draw_line((px + 8.0 * s, heading_y), (px + pw - 8.0 * s, heading_y), &paint);
+        paint.set_stroke(false);
+
+        paint.set_color(theme.muted_text);
+        if let Some(blob) = TextBlob::new("Recently closed", font) {
+            let bounds = blob.bounds();
+            let row_height = Self::RECENTLY_CLOSED_ROW_HEIGHT * s;
+            let text_y = heading_y + row_height / 2.0 - (bounds.top + bounds.height() / 2.0);
+            canvas.draw_text_blob(&blob, (px + 16.0 * s, text_y), &paint);
+        }
+
+        for (index, title) in self.recently_closed.iter().take(Self::MAX_RECENTLY_CLOSED_ROWS).enumerate() {
+            let (rx, ry, rw, rh) = self.recently_closed_row_rect(index);
+            let (mx, my) = self.mouse_pos;
+            if mx >= rx && mx <= rx + rw && my >= ry && my <= ry + rh {
+                paint.set_color(theme.row_hover);
+                canvas.draw_round_rect(Rect::from_xywh(rx, ry, rw, rh), 4.0 * s, 4.0 * s, &paint);
+            }
+
+            paint.set_color(theme.panel_text);
+            let label = Self::truncate_text_to_width(title, rw - 16.0 * s, font);
+            if let Some(blob) = TextBlob::new(&label, font) {
+                let bounds = blob.bounds();
+                let text_y = ry + rh / 2.0 - (bounds.top + bounds.height() / 2.0);
+                canvas.draw_text_blob(&blob, (rx + 8.0 * s, text_y), &paint);
+            }
+        }
+    }
+
+    /// Lines of the site information panel as (label, value) pairs; an empty label marks a
+    /// full-width line
+    fn site_info_lines(&self) -> Vec<(&'static str, String)> {
+        let security = &self.security;
+        let mut lines = Vec::new();
+        if let Some(host) = &security.host {
+            lines.push(("", url_display::display_host(host)));
+        }
+        lines.push(("", security.summary().to_string()));
+        match &security.tls {
+            Some(tls) => {
+                lines.push(("Protocol", tls.version.clone()));
+                if !tls.cipher.is_empty() {
+                    lines.push(("Cipher", tls.cipher.clone()));
+                }
+                if let Some(certificate) = &tls.certificate {
+                    lines.push(("Issued to", certificate.subject.clone()));
+                    lines.push(("Issued by", certificate.issuer.clone()));
+                    lines.push(("Valid from", certificate.valid_from.clone()));
+                    lines.push(("Expires", certificate.valid_until.clone()));
+                }
+                let verified = if tls.verified { "Verified" } else { "Not verified" };
+                lines.push(("Certificate", verified.to_string()));
+            }
+            None if security.level == SecurityLevel::NotSecure => {
+                lines.push(("", "Information you send can be read by others".to_string()));
+            }
+            None => {}
+        }
+        if security.mixed_content.blocked > 0 {
+            lines.push(("Blocked", format!("{} insecure request(s)", security.mixed_content.blocked)));
+        }
+        if security.mixed_content.insecure_displayed {
+            lines.push(("", "Insecure images are shown on this page".to_string()));
+        }
+        lines
+    }
+
+    /// Returns (x, y, width, height) for the site information panel, below the site info button
+    fn site_info_panel_rect(&self) -> (f32, f32, f32, f32) {
+        let s = self.viewport.hidpi_scale;
+        let width = (360.0 * s).min(self.window_width() - 16.0 * s).max(0.0);
+        let height = (48.0 + Self::RECENTLY_CLOSED_ROW_HEIGHT * self.site_info_lines().len() as f32) * s;
+        let x = self.components.iter()
+            .find_map(|comp| match comp {
+                UiComponent::Button { id, x, .. } if id == "site_info" => Some(*x),
+                _ => None,
+            })
+            .unwrap_or(0.0);
+        (x, self.chrome_height() + 4.0 * s, width, height)
+    }
+
+    /// Check if a click lands on the site information panel. Any click closes it except one inside
+    /// the panel.
+    pub fn handle_site_info_panel_click(&self, x: f32, y: f32) -> Option<String> {
+        if !self.show_site_info {
+            return None;
+        }
+        let (px, py, pw, ph) = self.site_info_panel_rect();
+        if x < px || x > px + pw || y < py || y > py + ph {
+            return Some("site_info_panel_close".to_string());
+        }
+        Some("site_info_panel_noop".to_string())
+    }
+
+    /// Render the site information panel overlay
+    pub fn render_site_info_panel(&self, canvas: &Canvas, font: &Font) {
+        if !self.show_site_info {
+            return;
+        }
+
+        let s = self.viewport.hidpi_scale;
+        let theme = &self.theme;
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+        let (px, py, pw, ph) = self.site_info_panel_rect();
+        let panel_rect = Rect::from_xywh(px, py, pw, ph);
+
+        // Shadow, background and border, matching the settings panel
+        paint.set_color(Color::from_argb(60, 0, 0, 0));
+        canvas.draw_round_rect(Rect::from_xywh(px + 3.0 * s, py + 3.0 * s, pw, ph), 8.0 * s, 8.0 * s, &paint);
+        paint.set_color(theme.panel);
+        canvas.draw_round_rect(panel_rect, 8.0 * s, 8.0 * s, &paint);
+        paint.set_color(theme.panel_border);
+        paint.set_stroke(true);
+        paint.set_stroke_width(1.0 * s);
+        canvas.draw_round_rect(panel_rect, 8.0 * s, 8.0 * s, &paint);
+        paint.set_stroke(false);
+
+        paint.set_color(theme.panel_text);
+        if let Some(blob) = TextBlob::new("Site information", font) {
+            let bounds = blob.bounds();
+            canvas.draw_text_blob(&blob, (px + 16.0 * s, py + 16.0 * s - bounds.top), &paint);
+        }
+        paint.set_color(theme.panel_separator);
+        paint.set_stroke(true);
+        canvas.draw_line((px + 8.0 * s, py + 40.0 * s), (px + pw - 8.0 * s, py + 40.0 * s), &paint);
+        paint.set_stroke(false);
+
+        let row_height = Self::RECENTLY_CLOSED_ROW_HEIGHT * s;
+        let label_width = 96.0 * s;
+        let summary_color = match self.security.level {
+            SecurityLevel::Secure => Color::from_rgb(24, 128, 56),
+            SecurityLevel::PartiallySecure | SecurityLevel::NotSecure => Color::from_rgb(190, 40, 40),
+            SecurityLevel::Internal => theme.panel_text,
+        };
+        let summary = self.security.summary();
+        for (index, (label, value)) in self.site_info_lines().iter().enumerate() {
+            let row_y = py + 44.0 * s + row_height * index as f32;
+            let value_x = if label.is_empty() { px + 16.0 * s } else { px + 16.0 * s + label_width };
+            if !label.is_empty() {
+                paint.set_color(theme.muted_text);
+                if let Some(blob) = TextBlob::new(label, font) {
+                    let bounds = blob.bounds();
+                    let text_y = row_y + row_height / 2.0 - (bounds.top + bounds.height() / 2.0);
+                    canvas.draw_text_blob(&blob, (px + 16.0 * s, text_y), &paint);
+                }
+            }
+            paint.set_color(if value == summary { summary_color } else { theme.panel_text });
+            let text = Self::truncate_text_to_width(value, px + pw - 16.0 * s - value_x, font);
+            if let Some(blob) = TextBlob::new(&text, font) {
+                let bounds = blob.bounds();
+                let text_y = row_y + row_height / 2.0 - (bounds.top + bounds.height() / 2.0);
+                canvas.draw_text_blob(&blob, (value_x, text_y), &paint);
+            }
+        }
     }
 
     /// Render the UI
@@ -2029,29 +3794,38 @@ impl BrowserUI {
         let canvas_width = canvas.image_info().width() as f32;
         let canvas_height = canvas.image_info().height() as f32;
         let chrome_height = self.chrome_height();
+        if self.fullscreen {
+            return;
+        }
 
-        // Draw browser chrome background bar at the top
-        let mut chrome_paint = Paint::default();
-        chrome_paint.set_color(Color::from_rgb(240, 240, 240)); // Light gray background
-        let chrome_rect = Rect::from_xywh(0.0, 0.0, canvas_width, chrome_height);
-        canvas.draw_rect(chrome_rect, &chrome_paint);
+        let theme = &self.theme;
+        let chrome_layout = self.layout();
 
-        // Draw a bottom border for the chrome
-        chrome_paint.set_color(Color::from_rgb(200, 200, 200));
-        let border_rect = Rect::from_xywh(0.0, chrome_height - 1.0, canvas_width, 1.0);
-        canvas.draw_rect(border_rect, &chrome_paint);
+        // App windows have no chrome; only the dialogs, prompts and toasts over the page are drawn
+        if !self.app_mode {
+            // Draw browser chrome background bar at the top
+            let mut chrome_paint = Paint::default();
+            chrome_paint.set_color(theme.chrome);
+            let chrome_rect = Rect::from_xywh(0.0, 0.0, canvas_width, chrome_height);
+            canvas.draw_rect(chrome_rect, &chrome_paint);
+
+            // Draw a bottom border for the chrome, one device pixel per logical pixel rounded
+            let border_width = chrome_layout.px(1.0).max(1.0);
+            chrome_paint.set_color(theme.chrome_border);
+            let border_rect = Rect::from_xywh(0.0, chrome_height - border_width, canvas_width, border_width);
+            canvas.draw_rect(border_rect, &chrome_paint);
+        }
 
         let mut paint = Paint::default();
 
         // Apply scale factor to font size for proper DPI scaling
-        let base_font_size = 14.0;
-        let scaled_font_size = base_font_size * self.viewport.hidpi_scale;
-        let font = Font::new(self.ui_typeface.clone(), scaled_font_size);
+        let base_font_size = self.theme_settings.density.metrics().font_size;
+        let font = Font::new(self.ui_typeface.clone(), chrome_layout.font_size);
 
-        self.render_bookmarks_bar(canvas, &font);
+        // Draw the bookmarks bar and the BROWSING WITH STOKES text in the top-right corner
+        if !self.app_mode {
+            self.render_bookmarks_bar(canvas, &font);
 
-        // Draw BROWSING WITH STOKES text in the top-right corner
-        {
             let text = &format!("STOKES BROWSER {VERSION}");
             let mut builder = layout_ctx.ranged_builder(font_ctx, text, self.viewport.hidpi_scale, true);
 
@@ -2099,7 +3873,7 @@ impl BrowserUI {
                                 true,
                                 run.normalized_coords(),
                                 Fill::NonZero,
-                                &anyrender::Paint::from(AlphaColor::BLACK),
+                                &anyrender::Paint::from(Self::alpha_color(theme.text)),
                                 1.0,
                                 transform,
                                 glyph_xform,
@@ -2126,11 +3900,19 @@ impl BrowserUI {
         let cursor_margin = 6.0 * self.viewport.hidpi_scale;
         let cursor_stroke_width = 1.5 * self.viewport.hidpi_scale;
         let shadow_offset = 2.0 * self.viewport.hidpi_scale;
+        let radius = 4.0 * self.viewport.hidpi_scale;
+
+        // The page dialog's and find bar's input fields are components, so they go underneath them
+        self.render_page_dialog(canvas, &font);
+        self.render_find_bar(canvas, &font);
 
         // Collect tooltips to render them above everything else at the end
         let mut tooltips_to_render: Vec<(&Tooltip, f32, f32)> = Vec::new();
 
         for comp in &self.components {
+            if self.app_mode && comp.id() != "page_dialog_input" && comp.id() != "find_input" {
+                continue;
+            }
             match comp {
                 UiComponent::Button { x, y, width, height, color, hover_color, pressed_color, is_pressed, is_hover, is_active, tooltip, icon_type, .. } => {
                     let rect = Rect::from_xywh(*x, *y, *width, *height);
@@ -2138,36 +3920,28 @@ impl BrowserUI {
                     // Draw button shadow for depth
                     let shadow_rect = Rect::from_xywh(*x + shadow_offset, *y + shadow_offset, *width, *height);
                     paint.set_color(Color::from_argb(50, 0, 0, 0)); // Semi-transparent shadow
-                    canvas.draw_round_rect(shadow_rect, 4.0, 4.0, &paint);
+                    canvas.draw_round_rect(shadow_rect, radius, radius, &paint);
 
                     // Choose color based on state
                     let current_color = if *is_pressed {
-                        pressed_color
+                        theme::from_rgb(*pressed_color)
                     } else if *is_active {
-                        &[0.98, 0.88, 0.45]
+                        theme.button_active
                     } else if *is_hover {
-                        hover_color
+                        theme::from_rgb(*hover_color)
                     } else {
-                        color
+                        theme::from_rgb(*color)
                     };
 
                     // Draw button background with rounded corners
-                    paint.set_color(Color::from_rgb(
-                        (current_color[0] * 255.0) as u8,
-                        (current_color[1] * 255.0) as u8,
-                        (current_color[2] * 255.0) as u8,
-                    ));
-                    canvas.draw_round_rect(rect, 4.0, 4.0, &paint);
+                    paint.set_color(current_color);
+                    canvas.draw_round_rect(rect, radius, radius, &paint);
 
                     // Draw button border
-                    paint.set_color(if *is_hover {
-                        Color::from_rgb(100, 150, 255)
-                    } else {
-                        Color::from_rgb(180, 180, 180)
-                    });
+                    paint.set_color(if *is_hover { theme.accent } else { theme.button_border });
                     paint.set_stroke(true);
                     paint.set_stroke_width(1.0 * self.viewport.hidpi_scale);
-                    canvas.draw_round_rect(rect, 4.0, 4.0, &paint);
+                    canvas.draw_round_rect(rect, radius, radius, &paint);
                     paint.set_stroke(false);
 
                     // Draw custom icon instead of text
@@ -2178,37 +3952,24 @@ impl BrowserUI {
                         tooltips_to_render.push((tooltip, *x, *y));
                     }
                 }
-                UiComponent::TextField { text, x, y, width, height, color, border_color, has_focus, cursor_position, selection_start, selection_end, .. } => {
+                UiComponent::TextField { id, text, x, y, width, height, color, border_color, has_focus, cursor_position, selection_start, selection_end, .. } => {
                     let rect = Rect::from_xywh(*x, *y, *width, *height);
 
                     // Draw field shadow
-                    let shadow_rect = Rect::from_xywh(*x + 1.0, *y + 1.0, *width, *height);
+                    let field_radius = radius / 2.0;
+                    let shadow_rect = Rect::from_xywh(*x + shadow_offset / 2.0, *y + shadow_offset / 2.0, *width, *height);
                     paint.set_color(Color::from_argb(30, 0, 0, 0));
-                    canvas.draw_round_rect(shadow_rect, 2.0, 2.0, &paint);
+                    canvas.draw_round_rect(shadow_rect, field_radius, field_radius, &paint);
 
                     // Draw field background (brighter when focused)
-                    let bg_color = if *has_focus {
-                        Color::WHITE
-                    } else {
-                        Color::from_rgb(250, 250, 250)
-                    };
-                    paint.set_color(bg_color);
-                    canvas.draw_round_rect(rect, 2.0, 2.0, &paint);
+                    paint.set_color(if *has_focus { theme.field_focused } else { theme::from_rgb(*color) });
+                    canvas.draw_round_rect(rect, field_radius, field_radius, &paint);
 
-                    // Draw field border (blue when focused) with scaled stroke width
-                    let border_color = if *has_focus {
-                        Color::from_rgb(100, 150, 255)
-                    } else {
-                        Color::from_rgb(
-                            (border_color[0] * 255.0) as u8,
-                            (border_color[1] * 255.0) as u8,
-                            (border_color[2] * 255.0) as u8,
-                        )
-                    };
-                    paint.set_color(border_color);
+                    // Draw field border (accent colored when focused) with scaled stroke width
+                    paint.set_color(if *has_focus { theme.accent } else { theme::from_rgb(*border_color) });
                     paint.set_stroke(true);
                     paint.set_stroke_width(if *has_focus { 2.0 * self.viewport.hidpi_scale } else { 1.0 * self.viewport.hidpi_scale });
-                    canvas.draw_round_rect(rect, 2.0, 2.0, &paint);
+                    canvas.draw_round_rect(rect, field_radius, field_radius, &paint);
                     paint.set_stroke(false);
 
                     // Draw text selection highlight first so glyphs render on top.
@@ -2219,7 +3980,7 @@ impl BrowserUI {
                             let (prefix_width, _) = font.measure_str(text_before_selection, None);
                             let (selected_width, _) = font.measure_str(selected_text, None);
 
-                            paint.set_color(Color::from_argb(140, 132, 185, 255));
+                            paint.set_color(theme.selection);
                             let selection_rect = Rect::from_xywh(
                                 rect.left() + text_padding + prefix_width,
                                 rect.top() + (2.0 * self.viewport.hidpi_scale),
@@ -2231,14 +3992,25 @@ impl BrowserUI {
                     }
 
                     // Draw text content with scaled padding, centered vertically
-                    paint.set_color(Color::BLACK);
-                    if let Some(blob) = TextBlob::new(text, &font) {
+                    paint.set_color(theme.text);
+                    if id == "address_bar" && !*has_focus {
+                        self.render_address_bar_url(canvas, &font, rect, text_padding, text);
+                    } else if let Some(blob) = TextBlob::new(text, &font) {
                         let text_bounds = blob.bounds();
                         // Center the text vertically in the field
                         let text_y = rect.top() + (rect.height() / 2.0) - (text_bounds.top + text_bounds.height() / 2.0);
                         canvas.draw_text_blob(&blob, (rect.left() + text_padding, text_y), &paint);
                     }
 
+                    if id == "address_bar" && !*has_focus {
+                        // The mixed content warning sits to the left of the popup and JavaScript badges
+                        let badges_left = self.popup_badge_rect().or(self.javascript_badge_rect()).map_or(rect.right(), |(badge_x, ..)| badge_x);
+                        let field = Rect::from_xywh(rect.left(), rect.top(), badges_left - rect.left(), rect.height());
+                        self.render_mixed_content_badge(canvas, &font, field);
+                        self.render_popup_badge(canvas, &font);
+                        self.render_javascript_badge(canvas, &font);
+                    }
+
                     // Draw cursor if focused
                     if *has_focus {
                         // Calculate cursor position in pixels
@@ -2260,7 +4032,7 @@ impl BrowserUI {
                         let cursor_x = rect.left() + text_padding + text_width;
 
                         // Draw cursor line with scaled stroke width and margins
-                        paint.set_color(Color::BLACK);
+                        paint.set_color(theme.text);
                         paint.set_stroke(true);
                         paint.set_stroke_width(cursor_stroke_width);
                         canvas.draw_line(
@@ -2271,43 +4043,39 @@ impl BrowserUI {
                         paint.set_stroke(false);
                     }
                 }
-                UiComponent::TabButton { title, x, y, width, height, color, hover_color, is_active, is_hover, tooltip, close_button_hover, close_button_tooltip, favicon, is_loading, .. } => {
+                UiComponent::TabButton { title, x, y, width, height, color, hover_color, is_active, is_hover, tooltip, close_button_hover, close_button_tooltip, favicon, is_loading, is_pinned, is_discarded, is_audible, is_muted, .. } => {
                     let rect = Rect::from_xywh(*x, *y, *width, *height);
 
                     // Draw tab shadow
-                    let shadow_rect = Rect::from_xywh(*x + 1.0, *y + 1.0, *width, *height);
+                    let shadow_rect = Rect::from_xywh(*x + shadow_offset / 2.0, *y + shadow_offset / 2.0, *width, *height);
                     paint.set_color(Color::from_argb(30, 0, 0, 0));
-                    canvas.draw_round_rect(shadow_rect, 4.0, 4.0, &paint);
+                    canvas.draw_round_rect(shadow_rect, radius, radius, &paint);
 
                     // Choose color based on state
-                    let current_color = if *is_hover {
-                        hover_color
-                    } else {
-                        color
-                    };
-
-                    paint.set_color(Color::from_rgb(
-                        (current_color[0] * 255.0) as u8,
-                        (current_color[1] * 255.0) as u8,
-                        (current_color[2] * 255.0) as u8,
-                    ));
-                    canvas.draw_round_rect(rect, 4.0, 4.0, &paint);
+                    let current_color = theme::from_rgb(if *is_hover { *hover_color } else { *color });
+                    paint.set_color(current_color);
+                    canvas.draw_round_rect(rect, radius, radius, &paint);
 
                     // Draw tab border (different for active tab)
                     paint.set_color(if *is_active {
-                        Color::from_rgb(100, 150, 255)
+                        theme.accent
                     } else if *is_hover {
-                        Color::from_rgb(150, 180, 255)
+                        theme.tab_hover_border
                     } else {
-                        Color::from_rgb(180, 180, 180)
+                        theme.button_border
                     });
                     paint.set_stroke(true);
                     paint.set_stroke_width(if *is_active { 2.0 * self.viewport.hidpi_scale } else { 1.0 * self.viewport.hidpi_scale });
-                    canvas.draw_round_rect(rect, 4.0, 4.0, &paint);
+                    canvas.draw_round_rect(rect, radius, radius, &paint);
                     paint.set_stroke(false);
 
                     let favicon_size = 16.0 * self.viewport.hidpi_scale;
-                    let favicon_padding_left = 8.0 * self.viewport.hidpi_scale;
+                    // Pinned tabs only show their favicon, centered in the compact tab
+                    let favicon_padding_left = if *is_pinned {
+                        (rect.width() - favicon_size) / 2.0
+                    } else {
+                        8.0 * self.viewport.hidpi_scale
+                    };
                     let favicon_rect = Rect::from_xywh(
                         rect.left() + favicon_padding_left,
                         rect.center_y() - (favicon_size / 2.0),
@@ -2317,6 +4085,12 @@ impl BrowserUI {
 
                     Self::draw_tab_favicon(canvas, &mut paint, favicon_rect, favicon.as_ref());
 
+                    // Fade the favicon of a discarded tab into the tab background
+                    if *is_discarded {
+                        paint.set_color(current_color.with_a(150));
+                        canvas.draw_rect(favicon_rect, &paint);
+                    }
+
                     if *is_loading {
                         let spinner_radius = (favicon_size * 0.75).max(8.0 * self.viewport.hidpi_scale);
                         Self::draw_spinner(
@@ -2329,16 +4103,29 @@ impl BrowserUI {
                         );
                     }
 
+                    if *is_pinned {
+                        if tooltip.is_visible {
+                            tooltips_to_render.push((tooltip, *x, *y));
+                        }
+                        continue;
+                    }
+
                     // Calculate space needed for close button if active
                     let close_button_space = if *is_active { 20.0 * self.viewport.hidpi_scale } else { 0.0 };
 
+                    // The speaker goes between the favicon and the title
+                    let audio_rect = Self::tab_audio_button_rect(rect, *is_pinned, *is_audible, *is_muted, self.viewport.hidpi_scale);
+                    if let Some(audio_rect) = audio_rect {
+                        self.draw_icon(painter, &IconType::Speaker { muted: *is_muted }, audio_rect, false, self.viewport.hidpi_scale);
+                    }
+
                     // Truncate tab text to fit within the tab width (leaving space for favicon + close button)
-                    let text_start_x = favicon_rect.right() + (6.0 * self.viewport.hidpi_scale);
+                    let text_start_x = audio_rect.map_or(favicon_rect.right(), |audio_rect| audio_rect.right()) + (6.0 * self.viewport.hidpi_scale);
                     let max_text_width = (rect.right() - close_button_space) - text_start_x - text_padding;
                     let display_text = Self::truncate_text_to_width(title, max_text_width, &font);
 
                     // Draw tab text with scaled padding, centered vertically
-                    paint.set_color(Color::BLACK);
+                    paint.set_color(if *is_discarded { theme.muted_text } else { theme.text });
                     if let Some(blob) = TextBlob::new(&display_text, &font) {
                         let text_bounds = blob.bounds();
                         // Center the text vertically in the tab
@@ -2359,7 +4146,7 @@ impl BrowserUI {
                         } else {
                             paint.set_color(Color::from_argb(20, 0, 0, 0)); // Subtle background
                         }
-                        canvas.draw_round_rect(close_button_rect, 2.0, 2.0, &paint);
+                        canvas.draw_round_rect(close_button_rect, radius / 2.0, radius / 2.0, &paint);
 
                         // Draw X icon with different color when hovering
                         self.draw_icon(painter, &IconType::Close, close_button_rect, *close_button_hover, self.viewport.hidpi_scale);
@@ -2382,11 +4169,19 @@ impl BrowserUI {
 
         // Render all tooltips last so they appear above everything else
         for (tooltip, x, y) in tooltips_to_render {
-            Self::draw_tooltip(painter, tooltip, x, y, &font, self.viewport.hidpi_scale, canvas_width, canvas_height);
+            Self::draw_tooltip(painter, theme, tooltip, x, y, &font, self.viewport.hidpi_scale, canvas_width, canvas_height);
         }
 
-        // Render settings panel on top of everything
+        self.render_permission_prompt(canvas, &font);
+        self.render_autofill_popup(canvas, &font);
+        self.render_tab_context_menu(canvas, &font);
+
+        // Render settings and site information panels on top of everything
         self.render_settings_panel(canvas, &font);
+        self.render_site_info_panel(canvas, &font);
+
+        self.render_drop_zone(canvas, canvas_width, canvas_height);
+        self.render_toast(canvas, &font, canvas_width, canvas_height);
     }
 
     fn render_bookmarks_bar(&self, canvas: &Canvas, font: &Font) {
@@ -2395,9 +4190,10 @@ impl BrowserUI {
         let (row_x, row_y, row_w, row_h) = self.bookmark_row_rect();
         let row_rect = Rect::from_xywh(row_x, row_y, row_w, row_h);
 
-        paint.set_color(Color::from_rgb(247, 247, 248));
+        let theme = &self.theme;
+        paint.set_color(theme.bookmarks_bar);
         canvas.draw_rect(row_rect, &paint);
-        paint.set_color(Color::from_rgb(214, 214, 214));
+        paint.set_color(theme.chrome_border);
         canvas.draw_line((row_x, row_y), (row_x + row_w, row_y), &paint);
 
         for (bookmark, index, item_rect) in self.visible_root_bookmark_layout() {
@@ -2410,17 +4206,17 @@ impl BrowserUI {
             paint.set_color(if is_drop_folder_target {
                 Color::from_rgb(192, 224, 255)
             } else if is_pressed {
-                Color::from_rgb(206, 219, 239)
+                theme.bookmark_pressed
             } else if is_hovered {
-                Color::from_rgb(228, 236, 248)
+                theme.bookmark_hover
             } else if is_selected {
-                Color::from_rgb(210, 228, 255)
+                theme.bookmark_selected
             } else {
-                Color::from_rgb(236, 236, 238)
+                theme.bookmark
             });
             canvas.draw_round_rect(item_rect, 7.0 * scale, 7.0 * scale, &paint);
 
-            paint.set_color(Color::from_rgb(180, 180, 190));
+            paint.set_color(theme.bookmark_border);
             paint.set_stroke(true);
             paint.set_stroke_width(1.0 * scale);
             canvas.draw_round_rect(item_rect, 7.0 * scale, 7.0 * scale, &paint);
@@ -2451,12 +4247,12 @@ impl BrowserUI {
             if let Some(blob) = TextBlob::new(&text, font) {
                 let bounds = blob.bounds();
                 let text_y = item_rect.center_y() - (bounds.top + bounds.height() / 2.0);
-                paint.set_color(Color::from_rgb(45, 45, 45));
+                paint.set_color(theme.panel_text);
                 canvas.draw_text_blob(&blob, (favicon_rect.right() + 5.0 * scale, text_y), &paint);
             }
 
             if self.bookmark_drag.active && self.bookmark_drag.drop_index == Some(index) {
-                paint.set_color(Color::from_rgb(70, 130, 240));
+                paint.set_color(theme.accent);
                 paint.set_stroke(true);
                 paint.set_stroke_width(2.0 * scale);
                 canvas.draw_line(
@@ -2469,7 +4265,7 @@ impl BrowserUI {
         }
 
         if self.bookmark_drag.active && self.bookmark_drag.drop_index == Some(self.visible_root_bookmark_layout().len()) {
-            paint.set_color(Color::from_rgb(70, 130, 240));
+            paint.set_color(theme.accent);
             paint.set_stroke(true);
             paint.set_stroke_width(2.0 * scale);
             let end_x = row_x + row_w - 8.0 * scale;
@@ -2491,18 +4287,32 @@ impl BrowserUI {
     }
 
     fn render_bookmark_context_menu(&self, canvas: &Canvas, font: &Font) {
-        let Some((x, y, w, h)) = self.bookmark_context_menu_rect() else {
+        let Some(rect) = self.bookmark_context_menu_rect() else {
             return;
         };
 
-        let scale = self.viewport.hidpi_scale;
         let menu = self.bookmark_context_menu.as_ref().expect("context menu should exist when rect exists");
         let entries = self.context_menu_entries(menu.target_id.as_deref());
+        self.render_context_menu(canvas, font, rect, &entries);
+    }
+
+    fn render_tab_context_menu(&self, canvas: &Canvas, font: &Font) {
+        let Some(rect) = self.tab_context_menu_rect() else {
+            return;
+        };
+
+        let menu = self.tab_context_menu.as_ref().expect("context menu should exist when rect exists");
+        let entries = self.tab_context_menu_entries(&menu.tab_id);
+        self.render_context_menu(canvas, font, rect, &entries);
+    }
+
+    fn render_context_menu(&self, canvas: &Canvas, font: &Font, (x, y, w, h): (f32, f32, f32, f32), entries: &[(&str, &str)]) {
+        let scale = self.viewport.hidpi_scale;
         let mut paint = Paint::default();
         let panel = Rect::from_xywh(x, y, w, h);
-        paint.set_color(Color::from_rgb(252, 252, 252));
+        paint.set_color(self.theme.panel);
         canvas.draw_round_rect(panel, 6.0 * scale, 6.0 * scale, &paint);
-        paint.set_color(Color::from_rgb(190, 190, 200));
+        paint.set_color(self.theme.panel_border);
         paint.set_stroke(true);
         paint.set_stroke_width(1.0 * scale);
         canvas.draw_round_rect(panel, 6.0 * scale, 6.0 * scale, &paint);
@@ -2515,7 +4325,7 @@ impl BrowserUI {
                 Rect::from_xywh(x + 1.0 * scale, row_y + 1.0 * scale, w - 2.0 * scale, row_h - 2.0 * scale),
             );
             if hover {
-                paint.set_color(Color::from_rgb(227, 236, 251));
+                paint.set_color(self.theme.row_hover);
                 canvas.draw_round_rect(
                     Rect::from_xywh(x + 2.0 * scale, row_y + 1.0 * scale, w - 4.0 * scale, row_h - 2.0 * scale),
                     4.0 * scale,
@@ -2526,7 +4336,7 @@ impl BrowserUI {
             if let Some(blob) = TextBlob::new(label, font) {
                 let bounds = blob.bounds();
                 let text_y = row_y + (row_h / 2.0) - (bounds.top + bounds.height() / 2.0);
-                paint.set_color(Color::from_rgb(45, 45, 45));
+                paint.set_color(self.theme.panel_text);
                 canvas.draw_text_blob(&blob, (x + 8.0 * scale, text_y), &paint);
             }
         }
@@ -2540,9 +4350,9 @@ impl BrowserUI {
 
         let mut paint = Paint::default();
         let panel = Rect::from_xywh(x, y, w, h);
-        paint.set_color(Color::from_rgb(252, 252, 252));
+        paint.set_color(self.theme.panel);
         canvas.draw_round_rect(panel, 6.0 * scale, 6.0 * scale, &paint);
-        paint.set_color(Color::from_rgb(190, 190, 200));
+        paint.set_color(self.theme.panel_border);
         paint.set_stroke(true);
         paint.set_stroke_width(1.0 * scale);
         canvas.draw_round_rect(panel, 6.0 * scale, 6.0 * scale, &paint);
@@ -2560,11 +4370,7 @@ impl BrowserUI {
             let is_pressed = self.bookmark_pressed_id.as_deref() == Some(bookmark.id.as_str());
             if is_selected || is_hovered || is_pressed {
                 let selected_row = Rect::from_xywh(x + 2.0 * scale, row_y + 1.0 * scale, w - 4.0 * scale, row_h - 2.0 * scale);
-                paint.set_color(if is_pressed {
-                    Color::from_rgb(203, 217, 240)
-                } else {
-                    Color::from_rgb(221, 235, 255)
-                });
+                paint.set_color(if is_pressed { self.theme.bookmark_pressed } else { self.theme.row_hover });
                 canvas.draw_round_rect(selected_row, 4.0 * scale, 4.0 * scale, &paint);
             }
 
@@ -2585,7 +4391,7 @@ impl BrowserUI {
             if let Some(blob) = TextBlob::new(&text, font) {
                 let bounds = blob.bounds();
                 let text_y = row_y + (row_h / 2.0) - (bounds.top + bounds.height() / 2.0);
-                paint.set_color(Color::from_rgb(40, 40, 40));
+                paint.set_color(self.theme.panel_text);
                 canvas.draw_text_blob(&blob, (icon_rect.right() + 6.0 * scale, text_y), &paint);
             }
         }
@@ -2594,6 +4400,9 @@ impl BrowserUI {
     /// Update mouse hover state and handle tooltips
     pub fn update_mouse_hover(&mut self, x: f32, y: f32, current_time: Instant) {
         self.mouse_pos = (x, y);
+        if self.fullscreen || self.app_mode {
+            return;
+        }
         self.bookmark_hover_id = self.bookmark_at_point(x, y).map(|(id, _)| id);
 
         for comp in &mut self.components {
@@ -2620,9 +4429,9 @@ impl BrowserUI {
                         }
                     }
                 }
-                UiComponent::TabButton { x: tab_x, y: tab_y, width, height, is_active, is_hover, tooltip, close_button_hover, close_button_tooltip, .. } => {
+                UiComponent::TabButton { x: tab_x, y: tab_y, width, height, is_active, is_pinned, is_hover, tooltip, close_button_hover, close_button_tooltip, .. } => {
                     // Check if hovering over close button specifically (inline calculation to avoid borrowing issues)
-                    let is_over_close_button = if *is_active {
+                    let is_over_close_button = if *is_active && !*is_pinned {
                         let close_button_size = 16.0 * self.viewport.hidpi_scale;
                         let close_button_x = *tab_x + *width - close_button_size - (4.0 * self.viewport.hidpi_scale);
                         let close_button_y = *tab_y + (*height / 2.0) - (close_button_size / 2.0);
@@ -2729,6 +4538,27 @@ impl BrowserUI {
         changed
     }
 
+    /// When a tooltip or the toast next appears or goes away by itself, which needs a redraw
+    /// nothing else asks for
+    pub fn next_redraw_at(&self) -> Option<Instant> {
+        let pending = |tooltip: &Tooltip, hovered: bool| {
+            tooltip.hover_start.filter(|_| hovered && !tooltip.is_visible).map(|start| start + tooltip.show_after)
+        };
+        let tooltips = self.components.iter().flat_map(|comp| match comp {
+            UiComponent::Button { is_hover, tooltip, .. } => [pending(tooltip, *is_hover), None],
+            UiComponent::TabButton { is_hover, tooltip, close_button_hover, close_button_tooltip, .. } => {
+                [pending(tooltip, *is_hover), pending(close_button_tooltip, *close_button_hover)]
+            }
+            _ => [None, None],
+        });
+        let toast = self
+            .toast
+            .as_ref()
+            .map(|(_, shown_at)| *shown_at + Self::TOAST_DURATION)
+            .filter(|hidden_at| *hidden_at > Instant::now());
+        tooltips.flatten().chain(toast).min()
+    }
+
     /// Handle mouse press
     pub fn handle_mouse_press(&mut self, x: f32, y: f32) -> Option<String> {
         for comp in &mut self.components {
@@ -2774,8 +4604,7 @@ impl BrowserUI {
             .with_caps(kurbo::Cap::Round)
             .with_join(kurbo::Join::Round);
 
-        // Icon color (dark gray for most icons)
-        let icon_color = AlphaColor::from_rgba8(60, 60, 60, 255);
+        let icon_color = Self::alpha_color(self.theme.icon);
         let hover_color = AlphaColor::from_rgba8(200, 50, 50, 255); // Red for close icon when hovering
 
         match icon_type {
@@ -2804,9 +4633,28 @@ impl BrowserUI {
             IconType::Settings => {
                 Self::render_svg(painter, &self.settings_svg, rect, icon_color, hidpi_scale);
             }
+            IconType::SiteInfo => match self.security.level {
+                SecurityLevel::Secure => Self::render_svg(painter, &self.lock_svg, rect, icon_color, hidpi_scale),
+                SecurityLevel::PartiallySecure | SecurityLevel::NotSecure => {
+                    let warning_color = AlphaColor::from_rgba8(190, 40, 40, 255);
+                    Self::render_svg(painter, &self.info_svg, rect, warning_color, hidpi_scale);
+                }
+                SecurityLevel::Internal => {
+                    let muted_color = AlphaColor::from_rgba8(120, 120, 120, 255);
+                    Self::render_svg(painter, &self.info_svg, rect, muted_color, hidpi_scale);
+                }
+            },
+            IconType::Speaker { muted } => {
+                let tree = if *muted { &self.speaker_muted_svg } else { &self.speaker_svg };
+                Self::render_svg(painter, tree, rect, icon_color, hidpi_scale);
+            }
         }
     }
 
+    fn alpha_color(color: Color) -> AlphaColor<Srgb> {
+        AlphaColor::from_rgba8(color.r(), color.g(), color.b(), color.a())
+    }
+
     /// Render an SVG tree into a rect
     fn render_svg(painter: &mut ScenePainter, tree: &Tree, rect: Rect, color: AlphaColor<Srgb>, hidpi_scale: f32) {
         // Save canvas state before SVG rendering
@@ -2977,7 +4825,7 @@ impl BrowserUI {
 
 
     /// Draw a tooltip
-    fn draw_tooltip(painter: &mut ScenePainter, tooltip: &Tooltip, x: f32, y: f32, font: &Font, hidpi_scale: f32, canvas_width: f32, canvas_height: f32) {
+    fn draw_tooltip(painter: &mut ScenePainter, theme: &Theme, tooltip: &Tooltip, x: f32, y: f32, font: &Font, hidpi_scale: f32, canvas_width: f32, canvas_height: f32) {
         if !tooltip.is_visible {
             return;
         }
@@ -3034,7 +4882,7 @@ impl BrowserUI {
         // Draw tooltip background with shadow
         let shadow_rect = kurbo::RoundedRect::from_rect(
             kurbo::Rect::new(tooltip_x + 2.0, tooltip_y + 2.0, tooltip_x + tooltip_width + 2.0, tooltip_y + tooltip_height + 2.0),
-            4.0
+            4.0 * hidpi_scale as f64
         );
         let shadow_color = AlphaColor::from_rgba8(0, 0, 0, 100); // Semi-transparent black shadow
         painter.fill(Fill::NonZero, transform, shadow_color, None, &shadow_rect);
@@ -3042,20 +4890,20 @@ impl BrowserUI {
         // Draw tooltip background
         let tooltip_rect = kurbo::RoundedRect::from_rect(
             kurbo::Rect::new(tooltip_x, tooltip_y, tooltip_x + tooltip_width, tooltip_y + tooltip_height),
-            4.0
+            4.0 * hidpi_scale as f64
         );
-        let bg_color = AlphaColor::from_rgba8(255, 255, 220, 255); // Light yellow background
+        let bg_color = Self::alpha_color(theme.tooltip);
         painter.fill(Fill::NonZero, transform, bg_color, None, &tooltip_rect);
 
         // Draw tooltip border
         let stroke = kurbo::Stroke::new(1.0 * hidpi_scale as f64);
-        let border_color = AlphaColor::from_rgba8(180, 180, 140, 255);
+        let border_color = Self::alpha_color(theme.tooltip_border);
         painter.stroke(&stroke, transform, border_color, None, &tooltip_rect);
 
         // Draw tooltip text using canvas directly (TextBlob is Skia-specific)
         painter.set_matrix(transform);
         let mut paint = Paint::default();
-        paint.set_color(Color::BLACK);
+        paint.set_color(theme.text);
 
         for (index, line) in lines.iter().enumerate() {
             let draw_line = if line.is_empty() { " " } else { line };
@@ -3179,7 +5027,7 @@ impl BrowserUI {
         let new_tab_x = self.tab_drag_state.original_tab_x + drag_offset;
 
         // Get tab info for reordering calculation
-        let scaled_spacing = Self::TAB_SPACING * self.viewport.hidpi_scale;
+        let scaled_spacing = self.layout().tab_spacing;
         let tab_width = self.calculate_tab_width();
 
         // Find the dragged tab's current center
@@ -3248,7 +5096,7 @@ impl BrowserUI {
 
         let drag_offset = self.tab_drag_state.drag_offset;
         let tab_width = self.calculate_tab_width();
-        let scaled_spacing = Self::TAB_SPACING * self.viewport.hidpi_scale;
+        let scaled_spacing = self.layout().tab_spacing;
         let tab_slot_width = tab_width + scaled_spacing;
 
         // Calculate how many positions to move based on drag distance
@@ -3316,23 +5164,47 @@ impl BrowserUI {
         let from_comp_index = tab_indices[from_index];
         let to_comp_index = tab_indices[to_index];
 
-        // Remove the tab from its original position
+        // Remove the tab from its original position. When moving right, the target tab
+        // shifts left by one after removal, so inserting at its old index places the
+        // dragged tab after it (matching how `tab_order` is reordered).
         let tab = self.components.remove(from_comp_index);
 
-        // Adjust the target index if necessary
-        let adjusted_to_index = if from_comp_index < to_comp_index {
-            to_comp_index - 1
-        } else {
-            to_comp_index
-        };
-
         // Insert at the new position
-        self.components.insert(adjusted_to_index, tab);
+        self.components.insert(to_comp_index, tab);
 
         // Update layout to recalculate positions
         self.update_tab_layout();
     }
 
+    /// Pin or unpin a tab. Callers are responsible for moving the tab into the pinned group.
+    pub fn set_tab_pinned(&mut self, tab_id: &str, pinned: bool) {
+        for comp in &mut self.components {
+            if let UiComponent::TabButton { id, is_pinned, close_button_hover, close_button_tooltip, .. } = comp {
+                if id == tab_id {
+                    *is_pinned = pinned;
+                    *close_button_hover = false;
+                    close_button_tooltip.is_visible = false;
+                    break;
+                }
+            }
+        }
+        self.update_tab_layout();
+    }
+
+    pub fn is_tab_pinned(&self, tab_id: &str) -> bool {
+        self.components.iter().any(|comp| {
+            matches!(comp, UiComponent::TabButton { id, is_pinned: true, .. } if id == tab_id)
+        })
+    }
+
+    /// Find the tab under the given point, if any
+    pub fn tab_at_point(&self, x: f32, y: f32) -> Option<String> {
+        self.components.iter().find_map(|comp| match comp {
+            UiComponent::TabButton { id, .. } if comp.contains_point(x, y) => Some(id.clone()),
+            _ => None,
+        })
+    }
+
     // Helper: find previous character boundary strictly before or at byte_pos
     fn prev_char_boundary(s: &str, byte_pos: usize) -> usize {
         if byte_pos == 0 {
@@ -0,0 +1,238 @@
+// Sizes of the browser chrome for each density and the layout that turns them into device pixels.
+// Every position and size is rounded to whole device pixels, so edges stay sharp and rows line up
+// at fractional scale factors such as 125% and 150%.
+
+use serde::{Deserialize, Serialize};
+
+/// How much room the chrome takes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Density {
+    Compact,
+    #[default]
+    Normal,
+}
+
+impl Density {
+    /// The other density, for the settings panel button
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Compact => Self::Normal,
+            Self::Normal => Self::Compact,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Compact => "Compact",
+            Self::Normal => "Normal",
+        }
+    }
+
+    pub fn metrics(self) -> Metrics {
+        match self {
+            Self::Compact => Metrics::COMPACT,
+            Self::Normal => Metrics::NORMAL,
+        }
+    }
+}
+
+/// Sizes of the chrome in logical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metrics {
+    /// Space around and between the controls of a row
+    pub margin: f32,
+    /// Width and height of the toolbar buttons and the new tab button
+    pub button_size: f32,
+    /// Height of the row of tabs, including the margins above and below the tabs
+    pub tab_row_height: f32,
+    pub tab_spacing: f32,
+    /// Height of the row with the navigation buttons and the address bar
+    pub toolbar_height: f32,
+    pub bookmarks_row_height: f32,
+    /// Space between the bookmarks bar and the page
+    pub bottom_padding: f32,
+    pub font_size: f32,
+}
+
+impl Metrics {
+    pub const NORMAL: Metrics = Metrics {
+        margin: 8.0,
+        button_size: 32.0,
+        tab_row_height: 48.0,
+        tab_spacing: 4.0,
+        toolbar_height: 40.0,
+        bookmarks_row_height: 32.0,
+        bottom_padding: 4.0,
+        font_size: 14.0,
+    };
+
+    pub const COMPACT: Metrics = Metrics {
+        margin: 4.0,
+        button_size: 28.0,
+        tab_row_height: 36.0,
+        tab_spacing: 2.0,
+        toolbar_height: 32.0,
+        bookmarks_row_height: 28.0,
+        bottom_padding: 2.0,
+        font_size: 13.0,
+    };
+
+    /// Height of the whole chrome in logical pixels
+    pub fn chrome_height(&self) -> f32 {
+        self.tab_row_height + self.toolbar_height + self.bookmarks_row_height + self.bottom_padding
+    }
+}
+
+/// How wide a control in a row is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Width {
+    /// As wide as a toolbar button
+    Button,
+    /// Whatever the other controls leave, shared with the other `Fill` controls, but no less than
+    /// `min` logical pixels
+    Fill { min: f32 },
+}
+
+/// The toolbar, left to right
+pub const TOOLBAR: &[(&str, Width)] = &[
+    ("back", Width::Button),
+    ("forward", Width::Button),
+    ("refresh", Width::Button),
+    ("home", Width::Button),
+    ("site_info", Width::Button),
+    ("address_bar", Width::Fill { min: 200.0 }),
+    ("bookmark_toggle", Width::Button),
+    ("settings", Width::Button),
+];
+
+/// Where a control goes, in device pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    pub id: &'static str,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// The chrome's metrics at a scale factor, in whole device pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChromeLayout {
+    pub scale: f32,
+    pub margin: f32,
+    pub button_size: f32,
+    pub tab_spacing: f32,
+    pub tab_row_height: f32,
+    /// Top and height of the tabs and the new tab button
+    pub tab_y: f32,
+    pub tab_height: f32,
+    pub toolbar_y: f32,
+    pub bookmarks_y: f32,
+    pub bookmarks_height: f32,
+    pub chrome_height: f32,
+    /// Font size of the chrome's text, which isn't rounded
+    pub font_size: f32,
+}
+
+impl ChromeLayout {
+    pub fn new(metrics: &Metrics, scale: f32) -> Self {
+        let px = |logical: f32| (logical * scale).round();
+        // Rows are placed from their rounded edges so that their heights add up to the chrome's
+        let tab_row_height = px(metrics.tab_row_height);
+        let toolbar_bottom = px(metrics.tab_row_height + metrics.toolbar_height);
+        let bookmarks_bottom = px(metrics.tab_row_height + metrics.toolbar_height + metrics.bookmarks_row_height);
+        let button_size = px(metrics.button_size);
+        let margin = px(metrics.margin);
+        Self {
+            scale,
+            margin,
+            button_size,
+            tab_spacing: px(metrics.tab_spacing),
+            tab_row_height,
+            tab_y: ((tab_row_height - button_size) / 2.0).round(),
+            tab_height: button_size,
+            toolbar_y: tab_row_height,
+            bookmarks_y: toolbar_bottom,
+            bookmarks_height: bookmarks_bottom - toolbar_bottom,
+            chrome_height: px(metrics.chrome_height()),
+            font_size: metrics.font_size * scale,
+        }
+    }
+
+    /// `logical` pixels at this layout's scale, rounded to whole device pixels
+    pub fn px(&self, logical: f32) -> f32 {
+        (logical * self.scale).round()
+    }
+
+    /// Place `items` left to right across a row `width` device pixels wide starting at `y`, with
+    /// a margin around and between them. When the window is too narrow for the `Fill` items'
+    /// minimum width the row overflows on the right.
+    pub fn row(&self, items: &[(&'static str, Width)], y: f32, width: f32) -> Vec<Placement> {
+        let gaps = self.margin * (items.len() + 1) as f32;
+        let fixed: f32 = items.iter().filter(|(_, width)| *width == Width::Button).count() as f32 * self.button_size;
+        let fills = items.iter().filter(|(_, width)| matches!(width, Width::Fill { .. })).count();
+        let fill_width = if fills == 0 { 0.0 } else { ((width - gaps - fixed) / fills as f32).floor() };
+
+        let mut x = self.margin;
+        items
+            .iter()
+            .map(|&(id, item_width)| {
+                let width = match item_width {
+                    Width::Button => self.button_size,
+                    Width::Fill { min } => fill_width.max(self.px(min)),
+                };
+                let placement = Placement { id, x, y, width, height: self.button_size };
+                x += width + self.margin;
+                placement
+            })
+            .collect()
+    }
+
+    /// Place the toolbar's controls for a window `width` device pixels wide
+    pub fn toolbar(&self, width: f32) -> Vec<Placement> {
+        self.row(TOOLBAR, self.toolbar_y, width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChromeLayout, Metrics};
+
+    #[test]
+    fn layout_is_snapped_to_device_pixels_at_fractional_scales() {
+        for scale in [1.0, 1.25, 1.5, 1.75, 2.0] {
+            for metrics in [Metrics::NORMAL, Metrics::COMPACT] {
+                let layout = ChromeLayout::new(&metrics, scale);
+                assert_eq!(layout.chrome_height.fract(), 0.0);
+                assert!(layout.bookmarks_y + layout.bookmarks_height <= layout.chrome_height);
+                for placement in layout.toolbar(1001.0) {
+                    assert_eq!(placement.x.fract(), 0.0, "{} at {scale}", placement.id);
+                    assert_eq!(placement.width.fract(), 0.0, "{} at {scale}", placement.id);
+                    assert_eq!(placement.y, layout.tab_row_height);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn toolbar_fills_the_window_and_keeps_the_address_bar_minimum() {
+        let layout = ChromeLayout::new(&Metrics::NORMAL, 1.5);
+        let toolbar = layout.toolbar(1500.0);
+        let settings = toolbar.last().unwrap();
+        assert_eq!(settings.id, "settings");
+        assert!(1500.0 - (settings.x + settings.width) - layout.margin < 1.0);
+
+        let narrow = layout.toolbar(400.0);
+        let address_bar = narrow.iter().find(|placement| placement.id == "address_bar").unwrap();
+        assert_eq!(address_bar.width, 300.0);
+    }
+
+    #[test]
+    fn normal_density_keeps_the_original_chrome_size() {
+        let layout = ChromeLayout::new(&Metrics::NORMAL, 1.0);
+        assert_eq!(layout.chrome_height, 124.0);
+        assert_eq!((layout.tab_y, layout.toolbar_y, layout.bookmarks_y), (8.0, 48.0, 88.0));
+        assert!(Metrics::COMPACT.chrome_height() < Metrics::NORMAL.chrome_height());
+    }
+}
@@ -0,0 +1,256 @@
+// Colors of the browser chrome. Light and dark themes are built in; the custom theme starts from one
+// of them and replaces the colors listed in the profile's theme.json, which also keeps the theme and
+// density picked in the settings panel.
+
+use super::layout::Density;
+use serde::{Deserialize, Serialize};
+use skia_safe::Color;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const STORAGE_VERSION: u32 = 1;
+const THEME_FILE: &str = "theme.json";
+
+/// Which theme the chrome is drawn with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeChoice {
+    #[default]
+    Light,
+    Dark,
+    Custom,
+}
+
+impl ThemeChoice {
+    /// Next value when cycling through the settings panel button
+    pub fn next(self) -> Self {
+        match self {
+            Self::Light => Self::Dark,
+            Self::Dark => Self::Custom,
+            Self::Custom => Self::Light,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Light => "Light",
+            Self::Dark => "Dark",
+            Self::Custom => "Custom",
+        }
+    }
+}
+
+/// Defines `Theme` with one color per field and the light and dark value of each
+macro_rules! theme_colors {
+    ($($(#[$doc:meta])* $name:ident: $light:expr, $dark:expr;)*) => {
+        /// Colors the chrome is drawn with
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct Theme {
+            $($(#[$doc])* pub $name: Color,)*
+        }
+
+        impl Theme {
+            pub fn light() -> Self {
+                Self { $($name: $light,)* }
+            }
+
+            pub fn dark() -> Self {
+                Self { $($name: $dark,)* }
+            }
+
+            /// The color called `name` in theme.json
+            fn color_mut(&mut self, name: &str) -> Option<&mut Color> {
+                match name {
+                    $(stringify!($name) => Some(&mut self.$name),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+theme_colors! {
+    /// Background of the tab strip and toolbar
+    chrome: Color::from_rgb(240, 240, 240), Color::from_rgb(32, 33, 36);
+    /// Line between the chrome and the page
+    chrome_border: Color::from_rgb(200, 200, 200), Color::from_rgb(60, 62, 66);
+    button: Color::from_rgb(242, 242, 242), Color::from_rgb(53, 54, 58);
+    button_hover: Color::from_rgb(216, 229, 255), Color::from_rgb(66, 72, 88);
+    button_pressed: Color::from_rgb(191, 204, 242), Color::from_rgb(80, 90, 115);
+    /// Toolbar button that is switched on, like the bookmark star of a bookmarked page
+    button_active: Color::from_rgb(249, 224, 114), Color::from_rgb(120, 100, 40);
+    button_border: Color::from_rgb(180, 180, 180), Color::from_rgb(85, 87, 92);
+    /// Border of hovered buttons, the focused address bar and the active tab
+    accent: Color::from_rgb(100, 150, 255), Color::from_rgb(138, 180, 248);
+    icon: Color::from_rgb(60, 60, 60), Color::from_rgb(220, 222, 226);
+    tab: Color::from_rgb(204, 204, 204), Color::from_rgb(40, 41, 44);
+    tab_active: Color::from_rgb(242, 242, 242), Color::from_rgb(53, 54, 58);
+    tab_hover_border: Color::from_rgb(150, 180, 255), Color::from_rgb(110, 140, 200);
+    field: Color::from_rgb(250, 250, 250), Color::from_rgb(41, 42, 45);
+    field_focused: Color::WHITE, Color::from_rgb(48, 49, 52);
+    field_border: Color::from_rgb(178, 178, 178), Color::from_rgb(95, 99, 104);
+    /// Selected text in the address bar and the other chrome text fields
+    selection: Color::from_argb(140, 132, 185, 255), Color::from_argb(140, 80, 120, 200);
+    text: Color::BLACK, Color::from_rgb(232, 234, 237);
+    /// Titles of discarded tabs, section headings, and the address bar's URL around its domain
+    muted_text: Color::from_rgb(110, 110, 110), Color::from_rgb(154, 160, 166);
+    bookmarks_bar: Color::from_rgb(247, 247, 248), Color::from_rgb(38, 39, 42);
+    bookmark: Color::from_rgb(236, 236, 238), Color::from_rgb(50, 51, 55);
+    bookmark_hover: Color::from_rgb(228, 236, 248), Color::from_rgb(62, 68, 80);
+    bookmark_pressed: Color::from_rgb(206, 219, 239), Color::from_rgb(74, 82, 98);
+    bookmark_selected: Color::from_rgb(210, 228, 255), Color::from_rgb(58, 74, 104);
+    bookmark_border: Color::from_rgb(180, 180, 190), Color::from_rgb(80, 82, 88);
+    /// Background of the settings panel, menus, popups and dialogs
+    panel: Color::from_rgb(250, 250, 252), Color::from_rgb(41, 42, 45);
+    panel_border: Color::from_rgb(200, 200, 210), Color::from_rgb(80, 82, 88);
+    panel_separator: Color::from_rgb(220, 220, 220), Color::from_rgb(64, 66, 70);
+    panel_button: Color::from_rgb(236, 238, 244), Color::from_rgb(53, 54, 58);
+    panel_text: Color::from_rgb(40, 40, 40), Color::from_rgb(232, 234, 237);
+    /// Hovered row of a menu or popup
+    row_hover: Color::from_rgb(227, 236, 251), Color::from_rgb(60, 66, 80);
+    /// Buttons that confirm, like "Allow" and "OK"
+    primary_button: Color::from_rgb(70, 130, 220), Color::from_rgb(70, 130, 220);
+    primary_text: Color::WHITE, Color::WHITE;
+    prompt_bar: Color::from_rgb(255, 248, 225), Color::from_rgb(60, 55, 40);
+    prompt_bar_border: Color::from_rgb(220, 200, 150), Color::from_rgb(100, 90, 60);
+    tooltip: Color::from_rgb(255, 255, 220), Color::from_rgb(60, 62, 66);
+    tooltip_border: Color::from_rgb(180, 180, 140), Color::from_rgb(95, 99, 104);
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::light()
+    }
+}
+
+/// A color as the red, green and blue fractions components keep their colors in
+pub fn to_rgb(color: Color) -> [f32; 3] {
+    [color.r() as f32 / 255.0, color.g() as f32 / 255.0, color.b() as f32 / 255.0]
+}
+
+pub fn from_rgb([r, g, b]: [f32; 3]) -> Color {
+    let channel = |value: f32| (value * 255.0).round().clamp(0.0, 255.0) as u8;
+    Color::from_rgb(channel(r), channel(g), channel(b))
+}
+
+/// Parse "#rrggbb" or "#rrggbbaa"
+fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.trim().strip_prefix('#')?;
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |index: usize| u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok();
+    let alpha = if hex.len() == 8 { channel(3)? } else { 255 };
+    Some(Color::from_argb(alpha, channel(0)?, channel(1)?, channel(2)?))
+}
+
+/// The custom theme: the built-in theme it starts from and the colors it replaces, keyed by the
+/// names of `Theme`'s fields
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CustomTheme {
+    pub base: ThemeChoice,
+    pub colors: BTreeMap<String, String>,
+}
+
+impl CustomTheme {
+    fn theme(&self) -> Theme {
+        let mut theme = match self.base {
+            ThemeChoice::Dark => Theme::dark(),
+            ThemeChoice::Light | ThemeChoice::Custom => Theme::light(),
+        };
+        for (name, value) in &self.colors {
+            match (theme.color_mut(name), parse_color(value)) {
+                (Some(color), Some(value)) => *color = value,
+                (None, _) => tracing::warn!("Unknown theme color {}", name),
+                (_, None) => tracing::warn!("Invalid theme color {}: {}", name, value),
+            }
+        }
+        theme
+    }
+}
+
+/// The theme and density of the chrome, kept in theme.json in the profile
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeSettings {
+    pub theme: ThemeChoice,
+    pub density: Density,
+    pub custom: CustomTheme,
+}
+
+#[derive(Serialize)]
+struct PersistedThemeSettings<'a> {
+    version: u32,
+    #[serde(flatten)]
+    settings: &'a ThemeSettings,
+}
+
+impl ThemeSettings {
+    fn path() -> PathBuf {
+        crate::profile::data_dir().join(THEME_FILE)
+    }
+
+    pub fn load_from_disk() -> Self {
+        std::fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save_to_disk(&self) {
+        let payload = PersistedThemeSettings { version: STORAGE_VERSION, settings: self };
+        let Ok(json) = serde_json::to_string_pretty(&payload) else {
+            return;
+        };
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::write(path, json);
+    }
+
+    /// The colors of the chosen theme
+    pub fn colors(&self) -> Theme {
+        match self.theme {
+            ThemeChoice::Light => Theme::light(),
+            ThemeChoice::Dark => Theme::dark(),
+            ThemeChoice::Custom => self.custom.theme(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Density, Theme, ThemeChoice, ThemeSettings, from_rgb, parse_color, to_rgb};
+    use skia_safe::Color;
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_color("#ff8800"), Some(Color::from_rgb(255, 136, 0)));
+        assert_eq!(parse_color(" #11223380 "), Some(Color::from_argb(0x80, 0x11, 0x22, 0x33)));
+        assert_eq!(parse_color("ff8800"), None);
+        assert_eq!(parse_color("#ff88"), None);
+        assert_eq!(parse_color("#gg8800"), None);
+        assert_eq!(from_rgb(to_rgb(Color::from_rgb(242, 17, 128))), Color::from_rgb(242, 17, 128));
+    }
+
+    #[test]
+    fn custom_theme_overrides_colors_of_its_base() {
+        let settings: ThemeSettings = serde_json::from_str(
+            r##"{"theme": "custom", "density": "compact", "custom": {"base": "dark", "colors": {"accent": "#ff8800", "nope": "#000000"}}}"##,
+        ).unwrap();
+        assert_eq!(settings.density, Density::Compact);
+        let colors = settings.colors();
+        assert_eq!(colors.accent, Color::from_rgb(255, 136, 0));
+        assert_eq!(colors.chrome, Theme::dark().chrome);
+    }
+
+    #[test]
+    fn missing_or_unknown_settings_fall_back_to_the_defaults() {
+        let settings: ThemeSettings = serde_json::from_str(r#"{"version": 1}"#).unwrap();
+        assert_eq!(settings, ThemeSettings::default());
+        assert_eq!(settings.colors(), Theme::light());
+        assert_eq!(ThemeChoice::Custom.next(), ThemeChoice::Light);
+    }
+}
@@ -0,0 +1,290 @@
+// Visited links. The browser process records the pages the user visited and sends tab processes a
+// Bloom filter of them rather than the URLs, so a tab can style `:visited` links without holding
+// the browsing history. Stylo keeps `:visited` rules to color properties, and painting reads those
+// colors only, so a page can't tell from layout or script which links are visited.
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::{LazyLock, RwLock};
+use style::computed_value_flags::ComputedValueFlags;
+use style::properties::ComputedValues;
+use url::Url;
+
+const STORAGE_VERSION: u32 = 1;
+const VISITED_LINKS_FILE: &str = "visited_links.json";
+
+/// Visits kept before the oldest are forgotten
+const MAX_VISITED_LINKS: usize = 10_000;
+
+/// Filter bits per remembered URL, for about a 1% false positive rate
+const BITS_PER_URL: usize = 10;
+const HASHES: u32 = 7;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PersistedVisitedLinks {
+    #[serde(default = "default_storage_version")]
+    version: u32,
+    /// Oldest first
+    #[serde(default)]
+    urls: Vec<String>,
+}
+
+const fn default_storage_version() -> u32 {
+    STORAGE_VERSION
+}
+
+/// The URLs the user visited, kept by the browser process
+#[derive(Debug, Clone)]
+pub struct VisitedLinkStore {
+    /// Oldest first
+    urls: VecDeque<String>,
+    set: HashSet<String>,
+    path: PathBuf,
+}
+
+impl Default for VisitedLinkStore {
+    fn default() -> Self {
+        Self {
+            urls: VecDeque::new(),
+            set: HashSet::new(),
+            path: visited_links_file_path(),
+        }
+    }
+}
+
+impl VisitedLinkStore {
+    pub fn load_from_disk() -> Self {
+        let mut store = Self::default();
+        if let Ok(contents) = std::fs::read_to_string(&store.path) {
+            if let Ok(persisted) = serde_json::from_str::<PersistedVisitedLinks>(&contents) {
+                for url in persisted.urls {
+                    store.insert(url);
+                }
+            }
+        }
+        store
+    }
+
+    pub fn save_to_disk(&self) {
+        let payload = PersistedVisitedLinks {
+            version: STORAGE_VERSION,
+            urls: self.urls.iter().cloned().collect(),
+        };
+
+        let Ok(json) = serde_json::to_string(&payload) else {
+            return;
+        };
+
+        if let Some(parent) = self.path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::write(&self.path, json);
+    }
+
+    /// Record a visit to `url`. Returns whether it wasn't known yet, so tabs need a new filter.
+    pub fn add(&mut self, url: &str) -> bool {
+        let Some(key) = Url::parse(url).ok().filter(|url| is_recorded(url)).map(|url| link_key(&url)) else {
+            return false;
+        };
+        if self.set.contains(&key) {
+            return false;
+        }
+        self.insert(key);
+        true
+    }
+
+    fn insert(&mut self, key: String) {
+        if !self.set.insert(key.clone()) {
+            return;
+        }
+        self.urls.push_back(key);
+        while self.urls.len() > MAX_VISITED_LINKS {
+            if let Some(oldest) = self.urls.pop_front() {
+                self.set.remove(&oldest);
+            }
+        }
+    }
+
+    /// The origin that address bar text most likely completes to: that of the most recently
+    /// visited page whose address starts with it, ignoring the scheme and `www.`, or the text
+    /// itself when it reads as a host name. `None` for text that looks like a search.
+    pub fn likely_completion(&self, typed: &str) -> Option<String> {
+        let typed = typed.trim().to_ascii_lowercase();
+        if typed.is_empty() || typed.contains(char::is_whitespace) {
+            return None;
+        }
+        let typed = strip_scheme_and_www(&typed);
+        if typed.is_empty() {
+            return None;
+        }
+
+        let visited = self
+            .urls
+            .iter()
+            .rev()
+            .find(|url| strip_scheme_and_www(url).starts_with(typed))
+            .and_then(|url| Url::parse(url).ok());
+        let typed_host = || {
+            let host = typed.split(['/', '?', '#']).next()?;
+            let (_, tld) = host.rsplit_once('.')?;
+            if tld.len() < 2 || !tld.chars().all(|c| c.is_ascii_alphabetic()) {
+                return None;
+            }
+            Url::parse(&format!("https://{host}")).ok()
+        };
+        let url = visited.or_else(typed_host)?;
+        Some(url.origin().ascii_serialization())
+    }
+
+    pub fn filter(&self) -> VisitedLinkFilter {
+        let mut filter = VisitedLinkFilter::with_capacity(MAX_VISITED_LINKS);
+        for url in &self.urls {
+            filter.insert(url);
+        }
+        filter
+    }
+}
+
+/// A Bloom filter over visited URLs: it can answer "maybe visited" for a URL it was never given,
+/// but never "not visited" for one it was
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VisitedLinkFilter {
+    bits: Vec<u64>,
+}
+
+impl Default for VisitedLinkFilter {
+    fn default() -> Self {
+        Self { bits: vec![0] }
+    }
+}
+
+impl VisitedLinkFilter {
+    pub fn with_capacity(urls: usize) -> Self {
+        Self {
+            bits: vec![0; (urls * BITS_PER_URL).div_ceil(64).max(1)],
+        }
+    }
+
+    pub fn insert(&mut self, key: &str) {
+        for bit in self.bit_indices(key) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.bit_indices(key).all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Double hashing over FNV-1a, which unlike the std hasher is the same in every process and
+    /// every build
+    fn bit_indices(&self, key: &str) -> impl Iterator<Item = usize> {
+        let len = self.bits.len() as u64 * 64;
+        let first = fnv1a(key.as_bytes(), 0xcbf2_9ce4_8422_2325);
+        let second = fnv1a(key.as_bytes(), 0x6c62_272e_07bb_0142) | 1;
+        (0..HASHES as u64).map(move |i| (first.wrapping_add(i.wrapping_mul(second)) % len) as usize)
+    }
+}
+
+fn fnv1a(bytes: &[u8], basis: u64) -> u64 {
+    bytes.iter().fold(basis, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Only pages the user can come back to through a link are worth remembering
+fn is_recorded(url: &Url) -> bool {
+    matches!(url.scheme(), "http" | "https" | "file")
+}
+
+fn strip_scheme_and_www(url: &str) -> &str {
+    let url = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")).unwrap_or(url);
+    url.strip_prefix("www.").unwrap_or(url)
+}
+
+/// Links differing only in their fragment point at the same visited page
+fn link_key(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    url.into()
+}
+
+fn visited_links_file_path() -> PathBuf {
+    crate::profile::data_dir().join(VISITED_LINKS_FILE)
+}
+
+/// The tab process's copy of the filter, from the last `SetVisitedLinks` message
+static FILTER: LazyLock<RwLock<VisitedLinkFilter>> = LazyLock::new(|| RwLock::new(VisitedLinkFilter::default()));
+
+pub fn set_filter(filter: VisitedLinkFilter) {
+    if let Ok(mut current) = FILTER.write() {
+        *current = filter;
+    }
+}
+
+/// Whether a link to `url` should be styled as visited
+pub fn is_visited(url: &Url) -> bool {
+    is_recorded(url) && FILTER.read().is_ok_and(|filter| filter.contains(&link_key(url)))
+}
+
+/// The style to read colors from when painting: the `:visited` style for a visited link and its
+/// contents, otherwise `style` itself
+pub(crate) fn color_style(style: &ComputedValues) -> &ComputedValues {
+    match style.visited_style() {
+        Some(visited) if style.flags.contains(ComputedValueFlags::IS_RELEVANT_LINK_VISITED) => visited,
+        _ => style,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_remembers_inserted_urls() {
+        let mut filter = VisitedLinkFilter::with_capacity(100);
+        filter.insert("https://example.com/");
+        filter.insert("https://example.com/docs");
+        assert!(filter.contains("https://example.com/"));
+        assert!(filter.contains("https://example.com/docs"));
+        assert!(!filter.contains("https://example.com/other"));
+        assert!(!VisitedLinkFilter::default().contains("https://example.com/"));
+    }
+
+    #[test]
+    fn store_ignores_fragments_and_other_schemes() {
+        let mut store = VisitedLinkStore::default();
+        assert!(store.add("https://example.com/page#intro"));
+        assert!(!store.add("https://example.com/page#usage"));
+        assert!(!store.add("about:blank"));
+        assert!(!store.add("data:text/html,hi"));
+
+        let filter = store.filter();
+        assert!(filter.contains("https://example.com/page"));
+    }
+
+    #[test]
+    fn completes_typed_text_to_the_most_recent_match() {
+        let mut store = VisitedLinkStore::default();
+        store.add("https://www.rust-lang.org/learn");
+        store.add("https://docs.rs/serde");
+        store.add("http://rustup.rs/");
+
+        assert_eq!(store.likely_completion("rust").as_deref(), Some("http://rustup.rs"));
+        assert_eq!(store.likely_completion("rust-").as_deref(), Some("https://www.rust-lang.org"));
+        assert_eq!(store.likely_completion("https://docs").as_deref(), Some("https://docs.rs"));
+        // Text that reads as a host name is connected to even when it was never visited
+        assert_eq!(store.likely_completion("example.com/page").as_deref(), Some("https://example.com"));
+        assert_eq!(store.likely_completion("exam"), None);
+        assert_eq!(store.likely_completion("rust book"), None);
+        assert_eq!(store.likely_completion("1.5"), None);
+    }
+
+    #[test]
+    fn store_forgets_the_oldest_visits() {
+        let mut store = VisitedLinkStore::default();
+        for i in 0..=MAX_VISITED_LINKS {
+            store.add(&format!("https://example.com/{i}"));
+        }
+        assert_eq!(store.urls.len(), MAX_VISITED_LINKS);
+        assert!(!store.set.contains("https://example.com/0"));
+        assert!(store.add("https://example.com/0"));
+    }
+}
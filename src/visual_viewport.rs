@@ -0,0 +1,100 @@
+// The visual viewport: the part of the layout viewport that is on screen. Pinching or Ctrl+wheel
+// magnifies the page the way a phone does, without relayout. The tab keeps painting its layout
+// viewport at the same size; the browser scales and pans that frame when compositing it, maps
+// pointer positions back through the same transform, and tells the tab where the visual viewport
+// is so the page can read it from `window.visualViewport`.
+//
+// Page zoom is a different thing: it changes the CSS pixel size and lays the page out again.
+
+use serde::{Deserialize, Serialize};
+
+/// Furthest the visual viewport magnifies the page
+pub const MAX_VISUAL_SCALE: f32 = 5.0;
+
+/// Where the visual viewport is. Offsets are from the top left of the layout viewport, and like
+/// sizes given to its methods are in the page area's logical pixels before page zoom.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VisualViewport {
+    pub scale: f32,
+    pub offset_x: f32,
+    pub offset_y: f32,
+}
+
+impl Default for VisualViewport {
+    fn default() -> Self {
+        Self { scale: 1.0, offset_x: 0.0, offset_y: 0.0 }
+    }
+}
+
+impl VisualViewport {
+    pub fn is_magnified(&self) -> bool {
+        self.scale > 1.0
+    }
+
+    /// Magnify by `factor`, keeping the page under `focus`, a point on screen relative to the page
+    /// area, where it is
+    pub fn zoom_around(&mut self, factor: f32, focus: (f32, f32), layout_size: (f32, f32)) {
+        let (x, y) = self.to_layout(focus);
+        self.scale = (self.scale * factor).clamp(1.0, MAX_VISUAL_SCALE);
+        self.offset_x = x - focus.0 / self.scale;
+        self.offset_y = y - focus.1 / self.scale;
+        self.clamp(layout_size);
+    }
+
+    /// Pan by `(dx, dy)` on screen, as far as the layout viewport allows. Returns how much of it
+    /// was left over at the edges, to scroll the page by instead.
+    pub fn pan(&mut self, dx: f32, dy: f32, layout_size: (f32, f32)) -> (f32, f32) {
+        let (before_x, before_y) = (self.offset_x, self.offset_y);
+        self.offset_x += dx / self.scale;
+        self.offset_y += dy / self.scale;
+        self.clamp(layout_size);
+        (
+            dx - (self.offset_x - before_x) * self.scale,
+            dy - (self.offset_y - before_y) * self.scale,
+        )
+    }
+
+    /// The point of the layout viewport shown at `point` on screen
+    pub fn to_layout(&self, point: (f32, f32)) -> (f32, f32) {
+        (self.offset_x + point.0 / self.scale, self.offset_y + point.1 / self.scale)
+    }
+
+    /// Keep the visual viewport inside the layout viewport, as after a window resize
+    pub fn clamp(&mut self, layout_size: (f32, f32)) {
+        let max_x = (layout_size.0 - layout_size.0 / self.scale).max(0.0);
+        let max_y = (layout_size.1 - layout_size.1 / self.scale).max(0.0);
+        self.offset_x = self.offset_x.clamp(0.0, max_x);
+        self.offset_y = self.offset_y.clamp(0.0, max_y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAYOUT: (f32, f32) = (800.0, 600.0);
+
+    #[test]
+    fn zooming_keeps_the_focus_in_place() {
+        let mut viewport = VisualViewport::default();
+        viewport.zoom_around(2.0, (400.0, 300.0), LAYOUT);
+        assert_eq!(viewport, VisualViewport { scale: 2.0, offset_x: 200.0, offset_y: 150.0 });
+        assert_eq!(viewport.to_layout((400.0, 300.0)), (400.0, 300.0));
+
+        // Zooming out past 1 snaps back to the whole layout viewport
+        viewport.zoom_around(0.25, (0.0, 0.0), LAYOUT);
+        assert_eq!(viewport, VisualViewport::default());
+    }
+
+    #[test]
+    fn panning_stops_at_the_layout_viewport_edges() {
+        let mut viewport = VisualViewport::default();
+        viewport.zoom_around(2.0, (0.0, 0.0), LAYOUT);
+        assert_eq!(viewport.pan(100.0, -50.0, LAYOUT), (0.0, -50.0));
+        assert_eq!((viewport.offset_x, viewport.offset_y), (50.0, 0.0));
+
+        // Only 400px of the 800 are off screen, so most of a long pan is left for the page
+        assert_eq!(viewport.pan(1000.0, 0.0, LAYOUT), (300.0, 0.0));
+        assert_eq!(VisualViewport::default().pan(10.0, 10.0, LAYOUT), (10.0, 10.0));
+    }
+}
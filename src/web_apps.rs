@@ -0,0 +1,437 @@
+// Installable web apps. A page that links a web app manifest can be installed: its icon is saved in
+// the profile and a desktop shortcut starts the browser with `--app=<start_url>`, which opens the
+// app in a window without tabs, toolbar or bookmarks bar.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use url::Url;
+
+const STORAGE_VERSION: u32 = 1;
+const APPS_FILE: &str = "web_apps.json";
+const ICONS_DIR: &str = "web_app_icons";
+/// The command-line flag that opens a URL in app mode
+const APP_FLAG: &str = "--app";
+
+/// An icon listed in a manifest, with its URL resolved
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestIcon {
+    pub src: String,
+    /// Space-separated sizes such as "192x192 512x512", or "any" for scalable icons
+    pub sizes: String,
+    pub purpose: String,
+}
+
+impl ManifestIcon {
+    /// Width of the largest size the icon comes in; scalable icons count as large
+    fn largest_size(&self) -> u32 {
+        self.sizes
+            .split_ascii_whitespace()
+            .filter_map(|size| {
+                if size.eq_ignore_ascii_case("any") {
+                    return Some(1024);
+                }
+                let (width, _) = size.split_once(['x', 'X'])?;
+                width.parse().ok()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// The parts of a web app manifest the browser uses, with URLs resolved
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebAppManifest {
+    pub name: String,
+    pub short_name: String,
+    pub start_url: String,
+    /// URLs starting with the scope belong to the app
+    pub scope: String,
+    /// "fullscreen", "standalone", "minimal-ui" or "browser"
+    pub display: String,
+    pub icons: Vec<ManifestIcon>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawManifest {
+    name: Option<String>,
+    short_name: Option<String>,
+    start_url: Option<String>,
+    scope: Option<String>,
+    display: Option<String>,
+    icons: Vec<RawIcon>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawIcon {
+    src: String,
+    sizes: Option<String>,
+    purpose: Option<String>,
+}
+
+/// Parse the manifest at `manifest_url`, linked from the document at `document_url`. Like other
+/// browsers, a start_url on another origin than the document is replaced by the document's URL,
+/// and a scope that doesn't contain the start_url by the start_url's directory. Manifests without
+/// a name can't be installed and give `None`.
+pub fn parse_manifest(json: &str, manifest_url: &Url, document_url: &Url) -> Option<WebAppManifest> {
+    let raw: RawManifest = serde_json::from_str(json).ok()?;
+    let trimmed = |value: Option<String>| value.map(|value| value.trim().to_string()).filter(|value| !value.is_empty());
+    let short_name = trimmed(raw.short_name);
+    let name = trimmed(raw.name).or_else(|| short_name.clone())?;
+
+    let start_url = raw
+        .start_url
+        .and_then(|start_url| manifest_url.join(&start_url).ok())
+        .filter(|start_url| start_url.origin() == document_url.origin())
+        .unwrap_or_else(|| document_url.clone());
+    let default_scope = start_url.join("./").ok()?;
+    let scope = raw
+        .scope
+        .and_then(|scope| manifest_url.join(&scope).ok())
+        .filter(|scope| scope.origin() == start_url.origin() && start_url.path().starts_with(scope.path()))
+        .unwrap_or(default_scope);
+    let display = match raw.display.as_deref().map(str::trim) {
+        Some(display @ ("fullscreen" | "standalone" | "minimal-ui")) => display.to_string(),
+        _ => "browser".to_string(),
+    };
+    let icons = raw
+        .icons
+        .into_iter()
+        .filter_map(|icon| {
+            let src = manifest_url.join(&icon.src).ok()?;
+            Some(ManifestIcon {
+                src: src.to_string(),
+                sizes: icon.sizes.unwrap_or_default(),
+                purpose: icon.purpose.unwrap_or_else(|| "any".to_string()),
+            })
+        })
+        .collect();
+
+    Some(WebAppManifest {
+        short_name: short_name.unwrap_or_else(|| name.clone()),
+        name,
+        start_url: start_url.to_string(),
+        scope: scope.to_string(),
+        display,
+        icons,
+    })
+}
+
+impl WebAppManifest {
+    /// Apps that ask for a browser tab (display "browser") aren't offered for installation
+    pub fn is_installable(&self) -> bool {
+        self.display != "browser"
+    }
+
+    /// The largest icon meant for general use, for the shortcut
+    pub fn best_icon(&self) -> Option<&ManifestIcon> {
+        self.icons
+            .iter()
+            .filter(|icon| icon.purpose.split_ascii_whitespace().any(|purpose| purpose == "any"))
+            .max_by_key(|icon| icon.largest_size())
+    }
+
+    pub fn in_scope(&self, url: &str) -> bool {
+        url.starts_with(&self.scope)
+    }
+}
+
+/// An app the user installed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstalledApp {
+    pub name: String,
+    pub start_url: String,
+    pub scope: String,
+    /// The shortcut that launches the app
+    pub shortcut: PathBuf,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct AppsFile {
+    version: u32,
+    apps: Vec<InstalledApp>,
+}
+
+fn apps_path() -> PathBuf {
+    crate::profile::data_dir().join(APPS_FILE)
+}
+
+/// The apps installed in this profile
+pub fn installed() -> Vec<InstalledApp> {
+    std::fs::read_to_string(apps_path())
+        .ok()
+        .and_then(|contents| serde_json::from_str::<AppsFile>(&contents).ok())
+        .map(|file| file.apps)
+        .unwrap_or_default()
+}
+
+fn save_installed(apps: Vec<InstalledApp>) {
+    let file = AppsFile { version: STORAGE_VERSION, apps };
+    let Ok(json) = serde_json::to_string_pretty(&file) else {
+        return;
+    };
+    let _ = std::fs::write(apps_path(), json);
+}
+
+pub fn is_installed(manifest: &WebAppManifest) -> bool {
+    installed().iter().any(|app| app.start_url == manifest.start_url)
+}
+
+/// A name for the app's files that stays the same across installs
+fn app_id(start_url: &str) -> String {
+    let digest = Sha256::digest(start_url.as_bytes());
+    digest.iter().take(8).map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Install the app: save `icon` (the bytes of the manifest's best icon, in any format the image
+/// crate reads) and create a desktop shortcut that opens its start_url in app mode. Returns where
+/// the shortcut was put.
+pub fn install(manifest: &WebAppManifest, icon: Option<&[u8]>) -> Result<PathBuf, String> {
+    let id = app_id(&manifest.start_url);
+    let icon_path = icon.and_then(|bytes| save_icon(&id, bytes));
+    let exe = std::env::current_exe().map_err(|e| format!("can't find the browser executable: {e}"))?;
+    let args = launch_args(&crate::profile::current_name(), &manifest.start_url);
+    let shortcut = create_shortcut(&id, &manifest.name, &exe, &args, icon_path.as_deref())?;
+
+    let mut apps = installed();
+    apps.retain(|app| app.start_url != manifest.start_url);
+    apps.push(InstalledApp {
+        name: manifest.name.clone(),
+        start_url: manifest.start_url.clone(),
+        scope: manifest.scope.clone(),
+        shortcut: shortcut.clone(),
+    });
+    save_installed(apps);
+    Ok(shortcut)
+}
+
+/// Convert the icon to PNG in the profile's icon directory
+fn save_icon(id: &str, bytes: &[u8]) -> Option<PathBuf> {
+    let image = match image::load_from_memory(bytes) {
+        Ok(image) => image,
+        Err(e) => {
+            tracing::warn!("Failed to decode web app icon: {}", e);
+            return None;
+        }
+    };
+    let dir = crate::profile::data_dir().join(ICONS_DIR);
+    let _ = std::fs::create_dir_all(&dir);
+    let path = dir.join(format!("{id}.png"));
+    match image.save_with_format(&path, image::ImageFormat::Png) {
+        Ok(()) => Some(path),
+        Err(e) => {
+            tracing::warn!("Failed to save web app icon {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Arguments that start the browser with `profile` and open `start_url` in app mode
+fn launch_args(profile: &str, start_url: &str) -> Vec<String> {
+    vec!["--profile".to_string(), profile.to_string(), format!("{APP_FLAG}={start_url}")]
+}
+
+/// The URL given with `--app=<url>` or `--app <url>`, which opens it in app mode
+pub fn app_url_from_args(args: &[String]) -> Option<String> {
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == APP_FLAG {
+            return args.next().cloned();
+        }
+        if let Some(url) = arg.strip_prefix(APP_FLAG).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(url.to_string());
+        }
+    }
+    None
+}
+
+/// A file name made of the characters of `name` that are safe everywhere
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn file_name(name: &str) -> String {
+    let safe: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_') { c } else { '_' })
+        .collect();
+    let safe = safe.trim();
+    if safe.is_empty() { "Web App".to_string() } else { safe.to_string() }
+}
+
+/// Quote an argument of a desktop entry's Exec key
+fn quote_exec_arg(arg: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        match c {
+            '"' | '`' | '$' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            // Field codes like %u start with a percent sign
+            '%' => quoted.push_str("%%"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn desktop_entry(id: &str, name: &str, exe: &Path, args: &[String], icon: Option<&Path>) -> String {
+    let exec: Vec<String> = std::iter::once(exe.to_string_lossy().into_owned())
+        .chain(args.iter().cloned())
+        .map(|arg| quote_exec_arg(&arg))
+        .collect();
+    let name = name.replace(['\n', '\r'], " ");
+    let mut entry = format!(
+        "[Desktop Entry]\nType=Application\nName={name}\nComment=Web app installed from Stokes Browser\nExec={}\nTerminal=false\nStartupWMClass=stokes-app-{id}\nCategories=Network;\n",
+        exec.join(" "),
+    );
+    if let Some(icon) = icon {
+        entry.push_str(&format!("Icon={}\n", icon.display()));
+    }
+    entry
+}
+
+#[cfg(target_os = "linux")]
+fn create_shortcut(id: &str, name: &str, exe: &Path, args: &[String], icon: Option<&Path>) -> Result<PathBuf, String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let entry = desktop_entry(id, name, exe, args, icon);
+    let write = |path: &Path| -> Result<(), String> {
+        std::fs::write(path, &entry).map_err(|e| format!("can't write {}: {e}", path.display()))?;
+        // Desktops only launch entries on the desktop that are executable
+        let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755));
+        Ok(())
+    };
+
+    // The application menu entry, and a copy on the desktop when there is one
+    let file = format!("stokes-app-{id}.desktop");
+    let applications = dirs::data_dir().ok_or("no data directory")?.join("applications");
+    std::fs::create_dir_all(&applications).map_err(|e| e.to_string())?;
+    let menu_entry = applications.join(&file);
+    write(&menu_entry)?;
+    match dirs::desktop_dir().filter(|dir| dir.is_dir()) {
+        Some(desktop) => {
+            let shortcut = desktop.join(&file);
+            write(&shortcut)?;
+            Ok(shortcut)
+        }
+        None => Ok(menu_entry),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn create_shortcut(_id: &str, name: &str, exe: &Path, args: &[String], _icon: Option<&Path>) -> Result<PathBuf, String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // A shell script the Finder runs when it is opened. It keeps the generic script icon.
+    let desktop = dirs::desktop_dir().ok_or("no desktop directory")?;
+    let path = desktop.join(format!("{}.command", file_name(name)));
+    let quote = |arg: &str| format!("'{}'", arg.replace('\'', "'\\''"));
+    let command: Vec<String> = std::iter::once(exe.to_string_lossy().into_owned())
+        .chain(args.iter().cloned())
+        .map(|arg| quote(&arg))
+        .collect();
+    let script = format!("#!/bin/sh\nexec {} >/dev/null 2>&1 &\n", command.join(" "));
+    std::fs::write(&path, script).map_err(|e| format!("can't write {}: {e}", path.display()))?;
+    let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755));
+    Ok(path)
+}
+
+#[cfg(target_os = "windows")]
+fn create_shortcut(id: &str, name: &str, exe: &Path, args: &[String], icon: Option<&Path>) -> Result<PathBuf, String> {
+    use std::process::Command;
+
+    let desktop = dirs::desktop_dir().ok_or("no desktop directory")?;
+    let path = desktop.join(format!("{}.lnk", file_name(name)));
+    // Shortcuts take their icon from an .ico file
+    let icon = icon.and_then(|png| {
+        let ico = png.with_file_name(format!("{id}.ico"));
+        let image = image::open(png).ok()?.resize(256, 256, image::imageops::FilterType::Lanczos3);
+        image.save_with_format(&ico, image::ImageFormat::Ico).ok()?;
+        Some(ico)
+    });
+    let quote = |value: &str| format!("'{}'", value.replace('\'', "''"));
+    let arguments: Vec<String> = args.iter().map(|arg| format!("\"{}\"", arg.replace('"', "\\\""))).collect();
+    let mut script = format!(
+        "$s = (New-Object -ComObject WScript.Shell).CreateShortcut({}); $s.TargetPath = {}; $s.Arguments = {};",
+        quote(&path.to_string_lossy()),
+        quote(&exe.to_string_lossy()),
+        quote(&arguments.join(" ")),
+    );
+    if let Some(icon) = icon {
+        script.push_str(&format!(" $s.IconLocation = {};", quote(&icon.to_string_lossy())));
+    }
+    script.push_str(" $s.Save()");
+    let status = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .status()
+        .map_err(|e| format!("can't run powershell: {e}"))?;
+    if !status.success() {
+        return Err(format!("powershell exited with {status}"));
+    }
+    Ok(path)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn create_shortcut(_id: &str, _name: &str, _exe: &Path, _args: &[String], _icon: Option<&Path>) -> Result<PathBuf, String> {
+    Err("shortcuts can't be created on this platform".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{app_url_from_args, desktop_entry, launch_args, parse_manifest};
+    use std::path::Path;
+    use url::Url;
+
+    fn parse(json: &str) -> Option<super::WebAppManifest> {
+        let manifest_url = Url::parse("https://example.com/app/manifest.json").unwrap();
+        let document_url = Url::parse("https://example.com/app/index.html?x=1").unwrap();
+        parse_manifest(json, &manifest_url, &document_url)
+    }
+
+    #[test]
+    fn resolves_manifest_urls_against_the_manifest() {
+        let manifest = parse(r#"{
+            "name": "Example App", "short_name": "Example", "start_url": "./?source=pwa",
+            "display": "standalone",
+            "icons": [
+                {"src": "icon-192.png", "sizes": "192x192"},
+                {"src": "/icon-512.png", "sizes": "512x512", "purpose": "any maskable"},
+                {"src": "mask.png", "sizes": "1024x1024", "purpose": "maskable"}
+            ]
+        }"#).unwrap();
+        assert_eq!(manifest.start_url, "https://example.com/app/?source=pwa");
+        assert_eq!(manifest.scope, "https://example.com/app/");
+        assert!(manifest.is_installable());
+        assert_eq!(manifest.best_icon().unwrap().src, "https://example.com/icon-512.png");
+        assert!(manifest.in_scope("https://example.com/app/page"));
+        assert!(!manifest.in_scope("https://example.com/other"));
+    }
+
+    #[test]
+    fn falls_back_when_the_manifest_points_elsewhere() {
+        let manifest = parse(r#"{"short_name": "Ex", "start_url": "https://evil.test/", "scope": "/other/"}"#).unwrap();
+        assert_eq!(manifest.name, "Ex");
+        assert_eq!(manifest.start_url, "https://example.com/app/index.html?x=1");
+        assert_eq!(manifest.scope, "https://example.com/app/");
+        assert_eq!(manifest.display, "browser");
+        assert!(!manifest.is_installable());
+
+        assert!(parse(r#"{"start_url": "/"}"#).is_none());
+        assert!(parse("not json").is_none());
+    }
+
+    #[test]
+    fn shortcut_opens_the_start_url_in_app_mode() {
+        let args = launch_args("Work", "https://example.com/app/?a=1&b=%20");
+        let entry = desktop_entry("abc", "Example", Path::new("/opt/stokes browser/stokes"), &args, None);
+        assert!(entry.contains("Exec=\"/opt/stokes browser/stokes\" \"--profile\" \"Work\" \"--app=https://example.com/app/?a=1&b=%%20\"\n"));
+        assert!(!entry.contains("Icon="));
+
+        let args: Vec<String> = ["stokes", "--profile", "Work"].iter().map(|arg| arg.to_string()).chain(args).collect();
+        assert_eq!(app_url_from_args(&args), Some("https://example.com/app/?a=1&b=%20".to_string()));
+        assert_eq!(app_url_from_args(&["stokes".to_string(), "--apps".to_string()]), None);
+    }
+}
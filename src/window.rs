@@ -2,21 +2,61 @@ use gl::types::GLint;
 use glutin::config::{ConfigTemplateBuilder, GlConfig};
 use glutin::context::{ContextApi, ContextAttributesBuilder, NotCurrentGlContext, PossiblyCurrentContext};
 use glutin::display::{GetGlDisplay, GlDisplay};
-use glutin::surface::{Surface as GlutinSurface, SurfaceAttributesBuilder, WindowSurface};
+use glutin::surface::{GlSurface, Surface as GlutinSurface, SurfaceAttributesBuilder, SwapInterval, WindowSurface};
 use glutin_winit::{ApiPreference, DisplayBuilder};
 use skia_safe::gpu::gl::{Format, FramebufferInfo, Interface};
 use skia_safe::gpu::surfaces::wrap_backend_render_target;
 use skia_safe::gpu::{backend_render_targets, DirectContext};
 use skia_safe::{gpu, ColorType, Surface};
 use std::ffi::CString;
+use std::fmt;
 use std::num::NonZeroU32;
-use winit::dpi::LogicalSize;
+use std::time::Duration;
+use tracing::warn;
+use crate::window_placement::{MonitorArea, WindowPlacement};
+use winit::dpi::{LogicalSize, PhysicalPosition};
 use winit::event_loop::EventLoop;
 use winit::raw_window_handle::HasWindowHandle;
 use winit::window::{Window, WindowAttributes};
 use winit_core::event_loop::ActiveEventLoop;
 use winit_core::icon::{Icon, RgbaIcon};
 
+/// Why a browser window or its drawing surface could not be set up
+#[derive(Debug)]
+pub enum WindowError {
+    /// No GL display or config could be created for the window
+    Display(String),
+    /// The platform created no window alongside the GL config
+    NoWindow,
+    WindowHandle(String),
+    Context(String),
+    Surface(String),
+    /// The GL function pointers Skia needs could not be loaded
+    GlInterface,
+    SkiaContext,
+    /// Skia could not wrap the window's framebuffer, as when the window has no area
+    RenderTarget { width: u32, height: u32 },
+}
+
+impl fmt::Display for WindowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WindowError::Display(msg) => write!(f, "Could not create a GL display: {}", msg),
+            WindowError::NoWindow => write!(f, "Could not create a window with an OpenGL context"),
+            WindowError::WindowHandle(msg) => write!(f, "Could not get the window handle: {}", msg),
+            WindowError::Context(msg) => write!(f, "Could not create a GL context: {}", msg),
+            WindowError::Surface(msg) => write!(f, "Could not create a GL surface: {}", msg),
+            WindowError::GlInterface => write!(f, "Could not load the GL interface"),
+            WindowError::SkiaContext => write!(f, "Could not create the Skia GL context"),
+            WindowError::RenderTarget { width, height } => {
+                write!(f, "Could not create a {}x{} render target", width, height)
+            }
+        }
+    }
+}
+
+impl std::error::Error for WindowError {}
+
 pub(crate) struct Env {
     pub(crate) surface: Surface,
     pub(crate) gl_surface: GlutinSurface<WindowSurface>,
@@ -28,29 +68,74 @@ pub(crate) struct Env {
     pub(crate) stencil_size: usize,
 }
 
-pub(crate) fn create_window(el: &dyn ActiveEventLoop) -> Env {
-    // Load and set window icon
+/// The window icon. A window without one still works, so failing to decode it is only logged.
+fn window_icon() -> Option<Icon> {
     let icon_data = include_bytes!("../assets/com.ethanstokes.stokes-browser.png");
-    let icon = image::load_from_memory(icon_data)
-        .expect("Failed to load icon")
-        .into_rgba8();
+    let icon = match image::load_from_memory(icon_data) {
+        Ok(icon) => icon.into_rgba8(),
+        Err(e) => {
+            warn!("Failed to load the window icon: {}", e);
+            return None;
+        }
+    };
     let (icon_width, icon_height) = icon.dimensions();
-    let icon: Icon = RgbaIcon::new(icon.into_raw(), icon_width, icon_height)
-        .expect("Failed to create icon")
-        .into();
+    match RgbaIcon::new(icon.into_raw(), icon_width, icon_height) {
+        Ok(icon) => Some(icon.into()),
+        Err(e) => {
+            warn!("Failed to create the window icon: {}", e);
+            None
+        }
+    }
+}
 
+/// The monitors connected now, the primary one first
+fn monitor_areas(el: &dyn ActiveEventLoop) -> Vec<MonitorArea> {
+    el.primary_monitor()
+        .into_iter()
+        .chain(el.available_monitors())
+        .filter_map(|monitor| {
+            let position = monitor.position()?;
+            let size = monitor.current_video_mode()?.size();
+            Some(MonitorArea {
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+                scale_factor: monitor.scale_factor(),
+            })
+        })
+        .collect()
+}
+
+/// Time between refreshes of the monitor the window is on, when the platform reports it
+pub(crate) fn refresh_interval(window: &dyn Window) -> Option<Duration> {
+    let millihertz = window.current_monitor()?.current_video_mode()?.refresh_rate_millihertz()?.get();
+    Some(Duration::from_secs_f64(1000.0 / f64::from(millihertz)))
+}
+
+/// Open a browser window, where and as large as `placement` says if given
+pub(crate) fn create_window(el: &dyn ActiveEventLoop, placement: Option<WindowPlacement>) -> Result<Env, WindowError> {
     // Create window
-    let window_attrs = WindowAttributes::default()
+    let mut window_attrs = WindowAttributes::default()
         .with_title("Stokes Browser")
         .with_surface_size(LogicalSize::new(1024, 768))
-        .with_min_surface_size(LogicalSize::new(500, crate::ui::BrowserUI::CHROME_HEIGHT as i32))
-        .with_window_icon(Some(icon));
+        .with_min_surface_size(LogicalSize::new(500, crate::ui::Density::Normal.metrics().chrome_height() as i32))
+        .with_window_icon(window_icon());
+    if let Some(placement) = placement.map(|placement| placement.clamp_to_monitors(&monitor_areas(el))) {
+        window_attrs = window_attrs
+            .with_surface_size(LogicalSize::new(placement.width, placement.height))
+            .with_maximized(placement.maximized);
+        if let Some((x, y)) = placement.position {
+            window_attrs = window_attrs.with_position(PhysicalPosition::new(x, y));
+        }
+    }
 
     let template = ConfigTemplateBuilder::new()
         .with_alpha_size(8)
         .with_transparency(true);
 
     let display_builder = DisplayBuilder::new().with_preference(ApiPreference::PreferEgl).with_window_attributes(window_attrs.into());
+    // glutin only calls the picker with at least one config, so the reduce always has a result
     let (window, gl_config) = display_builder
         .build(el, template, |configs| {
             configs
@@ -63,12 +148,12 @@ pub(crate) fn create_window(el: &dyn ActiveEventLoop) -> Env {
                         accum
                     }
                 })
-                .unwrap()
+                .expect("glutin offered no GL configs")
         })
-        .unwrap();
+        .map_err(|e| WindowError::Display(e.to_string()))?;
 
-    let window = window.expect("Could not create window with OpenGL context.");
-    let window_handle = window.window_handle().expect("Failed to retrieve RawWindowHandle");
+    let window = window.ok_or(WindowError::NoWindow)?;
+    let window_handle = window.window_handle().map_err(|e| WindowError::WindowHandle(e.to_string()))?;
     let raw_window_handle = window_handle.as_raw();
 
     // Create GL context
@@ -81,31 +166,37 @@ pub(crate) fn create_window(el: &dyn ActiveEventLoop) -> Env {
         gl_config
             .display()
             .create_context(&gl_config, &context_attributes)
-            .unwrap_or_else(|_| {
+            .or_else(|_| {
                 gl_config
                     .display()
                     .create_context(&gl_config, &fallback_context_attributes)
-                    .expect("failed to create context")
             })
+            .map_err(|e| WindowError::Context(e.to_string()))?
     };
 
-    let (width, height) = window.surface_size().into();
+    // A window can report no area before it is first shown; the surface is resized once it does
+    let (width, height): (u32, u32) = window.surface_size().into();
     let attrs = SurfaceAttributesBuilder::<WindowSurface>::new().build(
         raw_window_handle,
-        NonZeroU32::new(width).unwrap(),
-        NonZeroU32::new(height).unwrap()
+        NonZeroU32::new(width).unwrap_or(NonZeroU32::MIN),
+        NonZeroU32::new(height).unwrap_or(NonZeroU32::MIN)
     );
 
     let gl_surface = unsafe {
         gl_config
             .display()
             .create_window_surface(&gl_config, &attrs)
-            .expect("Failed to create GL surface")
+            .map_err(|e| WindowError::Surface(e.to_string()))?
     };
 
     let gl_context = not_current_gl_context
         .make_current(&gl_surface)
-        .expect("Failed to make GL context current");
+        .map_err(|e| WindowError::Context(e.to_string()))?;
+
+    // Present in step with the display, so the time each present completes marks a vsync
+    if let Err(e) = gl_surface.set_swap_interval(&gl_context, SwapInterval::Wait(NonZeroU32::MIN)) {
+        warn!("Failed to enable vsync: {e}");
+    }
 
     gl::load_with(|s| {
         gl_config
@@ -120,19 +211,17 @@ pub(crate) fn create_window(el: &dyn ActiveEventLoop) -> Env {
         gl_config
             .display()
             .get_proc_address(CString::new(name).unwrap().as_c_str())
-    }).expect("Could not create interface");
+    }).ok_or(WindowError::GlInterface)?;
 
     let context_options = gpu::ContextOptions::default();
     let mut gr_context = gpu::direct_contexts::make_gl(interface, Some(&context_options))
-        .expect("Failed to create Skia GL context");
-
-    let size = window.surface_size();
+        .ok_or(WindowError::SkiaContext)?;
 
     let fb_info = {
         let mut fboid: GLint = 0;
         unsafe { gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut fboid) };
         FramebufferInfo {
-            fboid: fboid.try_into().unwrap(),
+            fboid: fboid.try_into().unwrap_or_default(),
             format: Format::RGBA8.into(),
             ..Default::default()
         }
@@ -141,9 +230,9 @@ pub(crate) fn create_window(el: &dyn ActiveEventLoop) -> Env {
     let num_samples = gl_config.num_samples() as usize;
     let stencil_size = gl_config.stencil_size() as usize;
 
-    let surface = create_surface(&window, fb_info, &mut gr_context, num_samples, stencil_size);
+    let surface = create_surface(&window, fb_info, &mut gr_context, num_samples, stencil_size)?;
 
-    Env {
+    Ok(Env {
         surface,
         gl_surface,
         gr_context: gr_context.clone(),
@@ -152,7 +241,7 @@ pub(crate) fn create_window(el: &dyn ActiveEventLoop) -> Env {
         fb_info,
         num_samples,
         stencil_size,
-    }
+    })
 }
 
 pub(crate) fn create_surface(
@@ -161,19 +250,20 @@ pub(crate) fn create_surface(
     gr_context: &mut DirectContext,
     num_samples: usize,
     stencil_size: usize
-) -> Surface {
+) -> Result<Surface, WindowError> {
     let size = window.surface_size();
-    let size = (
-        size.width.try_into().expect("Could not convert width"),
-        size.height.try_into().expect("Could not convert height")
+    let (width, height) = (size.width.max(1), size.height.max(1));
+    let render_target_size = (
+        i32::try_from(width).unwrap_or(i32::MAX),
+        i32::try_from(height).unwrap_or(i32::MAX),
     );
-    let backend_render_target = backend_render_targets::make_gl(size, num_samples, stencil_size, fb_info);
+    let backend_render_target = backend_render_targets::make_gl(render_target_size, num_samples, stencil_size, fb_info);
     wrap_backend_render_target(
         gr_context,
         &backend_render_target,
         gpu::SurfaceOrigin::BottomLeft,
         ColorType::RGBA8888,
-        None,
+        Some(crate::color_management::display_color_space()),
         None
-    ).expect("Failed to wrap backend render target")
+    ).ok_or(WindowError::RenderTarget { width, height })
 }
\ No newline at end of file
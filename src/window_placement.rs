@@ -0,0 +1,145 @@
+// Where the browser window was left and how big it was, saved per profile so the next launch
+// opens it the same way. Saved positions are checked against the monitors present at launch,
+// since the one the window was on may be gone.
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const WINDOW_PLACEMENT_FILE: &str = "window.json";
+/// How much of a window's top edge has to be on a monitor for the user to grab and move it
+const MIN_VISIBLE_WIDTH: f64 = 100.0;
+const MIN_VISIBLE_HEIGHT: f64 = 32.0;
+
+/// A window's geometry as of its last normal (not maximized, fullscreen or minimized) state,
+/// and whether it was maximized over it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowPlacement {
+    /// Outer top-left corner in physical desktop pixels. Not every platform tells windows where
+    /// they are, Wayland among them.
+    #[serde(default)]
+    pub position: Option<(i32, i32)>,
+    /// Surface size in logical pixels, so the window keeps its apparent size on a monitor with
+    /// another scale factor
+    pub width: f64,
+    pub height: f64,
+    #[serde(default)]
+    pub maximized: bool,
+}
+
+/// A monitor's area in physical desktop pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonitorArea {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+}
+
+impl MonitorArea {
+    /// The monitor's size in logical pixels
+    fn logical_size(&self) -> (f64, f64) {
+        (self.width as f64 / self.scale_factor, self.height as f64 / self.scale_factor)
+    }
+
+    /// Whether enough of the top edge of a window at `position`, `width` logical pixels wide,
+    /// lies on this monitor to grab it
+    fn shows_title_of(&self, (x, y): (i32, i32), width: f64) -> bool {
+        let (x, y) = (x as f64, y as f64);
+        let right = x + width * self.scale_factor;
+        let overlap_x = right.min(self.x as f64 + self.width as f64) - x.max(self.x as f64);
+        let overlap_y = (y + MIN_VISIBLE_HEIGHT).min(self.y as f64 + self.height as f64) - y.max(self.y as f64);
+        overlap_x >= MIN_VISIBLE_WIDTH.min(width * self.scale_factor) && overlap_y >= MIN_VISIBLE_HEIGHT
+    }
+}
+
+impl WindowPlacement {
+    pub fn load_from_disk() -> Option<Self> {
+        std::fs::read_to_string(window_placement_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Self>(&contents).ok())
+            .filter(|placement| placement.width >= 1.0 && placement.height >= 1.0)
+    }
+
+    pub fn save_to_disk(&self) {
+        let path = window_placement_path();
+        let Ok(json) = serde_json::to_string_pretty(self) else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::write(path, json);
+    }
+
+    /// Fit the placement to `monitors`, the primary one first. A window whose top edge is on a
+    /// monitor stays there, shrunk to fit it; one that would open off-screen is centered on the
+    /// primary monitor instead. Without any monitors to go by the placement is left alone.
+    pub fn clamp_to_monitors(self, monitors: &[MonitorArea]) -> Self {
+        let Some(primary) = monitors.first() else {
+            return self;
+        };
+        let Some(position) = self.position else {
+            let (width, height) = primary.logical_size();
+            return Self { width: self.width.min(width), height: self.height.min(height), ..self };
+        };
+        if let Some(monitor) = monitors.iter().find(|monitor| monitor.shows_title_of(position, self.width)) {
+            let (width, height) = monitor.logical_size();
+            return Self { width: self.width.min(width), height: self.height.min(height), ..self };
+        }
+
+        let (width, height) = primary.logical_size();
+        let (width, height) = (self.width.min(width), self.height.min(height));
+        let x = primary.x + ((primary.width as f64 - width * primary.scale_factor) / 2.0) as i32;
+        let y = primary.y + ((primary.height as f64 - height * primary.scale_factor) / 2.0) as i32;
+        Self { position: Some((x, y)), width, height, ..self }
+    }
+}
+
+fn window_placement_path() -> PathBuf {
+    crate::profile::data_dir().join(WINDOW_PLACEMENT_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MonitorArea, WindowPlacement};
+
+    const PRIMARY: MonitorArea = MonitorArea { x: 0, y: 0, width: 1920, height: 1080, scale_factor: 1.0 };
+    // A HiDPI monitor to the right of the primary one
+    const SECONDARY: MonitorArea = MonitorArea { x: 1920, y: 0, width: 3840, height: 2160, scale_factor: 2.0 };
+
+    fn placement(position: Option<(i32, i32)>, width: f64, height: f64) -> WindowPlacement {
+        WindowPlacement { position, width, height, maximized: false }
+    }
+
+    #[test]
+    fn keeps_windows_that_are_on_a_monitor() {
+        let on_secondary = placement(Some((2400, 200)), 1024.0, 768.0);
+        assert_eq!(on_secondary.clamp_to_monitors(&[PRIMARY, SECONDARY]), on_secondary);
+        // Partly off the right edge, but the title bar can still be grabbed
+        let hanging_off = placement(Some((1800, 100)), 1024.0, 768.0);
+        assert_eq!(hanging_off.clamp_to_monitors(&[PRIMARY]), hanging_off);
+    }
+
+    #[test]
+    fn centers_windows_left_on_a_monitor_that_is_gone() {
+        let clamped = placement(Some((2400, 200)), 1024.0, 768.0).clamp_to_monitors(&[PRIMARY]);
+        assert_eq!(clamped, placement(Some((448, 156)), 1024.0, 768.0));
+        // The title bar above the top of the screen can't be grabbed either
+        let above = placement(Some((100, -500)), 800.0, 600.0).clamp_to_monitors(&[PRIMARY]);
+        assert_eq!(above.position, Some((560, 240)));
+    }
+
+    #[test]
+    fn shrinks_windows_larger_than_their_monitor() {
+        let clamped = placement(Some((0, 0)), 2560.0, 1440.0).clamp_to_monitors(&[PRIMARY]);
+        assert_eq!(clamped, placement(Some((0, 0)), 1920.0, 1080.0));
+        let unplaced = placement(None, 2560.0, 1440.0).clamp_to_monitors(&[PRIMARY]);
+        assert_eq!(unplaced, placement(None, 1920.0, 1080.0));
+    }
+
+    #[test]
+    fn leaves_placement_alone_without_monitors() {
+        let far_away = placement(Some((-9000, 9000)), 1024.0, 768.0);
+        assert_eq!(far_away.clamp_to_monitors(&[]), far_away);
+    }
+}
@@ -0,0 +1,442 @@
+// WPT-lite: runs the curated web-platform-tests listed in tests/wpt/MANIFEST headlessly, in an
+// engine of this process, and keeps score.
+//
+// `stokes-browser --wpt [dir]` loads every listed file with tests/wpt/resources/testharness.js
+// inlined in place of the page's own harness, runs its scripts and timers until the harness
+// reports, and writes the results to `dir/report.json`. Each run's score is appended to
+// `dir/scores.jsonl` so it can be followed over time, and subtests that passed in the previous
+// report but no longer do are listed as regressions, which also fail the run.
+use crate::engine::nav_provider::{NavigationProviderMessage, StokesNavigationProvider};
+use crate::engine::{Engine, EngineConfig, ENGINE_REF, USER_AGENT_REF};
+use crate::profile;
+use crate::shell_provider::{ShellProviderMessage, StokesShellProvider};
+use blitz_traits::shell::Viewport;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::unbounded_channel;
+use tracing::{info, warn};
+use url::Url;
+
+const DEFAULT_DIR: &str = "tests/wpt";
+const MANIFEST_FILE: &str = "MANIFEST";
+const REPORT_FILE: &str = "report.json";
+const SCORES_FILE: &str = "scores.jsonl";
+/// Replaces the page's /resources/testharness.js
+const HARNESS: &str = include_str!("../tests/wpt/resources/testharness.js");
+/// Element the harness leaves its results in, as JSON
+const RESULTS_ID: &str = "__stokes_wpt_results";
+/// How long a file may run, and with `<meta name="timeout" content="long">`
+const TIMEOUT: Duration = Duration::from_secs(10);
+const LONG_TIMEOUT: Duration = Duration::from_secs(60);
+/// Longest wait between checks for the harness's results
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HarnessStatus {
+    Ok,
+    Error,
+    Timeout,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SubtestStatus {
+    Pass,
+    Fail,
+    Timeout,
+    Notrun,
+    PreconditionFailed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubtestResult {
+    pub name: String,
+    pub status: SubtestStatus,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// The results of one test file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileResult {
+    /// Path relative to the WPT directory
+    pub path: String,
+    pub status: HarnessStatus,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub subtests: Vec<SubtestResult>,
+}
+
+impl FileResult {
+    fn broken(path: &str, status: HarnessStatus, message: String) -> Self {
+        Self { path: path.to_string(), status, message: Some(message), subtests: Vec::new() }
+    }
+
+    /// Passing subtests and subtests in all. A file whose harness never reported any subtests
+    /// still counts as one failure.
+    fn score(&self) -> (usize, usize) {
+        let passed = self.subtests.iter().filter(|subtest| subtest.status == SubtestStatus::Pass).count();
+        (passed, self.subtests.len().max(1))
+    }
+}
+
+/// What the harness publishes when it is done
+#[derive(Debug, Deserialize)]
+struct HarnessResults {
+    status: HarnessStatus,
+    #[serde(default)]
+    message: Option<String>,
+    tests: Vec<SubtestResult>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WptReport {
+    pub files: Vec<FileResult>,
+}
+
+impl WptReport {
+    pub fn score(&self) -> Score {
+        let (passed, total) = self
+            .files
+            .iter()
+            .map(FileResult::score)
+            .fold((0, 0), |(passed, total), (p, t)| (passed + p, total + t));
+        Score { passed, total }
+    }
+
+    /// Subtests that passed in `previous` and don't now, as "path: name"
+    pub fn regressions_since(&self, previous: &WptReport) -> Vec<String> {
+        let passing: HashSet<(&str, &str)> = self
+            .files
+            .iter()
+            .flat_map(|file| {
+                file.subtests
+                    .iter()
+                    .filter(|subtest| subtest.status == SubtestStatus::Pass)
+                    .map(move |subtest| (file.path.as_str(), subtest.name.as_str()))
+            })
+            .collect();
+        previous
+            .files
+            .iter()
+            .flat_map(|file| {
+                file.subtests
+                    .iter()
+                    .filter(|subtest| subtest.status == SubtestStatus::Pass)
+                    .map(move |subtest| (file.path.as_str(), subtest.name.as_str()))
+            })
+            .filter(|key| !passing.contains(key))
+            .map(|(path, name)| format!("{path}: {name}"))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Score {
+    pub passed: usize,
+    pub total: usize,
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = if self.total == 0 { 0.0 } else { self.passed as f64 * 100.0 / self.total as f64 };
+        write!(f, "{}/{} subtests passed ({percent:.1}%)", self.passed, self.total)
+    }
+}
+
+/// One line of scores.jsonl
+#[derive(Debug, Serialize, Deserialize)]
+struct ScoreEntry {
+    /// Seconds since the Unix epoch
+    time: u64,
+    files: usize,
+    #[serde(flatten)]
+    score: Score,
+}
+
+/// Test paths listed in a manifest: one per line, with blank lines and `#` comments skipped
+fn parse_manifest(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Put the harness where the page loads testharness.js and drop testharnessreport.js. Pages that
+/// load neither get the harness right after their doctype.
+fn inline_harness(contents: &str) -> String {
+    let harness = format!("<script>{HARNESS}</script>");
+    // ASCII lowercasing keeps byte offsets, so positions found in `lower` apply to `contents`
+    let lower = contents.to_ascii_lowercase();
+    let mut output = String::with_capacity(contents.len() + harness.len());
+    let mut injected = false;
+    let mut position = 0;
+    while let Some(start) = lower[position..].find("<script").map(|offset| position + offset) {
+        let Some(end) = lower[start..].find("</script>").map(|offset| start + offset + "</script>".len()) else {
+            break;
+        };
+        let open_tag = &lower[start..start + lower[start..end].find('>').unwrap_or(end - start)];
+        output.push_str(&contents[position..start]);
+        if open_tag.contains("testharness.js") {
+            if !injected {
+                output.push_str(&harness);
+                injected = true;
+            }
+        } else if !open_tag.contains("testharnessreport.js") {
+            output.push_str(&contents[start..end]);
+        }
+        position = end;
+    }
+    output.push_str(&contents[position..]);
+    if injected {
+        return output;
+    }
+
+    let doctype_end = if lower.trim_start().starts_with("<!doctype") {
+        lower.find('>').map_or(0, |index| index + 1)
+    } else {
+        0
+    };
+    output.insert_str(doctype_end, &harness);
+    output
+}
+
+/// Run the tests in `dir` and report on them. Returns whether no subtest regressed.
+pub async fn run(dir: Option<&str>) -> io::Result<bool> {
+    let dir = PathBuf::from(dir.unwrap_or(DEFAULT_DIR));
+    let manifest = fs::read_to_string(dir.join(MANIFEST_FILE))?;
+    let paths = parse_manifest(&manifest);
+
+    // Keep the user's cookies and storage out of it
+    let profile_dir = std::env::temp_dir().join("stokes-wpt-profile");
+    let _ = fs::remove_dir_all(&profile_dir);
+    profile::init(profile_dir);
+
+    let mut runner = Runner::new();
+    let mut report = WptReport::default();
+    for path in &paths {
+        let result = runner.run_file(&dir, path).await;
+        let (passed, total) = result.score();
+        println!("{:?} {path} ({passed}/{total})", result.status);
+        for subtest in result.subtests.iter().filter(|subtest| subtest.status != SubtestStatus::Pass) {
+            println!("    {:?} {}: {}", subtest.status, subtest.name, subtest.message.as_deref().unwrap_or(""));
+        }
+        if let Some(message) = result.message.as_deref().filter(|_| result.status != HarnessStatus::Ok) {
+            println!("    {message}");
+        }
+        report.files.push(result);
+    }
+
+    let report_path = dir.join(REPORT_FILE);
+    let previous = fs::read_to_string(&report_path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<WptReport>(&contents).ok());
+    fs::write(&report_path, serde_json::to_string_pretty(&report).map_err(io::Error::other)?)?;
+
+    let score = report.score();
+    let last_score = last_score(&dir.join(SCORES_FILE));
+    append_score(&dir.join(SCORES_FILE), &ScoreEntry {
+        time: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs()),
+        files: report.files.len(),
+        score,
+    })?;
+
+    println!();
+    match last_score {
+        Some(last) => println!(
+            "{score}, {:+} since the last run",
+            score.passed as i64 - last.passed as i64
+        ),
+        None => println!("{score}"),
+    }
+    let regressions = previous.map(|previous| report.regressions_since(&previous)).unwrap_or_default();
+    if !regressions.is_empty() {
+        println!("{} subtests regressed:", regressions.len());
+        for regression in &regressions {
+            println!("    {regression}");
+        }
+    }
+    Ok(regressions.is_empty())
+}
+
+fn last_score(path: &Path) -> Option<Score> {
+    let contents = fs::read_to_string(path).ok()?;
+    let line = contents.lines().rev().find(|line| !line.trim().is_empty())?;
+    serde_json::from_str::<ScoreEntry>(line).ok().map(|entry| entry.score)
+}
+
+fn append_score(path: &Path, entry: &ScoreEntry) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry).map_err(io::Error::other)?)
+}
+
+/// An engine that test files are loaded into one after another, like a tab without a window
+struct Runner {
+    engine: Box<Engine>,
+    // Pages may ask for redraws or navigations; nobody acts on them, but the providers need
+    // somewhere to send them
+    _shell_receiver: tokio::sync::mpsc::UnboundedReceiver<ShellProviderMessage>,
+    _nav_receiver: tokio::sync::mpsc::UnboundedReceiver<NavigationProviderMessage>,
+}
+
+impl Runner {
+    fn new() -> Self {
+        let (shell_tx, shell_receiver) = unbounded_channel();
+        let (nav_tx, nav_receiver) = unbounded_channel();
+        let viewport = Viewport { window_size: (800, 600), hidpi_scale: 1.0, ..Default::default() };
+        let mut engine = Box::new(Engine::new(
+            EngineConfig::default(),
+            viewport,
+            Arc::new(StokesShellProvider::new(shell_tx)),
+            Arc::new(StokesNavigationProvider::new(nav_tx)),
+        ));
+        ENGINE_REF.with(|engine_ref| {
+            *engine_ref.borrow_mut() = Some(&mut *engine as *mut Engine);
+        });
+        USER_AGENT_REF.with(|agent_ref| {
+            *agent_ref.borrow_mut() = Some(engine.config.user_agent.clone());
+        });
+        Self { engine, _shell_receiver: shell_receiver, _nav_receiver: nav_receiver }
+    }
+
+    async fn run_file(&mut self, dir: &Path, path: &str) -> FileResult {
+        let file = dir.join(path);
+        let contents = match fs::read_to_string(&file) {
+            Ok(contents) => contents,
+            Err(e) => return FileResult::broken(path, HarnessStatus::Error, format!("Failed to read {}: {e}", file.display())),
+        };
+        let url = match fs::canonicalize(&file).ok().and_then(|file| Url::from_file_path(file).ok()) {
+            Some(url) => url.to_string(),
+            None => return FileResult::broken(path, HarnessStatus::Error, format!("No file URL for {}", file.display())),
+        };
+        let timeout = if contents.contains(r#"name="timeout" content="long""#) { LONG_TIMEOUT } else { TIMEOUT };
+
+        info!("Running {path}");
+        let start = Instant::now();
+        if let Err(e) = self.engine.navigate(&url, inline_harness(&contents), true, false, None).await {
+            return FileResult::broken(path, HarnessStatus::Error, format!("Navigation failed: {e}"));
+        }
+
+        loop {
+            self.engine.resolve(start.elapsed().as_secs_f64());
+            if let Some(results) = self.results() {
+                return results.into_file_result(path);
+            }
+            if start.elapsed() >= timeout {
+                warn!("{path} timed out");
+                self.engine.execute_javascript("__stokesWptTimeout()", false);
+                self.engine.resolve(start.elapsed().as_secs_f64());
+                return match self.results() {
+                    Some(results) => results.into_file_result(path),
+                    None => FileResult::broken(path, HarnessStatus::Timeout, "The harness never reported".to_string()),
+                };
+            }
+            let wait = self.engine.time_until_next_timer().map_or(POLL_INTERVAL, |next| next.min(POLL_INTERVAL));
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn results(&self) -> Option<HarnessResults> {
+        let dom = self.engine.dom.as_ref()?;
+        let output = dom.root_node().get_element_by_id(RESULTS_ID)?;
+        match serde_json::from_str(&output.text_content()) {
+            Ok(results) => Some(results),
+            Err(e) => {
+                warn!("Unreadable harness results: {e}");
+                None
+            }
+        }
+    }
+}
+
+impl HarnessResults {
+    fn into_file_result(self, path: &str) -> FileResult {
+        FileResult { path: path.to_string(), status: self.status, message: self.message, subtests: self.tests }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, subtests: &[(&str, SubtestStatus)]) -> FileResult {
+        FileResult {
+            path: path.to_string(),
+            status: HarnessStatus::Ok,
+            message: None,
+            subtests: subtests
+                .iter()
+                .map(|(name, status)| SubtestResult { name: name.to_string(), status: *status, message: None })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn manifest_skips_comments_and_blank_lines() {
+        let paths = parse_manifest("# curated\n\ndom/a.html\n  html/b.html  \n#dom/c.html\n");
+        assert_eq!(paths, vec!["dom/a.html", "html/b.html"]);
+    }
+
+    #[test]
+    fn harness_replaces_the_page_harness() {
+        let page = "<!DOCTYPE html><SCRIPT src=\"/resources/testharness.js\"></SCRIPT>\
+                    <script src=\"/resources/testharnessreport.js\"></script><script>test(f)</script>";
+        let inlined = inline_harness(page);
+        assert!(inlined.starts_with("<!DOCTYPE html><script>"));
+        assert!(!inlined.contains("src=\"/resources/"));
+        assert!(inlined.ends_with("</script><script>test(f)</script>"));
+        assert_eq!(inlined.matches(HARNESS).count(), 1);
+    }
+
+    #[test]
+    fn harness_goes_after_the_doctype_of_pages_without_one() {
+        let inlined = inline_harness("<!doctype html>\n<p>hi</p>");
+        assert!(inlined.starts_with("<!doctype html><script>"));
+        assert!(inlined.ends_with("</script>\n<p>hi</p>"));
+        assert!(inline_harness("<p>hi</p>").starts_with("<script>"));
+    }
+
+    #[test]
+    fn broken_files_count_as_one_failure() {
+        let report = WptReport {
+            files: vec![
+                file("a.html", &[("one", SubtestStatus::Pass), ("two", SubtestStatus::Fail)]),
+                FileResult::broken("b.html", HarnessStatus::Timeout, "timed out".to_string()),
+            ],
+        };
+        assert_eq!(report.score(), Score { passed: 1, total: 3 });
+        assert_eq!(report.score().to_string(), "1/3 subtests passed (33.3%)");
+    }
+
+    #[test]
+    fn regressions_are_subtests_that_stopped_passing() {
+        let previous = WptReport {
+            files: vec![file("a.html", &[("one", SubtestStatus::Pass), ("two", SubtestStatus::Fail), ("three", SubtestStatus::Pass)])],
+        };
+        let current = WptReport {
+            files: vec![file("a.html", &[("one", SubtestStatus::Timeout), ("two", SubtestStatus::Pass), ("three", SubtestStatus::Pass)])],
+        };
+        assert_eq!(current.regressions_since(&previous), vec!["a.html: one"]);
+    }
+
+    #[test]
+    fn harness_results_parse() {
+        let results: HarnessResults = serde_json::from_str(
+            r#"{"status":"OK","message":null,"tests":[{"name":"x","status":"PRECONDITION_FAILED","message":"no"}]}"#,
+        )
+        .unwrap();
+        let file = results.into_file_result("x.html");
+        assert_eq!(file.subtests[0].status, SubtestStatus::PreconditionFailed);
+        assert_eq!(file.status, HarnessStatus::Ok);
+    }
+}
@@ -0,0 +1,26 @@
+// Worker for service-worker-test.html: precaches the page and answers /sw-echo itself
+const CACHE = 'service-worker-test-v1';
+
+self.addEventListener('install', function(event) {
+    event.waitUntil(caches.open(CACHE).then(function(cache) {
+        return cache.addAll(['service-worker-test.html']);
+    }));
+    self.skipWaiting();
+});
+
+self.addEventListener('activate', function(event) {
+    event.waitUntil(self.clients.claim());
+});
+
+self.addEventListener('fetch', function(event) {
+    const url = new URL(event.request.url);
+    if (url.pathname.endsWith('/sw-echo')) {
+        event.respondWith(new Response('answered by the worker for ' + url.search, {
+            headers: { 'Content-Type': 'text/plain' },
+        }));
+        return;
+    }
+    event.respondWith(caches.match(event.request).then(function(cached) {
+        return cached || fetch(event.request);
+    }));
+});
@@ -0,0 +1,580 @@
+// A small stand-in for web-platform-tests' testharness.js, covering the API the curated tests use:
+// test, async_test, promise_test, setup, done, the assert_* functions and completion callbacks.
+// The WPT-lite runner (`stokes-browser --wpt`) inlines it in place of the page's
+// /resources/testharness.js and reads the results from #__stokes_wpt_results once it is done.
+(function() {
+    const root = typeof globalThis !== 'undefined' ? globalThis : window;
+
+    const PASS = 'PASS';
+    const FAIL = 'FAIL';
+    const TIMEOUT = 'TIMEOUT';
+    const NOTRUN = 'NOTRUN';
+    const PRECONDITION_FAILED = 'PRECONDITION_FAILED';
+
+    const tests = [];
+    const completionCallbacks = [];
+    const resultCallbacks = [];
+    const settings = { explicitDone: false, singleTest: false };
+    let harnessStatus = 'OK';
+    let harnessMessage = null;
+    let loaded = false;
+    let explicitlyDone = false;
+    let finished = false;
+
+    function AssertionError(message) {
+        this.message = message;
+    }
+    AssertionError.prototype.toString = function() {
+        return this.message;
+    };
+
+    function OptionalFeatureUnsupportedError(message) {
+        this.message = message;
+    }
+    OptionalFeatureUnsupportedError.prototype = Object.create(AssertionError.prototype);
+
+    function formatValue(value) {
+        if (typeof value === 'string') {
+            return JSON.stringify(value);
+        }
+        if (typeof value === 'number' && Object.is(value, -0)) {
+            return '-0';
+        }
+        if (typeof value === 'function') {
+            return 'function "' + (value.name || 'anonymous') + '"';
+        }
+        if (value && typeof value === 'object') {
+            if (value.nodeType === 1) {
+                return 'Element node <' + String(value.localName) + '>';
+            }
+            if (typeof value.nodeType === 'number') {
+                return 'Node of type ' + value.nodeType;
+            }
+            try {
+                return String(value);
+            } catch (_err) {
+                return '[object]';
+            }
+        }
+        return String(value);
+    }
+
+    function errorMessage(error) {
+        if (error instanceof AssertionError) {
+            return error.message;
+        }
+        if (error && typeof error === 'object' && 'message' in error) {
+            return (error.name ? error.name + ': ' : '') + error.message;
+        }
+        return String(error);
+    }
+
+    function assert(condition, functionName, description, message) {
+        if (!condition) {
+            const prefix = description ? description + ': ' : '';
+            throw new AssertionError(functionName + ': ' + prefix + message);
+        }
+    }
+
+    function sameValue(a, b) {
+        return Object.is(a, b);
+    }
+
+    function Test(name, properties) {
+        this.name = name;
+        this.properties = properties || {};
+        this.status = NOTRUN;
+        this.message = null;
+        this.phase = 'started';
+        this.cleanups = [];
+        this.timeouts = [];
+        tests.push(this);
+    }
+
+    Test.prototype.step = function(func, thisObj) {
+        if (this.phase === 'complete') {
+            return undefined;
+        }
+        try {
+            return func.apply(thisObj === undefined ? this : thisObj, Array.prototype.slice.call(arguments, 2));
+        } catch (error) {
+            this.fail(error);
+            return undefined;
+        }
+    };
+
+    Test.prototype.step_func = function(func, thisObj) {
+        const test = this;
+        return function() {
+            return test.step.apply(test, [func, thisObj === undefined ? this : thisObj].concat(Array.prototype.slice.call(arguments)));
+        };
+    };
+
+    Test.prototype.step_func_done = function(func, thisObj) {
+        const test = this;
+        return function() {
+            if (func) {
+                test.step.apply(test, [func, thisObj === undefined ? this : thisObj].concat(Array.prototype.slice.call(arguments)));
+            }
+            test.done();
+        };
+    };
+
+    Test.prototype.unreached_func = function(description) {
+        return this.step_func(function() {
+            assert_unreached(description);
+        });
+    };
+
+    Test.prototype.step_timeout = function(func, timeout) {
+        const test = this;
+        const args = Array.prototype.slice.call(arguments, 2);
+        const id = setTimeout(function() {
+            test.step.apply(test, [func, test].concat(args));
+        }, timeout);
+        this.timeouts.push(id);
+        return id;
+    };
+
+    Test.prototype.add_cleanup = function(func) {
+        this.cleanups.push(func);
+    };
+
+    Test.prototype.fail = function(error) {
+        if (this.phase === 'complete') {
+            return;
+        }
+        this.status = error instanceof OptionalFeatureUnsupportedError ? PRECONDITION_FAILED : FAIL;
+        this.message = errorMessage(error);
+        this.complete();
+    };
+
+    Test.prototype.done = function() {
+        if (this.phase === 'complete') {
+            return;
+        }
+        this.status = PASS;
+        this.complete();
+    };
+
+    Test.prototype.complete = function() {
+        this.phase = 'complete';
+        for (const id of this.timeouts) {
+            clearTimeout(id);
+        }
+        for (const cleanup of this.cleanups) {
+            try {
+                cleanup();
+            } catch (error) {
+                harnessStatus = 'ERROR';
+                harnessMessage = 'Cleanup function for "' + this.name + '" threw: ' + errorMessage(error);
+            }
+        }
+        for (const callback of resultCallbacks) {
+            try { callback(this); } catch (_err) {}
+        }
+        maybeFinish();
+    };
+
+    function defaultName(func) {
+        return (func && func.name) || (typeof document !== 'undefined' && document.title) || 'Untitled';
+    }
+
+    function test(func, name, properties) {
+        const t = new Test(name || defaultName(func), properties);
+        t.step(func, t, t);
+        t.done();
+    }
+
+    function async_test(func, name, properties) {
+        if (typeof func !== 'function') {
+            properties = name;
+            name = func;
+            func = null;
+        }
+        const t = new Test(name || defaultName(func), properties);
+        if (func) {
+            t.step(func, t, t);
+        }
+        return t;
+    }
+
+    // Promise tests run one after another, like in testharness.js
+    let promiseChain = Promise.resolve();
+
+    function promise_test(func, name, properties) {
+        const t = new Test(name || defaultName(func), properties);
+        promiseChain = promiseChain.then(function() {
+            if (t.phase === 'complete') {
+                return undefined;
+            }
+            let promise;
+            try {
+                promise = func(t);
+            } catch (error) {
+                t.fail(error);
+                return undefined;
+            }
+            if (!promise || typeof promise.then !== 'function') {
+                t.fail(new AssertionError('promise_test: test body must return a promise'));
+                return undefined;
+            }
+            return promise.then(function() { t.done(); }, function(error) { t.fail(error); });
+        });
+    }
+
+    function promise_rejects_js(t, constructor, promise, description) {
+        return promise.then(t.unreached_func('Should have rejected: ' + (description || '')), function(error) {
+            assert_true(error instanceof constructor, description || 'rejection is a ' + constructor.name);
+        });
+    }
+
+    function promise_rejects_dom(t, name, promise, description) {
+        return promise.then(t.unreached_func('Should have rejected: ' + (description || '')), function(error) {
+            assert_dom_exception(error, name, description, 'promise_rejects_dom');
+        });
+    }
+
+    function setup(funcOrProperties, maybeProperties) {
+        let func = null;
+        let properties = funcOrProperties;
+        if (typeof funcOrProperties === 'function') {
+            func = funcOrProperties;
+            properties = maybeProperties;
+        }
+        properties = properties || {};
+        settings.explicitDone = !!properties.explicit_done;
+        settings.singleTest = !!properties.single_test;
+        if (settings.singleTest) {
+            settings.explicitDone = true;
+        }
+        if (func) {
+            try {
+                func();
+            } catch (error) {
+                harnessStatus = 'ERROR';
+                harnessMessage = 'Setup failed: ' + errorMessage(error);
+            }
+        }
+    }
+
+    function promise_setup(func, properties) {
+        setup(properties || {});
+        promiseChain = promiseChain.then(function() { return func(); }).catch(function(error) {
+            harnessStatus = 'ERROR';
+            harnessMessage = 'Setup failed: ' + errorMessage(error);
+            finish();
+        });
+    }
+
+    function done() {
+        if (settings.singleTest && tests.length === 0) {
+            const t = new Test(defaultName(null));
+            t.done();
+        }
+        explicitlyDone = true;
+        maybeFinish();
+    }
+
+    function step_timeout(func, timeout) {
+        const args = Array.prototype.slice.call(arguments, 2);
+        return setTimeout(function() { func.apply(this, args); }, timeout);
+    }
+
+    function maybeFinish() {
+        if (finished || !loaded) {
+            return;
+        }
+        if (settings.explicitDone && !explicitlyDone) {
+            return;
+        }
+        if (tests.some(function(t) { return t.phase !== 'complete'; })) {
+            return;
+        }
+        finish();
+    }
+
+    function finish() {
+        if (finished) {
+            return;
+        }
+        finished = true;
+        for (const callback of completionCallbacks) {
+            try {
+                callback(tests, { status: harnessStatus, message: harnessMessage });
+            } catch (_err) {}
+        }
+        publish();
+    }
+
+    // The runner gives up on a page that took too long; whatever is left counts as timed out
+    root.__stokesWptTimeout = function() {
+        if (finished) {
+            return;
+        }
+        for (const t of tests) {
+            if (t.phase !== 'complete') {
+                t.phase = 'complete';
+                t.status = TIMEOUT;
+                t.message = 'Test timed out';
+            }
+        }
+        harnessStatus = 'TIMEOUT';
+        finish();
+    };
+
+    function publish() {
+        const results = {
+            status: harnessStatus,
+            message: harnessMessage,
+            tests: tests.map(function(t) {
+                return { name: String(t.name), status: t.status, message: t.message };
+            }),
+        };
+        const doc = root.document;
+        if (!doc || !doc.body) {
+            return;
+        }
+        let log = doc.getElementById('log');
+        if (!log) {
+            log = doc.createElement('div');
+            log.id = 'log';
+            doc.body.appendChild(log);
+        }
+        const passed = results.tests.filter(function(t) { return t.status === PASS; }).length;
+        const summary = doc.createElement('p');
+        summary.textContent = 'Harness status: ' + results.status + '. ' + passed + ' of ' + results.tests.length + ' tests passed.';
+        log.appendChild(summary);
+        for (const t of results.tests) {
+            const line = doc.createElement('div');
+            line.textContent = t.status + ' ' + t.name + (t.message ? ': ' + t.message : '');
+            line.style.color = t.status === PASS ? 'green' : 'red';
+            log.appendChild(line);
+        }
+        const output = doc.createElement('pre');
+        output.id = '__stokes_wpt_results';
+        output.setAttribute('hidden', '');
+        output.textContent = JSON.stringify(results);
+        doc.body.appendChild(output);
+    }
+
+    function assert_true(actual, description) {
+        assert(actual === true, 'assert_true', description, 'expected true got ' + formatValue(actual));
+    }
+
+    function assert_false(actual, description) {
+        assert(actual === false, 'assert_false', description, 'expected false got ' + formatValue(actual));
+    }
+
+    function assert_equals(actual, expected, description) {
+        assert(sameValue(actual, expected), 'assert_equals', description,
+            'expected ' + formatValue(expected) + ' but got ' + formatValue(actual));
+    }
+
+    function assert_not_equals(actual, expected, description) {
+        assert(!sameValue(actual, expected), 'assert_not_equals', description,
+            'got disallowed value ' + formatValue(actual));
+    }
+
+    function assert_in_array(actual, expected, description) {
+        assert(expected.indexOf(actual) !== -1, 'assert_in_array', description,
+            'value ' + formatValue(actual) + ' not in array ' + formatValue(expected));
+    }
+
+    function assert_array_equals(actual, expected, description) {
+        assert(actual && typeof actual === 'object' && 'length' in actual, 'assert_array_equals', description,
+            'value is ' + formatValue(actual) + ', expected array');
+        assert(actual.length === expected.length, 'assert_array_equals', description,
+            'lengths differ, expected array ' + formatValue(expected) + ' length ' + expected.length
+                + ', got ' + formatValue(actual) + ' length ' + actual.length);
+        for (let i = 0; i < actual.length; i++) {
+            assert(sameValue(actual[i], expected[i]), 'assert_array_equals', description,
+                'expected property ' + i + ' to be ' + formatValue(expected[i]) + ' but got ' + formatValue(actual[i]));
+        }
+    }
+
+    function assert_object_equals(actual, expected, description) {
+        function check(actual, expected, path) {
+            if (typeof expected !== 'object' || expected === null) {
+                assert(sameValue(actual, expected), 'assert_object_equals', description,
+                    'property ' + path + ' expected ' + formatValue(expected) + ' got ' + formatValue(actual));
+                return;
+            }
+            for (const key of Object.keys(expected)) {
+                assert(actual && Object.prototype.hasOwnProperty.call(actual, key), 'assert_object_equals', description,
+                    'expected property ' + path + key);
+                check(actual[key], expected[key], path + key + '.');
+            }
+            for (const key of Object.keys(actual)) {
+                assert(Object.prototype.hasOwnProperty.call(expected, key), 'assert_object_equals', description,
+                    'unexpected property ' + path + key);
+            }
+        }
+        check(actual, expected, '');
+    }
+
+    function assert_approx_equals(actual, expected, epsilon, description) {
+        assert(typeof actual === 'number', 'assert_approx_equals', description,
+            'expected a number but got a ' + typeof actual);
+        assert(Math.abs(actual - expected) <= epsilon, 'assert_approx_equals', description,
+            'expected ' + formatValue(expected) + ' +/- ' + epsilon + ' but got ' + formatValue(actual));
+    }
+
+    function compare(name, test, relation) {
+        return function(actual, expected, description) {
+            assert(typeof actual === 'number', name, description, 'expected a number but got a ' + typeof actual);
+            assert(test(actual, expected), name, description,
+                'expected a number ' + relation + ' ' + formatValue(expected) + ' but got ' + formatValue(actual));
+        };
+    }
+
+    const assert_less_than = compare('assert_less_than', function(a, b) { return a < b; }, 'less than');
+    const assert_greater_than = compare('assert_greater_than', function(a, b) { return a > b; }, 'greater than');
+    const assert_less_than_equal = compare('assert_less_than_equal', function(a, b) { return a <= b; }, 'less than or equal to');
+    const assert_greater_than_equal = compare('assert_greater_than_equal', function(a, b) { return a >= b; }, 'greater than or equal to');
+
+    function assert_regexp_match(actual, expected, description) {
+        assert(expected.test(actual), 'assert_regexp_match', description,
+            'expected ' + formatValue(expected) + ' but got ' + formatValue(actual));
+    }
+
+    function assert_class_string(object, className, description) {
+        const actual = Object.prototype.toString.call(object);
+        const expected = '[object ' + className + ']';
+        assert(actual === expected, 'assert_class_string', description,
+            'expected ' + formatValue(expected) + ' but got ' + formatValue(actual));
+    }
+
+    function assert_own_property(object, name, description) {
+        assert(Object.prototype.hasOwnProperty.call(object, name), 'assert_own_property', description,
+            'expected property ' + formatValue(name) + ' missing');
+    }
+
+    function assert_not_own_property(object, name, description) {
+        assert(!Object.prototype.hasOwnProperty.call(object, name), 'assert_not_own_property', description,
+            'unexpected property ' + formatValue(name) + ' is found on object');
+    }
+
+    function assert_inherits(object, name, description) {
+        assert(typeof object === 'object' || typeof object === 'function', 'assert_inherits', description,
+            'provided value is not an object');
+        assert(!Object.prototype.hasOwnProperty.call(object, name), 'assert_inherits', description,
+            'property ' + formatValue(name) + ' found on object, expected in prototype chain');
+        assert(name in object, 'assert_inherits', description,
+            'property ' + formatValue(name) + ' not found in prototype chain');
+    }
+
+    function assert_idl_attribute(object, name, description) {
+        assert(name in object, 'assert_idl_attribute', description,
+            'property ' + formatValue(name) + ' not found');
+    }
+
+    function assert_throws_js(constructor, func, description) {
+        let thrown = false;
+        try {
+            func.call(this);
+        } catch (error) {
+            thrown = true;
+            assert(error instanceof constructor || (error && error.name === constructor.name), 'assert_throws_js', description,
+                func + ' threw ' + formatValue(error) + ' (' + (error && error.name) + ') expected instance of ' + constructor.name);
+        }
+        assert(thrown, 'assert_throws_js', description, func + ' did not throw');
+    }
+
+    function assert_dom_exception(error, name, description, functionName) {
+        // Legacy code numbers are accepted in place of the name
+        const codes = {
+            1: 'IndexSizeError', 3: 'HierarchyRequestError', 4: 'WrongDocumentError', 5: 'InvalidCharacterError',
+            7: 'NoModificationAllowedError', 8: 'NotFoundError', 9: 'NotSupportedError', 11: 'InvalidStateError',
+            12: 'SyntaxError', 13: 'InvalidModificationError', 14: 'NamespaceError', 15: 'InvalidAccessError',
+            18: 'SecurityError', 19: 'NetworkError', 20: 'AbortError', 23: 'TimeoutError', 25: 'DataCloneError',
+        };
+        const expected = typeof name === 'number' ? codes[name] : name;
+        assert(error && typeof error === 'object', functionName, description,
+            'threw ' + formatValue(error) + ' instead of a DOMException');
+        assert(error.name === expected, functionName, description,
+            'threw an exception named ' + formatValue(error.name) + ' instead of ' + formatValue(expected));
+    }
+
+    function assert_throws_dom(name, constructorOrFunc, maybeFunc, maybeDescription) {
+        // The constructor argument is for other realms, which there are none of here
+        let func = constructorOrFunc;
+        let description = maybeFunc;
+        if (typeof maybeFunc === 'function') {
+            func = maybeFunc;
+            description = maybeDescription;
+        }
+        let thrown = false;
+        try {
+            func.call(this);
+        } catch (error) {
+            thrown = true;
+            assert_dom_exception(error, name, description, 'assert_throws_dom');
+        }
+        assert(thrown, 'assert_throws_dom', description, func + ' did not throw');
+    }
+
+    function assert_throws_exactly(exception, func, description) {
+        let thrown = false;
+        try {
+            func.call(this);
+        } catch (error) {
+            thrown = true;
+            assert(sameValue(error, exception), 'assert_throws_exactly', description,
+                func + ' threw ' + formatValue(error) + ' but we expected it to throw ' + formatValue(exception));
+        }
+        assert(thrown, 'assert_throws_exactly', description, func + ' did not throw');
+    }
+
+    function assert_unreached(description) {
+        assert(false, 'assert_unreached', description, 'Reached unreachable code');
+    }
+
+    function assert_implements(condition, description) {
+        assert(!!condition, 'assert_implements', description, '');
+    }
+
+    function assert_implements_optional(condition, description) {
+        if (!condition) {
+            throw new OptionalFeatureUnsupportedError(description);
+        }
+    }
+
+    function add_completion_callback(callback) {
+        completionCallbacks.push(callback);
+    }
+
+    function add_result_callback(callback) {
+        resultCallbacks.push(callback);
+    }
+
+    const api = {
+        test, async_test, promise_test, promise_rejects_js, promise_rejects_dom, setup, promise_setup, done,
+        step_timeout, format_value: formatValue, add_completion_callback, add_result_callback,
+        assert_true, assert_false, assert_equals, assert_not_equals, assert_in_array, assert_array_equals,
+        assert_object_equals, assert_approx_equals, assert_less_than, assert_greater_than,
+        assert_less_than_equal, assert_greater_than_equal, assert_regexp_match, assert_class_string,
+        assert_own_property, assert_not_own_property, assert_inherits, assert_idl_attribute,
+        assert_throws_js, assert_throws_dom, assert_throws_exactly, assert_unreached, assert_implements,
+        assert_implements_optional,
+    };
+    for (const name of Object.keys(api)) {
+        root[name] = api[name];
+    }
+
+    // An error outside of any test step breaks the whole file
+    const previousOnError = root.onerror;
+    root.onerror = function(message) {
+        if (!finished) {
+            harnessStatus = 'ERROR';
+            harnessMessage = String(message);
+            finish();
+        }
+        return typeof previousOnError === 'function' ? previousOnError.apply(this, arguments) : false;
+    };
+
+    root.addEventListener('load', function() {
+        loaded = true;
+        maybeFinish();
+    });
+})();